@@ -0,0 +1,56 @@
+// Example collector plugin implementing include/triageir_plugin.h
+// Build with `cargo build --example sample_plugin` and copy the library
+// (target/debug/examples/sample_plugin.dll) into the --plugins-dir directory.
+use std::ffi::{c_char, CStr, CString};
+
+#[no_mangle]
+pub extern "C" fn triageir_plugin_abi_version() -> u32 {
+    1
+}
+
+#[no_mangle]
+pub extern "C" fn triageir_plugin_name() -> *const c_char {
+    c"sample_env".as_ptr()
+}
+
+#[no_mangle]
+pub extern "C" fn triageir_plugin_version() -> *const c_char {
+    c"1.0.0".as_ptr()
+}
+
+/// Report the collector's environment variables that point into the file system
+///
+/// # Safety
+/// `context_json` must be a valid NUL-terminated string and `output_json` a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn triageir_plugin_collect(context_json: *const c_char, output_json: *mut *mut c_char) -> i32 {
+    let context: serde_json::Value = serde_json::from_str(&CStr::from_ptr(context_json).to_string_lossy()).unwrap_or_default();
+
+    let mut artifacts = Vec::new();
+    let mut audit = Vec::new();
+    if context["offline_root"].is_string() {
+        audit.push(serde_json::json!({
+            "level": "INFO",
+            "action": "collect",
+            "details": "Environment variables are live-only; skipped in offline mode",
+            "result": "skipped"
+        }));
+    } else {
+        for (name, value) in std::env::vars().filter(|(_, value)| value.contains(std::path::MAIN_SEPARATOR)) {
+            artifacts.push(serde_json::json!({ "name": name, "value": value }));
+        }
+    }
+
+    let output = serde_json::json!({ "artifacts": artifacts, "audit": audit }).to_string();
+    *output_json = CString::new(output).unwrap_or_default().into_raw();
+    0
+}
+
+/// # Safety
+/// `output_json` must have been returned by `triageir_plugin_collect`.
+#[no_mangle]
+pub unsafe extern "C" fn triageir_plugin_free(output_json: *mut c_char) {
+    if !output_json.is_null() {
+        drop(CString::from_raw(output_json));
+    }
+}
@@ -0,0 +1,50 @@
+/*
+ * TriageIR collector C ABI
+ *
+ * Link against triageir_cli.dll (import library triageir_cli.dll.lib) or
+ * libtriageir_cli.so, built with `cargo build --release`.
+ * All strings are NUL-terminated UTF-8.
+ */
+#ifndef TRIAGEIR_H
+#define TRIAGEIR_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define TRIAGEIR_OK                    0
+#define TRIAGEIR_INVALID_ARGUMENT      1
+#define TRIAGEIR_COMPLETED_WITH_ERRORS 2 /* Output written; some collectors failed */
+#define TRIAGEIR_OUTPUT_FAILED         3
+#define TRIAGEIR_INTERNAL_ERROR        4
+
+/* percent: 0-100; message is only valid for the duration of the call */
+typedef void (*triageir_progress_cb)(uint32_t percent, const char *message, void *user_data);
+
+/*
+ * Run a scan synchronously and write the JSON result to out_path.
+ *
+ * config_json may be NULL for the defaults, or an object with the optional keys
+ *   "offline_root", "since", "until", "event_channels" (array of "CHANNEL[=XPATH]"),
+ *   "event_config", "raw_dir", "plugins_dir", "shadow_copy" ("existing" or "create")
+ *   "hash_algorithms" (e.g. "md5,sha1,imphash"), "fuzzy_hash" (bool), "scan_paths" (array of directories),
+ *   "scan_extensions", "scan_max_size_mb", "scan_depth", "ioc_list", "yara_rules"
+ *   and "canaries" (path of a TOML canary list)
+ * matching the CLI flags. progress may be NULL.
+ */
+int32_t triageir_run_scan(const char *config_json, const char *out_path,
+                          triageir_progress_cb progress, void *user_data);
+
+/* Message for the last failed call on this thread, or NULL. Valid until the next scan call. */
+const char *triageir_last_error(void);
+
+/* Library version, e.g. "0.1.0" */
+const char *triageir_version(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* TRIAGEIR_H */
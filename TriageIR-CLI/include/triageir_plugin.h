@@ -0,0 +1,52 @@
+/*
+ * TriageIR collector plugin ABI
+ *
+ * A plugin is a shared library (.dll, .so or .dylib) placed in the directory
+ * given with --plugins-dir. The collector loads each library, checks the ABI
+ * version and calls triageir_plugin_collect once per scan. Plugins run in the
+ * collector's process with its privileges.
+ * All strings are NUL-terminated UTF-8.
+ */
+#ifndef TRIAGEIR_PLUGIN_H
+#define TRIAGEIR_PLUGIN_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define TRIAGEIR_PLUGIN_ABI_VERSION 1
+
+/* Must return TRIAGEIR_PLUGIN_ABI_VERSION; libraries built for another version are skipped */
+uint32_t triageir_plugin_abi_version(void);
+
+/* Unique plugin name (letters, digits, '_', '-', '.'), used in output and audit entries */
+const char *triageir_plugin_name(void);
+
+/* Plugin version, e.g. "1.2.0" */
+const char *triageir_plugin_version(void);
+
+/*
+ * Collect artifacts.
+ *
+ * context_json is an object with "abi_version", "cli_version", "scan_id",
+ * "offline_root" (null for live scans), "since" and "until" (null when unset).
+ *
+ * On return *output_json must point to a string allocated by the plugin:
+ *   {
+ *     "artifacts": [ { ... }, ... ],
+ *     "audit": [ { "level": "INFO|WARN|ERROR|DEBUG", "action": "...", "details": "...", "result": "..." } ]
+ *   }
+ * The collector releases it with triageir_plugin_free. Return 0 on success;
+ * any other value is recorded as a failed collection.
+ */
+int32_t triageir_plugin_collect(const char *context_json, char **output_json);
+
+void triageir_plugin_free(char *output_json);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* TRIAGEIR_PLUGIN_H */
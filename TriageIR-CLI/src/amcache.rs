@@ -0,0 +1,274 @@
+//! Amcache.hve analysis for evidence of execution and presence
+//! Amcache records the path, SHA-1 and version information of executables that
+//! were run or inventoried. The hive is held open by the system on a live host,
+//! so it is read from offline volumes (or copies of the hive) with the raw
+//! hive parser, on any platform
+
+use crate::binutil::filetime_to_string;
+use crate::forensic_types::{AmcacheEntry, AuditEntry};
+use crate::hive::{Hive, Key};
+use crate::offline::OfflineRoot;
+use std::path::Path;
+
+const COMPONENT: &str = "amcache";
+
+const AMCACHE_PATH: &str = "C:\\Windows\\AppCompat\\Programs\\Amcache.hve";
+
+/// Windows 10/11 inventory of application files
+const INVENTORY_APPLICATION_FILE_KEY: &str = "Root\\InventoryApplicationFile";
+
+/// Windows 8 / early Windows 10 layout: Root\File\{volume GUID}\{file reference}
+const LEGACY_FILE_KEY: &str = "Root\\File";
+
+/// Analyze the Amcache hive of a mounted Windows volume
+pub fn collect_amcache_entries_offline(root: &OfflineRoot) -> (Vec<AmcacheEntry>, Vec<AuditEntry>) {
+    collect_amcache_from_hive(&root.resolve(AMCACHE_PATH))
+}
+
+/// Analyze an Amcache.hve file
+pub fn collect_amcache_from_hive(hive_path: &Path) -> (Vec<AmcacheEntry>, Vec<AuditEntry>) {
+    let mut amcache_entries = Vec::new();
+    let mut audit_log = Vec::new();
+
+    let start_time = std::time::Instant::now();
+
+    audit_log.push(AuditEntry::new(
+        COMPONENT,
+        "INFO",
+        "start_collection",
+        format!("Starting Amcache analysis of {}", hive_path.display()),
+        "started",
+    ));
+
+    if !hive_path.exists() {
+        audit_log.push(AuditEntry::new(
+            COMPONENT,
+            "WARN",
+            "hive_check",
+            format!("Amcache hive not found: {}", hive_path.display()),
+            "not_found",
+        ));
+        return (amcache_entries, audit_log);
+    }
+
+    parse_amcache_hive(hive_path, &mut amcache_entries, &mut audit_log);
+
+    let duration = start_time.elapsed();
+    audit_log.push(AuditEntry {
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        level: "INFO".to_string(),
+        component: "amcache".to_string(),
+        action: "complete_collection".to_string(),
+        details: format!("Collected {} amcache entries", amcache_entries.len()),
+        duration_ms: Some(duration.as_millis() as u64),
+        result: "success".to_string(),
+    });
+
+    (amcache_entries, audit_log)
+}
+
+fn parse_amcache_hive(hive_path: &Path, amcache_entries: &mut Vec<AmcacheEntry>, audit_log: &mut Vec<AuditEntry>) {
+    let hive = match Hive::open(hive_path) {
+        Ok(hive) => hive,
+        Err(e) => {
+            audit_log.push(AuditEntry::new(
+                COMPONENT,
+                "ERROR",
+                "load_hive",
+                format!("Failed to load Amcache hive {}: {}", hive_path.display(), e),
+                "error",
+            ));
+            return;
+        }
+    };
+    if hive.is_dirty() {
+        audit_log.push(AuditEntry::new(
+            COMPONENT,
+            "WARN",
+            "hive_state",
+            format!("{} is dirty; entries only in its transaction logs are missing", hive_path.display()),
+            "dirty",
+        ));
+    }
+
+    match hive.open_key(INVENTORY_APPLICATION_FILE_KEY) {
+        Some(inventory_key) => {
+            let entries = collect_inventory_application_files(&inventory_key);
+            audit_log.push(AuditEntry::new(
+                COMPONENT,
+                "DEBUG",
+                "parse_inventory",
+                format!("Parsed {} InventoryApplicationFile entries", entries.len()),
+                "success",
+            ));
+            amcache_entries.extend(entries);
+        }
+        None => {
+            audit_log.push(AuditEntry::new(
+                COMPONENT,
+                "DEBUG",
+                "parse_inventory",
+                "InventoryApplicationFile key not present".to_string(),
+                "not_found",
+            ));
+        }
+    }
+
+    match hive.open_key(LEGACY_FILE_KEY) {
+        Some(file_key) => {
+            let entries = collect_legacy_file_entries(&file_key);
+            audit_log.push(AuditEntry::new(
+                COMPONENT,
+                "DEBUG",
+                "parse_legacy_files",
+                format!("Parsed {} legacy Root\\File entries", entries.len()),
+                "success",
+            ));
+            amcache_entries.extend(entries);
+        }
+        None => {
+            audit_log.push(AuditEntry::new(
+                COMPONENT,
+                "DEBUG",
+                "parse_legacy_files",
+                "Legacy Root\\File key not present".to_string(),
+                "not_found",
+            ));
+        }
+    }
+}
+
+fn collect_inventory_application_files(inventory_key: &Key) -> Vec<AmcacheEntry> {
+    let mut entries = Vec::new();
+
+    for file_key in inventory_key.subkeys() {
+        let path = match file_key.string("LowerCaseLongPath") {
+            Some(path) => path,
+            None => continue,
+        };
+
+        entries.push(AmcacheEntry {
+            path,
+            sha1: normalize_file_id(&file_key.string("FileId").unwrap_or_default()),
+            first_installation: String::new(),
+            last_modified: file_key.last_written(),
+            publisher: file_key.string("Publisher").unwrap_or_default(),
+            version: file_key.string("Version").unwrap_or_default(),
+            language: file_key
+                .u32("Language")
+                .map(|language| language.to_string())
+                .unwrap_or_default(),
+            install_date: file_key.string("LinkDate").unwrap_or_default(),
+        });
+    }
+
+    entries
+}
+
+fn collect_legacy_file_entries(file_key: &Key) -> Vec<AmcacheEntry> {
+    let mut entries = Vec::new();
+
+    for volume_key in file_key.subkeys() {
+        for entry_key in volume_key.subkeys() {
+            // Legacy entries use numbered value names: 15 = full path, 101 = SHA-1,
+            // 1 = company, 5 = file version, 3 = language, 17 = last modified, 12 = created
+            let path = match entry_key.string("15") {
+                Some(path) => path,
+                None => continue,
+            };
+
+            entries.push(AmcacheEntry {
+                path,
+                sha1: normalize_file_id(&entry_key.string("101").unwrap_or_default()),
+                first_installation: entry_key
+                    .u64("12")
+                    .map(filetime_to_string)
+                    .unwrap_or_default(),
+                last_modified: entry_key
+                    .u64("17")
+                    .map(filetime_to_string)
+                    .unwrap_or_else(|| entry_key.last_written()),
+                publisher: entry_key.string("1").unwrap_or_default(),
+                version: entry_key.string("5").unwrap_or_default(),
+                language: entry_key
+                    .u32("3")
+                    .map(|language| language.to_string())
+                    .unwrap_or_default(),
+                install_date: String::new(),
+            });
+        }
+    }
+
+    entries
+}
+
+/// Amcache stores SHA-1 hashes as a FileId padded with four leading zeros
+fn normalize_file_id(file_id: &str) -> String {
+    let file_id = file_id.trim().to_lowercase();
+    if file_id.len() == 44 && file_id.starts_with("0000") {
+        file_id[4..].to_string()
+    } else {
+        file_id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_collect_amcache_missing_hive() {
+        let temp_dir = TempDir::new().unwrap();
+        let (entries, audit_log) = collect_amcache_from_hive(&temp_dir.path().join("Amcache.hve"));
+
+        assert!(entries.is_empty());
+        assert!(audit_log.iter().any(|log| log.action == "hive_check" && log.result == "not_found"));
+    }
+
+    #[test]
+    fn test_collect_amcache_from_raw_hive() {
+        let temp_dir = TempDir::new().unwrap();
+        let hive_path = temp_dir.path().join("Amcache.hve");
+        let file_key = "Root\\InventoryApplicationFile\\setup.exe|1a2b3c";
+        let legacy_key = "Root\\File\\{c7c8a0e4-0000-0000-0000-100000000000}\\1000000a3f2";
+        let hive = crate::hive::HiveBuilder::default()
+            .key(file_key, 132_539_328_000_000_000)
+            .string(file_key, "LowerCaseLongPath", "c:\\users\\bob\\downloads\\setup.exe")
+            .string(file_key, "FileId", "0000A9993E364706816ABA3E25717850C26C9CD0D89D")
+            .dword(file_key, "Language", 1033)
+            .string(legacy_key, "15", "C:\\Tools\\nc.exe")
+            .value(legacy_key, "17", crate::hive::REG_QWORD, &132_539_328_000_000_000u64.to_le_bytes())
+            .build();
+        std::fs::write(&hive_path, hive).unwrap();
+
+        let (entries, audit_log) = collect_amcache_from_hive(&hive_path);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].path, "c:\\users\\bob\\downloads\\setup.exe");
+        assert_eq!(entries[0].sha1, "a9993e364706816aba3e25717850c26c9cd0d89d");
+        assert_eq!((entries[0].language.as_str(), entries[0].last_modified.as_str()), ("1033", "2021-01-01T00:00:00+00:00"));
+        assert_eq!((entries[1].path.as_str(), entries[1].last_modified.as_str()), ("C:\\Tools\\nc.exe", "2021-01-01T00:00:00+00:00"));
+        assert!(!audit_log.iter().any(|log| log.level == "ERROR"));
+
+        std::fs::write(&hive_path, b"not a hive").unwrap();
+        let (_, audit_log) = collect_amcache_from_hive(&hive_path);
+        assert!(audit_log.iter().any(|log| log.action == "load_hive" && log.level == "ERROR"));
+    }
+
+    #[test]
+    fn test_normalize_file_id() {
+        assert_eq!(
+            normalize_file_id("0000A9993E364706816ABA3E25717850C26C9CD0D89D"),
+            "a9993e364706816aba3e25717850c26c9cd0d89d"
+        );
+        assert_eq!(normalize_file_id("abc"), "abc");
+        assert_eq!(normalize_file_id(""), "");
+    }
+
+    #[test]
+    fn test_filetime_to_string() {
+        // 2021-01-01T00:00:00Z
+        assert_eq!(filetime_to_string(132_539_328_000_000_000), "2021-01-01T00:00:00+00:00");
+        assert_eq!(filetime_to_string(0), "");
+    }
+}
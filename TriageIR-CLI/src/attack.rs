@@ -0,0 +1,252 @@
+//! MITRE ATT&CK technique tagging
+//! Persistence mechanisms are tagged with the technique their type implements
+//! (high confidence), the techniques of the interpreter or proxy binary they
+//! launch (medium) and those suggested by command line keywords such as
+//! encoded commands or downloads (low). The scan metadata rolls the tags of the
+//! persistence mechanisms, the hijack artifacts and the detection rule matches
+//! up into one entry per technique.
+//! Technique catalog: https://attack.mitre.org/techniques/enterprise/
+
+use crate::forensic_types::{AttackTechnique, AttackTechniqueSummary};
+use crate::ioc_export::command_executable;
+use crate::path_norm;
+use crate::persistence;
+use crate::types::PersistenceMechanism;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Technique ID, name and primary tactic of the techniques tagged by the collector
+const TECHNIQUES: [(&str, &str, &str); 22] = [
+    ("T1547.001", "Registry Run Keys / Startup Folder", "persistence"),
+    ("T1543.003", "Windows Service", "persistence"),
+    ("T1053.005", "Scheduled Task", "persistence"),
+    ("T1546.003", "Windows Management Instrumentation Event Subscription", "persistence"),
+    ("T1547.004", "Winlogon Helper DLL", "persistence"),
+    ("T1546.012", "Image File Execution Options Injection", "persistence"),
+    ("T1546.010", "AppInit DLLs", "persistence"),
+    ("T1546.009", "AppCert DLLs", "persistence"),
+    ("T1574.001", "DLL Search Order Hijacking", "persistence"),
+    ("T1574.007", "Path Interception by PATH Environment Variable", "persistence"),
+    ("T1574", "Hijack Execution Flow", "persistence"),
+    ("T1059.001", "PowerShell", "execution"),
+    ("T1059.003", "Windows Command Shell", "execution"),
+    ("T1059.005", "Visual Basic", "execution"),
+    ("T1059.007", "JavaScript", "execution"),
+    ("T1218.005", "Mshta", "defense-evasion"),
+    ("T1218.010", "Regsvr32", "defense-evasion"),
+    ("T1218.011", "Rundll32", "defense-evasion"),
+    ("T1197", "BITS Jobs", "defense-evasion"),
+    ("T1027", "Obfuscated Files or Information", "defense-evasion"),
+    ("T1564.003", "Hidden Window", "defense-evasion"),
+    ("T1105", "Ingress Tool Transfer", "command-and-control"),
+];
+
+/// Confidence levels, lowest first
+const CONFIDENCE: [&str; 3] = ["low", "medium", "high"];
+
+/// Technique implemented by each persistence mechanism type
+const MECHANISM_TECHNIQUES: [(&str, &str); 5] = [
+    ("Registry Run Key", "T1547.001"),
+    ("Startup Folder", "T1547.001"),
+    ("Windows Service", "T1543.003"),
+    ("Scheduled Task", "T1053.005"),
+    ("WMI Event Consumer", "T1546.003"),
+];
+
+/// Interpreters and proxy binaries launched by a mechanism, by file name without extension
+const PROGRAM_TECHNIQUES: [(&str, &str); 10] = [
+    ("powershell", "T1059.001"),
+    ("pwsh", "T1059.001"),
+    ("cmd", "T1059.003"),
+    ("wscript", "T1059.005"),
+    ("cscript", "T1059.005"),
+    ("mshta", "T1218.005"),
+    ("regsvr32", "T1218.010"),
+    ("rundll32", "T1218.011"),
+    ("bitsadmin", "T1197"),
+    ("certutil", "T1105"),
+];
+
+/// Command line fragments (normalized, lowercase) and the technique they suggest
+const KEYWORD_TECHNIQUES: [(&str, &str); 12] = [
+    (" -enc", "T1027"),
+    ("encodedcommand", "T1027"),
+    ("frombase64string", "T1027"),
+    ("-w hidden", "T1564.003"),
+    ("windowstyle hidden", "T1564.003"),
+    (".vbs", "T1059.005"),
+    (".js ", "T1059.007"),
+    ("downloadstring", "T1105"),
+    ("downloadfile", "T1105"),
+    ("invoke-webrequest", "T1105"),
+    ("-urlcache", "T1105"),
+    ("start-bitstransfer", "T1197"),
+];
+
+/// Attach the techniques to every mechanism
+pub fn tag_persistence_mechanisms(mechanisms: &mut [PersistenceMechanism]) {
+    for mechanism in mechanisms {
+        mechanism.attack_techniques = persistence_techniques(&mechanism.mechanism_type, &mechanism.command);
+    }
+}
+
+/// Techniques of a persistence mechanism, strongest evidence first
+pub fn persistence_techniques(mechanism_type: &str, command: &str) -> Vec<AttackTechnique> {
+    let mut tags: Vec<AttackTechnique> = Vec::new();
+    let mut add = |id: &str, confidence: &str| {
+        if !tags.iter().any(|tag| tag.technique_id == id) {
+            tags.push(technique(id, confidence));
+        }
+    };
+
+    if let Some((_, id)) = MECHANISM_TECHNIQUES.iter().find(|(name, _)| *name == mechanism_type) {
+        add(id, "high");
+    }
+    let normalized = format!("{} ", path_norm::normalize_command(command));
+    let executable = command_executable(command).unwrap_or_else(|| command.split_whitespace().next().unwrap_or_default().to_string());
+    let program = executable.rsplit(['\\', '/']).next().unwrap_or_default().to_lowercase();
+    let program = program.strip_suffix(".exe").unwrap_or(&program);
+    if let Some((_, id)) = PROGRAM_TECHNIQUES.iter().find(|(name, _)| *name == program) {
+        add(id, "medium");
+    }
+    for (fragment, id) in KEYWORD_TECHNIQUES {
+        if normalized.contains(fragment) {
+            add(id, "low");
+        }
+    }
+    tags
+}
+
+fn technique(id: &str, confidence: &str) -> AttackTechnique {
+    let (name, tactic) = TECHNIQUES.iter().find(|(known, _, _)| *known == id).map(|(_, name, tactic)| (*name, *tactic)).unwrap_or_default();
+    AttackTechnique {
+        technique_id: id.to_string(),
+        name: name.to_string(),
+        tactic: tactic.to_string(),
+        confidence: confidence.to_string(),
+    }
+}
+
+/// One entry per technique over the tagged persistence mechanisms, the hijack
+/// artifacts and the detection rule matches, most suspicious first
+pub fn summarize(scan: &Value) -> Vec<AttackTechniqueSummary> {
+    let mut summaries: BTreeMap<String, AttackTechniqueSummary> = BTreeMap::new();
+    let mut record = |tag: AttackTechnique, source: &str, count: usize, suspicious: usize| {
+        let summary = summaries.entry(tag.technique_id.clone()).or_insert_with(|| AttackTechniqueSummary {
+            technique_id: tag.technique_id.clone(),
+            name: tag.name.clone(),
+            tactic: tag.tactic.clone(),
+            confidence: tag.confidence.clone(),
+            artifact_count: 0,
+            suspicious_count: 0,
+            sources: Vec::new(),
+        });
+        summary.artifact_count += count;
+        summary.suspicious_count += suspicious;
+        if rank(&tag.confidence) > rank(&summary.confidence) {
+            summary.confidence = tag.confidence;
+        }
+        if !summary.sources.iter().any(|s| s == source) {
+            summary.sources.push(source.to_string());
+        }
+    };
+
+    let artifacts = &scan["artifacts"];
+    for mechanism in array(&artifacts["persistence_mechanisms"]) {
+        let suspicious = usize::from(mechanism["is_suspicious"].as_bool().unwrap_or(false));
+        let tags: Vec<AttackTechnique> = serde_json::from_value(mechanism["attack_techniques"].clone()).unwrap_or_default();
+        for tag in tags {
+            record(tag, "persistence_mechanisms", 1, suspicious);
+        }
+    }
+
+    let hijacks = &artifacts["persistence_artifacts"];
+    let flagged = |entry: &Value| usize::from(entry["suspicion_score"].as_u64().unwrap_or(0) >= persistence::HIJACK_SUSPICION_THRESHOLD as u64);
+    for entry in array(&hijacks["winlogon_entries"]) {
+        record(technique("T1547.004", "high"), "winlogon_entries", 1, flagged(entry));
+    }
+    for entry in array(&hijacks["image_hijacks"]) {
+        record(technique("T1546.012", "high"), "image_hijacks", 1, flagged(entry));
+    }
+    for entry in array(&hijacks["appinit_dlls"]) {
+        let id = if entry["value_name"].as_str() == Some("AppInit_DLLs") { "T1546.010" } else { "T1546.009" };
+        record(technique(id, "high"), "appinit_dlls", 1, flagged(entry));
+    }
+    for entry in array(&hijacks["path_hijacks"]) {
+        let id = match (entry["source"].as_str().unwrap_or_default(), entry["value_name"].as_str().unwrap_or_default()) {
+            ("system_path" | "user_path", _) => "T1574.007",
+            ("known_folder", folder) if folder.ends_with("Startup") => "T1547.001",
+            _ => "T1574",
+        };
+        record(technique(id, "high"), "path_hijacks", 1, flagged(entry));
+    }
+    // Search-order hijack candidates are flagged by definition, but may be benign side-loading
+    for _ in array(&hijacks["dll_hijacks"]) {
+        record(technique("T1574.001", "medium"), "dll_hijacks", 1, 1);
+    }
+
+    for detection in array(&scan["detections"]) {
+        let matches = detection["match_count"].as_u64().unwrap_or(0) as usize;
+        for id in array(&detection["mitre_techniques"]).iter().filter_map(Value::as_str) {
+            record(technique(id, "medium"), "detections", matches, matches);
+        }
+    }
+
+    let mut summaries: Vec<AttackTechniqueSummary> = summaries.into_values().collect();
+    summaries.sort_by(|a, b| b.suspicious_count.cmp(&a.suspicious_count).then_with(|| a.technique_id.cmp(&b.technique_id)));
+    summaries
+}
+
+fn rank(confidence: &str) -> usize {
+    CONFIDENCE.iter().position(|level| *level == confidence).unwrap_or(0)
+}
+
+fn array(value: &Value) -> &[Value] {
+    value.as_array().map(Vec::as_slice).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn ids(tags: &[AttackTechnique]) -> Vec<(&str, &str)> {
+        tags.iter().map(|tag| (tag.technique_id.as_str(), tag.confidence.as_str())).collect()
+    }
+
+    #[test]
+    fn test_persistence_techniques() {
+        let tags = persistence_techniques("Registry Run Key", "powershell.exe -NoP -W Hidden -EncodedCommand SQBFAFgA");
+        assert_eq!(ids(&tags), vec![("T1547.001", "high"), ("T1059.001", "medium"), ("T1027", "low"), ("T1564.003", "low")]);
+        assert_eq!(tags[0].name, "Registry Run Keys / Startup Folder");
+        assert_eq!(tags[1].tactic, "execution");
+
+        let tags = persistence_techniques("Scheduled Task", "C:\\Windows\\System32\\rundll32.exe C:\\Users\\Public\\x.dll,Start (User: SYSTEM)");
+        assert_eq!(ids(&tags), vec![("T1053.005", "high"), ("T1218.011", "medium")]);
+        assert_eq!(ids(&persistence_techniques("Windows Service", "\"C:\\Program Files\\Agent\\agent.exe\" -k")), vec![("T1543.003", "high")]);
+        assert!(persistence_techniques("Unknown", "C:\\Tools\\tool.exe").is_empty());
+    }
+
+    #[test]
+    fn test_summarize() {
+        let scan = json!({
+            "artifacts": {
+                "persistence_mechanisms": [
+                    {"is_suspicious": true, "attack_techniques": [{"technique_id": "T1547.001", "name": "Registry Run Keys / Startup Folder", "tactic": "persistence", "confidence": "high"}]},
+                    {"is_suspicious": false, "attack_techniques": [{"technique_id": "T1547.001", "name": "Registry Run Keys / Startup Folder", "tactic": "persistence", "confidence": "high"}]}
+                ],
+                "persistence_artifacts": {"winlogon_entries": [{"suspicion_score": 10}], "image_hijacks": [], "appinit_dlls": [{"value_name": "AppCertDlls", "suspicion_score": 80}], "dll_hijacks": []}
+            },
+            "detections": [{"match_count": 3, "mitre_techniques": ["T1547.001", "T1003.001"]}]
+        });
+        let summary = summarize(&scan);
+        assert_eq!(summary[0].technique_id, "T1547.001");
+        assert_eq!((summary[0].artifact_count, summary[0].suspicious_count), (5, 4));
+        assert_eq!(summary[0].confidence, "high");
+        assert_eq!(summary[0].sources, vec!["persistence_mechanisms", "detections"]);
+        assert_eq!(summary[1].technique_id, "T1003.001"); // Not in the catalog: no name
+        assert!(summary[1].name.is_empty());
+        assert!(summary.iter().any(|s| s.technique_id == "T1546.009" && s.suspicious_count == 1));
+        assert!(summary.iter().any(|s| s.technique_id == "T1547.004" && s.suspicious_count == 0));
+    }
+}
@@ -0,0 +1,106 @@
+//! Helpers shared by the binary artifact parsers
+//! Little-endian integer reads that return None past the end of the buffer
+//! instead of panicking, NUL-terminated UTF-16 strings, binary SIDs and Windows
+//! FILETIME values, as found in registry hives, the MFT, the USN journal,
+//! prefetch files and the SRUM and SAM databases.
+
+use chrono::{DateTime, SecondsFormat, Utc};
+
+/// Seconds between the FILETIME epoch (1601-01-01) and the Unix epoch
+pub const FILETIME_EPOCH_DIFF: u64 = 11644473600;
+/// FILETIME counts 100-nanosecond intervals
+pub const FILETIME_UNITS_PER_SEC: u64 = 10_000_000;
+
+pub fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    data.get(offset..offset + 2).map(|b| u16::from_le_bytes([b[0], b[1]]))
+}
+
+pub fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+pub fn read_u64(data: &[u8], offset: usize) -> Option<u64> {
+    data.get(offset..offset + 8).map(|b| u64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]))
+}
+
+/// UTF-16LE text up to the first NUL, or the whole buffer when unterminated
+pub fn utf16_string(data: &[u8]) -> String {
+    let units: Vec<u16> = data.chunks_exact(2).map(|b| u16::from_le_bytes([b[0], b[1]])).take_while(|&unit| unit != 0).collect();
+    String::from_utf16_lossy(&units)
+}
+
+/// Binary SID (revision, sub-authority count, 48-bit big-endian authority,
+/// little-endian sub-authorities) in its S-1-5-... form
+pub fn sid_to_string(blob: &[u8]) -> Option<String> {
+    let (&revision, rest) = blob.split_first()?;
+    let (&sub_authority_count, rest) = rest.split_first()?;
+    if revision != 1 || rest.len() < 6 + sub_authority_count as usize * 4 {
+        return None;
+    }
+    let authority = rest[..6].iter().fold(0u64, |value, &b| (value << 8) | b as u64);
+    let mut sid = format!("S-{}-{}", revision, authority);
+    for sub_authority in rest[6..].chunks_exact(4).take(sub_authority_count as usize) {
+        sid.push_str(&format!("-{}", u32::from_le_bytes([sub_authority[0], sub_authority[1], sub_authority[2], sub_authority[3]])));
+    }
+    Some(sid)
+}
+
+/// FILETIME as a UTC time; None for unset values (zero, anything up to the
+/// Unix epoch) and the 0x7FFF... "never" sentinels
+pub fn filetime_to_datetime(filetime: u64) -> Option<DateTime<Utc>> {
+    if filetime / FILETIME_UNITS_PER_SEC <= FILETIME_EPOCH_DIFF || filetime >= i64::MAX as u64 {
+        return None;
+    }
+    let unix_seconds = filetime / FILETIME_UNITS_PER_SEC - FILETIME_EPOCH_DIFF;
+    let nanos = (filetime % FILETIME_UNITS_PER_SEC) as u32 * 100;
+    DateTime::from_timestamp(unix_seconds as i64, nanos)
+}
+
+/// FILETIME as RFC 3339 in whole seconds, empty when unset
+pub fn filetime_to_string(filetime: u64) -> String {
+    filetime_to_datetime(filetime)
+        .map(|datetime| datetime.to_rfc3339_opts(SecondsFormat::Secs, false))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_past_end() {
+        let data = [0x34, 0x12, 0x78, 0x56, 0, 0, 0, 0x80];
+        assert_eq!(read_u16(&data, 0), Some(0x1234));
+        assert_eq!(read_u32(&data, 0), Some(0x5678_1234));
+        assert_eq!(read_u64(&data, 0), Some(0x8000_0000_5678_1234));
+        assert_eq!(read_u16(&data, 7), None);
+        assert_eq!(read_u64(&data, 1), None);
+    }
+
+    #[test]
+    fn test_utf16_string() {
+        let data: Vec<u8> = "rclone.exe\0junk".encode_utf16().flat_map(|unit| unit.to_le_bytes()).collect();
+        assert_eq!(utf16_string(&data), "rclone.exe");
+        assert_eq!(utf16_string(&data[..9]), "rclo");
+        assert_eq!(utf16_string(&[]), "");
+    }
+
+    #[test]
+    fn test_sid_to_string() {
+        let system = [1, 1, 0, 0, 0, 0, 0, 5, 18, 0, 0, 0];
+        assert_eq!(sid_to_string(&system).as_deref(), Some("S-1-5-18"));
+        assert_eq!(sid_to_string(&system[..8]), None);
+    }
+
+    #[test]
+    fn test_filetime() {
+        assert_eq!(filetime_to_string(132_539_328_000_000_000), "2021-01-01T00:00:00+00:00");
+        assert_eq!(filetime_to_string(132_539_328_012_345_670), "2021-01-01T00:00:01+00:00");
+        assert_eq!(filetime_to_datetime(132_539_328_012_345_670).unwrap().to_rfc3339(), "2021-01-01T00:00:01.234567+00:00");
+        assert_eq!(filetime_to_datetime(133_170_048_000_000_000).unwrap().to_rfc3339(), "2023-01-01T00:00:00+00:00");
+        assert_eq!(filetime_to_string(0), "");
+        assert!(filetime_to_datetime(0).is_none());
+        assert_eq!(filetime_to_string(FILETIME_EPOCH_DIFF * FILETIME_UNITS_PER_SEC), "");
+        assert_eq!(filetime_to_string(i64::MAX as u64), "");
+    }
+}
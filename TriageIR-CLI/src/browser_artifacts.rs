@@ -0,0 +1,423 @@
+//! Web browser history and downloads
+//! Parses the History database of every Chrome and Edge (Chromium) profile and
+//! places.sqlite of every Firefox profile. Running browsers keep these databases
+//! open, so each one is staged into a temporary directory with a shared-read copy
+//! (together with its WAL) and only the copy is opened by SQLite. A database locked
+//! exclusively is read from the scan's shadow copy when one is active.
+
+use crate::binutil::FILETIME_EPOCH_DIFF;
+use crate::footprint;
+use crate::forensic_types::{AuditEntry, BrowserArtifact};
+use crate::offline::OfflineRoot;
+use crate::vss;
+use rusqlite::{Connection, OpenFlags, Row};
+use std::path::{Path, PathBuf};
+
+const COMPONENT: &str = "browser_artifacts";
+
+const USERS_DIRECTORY: &str = "C:\\Users";
+
+/// Chromium-based browsers and their `User Data` directory below the profile
+const CHROMIUM_BROWSERS: [(&str, &[&str]); 2] = [
+    ("Chrome", &["AppData", "Local", "Google", "Chrome", "User Data"]),
+    ("Edge", &["AppData", "Local", "Microsoft", "Edge", "User Data"]),
+];
+const FIREFOX_PROFILES_SUBPATH: [&str; 5] = ["AppData", "Roaming", "Mozilla", "Firefox", "Profiles"];
+
+const CHROMIUM_HISTORY_QUERY: &str = "SELECT url, title, visit_count, typed_count, last_visit_time FROM urls ORDER BY last_visit_time DESC";
+const CHROMIUM_DOWNLOADS_QUERY: &str = "SELECT \
+     (SELECT c.url FROM downloads_url_chains c WHERE c.id = d.id ORDER BY c.chain_index DESC LIMIT 1), \
+     d.target_path, d.start_time, d.referrer \
+     FROM downloads d ORDER BY d.start_time DESC";
+const FIREFOX_HISTORY_QUERY: &str = "SELECT url, title, visit_count, typed, last_visit_date FROM moz_places \
+     WHERE last_visit_date IS NOT NULL ORDER BY last_visit_date DESC";
+const FIREFOX_DOWNLOADS_QUERY: &str = "SELECT p.url, p.title, a.content, a.dateAdded \
+     FROM moz_annos a JOIN moz_anno_attributes n ON a.anno_attribute_id = n.id JOIN moz_places p ON p.id = a.place_id \
+     WHERE n.name = 'downloads/destinationFileURI' ORDER BY a.dateAdded DESC";
+
+/// Collect browser history and downloads for every user profile on the live system
+pub fn collect_browser_artifacts() -> (Vec<BrowserArtifact>, Vec<AuditEntry>) {
+    collect_browser_artifacts_under(Path::new(USERS_DIRECTORY))
+}
+
+/// Collect browser history and downloads for every user profile of a mounted Windows volume
+pub fn collect_browser_artifacts_offline(root: &OfflineRoot) -> (Vec<BrowserArtifact>, Vec<AuditEntry>) {
+    collect_browser_artifacts_under(&root.resolve(USERS_DIRECTORY))
+}
+
+fn collect_browser_artifacts_under(users_dir: &Path) -> (Vec<BrowserArtifact>, Vec<AuditEntry>) {
+    let mut artifacts = Vec::new();
+    let start_time = std::time::Instant::now();
+
+    let mut audit_log = vec![AuditEntry::new(COMPONENT, "INFO", "start_collection", "Starting browser history analysis".to_string(), "started")];
+
+    let user_dirs = match footprint::read_dir(users_dir) {
+        Ok(user_dirs) => user_dirs,
+        Err(e) => {
+            audit_log.push(AuditEntry::new(
+                COMPONENT,
+                "WARN",
+                "directory_check",
+                format!("Users directory not readable: {} ({})", users_dir.display(), e),
+                "not_found",
+            ));
+            return (artifacts, audit_log);
+        }
+    };
+
+    for user_dir in user_dirs.filter_map(|e| e.ok()) {
+        let username = user_dir.file_name().to_string_lossy().to_string();
+
+        for (browser, subpath) in CHROMIUM_BROWSERS {
+            let user_data = join_all(user_dir.path(), subpath);
+            for (profile, db_path) in profile_databases(&user_data, "History") {
+                let result = parse_chromium_history(&db_path, browser, &profile, &username);
+                record_result(&mut artifacts, &mut audit_log, &db_path, result);
+            }
+        }
+
+        let firefox_profiles = join_all(user_dir.path(), &FIREFOX_PROFILES_SUBPATH);
+        for (profile, db_path) in profile_databases(&firefox_profiles, "places.sqlite") {
+            let result = parse_firefox_places(&db_path, &profile, &username);
+            record_result(&mut artifacts, &mut audit_log, &db_path, result);
+        }
+    }
+
+    let duration = start_time.elapsed();
+    let mut complete = AuditEntry::new(
+        COMPONENT,
+        "INFO",
+        "complete_collection",
+        format!("Collected {} browser history and download records", artifacts.len()),
+        "success",
+    );
+    complete.duration_ms = Some(duration.as_millis() as u64);
+    audit_log.push(complete);
+
+    (artifacts, audit_log)
+}
+
+fn record_result(
+    artifacts: &mut Vec<BrowserArtifact>,
+    audit_log: &mut Vec<AuditEntry>,
+    db_path: &Path,
+    result: Result<Vec<BrowserArtifact>, Box<dyn std::error::Error>>,
+) {
+    match result {
+        Ok(records) => {
+            audit_log.push(AuditEntry::new(
+                COMPONENT,
+                "DEBUG",
+                "parse_database",
+                format!("Parsed {} records from {}", records.len(), db_path.display()),
+                "success",
+            ));
+            artifacts.extend(records);
+        }
+        Err(e) => {
+            audit_log.push(AuditEntry::new(
+                COMPONENT,
+                "ERROR",
+                "parse_database",
+                format!("Failed to parse {}: {} (if the browser holds an exclusive lock, collect from a shadow copy with --offline-root)", db_path.display(), e),
+                "error",
+            ));
+        }
+    }
+}
+
+/// Profile directories below `parent` that contain `database`, e.g. `Default` and `Profile 1`
+fn profile_databases(parent: &Path, database: &str) -> Vec<(String, PathBuf)> {
+    let mut databases: Vec<(String, PathBuf)> = footprint::read_dir(parent)
+        .into_iter()
+        .flatten()
+        .filter_map(|e| e.ok())
+        .map(|entry| (entry.file_name().to_string_lossy().to_string(), entry.path().join(database)))
+        .filter(|(_, path)| path.is_file())
+        .collect();
+    databases.sort();
+    databases
+}
+
+/// Parse the History database of a Chrome or Edge profile (visited URLs and downloads)
+pub fn parse_chromium_history(db_path: &Path, browser: &str, profile: &str, user: &str) -> Result<Vec<BrowserArtifact>, Box<dyn std::error::Error>> {
+    let staging = tempfile::TempDir::new()?;
+    let connection = open_staged(db_path, staging.path())?;
+    let record = |artifact_type: &str, url: String, title: String| BrowserArtifact {
+        browser: browser.to_string(),
+        profile: profile.to_string(),
+        artifact_type: artifact_type.to_string(),
+        url,
+        title,
+        visit_count: 0,
+        last_visit: String::new(),
+        typed_count: 0,
+        download_path: None,
+        referrer: None,
+        user: user.to_string(),
+        source_path: db_path.display().to_string(),
+    };
+
+    let mut records = Vec::new();
+    let mut statement = connection.prepare(CHROMIUM_HISTORY_QUERY)?;
+    let rows = statement.query_map([], |row| {
+        Ok(BrowserArtifact {
+            visit_count: count(row, 2)?,
+            typed_count: count(row, 3)?,
+            last_visit: webkit_time_to_string(row.get::<_, Option<i64>>(4)?.unwrap_or(0)),
+            ..record("history", text(row, 0)?, text(row, 1)?)
+        })
+    })?;
+    for row in rows {
+        records.push(row?);
+    }
+
+    // Freshly created profiles may not have the downloads tables yet
+    if has_table(&connection, "downloads")? {
+        let mut statement = connection.prepare(CHROMIUM_DOWNLOADS_QUERY)?;
+        let rows = statement.query_map([], |row| {
+            let target_path = text(row, 1)?;
+            Ok(BrowserArtifact {
+                last_visit: webkit_time_to_string(row.get::<_, Option<i64>>(2)?.unwrap_or(0)),
+                referrer: non_empty(text(row, 3)?),
+                download_path: non_empty(target_path.clone()),
+                ..record("downloads", text(row, 0)?, file_name(&target_path))
+            })
+        })?;
+        for row in rows {
+            records.push(row?);
+        }
+    }
+
+    Ok(records)
+}
+
+/// Parse places.sqlite of a Firefox profile (visited URLs and downloads)
+pub fn parse_firefox_places(db_path: &Path, profile: &str, user: &str) -> Result<Vec<BrowserArtifact>, Box<dyn std::error::Error>> {
+    let staging = tempfile::TempDir::new()?;
+    let connection = open_staged(db_path, staging.path())?;
+    let record = |artifact_type: &str, url: String, title: String| BrowserArtifact {
+        browser: "Firefox".to_string(),
+        profile: profile.to_string(),
+        artifact_type: artifact_type.to_string(),
+        url,
+        title,
+        visit_count: 0,
+        last_visit: String::new(),
+        typed_count: 0,
+        download_path: None,
+        referrer: None,
+        user: user.to_string(),
+        source_path: db_path.display().to_string(),
+    };
+
+    let mut records = Vec::new();
+    let mut statement = connection.prepare(FIREFOX_HISTORY_QUERY)?;
+    let rows = statement.query_map([], |row| {
+        Ok(BrowserArtifact {
+            visit_count: count(row, 2)?,
+            typed_count: count(row, 3)?,
+            last_visit: prtime_to_string(row.get::<_, Option<i64>>(4)?.unwrap_or(0)),
+            ..record("history", text(row, 0)?, text(row, 1)?)
+        })
+    })?;
+    for row in rows {
+        records.push(row?);
+    }
+
+    if has_table(&connection, "moz_annos")? {
+        let mut statement = connection.prepare(FIREFOX_DOWNLOADS_QUERY)?;
+        let rows = statement.query_map([], |row| {
+            Ok(BrowserArtifact {
+                last_visit: prtime_to_string(row.get::<_, Option<i64>>(3)?.unwrap_or(0)),
+                download_path: non_empty(file_uri_to_path(&text(row, 2)?)),
+                ..record("downloads", text(row, 0)?, text(row, 1)?)
+            })
+        })?;
+        for row in rows {
+            records.push(row?);
+        }
+    }
+
+    Ok(records)
+}
+
+/// Copy the database and its WAL into `staging_dir` and open the copy read-only
+fn open_staged(db_path: &Path, staging_dir: &Path) -> Result<Connection, Box<dyn std::error::Error>> {
+    let staged_db = vss::stage_sqlite_database(db_path, staging_dir)?;
+    Ok(Connection::open_with_flags(&staged_db, OpenFlags::SQLITE_OPEN_READ_ONLY)?)
+}
+
+fn has_table(connection: &Connection, table: &str) -> rusqlite::Result<bool> {
+    connection.query_row("SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = ?1", [table], |row| {
+        row.get::<_, i64>(0)
+    })
+    .map(|count| count > 0)
+}
+
+fn text(row: &Row, index: usize) -> rusqlite::Result<String> {
+    Ok(row.get::<_, Option<String>>(index)?.unwrap_or_default())
+}
+
+fn count(row: &Row, index: usize) -> rusqlite::Result<u32> {
+    Ok(row.get::<_, Option<i64>>(index)?.unwrap_or(0).clamp(0, u32::MAX as i64) as u32)
+}
+
+/// Chromium timestamps are microseconds since 1601-01-01
+fn webkit_time_to_string(microseconds: i64) -> String {
+    if microseconds <= 0 {
+        return String::new();
+    }
+    prtime_to_string(microseconds - FILETIME_EPOCH_DIFF as i64 * 1_000_000)
+}
+
+/// Firefox (PRTime) timestamps are microseconds since 1970-01-01
+fn prtime_to_string(microseconds: i64) -> String {
+    if microseconds <= 0 {
+        return String::new();
+    }
+    chrono::DateTime::from_timestamp(microseconds.div_euclid(1_000_000), 0)
+        .map(|datetime| datetime.to_rfc3339())
+        .unwrap_or_default()
+}
+
+/// `file:///C:/Users/a/Downloads/setup%20v2.exe` -> `C:\Users\a\Downloads\setup v2.exe`
+fn file_uri_to_path(uri: &str) -> String {
+    let Some(path) = uri.strip_prefix("file:///") else {
+        return uri.to_string();
+    };
+
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escape = bytes.get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], escape) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).replace('/', "\\")
+}
+
+fn non_empty(value: String) -> Option<String> {
+    Some(value).filter(|value| !value.is_empty())
+}
+
+fn file_name(path: &str) -> String {
+    path.rsplit(['\\', '/']).next().unwrap_or_default().to_string()
+}
+
+fn join_all(base: PathBuf, components: &[&str]) -> PathBuf {
+    components.iter().fold(base, |path, component| path.join(component))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn create_chromium_history(path: &Path) {
+        let connection = Connection::open(path).unwrap();
+        connection.execute_batch(
+            "CREATE TABLE urls (id INTEGER PRIMARY KEY, url TEXT, title TEXT, visit_count INTEGER, typed_count INTEGER, last_visit_time INTEGER);
+             CREATE TABLE downloads (id INTEGER PRIMARY KEY, target_path TEXT, start_time INTEGER, referrer TEXT);
+             CREATE TABLE downloads_url_chains (id INTEGER, chain_index INTEGER, url TEXT);
+             INSERT INTO urls VALUES (1, 'https://mail.example.com/', 'Inbox', 12, 3, 13253932800000000);
+             INSERT INTO downloads VALUES (1, 'C:\\Users\\alice\\Downloads\\invoice.exe', 13253932800000000, 'https://mail.example.com/');
+             INSERT INTO downloads_url_chains VALUES (1, 0, 'https://short.example/x');
+             INSERT INTO downloads_url_chains VALUES (1, 1, 'https://cdn.example.net/invoice.exe');",
+        ).unwrap();
+    }
+
+    fn create_firefox_places(path: &Path) {
+        let connection = Connection::open(path).unwrap();
+        connection.execute_batch(
+            "CREATE TABLE moz_places (id INTEGER PRIMARY KEY, url TEXT, title TEXT, visit_count INTEGER, typed INTEGER, last_visit_date INTEGER);
+             CREATE TABLE moz_anno_attributes (id INTEGER PRIMARY KEY, name TEXT);
+             CREATE TABLE moz_annos (id INTEGER PRIMARY KEY, place_id INTEGER, anno_attribute_id INTEGER, content TEXT, dateAdded INTEGER);
+             INSERT INTO moz_places VALUES (1, 'https://forum.example.org/', 'Forum', 4, 1, 1609459200000000);
+             INSERT INTO moz_places VALUES (2, 'https://dl.example.org/tool.zip', 'tool.zip', 1, 0, 1609459260000000);
+             INSERT INTO moz_places VALUES (3, 'place:sort=8', NULL, 0, 0, NULL);
+             INSERT INTO moz_anno_attributes VALUES (1, 'downloads/destinationFileURI');
+             INSERT INTO moz_annos VALUES (1, 2, 1, 'file:///C:/Users/bob/Downloads/tool%20v2.zip', 1609459260000000);",
+        ).unwrap();
+    }
+
+    #[test]
+    fn test_parse_chromium_history() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("History");
+        create_chromium_history(&db_path);
+
+        let records = parse_chromium_history(&db_path, "Chrome", "Default", "alice").unwrap();
+        assert_eq!(records.len(), 2);
+
+        let history = &records[0];
+        assert_eq!((history.artifact_type.as_str(), history.url.as_str(), history.title.as_str()), ("history", "https://mail.example.com/", "Inbox"));
+        assert_eq!((history.visit_count, history.typed_count), (12, 3));
+        assert_eq!(history.last_visit, "2021-01-01T00:00:00+00:00");
+
+        let download = &records[1];
+        assert_eq!(download.artifact_type, "downloads");
+        assert_eq!(download.url, "https://cdn.example.net/invoice.exe"); // Final URL after redirects
+        assert_eq!(download.title, "invoice.exe");
+        assert_eq!(download.download_path.as_deref(), Some("C:\\Users\\alice\\Downloads\\invoice.exe"));
+        assert_eq!(download.referrer.as_deref(), Some("https://mail.example.com/"));
+        assert_eq!(download.user, "alice");
+    }
+
+    #[test]
+    fn test_parse_firefox_places() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("places.sqlite");
+        create_firefox_places(&db_path);
+
+        let records = parse_firefox_places(&db_path, "abcd1234.default-release", "bob").unwrap();
+        assert_eq!(records.len(), 3); // Never-visited bookmarks and queries are skipped
+
+        assert_eq!(records[0].url, "https://dl.example.org/tool.zip");
+        assert_eq!(records[1].url, "https://forum.example.org/");
+        assert_eq!(records[1].typed_count, 1);
+        assert_eq!(records[1].last_visit, "2021-01-01T00:00:00+00:00");
+        assert_eq!(records[2].artifact_type, "downloads");
+        assert_eq!(records[2].download_path.as_deref(), Some("C:\\Users\\bob\\Downloads\\tool v2.zip"));
+        assert_eq!(records[2].browser, "Firefox");
+    }
+
+    #[test]
+    fn test_collect_browser_artifacts_under_users_directory() {
+        let temp_dir = TempDir::new().unwrap();
+        let edge_profile = join_all(temp_dir.path().join("alice"), &CHROMIUM_BROWSERS[1].1).join("Profile 1");
+        fs::create_dir_all(&edge_profile).unwrap();
+        create_chromium_history(&edge_profile.join("History"));
+        let firefox_profile = join_all(temp_dir.path().join("bob"), &FIREFOX_PROFILES_SUBPATH).join("abcd1234.default-release");
+        fs::create_dir_all(&firefox_profile).unwrap();
+        create_firefox_places(&firefox_profile.join("places.sqlite"));
+        fs::create_dir(temp_dir.path().join("Public")).unwrap();
+
+        let (artifacts, audit_log) = collect_browser_artifacts_under(temp_dir.path());
+
+        assert_eq!(artifacts.len(), 5);
+        assert!(artifacts.iter().any(|a| a.browser == "Edge" && a.profile == "Profile 1" && a.user == "alice"));
+        assert!(artifacts.iter().any(|a| a.browser == "Firefox" && a.user == "bob"));
+        assert!(audit_log.iter().any(|log| log.action == "complete_collection"));
+    }
+
+    #[test]
+    fn test_timestamp_conversion() {
+        assert_eq!(webkit_time_to_string(13253932800000000), "2021-01-01T00:00:00+00:00");
+        assert_eq!(prtime_to_string(1609459200000000), "2021-01-01T00:00:00+00:00");
+        assert_eq!(webkit_time_to_string(0), "");
+        assert_eq!(file_uri_to_path("file:///D:/a%2Gb/%E2%82%AC.txt"), "D:\\a%2Gb\\€.txt");
+    }
+}
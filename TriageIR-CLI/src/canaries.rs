@@ -0,0 +1,467 @@
+//! Honeytoken and canary verification
+//! Checks the deception assets listed in a TOML file given with `--canaries`:
+//! decoy files must exist and keep their SHA-256, decoy accounts must exist and
+//! never have logged on or seen a failed password, and decoy registry values
+//! must keep their data. Anything else is reported as tampering, so the triage
+//! run doubles as a health check of the deception layer during an incident.
+//!
+//! ```toml
+//! [[file]]
+//! path = "C:\\Users\\Public\\Documents\\passwords.xlsx"
+//! sha256 = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08"
+//!
+//! [[account]]
+//! name = "svc_backup_legacy"
+//!
+//! [[registry]]
+//! key = "HKLM\\SOFTWARE\\Vendor\\Vault"
+//! value = "ApiKey"
+//! data = "AKIA-CANARY-0001"
+//! ```
+
+// Account and registry canaries are only checked on Windows
+#![cfg_attr(not(windows), allow(dead_code))]
+
+use crate::forensic_types::{AuditEntry, CanaryStatus};
+use crate::hashing;
+use crate::offline::OfflineRoot;
+use crate::remediate::split_registry_key;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+#[cfg(windows)]
+use crate::footprint::TrackedKey;
+
+const COMPONENT: &str = "canaries";
+
+#[derive(Debug, Clone, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct CanaryConfig {
+    #[serde(default, rename = "file")]
+    pub files: Vec<FileCanary>,
+    #[serde(default, rename = "account")]
+    pub accounts: Vec<AccountCanary>,
+    #[serde(default, rename = "registry")]
+    pub registry: Vec<RegistryCanary>,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct FileCanary {
+    pub path: String,
+    /// Expected SHA-256; only presence is checked when absent
+    #[serde(default)]
+    pub sha256: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct AccountCanary {
+    /// Local account name
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct RegistryCanary {
+    /// Key with its hive, e.g. `HKLM\SOFTWARE\Vendor\Vault`
+    pub key: String,
+    /// Value that must exist below the key; only the key is checked when absent
+    #[serde(default)]
+    pub value: Option<String>,
+    /// Expected data of the value, compared as text
+    #[serde(default)]
+    pub data: Option<String>,
+}
+
+impl CanaryConfig {
+    /// Load and validate a canary list from a TOML file
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read canary list {}: {}", path.display(), e))?;
+        Self::parse(&text).map_err(|e| format!("Invalid canary list {}: {}", path.display(), e))
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let config: CanaryConfig = toml::from_str(text).map_err(|e| e.to_string())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn count(&self) -> usize {
+        self.files.len() + self.accounts.len() + self.registry.len()
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.count() == 0 {
+            return Err("no [[file]], [[account]] or [[registry]] entries".to_string());
+        }
+        for file in &self.files {
+            if file.path.trim().is_empty() {
+                return Err("file canary path must not be empty".to_string());
+            }
+            if let Some(sha256) = &file.sha256 {
+                if sha256.len() != 64 || !sha256.chars().all(|c| c.is_ascii_hexdigit()) {
+                    return Err(format!("sha256 of file canary {} is not a SHA-256 digest", file.path));
+                }
+            }
+        }
+        if self.accounts.iter().any(|account| account.name.trim().is_empty()) {
+            return Err("account canary name must not be empty".to_string());
+        }
+        for entry in &self.registry {
+            if split_registry_key(&entry.key).is_none() {
+                return Err(format!("registry canary key {} must start with HKLM, HKCU or HKU", entry.key));
+            }
+            if entry.data.is_some() && entry.value.is_none() {
+                return Err(format!("registry canary {} has data but no value name", entry.key));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Check every canary of the list on the live system or the offline volume
+pub fn verify_canaries(config: &CanaryConfig, root: Option<&OfflineRoot>) -> (Vec<CanaryStatus>, Vec<AuditEntry>) {
+    let start_time = std::time::Instant::now();
+    let mut audit_log = vec![AuditEntry::new(COMPONENT, "INFO", "start_verification", format!("Verifying {} canaries", config.count()), "started")];
+
+    let mut results: Vec<CanaryStatus> = config.files.iter().map(|file| check_file(file, root)).collect();
+    results.extend(config.accounts.iter().map(|account| check_account(account, root)));
+    results.extend(config.registry.iter().map(|entry| check_registry(entry, root)));
+
+    for result in &results {
+        let level = match result.status.as_str() {
+            "intact" => continue,
+            "unverified" => "WARN",
+            _ => "ERROR",
+        };
+        audit_log.push(AuditEntry::new(COMPONENT, level, "verify_canary", format!("{} canary {} is {}: {}", result.kind, result.target, result.status, result.details), &result.status));
+    }
+
+    let tampered = results.iter().filter(|r| tampered(r)).count();
+    audit_log.push(AuditEntry {
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        level: "INFO".to_string(),
+        component: "canaries".to_string(),
+        action: "complete_verification".to_string(),
+        details: format!("Verified {} canaries, {} tampered", results.len(), tampered),
+        duration_ms: Some(start_time.elapsed().as_millis() as u64),
+        result: "success".to_string(),
+    });
+
+    (results, audit_log)
+}
+
+/// Whether a canary was removed, changed or used
+pub fn tampered(status: &CanaryStatus) -> bool {
+    matches!(status.status.as_str(), "missing" | "modified" | "accessed")
+}
+
+fn check_file(canary: &FileCanary, root: Option<&OfflineRoot>) -> CanaryStatus {
+    let path = match root {
+        Some(root) => root.resolve(&canary.path),
+        None => PathBuf::from(&canary.path),
+    };
+    let mut status = CanaryStatus::new("file", &canary.path);
+    status.expected = canary.sha256.as_ref().map(|sha256| sha256.to_lowercase());
+
+    let metadata = match std::fs::metadata(&path) {
+        Ok(metadata) if metadata.is_file() => metadata,
+        Ok(_) => return status.with("missing", "the path is not a file".to_string()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return status.with("missing", "the file no longer exists".to_string()),
+        Err(e) => return status.with("unverified", format!("failed to read {}: {}", path.display(), e)),
+    };
+    status.last_activity = metadata.accessed().ok().map(|time| chrono::DateTime::<chrono::Utc>::from(time).to_rfc3339());
+
+    let Some(expected) = status.expected.clone() else {
+        return status.with("intact", format!("present ({} bytes)", metadata.len()));
+    };
+    match hashing::shared().hash_file(&path).map(|hashes| hashes.sha256.unwrap_or_default().to_lowercase()) {
+        Ok(sha256) if sha256 == expected => {
+            status.observed = Some(sha256);
+            status.with("intact", format!("present ({} bytes), content unchanged", metadata.len()))
+        }
+        Ok(sha256) => {
+            status.observed = Some(sha256);
+            status.with("modified", "the content no longer matches the expected SHA-256".to_string())
+        }
+        Err(e) => status.with("unverified", e),
+    }
+}
+
+fn check_account(canary: &AccountCanary, root: Option<&OfflineRoot>) -> CanaryStatus {
+    let status = CanaryStatus::new("account", &canary.name);
+    match root {
+        Some(root) => offline_account(status, &canary.name, root),
+        None => live_account(status, &canary.name),
+    }
+}
+
+#[cfg(windows)]
+fn live_account(status: CanaryStatus, name: &str) -> CanaryStatus {
+    let account = match windows_api::local_account(name) {
+        Ok(Some(account)) => account,
+        Ok(None) => return status.with("missing", "the account no longer exists".to_string()),
+        Err(e) => return status.with("unverified", e),
+    };
+    account_status(status, &account)
+}
+
+#[cfg(not(windows))]
+fn live_account(status: CanaryStatus, _name: &str) -> CanaryStatus {
+    status.with("unverified", "account checks require Windows".to_string())
+}
+
+/// Logon activity of a local account
+struct AccountActivity {
+    disabled: bool,
+    last_logon: u32, // Seconds since 1970, 0 when never logged on
+    logons: u32,
+    bad_passwords: u32,
+}
+
+/// A decoy account must never be used: any logon or failed password counts as access
+fn account_status(mut status: CanaryStatus, account: &AccountActivity) -> CanaryStatus {
+    status.last_activity = (account.last_logon > 0)
+        .then(|| chrono::DateTime::from_timestamp(account.last_logon as i64, 0))
+        .flatten()
+        .map(|time| time.to_rfc3339());
+    status.observed = Some(format!("{} logons, {} failed passwords{}", account.logons, account.bad_passwords, if account.disabled { ", disabled" } else { "" }));
+
+    if account.last_logon > 0 || account.logons > 0 {
+        status.with("accessed", "the account has logged on".to_string())
+    } else if account.bad_passwords > 0 {
+        status.with("accessed", "logon attempts with a wrong password were recorded".to_string())
+    } else {
+        status.with("intact", "present and never used".to_string())
+    }
+}
+
+/// Offline, only the presence of the account in the SAM hive is checked
+#[cfg(windows)]
+fn offline_account(status: CanaryStatus, name: &str, root: &OfflineRoot) -> CanaryStatus {
+    let sam = match root.load_hive("SAM") {
+        Ok(sam) => sam,
+        Err(e) => return status.with("unverified", format!("failed to load the SAM hive: {}", e)),
+    };
+    match sam.open_tracked(format!("SAM\\Domains\\Account\\Users\\Names\\{}", name)) {
+        Ok(_) => status.with("intact", "present in the SAM hive; logon activity is not checked offline".to_string()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => status.with("missing", "the account is not in the SAM hive".to_string()),
+        Err(e) => status.with("unverified", e.to_string()),
+    }
+}
+
+#[cfg(not(windows))]
+fn offline_account(status: CanaryStatus, _name: &str, _root: &OfflineRoot) -> CanaryStatus {
+    status.with("unverified", "reading the offline SAM hive requires Windows".to_string())
+}
+
+#[cfg(windows)]
+fn check_registry(canary: &RegistryCanary, root: Option<&OfflineRoot>) -> CanaryStatus {
+    use std::io::ErrorKind;
+
+    let mut status = CanaryStatus::new("registry", &registry_target(canary));
+    status.expected = canary.data.clone();
+    let key = match windows_api::open_key(&canary.key, root) {
+        Ok(key) => key,
+        Err(e) if e.kind() == ErrorKind::NotFound => return status.with("missing", "the key no longer exists".to_string()),
+        Err(e) => return status.with("unverified", e.to_string()),
+    };
+    status.last_activity = Some(crate::offline::key_last_write_time(&key)).filter(|time| !time.is_empty());
+
+    let Some(value_name) = &canary.value else {
+        return status.with("intact", "the key is present".to_string());
+    };
+    let data = match windows_api::value_text(&key, value_name) {
+        Ok(data) => data,
+        Err(e) if e.kind() == ErrorKind::NotFound => return status.with("missing", "the value no longer exists".to_string()),
+        Err(e) => return status.with("unverified", e.to_string()),
+    };
+    status.observed = Some(data.clone());
+    match &canary.data {
+        Some(expected) if *expected != data => status.with("modified", "the value data changed".to_string()),
+        _ => status.with("intact", "the value is present".to_string()),
+    }
+}
+
+#[cfg(not(windows))]
+fn check_registry(canary: &RegistryCanary, _root: Option<&OfflineRoot>) -> CanaryStatus {
+    CanaryStatus::new("registry", &registry_target(canary)).with("unverified", "registry checks require Windows".to_string())
+}
+
+/// `KEY` or `KEY\VALUE`
+fn registry_target(canary: &RegistryCanary) -> String {
+    match &canary.value {
+        Some(value) => format!("{}\\{}", canary.key, value),
+        None => canary.key.clone(),
+    }
+}
+
+impl CanaryStatus {
+    fn new(kind: &str, target: &str) -> Self {
+        CanaryStatus {
+            kind: kind.to_string(),
+            target: target.to_string(),
+            status: String::new(),
+            expected: None,
+            observed: None,
+            last_activity: None,
+            details: String::new(),
+        }
+    }
+
+    fn with(mut self, status: &str, details: String) -> Self {
+        self.status = status.to_string();
+        self.details = details;
+        self
+    }
+}
+
+#[cfg(windows)]
+mod windows_api {
+    use super::AccountActivity;
+    use crate::footprint::TrackedKey;
+    use crate::offline::{current_control_set, OfflineRoot};
+    use crate::remediate::split_registry_key;
+    use std::io::{self, ErrorKind};
+    use windows::core::{HSTRING, PCWSTR};
+    use windows::Win32::NetworkManagement::NetManagement::{
+        NetApiBufferFree, NetUserGetInfo, NERR_Success, NERR_UserNotFound, UF_ACCOUNTDISABLE, USER_INFO_2,
+    };
+    use winreg::enums::{HKEY_CLASSES_ROOT, HKEY_CURRENT_USER, HKEY_LOCAL_MACHINE, HKEY_USERS};
+    use winreg::RegKey;
+
+    /// Activity of a local account, None when it does not exist
+    pub fn local_account(name: &str) -> Result<Option<AccountActivity>, String> {
+        let username = HSTRING::from(name);
+        let mut buffer: *mut u8 = std::ptr::null_mut();
+        let status = unsafe { NetUserGetInfo(PCWSTR::null(), &username, 2, &mut buffer) };
+        if status == NERR_UserNotFound {
+            return Ok(None);
+        }
+        if status != NERR_Success || buffer.is_null() {
+            return Err(format!("NetUserGetInfo failed: error {}", status));
+        }
+
+        let info = unsafe { &*(buffer as *const USER_INFO_2) };
+        let account = AccountActivity {
+            disabled: info.usri2_flags.0 & UF_ACCOUNTDISABLE.0 != 0,
+            last_logon: info.usri2_last_logon,
+            logons: info.usri2_num_logons,
+            bad_passwords: info.usri2_bad_pw_count,
+        };
+        unsafe { NetApiBufferFree(Some(buffer as *const _)) };
+        Ok(Some(account))
+    }
+
+    /// Open a key of the live registry, or of a machine hive on the offline volume
+    pub fn open_key(key: &str, root: Option<&OfflineRoot>) -> io::Result<RegKey> {
+        let unsupported = || io::Error::new(ErrorKind::Unsupported, format!("unsupported registry key {}", key));
+        let (hive, path) = split_registry_key(key).ok_or_else(unsupported)?;
+        let Some(root) = root else {
+            let predef = match hive {
+                "HKLM" => HKEY_LOCAL_MACHINE,
+                "HKCU" => HKEY_CURRENT_USER,
+                "HKU" => HKEY_USERS,
+                _ => HKEY_CLASSES_ROOT,
+            };
+            return RegKey::predef(predef).open_tracked(path);
+        };
+
+        // Offline, HKLM\<HIVE>\... maps onto the hive file of that name
+        if hive != "HKLM" {
+            return Err(io::Error::new(ErrorKind::Unsupported, "only HKLM canaries are checked offline"));
+        }
+        let (hive_name, subkey) = path.split_once('\\').unwrap_or((path, ""));
+        let hive_name = hive_name.to_uppercase();
+        if !["SOFTWARE", "SYSTEM", "SAM", "SECURITY"].contains(&hive_name.as_str()) {
+            return Err(unsupported());
+        }
+        let hive_key = root.load_hive(&hive_name)?;
+        let subkey = match subkey.split_once('\\') {
+            Some((first, rest)) if hive_name == "SYSTEM" && first.eq_ignore_ascii_case("CurrentControlSet") => {
+                format!("{}\\{}", current_control_set(&hive_key), rest)
+            }
+            _ if hive_name == "SYSTEM" && subkey.eq_ignore_ascii_case("CurrentControlSet") => current_control_set(&hive_key),
+            _ => subkey.to_string(),
+        };
+        hive_key.open_tracked(subkey)
+    }
+
+    /// Value data as text: strings as-is, numbers in decimal, anything else as hex
+    pub fn value_text(key: &RegKey, name: &str) -> io::Result<String> {
+        let raw = key.get_raw_value(name)?;
+        Ok(key
+            .get_value::<String, _>(name)
+            .or_else(|_| key.get_value::<u32, _>(name).map(|value| value.to_string()))
+            .or_else(|_| key.get_value::<u64, _>(name).map(|value| value.to_string()))
+            .unwrap_or_else(|_| hex::encode(raw.bytes)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_canary_config() {
+        let config = CanaryConfig::parse(
+            r#"
+[[file]]
+path = "C:\\Users\\Public\\Documents\\passwords.xlsx"
+
+[[account]]
+name = "svc_backup_legacy"
+
+[[registry]]
+key = "HKLM\\SOFTWARE\\Vendor\\Vault"
+value = "ApiKey"
+data = "AKIA-CANARY-0001"
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.count(), 3);
+        assert_eq!(config.registry[0].value.as_deref(), Some("ApiKey"));
+        assert_eq!(registry_target(&config.registry[0]), "HKLM\\SOFTWARE\\Vendor\\Vault\\ApiKey");
+
+        assert!(CanaryConfig::parse("").is_err());
+        assert!(CanaryConfig::parse("[[file]]\npath = \"C:\\\\x\"\nsha256 = \"abc\"").is_err());
+        assert!(CanaryConfig::parse("[[registry]]\nkey = \"SOFTWARE\\\\Vendor\"").is_err());
+        assert!(CanaryConfig::parse("[[registry]]\nkey = \"HKLM\\\\SOFTWARE\\\\Vendor\"\ndata = \"x\"").is_err());
+        assert!(CanaryConfig::parse("[[share]]\nname = \"x\"").is_err());
+    }
+
+    #[test]
+    fn test_check_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("passwords.xlsx");
+        std::fs::write(&path, b"decoy").unwrap();
+        let sha256 = hashing::HashService::new(Vec::new()).hash_file(&path).unwrap().sha256.unwrap();
+
+        let canary = |sha256: Option<String>| FileCanary { path: path.display().to_string(), sha256 };
+        assert_eq!(check_file(&canary(None), None).status, "intact");
+        assert_eq!(check_file(&canary(Some(sha256.to_uppercase())), None).status, "intact");
+
+        std::fs::write(&path, b"opened and saved").unwrap();
+        let modified = check_file(&canary(Some(sha256.clone())), None);
+        assert_eq!(modified.status, "modified");
+        assert_ne!(modified.observed, modified.expected);
+
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(check_file(&canary(Some(sha256)), None).status, "missing");
+    }
+
+    #[test]
+    fn test_account_status() {
+        let account = |last_logon, logons, bad_passwords| AccountActivity { disabled: false, last_logon, logons, bad_passwords };
+        let status = |activity| account_status(CanaryStatus::new("account", "svc_backup_legacy"), &activity);
+
+        assert_eq!(status(account(0, 0, 0)).status, "intact");
+        assert_eq!(status(account(0, 0, 3)).status, "accessed");
+        let used = status(account(1_700_000_000, 1, 0));
+        assert_eq!(used.status, "accessed");
+        assert_eq!(used.last_activity.as_deref(), Some("2023-11-14T22:13:20+00:00"));
+        assert!(tampered(&used));
+    }
+}
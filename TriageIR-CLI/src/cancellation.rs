@@ -0,0 +1,185 @@
+//! Collector timeouts and Ctrl+C
+//! Collectors run on a thread of their own while the scan thread waits for
+//! them, so a collector that hangs (an event log query, module enumeration of a
+//! protected process) can be abandoned when it exceeds its timeout or when the
+//! user presses Ctrl+C. The abandoned thread cannot be killed; it keeps running
+//! until the process exits, but the scan moves on with an empty result for it.
+//! After Ctrl+C the remaining collectors are skipped, the results gathered so
+//! far are written with `scan_interrupted` set and the process exits with
+//! EXIT_INTERRUPTED. A second Ctrl+C terminates immediately. A collector
+//! thread that panics is recorded as failed and the scan carries on. The flag
+//! is cleared when a scan starts, so a worker or server process that was
+//! interrupted once still runs its next scans.
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+/// Exit code of a scan stopped with Ctrl+C
+pub const EXIT_INTERRUPTED: i32 = 130;
+
+/// How often a waiting scan thread checks for Ctrl+C
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Why a collector did not finish
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stop {
+    TimedOut(Duration),
+    Interrupted,
+    MemoryLimit(u64), // --max-memory-mb
+    Disabled, // Left out by the scan profile
+    Failed, // The collector thread panicked
+}
+
+impl Stop {
+    /// Collector status recorded in collector_stats
+    pub fn status(&self) -> &'static str {
+        match self {
+            Stop::TimedOut(_) => "timed_out",
+            Stop::Interrupted => "interrupted",
+            Stop::MemoryLimit(_) => "truncated",
+            Stop::Disabled => "skipped",
+            Stop::Failed => "failed",
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Stop::TimedOut(timeout) => format!("exceeded the collector timeout of {}s", timeout.as_secs()),
+            Stop::Interrupted => "scan interrupted (Ctrl+C)".to_string(),
+            Stop::MemoryLimit(limit_mb) => format!("memory limit of {} MB approached; collector skipped", limit_mb),
+            Stop::Disabled => "disabled by the scan profile".to_string(),
+            Stop::Failed => "collector panicked; its results are lost".to_string(),
+        }
+    }
+}
+
+/// Timeouts from `--collector-timeout`: `SECS` for every collector,
+/// `COLLECTOR=SECS` for one
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CollectorTimeouts {
+    pub default: Option<Duration>,
+    pub collectors: BTreeMap<String, Duration>,
+}
+
+impl CollectorTimeouts {
+    pub fn parse(specs: &[String]) -> Result<Self, String> {
+        let mut timeouts = CollectorTimeouts::default();
+        for spec in specs {
+            let (collector, seconds) = match spec.split_once('=') {
+                Some((collector, seconds)) => (Some(collector.trim()), seconds),
+                None => (None, spec.as_str()),
+            };
+            let timeout = match seconds.trim().parse::<u64>() {
+                Ok(seconds) if seconds > 0 => Duration::from_secs(seconds),
+                _ => return Err(format!("invalid collector timeout {} (SECS or COLLECTOR=SECS, at least 1 second)", spec)),
+            };
+            match collector {
+                Some("") => return Err(format!("invalid collector timeout {}: no collector name", spec)),
+                Some(collector) => {
+                    timeouts.collectors.insert(collector.to_string(), timeout);
+                }
+                None => timeouts.default = Some(timeout),
+            }
+        }
+        Ok(timeouts)
+    }
+
+    pub fn get(&self, collector: &str) -> Option<Duration> {
+        self.collectors.get(collector).copied().or(self.default)
+    }
+}
+
+/// True once Ctrl+C was pressed
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// Clear a Ctrl+C of an earlier scan in this process
+pub fn reset_interrupt() {
+    INTERRUPTED.store(false, Ordering::SeqCst);
+}
+
+/// Run `collector` on its own thread and wait for it until the timeout
+/// expires or the scan is interrupted
+pub fn run_bounded<T: Send + 'static>(timeout: Option<Duration>, collector: impl FnOnce() -> T + Send + 'static) -> Result<T, Stop> {
+    if interrupted() {
+        return Err(Stop::Interrupted);
+    }
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        let _ = sender.send(collector());
+    });
+
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    loop {
+        let wait = match deadline {
+            Some(deadline) => deadline.saturating_duration_since(Instant::now()).min(POLL_INTERVAL),
+            None => POLL_INTERVAL,
+        };
+        match receiver.recv_timeout(wait) {
+            Ok(result) => return Ok(result),
+            Err(RecvTimeoutError::Disconnected) => return Err(Stop::Failed),
+            Err(RecvTimeoutError::Timeout) => {}
+        }
+        if interrupted() {
+            return Err(Stop::Interrupted);
+        }
+        if let (Some(deadline), Some(timeout)) = (deadline, timeout) {
+            if Instant::now() >= deadline {
+                return Err(Stop::TimedOut(timeout));
+            }
+        }
+    }
+}
+
+/// Catch Ctrl+C: the first press stops the scan gracefully, a second one exits
+pub fn install_interrupt_handler() {
+    #[cfg(unix)]
+    {
+        extern "C" fn on_interrupt(_signal: libc::c_int) {
+            if INTERRUPTED.swap(true, Ordering::SeqCst) {
+                unsafe { libc::_exit(EXIT_INTERRUPTED) };
+            }
+        }
+        unsafe {
+            libc::signal(libc::SIGINT, on_interrupt as extern "C" fn(libc::c_int) as libc::sighandler_t);
+        }
+    }
+    #[cfg(windows)]
+    {
+        use windows::Win32::Foundation::{BOOL, TRUE};
+        use windows::Win32::System::Console::{SetConsoleCtrlHandler, CTRL_BREAK_EVENT, CTRL_C_EVENT};
+
+        unsafe extern "system" fn on_control(control_type: u32) -> BOOL {
+            // FALSE passes a second press on to the default handler, which exits
+            let first = (control_type == CTRL_C_EVENT || control_type == CTRL_BREAK_EVENT) && !INTERRUPTED.swap(true, Ordering::SeqCst);
+            BOOL::from(first)
+        }
+        unsafe {
+            let _ = SetConsoleCtrlHandler(Some(on_control), TRUE);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_bounded() {
+        assert_eq!(run_bounded(None, || 42), Ok(42));
+        let timeout = Duration::from_millis(50);
+        assert_eq!(run_bounded(Some(timeout), || std::thread::sleep(Duration::from_secs(5))), Err(Stop::TimedOut(timeout)));
+        assert_eq!(run_bounded(None, || -> u32 { panic!("collector bug") }), Err(Stop::Failed));
+
+        let timeouts = CollectorTimeouts::parse(&["120".to_string(), "event_logs=600".to_string()]).unwrap();
+        assert_eq!(timeouts.get("event_logs"), Some(Duration::from_secs(600)));
+        assert_eq!(timeouts.get("processes"), Some(Duration::from_secs(120)));
+        assert!(CollectorTimeouts::parse(&["event_logs=0".to_string()]).is_err());
+        assert!(CollectorTimeouts::parse(&["=5".to_string()]).is_err());
+    }
+}
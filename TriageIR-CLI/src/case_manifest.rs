@@ -0,0 +1,210 @@
+//! Case folder hash manifest
+//! After a collection onto removable media the case folder (TRIAGEIR_OUTPUT_DIR
+//! in portable mode) is hashed recursively with the --hash-algorithms digests.
+//! The manifest and a receipt holding its SHA-256 and an HMAC-SHA256 signature
+//! are written to a separate location (--receipt-dir, e.g. the examiner laptop
+//! or a network share) and made read-only there. Files changed, removed or
+//! added on the drive after the collection then show up in `verify-case`, and a
+//! manifest rewritten to match needs the key to forge its receipt.
+
+use crate::forensic_types::{CaseManifest, CaseReceipt, ManifestEntry};
+use crate::hashing;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Signature scheme recorded in the receipt
+pub const SIGNATURE_ALGORITHM: &str = "HMAC-SHA256";
+
+/// Environment variable holding the receipt signing key; --password is used when unset
+pub const RECEIPT_KEY_VARIABLE: &str = "TRIAGEIR_RECEIPT_KEY";
+
+const HMAC_BLOCK_SIZE: usize = 64;
+
+/// Hash every file below the case folder, in path order
+pub fn build_manifest(case_directory: &Path, scan_id: &str) -> Result<CaseManifest, String> {
+    if !case_directory.is_dir() {
+        return Err(format!("Case folder not found: {}", case_directory.display()));
+    }
+    let hashes = hashing::shared();
+    let mut files = Vec::new();
+
+    for entry in WalkDir::new(case_directory).sort_by_file_name() {
+        let entry = entry.map_err(|e| format!("Failed to walk {}: {}", case_directory.display(), e))?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let metadata = entry.metadata().map_err(|e| format!("Failed to read {}: {}", entry.path().display(), e))?;
+        files.push(ManifestEntry {
+            path: relative_path(case_directory, entry.path()),
+            size: metadata.len(),
+            modified: metadata.modified().ok().map(|time| chrono::DateTime::<chrono::Utc>::from(time).to_rfc3339()),
+            hashes: hashes.hash_file(entry.path())?,
+        });
+    }
+
+    Ok(CaseManifest {
+        scan_id: scan_id.to_string(),
+        case_directory: case_directory.display().to_string(),
+        created_at: chrono::Utc::now().to_rfc3339(),
+        hash_algorithms: hashes.algorithms().iter().map(|a| a.name().to_string()).collect(),
+        files,
+    })
+}
+
+/// Write the manifest and its signed receipt below `receipt_directory` as read-only
+/// files; returns the two paths
+pub fn write_receipt(manifest: &CaseManifest, receipt_directory: &Path, key: &str) -> Result<(PathBuf, PathBuf), String> {
+    fs::create_dir_all(receipt_directory).map_err(|e| format!("Failed to create {}: {}", receipt_directory.display(), e))?;
+    let case_directory = Path::new(&manifest.case_directory).canonicalize().map_err(|e| format!("Failed to resolve {}: {}", manifest.case_directory, e))?;
+    let receipt_root = receipt_directory.canonicalize().map_err(|e| format!("Failed to resolve {}: {}", receipt_directory.display(), e))?;
+    if receipt_root.starts_with(&case_directory) {
+        return Err("the receipt directory must not be inside the case folder".to_string());
+    }
+
+    let manifest_json = serde_json::to_vec_pretty(manifest).map_err(|e| format!("Failed to serialize the manifest: {}", e))?;
+    let manifest_file = format!("manifest-{}.json", manifest.scan_id);
+    let receipt = CaseReceipt {
+        scan_id: manifest.scan_id.clone(),
+        manifest_file: manifest_file.clone(),
+        manifest_sha256: hex::encode(Sha256::digest(&manifest_json)),
+        file_count: manifest.files.len(),
+        total_bytes: manifest.files.iter().map(|file| file.size).sum(),
+        created_at: chrono::Utc::now().to_rfc3339(),
+        signature_algorithm: SIGNATURE_ALGORITHM.to_string(),
+        signature: hmac_sha256(key.as_bytes(), &manifest_json),
+    };
+    let receipt_json = serde_json::to_vec_pretty(&receipt).map_err(|e| format!("Failed to serialize the receipt: {}", e))?;
+
+    let manifest_path = receipt_directory.join(manifest_file);
+    let receipt_path = receipt_directory.join(format!("receipt-{}.json", manifest.scan_id));
+    write_read_only(&manifest_path, &manifest_json)?;
+    write_read_only(&receipt_path, &receipt_json)?;
+    Ok((manifest_path, receipt_path))
+}
+
+/// Save the RFC 3161 request and token of the manifest next to its receipt as
+/// read-only files; returns the token path
+pub fn write_timestamp(receipt_directory: &Path, scan_id: &str, request: &[u8], token: &[u8]) -> Result<PathBuf, String> {
+    let token_path = receipt_directory.join(format!("timestamp-{}.tsr", scan_id));
+    write_read_only(&receipt_directory.join(format!("timestamp-{}.tsq", scan_id)), request)?;
+    write_read_only(&token_path, token)?;
+    Ok(token_path)
+}
+
+/// Check a receipt against its manifest and the manifest against the case folder;
+/// returns one line per discrepancy, none if the folder is unchanged
+pub fn verify_case(manifest_path: &Path, receipt_path: &Path, case_directory: &Path, key: &str) -> Result<Vec<String>, String> {
+    let manifest_json = fs::read(manifest_path).map_err(|e| format!("Failed to read {}: {}", manifest_path.display(), e))?;
+    let receipt: CaseReceipt = fs::read(receipt_path)
+        .map_err(|e| format!("Failed to read {}: {}", receipt_path.display(), e))
+        .and_then(|data| serde_json::from_slice(&data).map_err(|e| format!("Invalid receipt {}: {}", receipt_path.display(), e)))?;
+    let manifest: CaseManifest = serde_json::from_slice(&manifest_json).map_err(|e| format!("Invalid manifest {}: {}", manifest_path.display(), e))?;
+
+    let mut discrepancies = Vec::new();
+    if hex::encode(Sha256::digest(&manifest_json)) != receipt.manifest_sha256 {
+        discrepancies.push("manifest: SHA-256 differs from the receipt".to_string());
+    }
+    if hmac_sha256(key.as_bytes(), &manifest_json) != receipt.signature {
+        discrepancies.push("receipt: signature does not match the manifest (wrong key or altered files)".to_string());
+    }
+
+    let mut recorded: BTreeMap<&str, &ManifestEntry> = manifest.files.iter().map(|file| (file.path.as_str(), file)).collect();
+    // A fresh service: the shared cache would return the digests recorded at collection time
+    let hashes = hashing::HashService::new(vec![hashing::HashAlgorithm::Sha256]);
+    for entry in WalkDir::new(case_directory).sort_by_file_name().into_iter().flatten().filter(|entry| entry.file_type().is_file()) {
+        let path = relative_path(case_directory, entry.path());
+        match recorded.remove(path.as_str()) {
+            Some(file) => match hashes.hash_file(entry.path()) {
+                Ok(current) if current.sha256 == file.hashes.sha256 => {}
+                Ok(_) => discrepancies.push(format!("modified: {}", path)),
+                Err(e) => discrepancies.push(format!("unreadable: {} ({})", path, e)),
+            },
+            None => discrepancies.push(format!("added: {}", path)),
+        }
+    }
+    discrepancies.extend(recorded.keys().map(|path| format!("removed: {}", path)));
+    Ok(discrepancies)
+}
+
+/// Path below the case folder with forward slashes, identical on every platform
+fn relative_path(root: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    relative.components().map(|c| c.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/")
+}
+
+fn write_read_only(path: &Path, data: &[u8]) -> Result<(), String> {
+    fs::write(path, data).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    let mut permissions = fs::metadata(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?.permissions();
+    permissions.set_readonly(true);
+    fs::set_permissions(path, permissions).map_err(|e| format!("Failed to make {} read-only: {}", path.display(), e))
+}
+
+/// HMAC-SHA256 (RFC 2104) as lowercase hex
+fn hmac_sha256(key: &[u8], message: &[u8]) -> String {
+    hex::encode(hmac_sha256_bytes(key, message))
+}
+
+/// Raw HMAC-SHA256 tag, for signing schemes that chain keys (AWS SigV4)
+pub(crate) fn hmac_sha256_bytes(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut block = [0u8; HMAC_BLOCK_SIZE];
+    if key.len() > HMAC_BLOCK_SIZE {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let pad = |byte: u8| block.iter().map(|b| b ^ byte).collect::<Vec<u8>>();
+
+    let mut inner = Sha256::new();
+    inner.update(pad(0x36));
+    inner.update(message);
+    let mut outer = Sha256::new();
+    outer.update(pad(0x5c));
+    outer.update(inner.finalize());
+    outer.finalize().to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hmac_sha256() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            hmac_sha256(b"Jefe", b"what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn test_manifest_detects_tampering() {
+        let case_dir = tempfile::tempdir().unwrap();
+        let receipt_dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(case_dir.path().join("Output")).unwrap();
+        fs::write(case_dir.path().join("Output").join("results.json"), b"{}").unwrap();
+        fs::write(case_dir.path().join("notes.txt"), b"collected by IR").unwrap();
+
+        let manifest = build_manifest(case_dir.path(), "scan-1").unwrap();
+        let paths: Vec<&str> = manifest.files.iter().map(|file| file.path.as_str()).collect();
+        assert_eq!(paths, ["Output/results.json", "notes.txt"]);
+
+        let (manifest_path, receipt_path) = write_receipt(&manifest, receipt_dir.path(), "secret").unwrap();
+        assert!(fs::metadata(&receipt_path).unwrap().permissions().readonly());
+        assert!(verify_case(&manifest_path, &receipt_path, case_dir.path(), "secret").unwrap().is_empty());
+
+        fs::write(case_dir.path().join("notes.txt"), b"edited later").unwrap();
+        fs::write(case_dir.path().join("planted.exe"), b"MZ").unwrap();
+        fs::remove_file(case_dir.path().join("Output").join("results.json")).unwrap();
+        let discrepancies = verify_case(&manifest_path, &receipt_path, case_dir.path(), "wrong").unwrap();
+        assert_eq!(discrepancies.len(), 4);
+        assert!(discrepancies.contains(&"modified: notes.txt".to_string()));
+        assert!(discrepancies.contains(&"added: planted.exe".to_string()));
+        assert!(discrepancies.contains(&"removed: Output/results.json".to_string()));
+
+        // The receipt may not live on the media it vouches for
+        assert!(write_receipt(&manifest, &case_dir.path().join("receipts"), "secret").is_err());
+    }
+}
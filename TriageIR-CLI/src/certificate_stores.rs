@@ -0,0 +1,494 @@
+//! Root and intermediate certificate stores
+//! Certificates are read from the registry stores of the machine (local,
+//! group policy and enterprise) and of every user, each a serialized
+//! certificate under `Certificates\<thumbprint>\Blob`. A root is Microsoft
+//! trusted when its thumbprint is in the root program's certificate trust list
+//! that Windows caches in AuthRoot\AutoUpdate\EncodedCtl, or it is one of the
+//! roots Windows ships with. Other roots whose store entry was written inside
+//! the scan time window (without one, the last 90 days) are flagged: a
+//! planted root lets an intruder intercept TLS or sign malware that validates.
+
+use crate::binutil::{read_u32, utf16_string};
+use crate::footprint;
+use crate::forensic_types::{AuditEntry, CertificateEntry};
+use crate::hive::Hive;
+use crate::offline::OfflineRoot;
+use crate::time_window::{self, TimeWindow};
+use sha1::{Digest, Sha1};
+use std::path::Path;
+
+#[cfg(windows)]
+use crate::footprint::TrackedKey;
+#[cfg(windows)]
+use crate::user_hives;
+#[cfg(windows)]
+use winreg::enums::HKEY_LOCAL_MACHINE;
+#[cfg(windows)]
+use winreg::RegKey;
+
+const COMPONENT: &str = "certificate_stores";
+
+/// Machine stores, relative to the SOFTWARE hive: (location, store, key)
+const MACHINE_STORES: [(&str, &str, &str); 7] = [
+    ("machine", "Root", "Microsoft\\SystemCertificates\\ROOT\\Certificates"),
+    ("machine", "AuthRoot", "Microsoft\\SystemCertificates\\AuthRoot\\Certificates"),
+    ("machine", "CA", "Microsoft\\SystemCertificates\\CA\\Certificates"),
+    ("group_policy", "Root", "Policies\\Microsoft\\SystemCertificates\\Root\\Certificates"),
+    ("group_policy", "CA", "Policies\\Microsoft\\SystemCertificates\\CA\\Certificates"),
+    ("enterprise", "Root", "Microsoft\\EnterpriseCertificates\\Root\\Certificates"),
+    ("enterprise", "CA", "Microsoft\\EnterpriseCertificates\\CA\\Certificates"),
+];
+/// User stores, relative to NTUSER.DAT
+const USER_STORES: [(&str, &str); 2] = [
+    ("Root", "Software\\Microsoft\\SystemCertificates\\Root\\Certificates"),
+    ("CA", "Software\\Microsoft\\SystemCertificates\\CA\\Certificates"),
+];
+const AUTHROOT_CTL_KEY: &str = "Microsoft\\SystemCertificates\\AuthRoot\\AutoUpdate";
+const USERS_DIRECTORY: &str = "C:\\Users";
+/// Roots added this many days before the scan (or --until) count as recent
+const RECENT_ROOT_DAYS: i64 = 90;
+
+/// Roots in the Root store of a fresh Windows installation, before any
+/// automatic root update (SHA-1 thumbprints)
+const WINDOWS_DEFAULT_ROOTS: [&str; 9] = [
+    "CDD4EEAE6000AC7F40C3802C171E30148030C072", // Microsoft Root Certificate Authority
+    "3B1EFD3A66EA28B16697394703A72CA340A05BD5", // Microsoft Root Certificate Authority 2010
+    "8F43288AD272F3103B6FB1428485EA3014C0BCFE", // Microsoft Root Certificate Authority 2011
+    "A43489159A520F0D93D032CCAF37E7FE20A8B419", // Microsoft Root Authority
+    "245C97DF7514E7CF2DF8BE72AE957B9E04741E85", // Copyright (c) 1997 Microsoft Corp.
+    "7F88CD7223F3C813818C994614A89C99FA3B5247", // Microsoft Authenticode(tm) Root Authority
+    "18F7C1FCC3090203FD5BAA2F861A754976C8DD25", // NO LIABILITY ACCEPTED, (c)97 VeriSign, Inc.
+    "BE36A4562FB2EE05DBB3D32323ADF445084ED656", // Thawte Timestamping CA
+    "742C3192E607E424EB4549542BE1BBC53E6174E2", // VeriSign Class 3 Public Primary Certification Authority
+];
+
+/// Serialized certificate property holding the DER encoding
+const CERT_CERT_PROP_ID: u32 = 32;
+const CERT_FRIENDLY_NAME_PROP_ID: u32 = 11;
+
+const DER_INTEGER: u8 = 0x02;
+const DER_SEQUENCE: u8 = 0x30;
+const DER_UTC_TIME: u8 = 0x17;
+const DER_GENERALIZED_TIME: u8 = 0x18;
+const DER_BMP_STRING: u8 = 0x1E;
+/// X.520 attribute types named in subjects and issuers (DER-encoded OIDs)
+const NAME_ATTRIBUTES: [(&[u8], &str); 7] = [
+    (&[0x55, 0x04, 0x03], "CN"),
+    (&[0x55, 0x04, 0x0B], "OU"),
+    (&[0x55, 0x04, 0x0A], "O"),
+    (&[0x55, 0x04, 0x07], "L"),
+    (&[0x55, 0x04, 0x08], "S"),
+    (&[0x55, 0x04, 0x06], "C"),
+    (&[0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x09, 0x01], "E"),
+];
+
+/// A registry hive holding certificate stores: SOFTWARE or a user's NTUSER.DAT
+trait CertificateHive {
+    fn subkeys(&self, key: &str) -> Vec<String>;
+    fn binary(&self, key: &str, name: &str) -> Option<Vec<u8>>;
+    fn last_written(&self, key: &str) -> String;
+}
+
+impl CertificateHive for Hive {
+    fn subkeys(&self, key: &str) -> Vec<String> {
+        self.open_key(key).map(|key| key.subkeys().iter().map(|subkey| subkey.name()).collect()).unwrap_or_default()
+    }
+
+    fn binary(&self, key: &str, name: &str) -> Option<Vec<u8>> {
+        self.open_key(key)?.value(name).map(|value| value.data)
+    }
+
+    fn last_written(&self, key: &str) -> String {
+        self.open_key(key).map(|key| key.last_written()).unwrap_or_default()
+    }
+}
+
+#[cfg(windows)]
+impl CertificateHive for RegKey {
+    fn subkeys(&self, key: &str) -> Vec<String> {
+        self.open_tracked(key).map(|key| key.enum_keys().filter_map(|name| name.ok()).collect()).unwrap_or_default()
+    }
+
+    fn binary(&self, key: &str, name: &str) -> Option<Vec<u8>> {
+        self.open_tracked(key).ok()?.get_raw_value(name).ok().map(|value| value.bytes)
+    }
+
+    fn last_written(&self, key: &str) -> String {
+        self.open_tracked(key).map(|key| crate::offline::key_last_write_time(&key)).unwrap_or_default()
+    }
+}
+
+/// Certificate fields read from the DER encoding
+#[derive(Debug, Clone, PartialEq)]
+struct ParsedCertificate {
+    serial_number: String,
+    issuer: String,
+    subject: String,
+    not_before: String,
+    not_after: String,
+    self_signed: bool,
+}
+
+/// Machine and user certificate stores of the running system
+#[cfg(windows)]
+pub fn collect_certificates(window: &TimeWindow) -> (Vec<CertificateEntry>, Vec<AuditEntry>) {
+    let start_time = std::time::Instant::now();
+    let mut audit_log = vec![AuditEntry::new(COMPONENT, "INFO", "start_collection", "Starting certificate store collection".to_string(), "started")];
+
+    let mut stores = CertificateStores::new(window);
+    match RegKey::predef(HKEY_LOCAL_MACHINE).open_tracked("SOFTWARE") {
+        Ok(software) => stores.add_machine(&software, &mut audit_log),
+        Err(e) => audit_log.push(AuditEntry::new(COMPONENT, "ERROR", "registry_access", format!("Failed to open HKLM\\SOFTWARE: {}", e), "error")),
+    }
+    let (hives, failures) = user_hives::live_user_hives();
+    for failure in failures {
+        audit_log.push(AuditEntry::new(COMPONENT, "WARN", "load_user_hive", failure, "error"));
+    }
+    for hive in &hives {
+        stores.add_user(&hive.key, &hive.user);
+    }
+
+    let certificates = stores.finish(&mut audit_log);
+    audit_log.push(completed(&certificates, start_time));
+    (certificates, audit_log)
+}
+
+#[cfg(not(windows))]
+pub fn collect_certificates(_window: &TimeWindow) -> (Vec<CertificateEntry>, Vec<AuditEntry>) {
+    let audit_log = vec![
+        AuditEntry::new(COMPONENT, "INFO", "start_collection", "Starting certificate store collection".to_string(), "started"),
+        AuditEntry::new(COMPONENT, "WARN", "registry_access", "Certificate store collection requires the Windows registry".to_string(), "unsupported"),
+        AuditEntry::new(COMPONENT, "INFO", "complete_collection", "Collected 0 certificates (0 suspicious roots)".to_string(), "success"),
+    ];
+    (Vec::new(), audit_log)
+}
+
+/// Certificate stores of the SOFTWARE hive and the NTUSER.DAT hives of a mounted Windows volume
+pub fn collect_certificates_offline(root: &OfflineRoot, window: &TimeWindow) -> (Vec<CertificateEntry>, Vec<AuditEntry>) {
+    let start_time = std::time::Instant::now();
+    let mut audit_log = vec![AuditEntry::new(
+        COMPONENT,
+        "INFO",
+        "start_collection",
+        format!("Starting offline certificate store collection of {}", root.root().display()),
+        "started",
+    )];
+
+    let mut stores = CertificateStores::new(window);
+    match root.open_hive("SOFTWARE") {
+        Ok(software) => stores.add_machine(&software, &mut audit_log),
+        Err(e) => audit_log.push(AuditEntry::new(COMPONENT, "ERROR", "load_hive", format!("Failed to load offline SOFTWARE hive: {}", e), "error")),
+    }
+    add_offline_users(&mut stores, &root.resolve(USERS_DIRECTORY), &mut audit_log);
+
+    let certificates = stores.finish(&mut audit_log);
+    audit_log.push(completed(&certificates, start_time));
+    (certificates, audit_log)
+}
+
+fn add_offline_users(stores: &mut CertificateStores, users_dir: &Path, audit_log: &mut Vec<AuditEntry>) {
+    let profiles = match footprint::read_dir(users_dir) {
+        Ok(profiles) => profiles,
+        Err(e) => {
+            audit_log.push(AuditEntry::new(COMPONENT, "WARN", "directory_check", format!("Users directory not readable: {} ({})", users_dir.display(), e), "not_found"));
+            return;
+        }
+    };
+    for profile in profiles.filter_map(|p| p.ok()) {
+        let hive_path = profile.path().join("NTUSER.DAT");
+        if !hive_path.is_file() {
+            continue;
+        }
+        match Hive::open(&hive_path) {
+            Ok(hive) => stores.add_user(&hive, &profile.file_name().to_string_lossy()),
+            Err(e) => audit_log.push(AuditEntry::new(COMPONENT, "WARN", "load_user_hive", format!("Failed to load {}: {}", hive_path.display(), e), "error")),
+        }
+    }
+}
+
+/// Certificates gathered from the stores, classified once all are read
+struct CertificateStores {
+    certificates: Vec<CertificateEntry>,
+    /// Encoded root program CTL; None when Windows never downloaded one
+    trusted_list: Option<Vec<u8>>,
+    recent_since: chrono::DateTime<chrono::Utc>,
+    recent_until: chrono::DateTime<chrono::Utc>,
+}
+
+impl CertificateStores {
+    fn new(window: &TimeWindow) -> Self {
+        let recent_until = window.until.unwrap_or_else(chrono::Utc::now);
+        CertificateStores {
+            certificates: Vec::new(),
+            trusted_list: None,
+            recent_since: window.since.unwrap_or(recent_until - chrono::Duration::days(RECENT_ROOT_DAYS)),
+            recent_until,
+        }
+    }
+
+    fn add_machine(&mut self, software: &dyn CertificateHive, audit_log: &mut Vec<AuditEntry>) {
+        self.trusted_list = software.binary(AUTHROOT_CTL_KEY, "EncodedCtl");
+        if self.trusted_list.is_none() {
+            audit_log.push(AuditEntry::new(
+                COMPONENT,
+                "INFO",
+                "trusted_list",
+                "No cached Microsoft root program list (AuthRoot\\AutoUpdate\\EncodedCtl); AuthRoot store members count as trusted".to_string(),
+                "not_found",
+            ));
+        }
+        for (location, store, key) in MACHINE_STORES {
+            self.add_store(software, location, store, key, None);
+        }
+    }
+
+    fn add_user(&mut self, hive: &dyn CertificateHive, user: &str) {
+        for (store, key) in USER_STORES {
+            self.add_store(hive, "user", store, key, Some(user));
+        }
+    }
+
+    fn add_store(&mut self, hive: &dyn CertificateHive, location: &str, store: &str, key: &str, user: Option<&str>) {
+        for thumbprint in hive.subkeys(key) {
+            let certificate_key = format!("{}\\{}", key, thumbprint);
+            let Some(blob) = hive.binary(&certificate_key, "Blob") else { continue };
+            let properties = certificate_properties(&blob);
+            let Some(der) = properties.iter().find(|(id, _)| *id == CERT_CERT_PROP_ID).map(|(_, data)| *data) else { continue };
+            let friendly_name = properties
+                .iter()
+                .find(|(id, _)| *id == CERT_FRIENDLY_NAME_PROP_ID)
+                .map(|(_, data)| utf16_string(data))
+                .filter(|name| !name.is_empty());
+            let parsed = parse_certificate(der);
+
+            self.certificates.push(CertificateEntry {
+                store: store.to_string(),
+                location: location.to_string(),
+                user: user.map(str::to_string),
+                thumbprint: hex::encode_upper(Sha1::digest(der)),
+                subject: parsed.as_ref().map(|c| c.subject.clone()).unwrap_or_default(),
+                issuer: parsed.as_ref().map(|c| c.issuer.clone()).unwrap_or_default(),
+                serial_number: parsed.as_ref().map(|c| c.serial_number.clone()).unwrap_or_default(),
+                not_before: parsed.as_ref().map(|c| c.not_before.clone()).unwrap_or_default(),
+                not_after: parsed.as_ref().map(|c| c.not_after.clone()).unwrap_or_default(),
+                self_signed: parsed.as_ref().is_some_and(|c| c.self_signed),
+                microsoft_trusted: false,
+                added: hive.last_written(&certificate_key),
+                friendly_name,
+                suspicious: false,
+                suspicion_reasons: Vec::new(),
+            });
+        }
+    }
+
+    fn finish(mut self, audit_log: &mut Vec<AuditEntry>) -> Vec<CertificateEntry> {
+        let authroot: Vec<String> = self.certificates.iter().filter(|c| c.store == "AuthRoot" && c.location == "machine").map(|c| c.thumbprint.clone()).collect();
+        for certificate in &mut self.certificates {
+            certificate.microsoft_trusted = WINDOWS_DEFAULT_ROOTS.contains(&certificate.thumbprint.as_str())
+                || match &self.trusted_list {
+                    Some(list) => in_trusted_list(list, &certificate.thumbprint),
+                    None => authroot.contains(&certificate.thumbprint),
+                };
+            let is_root = certificate.store == "Root" || certificate.store == "AuthRoot";
+            let added = time_window::parse_timestamp(&certificate.added);
+            if is_root && !certificate.microsoft_trusted && added.is_some_and(|added| added >= self.recent_since && added <= self.recent_until) {
+                certificate.suspicion_reasons.push(format!("root CA outside the Microsoft trusted list added {}", certificate.added));
+                if !certificate.self_signed {
+                    certificate.suspicion_reasons.push("not self-signed, unlike a genuine root".to_string());
+                }
+                if certificate.store == "AuthRoot" {
+                    certificate.suspicion_reasons.push("planted in the AuthRoot store Windows fills from the trusted list".to_string());
+                }
+            }
+            certificate.suspicious = !certificate.suspicion_reasons.is_empty();
+        }
+
+        let suspicious = self.certificates.iter().filter(|c| c.suspicious).count();
+        if suspicious > 0 {
+            audit_log.push(AuditEntry::new(COMPONENT, "WARN", "root_check", format!("{} recently added root CAs are not Microsoft trusted", suspicious), "warning"));
+        }
+        self.certificates
+    }
+}
+
+/// Properties of a serialized certificate: (property ID, reserved, length, data)...
+fn certificate_properties(blob: &[u8]) -> Vec<(u32, &[u8])> {
+    let mut properties = Vec::new();
+    let mut position = 0;
+    while let (Some(id), Some(length)) = (read_u32(blob, position), read_u32(blob, position + 8)) {
+        let start = position + 12;
+        let Some(data) = blob.get(start..start + length as usize) else { break };
+        properties.push((id, data));
+        position = start + length as usize;
+    }
+    properties
+}
+
+/// Whether the thumbprint is a subject of the encoded CTL, where each trusted
+/// root is identified by its SHA-1 hash as an OCTET STRING
+fn in_trusted_list(list: &[u8], thumbprint: &str) -> bool {
+    let Ok(hash) = hex::decode(thumbprint) else { return false };
+    let needle = [&[0x04, 0x14][..], &hash].concat();
+    list.windows(needle.len()).any(|window| window == needle.as_slice())
+}
+
+/// Serial number, names and validity of an X.509 certificate
+fn parse_certificate(der: &[u8]) -> Option<ParsedCertificate> {
+    let (_, certificate, _) = der_element(der).filter(|(tag, _, _)| *tag == DER_SEQUENCE)?;
+    let (_, tbs, _) = der_element(certificate).filter(|(tag, _, _)| *tag == DER_SEQUENCE)?;
+    let mut fields = der_children(tbs).into_iter().peekable();
+    // [0] version, absent in v1 certificates
+    fields.next_if(|(tag, _, _)| *tag == 0xA0);
+    let (_, serial, _) = fields.next().filter(|(tag, _, _)| *tag == DER_INTEGER)?;
+    fields.next()?; // signature algorithm
+    let (_, issuer, issuer_raw) = fields.next()?;
+    let (_, validity, _) = fields.next()?;
+    let (_, subject, subject_raw) = fields.next()?;
+    let times = der_children(validity);
+
+    Some(ParsedCertificate {
+        serial_number: hex::encode_upper(serial),
+        issuer: distinguished_name(issuer),
+        subject: distinguished_name(subject),
+        not_before: times.first().and_then(|(tag, time, _)| der_time(*tag, time)).unwrap_or_default(),
+        not_after: times.get(1).and_then(|(tag, time, _)| der_time(*tag, time)).unwrap_or_default(),
+        self_signed: issuer_raw == subject_raw,
+    })
+}
+
+/// `CN=..., OU=..., O=..., C=...`, most specific first as Windows shows it
+fn distinguished_name(name: &[u8]) -> String {
+    let mut parts = Vec::new();
+    for (_, set, _) in der_children(name) {
+        for (_, attribute, _) in der_children(set) {
+            let children = der_children(attribute);
+            let (Some((_, oid, _)), Some((tag, value, _))) = (children.first(), children.get(1)) else { continue };
+            let label = NAME_ATTRIBUTES.iter().find(|(known, _)| known == oid).map(|(_, label)| label.to_string()).unwrap_or_else(|| format!("OID.{}", hex::encode(oid)));
+            let text = match *tag {
+                DER_BMP_STRING => String::from_utf16_lossy(&value.chunks_exact(2).map(|b| u16::from_be_bytes([b[0], b[1]])).collect::<Vec<_>>()),
+                _ => String::from_utf8_lossy(value).into_owned(),
+            };
+            parts.push(format!("{}={}", label, text));
+        }
+    }
+    parts.reverse();
+    parts.join(", ")
+}
+
+/// UTCTime (YYMMDDHHMMSSZ) or GeneralizedTime (YYYYMMDDHHMMSSZ) as RFC 3339
+fn der_time(tag: u8, value: &[u8]) -> Option<String> {
+    let text = std::str::from_utf8(value).ok()?.trim_end_matches('Z');
+    let text = match tag {
+        // Two-digit years 50-99 are 19xx
+        DER_UTC_TIME => format!("{}{}", if text.get(..2)? >= "50" { "19" } else { "20" }, text),
+        DER_GENERALIZED_TIME => text.to_string(),
+        _ => return None,
+    };
+    chrono::NaiveDateTime::parse_from_str(text.get(..14)?, "%Y%m%d%H%M%S").ok().map(|time| time.and_utc().to_rfc3339())
+}
+
+/// Tag, content and encoded length of the DER element at the start of `data`
+fn der_element(data: &[u8]) -> Option<(u8, &[u8], usize)> {
+    let tag = *data.first()?;
+    let first = *data.get(1)? as usize;
+    let (length, header) = if first < 0x80 {
+        (first, 2)
+    } else {
+        let count = first & 0x7F;
+        if count == 0 || count > 4 {
+            return None;
+        }
+        (data.get(2..2 + count)?.iter().fold(0usize, |length, byte| (length << 8) | *byte as usize), 2 + count)
+    };
+    Some((tag, data.get(header..header + length)?, header + length))
+}
+
+/// Elements of a constructed value: (tag, content, whole encoding)
+fn der_children(content: &[u8]) -> Vec<(u8, &[u8], &[u8])> {
+    let mut children = Vec::new();
+    let mut position = 0;
+    while let Some((tag, value, length)) = content.get(position..).and_then(der_element) {
+        children.push((tag, value, &content[position..position + length]));
+        position += length;
+    }
+    children
+}
+
+fn completed(certificates: &[CertificateEntry], start_time: std::time::Instant) -> AuditEntry {
+    let suspicious = certificates.iter().filter(|c| c.suspicious).count();
+    let mut entry = AuditEntry::new(COMPONENT, "INFO", "complete_collection", format!("Collected {} certificates ({} suspicious roots)", certificates.len(), suspicious), "success");
+    entry.duration_ms = Some(start_time.elapsed().as_millis() as u64);
+    entry
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hive::{HiveBuilder, REG_BINARY};
+
+    fn der(tag: u8, content: &[u8]) -> Vec<u8> {
+        let length = content.len();
+        let mut encoded = vec![tag];
+        if length < 0x80 {
+            encoded.push(length as u8);
+        } else {
+            encoded.extend([0x82, (length >> 8) as u8, length as u8]);
+        }
+        encoded.extend_from_slice(content);
+        encoded
+    }
+
+    fn name(common_name: &str, organization: &str) -> Vec<u8> {
+        let attribute = |oid: &[u8], value: &str| der(0x31, &der(DER_SEQUENCE, &[der(0x06, oid), der(0x0C, value.as_bytes())].concat()));
+        der(DER_SEQUENCE, &[attribute(&[0x55, 0x04, 0x0A], organization), attribute(&[0x55, 0x04, 0x03], common_name)].concat())
+    }
+
+    fn certificate(subject: &[u8], issuer: &[u8], serial: &[u8]) -> Vec<u8> {
+        let validity = der(DER_SEQUENCE, &[der(DER_UTC_TIME, b"240501000000Z"), der(DER_GENERALIZED_TIME, b"20440501000000Z")].concat());
+        let algorithm = der(DER_SEQUENCE, &der(0x06, &[0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x01, 0x0B]));
+        let tbs = der(DER_SEQUENCE, &[der(0xA0, &der(DER_INTEGER, &[2])), der(DER_INTEGER, serial), algorithm.clone(), issuer.to_vec(), validity, subject.to_vec()].concat());
+        der(DER_SEQUENCE, &[tbs, algorithm, der(0x03, &[0, 1, 2, 3])].concat())
+    }
+
+    fn blob(der: &[u8]) -> Vec<u8> {
+        [CERT_CERT_PROP_ID.to_le_bytes(), 1u32.to_le_bytes(), (der.len() as u32).to_le_bytes()].concat().into_iter().chain(der.iter().copied()).collect()
+    }
+
+    #[test]
+    fn test_recent_untrusted_root_is_flagged() {
+        let rogue = certificate(&name("Proxy Inspection Root", "Acme"), &name("Proxy Inspection Root", "Acme"), &[0x01, 0x7F]);
+        let trusted = certificate(&name("Trusted Root", "Vendor"), &name("Trusted Root", "Vendor"), &[0x42]);
+        let intermediate = certificate(&name("Issuing CA", "Vendor"), &name("Trusted Root", "Vendor"), &[0x43]);
+        let thumbprint = |der: &[u8]| hex::encode_upper(Sha1::digest(der));
+        // CTL fragment listing the trusted root as a SHA-1 OCTET STRING
+        let ctl = [vec![0x30, 0x16, 0x04, 0x14], Sha1::digest(&trusted).to_vec()].concat();
+
+        // 2024-05-10T00:00:00Z
+        const ADDED: u64 = 133_597_728_000_000_000;
+        let root_key = |der: &[u8]| format!("Microsoft\\SystemCertificates\\ROOT\\Certificates\\{}", thumbprint(der));
+        let software = HiveBuilder::default()
+            .key(&root_key(&rogue), ADDED)
+            .key(&root_key(&trusted), ADDED)
+            .value(&format!("Microsoft\\SystemCertificates\\ROOT\\Certificates\\{}", thumbprint(&rogue)), "Blob", REG_BINARY, &blob(&rogue))
+            .value(&format!("Microsoft\\SystemCertificates\\ROOT\\Certificates\\{}", thumbprint(&trusted)), "Blob", REG_BINARY, &blob(&trusted))
+            .value(&format!("Microsoft\\SystemCertificates\\CA\\Certificates\\{}", thumbprint(&intermediate)), "Blob", REG_BINARY, &blob(&intermediate))
+            .value(AUTHROOT_CTL_KEY, "EncodedCtl", REG_BINARY, &ctl)
+            .build();
+        let software = Hive::parse(software).unwrap();
+
+        let mut audit_log = Vec::new();
+        let mut stores = CertificateStores::new(&TimeWindow::new(Some("2024-05-01"), Some("2024-06-01")).unwrap());
+        stores.add_machine(&software, &mut audit_log);
+        let certificates = stores.finish(&mut audit_log);
+
+        assert_eq!(certificates.len(), 3);
+        let rogue_entry = certificates.iter().find(|c| c.subject == "CN=Proxy Inspection Root, O=Acme").unwrap();
+        assert!(rogue_entry.self_signed && rogue_entry.suspicious && !rogue_entry.microsoft_trusted);
+        assert_eq!((rogue_entry.serial_number.as_str(), rogue_entry.not_before.as_str(), rogue_entry.not_after.as_str()), ("017F", "2024-05-01T00:00:00+00:00", "2044-05-01T00:00:00+00:00"));
+        let trusted_entry = certificates.iter().find(|c| c.subject == "CN=Trusted Root, O=Vendor").unwrap();
+        assert!(trusted_entry.microsoft_trusted && !trusted_entry.suspicious);
+        let ca = certificates.iter().find(|c| c.store == "CA").unwrap();
+        assert!(!ca.self_signed && !ca.suspicious);
+        assert_eq!(ca.issuer, "CN=Trusted Root, O=Vendor");
+    }
+}
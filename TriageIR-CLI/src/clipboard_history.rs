@@ -0,0 +1,298 @@
+//! Windows 10+ clipboard history
+//! The clipboard history (Win+V) itself is held in memory by the clipboard user
+//! service, but two copies reach the disk per user: items synced across devices
+//! (cloud clipboard) are written to the Connected Devices Platform activity cache
+//! as ActivityType 10 with base64 payloads, and pinned items are kept under
+//! Clipboard\Pinned, one folder per item with one file per clipboard format named
+//! by the base64 of the format name. Both often hold attacker-pasted commands and
+//! copied credentials.
+
+use crate::footprint;
+use crate::forensic_types::{AuditEntry, ClipboardHistoryEntry};
+use crate::offline::OfflineRoot;
+use crate::vss;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use rusqlite::{Connection, OpenFlags};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::path::Path;
+
+const COMPONENT: &str = "clipboard_history";
+
+const USERS_DIRECTORY: &str = "C:\\Users";
+const ACTIVITIES_CACHE_SUBPATH: [&str; 3] = ["AppData", "Local", "ConnectedDevicesPlatform"];
+const ACTIVITIES_CACHE_FILE: &str = "ActivitiesCache.db";
+const PINNED_SUBPATH: [&str; 6] = ["AppData", "Local", "Microsoft", "Windows", "Clipboard", "Pinned"];
+
+/// Activity type of cloud clipboard items
+const CLIPBOARD_ACTIVITY_TYPE: i64 = 10;
+/// Synced items are in Activity, items awaiting upload in ActivityOperation
+const ACTIVITY_TABLES: [&str; 2] = ["Activity", "ActivityOperation"];
+
+/// Text formats, preferred first when an item has several
+const TEXT_FORMATS: [&str; 5] = ["UnicodeText", "Text", "OEMText", "HTML Format", "Rich Text Format"];
+
+/// Collect clipboard history for every user profile on the live system
+pub fn collect_clipboard_history() -> (Vec<ClipboardHistoryEntry>, Vec<AuditEntry>) {
+    collect_clipboard_history_under(Path::new(USERS_DIRECTORY))
+}
+
+/// Collect clipboard history for every user profile of a mounted Windows volume
+pub fn collect_clipboard_history_offline(root: &OfflineRoot) -> (Vec<ClipboardHistoryEntry>, Vec<AuditEntry>) {
+    collect_clipboard_history_under(&root.resolve(USERS_DIRECTORY))
+}
+
+fn collect_clipboard_history_under(users_dir: &Path) -> (Vec<ClipboardHistoryEntry>, Vec<AuditEntry>) {
+    let mut entries = Vec::new();
+    let start_time = std::time::Instant::now();
+
+    let mut audit_log = vec![AuditEntry::new(COMPONENT, "INFO", "start_collection", "Starting clipboard history analysis".to_string(), "started")];
+
+    let user_dirs = match footprint::read_dir(users_dir) {
+        Ok(user_dirs) => user_dirs,
+        Err(e) => {
+            audit_log.push(AuditEntry::new(
+                COMPONENT,
+                "WARN",
+                "directory_check",
+                format!("Users directory not readable: {} ({})", users_dir.display(), e),
+                "not_found",
+            ));
+            return (entries, audit_log);
+        }
+    };
+
+    for user_dir in user_dirs.filter_map(|e| e.ok()) {
+        let username = user_dir.file_name().to_string_lossy().to_string();
+
+        // One activity cache per connected account (L.<user>, AAD or MSA id)
+        let platform_dir = ACTIVITIES_CACHE_SUBPATH.iter().fold(user_dir.path(), |path, component| path.join(component));
+        for account_dir in footprint::read_dir(&platform_dir).into_iter().flatten().filter_map(|e| e.ok()) {
+            let db_path = account_dir.path().join(ACTIVITIES_CACHE_FILE);
+            if !db_path.is_file() {
+                continue;
+            }
+            match parse_activities_cache(&db_path, &username) {
+                Ok(records) => {
+                    audit_log.push(AuditEntry::new(
+                        COMPONENT,
+                        "DEBUG",
+                        "parse_activities_cache",
+                        format!("Parsed {} clipboard items from {}", records.len(), db_path.display()),
+                        "success",
+                    ));
+                    entries.extend(records);
+                }
+                Err(e) => {
+                    audit_log.push(AuditEntry::new(
+                        COMPONENT,
+                        "ERROR",
+                        "parse_activities_cache",
+                        format!("Failed to parse {}: {}", db_path.display(), e),
+                        "error",
+                    ));
+                }
+            }
+        }
+
+        let pinned_dir = PINNED_SUBPATH.iter().fold(user_dir.path(), |path, component| path.join(component));
+        if pinned_dir.is_dir() {
+            let pinned = collect_pinned_items(&pinned_dir, &username);
+            audit_log.push(AuditEntry::new(
+                COMPONENT,
+                "DEBUG",
+                "parse_pinned_items",
+                format!("Parsed {} pinned clipboard items from {}", pinned.len(), pinned_dir.display()),
+                "success",
+            ));
+            entries.extend(pinned);
+        }
+    }
+
+    entries.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+
+    let duration = start_time.elapsed();
+    let mut complete = AuditEntry::new(
+        COMPONENT,
+        "INFO",
+        "complete_collection",
+        format!("Collected {} clipboard history items", entries.len()),
+        "success",
+    );
+    complete.duration_ms = Some(duration.as_millis() as u64);
+    audit_log.push(complete);
+
+    (entries, audit_log)
+}
+
+/// Parse the clipboard activities of an ActivitiesCache.db. The database (and its
+/// WAL) is copied to a temporary directory first so the original is never opened by SQLite.
+pub fn parse_activities_cache(db_path: &Path, user: &str) -> Result<Vec<ClipboardHistoryEntry>, Box<dyn std::error::Error>> {
+    let staging = tempfile::TempDir::new()?;
+    let staged_db = vss::stage_sqlite_database(db_path, staging.path())?;
+    let connection = Connection::open_with_flags(&staged_db, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+
+    let mut entries = Vec::new();
+    let mut seen = HashSet::new();
+    for table in ACTIVITY_TABLES {
+        let exists: bool = connection.query_row("SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = ?1", [table], |row| row.get(0))?;
+        if !exists {
+            continue;
+        }
+        let query = format!(
+            "SELECT StartTime, LastModifiedTime, ClipboardPayload FROM {} WHERE ActivityType = ?1 AND ClipboardPayload IS NOT NULL ORDER BY StartTime DESC",
+            table
+        );
+        let mut statement = connection.prepare(&query)?;
+        let rows = statement.query_map([CLIPBOARD_ACTIVITY_TYPE], |row| {
+            Ok((row.get::<_, Option<i64>>(0)?, row.get::<_, Option<i64>>(1)?, row.get::<_, Option<String>>(2)?))
+        })?;
+
+        for row in rows {
+            let (start_time, modified_time, payload) = row?;
+            let timestamp = unix_to_string(start_time.or(modified_time).unwrap_or(0));
+            // [{"content": "<base64>", "formatName": "Text"}, ...]
+            let formats: Vec<Value> = serde_json::from_str(payload.as_deref().unwrap_or_default()).unwrap_or_default();
+            let decoded: Vec<(String, Vec<u8>)> = formats
+                .iter()
+                .filter_map(|format| {
+                    let name = format["formatName"].as_str()?.to_string();
+                    let data = STANDARD.decode(format["content"].as_str()?).ok()?;
+                    Some((name, data))
+                })
+                .collect();
+            let Some(entry) = clipboard_entry(user, "activities_cache", &timestamp, &decoded, db_path) else {
+                continue;
+            };
+            // Items awaiting upload are also in Activity once synced
+            if seen.insert((entry.timestamp.clone(), entry.format.clone(), entry.text.clone())) {
+                entries.push(entry);
+            }
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Pinned\<collection>\<item>\ with metadata.json and one file per format
+fn collect_pinned_items(pinned_dir: &Path, user: &str) -> Vec<ClipboardHistoryEntry> {
+    let mut entries = Vec::new();
+    let collections = footprint::read_dir(pinned_dir).into_iter().flatten().filter_map(|e| e.ok()).filter(|e| e.path().is_dir());
+    for collection in collections {
+        let items = footprint::read_dir(collection.path()).into_iter().flatten().filter_map(|e| e.ok()).filter(|e| e.path().is_dir());
+        for item in items {
+            let mut formats = Vec::new();
+            let mut modified = None;
+            for file in footprint::read_dir(item.path()).into_iter().flatten().filter_map(|e| e.ok()) {
+                let file_name = file.file_name().to_string_lossy().to_string();
+                if file_name.eq_ignore_ascii_case("metadata.json") {
+                    continue;
+                }
+                let Ok(data) = footprint::read(file.path()) else {
+                    continue;
+                };
+                let name = STANDARD.decode(&file_name).ok().and_then(|bytes| String::from_utf8(bytes).ok()).unwrap_or(file_name);
+                modified = modified.max(file.metadata().and_then(|m| m.modified()).ok());
+                formats.push((name, data));
+            }
+            let timestamp = modified.map(|time| chrono::DateTime::<chrono::Utc>::from(time).to_rfc3339()).unwrap_or_default();
+            if let Some(entry) = clipboard_entry(user, "pinned", &timestamp, &formats, &item.path()) {
+                entries.push(entry);
+            }
+        }
+    }
+    entries
+}
+
+/// One entry per clipboard item, with the text of its preferred text format
+fn clipboard_entry(user: &str, source: &str, timestamp: &str, formats: &[(String, Vec<u8>)], source_path: &Path) -> Option<ClipboardHistoryEntry> {
+    let rank = |name: &str| TEXT_FORMATS.iter().position(|format| format.eq_ignore_ascii_case(name)).unwrap_or(TEXT_FORMATS.len());
+    let (format, data) = formats.iter().min_by_key(|(name, _)| rank(name))?;
+    let text = match rank(format) {
+        0 => decode_utf16(data),
+        position if position < TEXT_FORMATS.len() => String::from_utf8_lossy(data).to_string(),
+        _ => String::new(),
+    };
+    Some(ClipboardHistoryEntry {
+        user: user.to_string(),
+        source: source.to_string(),
+        timestamp: timestamp.to_string(),
+        format: format.clone(),
+        text: text.trim_end_matches('\0').to_string(),
+        size: data.len() as u64,
+        source_path: source_path.display().to_string(),
+    })
+}
+
+fn decode_utf16(data: &[u8]) -> String {
+    let units: Vec<u16> = data.chunks_exact(2).map(|pair| u16::from_le_bytes([pair[0], pair[1]])).collect();
+    String::from_utf16_lossy(&units)
+}
+
+fn unix_to_string(seconds: i64) -> String {
+    if seconds <= 0 {
+        return String::new();
+    }
+    chrono::DateTime::from_timestamp(seconds, 0).map(|datetime| datetime.to_rfc3339()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn create_activities_cache(path: &Path) {
+        let connection = Connection::open(path).unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE Activity (Id BLOB, ActivityType INTEGER, StartTime INTEGER, LastModifiedTime INTEGER, ClipboardPayload TEXT);
+                 CREATE TABLE ActivityOperation (Id BLOB, ActivityType INTEGER, StartTime INTEGER, LastModifiedTime INTEGER, ClipboardPayload TEXT);
+                 INSERT INTO Activity VALUES (x'01', 10, 1609459200, 1609459260, '[{\"content\":\"cG93ZXJzaGVsbCAtZW5jIFNRQkZBRmdB\",\"formatName\":\"Text\"}]');
+                 INSERT INTO Activity VALUES (x'02', 5, 1609459300, 1609459300, NULL);
+                 INSERT INTO ActivityOperation VALUES (x'01', 10, 1609459200, 1609459260, '[{\"content\":\"cG93ZXJzaGVsbCAtZW5jIFNRQkZBRmdB\",\"formatName\":\"Text\"}]');",
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn test_parse_activities_cache() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("ActivitiesCache.db");
+        create_activities_cache(&db_path);
+
+        let entries = parse_activities_cache(&db_path, "alice").unwrap();
+        assert_eq!(entries.len(), 1); // Pending upload duplicate and non-clipboard activity skipped
+        assert_eq!(entries[0].text, "powershell -enc SQBFAFgA");
+        assert_eq!(entries[0].format, "Text");
+        assert_eq!(entries[0].timestamp, "2021-01-01T00:00:00+00:00");
+        assert_eq!(entries[0].source, "activities_cache");
+    }
+
+    #[test]
+    fn test_collect_clipboard_history_under_users_directory() {
+        let temp_dir = TempDir::new().unwrap();
+        let account_dir = ["bob", "AppData", "Local", "ConnectedDevicesPlatform", "L.bob"]
+            .iter()
+            .fold(temp_dir.path().to_path_buf(), |path, component| path.join(component));
+        fs::create_dir_all(&account_dir).unwrap();
+        create_activities_cache(&account_dir.join("ActivitiesCache.db"));
+
+        // Pinned item with UnicodeText and Text formats; file names are base64 of the format
+        let item_dir = PINNED_SUBPATH.iter().fold(temp_dir.path().join("bob"), |path, component| path.join(component)).join("{C1}").join("{I1}");
+        fs::create_dir_all(&item_dir).unwrap();
+        fs::write(item_dir.join("metadata.json"), "{}").unwrap();
+        let unicode: Vec<u8> = "net user backdoor P@ss /add\0".encode_utf16().flat_map(u16::to_le_bytes).collect();
+        fs::write(item_dir.join(STANDARD.encode("UnicodeText")), unicode).unwrap();
+        fs::write(item_dir.join(STANDARD.encode("Text")), "net user backdoor P@ss /add").unwrap();
+
+        let (entries, audit_log) = collect_clipboard_history_under(temp_dir.path());
+        assert_eq!(entries.len(), 2);
+        let pinned = entries.iter().find(|entry| entry.source == "pinned").unwrap();
+        assert_eq!((pinned.format.as_str(), pinned.text.as_str()), ("UnicodeText", "net user backdoor P@ss /add"));
+        assert_eq!(pinned.user, "bob");
+        assert!(!pinned.timestamp.is_empty());
+        assert!(audit_log.iter().any(|log| log.action == "complete_collection"));
+    }
+}
@@ -0,0 +1,317 @@
+//! Collector health self-metrics
+//! Records items collected, items skipped, errors, duration and peak memory of
+//! every collector run for the `collector_stats` section of the scan output.
+//! Peak memory is sampled by a background thread because the collectors run
+//! on the scan thread and cannot report their own allocations. Collectors run
+//! through `bounded` are cut off at their timeout or on Ctrl+C and recorded as
+//! timed_out or interrupted, or as failed when they panic. With a memory limit (`--max-memory-mb`) they are
+//! not started once resident memory approaches it and are recorded as truncated.
+//! Collectors disabled by the scan profile (`--profile`) are recorded as skipped.
+
+use crate::cancellation::{self, CollectorTimeouts, Stop};
+use crate::footprint;
+use crate::forensic_types::{AuditEntry, CollectorStats};
+use crate::types::LogEntry;
+use std::cell::Cell;
+use std::collections::BTreeSet;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use sysinfo::{Pid, ProcessRefreshKind, System};
+
+/// Interval between resident memory samples
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(25);
+
+/// Share of the memory limit at which further collectors are skipped, leaving
+/// room for the analysis and serialization that follow the collection
+const MEMORY_LIMIT_THRESHOLD: f64 = 0.9;
+
+/// Samples the resident memory of the collector process and keeps the peak
+/// since the last `reset`
+struct MemorySampler {
+    pid: Option<Pid>,
+    peak_bytes: Arc<AtomicU64>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl MemorySampler {
+    fn start() -> Self {
+        let pid = sysinfo::get_current_pid().ok();
+        let peak_bytes = Arc::new(AtomicU64::new(resident_memory(pid)));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let thread = {
+            let (peak_bytes, stop) = (peak_bytes.clone(), stop.clone());
+            std::thread::Builder::new()
+                .name("collector-stats".to_string())
+                .spawn(move || {
+                    while !stop.load(Ordering::Relaxed) {
+                        peak_bytes.fetch_max(resident_memory(pid), Ordering::Relaxed);
+                        std::thread::sleep(SAMPLE_INTERVAL);
+                    }
+                })
+                .ok()
+        };
+
+        MemorySampler { pid, peak_bytes, stop, thread }
+    }
+
+    /// Restart peak tracking from the current resident memory
+    fn reset(&self) {
+        self.peak_bytes.store(resident_memory(self.pid), Ordering::Relaxed);
+    }
+
+    /// Peak since the last reset, including the memory in use right now
+    fn peak(&self) -> u64 {
+        let current = resident_memory(self.pid);
+        self.peak_bytes.fetch_max(current, Ordering::Relaxed).max(current)
+    }
+
+    fn current(&self) -> u64 {
+        resident_memory(self.pid)
+    }
+}
+
+impl Drop for MemorySampler {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn resident_memory(pid: Option<Pid>) -> u64 {
+    let Some(pid) = pid else {
+        return 0;
+    };
+    let mut system = System::new();
+    system.refresh_process_specifics(pid, ProcessRefreshKind::new().with_memory());
+    system.process(pid).map(|process| process.memory()).unwrap_or(0)
+}
+
+/// A collector run started with `CollectorStatsRecorder::start`
+pub struct CollectorRun {
+    collector: &'static str,
+    started: Instant,
+    stop: Cell<Option<Stop>>,
+}
+
+/// Accumulates the metrics of every collector of a scan
+pub struct CollectorStatsRecorder {
+    sampler: MemorySampler,
+    stats: Vec<CollectorStats>,
+    timeouts: CollectorTimeouts,
+    memory_limit_mb: Option<u64>,
+    disabled: BTreeSet<String>,
+}
+
+impl CollectorStatsRecorder {
+    pub fn new() -> Self {
+        CollectorStatsRecorder { sampler: MemorySampler::start(), stats: Vec::new(), timeouts: CollectorTimeouts::default(), memory_limit_mb: None, disabled: BTreeSet::new() }
+    }
+
+    pub fn with_timeouts(mut self, timeouts: CollectorTimeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    pub fn with_memory_limit(mut self, limit_mb: Option<u64>) -> Self {
+        self.memory_limit_mb = limit_mb;
+        self
+    }
+
+    pub fn with_disabled(mut self, collectors: BTreeSet<String>) -> Self {
+        self.disabled = collectors;
+        self
+    }
+
+    /// True when the scan profile disables the collector of `run`, which is then
+    /// recorded as skipped; collectors run through `bounded` are checked there
+    pub fn disabled(&self, run: &CollectorRun) -> bool {
+        let disabled = self.disabled.contains(run.collector);
+        if disabled {
+            run.stop.set(Some(Stop::Disabled));
+        }
+        disabled
+    }
+
+    /// True once resident memory is within MEMORY_LIMIT_THRESHOLD of the limit
+    pub fn memory_limit_approached(&self) -> bool {
+        self.memory_limit_mb.is_some_and(|limit_mb| {
+            self.sampler.current() as f64 >= limit_mb as f64 * 1024.0 * 1024.0 * MEMORY_LIMIT_THRESHOLD
+        })
+    }
+
+    pub fn start(&self, collector: &'static str) -> CollectorRun {
+        self.sampler.reset();
+        footprint::enter(collector);
+        CollectorRun { collector, started: Instant::now(), stop: Cell::new(None) }
+    }
+
+    /// Run the collector of `run` on its own thread with `input` moved to it.
+    /// A collector that times out, is interrupted or is not started because of
+    /// the memory limit yields an empty result.
+    pub fn bounded<I, T>(&self, run: &CollectorRun, input: I, collector: impl FnOnce(I) -> T + Send + 'static) -> T
+    where
+        I: Send + 'static,
+        T: Default + Send + 'static,
+    {
+        if self.disabled(run) {
+            return T::default();
+        }
+        if let Some(limit_mb) = self.memory_limit_mb.filter(|_| self.memory_limit_approached()) {
+            run.stop.set(Some(Stop::MemoryLimit(limit_mb)));
+            return T::default();
+        }
+        cancellation::run_bounded(self.timeouts.get(run.collector), move || collector(input)).unwrap_or_else(|stop| {
+            run.stop.set(Some(stop));
+            T::default()
+        })
+    }
+
+    pub fn finish(&mut self, run: CollectorRun, items_collected: usize, items_skipped: usize, errors: usize) {
+        let duration_ms = run.started.elapsed().as_millis() as u64;
+        let stop = run.stop.get();
+        self.stats.push(CollectorStats {
+            collector: run.collector.to_string(),
+            status: stop.map_or("completed", |stop| stop.status()).to_string(),
+            skip_reason: stop.map(|stop| stop.describe()),
+            items_collected,
+            items_skipped,
+            errors,
+            duration_ms,
+            peak_memory_mb: bytes_to_mb(self.sampler.peak()),
+        });
+    }
+
+    /// Record a collector that did not run (e.g. a live-only collector in offline mode)
+    pub fn skip(&mut self, collector: &'static str, reason: &str) {
+        self.stats.push(CollectorStats {
+            collector: collector.to_string(),
+            status: "skipped".to_string(),
+            skip_reason: Some(reason.to_string()),
+            items_collected: 0,
+            items_skipped: 0,
+            errors: 0,
+            duration_ms: 0,
+            peak_memory_mb: 0.0,
+        });
+    }
+
+    /// Collectors cut off by their timeout, Ctrl+C or the memory limit, or that panicked
+    pub fn stopped(&self) -> impl Iterator<Item = &CollectorStats> {
+        self.stats.iter().filter(|stats| matches!(stats.status.as_str(), "timed_out" | "interrupted" | "truncated" | "failed"))
+    }
+
+    pub fn into_stats(self) -> Vec<CollectorStats> {
+        self.stats
+    }
+}
+
+impl Default for CollectorStatsRecorder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Number of ERROR entries in a collector's audit log
+pub fn audit_errors(entries: &[AuditEntry]) -> usize {
+    entries.iter().filter(|entry| entry.level.eq_ignore_ascii_case("ERROR")).count()
+}
+
+/// Number of ERROR entries in a collector's log
+pub fn log_errors(entries: &[LogEntry]) -> usize {
+    entries.iter().filter(|entry| entry.level.eq_ignore_ascii_case("ERROR")).count()
+}
+
+/// Megabytes rounded to one decimal
+fn bytes_to_mb(bytes: u64) -> f64 {
+    (bytes as f64 / (1024.0 * 1024.0) * 10.0).round() / 10.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn audit(level: &str) -> AuditEntry {
+        AuditEntry {
+            timestamp: String::new(),
+            level: level.to_string(),
+            component: "test".to_string(),
+            action: "parse".to_string(),
+            details: String::new(),
+            duration_ms: None,
+            result: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_recorder() {
+        let mut recorder = CollectorStatsRecorder::new();
+        let run = recorder.start("prefetch");
+        let logs = vec![audit("INFO"), audit("ERROR"), audit("WARN"), audit("error")];
+        recorder.finish(run, 12, 3, audit_errors(&logs));
+        recorder.skip("processes", "live-only collector (offline mode)");
+
+        let stats = recorder.into_stats();
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[0].collector, "prefetch");
+        assert_eq!(stats[0].status, "completed");
+        assert_eq!((stats[0].items_collected, stats[0].items_skipped, stats[0].errors), (12, 3, 2));
+        assert!(stats[0].peak_memory_mb > 0.0);
+        assert_eq!(stats[1].status, "skipped");
+        assert_eq!(stats[1].skip_reason.as_deref(), Some("live-only collector (offline mode)"));
+    }
+
+    #[test]
+    fn test_bounded_collector_times_out() {
+        let timeouts = CollectorTimeouts::parse(&["event_logs=1".to_string()]).unwrap();
+        let mut recorder = CollectorStatsRecorder::new().with_timeouts(timeouts);
+        let run = recorder.start("event_logs");
+        let entries: Vec<u32> = recorder.bounded(&run, 7, |count| {
+            std::thread::sleep(Duration::from_secs(10));
+            vec![count]
+        });
+        assert!(entries.is_empty());
+        recorder.finish(run, entries.len(), 0, 0);
+        let run = recorder.start("prefetch");
+        let entries: Vec<u32> = recorder.bounded(&run, 7, |count| vec![count]);
+        recorder.finish(run, entries.len(), 0, 0);
+        let run = recorder.start("amcache");
+        let entries: Vec<u32> = recorder.bounded(&run, 7, |_| panic!("malformed hive"));
+        recorder.finish(run, entries.len(), 0, 0);
+
+        assert_eq!(recorder.stopped().map(|stats| stats.collector.as_str()).collect::<Vec<_>>(), ["event_logs", "amcache"]);
+        let stats = recorder.into_stats();
+        assert_eq!(stats[0].status, "timed_out");
+        assert_eq!(stats[0].skip_reason.as_deref(), Some("exceeded the collector timeout of 1s"));
+        assert_eq!((stats[1].status.as_str(), stats[1].items_collected), ("completed", 1));
+        assert_eq!(stats[2].status, "failed");
+    }
+
+    #[test]
+    fn test_memory_limit_truncates_collection() {
+        let mut recorder = CollectorStatsRecorder::new().with_memory_limit(Some(1));
+        assert!(recorder.memory_limit_approached());
+        let run = recorder.start("event_logs");
+        let entries: Vec<u32> = recorder.bounded(&run, 7, |count| vec![count]);
+        assert!(entries.is_empty());
+        recorder.finish(run, entries.len(), 0, 0);
+
+        let stats = recorder.into_stats();
+        assert_eq!(stats[0].status, "truncated");
+        assert_eq!(stats[0].skip_reason.as_deref(), Some("memory limit of 1 MB approached; collector skipped"));
+        assert!(!CollectorStatsRecorder::new().memory_limit_approached());
+    }
+
+    #[test]
+    fn test_log_errors_and_rounding() {
+        let logs = vec![LogEntry::info("ok"), LogEntry::error("failed")];
+        assert_eq!(log_errors(&logs), 1);
+        assert_eq!(bytes_to_mb(15 * 1024 * 1024 + 512 * 1024), 15.5);
+        assert_eq!(bytes_to_mb(0), 0.0);
+    }
+}
@@ -0,0 +1,284 @@
+//! Scan result format conversion
+//! `convert` turns a scan result into one record per artifact for log pipelines
+//! and spreadsheets, and back. Every record names its section (`scan_metadata`,
+//! `artifacts.running_processes`, `collection_log`, ...) and its kind: `record`
+//! for one element of an array section, `value` for a whole section. JSON Lines
+//! carries `{"section", "kind", "data"}` objects; CSV has the columns `_section`
+//! and `_kind` plus one column per field (`_value` for data that is not an
+//! object), nested objects flattened with dots and arrays written as JSON. Text
+//! that would read back as another JSON type is written JSON-quoted, so a CSV
+//! file converts back to the original result.
+
+use crate::ioc_export::csv_field;
+use serde_json::{Map, Value};
+use std::collections::BTreeSet;
+use std::path::Path;
+
+/// Reserved CSV columns
+const SECTION_COLUMN: &str = "_section";
+const KIND_COLUMN: &str = "_kind";
+
+/// Column of a CSV row whose data is not an object
+const VALUE_COLUMN: &str = "_value";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Jsonl,
+    Csv,
+}
+
+impl Format {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.to_ascii_lowercase().as_str() {
+            "json" => Ok(Format::Json),
+            "jsonl" | "ndjson" => Ok(Format::Jsonl),
+            "csv" => Ok(Format::Csv),
+            _ => Err(format!("unknown format {} (json, jsonl or csv)", name)),
+        }
+    }
+
+    /// Format implied by a file extension
+    pub fn from_path(path: &Path) -> Result<Self, String> {
+        let extension = path.extension().map(|extension| extension.to_string_lossy().to_string()).unwrap_or_default();
+        Self::parse(&extension).map_err(|_| format!("cannot tell the format of {} from its extension; use --from/--to", path.display()))
+    }
+}
+
+/// Convert the text of a scan result between formats
+pub fn convert(text: &str, from: Format, to: Format) -> Result<String, String> {
+    let scan = match from {
+        Format::Json => serde_json::from_str(text).map_err(|e| format!("Invalid JSON: {}", e))?,
+        Format::Jsonl => from_jsonl(text)?,
+        Format::Csv => from_csv(text)?,
+    };
+    if scan.get("artifacts").is_none() {
+        return Err("not a TriageIR scan result (no artifacts section)".to_string());
+    }
+    Ok(match to {
+        Format::Json => serde_json::to_string_pretty(&scan).map_err(|e| e.to_string())?,
+        Format::Jsonl => to_jsonl(&scan),
+        Format::Csv => to_csv(&scan),
+    })
+}
+
+/// (section, kind, data) of every record of a scan result
+pub fn records(scan: &Value) -> Vec<(String, &'static str, &Value)> {
+    let mut records = Vec::new();
+    let Some(sections) = scan.as_object() else { return records };
+    for (name, section) in sections {
+        match section.as_object().filter(|_| name == "artifacts") {
+            Some(artifacts) => {
+                for (artifact, value) in artifacts {
+                    push_section(&mut records, format!("artifacts.{}", artifact), value);
+                }
+            }
+            None => push_section(&mut records, name.clone(), section),
+        }
+    }
+    records
+}
+
+fn push_section<'a>(records: &mut Vec<(String, &'static str, &'a Value)>, section: String, value: &'a Value) {
+    match value.as_array().filter(|items| !items.is_empty()) {
+        Some(items) => records.extend(items.iter().map(|item| (section.clone(), "record", item))),
+        None => records.push((section, "value", value)),
+    }
+}
+
+/// Rebuild a scan result from its records
+fn assemble(records: impl IntoIterator<Item = (String, String, Value)>) -> Result<Value, String> {
+    let mut scan = Map::new();
+    for (section, kind, data) in records {
+        let (parent, name) = match section.split_once('.') {
+            Some((parent, name)) => {
+                let parent = scan.entry(parent.to_string()).or_insert_with(|| Value::Object(Map::new()));
+                let parent = parent.as_object_mut().ok_or_else(|| format!("section {} is not an object", section))?;
+                (parent, name.to_string())
+            }
+            None => (&mut scan, section.clone()),
+        };
+        match kind.as_str() {
+            "record" => match parent.entry(name).or_insert_with(|| Value::Array(Vec::new())) {
+                Value::Array(items) => items.push(data),
+                _ => return Err(format!("section {} mixes records and a value", section)),
+            },
+            "value" => {
+                parent.insert(name, data);
+            }
+            _ => return Err(format!("unknown record kind {} (record or value)", kind)),
+        }
+    }
+    Ok(Value::Object(scan))
+}
+
+pub fn to_jsonl(scan: &Value) -> String {
+    records(scan)
+        .into_iter()
+        .map(|(section, kind, data)| serde_json::json!({ "section": section, "kind": kind, "data": data }).to_string() + "\n")
+        .collect()
+}
+
+pub fn from_jsonl(text: &str) -> Result<Value, String> {
+    let records = text
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(number, line)| {
+            let mut record: Value = serde_json::from_str(line).map_err(|e| format!("line {}: {}", number + 1, e))?;
+            let field = |name: &str| record[name].as_str().map(str::to_string).ok_or_else(|| format!("line {}: no {}", number + 1, name));
+            let (section, kind) = (field("section")?, field("kind")?);
+            Ok((section, kind, record["data"].take()))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    assemble(records)
+}
+
+pub fn to_csv(scan: &Value) -> String {
+    let rows: Vec<(String, &str, Map<String, Value>)> = records(scan)
+        .into_iter()
+        .map(|(section, kind, data)| {
+            let mut cells = Map::new();
+            match data.as_object().filter(|fields| !fields.is_empty()) {
+                Some(fields) => flatten("", fields, &mut cells),
+                None => {
+                    cells.insert(VALUE_COLUMN.to_string(), data.clone());
+                }
+            }
+            (section, kind, cells)
+        })
+        .collect();
+    let columns: BTreeSet<&String> = rows.iter().flat_map(|(_, _, cells)| cells.keys()).collect();
+
+    let mut csv = [SECTION_COLUMN, KIND_COLUMN].into_iter().chain(columns.iter().map(|column| column.as_str())).map(csv_field).collect::<Vec<_>>().join(",") + "\n";
+    for (section, kind, cells) in &rows {
+        let fields = [csv_field(section), csv_field(kind)]
+            .into_iter()
+            .chain(columns.iter().map(|column| cells.get(*column).map(|value| csv_field(&cell(value))).unwrap_or_default()));
+        csv.push_str(&fields.collect::<Vec<_>>().join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+pub fn from_csv(text: &str) -> Result<Value, String> {
+    let mut rows = parse_csv(text).into_iter();
+    let header = rows.next().ok_or("empty CSV file")?;
+    if header.first().map(String::as_str) != Some(SECTION_COLUMN) || header.get(1).map(String::as_str) != Some(KIND_COLUMN) {
+        return Err(format!("the first columns must be {} and {}", SECTION_COLUMN, KIND_COLUMN));
+    }
+
+    let records = rows.map(|row| {
+        let mut data = Value::Object(Map::new());
+        for (column, text) in header.iter().zip(&row).skip(2).filter(|(_, text)| !text.is_empty()) {
+            let value = serde_json::from_str(text).unwrap_or_else(|_| Value::String(text.clone()));
+            if column == VALUE_COLUMN {
+                data = value;
+                continue;
+            }
+            let mut target = &mut data;
+            for key in column.split('.') {
+                if !target.is_object() {
+                    *target = Value::Object(Map::new());
+                }
+                target = target.as_object_mut().unwrap().entry(key.to_string()).or_insert(Value::Null);
+            }
+            *target = value;
+        }
+        (row.first().cloned().unwrap_or_default(), row.get(1).cloned().unwrap_or_default(), data)
+    });
+    assemble(records)
+}
+
+/// Dotted column names for the fields of nested objects
+fn flatten(prefix: &str, fields: &Map<String, Value>, cells: &mut Map<String, Value>) {
+    for (key, value) in fields {
+        let column = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+        match value.as_object().filter(|nested| !nested.is_empty()) {
+            Some(nested) => flatten(&column, nested, cells),
+            None => {
+                cells.insert(column, value.clone());
+            }
+        }
+    }
+}
+
+/// Cell text: strings as they are unless they would read back as another type
+fn cell(value: &Value) -> String {
+    match value {
+        Value::String(text) if !text.is_empty() && serde_json::from_str::<Value>(text).is_err() => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Rows of a CSV document; quoted fields may span lines
+fn parse_csv(text: &str) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    let mut row = vec![String::new()];
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                row.last_mut().unwrap().push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => row.push(String::new()),
+            '\r' if !quoted => {}
+            '\n' if !quoted => rows.push(std::mem::replace(&mut row, vec![String::new()])),
+            _ => row.last_mut().unwrap().push(c),
+        }
+    }
+    if row.len() > 1 || !row[0].is_empty() {
+        rows.push(row);
+    }
+    rows.retain(|row| row.len() > 1 || !row[0].is_empty());
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn scan() -> Value {
+        json!({
+            "scan_metadata": { "scan_id": "1234", "hostname": "WS-07", "total_artifacts": 2 },
+            "artifacts": {
+                "system_info": { "uptime_hours": 5.5, "logged_on_users": [] },
+                "running_processes": [
+                    { "pid": 4242, "name": "rund,ll32.exe", "command_line": "rundll32 \"a b.dll\"\nnext", "hashes": { "sha256": "aa" }, "parent_pid": null },
+                    { "pid": 88, "name": "123", "command_line": "", "hashes": {}, "is_signed": true }
+                ],
+                "network_connections": []
+            },
+            "collection_log": [{ "level": "INFO", "message": "done" }]
+        })
+    }
+
+    #[test]
+    fn test_round_trips() {
+        let text = scan().to_string();
+        for format in [Format::Jsonl, Format::Csv] {
+            let converted = convert(&text, Format::Json, format).unwrap();
+            let back: Value = serde_json::from_str(&convert(&converted, format, Format::Json).unwrap()).unwrap();
+            assert_eq!(back, scan(), "{:?}", format);
+        }
+
+        let jsonl = to_jsonl(&scan());
+        assert_eq!(jsonl.lines().count(), 6);
+        assert!(jsonl.contains(r#"{"data":{"command_line":"","hashes":{},"is_signed":true,"name":"123","pid":88},"kind":"record","section":"artifacts.running_processes"}"#));
+
+        let csv = to_csv(&scan());
+        assert!(csv.starts_with("_section,_kind,_value,command_line,hashes,hashes.sha256,hostname,"));
+        assert!(csv.contains("\nartifacts.running_processes,record,,\"\"\"\"\"\",{},,,true,,,,\"\"\"123\"\"\",,88,,,\n"));
+    }
+
+    #[test]
+    fn test_format_from_path() {
+        assert_eq!(Format::from_path(Path::new("out/scan.NDJSON")).unwrap(), Format::Jsonl);
+        assert!(Format::from_path(Path::new("scan.txt")).is_err());
+        assert!(convert("{}", Format::Json, Format::Csv).is_err());
+    }
+}
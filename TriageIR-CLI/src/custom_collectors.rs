@@ -0,0 +1,359 @@
+//! Custom collectors (`--collectors-config`)
+//! Third-party collectors whose artifacts the scan output has no section for
+//! are stored under `artifacts.custom_artifacts.<namespace>`. A collector is
+//! either compiled in, implementing `Collector` in a module behind a cargo
+//! feature and registered in `CollectorRegistry::builtin`, or an external
+//! executable declared in the collectors config. Executables get the scan
+//! context as JSON in TRIAGEIR_CONTEXT and print the document of a plugin's
+//! `triageir_plugin_collect` on stdout: {"artifacts": [...], "audit": [...]}.
+//! Their runs are recorded in `external_commands`. Plain command lines
+//! declared under [[command]] are run by `custom_commands::CommandCollector`.
+//!
+//! ```toml
+//! [[external]]
+//! name = "crm_sessions"
+//! description = "Sessions of the CRM client"
+//! namespace = "crm"                   # default: the name
+//! program = "tools\\crm-sessions.exe" # relative to this file; bare names are looked up in PATH
+//! arguments = ["--json"]
+//! ```
+
+use crate::forensic_types::{AuditEntry, CustomArtifacts};
+use crate::logger;
+use crate::plugins;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Environment variable carrying the scan context to external collectors
+pub const CONTEXT_VARIABLE: &str = "TRIAGEIR_CONTEXT";
+
+/// What a collector knows about the scan it runs in
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CollectorContext {
+    pub cli_version: String,
+    pub scan_id: String,
+    pub hostname: String,
+    pub offline_root: Option<String>, // Volume root of an offline scan
+    pub since: Option<String>,
+    pub until: Option<String>,
+}
+
+/// Artifacts and audit entries of one collector run
+pub struct CollectorOutput<A> {
+    pub artifacts: Vec<A>,
+    pub audit: Vec<AuditEntry>,
+}
+
+/// A collector outside the built-in set; its artifacts are serialized into
+/// `custom_artifacts` under its namespace
+pub trait Collector: Send + Sync {
+    type Artifact: Serialize;
+
+    /// Letters, digits, '_', '-' and '.'
+    fn name(&self) -> &str;
+
+    fn description(&self) -> &str;
+
+    /// Key under `custom_artifacts`
+    fn namespace(&self) -> &str {
+        self.name()
+    }
+
+    /// Where the collector comes from, recorded with its artifacts
+    fn source(&self) -> String {
+        "compiled-in".to_string()
+    }
+
+    fn run(&self, context: &CollectorContext) -> Result<CollectorOutput<Self::Artifact>, String>;
+}
+
+/// `Collector` with the artifact type erased, so the registry can hold any collector
+trait RegisteredCollector: Send + Sync {
+    fn name(&self) -> &str;
+    fn description(&self) -> &str;
+    fn namespace(&self) -> &str;
+    fn source(&self) -> String;
+    fn run_json(&self, context: &CollectorContext) -> Result<CollectorOutput<Value>, String>;
+}
+
+impl<C: Collector> RegisteredCollector for C {
+    fn name(&self) -> &str {
+        Collector::name(self)
+    }
+
+    fn description(&self) -> &str {
+        Collector::description(self)
+    }
+
+    fn namespace(&self) -> &str {
+        Collector::namespace(self)
+    }
+
+    fn source(&self) -> String {
+        Collector::source(self)
+    }
+
+    fn run_json(&self, context: &CollectorContext) -> Result<CollectorOutput<Value>, String> {
+        let output = self.run(context)?;
+        let artifacts = output.artifacts.iter()
+            .map(|artifact| serde_json::to_value(artifact).map_err(|e| format!("cannot serialize an artifact: {}", e)))
+            .collect::<Result<_, _>>()?;
+        Ok(CollectorOutput { artifacts, audit: output.audit })
+    }
+}
+
+/// The custom collectors of a scan, run in registration order
+#[derive(Default)]
+pub struct CollectorRegistry {
+    collectors: Vec<Box<dyn RegisteredCollector>>,
+}
+
+impl CollectorRegistry {
+    /// The compiled-in collectors of the enabled cargo features
+    pub fn builtin() -> Self {
+        #[allow(unused_mut)]
+        let mut registry = CollectorRegistry::default();
+        #[cfg(feature = "collector-recycle-bin")]
+        registry.register(crate::recycle_bin::RecycleBinCollector).expect("valid compiled-in collector");
+        registry
+    }
+
+    /// Add a collector; names and namespaces must be valid and unique
+    pub fn register<C: Collector + 'static>(&mut self, collector: C) -> Result<(), String> {
+        for (kind, value) in [("name", Collector::name(&collector)), ("namespace", Collector::namespace(&collector))] {
+            if !plugins::is_valid_plugin_name(value) {
+                return Err(format!("invalid collector {} '{}' (use letters, digits, '_', '-' and '.')", kind, value));
+            }
+        }
+        if let Some(existing) = self.collectors.iter().find(|existing| {
+            existing.name() == Collector::name(&collector) || existing.namespace() == Collector::namespace(&collector)
+        }) {
+            return Err(format!("collector '{}' clashes with '{}' (namespace {})", Collector::name(&collector), existing.name(), existing.namespace()));
+        }
+        self.collectors.push(Box::new(collector));
+        Ok(())
+    }
+
+    /// Register the external collectors and commands declared in a collectors config file
+    pub fn load_config(&mut self, path: &Path) -> Result<(), String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("Failed to read collectors config {}: {}", path.display(), e))?;
+        let config: CollectorsConfig = toml::from_str(&text).map_err(|e| format!("Invalid collectors config {}: {}", path.display(), e))?;
+        let base = path.parent().unwrap_or(Path::new(""));
+        for mut collector in config.external {
+            collector.program = resolve_program(base, &collector.program);
+            self.register(collector).map_err(|e| format!("Invalid collectors config {}: {}", path.display(), e))?;
+        }
+        if !config.command.is_empty() {
+            crate::custom_commands::CommandCollector::new(config.command, base)
+                .and_then(|collector| self.register(collector))
+                .map_err(|e| format!("Invalid collectors config {}: {}", path.display(), e))?;
+        }
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.collectors.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.collectors.len()
+    }
+
+    /// Run every collector; a failing collector is audited and leaves no namespace
+    pub fn run(&self, context: &CollectorContext) -> (BTreeMap<String, CustomArtifacts>, Vec<AuditEntry>) {
+        let mut results = BTreeMap::new();
+        let mut audit_log = vec![AuditEntry::new("custom_collectors", "INFO", "start_collection", format!("Running {} custom collectors", self.len()), "started")];
+        let start_time = std::time::Instant::now();
+
+        for collector in &self.collectors {
+            let collector_start = std::time::Instant::now();
+            let component = format!("custom:{}", collector.name());
+            match collector.run_json(context) {
+                Ok(output) => {
+                    audit_log.extend(output.audit);
+                    audit_log.push(AuditEntry {
+                        duration_ms: Some(collector_start.elapsed().as_millis() as u64),
+                        ..AuditEntry::new(&component, "INFO", "complete_collection", format!("Collected {} artifacts into custom_artifacts.{}", output.artifacts.len(), collector.namespace()), "success")
+                    });
+                    results.insert(collector.namespace().to_string(), CustomArtifacts {
+                        collector: collector.name().to_string(),
+                        description: collector.description().to_string(),
+                        source: collector.source(),
+                        artifacts: output.artifacts,
+                    });
+                }
+                Err(e) => audit_log.push(AuditEntry::new(&component, "ERROR", "collect", e, "error")),
+            }
+        }
+
+        audit_log.push(AuditEntry {
+            duration_ms: Some(start_time.elapsed().as_millis() as u64),
+            ..AuditEntry::new("custom_collectors", "INFO", "complete_collection", format!("Ran {} of {} custom collectors", results.len(), self.collectors.len()), "success")
+        });
+        (results, audit_log)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CollectorsConfig {
+    #[serde(default)]
+    external: Vec<ExternalCollector>,
+    #[serde(default)]
+    command: Vec<crate::custom_commands::CommandSpec>,
+}
+
+/// An executable declared under [[external]] in the collectors config
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExternalCollector {
+    name: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    namespace: Option<String>,
+    program: PathBuf,
+    #[serde(default)]
+    arguments: Vec<String>,
+}
+
+impl Collector for ExternalCollector {
+    type Artifact = Value;
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn namespace(&self) -> &str {
+        self.namespace.as_deref().unwrap_or(&self.name)
+    }
+
+    fn source(&self) -> String {
+        self.program.display().to_string()
+    }
+
+    fn run(&self, context: &CollectorContext) -> Result<CollectorOutput<Value>, String> {
+        let context_json = serde_json::to_string(context).map_err(|e| e.to_string())?;
+        let mut command = Command::new(&self.program);
+        command.args(&self.arguments).env(CONTEXT_VARIABLE, context_json).stdin(Stdio::null());
+        let output = logger::run_command(&format!("custom:{}", self.name), &mut command)
+            .map_err(|e| format!("cannot run {}: {}", self.program.display(), e))?;
+        if !output.status.success() {
+            return Err(format!("{} exited with {}", self.program.display(), output.status));
+        }
+
+        let output = plugins::parse_plugin_output(&String::from_utf8_lossy(&output.stdout))?;
+        let component = format!("custom:{}", self.name);
+        let audit = output.audit.into_iter().map(|entry| plugins::plugin_audit_entry(&component, entry)).collect();
+        Ok(CollectorOutput { artifacts: output.artifacts, audit })
+    }
+}
+
+/// Programs given with a directory are relative to the config file, so a
+/// toolkit can carry its collectors next to it; bare names go through PATH
+pub(crate) fn resolve_program(base: &Path, program: &Path) -> PathBuf {
+    if program.is_relative() && program.components().count() > 1 {
+        base.join(program)
+    } else {
+        program.to_path_buf()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Session {
+        user: String,
+        active: bool,
+    }
+
+    struct SessionCollector;
+
+    impl Collector for SessionCollector {
+        type Artifact = Session;
+
+        fn name(&self) -> &str {
+            "sessions"
+        }
+
+        fn description(&self) -> &str {
+            "Test sessions"
+        }
+
+        fn namespace(&self) -> &str {
+            "crm"
+        }
+
+        fn run(&self, context: &CollectorContext) -> Result<CollectorOutput<Session>, String> {
+            let user = format!("{}\\alice", context.hostname);
+            Ok(CollectorOutput { artifacts: vec![Session { user, active: true }], audit: Vec::new() })
+        }
+    }
+
+    #[test]
+    fn test_registry_runs_collectors() {
+        let mut registry = CollectorRegistry::default();
+        registry.register(SessionCollector).unwrap();
+        assert!(registry.register(SessionCollector).unwrap_err().contains("clashes"));
+
+        let config_dir = tempfile::tempdir().unwrap();
+        let config = config_dir.path().join("collectors.toml");
+        std::fs::write(&config, "[[external]]\nname = \"missing\"\nprogram = \"bin/does-not-exist\"\n").unwrap();
+        registry.load_config(&config).unwrap();
+        assert_eq!(registry.len(), 2);
+
+        let context = CollectorContext { hostname: "WS01".to_string(), ..CollectorContext::default() };
+        let (artifacts, audit) = registry.run(&context);
+        assert_eq!(artifacts.keys().collect::<Vec<_>>(), ["crm"]);
+        assert_eq!(artifacts["crm"].collector, "sessions");
+        assert_eq!(artifacts["crm"].source, "compiled-in");
+        assert_eq!(artifacts["crm"].artifacts, [serde_json::json!({"user": "WS01\\alice", "active": true})]);
+        assert!(audit.iter().any(|entry| entry.component == "custom:missing" && entry.level == "ERROR" && entry.details.contains("does-not-exist")));
+        assert_eq!(audit.last().unwrap().details, "Ran 1 of 2 custom collectors");
+    }
+
+    #[test]
+    fn test_collectors_config() {
+        let config_dir = tempfile::tempdir().unwrap();
+        let config = config_dir.path().join("collectors.toml");
+        let mut registry = CollectorRegistry::default();
+
+        std::fs::write(&config, "[[external]]\nname = \"../evil\"\nprogram = \"x\"\n").unwrap();
+        assert!(registry.load_config(&config).unwrap_err().contains("invalid collector name"));
+        std::fs::write(&config, "[[external]]\nname = \"a\"\nprogram = \"x\"\nshell = true\n").unwrap();
+        assert!(registry.load_config(&config).is_err());
+        std::fs::write(&config, "[[command]]\nname = \"shadows\"\ncommand = \"vssadmin list \\\"shadows\"\n").unwrap();
+        assert!(registry.load_config(&config).unwrap_err().contains("unterminated quote"));
+        std::fs::write(&config, "[[command]]\nname = \"tickets\"\ncommand = \"klist\"\n\n[[command]]\nname = \"logs\"\ncommand = \"wevtutil el\"\n").unwrap();
+        registry.load_config(&config).unwrap();
+        assert_eq!(registry.len(), 1);
+
+        assert_eq!(resolve_program(Path::new("E:\\kit"), Path::new("klist")), PathBuf::from("klist"));
+        assert_eq!(resolve_program(Path::new("kit"), Path::new("tools/crm.exe")), Path::new("kit").join("tools/crm.exe"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_external_collector() {
+        let collector = ExternalCollector {
+            name: "context".to_string(),
+            description: String::new(),
+            namespace: None,
+            program: PathBuf::from("sh"),
+            arguments: vec!["-c".to_string(), format!("echo \"{{\\\"artifacts\\\": [${}], \\\"audit\\\": [{{\\\"action\\\": \\\"read\\\"}}]}}\"", CONTEXT_VARIABLE)],
+        };
+        let context = CollectorContext { scan_id: "scan-1".to_string(), ..CollectorContext::default() };
+        let output = Collector::run(&collector, &context).unwrap();
+        assert_eq!(output.artifacts[0]["scan_id"], "scan-1");
+        assert_eq!(output.audit[0].component, "custom:context");
+    }
+}
@@ -0,0 +1,224 @@
+//! Custom commands (`[[command]]` in the collectors config)
+//! KAPE-style command collection: command lines such as `klist`,
+//! `vssadmin list shadows` or `wevtutil el` are run in order on the collector
+//! host and their output is stored, with its SHA-256, under
+//! `custom_artifacts.commands`. Each run is also recorded in
+//! `external_commands`. The commands describe the host the collector runs on,
+//! so they are skipped in offline scans.
+//!
+//! ```toml
+//! [[command]]
+//! name = "shadow_copies"
+//! command = "vssadmin list shadows"
+//! description = "Volume shadow copies"
+//! ```
+
+use crate::custom_collectors::{self, Collector, CollectorContext, CollectorOutput};
+use crate::forensic_types::AuditEntry;
+use crate::logger;
+use crate::plugins;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Instant;
+
+const COMPONENT: &str = "custom:commands";
+
+/// Output kept per stream; the hashes cover the whole output
+const MAX_OUTPUT: usize = 8 * 1024 * 1024;
+
+/// A command line declared under [[command]]
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CommandSpec {
+    pub name: String,
+    /// Program and arguments separated by spaces; "double quotes" group an argument
+    pub command: String,
+    #[serde(default)]
+    pub description: String,
+}
+
+/// Output of one declared command
+#[derive(Debug, Clone, Serialize)]
+pub struct CommandArtifact {
+    pub name: String,
+    pub description: String,
+    pub command_line: String,
+    pub started: String,
+    pub duration_ms: u64,
+    pub exit_code: Option<i32>, // None when it did not start or was terminated
+    pub stdout: String, // Lossy UTF-8, cut at MAX_OUTPUT
+    pub stderr: String,
+    pub stdout_sha256: String, // Of the raw output
+    pub stderr_sha256: String,
+    pub stdout_size: u64,
+    pub stderr_size: u64,
+    pub output_truncated: bool,
+    pub error: Option<String>, // Why the program could not be started
+}
+
+/// Runs the declared commands as the `commands` custom collector
+pub struct CommandCollector {
+    commands: Vec<DeclaredCommand>,
+}
+
+struct DeclaredCommand {
+    spec: CommandSpec,
+    program: PathBuf,
+    arguments: Vec<String>,
+}
+
+impl CommandCollector {
+    /// Programs given with a directory are relative to `base`, the config file's directory
+    pub fn new(specs: Vec<CommandSpec>, base: &Path) -> Result<Self, String> {
+        let mut names = HashSet::new();
+        let mut commands = Vec::new();
+        for spec in specs {
+            if !plugins::is_valid_plugin_name(&spec.name) {
+                return Err(format!("invalid command name '{}' (use letters, digits, '_', '-' and '.')", spec.name));
+            }
+            if !names.insert(spec.name.clone()) {
+                return Err(format!("command '{}' is declared twice", spec.name));
+            }
+            let mut arguments = split_command_line(&spec.command).map_err(|e| format!("command '{}': {}", spec.name, e))?;
+            let program = custom_collectors::resolve_program(base, Path::new(&arguments.remove(0)));
+            commands.push(DeclaredCommand { spec, program, arguments });
+        }
+        Ok(CommandCollector { commands })
+    }
+}
+
+impl Collector for CommandCollector {
+    type Artifact = CommandArtifact;
+
+    fn name(&self) -> &str {
+        "commands"
+    }
+
+    fn description(&self) -> &str {
+        "Output of the command lines declared in the collectors config"
+    }
+
+    fn run(&self, context: &CollectorContext) -> Result<CollectorOutput<CommandArtifact>, String> {
+        if let Some(root) = &context.offline_root {
+            let details = format!("{} commands skipped: they describe the collector host, not the offline volume {}", self.commands.len(), root);
+            return Ok(CollectorOutput { artifacts: Vec::new(), audit: vec![AuditEntry::new(COMPONENT, "WARN", "skip_commands", details, "skipped")] });
+        }
+
+        let mut artifacts = Vec::new();
+        let mut audit = Vec::new();
+        for DeclaredCommand { spec, program, arguments } in &self.commands {
+            let started = chrono::Utc::now().to_rfc3339();
+            let start_time = Instant::now();
+            let mut command = Command::new(program);
+            command.args(arguments).stdin(Stdio::null());
+            let result = logger::run_command(&format!("custom:commands:{}", spec.name), &mut command);
+
+            let mut artifact = CommandArtifact {
+                name: spec.name.clone(),
+                description: spec.description.clone(),
+                command_line: spec.command.clone(),
+                started,
+                duration_ms: start_time.elapsed().as_millis() as u64,
+                exit_code: None,
+                stdout: String::new(),
+                stderr: String::new(),
+                stdout_sha256: String::new(),
+                stderr_sha256: String::new(),
+                stdout_size: 0,
+                stderr_size: 0,
+                output_truncated: false,
+                error: None,
+            };
+            match result {
+                Ok(output) => {
+                    artifact.exit_code = output.status.code();
+                    artifact.stdout_sha256 = hex::encode(Sha256::digest(&output.stdout));
+                    artifact.stderr_sha256 = hex::encode(Sha256::digest(&output.stderr));
+                    artifact.stdout_size = output.stdout.len() as u64;
+                    artifact.stderr_size = output.stderr.len() as u64;
+                    artifact.output_truncated = output.stdout.len() > MAX_OUTPUT || output.stderr.len() > MAX_OUTPUT;
+                    artifact.stdout = String::from_utf8_lossy(&output.stdout[..output.stdout.len().min(MAX_OUTPUT)]).into_owned();
+                    artifact.stderr = String::from_utf8_lossy(&output.stderr[..output.stderr.len().min(MAX_OUTPUT)]).into_owned();
+
+                    let (level, result) = if output.status.success() { ("INFO", "success") } else { ("WARN", "failed") };
+                    let details = format!("{}: {} ({} bytes of output, SHA-256 {})", spec.command, output.status, artifact.stdout_size, artifact.stdout_sha256);
+                    audit.push(AuditEntry { duration_ms: Some(artifact.duration_ms), ..AuditEntry::new(COMPONENT, level, "run_command", details, result) });
+                }
+                Err(e) => {
+                    audit.push(AuditEntry::new(COMPONENT, "ERROR", "run_command", format!("{}: cannot start {}: {}", spec.command, program.display(), e), "error"));
+                    artifact.error = Some(e.to_string());
+                }
+            }
+            artifacts.push(artifact);
+        }
+        Ok(CollectorOutput { artifacts, audit })
+    }
+}
+
+/// Split a declared command line into program and arguments
+fn split_command_line(command_line: &str) -> Result<Vec<String>, String> {
+    let mut parts = Vec::new();
+    let mut current: Option<String> = None;
+    let mut quoted = false;
+    for c in command_line.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                current.get_or_insert_with(String::new);
+            }
+            c if c.is_whitespace() && !quoted => parts.extend(current.take()),
+            c => current.get_or_insert_with(String::new).push(c),
+        }
+    }
+    if quoted {
+        return Err("unterminated quote".to_string());
+    }
+    parts.extend(current);
+    if parts.is_empty() {
+        return Err("empty command line".to_string());
+    }
+    Ok(parts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(name: &str, command: &str) -> CommandSpec {
+        CommandSpec { name: name.to_string(), command: command.to_string(), description: String::new() }
+    }
+
+    #[test]
+    fn test_split_command_line() {
+        assert_eq!(split_command_line("vssadmin list  shadows").unwrap(), ["vssadmin", "list", "shadows"]);
+        assert_eq!(split_command_line("wevtutil qe \"Microsoft-Windows-TaskScheduler/Operational\" /c:5").unwrap(), ["wevtutil", "qe", "Microsoft-Windows-TaskScheduler/Operational", "/c:5"]);
+        assert_eq!(split_command_line("reg query \"\"").unwrap(), ["reg", "query", ""]);
+        assert!(split_command_line("klist \"tickets").is_err());
+        assert!(split_command_line("   ").is_err());
+
+        assert!(CommandCollector::new(vec![spec("klist", "klist"), spec("klist", "klist tgt")], Path::new("")).is_err());
+        assert!(CommandCollector::new(vec![spec("list shadows", "vssadmin list shadows")], Path::new("")).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_commands() {
+        let collector = CommandCollector::new(vec![spec("greeting", "echo \"hello world\""), spec("missing", "triageir-no-such-program")], Path::new("")).unwrap();
+        let output = collector.run(&CollectorContext::default()).unwrap();
+
+        assert_eq!(output.artifacts.len(), 2);
+        let greeting = &output.artifacts[0];
+        assert_eq!((greeting.stdout.as_str(), greeting.exit_code), ("hello world\n", Some(0)));
+        assert_eq!(greeting.stdout_sha256, hex::encode(Sha256::digest(b"hello world\n")));
+        assert!(output.artifacts[1].error.is_some());
+        assert_eq!(output.audit.iter().map(|entry| entry.level.as_str()).collect::<Vec<_>>(), ["INFO", "ERROR"]);
+
+        let offline = CollectorContext { offline_root: Some("E:\\".to_string()), ..CollectorContext::default() };
+        let output = collector.run(&offline).unwrap();
+        assert!(output.artifacts.is_empty());
+        assert_eq!(output.audit[0].action, "skip_commands");
+    }
+}
@@ -0,0 +1,338 @@
+//! Microsoft Defender detection history and exclusions
+//! Detections come from the Defender operational log (1116 malware detected,
+//! 1117 action taken) and from the DetectionHistory files Defender keeps per
+//! threat, which survive clearing the event log. Exclusions are listed from
+//! the registry (as read by the security configuration audit) and from 5007
+//! configuration change events, which also show exclusions added and later
+//! removed again by an intruder.
+
+use crate::event_logs::DEFENDER_CHANNEL;
+use crate::footprint::{self, Access};
+use crate::forensic_types::{AuditEntry, DefenderEvent, DefenderExclusion, SecuritySetting};
+use crate::offline::OfflineRoot;
+use crate::types::{EventLogEntry, EventLogs};
+use regex::Regex;
+use std::path::Path;
+use std::sync::OnceLock;
+
+const COMPONENT: &str = "defender_detections";
+
+const DETECTION_HISTORY_DIRECTORY: &str = "C:\\ProgramData\\Microsoft\\Windows Defender\\Scans\\History\\Service\\DetectionHistory";
+
+const EVENT_MALWARE_DETECTED: u32 = 1116;
+const EVENT_ACTION_TAKEN: u32 = 1117;
+const EVENT_CONFIGURATION_CHANGED: u32 = 5007;
+/// DetectionHistory files are a few KiB; anything far larger is not one
+const MAX_DETECTION_FILE_SIZE: u64 = 1024 * 1024;
+/// Shortest UTF-16 run taken from a DetectionHistory file
+const MIN_STRING_LENGTH: usize = 4;
+
+/// Defender events and exclusions of the collected event logs, the security
+/// configuration audit and the DetectionHistory files of the live system or offline volume
+pub fn collect_defender_detections(
+    event_logs: &EventLogs,
+    security_settings: &[SecuritySetting],
+    offline_root: Option<&OfflineRoot>,
+) -> (Vec<DefenderEvent>, Vec<DefenderExclusion>, Vec<AuditEntry>) {
+    let start_time = std::time::Instant::now();
+    let mut audit_log = vec![AuditEntry::new(COMPONENT, "INFO", "start_collection", "Starting Defender detection analysis".to_string(), "started")];
+
+    let channel_events = event_logs.channels.get(DEFENDER_CHANNEL).map(Vec::as_slice).unwrap_or_default();
+    if !event_logs.channels.contains_key(DEFENDER_CHANNEL) {
+        audit_log.push(AuditEntry::new(
+            COMPONENT,
+            "INFO",
+            "event_logs",
+            format!("{} was not collected; detections come from DetectionHistory files only", DEFENDER_CHANNEL),
+            "not_found",
+        ));
+    }
+    let mut events: Vec<DefenderEvent> = channel_events.iter().filter_map(defender_event).collect();
+
+    let history_dir = match offline_root {
+        Some(root) => root.resolve(DETECTION_HISTORY_DIRECTORY),
+        None => DETECTION_HISTORY_DIRECTORY.into(),
+    };
+    events.extend(detection_history(&history_dir, &mut audit_log));
+    events.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+
+    let mut exclusions = registry_exclusions(security_settings);
+    exclusions.extend(channel_events.iter().filter(|e| e.event_id == EVENT_CONFIGURATION_CHANGED).filter_map(exclusion_change));
+
+    let mut complete = AuditEntry::new(
+        COMPONENT,
+        "INFO",
+        "complete_collection",
+        format!("Collected {} Defender events and {} exclusions", events.len(), exclusions.len()),
+        "success",
+    );
+    complete.duration_ms = Some(start_time.elapsed().as_millis() as u64);
+    audit_log.push(complete);
+    (events, exclusions, audit_log)
+}
+
+fn defender_event(event: &EventLogEntry) -> Option<DefenderEvent> {
+    let field = |name: &str| event.event_data.get(name).cloned().unwrap_or_default();
+    match event.event_id {
+        EVENT_MALWARE_DETECTED | EVENT_ACTION_TAKEN => Some(DefenderEvent {
+            event_id: event.event_id,
+            timestamp: event.timestamp.clone(),
+            threat_name: field("Threat Name"),
+            severity: field("Severity Name"),
+            category: field("Category Name"),
+            path: resource_path(&field("Path")),
+            action_taken: field("Action Name"),
+            user: field("Detection User"),
+            detection_source: field("Source Name"),
+            details: String::new(),
+        }),
+        EVENT_CONFIGURATION_CHANGED => Some(DefenderEvent {
+            event_id: event.event_id,
+            timestamp: event.timestamp.clone(),
+            threat_name: String::new(),
+            severity: String::new(),
+            category: "Configuration change".to_string(),
+            path: String::new(),
+            action_taken: String::new(),
+            user: String::new(),
+            detection_source: String::new(),
+            details: format!("Old value: {}; new value: {}", field("Old Value").trim(), field("New Value").trim()),
+        }),
+        _ => None,
+    }
+}
+
+/// First file of a Defender resource list (`file:_C:\x.exe; process:_pid:4,...`)
+fn resource_path(resources: &str) -> String {
+    resources
+        .split(';')
+        .map(str::trim)
+        .find_map(|resource| resource.strip_prefix("file:_").or_else(|| resource.strip_prefix("containerfile:_")))
+        .unwrap_or(resources.trim())
+        .to_string()
+}
+
+/// Parse the DetectionHistory files, one per threat, below `history_dir`
+fn detection_history(history_dir: &Path, audit_log: &mut Vec<AuditEntry>) -> Vec<DefenderEvent> {
+    if !history_dir.is_dir() {
+        audit_log.push(AuditEntry::new(
+            COMPONENT,
+            "DEBUG",
+            "detection_history",
+            format!("DetectionHistory directory not present: {}", history_dir.display()),
+            "not_found",
+        ));
+        return Vec::new();
+    }
+
+    let mut events = Vec::new();
+    for entry in walkdir::WalkDir::new(history_dir).max_depth(2).into_iter() {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                // Only SYSTEM can list the folder on a live host
+                audit_log.push(AuditEntry::new(COMPONENT, "WARN", "detection_history", format!("Cannot read {}: {}", history_dir.display(), e), "error"));
+                continue;
+            }
+        };
+        if entry.file_type().is_dir() {
+            footprint::record_file(entry.path(), Access::List);
+        }
+        if !entry.file_type().is_file() || entry.metadata().map(|m| m.len() > MAX_DETECTION_FILE_SIZE).unwrap_or(true) {
+            continue;
+        }
+        match footprint::read(entry.path()) {
+            Ok(data) => {
+                let modified = entry.metadata().ok().and_then(|m| m.modified().ok()).map(|time| chrono::DateTime::<chrono::Utc>::from(time).to_rfc3339());
+                if let Some(event) = parse_detection_file(&data, &entry.path().display().to_string(), modified.unwrap_or_default()) {
+                    events.push(event);
+                }
+            }
+            Err(e) => audit_log.push(AuditEntry::new(COMPONENT, "WARN", "detection_history", format!("Failed to read {}: {}", entry.path().display(), e), "error")),
+        }
+    }
+    audit_log.push(AuditEntry::new(COMPONENT, "DEBUG", "detection_history", format!("Parsed {} DetectionHistory files", events.len()), "success"));
+    events
+}
+
+/// The DetectionHistory record format is undocumented; the threat name, the
+/// detected resources and the user are recognized among its UTF-16 strings
+fn parse_detection_file(data: &[u8], file: &str, modified: String) -> Option<DefenderEvent> {
+    static THREAT_NAME: OnceLock<Regex> = OnceLock::new();
+    static ACCOUNT: OnceLock<Regex> = OnceLock::new();
+    let threat_name = THREAT_NAME.get_or_init(|| Regex::new(r"^[A-Za-z]+:[A-Za-z0-9]+/\S+$").unwrap());
+    let account = ACCOUNT.get_or_init(|| Regex::new(r"^[^\\/:]+\\[^\\/:]+$").unwrap());
+
+    let strings = utf16_strings(data);
+    let threat = strings.iter().find(|s| threat_name.is_match(s))?;
+    let path = strings.iter().find(|s| s.starts_with("file:_") || s.starts_with("containerfile:_")).map(|s| resource_path(s)).unwrap_or_default();
+    Some(DefenderEvent {
+        event_id: 0,
+        timestamp: modified,
+        threat_name: threat.clone(),
+        severity: String::new(),
+        category: String::new(),
+        path,
+        action_taken: String::new(),
+        user: strings.iter().find(|s| account.is_match(s)).cloned().unwrap_or_default(),
+        detection_source: "DetectionHistory".to_string(),
+        details: file.to_string(),
+    })
+}
+
+/// Printable UTF-16LE runs of at least MIN_STRING_LENGTH characters, at even and odd offsets
+fn utf16_strings(data: &[u8]) -> Vec<String> {
+    let mut strings = Vec::new();
+    for alignment in 0..2 {
+        let mut current = String::new();
+        for unit in data[alignment.min(data.len())..].chunks_exact(2).map(|pair| u16::from_le_bytes([pair[0], pair[1]])) {
+            match char::from_u32(unit as u32).filter(|c| !c.is_control()) {
+                Some(c) => current.push(c),
+                None => {
+                    if current.chars().count() >= MIN_STRING_LENGTH {
+                        strings.push(std::mem::take(&mut current));
+                    }
+                    current.clear();
+                }
+            }
+        }
+        if current.chars().count() >= MIN_STRING_LENGTH {
+            strings.push(current);
+        }
+    }
+    strings
+}
+
+/// Exclusions found by the security configuration audit
+fn registry_exclusions(settings: &[SecuritySetting]) -> Vec<DefenderExclusion> {
+    settings
+        .iter()
+        .filter(|setting| setting.category == "defender")
+        .filter_map(|setting| {
+            let exclusion_type = setting.name.strip_suffix(" exclusion")?;
+            Some(DefenderExclusion {
+                exclusion_type: exclusion_type_name(exclusion_type),
+                value: setting.value.clone().unwrap_or_default(),
+                source: if setting.registry_key.contains("\\Policies\\") { "policy" } else { "registry" }.to_string(),
+                registry_key: setting.registry_key.clone(),
+                timestamp: String::new(),
+                removed: false,
+            })
+        })
+        .collect()
+}
+
+/// Exclusion added or removed by a 5007 event, whose values read
+/// `HKLM\SOFTWARE\Microsoft\Windows Defender\Exclusions\Paths\C:\Temp = 0x0`
+fn exclusion_change(event: &EventLogEntry) -> Option<DefenderExclusion> {
+    let parse = |value: &str| {
+        let (key, rest) = value.split_once("\\Exclusions\\")?;
+        let (exclusion_type, exclusion) = rest.split_once('\\')?;
+        let exclusion = exclusion.rsplit_once(" = ").map(|(name, _)| name).unwrap_or(exclusion);
+        Some((format!("{}\\Exclusions\\{}", key.trim(), exclusion_type), exclusion_type.to_string(), exclusion.trim().to_string()))
+    };
+    let new_value = event.event_data.get("New Value").and_then(|value| parse(value));
+    let old_value = event.event_data.get("Old Value").and_then(|value| parse(value));
+    let (removed, (registry_key, exclusion_type, value)) = match (new_value, old_value) {
+        (Some(added), _) => (false, added),
+        (None, Some(removed)) => (true, removed),
+        (None, None) => return None,
+    };
+    Some(DefenderExclusion {
+        exclusion_type: exclusion_type_name(&exclusion_type),
+        value,
+        source: "event_log".to_string(),
+        registry_key,
+        timestamp: event.timestamp.clone(),
+        removed,
+    })
+}
+
+fn exclusion_type_name(registry_name: &str) -> String {
+    match registry_name {
+        "Paths" => "path",
+        "Extensions" => "extension",
+        "Processes" => "process",
+        "IpAddresses" => "ip_address",
+        other => other,
+    }
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(event_id: u32, timestamp: &str, data: &[(&str, &str)]) -> EventLogEntry {
+        let mut entry = EventLogEntry::new(event_id, "Warning".to_string(), timestamp.to_string(), String::new());
+        entry.event_data = data.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        entry
+    }
+
+    fn utf16(text: &str) -> Vec<u8> {
+        text.encode_utf16().flat_map(|unit| unit.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn test_defender_events_and_exclusions() {
+        let mut event_logs = EventLogs::default();
+        event_logs.channels.insert(DEFENDER_CHANNEL.to_string(), vec![
+            event(1116, "2024-04-19T11:33:20Z", &[
+                ("Threat Name", "HackTool:Win32/Mimikatz!pz"),
+                ("Severity Name", "High"),
+                ("Category Name", "Tool"),
+                ("Path", "file:_C:\\Users\\bob\\Downloads\\m.exe; process:_pid:4120,ProcessStart:1"),
+                ("Action Name", "Not Applicable"),
+                ("Detection User", "CORP\\bob"),
+                ("Source Name", "Real-Time Protection"),
+            ]),
+            event(5007, "2024-04-19T11:30:00Z", &[
+                ("Old Value", ""),
+                ("New Value", "HKLM\\SOFTWARE\\Microsoft\\Windows Defender\\Exclusions\\Paths\\C:\\ProgramData\\x = 0x0"),
+            ]),
+            event(5007, "2024-04-19T11:40:00Z", &[
+                ("Old Value", "HKLM\\SOFTWARE\\Microsoft\\Windows Defender\\Exclusions\\Extensions\\.locked = 0x0"),
+                ("New Value", ""),
+            ]),
+        ]);
+        let settings = vec![SecuritySetting {
+            category: "defender".to_string(),
+            name: "Processes exclusion".to_string(),
+            registry_key: "HKLM\\SOFTWARE\\Policies\\Microsoft\\Windows Defender\\Exclusions\\Processes".to_string(),
+            value_name: "rclone.exe".to_string(),
+            value: Some("rclone.exe".to_string()),
+            state: "excluded from scanning".to_string(),
+            weakened: true,
+        }];
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir(temp_dir.path().join("Windows")).unwrap();
+        let root = OfflineRoot::new(temp_dir.path()).unwrap();
+
+        let (events, exclusions, _) = collect_defender_detections(&event_logs, &settings, Some(&root));
+        assert_eq!(events.iter().map(|e| e.event_id).collect::<Vec<_>>(), vec![5007, 1116, 5007]);
+        let detection = &events[1];
+        assert_eq!(detection.threat_name, "HackTool:Win32/Mimikatz!pz");
+        assert_eq!(detection.path, "C:\\Users\\bob\\Downloads\\m.exe");
+        assert_eq!((detection.user.as_str(), detection.detection_source.as_str()), ("CORP\\bob", "Real-Time Protection"));
+
+        assert_eq!(exclusions.len(), 3);
+        assert_eq!((exclusions[0].exclusion_type.as_str(), exclusions[0].source.as_str()), ("process", "policy"));
+        assert_eq!((exclusions[1].exclusion_type.as_str(), exclusions[1].value.as_str(), exclusions[1].removed), ("path", "C:\\ProgramData\\x", false));
+        assert_eq!((exclusions[2].value.as_str(), exclusions[2].removed), (".locked", true));
+        assert_eq!(exclusions[2].registry_key, "HKLM\\SOFTWARE\\Microsoft\\Windows Defender\\Exclusions\\Extensions");
+    }
+
+    #[test]
+    fn test_parse_detection_history_file() {
+        let mut data = vec![0x08, 0, 0, 0, 0x5a, 0x12];
+        for text in ["Magic.Version", "ThreatTrackingId", "Ransom:Win32/LockBit.PA!MTB", "file:_C:\\Users\\Public\\lb3.exe", "CORP\\svc_sql"] {
+            data.extend(utf16(text));
+            data.extend([0, 0, 0x0c, 0]);
+        }
+        let event = parse_detection_file(&data, "DetectionHistory\\02\\{GUID}", "2024-04-19T11:33:20+00:00".to_string()).unwrap();
+        assert_eq!(event.threat_name, "Ransom:Win32/LockBit.PA!MTB");
+        assert_eq!(event.path, "C:\\Users\\Public\\lb3.exe");
+        assert_eq!(event.user, "CORP\\svc_sql");
+        assert!(parse_detection_file(&utf16("no threat here"), "x", String::new()).is_none());
+    }
+}
@@ -0,0 +1,232 @@
+//! Microsoft Defender support log analysis
+//! MPLog-*.log records per-process scan impact (ProcessImageName/Pid lines) and
+//! cloud lookups of scanned files (SDN queries); MPDetection-*.log records
+//! detections. Both outlive event log clearing and often show executions of
+//! attacker tools that no event log captured.
+
+use crate::footprint;
+use crate::forensic_types::{DefenderLogEntry, AuditEntry};
+use crate::offline::OfflineRoot;
+use regex::Regex;
+use std::path::Path;
+
+const COMPONENT: &str = "defender_logs";
+
+const SUPPORT_DIRECTORY: &str = "C:\\ProgramData\\Microsoft\\Windows Defender\\Support";
+
+/// Collect breadcrumbs from the Defender support logs of the live system
+pub fn collect_defender_logs() -> (Vec<DefenderLogEntry>, Vec<AuditEntry>) {
+    collect_defender_logs_from_directory(Path::new(SUPPORT_DIRECTORY))
+}
+
+/// Collect breadcrumbs from the Defender support logs of a mounted Windows volume
+pub fn collect_defender_logs_offline(root: &OfflineRoot) -> (Vec<DefenderLogEntry>, Vec<AuditEntry>) {
+    collect_defender_logs_from_directory(&root.resolve(SUPPORT_DIRECTORY))
+}
+
+fn collect_defender_logs_from_directory(support_dir: &Path) -> (Vec<DefenderLogEntry>, Vec<AuditEntry>) {
+    let mut entries = Vec::new();
+    let start_time = std::time::Instant::now();
+
+    let mut audit_log = vec![AuditEntry::new(
+        COMPONENT,
+        "INFO",
+        "start_collection",
+        format!("Starting Defender support log analysis of {}", support_dir.display()),
+        "started",
+    )];
+
+    let log_files = match footprint::read_dir(support_dir) {
+        Ok(dir_entries) => {
+            let mut files: Vec<_> = dir_entries
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|path| path.is_file() && is_support_log(path))
+                .collect();
+            files.sort();
+            files
+        }
+        Err(e) => {
+            audit_log.push(AuditEntry::new(
+                COMPONENT,
+                "WARN",
+                "directory_check",
+                format!("Defender support directory not readable: {} ({})", support_dir.display(), e),
+                "not_found",
+            ));
+            Vec::new()
+        }
+    };
+
+    for log_file in &log_files {
+        match footprint::read(log_file) {
+            Ok(data) => {
+                let parsed = parse_support_log(&decode_log_text(&data), &log_file.display().to_string());
+                audit_log.push(AuditEntry::new(
+                    COMPONENT,
+                    "DEBUG",
+                    "parse_log",
+                    format!("Parsed {} entries from {}", parsed.len(), log_file.display()),
+                    "success",
+                ));
+                entries.extend(parsed);
+            }
+            Err(e) => {
+                audit_log.push(AuditEntry::new(
+                    COMPONENT,
+                    "ERROR",
+                    "read_log",
+                    format!("Failed to read {}: {}", log_file.display(), e),
+                    "error",
+                ));
+            }
+        }
+    }
+
+    let duration = start_time.elapsed();
+    let mut complete = AuditEntry::new(
+        COMPONENT,
+        "INFO",
+        "complete_collection",
+        format!("Collected {} Defender log entries from {} files", entries.len(), log_files.len()),
+        "success",
+    );
+    complete.duration_ms = Some(duration.as_millis() as u64);
+    audit_log.push(complete);
+
+    (entries, audit_log)
+}
+
+fn is_support_log(path: &Path) -> bool {
+    let name = path.file_name().unwrap_or_default().to_string_lossy().to_lowercase();
+    (name.starts_with("mplog-") || name.starts_with("mpdetection-")) && name.ends_with(".log")
+}
+
+/// Support logs are usually UTF-16LE with a BOM; older ones are ANSI/UTF-8
+fn decode_log_text(data: &[u8]) -> String {
+    let utf16 = data.starts_with(&[0xFF, 0xFE])
+        || (data.len() >= 4 && data[1] == 0 && data[3] == 0);
+
+    if utf16 {
+        let body = data.strip_prefix(&[0xFF, 0xFE]).unwrap_or(data);
+        let units: Vec<u16> = body
+            .chunks_exact(2)
+            .map(|chunk| u16::from_le_bytes([chunk[0], chunk[1]]))
+            .collect();
+        String::from_utf16_lossy(&units)
+    } else {
+        String::from_utf8_lossy(data).to_string()
+    }
+}
+
+/// Extract the process-scan, SDN query and detection lines of a support log
+fn parse_support_log(text: &str, source_file: &str) -> Vec<DefenderLogEntry> {
+    let timestamped = Regex::new(r"^(\d{4}-\d{2}-\d{2}T\d{2}:\d{2}:\d{2}(?:\.\d+)?Z?)\s+(.*)$").unwrap();
+    let process_impact = Regex::new(r"ProcessImageName:\s*([^,]+),\s*Pid:\s*(\d+)(?:.*?MaxTimeFile:\s*([^,]*))?").unwrap();
+    let sdn_query = Regex::new(r"SDN:Issuing SDN query for (.+?)(?:\s+\(|$)").unwrap();
+    let detection = Regex::new(r"^DETECTION\S*\s+(\S+)\s+(?:[a-zA-Z]{2,}:)?(.*)$").unwrap(); // Resource prefixed with file:, process:, ...
+
+    let mut entries = Vec::new();
+
+    for (index, raw_line) in text.lines().enumerate() {
+        let line = raw_line.trim_matches(|c: char| c.is_whitespace() || c == '\u{feff}');
+        let Some(parts) = timestamped.captures(line) else {
+            continue;
+        };
+        let message = &parts[2];
+
+        let mut entry = DefenderLogEntry {
+            timestamp: parts[1].to_string(),
+            category: String::new(),
+            process_name: String::new(),
+            pid: None,
+            path: String::new(),
+            threat_name: String::new(),
+            line: line.to_string(),
+            source_file: source_file.to_string(),
+            line_number: index + 1,
+        };
+
+        if let Some(capture) = process_impact.captures(message) {
+            entry.category = "process_impact".to_string();
+            entry.process_name = capture[1].trim().to_string();
+            entry.pid = capture[2].parse().ok();
+            entry.path = capture.get(3).map(|m| m.as_str().trim().to_string()).unwrap_or_default();
+        } else if let Some(capture) = sdn_query.captures(message) {
+            entry.category = "sdn_query".to_string();
+            entry.path = capture[1].trim().to_string();
+        } else if let Some(capture) = detection.captures(message) {
+            entry.category = "detection".to_string();
+            entry.threat_name = capture[1].to_string();
+            entry.path = capture[2].trim().to_string();
+        } else {
+            continue;
+        }
+
+        entries.push(entry);
+    }
+
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    const SAMPLE_MPLOG: &str = "\
+************************************************************
+2023-04-12T09:31:11.672Z ProcessImageName: mimikatz.exe, Pid: 5124, TotalTime: 31, Count: 2, MaxTime: 31, MaxTimeFile: \\Device\\HarddiskVolume3\\Users\\bob\\Downloads\\mimikatz.exe, EstimatedImpact: 45%
+2023-04-12T09:31:12.001Z SDN:Issuing SDN query for \\Device\\HarddiskVolume3\\Users\\bob\\Downloads\\mimikatz.exe (\\Device\\HarddiskVolume3\\Users\\bob\\Downloads\\mimikatz.exe) (sha1=d1f7832035c3e8a73cc78afd28cfd7f4cece6d20)
+2023-04-12T09:31:12.500Z Engine:Signature update completed
+2023-04-12T09:31:13.104Z DETECTION HackTool:Win32/Mimikatz.D file:C:\\Users\\bob\\Downloads\\mimikatz.exe
+";
+
+    #[test]
+    fn test_parse_support_log() {
+        let entries = parse_support_log(SAMPLE_MPLOG, "MPLog-20230401-101010.log");
+
+        assert_eq!(entries.len(), 3);
+
+        assert_eq!(entries[0].category, "process_impact");
+        assert_eq!(entries[0].process_name, "mimikatz.exe");
+        assert_eq!(entries[0].pid, Some(5124));
+        assert_eq!(entries[0].path, "\\Device\\HarddiskVolume3\\Users\\bob\\Downloads\\mimikatz.exe");
+        assert_eq!(entries[0].timestamp, "2023-04-12T09:31:11.672Z");
+        assert_eq!(entries[0].line_number, 2);
+
+        assert_eq!(entries[1].category, "sdn_query");
+        assert_eq!(entries[1].path, "\\Device\\HarddiskVolume3\\Users\\bob\\Downloads\\mimikatz.exe");
+
+        assert_eq!(entries[2].category, "detection");
+        assert_eq!(entries[2].threat_name, "HackTool:Win32/Mimikatz.D");
+        assert_eq!(entries[2].path, "C:\\Users\\bob\\Downloads\\mimikatz.exe");
+    }
+
+    #[test]
+    fn test_decode_utf16_log() {
+        let mut data = vec![0xFF, 0xFE];
+        data.extend("2023-04-12T09:31:11Z ProcessImageName: a.exe, Pid: 1".encode_utf16().flat_map(|u| u.to_le_bytes()));
+
+        let text = decode_log_text(&data);
+        assert!(text.starts_with("2023-04-12T09:31:11Z"));
+        assert_eq!(decode_log_text(b"plain text"), "plain text");
+    }
+
+    #[test]
+    fn test_collect_from_support_directory() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("MPLog-20230401-101010.log"), SAMPLE_MPLOG).unwrap();
+        fs::write(temp_dir.path().join("MpCmdRun.log"), SAMPLE_MPLOG).unwrap();
+
+        let (entries, audit_log) = collect_defender_logs_from_directory(temp_dir.path());
+
+        assert_eq!(entries.len(), 3);
+        assert!(audit_log.iter().any(|log| log.action == "complete_collection"));
+
+        let (entries, audit_log) = collect_defender_logs_from_directory(&temp_dir.path().join("missing"));
+        assert!(entries.is_empty());
+        assert!(audit_log.iter().any(|log| log.action == "directory_check"));
+    }
+}
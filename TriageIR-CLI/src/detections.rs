@@ -0,0 +1,603 @@
+//! Sigma-style detection rules
+//! Loads a directory of YAML rules written in a simplified Sigma subset and
+//! evaluates them against the collected processes, persistence mechanisms,
+//! network connections and event logs at the end of the scan. Supported:
+//! `logsource` categories process_creation, persistence and network_connection
+//! and event log services; selections as field maps, lists of field maps or
+//! keyword lists; the contains, startswith, endswith, re and all modifiers;
+//! `*`/`?` wildcards; and conditions with and, or, not, parentheses and
+//! `1 of`/`all of` selection patterns or `them`. Field names are those of
+//! the scan output, the common Sysmon names (Image, CommandLine, ParentImage,
+//! DestinationIp, ...) or, for events, EventData names. Comparisons ignore case.
+
+use crate::forensic_types::{Detection, DetectionMatch};
+use crate::yaml;
+use regex::Regex;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+/// Levels accepted in the `level` field, lowest first
+pub const LEVELS: [&str; 5] = ["informational", "low", "medium", "high", "critical"];
+
+/// Artifact references listed per detection; the match count covers all of them
+const MAX_MATCHES_PER_RULE: usize = 100;
+
+/// Sigma field names mapped to the fields of the scan output
+const PROCESS_FIELDS: [(&str, &str); 8] = [
+    ("image", "executable_path"),
+    ("commandline", "command_line"),
+    ("processid", "pid"),
+    ("parentprocessid", "parent_pid"),
+    ("user", "user"),
+    ("sha256", "sha256_hash"),
+    ("hashes", "sha256_hash"),
+    ("integritylevel", "integrity_level"),
+];
+const PERSISTENCE_FIELDS: [(&str, &str); 4] = [("image", "command"), ("commandline", "command"), ("targetobject", "source"), ("details", "value")];
+const NETWORK_FIELDS: [(&str, &str); 6] = [
+    ("destinationip", "remote_address"),
+    ("destinationport", "remote_port"),
+    ("sourceip", "local_address"),
+    ("sourceport", "local_port"),
+    ("processid", "owning_pid"),
+    ("protocol", "protocol"),
+];
+const EVENT_FIELDS: [(&str, &str); 5] = [("eventid", "event_id"), ("channel", "source"), ("provider_name", "provider"), ("computer", "computer"), ("eventrecordid", "record_id")];
+
+/// Channels of the event log `service` names used by Sigma
+const EVENT_SERVICES: [(&str, &str); 6] = [
+    ("security", "Security"),
+    ("system", "System"),
+    ("application", "Application"),
+    ("powershell", "Microsoft-Windows-PowerShell/Operational"),
+    ("sysmon", "Microsoft-Windows-Sysmon/Operational"),
+    ("taskscheduler", "Microsoft-Windows-TaskScheduler/Operational"),
+];
+
+#[derive(Debug, Clone)]
+pub struct DetectionRule {
+    pub id: String,
+    pub title: String,
+    pub level: String,
+    pub description: String,
+    pub techniques: Vec<String>, // T1059.001, from the attack.tNNNN tags
+    source: LogSource,
+    selections: BTreeMap<String, Selection>,
+    condition: Condition,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum LogSource {
+    Processes,
+    Persistence,
+    Network,
+    Events(Option<String>), // Channel, None for every collected channel
+}
+
+#[derive(Debug, Clone)]
+enum Selection {
+    Fields(Vec<FieldMatcher>), // Every field matches
+    AnyOf(Vec<Vec<FieldMatcher>>), // One of the field maps matches
+    Keywords(Vec<Pattern>), // One of the values occurs in any field
+}
+
+#[derive(Debug, Clone)]
+struct FieldMatcher {
+    field: String,
+    patterns: Vec<Pattern>,
+    all: bool, // |all: every pattern instead of one of them
+}
+
+#[derive(Debug, Clone)]
+enum Pattern {
+    Equals(String),
+    Contains(String),
+    StartsWith(String),
+    EndsWith(String),
+    Regex(Regex),
+    Missing, // null: the field is absent or empty
+}
+
+#[derive(Debug, Clone)]
+enum Condition {
+    Selection(String),
+    OneOf(Vec<String>),
+    AllOf(Vec<String>),
+    Not(Box<Condition>),
+    And(Box<Condition>, Box<Condition>),
+    Or(Box<Condition>, Box<Condition>),
+}
+
+/// Load every .yml/.yaml rule of the directory, in file name order
+pub fn load_rules(directory: &Path) -> Result<Vec<DetectionRule>, String> {
+    let entries = std::fs::read_dir(directory).map_err(|e| format!("Failed to read detection rules {}: {}", directory.display(), e))?;
+    let mut files: Vec<_> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("yml") || ext.eq_ignore_ascii_case("yaml")))
+        .collect();
+    files.sort();
+
+    let mut rules = Vec::new();
+    for file in files {
+        let text = std::fs::read_to_string(&file).map_err(|e| format!("Failed to read {}: {}", file.display(), e))?;
+        for document in yaml::parse_documents(&text).map_err(|e| format!("Invalid rule file {}: {}", file.display(), e))? {
+            rules.push(DetectionRule::parse(&document).map_err(|e| format!("Invalid rule in {}: {}", file.display(), e))?);
+        }
+    }
+    if rules.is_empty() {
+        return Err(format!("No detection rules (.yml, .yaml) in {}", directory.display()));
+    }
+    Ok(rules)
+}
+
+impl DetectionRule {
+    fn parse(document: &Value) -> Result<Self, String> {
+        let title = document["title"].as_str().ok_or("rule without title")?.to_string();
+        let level = document["level"].as_str().unwrap_or("medium").to_lowercase();
+        if !LEVELS.contains(&level.as_str()) {
+            return Err(format!("{}: unknown level {}", title, level));
+        }
+        let techniques = document["tags"]
+            .as_array()
+            .map(|tags| tags.iter().filter_map(|tag| technique_id(tag.as_str()?)).collect())
+            .unwrap_or_default();
+
+        let source = log_source(&document["logsource"]).map_err(|e| format!("{}: {}", title, e))?;
+        let detection = document["detection"].as_object().ok_or_else(|| format!("{}: no detection section", title))?;
+        let mut selections = BTreeMap::new();
+        for (name, definition) in detection.iter().filter(|(name, _)| *name != "condition") {
+            selections.insert(name.clone(), selection(definition).map_err(|e| format!("{}: selection {}: {}", title, name, e))?);
+        }
+        let condition_text = detection.get("condition").and_then(Value::as_str).ok_or_else(|| format!("{}: no condition", title))?;
+        let condition = parse_condition(condition_text, &selections).map_err(|e| format!("{}: condition: {}", title, e))?;
+
+        Ok(DetectionRule {
+            id: document["id"].as_str().unwrap_or_default().to_string(),
+            description: document["description"].as_str().unwrap_or_default().trim().to_string(),
+            title,
+            level,
+            techniques,
+            source,
+            selections,
+            condition,
+        })
+    }
+}
+
+/// T1059.001 from an attack.t1059.001 tag; tactic tags (attack.execution) are skipped
+fn technique_id(tag: &str) -> Option<String> {
+    let id = tag.to_lowercase().strip_prefix("attack.t")?.to_string();
+    id.split('.').all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit())).then(|| format!("T{}", id))
+}
+
+fn log_source(logsource: &Value) -> Result<LogSource, String> {
+    let field = |name: &str| logsource[name].as_str().map(str::to_lowercase);
+    match (field("category").as_deref(), field("service")) {
+        (Some("process_creation" | "process"), _) => Ok(LogSource::Processes),
+        (Some("persistence" | "registry_set" | "registry_event"), _) => Ok(LogSource::Persistence),
+        (Some("network_connection"), _) => Ok(LogSource::Network),
+        (Some(other), _) => Err(format!("unsupported logsource category {}", other)),
+        (None, Some(service)) => {
+            let channel = EVENT_SERVICES.iter().find(|(name, _)| *name == service).map(|(_, channel)| channel.to_string());
+            // Other services name the channel itself
+            Ok(LogSource::Events(Some(channel.unwrap_or_else(|| logsource["service"].as_str().unwrap_or_default().to_string()))))
+        }
+        (None, None) if field("product").as_deref() == Some("windows") => Ok(LogSource::Events(None)),
+        (None, None) => Err("logsource needs a category, a service or product: windows".to_string()),
+    }
+}
+
+fn selection(definition: &Value) -> Result<Selection, String> {
+    match definition {
+        Value::Object(fields) => Ok(Selection::Fields(field_matchers(fields)?)),
+        Value::Array(items) if items.iter().all(Value::is_object) => {
+            let maps = items.iter().filter_map(Value::as_object).map(field_matchers).collect::<Result<Vec<_>, _>>()?;
+            Ok(Selection::AnyOf(maps))
+        }
+        Value::Array(items) => Ok(Selection::Keywords(items.iter().map(|item| Pattern::Contains(scalar_text(item).to_lowercase())).collect())),
+        Value::String(keyword) => Ok(Selection::Keywords(vec![Pattern::Contains(keyword.to_lowercase())])),
+        _ => Err("expected a field map, a list of field maps or keywords".to_string()),
+    }
+}
+
+fn field_matchers(fields: &serde_json::Map<String, Value>) -> Result<Vec<FieldMatcher>, String> {
+    let mut matchers = Vec::new();
+    for (key, values) in fields {
+        let mut parts = key.split('|');
+        let field = parts.next().unwrap_or_default().to_string();
+        let mut modifier = None;
+        let mut all = false;
+        for part in parts {
+            match part {
+                "all" => all = true,
+                "contains" | "startswith" | "endswith" | "re" if modifier.is_none() => modifier = Some(part),
+                other => return Err(format!("unsupported modifier {} on {}", other, field)),
+            }
+        }
+        let values = match values {
+            Value::Array(items) => items.clone(),
+            single => vec![single.clone()],
+        };
+        let patterns = values.iter().map(|value| pattern(value, modifier)).collect::<Result<Vec<_>, _>>()?;
+        matchers.push(FieldMatcher { field, patterns, all });
+    }
+    Ok(matchers)
+}
+
+fn pattern(value: &Value, modifier: Option<&str>) -> Result<Pattern, String> {
+    if value.is_null() {
+        return Ok(Pattern::Missing);
+    }
+    let text = scalar_text(value);
+    let lower = text.to_lowercase();
+    Ok(match modifier {
+        Some("contains") => Pattern::Contains(lower),
+        Some("startswith") => Pattern::StartsWith(lower),
+        Some("endswith") => Pattern::EndsWith(lower),
+        Some(_) => Pattern::Regex(Regex::new(&text).map_err(|e| format!("invalid regular expression {}: {}", text, e))?),
+        None if text.contains(['*', '?']) => {
+            let expression: String = text
+                .chars()
+                .map(|c| match c {
+                    '*' => ".*".to_string(),
+                    '?' => ".".to_string(),
+                    other => regex::escape(&other.to_string()),
+                })
+                .collect();
+            Pattern::Regex(Regex::new(&format!("(?is)^{}$", expression)).map_err(|e| e.to_string())?)
+        }
+        None => Pattern::Equals(lower),
+    })
+}
+
+fn scalar_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+/// Parse the condition; `not` binds tighter than `and`, `and` tighter than `or`
+fn parse_condition(text: &str, selections: &BTreeMap<String, Selection>) -> Result<Condition, String> {
+    let spaced = text.replace('(', " ( ").replace(')', " ) ");
+    let tokens: Vec<&str> = spaced.split_whitespace().collect();
+    let mut parser = ConditionParser { tokens: &tokens, position: 0, selections };
+    let condition = parser.or()?;
+    match parser.tokens.get(parser.position) {
+        Some(token) => Err(format!("unexpected {}", token)),
+        None => Ok(condition),
+    }
+}
+
+struct ConditionParser<'a> {
+    tokens: &'a [&'a str],
+    position: usize,
+    selections: &'a BTreeMap<String, Selection>,
+}
+
+impl ConditionParser<'_> {
+    fn next_is(&self, keyword: &str) -> bool {
+        self.tokens.get(self.position).is_some_and(|token| token.eq_ignore_ascii_case(keyword))
+    }
+
+    fn or(&mut self) -> Result<Condition, String> {
+        let mut condition = self.and()?;
+        while self.next_is("or") {
+            self.position += 1;
+            condition = Condition::Or(Box::new(condition), Box::new(self.and()?));
+        }
+        Ok(condition)
+    }
+
+    fn and(&mut self) -> Result<Condition, String> {
+        let mut condition = self.unary()?;
+        while self.next_is("and") {
+            self.position += 1;
+            condition = Condition::And(Box::new(condition), Box::new(self.unary()?));
+        }
+        Ok(condition)
+    }
+
+    fn unary(&mut self) -> Result<Condition, String> {
+        let token = *self.tokens.get(self.position).ok_or("unexpected end")?;
+        self.position += 1;
+        match token.to_lowercase().as_str() {
+            "not" => Ok(Condition::Not(Box::new(self.unary()?))),
+            "(" => {
+                let condition = self.or()?;
+                if !self.next_is(")") {
+                    return Err("missing )".to_string());
+                }
+                self.position += 1;
+                Ok(condition)
+            }
+            quantifier @ ("1" | "all") if self.next_is("of") => {
+                let target = *self.tokens.get(self.position + 1).ok_or("of without selections")?;
+                self.position += 2;
+                let names = self.expand(target)?;
+                Ok(if quantifier == "1" { Condition::OneOf(names) } else { Condition::AllOf(names) })
+            }
+            _ if self.selections.contains_key(token) => Ok(Condition::Selection(token.to_string())),
+            _ => Err(format!("unknown selection {}", token)),
+        }
+    }
+
+    /// Selection names of `them` or a `name*` pattern
+    fn expand(&self, target: &str) -> Result<Vec<String>, String> {
+        let names: Vec<String> = if target == "them" {
+            self.selections.keys().cloned().collect()
+        } else {
+            let prefix = target.strip_suffix('*');
+            self.selections.keys().filter(|name| prefix.map_or(name.as_str() == target, |prefix| name.starts_with(prefix))).cloned().collect()
+        };
+        if names.is_empty() {
+            return Err(format!("no selection matches {}", target));
+        }
+        Ok(names)
+    }
+}
+
+/// Evaluate the rules against the scan output; one detection per rule with matches
+pub fn evaluate(rules: &[DetectionRule], scan: &Value) -> Vec<Detection> {
+    let artifacts = &scan["artifacts"];
+    let processes_by_pid: HashMap<u64, &Value> = array(&artifacts["running_processes"])
+        .iter()
+        .filter_map(|process| Some((process["pid"].as_u64()?, process)))
+        .collect();
+
+    let mut detections = Vec::new();
+    for rule in rules {
+        let mut matches = Vec::new();
+        let mut match_count = 0;
+        for (reference, item) in source_items(&rule.source, artifacts) {
+            let context = ItemContext { item, source: &rule.source, processes_by_pid: &processes_by_pid };
+            if context.satisfies(&rule.condition, &rule.selections) {
+                match_count += 1;
+                if matches.len() < MAX_MATCHES_PER_RULE {
+                    matches.push(DetectionMatch { summary: summary(&rule.source, item), reference });
+                }
+            }
+        }
+        if match_count > 0 {
+            detections.push(Detection {
+                rule_id: rule.id.clone(),
+                rule_name: rule.title.clone(),
+                severity: rule.level.clone(),
+                description: rule.description.clone(),
+                mitre_techniques: rule.techniques.clone(),
+                match_count,
+                matches,
+            });
+        }
+    }
+    detections
+}
+
+/// Artifacts a rule applies to, with their JSON pointers into the scan output
+fn source_items<'a>(source: &LogSource, artifacts: &'a Value) -> Vec<(String, &'a Value)> {
+    let indexed = |pointer: String, items: &'a Value| -> Vec<(String, &'a Value)> {
+        array(items).iter().enumerate().map(|(index, item)| (format!("{}/{}", pointer, index), item)).collect()
+    };
+    match source {
+        LogSource::Processes => indexed("/artifacts/running_processes".to_string(), &artifacts["running_processes"]),
+        LogSource::Persistence => indexed("/artifacts/persistence_mechanisms".to_string(), &artifacts["persistence_mechanisms"]),
+        LogSource::Network => indexed("/artifacts/network_connections".to_string(), &artifacts["network_connections"]),
+        LogSource::Events(channel) => {
+            let event_logs = &artifacts["event_logs"];
+            let mut items = Vec::new();
+            for (key, channel_name) in [("security", "Security"), ("system", "System"), ("application", "Application")] {
+                if channel.as_deref().is_none_or(|wanted| wanted.eq_ignore_ascii_case(channel_name)) {
+                    items.extend(indexed(format!("/artifacts/event_logs/{}", key), &event_logs[key]));
+                }
+            }
+            for (name, events) in event_logs["channels"].as_object().into_iter().flatten() {
+                if channel.as_deref().is_none_or(|wanted| wanted.eq_ignore_ascii_case(name)) {
+                    let escaped = name.replace('~', "~0").replace('/', "~1");
+                    items.extend(indexed(format!("/artifacts/event_logs/channels/{}", escaped), events));
+                }
+            }
+            items
+        }
+    }
+}
+
+struct ItemContext<'a> {
+    item: &'a Value,
+    source: &'a LogSource,
+    processes_by_pid: &'a HashMap<u64, &'a Value>,
+}
+
+impl ItemContext<'_> {
+    fn satisfies(&self, condition: &Condition, selections: &BTreeMap<String, Selection>) -> bool {
+        let selected = |name: &String| selections.get(name).is_some_and(|selection| self.matches(selection));
+        match condition {
+            Condition::Selection(name) => selected(name),
+            Condition::OneOf(names) => names.iter().any(selected),
+            Condition::AllOf(names) => names.iter().all(selected),
+            Condition::Not(inner) => !self.satisfies(inner, selections),
+            Condition::And(left, right) => self.satisfies(left, selections) && self.satisfies(right, selections),
+            Condition::Or(left, right) => self.satisfies(left, selections) || self.satisfies(right, selections),
+        }
+    }
+
+    fn matches(&self, selection: &Selection) -> bool {
+        match selection {
+            Selection::Fields(matchers) => matchers.iter().all(|matcher| self.field_matches(matcher)),
+            Selection::AnyOf(maps) => maps.iter().any(|matchers| matchers.iter().all(|matcher| self.field_matches(matcher))),
+            Selection::Keywords(patterns) => {
+                let values = all_strings(self.item);
+                patterns.iter().any(|pattern| values.iter().any(|value| pattern_matches(pattern, value)))
+            }
+        }
+    }
+
+    fn field_matches(&self, matcher: &FieldMatcher) -> bool {
+        let values = self.field_values(&matcher.field);
+        let matched = |pattern: &Pattern| match pattern {
+            Pattern::Missing => values.iter().all(String::is_empty),
+            _ => values.iter().any(|value| pattern_matches(pattern, value)),
+        };
+        if matcher.all {
+            matcher.patterns.iter().all(matched)
+        } else {
+            matcher.patterns.iter().any(matched)
+        }
+    }
+
+    /// Values of a field: the output field of that name, its Sigma alias, a
+    /// field of the parent or owning process, or an EventData field
+    fn field_values(&self, field: &str) -> Vec<String> {
+        let lower = field.to_lowercase();
+        if let Some(value) = self.item.as_object().and_then(|object| object.iter().find(|(key, _)| key.eq_ignore_ascii_case(field))).map(|(_, value)| value) {
+            return strings(value);
+        }
+        let aliases: &[(&str, &str)] = match self.source {
+            LogSource::Processes => &PROCESS_FIELDS,
+            LogSource::Persistence => &PERSISTENCE_FIELDS,
+            LogSource::Network => &NETWORK_FIELDS,
+            LogSource::Events(_) => &EVENT_FIELDS,
+        };
+        if let Some((_, name)) = aliases.iter().find(|(alias, _)| *alias == lower) {
+            return strings(&self.item[*name]);
+        }
+
+        let related = match (self.source, lower.as_str()) {
+            (LogSource::Processes, "parentimage" | "parentcommandline") => self.process(&self.item["parent_pid"]),
+            (LogSource::Network, "image" | "commandline" | "user") => self.process(&self.item["owning_pid"]),
+            _ => None,
+        };
+        if let Some(process) = related {
+            let name = if lower.ends_with("image") { "executable_path" } else if lower.ends_with("commandline") { "command_line" } else { "user" };
+            return strings(&process[name]);
+        }
+        if let LogSource::Events(_) = self.source {
+            return self.item["event_data"].as_object().and_then(|data| data.iter().find(|(key, _)| key.eq_ignore_ascii_case(field))).map(|(_, value)| strings(value)).unwrap_or_default();
+        }
+        Vec::new()
+    }
+
+    fn process(&self, pid: &Value) -> Option<&Value> {
+        pid.as_u64().and_then(|pid| self.processes_by_pid.get(&pid).copied())
+    }
+}
+
+fn pattern_matches(pattern: &Pattern, value: &str) -> bool {
+    match pattern {
+        Pattern::Equals(expected) => value.to_lowercase() == *expected,
+        Pattern::Contains(fragment) => value.to_lowercase().contains(fragment.as_str()),
+        Pattern::StartsWith(prefix) => value.to_lowercase().starts_with(prefix.as_str()),
+        Pattern::EndsWith(suffix) => value.to_lowercase().ends_with(suffix.as_str()),
+        Pattern::Regex(expression) => expression.is_match(value),
+        Pattern::Missing => value.is_empty(),
+    }
+}
+
+/// Scalar values of a field, one per array element
+fn strings(value: &Value) -> Vec<String> {
+    match value {
+        Value::Array(items) => items.iter().filter(|item| !item.is_object() && !item.is_array()).map(scalar_text).collect(),
+        Value::Object(_) => Vec::new(),
+        scalar => vec![scalar_text(scalar)],
+    }
+}
+
+/// Every scalar below the artifact, for keyword selections
+fn all_strings(value: &Value) -> Vec<String> {
+    match value {
+        Value::Array(items) => items.iter().flat_map(all_strings).collect(),
+        Value::Object(fields) => fields.values().flat_map(all_strings).collect(),
+        Value::Null => Vec::new(),
+        scalar => vec![scalar_text(scalar)],
+    }
+}
+
+fn summary(source: &LogSource, item: &Value) -> String {
+    match source {
+        LogSource::Processes => format!("{} (PID {}): {}", text(&item["name"]), item["pid"], text(&item["command_line"])),
+        LogSource::Persistence => format!("{} {}: {}", text(&item["type"]), text(&item["name"]), text(&item["command"])),
+        LogSource::Network => format!(
+            "{} {}:{} -> {}:{} (PID {})",
+            text(&item["protocol"]), text(&item["local_address"]), item["local_port"], text(&item["remote_address"]), item["remote_port"], item["owning_pid"]
+        ),
+        LogSource::Events(_) => format!("{} event {} at {}", text(&item["source"]), item["event_id"], text(&item["timestamp"])),
+    }
+}
+
+fn array(value: &Value) -> &[Value] {
+    value.as_array().map(Vec::as_slice).unwrap_or_default()
+}
+
+fn text(value: &Value) -> &str {
+    value.as_str().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn rule(text: &str) -> DetectionRule {
+        DetectionRule::parse(&yaml::parse_documents(text).unwrap()[0]).unwrap()
+    }
+
+    #[test]
+    fn test_evaluate_rules() {
+        let scan = json!({"artifacts": {
+            "running_processes": [
+                {"pid": 4, "parent_pid": 0, "name": "explorer.exe", "executable_path": "C:\\Windows\\explorer.exe", "command_line": "explorer.exe"},
+                {"pid": 7, "parent_pid": 4, "name": "powershell.exe", "executable_path": "C:\\Windows\\System32\\WindowsPowerShell\\v1.0\\powershell.exe",
+                 "command_line": "powershell -NoP -EncodedCommand SQBFAFgA"}
+            ],
+            "network_connections": [{"protocol": "TCP", "local_address": "10.0.0.5", "local_port": 50000, "remote_address": "203.0.113.9", "remote_port": 4444, "owning_pid": 7}],
+            "event_logs": {"security": [{"event_id": 4688, "source": "Security", "timestamp": "2024-01-01T00:00:00Z", "event_data": {"NewProcessName": "C:\\Users\\Public\\x.exe"}}],
+                           "system": [], "application": [], "channels": {}}
+        }});
+
+        let encoded = rule(r#"
+title: Encoded PowerShell from Explorer
+id: 1
+level: high
+tags: [attack.execution, attack.t1059.001]
+logsource:
+    category: process_creation
+detection:
+    selection_image:
+        Image|endswith: '\powershell.exe'
+    selection_args:
+        CommandLine|contains|all: [' -nop', '-encodedcommand']
+    filter:
+        ParentImage: '*\svchost.exe'
+    condition: all of selection_* and not filter
+"#);
+        let c2_port = rule("title: C2 port\nlogsource:\n    category: network_connection\ndetection:\n    selection:\n        DestinationPort: [4444, 8443]\n        Image|re: '(?i)powershell'\n    condition: selection");
+        let public_exe = rule("title: Public exe\nlevel: low\nlogsource:\n    service: security\ndetection:\n    selection:\n        EventID: 4688\n        NewProcessName|startswith: 'c:\\users\\public\\'\n    condition: selection");
+        let unmatched = rule("title: Unmatched\nlogsource:\n    product: windows\ndetection:\n    keywords:\n        - mimikatz\n    condition: keywords");
+
+        let detections = evaluate(&[encoded, c2_port, public_exe, unmatched], &scan);
+        assert_eq!(detections.len(), 3);
+        assert_eq!(detections[0].severity, "high");
+        assert_eq!(detections[0].mitre_techniques, vec!["T1059.001"]);
+        assert_eq!(detections[0].matches[0].reference, "/artifacts/running_processes/1");
+        assert_eq!(detections[1].matches[0].reference, "/artifacts/network_connections/0");
+        assert_eq!(detections[2].rule_name, "Public exe");
+        assert_eq!(detections[2].matches[0].reference, "/artifacts/event_logs/security/0");
+    }
+
+    #[test]
+    fn test_invalid_rules() {
+        let parse = |text: &str| DetectionRule::parse(&yaml::parse_documents(text).unwrap()[0]);
+        let base = "title: T\nlogsource:\n    category: process_creation\ndetection:\n    selection:\n        Image: x\n";
+        assert!(parse(&format!("{}    condition: selection or (not selection)", base)).is_ok());
+        assert!(parse(&format!("{}    condition: selection and other", base)).is_err());
+        assert!(parse(&format!("{}    condition: (selection", base)).is_err());
+        assert!(parse(&format!("{}    condition: 1 of filter*", base)).is_err());
+        assert!(parse(&base.replace("Image:", "Image|base64:").replace("x\n", "x\n    condition: selection\n")).is_err());
+        assert!(parse(&format!("{}    condition: selection\nlevel: severe", base)).is_err());
+
+        // The shipped example rules load
+        let examples = Path::new(env!("CARGO_MANIFEST_DIR")).join("../examples/detection-rules");
+        assert_eq!(load_rules(&examples).unwrap().len(), 3);
+    }
+}
@@ -0,0 +1,220 @@
+//! Rendering of a scan comparison
+//! A plain text listing for the terminal, and a single self-contained HTML
+//! page (inline CSS, no scripts) with one section per artifact category and
+//! the added, removed and changed artifacts color-coded, for containment
+//! verification after remediation.
+
+use crate::forensic_types::{ArtifactDiff, DiffEntry, ScanDiff};
+use serde_json::Value;
+use std::fmt::Write;
+
+const STYLE: &str = "body{font-family:Segoe UI,Arial,sans-serif;margin:2em;color:#222}\
+h1{font-size:1.5em}h2{font-size:1.2em;margin-top:1.5em;border-bottom:1px solid #ccc}\
+table{border-collapse:collapse;width:100%;margin:.5em 0}th,td{text-align:left;padding:4px 8px;border:1px solid #ddd;vertical-align:top}\
+th{background:#f3f3f3}td.key{font-family:Consolas,monospace;word-break:break-all}\
+tr.added td{background:#e6f4ea}tr.removed td{background:#fce8e6}tr.changed td{background:#fef7e0}\
+.badge{display:inline-block;min-width:2em;padding:0 6px;border-radius:3px;text-align:center;font-weight:bold}\
+.badge.added{background:#34a853;color:#fff}.badge.removed{background:#ea4335;color:#fff}.badge.changed{background:#fbbc04;color:#222}\
+p.none{color:#666;font-style:italic}";
+
+/// Render the comparison of `baseline` and `current` as an HTML document
+pub fn render_html(diff: &ScanDiff, baseline: &Value, current: &Value) -> String {
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>TriageIR scan comparison - {}</title>\n<style>{}</style>\n</head>\n<body>\n",
+        escape(text(&current["scan_metadata"]["hostname"])),
+        STYLE
+    );
+
+    html.push_str("<h1>Scan comparison</h1>\n<table>\n<tr><th></th><th>Scan ID</th><th>Host</th><th>Started (UTC)</th></tr>\n");
+    for (label, scan) in [("Baseline", baseline), ("Current", current)] {
+        let metadata = &scan["scan_metadata"];
+        let _ = writeln!(
+            html,
+            "<tr><th>{}</th><td>{}</td><td>{}</td><td>{}</td></tr>",
+            label,
+            escape(text(&metadata["scan_id"])),
+            escape(text(&metadata["hostname"])),
+            escape(text(&metadata["scan_start_utc"]))
+        );
+    }
+    html.push_str("</table>\n");
+
+    let baseline_host = text(&baseline["scan_metadata"]["hostname"]);
+    let current_host = text(&current["scan_metadata"]["hostname"]);
+    if !baseline_host.eq_ignore_ascii_case(current_host) {
+        let _ = writeln!(
+            html,
+            "<p><strong>Warning:</strong> the scans are from different hosts ({} and {}).</p>",
+            escape(baseline_host),
+            escape(current_host)
+        );
+    }
+
+    html.push_str("<h2>Summary</h2>\n<table>\n<tr><th>Category</th><th>Added</th><th>Removed</th><th>Changed</th></tr>\n");
+    for category in &diff.categories {
+        let _ = writeln!(
+            html,
+            "<tr><td><a href=\"#{0}\">{0}</a></td><td>{1}</td><td>{2}</td><td>{3}</td></tr>",
+            escape(&category.category),
+            badge("added", category.added.len()),
+            badge("removed", category.removed.len()),
+            badge("changed", category.changed.len())
+        );
+    }
+    html.push_str("</table>\n");
+
+    for category in &diff.categories {
+        render_category(&mut html, category);
+    }
+
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+/// Render the comparison as plain text for the terminal: a count line per
+/// category followed by its entries, marked +, - and ~
+pub fn render_text(diff: &ScanDiff, baseline: &Value, current: &Value) -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "Comparing scan {} ({}) with {} ({}) of {}",
+        diff.baseline_scan_id,
+        text(&baseline["scan_metadata"]["scan_start_utc"]),
+        diff.current_scan_id,
+        text(&current["scan_metadata"]["scan_start_utc"]),
+        text(&current["scan_metadata"]["hostname"])
+    );
+    let baseline_host = text(&baseline["scan_metadata"]["hostname"]);
+    if !baseline_host.eq_ignore_ascii_case(text(&current["scan_metadata"]["hostname"])) {
+        let _ = writeln!(out, "Warning: the baseline is from a different host ({})", baseline_host);
+    }
+
+    for category in &diff.categories {
+        let _ = writeln!(
+            out,
+            "\n{}: {} added, {} removed, {} changed",
+            category.category,
+            category.added.len(),
+            category.removed.len(),
+            category.changed.len()
+        );
+        for (marker, entries) in [('+', &category.added), ('-', &category.removed), ('~', &category.changed)] {
+            for entry in entries {
+                let _ = writeln!(out, "  {} {}  {}", marker, entry.key, entry.description);
+            }
+        }
+    }
+    out
+}
+
+fn render_category(html: &mut String, category: &ArtifactDiff) {
+    let _ = writeln!(html, "<h2 id=\"{0}\">{0}</h2>", escape(&category.category));
+
+    if category.added.is_empty() && category.removed.is_empty() && category.changed.is_empty() {
+        html.push_str("<p class=\"none\">No differences</p>\n");
+        return;
+    }
+
+    html.push_str("<table>\n<tr><th>Status</th><th>Artifact</th><th>Details</th></tr>\n");
+    for (status, entries) in [("added", &category.added), ("removed", &category.removed), ("changed", &category.changed)] {
+        for entry in entries {
+            render_entry(html, status, entry);
+        }
+    }
+    html.push_str("</table>\n");
+}
+
+fn render_entry(html: &mut String, status: &str, entry: &DiffEntry) {
+    let _ = writeln!(
+        html,
+        "<tr class=\"{0}\"><td>{0}</td><td class=\"key\">{1}</td><td>{2}</td></tr>",
+        status,
+        escape(&entry.key),
+        escape(&entry.description)
+    );
+}
+
+fn badge(status: &str, count: usize) -> String {
+    if count == 0 {
+        "0".to_string()
+    } else {
+        format!("<span class=\"badge {}\">{}</span>", status, count)
+    }
+}
+
+pub(crate) fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn text(value: &Value) -> &str {
+    value.as_str().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_render_html() {
+        let baseline = json!({ "scan_metadata": { "scan_id": "base", "hostname": "WKS01" } });
+        let current = json!({ "scan_metadata": { "scan_id": "after", "hostname": "WKS01" } });
+        let diff = ScanDiff {
+            baseline_scan_id: "base".to_string(),
+            current_scan_id: "after".to_string(),
+            categories: vec![
+                ArtifactDiff {
+                    category: "persistence_mechanisms".to_string(),
+                    removed: vec![DiffEntry { key: "run|hklm|updater".to_string(), description: "Registry Run Key <Updater>".to_string() }],
+                    ..Default::default()
+                },
+                ArtifactDiff { category: "loaded_drivers".to_string(), ..Default::default() },
+            ],
+        };
+
+        let html = render_html(&diff, &baseline, &current);
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<tr class=\"removed\"><td>removed</td><td class=\"key\">run|hklm|updater</td><td>Registry Run Key &lt;Updater&gt;</td></tr>"));
+        assert!(html.contains("<span class=\"badge removed\">1</span>"));
+        assert!(html.contains("<h2 id=\"loaded_drivers\">loaded_drivers</h2>\n<p class=\"none\">No differences</p>"));
+        assert!(!html.contains("different hosts"));
+        assert!(!html.contains("<Updater>"));
+    }
+
+    #[test]
+    fn test_render_text() {
+        let baseline = json!({ "scan_metadata": { "scan_id": "base", "hostname": "WKS01", "scan_start_utc": "2026-10-01T08:00:00Z" } });
+        let current = json!({ "scan_metadata": { "scan_id": "after", "hostname": "WKS02", "scan_start_utc": "2026-10-16T08:00:00Z" } });
+        let diff = ScanDiff {
+            baseline_scan_id: "base".to_string(),
+            current_scan_id: "after".to_string(),
+            categories: vec![ArtifactDiff {
+                category: "services".to_string(),
+                changed: vec![DiffEntry { key: "windows service|spooler".to_string(), description: "Windows Service Spooler: sha256 aa -> bb".to_string() }],
+                ..Default::default()
+            }],
+        };
+
+        let text = render_text(&diff, &baseline, &current);
+        assert!(text.starts_with("Comparing scan base (2026-10-01T08:00:00Z) with after (2026-10-16T08:00:00Z) of WKS02\nWarning: the baseline is from a different host (WKS01)\n"));
+        assert!(text.contains("\nservices: 0 added, 0 removed, 1 changed\n  ~ windows service|spooler  Windows Service Spooler: sha256 aa -> bb\n"));
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("<script>alert('x') & \"y\"</script>"), "&lt;script&gt;alert(&#39;x&#39;) &amp; &quot;y&quot;&lt;/script&gt;");
+    }
+}
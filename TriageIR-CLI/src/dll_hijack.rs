@@ -0,0 +1,238 @@
+//! DLL search-order hijack detection
+//! The loader searches the application directory before System32 for DLLs that
+//! are not KnownDLLs, so a DLL dropped next to an executable (or in a writable
+//! directory the process loads from) under the name of a system DLL is loaded
+//! instead of it. For every running process, non-system DLLs loaded from the
+//! executable's directory or from user-writable paths are reported when a DLL
+//! of the same name exists in System32. Applications that ship their own copy
+//! of a system DLL show up as well; the hashes tell the copies apart.
+
+// Candidate matching is only reachable through the Windows module enumeration
+#![cfg_attr(not(windows), allow(dead_code))]
+
+use crate::forensic_types::{AuditEntry, DllHijack};
+use crate::path_norm;
+use crate::processes::calculate_file_hash;
+use crate::types::Process;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+#[cfg(windows)]
+use windows::{
+    Win32::Foundation::{CloseHandle, HMODULE},
+    Win32::System::ProcessStatus::{EnumProcessModulesEx, GetModuleFileNameExW, LIST_MODULES_ALL},
+    Win32::System::Threading::{OpenProcess, PROCESS_QUERY_INFORMATION, PROCESS_VM_READ},
+};
+
+const COMPONENT: &str = "dll_hijack";
+
+/// Path fragments of user-writable locations DLLs should not be loaded from
+const WRITABLE_PATHS: [&str; 8] = [
+    "\\appdata\\", "\\temp\\", "\\tmp\\", "\\downloads\\", "\\desktop\\", "\\users\\public\\", "\\programdata\\", "\\$recycle.bin\\",
+];
+
+/// Upper bound on the module handles read per process
+#[cfg(windows)]
+const MAX_MODULES: usize = 4096;
+
+/// Check the loaded modules of every running process for search-order hijack candidates
+#[cfg(windows)]
+pub fn detect_dll_hijacks(processes: &[Process]) -> (Vec<DllHijack>, Vec<AuditEntry>) {
+    let start_time = std::time::Instant::now();
+    let mut audit_log = vec![AuditEntry::new(COMPONENT, "INFO", "start_detection", "Checking loaded DLLs for search-order hijacks".to_string(), "started")];
+    let system_dir = system_directory();
+    let mut hashes = HashMap::new();
+    let mut hijacks = Vec::new();
+    let mut checked = 0;
+    let mut denied = 0;
+
+    for process in processes.iter().filter(|p| p.has_executable_path()) {
+        // Flagged processes already carry their loader list
+        let module_paths = if process.loaded_modules.is_empty() {
+            match module_paths(process.pid) {
+                Ok(paths) => paths,
+                Err(_) => {
+                    denied += 1;
+                    continue;
+                }
+            }
+        } else {
+            process.loaded_modules.iter().map(|m| m.file_path.clone()).collect()
+        };
+        checked += 1;
+        hijacks.extend(process_hijacks(process, &module_paths, &system_dir, &mut hashes));
+    }
+
+    for hijack in &hijacks {
+        audit_log.push(AuditEntry::new(
+            COMPONENT,
+            "WARN",
+            "hijack_candidate",
+            format!("{} (PID {}) loaded {} instead of {} ({})", hijack.process_name, hijack.pid, hijack.hijack_dll, hijack.target_dll, hijack.search_path),
+            "flagged",
+        ));
+    }
+    if denied > 0 {
+        audit_log.push(AuditEntry::new(COMPONENT, "DEBUG", "enumerate_modules", format!("Modules of {} processes could not be read (access denied or exited)", denied), "partial"));
+    }
+
+    audit_log.push(AuditEntry {
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        level: "INFO".to_string(),
+        component: "dll_hijack".to_string(),
+        action: "complete_detection".to_string(),
+        details: format!("Checked the modules of {} processes, {} hijack candidates", checked, hijacks.len()),
+        duration_ms: Some(start_time.elapsed().as_millis() as u64),
+        result: "success".to_string(),
+    });
+
+    (hijacks, audit_log)
+}
+
+#[cfg(not(windows))]
+pub fn detect_dll_hijacks(_processes: &[Process]) -> (Vec<DllHijack>, Vec<AuditEntry>) {
+    let audit_log = vec![
+        AuditEntry::new(COMPONENT, "INFO", "start_detection", "Checking loaded DLLs for search-order hijacks".to_string(), "started"),
+        AuditEntry::new(COMPONENT, "WARN", "enumerate_modules", "Module enumeration requires Windows".to_string(), "unsupported"),
+        AuditEntry::new(COMPONENT, "INFO", "complete_detection", "Checked the modules of 0 processes, 0 hijack candidates".to_string(), "success"),
+    ];
+    (Vec::new(), audit_log)
+}
+
+/// Hijack candidates among the modules of one process; hashes are cached per path
+fn process_hijacks(
+    process: &Process,
+    module_paths: &[String],
+    system_dir: &Path,
+    hashes: &mut HashMap<String, Option<String>>,
+) -> Vec<DllHijack> {
+    let mut hijacks = Vec::new();
+    for module_path in module_paths {
+        let Some(search_path) = hijack_search_path(&process.executable_path, module_path) else {
+            continue;
+        };
+        let dll_name = module_path.rsplit(['\\', '/']).next().unwrap_or_default();
+        let target = system_dir.join(dll_name);
+        if !target.is_file() {
+            continue;
+        }
+
+        let target_dll = target.to_string_lossy().to_string();
+        let mut hash = |path: &str| hashes.entry(path.to_lowercase()).or_insert_with(|| calculate_file_hash(path).ok()).clone();
+        hijacks.push(DllHijack {
+            hijack_sha256: hash(module_path),
+            target_sha256: hash(&target_dll),
+            target_dll,
+            hijack_dll: module_path.clone(),
+            search_path: search_path.to_string(),
+            process_name: process.name.clone(),
+            pid: process.pid,
+            executable_path: process.executable_path.clone(),
+        });
+    }
+    hijacks
+}
+
+/// Where a module was picked up from when it would shadow a system DLL: the
+/// executable's own directory or a user-writable path. Modules below the
+/// Windows directory (System32, SysWOW64, WinSxS) are never candidates.
+fn hijack_search_path(executable_path: &str, module_path: &str) -> Option<&'static str> {
+    let module = path_norm::normalize(module_path);
+    if !module.ends_with(".dll") || module.contains(":\\windows\\") || module.starts_with("\\systemroot\\") {
+        return None;
+    }
+
+    let parent = |path: &str| path.rsplit_once('\\').map(|(dir, _)| dir.to_string());
+    let executable = path_norm::normalize(executable_path);
+    if parent(&module).is_some() && parent(&module) == parent(&executable) {
+        Some("application_directory")
+    } else if WRITABLE_PATHS.iter().any(|fragment| module.contains(fragment)) {
+        Some("writable_path")
+    } else {
+        None
+    }
+}
+
+fn system_directory() -> PathBuf {
+    let root = std::env::var("SystemRoot").unwrap_or_else(|_| "C:\\Windows".to_string());
+    Path::new(&root).join("System32")
+}
+
+/// Full paths of the modules loaded in a process, 32-bit modules of WOW64 processes included
+#[cfg(windows)]
+fn module_paths(pid: u32) -> Result<Vec<String>, String> {
+    unsafe {
+        let process = OpenProcess(PROCESS_QUERY_INFORMATION | PROCESS_VM_READ, false, pid)
+            .map_err(|e| format!("OpenProcess failed: {}", e))?;
+
+        let mut modules = vec![HMODULE::default(); MAX_MODULES];
+        let mut needed = 0u32;
+        let result = EnumProcessModulesEx(
+            process,
+            modules.as_mut_ptr(),
+            (modules.len() * std::mem::size_of::<HMODULE>()) as u32,
+            &mut needed,
+            LIST_MODULES_ALL,
+        );
+
+        let mut paths = Vec::new();
+        if result.is_ok() {
+            let count = (needed as usize / std::mem::size_of::<HMODULE>()).min(modules.len());
+            for module in &modules[..count] {
+                let mut buffer = [0u16; 1024];
+                let len = GetModuleFileNameExW(process, *module, &mut buffer) as usize;
+                if len > 0 {
+                    paths.push(String::from_utf16_lossy(&buffer[..len]));
+                }
+            }
+        }
+        let _ = CloseHandle(process);
+
+        result.map_err(|e| format!("EnumProcessModulesEx failed: {}", e))?;
+        Ok(paths)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hijack_search_path() {
+        let exe = "C:\\Program Files\\Vendor\\app.exe";
+        assert_eq!(hijack_search_path(exe, "C:\\Program Files\\Vendor\\version.dll"), Some("application_directory"));
+        assert_eq!(hijack_search_path(exe, "C:\\Users\\bob\\AppData\\Local\\Temp\\dbghelp.dll"), Some("writable_path"));
+        assert_eq!(hijack_search_path(exe, "C:\\Windows\\System32\\version.dll"), None);
+        assert_eq!(hijack_search_path(exe, "C:\\Windows\\WinSxS\\amd64_x\\comctl32.dll"), None);
+        assert_eq!(hijack_search_path(exe, "C:\\Program Files\\Common Files\\shared.dll"), None);
+        assert_eq!(hijack_search_path(exe, "C:\\Program Files\\Vendor\\app.exe"), None);
+    }
+
+    #[test]
+    fn test_process_hijacks() {
+        let system_dir = tempfile::tempdir().unwrap();
+        std::fs::write(system_dir.path().join("version.dll"), b"system copy").unwrap();
+
+        let process = Process::new(
+            4242,
+            1,
+            "app.exe".to_string(),
+            String::new(),
+            "C:\\Program Files\\Vendor\\app.exe".to_string(),
+        );
+        let modules = vec![
+            "C:\\Program Files\\Vendor\\app.exe".to_string(),
+            "C:\\Program Files\\Vendor\\version.dll".to_string(),
+            "C:\\Program Files\\Vendor\\vendor.dll".to_string(),
+            "C:\\Windows\\System32\\kernel32.dll".to_string(),
+        ];
+
+        let hijacks = process_hijacks(&process, &modules, system_dir.path(), &mut HashMap::new());
+        assert_eq!(hijacks.len(), 1);
+        assert_eq!(hijacks[0].hijack_dll, "C:\\Program Files\\Vendor\\version.dll");
+        assert_eq!(hijacks[0].search_path, "application_directory");
+        assert_eq!(hijacks[0].pid, 4242);
+        assert_eq!(hijacks[0].hijack_sha256, None); // Not a file on this host
+        assert_eq!(hijacks[0].target_sha256.as_deref(), Some(calculate_file_hash(&hijacks[0].target_dll).unwrap().as_str()));
+    }
+}
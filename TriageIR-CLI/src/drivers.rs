@@ -0,0 +1,453 @@
+//! Loaded kernel driver enumeration
+//! Lists the drivers mapped into kernel space with their base address, image
+//! size, SHA-256 and Authenticode status (embedded or catalog signature).
+//! Unsigned drivers and drivers loaded from user-writable locations are a
+//! common rootkit and BYOVD indicator and are flagged.
+
+// Path and indicator helpers are only reachable through the Windows driver APIs
+#![cfg_attr(not(windows), allow(dead_code))]
+
+use crate::forensic_types::{AuditEntry, FileHashes, KernelDriverInfo};
+use crate::hashing;
+use crate::path_norm;
+#[cfg(windows)]
+use std::path::Path;
+
+const COMPONENT: &str = "drivers";
+
+/// Directories a standard user can write to; drivers should never load from here
+const USER_WRITABLE_DIRECTORIES: &[&str] = &[
+    "\\windows\\temp\\",
+    "\\temp\\",
+    "\\tmp\\",
+    "\\users\\",
+    "\\programdata\\",
+    "\\$recycle.bin\\",
+];
+
+/// Enumerate the kernel drivers loaded on the live system
+#[cfg(windows)]
+pub fn collect_loaded_drivers() -> (Vec<KernelDriverInfo>, Vec<AuditEntry>) {
+    let mut drivers = Vec::new();
+    let mut audit_log = Vec::new();
+
+    let start_time = std::time::Instant::now();
+
+    audit_log.push(AuditEntry::new(COMPONENT, "INFO", "start_collection", "Starting kernel driver enumeration".to_string(), "started"));
+
+    let loaded_images = match windows_api::query_system_modules() {
+        Ok(images) => images,
+        Err(e) => {
+            audit_log.push(AuditEntry::new(
+                COMPONENT,
+                "WARN",
+                "query_system_modules",
+                format!("NtQuerySystemInformation(SystemModuleInformation) failed: {}; falling back to EnumDeviceDrivers", e),
+                "fallback",
+            ));
+            match windows_api::enum_device_drivers() {
+                Ok(images) => images,
+                Err(e) => {
+                    audit_log.push(AuditEntry::new(COMPONENT, "ERROR", "enum_device_drivers", format!("EnumDeviceDrivers failed: {}", e), "error"));
+                    Vec::new()
+                }
+            }
+        }
+    };
+
+    let system_root = std::env::var("SystemRoot").unwrap_or_else(|_| "C:\\Windows".to_string());
+    let mut unreadable = 0;
+
+    for image in loaded_images {
+        let path = normalize_driver_path(&image.raw_path, &system_root);
+        let file_exists = Path::new(&path).is_file();
+
+        let hashes = if file_exists {
+            calculate_file_hashes(&path).map_err(|_| unreadable += 1).ok()
+        } else {
+            None
+        };
+        let sha256 = hashes.as_ref().and_then(|h| h.sha256.clone()).unwrap_or_default();
+        let signature_status = if file_exists { windows_api::signature_status(&path) } else { "unknown".to_string() };
+        let suspicious_indicators = driver_indicators(&path, &signature_status, file_exists);
+
+        drivers.push(KernelDriverInfo {
+            name: driver_name(&path),
+            path,
+            base_address: format!("0x{:016X}", image.base_address),
+            size: image.size,
+            sha256,
+            signature_status,
+            suspicious_indicators,
+            hashes,
+        });
+    }
+
+    if unreadable > 0 {
+        audit_log.push(AuditEntry::new(COMPONENT, "WARN", "hash_drivers", format!("Could not read {} driver images for hashing", unreadable), "partial"));
+    }
+    for driver in drivers.iter().filter(|d| !d.suspicious_indicators.is_empty()) {
+        audit_log.push(AuditEntry::new(
+            COMPONENT,
+            "WARN",
+            "suspicious_driver",
+            format!("{} ({}): {}", driver.name, driver.path, driver.suspicious_indicators.join("; ")),
+            "flagged",
+        ));
+    }
+
+    let duration = start_time.elapsed();
+    audit_log.push(AuditEntry {
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        level: "INFO".to_string(),
+        component: "drivers".to_string(),
+        action: "complete_collection".to_string(),
+        details: format!("Collected {} loaded kernel drivers", drivers.len()),
+        duration_ms: Some(duration.as_millis() as u64),
+        result: "success".to_string(),
+    });
+
+    (drivers, audit_log)
+}
+
+#[cfg(not(windows))]
+pub fn collect_loaded_drivers() -> (Vec<KernelDriverInfo>, Vec<AuditEntry>) {
+    let audit_log = vec![
+        AuditEntry::new(COMPONENT, "INFO", "start_collection", "Starting kernel driver enumeration".to_string(), "started"),
+        AuditEntry::new(COMPONENT, "WARN", "enum_device_drivers", "Kernel driver enumeration requires Windows".to_string(), "unsupported"),
+        AuditEntry::new(COMPONENT, "INFO", "complete_collection", "Collected 0 loaded kernel drivers".to_string(), "success"),
+    ];
+    (Vec::new(), audit_log)
+}
+
+/// Convert the NT paths reported for kernel images into Win32 paths
+pub(crate) fn normalize_driver_path(raw_path: &str, system_root: &str) -> String {
+    let system_root = system_root.trim_end_matches('\\');
+    let lower = raw_path.to_lowercase();
+
+    if lower.starts_with("\\systemroot\\") {
+        format!("{}{}", system_root, &raw_path["\\SystemRoot".len()..])
+    } else if lower.starts_with("\\??\\") {
+        raw_path[4..].to_string()
+    } else if lower.starts_with("\\windows\\") {
+        // Drive-relative path on the system volume
+        let drive = system_root.get(..2).unwrap_or("C:");
+        format!("{}{}", drive, raw_path)
+    } else if lower.starts_with("system32\\") {
+        format!("{}\\{}", system_root, raw_path)
+    } else {
+        raw_path.to_string()
+    }
+}
+
+fn driver_name(path: &str) -> String {
+    path.rsplit(['\\', '/']).next().unwrap_or(path).to_string()
+}
+
+fn driver_indicators(path: &str, signature_status: &str, file_exists: bool) -> Vec<String> {
+    let mut indicators = Vec::new();
+    let lower = path_norm::normalize(path);
+
+    if !file_exists {
+        indicators.push("missing_image: driver file not found on disk".to_string());
+    }
+    match signature_status {
+        "unsigned" => indicators.push("unsigned: no embedded or catalog signature".to_string()),
+        "invalid" => indicators.push("invalid_signature: signature does not verify".to_string()),
+        _ => {}
+    }
+    if let Some(directory) = USER_WRITABLE_DIRECTORIES.iter().find(|dir| lower.contains(*dir)) {
+        indicators.push(format!("user_writable_path: loaded from a {} directory", directory.trim_matches('\\')));
+    }
+
+    indicators
+}
+
+/// Authenticode status of an image file: signed, catalog_signed, unsigned, invalid or unknown
+#[cfg(windows)]
+pub(crate) fn file_signature_status(path: &str) -> String {
+    windows_api::signature_status(path)
+}
+
+#[cfg(not(windows))]
+pub(crate) fn file_signature_status(_path: &str) -> String {
+    "unknown".to_string()
+}
+
+/// Hash a driver image through the shared hashing service
+fn calculate_file_hashes(path: &str) -> Result<FileHashes, String> {
+    hashing::shared().hash_file(path)
+}
+
+#[cfg(windows)]
+mod windows_api {
+    use crate::footprint::{self, Access};
+    use std::ffi::c_void;
+    use std::mem::size_of;
+    use std::path::Path;
+    use windows::core::{w, GUID, PCWSTR};
+    use windows::Wdk::System::SystemInformation::{NtQuerySystemInformation, SYSTEM_INFORMATION_CLASS};
+    use windows::Win32::Foundation::{CloseHandle, HANDLE, HWND};
+    use windows::Win32::Security::Cryptography::Catalog::{
+        CryptCATAdminAcquireContext2, CryptCATAdminCalcHashFromFileHandle2, CryptCATAdminEnumCatalogFromHash,
+        CryptCATAdminReleaseCatalogContext, CryptCATAdminReleaseContext, CryptCATCatalogInfoFromContext, CATALOG_INFO,
+    };
+    use windows::Win32::Security::WinTrust::{
+        WinVerifyTrust, WINTRUST_ACTION_GENERIC_VERIFY_V2, WINTRUST_CATALOG_INFO, WINTRUST_DATA, WINTRUST_DATA_0,
+        WINTRUST_FILE_INFO, WTD_CHOICE_CATALOG, WTD_CHOICE_FILE, WTD_REVOKE_NONE, WTD_STATEACTION_CLOSE,
+        WTD_STATEACTION_VERIFY, WTD_UI_NONE,
+    };
+    use windows::Win32::Storage::FileSystem::{
+        CreateFileW, FILE_FLAGS_AND_ATTRIBUTES, FILE_GENERIC_READ, FILE_SHARE_READ, FILE_SHARE_WRITE, OPEN_EXISTING,
+    };
+    use windows::Win32::System::ProcessStatus::{EnumDeviceDrivers, GetDeviceDriverFileNameW};
+
+    const SYSTEM_MODULE_INFORMATION: SYSTEM_INFORMATION_CLASS = SYSTEM_INFORMATION_CLASS(11);
+    const STATUS_INFO_LENGTH_MISMATCH: i32 = 0xC000_0004_u32 as i32;
+    const TRUST_E_NOSIGNATURE: i32 = 0x800B_0100_u32 as i32;
+    const TRUST_E_SUBJECT_FORM_UNKNOWN: i32 = 0x800B_0003_u32 as i32;
+    const TRUST_E_PROVIDER_UNKNOWN: i32 = 0x800B_0001_u32 as i32;
+
+    /// RTL_PROCESS_MODULE_INFORMATION (not exposed by the windows crate)
+    #[repr(C)]
+    struct RtlProcessModuleInformation {
+        section: HANDLE,
+        mapped_base: *mut c_void,
+        image_base: *mut c_void,
+        image_size: u32,
+        flags: u32,
+        load_order_index: u16,
+        init_order_index: u16,
+        load_count: u16,
+        offset_to_file_name: u16,
+        full_path_name: [u8; 256],
+    }
+
+    pub struct LoadedImage {
+        pub raw_path: String,
+        pub base_address: u64,
+        pub size: u64,
+    }
+
+    /// Loaded kernel images with their sizes, via SystemModuleInformation
+    pub fn query_system_modules() -> Result<Vec<LoadedImage>, String> {
+        let mut buffer: Vec<u64> = vec![0; 8192];
+        loop {
+            let buffer_bytes = (buffer.len() * size_of::<u64>()) as u32;
+            let mut needed = 0u32;
+            let status = unsafe {
+                NtQuerySystemInformation(SYSTEM_MODULE_INFORMATION, buffer.as_mut_ptr() as *mut c_void, buffer_bytes, &mut needed)
+            };
+            if status.0 == STATUS_INFO_LENGTH_MISMATCH {
+                buffer = vec![0; needed as usize / size_of::<u64>() + 1024];
+                continue;
+            }
+            if status.0 < 0 {
+                return Err(format!("NTSTATUS 0x{:08X}", status.0 as u32));
+            }
+            break;
+        }
+
+        // RTL_PROCESS_MODULES: ULONG NumberOfModules followed by the module array
+        let base = buffer.as_ptr() as *const u8;
+        let count = unsafe { *(base as *const u32) } as usize;
+        let modules = unsafe { base.add(size_of::<usize>()) } as *const RtlProcessModuleInformation;
+
+        let images = (0..count)
+            .map(|i| {
+                let module = unsafe { &*modules.add(i) };
+                let end = module.full_path_name.iter().position(|&b| b == 0).unwrap_or(module.full_path_name.len());
+                LoadedImage {
+                    raw_path: String::from_utf8_lossy(&module.full_path_name[..end]).to_string(),
+                    base_address: module.image_base as u64,
+                    size: module.image_size as u64,
+                }
+            })
+            .collect();
+        Ok(images)
+    }
+
+    /// Loaded driver base addresses and paths, via EnumDeviceDrivers (no sizes)
+    pub fn enum_device_drivers() -> Result<Vec<LoadedImage>, String> {
+        let mut bases: Vec<*mut c_void> = vec![std::ptr::null_mut(); 1024];
+        let mut needed = 0u32;
+        loop {
+            let buffer_bytes = (bases.len() * size_of::<*mut c_void>()) as u32;
+            unsafe { EnumDeviceDrivers(bases.as_mut_ptr(), buffer_bytes, &mut needed) }.map_err(|e| e.to_string())?;
+            if needed <= buffer_bytes {
+                break;
+            }
+            bases = vec![std::ptr::null_mut(); needed as usize / size_of::<*mut c_void>() + 64];
+        }
+        bases.truncate(needed as usize / size_of::<*mut c_void>());
+
+        let images = bases
+            .into_iter()
+            .map(|base| {
+                let mut name = [0u16; 1024];
+                let len = unsafe { GetDeviceDriverFileNameW(base, &mut name) } as usize;
+                LoadedImage {
+                    raw_path: String::from_utf16_lossy(&name[..len]),
+                    base_address: base as u64,
+                    size: 0,
+                }
+            })
+            .collect();
+        Ok(images)
+    }
+
+    /// Authenticode status of a driver image: embedded signature first, then the system catalogs
+    pub fn signature_status(path: &str) -> String {
+        footprint::record_file(Path::new(path), Access::Read);
+        let wide_path: Vec<u16> = path.encode_utf16().chain(std::iter::once(0)).collect();
+
+        let mut file_info = WINTRUST_FILE_INFO {
+            cbStruct: size_of::<WINTRUST_FILE_INFO>() as u32,
+            pcwszFilePath: PCWSTR(wide_path.as_ptr()),
+            hFile: HANDLE::default(),
+            pgKnownSubject: std::ptr::null_mut(),
+        };
+        let mut data = trust_data(WTD_CHOICE_FILE, WINTRUST_DATA_0 { pFile: &mut file_info });
+
+        match verify_trust(&mut data) {
+            0 => "signed".to_string(),
+            TRUST_E_NOSIGNATURE | TRUST_E_SUBJECT_FORM_UNKNOWN | TRUST_E_PROVIDER_UNKNOWN => {
+                match catalog_signature_status(&wide_path) {
+                    Some(true) => "catalog_signed".to_string(),
+                    Some(false) => "invalid".to_string(),
+                    None => "unsigned".to_string(),
+                }
+            }
+            _ => "invalid".to_string(),
+        }
+    }
+
+    /// Some(valid) when the file hash is listed in a system catalog, None when no catalog lists it
+    fn catalog_signature_status(wide_path: &[u16]) -> Option<bool> {
+        let file = unsafe {
+            CreateFileW(
+                PCWSTR(wide_path.as_ptr()),
+                FILE_GENERIC_READ.0,
+                FILE_SHARE_READ | FILE_SHARE_WRITE,
+                None,
+                OPEN_EXISTING,
+                FILE_FLAGS_AND_ATTRIBUTES(0),
+                HANDLE::default(),
+            )
+        }
+        .ok()?;
+
+        // Windows 10+ catalogs use SHA-256; older catalogs use SHA-1
+        let result = [w!("SHA256"), w!("SHA1")].into_iter().find_map(|algorithm| lookup_catalog(file, wide_path, algorithm));
+
+        unsafe {
+            let _ = CloseHandle(file);
+        }
+        result
+    }
+
+    fn lookup_catalog(file: HANDLE, wide_path: &[u16], algorithm: PCWSTR) -> Option<bool> {
+        let mut cat_admin = 0isize;
+        unsafe { CryptCATAdminAcquireContext2(&mut cat_admin, None, algorithm, None, 0) }.ok()?;
+
+        let mut hash_len = 0u32;
+        let mut hash = Vec::new();
+        let hashed = unsafe {
+            let _ = CryptCATAdminCalcHashFromFileHandle2(cat_admin, file, &mut hash_len, None, 0);
+            hash.resize(hash_len as usize, 0);
+            CryptCATAdminCalcHashFromFileHandle2(cat_admin, file, &mut hash_len, Some(hash.as_mut_ptr()), 0).is_ok()
+        };
+
+        let cat_info = if hashed { unsafe { CryptCATAdminEnumCatalogFromHash(cat_admin, &hash, 0, None) } } else { 0 };
+        let result = if cat_info == 0 {
+            None
+        } else {
+            let mut catalog = CATALOG_INFO { cbStruct: size_of::<CATALOG_INFO>() as u32, wszCatalogFile: [0; 260] };
+            let verified = unsafe { CryptCATCatalogInfoFromContext(cat_info, &mut catalog, 0) }.is_ok() && {
+                let member_tag: Vec<u16> = hex::encode_upper(&hash).encode_utf16().chain(std::iter::once(0)).collect();
+                let mut info = WINTRUST_CATALOG_INFO {
+                    cbStruct: size_of::<WINTRUST_CATALOG_INFO>() as u32,
+                    pcwszCatalogFilePath: PCWSTR(catalog.wszCatalogFile.as_ptr()),
+                    pcwszMemberTag: PCWSTR(member_tag.as_ptr()),
+                    pcwszMemberFilePath: PCWSTR(wide_path.as_ptr()),
+                    hMemberFile: file,
+                    pbCalculatedFileHash: hash.as_mut_ptr(),
+                    cbCalculatedFileHash: hash.len() as u32,
+                    hCatAdmin: cat_admin,
+                    ..Default::default()
+                };
+                let mut data = trust_data(WTD_CHOICE_CATALOG, WINTRUST_DATA_0 { pCatalog: &mut info });
+                verify_trust(&mut data) == 0
+            };
+            unsafe {
+                let _ = CryptCATAdminReleaseCatalogContext(cat_admin, cat_info, 0);
+            }
+            Some(verified)
+        };
+
+        unsafe {
+            let _ = CryptCATAdminReleaseContext(cat_admin, 0);
+        }
+        result
+    }
+
+    fn trust_data(
+        choice: windows::Win32::Security::WinTrust::WINTRUST_DATA_UNION_CHOICE,
+        subject: WINTRUST_DATA_0,
+    ) -> WINTRUST_DATA {
+        WINTRUST_DATA {
+            cbStruct: size_of::<WINTRUST_DATA>() as u32,
+            dwUIChoice: WTD_UI_NONE,
+            fdwRevocationChecks: WTD_REVOKE_NONE,
+            dwUnionChoice: choice,
+            Anonymous: subject,
+            dwStateAction: WTD_STATEACTION_VERIFY,
+            ..Default::default()
+        }
+    }
+
+    fn verify_trust(data: &mut WINTRUST_DATA) -> i32 {
+        let mut action: GUID = WINTRUST_ACTION_GENERIC_VERIFY_V2;
+        let status = unsafe { WinVerifyTrust(HWND::default(), &mut action, data as *mut WINTRUST_DATA as *mut c_void) };
+        data.dwStateAction = WTD_STATEACTION_CLOSE;
+        unsafe {
+            WinVerifyTrust(HWND::default(), &mut action, data as *mut WINTRUST_DATA as *mut c_void);
+        }
+        status
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_driver_path() {
+        let root = "C:\\Windows";
+        assert_eq!(normalize_driver_path("\\SystemRoot\\system32\\drivers\\ACPI.sys", root), "C:\\Windows\\system32\\drivers\\ACPI.sys");
+        assert_eq!(normalize_driver_path("\\??\\C:\\Users\\bob\\AppData\\Local\\Temp\\x.sys", root), "C:\\Users\\bob\\AppData\\Local\\Temp\\x.sys");
+        assert_eq!(normalize_driver_path("\\Windows\\System32\\drivers\\tcpip.sys", root), "C:\\Windows\\System32\\drivers\\tcpip.sys");
+        assert_eq!(normalize_driver_path("System32\\drivers\\ntfs.sys", "D:\\Windows\\"), "D:\\Windows\\System32\\drivers\\ntfs.sys");
+        assert_eq!(driver_name("C:\\Windows\\system32\\drivers\\ACPI.sys"), "ACPI.sys");
+    }
+
+    #[test]
+    fn test_driver_indicators() {
+        assert!(driver_indicators("C:\\Windows\\System32\\drivers\\ACPI.sys", "catalog_signed", true).is_empty());
+
+        let indicators = driver_indicators("C:\\Users\\bob\\AppData\\Local\\Temp\\rtcore64.sys", "unsigned", true);
+        assert_eq!(indicators.len(), 2);
+        assert!(indicators[0].starts_with("unsigned"));
+        assert!(indicators[1].starts_with("user_writable_path"));
+
+        let indicators = driver_indicators("C:\\Windows\\Temp\\x.sys", "invalid", false);
+        assert!(indicators[0].starts_with("missing_image"));
+        assert!(indicators[1].starts_with("invalid_signature"));
+        assert_eq!(indicators[2], "user_writable_path: loaded from a windows\\temp directory");
+
+        // Forward slashes, variables and NT prefixes do not hide the location
+        for variant in ["C:/Windows/Temp/x.sys", "\\??\\C:\\Windows\\System32\\..\\Temp\\x.sys", "%SystemRoot%\\Temp\\x.sys"] {
+            assert_eq!(driver_indicators(variant, "signed", true).len(), 1, "{}", variant);
+        }
+    }
+}
@@ -0,0 +1,524 @@
+//! Threat intelligence enrichment (`--enrich`)
+//! Attaches geolocation, AS information and known-bad verdicts to the public
+//! remote addresses of network connections and to process executable hashes
+//! before the results are serialized. Sources are providers behind one trait:
+//! offline indicator feeds (CSV or a MISP JSON export), local MaxMind
+//! databases, and VirusTotal REST lookups when `virustotal` is listed. Answers
+//! are merged in the order the sources are given: the first geolocation, the
+//! first AS and the first malicious verdict win.
+
+use crate::forensic_types::{AsnInfo, AuditEntry, FileHashes, GeoLocation, ThreatIntel};
+use crate::http_client;
+use crate::mmdb::MaxMindDb;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+const COMPONENT: &str = "enrichment";
+
+/// Environment variable holding the VirusTotal API key
+pub const VIRUSTOTAL_KEY_VARIABLE: &str = "VT_API_KEY";
+
+const VIRUSTOTAL_API: &str = "https://www.virustotal.com/api/v3";
+
+/// REST lookups per scan at most; the public VirusTotal API allows 500 a day
+const MAX_REST_LOOKUPS: usize = 50;
+
+const REST_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// MISP attribute types holding addresses or file hashes; `ip` is the second half of domain|ip
+const MISP_INDICATOR_TYPES: [&str; 6] = ["ip-src", "ip-dst", "md5", "sha1", "sha256", "ip"];
+
+/// What a provider knows about an address
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IpEnrichment {
+    pub geolocation: Option<GeoLocation>,
+    pub asn: Option<AsnInfo>,
+    pub threat: Option<ThreatIntel>,
+}
+
+impl IpEnrichment {
+    fn merge(&mut self, other: IpEnrichment) {
+        if self.geolocation.is_none() {
+            self.geolocation = other.geolocation;
+        }
+        if self.asn.is_none() {
+            self.asn = other.asn;
+        }
+        if !self.threat.as_ref().is_some_and(|threat| threat.is_malicious) && other.threat.is_some() {
+            self.threat = other.threat;
+        }
+    }
+}
+
+/// Source of enrichment data; lookups a provider cannot answer return Ok(None)
+pub trait EnrichmentProvider: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn lookup_ip(&self, _address: IpAddr) -> Result<Option<IpEnrichment>, String> {
+        Ok(None)
+    }
+
+    fn lookup_hash(&self, _hash: &str) -> Result<Option<ThreatIntel>, String> {
+        Ok(None)
+    }
+}
+
+/// Providers of one scan, with a cache so every address and hash is looked up once
+pub struct Enricher {
+    providers: Vec<Box<dyn EnrichmentProvider>>,
+    ip_cache: Mutex<HashMap<IpAddr, IpEnrichment>>,
+    hash_cache: Mutex<HashMap<String, Option<ThreatIntel>>>,
+    audit_log: Mutex<Vec<AuditEntry>>,
+}
+
+impl Enricher {
+    pub fn new(providers: Vec<Box<dyn EnrichmentProvider>>) -> Self {
+        Enricher { providers, ip_cache: Mutex::new(HashMap::new()), hash_cache: Mutex::new(HashMap::new()), audit_log: Mutex::new(Vec::new()) }
+    }
+
+    /// Providers from `--enrich` sources: csv:PATH, misp:PATH, mmdb:PATH or virustotal
+    pub fn from_sources(sources: &[String]) -> Result<Self, String> {
+        let mut providers: Vec<Box<dyn EnrichmentProvider>> = Vec::new();
+        for source in sources.iter().map(|source| source.trim()).filter(|source| !source.is_empty()) {
+            let (kind, path) = source.split_once(':').unwrap_or((source, ""));
+            let provider: Box<dyn EnrichmentProvider> = match kind.to_ascii_lowercase().as_str() {
+                "csv" => Box::new(IndicatorFeed::load_csv(Path::new(path))?),
+                "misp" => Box::new(IndicatorFeed::load_misp(Path::new(path))?),
+                "mmdb" => Box::new(MaxMindProvider::open(Path::new(path))?),
+                "virustotal" => Box::new(VirusTotalProvider::from_environment()?),
+                _ => return Err(format!("unknown enrichment source {} (csv:PATH, misp:PATH, mmdb:PATH or virustotal)", source)),
+            };
+            providers.push(provider);
+        }
+        if providers.is_empty() {
+            return Err("no enrichment sources given".to_string());
+        }
+        Ok(Enricher::new(providers))
+    }
+
+    /// Enrichment of a remote address; private, loopback and link-local addresses are not looked up
+    pub fn enrich_ip(&self, address: &str) -> IpEnrichment {
+        let Some(address) = parse_address(address).filter(is_public) else {
+            return IpEnrichment::default();
+        };
+        if let Some(cached) = self.ip_cache.lock().unwrap().get(&address) {
+            return cached.clone();
+        }
+        let mut enrichment = IpEnrichment::default();
+        for provider in &self.providers {
+            match provider.lookup_ip(address) {
+                Ok(Some(answer)) => enrichment.merge(answer),
+                Ok(None) => {}
+                Err(e) => self.record_failure(provider.name(), &address.to_string(), e),
+            }
+        }
+        self.ip_cache.lock().unwrap().insert(address, enrichment.clone());
+        enrichment
+    }
+
+    /// Verdict on the digests of a file; the first malicious answer, else the first answer
+    pub fn enrich_hashes(&self, sha256: &str, hashes: Option<&FileHashes>) -> Option<ThreatIntel> {
+        let mut digests = vec![sha256.to_lowercase()];
+        if let Some(hashes) = hashes {
+            digests.extend([&hashes.sha256, &hashes.sha1, &hashes.md5].into_iter().flatten().map(|digest| digest.to_lowercase()));
+        }
+        digests.retain(|digest| is_hex_digest(digest));
+        digests.dedup();
+
+        let mut verdict: Option<ThreatIntel> = None;
+        for digest in digests {
+            let answer = self.enrich_hash(&digest);
+            if answer.as_ref().is_some_and(|threat| threat.is_malicious) {
+                return answer;
+            }
+            verdict = verdict.or(answer);
+        }
+        verdict
+    }
+
+    fn enrich_hash(&self, digest: &str) -> Option<ThreatIntel> {
+        if let Some(cached) = self.hash_cache.lock().unwrap().get(digest) {
+            return cached.clone();
+        }
+        let mut verdict: Option<ThreatIntel> = None;
+        for provider in &self.providers {
+            match provider.lookup_hash(digest) {
+                Ok(Some(answer)) if answer.is_malicious => {
+                    verdict = Some(answer);
+                    break;
+                }
+                Ok(answer) => verdict = verdict.or(answer),
+                Err(e) => self.record_failure(provider.name(), digest, e),
+            }
+        }
+        self.hash_cache.lock().unwrap().insert(digest.to_string(), verdict.clone());
+        verdict
+    }
+
+    fn record_failure(&self, provider: &str, indicator: &str, error: String) {
+        self.audit_log.lock().unwrap().push(AuditEntry::new(COMPONENT, "WARN", "lookup", format!("{} lookup of {} failed: {}", provider, indicator, error), "error"));
+    }
+
+    /// Forget the answers and failures of earlier lookups; the loaded feeds and
+    /// databases stay open for the next scan
+    pub fn clear_lookups(&self) {
+        self.ip_cache.lock().unwrap().clear();
+        self.hash_cache.lock().unwrap().clear();
+        self.audit_log.lock().unwrap().clear();
+    }
+
+    /// Failed lookups and a summary of the enrichment, for the collection log
+    pub fn take_audit_log(&self) -> Vec<AuditEntry> {
+        let mut audit_log = std::mem::take(&mut *self.audit_log.lock().unwrap());
+        let addresses = self.ip_cache.lock().unwrap();
+        let hashes = self.hash_cache.lock().unwrap();
+        let malicious = addresses.values().filter_map(|answer| answer.threat.as_ref()).chain(hashes.values().flatten()).filter(|threat| threat.is_malicious).count();
+        audit_log.push(AuditEntry::new(
+            COMPONENT,
+            "INFO",
+            "complete_enrichment",
+            format!(
+                "Enriched {} addresses and {} hashes from {} ({} known-bad)",
+                addresses.len(),
+                hashes.len(),
+                self.providers.iter().map(|provider| provider.name()).collect::<Vec<_>>().join(", "),
+                malicious
+            ),
+            "success",
+        ));
+        audit_log
+    }
+}
+
+/// Known-bad addresses and hashes from an offline feed
+pub struct IndicatorFeed {
+    name: String,
+    indicators: HashMap<String, ThreatIntel>,
+}
+
+impl IndicatorFeed {
+    /// CSV with a header row: indicator,threat_type,confidence,source,last_seen,tags
+    /// (tags separated by semicolons; only indicator is required)
+    pub fn load_csv(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("Failed to read indicator feed {}: {}", path.display(), e))?;
+        let name = feed_name(path);
+        Self::parse_csv(&name, &text).map_err(|e| format!("Invalid indicator feed {}: {}", path.display(), e))
+    }
+
+    fn parse_csv(name: &str, text: &str) -> Result<Self, String> {
+        let mut lines = text.lines().filter(|line| !line.trim().is_empty() && !line.starts_with('#'));
+        let header: Vec<String> = split_csv_line(lines.next().ok_or("empty file")?).iter().map(|column| column.to_lowercase()).collect();
+        let column = |name: &str| header.iter().position(|column| column == name);
+        let indicator_column = column("indicator").ok_or("no indicator column")?;
+        let (threat_type, confidence, source, last_seen, tags) = (column("threat_type"), column("confidence"), column("source"), column("last_seen"), column("tags"));
+
+        let mut indicators = HashMap::new();
+        for line in lines {
+            let fields = split_csv_line(line);
+            let field = |index: Option<usize>| index.and_then(|index| fields.get(index)).map(|value| value.trim()).filter(|value| !value.is_empty());
+            let Some(indicator) = field(Some(indicator_column)) else { continue };
+            indicators.insert(
+                indicator.to_lowercase(),
+                ThreatIntel {
+                    is_malicious: true,
+                    threat_type: field(threat_type).unwrap_or("known-bad").to_string(),
+                    confidence: field(confidence).and_then(|value| value.parse().ok()).unwrap_or(1.0),
+                    source: field(source).map_or_else(|| name.to_string(), |source| format!("{} ({})", name, source)),
+                    last_seen: field(last_seen).unwrap_or_default().to_string(),
+                    tags: field(tags).map(|tags| tags.split(';').map(|tag| tag.trim().to_string()).filter(|tag| !tag.is_empty()).collect()).unwrap_or_default(),
+                },
+            );
+        }
+        Ok(IndicatorFeed { name: name.to_string(), indicators })
+    }
+
+    /// MISP JSON export: an event, a restSearch list of events or a list of attributes
+    pub fn load_misp(path: &Path) -> Result<Self, String> {
+        let document: Value = std::fs::read(path)
+            .map_err(|e| format!("Failed to read MISP export {}: {}", path.display(), e))
+            .and_then(|data| serde_json::from_slice(&data).map_err(|e| format!("Invalid MISP export {}: {}", path.display(), e)))?;
+        Ok(Self::parse_misp(&feed_name(path), &document))
+    }
+
+    fn parse_misp(name: &str, document: &Value) -> Self {
+        let mut indicators = HashMap::new();
+        let events: Vec<&Value> = match &document["response"] {
+            Value::Array(items) => items.iter().map(|item| &item["Event"]).collect(),
+            _ => vec![&document["Event"]],
+        };
+        let mut add_attributes = |attributes: &Value, event_info: &str, event_tags: &[String]| {
+            for attribute in attributes.as_array().map(Vec::as_slice).unwrap_or_default() {
+                let types = attribute["type"].as_str().unwrap_or_default().split('|');
+                let values = attribute["value"].as_str().unwrap_or_default().split('|');
+                for (_, value) in types.zip(values).filter(|(attribute_type, _)| MISP_INDICATOR_TYPES.contains(attribute_type)) {
+                    let mut tags = event_tags.to_vec();
+                    tags.extend(misp_tags(&attribute["Tag"]));
+                    indicators.insert(
+                        value.trim().to_lowercase(),
+                        ThreatIntel {
+                            is_malicious: attribute["to_ids"].as_bool().unwrap_or(true),
+                            threat_type: [event_info, attribute["category"].as_str().unwrap_or_default()].into_iter().find(|text| !text.is_empty()).unwrap_or("misp").to_string(),
+                            confidence: 1.0,
+                            source: name.to_string(),
+                            last_seen: attribute["timestamp"]
+                                .as_str()
+                                .and_then(|timestamp| timestamp.parse::<i64>().ok())
+                                .and_then(|timestamp| chrono::DateTime::from_timestamp(timestamp, 0))
+                                .map(|time| time.to_rfc3339())
+                                .unwrap_or_default(),
+                            tags,
+                        },
+                    );
+                }
+            }
+        };
+        for event in events {
+            let info = event["info"].as_str().unwrap_or_default();
+            let tags = misp_tags(&event["Tag"]);
+            add_attributes(&event["Attribute"], info, &tags);
+            for object in event["Object"].as_array().map(Vec::as_slice).unwrap_or_default() {
+                add_attributes(&object["Attribute"], info, &tags);
+            }
+        }
+        add_attributes(&document["response"]["Attribute"], "", &[]);
+        IndicatorFeed { name: name.to_string(), indicators }
+    }
+}
+
+impl EnrichmentProvider for IndicatorFeed {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn lookup_ip(&self, address: IpAddr) -> Result<Option<IpEnrichment>, String> {
+        Ok(self.indicators.get(&address.to_string()).map(|threat| IpEnrichment { threat: Some(threat.clone()), ..Default::default() }))
+    }
+
+    fn lookup_hash(&self, hash: &str) -> Result<Option<ThreatIntel>, String> {
+        Ok(self.indicators.get(hash).cloned())
+    }
+}
+
+/// Geolocation and AS from a local GeoLite2/GeoIP2 database
+pub struct MaxMindProvider {
+    name: String,
+    database: MaxMindDb,
+}
+
+impl MaxMindProvider {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let database = MaxMindDb::open(path)?;
+        let name = if database.database_type.is_empty() { feed_name(path) } else { database.database_type.clone() };
+        Ok(MaxMindProvider { name, database })
+    }
+}
+
+impl EnrichmentProvider for MaxMindProvider {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn lookup_ip(&self, address: IpAddr) -> Result<Option<IpEnrichment>, String> {
+        Ok(self.database.lookup(address)?.map(|record| maxmind_enrichment(&record)))
+    }
+}
+
+/// City, Country, ASN and ISP records share their field names
+fn maxmind_enrichment(record: &Value) -> IpEnrichment {
+    let name = |value: &Value| value["names"]["en"].as_str().unwrap_or_default().to_string();
+    let organization = record["autonomous_system_organization"].as_str().or(record["organization"].as_str()).unwrap_or_default();
+    let geolocation = (record["country"].is_object() || record["city"].is_object()).then(|| GeoLocation {
+        country: name(&record["country"]),
+        region: name(&record["subdivisions"][0]),
+        city: name(&record["city"]),
+        latitude: record["location"]["latitude"].as_f64().unwrap_or_default(),
+        longitude: record["location"]["longitude"].as_f64().unwrap_or_default(),
+        isp: record["isp"].as_str().unwrap_or_default().to_string(),
+        organization: organization.to_string(),
+    });
+    let asn = record["autonomous_system_number"].as_u64().map(|number| AsnInfo { number: number as u32, organization: organization.to_string() });
+    IpEnrichment { geolocation, asn, threat: None }
+}
+
+/// VirusTotal v3 file and IP address reports
+pub struct VirusTotalProvider {
+    api_key: String,
+    lookups: AtomicUsize,
+}
+
+impl VirusTotalProvider {
+    pub fn from_environment() -> Result<Self, String> {
+        let api_key = std::env::var(VIRUSTOTAL_KEY_VARIABLE).ok().filter(|key| !key.is_empty());
+        let api_key = api_key.ok_or_else(|| format!("the virustotal source needs an API key in {}", VIRUSTOTAL_KEY_VARIABLE))?;
+        Ok(VirusTotalProvider { api_key, lookups: AtomicUsize::new(0) })
+    }
+
+    /// Report attributes, None when VirusTotal does not know the object
+    fn report(&self, object: &str) -> Result<Option<Value>, String> {
+        if self.lookups.fetch_add(1, Ordering::Relaxed) >= MAX_REST_LOOKUPS {
+            return Err(format!("lookup limit of {} per scan reached", MAX_REST_LOOKUPS));
+        }
+        let headers = vec![("x-apikey".to_string(), self.api_key.clone()), ("Accept".to_string(), "application/json".to_string())];
+        let response = http_client::send("GET", &format!("{}/{}", VIRUSTOTAL_API, object), &headers, &[], REST_TIMEOUT)?;
+        match response.status {
+            404 => Ok(None),
+            429 => {
+                // Quota exhausted: no point in asking again during this scan
+                self.lookups.store(MAX_REST_LOOKUPS, Ordering::Relaxed);
+                Err("quota exceeded (HTTP 429)".to_string())
+            }
+            _ if response.is_success() => {
+                let report: Value = serde_json::from_slice(&response.body).map_err(|e| format!("invalid response: {}", e))?;
+                Ok(Some(report["data"]["attributes"].clone()))
+            }
+            status => Err(format!("HTTP {}: {}", status, response.body_excerpt())),
+        }
+    }
+}
+
+impl EnrichmentProvider for VirusTotalProvider {
+    fn name(&self) -> &str {
+        "virustotal"
+    }
+
+    fn lookup_ip(&self, address: IpAddr) -> Result<Option<IpEnrichment>, String> {
+        Ok(self.report(&format!("ip_addresses/{}", address))?.map(|attributes| {
+            let organization = attributes["as_owner"].as_str().unwrap_or_default().to_string();
+            IpEnrichment {
+                geolocation: attributes["country"].as_str().map(|country| GeoLocation {
+                    country: country.to_string(),
+                    region: String::new(),
+                    city: String::new(),
+                    latitude: 0.0,
+                    longitude: 0.0,
+                    isp: String::new(),
+                    organization: organization.clone(),
+                }),
+                asn: attributes["asn"].as_u64().map(|number| AsnInfo { number: number as u32, organization }),
+                threat: Some(virustotal_verdict(&attributes)),
+            }
+        }))
+    }
+
+    fn lookup_hash(&self, hash: &str) -> Result<Option<ThreatIntel>, String> {
+        Ok(self.report(&format!("files/{}", hash))?.map(|attributes| virustotal_verdict(&attributes)))
+    }
+}
+
+/// Verdict from the engine counts of a VirusTotal report
+fn virustotal_verdict(attributes: &Value) -> ThreatIntel {
+    let stats = &attributes["last_analysis_stats"];
+    let count = |name: &str| stats[name].as_u64().unwrap_or_default();
+    let detections = count("malicious") + count("suspicious");
+    let total = detections + count("harmless") + count("undetected");
+    ThreatIntel {
+        is_malicious: count("malicious") > 0,
+        threat_type: attributes["popular_threat_classification"]["suggested_threat_label"].as_str().unwrap_or(if detections > 0 { "malicious" } else { "clean" }).to_string(),
+        confidence: if total == 0 { 0.0 } else { detections as f32 / total as f32 },
+        source: "virustotal".to_string(),
+        last_seen: attributes["last_analysis_date"].as_i64().and_then(|date| chrono::DateTime::from_timestamp(date, 0)).map(|date| date.to_rfc3339()).unwrap_or_default(),
+        tags: attributes["tags"].as_array().map(|tags| tags.iter().filter_map(|tag| tag.as_str().map(str::to_string)).collect()).unwrap_or_default(),
+    }
+}
+
+fn misp_tags(tags: &Value) -> Vec<String> {
+    tags.as_array().map(|tags| tags.iter().filter_map(|tag| tag["name"].as_str().map(str::to_string)).collect()).unwrap_or_default()
+}
+
+fn feed_name(path: &Path) -> String {
+    path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_else(|| path.display().to_string())
+}
+
+/// Fields of a CSV line; double quotes group commas and "" is a literal quote
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                fields.last_mut().unwrap().push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(String::new()),
+            _ => fields.last_mut().unwrap().push(c),
+        }
+    }
+    fields
+}
+
+/// Address of a connection endpoint, without IPv6 brackets or a zone index
+fn parse_address(address: &str) -> Option<IpAddr> {
+    let address = address.trim().trim_start_matches('[').trim_end_matches(']');
+    address.split('%').next().unwrap_or_default().parse().ok()
+}
+
+/// Globally routable addresses only; the rest cannot be geolocated or attributed
+fn is_public(address: &IpAddr) -> bool {
+    match address {
+        IpAddr::V4(v4) => !(v4.is_private() || v4.is_loopback() || v4.is_link_local() || v4.is_unspecified() || v4.is_broadcast() || v4.is_multicast() || v4.octets()[0] == 100 && (v4.octets()[1] & 0xC0) == 64),
+        IpAddr::V6(v6) => {
+            let first = v6.segments()[0];
+            !(v6.is_loopback() || v6.is_unspecified() || v6.is_multicast() || (first & 0xFE00) == 0xFC00 || (first & 0xFFC0) == 0xFE80)
+        }
+    }
+}
+
+fn is_hex_digest(digest: &str) -> bool {
+    matches!(digest.len(), 32 | 40 | 64) && digest.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mmdb;
+
+    const BAD_HASH: &str = "44d88612fea8a8f36de82e1278abb02f";
+
+    #[test]
+    fn test_indicator_feeds() {
+        let csv = IndicatorFeed::parse_csv("intel.csv", "indicator,threat_type,tags\n203.0.113.7,c2,\"cobalt strike;apt\"\n44D88612FEA8A8F36DE82E1278ABB02F,eicar,\n").unwrap();
+        let misp = IndicatorFeed::parse_misp(
+            "misp.json",
+            &serde_json::json!({ "response": [{ "Event": {
+                "info": "Phishing wave", "Tag": [{ "name": "tlp:amber" }],
+                "Attribute": [{ "type": "ip-dst|port", "value": "198.51.100.9|443", "category": "Network activity", "to_ids": true, "timestamp": "1700000000" }]
+            }}]}),
+        );
+        let enricher = Enricher::new(vec![Box::new(csv), Box::new(misp)]);
+
+        let c2 = enricher.enrich_ip("203.0.113.7").threat.unwrap();
+        assert_eq!((c2.threat_type.as_str(), c2.tags.as_slice()), ("c2", ["cobalt strike".to_string(), "apt".to_string()].as_slice()));
+        let phishing = enricher.enrich_ip("198.51.100.9").threat.unwrap();
+        assert_eq!((phishing.threat_type.as_str(), phishing.source.as_str()), ("Phishing wave", "misp.json"));
+        assert_eq!(phishing.tags, ["tlp:amber"]);
+        assert_eq!(phishing.last_seen, "2023-11-14T22:13:20+00:00");
+
+        let hashes = FileHashes { md5: Some(BAD_HASH.to_string()), sha1: None, sha256: None, imphash: None, ssdeep: None };
+        assert_eq!(enricher.enrich_hashes("", Some(&hashes)).unwrap().threat_type, "eicar");
+        assert!(enricher.enrich_hashes(&"0".repeat(64), None).is_none());
+        // Private addresses are never looked up
+        assert_eq!(enricher.enrich_ip("10.0.0.5"), IpEnrichment::default());
+    }
+
+    #[test]
+    fn test_maxmind_enrichment() {
+        let database = MaxMindDb::from_bytes(mmdb::tests::sample_database(mmdb::tests::city_record())).unwrap();
+        let enricher = Enricher::new(vec![Box::new(MaxMindProvider { name: "Test-City".to_string(), database })]);
+
+        let enrichment = enricher.enrich_ip("8.8.8.8");
+        let geolocation = enrichment.geolocation.unwrap();
+        assert_eq!((geolocation.country.as_str(), geolocation.latitude), ("Germany", 52.52));
+        assert_eq!(enrichment.asn.unwrap().number, 64_500);
+        assert!(enricher.enrich_ip("[2001:4860::8888]").geolocation.is_none()); // IPv4 database
+
+        let summary = enricher.take_audit_log().pop().unwrap();
+        assert_eq!(summary.details, "Enriched 2 addresses and 0 hashes from Test-City (0 known-bad)");
+    }
+}
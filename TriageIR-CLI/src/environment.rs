@@ -0,0 +1,208 @@
+//! Host environment detection
+//! Windows containers and Server Core/Nano Server installations have no desktop
+//! shell: Explorer, the notification platform, Windows Search and browsers are
+//! absent, so their artifacts cannot exist and collecting them only produces
+//! errors. Inside a container the Prefetcher does not run either; program
+//! launches are prefetched by the container host. The environment is read from
+//! InstallationType of the SOFTWARE hive and the container markers of the
+//! SYSTEM hive (the ContainerType value and the Container Execution Agent service).
+
+// Container accounts are only checked on Windows
+#![cfg_attr(not(windows), allow(dead_code))]
+use crate::forensic_types::{AuditEntry, HostEnvironment};
+use crate::offline::OfflineRoot;
+
+#[cfg(windows)]
+use crate::footprint::TrackedKey;
+#[cfg(windows)]
+use crate::offline;
+#[cfg(windows)]
+use winreg::enums::HKEY_LOCAL_MACHINE;
+#[cfg(windows)]
+use winreg::RegKey;
+
+const COMPONENT: &str = "environment";
+
+/// Below the SOFTWARE hive
+#[cfg(windows)]
+const CURRENT_VERSION_KEY: &str = "Microsoft\\Windows NT\\CurrentVersion";
+
+/// Service that runs inside every Windows container (Container Execution Agent)
+#[cfg(windows)]
+const CONTAINER_SERVICE: &str = "cexecsvc";
+
+/// Accounts built into the Windows container base images
+const CONTAINER_ACCOUNTS: [&str; 2] = ["ContainerAdministrator", "ContainerUser"];
+
+/// Environment of the live system
+#[cfg(windows)]
+pub fn detect_environment() -> (HostEnvironment, Vec<AuditEntry>) {
+    let hklm = RegKey::predef(HKEY_LOCAL_MACHINE);
+    let installation_type = hklm
+        .open_tracked(format!("SOFTWARE\\{}", CURRENT_VERSION_KEY))
+        .and_then(|key| key.get_value::<String, _>("InstallationType"))
+        .ok();
+
+    let mut indicators = match hklm.open_tracked("SYSTEM\\CurrentControlSet") {
+        Ok(control_set) => container_markers(&control_set),
+        Err(_) => Vec::new(),
+    };
+    if let Some(user) = std::env::var("USERNAME").ok().filter(|user| is_container_account(user)) {
+        indicators.push(format!("running as {}", user));
+    }
+
+    let environment = classify(installation_type, indicators);
+    let audit_log = vec![environment_audit_entry(&environment)];
+    (environment, audit_log)
+}
+
+#[cfg(not(windows))]
+pub fn detect_environment() -> (HostEnvironment, Vec<AuditEntry>) {
+    environment_without_registry()
+}
+
+/// Environment of a mounted Windows volume, from its SOFTWARE and SYSTEM hives
+#[cfg(windows)]
+pub fn detect_environment_offline(root: &OfflineRoot) -> (HostEnvironment, Vec<AuditEntry>) {
+    let mut audit_log = Vec::new();
+
+    let installation_type = match root.load_hive("SOFTWARE") {
+        Ok(software_hive) => software_hive
+            .open_tracked(CURRENT_VERSION_KEY)
+            .and_then(|key| key.get_value::<String, _>("InstallationType"))
+            .ok(),
+        Err(e) => {
+            audit_log.push(AuditEntry::new(
+                COMPONENT,
+                "WARN",
+                "load_hive",
+                format!("Failed to load offline SOFTWARE hive {}: {}", root.hive_path("SOFTWARE").display(), e),
+                "error",
+            ));
+            None
+        }
+    };
+    let indicators = match root.load_hive("SYSTEM") {
+        Ok(system_hive) => system_hive
+            .open_tracked(offline::current_control_set(&system_hive))
+            .map(|control_set| container_markers(&control_set))
+            .unwrap_or_default(),
+        Err(e) => {
+            audit_log.push(AuditEntry::new(
+                COMPONENT,
+                "WARN",
+                "load_hive",
+                format!("Failed to load offline SYSTEM hive {}: {}", root.hive_path("SYSTEM").display(), e),
+                "error",
+            ));
+            Vec::new()
+        }
+    };
+
+    let environment = classify(installation_type, indicators);
+    audit_log.push(environment_audit_entry(&environment));
+    (environment, audit_log)
+}
+
+#[cfg(not(windows))]
+pub fn detect_environment_offline(_root: &OfflineRoot) -> (HostEnvironment, Vec<AuditEntry>) {
+    environment_without_registry()
+}
+
+/// Without the registry the environment is unknown and every collector runs
+#[cfg(not(windows))]
+fn environment_without_registry() -> (HostEnvironment, Vec<AuditEntry>) {
+    let environment = classify(None, Vec::new());
+    let audit_log = vec![
+        AuditEntry::new(COMPONENT, "WARN", "registry_access", "Environment detection requires the Windows registry".to_string(), "unsupported"),
+        environment_audit_entry(&environment),
+    ];
+    (environment, audit_log)
+}
+
+/// Container markers below a control set key
+#[cfg(windows)]
+fn container_markers(control_set: &RegKey) -> Vec<String> {
+    let mut indicators = Vec::new();
+    if let Ok(container_type) = control_set.open_tracked("Control").and_then(|control| control.get_value::<u32, _>("ContainerType")) {
+        indicators.push(format!("ContainerType is {}", container_type));
+    }
+    if control_set.open_tracked(format!("Services\\{}", CONTAINER_SERVICE)).is_ok() {
+        indicators.push(format!("{} service installed", CONTAINER_SERVICE));
+    }
+    indicators
+}
+
+fn is_container_account(user: &str) -> bool {
+    CONTAINER_ACCOUNTS.iter().any(|account| account.eq_ignore_ascii_case(user))
+}
+
+/// Container markers win over InstallationType, which container base images
+/// report as "Server Core" or "Nano Server"
+fn classify(installation_type: Option<String>, indicators: Vec<String>) -> HostEnvironment {
+    let environment_type = if !indicators.is_empty() {
+        "container"
+    } else {
+        match installation_type.as_deref() {
+            Some("Client") => "workstation",
+            Some("Server") => "server",
+            Some("Server Core") => "server_core",
+            Some("Nano Server") => "nano_server",
+            _ => "unknown",
+        }
+    };
+    HostEnvironment {
+        environment_type: environment_type.to_string(),
+        installation_type,
+        desktop_shell: matches!(environment_type, "workstation" | "server" | "unknown"),
+        indicators,
+    }
+}
+
+/// Skip reason for collectors of desktop shell artifacts, None if the host has a shell
+pub fn shell_skip_reason(environment: &HostEnvironment) -> Option<String> {
+    (!environment.desktop_shell).then(|| format!("no desktop shell ({})", environment.environment_type))
+}
+
+/// Skip reason for the Prefetch collector, None unless the host is a container
+pub fn prefetch_skip_reason(environment: &HostEnvironment) -> Option<String> {
+    (environment.environment_type == "container").then(|| "Prefetch is recorded by the container host".to_string())
+}
+
+fn environment_audit_entry(environment: &HostEnvironment) -> AuditEntry {
+    let mut details = format!("Host environment: {}", environment.environment_type);
+    if let Some(installation_type) = &environment.installation_type {
+        details.push_str(&format!(" (InstallationType {})", installation_type));
+    }
+    if !environment.indicators.is_empty() {
+        details.push_str(&format!("; {}", environment.indicators.join(", ")));
+    }
+    AuditEntry::new(COMPONENT, "INFO", "detect_environment", details, &environment.environment_type)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_environment() {
+        let desktop = classify(Some("Client".to_string()), Vec::new());
+        assert_eq!(desktop.environment_type, "workstation");
+        assert!(shell_skip_reason(&desktop).is_none());
+
+        let core = classify(Some("Server Core".to_string()), Vec::new());
+        assert_eq!(core.environment_type, "server_core");
+        assert_eq!(shell_skip_reason(&core).as_deref(), Some("no desktop shell (server_core)"));
+        assert!(prefetch_skip_reason(&core).is_none());
+
+        let container = classify(Some("Server Core".to_string()), vec!["cexecsvc service installed".to_string()]);
+        assert_eq!(container.environment_type, "container");
+        assert_eq!(container.installation_type.as_deref(), Some("Server Core"));
+        assert!(!container.desktop_shell);
+        assert!(prefetch_skip_reason(&container).is_some());
+
+        // Undetectable environments keep the full collection
+        assert!(classify(None, Vec::new()).desktop_shell);
+        assert!(is_container_account("containeradministrator"));
+    }
+}
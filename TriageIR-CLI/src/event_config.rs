@@ -0,0 +1,259 @@
+//! Event log collection profile
+//! Describes which channels to collect, the event IDs to include or exclude, the
+//! maximum number of entries per channel and an optional time window. Loaded from
+//! a TOML file with `--event-config`; the built-in profile is used otherwise.
+//!
+//! ```toml
+//! max_events = 1000
+//! window_hours = 72
+//!
+//! [[channel]]
+//! name = "Security"
+//! include = [4624, 4625, 4688]
+//!
+//! [[channel]]
+//! name = "Microsoft-Windows-Sysmon/Operational"
+//! exclude = [4, 16]
+//! max_events = 5000
+//! ```
+
+use crate::event_logs::{self, EventChannelQuery, DEFAULT_MAX_EVENTS};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct EventLogConfig {
+    /// Default maximum number of entries per channel
+    #[serde(default = "default_max_events")]
+    pub max_events: usize,
+    /// Default time window: only events from the last N hours
+    #[serde(default)]
+    pub window_hours: Option<u64>,
+    #[serde(default, rename = "channel")]
+    pub channels: Vec<ChannelConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ChannelConfig {
+    /// Channel name, e.g. `Security` or `Microsoft-Windows-PowerShell/Operational`
+    pub name: String,
+    /// Event IDs to collect (all events when empty)
+    #[serde(default)]
+    pub include: Vec<u32>,
+    /// Event IDs to drop
+    #[serde(default)]
+    pub exclude: Vec<u32>,
+    #[serde(default)]
+    pub max_events: Option<usize>,
+    #[serde(default)]
+    pub window_hours: Option<u64>,
+    /// Raw XPath filter; takes precedence over include/exclude/window_hours
+    #[serde(default)]
+    pub xpath: Option<String>,
+}
+
+fn default_max_events() -> usize {
+    DEFAULT_MAX_EVENTS
+}
+
+impl EventLogConfig {
+    /// Load and validate a profile from a TOML file
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read event config {}: {}", path.display(), e))?;
+        Self::parse(&text).map_err(|e| format!("Invalid event config {}: {}", path.display(), e))
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let config: EventLogConfig = toml::from_str(text).map_err(|e| e.to_string())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Built-in profile: the important event IDs of the Security, System and
+    /// Application logs plus Sysmon, PowerShell, Defender and Task Scheduler
+    pub fn builtin() -> Self {
+        EventLogConfig {
+            max_events: DEFAULT_MAX_EVENTS,
+            window_hours: None,
+            channels: vec![
+                ChannelConfig::including("Security", sorted_ids(&event_logs::get_security_event_filter())),
+                ChannelConfig::including("System", sorted_ids(&event_logs::get_system_event_filter())),
+                ChannelConfig::including("Application", sorted_ids(&event_logs::get_application_event_filter())),
+                ChannelConfig::including(event_logs::SYSMON_CHANNEL, Vec::new()),
+                ChannelConfig::including(event_logs::POWERSHELL_CHANNEL, vec![4103, 4104]),
+                ChannelConfig::including(
+                    event_logs::DEFENDER_CHANNEL,
+                    vec![1006, 1007, 1008, 1015, 1116, 1117, 1118, 1119, 5001, 5004, 5007, 5010, 5012],
+                ),
+                ChannelConfig::including(event_logs::TASK_SCHEDULER_CHANNEL, vec![106, 140, 141, 200, 201]),
+            ],
+        }
+    }
+
+    /// Channel queries for the event log collector
+    pub fn channel_queries(&self) -> Vec<EventChannelQuery> {
+        self.channels
+            .iter()
+            .map(|channel| EventChannelQuery {
+                channel: channel.name.clone(),
+                xpath: channel.xpath(self.window_hours),
+                max_events: channel.max_events.unwrap_or(self.max_events),
+            })
+            .collect()
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.channels.is_empty() {
+            return Err("no [[channel]] entries".to_string());
+        }
+        if self.max_events == 0 {
+            return Err("max_events must be greater than 0".to_string());
+        }
+        for channel in &self.channels {
+            if channel.name.trim().is_empty() {
+                return Err("channel name must not be empty".to_string());
+            }
+            if channel.max_events == Some(0) {
+                return Err(format!("{}: max_events must be greater than 0", channel.name));
+            }
+            if channel.xpath.as_deref().is_some_and(|xpath| xpath.trim().is_empty()) {
+                return Err(format!("{}: xpath must not be empty", channel.name));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ChannelConfig {
+    fn including(name: &str, include: Vec<u32>) -> Self {
+        ChannelConfig {
+            name: name.to_string(),
+            include,
+            exclude: Vec::new(),
+            max_events: None,
+            window_hours: None,
+            xpath: None,
+        }
+    }
+
+    /// XPath selecting the configured event IDs and time window, e.g.
+    /// `*[System[(EventID=4624 or EventID=4625) and TimeCreated[timediff(@SystemTime) <= 86400000]]]`
+    fn xpath(&self, default_window_hours: Option<u64>) -> String {
+        if let Some(xpath) = &self.xpath {
+            return xpath.clone();
+        }
+
+        let mut conditions = Vec::new();
+        if !self.include.is_empty() {
+            let ids: Vec<String> = self.include.iter().map(|id| format!("EventID={}", id)).collect();
+            conditions.push(format!("({})", ids.join(" or ")));
+        }
+        conditions.extend(self.exclude.iter().map(|id| format!("EventID!={}", id)));
+        if let Some(hours) = self.window_hours.or(default_window_hours) {
+            conditions.push(format!("TimeCreated[timediff(@SystemTime) <= {}]", hours * 3_600_000));
+        }
+
+        if conditions.is_empty() {
+            "*".to_string()
+        } else {
+            format!("*[System[{}]]", conditions.join(" and "))
+        }
+    }
+}
+
+fn sorted_ids(filter: &HashMap<u32, &'static str>) -> Vec<u32> {
+    let mut ids: Vec<u32> = filter.keys().copied().collect();
+    ids.sort_unstable();
+    ids
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_config() {
+        let config = EventLogConfig::parse(
+            r#"
+            max_events = 500
+            window_hours = 24
+
+            [[channel]]
+            name = "Security"
+            include = [4624, 4625]
+
+            [[channel]]
+            name = "Microsoft-Windows-Sysmon/Operational"
+            exclude = [4]
+            max_events = 5000
+            window_hours = 2
+
+            [[channel]]
+            name = "System"
+            xpath = "*[System[Level<=2]]"
+            "#,
+        )
+        .unwrap();
+
+        let queries = config.channel_queries();
+        assert_eq!(queries.len(), 3);
+        assert_eq!(
+            queries[0].xpath,
+            "*[System[(EventID=4624 or EventID=4625) and TimeCreated[timediff(@SystemTime) <= 86400000]]]"
+        );
+        assert_eq!(queries[0].max_events, 500);
+        assert_eq!(
+            queries[1].xpath,
+            "*[System[EventID!=4 and TimeCreated[timediff(@SystemTime) <= 7200000]]]"
+        );
+        assert_eq!(queries[1].max_events, 5000);
+        assert_eq!(queries[2].xpath, "*[System[Level<=2]]");
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        assert!(EventLogConfig::parse("max_events = 10").is_err()); // No channels
+        assert!(EventLogConfig::parse("[[channel]]\nname = \"\"").is_err());
+        assert!(EventLogConfig::parse("[[channel]]\nname = \"Security\"\nmax_events = 0").is_err());
+        assert!(EventLogConfig::parse("[[channel]]\nname = \"Security\"\nevent_ids = [1]").is_err()); // Unknown field
+    }
+
+    #[test]
+    fn test_builtin_profile() {
+        let queries = EventLogConfig::builtin().channel_queries();
+        let channels: Vec<&str> = queries.iter().map(|q| q.channel.as_str()).collect();
+
+        assert_eq!(
+            channels,
+            vec![
+                "Security",
+                "System",
+                "Application",
+                event_logs::SYSMON_CHANNEL,
+                event_logs::POWERSHELL_CHANNEL,
+                event_logs::DEFENDER_CHANNEL,
+                event_logs::TASK_SCHEDULER_CHANNEL,
+            ]
+        );
+        assert!(queries[0].xpath.contains("EventID=4624"));
+        assert!(queries[0].xpath.contains("EventID=4688"));
+        assert_eq!(queries[3].xpath, "*");
+        assert!(queries.iter().all(|q| q.max_events == DEFAULT_MAX_EVENTS));
+    }
+
+    #[test]
+    fn test_load_config_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("events.toml");
+        std::fs::write(&path, "[[channel]]\nname = \"Microsoft-Windows-PowerShell/Operational\"\ninclude = [4104]\n").unwrap();
+
+        let config = EventLogConfig::load(&path).unwrap();
+        assert_eq!(config.channel_queries()[0].xpath, "*[System[(EventID=4104)]]");
+        assert!(EventLogConfig::load(&temp_dir.path().join("missing.toml")).is_err());
+    }
+}
@@ -0,0 +1,381 @@
+//! Event log channel configuration capture
+//! Records each channel's enabled state, maximum size and retention policy
+//! (including disabled analytic/debug channels) from the registry, so missing
+//! or short-lived event evidence can be explained and logging improved.
+
+// Channel assessment helpers are only reachable through the Windows registry API
+#![cfg_attr(not(windows), allow(dead_code))]
+
+use crate::forensic_types::{EventLogChannelConfig, AuditEntry};
+use crate::event_logs::{DEFENDER_CHANNEL, POWERSHELL_CHANNEL, SYSMON_CHANNEL, TASK_SCHEDULER_CHANNEL};
+use crate::offline::OfflineRoot;
+
+#[cfg(windows)]
+use crate::footprint::TrackedKey;
+#[cfg(windows)]
+use crate::offline;
+#[cfg(windows)]
+use std::collections::BTreeMap;
+#[cfg(windows)]
+use winreg::enums::*;
+#[cfg(windows)]
+use winreg::RegKey;
+
+const COMPONENT: &str = "event_log_retention";
+
+const CHANNELS_KEY: &str = "Microsoft\\Windows\\CurrentVersion\\WINEVT\\Channels";
+const POLICY_KEY: &str = "Policies\\Microsoft\\Windows\\EventLog";
+
+/// Windows defaults when MaxSize is not configured
+const DEFAULT_CLASSIC_MAX_SIZE: u64 = 20 * 1024 * 1024;
+const DEFAULT_CHANNEL_MAX_SIZE: u64 = 1024 * 1024 + 4096;
+
+/// Channels whose configuration is assessed, with the recommended minimum size
+const KEY_CHANNELS: [(&str, u64); 7] = [
+    ("Security", 192 * 1024 * 1024),
+    ("System", 32 * 1024 * 1024),
+    ("Application", 32 * 1024 * 1024),
+    (POWERSHELL_CHANNEL, 32 * 1024 * 1024),
+    (SYSMON_CHANNEL, 64 * 1024 * 1024),
+    (TASK_SCHEDULER_CHANNEL, 16 * 1024 * 1024),
+    (DEFENDER_CHANNEL, 16 * 1024 * 1024),
+];
+
+/// Raw registry settings of a channel before defaults are applied
+#[derive(Debug, Default, Clone)]
+struct ChannelSettings {
+    enabled: Option<u32>,
+    channel_type: Option<u32>,
+    max_size: Option<u64>,
+    retention: Option<u32>,
+    auto_backup: Option<u32>,
+    log_file_path: Option<String>,
+    classic: bool,
+    policy_managed: bool,
+}
+
+/// Capture the event log channel configuration of the live system
+#[cfg(windows)]
+pub fn collect_channel_configuration() -> (Vec<EventLogChannelConfig>, Vec<AuditEntry>) {
+    let hklm = RegKey::predef(HKEY_LOCAL_MACHINE);
+    let software = hklm.open_tracked("SOFTWARE");
+    let eventlog = hklm.open_tracked("SYSTEM\\CurrentControlSet\\Services\\EventLog");
+
+    match software {
+        Ok(software) => collect_from_keys(&software, eventlog.ok().as_ref(), Vec::new()),
+        Err(e) => collect_from_keys_failed(format!("Failed to open HKLM\\SOFTWARE: {}", e)),
+    }
+}
+
+#[cfg(not(windows))]
+pub fn collect_channel_configuration() -> (Vec<EventLogChannelConfig>, Vec<AuditEntry>) {
+    collect_from_keys_failed("Event log channel configuration requires the Windows registry".to_string())
+}
+
+/// Capture the event log channel configuration from the hives of a mounted Windows volume
+#[cfg(windows)]
+pub fn collect_channel_configuration_offline(root: &OfflineRoot) -> (Vec<EventLogChannelConfig>, Vec<AuditEntry>) {
+    let mut warnings = Vec::new();
+    let eventlog = match root.load_hive("SYSTEM") {
+        Ok(system) => {
+            let control_set = offline::current_control_set(&system);
+            system.open_tracked(format!("{}\\Services\\EventLog", control_set)).ok()
+        }
+        Err(e) => {
+            warnings.push(format!("Failed to load offline SYSTEM hive: {}", e));
+            None
+        }
+    };
+
+    match root.load_hive("SOFTWARE") {
+        Ok(software) => collect_from_keys(&software, eventlog.as_ref(), warnings),
+        Err(e) => collect_from_keys_failed(format!("Failed to load offline SOFTWARE hive: {}", e)),
+    }
+}
+
+#[cfg(not(windows))]
+pub fn collect_channel_configuration_offline(_root: &OfflineRoot) -> (Vec<EventLogChannelConfig>, Vec<AuditEntry>) {
+    collect_from_keys_failed("Cannot load offline SOFTWARE hive: registry hive loading requires Windows".to_string())
+}
+
+fn collect_from_keys_failed(error: String) -> (Vec<EventLogChannelConfig>, Vec<AuditEntry>) {
+    let audit_log = vec![
+        AuditEntry::new(COMPONENT, "INFO", "start_collection", "Starting event log channel configuration capture".to_string(), "started"),
+        AuditEntry::new(COMPONENT, "ERROR", "open_registry", error, "error"),
+    ];
+    (Vec::new(), audit_log)
+}
+
+/// `software` is the root of the SOFTWARE hive, `eventlog` the Services\EventLog key
+#[cfg(windows)]
+fn collect_from_keys(software: &RegKey, eventlog: Option<&RegKey>, warnings: Vec<String>) -> (Vec<EventLogChannelConfig>, Vec<AuditEntry>) {
+    let start_time = std::time::Instant::now();
+    let mut audit_log = vec![AuditEntry::new(
+        COMPONENT,
+        "INFO",
+        "start_collection",
+        "Starting event log channel configuration capture".to_string(),
+        "started",
+    )];
+    for warning in warnings {
+        audit_log.push(AuditEntry::new(COMPONENT, "WARN", "load_hive", warning, "error"));
+    }
+
+    let mut settings: BTreeMap<String, ChannelSettings> = BTreeMap::new();
+
+    match software.open_tracked(CHANNELS_KEY) {
+        Ok(channels_key) => {
+            for name in channels_key.enum_keys().filter_map(|k| k.ok()) {
+                if let Ok(channel_key) = channels_key.open_tracked(&name) {
+                    let entry = settings.entry(name).or_default();
+                    entry.enabled = channel_key.get_value::<u32, _>("Enabled").ok();
+                    entry.channel_type = channel_key.get_value::<u32, _>("Type").ok();
+                    read_size_and_retention(&channel_key, entry);
+                }
+            }
+        }
+        Err(e) => {
+            audit_log.push(AuditEntry::new(COMPONENT, "WARN", "open_channels", format!("WINEVT Channels key not readable: {}", e), "not_found"));
+        }
+    }
+
+    // Classic logs (Application, Security, System, ...) keep their settings under Services\EventLog
+    if let Some(eventlog) = eventlog {
+        for name in eventlog.enum_keys().filter_map(|k| k.ok()) {
+            if let Ok(log_key) = eventlog.open_tracked(&name) {
+                let entry = settings.entry(name).or_default();
+                entry.classic = true;
+                read_size_and_retention(&log_key, entry);
+            }
+        }
+    }
+
+    // Group Policy overrides: MaxSize in KB, Retention/AutoBackupLogFiles as "0"/"1" strings
+    if let Ok(policy_key) = software.open_tracked(POLICY_KEY) {
+        for name in policy_key.enum_keys().filter_map(|k| k.ok()) {
+            let Some(entry) = settings.get_mut(&name) else {
+                continue;
+            };
+            if let Ok(channel_policy) = policy_key.open_tracked(&name) {
+                if let Ok(max_size_kb) = channel_policy.get_value::<u32, _>("MaxSize") {
+                    entry.max_size = Some(max_size_kb as u64 * 1024);
+                    entry.policy_managed = true;
+                }
+                if let Some(retention) = policy_flag(&channel_policy, "Retention") {
+                    entry.retention = Some(if retention { u32::MAX } else { 0 });
+                    entry.policy_managed = true;
+                }
+                if let Some(auto_backup) = policy_flag(&channel_policy, "AutoBackupLogFiles") {
+                    entry.auto_backup = Some(auto_backup as u32);
+                    entry.policy_managed = true;
+                }
+            }
+        }
+    }
+
+    let configurations: Vec<EventLogChannelConfig> = settings
+        .into_iter()
+        .map(|(channel, settings)| build_channel_config(channel, settings))
+        .collect();
+
+    let disabled = configurations.iter().filter(|c| !c.enabled).count();
+    let with_findings = configurations.iter().filter(|c| !c.findings.is_empty()).count();
+    let duration = start_time.elapsed();
+    let mut complete = AuditEntry::new(
+        COMPONENT,
+        "INFO",
+        "complete_collection",
+        format!(
+            "Captured configuration of {} channels ({} disabled, {} with findings)",
+            configurations.len(),
+            disabled,
+            with_findings
+        ),
+        "success",
+    );
+    complete.duration_ms = Some(duration.as_millis() as u64);
+    audit_log.push(complete);
+
+    (configurations, audit_log)
+}
+
+#[cfg(windows)]
+fn read_size_and_retention(key: &RegKey, settings: &mut ChannelSettings) {
+    if let Some(max_size) = key
+        .get_value::<u32, _>("MaxSize")
+        .map(u64::from)
+        .or_else(|_| key.get_value::<u64, _>("MaxSize"))
+        .ok()
+    {
+        settings.max_size = Some(max_size);
+    }
+    if let Ok(retention) = key.get_value::<u32, _>("Retention") {
+        settings.retention = Some(retention);
+    }
+    if let Ok(auto_backup) = key.get_value::<u32, _>("AutoBackupLogFiles") {
+        settings.auto_backup = Some(auto_backup);
+    }
+    if let Ok(file) = key.get_value::<String, _>("File") {
+        settings.log_file_path = Some(file);
+    }
+}
+
+#[cfg(windows)]
+fn policy_flag(key: &RegKey, name: &str) -> Option<bool> {
+    key.get_value::<String, _>(name)
+        .ok()
+        .map(|value| value.trim() == "1")
+        .or_else(|| key.get_value::<u32, _>(name).ok().map(|value| value != 0))
+}
+
+fn build_channel_config(channel: String, settings: ChannelSettings) -> EventLogChannelConfig {
+    let classic_default = matches!(channel.as_str(), "Application" | "Security" | "System");
+    let max_size_bytes = settings.max_size.unwrap_or(if classic_default {
+        DEFAULT_CLASSIC_MAX_SIZE
+    } else {
+        DEFAULT_CHANNEL_MAX_SIZE
+    });
+
+    let mut config = EventLogChannelConfig {
+        enabled: settings.enabled.map_or(settings.classic, |enabled| enabled != 0),
+        channel_type: channel_type_name(settings.channel_type, settings.classic),
+        max_size_bytes,
+        max_size_configured: settings.max_size.is_some(),
+        retention: retention_policy(settings.retention, settings.auto_backup).to_string(),
+        log_file_path: settings.log_file_path.unwrap_or_default(),
+        policy_managed: settings.policy_managed,
+        findings: Vec::new(),
+        recommendations: Vec::new(),
+        channel,
+    };
+
+    assess_channel(&mut config);
+    config
+}
+
+fn channel_type_name(channel_type: Option<u32>, classic: bool) -> String {
+    match channel_type {
+        Some(0) => "Admin",
+        Some(1) => "Operational",
+        Some(2) => "Analytic",
+        Some(3) => "Debug",
+        None if classic => "Admin",
+        _ => "Unknown",
+    }
+    .to_string()
+}
+
+/// Retention is 0 (overwrite as needed), 1/0xFFFFFFFF (never overwrite) or, for
+/// legacy classic logs, the number of seconds events are kept
+fn retention_policy(retention: Option<u32>, auto_backup: Option<u32>) -> &'static str {
+    if auto_backup.unwrap_or(0) != 0 {
+        return "archive";
+    }
+    match retention.unwrap_or(0) {
+        0 => "overwrite",
+        1 | u32::MAX => "do_not_overwrite",
+        _ => "overwrite_older_than",
+    }
+}
+
+/// Explain evidence gaps for the key channels and recommend configuration changes
+fn assess_channel(config: &mut EventLogChannelConfig) {
+    let Some(&(_, recommended_size)) = KEY_CHANNELS.iter().find(|(name, _)| *name == config.channel) else {
+        return;
+    };
+
+    if !config.enabled {
+        config.findings.push("Channel is disabled: no events are being recorded".to_string());
+        config.recommendations.push(format!("Enable the channel (wevtutil sl \"{}\" /e:true)", config.channel));
+        return;
+    }
+
+    if config.max_size_bytes < recommended_size {
+        config.findings.push(format!(
+            "Maximum size {} MB is below the recommended {} MB: older events roll over quickly",
+            config.max_size_bytes / (1024 * 1024),
+            recommended_size / (1024 * 1024)
+        ));
+        config.recommendations.push(format!(
+            "Increase the maximum size (wevtutil sl \"{}\" /ms:{})",
+            config.channel, recommended_size
+        ));
+    }
+
+    if config.retention == "do_not_overwrite" {
+        config.findings.push("Retention is do-not-overwrite: new events are discarded once the log is full".to_string());
+        config.recommendations.push("Overwrite events as needed, or archive the log when full".to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retention_policy() {
+        assert_eq!(retention_policy(None, None), "overwrite");
+        assert_eq!(retention_policy(Some(0), Some(0)), "overwrite");
+        assert_eq!(retention_policy(Some(u32::MAX), None), "do_not_overwrite");
+        assert_eq!(retention_policy(Some(1), None), "do_not_overwrite");
+        assert_eq!(retention_policy(Some(u32::MAX), Some(1)), "archive");
+        assert_eq!(retention_policy(Some(604800), None), "overwrite_older_than");
+    }
+
+    #[test]
+    fn test_default_security_log_is_undersized() {
+        let config = build_channel_config(
+            "Security".to_string(),
+            ChannelSettings { classic: true, ..Default::default() },
+        );
+
+        assert!(config.enabled);
+        assert_eq!(config.channel_type, "Admin");
+        assert_eq!(config.max_size_bytes, DEFAULT_CLASSIC_MAX_SIZE);
+        assert!(!config.max_size_configured);
+        assert_eq!(config.findings.len(), 1);
+        assert!(config.recommendations[0].contains("/ms:201326592"));
+    }
+
+    #[test]
+    fn test_disabled_key_channel() {
+        let config = build_channel_config(
+            POWERSHELL_CHANNEL.to_string(),
+            ChannelSettings { enabled: Some(0), channel_type: Some(1), ..Default::default() },
+        );
+
+        assert!(!config.enabled);
+        assert_eq!(config.channel_type, "Operational");
+        assert_eq!(config.findings, vec!["Channel is disabled: no events are being recorded"]);
+    }
+
+    #[test]
+    fn test_other_channels_are_not_assessed() {
+        let config = build_channel_config(
+            "Microsoft-Windows-Kernel-Process/Analytic".to_string(),
+            ChannelSettings { enabled: Some(0), channel_type: Some(2), ..Default::default() },
+        );
+
+        assert!(!config.enabled);
+        assert_eq!(config.channel_type, "Analytic");
+        assert!(config.findings.is_empty());
+        assert!(config.recommendations.is_empty());
+    }
+
+    #[test]
+    fn test_full_log_with_do_not_overwrite() {
+        let config = build_channel_config(
+            "System".to_string(),
+            ChannelSettings {
+                classic: true,
+                max_size: Some(64 * 1024 * 1024),
+                retention: Some(u32::MAX),
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(config.retention, "do_not_overwrite");
+        assert_eq!(config.findings.len(), 1);
+        assert!(config.findings[0].starts_with("Retention is do-not-overwrite"));
+    }
+}
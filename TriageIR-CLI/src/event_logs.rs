@@ -1,53 +1,141 @@
+// Event XML parsing is only reachable through the Windows event log API
+#![cfg_attr(not(windows), allow(dead_code))]
+
 use crate::types::{EventLogs, EventLogEntry, LogEntry};
+use crate::offline::OfflineRoot;
+use crate::event_config::EventLogConfig;
+use crate::time_window::TimeWindow;
+use regex::Regex;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::OnceLock;
 
+#[cfg(windows)]
+use crate::footprint::{self, Access};
+#[cfg(windows)]
+use std::path::Path;
 #[cfg(windows)]
 use windows::{
     core::PCWSTR,
     Win32::System::EventLog::*,
 };
 
-use std::collections::HashMap;
+/// Maximum number of events kept per channel unless a query says otherwise
+pub const DEFAULT_MAX_EVENTS: usize = 1000;
 
-/// Collect Windows Event Log entries from Security and System logs
-pub fn collect_event_logs() -> (EventLogs, Vec<LogEntry>) {
-    let mut logs = Vec::new();
-    logs.push(LogEntry::info("Starting event log collection"));
-    
-    let mut event_logs = EventLogs::default();
-    
-    // Collect Security event log entries
-    match collect_security_events() {
-        Ok(security_events) => {
-            let count = security_events.len();
-            event_logs.security = security_events;
-            logs.push(LogEntry::info(&format!("Collected {} Security log entries", count)));
+pub const SYSMON_CHANNEL: &str = "Microsoft-Windows-Sysmon/Operational";
+pub const POWERSHELL_CHANNEL: &str = "Microsoft-Windows-PowerShell/Operational";
+pub const DEFENDER_CHANNEL: &str = "Microsoft-Windows-Windows Defender/Operational";
+pub const TASK_SCHEDULER_CHANNEL: &str = "Microsoft-Windows-TaskScheduler/Operational";
+
+/// An event log channel to collect and the XPath filter applied to it
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventChannelQuery {
+    pub channel: String,
+    pub xpath: String,
+    pub max_events: usize,
+}
+
+impl EventChannelQuery {
+    /// Collect every event of a channel
+    pub fn all(channel: &str) -> Self {
+        Self::with_xpath(channel, "*")
+    }
+
+    pub fn with_xpath(channel: &str, xpath: &str) -> Self {
+        EventChannelQuery {
+            channel: channel.to_string(),
+            xpath: xpath.to_string(),
+            max_events: DEFAULT_MAX_EVENTS,
+        }
+    }
+
+    /// Parse a `--event-channel` argument of the form `CHANNEL` or `CHANNEL=XPATH`
+    pub fn parse(argument: &str) -> Result<Self, String> {
+        let (channel, xpath) = match argument.split_once('=') {
+            Some((channel, xpath)) => (channel.trim(), xpath.trim()),
+            None => (argument.trim(), "*"),
+        };
+        if channel.is_empty() {
+            return Err(format!("Invalid event channel '{}': channel name is empty", argument));
         }
-        Err(e) => {
-            logs.push(LogEntry::warn(&format!("Failed to collect Security log entries: {}", e)));
+        if xpath.is_empty() {
+            return Err(format!("Invalid event channel '{}': XPath filter is empty", argument));
         }
+        Ok(Self::with_xpath(channel, xpath))
     }
-    
-    // Collect System event log entries
-    match collect_system_events() {
-        Ok(system_events) => {
-            let count = system_events.len();
-            event_logs.system = system_events;
-            logs.push(LogEntry::info(&format!("Collected {} System log entries", count)));
+
+    /// Narrow the XPath filter to a time window. Filters other than `*` and
+    /// `*[System[...]]` are left unchanged and must be filtered after collection.
+    pub fn restrict_to(&mut self, window: &TimeWindow) -> bool {
+        let Some(condition) = window.xpath_condition() else {
+            return true;
+        };
+
+        if self.xpath == "*" {
+            self.xpath = format!("*[System[{}]]", condition);
+            return true;
         }
-        Err(e) => {
-            logs.push(LogEntry::warn(&format!("Failed to collect System log entries: {}", e)));
+
+        match self.xpath.strip_prefix("*[System[").and_then(|inner| inner.strip_suffix("]]")) {
+            Some(inner) if brackets_balanced(inner) => {
+                self.xpath = format!("*[System[{} and {}]]", inner, condition);
+                true
+            }
+            _ => false,
         }
     }
-    
-    // Collect Application event log entries
-    match collect_application_events() {
-        Ok(application_events) => {
-            let count = application_events.len();
-            event_logs.application = application_events;
-            logs.push(LogEntry::info(&format!("Collected {} Application log entries", count)));
+}
+
+fn brackets_balanced(xpath: &str) -> bool {
+    let mut depth = 0i32;
+    for c in xpath.chars() {
+        match c {
+            '[' => depth += 1,
+            ']' => depth -= 1,
+            _ => {}
         }
-        Err(e) => {
-            logs.push(LogEntry::warn(&format!("Failed to collect Application log entries: {}", e)));
+        if depth < 0 {
+            return false;
+        }
+    }
+    depth == 0
+}
+
+/// Channel queries of the built-in event log profile
+pub fn default_channel_queries() -> Vec<EventChannelQuery> {
+    EventLogConfig::builtin().channel_queries()
+}
+
+/// Collect the default channel set from the live system
+pub fn collect_event_logs() -> (EventLogs, Vec<LogEntry>) {
+    collect_event_logs_with_queries(&default_channel_queries(), None)
+}
+
+/// Collect the given channels from the live system, or from the .evtx files of a
+/// mounted Windows volume when an offline root is given
+pub fn collect_event_logs_with_queries(queries: &[EventChannelQuery], root: Option<&OfflineRoot>) -> (EventLogs, Vec<LogEntry>) {
+    let mut logs = Vec::new();
+    logs.push(LogEntry::info("Starting event log collection"));
+    if let Some(root) = root {
+        logs.push(LogEntry::info(&format!("Reading offline event log files under {}", root.root().display())));
+    }
+    
+    let mut event_logs = EventLogs::default();
+    
+    for query in queries {
+        match query_channel(query, root) {
+            Ok(events) => {
+                logs.push(LogEntry::info(&format!("Collected {} {} log entries", events.len(), query.channel)));
+                match query.channel.to_lowercase().as_str() {
+                    "security" => event_logs.security.extend(events),
+                    "system" => event_logs.system.extend(events),
+                    "application" => event_logs.application.extend(events),
+                    _ => event_logs.channels.entry(query.channel.clone()).or_default().extend(events),
+                }
+            }
+            Err(e) => {
+                logs.push(LogEntry::warn(&format!("Failed to collect {} log entries: {}", query.channel, e)));
+            }
         }
     }
     
@@ -58,145 +146,258 @@ pub fn collect_event_logs() -> (EventLogs, Vec<LogEntry>) {
     (event_logs, logs)
 }
 
-/// Collect Security event log entries
+/// Query a channel (or its .evtx file) with EvtQuery, newest events first, and
+/// render each event's XML and formatted message
 #[cfg(windows)]
-fn collect_security_events() -> std::result::Result<Vec<EventLogEntry>, String> {
-    collect_events_from_log("Security", get_security_event_filter())
+fn query_channel(query: &EventChannelQuery, root: Option<&OfflineRoot>) -> std::result::Result<Vec<EventLogEntry>, String> {
+    let (path, path_flag) = match root {
+        Some(root) => (root.event_log_path(&query.channel).to_string_lossy().to_string(), EvtQueryFilePath.0),
+        None => (query.channel.clone(), EvtQueryChannelPath.0),
+    };
+    if root.is_some() {
+        footprint::record_file(Path::new(&path), Access::Read);
+    }
+    let path_wide = to_wide(&path);
+    let xpath_wide = to_wide(&query.xpath);
+    
+    let mut events = Vec::new();
+    let mut publishers: HashMap<String, Option<EVT_HANDLE>> = HashMap::new();
+    
+    unsafe {
+        let result_set = EvtQuery(
+            EVT_HANDLE::default(),
+            PCWSTR(path_wide.as_ptr()),
+            PCWSTR(xpath_wide.as_ptr()),
+            path_flag | EvtQueryReverseDirection.0,
+        ).map_err(|e| format!("Failed to query {}: {}", path, e))?;
+        
+        let mut handles = [0isize; 64];
+        while events.len() < query.max_events {
+            let mut returned = 0u32;
+            // Fails with ERROR_NO_MORE_ITEMS once the result set is exhausted
+            if EvtNext(result_set, &mut handles, 1000, 0, &mut returned).is_err() {
+                break;
+            }
+            
+            for &raw_handle in &handles[..returned as usize] {
+                let event = EVT_HANDLE(raw_handle);
+                if events.len() < query.max_events {
+                    if let Some(entry) = render_event(event, &query.channel, &mut publishers) {
+                        events.push(entry);
+                    }
+                }
+                let _ = EvtClose(event);
+            }
+        }
+        
+        for publisher in publishers.values().flatten() {
+            let _ = EvtClose(*publisher);
+        }
+        let _ = EvtClose(result_set);
+    }
+    
+    Ok(events)
 }
 
-/// Collect System event log entries
+/// Render an event to XML and resolve its message through the provider's
+/// publisher metadata (registered on the collecting machine)
 #[cfg(windows)]
-fn collect_system_events() -> std::result::Result<Vec<EventLogEntry>, String> {
-    collect_events_from_log("System", get_system_event_filter())
+unsafe fn render_event(event: EVT_HANDLE, channel: &str, publishers: &mut HashMap<String, Option<EVT_HANDLE>>) -> Option<EventLogEntry> {
+    let xml = render_event_xml(event)?;
+    let mut entry = parse_event_xml(&xml, channel)?;
+    
+    let publisher = *publishers.entry(entry.provider.clone()).or_insert_with(|| {
+        let provider_wide = to_wide(&entry.provider);
+        EvtOpenPublisherMetadata(EVT_HANDLE::default(), PCWSTR(provider_wide.as_ptr()), PCWSTR::null(), 0, 0).ok()
+    });
+    
+    if let Some(message) = publisher.and_then(|publisher| format_event_message(publisher, event)) {
+        entry.message = message;
+    }
+    
+    Some(entry)
 }
 
-/// Collect Application event log entries
 #[cfg(windows)]
-fn collect_application_events() -> std::result::Result<Vec<EventLogEntry>, String> {
-    collect_events_from_log("Application", get_application_event_filter())
+unsafe fn render_event_xml(event: EVT_HANDLE) -> Option<String> {
+    let mut buffer_used = 0u32;
+    let mut property_count = 0u32;
+    
+    // First call reports the required buffer size in bytes
+    let _ = EvtRender(EVT_HANDLE::default(), event, EvtRenderEventXml.0, 0, None, &mut buffer_used, &mut property_count);
+    if buffer_used == 0 {
+        return None;
+    }
+    
+    let mut buffer = vec![0u16; (buffer_used as usize + 1) / 2];
+    EvtRender(
+        EVT_HANDLE::default(),
+        event,
+        EvtRenderEventXml.0,
+        (buffer.len() * 2) as u32,
+        Some(buffer.as_mut_ptr() as *mut _),
+        &mut buffer_used,
+        &mut property_count,
+    ).ok()?;
+    
+    Some(from_wide(&buffer))
 }
 
-/// Collect events from a specific Windows Event Log
 #[cfg(windows)]
-fn collect_events_from_log(log_name: &str, event_filter: HashMap<u32, &str>) -> std::result::Result<Vec<EventLogEntry>, String> {
-    let mut events = Vec::new();
+unsafe fn format_event_message(publisher: EVT_HANDLE, event: EVT_HANDLE) -> Option<String> {
+    let mut buffer_used = 0u32;
     
-    unsafe {
-        // Open the event log
-        let log_name_wide: Vec<u16> = log_name.encode_utf16().chain(std::iter::once(0)).collect();
-        let h_event_log = match OpenEventLogW(None, PCWSTR(log_name_wide.as_ptr())) {
-            Ok(handle) => handle,
-            Err(_) => return Err(format!("Failed to open {} event log", log_name)),
-        };
-        
-        if h_event_log.is_invalid() {
-            return Err(format!("Failed to open {} event log", log_name));
-        }
-        
-        // Get the number of records
-        let mut num_records = 0u32;
-        let mut oldest_record = 0u32;
-        
-        if GetNumberOfEventLogRecords(h_event_log, &mut num_records).is_err() ||
-           GetOldestEventLogRecord(h_event_log, &mut oldest_record).is_err() {
-            let _ = CloseEventLog(h_event_log);
-            return Err("Failed to get event log information".to_string());
-        }
-        
-        // Limit the number of events to collect (most recent 1000)
-        let max_events = 1000;
-        let start_record = if num_records > max_events {
-            oldest_record + num_records - max_events
-        } else {
-            oldest_record
-        };
-        
-        // Read events
-        let mut buffer = vec![0u8; 65536]; // 64KB buffer
-        let mut bytes_read = 0u32;
-        let mut bytes_needed = 0u32;
-        
-        for record_num in start_record..(start_record + std::cmp::min(num_records, max_events)) {
-            if ReadEventLogW(
-                h_event_log,
-                READ_EVENT_LOG_READ_FLAGS(0x0002 | 0x0004), // EVENTLOG_SEEK_READ | EVENTLOG_FORWARDS_READ
-                record_num,
-                buffer.as_mut_ptr() as *mut _,
-                buffer.len() as u32,
-                &mut bytes_read,
-                &mut bytes_needed,
-            ).is_ok() {
-                // Parse the event record
-                if let Ok(mut event) = parse_event_record(&buffer[..bytes_read as usize], &event_filter) {
-                    event.source = log_name.to_string(); // Set the correct source
-                    events.push(event);
-                }
-            }
-        }
-        
-        let _ = CloseEventLog(h_event_log);
+    // First call reports the required buffer size in characters
+    let _ = EvtFormatMessage(publisher, event, 0, None, EvtFormatMessageEvent.0, None, &mut buffer_used);
+    if buffer_used == 0 {
+        return None;
     }
     
-    // Sort events by timestamp (most recent first)
-    events.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+    let mut buffer = vec![0u16; buffer_used as usize];
+    EvtFormatMessage(publisher, event, 0, None, EvtFormatMessageEvent.0, Some(&mut buffer), &mut buffer_used).ok()?;
     
-    Ok(events)
+    let message = from_wide(&buffer).trim().to_string();
+    if message.is_empty() { None } else { Some(message) }
 }
 
-/// Parse an event log record
 #[cfg(windows)]
-fn parse_event_record(buffer: &[u8], event_filter: &HashMap<u32, &str>) -> std::result::Result<EventLogEntry, std::io::Error> {
-    if buffer.len() < std::mem::size_of::<EVENTLOGRECORD>() {
-        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "Buffer too small for event record"));
+fn to_wide(value: &str) -> Vec<u16> {
+    value.encode_utf16().chain(std::iter::once(0)).collect()
+}
+
+#[cfg(windows)]
+fn from_wide(buffer: &[u16]) -> String {
+    let end = buffer.iter().position(|&c| c == 0).unwrap_or(buffer.len());
+    String::from_utf16_lossy(&buffer[..end])
+}
+
+/// Fallback implementation for non-Windows platforms
+#[cfg(not(windows))]
+fn query_channel(_query: &EventChannelQuery, _root: Option<&OfflineRoot>) -> std::result::Result<Vec<EventLogEntry>, String> {
+    Ok(Vec::new()) // Return empty vector on non-Windows platforms
+}
+
+struct EventXmlPatterns {
+    event_id: Regex,
+    level: Regex,
+    keywords: Regex,
+    time_created: Regex,
+    record_id: Regex,
+    provider: Regex,
+    computer: Regex,
+    data: Regex,
+    user_data: Regex,
+    user_data_field: Regex,
+}
+
+fn event_xml_patterns() -> &'static EventXmlPatterns {
+    static PATTERNS: OnceLock<EventXmlPatterns> = OnceLock::new();
+    PATTERNS.get_or_init(|| EventXmlPatterns {
+        event_id: Regex::new(r"<EventID(?:\s[^>]*)?>(\d+)</EventID>").unwrap(),
+        level: Regex::new(r"<Level>(\d+)</Level>").unwrap(),
+        keywords: Regex::new(r"<Keywords>0x([0-9a-fA-F]+)</Keywords>").unwrap(),
+        time_created: Regex::new(r#"<TimeCreated SystemTime=['"]([^'"]+)['"]"#).unwrap(),
+        record_id: Regex::new(r"<EventRecordID>(\d+)</EventRecordID>").unwrap(),
+        provider: Regex::new(r#"<Provider Name=['"]([^'"]+)['"]"#).unwrap(),
+        computer: Regex::new(r"<Computer>([^<]*)</Computer>").unwrap(),
+        data: Regex::new(r#"(?s)<Data(?:\s+Name=['"]([^'"]*)['"])?\s*(?:/>|>(.*?)</Data>)"#).unwrap(),
+        user_data: Regex::new(r"(?s)<UserData>(.*?)</UserData>").unwrap(),
+        user_data_field: Regex::new(r"<(\w+)(?:\s[^>]*)?>([^<]*)</(\w+)>").unwrap(),
+    })
+}
+
+/// Build an event entry from the XML rendering of an event. The message falls
+/// back to the built-in description of the event ID until it is formatted by
+/// the provider.
+fn parse_event_xml(xml: &str, channel: &str) -> Option<EventLogEntry> {
+    let patterns = event_xml_patterns();
+    let capture = |pattern: &Regex| pattern.captures(xml).map(|c| c[1].to_string());
+    
+    let event_id = capture(&patterns.event_id)?.parse::<u32>().ok()?;
+    let provider = capture(&patterns.provider).unwrap_or_default();
+    let keywords = capture(&patterns.keywords)
+        .and_then(|k| u64::from_str_radix(&k, 16).ok())
+        .unwrap_or(0);
+    let level = event_level_name(capture(&patterns.level).and_then(|l| l.parse().ok()).unwrap_or(0), keywords);
+    let timestamp = capture(&patterns.time_created)
+        .map(|t| chrono::DateTime::parse_from_rfc3339(&t).map(|dt| dt.to_rfc3339()).unwrap_or(t))
+        .unwrap_or_default();
+    
+    let mut event_data = BTreeMap::new();
+    for (index, data) in patterns.data.captures_iter(xml).enumerate() {
+        let name = data.get(1)
+            .map(|n| n.as_str().to_string())
+            .filter(|n| !n.is_empty())
+            .unwrap_or_else(|| format!("Data{}", index));
+        let value = data.get(2).map(|v| unescape_xml(v.as_str())).unwrap_or_default();
+        event_data.insert(name, value);
     }
     
-    unsafe {
-        let record = &*(buffer.as_ptr() as *const EVENTLOGRECORD);
-        
-        // Only collect events we're interested in
-        if !event_filter.contains_key(&record.EventID) {
-            return Err(std::io::Error::new(std::io::ErrorKind::NotFound, "Event not in filter"));
+    // Providers such as TerminalServices log their fields as UserData elements instead of Data
+    if let Some(user_data) = patterns.user_data.captures(xml) {
+        for field in patterns.user_data_field.captures_iter(&user_data[1]) {
+            if field[1] == field[3] {
+                event_data.entry(field[1].to_string()).or_insert_with(|| unescape_xml(&field[2]));
+            }
         }
-        
-        // Convert timestamp
-        let timestamp = convert_event_timestamp(record.TimeGenerated);
-        
-        // Get event level
-        let level = match record.EventType {
-            EVENTLOG_ERROR_TYPE => "Error",
-            EVENTLOG_WARNING_TYPE => "Warning", 
-            EVENTLOG_INFORMATION_TYPE => "Information",
-            EVENTLOG_AUDIT_SUCCESS => "Audit Success",
-            EVENTLOG_AUDIT_FAILURE => "Audit Failure",
-            _ => "Unknown",
-        }.to_string();
-        
-        // Extract message (simplified - in full implementation would resolve message strings)
-        let message = event_filter.get(&record.EventID)
-            .unwrap_or(&"Unknown event")
-            .to_string();
-        
-        Ok(EventLogEntry::new_with_source(
-            record.EventID,
-            level,
-            timestamp,
-            message,
-            "Security".to_string(), // This will be set by the calling function
-        ))
     }
+    
+    let message = builtin_event_description(channel, event_id)
+        .map(|description| description.to_string())
+        .unwrap_or_else(|| format!("Event {} from {}", event_id, provider));
+    
+    let mut entry = EventLogEntry::new_with_source(event_id, level, timestamp, message, channel.to_string());
+    entry.record_id = capture(&patterns.record_id).and_then(|r| r.parse().ok()).unwrap_or(0);
+    entry.provider = provider;
+    entry.computer = capture(&patterns.computer).unwrap_or_default();
+    entry.event_data = event_data;
+    Some(entry)
 }
 
-/// Convert Windows event timestamp to ISO 8601 string
-#[cfg(windows)]
-fn convert_event_timestamp(timestamp: u32) -> String {
-    // Windows event log timestamps are seconds since January 1, 1970 (Unix epoch)
-    let datetime = chrono::DateTime::from_timestamp(timestamp as i64, 0)
-        .unwrap_or_else(|| chrono::Utc::now());
-    datetime.to_rfc3339()
+/// Map the System/Level value to a name; Security events carry their outcome in
+/// the audit keywords instead
+fn event_level_name(level: u8, keywords: u64) -> String {
+    const AUDIT_FAILURE: u64 = 0x0010_0000_0000_0000;
+    const AUDIT_SUCCESS: u64 = 0x0020_0000_0000_0000;
+    
+    if keywords & AUDIT_FAILURE != 0 {
+        return "Audit Failure".to_string();
+    }
+    if keywords & AUDIT_SUCCESS != 0 {
+        return "Audit Success".to_string();
+    }
+    
+    match level {
+        1 => "Critical",
+        2 => "Error",
+        3 => "Warning",
+        0 | 4 => "Information",
+        5 => "Verbose",
+        _ => "Unknown",
+    }.to_string()
+}
+
+fn builtin_event_description(channel: &str, event_id: u32) -> Option<&'static str> {
+    let filter = match channel.to_lowercase().as_str() {
+        "security" => get_security_event_filter(),
+        "system" => get_system_event_filter(),
+        "application" => get_application_event_filter(),
+        _ => return None,
+    };
+    filter.get(&event_id).copied()
+}
+
+/// Replace the five predefined XML entities; `&amp;` goes last so `&amp;lt;` stays `&lt;`
+pub(crate) fn unescape_xml(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
 }
 
 /// Get filter for Security event log (important event IDs)
-fn get_security_event_filter() -> HashMap<u32, &'static str> {
+pub(crate) fn get_security_event_filter() -> HashMap<u32, &'static str> {
     let mut filter = HashMap::new();
     
     // Logon events
@@ -235,7 +436,7 @@ fn get_security_event_filter() -> HashMap<u32, &'static str> {
 }
 
 /// Get filter for System event log (important event IDs)
-fn get_system_event_filter() -> HashMap<u32, &'static str> {
+pub(crate) fn get_system_event_filter() -> HashMap<u32, &'static str> {
     let mut filter = HashMap::new();
     
     // System startup/shutdown
@@ -267,7 +468,7 @@ fn get_system_event_filter() -> HashMap<u32, &'static str> {
 }
 
 /// Get filter for Application event log (important event IDs)
-fn get_application_event_filter() -> HashMap<u32, &'static str> {
+pub(crate) fn get_application_event_filter() -> HashMap<u32, &'static str> {
     let mut filter = HashMap::new();
     
     // Application errors and crashes
@@ -298,22 +499,6 @@ fn get_application_event_filter() -> HashMap<u32, &'static str> {
     filter
 }
 
-/// Fallback implementation for non-Windows platforms
-#[cfg(not(windows))]
-fn collect_security_events() -> std::result::Result<Vec<EventLogEntry>, String> {
-    Ok(Vec::new()) // Return empty vector on non-Windows platforms
-}
-
-#[cfg(not(windows))]
-fn collect_system_events() -> std::result::Result<Vec<EventLogEntry>, String> {
-    Ok(Vec::new()) // Return empty vector on non-Windows platforms
-}
-
-#[cfg(not(windows))]
-fn collect_application_events() -> std::result::Result<Vec<EventLogEntry>, String> {
-    Ok(Vec::new()) // Return empty vector on non-Windows platforms
-}
-
 /// Filter events by event ID
 pub fn filter_events_by_id(events: &[EventLogEntry], event_id: u32) -> Vec<&EventLogEntry> {
     events.iter().filter(|e| e.event_id == event_id).collect()
@@ -413,6 +598,92 @@ mod tests {
         assert_eq!(filter.get(&11707), Some(&"Installation completed successfully"));
     }
 
+    #[test]
+    fn test_parse_event_xml() {
+        let xml = "<Event xmlns='http://schemas.microsoft.com/win/2004/08/events/event'><System>\
+            <Provider Name='Microsoft-Windows-Security-Auditing' Guid='{54849625-5478-4994-a5ba-3e3b0328c30d}'/>\
+            <EventID>4625</EventID><Version>0</Version><Level>0</Level><Task>12544</Task>\
+            <Keywords>0x8010000000000000</Keywords><TimeCreated SystemTime='2023-05-01T10:15:30.1234567Z'/>\
+            <EventRecordID>88231</EventRecordID><Channel>Security</Channel><Computer>WS01.corp.local</Computer>\
+            </System><EventData><Data Name='TargetUserName'>administrator</Data>\
+            <Data Name='IpAddress'>10.0.0.5</Data><Data Name='SubjectUserName'/>\
+            <Data Name='ProcessName'>C:\\Windows\\System32\\svchost.exe &amp; more</Data></EventData></Event>";
+        
+        let entry = parse_event_xml(xml, "Security").unwrap();
+        
+        assert_eq!(entry.event_id, 4625);
+        assert_eq!(entry.level, "Audit Failure");
+        assert_eq!(entry.timestamp, "2023-05-01T10:15:30.123456700+00:00");
+        assert_eq!(entry.record_id, 88231);
+        assert_eq!(entry.provider, "Microsoft-Windows-Security-Auditing");
+        assert_eq!(entry.computer, "WS01.corp.local");
+        assert_eq!(entry.source, "Security");
+        assert_eq!(entry.message, "An account failed to log on");
+        assert_eq!(entry.event_data.get("TargetUserName").map(String::as_str), Some("administrator"));
+        assert_eq!(entry.event_data.get("SubjectUserName").map(String::as_str), Some(""));
+        assert_eq!(entry.event_data.get("ProcessName").map(String::as_str), Some("C:\\Windows\\System32\\svchost.exe & more"));
+    }
+
+    #[test]
+    fn test_parse_event_xml_other_channel() {
+        let xml = "<Event><System><Provider Name=\"Microsoft-Windows-Sysmon\"/><EventID>1</EventID>\
+            <Level>4</Level><Keywords>0x8000000000000000</Keywords><TimeCreated SystemTime=\"2023-05-01T10:15:30Z\"/>\
+            <EventRecordID>7</EventRecordID></System><EventData><Data Name=\"Image\">C:\\Temp\\a.exe</Data></EventData></Event>";
+        
+        let entry = parse_event_xml(xml, SYSMON_CHANNEL).unwrap();
+        
+        assert_eq!(entry.level, "Information");
+        assert_eq!(entry.message, "Event 1 from Microsoft-Windows-Sysmon");
+        assert_eq!(entry.source, SYSMON_CHANNEL);
+        assert!(parse_event_xml("<Event><System></System></Event>", "System").is_none());
+    }
+
+    #[test]
+    fn test_parse_event_xml_user_data() {
+        let xml = "<Event><System><Provider Name='Microsoft-Windows-TerminalServices-LocalSessionManager'/>\
+            <EventID>21</EventID><Level>4</Level><TimeCreated SystemTime='2023-05-01T10:15:30Z'/></System>\
+            <UserData><EventXML xmlns='Event_NS'><User>CORP\\alice</User><SessionID>3</SessionID>\
+            <Address>10.0.0.7</Address></EventXML></UserData></Event>";
+        
+        let entry = parse_event_xml(xml, "Microsoft-Windows-TerminalServices-LocalSessionManager/Operational").unwrap();
+        
+        assert_eq!(entry.event_data.get("User").map(String::as_str), Some("CORP\\alice"));
+        assert_eq!(entry.event_data.get("SessionID").map(String::as_str), Some("3"));
+        assert_eq!(entry.event_data.get("Address").map(String::as_str), Some("10.0.0.7"));
+        assert!(!entry.event_data.contains_key("EventXML"));
+    }
+
+    #[test]
+    fn test_event_channel_query_parse() {
+        let query = EventChannelQuery::parse("Microsoft-Windows-Sysmon/Operational=*[System[(EventID=1 or EventID=3)]]").unwrap();
+        assert_eq!(query.channel, SYSMON_CHANNEL);
+        assert_eq!(query.xpath, "*[System[(EventID=1 or EventID=3)]]");
+        assert_eq!(query.max_events, DEFAULT_MAX_EVENTS);
+        
+        assert_eq!(EventChannelQuery::parse("Security").unwrap(), EventChannelQuery::all("Security"));
+        assert!(EventChannelQuery::parse("=*").is_err());
+        assert!(EventChannelQuery::parse("Security=").is_err());
+    }
+
+    #[test]
+    fn test_restrict_query_to_time_window() {
+        let window = TimeWindow::new(Some("2024-05-01T00:00:00Z"), None).unwrap();
+        let condition = "TimeCreated[@SystemTime>='2024-05-01T00:00:00.000Z']";
+        
+        let mut query = EventChannelQuery::all(SYSMON_CHANNEL);
+        assert!(query.restrict_to(&window));
+        assert_eq!(query.xpath, format!("*[System[{}]]", condition));
+        
+        let mut query = EventChannelQuery::with_xpath("Security", "*[System[(EventID=4624) and Level<=4]]");
+        assert!(query.restrict_to(&window));
+        assert_eq!(query.xpath, format!("*[System[(EventID=4624) and Level<=4 and {}]]", condition));
+        
+        let raw = "*[System[EventID=4688]] and *[EventData[Data[@Name='NewProcessName']]]";
+        let mut query = EventChannelQuery::with_xpath("Security", raw);
+        assert!(!query.restrict_to(&window));
+        assert_eq!(query.xpath, raw);
+    }
+
     #[test]
     fn test_filter_events_by_id() {
         let events = vec![
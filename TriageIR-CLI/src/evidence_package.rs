@@ -1,24 +1,123 @@
-use crate::forensic_types::{ForensicEvidence, FileHash, AuditEntry};
+//! Professional evidence packaging for forensic integrity
+//! Creates password-protected, timestamped archives with chain of custody
+
+use crate::case_manifest::hmac_sha256_bytes;
+use crate::forensic_types::{ForensicEvidence, AuditEntry, CaseMetadata, CollectorInfo};
+use crate::logger;
+use crate::package_signing::{self, PackageSigner, SignatureCheck};
+use crate::package_volumes;
+use crate::report_lang::{self, ReportLanguage, ReportTemplates};
+use crate::schema;
+use serde_json::Value;
 use std::fs::{self, File};
 use std::io::{Write, Read, BufWriter};
+use std::ops::RangeInclusive;
 use std::path::{Path, PathBuf};
+use std::rc::Rc;
 use zip::{ZipWriter, write::FileOptions, CompressionMethod};
 use sha2::{Sha256, Digest};
 use aes::Aes256;
-use aes::cipher::{BlockEncrypt, KeyInit, generic_array::GenericArray};
+use aes::cipher::{BlockDecrypt, BlockEncrypt, KeyInit, generic_array::GenericArray};
 use pbkdf2::pbkdf2_hmac;
 use sha2::Sha256 as Sha256Hash;
 use rand::{Rng, thread_rng};
 
-/// Professional evidence packaging for forensic integrity
-/// Creates password-protected, timestamped archives with chain of custody
+/// OpenSSL `enc` salted-format magic, so packages can be decrypted with standard tooling
+const SALTED_MAGIC: &[u8; 8] = b"Salted__";
+const PBKDF2_ITERATIONS: u32 = 100_000;
+const AES_BLOCK_SIZE: usize = 16;
+/// HMAC-SHA256 tag appended to the OpenSSL layout, checked before decrypting
+const MAC_SIZE: usize = 32;
+
+/// Machine-readable case metadata and chain of custody, next to chain_of_custody.txt
+pub const CASE_METADATA: &str = "case_metadata.json";
+
+/// Compression of the evidence and attachments (`--compression`); the text
+/// documents are always deflated so any ZIP tool can read them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PackageCompression {
+    Zstd,
+    #[default]
+    Deflate,
+    Store,
+}
+
+impl PackageCompression {
+    pub const NAMES: [&'static str; 3] = ["zstd", "deflate", "store"];
+    
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "zstd" => Some(PackageCompression::Zstd),
+            "deflate" => Some(PackageCompression::Deflate),
+            "store" => Some(PackageCompression::Store),
+            _ => None,
+        }
+    }
+    
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PackageCompression::Zstd => "zstd",
+            PackageCompression::Deflate => "deflate",
+            PackageCompression::Store => "store",
+        }
+    }
+    
+    /// Accepted levels; None when the method has none
+    pub fn levels(&self) -> Option<RangeInclusive<i32>> {
+        match self {
+            PackageCompression::Zstd => Some(-7..=22), // Negative levels trade ratio for speed
+            PackageCompression::Deflate => Some(0..=9),
+            PackageCompression::Store => None,
+        }
+    }
+    
+    fn default_level(&self) -> Option<i32> {
+        match self {
+            PackageCompression::Zstd => Some(3),
+            PackageCompression::Deflate => Some(6), // Balanced compression
+            PackageCompression::Store => None,
+        }
+    }
+    
+    /// The level to compress with: `level` when the method accepts it, else its default
+    pub fn check_level(&self, level: Option<i32>) -> Result<Option<i32>, String> {
+        match (self.levels(), level) {
+            (_, None) => Ok(self.default_level()),
+            (Some(levels), Some(level)) if levels.contains(&level) => Ok(Some(level)),
+            (Some(levels), Some(level)) => Err(format!("{} compression level {} is outside {}..={}", self.as_str(), level, levels.start(), levels.end())),
+            (None, Some(_)) => Err(format!("{} has no compression level", self.as_str())),
+        }
+    }
+    
+    fn file_options(&self, level: Option<i32>) -> FileOptions {
+        let method = match self {
+            PackageCompression::Zstd => CompressionMethod::Zstd,
+            PackageCompression::Deflate => CompressionMethod::Deflated,
+            PackageCompression::Store => CompressionMethod::Stored,
+        };
+        FileOptions::default().compression_method(method).compression_level(level)
+    }
+    
+    /// As recorded in integrity_verification.txt, e.g. "zstd (level 3)"
+    fn describe(&self, level: Option<i32>) -> String {
+        match level {
+            Some(level) => format!("{} (level {})", self.as_str(), level),
+            None => self.as_str().to_string(),
+        }
+    }
+}
 
 pub struct EvidencePackager {
     case_id: String,
     output_directory: PathBuf,
     temp_directory: PathBuf,
     password: String,
-    compression_level: u32,
+    compression: PackageCompression,
+    compression_level: Option<i32>,
+    language: ReportLanguage,
+    attachments: Vec<(String, Vec<u8>)>,
+    signer: Option<Rc<PackageSigner>>,
+    max_volume_size: Option<u64>,
 }
 
 impl EvidencePackager {
@@ -35,12 +134,58 @@ impl EvidencePackager {
             output_directory,
             temp_directory,
             password,
-            compression_level: 6, // Balanced compression
+            compression: PackageCompression::default(),
+            compression_level: PackageCompression::default().default_level(),
+            language: ReportLanguage::default(),
+            attachments: Vec::new(),
+            signer: None,
+            max_volume_size: None,
         })
     }
     
+    /// Write chain_of_custody.txt, README.txt and collection_audit.txt in the given language
+    pub fn with_language(mut self, language: ReportLanguage) -> Self {
+        self.language = language;
+        self
+    }
+    
+    /// Additional files (archive path, content) stored next to the evidence JSON,
+    /// encrypted like it and listed with their hashes in the integrity manifest
+    pub fn with_attachments(mut self, attachments: Vec<(String, Vec<u8>)>) -> Self {
+        self.attachments = attachments;
+        self
+    }
+    
+    /// Sign evidence.json and the archive with detached CMS signatures (`--signing-cert`)
+    pub fn with_signer(mut self, signer: Option<Rc<PackageSigner>>) -> Self {
+        self.signer = signer;
+        self
+    }
+    
+    /// Compress the evidence and attachments with `compression` at `level`
+    /// (its default when None); levels outside `PackageCompression::levels` fail
+    pub fn with_compression(mut self, compression: PackageCompression, level: Option<i32>) -> Result<Self, String> {
+        self.compression_level = compression.check_level(level)?;
+        self.compression = compression;
+        Ok(self)
+    }
+    
+    /// Split archives larger than `max_volume_size` bytes into volumes (`--max-package-size`)
+    pub fn with_max_volume_size(mut self, max_volume_size: Option<u64>) -> Self {
+        self.max_volume_size = max_volume_size;
+        self
+    }
+    
     /// Package forensic evidence into secure archive
     pub fn package_evidence(&self, evidence: &ForensicEvidence) -> Result<(PathBuf, Vec<AuditEntry>), Box<dyn std::error::Error>> {
+        let evidence_json = serde_json::to_string_pretty(evidence)?;
+        self.package_evidence_json(evidence, &evidence_json)
+    }
+    
+    /// Package an already serialized evidence document into secure archive.
+    /// Case metadata, custody and audit documents are taken from `evidence`.
+    /// Returns the archive or, when it was split into volumes, the volume manifest.
+    pub fn package_evidence_json(&self, evidence: &ForensicEvidence, evidence_json: &str) -> Result<(PathBuf, Vec<AuditEntry>), Box<dyn std::error::Error>> {
         let mut audit_log = Vec::new();
         let start_time = std::time::Instant::now();
         
@@ -63,30 +208,79 @@ impl EvidencePackager {
         let file = File::create(&archive_path)?;
         let mut zip = ZipWriter::new(BufWriter::new(file));
         
-        // Set compression options
-        let options = FileOptions::default()
-            .compression_method(CompressionMethod::Deflated)
-            .compression_level(Some(self.compression_level as i32));
+        // Evidence and attachments are streamed through the chosen compressor,
+        // the text documents stay deflated for tools without zstd support
+        let options = self.compression.file_options(self.compression_level);
+        let document_options = PackageCompression::Deflate.file_options(PackageCompression::Deflate.default_level());
         
-        // Add main evidence JSON
-        let evidence_json = serde_json::to_string_pretty(evidence)?;
-        zip.start_file("evidence.json", options)?;
-        zip.write_all(evidence_json.as_bytes())?;
+        // Add main evidence JSON, encrypted when a password is configured
+        let encrypted_evidence = if self.password.is_empty() {
+            zip.start_file("evidence.json", options)?;
+            zip.write_all(evidence_json.as_bytes())?;
+            None
+        } else {
+            let ciphertext = encrypt_evidence(evidence_json.as_bytes(), &self.password);
+            zip.start_file("evidence.json.enc", options)?;
+            zip.write_all(&ciphertext)?;
+            Some(ciphertext)
+        };
         
         audit_log.push(AuditEntry {
             timestamp: chrono::Utc::now().to_rfc3339(),
             level: "DEBUG".to_string(),
             component: "evidence_packager".to_string(),
             action: "add_evidence_json".to_string(),
-            details: format!("Added evidence.json ({} bytes)", evidence_json.len()),
+            details: match encrypted_evidence {
+                Some(ref ciphertext) => format!(
+                    "Added evidence.json.enc ({} bytes plaintext, {} bytes AES-256-CBC)",
+                    evidence_json.len(), ciphertext.len()
+                ),
+                None => format!("Added evidence.json ({} bytes)", evidence_json.len()),
+            },
             duration_ms: None,
             result: "success".to_string(),
         });
         
+        // The signature covers the plaintext, so it can be checked after decryption
+        if let Some(signer) = &self.signer {
+            let signature = signer.sign(evidence_json.as_bytes())?;
+            zip.start_file(package_signing::EVIDENCE_SIGNATURE, options)?;
+            zip.write_all(&signature)?;
+            audit_log.push(signing_audit_entry(package_signing::EVIDENCE_SIGNATURE, signer));
+        }
+        
+        // Add the attachments (screenshots, ...), encrypted like the evidence
+        let mut attachment_hashes = Vec::new();
+        for (name, content) in &self.attachments {
+            let plaintext_hash = hex::encode(sha2::Sha256::digest(content));
+            let (archive_name, ciphertext_hash) = if self.password.is_empty() {
+                zip.start_file(name.as_str(), options)?;
+                zip.write_all(content)?;
+                (name.clone(), None)
+            } else {
+                let ciphertext = encrypt_evidence(content, &self.password);
+                let archive_name = format!("{}.enc", name);
+                zip.start_file(archive_name.as_str(), options)?;
+                zip.write_all(&ciphertext)?;
+                (archive_name, Some(hex::encode(sha2::Sha256::digest(&ciphertext))))
+            };
+            
+            audit_log.push(AuditEntry {
+                timestamp: chrono::Utc::now().to_rfc3339(),
+                level: "DEBUG".to_string(),
+                component: "evidence_packager".to_string(),
+                action: "add_attachment".to_string(),
+                details: format!("Added {} ({} bytes, SHA-256 {})", archive_name, content.len(), plaintext_hash),
+                duration_ms: None,
+                result: "success".to_string(),
+            });
+            attachment_hashes.push((name.clone(), plaintext_hash, ciphertext_hash));
+        }
+        
         // Add integrity verification files
-        let integrity_files = self.create_integrity_files(evidence, &evidence_json)?;
+        let integrity_files = self.create_integrity_files(evidence, evidence_json, encrypted_evidence.as_deref(), &attachment_hashes, &archive_name)?;
         for (filename, content) in integrity_files {
-            zip.start_file(&filename, options)?;
+            zip.start_file(&filename, document_options)?;
             zip.write_all(content.as_bytes())?;
             
             audit_log.push(AuditEntry {
@@ -101,18 +295,19 @@ impl EvidencePackager {
         }
         
         // Add chain of custody document
-        let custody_doc = self.create_custody_document(evidence)?;
-        zip.start_file("chain_of_custody.txt", options)?;
+        let templates = ReportTemplates::load(self.language)?;
+        let custody_doc = self.create_custody_document(evidence, &templates)?;
+        zip.start_file("chain_of_custody.txt", document_options)?;
         zip.write_all(custody_doc.as_bytes())?;
         
         // Add collection audit log
-        let audit_doc = self.create_audit_document(evidence)?;
-        zip.start_file("collection_audit.txt", options)?;
+        let audit_doc = self.create_audit_document(evidence, &templates)?;
+        zip.start_file("collection_audit.txt", document_options)?;
         zip.write_all(audit_doc.as_bytes())?;
         
         // Add README with instructions
-        let readme = self.create_readme_document(evidence)?;
-        zip.start_file("README.txt", options)?;
+        let readme = self.create_readme_document(evidence, encrypted_evidence.is_some(), &templates)?;
+        zip.start_file("README.txt", document_options)?;
         zip.write_all(readme.as_bytes())?;
         
         // Finalize the archive
@@ -144,32 +339,70 @@ impl EvidencePackager {
             result: "success".to_string(),
         });
         
+        if let Some(signer) = &self.signer {
+            let signature = signer.sign(&fs::read(&archive_path)?)?;
+            let signature_file = archive_path.with_extension(package_signing::ARCHIVE_SIGNATURE_EXTENSION);
+            fs::write(&signature_file, signature)?;
+            audit_log.push(signing_audit_entry(&signature_file.display().to_string(), signer));
+        }
+        
+        // Hashes and signature above cover the whole archive, also once it is split
+        let package_path = match self.max_volume_size {
+            Some(max_volume_size) => match package_volumes::split(&archive_path, max_volume_size)? {
+                Some(manifest_path) => {
+                    audit_log.push(AuditEntry {
+                        timestamp: chrono::Utc::now().to_rfc3339(),
+                        level: "INFO".to_string(),
+                        component: "evidence_packager".to_string(),
+                        action: "split_volumes".to_string(),
+                        details: format!("Split into volumes of at most {} bytes: {}", max_volume_size, manifest_path.display()),
+                        duration_ms: None,
+                        result: "success".to_string(),
+                    });
+                    manifest_path
+                }
+                None => archive_path,
+            },
+            None => archive_path,
+        };
+        
         // Clean up temporary directory
         if self.temp_directory.exists() {
             fs::remove_dir_all(&self.temp_directory)?;
         }
         
-        Ok((archive_path, audit_log))
+        Ok((package_path, audit_log))
     }
     
-    fn create_integrity_files(&self, evidence: &ForensicEvidence, evidence_json: &str) -> Result<Vec<(String, String)>, Box<dyn std::error::Error>> {
+    /// `attachments` are (name, SHA-256 of the content, SHA-256 of the encrypted file)
+    fn create_integrity_files(
+        &self,
+        evidence: &ForensicEvidence,
+        evidence_json: &str,
+        encrypted_evidence: Option<&[u8]>,
+        attachments: &[(String, String, Option<String>)],
+        archive_name: &str,
+    ) -> Result<Vec<(String, String)>, Box<dyn std::error::Error>> {
         let mut files = Vec::new();
         
         // Create SHA-256 hash of evidence
         let evidence_hash = sha2::Sha256::digest(evidence_json.as_bytes());
         let hash_hex = hex::encode(evidence_hash);
+        let case_metadata = serde_json::to_string_pretty(&evidence.case_metadata)?;
         
         // Create hash manifest
-        let hash_manifest = format!(
+        let mut hash_manifest = format!(
             "TriageIR Evidence Integrity Verification\n\
             ==========================================\n\n\
             Case ID: {}\n\
             Evidence ID: {}\n\
             Collection Time: {}\n\
             Hash Algorithm: SHA-256\n\
-            Evidence Hash: {}\n\n\
+            Evidence Hash: {}\n\
+            Compression: {}\n\n\
             File Integrity:\n\
-            - evidence.json: {}\n\n\
+            - evidence.json: {}\n\
+            - {}: {}\n\n\
             Verification Instructions:\n\
             1. Extract evidence.json from the archive\n\
             2. Calculate SHA-256 hash of evidence.json\n\
@@ -181,211 +414,202 @@ impl EvidencePackager {
             evidence.case_metadata.evidence_id,
             evidence.case_metadata.collection_timestamp,
             hash_hex,
+            self.compression.describe(self.compression_level),
             hash_hex,
+            CASE_METADATA,
+            hex::encode(sha2::Sha256::digest(case_metadata.as_bytes())),
             env!("CARGO_PKG_VERSION"),
             chrono::Utc::now().to_rfc3339()
         );
         
-        files.push(("integrity_verification.txt".to_string(), hash_manifest));
+        if let Some(ciphertext) = encrypted_evidence {
+            hash_manifest.push_str(&format!(
+                "\nEncrypted Evidence:\n\
+                - evidence.json.enc: {}\n\
+                - Cipher: AES-256-CBC, PBKDF2-HMAC-SHA256 ({} iterations), OpenSSL salted format followed by an HMAC-SHA256 tag\n\
+                - The evidence.json hash above applies to the decrypted file\n",
+                hex::encode(sha2::Sha256::digest(ciphertext)),
+                PBKDF2_ITERATIONS
+            ));
+        }
         
-        // Create digital signature placeholder (would implement actual signing in production)
-        let signature_info = format!(
-            "Digital Signature Information\n\
-            =============================\n\n\
-            This evidence package can be digitally signed for additional integrity verification.\n\n\
-            To implement digital signatures:\n\
-            1. Generate or obtain a code signing certificate\n\
-            2. Sign the evidence.json file\n\
-            3. Include the signature and certificate chain\n\n\
-            Current Status: Not digitally signed\n\
-            Reason: No signing certificate configured\n\n\
-            For production use, implement proper digital signatures using:\n\
-            - X.509 certificates from trusted CA\n\
-            - RSA or ECDSA signing algorithms\n\
-            - Timestamping for long-term validity\n"
-        );
+        if !attachments.is_empty() {
+            hash_manifest.push_str("\nAttachments (SHA-256 of the decrypted files):\n");
+            for (name, plaintext_hash, ciphertext_hash) in attachments {
+                hash_manifest.push_str(&format!("- {}: {}\n", name, plaintext_hash));
+                if let Some(ciphertext_hash) = ciphertext_hash {
+                    hash_manifest.push_str(&format!("  {}.enc: {}\n", name, ciphertext_hash));
+                }
+            }
+        }
+        
+        files.push(("integrity_verification.txt".to_string(), hash_manifest));
+        files.push((CASE_METADATA.to_string(), case_metadata));
         
+        let signature_info = package_signing::signature_info(self.signer.as_deref().map(PackageSigner::info), archive_name);
         files.push(("digital_signature_info.txt".to_string(), signature_info));
         
         Ok(files)
     }
     
-    fn create_custody_document(&self, evidence: &ForensicEvidence) -> Result<String, Box<dyn std::error::Error>> {
-        let mut doc = String::new();
+    fn create_custody_document(&self, evidence: &ForensicEvidence, templates: &ReportTemplates) -> Result<String, Box<dyn std::error::Error>> {
+        let metadata = &evidence.case_metadata;
+        let custody = &templates.custody;
         
-        doc.push_str("CHAIN OF CUSTODY RECORD\n");
-        doc.push_str("=======================\n\n");
+        let legal_authority = metadata.legal_authority.as_ref()
+            .map(|authority| report_lang::render(&custody.legal_authority, &[("legal_authority", authority)]))
+            .unwrap_or_default();
         
-        doc.push_str(&format!("Case Information:\n"));
-        doc.push_str(&format!("  Case ID: {}\n", evidence.case_metadata.case_id));
-        doc.push_str(&format!("  Evidence ID: {}\n", evidence.case_metadata.evidence_id));
-        doc.push_str(&format!("  Collection Date: {}\n", evidence.case_metadata.collection_timestamp));
-        doc.push_str(&format!("  Collection Method: {}\n", evidence.case_metadata.collection_method));
+        let entries: String = metadata.chain_of_custody.iter().enumerate().map(|(i, entry)| {
+            report_lang::render(&custody.entry, &[
+                ("number", &(i + 1).to_string()),
+                ("timestamp", &entry.timestamp),
+                ("action", &entry.action),
+                ("person", &entry.person),
+                ("organization", &entry.organization),
+                ("notes", &entry.notes),
+            ])
+        }).collect();
         
-        if let Some(ref legal_authority) = evidence.case_metadata.legal_authority {
-            doc.push_str(&format!("  Legal Authority: {}\n", legal_authority));
-        }
+        Ok(report_lang::render(&custody.document, &[
+            ("case_id", &metadata.case_id),
+            ("evidence_id", &metadata.evidence_id),
+            ("collection_date", &metadata.collection_timestamp),
+            ("collection_method", &metadata.collection_method),
+            ("legal_authority", &legal_authority),
+            ("collector_name", &metadata.collector_info.name),
+            ("collector_organization", &metadata.collector_info.organization),
+            ("collector_contact", &metadata.collector_info.contact),
+            ("tool_version", &metadata.collector_info.tool_version),
+            ("collection_host", &metadata.collector_info.collection_host),
+            ("hostname", &metadata.target_system.hostname),
+            ("domain", &metadata.target_system.domain),
+            ("os_version", &metadata.target_system.os_version),
+            ("architecture", &metadata.target_system.architecture),
+            ("last_boot", &metadata.target_system.last_boot_time),
+            ("entries", &entries),
+            ("hash_algorithm", &evidence.integrity_verification.hash_algorithm),
+            ("evidence_hash", &evidence.integrity_verification.evidence_hash),
+            ("verification_time", &evidence.integrity_verification.verification_timestamp),
+        ]))
+    }
+    
+    fn create_audit_document(&self, evidence: &ForensicEvidence, templates: &ReportTemplates) -> Result<String, Box<dyn std::error::Error>> {
+        let collection_audit = &evidence.collection_audit;
+        let audit = &templates.audit;
         
-        doc.push_str("\nCollector Information:\n");
-        doc.push_str(&format!("  Name: {}\n", evidence.case_metadata.collector_info.name));
-        doc.push_str(&format!("  Organization: {}\n", evidence.case_metadata.collector_info.organization));
-        doc.push_str(&format!("  Contact: {}\n", evidence.case_metadata.collector_info.contact));
-        doc.push_str(&format!("  Tool Version: {}\n", evidence.case_metadata.collector_info.tool_version));
-        doc.push_str(&format!("  Collection Host: {}\n", evidence.case_metadata.collector_info.collection_host));
-        
-        doc.push_str("\nTarget System Information:\n");
-        doc.push_str(&format!("  Hostname: {}\n", evidence.case_metadata.target_system.hostname));
-        doc.push_str(&format!("  Domain: {}\n", evidence.case_metadata.target_system.domain));
-        doc.push_str(&format!("  OS Version: {}\n", evidence.case_metadata.target_system.os_version));
-        doc.push_str(&format!("  Architecture: {}\n", evidence.case_metadata.target_system.architecture));
-        doc.push_str(&format!("  Last Boot: {}\n", evidence.case_metadata.target_system.last_boot_time));
-        
-        doc.push_str("\nChain of Custody Entries:\n");
-        doc.push_str("-" .repeat(50).as_str());
-        doc.push_str("\n");
-        
-        for (i, entry) in evidence.case_metadata.chain_of_custody.iter().enumerate() {
-            doc.push_str(&format!("{}. {}\n", i + 1, entry.timestamp));
-            doc.push_str(&format!("   Action: {}\n", entry.action));
-            doc.push_str(&format!("   Person: {}\n", entry.person));
-            doc.push_str(&format!("   Organization: {}\n", entry.organization));
-            doc.push_str(&format!("   Notes: {}\n", entry.notes));
-            doc.push_str("\n");
+        let mut errors = String::new();
+        if !collection_audit.collection_errors.is_empty() {
+            let entries: String = collection_audit.collection_errors.iter().enumerate().map(|(i, error)| {
+                let stack_trace = error.stack_trace.as_ref()
+                    .map(|trace| report_lang::render(&audit.stack_trace, &[("stack_trace", trace)]))
+                    .unwrap_or_default();
+                report_lang::render(&audit.error_entry, &[
+                    ("number", &(i + 1).to_string()),
+                    ("timestamp", &error.timestamp),
+                    ("component", &error.component),
+                    ("code", &error.error_code),
+                    ("message", &error.error_message),
+                    ("impact", &error.impact),
+                    ("stack_trace", &stack_trace),
+                ])
+            }).collect();
+            errors = report_lang::render(&audit.errors, &[("entries", &entries)]);
         }
         
-        doc.push_str("\nEvidence Integrity:\n");
-        doc.push_str(&format!("  Hash Algorithm: {}\n", evidence.integrity_verification.hash_algorithm));
-        doc.push_str(&format!("  Evidence Hash: {}\n", evidence.integrity_verification.evidence_hash));
-        doc.push_str(&format!("  Verification Time: {}\n", evidence.integrity_verification.verification_timestamp));
-        
-        doc.push_str("\nLegal Notice:\n");
-        doc.push_str("This evidence was collected in accordance with applicable laws and regulations.\n");
-        doc.push_str("Any unauthorized access, modification, or distribution is prohibited.\n");
-        doc.push_str("Maintain proper chain of custody at all times.\n");
-        
-        Ok(doc)
-    }
-    
-    fn create_audit_document(&self, evidence: &ForensicEvidence) -> Result<String, Box<dyn std::error::Error>> {
-        let mut doc = String::new();
-        
-        doc.push_str("COLLECTION AUDIT LOG\n");
-        doc.push_str("===================\n\n");
-        
-        doc.push_str("Collection Summary:\n");
-        doc.push_str(&format!("  Start Time: {}\n", evidence.collection_audit.collection_start));
-        doc.push_str(&format!("  End Time: {}\n", evidence.collection_audit.collection_end));
-        doc.push_str(&format!("  Duration: {} seconds\n", evidence.collection_audit.total_duration_seconds));
-        doc.push_str(&format!("  Method: {}\n", evidence.collection_audit.collection_method));
-        doc.push_str(&format!("  Tool Version: {}\n", evidence.collection_audit.tool_version));
-        doc.push_str(&format!("  Command Line: {}\n", evidence.collection_audit.command_line));
-        doc.push_str(&format!("  Working Directory: {}\n", evidence.collection_audit.working_directory));
-        
-        doc.push_str("\nCollection Statistics:\n");
-        let stats = &evidence.collection_audit.collection_statistics;
-        doc.push_str(&format!("  Total Processes: {}\n", stats.total_processes));
-        doc.push_str(&format!("  Network Connections: {}\n", stats.total_network_connections));
-        doc.push_str(&format!("  Files Analyzed: {}\n", stats.total_files_analyzed));
-        doc.push_str(&format!("  Registry Keys: {}\n", stats.total_registry_keys));
-        doc.push_str(&format!("  Event Log Entries: {}\n", stats.total_event_log_entries));
-        doc.push_str(&format!("  Prefetch Files: {}\n", stats.total_prefetch_files));
-        doc.push_str(&format!("  Scheduled Tasks: {}\n", stats.total_scheduled_tasks));
-        doc.push_str(&format!("  Peak Memory Usage: {:.2} MB\n", stats.memory_usage_peak_mb));
-        doc.push_str(&format!("  Disk Space Used: {:.2} MB\n", stats.disk_space_used_mb));
-        
-        if !evidence.collection_audit.collection_errors.is_empty() {
-            doc.push_str("\nCollection Errors:\n");
-            for (i, error) in evidence.collection_audit.collection_errors.iter().enumerate() {
-                doc.push_str(&format!("{}. {} - {}\n", i + 1, error.timestamp, error.component));
-                doc.push_str(&format!("   Error: {} - {}\n", error.error_code, error.error_message));
-                doc.push_str(&format!("   Impact: {}\n", error.impact));
-                if let Some(ref stack_trace) = error.stack_trace {
-                    doc.push_str(&format!("   Stack Trace: {}\n", stack_trace));
-                }
-                doc.push_str("\n");
-            }
+        let mut warnings = String::new();
+        if !collection_audit.collection_warnings.is_empty() {
+            let entries: String = collection_audit.collection_warnings.iter().enumerate().map(|(i, warning)| {
+                report_lang::render(&audit.warning_entry, &[
+                    ("number", &(i + 1).to_string()),
+                    ("timestamp", &warning.timestamp),
+                    ("component", &warning.component),
+                    ("message", &warning.warning_message),
+                    ("recommendation", &warning.recommendation),
+                ])
+            }).collect();
+            warnings = report_lang::render(&audit.warnings, &[("entries", &entries)]);
         }
         
-        if !evidence.collection_audit.collection_warnings.is_empty() {
-            doc.push_str("\nCollection Warnings:\n");
-            for (i, warning) in evidence.collection_audit.collection_warnings.iter().enumerate() {
-                doc.push_str(&format!("{}. {} - {}\n", i + 1, warning.timestamp, warning.component));
-                doc.push_str(&format!("   Warning: {}\n", warning.warning_message));
-                doc.push_str(&format!("   Recommendation: {}\n", warning.recommendation));
-                doc.push_str("\n");
-            }
+        let mut external_commands = String::new();
+        if !collection_audit.external_commands.is_empty() {
+            let entries: String = collection_audit.external_commands.iter().enumerate().map(|(i, command)| {
+                let exit_code = command.exit_code.map(|code| code.to_string()).unwrap_or_else(|| "-".to_string());
+                let stderr = command.error.as_deref().unwrap_or(&command.stderr);
+                report_lang::render(&audit.external_command_entry, &[
+                    ("number", &(i + 1).to_string()),
+                    ("timestamp", &command.timestamp),
+                    ("component", &command.component),
+                    ("command", &logger::command_line(command)),
+                    ("exit_code", &exit_code),
+                    ("duration_ms", &command.duration_ms.to_string()),
+                    ("stdout", &command_output(&audit.external_stdout, &command.stdout, command.output_truncated)),
+                    ("stderr", &command_output(&audit.external_stderr, stderr, command.output_truncated)),
+                ])
+            }).collect();
+            external_commands = report_lang::render(&audit.external_commands, &[("entries", &entries)]);
         }
         
-        doc.push_str("\nDetailed Audit Log:\n");
-        doc.push_str("-" .repeat(80).as_str());
-        doc.push_str("\n");
+        let entries: String = collection_audit.audit_log.iter().map(|entry| {
+            let duration = entry.duration_ms
+                .map(|duration| report_lang::render(&audit.log_duration, &[("duration_ms", &duration.to_string())]))
+                .unwrap_or_default();
+            report_lang::render(&audit.log_entry, &[
+                ("timestamp", &entry.timestamp),
+                ("level", &entry.level),
+                ("component", &entry.component),
+                ("action", &entry.action),
+                ("details", &entry.details),
+                ("duration", &duration),
+                ("result", &entry.result),
+            ])
+        }).collect();
         
-        for entry in &evidence.collection_audit.audit_log {
-            doc.push_str(&format!("[{}] {} - {} - {}\n", 
-                entry.timestamp, entry.level, entry.component, entry.action));
-            doc.push_str(&format!("  Details: {}\n", entry.details));
-            if let Some(duration) = entry.duration_ms {
-                doc.push_str(&format!("  Duration: {} ms\n", duration));
-            }
-            doc.push_str(&format!("  Result: {}\n", entry.result));
-            doc.push_str("\n");
-        }
+        let stats = &collection_audit.collection_statistics;
+        Ok(report_lang::render(&audit.document, &[
+            ("start_time", &collection_audit.collection_start),
+            ("end_time", &collection_audit.collection_end),
+            ("duration", &collection_audit.total_duration_seconds.to_string()),
+            ("method", &collection_audit.collection_method),
+            ("tool_version", &collection_audit.tool_version),
+            ("command_line", &collection_audit.command_line),
+            ("working_directory", &collection_audit.working_directory),
+            ("total_processes", &stats.total_processes.to_string()),
+            ("total_network_connections", &stats.total_network_connections.to_string()),
+            ("total_files_analyzed", &stats.total_files_analyzed.to_string()),
+            ("total_registry_keys", &stats.total_registry_keys.to_string()),
+            ("total_event_log_entries", &stats.total_event_log_entries.to_string()),
+            ("total_prefetch_files", &stats.total_prefetch_files.to_string()),
+            ("total_scheduled_tasks", &stats.total_scheduled_tasks.to_string()),
+            ("peak_memory_mb", &format!("{:.2}", stats.memory_usage_peak_mb)),
+            ("disk_space_mb", &format!("{:.2}", stats.disk_space_used_mb)),
+            ("errors", &errors),
+            ("warnings", &warnings),
+            ("external_commands", &external_commands),
+            ("entries", &entries),
+        ]))
+    }
+    
+    fn create_readme_document(&self, evidence: &ForensicEvidence, encrypted: bool, templates: &ReportTemplates) -> Result<String, Box<dyn std::error::Error>> {
+        let metadata = &evidence.case_metadata;
+        let created = chrono::Utc::now().to_rfc3339();
         
-        Ok(doc)
-    }
-    
-    fn create_readme_document(&self, evidence: &ForensicEvidence) -> Result<String, Box<dyn std::error::Error>> {
-        let readme = format!(
-            "TriageIR Forensic Evidence Package\n\
-            ==================================\n\n\
-            This archive contains digital forensic evidence collected from a live Windows system.\n\n\
-            Package Information:\n\
-            - Case ID: {}\n\
-            - Evidence ID: {}\n\
-            - Collection Date: {}\n\
-            - Tool Version: TriageIR v{}\n\
-            - Package Created: {}\n\n\
-            Contents:\n\
-            - evidence.json: Complete forensic data in JSON format\n\
-            - chain_of_custody.txt: Chain of custody documentation\n\
-            - collection_audit.txt: Detailed collection audit log\n\
-            - integrity_verification.txt: Hash verification information\n\
-            - digital_signature_info.txt: Digital signature information\n\
-            - README.txt: This file\n\n\
-            Integrity Verification:\n\
-            1. Extract evidence.json from this archive\n\
-            2. Calculate SHA-256 hash of the file\n\
-            3. Compare with hash in integrity_verification.txt\n\
-            4. Hashes must match exactly\n\n\
-            Security Notice:\n\
-            - This evidence package may contain sensitive information\n\
-            - Handle according to your organization's data protection policies\n\
-            - Maintain proper chain of custody at all times\n\
-            - Do not modify any files within this archive\n\n\
-            Legal Notice:\n\
-            - This evidence was collected under proper legal authority\n\
-            - Unauthorized access or distribution is prohibited\n\
-            - Evidence must be handled by authorized personnel only\n\n\
-            Technical Support:\n\
-            - Tool Documentation: See TriageIR user manual\n\
-            - Evidence Format: JSON with forensic data structures\n\
-            - Compatibility: Standard forensic analysis tools\n\n\
-            For questions about this evidence package, contact:\n\
-            - Collector: {}\n\
-            - Organization: {}\n\
-            - Contact: {}\n\n\
-            Generated by TriageIR v{} on {}\n",
-            evidence.case_metadata.case_id,
-            evidence.case_metadata.evidence_id,
-            evidence.case_metadata.collection_timestamp,
-            env!("CARGO_PKG_VERSION"),
-            chrono::Utc::now().to_rfc3339(),
-            evidence.case_metadata.collector_info.name,
-            evidence.case_metadata.collector_info.organization,
-            evidence.case_metadata.collector_info.contact,
-            env!("CARGO_PKG_VERSION"),
-            chrono::Utc::now().to_rfc3339()
-        );
+        let mut readme = report_lang::render(&templates.readme.document, &[
+            ("case_id", &metadata.case_id),
+            ("evidence_id", &metadata.evidence_id),
+            ("collection_date", &metadata.collection_timestamp),
+            ("version", env!("CARGO_PKG_VERSION")),
+            ("created", &created),
+            ("collector_name", &metadata.collector_info.name),
+            ("collector_organization", &metadata.collector_info.organization),
+            ("collector_contact", &metadata.collector_info.contact),
+        ]);
+        
+        if encrypted {
+            readme.push_str(&report_lang::render(&templates.readme.encrypted, &[
+                ("iterations", &PBKDF2_ITERATIONS.to_string()),
+            ]));
+        }
         
         Ok(readme)
     }
@@ -422,44 +646,411 @@ pub fn create_evidence_package(
     packager.package_evidence(evidence)
 }
 
-/// Verify evidence package integrity
+fn signing_audit_entry(signature: &str, signer: &PackageSigner) -> AuditEntry {
+    let info = signer.info();
+    AuditEntry {
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        level: "INFO".to_string(),
+        component: "evidence_packager".to_string(),
+        action: "sign_package".to_string(),
+        details: format!("Created {} signed by {} (thumbprint {})", signature, info.subject, info.thumbprint),
+        duration_ms: None,
+        result: "success".to_string(),
+    }
+}
+
+/// Indented output of an external command for collection_audit.txt; empty when it wrote nothing
+fn command_output(template: &str, output: &str, truncated: bool) -> String {
+    let output = output.trim_end();
+    if output.is_empty() {
+        return String::new();
+    }
+    let mut indented: Vec<String> = output.lines().map(|line| format!("      {}", line)).collect();
+    if truncated {
+        indented.push("      [...]".to_string());
+    }
+    report_lang::render(template, &[("output", &indented.join("\n"))])
+}
+
+/// Encrypt evidence with AES-256-CBC using a PBKDF2-HMAC-SHA256 derived key and IV.
+/// Output uses the OpenSSL salted layout: `Salted__` || salt (8 bytes) || ciphertext,
+/// followed by HMAC-SHA256(salt || IV || ciphertext) under a MAC key derived with
+/// them. Without the last 32 bytes the file decrypts with `openssl enc -d`.
+pub fn encrypt_evidence(plaintext: &[u8], password: &str) -> Vec<u8> {
+    let mut salt = [0u8; 8];
+    thread_rng().fill(&mut salt);
+    let (cipher, iv, mac_key) = derive_keys(password, &salt);
+    let mut chain = iv;
+    
+    // PKCS#7 padding always adds at least one byte
+    let padding = AES_BLOCK_SIZE - plaintext.len() % AES_BLOCK_SIZE;
+    let mut padded = plaintext.to_vec();
+    padded.resize(plaintext.len() + padding, padding as u8);
+    
+    let mut output = Vec::with_capacity(SALTED_MAGIC.len() + salt.len() + padded.len());
+    output.extend_from_slice(SALTED_MAGIC);
+    output.extend_from_slice(&salt);
+    
+    for chunk in padded.chunks(AES_BLOCK_SIZE) {
+        let mut block = GenericArray::clone_from_slice(chunk);
+        for (byte, previous) in block.iter_mut().zip(chain.iter()) {
+            *byte ^= previous;
+        }
+        cipher.encrypt_block(&mut block);
+        chain.copy_from_slice(&block);
+        output.extend_from_slice(&block);
+    }
+    
+    let tag = authentication_tag(&mac_key, &salt, &iv, &output[SALTED_MAGIC.len() + salt.len()..]);
+    output.extend_from_slice(&tag);
+    output
+}
+
+/// Decrypt evidence produced by `encrypt_evidence`; the tag is checked first, so
+/// a wrong password or any change to the file is reported before decrypting
+pub fn decrypt_evidence(data: &[u8], password: &str) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let header_len = SALTED_MAGIC.len() + 8;
+    if data.len() < header_len + MAC_SIZE || &data[..SALTED_MAGIC.len()] != SALTED_MAGIC {
+        return Err("Encrypted evidence is missing the salted header".into());
+    }
+    let (ciphertext, tag) = data[header_len..].split_at(data.len() - header_len - MAC_SIZE);
+    if ciphertext.is_empty() || ciphertext.len() % AES_BLOCK_SIZE != 0 {
+        return Err("Encrypted evidence has an invalid length".into());
+    }
+    
+    let salt = &data[SALTED_MAGIC.len()..header_len];
+    let (cipher, iv, mac_key) = derive_keys(password, salt);
+    let expected = authentication_tag(&mac_key, salt, &iv, ciphertext);
+    // Compared without an early exit
+    if expected.iter().zip(tag).fold(0u8, |difference, (a, b)| difference | (a ^ b)) != 0 {
+        return Err("Failed to authenticate evidence (wrong password or tampered package)".into());
+    }
+    let mut chain = iv;
+    let mut plaintext = Vec::with_capacity(ciphertext.len());
+    
+    for chunk in ciphertext.chunks(AES_BLOCK_SIZE) {
+        let mut block = GenericArray::clone_from_slice(chunk);
+        cipher.decrypt_block(&mut block);
+        for (byte, previous) in block.iter_mut().zip(chain.iter()) {
+            *byte ^= previous;
+        }
+        chain.copy_from_slice(chunk);
+        plaintext.extend_from_slice(&block);
+    }
+    
+    let padding = *plaintext.last().unwrap_or(&0) as usize;
+    if padding == 0
+        || padding > AES_BLOCK_SIZE
+        || !plaintext[plaintext.len() - padding..].iter().all(|&b| b as usize == padding)
+    {
+        return Err("Failed to decrypt evidence (wrong password or corrupted package)".into());
+    }
+    plaintext.truncate(plaintext.len() - padding);
+    
+    Ok(plaintext)
+}
+
+/// Cipher, IV and MAC key; PBKDF2 output is prefix-stable, so the key and IV
+/// are the ones `openssl enc -pbkdf2 -md sha256` derives
+fn derive_keys(password: &str, salt: &[u8]) -> (Aes256, [u8; AES_BLOCK_SIZE], [u8; MAC_SIZE]) {
+    let mut key_iv = [0u8; 32 + AES_BLOCK_SIZE + MAC_SIZE];
+    pbkdf2_hmac::<Sha256Hash>(password.as_bytes(), salt, PBKDF2_ITERATIONS, &mut key_iv);
+    
+    let cipher = Aes256::new(GenericArray::from_slice(&key_iv[..32]));
+    let mut iv = [0u8; AES_BLOCK_SIZE];
+    iv.copy_from_slice(&key_iv[32..32 + AES_BLOCK_SIZE]);
+    let mut mac_key = [0u8; MAC_SIZE];
+    mac_key.copy_from_slice(&key_iv[32 + AES_BLOCK_SIZE..]);
+    
+    (cipher, iv, mac_key)
+}
+
+fn authentication_tag(mac_key: &[u8], salt: &[u8], iv: &[u8], ciphertext: &[u8]) -> Vec<u8> {
+    let mut message = Vec::with_capacity(salt.len() + iv.len() + ciphertext.len());
+    message.extend_from_slice(salt);
+    message.extend_from_slice(iv);
+    message.extend_from_slice(ciphertext);
+    hmac_sha256_bytes(mac_key, &message)
+}
+
+/// Verify an evidence package: every file listed in integrity_verification.txt
+/// must be in the archive with the recorded SHA-256 (encrypted files are checked
+/// as stored and, decrypted with `password`, against their plaintext hash), the
+/// evidence must match the manifest's evidence hash and be a valid scan result
+/// or forensic evidence document, and chain_of_custody.txt must describe the
+/// case, evidence and custody entries of the manifest and case_metadata.json.
+/// Multi-volume packages are named by their archive, volume manifest or a volume.
+/// Returns the discrepancies; an empty list means the package is intact.
 pub fn verify_evidence_package(
     package_path: &Path,
     password: &str,
-) -> Result<bool, Box<dyn std::error::Error>> {
-    // This would implement package verification
-    // For now, just check if file exists and is readable
-    if !package_path.exists() {
-        return Err("Evidence package not found".into());
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    match package_volumes::open(package_path)? {
+        Ok(package) => verify_archive(package.path(), password),
+        Err(volume_discrepancies) => Ok(volume_discrepancies),
     }
+}
+
+fn verify_archive(archive_path: &Path, password: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut archive = open_package(archive_path)?;
+    let manifest = String::from_utf8(read_entry(&mut archive, "integrity_verification.txt")
+        .ok_or("integrity_verification.txt is missing from the package")?)?;
     
-    // In production, this would:
-    // 1. Extract the archive with password
-    // 2. Verify file hashes
-    // 3. Check digital signatures
-    // 4. Validate JSON structure
-    // 5. Verify chain of custody
+    // "- evidence.json: <sha256>" and "  screenshots/screen_1.png.enc: <sha256>"
+    let recorded: Vec<(String, String)> = manifest
+        .lines()
+        .filter_map(|line| line.trim_start().strip_prefix("- ").or_else(|| line.strip_prefix("  ")))
+        .filter_map(|entry| entry.rsplit_once(": "))
+        .filter(|(_, hash)| hash.len() == 64 && hash.bytes().all(|b| b.is_ascii_hexdigit()))
+        .map(|(name, hash)| (name.trim().to_string(), hash.to_ascii_lowercase()))
+        .collect();
+    if recorded.is_empty() {
+        return Err("integrity_verification.txt lists no file hashes".into());
+    }
     
-    Ok(true)
+    let mut discrepancies = Vec::new();
+    let mut evidence = None;
+    for (name, expected) in &recorded {
+        let encrypted_name = format!("{}.enc", name);
+        let content = match read_entry(&mut archive, name) {
+            Some(content) => content,
+            None => match read_entry(&mut archive, &encrypted_name) {
+                Some(_) if password.is_empty() => {
+                    discrepancies.push(format!("{}: encrypted, a password is needed to check it", name));
+                    continue;
+                }
+                Some(ciphertext) => match decrypt_evidence(&ciphertext, password) {
+                    Ok(plaintext) => plaintext,
+                    Err(e) => {
+                        discrepancies.push(format!("{}: {}", encrypted_name, e));
+                        continue;
+                    }
+                },
+                None => {
+                    discrepancies.push(format!("{}: missing from the package", name));
+                    continue;
+                }
+            },
+        };
+        let actual = hex::encode(Sha256::digest(&content));
+        if actual != *expected {
+            discrepancies.push(format!("{}: SHA-256 {} does not match the recorded {}", name, actual, expected));
+        }
+        if name == "evidence.json" {
+            evidence = Some(content);
+        }
+    }
+    
+    let mut case_metadata = read_entry(&mut archive, CASE_METADATA)
+        .map(|content| serde_json::from_slice::<CaseMetadata>(&content).map_err(|e| format!("{}: {}", CASE_METADATA, e)));
+    if let Some(evidence) = &evidence {
+        let actual = hex::encode(Sha256::digest(evidence));
+        if manifest_field(&manifest, "Evidence Hash").is_some_and(|hash| !hash.eq_ignore_ascii_case(&actual)) {
+            discrepancies.push(format!("evidence.json: SHA-256 {} does not match the manifest's evidence hash", actual));
+        }
+        match serde_json::from_slice::<Value>(evidence) {
+            Err(e) => discrepancies.push(format!("evidence.json: not valid JSON: {}", e)),
+            // Scan results, as written by --password
+            Ok(document) if document.get("scan_metadata").is_some() => {
+                discrepancies.extend(schema::validate_results(&document).iter().map(|error| format!("evidence.json: {}", error)));
+            }
+            Ok(document) => match serde_json::from_value::<ForensicEvidence>(document) {
+                Ok(forensic) if case_metadata.is_none() => case_metadata = Some(Ok(forensic.case_metadata)),
+                Ok(_) => {}
+                Err(e) => discrepancies.push(format!("evidence.json: neither scan results nor a forensic evidence document: {}", e)),
+            },
+        }
+    }
+    
+    match read_entry(&mut archive, "chain_of_custody.txt").map(String::from_utf8) {
+        Some(Ok(custody)) => match case_metadata {
+            Some(Ok(metadata)) => discrepancies.extend(custody_discrepancies(&manifest, &custody, &metadata)),
+            Some(Err(e)) => discrepancies.push(e),
+            // Packages written before case_metadata.json: the identifiers of the manifest
+            None => {
+                for field in ["Case ID", "Evidence ID"] {
+                    if let Some(value) = manifest_field(&manifest, field).filter(|value| !custody.contains(value)) {
+                        discrepancies.push(format!("chain_of_custody.txt: does not name the {} {} of the integrity manifest", field, value));
+                    }
+                }
+            }
+        },
+        Some(Err(_)) => discrepancies.push("chain_of_custody.txt: not valid UTF-8".to_string()),
+        None => discrepancies.push("chain_of_custody.txt: missing from the package".to_string()),
+    }
+    Ok(discrepancies)
 }
 
-/// Extract evidence from package
+/// The case and evidence IDs must agree between the manifest and the case metadata,
+/// and the (localized) custody document must name them and every custody entry
+fn custody_discrepancies(manifest: &str, custody: &str, metadata: &CaseMetadata) -> Vec<String> {
+    let mut discrepancies = Vec::new();
+    for (field, value) in [("Case ID", &metadata.case_id), ("Evidence ID", &metadata.evidence_id)] {
+        if manifest_field(manifest, field).is_some_and(|recorded| recorded != value) {
+            discrepancies.push(format!("{}: {} {} differs from the integrity manifest", CASE_METADATA, field, value));
+        }
+        if !custody.contains(value.as_str()) {
+            discrepancies.push(format!("chain_of_custody.txt: does not name the {} {}", field, value));
+        }
+    }
+    for (i, entry) in metadata.chain_of_custody.iter().enumerate() {
+        if !custody.contains(&entry.timestamp) || !custody.contains(&entry.action) {
+            discrepancies.push(format!("chain_of_custody.txt: custody entry {} ({} at {}) is missing", i + 1, entry.action, entry.timestamp));
+        }
+    }
+    discrepancies
+}
+
+/// Value of a "Name: value" header line of integrity_verification.txt
+fn manifest_field<'a>(manifest: &'a str, name: &str) -> Option<&'a str> {
+    manifest.lines()
+        .filter_map(|line| line.strip_prefix(name)?.strip_prefix(": "))
+        .map(str::trim)
+        .next()
+}
+
+fn open_package(package_path: &Path) -> Result<zip::ZipArchive<File>, Box<dyn std::error::Error>> {
+    let file = File::open(package_path).map_err(|e| format!("Failed to open {}: {}", package_path.display(), e))?;
+    Ok(zip::ZipArchive::new(file)?)
+}
+
+/// Outcome of each signature found in a package, named by where it was found
+pub type SignatureOutcomes = Vec<(String, Result<SignatureCheck, String>)>;
+
+/// Check the signatures of a package signed with `--signing-cert`: evidence.json.p7s
+/// in the archive (against the decrypted evidence) and `<archive>.zip.p7s` next to
+/// it. Returns (signature, outcome) per signature found; empty for unsigned packages.
+pub fn verify_package_signatures(
+    package_path: &Path,
+    password: &str,
+) -> Result<SignatureOutcomes, Box<dyn std::error::Error>> {
+    let package = match package_volumes::open(package_path)? {
+        Ok(package) => package,
+        Err(volume_discrepancies) => return Err(volume_discrepancies.join("; ").into()),
+    };
+    let mut archive = open_package(package.path())?;
+    let mut checks = Vec::new();
+    
+    if let Some(signature) = read_entry(&mut archive, package_signing::EVIDENCE_SIGNATURE) {
+        let evidence = match read_entry(&mut archive, "evidence.json") {
+            Some(evidence) => Ok(evidence),
+            None if password.is_empty() => Err("the evidence is encrypted, a password is needed to check it".to_string()),
+            None => read_entry(&mut archive, "evidence.json.enc")
+                .ok_or_else(|| "evidence.json is missing from the package".to_string())
+                .and_then(|ciphertext| decrypt_evidence(&ciphertext, password).map_err(|e| e.to_string())),
+        };
+        let outcome = evidence.and_then(|evidence| package_signing::verify(&signature, &evidence));
+        checks.push((package_signing::EVIDENCE_SIGNATURE.to_string(), outcome));
+    }
+    
+    let signature_file = package.archive_path.with_extension(package_signing::ARCHIVE_SIGNATURE_EXTENSION);
+    if let Ok(signature) = fs::read(&signature_file) {
+        let outcome = fs::read(package.path()).map_err(|e| e.to_string())
+            .and_then(|content| package_signing::verify(&signature, &content));
+        checks.push((signature_file.display().to_string(), outcome));
+    }
+    Ok(checks)
+}
+
+fn read_entry(archive: &mut zip::ZipArchive<File>, name: &str) -> Option<Vec<u8>> {
+    let mut entry = archive.by_name(name).ok()?;
+    let mut content = Vec::new();
+    entry.read_to_end(&mut content).ok()?;
+    Some(content)
+}
+
+/// Extract a verified evidence package into `output_directory`, decrypting the
+/// encrypted files with `password`, and read it back as `ForensicEvidence`.
+/// Packages failing `verify_evidence_package` are not extracted; multi-volume
+/// packages are named as for it. For packages of scan results (--password) the
+/// case metadata and custody chain come from case_metadata.json; the artifacts
+/// stay in the extracted evidence.json.
 pub fn extract_evidence_from_package(
     package_path: &Path,
     password: &str,
     output_directory: &Path,
 ) -> Result<ForensicEvidence, Box<dyn std::error::Error>> {
-    // This would implement evidence extraction
-    // For now, return a placeholder
-    
-    // In production, this would:
-    // 1. Verify package integrity
-    // 2. Extract with password
-    // 3. Verify extracted files
-    // 4. Parse evidence.json
-    // 5. Return ForensicEvidence structure
+    let discrepancies = match package_volumes::open(package_path)? {
+        Ok(package) => match verify_archive(package.path(), password)? {
+            discrepancies if discrepancies.is_empty() => {
+                extract_archive(package.path(), password, output_directory)?;
+                return read_extracted_evidence(output_directory);
+            }
+            discrepancies => discrepancies,
+        },
+        Err(volume_discrepancies) => volume_discrepancies,
+    };
+    Err(format!("{} failed verification, nothing was extracted:\n  {}", package_path.display(), discrepancies.join("\n  ")).into())
+}
+
+fn extract_archive(archive_path: &Path, password: &str, output_directory: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let mut archive = open_package(archive_path)?;
+    fs::create_dir_all(output_directory)?;
+    for index in 0..archive.len() {
+        let mut entry = archive.by_index(index)?;
+        if entry.is_dir() {
+            continue;
+        }
+        let name = entry.enclosed_name()
+            .map(Path::to_path_buf)
+            .ok_or_else(|| format!("{}: path leaves the extraction directory", entry.name()))?;
+        let mut content = Vec::new();
+        entry.read_to_end(&mut content)?;
+        
+        let (name, content) = match name.to_str().and_then(|name| name.strip_suffix(".enc")) {
+            Some(plaintext_name) if !password.is_empty() => (PathBuf::from(plaintext_name), decrypt_evidence(&content, password)?),
+            _ => (name, content),
+        };
+        let destination = output_directory.join(name);
+        if let Some(parent) = destination.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&destination, content)?;
+    }
+    Ok(())
+}
+
+fn read_extracted_evidence(output_directory: &Path) -> Result<ForensicEvidence, Box<dyn std::error::Error>> {
+    let evidence_json = fs::read(output_directory.join("evidence.json"))?;
+    if let Ok(evidence) = serde_json::from_slice::<ForensicEvidence>(&evidence_json) {
+        return Ok(evidence);
+    }
+    let scan: Value = serde_json::from_slice(&evidence_json)?;
+    let manifest = fs::read_to_string(output_directory.join("integrity_verification.txt"))?;
+    let case_metadata = match fs::read(output_directory.join(CASE_METADATA)) {
+        Ok(content) => Some(serde_json::from_slice(&content)?),
+        Err(_) => None,
+    };
+    Ok(scan_package_evidence(&scan, &manifest, case_metadata))
+}
+
+/// `ForensicEvidence` of a package of scan results; case metadata missing from
+/// older packages is taken from the manifest and the scan metadata
+fn scan_package_evidence(scan: &Value, manifest: &str, case_metadata: Option<CaseMetadata>) -> ForensicEvidence {
+    let scan_field = |name: &str| scan.pointer(&format!("/scan_metadata/{}", name)).and_then(Value::as_str).unwrap_or_default().to_string();
+    let manifest_value = |name: &str| manifest_field(manifest, name).unwrap_or_default().to_string();
     
-    Err("Evidence extraction not yet implemented".into())
+    let mut evidence = ForensicEvidence::new(manifest_value("Case ID"), CollectorInfo {
+        name: "Unknown".to_string(),
+        organization: "Unknown".to_string(),
+        contact: "Unknown".to_string(),
+        tool_version: scan_field("cli_version"),
+        collection_host: scan_field("hostname"),
+    });
+    match case_metadata {
+        Some(case_metadata) => evidence.case_metadata = case_metadata,
+        None => {
+            evidence.case_metadata.evidence_id = manifest_value("Evidence ID");
+            evidence.case_metadata.collection_timestamp = manifest_value("Collection Time");
+            evidence.case_metadata.target_system.hostname = scan_field("hostname");
+            evidence.case_metadata.target_system.os_version = scan_field("os_version");
+        }
+    }
+    evidence.collection_audit.collection_start = scan_field("scan_start_utc");
+    evidence.collection_audit.tool_version = scan_field("cli_version");
+    evidence.integrity_verification.evidence_hash = manifest_value("Evidence Hash");
+    evidence.integrity_verification.hash_algorithm = "SHA-256".to_string();
+    evidence
 }
 
 /// Generate secure random password for evidence packages
@@ -476,4 +1067,226 @@ pub fn generate_evidence_password(length: usize) -> String {
             CHARSET[idx] as char
         })
         .collect()
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_encrypt_decrypt_roundtrip() {
+        let plaintext = br#"{"scan_metadata":{"hostname":"WORKSTATION"}}"#;
+        let encrypted = encrypt_evidence(plaintext, "case-password");
+        
+        assert_eq!(&encrypted[..8], SALTED_MAGIC);
+        assert_eq!((encrypted.len() - 16 - MAC_SIZE) % AES_BLOCK_SIZE, 0);
+        assert!(!encrypted.windows(11).any(|w| w == b"WORKSTATION"));
+        
+        let decrypted = decrypt_evidence(&encrypted, "case-password").unwrap();
+        assert_eq!(decrypted, plaintext);
+    }
+    
+    #[test]
+    fn test_decrypt_rejects_wrong_password() {
+        let encrypted = encrypt_evidence(b"evidence", "correct");
+        assert!(decrypt_evidence(&encrypted, "incorrect").unwrap_err().to_string().contains("authenticate"));
+    }
+    
+    #[test]
+    fn test_decrypt_rejects_tampering() {
+        let encrypted = encrypt_evidence(&[b'A'; 64], "pw");
+        for position in [9, 20, encrypted.len() - 40, encrypted.len() - 1] {
+            let mut tampered = encrypted.clone();
+            tampered[position] ^= 0x01;
+            assert!(decrypt_evidence(&tampered, "pw").is_err(), "byte {} changed", position);
+        }
+        assert!(decrypt_evidence(&encrypted[..encrypted.len() - AES_BLOCK_SIZE], "pw").is_err());
+    }
+    
+    #[test]
+    fn test_decrypt_rejects_malformed_input() {
+        assert!(decrypt_evidence(b"not encrypted", "password").is_err());
+        assert!(decrypt_evidence(b"Salted__12345678abc", "password").is_err());
+    }
+    
+    #[test]
+    fn test_localized_documents() {
+        use crate::forensic_types::CollectorInfo;
+        
+        let collector = CollectorInfo {
+            name: "analyst".to_string(),
+            organization: "CERT".to_string(),
+            contact: "cert@example.org".to_string(),
+            tool_version: "1.0.0".to_string(),
+            collection_host: "WS01".to_string(),
+        };
+        let mut evidence = ForensicEvidence::new("CASE-7".to_string(), collector);
+        evidence.case_metadata.legal_authority = Some("Beschluss 12 Gs 34/24".to_string());
+        evidence.add_custody_entry("Evidence collected".to_string(), "analyst".to_string(), "CERT".to_string(), "{live}".to_string());
+        
+        let temp_dir = tempfile::tempdir().unwrap();
+        let packager = EvidencePackager::new("CASE-7".to_string(), temp_dir.path().to_path_buf(), "pw".to_string())
+            .unwrap()
+            .with_language(ReportLanguage::German);
+        let templates = ReportTemplates::load(packager.language).unwrap();
+        
+        let custody = packager.create_custody_document(&evidence, &templates).unwrap();
+        assert!(custody.starts_with("BEWEISMITTELKETTE"));
+        assert!(custody.contains("  Fall-ID: CASE-7\n  Beweismittel-ID: "));
+        assert!(custody.contains("  Rechtsgrundlage: Beschluss 12 Gs 34/24\n\nAngaben zur sichernden Person:"));
+        assert!(custody.contains("1. "));
+        assert!(custody.contains("   Anmerkungen: {live}\n"));
+        
+        let readme = packager.create_readme_document(&evidence, true, &templates).unwrap();
+        assert!(readme.contains("- Fall-ID: CASE-7\n"));
+        assert!(readme.contains("head -c -32 evidence.json.enc | openssl enc -d -aes-256-cbc -pbkdf2 -md sha256 -iter 100000 -out evidence.json"));
+        
+        let audit = packager.create_audit_document(&evidence, &ReportTemplates::load(ReportLanguage::English).unwrap()).unwrap();
+        assert!(audit.starts_with("COLLECTION AUDIT LOG\n"));
+        assert!(!audit.contains("Collection Errors:"));
+        assert!(!audit.contains("External Commands:"));
+        
+        evidence.collection_audit.external_commands.push(crate::forensic_types::ExternalCommand {
+            timestamp: "2026-10-16T12:00:00Z".to_string(),
+            component: "scheduled_tasks".to_string(),
+            program: "schtasks".to_string(),
+            arguments: vec!["/query".to_string(), "/xml".to_string(), "ONE".to_string()],
+            working_directory: None,
+            duration_ms: 840,
+            exit_code: Some(1),
+            stdout: String::new(),
+            stderr: "ERROR: Access is denied.\r\n".to_string(),
+            output_truncated: false,
+            error: None,
+        });
+        let audit = packager.create_audit_document(&evidence, &templates).unwrap();
+        assert!(audit.contains("\nExterne Befehle:\n1. 2026-10-16T12:00:00Z - scheduled_tasks\n   Befehl: schtasks /query /xml ONE\n   Exit-Code: 1\n   Dauer: 840 ms\n   Standardfehlerausgabe:\n      ERROR: Access is denied.\n\n"));
+        assert!(!audit.contains("Standardausgabe:"));
+    }
+    
+    #[test]
+    fn test_package_attachments() {
+        use crate::forensic_types::CollectorInfo;
+        
+        let collector = CollectorInfo {
+            name: "analyst".to_string(),
+            organization: "CERT".to_string(),
+            contact: "cert@example.org".to_string(),
+            tool_version: "1.0.0".to_string(),
+            collection_host: "WS01".to_string(),
+        };
+        let evidence = ForensicEvidence::new("CASE-8".to_string(), collector);
+        let screen = b"\x89PNG screen".to_vec();
+        
+        let temp_dir = tempfile::tempdir().unwrap();
+        let packager = EvidencePackager::new("CASE-8".to_string(), temp_dir.path().to_path_buf(), "pw".to_string())
+            .unwrap()
+            .with_attachments(vec![("screenshots/screen_1.png".to_string(), screen.clone())]);
+        let (archive_path, _) = packager.package_evidence(&evidence).unwrap();
+        
+        let mut archive = zip::ZipArchive::new(File::open(&archive_path).unwrap()).unwrap();
+        let mut encrypted = Vec::new();
+        archive.by_name("screenshots/screen_1.png.enc").unwrap().read_to_end(&mut encrypted).unwrap();
+        assert_eq!(decrypt_evidence(&encrypted, "pw").unwrap(), screen);
+        
+        let mut manifest = String::new();
+        archive.by_name("integrity_verification.txt").unwrap().read_to_string(&mut manifest).unwrap();
+        assert!(manifest.contains(&format!("- screenshots/screen_1.png: {}\n", hex::encode(sha2::Sha256::digest(&screen)))));
+        assert!(manifest.contains(&format!("  screenshots/screen_1.png.enc: {}\n", hex::encode(sha2::Sha256::digest(&encrypted)))));
+        
+        assert!(verify_evidence_package(&archive_path, "pw").unwrap().is_empty());
+        let discrepancies = verify_evidence_package(&archive_path, "").unwrap();
+        assert_eq!(discrepancies.len(), 2);
+        assert!(discrepancies[0].starts_with("evidence.json: encrypted"));
+    }
+    
+    #[test]
+    fn test_extract_package() {
+        let collector = CollectorInfo {
+            name: "analyst".to_string(),
+            organization: "CERT".to_string(),
+            contact: "cert@example.org".to_string(),
+            tool_version: "1.0.0".to_string(),
+            collection_host: "WS01".to_string(),
+        };
+        let mut evidence = ForensicEvidence::new("CASE-9".to_string(), collector);
+        evidence.add_custody_entry("Evidence collected".to_string(), "analyst".to_string(), "CERT".to_string(), "live".to_string());
+        evidence.add_custody_entry("Evidence packaged".to_string(), "analyst".to_string(), "CERT".to_string(), String::new());
+        
+        let temp_dir = tempfile::tempdir().unwrap();
+        let packager = EvidencePackager::new("CASE-9".to_string(), temp_dir.path().to_path_buf(), "pw".to_string()).unwrap();
+        let (archive_path, _) = packager.package_evidence(&evidence).unwrap();
+        assert!(verify_evidence_package(&archive_path, "pw").unwrap().is_empty());
+        
+        let extracted = temp_dir.path().join("extracted");
+        assert!(extract_evidence_from_package(&archive_path, "wrong", &extracted).is_err());
+        assert!(!extracted.exists());
+        let extracted_evidence = extract_evidence_from_package(&archive_path, "pw", &extracted).unwrap();
+        assert_eq!(extracted_evidence.case_metadata.evidence_id, evidence.case_metadata.evidence_id);
+        assert_eq!(extracted_evidence.case_metadata.chain_of_custody.len(), 2);
+        assert!(extracted.join("evidence.json").exists() && !extracted.join("evidence.json.enc").exists());
+        
+        // A custody document that lost an entry or names another case
+        let manifest = fs::read_to_string(extracted.join("integrity_verification.txt")).unwrap();
+        let custody = fs::read_to_string(extracted.join("chain_of_custody.txt")).unwrap();
+        assert!(custody_discrepancies(&manifest, &custody, &evidence.case_metadata).is_empty());
+        let truncated = &custody[..custody.find("Evidence packaged").unwrap()];
+        assert_eq!(custody_discrepancies(&manifest, truncated, &evidence.case_metadata).len(), 1);
+        evidence.case_metadata.case_id = "CASE-10".to_string();
+        assert_eq!(custody_discrepancies(&manifest, &custody, &evidence.case_metadata).len(), 2);
+        
+        // Split into volumes, named by the volume manifest or the archive
+        let packager = packager.with_max_volume_size(Some(2048));
+        let (manifest_path, _) = packager.package_evidence(&evidence).unwrap();
+        assert!(manifest_path.to_string_lossy().ends_with(".zip.volumes.json"));
+        assert!(verify_evidence_package(&manifest_path, "pw").unwrap().is_empty());
+        let archive_path = manifest_path.with_extension("").with_extension("");
+        assert!(!archive_path.exists() && archive_path.with_extension("zip.001").exists());
+        let extracted_evidence = extract_evidence_from_package(&archive_path, "pw", &temp_dir.path().join("volumes")).unwrap();
+        assert_eq!(extracted_evidence.case_metadata.case_id, "CASE-10");
+    }
+    
+    #[test]
+    fn test_scan_package_evidence() {
+        let scan = serde_json::json!({"scan_metadata": {"hostname": "WS01", "os_version": "Windows 11", "cli_version": "1.0.0", "scan_start_utc": "2026-10-16T10:00:00Z"}});
+        let manifest = "Case ID: CASE-1\nEvidence ID: 42\nCollection Time: 2026-10-16T10:05:00Z\nEvidence Hash: abc\n";
+        
+        let evidence = scan_package_evidence(&scan, manifest, None);
+        assert_eq!(evidence.case_metadata.case_id, "CASE-1");
+        assert_eq!(evidence.case_metadata.evidence_id, "42");
+        assert_eq!(evidence.case_metadata.target_system.hostname, "WS01");
+        assert_eq!(evidence.collection_audit.collection_start, "2026-10-16T10:00:00Z");
+        assert_eq!(evidence.integrity_verification.evidence_hash, "abc");
+    }
+    
+    #[test]
+    fn test_package_compression() {
+        assert_eq!(PackageCompression::Zstd.check_level(None), Ok(Some(3)));
+        assert_eq!(PackageCompression::Zstd.check_level(Some(-5)), Ok(Some(-5)));
+        assert!(PackageCompression::Deflate.check_level(Some(12)).is_err());
+        assert!(PackageCompression::Store.check_level(Some(1)).is_err());
+        assert_eq!(PackageCompression::parse("ZSTD"), Some(PackageCompression::Zstd));
+        
+        let collector = CollectorInfo {
+            name: "analyst".to_string(),
+            organization: "CERT".to_string(),
+            contact: "cert@example.org".to_string(),
+            tool_version: "1.0.0".to_string(),
+            collection_host: "WS01".to_string(),
+        };
+        let evidence = ForensicEvidence::new("CASE-11".to_string(), collector);
+        let temp_dir = tempfile::tempdir().unwrap();
+        let packager = EvidencePackager::new("CASE-11".to_string(), temp_dir.path().to_path_buf(), String::new())
+            .unwrap()
+            .with_compression(PackageCompression::Zstd, Some(19))
+            .unwrap();
+        let (archive_path, _) = packager.package_evidence(&evidence).unwrap();
+        assert!(verify_evidence_package(&archive_path, "").unwrap().is_empty());
+        
+        let mut archive = zip::ZipArchive::new(File::open(&archive_path).unwrap()).unwrap();
+        assert_eq!(archive.by_name("evidence.json").unwrap().compression(), CompressionMethod::Zstd);
+        assert_eq!(archive.by_name("README.txt").unwrap().compression(), CompressionMethod::Deflated);
+        let mut manifest = String::new();
+        archive.by_name("integrity_verification.txt").unwrap().read_to_string(&mut manifest).unwrap();
+        assert!(manifest.contains("\nCompression: zstd (level 19)\n"));
+    }
+}
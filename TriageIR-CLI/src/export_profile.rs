@@ -0,0 +1,240 @@
+//! Export profiles
+//! `--export-profile legal` bundles the options evidence for court proceedings
+//! needs behind one switch, so none of them is forgotten under time pressure:
+//! MD5, SHA-1 and SHA-256 of every hashed file (the digests case management
+//! systems and opposing experts check against), the raw supporting files copied
+//! into the case folder, the encrypted evidence package with its (localized)
+//! chain of custody, the signed case receipt with an RFC 3161 timestamp of the
+//! manifest, and a self-contained HTML report laid out for printing to PDF with
+//! page numbers and a signature block. Missing prerequisites (password, receipt
+//! directory, timestamp authority) are errors, never silently dropped.
+
+use crate::diff_report::escape;
+use crate::forensic_types::FileHashes;
+use crate::scan::ScanConfig;
+use serde_json::Value;
+use std::fmt::Write;
+use std::path::Path;
+
+/// Names accepted by --export-profile
+pub const PROFILES: [&str; 1] = ["legal"];
+
+/// Digests of the legal profile, in addition to any --hash-algorithms
+const LEGAL_HASH_ALGORITHMS: &str = "md5,sha1,sha256";
+
+/// Folder below the case folder receiving the raw supporting files
+const RAW_DIRECTORY: &str = "raw";
+
+const STYLE: &str = "@page{size:A4;margin:18mm 15mm 20mm 15mm;\
+@top-right{content:\"Case \" string(case-id);font-size:8pt;color:#555}\
+@bottom-right{content:\"Page \" counter(page) \" of \" counter(pages);font-size:8pt}}\
+body{font-family:Segoe UI,Arial,sans-serif;font-size:10pt;color:#111}\
+h1{font-size:16pt;string-set:case-id attr(data-case)}h2{font-size:12pt;margin-top:1.5em;border-bottom:1px solid #999;page-break-after:avoid}\
+table{border-collapse:collapse;width:100%;margin:.5em 0}th,td{text-align:left;padding:3px 6px;border:1px solid #bbb;vertical-align:top}\
+thead{display:table-header-group}tr{page-break-inside:avoid}th{background:#eee}\
+td.mono{font-family:Consolas,monospace;font-size:8pt;word-break:break-all}\
+tr.critical td,tr.high td,tr.ERROR td{background:#fce8e6}tr.medium td,tr.WARN td{background:#fef7e0}\
+p.none{color:#555;font-style:italic}.signature td{height:3em}";
+
+/// The legal profile's changes to the scan settings; raw files go below `case_directory`.
+/// Returns one line per forced setting, for the collection log
+pub fn apply_legal_profile(config: &mut ScanConfig, case_directory: &Path) -> Vec<String> {
+    let mut applied = Vec::new();
+    config.hash_algorithms = Some(match config.hash_algorithms.take().filter(|list| !list.trim().is_empty()) {
+        Some(list) => format!("{},{}", LEGAL_HASH_ALGORITHMS, list),
+        None => LEGAL_HASH_ALGORITHMS.to_string(),
+    });
+    applied.push(format!("hash algorithms: {}", config.hash_algorithms.as_deref().unwrap_or_default()));
+    if config.raw_dir.is_none() {
+        config.raw_dir = Some(case_directory.join(RAW_DIRECTORY).display().to_string());
+    }
+    applied.push(format!("raw artifact files: {}", config.raw_dir.as_deref().unwrap_or_default()));
+    applied
+}
+
+/// Render the court report of a scan; `evidence_files` are the delivered outputs with their digests
+pub fn render_report(scan: &Value, case_id: &str, evidence_files: &[(String, u64, FileHashes)]) -> String {
+    let metadata = &scan["scan_metadata"];
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>Forensic triage report - {}</title>\n<style>{}</style>\n</head>\n<body>\n",
+        escape(case_id),
+        STYLE
+    );
+    let _ = writeln!(html, "<h1 data-case=\"{0}\">Forensic triage report - case {0}</h1>", escape(case_id));
+
+    html.push_str("<h2>Collection</h2>\n<table>\n");
+    let mode = match text(&metadata["offline_root"]) {
+        "" => "Live system".to_string(),
+        root => format!("Offline volume {}", root),
+    };
+    let window = match (text(&metadata["since"]), text(&metadata["until"])) {
+        ("", "") => "Complete".to_string(),
+        (since, until) => format!("{} to {}", or_open(since), or_open(until)),
+    };
+    let rows = [
+        ("Case ID", case_id.to_string()),
+        ("Scan ID", text(&metadata["scan_id"]).to_string()),
+        ("Host", text(&metadata["hostname"]).to_string()),
+        ("Operating system", text(&metadata["os_version"]).to_string()),
+        ("Collection started (UTC)", text(&metadata["scan_start_utc"]).to_string()),
+        ("Duration", format!("{:.1} s", metadata["scan_duration_ms"].as_u64().unwrap_or(0) as f64 / 1000.0)),
+        ("Source", mode),
+        ("Time window", window),
+        ("Collector", format!("TriageIR CLI {}", text(&metadata["cli_version"]))),
+        ("Hash algorithms", strings(&metadata["hash_algorithms"]).join(", ")),
+        ("Artifacts collected", metadata["total_artifacts"].as_u64().unwrap_or(0).to_string()),
+        ("Collection errors / warnings", format!(
+            "{} / {}",
+            metadata["collection_summary"]["error_count"].as_u64().unwrap_or(0),
+            metadata["collection_summary"]["warning_count"].as_u64().unwrap_or(0)
+        )),
+    ];
+    for (label, value) in rows {
+        let _ = writeln!(html, "<tr><th>{}</th><td>{}</td></tr>", label, escape(&value));
+    }
+    html.push_str("</table>\n");
+
+    html.push_str("<h2>Evidence files</h2>\n");
+    if evidence_files.is_empty() {
+        html.push_str("<p class=\"none\">No evidence files written</p>\n");
+    } else {
+        html.push_str("<table>\n<thead><tr><th>File</th><th>Size (bytes)</th><th>Digests</th></tr></thead>\n");
+        for (path, size, hashes) in evidence_files {
+            let digests: Vec<String> = [("MD5", &hashes.md5), ("SHA-1", &hashes.sha1), ("SHA-256", &hashes.sha256)]
+                .into_iter()
+                .filter_map(|(name, digest)| digest.as_ref().map(|digest| format!("{}: {}", name, escape(digest))))
+                .collect();
+            let _ = writeln!(html, "<tr><td class=\"mono\">{}</td><td>{}</td><td class=\"mono\">{}</td></tr>", escape(path), size, digests.join("<br>"));
+        }
+        html.push_str("</table>\n");
+    }
+
+    html.push_str("<h2>Findings</h2>\n");
+    let findings = array(&scan["findings"]);
+    if findings.is_empty() {
+        html.push_str("<p class=\"none\">No high-severity findings</p>\n");
+    } else {
+        html.push_str("<table>\n<thead><tr><th>ID</th><th>Severity</th><th>Source</th><th>Description</th></tr></thead>\n");
+        for finding in findings {
+            let severity = text(&finding["severity"]);
+            let _ = writeln!(
+                html,
+                "<tr class=\"{0}\"><td>{1}</td><td>{0}</td><td>{2}</td><td>{3}</td></tr>",
+                escape(severity),
+                escape(text(&finding["finding_id"])),
+                escape(text(&finding["source_artifact"])),
+                escape(text(&finding["description"]))
+            );
+        }
+        html.push_str("</table>\n");
+    }
+
+    let detections = array(&scan["detections"]);
+    if !detections.is_empty() {
+        html.push_str("<h2>Detection rule matches</h2>\n<table>\n<thead><tr><th>Rule</th><th>Severity</th><th>Matches</th><th>Techniques</th></tr></thead>\n");
+        for detection in detections {
+            let severity = text(&detection["severity"]);
+            let _ = writeln!(
+                html,
+                "<tr class=\"{0}\"><td>{1} ({2})</td><td>{0}</td><td>{3}</td><td>{4}</td></tr>",
+                escape(severity),
+                escape(text(&detection["rule_name"])),
+                escape(text(&detection["rule_id"])),
+                detection["match_count"].as_u64().unwrap_or(0),
+                escape(&strings(&detection["mitre_techniques"]).join(", "))
+            );
+        }
+        html.push_str("</table>\n");
+    }
+
+    let techniques: Vec<&Value> = array(&metadata["attack_techniques"]).iter().filter(|t| t["suspicious_count"].as_u64().unwrap_or(0) > 0).collect();
+    if !techniques.is_empty() {
+        html.push_str("<h2>ATT&amp;CK techniques of suspicious artifacts</h2>\n<table>\n<thead><tr><th>Technique</th><th>Tactic</th><th>Suspicious</th><th>Artifacts</th></tr></thead>\n");
+        for technique in techniques {
+            let _ = writeln!(
+                html,
+                "<tr><td>{} {}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                escape(text(&technique["technique_id"])),
+                escape(text(&technique["name"])),
+                escape(text(&technique["tactic"])),
+                technique["suspicious_count"].as_u64().unwrap_or(0),
+                technique["artifact_count"].as_u64().unwrap_or(0)
+            );
+        }
+        html.push_str("</table>\n");
+    }
+
+    html.push_str("<h2>Collection log</h2>\n<table>\n<thead><tr><th>Time (UTC)</th><th>Level</th><th>Message</th></tr></thead>\n");
+    for entry in array(&scan["collection_log"]) {
+        let _ = writeln!(
+            html,
+            "<tr class=\"{0}\"><td class=\"mono\">{1}</td><td>{0}</td><td>{2}</td></tr>",
+            escape(text(&entry["level"])),
+            escape(text(&entry["timestamp"])),
+            escape(text(&entry["message"]))
+        );
+    }
+    html.push_str("</table>\n");
+
+    html.push_str("<h2>Examiner</h2>\n<table class=\"signature\">\n<tr><th>Name</th><td></td></tr>\n<tr><th>Date</th><td></td></tr>\n<tr><th>Signature</th><td></td></tr>\n</table>\n");
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+fn or_open(bound: &str) -> &str {
+    if bound.is_empty() { "(open)" } else { bound }
+}
+
+fn array(value: &Value) -> &[Value] {
+    value.as_array().map(Vec::as_slice).unwrap_or_default()
+}
+
+fn strings(value: &Value) -> Vec<&str> {
+    array(value).iter().filter_map(Value::as_str).collect()
+}
+
+fn text(value: &Value) -> &str {
+    value.as_str().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_apply_legal_profile() {
+        let mut config = ScanConfig { hash_algorithms: Some("imphash".to_string()), ..Default::default() };
+        let applied = apply_legal_profile(&mut config, Path::new("case"));
+        assert_eq!(config.hash_algorithms.as_deref(), Some("md5,sha1,sha256,imphash"));
+        assert_eq!(config.raw_dir, Some(Path::new("case").join("raw").display().to_string()));
+        assert_eq!(applied.len(), 2);
+
+        let mut config = ScanConfig { raw_dir: Some("E:\\raw".to_string()), ..Default::default() };
+        apply_legal_profile(&mut config, Path::new("case"));
+        assert_eq!((config.hash_algorithms.as_deref(), config.raw_dir.as_deref()), (Some("md5,sha1,sha256"), Some("E:\\raw")));
+    }
+
+    #[test]
+    fn test_render_report() {
+        let scan = json!({
+            "scan_metadata": { "scan_id": "s-1", "hostname": "WKS01", "hash_algorithms": ["sha256", "md5"], "since": "2026-10-01T00:00:00Z",
+                               "attack_techniques": [{ "technique_id": "T1547.001", "name": "Registry Run Keys / Startup Folder", "tactic": "persistence", "suspicious_count": 1, "artifact_count": 4 }] },
+            "findings": [{ "finding_id": "F-001", "severity": "high", "source_artifact": "persistence_mechanisms", "description": "Run key <Updater>" }],
+            "collection_log": [{ "timestamp": "2026-10-16T09:00:00Z", "level": "WARN", "message": "Prefetch disabled" }]
+        });
+        let hashes = FileHashes { md5: Some("aa".to_string()), sha256: Some("cc".to_string()), ..Default::default() };
+        let html = render_report(&scan, "CASE-7", &[("evidence_s-1.zip".to_string(), 1024, hashes)]);
+
+        assert!(html.contains("counter(page) \" of \" counter(pages)"));
+        assert!(html.contains("<h1 data-case=\"CASE-7\">"));
+        assert!(html.contains("<tr><th>Time window</th><td>2026-10-01T00:00:00Z to (open)</td></tr>"));
+        assert!(html.contains("<td class=\"mono\">MD5: aa<br>SHA-256: cc</td>"));
+        assert!(html.contains("<tr class=\"high\"><td>F-001</td><td>high</td><td>persistence_mechanisms</td><td>Run key &lt;Updater&gt;</td></tr>"));
+        assert!(html.contains("T1547.001 Registry Run Keys"));
+        assert!(html.contains("<tr class=\"WARN\">"));
+        assert!(!html.contains("Detection rule matches"));
+    }
+}
@@ -0,0 +1,197 @@
+//! C ABI for embedding the collector in other endpoint agents
+//! Built into the cdylib (triageir_cli.dll / libtriageir_cli.so); the matching
+//! declarations are in include/triageir.h. All strings are NUL-terminated
+//! UTF-8. Calls are synchronous; run them on a worker thread to stay responsive.
+
+use crate::logger::Logger;
+use crate::scan::{self, ScanConfig};
+use std::cell::RefCell;
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+
+pub const TRIAGEIR_OK: i32 = 0;
+pub const TRIAGEIR_INVALID_ARGUMENT: i32 = 1;
+/// The scan completed and the output was written, but some collectors failed
+pub const TRIAGEIR_COMPLETED_WITH_ERRORS: i32 = 2;
+pub const TRIAGEIR_OUTPUT_FAILED: i32 = 3;
+/// A collector panicked (only reported by builds with panic = "unwind")
+pub const TRIAGEIR_INTERNAL_ERROR: i32 = 4;
+
+/// Progress callback: percent complete (0-100), status message, caller's user data
+pub type TriageirProgressCallback = Option<extern "C" fn(percent: u32, message: *const c_char, user_data: *mut c_void)>;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Run a scan and write the JSON result to `out_path`
+///
+/// `config_json` may be NULL for the defaults; otherwise it is an object with
+/// the optional keys `offline_root`, `since`, `until`, `event_channels`,
+/// `event_config`, `raw_dir`, `plugins_dir`, `collectors_config` and
+/// `shadow_copy` (the same settings as the CLI flags).
+///
+/// # Safety
+/// `config_json` and `out_path` must be NULL or valid NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn triageir_run_scan(
+    config_json: *const c_char,
+    out_path: *const c_char,
+    progress: TriageirProgressCallback,
+    user_data: *mut c_void,
+) -> i32 {
+    clear_last_error();
+
+    let config_json = if config_json.is_null() { Ok("{}") } else { CStr::from_ptr(config_json).to_str() };
+    let Ok(config_json) = config_json else {
+        return fail(TRIAGEIR_INVALID_ARGUMENT, "config_json is not valid UTF-8".to_string());
+    };
+    if out_path.is_null() {
+        return fail(TRIAGEIR_INVALID_ARGUMENT, "out_path must not be NULL".to_string());
+    }
+    let Ok(out_path) = CStr::from_ptr(out_path).to_str() else {
+        return fail(TRIAGEIR_INVALID_ARGUMENT, "out_path is not valid UTF-8".to_string());
+    };
+
+    match panic::catch_unwind(AssertUnwindSafe(|| run_scan_to_file(config_json, Path::new(out_path), progress, user_data))) {
+        Ok(Ok(code)) => code,
+        Ok(Err((code, message))) => fail(code, message),
+        Err(_) => fail(TRIAGEIR_INTERNAL_ERROR, "scan aborted by an internal error".to_string()),
+    }
+}
+
+/// Message describing the last failed call on this thread, or NULL
+///
+/// The pointer stays valid until the next `triageir_run_scan` call on the same thread.
+#[no_mangle]
+pub extern "C" fn triageir_last_error() -> *const c_char {
+    LAST_ERROR.with(|error| error.borrow().as_ref().map_or(std::ptr::null(), |message| message.as_ptr()))
+}
+
+/// Library version (static string)
+#[no_mangle]
+pub extern "C" fn triageir_version() -> *const c_char {
+    concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr() as *const c_char
+}
+
+fn run_scan_to_file(
+    config_json: &str,
+    out_path: &Path,
+    progress: TriageirProgressCallback,
+    user_data: *mut c_void,
+) -> Result<i32, (i32, String)> {
+    let config: ScanConfig = serde_json::from_str(config_json)
+        .map_err(|e| (TRIAGEIR_INVALID_ARGUMENT, format!("Invalid scan configuration: {}", e)))?;
+    let (options, warnings) = config.into_options().map_err(|e| (TRIAGEIR_INVALID_ARGUMENT, e))?;
+
+    let logger = Logger::new(false);
+    for warning in &warnings {
+        logger.warn(warning);
+    }
+
+    let outcome = scan::run_scan(&options, &logger, None, &mut |update| {
+        if let Some(callback) = progress {
+            let message = CString::new(update.message.replace('\0', " ")).unwrap_or_default();
+            callback(update.percent, message.as_ptr(), user_data);
+        }
+    });
+
+    let json_output = serde_json::to_string_pretty(&outcome.results)
+        .map_err(|e| (TRIAGEIR_OUTPUT_FAILED, format!("Failed to serialize scan results: {}", e)))?;
+    if let Some(parent) = out_path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)
+            .map_err(|e| (TRIAGEIR_OUTPUT_FAILED, format!("Failed to create {}: {}", parent.display(), e)))?;
+    }
+    fs::write(out_path, json_output)
+        .map_err(|e| (TRIAGEIR_OUTPUT_FAILED, format!("Failed to write {}: {}", out_path.display(), e)))?;
+
+    if outcome.log_summary.has_errors() {
+        set_last_error(format!("{} errors encountered during collection", outcome.log_summary.error_count));
+        Ok(TRIAGEIR_COMPLETED_WITH_ERRORS)
+    } else {
+        Ok(TRIAGEIR_OK)
+    }
+}
+
+fn fail(code: i32, message: String) -> i32 {
+    set_last_error(message);
+    code
+}
+
+fn set_last_error(message: String) {
+    let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|error| *error.borrow_mut() = Some(message));
+}
+
+fn clear_last_error() {
+    LAST_ERROR.with(|error| *error.borrow_mut() = None);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    static PERCENTAGES: Mutex<Vec<u32>> = Mutex::new(Vec::new());
+
+    extern "C" fn record_progress(percent: u32, message: *const c_char, user_data: *mut c_void) {
+        assert!(!message.is_null());
+        assert_eq!(user_data as usize, 42);
+        PERCENTAGES.lock().unwrap().push(percent);
+    }
+
+    fn last_error() -> String {
+        let error = triageir_last_error();
+        assert!(!error.is_null());
+        unsafe { CStr::from_ptr(error) }.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn test_invalid_arguments() {
+        let out_path = CString::new("unused.json").unwrap();
+        let config = CString::new(r#"{"since": "yesterday"}"#).unwrap();
+
+        let code = unsafe { triageir_run_scan(config.as_ptr(), out_path.as_ptr(), None, std::ptr::null_mut()) };
+        assert_eq!(code, TRIAGEIR_INVALID_ARGUMENT);
+        assert!(last_error().contains("--since"));
+
+        let config = CString::new(r#"{"unknown_key": 1}"#).unwrap();
+        let code = unsafe { triageir_run_scan(config.as_ptr(), out_path.as_ptr(), None, std::ptr::null_mut()) };
+        assert_eq!(code, TRIAGEIR_INVALID_ARGUMENT);
+        assert!(last_error().starts_with("Invalid scan configuration"));
+
+        let code = unsafe { triageir_run_scan(std::ptr::null(), std::ptr::null(), None, std::ptr::null_mut()) };
+        assert_eq!(code, TRIAGEIR_INVALID_ARGUMENT);
+        assert_eq!(last_error(), "out_path must not be NULL");
+    }
+
+    #[test]
+    fn test_offline_scan_writes_output() {
+        let volume = tempfile::tempdir().unwrap();
+        fs::create_dir(volume.path().join("Windows")).unwrap();
+        let out_dir = tempfile::tempdir().unwrap();
+        let out_path = out_dir.path().join("nested").join("scan.json");
+
+        let config = CString::new(serde_json::json!({ "offline_root": volume.path() }).to_string()).unwrap();
+        let out = CString::new(out_path.to_str().unwrap()).unwrap();
+
+        let code = unsafe { triageir_run_scan(config.as_ptr(), out.as_ptr(), Some(record_progress), 42 as *mut c_void) };
+        assert!(code == TRIAGEIR_OK || code == TRIAGEIR_COMPLETED_WITH_ERRORS);
+
+        let scan: serde_json::Value = serde_json::from_str(&fs::read_to_string(&out_path).unwrap()).unwrap();
+        assert_eq!(scan["scan_metadata"]["offline"], true);
+        assert!(scan["artifacts"]["running_processes"].as_array().unwrap().is_empty());
+
+        let percentages = PERCENTAGES.lock().unwrap();
+        assert_eq!(percentages.last(), Some(&100));
+        assert!(percentages.windows(2).all(|pair| pair[0] <= pair[1]));
+    }
+
+    #[test]
+    fn test_version() {
+        let version = unsafe { CStr::from_ptr(triageir_version()) };
+        assert_eq!(version.to_str().unwrap(), env!("CARGO_PKG_VERSION"));
+    }
+}
@@ -0,0 +1,301 @@
+//! On-demand file acquisition
+//! Copies selected files into `files/` of the --password evidence package: paths
+//! named with --collect-files first, then the binaries of suspicious persistence
+//! entries, executables of unsigned running processes and Prefetch files. Every
+//! copy is hashed and listed in files/manifest.json with its original size and
+//! timestamps, read before the copy touches the file. Files are taken in that
+//! order until the size budget is spent; the rest are listed as over budget.
+
+use crate::drivers;
+use crate::footprint;
+use crate::forensic_types::{AcquiredFile, AuditEntry};
+use crate::hashing;
+use crate::offline::OfflineRoot;
+use crate::persistence;
+use crate::types::{PersistenceMechanism, Process};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const COMPONENT: &str = "file_collection";
+
+/// Directory of the copied files inside the evidence package
+pub const PACKAGE_DIR: &str = "files";
+
+/// Default total size of the copied files (256 MiB)
+pub const DEFAULT_BUDGET_MB: u64 = 256;
+
+const PREFETCH_DIRECTORY: &str = "C:\\Windows\\Prefetch";
+
+/// Evidence package entry: archive path and content
+pub type PackageEntry = (String, Vec<u8>);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FileSelector {
+    Persistence,
+    Unsigned,
+    Prefetch,
+    Path(String),
+}
+
+/// Validated --collect-files settings
+#[derive(Debug, Clone)]
+pub struct CollectOptions {
+    pub selectors: Vec<FileSelector>,
+    pub budget: u64, // Bytes
+}
+
+impl CollectOptions {
+    /// Parse a `--collect-files` list: `persistence`, `unsigned`, `prefetch` or file paths
+    pub fn parse(entries: &[String], budget_mb: Option<u64>) -> Result<Self, String> {
+        let mut selectors = Vec::new();
+        for entry in entries.iter().map(|entry| entry.trim()).filter(|entry| !entry.is_empty()) {
+            let selector = match entry.to_lowercase().as_str() {
+                "persistence" => FileSelector::Persistence,
+                "unsigned" => FileSelector::Unsigned,
+                "prefetch" => FileSelector::Prefetch,
+                _ if entry.contains(['\\', '/']) => FileSelector::Path(entry.to_string()),
+                _ => return Err(format!("Unknown file selection '{}': expected persistence, unsigned, prefetch or a file path", entry)),
+            };
+            if !selectors.contains(&selector) {
+                selectors.push(selector);
+            }
+        }
+        if selectors.is_empty() {
+            return Err("the file collection list is empty".to_string());
+        }
+        let budget = budget_mb.unwrap_or(DEFAULT_BUDGET_MB).saturating_mul(1024 * 1024);
+        Ok(CollectOptions { selectors, budget })
+    }
+}
+
+/// Copy the selected files; returns the manifest records and the package
+/// attachments (archive path, content), files/manifest.json included
+pub fn collect_files(
+    options: &CollectOptions,
+    processes: &[Process],
+    mechanisms: &[PersistenceMechanism],
+    root: Option<&OfflineRoot>,
+) -> (Vec<AcquiredFile>, Vec<PackageEntry>, Vec<AuditEntry>) {
+    let start_time = std::time::Instant::now();
+    let mut audit_log = vec![AuditEntry::new(
+        COMPONENT,
+        "INFO",
+        "start_collection",
+        format!("Collecting files into the evidence package (budget {} bytes)", options.budget),
+        "started",
+    )];
+
+    let candidates = select_candidates(options, processes, mechanisms, root);
+    let hashes = hashing::shared();
+    let mut records = Vec::new();
+    let mut attachments = Vec::new();
+    let mut remaining = options.budget;
+
+    for (path, reason) in candidates {
+        let mut record = AcquiredFile {
+            source_path: path.display().to_string(),
+            archive_path: None,
+            reason: reason.to_string(),
+            status: "failed".to_string(),
+            size: 0,
+            hashes: None,
+            created: None,
+            modified: None,
+            accessed: None,
+            details: None,
+        };
+
+        // Timestamps first: reading the file may update its last access time
+        let metadata = match fs::metadata(&path) {
+            Ok(metadata) if metadata.is_file() => metadata,
+            Ok(_) => {
+                record.details = Some("not a file".to_string());
+                records.push(record);
+                continue;
+            }
+            Err(e) => {
+                record.details = Some(e.to_string());
+                records.push(record);
+                continue;
+            }
+        };
+        let rfc3339 = |time: std::io::Result<std::time::SystemTime>| time.ok().map(|t| chrono::DateTime::<chrono::Utc>::from(t).to_rfc3339());
+        record.size = metadata.len();
+        record.created = rfc3339(metadata.created());
+        record.modified = rfc3339(metadata.modified());
+        record.accessed = rfc3339(metadata.accessed());
+
+        if metadata.len() > remaining {
+            record.status = "over_budget".to_string();
+            record.details = Some(format!("{} bytes left of the budget", remaining));
+            records.push(record);
+            continue;
+        }
+
+        match footprint::read(&path) {
+            Ok(content) => {
+                let archive_path = format!("{}/{:04}_{}", PACKAGE_DIR, attachments.len() + 1, file_name(&path));
+                record.hashes = Some(hashes.hash_loaded_file(&path, &metadata, &content));
+                record.archive_path = Some(archive_path.clone());
+                record.status = "collected".to_string();
+                remaining -= metadata.len();
+                attachments.push((archive_path, content));
+            }
+            Err(e) => {
+                audit_log.push(AuditEntry::new(COMPONENT, "WARN", "copy_file", format!("Failed to read {}: {}", path.display(), e), "failed"));
+                record.details = Some(e.to_string());
+            }
+        }
+        records.push(record);
+    }
+
+    let over_budget = records.iter().filter(|r| r.status == "over_budget").count();
+    if over_budget > 0 {
+        audit_log.push(AuditEntry::new(COMPONENT, "WARN", "apply_budget", format!("{} files did not fit into the size budget", over_budget), "partial"));
+    }
+    match serde_json::to_vec_pretty(&records) {
+        Ok(manifest) => attachments.push((format!("{}/manifest.json", PACKAGE_DIR), manifest)),
+        Err(e) => audit_log.push(AuditEntry::new(COMPONENT, "ERROR", "write_manifest", e.to_string(), "failed")),
+    }
+
+    audit_log.push(AuditEntry {
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        level: "INFO".to_string(),
+        component: "file_collection".to_string(),
+        action: "complete_collection".to_string(),
+        details: format!(
+            "Collected {} of {} selected files ({} bytes)",
+            records.iter().filter(|r| r.status == "collected").count(),
+            records.len(),
+            options.budget - remaining
+        ),
+        duration_ms: Some(start_time.elapsed().as_millis() as u64),
+        result: "success".to_string(),
+    });
+
+    (records, attachments, audit_log)
+}
+
+/// Files to copy in priority order, each path once
+fn select_candidates(
+    options: &CollectOptions,
+    processes: &[Process],
+    mechanisms: &[PersistenceMechanism],
+    root: Option<&OfflineRoot>,
+) -> Vec<(PathBuf, &'static str)> {
+    let resolve = |path: &str| match root {
+        Some(root) => root.resolve(path),
+        None => PathBuf::from(path),
+    };
+    let system_root = match root {
+        Some(_) => "C:\\Windows".to_string(),
+        None => std::env::var("SystemRoot").unwrap_or_else(|_| "C:\\Windows".to_string()),
+    };
+
+    let mut candidates = Vec::new();
+    for selector in &options.selectors {
+        if let FileSelector::Path(path) = selector {
+            candidates.push((resolve(path), "user_path"));
+        }
+    }
+    if options.selectors.contains(&FileSelector::Persistence) {
+        for mechanism in mechanisms.iter().filter(|m| m.is_suspicious) {
+            if let Some(path) = persistence::mechanism_executable(mechanism, &system_root, root) {
+                candidates.push((path, "suspicious_persistence"));
+            }
+        }
+    }
+    if options.selectors.contains(&FileSelector::Unsigned) {
+        let mut checked = HashSet::new();
+        for process in processes.iter().filter(|p| p.has_executable_path()) {
+            if checked.insert(process.executable_path.to_lowercase())
+                && matches!(drivers::file_signature_status(&process.executable_path).as_str(), "unsigned" | "invalid")
+            {
+                candidates.push((PathBuf::from(&process.executable_path), "unsigned_process"));
+            }
+        }
+    }
+    if options.selectors.contains(&FileSelector::Prefetch) {
+        let mut prefetch_files: Vec<PathBuf> = footprint::read_dir(resolve(PREFETCH_DIRECTORY))
+            .map(|entries| entries.flatten().map(|entry| entry.path()).collect())
+            .unwrap_or_default();
+        prefetch_files.retain(|path| path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("pf")));
+        prefetch_files.sort();
+        candidates.extend(prefetch_files.into_iter().map(|path| (path, "prefetch")));
+    }
+
+    let mut seen = HashSet::new();
+    candidates.retain(|(path, _)| seen.insert(path.to_string_lossy().to_lowercase()));
+    candidates
+}
+
+/// Last path component, made safe for an archive entry name
+fn file_name(path: &Path) -> String {
+    let name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    let name: String = name.chars().map(|c| if c.is_control() || "\\/:*?\"<>|".contains(c) { '_' } else { c }).collect();
+    if name.is_empty() {
+        "unnamed".to_string()
+    } else {
+        name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_collect_options() {
+        let entries: Vec<String> = ["Persistence", "prefetch", "C:\\Users\\bob\\evil.exe", "prefetch"].iter().map(|e| e.to_string()).collect();
+        let options = CollectOptions::parse(&entries, Some(10)).unwrap();
+        assert_eq!(
+            options.selectors,
+            vec![FileSelector::Persistence, FileSelector::Prefetch, FileSelector::Path("C:\\Users\\bob\\evil.exe".to_string())]
+        );
+        assert_eq!(options.budget, 10 * 1024 * 1024);
+        assert!(CollectOptions::parse(&["memory".to_string()], None).is_err());
+        assert!(CollectOptions::parse(&[], None).is_err());
+    }
+
+    #[test]
+    fn test_collect_files_offline() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let prefetch = temp_dir.path().join("Windows").join("Prefetch");
+        fs::create_dir_all(&prefetch).unwrap();
+        fs::write(prefetch.join("EVIL.EXE-1234ABCD.pf"), vec![1u8; 600]).unwrap();
+        fs::write(prefetch.join("LAYOUT.INI"), b"not prefetch").unwrap();
+        fs::create_dir_all(temp_dir.path().join("Tools")).unwrap();
+        fs::write(temp_dir.path().join("Tools").join("evil.exe"), vec![2u8; 500]).unwrap();
+        let root = OfflineRoot::new(temp_dir.path()).unwrap();
+
+        let mechanism = PersistenceMechanism::new_with_location_value(
+            "Registry Run Key".to_string(),
+            "Updater".to_string(),
+            "\"C:\\Tools\\evil.exe\" /silent".to_string(),
+            "HKLM".to_string(),
+            String::new(),
+            String::new(),
+            true,
+        );
+        let options = CollectOptions {
+            selectors: vec![FileSelector::Path("C:\\Tools\\missing.dll".to_string()), FileSelector::Persistence, FileSelector::Prefetch],
+            budget: 1000,
+        };
+
+        let (records, attachments, _) = collect_files(&options, &[], &[mechanism], Some(&root));
+        let statuses: Vec<(&str, &str)> = records.iter().map(|r| (r.reason.as_str(), r.status.as_str())).collect();
+        assert_eq!(statuses, [("user_path", "failed"), ("suspicious_persistence", "collected"), ("prefetch", "over_budget")]);
+
+        let collected = &records[1];
+        assert_eq!(collected.archive_path.as_deref(), Some("files/0001_evil.exe"));
+        assert_eq!(collected.size, 500);
+        assert!(collected.modified.is_some());
+        assert_eq!(collected.hashes.as_ref().unwrap().sha256, hashing::HashService::default().hash_file(temp_dir.path().join("Tools").join("evil.exe")).unwrap().sha256);
+
+        let names: Vec<&str> = attachments.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["files/0001_evil.exe", "files/manifest.json"]);
+        let manifest: Vec<AcquiredFile> = serde_json::from_slice(&attachments[1].1).unwrap();
+        assert_eq!(manifest.len(), 3);
+    }
+}
@@ -0,0 +1,468 @@
+//! Targeted directory sweep
+//! Recursively hashes the files below the directories given with --scan-paths
+//! and matches them against an indicator list (hashes, file names, paths) and
+//! YARA rules, closing the gap between artifact collection and a disk sweep.
+//! Path components may contain * and ? wildcards (C:\Users\*\Downloads). Files
+//! are filtered by extension and size and the recursion depth is capped, so a
+//! sweep of a large tree stays bounded; symbolic links are not followed.
+
+// YARA rules are only matched in builds with the yara-engine feature
+use crate::footprint::{self, Access};
+use crate::forensic_types::{AuditEntry, SweptFile, YaraMatch};
+use crate::hashing;
+use crate::ioc_match;
+use crate::offline::OfflineRoot;
+use std::cell::RefCell;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::rc::Rc;
+use std::time::SystemTime;
+use walkdir::WalkDir;
+
+const COMPONENT: &str = "file_sweep";
+
+/// Default size limit for swept files (64 MiB)
+pub const DEFAULT_MAX_FILE_SIZE_MB: u64 = 64;
+
+/// Default number of directory levels below each scan path
+pub const DEFAULT_MAX_DEPTH: usize = 8;
+
+/// Default extension filter: executables, scripts, shortcuts and containers
+pub const DEFAULT_EXTENSIONS: [&str; 24] = [
+    "exe", "dll", "sys", "scr", "com", "cpl", "ocx", "msi", "ps1", "psm1", "bat", "cmd",
+    "vbs", "vbe", "js", "jse", "wsf", "hta", "lnk", "jar", "iso", "img", "zip", "7z",
+];
+
+/// Upper bound on the files hashed per scan
+const MAX_FILES: usize = 100_000;
+
+/// Seconds a YARA scan of one file may take
+#[cfg(feature = "yara-engine")]
+const YARA_TIMEOUT_SECS: i32 = 30;
+
+/// Bytes of matched data kept per YARA match
+#[cfg(feature = "yara-engine")]
+const MAX_MATCH_DATA: usize = 64;
+
+pub const YARA_UNAVAILABLE: &str = "YARA rules require a build with the yara-engine feature";
+
+/// Validated --scan-paths settings
+#[derive(Debug, Clone)]
+pub struct SweepOptions {
+    pub paths: Vec<String>,
+    pub max_file_size: u64,
+    pub max_depth: usize,
+    pub extensions: Option<Vec<String>>, // Lowercase, without the dot; None sweeps every file
+    pub indicators: Vec<String>,
+    pub yara_rules: Option<PathBuf>,
+}
+
+impl SweepOptions {
+    /// Parse a comma-separated extension list; `*` selects every file
+    pub fn parse_extensions(list: &str) -> Result<Option<Vec<String>>, String> {
+        let extensions: Vec<String> = list
+            .split(',')
+            .map(|ext| ext.trim().trim_start_matches('.').to_lowercase())
+            .filter(|ext| !ext.is_empty())
+            .collect();
+        if extensions.is_empty() {
+            return Err("the scan extension list is empty".to_string());
+        }
+        if extensions.iter().any(|ext| ext == "*") {
+            return Ok(None);
+        }
+        Ok(Some(extensions))
+    }
+
+    pub fn default_extensions() -> Option<Vec<String>> {
+        Some(DEFAULT_EXTENSIONS.iter().map(|ext| ext.to_string()).collect())
+    }
+
+    fn extension_selected(&self, path: &Path) -> bool {
+        match &self.extensions {
+            None => true,
+            Some(extensions) => path
+                .extension()
+                .map(|ext| ext.to_string_lossy().to_lowercase())
+                .is_some_and(|ext| extensions.contains(&ext)),
+        }
+    }
+}
+
+/// Hash and match the files below the scan paths
+pub fn sweep_paths(options: &SweepOptions, root: Option<&OfflineRoot>) -> (Vec<SweptFile>, Vec<AuditEntry>) {
+    let start_time = std::time::Instant::now();
+    let mut audit_log = vec![AuditEntry::new(
+        COMPONENT,
+        "INFO",
+        "start_sweep",
+        format!("Sweeping {} paths ({} indicators, depth {}, files up to {} bytes)", options.paths.len(), options.indicators.len(), options.max_depth, options.max_file_size),
+        "started",
+    )];
+
+    let scanner = match &options.yara_rules {
+        Some(rules_path) => match compiled_rules(rules_path) {
+            Ok(scanner) => Some(scanner),
+            Err(e) => {
+                audit_log.push(AuditEntry::new(COMPONENT, "ERROR", "load_yara_rules", e, "failed"));
+                None
+            }
+        },
+        None => None,
+    };
+    let needles: Vec<(String, &String)> = options
+        .indicators
+        .iter()
+        .map(|indicator| (indicator.trim().to_lowercase(), indicator))
+        .filter(|(needle, _)| !needle.is_empty())
+        .collect();
+    let hashes = hashing::shared();
+
+    let mut files = Vec::new();
+    let (mut filtered, mut oversized, mut failed) = (0, 0, 0);
+    'paths: for scan_path in &options.paths {
+        let directories = expand_path(&resolve(scan_path, root));
+        if directories.is_empty() {
+            audit_log.push(AuditEntry::new(COMPONENT, "WARN", "expand_path", format!("{} matched no directory", scan_path), "not_found"));
+        }
+
+        for directory in directories {
+            for entry in WalkDir::new(&directory).max_depth(options.max_depth).follow_links(false) {
+                let entry = match entry {
+                    Ok(entry) => entry,
+                    Err(e) => {
+                        failed += 1;
+                        audit_log.push(AuditEntry::new(COMPONENT, "DEBUG", "walk_directory", e.to_string(), "failed"));
+                        continue;
+                    }
+                };
+                if entry.file_type().is_dir() {
+                    footprint::record_file(entry.path(), Access::List);
+                }
+                if !entry.file_type().is_file() {
+                    continue;
+                }
+                if !options.extension_selected(entry.path()) {
+                    filtered += 1;
+                    continue;
+                }
+                let metadata = match entry.metadata() {
+                    Ok(metadata) => metadata,
+                    Err(e) => {
+                        failed += 1;
+                        audit_log.push(AuditEntry::new(COMPONENT, "DEBUG", "read_metadata", format!("{}: {}", entry.path().display(), e), "failed"));
+                        continue;
+                    }
+                };
+                if metadata.len() > options.max_file_size {
+                    oversized += 1;
+                    continue;
+                }
+                if files.len() >= MAX_FILES {
+                    audit_log.push(AuditEntry::new(COMPONENT, "WARN", "limit_files", format!("Stopped after {} files; narrow the scan paths or filters", MAX_FILES), "truncated"));
+                    break 'paths;
+                }
+
+                let path = entry.path();
+                let file_hashes = match hashes.hash_file(path) {
+                    Ok(file_hashes) => file_hashes,
+                    Err(e) => {
+                        failed += 1;
+                        audit_log.push(AuditEntry::new(COMPONENT, "WARN", "hash_file", e, "failed"));
+                        continue;
+                    }
+                };
+                let yara_matches = match &scanner {
+                    Some(scanner) => scanner.scan(path).unwrap_or_else(|e| {
+                        audit_log.push(AuditEntry::new(COMPONENT, "WARN", "yara_scan", e, "failed"));
+                        Vec::new()
+                    }),
+                    None => Vec::new(),
+                };
+
+                let mut file = SweptFile {
+                    path: path.display().to_string(),
+                    size: metadata.len(),
+                    modified: metadata.modified().ok().map(|time| chrono::DateTime::<chrono::Utc>::from(time).to_rfc3339()),
+                    hashes: file_hashes,
+                    ioc_matches: Vec::new(),
+                    yara_matches,
+                };
+                file.ioc_matches = matching_indicators(&file, &needles);
+                files.push(file);
+            }
+        }
+    }
+
+    for file in files.iter().filter(|f| !f.ioc_matches.is_empty() || !f.yara_matches.is_empty()) {
+        let rules: Vec<&str> = file.yara_matches.iter().map(|m| m.rule_name.as_str()).collect();
+        audit_log.push(AuditEntry::new(
+            COMPONENT,
+            "WARN",
+            "file_match",
+            format!("{} matched indicators [{}] and YARA rules [{}]", file.path, file.ioc_matches.join(", "), rules.join(", ")),
+            "flagged",
+        ));
+    }
+
+    let matched = files.iter().filter(|f| !f.ioc_matches.is_empty() || !f.yara_matches.is_empty()).count();
+    audit_log.push(AuditEntry {
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        level: "INFO".to_string(),
+        component: "file_sweep".to_string(),
+        action: "complete_sweep".to_string(),
+        details: format!(
+            "Hashed {} files, {} matched; {} skipped by extension, {} over the size limit, {} unreadable",
+            files.len(), matched, filtered, oversized, failed
+        ),
+        duration_ms: Some(start_time.elapsed().as_millis() as u64),
+        result: "success".to_string(),
+    });
+
+    (files, audit_log)
+}
+
+fn resolve(scan_path: &str, root: Option<&OfflineRoot>) -> PathBuf {
+    match root {
+        Some(root) => root.resolve(scan_path),
+        None => PathBuf::from(scan_path),
+    }
+}
+
+/// Existing directories matching a path whose components may contain wildcards
+fn expand_path(path: &Path) -> Vec<PathBuf> {
+    let mut candidates = vec![PathBuf::new()];
+    for component in path.components() {
+        let name = component.as_os_str().to_string_lossy();
+        let is_pattern = matches!(component, Component::Normal(_)) && name.contains(['*', '?']);
+        if !is_pattern {
+            candidates.iter_mut().for_each(|candidate| candidate.push(component));
+            continue;
+        }
+
+        let mut expanded = Vec::new();
+        for candidate in &candidates {
+            let Ok(entries) = footprint::read_dir(candidate) else {
+                continue;
+            };
+            let mut children: Vec<PathBuf> = entries
+                .flatten()
+                .filter(|entry| entry.file_type().is_ok_and(|t| t.is_dir()))
+                .filter(|entry| wildcard_match(&name.to_lowercase(), &entry.file_name().to_string_lossy().to_lowercase()))
+                .map(|entry| entry.path())
+                .collect();
+            children.sort();
+            expanded.extend(children);
+        }
+        candidates = expanded;
+    }
+    candidates.retain(|candidate| candidate.is_dir());
+    candidates
+}
+
+/// Glob-style match of a single path component: * matches any run of characters, ? one character
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, n));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            n = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Indicators found in the path or among the digests of a file
+fn matching_indicators(file: &SweptFile, needles: &[(String, &String)]) -> Vec<String> {
+    let path = file.path.to_lowercase();
+    let digests: Vec<String> = [&file.hashes.md5, &file.hashes.sha1, &file.hashes.sha256, &file.hashes.imphash]
+        .into_iter()
+        .flatten()
+        .map(|digest| digest.to_lowercase())
+        .collect();
+    needles
+        .iter()
+        .filter(|(needle, _)| ioc_match::contains_token(&path, needle) || digests.iter().any(|digest| digest == needle))
+        .map(|(_, indicator)| indicator.to_string())
+        .collect()
+}
+
+/// Rule file, its modification time and the rules compiled from it
+type CompiledRules = (PathBuf, Option<SystemTime>, Rc<YaraScanner>);
+
+thread_local! {
+    /// Rules compiled by an earlier sweep on this thread
+    static COMPILED_RULES: RefCell<Option<CompiledRules>> = const { RefCell::new(None) };
+}
+
+/// Compiled rules for a rule file. Repeated sweeps in one process (the worker)
+/// compile an unchanged file once; included files are not checked for changes.
+fn compiled_rules(path: &Path) -> Result<Rc<YaraScanner>, String> {
+    let modified = fs::metadata(path).and_then(|metadata| metadata.modified()).ok();
+    let cached = COMPILED_RULES.with(|compiled| {
+        compiled
+            .borrow()
+            .as_ref()
+            .filter(|(rule_file, rule_modified, _)| rule_file == path && *rule_modified == modified)
+            .map(|(_, _, scanner)| Rc::clone(scanner))
+    });
+    if let Some(scanner) = cached {
+        return Ok(scanner);
+    }
+
+    let scanner = Rc::new(YaraScanner::load(path)?);
+    COMPILED_RULES.with(|compiled| *compiled.borrow_mut() = Some((path.to_path_buf(), modified, Rc::clone(&scanner))));
+    Ok(scanner)
+}
+
+/// Compiled YARA rules
+#[cfg(feature = "yara-engine")]
+struct YaraScanner {
+    rules: yara::Rules,
+    rule_file: String,
+}
+
+/// Without the yara-engine feature no rules can be loaded
+#[cfg(not(feature = "yara-engine"))]
+enum YaraScanner {}
+
+#[cfg(feature = "yara-engine")]
+impl YaraScanner {
+    fn load(path: &Path) -> Result<Self, String> {
+        let rules = yara::Compiler::new()
+            .and_then(|compiler| compiler.add_rules_file(path))
+            .and_then(|compiler| compiler.compile_rules())
+            .map_err(|e| format!("Failed to compile YARA rules {}: {}", path.display(), e))?;
+        Ok(YaraScanner { rules, rule_file: path.display().to_string() })
+    }
+
+    fn scan(&self, path: &Path) -> Result<Vec<YaraMatch>, String> {
+        let matched_rules = self
+            .rules
+            .scan_file(path, YARA_TIMEOUT_SECS)
+            .map_err(|e| format!("YARA scan of {} failed: {}", path.display(), e))?;
+
+        Ok(matched_rules
+            .iter()
+            .map(|rule| {
+                let first = rule.strings.iter().flat_map(|string| string.matches.iter()).next();
+                YaraMatch {
+                    rule_name: rule.identifier.to_string(),
+                    rule_file: self.rule_file.clone(),
+                    match_offset: first.map(|m| m.offset as u64).unwrap_or_default(),
+                    match_length: first.map(|m| m.length as u32).unwrap_or_default(),
+                    match_data: first.map(|m| hex::encode(&m.data[..m.data.len().min(MAX_MATCH_DATA)])).unwrap_or_default(),
+                    metadata: rule
+                        .metadatas
+                        .iter()
+                        .map(|meta| {
+                            let value = match &meta.value {
+                                yara::MetadataValue::Integer(value) => value.to_string(),
+                                yara::MetadataValue::String(value) => value.to_string(),
+                                yara::MetadataValue::Boolean(value) => value.to_string(),
+                            };
+                            (meta.identifier.to_string(), value)
+                        })
+                        .collect(),
+                }
+            })
+            .collect())
+    }
+}
+
+#[cfg(not(feature = "yara-engine"))]
+impl YaraScanner {
+    fn load(_path: &Path) -> Result<Self, String> {
+        Err(YARA_UNAVAILABLE.to_string())
+    }
+
+    fn scan(&self, _path: &Path) -> Result<Vec<YaraMatch>, String> {
+        match *self {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn options(paths: Vec<String>) -> SweepOptions {
+        SweepOptions {
+            paths,
+            max_file_size: 1024,
+            max_depth: DEFAULT_MAX_DEPTH,
+            extensions: SweepOptions::default_extensions(),
+            indicators: Vec::new(),
+            yara_rules: None,
+        }
+    }
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("*", "bob"));
+        assert!(wildcard_match("b?b", "bob"));
+        assert!(wildcard_match("*-admin", "svc-admin"));
+        assert!(wildcard_match("a*b*c", "aXXbYYc"));
+        assert!(!wildcard_match("a*b*c", "aXXbYY"));
+        assert!(!wildcard_match("b?b", "bobby"));
+    }
+
+    #[test]
+    fn test_expand_path() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        for user in ["alice", "bob", "Public"] {
+            fs::create_dir_all(temp_dir.path().join("Users").join(user).join("Downloads")).unwrap();
+        }
+        fs::create_dir_all(temp_dir.path().join("Users").join("Default")).unwrap();
+
+        let expanded = expand_path(&temp_dir.path().join("Users").join("*").join("Downloads"));
+        let users: Vec<String> = expanded
+            .iter()
+            .map(|dir| dir.parent().unwrap().file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(users, ["Public", "alice", "bob"]);
+        assert!(expand_path(&temp_dir.path().join("Missing").join("*")).is_empty());
+    }
+
+    #[test]
+    fn test_sweep_paths() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let downloads = temp_dir.path().join("Downloads");
+        fs::create_dir_all(downloads.join("nested")).unwrap();
+        fs::write(downloads.join("invoice.exe"), b"payload").unwrap();
+        fs::write(downloads.join("nested").join("update.ps1"), b"IEX").unwrap();
+        fs::write(downloads.join("notes.txt"), b"text").unwrap();
+        fs::write(downloads.join("large.dll"), vec![0u8; 2048]).unwrap();
+
+        let payload_sha256 = hashing::HashService::new(Vec::new()).hash_file(downloads.join("invoice.exe")).unwrap().sha256.unwrap();
+        let mut options = options(vec![downloads.display().to_string()]);
+        options.indicators = vec![payload_sha256.to_uppercase(), "update.ps1".to_string(), "evil.exe".to_string()];
+
+        let (mut files, audit_log) = sweep_paths(&options, None);
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        assert_eq!(files.len(), 2); // notes.txt is filtered by extension, large.dll by size
+        assert!(files[0].path.ends_with("invoice.exe"));
+        assert_eq!(files[0].ioc_matches, [payload_sha256.to_uppercase()]);
+        assert!(files[1].path.ends_with("update.ps1"));
+        assert_eq!(files[1].ioc_matches, ["update.ps1"]);
+        assert_eq!(audit_log.iter().filter(|e| e.action == "file_match").count(), 2);
+        assert!(audit_log.last().unwrap().details.contains("1 skipped by extension, 1 over the size limit"));
+
+        options.max_depth = 1;
+        options.extensions = SweepOptions::parse_extensions("*").unwrap();
+        let (files, _) = sweep_paths(&options, None);
+        assert_eq!(files.len(), 2); // invoice.exe and notes.txt; nested/ is below the depth cap
+    }
+}
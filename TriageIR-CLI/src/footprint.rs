@@ -0,0 +1,260 @@
+//! Collection footprint (`collection_footprint`)
+//! Collectors open the files, directories and registry keys of the examined
+//! system through the helpers here, which register the path, the access it was
+//! opened for and the collector that was running. The tool's own output and
+//! temporary copies are not part of the footprint. With --verify-read-only the
+//! scan fails when anything was opened for write.
+
+use crate::forensic_types::{CollectionFootprint, FootprintEntry};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, File, ReadDir};
+use std::io;
+use std::path::Path;
+use std::sync::Mutex;
+
+#[cfg(windows)]
+use std::ffi::OsStr;
+#[cfg(windows)]
+use windows::Wdk::System::SystemServices::{KeyNameInformation, ZwQueryKey};
+#[cfg(windows)]
+use windows::Win32::Foundation::{HANDLE, STATUS_BUFFER_OVERFLOW, STATUS_BUFFER_TOO_SMALL};
+#[cfg(windows)]
+use winreg::RegKey;
+
+/// Distinct paths kept per kind; further accesses are only counted
+const MAX_FOOTPRINT_ENTRIES: usize = 100_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Access {
+    Read,
+    List, // Directory enumeration
+    Write,
+}
+
+impl Access {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Access::Read => "read",
+            Access::List => "list",
+            Access::Write => "write",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Kind {
+    File,
+    RegistryKey,
+}
+
+struct Touch {
+    collectors: BTreeSet<&'static str>,
+    first_access: String,
+    count: u64,
+}
+
+/// Accesses of one scan, merged by path and access
+#[derive(Default)]
+pub struct Footprint {
+    touches: BTreeMap<(Kind, String, Access), Touch>,
+    files: usize,
+    registry_keys: usize,
+    dropped: u64,
+}
+
+impl Footprint {
+    const fn new() -> Self {
+        Footprint { touches: BTreeMap::new(), files: 0, registry_keys: 0, dropped: 0 }
+    }
+
+    fn record(&mut self, kind: Kind, path: String, access: Access, collector: &'static str) {
+        let key = (kind, path, access);
+        if let Some(touch) = self.touches.get_mut(&key) {
+            touch.collectors.insert(collector);
+            touch.count += 1;
+            return;
+        }
+        let distinct = match kind {
+            Kind::File => &mut self.files,
+            Kind::RegistryKey => &mut self.registry_keys,
+        };
+        if *distinct >= MAX_FOOTPRINT_ENTRIES {
+            self.dropped += 1;
+            return;
+        }
+        *distinct += 1;
+        let touch = Touch { collectors: BTreeSet::from([collector]), first_access: chrono::Utc::now().to_rfc3339(), count: 1 };
+        self.touches.insert(key, touch);
+    }
+
+    /// The output section; `verify_read_only` records whether nothing was opened for write
+    pub fn into_section(self, verify_read_only: bool) -> CollectionFootprint {
+        let mut section = CollectionFootprint { entries_dropped: self.dropped, ..CollectionFootprint::default() };
+        for ((kind, path, access), touch) in self.touches {
+            if access == Access::Write {
+                section.opened_for_write.push(path.clone());
+            }
+            let entry = FootprintEntry {
+                path,
+                access: access.as_str().to_string(),
+                collectors: touch.collectors.into_iter().map(str::to_string).collect(),
+                first_access: touch.first_access,
+                count: touch.count,
+            };
+            match kind {
+                Kind::File => section.files.push(entry),
+                Kind::RegistryKey => section.registry_keys.push(entry),
+            }
+        }
+        if verify_read_only {
+            section.read_only_verified = Some(section.opened_for_write.is_empty());
+        }
+        section
+    }
+}
+
+static FOOTPRINT: Mutex<Footprint> = Mutex::new(Footprint::new());
+static CURRENT_COLLECTOR: Mutex<&'static str> = Mutex::new("scan");
+
+/// Attribute the following accesses to `collector` (called when a collector run starts)
+pub fn enter(collector: &'static str) {
+    if let Ok(mut current) = CURRENT_COLLECTOR.lock() {
+        *current = collector;
+    }
+}
+
+/// The footprint recorded so far, clearing the record
+pub fn take() -> Footprint {
+    enter("scan");
+    FOOTPRINT.lock().map(|mut footprint| std::mem::take(&mut *footprint)).unwrap_or_default()
+}
+
+fn record(kind: Kind, path: String, access: Access) {
+    let collector = CURRENT_COLLECTOR.lock().map(|current| *current).unwrap_or("scan");
+    if let Ok(mut footprint) = FOOTPRINT.lock() {
+        footprint.record(kind, path, access, collector);
+    }
+}
+
+/// Register a file or directory of the examined system opened outside the helpers below
+pub fn record_file(path: &Path, access: Access) {
+    record(Kind::File, path.display().to_string(), access);
+}
+
+/// Register a registry key opened outside `TrackedKey`, e.g. a hive root
+#[cfg_attr(not(windows), allow(dead_code))]
+pub fn record_key(path: &str, access: Access) {
+    record(Kind::RegistryKey, path.to_string(), access);
+}
+
+/// `fs::read` of an artifact
+pub fn read<P: AsRef<Path>>(path: P) -> io::Result<Vec<u8>> {
+    record_file(path.as_ref(), Access::Read);
+    fs::read(path)
+}
+
+/// `File::open` of an artifact
+pub fn open<P: AsRef<Path>>(path: P) -> io::Result<File> {
+    record_file(path.as_ref(), Access::Read);
+    File::open(path)
+}
+
+/// `fs::read_dir` of an artifact directory
+pub fn read_dir<P: AsRef<Path>>(path: P) -> io::Result<ReadDir> {
+    record_file(path.as_ref(), Access::List);
+    fs::read_dir(path)
+}
+
+/// `fs::copy` of an artifact to a working copy; only the source belongs to the footprint
+pub fn copy<P: AsRef<Path>, Q: AsRef<Path>>(source: P, destination: Q) -> io::Result<u64> {
+    record_file(source.as_ref(), Access::Read);
+    fs::copy(source, destination)
+}
+
+/// Registry keys opened read-only and registered in the footprint
+#[cfg(windows)]
+pub trait TrackedKey {
+    fn open_tracked<P: AsRef<OsStr>>(&self, path: P) -> io::Result<RegKey>;
+}
+
+#[cfg(windows)]
+impl TrackedKey for RegKey {
+    fn open_tracked<P: AsRef<OsStr>>(&self, path: P) -> io::Result<RegKey> {
+        let key = self.open_subkey(path)?;
+        if let Some(name) = key_name(&key) {
+            record_key(&name, Access::Read);
+        }
+        Ok(key)
+    }
+}
+
+/// Full name of an open key, e.g. HKLM\SOFTWARE\Microsoft\Windows\CurrentVersion\Run
+#[cfg(windows)]
+fn key_name(key: &RegKey) -> Option<String> {
+    // KEY_NAME_INFORMATION: NameLength (bytes) followed by the UTF-16 name
+    let mut buffer = vec![0u32; 128];
+    loop {
+        let mut needed = 0u32;
+        let status = unsafe {
+            ZwQueryKey(HANDLE(key.raw_handle()), KeyNameInformation, Some(buffer.as_mut_ptr() as *mut _), (buffer.len() * 4) as u32, &mut needed)
+        };
+        if (status == STATUS_BUFFER_TOO_SMALL || status == STATUS_BUFFER_OVERFLOW) && needed as usize > buffer.len() * 4 {
+            buffer.resize((needed as usize).div_ceil(4), 0);
+            continue;
+        }
+        if status.is_err() {
+            return None;
+        }
+        break;
+    }
+    let name_bytes = (buffer[0] as usize).min((buffer.len() - 1) * 4);
+    let units: Vec<u16> = buffer[1..].iter().flat_map(|pair| [*pair as u16, (*pair >> 16) as u16]).take(name_bytes / 2).collect();
+    Some(friendly_key_name(&String::from_utf16_lossy(&units)))
+}
+
+/// `\REGISTRY\MACHINE\...` as `HKLM\...` and `\REGISTRY\USER\...` as `HKU\...`;
+/// application hives (`\REGISTRY\A\{GUID}\...`) keep their kernel name
+pub fn friendly_key_name(kernel_name: &str) -> String {
+    for (prefix, root) in [("\\REGISTRY\\MACHINE", "HKLM"), ("\\REGISTRY\\USER", "HKU")] {
+        if kernel_name.get(..prefix.len()).is_some_and(|head| head.eq_ignore_ascii_case(prefix)) {
+            return format!("{}{}", root, &kernel_name[prefix.len()..]);
+        }
+    }
+    kernel_name.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_footprint_section() {
+        let mut footprint = Footprint::new();
+        footprint.record(Kind::File, "C:\\Windows\\Prefetch".to_string(), Access::List, "prefetch");
+        footprint.record(Kind::File, "C:\\Windows\\Prefetch\\CMD.EXE-0BD30981.pf".to_string(), Access::Read, "prefetch");
+        footprint.record(Kind::File, "C:\\Windows\\Prefetch\\CMD.EXE-0BD30981.pf".to_string(), Access::Read, "file_collection");
+        footprint.record(Kind::RegistryKey, "HKLM\\SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Run".to_string(), Access::Read, "persistence");
+
+        let section = footprint.into_section(true);
+        assert_eq!(section.files.len(), 2);
+        assert_eq!(section.files[1].collectors, ["file_collection", "prefetch"]);
+        assert_eq!((section.files[1].access.as_str(), section.files[1].count), ("read", 2));
+        assert_eq!(section.registry_keys[0].path, "HKLM\\SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Run");
+        assert_eq!(section.read_only_verified, Some(true));
+
+        let mut footprint = Footprint::new();
+        footprint.record(Kind::File, "E:\\Windows\\System32\\config\\SYSTEM".to_string(), Access::Write, "offline");
+        let section = footprint.into_section(true);
+        assert_eq!(section.opened_for_write, ["E:\\Windows\\System32\\config\\SYSTEM"]);
+        assert_eq!(section.read_only_verified, Some(false));
+        assert_eq!(Footprint::new().into_section(false).read_only_verified, None);
+    }
+
+    #[test]
+    fn test_friendly_key_name() {
+        assert_eq!(friendly_key_name("\\REGISTRY\\MACHINE\\SOFTWARE\\Microsoft"), "HKLM\\SOFTWARE\\Microsoft");
+        assert_eq!(friendly_key_name("\\REGISTRY\\USER\\S-1-5-21-1\\Software"), "HKU\\S-1-5-21-1\\Software");
+        assert_eq!(friendly_key_name("\\Registry\\Machine"), "HKLM");
+        assert_eq!(friendly_key_name("\\REGISTRY\\A\\{0b1c}\\Root"), "\\REGISTRY\\A\\{0b1c}\\Root");
+    }
+}
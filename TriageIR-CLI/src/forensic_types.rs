@@ -1,10 +1,10 @@
+//! Professional forensic data structures for TriageIR
+//! Designed to match commercial DFIR tool capabilities
+
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use sha2::Digest;
 
-/// Professional forensic data structures for TriageIR
-/// Designed to match commercial DFIR tool capabilities
-
 /// Root forensic evidence package
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ForensicEvidence {
@@ -70,6 +70,8 @@ pub struct SystemSnapshot {
     pub collection_time: String,
     pub running_processes: Vec<ProcessInfo>,
     pub loaded_modules: Vec<ModuleInfo>,
+    #[serde(default)]
+    pub loaded_drivers: Vec<KernelDriverInfo>,
     pub open_handles: Vec<HandleInfo>,
     pub memory_regions: Vec<MemoryRegion>,
     pub system_services: Vec<ServiceInfo>,
@@ -157,6 +159,20 @@ pub struct ModuleInfo {
     pub load_count: u32,
 }
 
+/// Kernel driver loaded into the system address space
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct KernelDriverInfo {
+    pub name: String,
+    pub path: String,
+    pub base_address: String,
+    pub size: u64,
+    pub sha256: String,
+    pub signature_status: String, // signed, catalog_signed, unsigned, invalid, unknown
+    pub suspicious_indicators: Vec<String>,
+    #[serde(default)]
+    pub hashes: Option<FileHashes>, // Digests selected with --hash-algorithms
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct HandleInfo {
     pub process_id: u32,
@@ -199,6 +215,8 @@ pub struct VolatileArtifacts {
     pub netbios_sessions: Vec<NetbiosSession>,
     pub clipboard_contents: Vec<ClipboardEntry>,
     pub recent_documents: Vec<RecentDocument>,
+    #[serde(default)]
+    pub named_pipes: Vec<NamedPipeInfo>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -219,7 +237,7 @@ pub struct NetworkConnection {
     pub threat_intelligence: Option<ThreatIntel>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct GeoLocation {
     pub country: String,
     pub region: String,
@@ -230,7 +248,14 @@ pub struct GeoLocation {
     pub organization: String,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+/// Autonomous system announcing an address
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AsnInfo {
+    pub number: u32,
+    pub organization: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct ThreatIntel {
     pub is_malicious: bool,
     pub threat_type: String,
@@ -290,6 +315,130 @@ pub struct RecentDocument {
     pub access_count: u32,
 }
 
+/// Named pipe or mailslot open on the live system
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NamedPipeInfo {
+    pub name: String,
+    /// "pipe" or "mailslot"
+    pub kind: String,
+    /// Pipe server process, when a free pipe instance could be queried
+    pub owning_pid: Option<u32>,
+    pub owning_process: Option<String>,
+    pub suspicious_indicators: Vec<String>,
+}
+
+/// Process starts, image loads and connections recorded by the ETW monitor
+/// (--monitor) while the collection ran
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct LiveActivity {
+    pub started_at: String,
+    pub ended_at: String,
+    pub window_seconds: u64,
+    pub providers: Vec<String>,
+    pub process_starts: Vec<LiveProcessStart>,
+    pub image_loads: Vec<LiveImageLoad>,
+    pub connections: Vec<LiveConnection>,
+    pub events_lost: u64, // Dropped by ETW when the consumer fell behind
+    pub events_dropped: u64, // Beyond the per-category limit
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LiveProcessStart {
+    pub timestamp: String,
+    pub pid: u32,
+    pub parent_pid: u32,
+    pub session_id: u32,
+    pub image_name: String, // \Device\HarddiskVolumeN\... path
+    pub collector_activity: bool, // Started by the collector itself
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LiveImageLoad {
+    pub timestamp: String,
+    pub pid: u32,
+    pub image_name: String,
+    pub image_base: u64,
+    pub image_size: u64,
+    pub collector_activity: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LiveConnection {
+    pub timestamp: String,
+    pub pid: u32,
+    pub direction: String, // outbound (connect) or inbound (accept)
+    pub protocol: String,
+    pub local_address: String,
+    pub local_port: u16,
+    pub remote_address: String,
+    pub remote_port: u16,
+    pub collector_activity: bool,
+}
+
+/// Volume Shadow Copy of a local volume
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ShadowCopyInfo {
+    pub shadow_id: String,
+    pub set_id: String,
+    pub original_volume: String, // Drive letter, e.g. "C:"
+    pub device_path: String, // \\?\GLOBALROOT\Device\HarddiskVolumeShadowCopyN
+    pub creation_time: String, // As reported by vssadmin (local time, system locale)
+    pub provider: String,
+    pub attributes: Vec<String>,
+    pub created_by_collector: bool,
+}
+
+/// Screenshot of one monitor of the interactive desktop (--screenshot); the PNG is stored in the evidence package
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Screenshot {
+    pub file_name: String, // Path inside the evidence package, e.g. "screenshots/screen_1.png"
+    pub monitor: String, // Display device, e.g. "\\.\DISPLAY1"
+    pub primary: bool,
+    pub left: i32, // Position on the virtual screen
+    pub top: i32,
+    pub width: u32,
+    pub height: u32,
+    pub session_id: Option<u32>, // Session the collector (and the captured desktop) runs in
+    pub console_session: bool, // Whether that session is attached to the physical console
+    pub captured_at: String,
+    pub sha256: String, // Of the PNG file
+    pub size_bytes: u64,
+}
+
+/// Health metrics of one collector run, for tracking collector reliability across a fleet
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CollectorStats {
+    pub collector: String,
+    pub status: String, // completed | skipped | timed_out | interrupted | truncated | failed
+    #[serde(default)]
+    pub skip_reason: Option<String>,
+    pub items_collected: usize,
+    pub items_skipped: usize, // Dropped by the --since/--until window
+    pub errors: usize,
+    pub duration_ms: u64,
+    pub peak_memory_mb: f64, // Peak resident memory of the collector process while the collector ran
+}
+
+/// Artifacts returned by a third-party collector plugin
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PluginArtifacts {
+    pub plugin: String,
+    pub version: String,
+    /// Path of the plugin library that produced the artifacts
+    pub library: String,
+    pub artifacts: Vec<serde_json::Value>,
+}
+
+/// Artifacts of a custom collector (`custom_artifacts.<namespace>`)
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CustomArtifacts {
+    pub collector: String,
+    pub description: String,
+    /// "compiled-in", or the program of an external collector
+    pub source: String,
+    pub artifacts: Vec<serde_json::Value>,
+}
+
 /// Execution artifacts (evidence of program execution)
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ExecutionArtifacts {
@@ -307,15 +456,29 @@ pub struct PrefetchFile {
     pub filename: String,
     pub executable_name: String,
     pub run_count: u32,
-    pub last_run_time: String,
+    pub last_run_time: String, // Most recent run
+    pub last_run_times: Vec<String>, // Up to eight runs on Windows 8.1 and later, most recent first
     pub creation_time: String,
     pub file_size: u64,
     pub hash: String,
-    pub version: u32,
+    pub version: u32, // SCCA format: 17, 23, 26 or 30; 0 when the file did not parse
+    pub compressed: bool, // MAM (Xpress Huffman) compressed, Windows 10 and 11
     pub referenced_files: Vec<String>,
     pub volumes: Vec<VolumeInfo>,
 }
 
+/// Whether Prefetch recorded program runs on the examined system. When it did
+/// not, the alternative execution sources are listed in the order to consult them.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PrefetchStatus {
+    pub state: String, // "enabled", "disabled", "empty" or "not_applicable"
+    pub enable_prefetcher: Option<u32>, // PrefetchParameters\EnablePrefetcher, None if not set
+    pub server_sku: bool,
+    pub prefetch_file_count: usize, // .pf files found, before any time window
+    pub reason: Option<String>,
+    pub alternative_sources: Vec<String>,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct VolumeInfo {
     pub device_path: String,
@@ -326,11 +489,12 @@ pub struct VolumeInfo {
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ShimcacheEntry {
+    pub position: usize, // Cache order, 0 = most recently inserted or updated
     pub path: String,
     pub last_modified: String,
     pub file_size: u64,
     pub last_update: String,
-    pub execution_flag: bool,
+    pub execution_flag: Option<bool>, // None where the cache format records no flag
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -345,6 +509,160 @@ pub struct AmcacheEntry {
     pub install_date: String,
 }
 
+/// Background/Desktop Activity Moderator record of the last run of an executable by a user
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BamEntry {
+    pub source: String, // BAM or DAM
+    pub user_sid: String,
+    pub username: String, // The SID when it cannot be resolved
+    pub executable: String, // \Device\HarddiskVolumeN\... path or packaged app name
+    pub last_execution: String,
+    pub registry_path: String,
+}
+
+/// Program recorded as executed in a user's MUICache or Compatibility Assistant Store
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct UserExecutionEntry {
+    pub source: String, // MUICache or CompatibilityAssistantStore
+    pub user: String,
+    pub user_sid: Option<String>, // None for offline hives, attributed by profile folder
+    pub executable: String,
+    pub friendly_name: Option<String>, // MUICache FriendlyAppName
+    pub company: Option<String>, // MUICache ApplicationCompany
+    pub key_last_write: String, // Applies to the most recently added program of the key
+    pub registry_path: String,
+}
+
+/// Exited process reconstructed from a Security 4688 or Sysmon 1 process creation event
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HistoricalProcess {
+    pub source: String, // security_4688 or sysmon_1
+    pub timestamp: String,
+    pub record_id: u64,
+    pub pid: Option<u32>,
+    pub parent_pid: Option<u32>,
+    pub image_path: String,
+    pub command_line: String, // Empty when 4688 command line auditing is off
+    pub parent_image: String,
+    pub user: String,
+    pub process_guid: Option<String>, // Sysmon only
+    pub logged_hashes: Option<FileHashes>, // Hashes Sysmon recorded at execution
+    pub binary_present: bool,
+    pub hashes: Option<FileHashes>, // Hashes of the binary still on disk
+    pub hash_mismatch: bool, // The binary on disk differs from the logged hashes
+}
+
+/// Breadcrumb from a Microsoft Defender MPLog/MPDetection support log
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DefenderLogEntry {
+    pub timestamp: String,
+    pub category: String, // process_impact, sdn_query, detection, threat_action
+    pub process_name: String,
+    pub pid: Option<u32>,
+    pub path: String,
+    pub threat_name: String,
+    pub line: String,
+    pub source_file: String,
+    pub line_number: usize,
+}
+
+/// Application resource usage record from the SRUM database (one per app, user and hour)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SrumAppUsage {
+    pub timestamp: String,
+    pub application: String, // Executable path, service or package name
+    pub user_sid: String,
+    pub foreground_cycle_time: u64,
+    pub background_cycle_time: u64,
+    pub face_time: u64, // Not recorded before Windows 10
+    pub foreground_bytes_read: u64,
+    pub foreground_bytes_written: u64,
+    pub background_bytes_read: u64,
+    pub background_bytes_written: u64,
+}
+
+/// Network data usage record from the SRUM database (one per app, user, interface and hour)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SrumNetworkUsage {
+    pub timestamp: String,
+    pub application: String,
+    pub user_sid: String,
+    pub interface_luid: u64,
+    pub interface_type: String, // ethernet, wifi, mobile_broadband, ...
+    pub l2_profile_id: u32, // WLAN profile of the connection, 0 for wired
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+}
+
+/// Device or driver package installation section from a setupapi log
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DeviceInstallation {
+    pub section: String, // e.g. "Device Install (Hardware initiated)"
+    pub target: String, // Device instance ID or INF path
+    pub start_time: String, // Local time of the installing system
+    pub end_time: String,
+    pub exit_status: String,
+    pub inf_file: String,
+    pub driver_description: String,
+    pub signer_name: String,
+    pub signer_score: String,
+    pub signed: Option<bool>,
+    pub driver_files: Vec<String>, // .sys files copied by the installation
+    pub services: Vec<String>,
+    pub flags: Vec<String>, // unsigned, kernel_driver, recent_kernel_driver
+    pub source_file: String,
+}
+
+/// USB storage device attached to the host, from the Enum\USBSTOR and Enum\USB
+/// keys, MountedDevices, VolumeInfoCache and the setupapi log
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct UsbDevice {
+    pub device_id: String, // USBSTOR device key, e.g. Disk&Ven_SanDisk&Prod_Cruzer&Rev_1.00
+    pub vendor: String,
+    pub product: String,
+    pub revision: String,
+    pub serial_number: String,
+    /// Windows made the serial up (second character `&`): the device reports none
+    pub serial_generated: bool,
+    pub friendly_name: String,
+    pub vendor_id: String, // VID of the matching Enum\USB device
+    pub product_id: String,
+    pub first_connected: String, // First install: device property 0064, else setupapi (local time)
+    pub last_connected: String, // Last arrival: device property 0066, else the instance key write time
+    pub last_removed: String, // Last removal: device property 0067
+    pub drive_letters: Vec<String>,
+    pub volume_guids: Vec<String>,
+    pub volume_labels: Vec<String>,
+}
+
+/// File created, renamed or deleted according to the NTFS change journal ($UsnJrnl:$J)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct UsnJournalEntry {
+    pub timestamp: String,
+    pub usn: i64,
+    pub volume: String,
+    pub file_name: String,
+    pub old_name: Option<String>, // Name before a rename
+    pub action: String, // created, deleted, created_and_deleted, renamed
+    pub reasons: Vec<String>, // USN_REASON_* flags of the closing record
+    pub mft_entry: u64,
+    pub parent_mft_entry: u64,
+    pub is_directory: bool,
+}
+
+/// Master file table exported into the evidence package (`--collect-mft`)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MftExport {
+    pub source: String, // Volume device or offline $MFT copy
+    pub format: String, // raw or csv
+    pub archive_path: Option<String>, // mft/$MFT or mft/mft.csv inside the package
+    pub status: String, // collected or failed
+    pub size: u64, // Bytes of the $MFT
+    pub total_records: usize, // File records in use
+    pub exported_records: usize, // CSV rows inside the time window; every record for raw exports
+    pub details: Option<String>,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct UserAssistEntry {
     pub program_name: String,
@@ -382,13 +700,17 @@ pub struct LnkFile {
     pub network_path: String,
 }
 
+/// Entry of an Explorer most-recently-used list from a user's registry hive
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct MruEntry {
+    pub user: String,
+    pub source: String, // RecentDocs, OpenSavePidlMRU, LastVisitedPidlMRU, TypedPaths, RunMRU
     pub key_path: String,
     pub value_name: String,
-    pub value_data: String,
-    pub last_write_time: String,
-    pub position: u32,
+    pub value_data: String, // Path, file name or command decoded from the value
+    pub application: Option<String>, // Program that last used the folder (LastVisitedPidlMRU)
+    pub last_write_time: String, // Of the key; applies to the most recent entry
+    pub position: u32, // 0 = most recent
 }
 
 /// Persistence mechanisms
@@ -400,7 +722,10 @@ pub struct PersistenceArtifacts {
     pub startup_folders: Vec<StartupItem>,
     pub winlogon_entries: Vec<WinlogonEntry>,
     pub image_hijacks: Vec<ImageHijack>,
+    pub appinit_dlls: Vec<AppInitEntry>,
     pub dll_hijacks: Vec<DllHijack>,
+    #[serde(default)]
+    pub path_hijacks: Vec<PathHijack>,
     pub wmi_persistence: Vec<WmiPersistence>,
 }
 
@@ -432,6 +757,16 @@ pub struct ScheduledTask {
     pub creation_date: String,
     pub author: String,
     pub description: String,
+    #[serde(default)]
+    pub hidden: bool,
+    #[serde(default)]
+    pub last_task_result: Option<i32>,
+    #[serde(default)]
+    pub security_descriptor: Option<String>, // SDDL owner and DACL, from the Task Scheduler API
+    #[serde(default)]
+    pub suspicion_score: u32, // 0-100
+    #[serde(default)]
+    pub suspicion_reasons: Vec<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -482,25 +817,73 @@ pub struct StartupItem {
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct WinlogonEntry {
     pub key_name: String,
-    pub value_name: String,
+    pub value_name: String, // Shell, Userinit, or DllName of a Notify package
     pub value_data: String,
+    pub user: Option<String>, // Set for per-user Shell overrides
     pub last_write_time: String,
+    pub suspicion_score: u32, // 0-100
+    pub suspicion_reasons: Vec<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ImageHijack {
     pub target_executable: String,
     pub hijack_executable: String,
+    pub hijack_type: String, // "debugger" or "silent_process_exit"
     pub registry_key: String,
     pub last_write_time: String,
+    pub suspicion_score: u32, // 0-100
+    pub suspicion_reasons: Vec<String>,
 }
 
+/// DLLs loaded into processes through AppInit_DLLs (user32.dll) or AppCertDlls (CreateProcess)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AppInitEntry {
+    pub key_name: String,
+    pub value_name: String,
+    pub dlls: Vec<String>,
+    pub load_enabled: Option<bool>, // LoadAppInit_DLLs; None for AppCertDlls
+    pub last_write_time: String,
+    pub suspicion_score: u32, // 0-100
+    pub suspicion_reasons: Vec<String>,
+}
+
+/// Non-system DLL loaded in place of a same-named System32 DLL (search-order hijack candidate)
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct DllHijack {
-    pub target_dll: String,
-    pub hijack_dll: String,
-    pub search_path: String,
+    pub target_dll: String, // The System32 DLL of the same name
+    pub hijack_dll: String, // The DLL the process actually loaded
+    pub search_path: String, // "application_directory" or "writable_path"
     pub process_name: String,
+    pub pid: u32,
+    pub executable_path: String,
+    pub hijack_sha256: Option<String>,
+    pub target_sha256: Option<String>,
+}
+
+/// PATH entry, known folder redirection or App Paths registration through which a
+/// planted binary runs in place of the expected one
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PathHijack {
+    pub source: String, // system_path, user_path, known_folder, app_paths
+    pub registry_key: String,
+    pub value_name: String,
+    pub value_data: String, // As written, unexpanded
+    pub directory: String, // Expanded, normalized directory (App Paths: target executable)
+    pub user: Option<String>,
+    pub search_position: Option<u32>, // Index in the effective PATH, system entries first
+    pub planted_binaries: Vec<PlantedBinary>,
+    pub last_write_time: String,
+    pub suspicion_score: u32, // 0-100
+    pub suspicion_reasons: Vec<String>,
+}
+
+/// Executable in a hijackable search path directory named like a common tool or a later PATH binary
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PlantedBinary {
+    pub path: String,
+    pub shadows: Option<String>, // Same-named binary in a later PATH directory
+    pub hashes: Option<FileHashes>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -523,6 +906,17 @@ pub struct NetworkArtifacts {
     pub wifi_profiles: Vec<WifiProfile>,
     pub firewall_rules: Vec<FirewallRule>,
     pub proxy_settings: ProxySettings,
+    #[serde(default)]
+    pub rdp_connections: Vec<RdpConnection>,
+    #[serde(default)]
+    pub rdp_session_events: Vec<RdpSessionEvent>,
+    #[serde(default)]
+    pub smb_sessions: Vec<SmbSession>,
+    #[serde(default)]
+    pub smb_open_files: Vec<SmbOpenFile>,
+    /// WinINET proxy of each user; `proxy_settings` is the machine WinHTTP proxy
+    #[serde(default)]
+    pub user_proxy_settings: Vec<ProxySettings>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -535,6 +929,164 @@ pub struct ListeningPort {
     pub service_name: String,
 }
 
+/// Established Remote Desktop connection (inbound to this host or outbound from it)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RdpConnection {
+    pub direction: String, // inbound, outbound
+    pub local_address: String,
+    pub local_port: u16,
+    pub remote_address: String,
+    pub remote_port: u16,
+    pub process_id: u32,
+    pub process_name: String,
+}
+
+/// RDP logon, disconnect and reconnect events of the TerminalServices logs
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RdpSessionEvent {
+    pub timestamp: String,
+    pub event_id: u32,
+    pub action: String, // authenticated (1149), logon (21), disconnect (24), reconnect (25)
+    pub user: String,
+    pub source_address: String,
+    pub session_id: Option<u32>,
+    pub channel: String,
+}
+
+/// Inbound SMB session (NetSessionEnum)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SmbSession {
+    pub client: String,
+    pub username: String,
+    pub active_secs: u32,
+    pub idle_secs: u32,
+}
+
+/// File opened on this host over SMB (NetFileEnum)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SmbOpenFile {
+    pub id: u32,
+    pub path: String,
+    pub username: String,
+    pub permissions: Vec<String>, // read, write, create
+    pub locks: u32,
+}
+
+/// Record of the Windows Firewall connection log (pfirewall.log)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FirewallLogEntry {
+    pub timestamp: String, // Local time of the logging host
+    pub action: String, // ALLOW, DROP, INFO-EVENTS-LOST
+    pub protocol: String,
+    pub src_ip: String,
+    pub dst_ip: String,
+    pub src_port: Option<u16>,
+    pub dst_port: Option<u16>,
+    pub size: Option<u64>,
+    pub direction: String, // SEND, RECEIVE
+    pub pid: Option<u32>,
+    pub source_file: String,
+}
+
+/// DNS query or response from the DNS server debug log or the DNS client operational log
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DnsLogEntry {
+    pub timestamp: String,
+    pub source: String, // server_debug_log, client_event_log
+    pub direction: String, // Snd, Rcv; empty for client events
+    pub protocol: String,
+    pub remote_address: String, // Client or forwarder the packet was exchanged with
+    pub query_name: String,
+    pub query_type: String,
+    pub response: bool,
+    pub response_code: String,
+    pub results: Vec<String>, // Answers recorded by the DNS client
+    pub source_file: String,
+}
+
+/// Connection history from host network logs, complementing the instantaneous connection table
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct NetworkLogs {
+    pub firewall: Vec<FirewallLogEntry>,
+    pub dns: Vec<DnsLogEntry>,
+}
+
+/// Shares, firewall rules and proxies configured on the host
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct NetworkConfiguration {
+    pub network_shares: Vec<NetworkShare>,
+    pub firewall_rules: Vec<FirewallRule>,
+    pub proxy_settings: ProxySettings,
+    pub user_proxy_settings: Vec<ProxySettings>,
+}
+
+/// Name resolution settings that can redirect or intercept traffic
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct NameResolutionConfiguration {
+    pub hosts_file: Option<HostsFile>,
+    pub winsock_providers: Vec<WinsockProvider>,
+    pub nrpt_rules: Vec<NrptRule>,
+    pub dns_servers: Vec<DnsServerSetting>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HostsFile {
+    pub path: String,
+    pub database_path: String, // Tcpip DataBasePath as written
+    pub default_location: bool,
+    pub modified: Option<String>,
+    pub content: String,
+    pub entries: Vec<HostsEntry>,
+}
+
+/// One hostname of a hosts file line
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HostsEntry {
+    pub line: usize,
+    pub address: String,
+    pub hostname: String,
+    pub suspicious: bool,
+    pub suspicion_reasons: Vec<String>,
+}
+
+/// Winsock catalog entry: a transport (protocol catalog) or name space provider
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WinsockProvider {
+    pub catalog: String, // protocol or namespace
+    pub architecture: String, // 64-bit or 32-bit catalog
+    pub entry: String, // Catalog_Entries subkey
+    pub name: String,
+    pub dll_path: String,
+    pub layered: bool, // Layered service provider or protocol chain
+    pub enabled: Option<bool>, // Name space providers only
+    pub suspicious: bool,
+    pub suspicion_reasons: Vec<String>,
+}
+
+/// Name Resolution Policy Table rule
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NrptRule {
+    pub name: String,
+    pub source: String, // group_policy or local
+    pub namespaces: Vec<String>,
+    pub dns_servers: Vec<String>,
+    pub suspicious: bool,
+    pub suspicion_reasons: Vec<String>,
+}
+
+/// DNS servers of a network interface, or the machine-wide override
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DnsServerSetting {
+    pub interface: String, // Interface GUID, or global
+    pub interface_name: Option<String>,
+    pub ip_version: String, // IPv4 or IPv6
+    pub static_servers: Vec<String>,
+    pub dhcp_servers: Vec<String>,
+    pub domain: Option<String>,
+    pub suspicious: bool,
+    pub suspicion_reasons: Vec<String>,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct NetworkShare {
     pub name: String,
@@ -543,6 +1095,9 @@ pub struct NetworkShare {
     pub share_type: String,
     pub permissions: Vec<SharePermission>,
     pub current_connections: u32,
+    /// Reachable without credentials: a null session share, or granted to ANONYMOUS LOGON
+    #[serde(default)]
+    pub anonymous_access: bool,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -577,15 +1132,31 @@ pub struct FirewallRule {
     pub remote_addresses: String,
     pub enabled: bool,
     pub profile: String,
+    #[serde(default)]
+    pub application: String,
+    #[serde(default)]
+    pub service: String,
+    /// local or group_policy
+    #[serde(default)]
+    pub policy_store: String,
+    /// Enabled inbound allow rule for any program, local port and remote address
+    #[serde(default)]
+    pub allow_all: bool,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct ProxySettings {
     pub enabled: bool,
     pub server: String,
     pub port: u16,
     pub bypass_list: Vec<String>,
     pub auto_config_url: String,
+    /// Profile of a per-user (WinINET) proxy; empty for the machine WinHTTP proxy
+    #[serde(default)]
+    pub user: String,
+    /// Registry key or hive file the settings were read from
+    #[serde(default)]
+    pub source: String,
 }
 
 /// User activity artifacts
@@ -597,6 +1168,14 @@ pub struct UserActivity {
     pub recent_activity: Vec<ActivityEntry>,
     pub browser_artifacts: Vec<BrowserArtifact>,
     pub email_artifacts: Vec<EmailArtifact>,
+    pub search_index_files: Vec<CacheDatabaseFile>,
+    pub thumbcache_files: Vec<CacheDatabaseFile>,
+    pub notifications: Vec<NotificationRecord>,
+    pub search_history: Vec<SearchHistoryEntry>,
+    #[serde(default)]
+    pub rdp_connection_history: Vec<RdpConnectionHistory>,
+    #[serde(default)]
+    pub clipboard_history: Vec<ClipboardHistoryEntry>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -604,9 +1183,16 @@ pub struct LoggedOnUser {
     pub username: String,
     pub domain: String,
     pub session_id: u32,
+    /// Logon type name (Interactive, RemoteInteractive, Network, ...)
     pub session_type: String,
     pub logon_time: String,
     pub logon_server: String,
+    /// Terminal Services connection state (Active, Disconnected, ...); empty for logons without a desktop session
+    #[serde(default)]
+    pub session_state: String,
+    /// Window station name (Console, RDP-Tcp#0, ...)
+    #[serde(default)]
+    pub winstation: String,
     pub client_name: String,
     pub client_address: String,
 }
@@ -640,6 +1226,12 @@ pub struct UserProfile {
     pub password_last_set: String,
     pub groups: Vec<String>,
     pub privileges: Vec<String>,
+    /// disabled, locked_out, password_not_required, password_never_expires
+    #[serde(default)]
+    pub account_flags: Vec<String>,
+    /// Groups of `groups` that grant administrative or remote access
+    #[serde(default)]
+    pub privileged_groups: Vec<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -664,6 +1256,73 @@ pub struct BrowserArtifact {
     pub typed_count: u32,
     pub download_path: Option<String>,
     pub referrer: Option<String>,
+    #[serde(default)]
+    pub user: String,
+    #[serde(default)]
+    pub source_path: String,
+}
+
+/// Windows Search index or Explorer thumbnail/icon cache database
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CacheDatabaseFile {
+    pub artifact_type: String, // search_index, thumbcache, iconcache
+    pub user: Option<String>,
+    pub path: String,
+    pub file_size: u64,
+    pub creation_time: String,
+    pub last_modified: String,
+    pub last_accessed: String,
+    pub hash: String,
+    pub header_signature: String,
+    pub raw_copy_path: Option<String>,
+}
+
+/// Notification from the per-user Windows Notification Center database (wpndatabase.db)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NotificationRecord {
+    pub user: String,
+    pub notification_id: i64,
+    pub application: String,
+    pub notification_type: String, // toast, tile, badge
+    pub arrival_time: String,
+    pub expiry_time: String,
+    pub text: Vec<String>,
+    pub payload: String,
+    pub source_path: String,
+}
+
+/// Clipboard history item persisted by Windows 10+ (synced cloud clipboard or pinned item)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ClipboardHistoryEntry {
+    pub user: String,
+    pub source: String, // activities_cache, pinned
+    pub timestamp: String,
+    pub format: String, // Text, UnicodeText, HTML Format, ...
+    pub text: String, // Empty for non-text formats (bitmaps)
+    pub size: u64,
+    pub source_path: String,
+}
+
+/// Explorer/Start search query or Run dialog command from a user's registry hive
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SearchHistoryEntry {
+    pub user: String,
+    pub source: String, // WordWheelQuery, RunMRU
+    pub mru_position: u32, // 0 = most recent
+    pub value: String,
+    pub key_last_write: String, // Applies to the most recent entry
+    pub registry_path: String,
+}
+
+/// Remote Desktop client history from a user's Terminal Server Client key
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RdpConnectionHistory {
+    pub user: String,
+    pub server: String,
+    pub username_hint: String, // Account last used for the server
+    pub mru_position: Option<u32>, // Position in the Default MRU list, 0 = most recent
+    pub key_last_write: String,
+    pub registry_path: String,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -680,6 +1339,181 @@ pub struct EmailArtifact {
 }
 
 /// Security events and logs
+/// Configuration of an event log channel, used to explain gaps in event evidence
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EventLogChannelConfig {
+    pub channel: String,
+    pub enabled: bool,
+    pub channel_type: String, // Admin, Operational, Analytic, Debug
+    pub max_size_bytes: u64, // Effective size, Windows default when not configured
+    pub max_size_configured: bool,
+    pub retention: String, // overwrite, archive, do_not_overwrite, overwrite_older_than
+    pub log_file_path: String,
+    pub policy_managed: bool, // Overridden by Group Policy
+    pub findings: Vec<String>,
+    pub recommendations: Vec<String>,
+}
+
+/// Host-based indicator extracted from a flagged finding for EDR blocklists
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct IocIndicator {
+    pub indicator_type: String, // sha256, file_path, registry_key, ipv4, ipv6, domain, url, service_name, task_name
+    pub value: String,
+    pub source_artifact: String, // e.g. persistence_mechanisms, defender_log_entries
+    pub context: String, // Why the finding was flagged
+}
+
+/// Time-stamped event reconstructed from a saved scan
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TimelineEvent {
+    pub timestamp: String,
+    pub source_artifact: String, // e.g. running_processes, event_logs/security, prefetch_files
+    pub description: String,
+}
+
+/// Occurrence of a known indicator in a saved scan
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct IocMatch {
+    pub indicator: String,
+    pub artifact_path: String, // JSON pointer into the scan, e.g. /artifacts/running_processes/3/sha256_hash
+    pub matched_value: String,
+}
+
+/// Signal raised by the `--scope-check` quick look
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ScopeFinding {
+    pub check: String, // persistence, listening_ports, recent_services_tasks or ioc_match
+    pub description: String,
+    pub evidence: String,
+}
+
+/// Outcome of one `--scope-check` check
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ScopeCheckStatus {
+    pub check: String,
+    pub status: String, // completed or failed
+    pub items_examined: usize,
+    pub findings: usize,
+    pub duration_ms: u64,
+}
+
+/// Yes/no scoping verdict of a host from `--scope-check`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ScopeCheckReport {
+    pub hostname: String,
+    pub started_utc: String,
+    pub duration_ms: u64,
+    pub within_budget: bool,
+    pub verdict: String, // suspicious or clean
+    pub lookback_days: i64,
+    pub indicator_count: usize,
+    pub checks: Vec<ScopeCheckStatus>,
+    pub findings: Vec<ScopeFinding>,
+    pub collection_log: Vec<crate::types::LogEntry>,
+}
+
+/// Differences between a baseline scan and a later scan of the same host
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ScanDiff {
+    pub baseline_scan_id: String,
+    pub current_scan_id: String,
+    pub categories: Vec<ArtifactDiff>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ArtifactDiff {
+    pub category: String, // processes, persistence_mechanisms, listening_ports, loaded_drivers, services, scheduled_tasks
+    pub added: Vec<DiffEntry>,
+    pub removed: Vec<DiffEntry>,
+    pub changed: Vec<DiffEntry>, // Same artifact with a different hash or command
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DiffEntry {
+    pub key: String,
+    pub description: String,
+}
+
+/// High-severity finding with the suggested containment steps
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub finding_id: String, // F-001, F-002, ...
+    pub severity: String,
+    pub source_artifact: String, // e.g. persistence_mechanisms, defender_log_entries
+    pub description: String,
+    pub remediation: Vec<RemediationAction>,
+}
+
+/// MITRE ATT&CK technique attached to an artifact
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AttackTechnique {
+    pub technique_id: String, // e.g. T1547.001
+    pub name: String,
+    pub tactic: String,
+    pub confidence: String, // high (mechanism type), medium (launched binary), low (command line keyword)
+}
+
+/// Technique roll-up of a scan (scan_metadata.attack_techniques)
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AttackTechniqueSummary {
+    pub technique_id: String,
+    pub name: String, // Empty for techniques outside the collector's catalog (detection rule tags)
+    pub tactic: String,
+    pub confidence: String, // Highest confidence of the tagged artifacts
+    pub artifact_count: usize,
+    pub suspicious_count: usize,
+    pub sources: Vec<String>, // Artifact lists the technique was tagged in
+}
+
+/// Artifacts matched by a detection rule (--detection-rules)
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Detection {
+    pub rule_id: String,
+    pub rule_name: String,
+    pub severity: String, // informational, low, medium, high, critical
+    pub description: String,
+    pub mitre_techniques: Vec<String>, // ATT&CK technique IDs, e.g. T1059.001
+    pub match_count: usize,
+    pub matches: Vec<DetectionMatch>, // The first 100 matches
+}
+
+/// Reference to a matched artifact
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DetectionMatch {
+    pub reference: String, // JSON pointer into the scan output, e.g. /artifacts/running_processes/12
+    pub summary: String,
+}
+
+/// Suggested remediation step; never executed by the collector
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RemediationAction {
+    pub action: String, // delete_registry_value, disable_scheduled_task, stop_service, disable_service, stop_process, quarantine_file
+    pub target: String, // Registry key, task path, service name, PID or file path
+    #[serde(default)]
+    pub value_name: String, // Registry value for delete_registry_value
+}
+
+/// Analyst-approved list of remediation steps for `remediate --plan`
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct RemediationPlan {
+    #[serde(default)]
+    pub hostname: String, // Host the plan was written for; empty skips the host check
+    #[serde(default)]
+    pub scan_id: String,
+    pub actions: Vec<RemediationAction>,
+}
+
+/// Result of one plan step with the state captured around it
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RemediationOutcome {
+    pub action: RemediationAction,
+    pub status: String, // applied, planned (no --confirm), failed
+    pub before_state: String,
+    pub after_state: String,
+    pub message: String,
+    pub timestamp: String,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct SecurityEvents {
     pub security_log: Vec<SecurityEvent>,
@@ -738,6 +1572,12 @@ pub struct PowershellEvent {
     pub user: String,
     pub host_application: String,
     pub engine_version: String,
+    /// Script block ID shared by the parts of a 4104 script block
+    #[serde(default)]
+    pub script_block_id: String,
+    /// Event log channel or PSReadLine history file
+    #[serde(default)]
+    pub source: String,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -754,6 +1594,12 @@ pub struct SysmonEvent {
     pub parent_image: String,
     pub parent_command_line: String,
     pub hashes: HashMap<String, String>,
+    /// Sysmon event name (ProcessCreate, NetworkConnect, DnsQuery, ...)
+    #[serde(default)]
+    pub event_type: String,
+    /// Event specific fields: connection endpoints, target process, file, registry key or DNS query
+    #[serde(default)]
+    pub details: BTreeMap<String, String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -767,6 +1613,20 @@ pub struct DefenderEvent {
     pub action_taken: String,
     pub user: String,
     pub detection_source: String,
+    /// Old and new value of a 5007 configuration change, or the DetectionHistory file
+    #[serde(default)]
+    pub details: String,
+}
+
+/// Microsoft Defender exclusion, from the registry or from a 5007 configuration change event
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DefenderExclusion {
+    pub exclusion_type: String, // path, extension, process or ip_address
+    pub value: String,
+    pub source: String, // registry, policy or event_log
+    pub registry_key: String,
+    pub timestamp: String, // Time of the 5007 event; empty for registry entries
+    pub removed: bool,     // A 5007 event removed the exclusion
 }
 
 /// Integrity verification for chain of custody
@@ -791,6 +1651,144 @@ pub struct FileHash {
     pub modification_time: String,
 }
 
+/// Digests of a file from the shared hashing service; algorithms not selected with --hash-algorithms are None
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct FileHashes {
+    pub md5: Option<String>,
+    pub sha1: Option<String>,
+    pub sha256: Option<String>,
+    pub imphash: Option<String>, // PE files only
+    #[serde(default)]
+    pub ssdeep: Option<String>, // Process executables and suspicious persistence binaries, with --fuzzy-hash
+}
+
+/// File hashed by a --scan-paths sweep, with the indicators and YARA rules it matched
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SweptFile {
+    pub path: String,
+    pub size: u64,
+    pub modified: Option<String>,
+    pub hashes: FileHashes,
+    pub ioc_matches: Vec<String>,
+    pub yara_matches: Vec<YaraMatch>,
+}
+
+/// Installation and container type of the examined system
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HostEnvironment {
+    pub environment_type: String, // workstation, server, server_core, nano_server, container or unknown
+    pub installation_type: Option<String>, // InstallationType of the SOFTWARE hive
+    pub desktop_shell: bool, // False skips the collectors of Explorer and user GUI artifacts
+    pub indicators: Vec<String>, // Container markers found
+}
+
+/// Security-relevant configuration setting; `weakened` marks settings that
+/// lower the host's defenses (UAC off, WDigest caching, Defender exclusions, ...)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SecuritySetting {
+    pub category: String, // uac, lsa, wdigest, rdp, smb, powershell or defender
+    pub name: String,
+    pub registry_key: String,
+    pub value_name: String,
+    pub value: Option<String>, // Registry data as text; None when not set
+    pub state: String,
+    pub weakened: bool,
+}
+
+/// Certificate of a machine or user Root, AuthRoot or CA store
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CertificateEntry {
+    pub store: String, // Root, AuthRoot or CA
+    pub location: String, // machine, group_policy, enterprise or user
+    pub user: Option<String>,
+    pub thumbprint: String, // SHA-1 of the DER encoding
+    pub subject: String,
+    pub issuer: String,
+    pub serial_number: String,
+    pub not_before: String,
+    pub not_after: String,
+    pub self_signed: bool,
+    pub microsoft_trusted: bool, // In the Microsoft root program list or shipped with Windows
+    pub added: String, // Last write time of the store entry
+    pub friendly_name: Option<String>,
+    pub suspicious: bool,
+    pub suspicion_reasons: Vec<String>,
+}
+
+/// Result of checking one deception asset from the --canaries list
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CanaryStatus {
+    pub kind: String, // file, account or registry
+    pub target: String,
+    pub status: String, // intact, missing, modified, accessed or unverified
+    pub expected: Option<String>, // SHA-256 or registry data from the list
+    pub observed: Option<String>,
+    pub last_activity: Option<String>, // File last access, account last logon, key last write
+    pub details: String,
+}
+
+/// Recursive hash manifest of a case folder, written next to its receipt (--receipt-dir)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CaseManifest {
+    pub scan_id: String,
+    pub case_directory: String,
+    pub created_at: String,
+    pub hash_algorithms: Vec<String>,
+    pub files: Vec<ManifestEntry>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ManifestEntry {
+    pub path: String, // Below the case folder, forward slashes
+    pub size: u64,
+    pub modified: Option<String>,
+    pub hashes: FileHashes,
+}
+
+/// Signed receipt for a case manifest
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CaseReceipt {
+    pub scan_id: String,
+    pub manifest_file: String,
+    pub manifest_sha256: String,
+    pub file_count: usize,
+    pub total_bytes: u64,
+    pub created_at: String,
+    pub signature_algorithm: String, // HMAC-SHA256
+    pub signature: String, // Over the manifest file, keyed with TRIAGEIR_RECEIPT_KEY or --password
+}
+
+/// Minidump of a process written into the evidence package by --dump-process
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MemoryDump {
+    pub file_name: String, // Path inside the evidence package, e.g. "memory/4242_rundll32.exe.dmp"
+    pub pid: u32,
+    pub process_name: String,
+    pub executable_path: String,
+    pub dump_type: String, // full or targeted
+    pub reason: String, // requested, yara_match or unsigned_temp_path
+    pub size: u64,
+    pub md5: String,
+    pub sha1: String,
+    pub sha256: String,
+    pub captured_at: String,
+}
+
+/// File copied into the evidence package by --collect-files
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AcquiredFile {
+    pub source_path: String,
+    pub archive_path: Option<String>, // files/NNNN_<name> inside the package
+    pub reason: String, // user_path, suspicious_persistence, unsigned_process or prefetch
+    pub status: String, // collected, over_budget or failed
+    pub size: u64,
+    pub hashes: Option<FileHashes>,
+    pub created: Option<String>, // Original timestamps, read before the copy
+    pub modified: Option<String>,
+    pub accessed: Option<String>,
+    pub details: Option<String>,
+}
+
 /// Collection audit trail
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct CollectionAudit {
@@ -806,6 +1804,8 @@ pub struct CollectionAudit {
     pub collection_warnings: Vec<CollectionWarning>,
     pub collection_statistics: CollectionStatistics,
     pub audit_log: Vec<AuditEntry>,
+    #[serde(default)]
+    pub external_commands: Vec<ExternalCommand>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -850,6 +1850,56 @@ pub struct AuditEntry {
     pub result: String,
 }
 
+impl AuditEntry {
+    /// Entry timestamped now, without a duration
+    pub fn new(component: &str, level: &str, action: &str, details: String, result: &str) -> Self {
+        AuditEntry {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            level: level.to_string(),
+            component: component.to_string(),
+            action: action.to_string(),
+            details,
+            duration_ms: None,
+            result: result.to_string(),
+        }
+    }
+}
+
+/// External program the collector ran on the examined system
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ExternalCommand {
+    pub timestamp: String, // Start of the run
+    pub component: String,
+    pub program: String,
+    pub arguments: Vec<String>,
+    pub working_directory: Option<String>,
+    pub duration_ms: u64,
+    pub exit_code: Option<i32>, // None when it did not start or was terminated
+    pub stdout: String, // Lossy UTF-8, cut at the output limit
+    pub stderr: String,
+    pub output_truncated: bool,
+    pub error: Option<String>, // Why the program could not be started
+}
+
+/// Files, directories and registry keys the collectors opened on the examined system
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct CollectionFootprint {
+    pub files: Vec<FootprintEntry>, // Files and directories
+    pub registry_keys: Vec<FootprintEntry>,
+    pub entries_dropped: u64, // Accesses beyond the per-kind limit of distinct paths
+    pub opened_for_write: Vec<String>,
+    pub read_only_verified: Option<bool>, // Result of --verify-read-only
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FootprintEntry {
+    pub path: String,
+    pub access: String, // read, list (directory enumeration) or write
+    pub collectors: Vec<String>,
+    pub first_access: String,
+    pub count: u64,
+}
+
 impl ForensicEvidence {
     /// Create a new forensic evidence package
     pub fn new(case_id: String, collector_info: CollectorInfo) -> Self {
@@ -937,6 +1987,7 @@ impl Default for SystemSnapshot {
             collection_time: chrono::Utc::now().to_rfc3339(),
             running_processes: vec![],
             loaded_modules: vec![],
+            loaded_drivers: vec![],
             open_handles: vec![],
             memory_regions: vec![],
             system_services: vec![],
@@ -954,6 +2005,7 @@ impl Default for VolatileArtifacts {
             netbios_sessions: vec![],
             clipboard_contents: vec![],
             recent_documents: vec![],
+            named_pipes: vec![],
         }
     }
 }
@@ -981,7 +2033,9 @@ impl Default for PersistenceArtifacts {
             startup_folders: vec![],
             winlogon_entries: vec![],
             image_hijacks: vec![],
+            appinit_dlls: vec![],
             dll_hijacks: vec![],
+            path_hijacks: vec![],
             wmi_persistence: vec![],
         }
     }
@@ -995,13 +2049,12 @@ impl Default for NetworkArtifacts {
             network_shares: vec![],
             wifi_profiles: vec![],
             firewall_rules: vec![],
-            proxy_settings: ProxySettings {
-                enabled: false,
-                server: String::new(),
-                port: 0,
-                bypass_list: vec![],
-                auto_config_url: String::new(),
-            },
+            proxy_settings: ProxySettings::default(),
+            rdp_connections: vec![],
+            rdp_session_events: vec![],
+            smb_sessions: vec![],
+            smb_open_files: vec![],
+            user_proxy_settings: vec![],
         }
     }
 }
@@ -1015,6 +2068,12 @@ impl Default for UserActivity {
             recent_activity: vec![],
             browser_artifacts: vec![],
             email_artifacts: vec![],
+            search_index_files: vec![],
+            thumbcache_files: vec![],
+            notifications: vec![],
+            search_history: vec![],
+            rdp_connection_history: vec![],
+            clipboard_history: vec![],
         }
     }
 }
@@ -1032,6 +2091,30 @@ impl Default for SecurityEvents {
     }
 }
 
+impl Default for PrefetchStatus {
+    fn default() -> Self {
+        PrefetchStatus {
+            state: "not_applicable".to_string(),
+            enable_prefetcher: None,
+            server_sku: false,
+            prefetch_file_count: 0,
+            reason: None,
+            alternative_sources: Vec::new(),
+        }
+    }
+}
+
+impl Default for HostEnvironment {
+    fn default() -> Self {
+        HostEnvironment {
+            environment_type: "unknown".to_string(),
+            installation_type: None,
+            desktop_shell: true,
+            indicators: Vec::new(),
+        }
+    }
+}
+
 impl Default for IntegrityVerification {
     fn default() -> Self {
         IntegrityVerification {
@@ -1064,6 +2147,7 @@ impl CollectionAudit {
             collection_warnings: vec![],
             collection_statistics: CollectionStatistics::default(),
             audit_log: vec![],
+            external_commands: vec![],
         }
     }
 }
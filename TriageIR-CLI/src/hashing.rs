@@ -0,0 +1,689 @@
+//! Central file hashing service
+//! Collectors meet the same executables over and over (svchost.exe backs dozens
+//! of processes and services). Each unique file is read and hashed once; results
+//! are cached by path, size and modification time, so a file replaced during the
+//! scan is hashed again. SHA-256 is always computed, MD5, SHA-1 and the PE import
+//! hash are added with --hash-algorithms. The ssdeep fuzzy hash (--fuzzy-hash) is
+//! only computed where callers ask for it with `hash_file_fuzzy`, for process
+//! executables and suspicious persistence binaries.
+
+use crate::binutil::{read_u16, read_u32, read_u64};
+use crate::footprint;
+use crate::forensic_types::FileHashes;
+use md5::Md5;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::time::SystemTime;
+
+/// Files kept in the cache before the least recently used one is evicted
+pub const CACHE_CAPACITY: usize = 4096;
+
+/// Larger files are streamed; the import hash is only computed for files read whole
+const MAX_IN_MEMORY_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Import descriptors and thunks read per PE file, bounding malformed import tables
+const MAX_IMPORT_LIBRARIES: usize = 1024;
+const MAX_IMPORTS_PER_LIBRARY: usize = 8192;
+
+/// ssdeep parameters (spamsum): rolling hash window, smallest block size, signature length
+const ROLLING_WINDOW: usize = 7;
+const MIN_BLOCKSIZE: usize = 3;
+const SPAMSUM_LENGTH: usize = 64;
+const HASH_PRIME: u32 = 0x0100_0193;
+const HASH_INIT: u32 = 0x2802_1967;
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashAlgorithm {
+    Md5,
+    Sha1,
+    Sha256,
+    Imphash,
+    Ssdeep,
+}
+
+impl HashAlgorithm {
+    pub fn name(&self) -> &'static str {
+        match self {
+            HashAlgorithm::Md5 => "md5",
+            HashAlgorithm::Sha1 => "sha1",
+            HashAlgorithm::Sha256 => "sha256",
+            HashAlgorithm::Imphash => "imphash",
+            HashAlgorithm::Ssdeep => "ssdeep",
+        }
+    }
+
+    /// Parse a `--hash-algorithms` list such as `md5,sha1,imphash`; SHA-256 is always included
+    pub fn parse_list(list: &str) -> Result<Vec<HashAlgorithm>, String> {
+        let mut algorithms = vec![HashAlgorithm::Sha256];
+        for name in list.split(',').map(|name| name.trim().to_lowercase()).filter(|name| !name.is_empty()) {
+            let algorithm = match name.as_str() {
+                "md5" => HashAlgorithm::Md5,
+                "sha1" | "sha-1" => HashAlgorithm::Sha1,
+                "sha256" | "sha-256" => HashAlgorithm::Sha256,
+                "imphash" => HashAlgorithm::Imphash,
+                "ssdeep" => HashAlgorithm::Ssdeep,
+                _ => return Err(format!("Unknown hash algorithm '{}': expected md5, sha1, sha256, imphash or ssdeep", name)),
+            };
+            if !algorithms.contains(&algorithm) {
+                algorithms.push(algorithm);
+            }
+        }
+        Ok(algorithms)
+    }
+}
+
+/// Cache effectiveness of a hashing service
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct HashCacheStats {
+    pub files_hashed: usize,
+    pub cache_hits: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+    path: String, // Lowercased, Windows paths are case-insensitive
+    size: u64,
+    modified: Option<SystemTime>,
+}
+
+/// Least recently used cache of file digests
+struct LruCache {
+    entries: HashMap<CacheKey, (FileHashes, u64)>,
+    capacity: usize,
+    tick: u64,
+}
+
+impl LruCache {
+    fn new(capacity: usize) -> Self {
+        LruCache { entries: HashMap::new(), capacity, tick: 0 }
+    }
+
+    fn get(&mut self, key: &CacheKey) -> Option<FileHashes> {
+        self.tick += 1;
+        let tick = self.tick;
+        self.entries.get_mut(key).map(|(hashes, used)| {
+            *used = tick;
+            hashes.clone()
+        })
+    }
+
+    fn insert(&mut self, key: CacheKey, hashes: FileHashes) {
+        self.tick += 1;
+        if self.entries.len() >= self.capacity && !self.entries.contains_key(&key) {
+            if let Some(oldest) = self.entries.iter().min_by_key(|(_, (_, used))| *used).map(|(key, _)| key.clone()) {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.entries.insert(key, (hashes, self.tick));
+    }
+}
+
+pub struct HashService {
+    algorithms: Vec<HashAlgorithm>,
+    cache: Mutex<LruCache>,
+    files_hashed: AtomicUsize,
+    cache_hits: AtomicUsize,
+}
+
+impl HashService {
+    pub fn new(algorithms: Vec<HashAlgorithm>) -> Self {
+        Self::with_capacity(algorithms, CACHE_CAPACITY)
+    }
+
+    pub fn with_capacity(mut algorithms: Vec<HashAlgorithm>, capacity: usize) -> Self {
+        if !algorithms.contains(&HashAlgorithm::Sha256) {
+            algorithms.insert(0, HashAlgorithm::Sha256);
+        }
+        HashService {
+            algorithms,
+            cache: Mutex::new(LruCache::new(capacity.max(1))),
+            files_hashed: AtomicUsize::new(0),
+            cache_hits: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn algorithms(&self) -> &[HashAlgorithm] {
+        &self.algorithms
+    }
+
+    pub fn stats(&self) -> HashCacheStats {
+        HashCacheStats {
+            files_hashed: self.files_hashed.load(Ordering::Relaxed),
+            cache_hits: self.cache_hits.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Digests of a file, read from disk unless the cache holds the same version of it
+    pub fn hash_file(&self, path: impl AsRef<Path>) -> Result<FileHashes, String> {
+        let path = path.as_ref();
+        let metadata = fs::metadata(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+        if !metadata.is_file() {
+            return Err(format!("{} is not a file", path.display()));
+        }
+        let key = cache_key(path, &metadata);
+        if let Some(hashes) = self.cached(&key) {
+            return Ok(hashes);
+        }
+
+        let hashes = if metadata.len() <= MAX_IN_MEMORY_SIZE {
+            let data = footprint::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            self.digest(&data)
+        } else {
+            let file = footprint::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+            self.digest_stream(file).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?
+        };
+        self.store(key, hashes.clone());
+        Ok(hashes)
+    }
+
+    /// Digests of a file plus its ssdeep fuzzy hash when ssdeep is selected. Fuzzy
+    /// hashing reads the whole file, so files streamed by `hash_file` get none.
+    pub fn hash_file_fuzzy(&self, path: impl AsRef<Path>) -> Result<FileHashes, String> {
+        let path = path.as_ref();
+        let mut hashes = self.hash_file(path)?;
+        if hashes.ssdeep.is_some() || !self.algorithms.contains(&HashAlgorithm::Ssdeep) {
+            return Ok(hashes);
+        }
+        let metadata = fs::metadata(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+        if metadata.len() > MAX_IN_MEMORY_SIZE {
+            return Ok(hashes);
+        }
+
+        let data = footprint::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        hashes.ssdeep = Some(ssdeep(&data));
+        if let Ok(mut cache) = self.cache.lock() {
+            cache.insert(cache_key(path, &metadata), hashes.clone());
+        }
+        Ok(hashes)
+    }
+
+    /// Digests of a file the caller has already read into memory
+    pub fn hash_loaded_file(&self, path: &Path, metadata: &fs::Metadata, data: &[u8]) -> FileHashes {
+        let key = cache_key(path, metadata);
+        if let Some(hashes) = self.cached(&key) {
+            return hashes;
+        }
+        let hashes = self.digest(data);
+        self.store(key, hashes.clone());
+        hashes
+    }
+
+    fn cached(&self, key: &CacheKey) -> Option<FileHashes> {
+        let hashes = self.cache.lock().ok()?.get(key)?;
+        self.cache_hits.fetch_add(1, Ordering::Relaxed);
+        Some(hashes)
+    }
+
+    fn store(&self, key: CacheKey, hashes: FileHashes) {
+        self.files_hashed.fetch_add(1, Ordering::Relaxed);
+        if let Ok(mut cache) = self.cache.lock() {
+            cache.insert(key, hashes);
+        }
+    }
+
+    fn digest(&self, data: &[u8]) -> FileHashes {
+        let mut hashes = FileHashes::default();
+        for algorithm in &self.algorithms {
+            match algorithm {
+                HashAlgorithm::Md5 => hashes.md5 = Some(hex::encode(Md5::digest(data))),
+                HashAlgorithm::Sha1 => hashes.sha1 = Some(hex::encode(Sha1::digest(data))),
+                HashAlgorithm::Sha256 => hashes.sha256 = Some(hex::encode(Sha256::digest(data))),
+                HashAlgorithm::Imphash => hashes.imphash = imphash(data),
+                HashAlgorithm::Ssdeep => {} // Only on request, see hash_file_fuzzy
+            }
+        }
+        hashes
+    }
+
+    fn digest_stream(&self, mut reader: impl Read) -> std::io::Result<FileHashes> {
+        let selected = |algorithm| self.algorithms.contains(&algorithm);
+        let mut md5 = selected(HashAlgorithm::Md5).then(Md5::new);
+        let mut sha1 = selected(HashAlgorithm::Sha1).then(Sha1::new);
+        let mut sha256 = selected(HashAlgorithm::Sha256).then(Sha256::new);
+
+        let mut buffer = vec![0u8; 1024 * 1024];
+        loop {
+            let read = reader.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            let chunk = &buffer[..read];
+            md5.iter_mut().for_each(|hasher| hasher.update(chunk));
+            sha1.iter_mut().for_each(|hasher| hasher.update(chunk));
+            sha256.iter_mut().for_each(|hasher| hasher.update(chunk));
+        }
+
+        Ok(FileHashes {
+            md5: md5.map(|hasher| hex::encode(hasher.finalize())),
+            sha1: sha1.map(|hasher| hex::encode(hasher.finalize())),
+            sha256: sha256.map(|hasher| hex::encode(hasher.finalize())),
+            imphash: None,
+            ssdeep: None,
+        })
+    }
+}
+
+impl Default for HashService {
+    fn default() -> Self {
+        Self::new(vec![HashAlgorithm::Sha256])
+    }
+}
+
+fn cache_key(path: &Path, metadata: &fs::Metadata) -> CacheKey {
+    CacheKey {
+        path: path.to_string_lossy().to_lowercase(),
+        size: metadata.len(),
+        modified: metadata.modified().ok(),
+    }
+}
+
+fn shared_slot() -> &'static RwLock<Arc<HashService>> {
+    static SHARED: OnceLock<RwLock<Arc<HashService>>> = OnceLock::new();
+    SHARED.get_or_init(|| RwLock::new(Arc::new(HashService::default())))
+}
+
+/// The service used by the collectors of the current scan
+pub fn shared() -> Arc<HashService> {
+    match shared_slot().read() {
+        Ok(service) => Arc::clone(&service),
+        Err(poisoned) => Arc::clone(&poisoned.into_inner()),
+    }
+}
+
+/// Start a fresh shared service (empty cache) with the given algorithms
+pub fn configure(algorithms: Vec<HashAlgorithm>) {
+    let service = Arc::new(HashService::new(algorithms));
+    match shared_slot().write() {
+        Ok(mut slot) => *slot = service,
+        Err(poisoned) => *poisoned.into_inner() = service,
+    }
+}
+
+/// Like `configure`, but a service computing the same algorithms keeps its cache
+/// and only restarts its counters. Returns whether the cache was kept.
+pub fn configure_warm(mut algorithms: Vec<HashAlgorithm>) -> bool {
+    if !algorithms.contains(&HashAlgorithm::Sha256) {
+        algorithms.insert(0, HashAlgorithm::Sha256);
+    }
+    let current = shared();
+    if current.algorithms != algorithms {
+        configure(algorithms);
+        return false;
+    }
+    current.files_hashed.store(0, Ordering::Relaxed);
+    current.cache_hits.store(0, Ordering::Relaxed);
+    true
+}
+
+/// PE import hash (imphash): MD5 of the comma-separated `library.function` list
+/// in import order, lowercased, with the .dll/.ocx/.sys extension dropped.
+/// Imports by ordinal are written as `library.ordN`; the ordinal name tables
+/// pefile applies to ws2_32, wsock32 and oleaut32 are not reproduced.
+pub fn imphash(data: &[u8]) -> Option<String> {
+    let imports = pe_imports(data)?;
+    if imports.is_empty() {
+        return None;
+    }
+    Some(hex::encode(Md5::digest(imports.join(",").as_bytes())))
+}
+
+/// ssdeep context-triggered piecewise hash (`blocksize:signature:signature`),
+/// following ssdeep 2.x (libfuzzy). A chunk ends wherever the rolling hash of the last
+/// 7 bytes hits the block size; each chunk contributes one base64 character, so
+/// files sharing content share runs of the signature. The second signature uses
+/// twice the block size so digests of files of different sizes can be compared.
+pub fn ssdeep(data: &[u8]) -> String {
+    let mut block_size = MIN_BLOCKSIZE;
+    while block_size * SPAMSUM_LENGTH < data.len() {
+        block_size *= 2;
+    }
+    loop {
+        let (signature, double_signature, chunks) = spamsum(data, block_size);
+        // Too few chunks to compare meaningfully: retry with half the block size
+        if block_size > MIN_BLOCKSIZE && chunks < SPAMSUM_LENGTH / 2 {
+            block_size /= 2;
+            continue;
+        }
+        return format!("{}:{}:{}", block_size, signature, double_signature);
+    }
+}
+
+/// Signatures at one block size and the number of chunk boundaries found for it
+fn spamsum(data: &[u8], block_size: usize) -> (String, String, usize) {
+    let sum_hash = |c: u8, h: u32| h.wrapping_mul(HASH_PRIME) ^ c as u32;
+    let base64 = |h: u32| BASE64[(h % 64) as usize] as char;
+
+    let mut roll = RollingHash::default();
+    let (mut hash, mut double_hash) = (HASH_INIT, HASH_INIT);
+    let (mut signature, mut double_signature) = (String::new(), String::new());
+    // Characters of the chunk boundary past the end of each signature, which
+    // ssdeep appends when the input ends on a zero rolling hash
+    let (mut tail, mut double_tail) = (None, None);
+    for &c in data {
+        hash = sum_hash(c, hash);
+        double_hash = sum_hash(c, double_hash);
+        let rolling = roll.update(c).wrapping_add(1) as usize;
+
+        if rolling.is_multiple_of(block_size) {
+            // The last character of a full signature covers the rest of the input
+            if signature.len() < SPAMSUM_LENGTH - 1 {
+                signature.push(base64(hash));
+                hash = HASH_INIT;
+            } else {
+                tail = Some(base64(hash));
+            }
+            if rolling.is_multiple_of(block_size * 2) {
+                if double_signature.len() < SPAMSUM_LENGTH / 2 - 1 {
+                    double_signature.push(base64(double_hash));
+                    double_hash = HASH_INIT;
+                } else if double_tail.is_none() {
+                    double_tail = Some(base64(double_hash));
+                }
+            }
+        }
+    }
+
+    let chunks = signature.len();
+    if roll.sum() != 0 {
+        signature.push(base64(hash));
+        double_signature.push(base64(double_hash));
+    } else {
+        signature.extend(tail);
+        double_signature.extend(double_tail);
+    }
+    (signature, double_signature, chunks)
+}
+
+/// Rolling hash over the last ROLLING_WINDOW bytes (spamsum's Adler-32 variant)
+#[derive(Default)]
+struct RollingHash {
+    window: [u32; ROLLING_WINDOW],
+    position: usize,
+    h1: u32,
+    h2: u32,
+    h3: u32,
+}
+
+impl RollingHash {
+    fn update(&mut self, c: u8) -> u32 {
+        let c = c as u32;
+        self.h2 = self.h2.wrapping_sub(self.h1).wrapping_add(ROLLING_WINDOW as u32 * c);
+        self.h1 = self.h1.wrapping_add(c).wrapping_sub(self.window[self.position]);
+        self.window[self.position] = c;
+        self.position = (self.position + 1) % ROLLING_WINDOW;
+        self.h3 = (self.h3 << 5) ^ c;
+        self.sum()
+    }
+
+    fn sum(&self) -> u32 {
+        self.h1.wrapping_add(self.h2).wrapping_add(self.h3)
+    }
+}
+
+/// `library.function` entries of the import directory of a PE image
+fn pe_imports(data: &[u8]) -> Option<Vec<String>> {
+    if data.get(..2)? != b"MZ" {
+        return None;
+    }
+    let pe = read_u32(data, 0x3C)? as usize;
+    if data.get(pe..pe + 4)? != b"PE\0\0" {
+        return None;
+    }
+    let section_count = read_u16(data, pe + 6)? as usize;
+    let optional_header = pe + 24;
+    let optional_header_size = read_u16(data, pe + 20)? as usize;
+    let (pe32_plus, directories) = match read_u16(data, optional_header)? {
+        0x10b => (false, optional_header + 96),
+        0x20b => (true, optional_header + 112),
+        _ => return None,
+    };
+    let import_rva = read_u32(data, directories + 8)?;
+    if import_rva == 0 {
+        return Some(Vec::new());
+    }
+
+    let sections = optional_header + optional_header_size;
+    let rva_to_offset = |rva: u32| -> Option<usize> {
+        (0..section_count).find_map(|i| {
+            let header = sections + i * 40;
+            let virtual_size = read_u32(data, header + 8)?;
+            let virtual_address = read_u32(data, header + 12)?;
+            let raw_size = read_u32(data, header + 16)?;
+            let raw_pointer = read_u32(data, header + 20)?;
+            let size = virtual_size.max(raw_size);
+            (rva >= virtual_address && rva < virtual_address.checked_add(size)?)
+                .then(|| (rva - virtual_address + raw_pointer) as usize)
+        })
+    };
+    let c_string = |offset: usize| -> Option<String> {
+        let bytes = data.get(offset..)?;
+        let end = bytes.iter().take(512).position(|&b| b == 0)?;
+        Some(String::from_utf8_lossy(&bytes[..end]).to_string())
+    };
+
+    let mut imports = Vec::new();
+    let mut descriptor = rva_to_offset(import_rva)?;
+    for _ in 0..MAX_IMPORT_LIBRARIES {
+        let lookup_rva = read_u32(data, descriptor)?;
+        let name_rva = read_u32(data, descriptor + 12)?;
+        let address_rva = read_u32(data, descriptor + 16)?;
+        if name_rva == 0 && address_rva == 0 {
+            break;
+        }
+        descriptor += 20;
+
+        let Some(library) = rva_to_offset(name_rva).and_then(c_string) else {
+            continue;
+        };
+        let library = library.to_lowercase();
+        let library = match library.rsplit_once('.') {
+            Some((stem, "dll" | "ocx" | "sys")) => stem.to_string(),
+            _ => library,
+        };
+
+        let thunks = if lookup_rva != 0 { lookup_rva } else { address_rva };
+        let Some(mut thunk) = rva_to_offset(thunks) else {
+            continue;
+        };
+        for _ in 0..MAX_IMPORTS_PER_LIBRARY {
+            let (value, by_ordinal) = if pe32_plus {
+                let value = read_u64(data, thunk)?;
+                (value, value & (1 << 63) != 0)
+            } else {
+                let value = read_u32(data, thunk)? as u64;
+                (value, value & (1 << 31) != 0)
+            };
+            if value == 0 {
+                break;
+            }
+            thunk += if pe32_plus { 8 } else { 4 };
+
+            let function = if by_ordinal {
+                format!("ord{}", value & 0xFFFF)
+            } else {
+                match rva_to_offset(value as u32).and_then(|offset| c_string(offset + 2)) {
+                    Some(name) => name,
+                    None => continue,
+                }
+            };
+            imports.push(format!("{}.{}", library, function.to_lowercase()));
+        }
+    }
+
+    Some(imports)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// Minimal PE32 image importing kernel32.dll!CreateFileA, kernel32.dll!ord5 and USER32.dll!MessageBoxA
+    fn sample_pe() -> Vec<u8> {
+        let mut image = vec![0u8; 0x400];
+        image[..2].copy_from_slice(b"MZ");
+        image[0x3C..0x40].copy_from_slice(&0x80u32.to_le_bytes());
+        image[0x80..0x84].copy_from_slice(b"PE\0\0");
+        image[0x86..0x88].copy_from_slice(&1u16.to_le_bytes()); // one section
+        image[0x94..0x96].copy_from_slice(&224u16.to_le_bytes()); // optional header size
+        image[0x98..0x9A].copy_from_slice(&0x10bu16.to_le_bytes());
+        // Import directory at RVA 0x1000 (data directory 1)
+        image[0x98 + 96 + 8..0x98 + 96 + 12].copy_from_slice(&0x1000u32.to_le_bytes());
+        // .idata section: RVA 0x1000 -> file offset 0x200
+        let section = 0x98 + 224;
+        image[section + 8..section + 12].copy_from_slice(&0x200u32.to_le_bytes());
+        image[section + 12..section + 16].copy_from_slice(&0x1000u32.to_le_bytes());
+        image[section + 16..section + 20].copy_from_slice(&0x200u32.to_le_bytes());
+        image[section + 20..section + 24].copy_from_slice(&0x200u32.to_le_bytes());
+
+        let put = |image: &mut Vec<u8>, rva: u32, bytes: &[u8]| {
+            let offset = (rva - 0x1000 + 0x200) as usize;
+            image[offset..offset + bytes.len()].copy_from_slice(bytes);
+        };
+        // Descriptors: kernel32 (lookup 0x1080, name 0x1100), user32 (lookup 0x1090, name 0x1110)
+        put(&mut image, 0x1000, &0x1080u32.to_le_bytes());
+        put(&mut image, 0x100C, &0x1100u32.to_le_bytes());
+        put(&mut image, 0x1010, &0x1080u32.to_le_bytes());
+        put(&mut image, 0x1014, &0x1090u32.to_le_bytes());
+        put(&mut image, 0x1020, &0x1110u32.to_le_bytes());
+        put(&mut image, 0x1024, &0x1090u32.to_le_bytes());
+        // Thunks
+        put(&mut image, 0x1080, &0x1120u32.to_le_bytes());
+        put(&mut image, 0x1084, &0x8000_0005u32.to_le_bytes());
+        put(&mut image, 0x1090, &0x1130u32.to_le_bytes());
+        // Names
+        put(&mut image, 0x1100, b"kernel32.dll\0");
+        put(&mut image, 0x1110, b"USER32.dll\0");
+        put(&mut image, 0x1122, b"CreateFileA\0");
+        put(&mut image, 0x1132, b"MessageBoxA\0");
+        image
+    }
+
+    #[test]
+    fn test_imphash() {
+        let imports = pe_imports(&sample_pe()).unwrap();
+        assert_eq!(imports, vec!["kernel32.createfilea", "kernel32.ord5", "user32.messageboxa"]);
+        assert_eq!(imphash(&sample_pe()), Some(hex::encode(Md5::digest(b"kernel32.createfilea,kernel32.ord5,user32.messageboxa"))));
+        assert_eq!(imphash(b"not a PE file"), None);
+    }
+
+    #[test]
+    fn test_parse_hash_algorithms() {
+        assert_eq!(HashAlgorithm::parse_list("").unwrap(), vec![HashAlgorithm::Sha256]);
+        assert_eq!(
+            HashAlgorithm::parse_list("md5, SHA1,imphash,sha256").unwrap(),
+            vec![HashAlgorithm::Sha256, HashAlgorithm::Md5, HashAlgorithm::Sha1, HashAlgorithm::Imphash]
+        );
+        assert_eq!(HashAlgorithm::parse_list("ssdeep").unwrap(), vec![HashAlgorithm::Sha256, HashAlgorithm::Ssdeep]);
+        assert!(HashAlgorithm::parse_list("md5,crc32").is_err());
+    }
+
+    #[test]
+    fn test_hash_file_cached() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("tool.exe");
+        fs::write(&path, sample_pe()).unwrap();
+
+        let service = HashService::new(HashAlgorithm::parse_list("md5,sha1,imphash").unwrap());
+        let first = service.hash_file(&path).unwrap();
+        let second = service.hash_file(&path).unwrap();
+        assert_eq!(first, second);
+        assert_eq!(first.sha256, Some(hex::encode(Sha256::digest(sample_pe()))));
+        assert_eq!(first.md5, Some(hex::encode(Md5::digest(sample_pe()))));
+        assert!(first.sha1.is_some() && first.imphash.is_some());
+        assert_eq!(service.stats(), HashCacheStats { files_hashed: 1, cache_hits: 1 });
+
+        // A changed file is hashed again
+        fs::write(&path, b"replaced").unwrap();
+        let third = service.hash_file(&path).unwrap();
+        assert_eq!(third.sha256, Some(hex::encode(Sha256::digest(b"replaced"))));
+        assert_eq!(third.imphash, None);
+        assert!(service.hash_file(temp_dir.path().join("missing.exe")).is_err());
+    }
+
+    /// Deterministic pseudo-random bytes
+    fn noise(len: usize, seed: u32) -> Vec<u8> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                (state >> 16) as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_ssdeep() {
+        assert_eq!(ssdeep(b""), "3::");
+        // Digest printed by ssdeep 2.x for the python-ssdeep README example
+        assert_eq!(ssdeep(b"Also called fuzzy hashes, Ctph can match inputs that have homologies."), "3:AXGBicFlgVNhBGcL6wCrFQEv:AXGHsNhxLsr2C");
+
+        // Input ending in zero padding, as executables do, ends on a zero rolling
+        // hash: full signatures still get the character of their last boundary
+        let mut padded = noise(32 * 1024, 7);
+        padded.extend_from_slice(&[0u8; 512]);
+        let (signature, double_signature, chunks) = spamsum(&padded, MIN_BLOCKSIZE);
+        assert_eq!((chunks, signature.len(), double_signature.len()), (SPAMSUM_LENGTH - 1, SPAMSUM_LENGTH, SPAMSUM_LENGTH / 2));
+        let (short_signature, short_double, _) = spamsum(&[0u8; 64], MIN_BLOCKSIZE);
+        assert_eq!((short_signature.as_str(), short_double.as_str()), ("", ""));
+
+        let original = noise(32 * 1024, 7);
+        let digest = ssdeep(&original);
+        let parts: Vec<&str> = digest.split(':').collect();
+        assert_eq!(parts.len(), 3);
+        let block_size: usize = parts[0].parse().unwrap();
+        assert!(block_size >= MIN_BLOCKSIZE && block_size * SPAMSUM_LENGTH >= original.len() / 2);
+        assert!(parts[1].len() >= SPAMSUM_LENGTH / 2 && parts[1].len() <= SPAMSUM_LENGTH);
+        assert!(parts[2].len() <= SPAMSUM_LENGTH / 2);
+        assert_eq!(ssdeep(&original), digest);
+
+        // A small edit only changes the characters of the chunks it touches
+        let mut variant = original.clone();
+        variant[16 * 1024..16 * 1024 + 16].copy_from_slice(b"patched variant!");
+        let variant_digest = ssdeep(&variant);
+        assert_ne!(variant_digest, digest);
+        let variant_parts: Vec<&str> = variant_digest.split(':').collect();
+        assert_eq!(variant_parts[0], parts[0]);
+        let shared_prefix = parts[1].chars().zip(variant_parts[1].chars()).take_while(|(a, b)| a == b).count();
+        let shared_suffix = parts[1].chars().rev().zip(variant_parts[1].chars().rev()).take_while(|(a, b)| a == b).count();
+        assert!(shared_prefix >= 7 && shared_suffix >= 7);
+    }
+
+    #[test]
+    fn test_hash_file_fuzzy() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("dropper.exe");
+        fs::write(&path, noise(8192, 1)).unwrap();
+
+        // Without ssdeep selected no fuzzy hash is computed
+        assert_eq!(HashService::default().hash_file_fuzzy(&path).unwrap().ssdeep, None);
+
+        let service = HashService::new(HashAlgorithm::parse_list("ssdeep").unwrap());
+        assert_eq!(service.hash_file(&path).unwrap().ssdeep, None);
+        let fuzzy = service.hash_file_fuzzy(&path).unwrap();
+        assert_eq!(fuzzy.ssdeep, Some(ssdeep(&noise(8192, 1))));
+        assert_eq!(service.hash_file(&path).unwrap(), fuzzy); // Cached with the fuzzy hash
+        assert_eq!(service.stats().files_hashed, 1);
+    }
+
+    #[test]
+    fn test_lru_eviction() {
+        let key = |name: &str| CacheKey { path: name.to_string(), size: 0, modified: None };
+        let mut cache = LruCache::new(2);
+        cache.insert(key("a"), FileHashes::default());
+        cache.insert(key("b"), FileHashes::default());
+        assert!(cache.get(&key("a")).is_some());
+        cache.insert(key("c"), FileHashes::default());
+
+        assert!(cache.get(&key("a")).is_some());
+        assert!(cache.get(&key("b")).is_none());
+        assert!(cache.get(&key("c")).is_some());
+    }
+}
@@ -0,0 +1,231 @@
+//! Execution records of exited processes from process creation events
+//! Security 4688 records the image path and command line of every process
+//! started while process creation auditing was on; Sysmon event 1 adds the
+//! hashes of the image at start. Processes that exited before collection only
+//! survive there, so each event becomes a historical process record, and the
+//! binaries still on disk are hashed with the configured algorithms to make
+//! 4688 records IOC-matchable and to show binaries replaced since (hashes
+//! that no longer match the Sysmon ones).
+
+use crate::event_logs::SYSMON_CHANNEL;
+use crate::forensic_types::{AuditEntry, FileHashes, HistoricalProcess};
+use crate::hashing;
+use crate::offline::OfflineRoot;
+use crate::path_norm;
+use crate::types::{EventLogEntry, EventLogs};
+use std::collections::{HashMap, HashSet};
+
+const COMPONENT: &str = "historical_processes";
+
+const SECURITY_PROCESS_CREATION: u32 = 4688;
+const SYSMON_PROCESS_CREATION: u32 = 1;
+
+/// Historical process records of the collected events, skipping processes still running
+pub fn backfill_historical_processes(event_logs: &EventLogs, running: &[(u32, String)], offline_root: Option<&OfflineRoot>) -> (Vec<HistoricalProcess>, Vec<AuditEntry>) {
+    let start_time = std::time::Instant::now();
+    let running: HashSet<(u32, String)> = running.iter().map(|(pid, path)| (*pid, path_norm::normalize(path))).collect();
+
+    let security = event_logs.security.iter().filter(|e| e.event_id == SECURITY_PROCESS_CREATION).filter_map(from_security_event);
+    let sysmon = event_logs
+        .channels
+        .get(SYSMON_CHANNEL)
+        .into_iter()
+        .flatten()
+        .filter(|e| e.event_id == SYSMON_PROCESS_CREATION)
+        .filter_map(from_sysmon_event);
+    let mut records: Vec<HistoricalProcess> = security
+        .chain(sysmon)
+        .filter(|record| !record.pid.is_some_and(|pid| running.contains(&(pid, path_norm::normalize(&record.image_path)))))
+        .collect();
+    records.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+
+    // Hash every distinct binary once; the hashing service caches by path as well
+    let mut hashed: HashMap<String, Option<FileHashes>> = HashMap::new();
+    for record in &mut records {
+        let key = path_norm::normalize(&record.image_path);
+        let hashes = hashed
+            .entry(key)
+            .or_insert_with(|| {
+                let path = match offline_root {
+                    Some(root) => root.resolve(&record.image_path),
+                    None => record.image_path.clone().into(),
+                };
+                path.is_file().then(|| hashing::shared().hash_file(&path).ok()).flatten()
+            })
+            .clone();
+        record.binary_present = hashes.is_some();
+        record.hash_mismatch = match (&record.logged_hashes, &hashes) {
+            (Some(logged), Some(current)) => differs(&logged.sha256, &current.sha256) || differs(&logged.sha1, &current.sha1) || differs(&logged.md5, &current.md5),
+            _ => false,
+        };
+        record.hashes = hashes;
+    }
+
+    let present = hashed.values().filter(|hashes| hashes.is_some()).count();
+    let mismatches = records.iter().filter(|record| record.hash_mismatch).count();
+    let mut audit_log = Vec::new();
+    if mismatches > 0 {
+        audit_log.push(AuditEntry::new(COMPONENT, "WARN", "hash_mismatch", format!("{} binaries differ from the hashes Sysmon logged at execution", mismatches), "warning"));
+    }
+    audit_log.push(AuditEntry {
+        duration_ms: Some(start_time.elapsed().as_millis() as u64),
+        ..AuditEntry::new(
+            COMPONENT,
+            "INFO",
+            "complete_backfill",
+            format!(
+                "{} exited processes from process creation events, {} of {} binaries still present and hashed, {} replaced since execution",
+                records.len(),
+                present,
+                hashed.len(),
+                mismatches
+            ),
+            "success",
+        )
+    });
+    (records, audit_log)
+}
+
+fn from_security_event(event: &EventLogEntry) -> Option<HistoricalProcess> {
+    let field = |name: &str| event.event_data.get(name).map(String::as_str).unwrap_or_default();
+    let image_path = field("NewProcessName");
+    if image_path.is_empty() {
+        return None;
+    }
+    // The target account is set when the process runs as another user (4688 version 2)
+    let user = match field("TargetUserName") {
+        "" | "-" => account(field("SubjectDomainName"), field("SubjectUserName")),
+        target => account(field("TargetDomainName"), target),
+    };
+    Some(HistoricalProcess {
+        source: "security_4688".to_string(),
+        timestamp: event.timestamp.clone(),
+        record_id: event.record_id,
+        pid: parse_pid(field("NewProcessId")),
+        parent_pid: parse_pid(field("ProcessId")),
+        image_path: image_path.to_string(),
+        command_line: field("CommandLine").to_string(),
+        parent_image: field("ParentProcessName").to_string(),
+        user,
+        process_guid: None,
+        logged_hashes: None,
+        binary_present: false,
+        hashes: None,
+        hash_mismatch: false,
+    })
+}
+
+fn from_sysmon_event(event: &EventLogEntry) -> Option<HistoricalProcess> {
+    let field = |name: &str| event.event_data.get(name).map(String::as_str).unwrap_or_default();
+    let image_path = field("Image");
+    if image_path.is_empty() {
+        return None;
+    }
+    Some(HistoricalProcess {
+        source: "sysmon_1".to_string(),
+        timestamp: event.timestamp.clone(),
+        record_id: event.record_id,
+        pid: parse_pid(field("ProcessId")),
+        parent_pid: parse_pid(field("ParentProcessId")),
+        image_path: image_path.to_string(),
+        command_line: field("CommandLine").to_string(),
+        parent_image: field("ParentImage").to_string(),
+        user: field("User").to_string(),
+        process_guid: Some(field("ProcessGuid").trim_matches(['{', '}']).to_lowercase()).filter(|guid| !guid.is_empty()),
+        logged_hashes: parse_sysmon_hashes(field("Hashes")),
+        binary_present: false,
+        hashes: None,
+        hash_mismatch: false,
+    })
+}
+
+/// Sysmon `Hashes` field: SHA1=...,MD5=...,SHA256=...,IMPHASH=...
+fn parse_sysmon_hashes(field: &str) -> Option<FileHashes> {
+    let mut hashes = FileHashes::default();
+    for (algorithm, value) in field.split(',').filter_map(|pair| pair.split_once('=')) {
+        let value = Some(value.trim().to_lowercase());
+        match algorithm.trim().to_uppercase().as_str() {
+            "MD5" => hashes.md5 = value,
+            "SHA1" => hashes.sha1 = value,
+            "SHA256" => hashes.sha256 = value,
+            "IMPHASH" => hashes.imphash = value,
+            _ => {}
+        }
+    }
+    (hashes != FileHashes::default()).then_some(hashes)
+}
+
+/// PIDs are logged in hex by 4688 (0x1a2c) and in decimal by Sysmon
+fn parse_pid(text: &str) -> Option<u32> {
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+fn account(domain: &str, user: &str) -> String {
+    match (domain, user) {
+        (_, "" | "-") => String::new(),
+        ("" | "-", user) => user.to_string(),
+        (domain, user) => format!("{}\\{}", domain, user),
+    }
+}
+
+/// Only hashes computed both at execution and now can differ
+fn differs(logged: &Option<String>, current: &Option<String>) -> bool {
+    matches!((logged, current), (Some(logged), Some(current)) if !logged.eq_ignore_ascii_case(current))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(event_id: u32, source: &str, fields: &[(&str, &str)]) -> EventLogEntry {
+        let mut entry = EventLogEntry::new_with_source(event_id, "Information".to_string(), "2024-03-01T10:00:00Z".to_string(), String::new(), source.to_string());
+        entry.event_data = fields.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        entry
+    }
+
+    #[test]
+    fn test_backfill_historical_processes() {
+        let dir = tempfile::tempdir().unwrap();
+        let binary = dir.path().join("tool.exe");
+        std::fs::write(&binary, b"MZ replaced binary").unwrap();
+        let binary_path = binary.to_string_lossy().to_string();
+
+        let mut event_logs = EventLogs::default();
+        event_logs.security.push(event(4688, "Security", &[
+            ("NewProcessName", &binary_path),
+            ("NewProcessId", "0x1a2c"),
+            ("ProcessId", "0x4"),
+            ("CommandLine", "tool.exe -dump"),
+            ("SubjectDomainName", "CORP"),
+            ("SubjectUserName", "alice"),
+            ("TargetUserName", "-"),
+        ]));
+        // Still running with the same PID and image: not a historical process
+        event_logs.security.push(event(4688, "Security", &[("NewProcessName", "C:\\Windows\\System32\\svchost.exe"), ("NewProcessId", "0x2c0")]));
+        event_logs.channels.insert(SYSMON_CHANNEL.to_string(), vec![event(1, SYSMON_CHANNEL, &[
+            ("Image", &binary_path),
+            ("ProcessId", "6700"),
+            ("ProcessGuid", "{A1B2C3D4-0000-0000-0000-000000000001}"),
+            ("Hashes", "MD5=00000000000000000000000000000000,SHA256=ABCDEF,IMPHASH=11"),
+        ])]);
+
+        let running = vec![(704, "C:/WINDOWS/system32/svchost.exe".to_string())];
+        let (records, audit_log) = backfill_historical_processes(&event_logs, &running, None);
+        assert_eq!(records.len(), 2);
+
+        let security = records.iter().find(|r| r.source == "security_4688").unwrap();
+        assert_eq!((security.pid, security.parent_pid), (Some(0x1a2c), Some(4)));
+        assert_eq!(security.user, "CORP\\alice");
+        assert!(security.binary_present && security.hashes.as_ref().unwrap().sha256.is_some());
+        assert!(!security.hash_mismatch);
+
+        let sysmon = records.iter().find(|r| r.source == "sysmon_1").unwrap();
+        assert_eq!(sysmon.process_guid.as_deref(), Some("a1b2c3d4-0000-0000-0000-000000000001"));
+        assert_eq!(sysmon.logged_hashes.as_ref().unwrap().sha256.as_deref(), Some("abcdef"));
+        assert!(sysmon.hash_mismatch);
+        assert!(audit_log.iter().any(|entry| entry.action == "hash_mismatch"));
+    }
+}
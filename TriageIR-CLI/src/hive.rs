@@ -0,0 +1,532 @@
+//! Raw registry hive parser
+//! Reads regf hive files (SYSTEM, SOFTWARE, SAM, Amcache.hve, NTUSER.DAT, ...)
+//! directly, without the Windows registry API, so offline volumes and hives
+//! copied from a live host or its shadow copy can be examined on any platform
+//! and without the privileges RegLoadAppKey needs. Keys and values are read
+//! from the primary file only: transaction logs of a dirty hive are not
+//! replayed, which `is_dirty` reports so the collector can say so.
+
+use crate::binutil::{filetime_to_string, read_u16, read_u32, read_u64};
+use crate::footprint;
+use crate::offline;
+use std::collections::HashSet;
+use std::path::Path;
+
+/// Size of the base block preceding the hive bins
+const BASE_BLOCK_SIZE: usize = 4096;
+/// Largest data cell; longer values are split into segments by a "db" record
+const BIG_DATA_SEGMENT: usize = 16344;
+/// Nesting limit of index roots ("ri"), guarding against loops in corrupt hives
+const MAX_INDEX_DEPTH: usize = 8;
+/// Subkeys read from one key; a crafted list cannot make a key larger than this
+const MAX_SUBKEYS: usize = 1 << 20;
+
+/// Key node flag: the name is stored in Latin-1 rather than UTF-16
+const KEY_COMP_NAME: u16 = 0x0020;
+/// Value flag: the name is stored in Latin-1 rather than UTF-16
+const VALUE_COMP_NAME: u16 = 0x0001;
+/// Data size flag: up to four bytes of data are stored in the offset field
+const DATA_RESIDENT: u32 = 0x8000_0000;
+
+pub const REG_SZ: u32 = 1;
+pub const REG_EXPAND_SZ: u32 = 2;
+pub const REG_BINARY: u32 = 3;
+pub const REG_DWORD: u32 = 4;
+pub const REG_DWORD_BIG_ENDIAN: u32 = 5;
+pub const REG_MULTI_SZ: u32 = 7;
+pub const REG_QWORD: u32 = 11;
+
+pub struct Hive {
+    data: Vec<u8>,
+    root: u32,
+    minor_version: u32,
+    dirty: bool,
+}
+
+impl Hive {
+    pub fn open(path: &Path) -> Result<Hive, String> {
+        let data = footprint::read(path).map_err(|e| format!("Failed to read hive {}: {}", path.display(), e))?;
+        Hive::parse(data).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn parse(data: Vec<u8>) -> Result<Hive, String> {
+        if !data.starts_with(b"regf") {
+            return Err("not a registry hive (regf signature missing)".to_string());
+        }
+        if data.get(BASE_BLOCK_SIZE..BASE_BLOCK_SIZE + 4) != Some(b"hbin") {
+            return Err("no hive bins after the base block".to_string());
+        }
+        let field = |offset| read_u32(&data, offset).unwrap_or_default();
+        let hive = Hive { root: field(0x24), minor_version: field(0x18), dirty: field(0x04) != field(0x08), data };
+        hive.root()?;
+        Ok(hive)
+    }
+
+    /// Primary and secondary sequence numbers differ: the hive was not
+    /// written back completely and newer data may be in its transaction logs
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    pub fn root(&self) -> Result<Key<'_>, String> {
+        self.key(self.root).ok_or_else(|| format!("root key cell at offset {} is not a key node", self.root))
+    }
+
+    /// Key at a backslash-separated path below the root, matched case-insensitively
+    pub fn open_key(&self, path: &str) -> Option<Key<'_>> {
+        self.root().ok()?.subkey(path)
+    }
+
+    fn cell(&self, offset: u32) -> Option<&[u8]> {
+        let start = BASE_BLOCK_SIZE.checked_add(offset as usize)?;
+        // Allocated cells have a negative size; free ones are read as well
+        let size = (read_u32(&self.data, start)? as i32).unsigned_abs() as usize;
+        self.data.get(start + 4..start.checked_add(size)?)
+    }
+
+    fn key(&self, offset: u32) -> Option<Key<'_>> {
+        let cell = self.cell(offset)?;
+        (cell.starts_with(b"nk") && cell.len() >= 76).then_some(Key { hive: self, offset, cell })
+    }
+
+    /// Offsets of the key nodes of a subkey list: "lf", "lh" and "li" leaves and "ri" roots.
+    /// Each list and key cell is read once, so lists referring to themselves or each other end.
+    fn subkey_offsets(&self, list: u32, depth: usize, visited: &mut HashSet<u32>, offsets: &mut Vec<u32>) {
+        if !visited.insert(list) {
+            return;
+        }
+        let Some(cell) = self.cell(list) else { return };
+        let count = read_u16(cell, 2).unwrap_or_default() as usize;
+        let (stride, is_root) = match cell.get(..2) {
+            Some(b"lf") | Some(b"lh") => (8, false),
+            Some(b"li") => (4, false),
+            Some(b"ri") => (4, true),
+            _ => return,
+        };
+        for index in 0..count {
+            if offsets.len() >= MAX_SUBKEYS {
+                return;
+            }
+            let Some(offset) = read_u32(cell, 4 + index * stride) else { break };
+            if is_root {
+                if depth < MAX_INDEX_DEPTH {
+                    self.subkey_offsets(offset, depth + 1, visited, offsets);
+                }
+            } else if visited.insert(offset) {
+                offsets.push(offset);
+            }
+        }
+    }
+
+    fn value_data(&self, size: u32, offset: u32, vk: &[u8]) -> Vec<u8> {
+        if size & DATA_RESIDENT != 0 {
+            let length = ((size & !DATA_RESIDENT) as usize).min(4);
+            return vk.get(8..8 + length).map(<[u8]>::to_vec).unwrap_or_default();
+        }
+        let size = size as usize;
+        let Some(cell) = self.cell(offset) else { return Vec::new() };
+        if size > BIG_DATA_SEGMENT && self.minor_version >= 4 && cell.starts_with(b"db") {
+            let count = read_u16(cell, 2).unwrap_or_default() as usize;
+            let segments = read_u32(cell, 4).and_then(|list| self.cell(list)).unwrap_or_default();
+            let mut data = Vec::with_capacity(size);
+            for index in 0..count {
+                let Some(segment) = read_u32(segments, index * 4).and_then(|offset| self.cell(offset)) else { break };
+                data.extend_from_slice(&segment[..segment.len().min(BIG_DATA_SEGMENT)]);
+            }
+            data.truncate(size);
+            return data;
+        }
+        cell[..size.min(cell.len())].to_vec()
+    }
+}
+
+/// A key node of a hive
+#[derive(Clone, Copy)]
+pub struct Key<'a> {
+    hive: &'a Hive,
+    offset: u32,
+    cell: &'a [u8],
+}
+
+impl<'a> Key<'a> {
+    pub fn name(&self) -> String {
+        let length = read_u16(self.cell, 0x48).unwrap_or_default() as usize;
+        let flags = read_u16(self.cell, 0x02).unwrap_or_default();
+        decode_name(self.cell.get(0x4C..0x4C + length).unwrap_or_default(), flags & KEY_COMP_NAME != 0)
+    }
+
+    /// Last write time as a FILETIME
+    pub fn last_written_filetime(&self) -> u64 {
+        read_u64(self.cell, 0x04).unwrap_or_default()
+    }
+
+    /// Last write time as RFC 3339, or an empty string if unset
+    pub fn last_written(&self) -> String {
+        filetime_to_string(self.last_written_filetime())
+    }
+
+    pub fn subkeys(&self) -> Vec<Key<'a>> {
+        let mut offsets = Vec::new();
+        if read_u32(self.cell, 0x14).unwrap_or_default() > 0 {
+            if let Some(list) = read_u32(self.cell, 0x1C) {
+                // A key is never its own subkey
+                let mut visited = HashSet::from([self.offset]);
+                self.hive.subkey_offsets(list, 0, &mut visited, &mut offsets);
+            }
+        }
+        offsets.into_iter().filter_map(|offset| self.hive.key(offset)).collect()
+    }
+
+    /// Key at a backslash-separated path below this one, matched case-insensitively;
+    /// a path that leads back to a key already passed is not followed
+    pub fn subkey(&self, path: &str) -> Option<Key<'a>> {
+        let mut passed = HashSet::from([self.offset]);
+        path.split('\\').filter(|name| !name.is_empty()).try_fold(*self, |key, name| {
+            let subkey = key.subkeys().into_iter().find(|subkey| subkey.name().eq_ignore_ascii_case(name))?;
+            passed.insert(subkey.offset).then_some(subkey)
+        })
+    }
+
+    pub fn values(&self) -> Vec<Value> {
+        let count = read_u32(self.cell, 0x24).unwrap_or_default() as usize;
+        let Some(list) = read_u32(self.cell, 0x28).and_then(|offset| self.hive.cell(offset)) else {
+            return Vec::new();
+        };
+        (0..count)
+            .filter_map(|index| read_u32(list, index * 4))
+            .filter_map(|offset| self.hive.cell(offset))
+            .filter(|vk| vk.starts_with(b"vk") && vk.len() >= 20)
+            .map(|vk| {
+                let name_length = read_u16(vk, 0x02).unwrap_or_default() as usize;
+                let flags = read_u16(vk, 0x10).unwrap_or_default();
+                let (size, offset) = (read_u32(vk, 0x04).unwrap_or_default(), read_u32(vk, 0x08).unwrap_or_default());
+                Value {
+                    name: decode_name(vk.get(0x14..0x14 + name_length).unwrap_or_default(), flags & VALUE_COMP_NAME != 0),
+                    kind: read_u32(vk, 0x0C).unwrap_or_default(),
+                    data: self.hive.value_data(size, offset, vk),
+                }
+            })
+            .collect()
+    }
+
+    /// Value by name, case-insensitively; "" is the default value
+    pub fn value(&self, name: &str) -> Option<Value> {
+        self.values().into_iter().find(|value| value.name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn string(&self, name: &str) -> Option<String> {
+        self.value(name)?.as_string()
+    }
+
+    pub fn u32(&self, name: &str) -> Option<u32> {
+        self.value(name)?.as_u32()
+    }
+
+    pub fn u64(&self, name: &str) -> Option<u64> {
+        self.value(name)?.as_u64()
+    }
+}
+
+/// A value with its registry type and raw data
+#[derive(Debug, Clone, PartialEq)]
+pub struct Value {
+    pub name: String,
+    pub kind: u32, // REG_* type
+    pub data: Vec<u8>,
+}
+
+/// Interpreted value data
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValueData {
+    String(String), // REG_SZ and REG_EXPAND_SZ
+    MultiString(Vec<String>),
+    Dword(u32), // REG_DWORD and REG_DWORD_BIG_ENDIAN
+    Qword(u64),
+    Binary(Vec<u8>), // REG_BINARY and every other type
+}
+
+impl Value {
+    pub fn typed(&self) -> ValueData {
+        match self.kind {
+            REG_SZ | REG_EXPAND_SZ => ValueData::String(utf16_until_nul(&self.data)),
+            REG_MULTI_SZ => ValueData::MultiString(
+                utf16_units(&self.data).split(|&unit| unit == 0).filter(|s| !s.is_empty()).map(String::from_utf16_lossy).collect(),
+            ),
+            REG_DWORD if self.data.len() >= 4 => ValueData::Dword(read_u32(&self.data, 0).unwrap_or_default()),
+            REG_DWORD_BIG_ENDIAN if self.data.len() >= 4 => ValueData::Dword(u32::from_be_bytes([self.data[0], self.data[1], self.data[2], self.data[3]])),
+            REG_QWORD if self.data.len() >= 8 => ValueData::Qword(read_u64(&self.data, 0).unwrap_or_default()),
+            _ => ValueData::Binary(self.data.clone()),
+        }
+    }
+
+    /// Text of a REG_SZ or REG_EXPAND_SZ value, as winreg's get_value::<String> reads it
+    pub fn as_string(&self) -> Option<String> {
+        match self.typed() {
+            ValueData::String(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    pub fn as_u32(&self) -> Option<u32> {
+        match self.typed() {
+            ValueData::Dword(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        match self.typed() {
+            ValueData::Qword(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+/// Name of the control set the system booted with last (`Select\Current`);
+/// hive files have no `CurrentControlSet` link
+pub fn current_control_set(system_hive: &Hive) -> String {
+    let current = system_hive.open_key("Select").and_then(|select| select.u32("Current")).unwrap_or(1);
+    offline::control_set_name(current)
+}
+
+fn decode_name(bytes: &[u8], latin1: bool) -> String {
+    match latin1 {
+        true => bytes.iter().map(|&byte| byte as char).collect(),
+        false => String::from_utf16_lossy(&utf16_units(bytes)),
+    }
+}
+
+fn utf16_units(bytes: &[u8]) -> Vec<u16> {
+    bytes.chunks_exact(2).map(|pair| u16::from_le_bytes([pair[0], pair[1]])).collect()
+}
+
+fn utf16_until_nul(bytes: &[u8]) -> String {
+    let units = utf16_units(bytes);
+    let end = units.iter().position(|&unit| unit == 0).unwrap_or(units.len());
+    String::from_utf16_lossy(&units[..end])
+}
+
+/// Builds hive files for tests: keys are created along their paths, names are
+/// stored in Latin-1 and subkeys in "lf" lists, as Windows writes them
+#[cfg(test)]
+#[derive(Default)]
+pub(crate) struct HiveBuilder {
+    root: TestKey,
+}
+
+#[cfg(test)]
+#[derive(Default)]
+struct TestKey {
+    name: String,
+    last_written: u64,
+    subkeys: Vec<TestKey>,
+    values: Vec<(String, u32, Vec<u8>)>,
+}
+
+#[cfg(test)]
+impl HiveBuilder {
+    pub(crate) fn key(&mut self, path: &str, last_written: u64) -> &mut Self {
+        self.node(path).last_written = last_written;
+        self
+    }
+
+    pub(crate) fn value(&mut self, path: &str, name: &str, kind: u32, data: &[u8]) -> &mut Self {
+        self.node(path).values.push((name.to_string(), kind, data.to_vec()));
+        self
+    }
+
+    pub(crate) fn string(&mut self, path: &str, name: &str, text: &str) -> &mut Self {
+        let data: Vec<u8> = text.encode_utf16().chain([0]).flat_map(u16::to_le_bytes).collect();
+        self.value(path, name, REG_SZ, &data)
+    }
+
+    pub(crate) fn dword(&mut self, path: &str, name: &str, value: u32) -> &mut Self {
+        self.value(path, name, REG_DWORD, &value.to_le_bytes())
+    }
+
+    fn node(&mut self, path: &str) -> &mut TestKey {
+        path.split('\\').filter(|name| !name.is_empty()).fold(&mut self.root, |key, name| {
+            match key.subkeys.iter().position(|subkey| subkey.name == name) {
+                Some(index) => &mut key.subkeys[index],
+                None => {
+                    key.subkeys.push(TestKey { name: name.to_string(), ..Default::default() });
+                    key.subkeys.last_mut().unwrap()
+                }
+            }
+        })
+    }
+
+    pub(crate) fn build(&self) -> Vec<u8> {
+        let mut bins = b"hbin".to_vec();
+        bins.resize(32, 0);
+        let root = Self::write_key(&self.root, "ROOT", u32::MAX, &mut bins);
+        bins.resize(bins.len().div_ceil(4096) * 4096, 0);
+        let size = bins.len() as u32;
+        bins[8..12].copy_from_slice(&size.to_le_bytes());
+
+        let mut hive = b"regf".to_vec();
+        hive.resize(BASE_BLOCK_SIZE, 0);
+        hive[0x04..0x08].copy_from_slice(&1u32.to_le_bytes());
+        hive[0x08..0x0C].copy_from_slice(&1u32.to_le_bytes());
+        hive[0x14..0x18].copy_from_slice(&1u32.to_le_bytes());
+        hive[0x18..0x1C].copy_from_slice(&5u32.to_le_bytes());
+        hive[0x24..0x28].copy_from_slice(&root.to_le_bytes());
+        hive[0x28..0x2C].copy_from_slice(&size.to_le_bytes());
+        hive.extend(bins);
+        hive
+    }
+
+    /// Append a cell; returns its offset from the start of the hive bins
+    fn cell(bins: &mut Vec<u8>, data: &[u8]) -> u32 {
+        let offset = bins.len() as u32;
+        let size = (data.len() + 4).div_ceil(8) * 8;
+        bins.extend((-(size as i32)).to_le_bytes());
+        bins.extend(data);
+        bins.resize(offset as usize + size, 0);
+        offset
+    }
+
+    fn write_key(key: &TestKey, name: &str, parent: u32, bins: &mut Vec<u8>) -> u32 {
+        let mut nk = vec![0u8; 0x4C];
+        nk[..2].copy_from_slice(b"nk");
+        nk[2..4].copy_from_slice(&(KEY_COMP_NAME | if parent == u32::MAX { 0x0C } else { 0 }).to_le_bytes());
+        nk[4..12].copy_from_slice(&key.last_written.to_le_bytes());
+        nk[0x10..0x14].copy_from_slice(&parent.to_le_bytes());
+        nk[0x1C..0x20].copy_from_slice(&u32::MAX.to_le_bytes());
+        nk[0x20..0x24].copy_from_slice(&u32::MAX.to_le_bytes());
+        nk[0x28..0x2C].copy_from_slice(&u32::MAX.to_le_bytes());
+        nk[0x2C..0x30].copy_from_slice(&u32::MAX.to_le_bytes());
+        nk[0x30..0x34].copy_from_slice(&u32::MAX.to_le_bytes());
+        nk[0x48..0x4A].copy_from_slice(&(name.len() as u16).to_le_bytes());
+        nk.extend(name.bytes());
+        let offset = Self::cell(bins, &nk);
+        let at = |field: usize| offset as usize + 4 + field;
+
+        if !key.subkeys.is_empty() {
+            let children: Vec<u32> = key.subkeys.iter().map(|subkey| Self::write_key(subkey, &subkey.name, offset, bins)).collect();
+            let mut list = b"lf".to_vec();
+            list.extend((children.len() as u16).to_le_bytes());
+            for (child, subkey) in children.iter().zip(&key.subkeys) {
+                list.extend(child.to_le_bytes());
+                list.extend(subkey.name.bytes().chain([0; 4]).take(4).collect::<Vec<u8>>());
+            }
+            let list = Self::cell(bins, &list);
+            bins[at(0x14)..at(0x18)].copy_from_slice(&(children.len() as u32).to_le_bytes());
+            bins[at(0x1C)..at(0x20)].copy_from_slice(&list.to_le_bytes());
+        }
+
+        if !key.values.is_empty() {
+            let mut value_offsets = Vec::new();
+            for (name, kind, data) in &key.values {
+                let (size, data_offset) = match data.len() {
+                    length if length <= 4 => {
+                        let mut resident = [0u8; 4];
+                        resident[..length].copy_from_slice(data);
+                        (length as u32 | DATA_RESIDENT, u32::from_le_bytes(resident))
+                    }
+                    length if length > BIG_DATA_SEGMENT => {
+                        let segments: Vec<u32> = data.chunks(BIG_DATA_SEGMENT).map(|chunk| Self::cell(bins, chunk)).collect();
+                        let list = Self::cell(bins, &segments.iter().flat_map(|s| s.to_le_bytes()).collect::<Vec<u8>>());
+                        let db = [b"db".as_slice(), &(segments.len() as u16).to_le_bytes(), &list.to_le_bytes()].concat();
+                        (length as u32, Self::cell(bins, &db))
+                    }
+                    length => (length as u32, Self::cell(bins, data)),
+                };
+                let mut vk = b"vk".to_vec();
+                vk.extend((name.len() as u16).to_le_bytes());
+                vk.extend(size.to_le_bytes());
+                vk.extend(data_offset.to_le_bytes());
+                vk.extend(kind.to_le_bytes());
+                vk.extend(VALUE_COMP_NAME.to_le_bytes());
+                vk.extend([0u8; 2]);
+                vk.extend(name.bytes());
+                value_offsets.push(Self::cell(bins, &vk));
+            }
+            let list = Self::cell(bins, &value_offsets.iter().flat_map(|v| v.to_le_bytes()).collect::<Vec<u8>>());
+            bins[at(0x24)..at(0x28)].copy_from_slice(&(value_offsets.len() as u32).to_le_bytes());
+            bins[at(0x28)..at(0x2C)].copy_from_slice(&list.to_le_bytes());
+        }
+        offset
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_built_hive() {
+        let long: Vec<u8> = (0..40_000u32).map(|i| (i % 251) as u8).collect();
+        let hive = HiveBuilder::default()
+            .dword("Select", "Current", 2)
+            .key("ControlSet002\\Control\\ComputerName\\ComputerName", 133_580_000_000_000_000)
+            .string("ControlSet002\\Control\\ComputerName\\ComputerName", "ComputerName", "WKS-042")
+            .value("ControlSet002\\Control\\Session Manager\\AppCompatCache", "AppCompatCache", REG_BINARY, &long)
+            .value("ControlSet002\\Services\\Tcpip", "SearchList", REG_MULTI_SZ, &"a.corp\0b.corp\0\0".encode_utf16().flat_map(u16::to_le_bytes).collect::<Vec<u8>>())
+            .value("ControlSet002\\Services\\Tcpip", "Stamp", REG_QWORD, &7u64.to_le_bytes())
+            .build();
+        let hive = Hive::parse(hive).unwrap();
+        assert!(!hive.is_dirty());
+        assert_eq!(current_control_set(&hive), "ControlSet002");
+
+        let computer_name = hive.open_key("controlset002\\CONTROL\\ComputerName\\ComputerName").unwrap();
+        assert_eq!(computer_name.name(), "ComputerName");
+        assert_eq!(computer_name.string("computername").as_deref(), Some("WKS-042"));
+        assert_eq!(computer_name.last_written(), "2024-04-19T11:33:20+00:00");
+        assert_eq!(hive.root().unwrap().subkeys().len(), 2);
+
+        // Values over 16344 bytes are split into big data segments
+        let cache = hive.open_key("ControlSet002\\Control\\Session Manager\\AppCompatCache").unwrap().value("AppCompatCache").unwrap();
+        assert_eq!((cache.kind, cache.data.len()), (REG_BINARY, 40_000));
+        assert_eq!(cache.data, long);
+
+        let tcpip = hive.open_key("ControlSet002\\Services\\Tcpip").unwrap();
+        assert_eq!(tcpip.value("SearchList").unwrap().typed(), ValueData::MultiString(vec!["a.corp".to_string(), "b.corp".to_string()]));
+        assert_eq!(tcpip.u64("Stamp"), Some(7));
+        assert_eq!(tcpip.u32("Stamp"), None);
+        assert!(hive.open_key("ControlSet002\\Missing").is_none());
+
+        assert!(Hive::parse(b"not a hive".to_vec()).is_err());
+    }
+
+    /// Offset of the subkey list of the key node at `key`
+    fn list_offset(hive: &[u8], key: u32) -> usize {
+        BASE_BLOCK_SIZE + read_u32(hive, BASE_BLOCK_SIZE + key as usize + 4 + 0x1C).unwrap() as usize
+    }
+
+    #[test]
+    fn test_subkey_list_loops() {
+        let mut builder = HiveBuilder::default();
+        for index in 0..64 {
+            builder.key(&format!("K{:02}\\Child", index), 0);
+        }
+        builder.key("K00\\Other", 0);
+        let mut data = builder.build();
+        let root = read_u32(&data, 0x24).unwrap();
+
+        // An index root whose 64 entries all point back at itself
+        let list = list_offset(&data, root);
+        let list_cell = (list - BASE_BLOCK_SIZE) as u32;
+        data[list + 4..list + 8].copy_from_slice(&[b'r', b'i', 64, 0]);
+        for index in 0..64 {
+            data[list + 8 + index * 4..list + 12 + index * 4].copy_from_slice(&list_cell.to_le_bytes());
+        }
+        let hive = Hive::parse(data).unwrap();
+        let started = std::time::Instant::now();
+        assert!(hive.open_key("K00").is_none());
+        assert!(hive.root().unwrap().subkeys().is_empty());
+        assert!(started.elapsed() < std::time::Duration::from_secs(5));
+
+        // Subkey list entries pointing back at their own key or at the root are not followed
+        let mut data = builder.build();
+        let k00 = Hive::parse(data.clone()).unwrap().open_key("K00").unwrap().offset;
+        let child_list = list_offset(&data, k00);
+        data[child_list + 8..child_list + 12].copy_from_slice(&k00.to_le_bytes());
+        data[child_list + 16..child_list + 20].copy_from_slice(&root.to_le_bytes());
+        let hive = Hive::parse(data).unwrap();
+        let k00 = hive.open_key("K00").unwrap();
+        assert_eq!(k00.subkeys().iter().map(Key::name).collect::<Vec<_>>(), ["ROOT"]);
+        assert!(hive.open_key("K00\\ROOT").is_none());
+        assert!(hive.open_key("K00\\K00").is_none());
+    }
+}
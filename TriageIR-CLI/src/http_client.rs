@@ -0,0 +1,313 @@
+//! Minimal HTTP client
+//! Used by the remote output sinks. On Windows requests go through WinHTTP,
+//! which provides TLS with the system certificate store and the configured
+//! proxy; elsewhere only plain `http://` is supported, over a raw socket.
+
+use std::time::Duration;
+
+#[cfg(not(windows))]
+use std::io::{Read, Write};
+#[cfg(not(windows))]
+use std::net::{TcpStream, ToSocketAddrs};
+
+/// User-Agent of every request
+pub const USER_AGENT: &str = concat!("triageir-cli/", env!("CARGO_PKG_VERSION"));
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpUrl {
+    pub secure: bool,
+    pub host: String,
+    pub port: u16,
+    pub path: String, // Path and query, at least "/"
+}
+
+impl HttpUrl {
+    pub fn parse(url: &str) -> Result<Self, String> {
+        let (secure, rest) = if let Some(rest) = url.strip_prefix("https://") {
+            (true, rest)
+        } else if let Some(rest) = url.strip_prefix("http://") {
+            (false, rest)
+        } else {
+            return Err(format!("unsupported URL {} (http:// or https:// expected)", url));
+        };
+        let (authority, path) = match rest.find('/') {
+            Some(index) => (&rest[..index], &rest[index..]),
+            None => (rest, "/"),
+        };
+        let (host, port) = match authority.rsplit_once(':').filter(|(_, port)| !port.contains(']')) {
+            Some((host, port)) => (host, port.parse::<u16>().map_err(|_| format!("invalid port in URL {}", url))?),
+            None => (authority, if secure { 443 } else { 80 }),
+        };
+        if host.is_empty() {
+            return Err(format!("missing host in URL {}", url));
+        }
+        Ok(HttpUrl { secure, host: host.to_string(), port, path: path.to_string() })
+    }
+
+    /// Host header value; the port is left out when it is the scheme default
+    pub fn host_header(&self) -> String {
+        if self.port == if self.secure { 443 } else { 80 } {
+            self.host.clone()
+        } else {
+            format!("{}:{}", self.host, self.port)
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// First response header of that name, case-insensitive
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(header, _)| header.eq_ignore_ascii_case(name)).map(|(_, value)| value.as_str())
+    }
+
+    /// Start of the body for error messages
+    pub fn body_excerpt(&self) -> String {
+        String::from_utf8_lossy(&self.body).chars().take(200).collect()
+    }
+}
+
+/// Send a request and read the whole response
+pub fn send(method: &str, url: &str, headers: &[(String, String)], body: &[u8], timeout: Duration) -> Result<HttpResponse, String> {
+    let url = HttpUrl::parse(url)?;
+    #[cfg(windows)]
+    {
+        winhttp::send(method, &url, headers, body, timeout)
+    }
+    #[cfg(not(windows))]
+    {
+        send_plain(method, &url, headers, body, timeout)
+    }
+}
+
+#[cfg(not(windows))]
+fn send_plain(method: &str, url: &HttpUrl, headers: &[(String, String)], body: &[u8], timeout: Duration) -> Result<HttpResponse, String> {
+    if url.secure {
+        return Err(format!("HTTPS to {} requires WinHTTP (Windows)", url.host));
+    }
+    let address = (url.host.as_str(), url.port)
+        .to_socket_addrs()
+        .map_err(|e| format!("Failed to resolve {}: {}", url.host, e))?
+        .next()
+        .ok_or_else(|| format!("No address for {}", url.host))?;
+    let mut stream = TcpStream::connect_timeout(&address, timeout).map_err(|e| format!("Failed to connect to {}: {}", address, e))?;
+    stream.set_read_timeout(Some(timeout)).and_then(|_| stream.set_write_timeout(Some(timeout))).map_err(|e| e.to_string())?;
+
+    let mut request = format!(
+        "{} {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+        method, url.path, url.host_header(), USER_AGENT, body.len()
+    );
+    for (name, value) in headers {
+        request.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    request.push_str("\r\n");
+    stream.write_all(request.as_bytes()).and_then(|_| stream.write_all(body)).map_err(|e| format!("Failed to send to {}: {}", url.host, e))?;
+
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).map_err(|e| format!("Failed to read the response of {}: {}", url.host, e))?;
+    parse_response(&response)
+}
+
+/// Status and body of a raw HTTP/1.1 response, de-chunked
+#[cfg(not(windows))]
+fn parse_response(response: &[u8]) -> Result<HttpResponse, String> {
+    let header_end = response.windows(4).position(|window| window == b"\r\n\r\n").ok_or("truncated HTTP response")?;
+    let head = String::from_utf8_lossy(&response[..header_end]);
+    let mut lines = head.lines();
+    let status = lines
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|code| code.parse::<u16>().ok())
+        .ok_or("invalid HTTP status line")?;
+    let headers = header_lines(lines);
+    let chunked = headers.iter().any(|(name, value)| name.eq_ignore_ascii_case("transfer-encoding") && value.to_ascii_lowercase().contains("chunked"));
+
+    let mut body = &response[header_end + 4..];
+    if !chunked {
+        return Ok(HttpResponse { status, headers, body: body.to_vec() });
+    }
+    let mut decoded = Vec::new();
+    while let Some(line_end) = body.windows(2).position(|window| window == b"\r\n") {
+        let size_text = String::from_utf8_lossy(&body[..line_end]);
+        let size = usize::from_str_radix(size_text.split(';').next().unwrap_or("").trim(), 16).map_err(|_| "invalid chunk size")?;
+        let chunk = body.get(line_end + 2..line_end + 2 + size).ok_or("truncated chunk")?;
+        if size == 0 {
+            break;
+        }
+        decoded.extend_from_slice(chunk);
+        body = body.get(line_end + 4 + size..).unwrap_or_default();
+    }
+    Ok(HttpResponse { status, headers, body: decoded })
+}
+
+/// `Name: value` header lines after the status line
+fn header_lines<'a>(lines: impl Iterator<Item = &'a str>) -> Vec<(String, String)> {
+    lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .collect()
+}
+
+#[cfg(windows)]
+mod winhttp {
+    use super::{header_lines, HttpResponse, HttpUrl, USER_AGENT};
+    use std::ffi::c_void;
+    use std::time::Duration;
+    use windows::core::{HSTRING, PCWSTR};
+    use windows::Win32::Networking::WinHttp::{
+        WinHttpCloseHandle, WinHttpConnect, WinHttpOpen, WinHttpOpenRequest, WinHttpQueryDataAvailable, WinHttpQueryHeaders, WinHttpReadData,
+        WinHttpReceiveResponse, WinHttpSendRequest, WinHttpSetTimeouts, WINHTTP_ACCESS_TYPE_AUTOMATIC_PROXY, WINHTTP_FLAG_SECURE,
+        WINHTTP_OPEN_REQUEST_FLAGS, WINHTTP_QUERY_FLAG_NUMBER, WINHTTP_QUERY_RAW_HEADERS_CRLF, WINHTTP_QUERY_STATUS_CODE,
+    };
+
+    /// WinHTTP handle closed on drop
+    struct Handle(*mut c_void);
+
+    impl Handle {
+        fn new(raw: *mut c_void, operation: &str) -> Result<Self, String> {
+            if raw.is_null() {
+                Err(format!("{} failed: {}", operation, std::io::Error::last_os_error()))
+            } else {
+                Ok(Handle(raw))
+            }
+        }
+    }
+
+    impl Drop for Handle {
+        fn drop(&mut self) {
+            unsafe {
+                let _ = WinHttpCloseHandle(self.0);
+            }
+        }
+    }
+
+    pub fn send(method: &str, url: &HttpUrl, headers: &[(String, String)], body: &[u8], timeout: Duration) -> Result<HttpResponse, String> {
+        let timeout_ms = i32::try_from(timeout.as_millis()).unwrap_or(i32::MAX);
+        let header_text: Vec<u16> = headers.iter().map(|(name, value)| format!("{}: {}\r\n", name, value)).collect::<String>().encode_utf16().collect();
+        let flags = if url.secure { WINHTTP_FLAG_SECURE } else { WINHTTP_OPEN_REQUEST_FLAGS(0) };
+
+        unsafe {
+            // The automatic proxy setting follows the system and WPAD configuration (Windows 8.1 and later)
+            let session = Handle::new(
+                WinHttpOpen(&HSTRING::from(USER_AGENT), WINHTTP_ACCESS_TYPE_AUTOMATIC_PROXY, PCWSTR::null(), PCWSTR::null(), 0),
+                "WinHttpOpen",
+            )?;
+            WinHttpSetTimeouts(session.0, timeout_ms, timeout_ms, timeout_ms, timeout_ms).map_err(|e| format!("WinHttpSetTimeouts failed: {}", e))?;
+            let connection = Handle::new(WinHttpConnect(session.0, &HSTRING::from(url.host.as_str()), url.port, 0), "WinHttpConnect")?;
+            let request = Handle::new(
+                WinHttpOpenRequest(connection.0, &HSTRING::from(method), &HSTRING::from(url.path.as_str()), PCWSTR::null(), PCWSTR::null(), std::ptr::null(), flags),
+                "WinHttpOpenRequest",
+            )?;
+
+            let body_length = u32::try_from(body.len()).map_err(|_| "request body exceeds 4 GB".to_string())?;
+            WinHttpSendRequest(
+                request.0,
+                (!header_text.is_empty()).then_some(header_text.as_slice()),
+                Some(body.as_ptr() as *const c_void),
+                body_length,
+                body_length,
+                0,
+            )
+            .map_err(|e| format!("Request to {} failed: {}", url.host, e))?;
+            WinHttpReceiveResponse(request.0, std::ptr::null_mut()).map_err(|e| format!("No response from {}: {}", url.host, e))?;
+
+            let mut status = 0u32;
+            let mut status_size = std::mem::size_of::<u32>() as u32;
+            WinHttpQueryHeaders(
+                request.0,
+                WINHTTP_QUERY_STATUS_CODE | WINHTTP_QUERY_FLAG_NUMBER,
+                PCWSTR::null(),
+                Some(&mut status as *mut u32 as *mut c_void),
+                &mut status_size,
+                std::ptr::null_mut(),
+            )
+            .map_err(|e| format!("WinHttpQueryHeaders failed: {}", e))?;
+
+            // The first call only reports the size of the raw header block
+            let mut header_size = 0u32;
+            let _ = WinHttpQueryHeaders(request.0, WINHTTP_QUERY_RAW_HEADERS_CRLF, PCWSTR::null(), None, &mut header_size, std::ptr::null_mut());
+            let mut raw_headers = vec![0u16; header_size as usize / 2 + 1];
+            WinHttpQueryHeaders(
+                request.0,
+                WINHTTP_QUERY_RAW_HEADERS_CRLF,
+                PCWSTR::null(),
+                Some(raw_headers.as_mut_ptr() as *mut c_void),
+                &mut header_size,
+                std::ptr::null_mut(),
+            )
+            .map_err(|e| format!("WinHttpQueryHeaders failed: {}", e))?;
+            let raw_headers = String::from_utf16_lossy(&raw_headers[..(header_size as usize / 2).min(raw_headers.len())]);
+            let response_headers = header_lines(raw_headers.lines().skip(1));
+
+            let mut response_body = Vec::new();
+            loop {
+                let mut available = 0u32;
+                WinHttpQueryDataAvailable(request.0, &mut available).map_err(|e| format!("Failed to read the response of {}: {}", url.host, e))?;
+                if available == 0 {
+                    break;
+                }
+                let mut buffer = vec![0u8; available as usize];
+                let mut read = 0u32;
+                WinHttpReadData(request.0, buffer.as_mut_ptr() as *mut c_void, available, &mut read)
+                    .map_err(|e| format!("Failed to read the response of {}: {}", url.host, e))?;
+                buffer.truncate(read as usize);
+                response_body.extend_from_slice(&buffer);
+            }
+            Ok(HttpResponse { status: status as u16, headers: response_headers, body: response_body })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_url() {
+        let url = HttpUrl::parse("https://splunk.example.com:8088/services/collector/event?channel=1").unwrap();
+        assert_eq!(url, HttpUrl { secure: true, host: "splunk.example.com".to_string(), port: 8088, path: "/services/collector/event?channel=1".to_string() });
+
+        let url = HttpUrl::parse("http://10.0.0.5").unwrap();
+        assert_eq!((url.port, url.path.as_str()), (80, "/"));
+        assert!(HttpUrl::parse("ftp://host/").is_err());
+        assert!(HttpUrl::parse("http://host:99999/").is_err());
+    }
+
+    #[test]
+    fn test_send_over_loopback() {
+        use std::io::{Read, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buffer = [0u8; 1024];
+            while !String::from_utf8_lossy(&request).ends_with("{\"ok\":1}") {
+                let read = stream.read(&mut buffer).unwrap();
+                request.extend_from_slice(&buffer[..read]);
+            }
+            stream.write_all(b"HTTP/1.1 201 Created\r\nTransfer-Encoding: chunked\r\n\r\n4\r\ndone\r\n0\r\n\r\n").unwrap();
+            String::from_utf8(request).unwrap()
+        });
+
+        let headers = vec![("Authorization".to_string(), "Splunk token".to_string())];
+        let response = send("POST", &format!("http://127.0.0.1:{}/hook", port), &headers, b"{\"ok\":1}", Duration::from_secs(5)).unwrap();
+        assert!(response.is_success());
+        assert_eq!((response.status, response.body.as_slice()), (201, b"done".as_slice()));
+
+        let request = server.join().unwrap();
+        assert!(request.starts_with("POST /hook HTTP/1.1\r\n"));
+        assert!(request.contains("Authorization: Splunk token\r\n"));
+    }
+}
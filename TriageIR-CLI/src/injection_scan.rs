@@ -0,0 +1,349 @@
+//! Injected thread and shellcode memory detection (--deep-process-scan)
+//! Walks the address space of every process with VirtualQueryEx and resolves
+//! the start address of each of its threads. Code injected with
+//! VirtualAllocEx/WriteProcessMemory/CreateRemoteThread lives in private or
+//! pagefile-backed memory, so a thread starting outside every mapped image and
+//! read-write-execute pages not backed by a file are the usual traces of it.
+//! JIT compilers (browsers, .NET) also allocate unbacked executable memory, so
+//! the findings are indicators for the analyst, not verdicts.
+
+// Region analysis is only reachable through the Windows process API
+#![cfg_attr(not(windows), allow(dead_code))]
+
+use crate::types::{LogEntry, MemoryRegionSummary, Process, SuspiciousMemoryRegion};
+
+#[cfg(windows)]
+use std::collections::HashMap;
+#[cfg(windows)]
+use windows::{
+    Wdk::System::Threading::{NtQueryInformationThread, ThreadQuerySetWin32StartAddress},
+    Win32::Foundation::{CloseHandle, HANDLE},
+    Win32::System::Diagnostics::ToolHelp::{CreateToolhelp32Snapshot, Thread32First, Thread32Next, TH32CS_SNAPTHREAD, THREADENTRY32},
+    Win32::System::Memory::{VirtualQueryEx, MEMORY_BASIC_INFORMATION},
+    Win32::System::ProcessStatus::GetMappedFileNameW,
+    Win32::System::Threading::{OpenProcess, OpenThread, PROCESS_QUERY_INFORMATION, PROCESS_VM_READ, THREAD_QUERY_INFORMATION},
+};
+
+const MEM_COMMIT: u32 = 0x1000;
+const MEM_PRIVATE: u32 = 0x20000;
+const MEM_MAPPED: u32 = 0x40000;
+const MEM_IMAGE: u32 = 0x100_0000;
+
+const PAGE_EXECUTE: u32 = 0x10;
+const PAGE_EXECUTE_READ: u32 = 0x20;
+const PAGE_EXECUTE_READWRITE: u32 = 0x40;
+const PAGE_EXECUTE_WRITECOPY: u32 = 0x80;
+
+/// Suspicious regions kept per process; the counters cover all of them
+const MAX_REPORTED_REGIONS: usize = 32;
+
+/// Address spaces with more regions than this are treated as corrupt
+const MAX_REGIONS: usize = 1 << 20;
+
+/// A committed region of a process address space (MEMORY_BASIC_INFORMATION)
+#[derive(Debug, Clone, PartialEq)]
+struct MemoryRegion {
+    base: u64,
+    size: u64,
+    protect: u32,
+    kind: u32,
+    mapped_file: Option<String>,
+}
+
+impl MemoryRegion {
+    fn contains(&self, address: u64) -> bool {
+        address >= self.base && address - self.base < self.size
+    }
+
+    fn executable(&self) -> bool {
+        matches!(self.protect & 0xFF, PAGE_EXECUTE | PAGE_EXECUTE_READ | PAGE_EXECUTE_READWRITE | PAGE_EXECUTE_WRITECOPY)
+    }
+
+    fn rwx(&self) -> bool {
+        matches!(self.protect & 0xFF, PAGE_EXECUTE_READWRITE | PAGE_EXECUTE_WRITECOPY)
+    }
+
+    /// Private memory, or a mapped section without a file behind it
+    fn unbacked(&self) -> bool {
+        match self.kind {
+            MEM_IMAGE => false,
+            MEM_MAPPED => self.mapped_file.is_none(),
+            _ => true,
+        }
+    }
+}
+
+/// Scan the address space and threads of every process, attaching a
+/// `memory_regions` summary and `unbacked_thread`/`rwx_memory` indicators
+#[cfg(windows)]
+pub fn scan_process_memory(processes: &mut [Process]) -> Vec<LogEntry> {
+    let mut logs = Vec::new();
+    let threads = match enumerate_threads() {
+        Ok(threads) => threads,
+        Err(e) => {
+            logs.push(LogEntry::warn(&format!("Thread enumeration failed, only memory protections are checked: {}", e)));
+            HashMap::new()
+        }
+    };
+
+    let mut scanned = 0;
+    let mut failed = 0;
+    let mut flagged = 0;
+    // PID 0 (Idle) and 4 (System) have no user address space
+    for process in processes.iter_mut().filter(|p| p.pid > 4) {
+        let thread_ids = threads.get(&process.pid).map(Vec::as_slice).unwrap_or_default();
+        match scan_process(process.pid, thread_ids) {
+            Ok((regions, thread_starts)) => {
+                let (summary, indicators) = analyze(&regions, &thread_starts);
+                if !indicators.is_empty() {
+                    flagged += 1;
+                    logs.push(LogEntry::warn(&format!("Process {} (PID {}): {}", process.name, process.pid, indicators.join("; "))));
+                }
+                process.suspicious_indicators.extend(indicators);
+                process.memory_regions = Some(summary);
+                scanned += 1;
+            }
+            // Protected and exited processes; too many to log one by one
+            Err(_) => failed += 1,
+        }
+    }
+
+    logs.push(LogEntry::info(&format!(
+        "Deep process scan completed: {} processes scanned, {} with injection indicators ({} inaccessible)",
+        scanned, flagged, failed
+    )));
+    logs
+}
+
+#[cfg(not(windows))]
+pub fn scan_process_memory(_processes: &mut [Process]) -> Vec<LogEntry> {
+    vec![LogEntry::warn("Deep process scan skipped: requires the Windows process API")]
+}
+
+/// Thread IDs of every process, from a system-wide Toolhelp snapshot
+#[cfg(windows)]
+pub(crate) fn enumerate_threads() -> Result<HashMap<u32, Vec<u32>>, String> {
+    unsafe {
+        let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPTHREAD, 0)
+            .map_err(|e| format!("CreateToolhelp32Snapshot failed: {}", e))?;
+        let mut threads: HashMap<u32, Vec<u32>> = HashMap::new();
+        let mut entry = THREADENTRY32 { dwSize: std::mem::size_of::<THREADENTRY32>() as u32, ..Default::default() };
+        let mut next = Thread32First(snapshot, &mut entry);
+        while next.is_ok() {
+            threads.entry(entry.th32OwnerProcessID).or_default().push(entry.th32ThreadID);
+            next = Thread32Next(snapshot, &mut entry);
+        }
+        let _ = CloseHandle(snapshot);
+        Ok(threads)
+    }
+}
+
+#[cfg(windows)]
+fn scan_process(pid: u32, thread_ids: &[u32]) -> Result<(Vec<MemoryRegion>, Vec<(u32, u64)>), String> {
+    unsafe {
+        let process = OpenProcess(PROCESS_QUERY_INFORMATION | PROCESS_VM_READ, false, pid)
+            .map_err(|e| format!("OpenProcess failed: {}", e))?;
+        let regions = walk_regions(process);
+        let _ = CloseHandle(process);
+        let thread_starts = thread_ids.iter().filter_map(|&tid| Some((tid, thread_start_address(tid)?))).collect();
+        Ok((regions?, thread_starts))
+    }
+}
+
+/// Committed regions of the address space, with the backing file of executable mapped ones
+#[cfg(windows)]
+unsafe fn walk_regions(process: HANDLE) -> Result<Vec<MemoryRegion>, String> {
+    let mut regions = Vec::new();
+    let mut address = 0u64;
+    let mut walked = 0;
+    loop {
+        let mut info = MEMORY_BASIC_INFORMATION::default();
+        let written = VirtualQueryEx(process, Some(address as *const _), &mut info, std::mem::size_of::<MEMORY_BASIC_INFORMATION>());
+        if written == 0 {
+            break;
+        }
+        walked += 1;
+        if walked > MAX_REGIONS {
+            return Err(format!("more than {} regions", MAX_REGIONS));
+        }
+
+        let base = info.BaseAddress as u64;
+        let size = info.RegionSize as u64;
+        if info.State.0 == MEM_COMMIT {
+            let mut region = MemoryRegion { base, size, protect: info.Protect.0, kind: info.Type.0, mapped_file: None };
+            if region.kind == MEM_MAPPED && region.executable() {
+                region.mapped_file = mapped_file_name(process, base);
+            }
+            regions.push(region);
+        }
+        match base.checked_add(size) {
+            Some(next) if size > 0 && next > address => address = next,
+            _ => break,
+        }
+    }
+    if regions.is_empty() {
+        return Err("VirtualQueryEx returned no committed regions".to_string());
+    }
+    Ok(regions)
+}
+
+#[cfg(windows)]
+unsafe fn mapped_file_name(process: HANDLE, address: u64) -> Option<String> {
+    let mut buffer = [0u16; 1024];
+    let length = GetMappedFileNameW(process, address as *const _, &mut buffer) as usize;
+    (length > 0).then(|| String::from_utf16_lossy(&buffer[..length.min(buffer.len())]))
+}
+
+/// Win32 start address of a thread (the routine passed to CreateThread)
+#[cfg(windows)]
+fn thread_start_address(thread_id: u32) -> Option<u64> {
+    unsafe {
+        let thread = OpenThread(THREAD_QUERY_INFORMATION, false, thread_id).ok()?;
+        let mut start = 0u64;
+        let status = NtQueryInformationThread(
+            thread,
+            ThreadQuerySetWin32StartAddress,
+            &mut start as *mut _ as *mut _,
+            std::mem::size_of::<u64>() as u32,
+            std::ptr::null_mut(),
+        );
+        let _ = CloseHandle(thread);
+        (status.is_ok() && start != 0).then_some(start)
+    }
+}
+
+/// Summarize the committed regions and flag threads starting outside every
+/// image as well as unbacked RWX memory
+fn analyze(regions: &[MemoryRegion], thread_starts: &[(u32, u64)]) -> (MemoryRegionSummary, Vec<String>) {
+    let mut summary = MemoryRegionSummary {
+        committed_regions: regions.len(),
+        image_regions: regions.iter().filter(|r| r.kind == MEM_IMAGE).count(),
+        unbacked_executable_regions: regions.iter().filter(|r| r.executable() && r.unbacked()).count(),
+        threads_scanned: thread_starts.len(),
+        ..Default::default()
+    };
+    let mut indicators = Vec::new();
+
+    for &(thread_id, start) in thread_starts {
+        let region = regions.iter().find(|r| r.contains(start));
+        if region.is_some_and(|r| r.kind == MEM_IMAGE) {
+            continue;
+        }
+        summary.unbacked_threads += 1;
+        match region {
+            Some(region) => {
+                indicators.push(format!(
+                    "unbacked_thread: thread {} starts at 0x{:X} in {} {} memory",
+                    thread_id, start, region_type_name(region.kind), protection_name(region.protect)
+                ));
+                match summary.suspicious_regions.iter_mut().find(|r| r.base_address == format!("0x{:X}", region.base)) {
+                    Some(reported) => reported.thread_ids.push(thread_id),
+                    None => report_region(&mut summary, region, vec![thread_id]),
+                }
+            }
+            None => indicators.push(format!("unbacked_thread: thread {} starts at 0x{:X} outside any committed region", thread_id, start)),
+        }
+    }
+
+    let rwx: Vec<&MemoryRegion> = regions.iter().filter(|r| r.rwx() && r.unbacked()).collect();
+    summary.rwx_unbacked_bytes = rwx.iter().map(|r| r.size).sum();
+    if !rwx.is_empty() {
+        indicators.push(format!(
+            "rwx_memory: {} bytes of read-write-execute memory not backed by a file in {} regions",
+            summary.rwx_unbacked_bytes, rwx.len()
+        ));
+    }
+    for region in rwx {
+        let base_address = format!("0x{:X}", region.base);
+        if !summary.suspicious_regions.iter().any(|r| r.base_address == base_address) {
+            report_region(&mut summary, region, Vec::new());
+        }
+    }
+
+    (summary, indicators)
+}
+
+fn report_region(summary: &mut MemoryRegionSummary, region: &MemoryRegion, thread_ids: Vec<u32>) {
+    if summary.suspicious_regions.len() < MAX_REPORTED_REGIONS {
+        summary.suspicious_regions.push(SuspiciousMemoryRegion {
+            base_address: format!("0x{:X}", region.base),
+            size: region.size,
+            protection: protection_name(region.protect).to_string(),
+            region_type: region_type_name(region.kind).to_string(),
+            mapped_file: region.mapped_file.clone(),
+            thread_ids,
+        });
+    }
+}
+
+fn region_type_name(kind: u32) -> &'static str {
+    match kind {
+        MEM_IMAGE => "MEM_IMAGE",
+        MEM_MAPPED => "MEM_MAPPED",
+        MEM_PRIVATE => "MEM_PRIVATE",
+        _ => "unknown",
+    }
+}
+
+fn protection_name(protect: u32) -> &'static str {
+    match protect & 0xFF {
+        0x01 => "PAGE_NOACCESS",
+        0x02 => "PAGE_READONLY",
+        0x04 => "PAGE_READWRITE",
+        0x08 => "PAGE_WRITECOPY",
+        PAGE_EXECUTE => "PAGE_EXECUTE",
+        PAGE_EXECUTE_READ => "PAGE_EXECUTE_READ",
+        PAGE_EXECUTE_READWRITE => "PAGE_EXECUTE_READWRITE",
+        PAGE_EXECUTE_WRITECOPY => "PAGE_EXECUTE_WRITECOPY",
+        _ => "unknown",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn region(base: u64, size: u64, protect: u32, kind: u32, mapped_file: Option<&str>) -> MemoryRegion {
+        MemoryRegion { base, size, protect, kind, mapped_file: mapped_file.map(str::to_string) }
+    }
+
+    #[test]
+    fn test_flags_threads_outside_images_and_unbacked_rwx() {
+        let regions = vec![
+            region(0x7FF6_0000_0000, 0x10000, PAGE_EXECUTE_READ, MEM_IMAGE, None),
+            region(0x1F0_0000, 0x2000, PAGE_EXECUTE_READWRITE, MEM_PRIVATE, None),
+            region(0x2A0_0000, 0x1000, PAGE_EXECUTE_READ, MEM_MAPPED, Some("\\Device\\HarddiskVolume3\\Windows\\Fonts\\a.fon")),
+            region(0x300_0000, 0x4000, 0x04, MEM_PRIVATE, None),
+        ];
+        let threads = [(100, 0x7FF6_0000_1000), (200, 0x1F0_0010), (300, 0x5000_0000)];
+
+        let (summary, indicators) = analyze(&regions, &threads);
+        assert_eq!(summary.committed_regions, 4);
+        assert_eq!(summary.image_regions, 1);
+        assert_eq!(summary.unbacked_executable_regions, 1);
+        assert_eq!(summary.threads_scanned, 3);
+        assert_eq!(summary.unbacked_threads, 2);
+        assert_eq!(summary.rwx_unbacked_bytes, 0x2000);
+        assert_eq!(summary.suspicious_regions.len(), 1);
+        assert_eq!(summary.suspicious_regions[0].base_address, "0x1F00000");
+        assert_eq!(summary.suspicious_regions[0].protection, "PAGE_EXECUTE_READWRITE");
+        assert_eq!(summary.suspicious_regions[0].thread_ids, vec![200]);
+
+        assert_eq!(indicators.len(), 3);
+        assert!(indicators[0].starts_with("unbacked_thread: thread 200 starts at 0x1F00010 in MEM_PRIVATE"));
+        assert!(indicators[1].contains("outside any committed region"));
+        assert!(indicators[2].starts_with("rwx_memory: 8192 bytes"));
+    }
+
+    #[test]
+    fn test_clean_process_has_no_indicators() {
+        let regions = vec![
+            region(0x7FF6_0000_0000, 0x10000, PAGE_EXECUTE_READ, MEM_IMAGE, None),
+            region(0x7FFE_0000_0000, 0x8000, PAGE_EXECUTE_WRITECOPY, MEM_IMAGE, None),
+        ];
+        let (summary, indicators) = analyze(&regions, &[(1, 0x7FFE_0000_0100)]);
+        assert!(indicators.is_empty());
+        assert_eq!(summary.unbacked_threads, 0);
+        assert!(summary.suspicious_regions.is_empty());
+    }
+}
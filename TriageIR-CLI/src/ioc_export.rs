@@ -0,0 +1,494 @@
+//! Host-based indicator export (`export-iocs`)
+//! Extracts hashes, file paths, registry keys, IPs, domains, URLs and
+//! service/task names from the flagged findings of a saved scan and writes them
+//! as CSV and as a STIX 2.1 bundle for EDR blocklists. Only flagged findings
+//! contribute; loopback, private and link-local addresses are never exported.
+
+use crate::forensic_types::IocIndicator;
+use chrono::SecondsFormat;
+use regex::Regex;
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::fs;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Read a scan result written with `--output`
+pub fn load_scan_results(path: &Path) -> Result<Value, String> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read scan results {}: {}", path.display(), e))?;
+    let scan: Value = serde_json::from_str(&text)
+        .map_err(|e| format!("Invalid scan results {}: {}", path.display(), e))?;
+
+    if scan.get("artifacts").is_none() {
+        return Err(format!("{} is not a TriageIR scan result (no artifacts section)", path.display()));
+    }
+    Ok(scan)
+}
+
+/// Extract de-duplicated indicators from the flagged findings of a scan
+pub fn extract_indicators(scan: &Value) -> Vec<IocIndicator> {
+    let mut indicators = IndicatorSet::default();
+    let artifacts = &scan["artifacts"];
+    let mut flagged_paths = HashSet::new();
+
+    for mechanism in array(&artifacts["persistence_mechanisms"]) {
+        if !mechanism["is_suspicious"].as_bool().unwrap_or(false) {
+            continue;
+        }
+        let source = "persistence_mechanisms";
+        let mechanism_type = text(&mechanism["type"]);
+        let name = text(&mechanism["name"]);
+        let context = format!("Suspicious {}: {}", mechanism_type, name);
+
+        match mechanism_type {
+            "Scheduled Task" => indicators.add("task_name", name, source, &context),
+            "Windows Service" => {
+                indicators.add("service_name", name, source, &context);
+                indicators.add("registry_key", text(&mechanism["source"]), source, &context);
+            }
+            "Registry Run Key" => indicators.add("registry_key", text(&mechanism["source"]), source, &context),
+            _ => {}
+        }
+
+        // Built-in binaries (powershell.exe, rundll32.exe, ...) launched by the entry are not blocklist material
+        let command = text(&mechanism["command"]);
+        if let Some(executable) = command_executable(command).filter(|path| !is_windows_binary(path)) {
+            flagged_paths.insert(executable.to_lowercase());
+            indicators.add("file_path", &executable, source, &context);
+        }
+        indicators.add_network_indicators(command, source, &context);
+    }
+
+    for entry in array(&artifacts["execution_evidence"]["defender_log_entries"]) {
+        if text(&entry["category"]) != "detection" {
+            continue;
+        }
+        let path = text(&entry["path"]);
+        let context = format!("Defender detection {}", text(&entry["threat_name"]));
+        flagged_paths.insert(path.to_lowercase());
+        indicators.add("file_path", path, "defender_log_entries", &context);
+    }
+
+    for installation in array(&artifacts["device_installations"]) {
+        let flags: Vec<&str> = array(&installation["flags"]).iter().map(text).collect();
+        if flags.is_empty() {
+            continue;
+        }
+        let context = format!("Device installation {} flagged: {}", text(&installation["target"]), flags.join(", "));
+        for driver_file in array(&installation["driver_files"]) {
+            flagged_paths.insert(text(driver_file).to_lowercase());
+            indicators.add("file_path", text(driver_file), "device_installations", &context);
+        }
+        for service in array(&installation["services"]) {
+            indicators.add("service_name", text(service), "device_installations", &context);
+        }
+    }
+
+    // Hashes and remote endpoints of running processes launched from a flagged path
+    // or flagged by the process tree analysis
+    let mut flagged_pids = HashSet::new();
+    for process in array(&artifacts["running_processes"]) {
+        let path = text(&process["executable_path"]);
+        let tree_indicators: Vec<&str> = array(&process["suspicious_indicators"]).iter().map(text).collect();
+        let context = if flagged_paths.contains(&path.to_lowercase()) {
+            format!("Process {} (PID {}) runs a flagged executable", text(&process["name"]), process["pid"])
+        } else if !tree_indicators.is_empty() && !is_windows_binary(path) {
+            indicators.add("file_path", path, "running_processes", &tree_indicators.join("; "));
+            format!("Process {} (PID {}): {}", text(&process["name"]), process["pid"], tree_indicators.join("; "))
+        } else {
+            continue;
+        };
+        let hash = text(&process["sha256_hash"]);
+        if hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit()) {
+            indicators.add("sha256", &hash.to_lowercase(), "running_processes", &context);
+        }
+        if let Some(pid) = process["pid"].as_u64() {
+            flagged_pids.insert(pid);
+        }
+    }
+
+    for connection in array(&artifacts["network_connections"]) {
+        let owned_by_flagged = connection["owning_pid"].as_u64().is_some_and(|pid| flagged_pids.contains(&pid));
+        if owned_by_flagged {
+            let context = format!("Connection of flagged process {}", text(&connection["process_name"]));
+            indicators.add_ip(text(&connection["remote_address"]), "network_connections", &context);
+        }
+    }
+
+    indicators.items
+}
+
+/// Write `iocs-<scan_id>.csv` and `iocs-<scan_id>.stix.json` into the output directory
+pub fn write_ioc_package(indicators: &[IocIndicator], scan: &Value, output_dir: &Path) -> Result<(PathBuf, PathBuf), String> {
+    fs::create_dir_all(output_dir)
+        .map_err(|e| format!("Failed to create output directory {}: {}", output_dir.display(), e))?;
+
+    let scan_id = scan["scan_metadata"]["scan_id"].as_str().unwrap_or("scan");
+    let csv_path = output_dir.join(format!("iocs-{}.csv", scan_id));
+    let stix_path = output_dir.join(format!("iocs-{}.stix.json", scan_id));
+
+    fs::write(&csv_path, to_csv(indicators))
+        .map_err(|e| format!("Failed to write {}: {}", csv_path.display(), e))?;
+
+    let bundle = to_stix_bundle(indicators, scan);
+    let bundle_text = serde_json::to_string_pretty(&bundle).map_err(|e| e.to_string())?;
+    fs::write(&stix_path, bundle_text)
+        .map_err(|e| format!("Failed to write {}: {}", stix_path.display(), e))?;
+
+    Ok((csv_path, stix_path))
+}
+
+pub fn to_csv(indicators: &[IocIndicator]) -> String {
+    let mut csv = String::from("indicator_type,value,source_artifact,context\n");
+    for indicator in indicators {
+        let fields = [&indicator.indicator_type, &indicator.value, &indicator.source_artifact, &indicator.context];
+        let escaped: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+        csv.push_str(&escaped.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+/// STIX 2.1 bundle with one indicator object per IOC
+pub fn to_stix_bundle(indicators: &[IocIndicator], scan: &Value) -> Value {
+    let now = chrono::Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true);
+    let identity_id = format!("identity--{}", uuid::Uuid::new_v4());
+    let hostname = scan["scan_metadata"]["hostname"].as_str().unwrap_or("Unknown");
+    let scan_id = scan["scan_metadata"]["scan_id"].as_str().unwrap_or("");
+
+    let mut objects = vec![json!({
+        "type": "identity",
+        "spec_version": "2.1",
+        "id": identity_id,
+        "created": now,
+        "modified": now,
+        "name": "TriageIR-CLI",
+        "identity_class": "system"
+    })];
+
+    objects.extend(indicators.iter().map(|indicator| {
+        json!({
+            "type": "indicator",
+            "spec_version": "2.1",
+            "id": format!("indicator--{}", uuid::Uuid::new_v4()),
+            "created_by_ref": identity_id,
+            "created": now,
+            "modified": now,
+            "name": format!("{}: {}", indicator.indicator_type, indicator.value),
+            "description": indicator.context,
+            "indicator_types": ["malicious-activity"],
+            "pattern": stix_pattern(indicator),
+            "pattern_type": "stix",
+            "valid_from": now,
+            "labels": [indicator.source_artifact],
+            "x_triageir_hostname": hostname,
+            "x_triageir_scan_id": scan_id
+        })
+    }));
+
+    json!({
+        "type": "bundle",
+        "id": format!("bundle--{}", uuid::Uuid::new_v4()),
+        "objects": objects
+    })
+}
+
+fn stix_pattern(indicator: &IocIndicator) -> String {
+    let value = stix_escape(&indicator.value);
+    match indicator.indicator_type.as_str() {
+        "sha256" => format!("[file:hashes.'SHA-256' = '{}']", value),
+        "file_path" => match indicator.value.rfind(['\\', '/']) {
+            Some(split) => format!(
+                "[file:name = '{}' AND file:parent_directory_ref.path = '{}']",
+                stix_escape(&indicator.value[split + 1..]),
+                stix_escape(&indicator.value[..split])
+            ),
+            None => format!("[file:name = '{}']", value),
+        },
+        "registry_key" => format!("[windows-registry-key:key = '{}']", stix_escape(&expand_hive(&indicator.value))),
+        "ipv4" => format!("[ipv4-addr:value = '{}']", value),
+        "ipv6" => format!("[ipv6-addr:value = '{}']", value),
+        "domain" => format!("[domain-name:value = '{}']", value),
+        "url" => format!("[url:value = '{}']", value),
+        "service_name" => format!("[process:extensions.'windows-service-ext'.service_name = '{}']", value),
+        _ => format!("[x-windows-scheduled-task:name = '{}']", value),
+    }
+}
+
+fn stix_escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('\'', "\\'")
+}
+
+/// STIX registry keys use the full hive name
+pub(crate) fn expand_hive(key: &str) -> String {
+    let (hive, rest) = key.split_once('\\').unwrap_or((key, ""));
+    let full_hive = match hive.to_uppercase().as_str() {
+        "HKLM" => "HKEY_LOCAL_MACHINE",
+        "HKCU" => "HKEY_CURRENT_USER",
+        "HKU" => "HKEY_USERS",
+        "HKCR" => "HKEY_CLASSES_ROOT",
+        _ => hive,
+    };
+    if rest.is_empty() {
+        full_hive.to_string()
+    } else {
+        format!("{}\\{}", full_hive, rest)
+    }
+}
+
+pub(crate) fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Executable launched by a command line, e.g. `"C:\Tools\a b.exe" -x` -> `C:\Tools\a b.exe`
+pub(crate) fn command_executable(command: &str) -> Option<String> {
+    let command = command.trim();
+    let executable = if let Some(quoted) = command.strip_prefix('"') {
+        quoted.split('"').next().unwrap_or_default().to_string()
+    } else {
+        static EXECUTABLE: OnceLock<Regex> = OnceLock::new();
+        let pattern = EXECUTABLE.get_or_init(|| {
+            Regex::new(r"(?i)^(.+?\.(?:exe|dll|sys|bat|cmd|ps1|vbs|js|scr|com|hta))(?:\s|,|$)").unwrap()
+        });
+        match pattern.captures(command) {
+            Some(capture) => capture[1].to_string(),
+            None => command.split_whitespace().next().unwrap_or_default().to_string(),
+        }
+    };
+
+    executable.contains(['\\', '/']).then_some(executable)
+}
+
+pub(crate) fn is_windows_binary(path: &str) -> bool {
+    let path = path.to_lowercase().replace('/', "\\");
+    ["\\windows\\system32\\", "\\windows\\syswow64\\", "%systemroot%\\", "%windir%\\"]
+        .iter()
+        .any(|prefix| path.contains(prefix))
+}
+
+#[derive(Default)]
+struct IndicatorSet {
+    items: Vec<IocIndicator>,
+    seen: HashSet<(String, String)>,
+}
+
+impl IndicatorSet {
+    fn add(&mut self, indicator_type: &str, value: &str, source: &str, context: &str) {
+        let value = value.trim();
+        if value.is_empty() || value == "N/A" || value == "Unknown" {
+            return;
+        }
+        if self.seen.insert((indicator_type.to_string(), value.to_lowercase())) {
+            self.items.push(IocIndicator {
+                indicator_type: indicator_type.to_string(),
+                value: value.to_string(),
+                source_artifact: source.to_string(),
+                context: context.to_string(),
+            });
+        }
+    }
+
+    /// Add a routable IP address; loopback, private and link-local addresses are skipped
+    fn add_ip(&mut self, address: &str, source: &str, context: &str) {
+        match address.trim_matches(['[', ']']).parse::<IpAddr>() {
+            Ok(IpAddr::V4(ip)) => {
+                if !(ip.is_loopback() || ip.is_private() || ip.is_link_local() || ip.is_unspecified() || ip.is_broadcast()) {
+                    self.add("ipv4", &ip.to_string(), source, context);
+                }
+            }
+            Ok(IpAddr::V6(ip)) => {
+                let local = ip.is_loopback() || ip.is_unspecified() || (ip.segments()[0] & 0xfe00) == 0xfc00 || (ip.segments()[0] & 0xffc0) == 0xfe80;
+                if !local {
+                    self.add("ipv6", &ip.to_string(), source, context);
+                }
+            }
+            Err(_) => {}
+        }
+    }
+
+    /// URLs, UNC hosts and IP addresses embedded in a command line
+    fn add_network_indicators(&mut self, text: &str, source: &str, context: &str) {
+        static PATTERNS: OnceLock<(Regex, Regex, Regex)> = OnceLock::new();
+        let (url, unc, ipv4) = PATTERNS.get_or_init(|| {
+            (
+                Regex::new(r#"(?i)\b(?:https?|ftp)://([^\s/"'<>:]+)[^\s"'<>]*"#).unwrap(),
+                Regex::new(r"\\\\([A-Za-z0-9.\-]+)\\").unwrap(),
+                Regex::new(r"\b(?:\d{1,3}\.){3}\d{1,3}\b").unwrap(),
+            )
+        });
+
+        for capture in url.captures_iter(text) {
+            self.add("url", &capture[0], source, context);
+            self.add_host(&capture[1], source, context);
+        }
+        for capture in unc.captures_iter(text) {
+            self.add_host(&capture[1], source, context);
+        }
+        for address in ipv4.find_iter(text) {
+            self.add_ip(address.as_str(), source, context);
+        }
+    }
+
+    fn add_host(&mut self, host: &str, source: &str, context: &str) {
+        if host.parse::<IpAddr>().is_ok() {
+            self.add_ip(host, source, context);
+        } else if host.contains('.') {
+            self.add("domain", &host.to_lowercase(), source, context);
+        }
+    }
+}
+
+fn array(value: &Value) -> &[Value] {
+    value.as_array().map(Vec::as_slice).unwrap_or_default()
+}
+
+fn text(value: &Value) -> &str {
+    value.as_str().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn sample_scan() -> Value {
+        json!({
+            "scan_metadata": { "scan_id": "1234", "hostname": "WS01" },
+            "artifacts": {
+                "running_processes": [
+                    { "pid": 4242, "name": "updater.exe", "executable_path": "C:\\Users\\Public\\updater.exe",
+                      "sha256_hash": "AABBCCDDEEFF00112233445566778899AABBCCDDEEFF00112233445566778899" },
+                    { "pid": 100, "name": "explorer.exe", "executable_path": "C:\\Windows\\explorer.exe",
+                      "sha256_hash": "0000000000000000000000000000000000000000000000000000000000000000" },
+                    { "pid": 5000, "name": "stage2.exe", "executable_path": "C:\\ProgramData\\stage2.exe",
+                      "sha256_hash": "1111111111111111111111111111111111111111111111111111111111111111",
+                      "suspicious_indicators": ["suspicious_parent: WINWORD.EXE -> stage2.exe"] },
+                    { "pid": 5001, "name": "cmd.exe", "executable_path": "C:\\Windows\\System32\\cmd.exe",
+                      "sha256_hash": "2222222222222222222222222222222222222222222222222222222222222222",
+                      "suspicious_indicators": ["suspicious_parent: WINWORD.EXE -> cmd.exe"] }
+                ],
+                "network_connections": [
+                    { "owning_pid": 4242, "process_name": "updater.exe", "remote_address": "203.0.113.7" },
+                    { "owning_pid": 4242, "process_name": "updater.exe", "remote_address": "10.0.0.5" },
+                    { "owning_pid": 100, "process_name": "explorer.exe", "remote_address": "198.51.100.1" }
+                ],
+                "persistence_mechanisms": [
+                    { "type": "Registry Run Key", "name": "Updater", "command": "C:\\Users\\Public\\updater.exe -c http://evil.example.com/beacon",
+                      "source": "HKCU\\Software\\Microsoft\\Windows\\CurrentVersion\\Run", "is_suspicious": true },
+                    { "type": "Scheduled Task", "name": "SyncTask", "command": "powershell.exe -enc AAAA", "source": "Task Scheduler: \\SyncTask",
+                      "is_suspicious": true },
+                    { "type": "Windows Service", "name": "Spooler", "command": "C:\\Windows\\System32\\spoolsv.exe",
+                      "source": "HKLM\\SYSTEM\\CurrentControlSet\\Services\\Spooler", "is_suspicious": false }
+                ],
+                "execution_evidence": {
+                    "defender_log_entries": [
+                        { "category": "detection", "threat_name": "HackTool:Win32/Mimikatz.D", "path": "C:\\Temp\\m.exe" },
+                        { "category": "sdn_query", "path": "C:\\Temp\\other.exe" }
+                    ]
+                },
+                "device_installations": [
+                    { "target": "oem42.inf", "flags": ["unsigned", "kernel_driver"], "driver_files": ["C:\\Windows\\System32\\drivers\\rk.sys"], "services": ["rk"] },
+                    { "target": "usb", "flags": [], "driver_files": ["C:\\Windows\\System32\\drivers\\usb.sys"], "services": [] }
+                ]
+            }
+        })
+    }
+
+    fn values(indicators: &[IocIndicator], indicator_type: &str) -> Vec<String> {
+        indicators.iter().filter(|i| i.indicator_type == indicator_type).map(|i| i.value.clone()).collect()
+    }
+
+    #[test]
+    fn test_extract_indicators() {
+        let indicators = extract_indicators(&sample_scan());
+
+        assert_eq!(values(&indicators, "registry_key"), vec!["HKCU\\Software\\Microsoft\\Windows\\CurrentVersion\\Run"]);
+        assert_eq!(values(&indicators, "task_name"), vec!["SyncTask"]);
+        assert_eq!(values(&indicators, "service_name"), vec!["rk"]);
+        assert_eq!(values(&indicators, "url"), vec!["http://evil.example.com/beacon"]);
+        assert_eq!(values(&indicators, "domain"), vec!["evil.example.com"]);
+        assert_eq!(values(&indicators, "ipv4"), vec!["203.0.113.7"]); // Private and unflagged addresses skipped
+        assert_eq!(
+            values(&indicators, "sha256"),
+            vec![
+                "aabbccddeeff00112233445566778899aabbccddeeff00112233445566778899",
+                "1111111111111111111111111111111111111111111111111111111111111111"
+            ]
+        ); // Built-in cmd.exe is not exported
+
+        let paths = values(&indicators, "file_path");
+        assert!(paths.contains(&"C:\\Users\\Public\\updater.exe".to_string()));
+        assert!(paths.contains(&"C:\\Temp\\m.exe".to_string()));
+        assert!(paths.contains(&"C:\\Windows\\System32\\drivers\\rk.sys".to_string()));
+        assert!(paths.contains(&"C:\\ProgramData\\stage2.exe".to_string()));
+        assert!(!paths.iter().any(|p| p.contains("powershell") || p.contains("spoolsv") || p.contains("usb.sys") || p.contains("other.exe")));
+    }
+
+    #[test]
+    fn test_command_executable() {
+        assert_eq!(command_executable("\"C:\\Program Files\\A B\\a.exe\" -x").unwrap(), "C:\\Program Files\\A B\\a.exe");
+        assert_eq!(command_executable("C:\\Program Files\\A B\\a.exe -x").unwrap(), "C:\\Program Files\\A B\\a.exe");
+        assert!(command_executable("rundll32.exe C:\\x.dll,Start").is_none()); // Bare names are not paths
+        assert!(command_executable("").is_none());
+        assert!(is_windows_binary("C:\\Windows\\System32\\rundll32.exe"));
+        assert!(!is_windows_binary("C:\\Users\\Public\\updater.exe"));
+    }
+
+    #[test]
+    fn test_stix_patterns() {
+        let indicator = |indicator_type: &str, value: &str| IocIndicator {
+            indicator_type: indicator_type.to_string(),
+            value: value.to_string(),
+            source_artifact: String::new(),
+            context: String::new(),
+        };
+
+        assert_eq!(
+            stix_pattern(&indicator("file_path", "C:\\Temp\\it's.exe")),
+            "[file:name = 'it\\'s.exe' AND file:parent_directory_ref.path = 'C:\\\\Temp']"
+        );
+        assert_eq!(
+            stix_pattern(&indicator("registry_key", "HKLM\\SYSTEM\\x")),
+            "[windows-registry-key:key = 'HKEY_LOCAL_MACHINE\\\\SYSTEM\\\\x']"
+        );
+        assert_eq!(stix_pattern(&indicator("ipv4", "203.0.113.7")), "[ipv4-addr:value = '203.0.113.7']");
+    }
+
+    #[test]
+    fn test_write_ioc_package() {
+        let temp_dir = TempDir::new().unwrap();
+        let scan = sample_scan();
+        let indicators = extract_indicators(&scan);
+
+        let (csv_path, stix_path) = write_ioc_package(&indicators, &scan, temp_dir.path()).unwrap();
+
+        let csv = fs::read_to_string(&csv_path).unwrap();
+        assert!(csv.starts_with("indicator_type,value,source_artifact,context\n"));
+        assert_eq!(csv.lines().count(), indicators.len() + 1);
+        assert!(csv.contains("task_name,SyncTask,persistence_mechanisms,Suspicious Scheduled Task: SyncTask"));
+
+        let bundle: Value = serde_json::from_str(&fs::read_to_string(&stix_path).unwrap()).unwrap();
+        assert_eq!(bundle["type"], "bundle");
+        let objects = bundle["objects"].as_array().unwrap();
+        assert_eq!(objects.len(), indicators.len() + 1); // Identity + indicators
+        assert!(objects[1..].iter().all(|o| o["type"] == "indicator" && o["pattern_type"] == "stix"));
+        assert_eq!(csv_path.file_name().unwrap(), "iocs-1234.csv");
+    }
+
+    #[test]
+    fn test_load_scan_results() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("scan.json");
+        fs::write(&path, "{\"scan_metadata\": {}}").unwrap();
+        assert!(load_scan_results(&path).is_err());
+
+        fs::write(&path, sample_scan().to_string()).unwrap();
+        assert!(load_scan_results(&path).is_ok());
+    }
+}
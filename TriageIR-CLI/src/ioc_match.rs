@@ -0,0 +1,116 @@
+//! Known-indicator sweep over a saved scan
+//! Searches every string in the artifacts section for the given indicators
+//! (hashes, paths, IPs, domains, task or service names). Matching is
+//! case-insensitive and token-bounded, so 10.0.0.1 does not match 10.0.0.12
+//! and evil.exe does not match notevil.exe; subdomains of a domain still match.
+
+use crate::forensic_types::IocMatch;
+use serde_json::Value;
+
+/// Find every occurrence of the indicators in the scan artifacts
+pub fn match_indicators(scan: &Value, indicators: &[String]) -> Vec<IocMatch> {
+    let needles: Vec<(String, &String)> = indicators
+        .iter()
+        .map(|indicator| (indicator.trim().to_lowercase(), indicator))
+        .filter(|(needle, _)| !needle.is_empty())
+        .collect();
+
+    let mut matches = Vec::new();
+    if !needles.is_empty() {
+        walk(&scan["artifacts"], "/artifacts", &needles, &mut matches);
+    }
+    matches
+}
+
+fn walk(value: &Value, pointer: &str, needles: &[(String, &String)], matches: &mut Vec<IocMatch>) {
+    match value {
+        Value::String(s) => {
+            let haystack = s.to_lowercase();
+            for (needle, indicator) in needles {
+                if contains_token(&haystack, needle) {
+                    matches.push(IocMatch {
+                        indicator: indicator.to_string(),
+                        artifact_path: pointer.to_string(),
+                        matched_value: s.clone(),
+                    });
+                }
+            }
+        }
+        Value::Array(items) => {
+            for (i, item) in items.iter().enumerate() {
+                walk(item, &format!("{}/{}", pointer, i), needles, matches);
+            }
+        }
+        Value::Object(map) => {
+            for (key, item) in map {
+                // JSON pointer escaping (RFC 6901)
+                let key = key.replace('~', "~0").replace('/', "~1");
+                walk(item, &format!("{}/{}", pointer, key), needles, matches);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Whether the needle occurs in the haystack with no word characters on either side
+pub(crate) fn contains_token(haystack: &str, needle: &str) -> bool {
+    haystack.match_indices(needle).any(|(start, _)| {
+        let before = haystack[..start].chars().next_back();
+        let after = haystack[start + needle.len()..].chars().next();
+        // A leading dot is a subdomain boundary; a trailing dot extends the token (evil.exe.bak)
+        !before.is_some_and(is_word_char) && !after.is_some_and(|c| c == '.' || is_word_char(c))
+    })
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_match_indicators() {
+        let scan = json!({
+            "artifacts": {
+                "running_processes": [
+                    { "name": "beacon.exe", "executable_path": "C:\\Users\\Public\\beacon.exe", "sha256_hash": "ABCDEF0123" },
+                    { "name": "notbeacon.exe", "executable_path": "C:\\Tools\\notbeacon.exe", "sha256_hash": "" }
+                ],
+                "network_connections": [
+                    { "remote_address": "203.0.113.7" },
+                    { "remote_address": "203.0.113.70" }
+                ]
+            }
+        });
+        let indicators = vec!["abcdef0123".to_string(), "beacon.exe".to_string(), "203.0.113.7".to_string(), " ".to_string()];
+
+        let matches = match_indicators(&scan, &indicators);
+        let pointers: Vec<&str> = matches.iter().map(|m| m.artifact_path.as_str()).collect();
+
+        assert_eq!(
+            pointers,
+            vec![
+                "/artifacts/network_connections/0/remote_address",
+                "/artifacts/running_processes/0/executable_path",
+                "/artifacts/running_processes/0/name",
+                "/artifacts/running_processes/0/sha256_hash",
+            ]
+        );
+        assert_eq!(matches[3].indicator, "abcdef0123");
+        assert_eq!(matches[3].matched_value, "ABCDEF0123");
+    }
+
+    #[test]
+    fn test_contains_token() {
+        assert!(contains_token("c:\\temp\\evil.exe", "evil.exe"));
+        assert!(contains_token("cmd /c evil.exe -x", "evil.exe"));
+        assert!(!contains_token("notevil.exe", "evil.exe"));
+        assert!(!contains_token("evil.exe.bak", "evil.exe"));
+        assert!(contains_token("http://bad.example.com/x", "bad.example.com"));
+        assert!(contains_token("sub.bad.example.com", "bad.example.com"));
+        assert!(!contains_token("notbad.example.com", "bad.example.com"));
+    }
+}
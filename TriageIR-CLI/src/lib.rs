@@ -6,9 +6,108 @@ pub mod system_info;
 pub mod network;
 pub mod persistence;
 pub mod event_logs;
+pub mod event_config;
+pub mod event_log_retention;
 pub mod prefetch;
 pub mod shimcache;
 pub mod forensic_types;
+pub mod search_artifacts;
+pub mod evidence_package;
+pub mod offline;
+pub mod time_window;
+pub mod amcache;
+pub mod defender_logs;
+pub mod historical_processes;
+pub mod setupapi;
+pub mod notifications;
+pub mod clipboard_history;
+pub mod path_hijack;
+pub mod timestamp;
+pub mod export_profile;
+pub mod summary_report;
+pub mod worker;
+pub mod convert;
+pub mod remote_agent;
+pub mod upload;
+pub mod progress;
+pub mod cancellation;
+pub mod stream_writer;
+pub mod xpress;
+pub mod binutil;
+pub mod hive;
+pub mod local_accounts;
+pub mod security_configuration;
+pub mod defender_detections;
+pub mod powershell_history;
+pub mod sysmon_events;
+pub mod network_config;
+pub mod usb_devices;
+pub mod usn_journal;
+pub mod mft;
+pub mod name_resolution;
+pub mod certificate_stores;
+pub mod injection_scan;
+pub mod process_tokens;
+pub mod scan_profile;
+pub mod schema;
+pub mod scheduled_tasks;
+pub mod live_monitor;
+pub mod footprint;
+pub mod package_signing;
+pub mod package_volumes;
+pub mod custom_collectors;
+#[cfg(feature = "collector-recycle-bin")]
+pub mod recycle_bin;
+pub mod custom_commands;
+pub mod user_hives;
+pub mod search_history;
+pub mod ioc_export;
+pub mod process_tree;
+pub mod drivers;
+pub mod named_pipes;
+pub mod plugins;
+pub mod sessions;
+pub mod remote_access;
+pub mod timeline;
+pub mod ioc_match;
+pub mod scan_diff;
+pub mod diff_report;
+pub mod remediation;
+pub mod browser_artifacts;
+pub mod remediate;
+pub mod vss;
+pub mod collector_stats;
+pub mod srum;
+pub mod bam;
+pub mod scope_check;
+pub mod report_lang;
+pub mod user_execution;
+pub mod mru;
+pub mod module_load;
+pub mod screenshot;
+pub mod dll_hijack;
+pub mod network_logs;
+pub mod hashing;
+pub mod file_sweep;
+pub mod canaries;
+pub mod environment;
+pub mod file_collection;
+pub mod memory_dump;
+pub mod case_manifest;
+pub mod http_client;
+pub mod output_sinks;
+pub mod mmdb;
+pub mod enrichment;
+pub mod path_norm;
+pub mod suspicion;
+pub mod yaml;
+pub mod detections;
+pub mod attack;
+pub mod scan;
+pub mod ffi;
+
+#[cfg(feature = "python")]
+mod python;
 
 #[cfg(test)]
 pub mod test_error_scenarios;
\ No newline at end of file
@@ -0,0 +1,554 @@
+//! ETW live activity monitor (--monitor)
+//! A private real-time ETW session subscribes to the Microsoft-Windows-Kernel-Process
+//! (process starts, image loads) and Microsoft-Windows-Kernel-Network (TCP
+//! connects and accepts) providers when the collection starts and stops after
+//! the monitoring window. Attackers often react to responders touching the
+//! box, so activity of the collector itself and of the processes it starts is
+//! recorded but flagged as collector activity.
+
+// Event decoding is only reachable through the ETW consumer on Windows
+#![cfg_attr(not(windows), allow(dead_code))]
+
+use crate::forensic_types::{LiveActivity, LiveConnection, LiveImageLoad, LiveProcessStart};
+use crate::types::LogEntry;
+use std::collections::HashSet;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::time::Duration;
+
+#[cfg(windows)]
+use crate::binutil::filetime_to_datetime;
+#[cfg(windows)]
+use std::sync::mpsc::{self, Sender};
+#[cfg(windows)]
+use std::sync::{Arc, Mutex};
+#[cfg(windows)]
+use std::thread::JoinHandle;
+#[cfg(windows)]
+use windows::core::{GUID, PCWSTR, PWSTR};
+#[cfg(windows)]
+use windows::Win32::System::Diagnostics::Etw::{
+    CloseTrace, ControlTraceW, EnableTraceEx2, OpenTraceW, ProcessTrace, StartTraceW, CONTROLTRACE_HANDLE,
+    EVENT_CONTROL_CODE_ENABLE_PROVIDER, EVENT_HEADER_FLAG_32_BIT_HEADER, EVENT_RECORD, EVENT_TRACE_CONTROL_STOP,
+    EVENT_TRACE_LOGFILEW, EVENT_TRACE_PROPERTIES, EVENT_TRACE_REAL_TIME_MODE, PROCESSTRACE_HANDLE,
+    PROCESS_TRACE_MODE_EVENT_RECORD, PROCESS_TRACE_MODE_REAL_TIME, TRACE_LEVEL_INFORMATION, WNODE_FLAG_TRACED_GUID,
+};
+
+pub const MAX_MONITOR_SECONDS: u64 = 3600;
+
+/// Events kept per category; the rest are only counted
+const MAX_EVENTS_PER_CATEGORY: usize = 10_000;
+
+const KERNEL_PROCESS_PROVIDER: &str = "Microsoft-Windows-Kernel-Process";
+const KERNEL_NETWORK_PROVIDER: &str = "Microsoft-Windows-Kernel-Network";
+
+#[cfg(windows)]
+const KERNEL_PROCESS_GUID: GUID = GUID::from_u128(0x22fb2cd6_0e7b_422b_a0c7_2fad1fd0e716);
+#[cfg(windows)]
+const KERNEL_NETWORK_GUID: GUID = GUID::from_u128(0x7dd42a49_5329_4832_8dfd_43d979153a88);
+#[cfg(windows)]
+const WINEVENT_KEYWORD_PROCESS: u64 = 0x10;
+#[cfg(windows)]
+const WINEVENT_KEYWORD_IMAGE: u64 = 0x40;
+#[cfg(windows)]
+const KERNEL_NETWORK_KEYWORD_IPV4: u64 = 0x10;
+#[cfg(windows)]
+const KERNEL_NETWORK_KEYWORD_IPV6: u64 = 0x20;
+/// WNODE_HEADER.ClientContext: event timestamps as FILETIME
+#[cfg(windows)]
+const CLOCK_SYSTEM_TIME: u32 = 2;
+#[cfg(windows)]
+const ERROR_ALREADY_EXISTS: u32 = 183;
+
+/// Kernel-Process events
+const PROCESS_START_EVENT: u16 = 1;
+const IMAGE_LOAD_EVENT: u16 = 5;
+/// Kernel-Network TCP connection attempted and accepted events, IPv4 and IPv6
+const TCP_CONNECT_V4_EVENT: u16 = 12;
+const TCP_ACCEPT_V4_EVENT: u16 = 15;
+const TCP_CONNECT_V6_EVENT: u16 = 28;
+const TCP_ACCEPT_V6_EVENT: u16 = 31;
+
+/// Validate a --monitor window
+pub fn parse_window(seconds: u64) -> Result<Duration, String> {
+    if seconds == 0 || seconds > MAX_MONITOR_SECONDS {
+        return Err(format!("the monitoring window must be between 1 and {} seconds", MAX_MONITOR_SECONDS));
+    }
+    Ok(Duration::from_secs(seconds))
+}
+
+/// Activity decoded from one event
+#[derive(Debug, Clone, PartialEq)]
+pub enum ActivityEvent {
+    ProcessStart(LiveProcessStart),
+    ImageLoad(LiveImageLoad),
+    Connection(LiveConnection),
+}
+
+/// Events of the window, with the collector's process tree told apart
+pub struct ActivityRecorder {
+    activity: LiveActivity,
+    collector_pids: HashSet<u32>,
+}
+
+impl ActivityRecorder {
+    pub fn new(collector_pid: u32) -> Self {
+        ActivityRecorder { activity: LiveActivity::default(), collector_pids: HashSet::from([collector_pid]) }
+    }
+
+    pub fn record(&mut self, event: ActivityEvent) {
+        let activity = &mut self.activity;
+        match event {
+            ActivityEvent::ProcessStart(mut start) => {
+                if self.collector_pids.contains(&start.parent_pid) {
+                    self.collector_pids.insert(start.pid);
+                    start.collector_activity = true;
+                }
+                push_limited(&mut activity.process_starts, start, &mut activity.events_dropped);
+            }
+            ActivityEvent::ImageLoad(mut load) => {
+                load.collector_activity = self.collector_pids.contains(&load.pid);
+                push_limited(&mut activity.image_loads, load, &mut activity.events_dropped);
+            }
+            ActivityEvent::Connection(mut connection) => {
+                connection.collector_activity = self.collector_pids.contains(&connection.pid);
+                push_limited(&mut activity.connections, connection, &mut activity.events_dropped);
+            }
+        }
+    }
+
+    pub fn into_activity(self) -> LiveActivity {
+        self.activity
+    }
+}
+
+fn push_limited<T>(events: &mut Vec<T>, event: T, dropped: &mut u64) {
+    if events.len() < MAX_EVENTS_PER_CATEGORY {
+        events.push(event);
+    } else {
+        *dropped += 1;
+    }
+}
+
+/// Decode a Kernel-Process event; `pointer_size` is 4 for events of 32-bit systems
+pub fn parse_process_event(event_id: u16, version: u8, payload: &[u8], pointer_size: usize, timestamp: &str) -> Option<ActivityEvent> {
+    let mut reader = PayloadReader::new(payload);
+    match event_id {
+        // ProcessID, CreateTime, ParentProcessID, SessionID, [Flags,] ImageName, ...
+        PROCESS_START_EVENT => {
+            let pid = reader.u32()?;
+            reader.skip(8)?;
+            let parent_pid = reader.u32()?;
+            let session_id = reader.u32()?;
+            if version >= 1 {
+                reader.skip(4)?;
+            }
+            Some(ActivityEvent::ProcessStart(LiveProcessStart {
+                timestamp: timestamp.to_string(),
+                pid,
+                parent_pid,
+                session_id,
+                image_name: reader.utf16z()?,
+                collector_activity: false,
+            }))
+        }
+        // ImageBase, ImageSize, ProcessID, ImageCheckSum, TimeDateStamp, DefaultBase, ImageName
+        IMAGE_LOAD_EVENT => {
+            let image_base = reader.pointer(pointer_size)?;
+            let image_size = reader.pointer(pointer_size)?;
+            let pid = reader.u32()?;
+            reader.skip(8)?;
+            reader.pointer(pointer_size)?;
+            Some(ActivityEvent::ImageLoad(LiveImageLoad {
+                timestamp: timestamp.to_string(),
+                pid,
+                image_name: reader.utf16z()?,
+                image_base,
+                image_size,
+                collector_activity: false,
+            }))
+        }
+        _ => None,
+    }
+}
+
+/// Decode a Kernel-Network TCP connect or accept event; addresses are in
+/// network order, ports big-endian, and daddr is the remote end in both directions
+pub fn parse_network_event(event_id: u16, payload: &[u8], timestamp: &str) -> Option<ActivityEvent> {
+    let (direction, ipv6) = match event_id {
+        TCP_CONNECT_V4_EVENT => ("outbound", false),
+        TCP_ACCEPT_V4_EVENT => ("inbound", false),
+        TCP_CONNECT_V6_EVENT => ("outbound", true),
+        TCP_ACCEPT_V6_EVENT => ("inbound", true),
+        _ => return None,
+    };
+    // PID, size, daddr, saddr, dport, sport, ...
+    let mut reader = PayloadReader::new(payload);
+    let pid = reader.u32()?;
+    reader.skip(4)?;
+    let (remote_address, local_address) = if ipv6 {
+        (reader.ipv6()?.to_string(), reader.ipv6()?.to_string())
+    } else {
+        (reader.ipv4()?.to_string(), reader.ipv4()?.to_string())
+    };
+    let remote_port = reader.port()?;
+    let local_port = reader.port()?;
+    Some(ActivityEvent::Connection(LiveConnection {
+        timestamp: timestamp.to_string(),
+        pid,
+        direction: direction.to_string(),
+        protocol: "TCP".to_string(),
+        local_address,
+        local_port,
+        remote_address,
+        remote_port,
+        collector_activity: false,
+    }))
+}
+
+struct PayloadReader<'a> {
+    payload: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> PayloadReader<'a> {
+    fn new(payload: &'a [u8]) -> Self {
+        PayloadReader { payload, offset: 0 }
+    }
+
+    fn take(&mut self, length: usize) -> Option<&'a [u8]> {
+        let bytes = self.payload.get(self.offset..self.offset.checked_add(length)?)?;
+        self.offset += length;
+        Some(bytes)
+    }
+
+    fn skip(&mut self, length: usize) -> Option<()> {
+        self.take(length).map(|_| ())
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.take(4)?.try_into().ok()?))
+    }
+
+    fn pointer(&mut self, size: usize) -> Option<u64> {
+        match size {
+            4 => self.u32().map(u64::from),
+            _ => Some(u64::from_le_bytes(self.take(8)?.try_into().ok()?)),
+        }
+    }
+
+    fn port(&mut self) -> Option<u16> {
+        Some(u16::from_be_bytes(self.take(2)?.try_into().ok()?))
+    }
+
+    fn ipv4(&mut self) -> Option<Ipv4Addr> {
+        Some(Ipv4Addr::from(<[u8; 4]>::try_from(self.take(4)?).ok()?))
+    }
+
+    fn ipv6(&mut self) -> Option<Ipv6Addr> {
+        Some(Ipv6Addr::from(<[u8; 16]>::try_from(self.take(16)?).ok()?))
+    }
+
+    /// Null-terminated UTF-16 string, or the rest of the payload
+    fn utf16z(&mut self) -> Option<String> {
+        let rest = self.payload.get(self.offset..)?;
+        let units: Vec<u16> = rest.chunks_exact(2).map(|pair| u16::from_le_bytes([pair[0], pair[1]])).take_while(|&unit| unit != 0).collect();
+        self.offset += (units.len() + 1) * 2;
+        Some(String::from_utf16_lossy(&units))
+    }
+}
+
+/// Running monitoring session; `finish` waits for the end of the window
+pub struct LiveMonitor {
+    started_at: String,
+    window: Duration,
+    #[cfg(windows)]
+    session: EtwSession,
+}
+
+impl LiveMonitor {
+    /// Start the ETW session for the window
+    #[cfg(windows)]
+    pub fn start(window: Duration) -> Result<LiveMonitor, String> {
+        let started_at = chrono::Utc::now().to_rfc3339();
+        let session = unsafe { EtwSession::start(window)? };
+        Ok(LiveMonitor { started_at, window, session })
+    }
+
+    #[cfg(not(windows))]
+    pub fn start(_window: Duration) -> Result<LiveMonitor, String> {
+        Err("ETW monitoring requires Windows".to_string())
+    }
+
+    /// Wait for the window to end, or stop at once when the scan was interrupted,
+    /// and return what was recorded
+    #[cfg(windows)]
+    pub fn finish(self, interrupted: bool) -> (LiveActivity, Vec<LogEntry>) {
+        let mut logs = Vec::new();
+        let (mut activity, events_lost, failure) = self.session.finish(interrupted);
+        if let Some(failure) = failure {
+            logs.push(LogEntry::warn(&failure));
+        }
+        activity.started_at = self.started_at;
+        activity.ended_at = chrono::Utc::now().to_rfc3339();
+        activity.window_seconds = self.window.as_secs();
+        activity.providers = vec![KERNEL_PROCESS_PROVIDER.to_string(), KERNEL_NETWORK_PROVIDER.to_string()];
+        activity.events_lost = events_lost;
+        logs.push(LogEntry::info(&format!(
+            "Live activity monitor recorded {} process starts, {} image loads and {} connections ({} events lost, {} over the limit)",
+            activity.process_starts.len(), activity.image_loads.len(), activity.connections.len(), activity.events_lost, activity.events_dropped
+        )));
+        (activity, logs)
+    }
+
+    #[cfg(not(windows))]
+    pub fn finish(self, _interrupted: bool) -> (LiveActivity, Vec<LogEntry>) {
+        let activity = LiveActivity {
+            started_at: self.started_at,
+            window_seconds: self.window.as_secs(),
+            ..LiveActivity::default()
+        };
+        (activity, Vec::new())
+    }
+}
+
+/// Session control, the thread delivering its events and the one ending the window
+#[cfg(windows)]
+struct EtwSession {
+    recorder: Arc<Mutex<ActivityRecorder>>,
+    stop: Sender<()>,
+    stopper: JoinHandle<u64>,
+    consumer: JoinHandle<Result<(), String>>,
+}
+
+#[cfg(windows)]
+impl EtwSession {
+    unsafe fn start(window: Duration) -> Result<EtwSession, String> {
+        let name = format!("TriageIR-Monitor-{}", std::process::id());
+        let mut wide_name: Vec<u16> = name.encode_utf16().chain(std::iter::once(0)).collect();
+
+        let mut properties = trace_properties(wide_name.len());
+        let mut control = CONTROLTRACE_HANDLE::default();
+        let mut started = StartTraceW(&mut control, PCWSTR(wide_name.as_ptr()), properties.as_mut_ptr() as *mut EVENT_TRACE_PROPERTIES);
+        // A session left behind by a collector that was killed
+        if started.as_ref().is_err_and(|e| e.code() == windows::core::HRESULT::from_win32(ERROR_ALREADY_EXISTS)) {
+            let _ = ControlTraceW(CONTROLTRACE_HANDLE::default(), PCWSTR(wide_name.as_ptr()), properties.as_mut_ptr() as *mut EVENT_TRACE_PROPERTIES, EVENT_TRACE_CONTROL_STOP);
+            properties = trace_properties(wide_name.len());
+            started = StartTraceW(&mut control, PCWSTR(wide_name.as_ptr()), properties.as_mut_ptr() as *mut EVENT_TRACE_PROPERTIES);
+        }
+        started.map_err(|e| format!("Failed to start the ETW session {} (administrator rights are required): {}", name, e))?;
+
+        let providers = [
+            (KERNEL_PROCESS_GUID, WINEVENT_KEYWORD_PROCESS | WINEVENT_KEYWORD_IMAGE),
+            (KERNEL_NETWORK_GUID, KERNEL_NETWORK_KEYWORD_IPV4 | KERNEL_NETWORK_KEYWORD_IPV6),
+        ];
+        for (provider, keywords) in providers {
+            if let Err(e) = EnableTraceEx2(control, &provider, EVENT_CONTROL_CODE_ENABLE_PROVIDER.0, TRACE_LEVEL_INFORMATION as u8, keywords, 0, 0, None) {
+                stop_session(control, &wide_name, &mut properties);
+                return Err(format!("Failed to enable the ETW provider {:?}: {}", provider, e));
+            }
+        }
+
+        let recorder = Arc::new(Mutex::new(ActivityRecorder::new(std::process::id())));
+        let mut logfile = EVENT_TRACE_LOGFILEW::default();
+        logfile.LoggerName = PWSTR(wide_name.as_mut_ptr());
+        logfile.Anonymous1.ProcessTraceMode = PROCESS_TRACE_MODE_REAL_TIME | PROCESS_TRACE_MODE_EVENT_RECORD;
+        logfile.Anonymous2.EventRecordCallback = Some(record_event);
+        logfile.Context = Arc::as_ptr(&recorder) as *mut _;
+        let trace = OpenTraceW(&mut logfile);
+        if trace.Value == u64::MAX {
+            stop_session(control, &wide_name, &mut properties);
+            return Err(format!("Failed to open the ETW session {}: {}", name, windows::core::Error::from_win32()));
+        }
+
+        // ProcessTrace delivers events until the session stops
+        let consumer = std::thread::spawn(move || {
+            let result = ProcessTrace(&[trace], None, None).map_err(|e| format!("ETW event processing failed: {}", e));
+            let _ = CloseTrace(PROCESSTRACE_HANDLE { Value: trace.Value });
+            result
+        });
+        let (stop, stop_requested) = mpsc::channel();
+        let stopper = std::thread::spawn(move || {
+            let _ = stop_requested.recv_timeout(window);
+            stop_session(control, &wide_name, &mut properties)
+        });
+        Ok(EtwSession { recorder, stop, stopper, consumer })
+    }
+
+    /// Recorded activity, events lost by ETW and a processing failure
+    fn finish(self, interrupted: bool) -> (LiveActivity, u64, Option<String>) {
+        if interrupted {
+            let _ = self.stop.send(());
+        }
+        let events_lost = self.stopper.join().unwrap_or(0);
+        let failure = match self.consumer.join() {
+            Ok(Ok(())) => None,
+            Ok(Err(e)) => Some(e),
+            Err(_) => Some("The ETW consumer thread panicked".to_string()),
+        };
+        let activity = match Arc::try_unwrap(self.recorder) {
+            Ok(recorder) => recorder.into_inner().map(ActivityRecorder::into_activity).unwrap_or_default(),
+            Err(shared) => shared.lock().map(|recorder| recorder.activity.clone()).unwrap_or_default(),
+        };
+        (activity, events_lost, failure)
+    }
+}
+
+/// EVENT_TRACE_PROPERTIES of a real-time session followed by room for its name
+#[cfg(windows)]
+fn trace_properties(name_units: usize) -> Vec<u64> {
+    let header = std::mem::size_of::<EVENT_TRACE_PROPERTIES>();
+    let size = header + name_units * 2;
+    let mut buffer = vec![0u64; size.div_ceil(8)];
+    let properties = unsafe { &mut *(buffer.as_mut_ptr() as *mut EVENT_TRACE_PROPERTIES) };
+    properties.Wnode.BufferSize = size as u32;
+    properties.Wnode.Flags = WNODE_FLAG_TRACED_GUID;
+    properties.Wnode.ClientContext = CLOCK_SYSTEM_TIME;
+    properties.LogFileMode = EVENT_TRACE_REAL_TIME_MODE;
+    properties.LoggerNameOffset = header as u32;
+    buffer
+}
+
+/// Stop the session; returns the events and buffers it lost
+#[cfg(windows)]
+fn stop_session(control: CONTROLTRACE_HANDLE, wide_name: &[u16], properties: &mut Vec<u64>) -> u64 {
+    *properties = trace_properties(wide_name.len());
+    let pointer = properties.as_mut_ptr() as *mut EVENT_TRACE_PROPERTIES;
+    unsafe {
+        if ControlTraceW(control, PCWSTR(wide_name.as_ptr()), pointer, EVENT_TRACE_CONTROL_STOP).is_err() {
+            return 0;
+        }
+        u64::from((*pointer).EventsLost) + u64::from((*pointer).RealTimeBuffersLost)
+    }
+}
+
+#[cfg(windows)]
+unsafe extern "system" fn record_event(record: *mut EVENT_RECORD) {
+    let Some(record) = record.as_ref() else {
+        return;
+    };
+    let Some(recorder) = (record.UserContext as *const Mutex<ActivityRecorder>).as_ref() else {
+        return;
+    };
+    if record.UserData.is_null() || record.UserDataLength == 0 {
+        return;
+    }
+    let header = &record.EventHeader;
+    let payload = std::slice::from_raw_parts(record.UserData as *const u8, record.UserDataLength as usize);
+    let pointer_size = if u32::from(header.Flags) & EVENT_HEADER_FLAG_32_BIT_HEADER != 0 { 4 } else { 8 };
+    let timestamp = filetime_to_datetime(header.TimeStamp as u64).map(|time| time.to_rfc3339()).unwrap_or_default();
+    let descriptor = header.EventDescriptor;
+
+    let event = if header.ProviderId == KERNEL_PROCESS_GUID {
+        parse_process_event(descriptor.Id, descriptor.Version, payload, pointer_size, &timestamp)
+    } else if header.ProviderId == KERNEL_NETWORK_GUID {
+        parse_network_event(descriptor.Id, payload, &timestamp)
+    } else {
+        None
+    };
+    if let (Some(event), Ok(mut recorder)) = (event, recorder.lock()) {
+        recorder.record(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16z(text: &str) -> Vec<u8> {
+        text.encode_utf16().chain(std::iter::once(0)).flat_map(u16::to_le_bytes).collect()
+    }
+
+    #[test]
+    fn test_parse_process_events() {
+        let mut start = Vec::new();
+        start.extend(4242u32.to_le_bytes());
+        start.extend(0x01d9_0000_0000_0000u64.to_le_bytes());
+        start.extend(900u32.to_le_bytes());
+        start.extend(1u32.to_le_bytes());
+        start.extend(0u32.to_le_bytes()); // Flags, from version 1
+        start.extend(utf16z("\\Device\\HarddiskVolume3\\Windows\\System32\\cmd.exe"));
+        start.extend(0u32.to_le_bytes());
+        let Some(ActivityEvent::ProcessStart(process)) = parse_process_event(PROCESS_START_EVENT, 3, &start, 8, "2026-10-16T12:00:00Z") else {
+            panic!("process start not decoded");
+        };
+        assert_eq!((process.pid, process.parent_pid, process.session_id), (4242, 900, 1));
+        assert_eq!(process.image_name, "\\Device\\HarddiskVolume3\\Windows\\System32\\cmd.exe");
+
+        let mut load = Vec::new();
+        load.extend(0x7ff8_1000_0000u64.to_le_bytes());
+        load.extend(0x1f_0000u64.to_le_bytes());
+        load.extend(4242u32.to_le_bytes());
+        load.extend([0u8; 8]);
+        load.extend(0x7ff8_1000_0000u64.to_le_bytes());
+        load.extend(utf16z("\\Device\\HarddiskVolume3\\Users\\Public\\evil.dll"));
+        let Some(ActivityEvent::ImageLoad(image)) = parse_process_event(IMAGE_LOAD_EVENT, 0, &load, 8, "") else {
+            panic!("image load not decoded");
+        };
+        assert_eq!((image.pid, image.image_base, image.image_size), (4242, 0x7ff8_1000_0000, 0x1f_0000));
+        assert!(image.image_name.ends_with("evil.dll"));
+
+        assert_eq!(parse_process_event(PROCESS_START_EVENT, 3, &start[..10], 8, ""), None);
+        assert_eq!(parse_process_event(2, 0, &start, 8, ""), None);
+    }
+
+    #[test]
+    fn test_parse_network_events() {
+        let mut connect = Vec::new();
+        connect.extend(4242u32.to_le_bytes());
+        connect.extend(0u32.to_le_bytes());
+        connect.extend([203, 0, 113, 7]);
+        connect.extend([10, 0, 0, 5]);
+        connect.extend(443u16.to_be_bytes());
+        connect.extend(51234u16.to_be_bytes());
+        let Some(ActivityEvent::Connection(connection)) = parse_network_event(TCP_CONNECT_V4_EVENT, &connect, "") else {
+            panic!("connect not decoded");
+        };
+        assert_eq!(connection.direction, "outbound");
+        assert_eq!((connection.remote_address.as_str(), connection.remote_port), ("203.0.113.7", 443));
+        assert_eq!((connection.local_address.as_str(), connection.local_port), ("10.0.0.5", 51234));
+
+        let mut accept = Vec::new();
+        accept.extend(4u32.to_le_bytes());
+        accept.extend(0u32.to_le_bytes());
+        accept.extend("2001:db8::7".parse::<Ipv6Addr>().unwrap().octets());
+        accept.extend("2001:db8::5".parse::<Ipv6Addr>().unwrap().octets());
+        accept.extend(50000u16.to_be_bytes());
+        accept.extend(445u16.to_be_bytes());
+        let Some(ActivityEvent::Connection(connection)) = parse_network_event(TCP_ACCEPT_V6_EVENT, &accept, "") else {
+            panic!("accept not decoded");
+        };
+        assert_eq!((connection.direction.as_str(), connection.remote_address.as_str(), connection.local_port), ("inbound", "2001:db8::7", 445));
+        assert_eq!(parse_network_event(10, &connect, ""), None); // Data sent
+    }
+
+    #[test]
+    fn test_recorder_flags_collector_activity() {
+        let start = |pid, parent_pid| ActivityEvent::ProcessStart(LiveProcessStart {
+            timestamp: String::new(),
+            pid,
+            parent_pid,
+            session_id: 1,
+            image_name: "cmd.exe".to_string(),
+            collector_activity: false,
+        });
+        let mut recorder = ActivityRecorder::new(100);
+        recorder.record(start(200, 100)); // schtasks started by the collector
+        recorder.record(start(300, 200));
+        recorder.record(start(400, 900)); // started by someone else
+        recorder.record(ActivityEvent::ImageLoad(LiveImageLoad {
+            timestamp: String::new(),
+            pid: 300,
+            image_name: "ntdll.dll".to_string(),
+            image_base: 0,
+            image_size: 0,
+            collector_activity: false,
+        }));
+        let activity = recorder.into_activity();
+        assert_eq!(activity.process_starts.iter().map(|p| p.collector_activity).collect::<Vec<_>>(), [true, true, false]);
+        assert!(activity.image_loads[0].collector_activity);
+
+        assert!(parse_window(0).is_err());
+        assert!(parse_window(MAX_MONITOR_SECONDS + 1).is_err());
+        assert_eq!(parse_window(30), Ok(Duration::from_secs(30)));
+    }
+}
@@ -0,0 +1,570 @@
+//! Local account and group enumeration
+//! Reports the local users of the host with their SIDs, local group
+//! memberships, password-last-set and last-logon times and account flags.
+//! Live hosts are queried with NetUserEnum/NetLocalGroupEnum; on offline
+//! volumes the SAM hive is parsed directly: the fixed (`F`) and variable (`V`)
+//! records of each user and the member lists (`C`) of the local groups.
+//! Membership of privileged built-in groups is reported separately, keyed on
+//! the group SID so that localized group names are recognized.
+
+use crate::binutil::{filetime_to_string, read_u16, read_u32, read_u64, sid_to_string, utf16_string};
+use crate::forensic_types::{AuditEntry, UserProfile};
+use crate::hive::{Hive, Key};
+use crate::offline::OfflineRoot;
+use std::collections::HashMap;
+
+const COMPONENT: &str = "local_accounts";
+
+/// Users of the local account domain, one subkey per RID in hex
+const SAM_USERS_KEY: &str = "SAM\\Domains\\Account\\Users";
+/// Account domain key; the end of its V value is the machine SID
+const SAM_ACCOUNT_KEY: &str = "SAM\\Domains\\Account";
+/// Local groups: built-in (S-1-5-32-...) and machine-defined
+const SAM_ALIAS_KEYS: [(&str, &str); 2] = [("SAM\\Domains\\Builtin\\Aliases", "S-1-5-32"), ("SAM\\Domains\\Account\\Aliases", "")];
+const PROFILE_LIST_KEY: &str = "Microsoft\\Windows NT\\CurrentVersion\\ProfileList";
+
+/// Built-in groups whose members can administer the host or log on remotely
+const PRIVILEGED_GROUPS: [(&str, &str); 9] = [
+    ("S-1-5-32-544", "Administrators"),
+    ("S-1-5-32-548", "Account Operators"),
+    ("S-1-5-32-549", "Server Operators"),
+    ("S-1-5-32-550", "Print Operators"),
+    ("S-1-5-32-551", "Backup Operators"),
+    ("S-1-5-32-555", "Remote Desktop Users"),
+    ("S-1-5-32-562", "Distributed COM Users"),
+    ("S-1-5-32-578", "Hyper-V Administrators"),
+    ("S-1-5-32-580", "Remote Management Users"),
+];
+
+/// Account control bits of the SAM F record
+const ACB_DISABLED: u16 = 0x0001;
+const ACB_PASSWORD_NOT_REQUIRED: u16 = 0x0004;
+const ACB_PASSWORD_NEVER_EXPIRES: u16 = 0x0200;
+const ACB_LOCKED_OUT: u16 = 0x0400;
+/// Offsets into the V record are relative to the end of its header
+const V_DATA_OFFSET: usize = 0xCC;
+/// Offsets into the C record of a group are relative to the end of its header
+const C_DATA_OFFSET: usize = 0x34;
+
+/// Enumerate the local accounts of the running system
+#[cfg(windows)]
+pub fn collect_local_accounts() -> (Vec<UserProfile>, Vec<AuditEntry>) {
+    let start_time = std::time::Instant::now();
+    let mut audit_log = vec![AuditEntry::new(COMPONENT, "INFO", "start_collection", "Starting local account enumeration".to_string(), "started")];
+
+    let mut profiles = match windows_api::local_users() {
+        Ok(profiles) => profiles,
+        Err(e) => {
+            audit_log.push(AuditEntry::new(COMPONENT, "ERROR", "enumerate_users", format!("NetUserEnum failed: {}", e), "error"));
+            Vec::new()
+        }
+    };
+    match windows_api::local_groups() {
+        Ok(groups) => assign_groups(&mut profiles, &groups),
+        Err(e) => audit_log.push(AuditEntry::new(COMPONENT, "WARN", "enumerate_groups", format!("NetLocalGroupEnum failed: {}", e), "error")),
+    }
+    for profile in &mut profiles {
+        profile.profile_path = windows_api::profile_path(&profile.sid).unwrap_or_default();
+    }
+
+    audit_log.push(completed(&profiles, start_time));
+    (profiles, audit_log)
+}
+
+#[cfg(not(windows))]
+pub fn collect_local_accounts() -> (Vec<UserProfile>, Vec<AuditEntry>) {
+    let audit_log = vec![
+        AuditEntry::new(COMPONENT, "INFO", "start_collection", "Starting local account enumeration".to_string(), "started"),
+        AuditEntry::new(COMPONENT, "WARN", "enumerate_users", "Local account enumeration requires the Windows network management API".to_string(), "unsupported"),
+        AuditEntry::new(COMPONENT, "INFO", "complete_collection", "Collected 0 local accounts".to_string(), "success"),
+    ];
+    (Vec::new(), audit_log)
+}
+
+/// Enumerate the local accounts recorded in the SAM hive of a mounted Windows volume
+pub fn collect_local_accounts_offline(root: &OfflineRoot) -> (Vec<UserProfile>, Vec<AuditEntry>) {
+    let start_time = std::time::Instant::now();
+    let sam = match root.open_hive("SAM") {
+        Ok(hive) => hive,
+        Err(e) => {
+            let audit_log = vec![AuditEntry::new(COMPONENT, "ERROR", "load_hive", format!("Failed to load offline SAM hive: {}", e), "error")];
+            return (Vec::new(), audit_log);
+        }
+    };
+    let mut audit_log = vec![AuditEntry::new(
+        COMPONENT,
+        "INFO",
+        "start_collection",
+        format!("Starting offline local account analysis of {}", root.hive_path("SAM").display()),
+        "started",
+    )];
+    if sam.is_dirty() {
+        audit_log.push(AuditEntry::new(COMPONENT, "WARN", "hive_state", "SAM hive is dirty; changes only in its transaction logs are missing".to_string(), "dirty"));
+    }
+
+    let mut profiles = sam_users(&sam, &mut audit_log);
+    assign_groups(&mut profiles, &sam_groups(&sam));
+
+    // Profile directories are listed in the SOFTWARE hive; a missing one only costs the paths
+    match root.open_hive("SOFTWARE") {
+        Ok(software) => {
+            for profile in &mut profiles {
+                profile.profile_path = software
+                    .open_key(&format!("{}\\{}", PROFILE_LIST_KEY, profile.sid))
+                    .and_then(|key| key.string("ProfileImagePath"))
+                    .unwrap_or_default();
+            }
+        }
+        Err(e) => audit_log.push(AuditEntry::new(COMPONENT, "WARN", "load_hive", format!("Profile paths unavailable: {}", e), "error")),
+    }
+
+    audit_log.push(completed(&profiles, start_time));
+    (profiles, audit_log)
+}
+
+fn completed(profiles: &[UserProfile], start_time: std::time::Instant) -> AuditEntry {
+    let privileged = profiles.iter().filter(|p| !p.privileged_groups.is_empty()).count();
+    let mut entry = AuditEntry::new(
+        COMPONENT,
+        "INFO",
+        "complete_collection",
+        format!("Collected {} local accounts ({} in privileged groups)", profiles.len(), privileged),
+        "success",
+    );
+    entry.duration_ms = Some(start_time.elapsed().as_millis() as u64);
+    entry
+}
+
+/// A local group with the SIDs of its members
+struct LocalGroup {
+    name: String,
+    sid: String,
+    members: Vec<String>,
+}
+
+/// Record the groups each account is a member of, and which of them are privileged
+fn assign_groups(profiles: &mut [UserProfile], groups: &[LocalGroup]) {
+    for profile in profiles.iter_mut() {
+        for group in groups.iter().filter(|g| g.members.iter().any(|m| m.eq_ignore_ascii_case(&profile.sid))) {
+            profile.groups.push(group.name.clone());
+            if privileged_group(&group.sid).is_some() {
+                profile.privileged_groups.push(group.name.clone());
+            }
+        }
+    }
+}
+
+/// English name of a privileged built-in group
+pub fn privileged_group(sid: &str) -> Option<&'static str> {
+    PRIVILEGED_GROUPS.iter().find(|(group_sid, _)| *group_sid == sid).map(|(_, name)| *name)
+}
+
+fn sam_users(sam: &Hive, audit_log: &mut Vec<AuditEntry>) -> Vec<UserProfile> {
+    let Some(users_key) = sam.open_key(SAM_USERS_KEY) else {
+        audit_log.push(AuditEntry::new(COMPONENT, "WARN", "registry_access", format!("{} not found", SAM_USERS_KEY), "not_found"));
+        return Vec::new();
+    };
+    let machine_sid = sam
+        .open_key(SAM_ACCOUNT_KEY)
+        .and_then(|key| key.value("V"))
+        .and_then(|v| v.data.len().checked_sub(24).and_then(|start| sid_to_string(&v.data[start..])))
+        .unwrap_or_default();
+    if machine_sid.is_empty() {
+        audit_log.push(AuditEntry::new(COMPONENT, "WARN", "parse_machine_sid", "Machine SID not found; accounts are reported by RID".to_string(), "not_found"));
+    }
+    // The Names subkey of an account is written when the account is created
+    let created: HashMap<String, String> = users_key
+        .subkey("Names")
+        .map(|names| names.subkeys().iter().map(|key| (key.name().to_lowercase(), key.last_written())).collect())
+        .unwrap_or_default();
+
+    let mut profiles = Vec::new();
+    for user_key in users_key.subkeys().iter().filter(|key| key.name() != "Names") {
+        match parse_user(user_key) {
+            Some((rid, mut profile)) => {
+                profile.sid = if machine_sid.is_empty() { rid.to_string() } else { format!("{}-{}", machine_sid, rid) };
+                profile.creation_time = created.get(&profile.username.to_lowercase()).cloned().unwrap_or_default();
+                profiles.push(profile);
+            }
+            None => audit_log.push(AuditEntry::new(
+                COMPONENT,
+                "WARN",
+                "parse_user",
+                format!("Unreadable SAM record {}\\{}", SAM_USERS_KEY, user_key.name()),
+                "error",
+            )),
+        }
+    }
+    profiles
+}
+
+/// Parse the F and V records of a SAM user key; returns the RID and the account
+fn parse_user(user_key: &Key) -> Option<(u32, UserProfile)> {
+    let f = user_key.value("F")?.data;
+    let v = user_key.value("V")?.data;
+    if f.len() < 0x44 {
+        return None;
+    }
+    let flags = read_u16(&f, 0x38)?;
+    let profile = UserProfile {
+        username: v_string(&v, 0)?,
+        sid: String::new(),
+        profile_path: String::new(),
+        creation_time: String::new(),
+        last_logon: filetime_to_string(read_u64(&f, 0x08)?),
+        last_logoff: String::new(),
+        logon_count: read_u16(&f, 0x42)? as u32,
+        bad_password_count: read_u16(&f, 0x40)? as u32,
+        account_expires: filetime_to_string(read_u64(&f, 0x20)?),
+        password_last_set: filetime_to_string(read_u64(&f, 0x18)?),
+        groups: Vec::new(),
+        privileges: Vec::new(),
+        account_flags: acb_flags(flags),
+        privileged_groups: Vec::new(),
+    };
+    Some((read_u32(&f, 0x30)?, profile))
+}
+
+/// Names of the account control bits that matter for triage
+fn acb_flags(flags: u16) -> Vec<String> {
+    [
+        (ACB_DISABLED, "disabled"),
+        (ACB_LOCKED_OUT, "locked_out"),
+        (ACB_PASSWORD_NOT_REQUIRED, "password_not_required"),
+        (ACB_PASSWORD_NEVER_EXPIRES, "password_never_expires"),
+    ]
+    .iter()
+    .filter(|(bit, _)| flags & bit != 0)
+    .map(|(_, name)| name.to_string())
+    .collect()
+}
+
+/// String `index` of the V record, whose header holds an (offset, length,
+/// unknown) triple per field: 0 = username, 1 = full name, 2 = comment
+fn v_string(v: &[u8], index: usize) -> Option<String> {
+    let offset = read_u32(v, 0x0C + index * 12)? as usize + V_DATA_OFFSET;
+    let length = read_u32(v, 0x10 + index * 12)? as usize;
+    v.get(offset..offset + length).map(utf16_string)
+}
+
+fn sam_groups(sam: &Hive) -> Vec<LocalGroup> {
+    let mut groups = Vec::new();
+    for (aliases_key, domain_sid) in SAM_ALIAS_KEYS {
+        let Some(aliases) = sam.open_key(aliases_key) else {
+            continue;
+        };
+        for alias in aliases.subkeys() {
+            let (Ok(rid), Some(c)) = (u32::from_str_radix(&alias.name(), 16), alias.value("C")) else {
+                continue;
+            };
+            if let Some((name, members)) = parse_alias(&c.data) {
+                let sid = if domain_sid.is_empty() { format!("rid:{}", rid) } else { format!("{}-{}", domain_sid, rid) };
+                groups.push(LocalGroup { name, sid, members });
+            }
+        }
+    }
+    groups
+}
+
+/// Parse the C record of a local group: its name and the SIDs of its members
+fn parse_alias(c: &[u8]) -> Option<(String, Vec<String>)> {
+    let name_offset = read_u32(c, 0x10)? as usize + C_DATA_OFFSET;
+    let name_length = read_u32(c, 0x14)? as usize;
+    let name = utf16_string(c.get(name_offset..name_offset + name_length)?);
+    let mut position = read_u32(c, 0x28)? as usize + C_DATA_OFFSET;
+    let count = read_u32(c, 0x30)?;
+
+    let mut members = Vec::new();
+    for _ in 0..count {
+        let sub_authorities = *c.get(position + 1)? as usize;
+        let length = 8 + sub_authorities * 4;
+        members.push(sid_to_string(c.get(position..position + length)?)?);
+        position += length;
+    }
+    Some((name, members))
+}
+
+#[cfg(windows)]
+mod windows_api {
+    use super::{sid_to_string, LocalGroup, PROFILE_LIST_KEY};
+    use crate::footprint::TrackedKey;
+    use crate::forensic_types::UserProfile;
+    use windows::core::{HSTRING, PCWSTR, PWSTR};
+    use windows::Win32::Foundation::PSID;
+    use windows::Win32::NetworkManagement::NetManagement::{
+        NetApiBufferFree, NetLocalGroupEnum, NetLocalGroupGetMembers, NetUserEnum, NetUserGetInfo, FILTER_NORMAL_ACCOUNT,
+        LOCALGROUP_INFO_0, LOCALGROUP_MEMBERS_INFO_2, MAX_PREFERRED_LENGTH, NERR_Success, UF_ACCOUNTDISABLE,
+        UF_DONT_EXPIRE_PASSWD, UF_LOCKOUT, UF_PASSWD_NOTREQD, USER_INFO_0, USER_INFO_4,
+    };
+    use windows::Win32::Security::{GetLengthSid, LookupAccountNameW, SID_NAME_USE};
+    use winreg::enums::HKEY_LOCAL_MACHINE;
+    use winreg::RegKey;
+
+    /// NetAPI account expiry meaning never
+    const TIMEQ_FOREVER: u32 = u32::MAX;
+    /// SECURITY_MAX_SID_SIZE
+    const MAX_SID_SIZE: usize = 68;
+
+    pub fn local_users() -> Result<Vec<UserProfile>, String> {
+        let mut buffer: *mut u8 = std::ptr::null_mut();
+        let (mut read, mut total) = (0u32, 0u32);
+        let status = unsafe {
+            NetUserEnum(PCWSTR::null(), 0, FILTER_NORMAL_ACCOUNT, &mut buffer, MAX_PREFERRED_LENGTH, &mut read, &mut total, None)
+        };
+        if status != NERR_Success {
+            free(buffer);
+            return Err(format!("error {}", status));
+        }
+        let names: Vec<String> = (0..read as usize)
+            .map(|i| wide(unsafe { (*(buffer as *const USER_INFO_0).add(i)).usri0_name }))
+            .collect();
+        free(buffer);
+
+        Ok(names.iter().filter_map(|name| user_profile(name)).collect())
+    }
+
+    /// Level 4 adds the SID to the logon statistics of level 3
+    fn user_profile(name: &str) -> Option<UserProfile> {
+        let mut buffer: *mut u8 = std::ptr::null_mut();
+        let status = unsafe { NetUserGetInfo(PCWSTR::null(), &HSTRING::from(name), 4, &mut buffer) };
+        if status != NERR_Success || buffer.is_null() {
+            free(buffer);
+            return None;
+        }
+        let info = unsafe { &*(buffer as *const USER_INFO_4) };
+        let flags = info.usri4_flags.0;
+        let account_flags = [
+            (UF_ACCOUNTDISABLE.0, "disabled"),
+            (UF_LOCKOUT.0, "locked_out"),
+            (UF_PASSWD_NOTREQD.0, "password_not_required"),
+            (UF_DONT_EXPIRE_PASSWD.0, "password_never_expires"),
+        ]
+        .iter()
+        .filter(|(bit, _)| flags & bit != 0)
+        .map(|(_, name)| name.to_string())
+        .collect();
+        let password_last_set = chrono::Utc::now() - chrono::Duration::seconds(info.usri4_password_age as i64);
+
+        let profile = UserProfile {
+            username: wide(info.usri4_name),
+            sid: sid_string(info.usri4_user_sid).unwrap_or_default(),
+            profile_path: String::new(),
+            creation_time: String::new(),
+            last_logon: unix_time(info.usri4_last_logon),
+            last_logoff: unix_time(info.usri4_last_logoff),
+            logon_count: info.usri4_num_logons,
+            bad_password_count: info.usri4_bad_pw_count,
+            account_expires: if info.usri4_acct_expires == TIMEQ_FOREVER { String::new() } else { unix_time(info.usri4_acct_expires) },
+            password_last_set: password_last_set.to_rfc3339(),
+            groups: Vec::new(),
+            privileges: Vec::new(),
+            account_flags,
+            privileged_groups: Vec::new(),
+        };
+        free(buffer);
+        Some(profile)
+    }
+
+    pub fn local_groups() -> Result<Vec<LocalGroup>, String> {
+        let mut buffer: *mut u8 = std::ptr::null_mut();
+        let (mut read, mut total) = (0u32, 0u32);
+        let status = unsafe { NetLocalGroupEnum(PCWSTR::null(), 0, &mut buffer, MAX_PREFERRED_LENGTH, &mut read, &mut total, None) };
+        if status != NERR_Success {
+            free(buffer);
+            return Err(format!("error {}", status));
+        }
+        let names: Vec<String> = (0..read as usize)
+            .map(|i| wide(unsafe { (*(buffer as *const LOCALGROUP_INFO_0).add(i)).lgrpi0_name }))
+            .collect();
+        free(buffer);
+
+        Ok(names
+            .into_iter()
+            .map(|name| LocalGroup { sid: account_sid(&name).unwrap_or_default(), members: group_members(&name), name })
+            .collect())
+    }
+
+    fn group_members(group: &str) -> Vec<String> {
+        let mut buffer: *mut u8 = std::ptr::null_mut();
+        let (mut read, mut total) = (0u32, 0u32);
+        let status = unsafe {
+            NetLocalGroupGetMembers(PCWSTR::null(), &HSTRING::from(group), 2, &mut buffer, MAX_PREFERRED_LENGTH, &mut read, &mut total, None)
+        };
+        if status != NERR_Success {
+            free(buffer);
+            return Vec::new();
+        }
+        let members = (0..read as usize)
+            .filter_map(|i| sid_string(unsafe { (*(buffer as *const LOCALGROUP_MEMBERS_INFO_2).add(i)).lgrmi2_sid }))
+            .collect();
+        free(buffer);
+        members
+    }
+
+    /// SID of a local group, to recognize privileged groups whatever the display language
+    fn account_sid(name: &str) -> Option<String> {
+        let mut sid = [0u8; MAX_SID_SIZE];
+        let mut sid_size = MAX_SID_SIZE as u32;
+        let mut domain = [0u16; 256];
+        let mut domain_size = domain.len() as u32;
+        let mut sid_use = SID_NAME_USE::default();
+        unsafe {
+            LookupAccountNameW(
+                PCWSTR::null(),
+                &HSTRING::from(name),
+                PSID(sid.as_mut_ptr() as *mut _),
+                &mut sid_size,
+                PWSTR(domain.as_mut_ptr()),
+                &mut domain_size,
+                &mut sid_use,
+            )
+        }
+        .ok()?;
+        sid_to_string(&sid[..sid_size as usize])
+    }
+
+    pub fn profile_path(sid: &str) -> Option<String> {
+        RegKey::predef(HKEY_LOCAL_MACHINE)
+            .open_tracked(format!("SOFTWARE\\{}\\{}", PROFILE_LIST_KEY, sid))
+            .ok()?
+            .get_value::<String, _>("ProfileImagePath")
+            .ok()
+    }
+
+    fn sid_string(sid: PSID) -> Option<String> {
+        if sid.0.is_null() {
+            return None;
+        }
+        let length = unsafe { GetLengthSid(sid) } as usize;
+        sid_to_string(unsafe { std::slice::from_raw_parts(sid.0 as *const u8, length) })
+    }
+
+    /// NetAPI times are seconds since 1970; 0 means never
+    fn unix_time(seconds: u32) -> String {
+        if seconds == 0 {
+            return String::new();
+        }
+        chrono::DateTime::from_timestamp(seconds as i64, 0)
+            .map(|datetime| datetime.to_rfc3339())
+            .unwrap_or_default()
+    }
+
+    fn wide(value: PWSTR) -> String {
+        if value.is_null() {
+            return String::new();
+        }
+        unsafe { value.to_string() }.unwrap_or_default()
+    }
+
+    fn free(buffer: *mut u8) {
+        if !buffer.is_null() {
+            unsafe { NetApiBufferFree(Some(buffer as *const _)) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hive::{HiveBuilder, REG_BINARY};
+
+    const FILETIME: u64 = 133_580_000_000_000_000; // 2024-04-19T11:33:20Z
+
+    fn utf16(text: &str) -> Vec<u8> {
+        text.encode_utf16().flat_map(|unit| unit.to_le_bytes()).collect()
+    }
+
+    fn sid(sub_authorities: &[u32], authority: u8) -> Vec<u8> {
+        let mut blob = vec![1, sub_authorities.len() as u8, 0, 0, 0, 0, 0, authority];
+        blob.extend(sub_authorities.iter().flat_map(|s| s.to_le_bytes()));
+        blob
+    }
+
+    fn user_f(rid: u32, flags: u16, last_logon: u64) -> Vec<u8> {
+        let mut f = vec![0u8; 0x50];
+        f[0x08..0x10].copy_from_slice(&last_logon.to_le_bytes());
+        f[0x18..0x20].copy_from_slice(&FILETIME.to_le_bytes());
+        f[0x20..0x28].copy_from_slice(&0x7FFF_FFFF_FFFF_FFFFu64.to_le_bytes());
+        f[0x30..0x34].copy_from_slice(&rid.to_le_bytes());
+        f[0x38..0x3A].copy_from_slice(&flags.to_le_bytes());
+        f[0x40..0x42].copy_from_slice(&3u16.to_le_bytes());
+        f[0x42..0x44].copy_from_slice(&12u16.to_le_bytes());
+        f
+    }
+
+    fn user_v(name: &str) -> Vec<u8> {
+        let mut v = vec![0u8; V_DATA_OFFSET];
+        v[0x10..0x14].copy_from_slice(&(name.len() as u32 * 2).to_le_bytes());
+        v.extend(utf16(name));
+        v
+    }
+
+    fn alias_c(name: &str, members: &[Vec<u8>]) -> Vec<u8> {
+        let name = utf16(name);
+        let mut c = vec![0u8; C_DATA_OFFSET];
+        c[0x14..0x18].copy_from_slice(&(name.len() as u32).to_le_bytes());
+        c[0x28..0x2C].copy_from_slice(&(name.len() as u32).to_le_bytes());
+        c[0x30..0x34].copy_from_slice(&(members.len() as u32).to_le_bytes());
+        c.extend(name);
+        c.extend(members.concat());
+        c
+    }
+
+    #[test]
+    fn test_collect_local_accounts_offline_from_sam() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config = temp_dir.path().join("Windows").join("System32").join("config");
+        std::fs::create_dir_all(&config).unwrap();
+        let root = OfflineRoot::new(temp_dir.path()).unwrap();
+        let (profiles, audit_log) = collect_local_accounts_offline(&root);
+        assert!(profiles.is_empty());
+        assert!(audit_log.iter().any(|log| log.action == "load_hive" && log.level == "ERROR"));
+
+        let domain = [21, 1111, 2222, 3333];
+        let mut account_v = vec![0u8; 0x40];
+        account_v.extend(sid(&domain, 5));
+        let member = sid(&[21, 1111, 2222, 3333, 1001], 5);
+        let sam = HiveBuilder::default()
+            .value("SAM\\Domains\\Account", "V", REG_BINARY, &account_v)
+            .value("SAM\\Domains\\Account\\Users\\000001F4", "F", REG_BINARY, &user_f(500, ACB_DISABLED | ACB_PASSWORD_NEVER_EXPIRES, 0))
+            .value("SAM\\Domains\\Account\\Users\\000001F4", "V", REG_BINARY, &user_v("Administrator"))
+            .value("SAM\\Domains\\Account\\Users\\000003E9", "F", REG_BINARY, &user_f(1001, 0, FILETIME))
+            .value("SAM\\Domains\\Account\\Users\\000003E9", "V", REG_BINARY, &user_v("backup_svc"))
+            .key("SAM\\Domains\\Account\\Users\\Names\\backup_svc", FILETIME)
+            .value("SAM\\Domains\\Builtin\\Aliases\\00000220", "C", REG_BINARY, &alias_c("Administratoren", &[member.clone()]))
+            .value("SAM\\Domains\\Builtin\\Aliases\\00000221", "C", REG_BINARY, &alias_c("Benutzer", &[member, sid(&[4], 5)]))
+            .build();
+        let software = HiveBuilder::default()
+            .string("Microsoft\\Windows NT\\CurrentVersion\\ProfileList\\S-1-5-21-1111-2222-3333-1001", "ProfileImagePath", "C:\\Users\\backup_svc")
+            .build();
+        std::fs::write(config.join("SAM"), sam).unwrap();
+        std::fs::write(config.join("SOFTWARE"), software).unwrap();
+
+        let (profiles, audit_log) = collect_local_accounts_offline(&root);
+        assert_eq!(profiles.len(), 2);
+        let admin = &profiles[0];
+        assert_eq!((admin.username.as_str(), admin.sid.as_str()), ("Administrator", "S-1-5-21-1111-2222-3333-500"));
+        assert_eq!(admin.account_flags, vec!["disabled", "password_never_expires"]);
+        assert_eq!((admin.last_logon.as_str(), admin.account_expires.as_str()), ("", ""));
+        assert!(admin.groups.is_empty());
+
+        let user = &profiles[1];
+        assert_eq!(user.sid, "S-1-5-21-1111-2222-3333-1001");
+        assert_eq!(user.last_logon, "2024-04-19T11:33:20+00:00");
+        assert_eq!(user.password_last_set, "2024-04-19T11:33:20+00:00");
+        assert_eq!(user.creation_time, "2024-04-19T11:33:20+00:00");
+        assert_eq!((user.logon_count, user.bad_password_count), (12, 3));
+        assert_eq!(user.groups, vec!["Administratoren", "Benutzer"]);
+        assert_eq!(user.privileged_groups, vec!["Administratoren"]);
+        assert_eq!(user.profile_path, "C:\\Users\\backup_svc");
+        assert!(audit_log.iter().any(|log| log.details.contains("2 local accounts (1 in privileged groups)")));
+    }
+
+    #[test]
+    fn test_privileged_group_and_flags() {
+        assert_eq!(privileged_group("S-1-5-32-544"), Some("Administrators"));
+        assert_eq!(privileged_group("S-1-5-32-545"), None);
+        assert_eq!(acb_flags(ACB_LOCKED_OUT | ACB_PASSWORD_NOT_REQUIRED | 0x0010), vec!["locked_out", "password_not_required"]);
+        assert_eq!(sid_to_string(&sid(&[32, 544], 5)).as_deref(), Some("S-1-5-32-544"));
+    }
+}
@@ -1,12 +1,20 @@
+use crate::forensic_types::ExternalCommand;
 use crate::types::{LogEntry, LogLevel};
 use std::sync::Mutex;
 use std::collections::VecDeque;
+use std::io;
+use std::process::{Command, Output};
+use std::time::{Duration, Instant};
+
+/// Callback seeing every entry as it is logged
+type LogListener = Box<dyn Fn(&LogEntry) + Send + Sync>;
 
 /// Global logger for collecting all log entries during scan execution
 pub struct Logger {
     entries: Mutex<VecDeque<LogEntry>>,
     verbose: bool,
     max_entries: usize,
+    listener: Option<LogListener>,
 }
 
 impl Logger {
@@ -16,9 +24,16 @@ impl Logger {
             entries: Mutex::new(VecDeque::new()),
             verbose,
             max_entries: 10000, // Limit memory usage
+            listener: None,
         }
     }
     
+    /// Forward every entry to a listener as well, e.g. the --progress channel
+    pub fn with_listener(mut self, listener: impl Fn(&LogEntry) + Send + Sync + 'static) -> Self {
+        self.listener = Some(Box::new(listener));
+        self
+    }
+    
     /// Log an info message
     pub fn info(&self, message: &str) {
         self.log(LogLevel::Info, message);
@@ -42,6 +57,9 @@ impl Logger {
         if self.verbose {
             eprintln!("[{}] {}: {}", entry.timestamp, entry.level, entry.message);
         }
+        if let Some(listener) = &self.listener {
+            listener(&entry);
+        }
         
         // Add to internal log collection
         if let Ok(mut entries) = self.entries.lock() {
@@ -151,6 +169,80 @@ impl LogSummary {
     }
 }
 
+/// Output kept per stream of an external command
+const MAX_COMMAND_OUTPUT: usize = 4096;
+
+/// External commands run since the last `take_external_commands`
+static EXTERNAL_COMMANDS: Mutex<Vec<ExternalCommand>> = Mutex::new(Vec::new());
+
+/// Run an external program to completion, recording the invocation, its duration,
+/// exit code and the start of its output for the collection audit. Every
+/// collector that spawns a process goes through here so reviewers can see
+/// exactly what touched the system.
+pub fn run_command(component: &str, command: &mut Command) -> io::Result<Output> {
+    let timestamp = chrono::Utc::now().to_rfc3339();
+    let started = Instant::now();
+    let result = command.output();
+    let record = command_record(component, command, &result, timestamp, started.elapsed());
+    if let Ok(mut commands) = EXTERNAL_COMMANDS.lock() {
+        commands.push(record);
+    }
+    result
+}
+
+/// The commands recorded so far, clearing the record
+pub fn take_external_commands() -> Vec<ExternalCommand> {
+    EXTERNAL_COMMANDS.lock().map(|mut commands| std::mem::take(&mut *commands)).unwrap_or_default()
+}
+
+/// Program and arguments as one line, quoting arguments with spaces
+pub fn command_line(record: &ExternalCommand) -> String {
+    std::iter::once(&record.program).chain(&record.arguments)
+        .map(|part| if part.contains(' ') { format!("\"{}\"", part) } else { part.clone() })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn command_record(component: &str, command: &Command, result: &io::Result<Output>, timestamp: String, duration: Duration) -> ExternalCommand {
+    let mut record = ExternalCommand {
+        timestamp,
+        component: component.to_string(),
+        program: command.get_program().to_string_lossy().to_string(),
+        arguments: command.get_args().map(|arg| arg.to_string_lossy().to_string()).collect(),
+        working_directory: command.get_current_dir().map(|dir| dir.display().to_string()),
+        duration_ms: duration.as_millis() as u64,
+        exit_code: None,
+        stdout: String::new(),
+        stderr: String::new(),
+        output_truncated: false,
+        error: None,
+    };
+    match result {
+        Ok(output) => {
+            let (stdout, stdout_truncated) = truncate_output(&output.stdout);
+            let (stderr, stderr_truncated) = truncate_output(&output.stderr);
+            record.exit_code = output.status.code();
+            record.stdout = stdout;
+            record.stderr = stderr;
+            record.output_truncated = stdout_truncated || stderr_truncated;
+        }
+        Err(e) => record.error = Some(e.to_string()),
+    }
+    record
+}
+
+fn truncate_output(bytes: &[u8]) -> (String, bool) {
+    let text = String::from_utf8_lossy(bytes);
+    if text.len() <= MAX_COMMAND_OUTPUT {
+        return (text.into_owned(), false);
+    }
+    let mut end = MAX_COMMAND_OUTPUT;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    (text[..end].to_string(), true)
+}
+
 /// Format a message with arguments
 fn format_message(format: &str, args: &[&dyn std::fmt::Display]) -> String {
     let mut result = format.to_string();
@@ -644,4 +736,20 @@ mod tests {
         assert_eq!(summary.warn_count, 1);  // One non-fatal error
         assert!(summary.success_rate() > 0.0); // Some operations succeeded
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_external_command_record() {
+        let mut command = Command::new("schtasks");
+        command.args(["/query", "/tn", "\\Microsoft\\Windows\\Defrag\\Scheduled Defrag"]);
+        let failed: io::Result<Output> = Err(io::Error::new(io::ErrorKind::NotFound, "program not found"));
+        let record = command_record("scheduled_tasks", &command, &failed, "2026-10-16T12:00:00Z".to_string(), Duration::from_millis(12));
+        assert_eq!(record.arguments.len(), 3);
+        assert_eq!((record.exit_code, record.duration_ms, record.error.as_deref()), (None, 12, Some("program not found")));
+        assert_eq!(command_line(&record), "schtasks /query /tn \"\\Microsoft\\Windows\\Defrag\\Scheduled Defrag\"");
+
+        let (text, truncated) = truncate_output("é".repeat(MAX_COMMAND_OUTPUT).as_bytes());
+        assert!(truncated);
+        assert_eq!(text.len(), MAX_COMMAND_OUTPUT);
+        assert_eq!(truncate_output(b"SUCCESS\r\n"), ("SUCCESS\r\n".to_string(), false));
+    }
+}
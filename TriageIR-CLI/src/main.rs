@@ -1,10 +1,8 @@
 use clap::{Arg, Command};
-use serde_json::json;
 use std::fs;
 use std::sync::Arc;
 use std::env;
-use std::path::PathBuf;
-use sysinfo::System;
+use std::path::{Path, PathBuf};
 
 mod types;
 mod processes;
@@ -12,10 +10,104 @@ mod system_info;
 mod network;
 mod persistence;
 mod event_logs;
+mod event_config;
+mod event_log_retention;
 mod logger;
 mod prefetch;
 mod shimcache;
 mod forensic_types;
+mod search_artifacts;
+mod evidence_package;
+mod offline;
+mod time_window;
+mod amcache;
+mod defender_logs;
+mod historical_processes;
+mod setupapi;
+mod notifications;
+mod clipboard_history;
+mod path_hijack;
+mod timestamp;
+mod export_profile;
+mod summary_report;
+mod worker;
+mod convert;
+mod remote_agent;
+mod upload;
+mod progress;
+mod cancellation;
+mod stream_writer;
+mod xpress;
+mod binutil;
+mod hive;
+mod local_accounts;
+mod security_configuration;
+mod defender_detections;
+mod powershell_history;
+mod sysmon_events;
+mod network_config;
+mod usb_devices;
+mod usn_journal;
+mod mft;
+mod name_resolution;
+mod certificate_stores;
+mod injection_scan;
+mod process_tokens;
+mod scan_profile;
+mod schema;
+mod scheduled_tasks;
+mod live_monitor;
+mod footprint;
+mod package_signing;
+mod package_volumes;
+mod custom_collectors;
+#[cfg(feature = "collector-recycle-bin")]
+mod recycle_bin;
+mod custom_commands;
+mod user_hives;
+mod search_history;
+mod ioc_export;
+mod ioc_match;
+mod process_tree;
+mod drivers;
+mod named_pipes;
+mod plugins;
+mod sessions;
+mod remote_access;
+mod scan_diff;
+mod diff_report;
+mod remediation;
+mod browser_artifacts;
+mod remediate;
+mod vss;
+mod collector_stats;
+mod srum;
+mod bam;
+mod scope_check;
+mod report_lang;
+mod user_execution;
+mod mru;
+mod module_load;
+mod screenshot;
+mod dll_hijack;
+mod network_logs;
+mod hashing;
+mod file_sweep;
+mod canaries;
+mod environment;
+mod file_collection;
+mod memory_dump;
+mod case_manifest;
+mod http_client;
+mod output_sinks;
+mod mmdb;
+mod enrichment;
+mod path_norm;
+mod suspicion;
+mod yaml;
+mod detections;
+mod attack;
+mod scan;
 
 #[cfg(test)]
 mod integration_tests;
@@ -26,53 +118,725 @@ mod comprehensive_tests;
 #[cfg(test)]
 mod performance_tests;
 
-use logger::{Logger, error_handling::{ForensicResult, ForensicError, handle_error_gracefully}};
-use types::{ScanResults, LogEntry};
+use logger::{Logger, error_handling::{ForensicResult, ForensicError}};
+use types::ScanResults;
+use forensic_types::{AuditEntry, CollectionStatistics, CollectorInfo, FileHash, ForensicEvidence};
+
+/// Scan options, accepted at the top level and by the `scan` subcommand
+fn scan_args() -> Vec<Arg> {
+    vec![
+        Arg::new("output")
+            .short('o')
+            .long("output")
+            .value_name("FILE")
+            .help("Output file for results (default: stdout)"),
+        Arg::new("verbose")
+            .short('v')
+            .long("verbose")
+            .action(clap::ArgAction::SetTrue)
+            .help("Enable verbose output with progress information"),
+        Arg::new("brief")
+            .long("brief")
+            .action(clap::ArgAction::SetTrue)
+            .conflicts_with("verbose")
+            .help("Print only a one-line JSON summary (host, findings, top severity, output location) to stdout; results go to --output or triageir-<scan_id>.json"),
+        Arg::new("progress")
+            .long("progress")
+            .value_name("FORMAT")
+            .value_parser(progress::PROGRESS_FORMATS)
+            .default_value("text")
+            .help("Progress reporting: text (--verbose lines) or json (newline-delimited events with phase, percent, artifact and error counters, and warnings and errors, for the GUI)"),
+        Arg::new("progress-to")
+            .long("progress-to")
+            .value_name("PATH")
+            .help("Write the --progress json events to this named pipe (\\\\.\\pipe\\NAME) or file instead of stderr"),
+        Arg::new("format")
+            .long("format")
+            .value_name("FORMAT")
+            .default_value("json")
+            .help("Output format (currently only 'json' is supported)")
+            .value_parser(["json"]),
+        Arg::new("password")
+            .long("password")
+            .value_name("PASSWORD")
+            .help("Write an AES-256 encrypted evidence package (with hash manifest and chain of custody) instead of plaintext JSON"),
+        Arg::new("receipt-dir")
+            .long("receipt-dir")
+            .value_name("DIR")
+            .help("After writing the results, hash the case folder (TRIAGEIR_OUTPUT_DIR in portable mode, else the output directory) and write the manifest and a signed receipt, read-only, to DIR off the collection media (default in portable mode: TRIAGEIR_RECEIPT_DIR); signed with TRIAGEIR_RECEIPT_KEY or --password"),
+        Arg::new("sinks")
+            .long("sinks")
+            .value_name("FILE")
+            .help("TOML file of additional output sinks delivered in order: json files, an evidence_package (needs --password), splunk_hec, webhook (the --brief summary) and upload; each outcome is recorded in the collection log"),
+        Arg::new("upload")
+            .long("upload")
+            .value_name("URL")
+            .action(clap::ArgAction::Append)
+            .help("After writing the evidence package (or --output), upload the written files to s3://bucket/prefix, azblob://account/container/prefix or smb://server/share/path in chunks with retries; credentials come from the AWS_* and AZURE_STORAGE_SAS_TOKEN variables, the outcome is recorded in the collection log"),
+        Arg::new("report-lang")
+            .long("report-lang")
+            .value_name("LANG")
+            .value_parser(report_lang::ReportLanguage::CODES)
+            .requires("password")
+            .help("Language of chain_of_custody.txt, README.txt and the collection summary in the evidence package (default: en)"),
+        Arg::new("compression")
+            .long("compression")
+            .value_name("METHOD")
+            .value_parser(evidence_package::PackageCompression::NAMES)
+            .requires("password")
+            .help("Compression of the evidence and attachments in the evidence package: zstd (fast on multi-GB collections, needs a zstd-capable unzip such as 7-Zip ZS or unzip with libzstd), deflate or store; the text documents are always deflated. Encrypted files hardly compress, so store is the fastest choice for them (default: deflate)"),
+        Arg::new("compression-level")
+            .long("compression-level")
+            .value_name("LEVEL")
+            .value_parser(clap::value_parser!(i32))
+            .allow_negative_numbers(true)
+            .requires("password")
+            .help("Level of --compression: zstd -7..=22 (default 3), deflate 0..=9 (default 6); store has none"),
+        Arg::new("max-package-size")
+            .long("max-package-size")
+            .value_name("MB")
+            .value_parser(clap::value_parser!(u64).range(1..))
+            .requires("password")
+            .help("Split an evidence package larger than MB MiB into volumes <archive>.zip.001, .002, ... with the volume manifest <archive>.zip.volumes.json (e.g. 4095 for FAT32 media); verify and extract accept the manifest or any volume"),
+        Arg::new("signing-cert")
+            .long("signing-cert")
+            .value_name("PFX|machine:THUMBPRINT")
+            .requires("password")
+            .help("Sign evidence.json and the evidence package with detached CMS/PKCS#7 signatures (SHA-256, certificate chain embedded): a PFX file, whose key is not persisted on the host, or machine:THUMBPRINT|SUBJECT for a certificate in LocalMachine\\My. Check with verify"),
+        Arg::new("signing-cert-password")
+            .long("signing-cert-password")
+            .value_name("PASSWORD")
+            .requires("signing-cert")
+            .help("Password of the --signing-cert PFX file (default: TRIAGEIR_SIGNING_PASSWORD)"),
+        Arg::new("export-profile")
+            .long("export-profile")
+            .value_name("PROFILE")
+            .value_parser(export_profile::PROFILES)
+            .requires("password")
+            .help("legal: court-oriented export that forces MD5+SHA-1+SHA-256 hashing, raw artifact files in the case folder, the encrypted evidence package with its chain of custody (--report-lang), the signed case receipt (--receipt-dir) with an RFC 3161 timestamp (--tsa-url) and a page-numbered report-<scan_id>.html ready to print to PDF"),
+        Arg::new("tsa-url")
+            .long("tsa-url")
+            .value_name("URL")
+            .help("RFC 3161 timestamp authority that timestamps the case manifest written with --receipt-dir (default: TRIAGEIR_TSA_URL); request and token are saved next to the receipt"),
+        Arg::new("report")
+            .long("report")
+            .value_name("FILE")
+            .help("Also write a Markdown executive summary for an incident ticket: case and host, artifact counts, the top suspicious artifacts with the indicators behind their score, high-severity findings, rule matches and collection quality"),
+        Arg::new("report-top")
+            .long("report-top")
+            .value_name("N")
+            .value_parser(clap::value_parser!(usize))
+            .requires("report")
+            .help("Suspicious artifacts listed in the --report summary (default: 10)"),
+        Arg::new("case-id")
+            .long("case-id")
+            .value_name("CASE_ID")
+            .help("Case identifier recorded in the evidence package (default: scan ID)"),
+        Arg::new("offline-root")
+            .long("offline-root")
+            .value_name("DRIVE_OR_MOUNT")
+            .help("Collect from a mounted Windows volume (e.g. E:\\) instead of the live system; live-only collectors are skipped"),
+        Arg::new("profile")
+            .long("profile")
+            .value_name("PROFILE")
+            .help("Scan profile: quick (volatile state, persistence and execution artifacts with tight limits), standard (every collector, the default), deep (every collector, all digests, the deep process scan, larger event limits) or a TOML file choosing collectors, limits, timeouts and output; command line options take precedence"),
+        Arg::new("since")
+            .long("since")
+            .value_name("TIMESTAMP")
+            .help("Only keep time-stamped artifacts (event logs, prefetch, shimcache, ...) at or after this time (RFC 3339 or YYYY-MM-DD, UTC)"),
+        Arg::new("until")
+            .long("until")
+            .value_name("TIMESTAMP")
+            .help("Only keep time-stamped artifacts at or before this time (RFC 3339 or YYYY-MM-DD, UTC)"),
+        Arg::new("event-channel")
+            .long("event-channel")
+            .value_name("CHANNEL[=XPATH]")
+            .action(clap::ArgAction::Append)
+            .help("Event log channel to collect, optionally with an XPath filter (repeatable; replaces the default channel set)"),
+        Arg::new("event-config")
+            .long("event-config")
+            .value_name("FILE")
+            .conflicts_with("event-channel")
+            .help("TOML profile of event log channels, event IDs, per-channel limits and time window (default: built-in profile)"),
+        Arg::new("raw-dir")
+            .long("raw-dir")
+            .value_name("DIR")
+            .help("Raw mode: copy supporting artifact files (search index, thumbnail caches) into DIR"),
+        Arg::new("plugins-dir")
+            .long("plugins-dir")
+            .value_name("DIR")
+            .help("Run the third-party collector plugins (DLLs implementing include/triageir_plugin.h) found in DIR"),
+        Arg::new("collectors-config")
+            .long("collectors-config")
+            .value_name("FILE")
+            .help("TOML file declaring external collector executables ([[external]] name, namespace, program, arguments); they get the scan context in TRIAGEIR_CONTEXT and their JSON output is stored under artifacts.custom_artifacts.<namespace>"),
+        Arg::new("vss")
+            .long("vss")
+            .value_name("MODE")
+            .value_parser(["existing", "create"])
+            .conflicts_with("offline-root")
+            .help("Read files locked on the live system (Amcache.hve, browser databases) from the newest existing shadow copy of the system volume, or from one created for the scan and deleted afterwards"),
+        Arg::new("hash-algorithms")
+            .long("hash-algorithms")
+            .value_name("LIST")
+            .help("Digests computed for executables of processes, drivers, services and persistence entries, e.g. md5,sha1,imphash (SHA-256 is always computed; each file is hashed once)"),
+        Arg::new("fuzzy-hash")
+            .long("fuzzy-hash")
+            .action(clap::ArgAction::SetTrue)
+            .help("Add the ssdeep fuzzy hash of process executables and suspicious persistence binaries, for matching against clusters of known malware variants"),
+        Arg::new("deep-process-scan")
+            .long("deep-process-scan")
+            .action(clap::ArgAction::SetTrue)
+            .conflicts_with("offline-root")
+            .help("Walk the memory and threads of every process and flag threads starting outside any loaded module and read-write-execute memory not backed by a file (signs of injected shellcode)"),
+        Arg::new("scan-paths")
+            .long("scan-paths")
+            .value_name("LIST")
+            .value_delimiter(',')
+            .conflicts_with("scope-check")
+            .help("Directories to sweep recursively, comma-separated with * and ? wildcards (e.g. \"C:\\Users\\*\\Downloads,C:\\ProgramData\"); files are hashed and matched against --ioc-list and --yara-rules"),
+        Arg::new("scan-extensions")
+            .long("scan-extensions")
+            .value_name("LIST")
+            .requires("scan-paths")
+            .help("File extensions swept by --scan-paths, comma-separated, or * for every file (default: executables, scripts, shortcuts and archives)"),
+        Arg::new("scan-max-size")
+            .long("scan-max-size")
+            .value_name("MB")
+            .value_parser(clap::value_parser!(u64))
+            .requires("scan-paths")
+            .help("Skip files larger than this during the --scan-paths sweep (default: 64)"),
+        Arg::new("scan-depth")
+            .long("scan-depth")
+            .value_name("N")
+            .value_parser(clap::value_parser!(usize))
+            .requires("scan-paths")
+            .help("Directory levels below each --scan-paths directory that are swept (default: 8)"),
+        Arg::new("yara-rules")
+            .long("yara-rules")
+            .value_name("FILE")
+            .requires("scan-paths")
+            .help("YARA rule file matched against the files swept by --scan-paths (requires a build with the yara-engine feature)"),
+        Arg::new("canaries")
+            .long("canaries")
+            .value_name("FILE")
+            .help("TOML list of decoy files, accounts and registry values whose presence and integrity is verified; missing, modified or used canaries are reported as tampering"),
+        Arg::new("screenshot")
+            .long("screenshot")
+            .action(clap::ArgAction::SetTrue)
+            .requires("password")
+            .conflicts_with("offline-root")
+            .help("Capture every monitor of the interactive desktop as PNG into the evidence package before collecting, documenting what was on screen (ransom notes, open consoles)"),
+        Arg::new("monitor")
+            .long("monitor")
+            .value_name("SECONDS")
+            .value_parser(clap::value_parser!(u64))
+            .conflicts_with("offline-root")
+            .help("Record process starts, DLL loads and new TCP connections through an ETW session for SECONDS from the start of the collection into live_activity; the collection waits for the window to end. Activity of the collector itself is flagged"),
+        Arg::new("verify-read-only")
+            .long("verify-read-only")
+            .action(clap::ArgAction::SetTrue)
+            .help("Check the collection_footprint (every file, directory and registry key of the examined system the collectors opened) for anything opened for write; a violation is logged as an error and sets read_only_verified to false (exit code 2)"),
+        Arg::new("collect-files")
+            .long("collect-files")
+            .value_name("LIST")
+            .value_delimiter(',')
+            .requires("password")
+            .help("Copy files into files/ of the evidence package, hashed and with their original timestamps in files/manifest.json: persistence (binaries of suspicious persistence entries), unsigned (executables of unsigned processes), prefetch, or file paths"),
+        Arg::new("collect-budget")
+            .long("collect-budget")
+            .value_name("MB")
+            .value_parser(clap::value_parser!(u64))
+            .requires("collect-files")
+            .help("Total size of the files copied by --collect-files (default: 256); files beyond it are listed in the manifest but not copied"),
+        Arg::new("collect-mft")
+            .long("collect-mft")
+            .value_name("FORMAT")
+            .value_parser(["raw", "csv"])
+            .requires("password")
+            .help("Read the $MFT of the system volume through direct volume access (offline: a $MFT copy at the root) into mft/ of the evidence package: raw as stored, or csv with the path and $STANDARD_INFORMATION/$FILE_NAME timestamps of every file record created or modified inside --since/--until"),
+        Arg::new("dump-process")
+            .long("dump-process")
+            .value_name("PID|auto")
+            .value_delimiter(',')
+            .requires("password")
+            .conflicts_with("offline-root")
+            .help("Write minidumps of these processes into memory/ of the evidence package; auto dumps processes whose executable matched --yara-rules in the scan path sweep and unsigned executables running from temp directories"),
+        Arg::new("dump-type")
+            .long("dump-type")
+            .value_name("TYPE")
+            .value_parser(["full", "targeted"])
+            .requires("dump-process")
+            .help("full: every committed page; targeted: private read/write memory, data sections, handles and threads only (default: full)"),
+        Arg::new("enrich")
+            .long("enrich")
+            .value_name("SOURCES")
+            .value_delimiter(',')
+            .help("Attach threat intelligence to public remote addresses (geolocation, AS, verdict) and process hashes (known-bad): csv:PATH and misp:PATH indicator feeds, mmdb:PATH MaxMind databases, virustotal (REST lookups, API key in VT_API_KEY)"),
+        Arg::new("detection-rules")
+            .long("detection-rules")
+            .value_name("DIR")
+            .help("Directory of Sigma-style YAML rules evaluated against the collected processes, persistence, network connections and event logs; matches are listed under detections with severity, artifact references and ATT&CK techniques"),
+        Arg::new("suspicion-weights")
+            .long("suspicion-weights")
+            .value_name("FILE")
+            .help("TOML file tuning the suspicion scoring of persistence mechanisms, scheduled tasks and processes: a threshold (default 40) and [weights] per indicator (0-100, 0 disables it)"),
+        Arg::new("collector-timeout")
+            .long("collector-timeout")
+            .value_name("SECS|COLLECTOR=SECS")
+            .action(clap::ArgAction::Append)
+            .help("Abandon a collector that runs longer than SECS (all collectors) or COLLECTOR=SECS (one, by its collector_stats name, e.g. event_logs=600); it is recorded as timed_out and the scan continues. Ctrl+C stops the scan and writes partial results with scan_interrupted (exit code 130)"),
+        Arg::new("max-memory-mb")
+            .long("max-memory-mb")
+            .value_name("MB")
+            .value_parser(clap::value_parser!(u64))
+            .help("Memory guard for large hosts: once resident memory reaches 90% of MB the remaining collectors are skipped, recorded as truncated with a warning, and collection_truncated is set"),
+        Arg::new("scope-check")
+            .long("scope-check")
+            .action(clap::ArgAction::SetTrue)
+            .conflicts_with_all(["offline-root", "vss", "password", "brief"])
+            .help("Quick look for incident scoping: only suspicious persistence, listening ports, services/tasks created in the last 7 days and IOC matches on running processes; prints a yes/no verdict in under 30 seconds (exit code 3 when suspicious)"),
+        Arg::new("ioc-list")
+            .long("ioc-list")
+            .value_name("FILE")
+            .help("Indicators (hashes, paths, IPs, domains, names; one per line) matched against the running processes by --scope-check, or against the files swept by --scan-paths"),
+    ]
+}
 
 fn main() {
     let matches = Command::new("triageir-cli")
         .version(env!("CARGO_PKG_VERSION"))
         .about("Digital Forensics Triage Tool for Windows Systems")
         .long_about("TriageIR-CLI is a forensically sound command-line tool for rapid evidence collection from live Windows systems. It collects system information, running processes, network connections, persistence mechanisms, event logs, and execution evidence.")
-        .arg(
-            Arg::new("output")
-                .short('o')
-                .long("output")
-                .value_name("FILE")
-                .help("Output file for results (default: stdout)")
+        .args(scan_args())
+        .args_conflicts_with_subcommands(true)
+        .subcommand(
+            Command::new("scan")
+                .about("Collect artifacts from this host or an offline volume (the default when no subcommand is given)")
+                .args(scan_args())
+        )
+        .subcommand(
+            Command::new("export-iocs")
+                .about("Extract indicators (hashes, paths, registry keys, IPs, domains, service/task names) from the flagged findings of a saved scan as CSV and STIX 2.1")
+                .arg(
+                    Arg::new("input")
+                        .value_name("SCAN_JSON")
+                        .required(true)
+                        .help("Scan results written with --output")
+                )
+                .arg(
+                    Arg::new("output-dir")
+                        .long("output-dir")
+                        .value_name("DIR")
+                        .default_value(".")
+                        .help("Directory for iocs-<scan_id>.csv and iocs-<scan_id>.stix.json")
+                )
+        )
+        .subcommand(
+            Command::new("remediation-script")
+                .about("Write a reviewable PowerShell script with the suggested remediation steps for the high-severity findings of a saved scan (never executed by the collector)")
+                .arg(
+                    Arg::new("input")
+                        .value_name("SCAN_JSON")
+                        .required(true)
+                        .help("Scan results written with --output")
+                )
+                .arg(
+                    Arg::new("output")
+                        .long("output")
+                        .short('o')
+                        .value_name("FILE")
+                        .default_value("remediation.ps1")
+                        .help("Script file to write; steps run in -WhatIf mode unless the script is started with -Apply")
+                )
+                .arg(
+                    Arg::new("plan")
+                        .long("plan")
+                        .value_name("FILE")
+                        .help("Also write the registry, task and service steps as a JSON plan for `remediate --plan` (edit it to keep only the approved steps)")
+                )
+        )
+        .subcommand(
+            Command::new("remediate")
+                .about("Execute an analyst-approved remediation plan (delete Run key values, disable scheduled tasks, stop or disable services) with before/after state capture")
+                .arg(
+                    Arg::new("plan")
+                        .long("plan")
+                        .value_name("PLAN_JSON")
+                        .required(true)
+                        .help("Plan written with `remediation-script --plan` and reviewed by an analyst")
+                )
+                .arg(
+                    Arg::new("confirm")
+                        .long("confirm")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Apply the plan; without it only the current state of every target is recorded")
+                )
+                .arg(
+                    Arg::new("output")
+                        .long("output")
+                        .short('o')
+                        .value_name("FILE")
+                        .default_value("remediation-results.json")
+                        .help("Results file with the before/after state of every step and the audit log")
+                )
+        )
+        .subcommand(
+            Command::new("verify-case")
+                .about("Check a case folder against the manifest and signed receipt written with --receipt-dir (modified, removed and added files)")
+                .arg(
+                    Arg::new("case-dir")
+                        .value_name("CASE_DIR")
+                        .required(true)
+                        .help("Case folder on the collection media")
+                )
+                .arg(
+                    Arg::new("manifest")
+                        .long("manifest")
+                        .value_name("FILE")
+                        .required(true)
+                        .help("manifest-<scan_id>.json from the receipt directory")
+                )
+                .arg(
+                    Arg::new("receipt")
+                        .long("receipt")
+                        .value_name("FILE")
+                        .required(true)
+                        .help("receipt-<scan_id>.json from the receipt directory")
+                )
+                .arg(
+                    Arg::new("key")
+                        .long("key")
+                        .value_name("KEY")
+                        .help("Receipt signing key (default: TRIAGEIR_RECEIPT_KEY)")
+                )
+        )
+        .subcommand(
+            Command::new("diff")
+                .about("Compare a baseline scan with a later scan of the same host: new and removed processes, persistence mechanisms, listening ports, drivers, services and scheduled tasks, and binaries whose hash changed")
+                .arg(
+                    Arg::new("baseline")
+                        .value_name("BASELINE_JSON")
+                        .required(true)
+                        .help("Earlier scan results written with --output")
+                )
+                .arg(
+                    Arg::new("current")
+                        .value_name("CURRENT_JSON")
+                        .required(true)
+                        .help("Later scan results written with --output")
+                )
+                .arg(
+                    Arg::new("html")
+                        .long("html")
+                        .value_name("FILE")
+                        .help("Write a color-coded HTML report of the differences to FILE")
+                )
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .value_name("FILE")
+                        .help("Write the differences as JSON to FILE (- for stdout, replacing the text listing)")
+                )
         )
-        .arg(
-            Arg::new("verbose")
-                .short('v')
-                .long("verbose")
-                .action(clap::ArgAction::SetTrue)
-                .help("Enable verbose output with progress information")
+        .subcommand(
+            Command::new("verify")
+                .about("Check an evidence package written with --password: the hashes in its integrity_verification.txt, the evidence against the output schema, the chain of custody document against the case metadata and, when signed with --signing-cert, its signatures")
+                .arg(
+                    Arg::new("package")
+                        .value_name("PACKAGE_ZIP")
+                        .required(true)
+                        .help("Evidence package (<case>_<timestamp>_evidence.zip)")
+                )
+                .arg(
+                    Arg::new("password")
+                        .long("password")
+                        .value_name("PASSWORD")
+                        .help("Package password, needed to check the hashes of the decrypted evidence")
+                )
         )
-        .arg(
-            Arg::new("format")
-                .long("format")
-                .value_name("FORMAT")
-                .default_value("json")
-                .help("Output format (currently only 'json' is supported)")
-                .value_parser(["json"])
+        .subcommand(
+            Command::new("extract")
+                .about("Verify an evidence package and extract it, decrypted, into a directory; prints the case metadata and chain of custody")
+                .arg(
+                    Arg::new("package")
+                        .value_name("PACKAGE_ZIP")
+                        .required(true)
+                        .help("Evidence package (<case>_<timestamp>_evidence.zip)")
+                )
+                .arg(
+                    Arg::new("password")
+                        .long("password")
+                        .value_name("PASSWORD")
+                        .help("Package password, needed for encrypted packages")
+                )
+                .arg(
+                    Arg::new("output-dir")
+                        .short('o')
+                        .long("output-dir")
+                        .value_name("DIR")
+                        .help("Extraction directory (default: the package name without .zip, next to it)")
+                )
         )
-        .arg(
-            Arg::new("password")
-                .long("password")
-                .value_name("PASSWORD")
-                .help("Password for encrypted output (future feature)")
+        .subcommand(
+            Command::new("report")
+                .about("Render a report from scan results written with --output, without rescanning the host")
+                .arg(
+                    Arg::new("results")
+                        .value_name("RESULTS_JSON")
+                        .required(true)
+                        .help("Scan results written with --output")
+                )
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .value_name("FORMAT")
+                        .default_value("markdown")
+                        .value_parser(["markdown", "html"])
+                        .help("markdown: executive summary as written by --report; html: print-ready report of the legal export profile")
+                )
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .value_name("FILE")
+                        .help("Report file (default: stdout)")
+                )
+                .arg(
+                    Arg::new("case-id")
+                        .long("case-id")
+                        .value_name("CASE_ID")
+                        .help("Case identifier shown in the report (default: the scan ID)")
+                )
+                .arg(
+                    Arg::new("top")
+                        .long("top")
+                        .value_name("N")
+                        .value_parser(clap::value_parser!(usize))
+                        .help("Suspicious artifacts listed in the markdown summary (default: 10)")
+                )
+        )
+        .subcommand(
+            Command::new("convert")
+                .about("Convert scan results between JSON, JSON Lines and CSV, one record per artifact")
+                .arg(
+                    Arg::new("input")
+                        .value_name("INPUT")
+                        .required(true)
+                        .help("Scan results as .json, .jsonl or .csv")
+                )
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .value_name("FILE")
+                        .help("Converted file (default: stdout)")
+                )
+                .arg(
+                    Arg::new("from")
+                        .long("from")
+                        .value_name("FORMAT")
+                        .value_parser(["json", "jsonl", "csv"])
+                        .help("Input format (default: from the input extension)")
+                )
+                .arg(
+                    Arg::new("to")
+                        .long("to")
+                        .value_name("FORMAT")
+                        .value_parser(["json", "jsonl", "csv"])
+                        .help("Output format (default: from the --output extension)")
+                )
+        )
+        .subcommand(
+            Command::new("validate")
+                .about("Check a scan results file against the output JSON Schema")
+                .arg(
+                    Arg::new("input")
+                        .value_name("FILE")
+                        .required(true)
+                        .help("Scan results (.json)")
+                )
+        )
+        .subcommand(
+            Command::new("schema")
+                .about("Print the JSON Schema of the scan results")
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .value_name("FILE")
+                        .help("Write the schema to a file (default: stdout)")
+                )
+        )
+        .subcommand(
+            Command::new("serve")
+                .about("Remote collection agent: accept scan requests from a console over mutually authenticated TLS and stream progress and results back (needs --features remote-agent)")
+                .arg(
+                    Arg::new("listen")
+                        .long("listen")
+                        .value_name("ADDRESS")
+                        .default_value(remote_agent::DEFAULT_LISTEN)
+                        .help("Address and port to listen on")
+                )
+                .arg(
+                    Arg::new("cert")
+                        .long("cert")
+                        .value_name("PEM")
+                        .required(true)
+                        .help("Certificate chain presented by the agent")
+                )
+                .arg(
+                    Arg::new("key")
+                        .long("key")
+                        .value_name("PEM")
+                        .required(true)
+                        .help("Private key of the agent certificate")
+                )
+                .arg(
+                    Arg::new("client-ca")
+                        .long("client-ca")
+                        .value_name("PEM")
+                        .required(true)
+                        .help("Certificate authorities of the console; clients without a certificate they issued are refused")
+                )
+        )
+        .subcommand(
+            Command::new("worker")
+                .about("Stay running for the GUI: read scan requests from stdin as JSON lines and keep rules, enrichment databases and the hash cache loaded between scans")
         )
         .get_matches();
+    
+    if let Some(("worker", _)) = matches.subcommand() {
+        let stdin = std::io::stdin();
+        if let Err(e) = worker::run(stdin.lock(), &mut std::io::stdout()) {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if let Some(("serve", serve_matches)) = matches.subcommand() {
+        let path = |name: &str| PathBuf::from(serve_matches.get_one::<String>(name).unwrap());
+        let options = remote_agent::ServeOptions {
+            listen: serve_matches.get_one::<String>("listen").unwrap().clone(),
+            cert: path("cert"),
+            key: path("key"),
+            client_ca: path("client-ca"),
+        };
+        if let Err(e) = remote_agent::serve(&options) {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if let Some(("export-iocs", export_matches)) = matches.subcommand() {
+        run_export_iocs(export_matches);
+        return;
+    }
+    if let Some(("remediation-script", script_matches)) = matches.subcommand() {
+        run_remediation_script(script_matches);
+        return;
+    }
+    if let Some(("remediate", remediate_matches)) = matches.subcommand() {
+        run_remediate(remediate_matches);
+        return;
+    }
+    if let Some(("diff", diff_matches)) = matches.subcommand() {
+        run_diff(diff_matches);
+        return;
+    }
+    if let Some(("verify-case", verify_matches)) = matches.subcommand() {
+        run_verify_case(verify_matches);
+        return;
+    }
+    if let Some(("verify", verify_matches)) = matches.subcommand() {
+        run_verify_package(verify_matches);
+        return;
+    }
+    if let Some(("extract", extract_matches)) = matches.subcommand() {
+        run_extract_package(extract_matches);
+        return;
+    }
+    if let Some(("report", report_matches)) = matches.subcommand() {
+        run_report(report_matches);
+        return;
+    }
+    if let Some(("convert", convert_matches)) = matches.subcommand() {
+        run_convert(convert_matches);
+        return;
+    }
+    if let Some(("validate", validate_matches)) = matches.subcommand() {
+        run_validate(validate_matches);
+        return;
+    }
+    if let Some(("schema", schema_matches)) = matches.subcommand() {
+        match schema_matches.get_one::<String>("output") {
+            Some(output_path) => match fs::write(output_path, schema::OUTPUT_SCHEMA) {
+                Ok(()) => println!("✓ Output schema {} written to {}", schema::SCHEMA_VERSION, output_path),
+                Err(e) => {
+                    eprintln!("✗ Error writing {}: {}", output_path, e);
+                    std::process::exit(1);
+                }
+            },
+            None => print!("{}", schema::OUTPUT_SCHEMA),
+        }
+        return;
+    }
+    
+    // `scan` and the bare top-level flags run the same collection
+    let matches = match matches.subcommand() {
+        Some(("scan", scan_matches)) => scan_matches.clone(),
+        _ => matches,
+    };
+
+    if matches.get_flag("scope-check") {
+        run_scope_check(&matches);
+        return;
+    }
 
-    let verbose = matches.get_flag("verbose");
+    // Output settings of the scan profile apply here, its collection settings to the scan config below
+    let scan_profile = matches.get_one::<String>("profile").map(|name| match scan_profile::ScanProfile::resolve(name) {
+        Ok(profile) => (name.clone(), profile),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    });
+    let brief = matches.get_flag("brief");
+    let verbose = matches.get_flag("verbose") || (!brief && scan_profile.as_ref().and_then(|(_, profile)| profile.output.verbose).unwrap_or(false));
     let output_file = matches.get_one::<String>("output");
     let format = matches.get_one::<String>("format").unwrap();
-    let _password = matches.get_one::<String>("password"); // For future use
+    let password = matches.get_one::<String>("password");
+    let case_id = matches.get_one::<String>("case-id");
+    let report_language = matches.get_one::<String>("report-lang")
+        .map(|code| report_lang::ReportLanguage::parse(code).unwrap_or_default())
+        .unwrap_or_default();
+    let max_package_size = matches.get_one::<u64>("max-package-size").map(|mb| mb * 1024 * 1024);
+    let package_compression = matches.get_one::<String>("compression")
+        .and_then(|name| evidence_package::PackageCompression::parse(name))
+        .unwrap_or_default();
+    let compression_level = matches.get_one::<i32>("compression-level").copied();
+    if let Err(e) = package_compression.check_level(compression_level) {
+        eprintln!("Error: --compression-level: {}", e);
+        std::process::exit(1);
+    }
+    let package_options = PackageOptions {
+        compression: package_compression,
+        level: compression_level,
+        max_size: max_package_size,
+        password: password.cloned().unwrap_or_default(),
+    };
     
     // Detect portable mode
     let portable_mode = env::var("TRIAGEIR_PORTABLE").is_ok();
     let usb_drive = env::var("TRIAGEIR_USB_DRIVE").ok();
     let portable_output_dir = env::var("TRIAGEIR_OUTPUT_DIR").ok();
+    let receipt_dir = matches.get_one::<String>("receipt-dir").cloned()
+        .or_else(|| env::var("TRIAGEIR_RECEIPT_DIR").ok().filter(|_| portable_mode));
+    let receipt_key = env::var(case_manifest::RECEIPT_KEY_VARIABLE).ok().or_else(|| password.cloned());
+    let legal_profile = matches.get_one::<String>("export-profile").is_some_and(|profile| profile == "legal");
+    let tsa_url = matches.get_one::<String>("tsa-url").cloned()
+        .or_else(|| env::var(timestamp::TSA_URL_VARIABLE).ok())
+        .filter(|url| !url.is_empty());
     
     // Validate format argument
     if format != "json" {
@@ -80,13 +844,166 @@ fn main() {
         std::process::exit(1);
     }
     
-    let logger = Arc::new(Logger::new(verbose));
-    let start_time = std::time::Instant::now();
+    if password.is_some_and(|p| p.is_empty()) {
+        eprintln!("Error: --password must not be empty");
+        std::process::exit(1);
+    }
     
-    // Initialize scan results with proper error handling
-    let hostname = std::env::var("COMPUTERNAME").unwrap_or_else(|_| "Unknown".to_string());
-    let os_version = System::os_version().unwrap_or_else(|| "Unknown".to_string());
-    let mut scan_results = ScanResults::new(hostname.clone(), os_version.clone());
+    let sink_config = match matches.get_one::<String>("sinks") {
+        Some(path) => match output_sinks::SinkConfig::load(Path::new(path)) {
+            Ok(sink_config) => sink_config,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        },
+        None => output_sinks::SinkConfig::default(),
+    };
+    // Opened before the scan, so a wrong certificate or password fails fast
+    let package_signer = match matches.get_one::<String>("signing-cert") {
+        Some(argument) => {
+            let signing_password = matches.get_one::<String>("signing-cert-password").cloned()
+                .or_else(|| env::var(package_signing::SIGNING_PASSWORD_VARIABLE).ok())
+                .unwrap_or_default();
+            match package_signing::CertificateSource::parse(argument).and_then(|source| package_signing::PackageSigner::load(&source, &signing_password)) {
+                Ok(signer) => Some(std::rc::Rc::new(signer)),
+                Err(e) => {
+                    eprintln!("Error: --signing-cert: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        None => None,
+    };
+    
+    if sink_config.has_evidence_package() && password.is_none() {
+        eprintln!("Error: the evidence_package sink needs --password");
+        std::process::exit(1);
+    }
+    let mut upload_sinks = Vec::new();
+    for url in matches.get_many::<String>("upload").unwrap_or_default() {
+        if let Err(e) = upload::UploadTarget::parse(url) {
+            eprintln!("Error: --upload: {}", e);
+            std::process::exit(1);
+        }
+        upload_sinks.push(output_sinks::Sink::Upload { url: url.clone() });
+    }
+    if !upload_sinks.is_empty() && output_file.is_none() && password.is_none() && !brief && !sink_config.has_local_output() {
+        eprintln!("Error: --upload sends the files a scan writes and needs --password or --output");
+        std::process::exit(1);
+    }
+    
+    if legal_profile {
+        if receipt_dir.is_none() {
+            eprintln!("Error: --export-profile legal needs --receipt-dir for the signed case receipt");
+            std::process::exit(1);
+        }
+        if tsa_url.is_none() {
+            eprintln!("Error: --export-profile legal needs a timestamp authority: --tsa-url or {}", timestamp::TSA_URL_VARIABLE);
+            std::process::exit(1);
+        }
+    }
+    if matches.contains_id("tsa-url") && receipt_dir.is_none() {
+        eprintln!("Error: --tsa-url timestamps the case manifest and needs --receipt-dir");
+        std::process::exit(1);
+    }
+    
+    if receipt_dir.is_some() {
+        if receipt_key.as_deref().unwrap_or_default().is_empty() {
+            eprintln!("Error: the case receipt needs a signing key: set {} or use --password", case_manifest::RECEIPT_KEY_VARIABLE);
+            std::process::exit(1);
+        }
+        if output_file.is_none() && password.is_none() && portable_output_dir.is_none() && !sink_config.has_local_output() {
+            eprintln!("Error: --receipt-dir needs results written to a case folder (--output or --password)");
+            std::process::exit(1);
+        }
+    }
+    
+    let mut config = scan::ScanConfig {
+        offline_root: matches.get_one::<String>("offline-root").cloned(),
+        since: matches.get_one::<String>("since").cloned(),
+        until: matches.get_one::<String>("until").cloned(),
+        event_channels: matches.get_many::<String>("event-channel").map(|a| a.cloned().collect()).unwrap_or_default(),
+        event_config: matches.get_one::<String>("event-config").cloned(),
+        raw_dir: matches.get_one::<String>("raw-dir").cloned(),
+        plugins_dir: matches.get_one::<String>("plugins-dir").cloned(),
+        collectors_config: matches.get_one::<String>("collectors-config").cloned(),
+        shadow_copy: matches.get_one::<String>("vss").cloned(),
+        hash_algorithms: matches.get_one::<String>("hash-algorithms").cloned(),
+        fuzzy_hash: matches.get_flag("fuzzy-hash"),
+        deep_process_scan: matches.get_flag("deep-process-scan"),
+        scan_paths: matches.get_many::<String>("scan-paths").map(|a| a.cloned().collect()).unwrap_or_default(),
+        scan_extensions: matches.get_one::<String>("scan-extensions").cloned(),
+        scan_max_size_mb: matches.get_one::<u64>("scan-max-size").copied(),
+        scan_depth: matches.get_one::<usize>("scan-depth").copied(),
+        ioc_list: matches.get_one::<String>("ioc-list").cloned(),
+        yara_rules: matches.get_one::<String>("yara-rules").cloned(),
+        canaries: matches.get_one::<String>("canaries").cloned(),
+        enrich: matches.get_many::<String>("enrich").map(|a| a.cloned().collect()).unwrap_or_default(),
+        detection_rules: matches.get_one::<String>("detection-rules").cloned(),
+        suspicion_weights: matches.get_one::<String>("suspicion-weights").cloned(),
+        collector_timeouts: matches.get_many::<String>("collector-timeout").map(|a| a.cloned().collect()).unwrap_or_default(),
+        max_memory_mb: matches.get_one::<u64>("max-memory-mb").copied(),
+        monitor_seconds: matches.get_one::<u64>("monitor").copied(),
+        verify_read_only: matches.get_flag("verify-read-only"),
+        screenshot: matches.get_flag("screenshot"),
+        collect_files: matches.get_many::<String>("collect-files").map(|a| a.cloned().collect()).unwrap_or_default(),
+        collect_budget_mb: matches.get_one::<u64>("collect-budget").copied(),
+        collect_mft: matches.get_one::<String>("collect-mft").cloned(),
+        dump_processes: matches.get_many::<String>("dump-process").map(|a| a.cloned().collect()).unwrap_or_default(),
+        dump_type: matches.get_one::<String>("dump-type").cloned(),
+        ..Default::default()
+    };
+    let scan_profile_settings = scan_profile.as_ref().map(|(_, profile)| profile.apply(&mut config)).unwrap_or_default();
+    // Raw files go to the case folder so the manifest covers them
+    let profile_settings = if legal_profile {
+        let case_directory = portable_output_dir.as_ref().filter(|_| portable_mode).map(PathBuf::from)
+            .or_else(|| output_file.and_then(|output| Path::new(output).parent().map(Path::to_path_buf)))
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .unwrap_or_else(|| PathBuf::from("."));
+        export_profile::apply_legal_profile(&mut config, &case_directory)
+    } else {
+        Vec::new()
+    };
+    let (scan_options, warnings) = match config.into_options() {
+        Ok(validated) => validated,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    for warning in &warnings {
+        eprintln!("Warning: {}", warning);
+    }
+    
+    let progress_channel = match (matches.get_one::<String>("progress").map(String::as_str), matches.get_one::<String>("progress-to")) {
+        (Some("json"), target) => match progress::ProgressChannel::open(target.map(String::as_str)) {
+            Ok(channel) => Some(Arc::new(channel)),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        },
+        (_, Some(_)) => {
+            eprintln!("Error: --progress-to needs --progress json");
+            std::process::exit(1);
+        }
+        _ => None,
+    };
+    // Log lines on stderr would interleave with the events there
+    let events_on_stderr = progress_channel.is_some() && !matches.contains_id("progress-to");
+    let logger = match &progress_channel {
+        Some(channel) => {
+            let channel = Arc::clone(channel);
+            Logger::new(verbose && !events_on_stderr).with_listener(move |entry| {
+                if let Some(event) = progress::log_event(entry) {
+                    channel.emit(&event);
+                }
+            })
+        }
+        None => Logger::new(verbose),
+    };
+    let logger = Arc::new(logger);
     
     let cli_version = env!("CARGO_PKG_VERSION");
     logger.info(&format!("TriageIR CLI v{} - Digital Forensics Triage Tool started", cli_version));
@@ -102,9 +1019,14 @@ fn main() {
         }
     }
     
-    logger.info(&format!("Target system: {}", hostname));
-    logger.info(&format!("OS Version: {}", os_version));
-    logger.info(&format!("Current user: {}", std::env::var("USERNAME").unwrap_or_else(|_| "Unknown".to_string())));
+    if let Some((name, _)) = &scan_profile {
+        for setting in &scan_profile_settings {
+            logger.info(&format!("Scan profile {}: {}", name, setting));
+        }
+    }
+    for setting in &profile_settings {
+        logger.info(&format!("Export profile legal: {}", setting));
+    }
     logger.info(&format!("Verbose mode: {}", verbose));
     logger.info(&format!("Output format: {}", format));
     
@@ -130,468 +1052,863 @@ fn main() {
         None
     };
 
+    if let Some(channel) = &progress_channel {
+        channel.emit(&serde_json::json!({ "event": "start", "version": cli_version, "offline": scan_options.offline_root.is_some() }));
+    }
     if verbose {
         println!("TriageIR CLI v{} - Digital Forensics Triage Tool", cli_version);
         println!("==================================================");
         println!("Starting forensic data collection...");
-        println!("Target system: {}", hostname);
-        println!("OS Version: {}", os_version);
-        println!("Current user: {}", std::env::var("USERNAME").unwrap_or_else(|_| "Unknown".to_string()));
-        println!("Scan ID: {}", scan_results.scan_metadata.scan_id);
-        println!();
-    }
-
-    // Initialize system information collector with error handling
-    if verbose {
-        println!("🔍 Collecting system information...");
     }
-    let system_info_result = collect_system_info_safe(&logger);
-    let system_info = match &system_info_result {
-        Some(info) => {
-            logger.info("System information collected successfully");
-            if verbose {
-                println!("✓ System information collected");
+    
+    // The bulk sections are spooled next to the output while the scan runs and copied into it afterwards
+    let spool_directory = final_output_file.as_ref().and_then(|f| Path::new(f).parent().map(Path::to_path_buf))
+        .filter(|p| !p.as_os_str().is_empty())
+        .or_else(|| portable_output_dir.as_ref().map(PathBuf::from))
+        .unwrap_or_else(|| PathBuf::from("."));
+    let spool_path = spool_directory.join(format!(".triageir-{}.spool", std::process::id()));
+    let stream = stream_writer::StreamWriter::create(&spool_path, stream_writer::CHANNEL_CAPACITY)
+        .inspect_err(|e| logger.warn(&format!("Failed to create {}, the results are kept in memory: {}", spool_path.display(), e)))
+        .ok();
+    
+    cancellation::install_interrupt_handler();
+    let scan::ScanOutcome { results: mut final_scan_results, mut scan_results, statistics, summary, total_artifacts, duration, log_summary, screenshots, collected_files, memory_dumps, interrupted } =
+        scan::run_scan(&scan_options, &logger, stream.as_ref(), &mut |update| {
+            if let Some(channel) = &progress_channel {
+                channel.emit(&progress::progress_event(update));
             }
-            info.clone()
-        }
-        None => {
-            logger.error("Failed to collect system information, using defaults");
             if verbose {
-                println!("⚠ System information collection failed, using defaults");
+                println!("{}", update.message);
             }
-            json!({
-                "hostname": hostname,
-                "os_name": "Unknown",
-                "os_version": "Unknown",
-                "architecture": std::env::consts::ARCH,
-                "current_user": "Unknown",
-                "uptime_hours": 0.0,
-                "last_boot_time": chrono::Utc::now().to_rfc3339(),
-                "total_memory": 0,
-                "used_memory": 0,
-                "cpu_count": 0,
-                "logged_on_users": []
-            })
+        });
+    let spool = match stream.map(stream_writer::StreamWriter::finish).transpose() {
+        Ok(spool) => spool.unwrap_or_default(),
+        Err(e) => {
+            logger.error(&format!("Failed to spool the scan results: {}", e));
+            eprintln!("✗ Error spooling the scan results: {}", e);
+            std::process::exit(1);
         }
     };
+    if let Some(profile) = matches.get_one::<String>("export-profile") {
+        final_scan_results["scan_metadata"]["export_profile"] = serde_json::Value::from(profile.as_str());
+        scan_results.scan_metadata.export_profile = Some(profile.clone());
+    }
     
-    // Collect running processes with comprehensive error handling
-    if verbose {
-        println!("🔍 Enumerating running processes...");
-    }
-    logger.info("Starting process enumeration");
-    let (processes_data, process_logs) = processes::collect_processes();
-    
-    // Add process logs to main logger
-    for log in &process_logs {
-        scan_results.add_log(log.clone());
-    }
-    
-    let processes = processes_data.into_iter().map(|p| {
-        json!({
-            "pid": p.pid,
-            "parent_pid": p.parent_pid,
-            "name": p.name,
-            "command_line": p.command_line,
-            "executable_path": p.executable_path,
-            "sha256_hash": p.sha256_hash,
-            "user": p.user,
-            "memory_usage_mb": p.memory_usage_mb,
-            "loaded_modules": p.loaded_modules.into_iter().map(|m| {
-                json!({
-                    "name": m.name,
-                    "file_path": m.file_path,
-                    "base_address": m.base_address,
-                    "size": m.size,
-                    "version": m.version,
-                    "is_system_module": m.is_system_module()
-                })
-            }).collect::<Vec<_>>()
+    // Brief mode never prints the results, so they need a file
+    let final_output_file = final_output_file.or_else(|| {
+        (brief && password.is_none() && !sink_config.has_local_output()).then(|| {
+            let file_name = format!("triageir-{}.json", scan_results.scan_metadata.scan_id);
+            match portable_output_dir.as_ref() {
+                Some(dir) => PathBuf::from(dir).join(file_name).to_string_lossy().to_string(),
+                None => file_name,
+            }
         })
-    }).collect::<Vec<_>>();
+    });
+    let mut output_location = final_output_file.clone().unwrap_or_default();
     
-    logger.info(&format!("Process enumeration completed: {} processes collected", processes.len()));
     if verbose {
-        println!("✓ Process enumeration completed ({} processes)", processes.len());
+        println!();
+        println!("📊 Collection Summary:");
+        println!("====================");
+        for line in &summary {
+            println!("{}", line);
+        }
+        println!();
+        
+        if log_summary.has_errors() {
+            println!("⚠ {} errors encountered during collection", log_summary.error_count);
+        }
+        if log_summary.has_warnings() {
+            println!("⚠ {} warnings generated during collection", log_summary.warn_count);
+        }
+        
+        println!("Scan completed in {:.2} seconds", duration.as_secs_f64());
+        println!("Total artifacts collected: {}", total_artifacts);
+        println!();
     }
     
-    // Collect network connections with error handling
-    if verbose {
-        println!("🔍 Analyzing network connections...");
-    }
-    logger.info("Starting network connection enumeration");
-    let (network_connections_data, network_logs) = network::collect_network_connections();
-    
-    // Add network logs to main logger
-    for log in &network_logs {
-        scan_results.add_log(log.clone());
-    }
-    
-    let network_connections = network_connections_data.into_iter().map(|conn| {
-        json!({
-            "protocol": conn.protocol,
-            "local_address": conn.local_address,
-            "local_port": conn.local_port,
-            "remote_address": conn.remote_address,
-            "remote_port": conn.remote_port,
-            "state": conn.state,
-            "owning_pid": conn.owning_pid,
-            "process_name": conn.process_name,
-            "is_external": conn.is_external()
-        })
-    }).collect::<Vec<_>>();
+    // Deliver to every sink in order: --password/--output first, then the --sinks configuration
+    let portable_path = |path: &str| match portable_output_dir.as_ref().filter(|_| portable_mode && Path::new(path).is_relative()) {
+        Some(portable_dir) => PathBuf::from(portable_dir).join(path),
+        None => PathBuf::from(path),
+    };
+    let mut sinks = Vec::new();
+    if password.is_some() {
+        if !sink_config.has_evidence_package() {
+            sinks.push(output_sinks::Sink::EvidencePackage { directory: None });
+        }
+    } else if let Some(output_file) = final_output_file.as_ref() {
+        sinks.push(output_sinks::Sink::Json { path: output_file.clone() });
+    }
+    sinks.extend(sink_config.sinks.iter().cloned());
+    sinks.extend(upload_sinks);
     
-    logger.info(&format!("Network enumeration completed: {} connections collected", network_connections.len()));
-    if verbose {
-        println!("✓ Network analysis completed ({} connections)", network_connections.len());
+    if verbose && !sinks.is_empty() {
+        println!("📝 Generating output...");
     }
     
-    // Collect persistence mechanisms with error handling
-    if verbose {
-        println!("🔍 Detecting persistence mechanisms...");
+    let mut package_contents = Some((screenshots, collected_files, memory_dumps));
+    let mut written_files = Vec::new();
+    let mut failed_sinks = 0;
+    for sink in &sinks {
+        let started = std::time::Instant::now();
+        let delivered = match sink {
+            output_sinks::Sink::EvidencePackage { directory } => {
+                // Encrypted evidence package goes next to the requested output file unless configured
+                let output_directory = directory.as_deref().map(portable_path)
+                    .or_else(|| final_output_file.as_ref().and_then(|f| PathBuf::from(f).parent().map(|p| p.to_path_buf())))
+                    .filter(|p| !p.as_os_str().is_empty())
+                    .or_else(|| portable_output_dir.as_ref().map(PathBuf::from))
+                    .unwrap_or_else(|| PathBuf::from("."));
+                let (screenshots, collected_files, memory_dumps) = package_contents.take().unwrap_or_default();
+                let evidence = build_package_evidence(&scan_results, case_id, statistics.clone(), &memory_dumps);
+                let attachments = screenshots.into_iter().map(|screen| (screen.screenshot.file_name, screen.png))
+                    .chain(collected_files)
+                    .chain(memory_dumps.into_iter().map(|dump| (dump.dump.file_name, dump.content)))
+                    .collect();
+                let mut json_output = Vec::new();
+                spool.write_document(&final_scan_results, &mut json_output).map_err(|e| e.into())
+                    .and_then(|_| String::from_utf8(json_output).map_err(|e| e.into()))
+                    .and_then(|json_output| write_evidence_package(&evidence, &json_output, attachments, &package_options, report_language, package_signer.clone(), &output_directory, &logger))
+                    .map(|package_path| {
+                        output_location = package_path.display().to_string();
+                        written_files.extend(package_volumes::package_files(&package_path, package_signer.is_some()));
+                        if verbose {
+                            println!("✓ Encrypted evidence package written to: {}", package_path.display());
+                        } else if !brief {
+                            eprintln!("Evidence package: {}", package_path.display());
+                        }
+                        format!("encrypted evidence package written: {}", package_path.display())
+                    })
+                    .map_err(|e| e.to_string())
+            }
+            output_sinks::Sink::Json { path } => {
+                let output_path = portable_path(path).to_string_lossy().to_string();
+                write_output_file(&output_path, &final_scan_results, &spool, &logger).map_err(|e| e.to_string())
+                    .map(|size| {
+                        if output_location.is_empty() {
+                            output_location = output_path.clone();
+                        }
+                        written_files.push(PathBuf::from(&output_path));
+                        if verbose {
+                            println!("✓ Results written to: {}", output_path);
+                            println!("File size: {} bytes", size);
+                        }
+                        format!("{} bytes written", size)
+                    })
+            }
+            output_sinks::Sink::Upload { .. } => {
+                output_sinks::deliver_upload(sink, &written_files, sink_config.timeout()).inspect(|details| {
+                    if verbose {
+                        println!("✓ Uploaded: {}", details);
+                    } else if !brief {
+                        eprintln!("Uploaded to {}", sink.target());
+                    }
+                })
+            }
+            remote => {
+                let summary = scan::brief_summary(&final_scan_results, &output_location, &log_summary);
+                spool.load(&final_scan_results).map_err(|e| format!("Failed to read the spooled results: {}", e))
+                    .and_then(|results| output_sinks::deliver_remote(remote, &results, &summary, sink_config.timeout())).inspect(|_| {
+                    if verbose {
+                        println!("✓ Results sent to {} sink: {}", remote.kind(), remote.target());
+                    }
+                })
+            }
+        };
+        
+        let entry = output_sinks::outcome_entry(sink, &delivered, started.elapsed());
+        match &delivered {
+            Ok(_) => logger.info(&format!("Output sink {}: {}", sink.kind(), entry.details)),
+            Err(e) => {
+                failed_sinks += 1;
+                logger.error(&format!("Output sink {} failed: {}", sink.kind(), entry.details));
+                eprintln!("✗ Error delivering to {} sink {}: {}", sink.kind(), sink.target(), e);
+            }
+        }
+        output_sinks::record_outcome(&mut final_scan_results, &mut scan_results, &entry);
     }
-    logger.info("Starting persistence mechanism detection");
-    let (persistence_mechanisms_data, persistence_logs) = persistence::collect_persistence_mechanisms();
     
-    // Add persistence logs to main logger
-    for log in &persistence_logs {
-        scan_results.add_log(log.clone());
+    if sinks.is_empty() {
+        // Output to stdout
+        if let Err(e) = spool.write_document(&final_scan_results, std::io::stdout().lock()) {
+            logger.error(&format!("Failed to write scan results: {}", e));
+            eprintln!("✗ Error writing results: {}", e);
+            drop(spool);
+            std::process::exit(1);
+        }
     }
+    drop(spool);
+    let report_completion = |status: &str| {
+        if let Some(channel) = &progress_channel {
+            channel.emit(&serde_json::json!({
+                "event": "complete",
+                "status": status,
+                "artifacts": total_artifacts,
+                "errors": log_summary.error_count,
+                "warnings": log_summary.warn_count,
+                "output": Some(&output_location).filter(|location| !location.is_empty()),
+                "duration_ms": duration.as_millis() as u64,
+            }));
+        }
+    };
+    if failed_sinks > 0 {
+        report_completion("output_failed");
+        eprintln!("✗ {} of {} output sinks failed", failed_sinks, sinks.len());
+        std::process::exit(1);
+    }
+
+    let case_directory = portable_output_dir.as_ref().filter(|_| portable_mode).map(PathBuf::from)
+        .or_else(|| Path::new(&output_location).parent().map(Path::to_path_buf))
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or_else(|| PathBuf::from("."));
     
-    let persistence_mechanisms = persistence_mechanisms_data.into_iter().map(|p| {
-        json!({
-            "type": p.mechanism_type,
-            "name": p.name,
-            "command": p.command,
-            "source": p.source,
-            "location": p.location,
-            "value": p.value,
-            "is_suspicious": p.is_suspicious
-        })
-    }).collect::<Vec<_>>();
+    // Print-ready report in the case folder, written before the manifest so it is covered
+    if legal_profile {
+        let case_label = case_id.cloned().unwrap_or_else(|| scan_results.scan_metadata.scan_id.clone());
+        let evidence_files: Vec<_> = Some(&output_location).filter(|location| !location.is_empty())
+            .and_then(|location| {
+                let size = fs::metadata(location).ok()?.len();
+                hashing::shared().hash_file(location).ok().map(|hashes| (location.clone(), size, hashes))
+            })
+            .into_iter()
+            .collect();
+        let report_path = case_directory.join(format!("report-{}.html", scan_results.scan_metadata.scan_id));
+        match fs::write(&report_path, export_profile::render_report(&final_scan_results, &case_label, &evidence_files)) {
+            Ok(()) => {
+                logger.info(&format!("Legal report written: {}", report_path.display()));
+                if verbose {
+                    println!("✓ Print-ready report written to: {}", report_path.display());
+                }
+            }
+            Err(e) => {
+                logger.error(&format!("Failed to write the legal report: {}", e));
+                eprintln!("✗ Error writing {}: {}", report_path.display(), e);
+                std::process::exit(1);
+            }
+        }
+    }
     
-    logger.info(&format!("Persistence detection completed: {} mechanisms found", persistence_mechanisms.len()));
-    if verbose {
-        println!("✓ Persistence detection completed ({} mechanisms)", persistence_mechanisms.len());
+    if let Some(report) = matches.get_one::<String>("report") {
+        let report_path = portable_path(report);
+        let case_label = case_id.cloned().unwrap_or_else(|| scan_results.scan_metadata.scan_id.clone());
+        let top = matches.get_one::<usize>("report-top").copied()
+            .or_else(|| scan_profile.as_ref().and_then(|(_, profile)| profile.output.report_top))
+            .unwrap_or(summary_report::DEFAULT_TOP_ARTIFACTS);
+        match fs::write(&report_path, summary_report::render_markdown(&final_scan_results, &case_label, top)) {
+            Ok(()) => {
+                logger.info(&format!("Summary report written: {}", report_path.display()));
+                if verbose {
+                    println!("✓ Summary report written to: {}", report_path.display());
+                }
+            }
+            Err(e) => {
+                logger.error(&format!("Failed to write the summary report: {}", e));
+                eprintln!("✗ Error writing {}: {}", report_path.display(), e);
+                std::process::exit(1);
+            }
+        }
     }
     
-    // Collect event logs with error handling
-    if verbose {
-        println!("🔍 Collecting event logs...");
+    // Hash the case folder and keep the manifest and its signed receipt off the collection media
+    if let (Some(receipt_dir), Some(key)) = (&receipt_dir, &receipt_key) {
+        let receipt = case_manifest::build_manifest(&case_directory, &scan_results.scan_metadata.scan_id)
+            .and_then(|manifest| case_manifest::write_receipt(&manifest, Path::new(receipt_dir), key).map(|paths| (manifest.files.len(), paths)));
+        match receipt {
+            Ok((file_count, (manifest_path, receipt_path))) => {
+                logger.info(&format!("Case manifest of {} ({} files) written: {}, receipt {}", case_directory.display(), file_count, manifest_path.display(), receipt_path.display()));
+                if verbose {
+                    println!("✓ Case manifest ({} files) and signed receipt written to: {}", file_count, receipt_dir);
+                }
+                if let Some(url) = &tsa_url {
+                    let token = fs::read(&manifest_path).map_err(|e| format!("Failed to read {}: {}", manifest_path.display(), e))
+                        .and_then(|manifest| timestamp::request_timestamp(url, &manifest))
+                        .and_then(|(request, token)| {
+                            case_manifest::write_timestamp(Path::new(receipt_dir), &scan_results.scan_metadata.scan_id, &request, &token.response)
+                                .map(|token_path| (token, token_path))
+                        });
+                    match token {
+                        Ok((token, token_path)) => {
+                            logger.info(&format!("Case manifest timestamped by {} at {} (serial {}): {}", url, token.gen_time, token.serial_number, token_path.display()));
+                            if verbose {
+                                println!("✓ Case manifest timestamped at {}: {}", token.gen_time, token_path.display());
+                            }
+                        }
+                        Err(e) => {
+                            logger.error(&format!("Failed to timestamp the case manifest: {}", e));
+                            eprintln!("✗ Error timestamping the case manifest: {}", e);
+                            std::process::exit(1);
+                        }
+                    }
+                }
+            }
+            Err(e) => {
+                logger.error(&format!("Failed to write the case manifest: {}", e));
+                eprintln!("✗ Error writing the case manifest: {}", e);
+                std::process::exit(1);
+            }
+        }
     }
-    logger.info("Starting event log collection");
-    let (event_logs_data, event_logs_collection_logs) = event_logs::collect_event_logs();
     
-    // Add event log collection logs to main logger
-    for log in &event_logs_collection_logs {
-        scan_results.add_log(log.clone());
+    if brief {
+        println!("{}", scan::brief_summary(&final_scan_results, &output_location, &log_summary));
+    } else if !sinks.is_empty() {
+        // Final status reporting (only if not outputting to stdout)
+        if verbose {
+            println!();
+            println!("🎉 Scan completed successfully!");
+            println!("Duration: {:.2} seconds", duration.as_secs_f64());
+            println!("Total artifacts: {}", total_artifacts);
+            println!("Collection logs: {} entries ({} errors, {} warnings)", 
+                log_summary.total_count, log_summary.error_count, log_summary.warn_count);
+        } else {
+            eprintln!("Scan completed in {:.2} seconds", duration.as_secs_f64());
+            eprintln!("Total artifacts collected: {}", total_artifacts);
+            if log_summary.has_errors() || log_summary.has_warnings() {
+                eprintln!("Collection completed with {} errors and {} warnings", 
+                    log_summary.error_count, log_summary.warn_count);
+            }
+        }
     }
     
-    let total_event_entries = event_logs_data.total_entries();
-    let event_logs = json!({
-        "security": event_logs_data.security.into_iter().map(|e| {
-            json!({
-                "event_id": e.event_id,
-                "level": e.level,
-                "timestamp": e.timestamp,
-                "message": e.message,
-                "source": e.source
-            })
-        }).collect::<Vec<_>>(),
-        "system": event_logs_data.system.into_iter().map(|e| {
-            json!({
-                "event_id": e.event_id,
-                "level": e.level,
-                "timestamp": e.timestamp,
-                "message": e.message,
-                "source": e.source
-            })
-        }).collect::<Vec<_>>(),
-        "application": event_logs_data.application.into_iter().map(|e| {
-            json!({
-                "event_id": e.event_id,
-                "level": e.level,
-                "timestamp": e.timestamp,
-                "message": e.message,
-                "source": e.source
-            })
-        }).collect::<Vec<_>>()
-    });
+    // Exit with appropriate code based on collection success
+    if interrupted {
+        report_completion("interrupted");
+        eprintln!("⚠ Scan interrupted: partial results were written");
+        std::process::exit(cancellation::EXIT_INTERRUPTED);
+    }
+    report_completion(if log_summary.has_errors() { "completed_with_errors" } else { "completed" });
+    if log_summary.error_count > 0 {
+        std::process::exit(2); // Partial success with errors
+    }
+}
+
+/// `--scope-check`: answer whether a host needs a full triage, fast
+fn run_scope_check(matches: &clap::ArgMatches) {
+    let indicators = match matches.get_one::<String>("ioc-list") {
+        Some(path) => scope_check::load_indicators(Path::new(path)).unwrap_or_else(|e| {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }),
+        None => Vec::new(),
+    };
+    if matches.get_flag("verbose") {
+        println!("Running scope check ({} indicators)...", indicators.len());
+    }
     
-    logger.info(&format!("Event log collection completed: {} entries collected", total_event_entries));
-    if verbose {
-        println!("✓ Event log collection completed ({} entries)", total_event_entries);
+    let report = scope_check::run_scope_check(&indicators);
+    let json_output = match serde_json::to_string_pretty(&report) {
+        Ok(json_output) => json_output,
+        Err(e) => {
+            eprintln!("✗ Error serializing results: {}", e);
+            std::process::exit(1);
+        }
+    };
+    match matches.get_one::<String>("output") {
+        Some(output) => {
+            if let Err(e) = fs::write(output, &json_output) {
+                eprintln!("✗ Error writing {}: {}", output, e);
+                std::process::exit(1);
+            }
+            eprintln!("Scope check: {} ({} findings) in {:.2} seconds", report.verdict, report.findings.len(), report.duration_ms as f64 / 1000.0);
+        }
+        None => println!("{}", json_output),
     }
     
-    // Collect execution evidence with error handling
-    if verbose {
-        println!("🔍 Collecting execution evidence...");
+    if report.verdict == "suspicious" {
+        std::process::exit(3);
+    }
+    if report.checks.iter().any(|check| check.status == "failed") {
+        std::process::exit(2); // Clean only as far as the checks that ran can tell
     }
-    logger.info("Starting execution evidence collection");
+}
+
+/// `export-iocs`: write the indicators of a saved scan as CSV and STIX 2.1
+fn run_export_iocs(matches: &clap::ArgMatches) {
+    let input = Path::new(matches.get_one::<String>("input").unwrap());
+    let output_dir = Path::new(matches.get_one::<String>("output-dir").unwrap());
     
-    // Collect Prefetch files
-    if verbose {
-        println!("  📁 Analyzing Prefetch files...");
-    }
-    let (prefetch_files_data, prefetch_logs) = prefetch::collect_prefetch_files();
-    
-    // Convert forensic audit entries to log entries
-    for audit_entry in &prefetch_logs {
-        let duration_str = audit_entry.duration_ms.map_or("N/A".to_string(), |d| d.to_string());
-        let log_entry = LogEntry::new(&audit_entry.level, &format!("[{}] {}: {} ({}ms)", 
-            audit_entry.component, audit_entry.action, audit_entry.details, duration_str));
-        scan_results.add_log(log_entry);
-    }
-    
-    let prefetch_files = prefetch_files_data.into_iter().map(|pf| {
-        json!({
-            "filename": pf.filename,
-            "executable_name": pf.executable_name,
-            "run_count": pf.run_count,
-            "last_run_time": pf.last_run_time,
-            "creation_time": pf.creation_time,
-            "file_size": pf.file_size,
-            "hash": pf.hash,
-            "version": pf.version,
-            "referenced_files": pf.referenced_files,
-            "volumes": pf.volumes.into_iter().map(|v| {
-                json!({
-                    "device_path": v.device_path,
-                    "volume_name": v.volume_name,
-                    "serial_number": v.serial_number,
-                    "creation_time": v.creation_time
-                })
-            }).collect::<Vec<_>>()
-        })
-    }).collect::<Vec<_>>();
+    let scan = match ioc_export::load_scan_results(input) {
+        Ok(scan) => scan,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
     
-    logger.info(&format!("Prefetch analysis completed: {} files analyzed", prefetch_files.len()));
-    if verbose {
-        println!("  ✓ Prefetch analysis completed ({} files)", prefetch_files.len());
+    let indicators = ioc_export::extract_indicators(&scan);
+    match ioc_export::write_ioc_package(&indicators, &scan, output_dir) {
+        Ok((csv_path, stix_path)) => {
+            println!("✓ Exported {} indicators", indicators.len());
+            println!("  CSV:  {}", csv_path.display());
+            println!("  STIX: {}", stix_path.display());
+        }
+        Err(e) => {
+            eprintln!("✗ Error exporting indicators: {}", e);
+            std::process::exit(1);
+        }
     }
+}
+
+/// `remediation-script`: write the suggested remediation steps of a saved scan as PowerShell
+fn run_remediation_script(matches: &clap::ArgMatches) {
+    let input = Path::new(matches.get_one::<String>("input").unwrap());
+    let output = matches.get_one::<String>("output").unwrap();
     
-    // Collect Shimcache entries
-    if verbose {
-        println!("  📁 Analyzing Shimcache entries...");
-    }
-    let (shimcache_entries_data, shimcache_logs) = shimcache::collect_shimcache_entries();
+    let scan = match ioc_export::load_scan_results(input) {
+        Ok(scan) => scan,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
     
-    // Convert forensic audit entries to log entries
-    for audit_entry in &shimcache_logs {
-        let duration_str = audit_entry.duration_ms.map_or("N/A".to_string(), |d| d.to_string());
-        let log_entry = LogEntry::new(&audit_entry.level, &format!("[{}] {}: {} ({}ms)", 
-            audit_entry.component, audit_entry.action, audit_entry.details, duration_str));
-        scan_results.add_log(log_entry);
+    // Older scans have no findings section; derive it from the artifacts
+    let findings = match serde_json::from_value::<Vec<forensic_types::Finding>>(scan["findings"].clone()) {
+        Ok(findings) => findings,
+        Err(_) => remediation::collect_findings(&scan),
+    };
+    let script = remediation::render_powershell_script(&findings, &scan);
+    match fs::write(output, script) {
+        Ok(()) => {
+            let steps: usize = findings.iter().map(|f| f.remediation.len()).sum();
+            println!("✓ Wrote {} remediation steps for {} findings to {}", steps, findings.len(), output);
+            println!("  Review the script, then run it with -Apply to make the changes");
+        }
+        Err(e) => {
+            eprintln!("✗ Error writing {}: {}", output, e);
+            std::process::exit(1);
+        }
     }
     
-    let shimcache_entries = shimcache_entries_data.into_iter().map(|sc| {
-        json!({
-            "path": sc.path,
-            "last_modified": sc.last_modified,
-            "file_size": sc.file_size,
-            "last_update": sc.last_update,
-            "execution_flag": sc.execution_flag
-        })
-    }).collect::<Vec<_>>();
+    if let Some(plan_path) = matches.get_one::<String>("plan") {
+        let plan = remediation::build_plan(&findings, &scan);
+        let written = serde_json::to_string_pretty(&plan)
+            .map_err(|e| e.to_string())
+            .and_then(|text| fs::write(plan_path, text).map_err(|e| e.to_string()));
+        match written {
+            Ok(()) => println!("✓ Wrote remediation plan to {} (review it before `remediate --plan {} --confirm`)", plan_path, plan_path),
+            Err(e) => {
+                eprintln!("✗ Error writing {}: {}", plan_path, e);
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+/// `remediate`: execute an analyst-approved remediation plan on this host
+fn run_remediate(matches: &clap::ArgMatches) {
+    let plan_path = Path::new(matches.get_one::<String>("plan").unwrap());
+    let confirm = matches.get_flag("confirm");
+    let output = matches.get_one::<String>("output").unwrap();
     
-    logger.info(&format!("Shimcache analysis completed: {} entries collected", shimcache_entries.len()));
-    if verbose {
-        println!("  ✓ Shimcache analysis completed ({} entries)", shimcache_entries.len());
-        println!("✓ Execution evidence collection completed");
+    let plan = match remediate::load_plan(plan_path) {
+        Ok(plan) => plan,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let hostname = env::var("COMPUTERNAME").ok().or_else(sysinfo::System::host_name).unwrap_or_default();
+    if let Err(e) = remediate::validate_plan(&plan, &hostname) {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
     }
     
-    let total_artifacts = processes.len() + network_connections.len() + persistence_mechanisms.len() + total_event_entries + prefetch_files.len() + shimcache_entries.len();
+    if !confirm {
+        println!("Dry run: recording the current state only (add --confirm to apply the plan)");
+    }
+    let started = chrono::Utc::now().to_rfc3339();
+    let (outcomes, audit_log) = remediate::execute_plan(&plan, confirm);
+    for outcome in &outcomes {
+        println!("  [{}] {} {} {}", outcome.status, outcome.action.action, outcome.action.target, outcome.action.value_name);
+        println!("      before: {}", outcome.before_state);
+        if confirm {
+            println!("      after:  {} ({})", outcome.after_state, outcome.message);
+        }
+    }
     
-    let duration = start_time.elapsed();
-    logger.info(&format!("Scan completed in {:.2} seconds", duration.as_secs_f64()));
-    logger.info(&format!("Total artifacts collected: {}", total_artifacts));
+    let results = serde_json::json!({
+        "plan": plan,
+        "hostname": hostname,
+        "confirmed": confirm,
+        "started_utc": started,
+        "outcomes": outcomes,
+        "audit_log": audit_log
+    });
+    let written = serde_json::to_string_pretty(&results)
+        .map_err(|e| e.to_string())
+        .and_then(|text| fs::write(output, text).map_err(|e| e.to_string()));
+    match written {
+        Ok(()) => println!("✓ Results and audit log written to {}", output),
+        Err(e) => {
+            eprintln!("✗ Error writing {}: {}", output, e);
+            std::process::exit(1);
+        }
+    }
     
-    // Get logger summary for final reporting
-    let log_summary = logger.get_summary();
-    logger.info(&format!("Collection summary - Total logs: {}, Errors: {}, Warnings: {}, Success rate: {:.1}%", 
-        log_summary.total_count, log_summary.error_count, log_summary.warn_count, log_summary.success_rate()));
+    if outcomes.iter().any(|o| o.status == "failed") {
+        std::process::exit(2); // Partial success with errors
+    }
+}
+
+fn run_diff(matches: &clap::ArgMatches) {
+    let load = |name: &str| {
+        let path = Path::new(matches.get_one::<String>(name).unwrap());
+        ioc_export::load_scan_results(path).unwrap_or_else(|e| {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        })
+    };
+    let baseline = load("baseline");
+    let current = load("current");
     
-    if verbose {
-        println!();
-        println!("📊 Collection Summary:");
-        println!("====================");
-        println!("✓ System information collected");
-        println!("✓ Running processes enumerated ({} processes)", processes.len());
-        println!("✓ Network connections analyzed ({} connections)", network_connections.len());
-        println!("✓ Persistence mechanisms detected ({} mechanisms)", persistence_mechanisms.len());
-        println!("✓ Event logs collected ({} entries)", total_event_entries);
-        println!("✓ Prefetch files analyzed ({} files)", prefetch_files.len());
-        println!("✓ Shimcache entries collected ({} entries)", shimcache_entries.len());
-        println!();
-        
-        if log_summary.has_errors() {
-            println!("⚠ {} errors encountered during collection", log_summary.error_count);
-        }
-        if log_summary.has_warnings() {
-            println!("⚠ {} warnings generated during collection", log_summary.warn_count);
+    let diff = scan_diff::diff_scans(&baseline, &current);
+    let json_output = matches.get_one::<String>("json");
+    if json_output.is_some_and(|path| path == "-") {
+        match serde_json::to_string_pretty(&diff) {
+            Ok(json) => println!("{}", json),
+            Err(e) => {
+                eprintln!("✗ Error serializing the differences: {}", e);
+                std::process::exit(1);
+            }
         }
-        
-        println!("Scan completed in {:.2} seconds", duration.as_secs_f64());
-        println!("Total artifacts collected: {}", total_artifacts);
-        println!();
+    } else {
+        print!("{}", diff_report::render_text(&diff, &baseline, &current));
     }
     
-    // Finalize scan results with proper metadata
-    scan_results.finalize_scan();
-    
-    // Add all logger entries to the scan results
-    for entry in logger.get_entries() {
-        scan_results.add_log(entry);
-    }
-    
-    // Create comprehensive scan results JSON according to design document schema
-    let final_scan_results = json!({
-        "scan_metadata": {
-            "scan_id": scan_results.scan_metadata.scan_id,
-            "scan_start_utc": scan_results.scan_metadata.scan_start_utc,
-            "scan_duration_ms": duration.as_millis() as u64,
-            "hostname": hostname,
-            "os_version": scan_results.scan_metadata.os_version,
-            "cli_version": scan_results.scan_metadata.cli_version,
-            "total_artifacts": total_artifacts,
-            "collection_summary": {
-                "total_logs": log_summary.total_count,
-                "error_count": log_summary.error_count,
-                "warning_count": log_summary.warn_count,
-                "success_rate": log_summary.success_rate()
+    if let Some(json_path) = json_output.filter(|path| *path != "-") {
+        let written = serde_json::to_string_pretty(&diff).map_err(|e| e.to_string())
+            .and_then(|json| fs::write(json_path, json).map_err(|e| e.to_string()));
+        match written {
+            Ok(()) => println!("✓ JSON differences written to {}", json_path),
+            Err(e) => {
+                eprintln!("✗ Error writing {}: {}", json_path, e);
+                std::process::exit(1);
             }
-        },
-        "artifacts": {
-            "system_info": system_info,
-            "running_processes": processes,
-            "network_connections": network_connections,
-            "persistence_mechanisms": persistence_mechanisms,
-            "event_logs": event_logs,
-            "execution_evidence": {
-                "prefetch_files": prefetch_files,
-                "shimcache_entries": shimcache_entries
+        }
+    }
+    if let Some(html_path) = matches.get_one::<String>("html") {
+        let html = diff_report::render_html(&diff, &baseline, &current);
+        match fs::write(html_path, html) {
+            Ok(()) if json_output.is_some_and(|path| path == "-") => eprintln!("✓ HTML report written to {}", html_path),
+            Ok(()) => println!("✓ HTML report written to {}", html_path),
+            Err(e) => {
+                eprintln!("✗ Error writing {}: {}", html_path, e);
+                std::process::exit(1);
             }
-        },
-        "collection_log": scan_results.collection_log.into_iter().map(|log| {
-            json!({
-                "timestamp": log.timestamp,
-                "level": log.level,
-                "message": log.message
-            })
-        }).collect::<Vec<_>>()
-    });
+        }
+    }
+}
 
-    // Output results with comprehensive error handling
-    if verbose {
-        println!("📝 Generating output...");
+/// `verify-case`: detect changes to a case folder since its manifest was written
+fn run_verify_case(matches: &clap::ArgMatches) {
+    let key = matches.get_one::<String>("key").cloned()
+        .or_else(|| env::var(case_manifest::RECEIPT_KEY_VARIABLE).ok())
+        .unwrap_or_else(|| {
+            eprintln!("Error: the receipt signing key is required: use --key or set {}", case_manifest::RECEIPT_KEY_VARIABLE);
+            std::process::exit(1);
+        });
+    let path = |name: &str| Path::new(matches.get_one::<String>(name).unwrap());
+    
+    match case_manifest::verify_case(path("manifest"), path("receipt"), path("case-dir"), &key) {
+        Ok(discrepancies) if discrepancies.is_empty() => println!("✓ Case folder matches its manifest and receipt"),
+        Ok(discrepancies) => {
+            println!("✗ Case folder differs from its manifest ({} discrepancies):", discrepancies.len());
+            for discrepancy in &discrepancies {
+                println!("  {}", discrepancy);
+            }
+            std::process::exit(3);
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
     }
+}
+
+/// `verify`: check an evidence package against its integrity manifest and signatures
+fn run_verify_package(matches: &clap::ArgMatches) {
+    let package = Path::new(matches.get_one::<String>("package").unwrap());
+    let password = matches.get_one::<String>("password").map(String::as_str).unwrap_or_default();
     
-    match serde_json::to_string_pretty(&final_scan_results) {
-        Ok(json_output) => {
-            if let Some(output_file) = output_file {
-                match write_output_file(output_file, &json_output, &logger) {
-                    Ok(_) => {
-                        logger.info(&format!("Results written to file: {}", output_file));
-                        if verbose {
-                            println!("✓ Results written to: {}", output_file);
-                            println!("File size: {} bytes", json_output.len());
+    let mut failed = match evidence_package::verify_evidence_package(package, password) {
+        Ok(discrepancies) if discrepancies.is_empty() => {
+            println!("✓ Evidence package matches its integrity manifest, the output schema and its chain of custody");
+            false
+        }
+        Ok(discrepancies) => {
+            println!("✗ Evidence package failed verification ({} discrepancies):", discrepancies.len());
+            for discrepancy in &discrepancies {
+                println!("  {}", discrepancy);
+            }
+            true
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    
+    match evidence_package::verify_package_signatures(package, password) {
+        Ok(checks) if checks.is_empty() => println!("- Evidence package is not signed"),
+        Ok(_) if !package_signing::VERIFY_SUPPORTED => println!("⚠ Signatures not checked: checking them requires Windows (CryptoAPI)"),
+        Ok(checks) => {
+            for (signature, outcome) in checks {
+                match outcome {
+                    Ok(check) => {
+                        println!("✓ {} is valid, signed by {} (thumbprint {})", signature, check.signer.subject, check.signer.thumbprint);
+                        if let Some(trust_error) = check.trust_error {
+                            println!("  ⚠ certificate chain not trusted on this machine: {}", trust_error.trim_end());
                         }
                     }
                     Err(e) => {
-                        logger.error(&format!("Failed to write output file: {}", e));
-                        eprintln!("✗ Error writing to file: {}", e);
-                        std::process::exit(1);
+                        println!("✗ {}: {}", signature, e);
+                        failed = true;
                     }
                 }
-            } else {
-                // Output to stdout
-                println!("{}", json_output);
             }
         }
         Err(e) => {
-            logger.error(&format!("Failed to serialize scan results: {}", e));
-            eprintln!("✗ Error serializing results: {}", e);
+            eprintln!("Error: {}", e);
             std::process::exit(1);
         }
     }
+    if failed {
+        std::process::exit(3);
+    }
+}
 
-    // Final status reporting (only if not outputting to stdout)
-    if output_file.is_some() {
-        if verbose {
-            println!();
-            println!("🎉 Scan completed successfully!");
-            println!("Duration: {:.2} seconds", duration.as_secs_f64());
-            println!("Total artifacts: {}", total_artifacts);
-            println!("Collection logs: {} entries ({} errors, {} warnings)", 
-                log_summary.total_count, log_summary.error_count, log_summary.warn_count);
-        } else {
-            eprintln!("Scan completed in {:.2} seconds", duration.as_secs_f64());
-            eprintln!("Total artifacts collected: {}", total_artifacts);
-            if log_summary.has_errors() || log_summary.has_warnings() {
-                eprintln!("Collection completed with {} errors and {} warnings", 
-                    log_summary.error_count, log_summary.warn_count);
-            }
-        }
+/// `extract`: verify an evidence package and extract it into a directory
+fn run_extract_package(matches: &clap::ArgMatches) {
+    let package = Path::new(matches.get_one::<String>("package").unwrap());
+    let password = matches.get_one::<String>("password").map(String::as_str).unwrap_or_default();
+    let output_directory = matches.get_one::<String>("output-dir")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| package.with_extension(""));
+    
+    let evidence = evidence_package::extract_evidence_from_package(package, password, &output_directory).unwrap_or_else(|e| {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    });
+    let metadata = &evidence.case_metadata;
+    println!("✓ Evidence package verified and extracted to {}", output_directory.display());
+    println!("  Case ID: {}", metadata.case_id);
+    println!("  Evidence ID: {}", metadata.evidence_id);
+    println!("  Collected: {} from {} ({})", metadata.collection_timestamp, metadata.target_system.hostname, metadata.collection_method);
+    println!("  Chain of custody:");
+    for (i, entry) in metadata.chain_of_custody.iter().enumerate() {
+        println!("    {}. {} - {} ({})", i + 1, entry.timestamp, entry.action, entry.person);
     }
+}
+
+/// `report`: render a summary or the print-ready report from existing results
+fn run_report(matches: &clap::ArgMatches) {
+    let results_path = matches.get_one::<String>("results").unwrap();
+    let scan = ioc_export::load_scan_results(Path::new(results_path)).unwrap_or_else(|e| {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    });
+    let case_label = matches.get_one::<String>("case-id").cloned()
+        .unwrap_or_else(|| scan["scan_metadata"]["scan_id"].as_str().unwrap_or_default().to_string());
     
-    // Exit with appropriate code based on collection success
-    if log_summary.error_count > 0 {
-        std::process::exit(2); // Partial success with errors
+    let report = if matches.get_one::<String>("format").unwrap() == "html" {
+        let evidence_files: Vec<_> = fs::metadata(results_path).ok()
+            .and_then(|metadata| {
+                let hashes = hashing::HashService::new(hashing::HashAlgorithm::parse_list("md5,sha1,sha256").unwrap_or_default());
+                hashes.hash_file(results_path).ok().map(|file_hashes| (results_path.clone(), metadata.len(), file_hashes))
+            })
+            .into_iter()
+            .collect();
+        export_profile::render_report(&scan, &case_label, &evidence_files)
+    } else {
+        let top = matches.get_one::<usize>("top").copied().unwrap_or(summary_report::DEFAULT_TOP_ARTIFACTS);
+        summary_report::render_markdown(&scan, &case_label, top)
+    };
+    
+    match matches.get_one::<String>("output") {
+        Some(report_path) => match fs::write(report_path, report) {
+            Ok(()) => println!("✓ Report written to {}", report_path),
+            Err(e) => {
+                eprintln!("✗ Error writing {}: {}", report_path, e);
+                std::process::exit(1);
+            }
+        },
+        None => print!("{}", report),
     }
 }
 
-/// Collect system information with comprehensive error handling
-fn collect_system_info_safe(logger: &Logger) -> Option<serde_json::Value> {
-    let operation = || -> ForensicResult<serde_json::Value> {
-        let mut sys = System::new_all();
-        sys.refresh_all();
-        
-        let hostname = std::env::var("COMPUTERNAME")
-            .map_err(|_| ForensicError::system_api_error("Failed to get hostname"))?;
-        let username = std::env::var("USERNAME")
-            .map_err(|_| ForensicError::system_api_error("Failed to get username"))?;
-        
-        let boot_time = System::boot_time();
-        let uptime = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .map_err(|_| ForensicError::system_api_error("Failed to calculate uptime"))?
-            .as_secs() - boot_time;
-        
-        Ok(json!({
-            "hostname": hostname,
-            "os_name": System::name().unwrap_or_else(|| "Windows_NT".to_string()),
-            "os_version": System::os_version().unwrap_or_else(|| "Unknown".to_string()),
-            "architecture": std::env::consts::ARCH,
-            "current_user": username,
-            "uptime_hours": (uptime as f64) / 3600.0,
-            "last_boot_time": chrono::DateTime::from_timestamp(boot_time as i64, 0)
-                .unwrap_or_else(|| chrono::Utc::now())
-                .to_rfc3339(),
-            "total_memory": sys.total_memory(),
-            "used_memory": sys.used_memory(),
-            "cpu_count": sys.cpus().len()
-        }))
+/// `convert`: scan results between JSON, JSON Lines and CSV
+fn run_convert(matches: &clap::ArgMatches) {
+    let input = Path::new(matches.get_one::<String>("input").unwrap());
+    let output = matches.get_one::<String>("output");
+    let format = |name: &str, path: Option<&Path>| match (matches.get_one::<String>(name), path) {
+        (Some(format), _) => convert::Format::parse(format),
+        (None, Some(path)) => convert::Format::from_path(path),
+        (None, None) => Err(format!("--{} is needed when writing to stdout", name)),
     };
+    let converted = format("from", Some(input))
+        .and_then(|from| Ok((from, format("to", output.map(Path::new))?)))
+        .and_then(|(from, to)| {
+            let text = fs::read_to_string(input).map_err(|e| format!("Failed to read {}: {}", input.display(), e))?;
+            convert::convert(&text, from, to).map_err(|e| format!("{}: {}", input.display(), e))
+        })
+        .unwrap_or_else(|e| {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        });
     
-    handle_error_gracefully(operation(), logger, "system_info_collection")
+    match output {
+        Some(output_path) => match fs::write(output_path, converted) {
+            Ok(()) => println!("✓ Converted results written to {}", output_path),
+            Err(e) => {
+                eprintln!("✗ Error writing {}: {}", output_path, e);
+                std::process::exit(1);
+            }
+        },
+        None => print!("{}", converted),
+    }
+}
+
+/// Validate a results file against the embedded output schema; exits 1 when it does not conform
+fn run_validate(matches: &clap::ArgMatches) {
+    let input = matches.get_one::<String>("input").unwrap();
+    let results: serde_json::Value = fs::read_to_string(input)
+        .map_err(|e| format!("Failed to read {}: {}", input, e))
+        .and_then(|text| serde_json::from_str(&text).map_err(|e| format!("{} is not valid JSON: {}", input, e)))
+        .unwrap_or_else(|e| {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        });
+
+    let errors = schema::validate_results(&results);
+    let version = results.pointer("/scan_metadata/schema_version").and_then(|v| v.as_str()).unwrap_or("1.0.0");
+    if errors.is_empty() {
+        println!("✓ {} conforms to the output schema {} (written with {})", input, schema::SCHEMA_VERSION, version);
+        return;
+    }
+    eprintln!("✗ {} does not conform to the output schema {} (written with {}):", input, schema::SCHEMA_VERSION, version);
+    for error in &errors {
+        eprintln!("  {}", error);
+    }
+    std::process::exit(1);
+}
+
+/// Build case metadata, chain of custody and collection audit for an evidence package
+fn build_package_evidence(scan_results: &ScanResults, case_id: Option<&String>, statistics: CollectionStatistics, memory_dumps: &[memory_dump::CapturedDump]) -> ForensicEvidence {
+    let metadata = &scan_results.scan_metadata;
+    let collector = std::env::var("USERNAME").unwrap_or_else(|_| "Unknown".to_string());
+    
+    let mut evidence = ForensicEvidence::new(
+        case_id.cloned().unwrap_or_else(|| metadata.scan_id.clone()),
+        CollectorInfo {
+            name: collector.clone(),
+            organization: "Unknown".to_string(),
+            contact: "Unknown".to_string(),
+            tool_version: metadata.cli_version.clone(),
+            collection_host: metadata.hostname.clone(),
+        },
+    );
+    
+    evidence.case_metadata.target_system.hostname = metadata.hostname.clone();
+    evidence.case_metadata.target_system.os_version = metadata.os_version.clone();
+    evidence.case_metadata.target_system.architecture = std::env::consts::ARCH.to_string();
+    if let Some(ref root) = metadata.offline_root {
+        evidence.case_metadata.collection_method = format!("Offline Volume Triage ({})", root);
+        evidence.case_metadata.target_system.architecture = "Unknown".to_string();
+    }
+    
+    evidence.collection_audit.collection_start = metadata.scan_start_utc.clone();
+    evidence.collection_audit.command_line = env::args().collect::<Vec<_>>().join(" ");
+    evidence.collection_audit.working_directory = env::current_dir()
+        .map(|d| d.display().to_string())
+        .unwrap_or_default();
+    evidence.collection_audit.collection_statistics = statistics;
+    evidence.collection_audit.audit_log = scan_results.collection_log.iter().map(|log| AuditEntry {
+        timestamp: log.timestamp.clone(),
+        level: log.level.clone(),
+        component: "triageir-cli".to_string(),
+        action: "collection_log".to_string(),
+        details: log.message.clone(),
+        duration_ms: None,
+        result: if log.level == "ERROR" { "failed" } else { "success" }.to_string(),
+    }).collect();
+    evidence.collection_audit.external_commands = scan_results.external_commands.clone();
+    
+    evidence.add_custody_entry(
+        "Evidence collected".to_string(),
+        collector.clone(),
+        "Unknown".to_string(),
+        format!("Live triage scan {} of {}", metadata.scan_id, metadata.hostname),
+    );
+    // Dumps are stored next to the results, so their digests belong to the integrity record
+    for dump in memory_dumps.iter().map(|captured| &captured.dump) {
+        evidence.integrity_verification.file_hashes.insert(dump.file_name.clone(), FileHash {
+            md5: dump.md5.clone(),
+            sha1: dump.sha1.clone(),
+            sha256: dump.sha256.clone(),
+            file_size: dump.size,
+            creation_time: dump.captured_at.clone(),
+            modification_time: dump.captured_at.clone(),
+        });
+    }
+    
+    evidence.add_custody_entry(
+        "Evidence packaged".to_string(),
+        collector,
+        "Unknown".to_string(),
+        "Scan results encrypted with AES-256 and sealed in evidence package".to_string(),
+    );
+    evidence.finalize();
+    
+    evidence
+}
+
+/// How the evidence package is sealed: --compression, --compression-level,
+/// --max-package-size and --password
+struct PackageOptions {
+    compression: evidence_package::PackageCompression,
+    level: Option<i32>,
+    max_size: Option<u64>, // Volume size in bytes
+    password: String,
+}
+
+/// Write scan results and attachments (screenshots) as an encrypted evidence package, returning the archive path
+#[allow(clippy::too_many_arguments)]
+fn write_evidence_package(
+    evidence: &ForensicEvidence,
+    json_output: &str,
+    attachments: Vec<(String, Vec<u8>)>,
+    options: &PackageOptions,
+    language: report_lang::ReportLanguage,
+    signer: Option<std::rc::Rc<package_signing::PackageSigner>>,
+    output_directory: &std::path::Path,
+    logger: &Logger,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    logger.info(&format!("Creating encrypted evidence package in: {}", output_directory.display()));
+    
+    let packager = evidence_package::EvidencePackager::new(
+        evidence.case_metadata.case_id.clone(),
+        output_directory.to_path_buf(),
+        options.password.clone(),
+    )?.with_language(language).with_attachments(attachments).with_signer(signer).with_max_volume_size(options.max_size)
+        .with_compression(options.compression, options.level)?;
+    let (package_path, audit_log) = packager.package_evidence_json(evidence, json_output)?;
+    
+    for entry in audit_log {
+        logger.info(&format!("[{}] {}: {}", entry.component, entry.action, entry.details));
+    }
+    
+    Ok(package_path)
 }
 
 /// Write output file with proper error handling and logging
-fn write_output_file(output_file: &str, content: &str, logger: &Logger) -> ForensicResult<()> {
+fn write_output_file(output_file: &str, results: &serde_json::Value, spool: &stream_writer::Spool, logger: &Logger) -> ForensicResult<u64> {
     logger.info(&format!("Writing output to file: {}", output_file));
     
-    // Validate file path and create parent directories if needed
+    // The spooled sections are copied in from disk; parent directories are created as needed
     let path = std::path::Path::new(output_file);
-    if let Some(parent) = path.parent() {
-        if !parent.as_os_str().is_empty() && !parent.exists() {
-            logger.info(&format!("Creating parent directory: {:?}", parent));
-            std::fs::create_dir_all(parent)
-                .map_err(|e| ForensicError::system_api_error(&format!("Failed to create parent directory: {}", e)))?;
-        }
-    }
-    
-    // Write file with error handling
-    fs::write(output_file, content)
+    let streamed_size = spool.write_file(results, path)
         .map_err(|e| ForensicError::system_api_error(&format!("Failed to write file: {}", e)))?;
     
     // Verify file was written correctly
@@ -599,15 +1916,10 @@ fn write_output_file(output_file: &str, content: &str, logger: &Logger) -> Foren
         .map_err(|e| ForensicError::system_api_error(&format!("Failed to verify file: {}", e)))?
         .len();
     
-    if written_size != content.len() as u64 {
+    if written_size != streamed_size {
         return Err(ForensicError::invalid_data("File size mismatch after write"));
     }
     
     logger.info(&format!("Successfully wrote {} bytes to {}", written_size, output_file));
-    Ok(())
+    Ok(written_size)
 }
-
-
-
-
-
@@ -0,0 +1,298 @@
+//! Process memory dumps
+//! Writes minidumps of the processes named with --dump-process into `memory/` of
+//! the --password evidence package. `auto` selects the processes whose
+//! executable matched a YARA rule of the scan path sweep, and unsigned
+//! executables running from a temp directory. Full dumps hold every committed
+//! page; targeted dumps keep the private read/write memory (heaps, stacks and
+//! injected code), data sections, handles and thread information, at a fraction
+//! of the size. Every dump is hashed and its digests are recorded in the
+//! integrity section of the package.
+
+// Dumps are written with dbghelp, which only exists on Windows
+#![cfg_attr(not(windows), allow(dead_code))]
+
+use crate::drivers;
+use crate::forensic_types::{AuditEntry, MemoryDump, SweptFile};
+use crate::path_norm;
+use crate::types::Process;
+use md5::Md5;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+
+#[cfg(windows)]
+use std::os::windows::io::AsRawHandle;
+#[cfg(windows)]
+use windows::{
+    Win32::Foundation::{CloseHandle, HANDLE},
+    Win32::System::Diagnostics::Debug::{
+        MiniDumpWithDataSegs, MiniDumpWithFullMemory, MiniDumpWithFullMemoryInfo, MiniDumpWithHandleData,
+        MiniDumpWithPrivateReadWriteMemory, MiniDumpWithThreadInfo, MiniDumpWithUnloadedModules, MiniDumpWriteDump, MINIDUMP_TYPE,
+    },
+    Win32::System::Threading::{OpenProcess, PROCESS_QUERY_INFORMATION, PROCESS_VM_READ},
+};
+
+const COMPONENT: &str = "memory_dump";
+
+/// Directory of the dumps inside the evidence package
+pub const PACKAGE_DIR: &str = "memory";
+
+/// Processes dumped by `auto` at most, most suspicious first
+const MAX_AUTO_DUMPS: usize = 10;
+
+/// Unsigned executables started from here are dumped by `auto`
+const TEMP_DIRECTORIES: [&str; 3] = ["\\temp\\", "\\tmp\\", "\\inetcache\\"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DumpTarget {
+    Pid(u32),
+    Auto,
+}
+
+/// Validated --dump-process settings
+#[derive(Debug, Clone)]
+pub struct DumpOptions {
+    pub targets: Vec<DumpTarget>,
+    pub full: bool, // Full memory, or the targeted regions only
+}
+
+impl DumpOptions {
+    /// Parse a `--dump-process` list of PIDs and `auto`, and the `--dump-type` (full or targeted)
+    pub fn parse(entries: &[String], dump_type: Option<&str>) -> Result<Self, String> {
+        let mut targets = Vec::new();
+        for entry in entries.iter().map(|entry| entry.trim()).filter(|entry| !entry.is_empty()) {
+            let target = if entry.eq_ignore_ascii_case("auto") {
+                DumpTarget::Auto
+            } else {
+                DumpTarget::Pid(entry.parse().map_err(|_| format!("Invalid process to dump '{}': expected a PID or auto", entry))?)
+            };
+            if !targets.contains(&target) {
+                targets.push(target);
+            }
+        }
+        if targets.is_empty() {
+            return Err("the process dump list is empty".to_string());
+        }
+        let full = match dump_type.unwrap_or("full").to_lowercase().as_str() {
+            "full" => true,
+            "targeted" => false,
+            other => return Err(format!("Invalid dump type '{}': expected full or targeted", other)),
+        };
+        Ok(DumpOptions { targets, full })
+    }
+}
+
+/// Dump metadata with the minidump, for the evidence package
+pub struct CapturedDump {
+    pub dump: MemoryDump,
+    pub content: Vec<u8>,
+}
+
+/// Dump the selected processes; `swept_files` supplies the YARA matches for `auto`
+pub fn dump_processes(options: &DumpOptions, processes: &[Process], swept_files: &[SweptFile]) -> (Vec<CapturedDump>, Vec<AuditEntry>) {
+    let start_time = std::time::Instant::now();
+    let mut audit_log = vec![AuditEntry::new(
+        COMPONENT,
+        "INFO",
+        "start_dump",
+        format!("Dumping process memory ({} dumps)", if options.full { "full" } else { "targeted" }),
+        "started",
+    )];
+
+    let (targets, selection_logs) = select_processes(options, processes, swept_files, drivers::file_signature_status);
+    audit_log.extend(selection_logs);
+
+    let mut dumps = Vec::new();
+    for (process, reason) in targets {
+        match write_dump(process.pid, options.full) {
+            Ok(content) => {
+                let file_name = format!("{}/{}_{}.dmp", PACKAGE_DIR, process.pid, sanitize(&process.name));
+                audit_log.push(AuditEntry::new(
+                    COMPONENT,
+                    "INFO",
+                    "write_dump",
+                    format!("Dumped {} (PID {}, {} bytes) into {}", process.name, process.pid, content.len(), file_name),
+                    "success",
+                ));
+                dumps.push(CapturedDump {
+                    dump: MemoryDump {
+                        file_name,
+                        pid: process.pid,
+                        process_name: process.name.clone(),
+                        executable_path: process.executable_path.clone(),
+                        dump_type: if options.full { "full" } else { "targeted" }.to_string(),
+                        reason: reason.to_string(),
+                        size: content.len() as u64,
+                        md5: hex::encode(Md5::digest(&content)),
+                        sha1: hex::encode(Sha1::digest(&content)),
+                        sha256: hex::encode(Sha256::digest(&content)),
+                        captured_at: chrono::Utc::now().to_rfc3339(),
+                    },
+                    content,
+                });
+            }
+            Err(e) => audit_log.push(AuditEntry::new(
+                COMPONENT,
+                "ERROR",
+                "write_dump",
+                format!("Failed to dump {} (PID {}): {}", process.name, process.pid, e),
+                "failed",
+            )),
+        }
+    }
+
+    audit_log.push(AuditEntry {
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        level: "INFO".to_string(),
+        component: "memory_dump".to_string(),
+        action: "complete_dump".to_string(),
+        details: format!("Dumped {} processes ({} bytes)", dumps.len(), dumps.iter().map(|d| d.dump.size).sum::<u64>()),
+        duration_ms: Some(start_time.elapsed().as_millis() as u64),
+        result: "success".to_string(),
+    });
+
+    (dumps, audit_log)
+}
+
+/// Processes to dump with the reason: requested, yara_match or unsigned_temp_path
+fn select_processes<'a>(
+    options: &DumpOptions,
+    processes: &'a [Process],
+    swept_files: &[SweptFile],
+    signature_status: impl Fn(&str) -> String,
+) -> (Vec<(&'a Process, &'static str)>, Vec<AuditEntry>) {
+    let mut audit_log = Vec::new();
+    let mut selected: Vec<(&Process, &'static str)> = Vec::new();
+    let own_pid = std::process::id();
+
+    for target in &options.targets {
+        if let DumpTarget::Pid(pid) = target {
+            match processes.iter().find(|p| p.pid == *pid) {
+                Some(_) if *pid == own_pid => {
+                    audit_log.push(AuditEntry::new(COMPONENT, "WARN", "select_process", "The collector does not dump itself".to_string(), "skipped"));
+                }
+                Some(process) => selected.push((process, "requested")),
+                None => audit_log.push(AuditEntry::new(COMPONENT, "WARN", "select_process", format!("PID {} is not running", pid), "skipped")),
+            }
+        }
+    }
+
+    if options.targets.contains(&DumpTarget::Auto) {
+        let yara_hits: HashSet<String> = swept_files
+            .iter()
+            .filter(|file| !file.yara_matches.is_empty())
+            .map(|file| file.path.to_lowercase())
+            .collect();
+        let candidates = processes.iter().filter(|p| p.pid != own_pid && p.has_executable_path());
+        let mut automatic: Vec<(&Process, &'static str)> = candidates
+            .clone()
+            .filter(|p| yara_hits.contains(&p.executable_path.to_lowercase()))
+            .map(|p| (p, "yara_match"))
+            .collect();
+        automatic.extend(
+            candidates
+                .filter(|p| in_temp_directory(&p.executable_path))
+                .filter(|p| matches!(signature_status(&p.executable_path).as_str(), "unsigned" | "invalid"))
+                .map(|p| (p, "unsigned_temp_path")),
+        );
+        automatic.retain(|(process, _)| !selected.iter().any(|(s, _)| s.pid == process.pid));
+        let mut seen = HashSet::new();
+        automatic.retain(|(process, _)| seen.insert(process.pid));
+        if automatic.len() > MAX_AUTO_DUMPS {
+            audit_log.push(AuditEntry::new(
+                COMPONENT,
+                "WARN",
+                "select_process",
+                format!("{} processes qualify for an automatic dump; only the first {} are dumped", automatic.len(), MAX_AUTO_DUMPS),
+                "partial",
+            ));
+            automatic.truncate(MAX_AUTO_DUMPS);
+        }
+        selected.extend(automatic);
+    }
+
+    (selected, audit_log)
+}
+
+fn in_temp_directory(path: &str) -> bool {
+    path_norm::in_directory(path, &TEMP_DIRECTORIES)
+}
+
+/// Process name made safe for an archive entry name
+fn sanitize(name: &str) -> String {
+    name.chars().map(|c| if c.is_ascii_alphanumeric() || "._-".contains(c) { c } else { '_' }).collect()
+}
+
+/// Write a minidump to a temporary file and read it back
+#[cfg(windows)]
+fn write_dump(pid: u32, full: bool) -> Result<Vec<u8>, String> {
+    let common = MiniDumpWithHandleData | MiniDumpWithFullMemoryInfo | MiniDumpWithThreadInfo | MiniDumpWithUnloadedModules;
+    let dump_type: MINIDUMP_TYPE = if full {
+        common | MiniDumpWithFullMemory
+    } else {
+        common | MiniDumpWithDataSegs | MiniDumpWithPrivateReadWriteMemory
+    };
+
+    let file = tempfile::NamedTempFile::new().map_err(|e| format!("Failed to create a temporary dump file: {}", e))?;
+    unsafe {
+        let process = OpenProcess(PROCESS_QUERY_INFORMATION | PROCESS_VM_READ, false, pid)
+            .map_err(|e| format!("OpenProcess failed: {}", e))?;
+        let result = MiniDumpWriteDump(process, pid, HANDLE(file.as_file().as_raw_handle() as isize), dump_type, None, None, None);
+        let _ = CloseHandle(process);
+        result.map_err(|e| format!("MiniDumpWriteDump failed: {}", e))?;
+    }
+    std::fs::read(file.path()).map_err(|e| format!("Failed to read the dump back: {}", e))
+}
+
+#[cfg(not(windows))]
+fn write_dump(_pid: u32, _full: bool) -> Result<Vec<u8>, String> {
+    Err("process memory dumps require Windows".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::forensic_types::{FileHashes, YaraMatch};
+
+    #[test]
+    fn test_parse_dump_options() {
+        let options = DumpOptions::parse(&["1234".to_string(), "AUTO".to_string(), "1234".to_string()], Some("targeted")).unwrap();
+        assert_eq!(options.targets, vec![DumpTarget::Pid(1234), DumpTarget::Auto]);
+        assert!(!options.full);
+        assert!(DumpOptions::parse(&["1234".to_string()], None).unwrap().full);
+        assert!(DumpOptions::parse(&["lsass".to_string()], None).is_err());
+        assert!(DumpOptions::parse(&["auto".to_string()], Some("mini")).is_err());
+    }
+
+    #[test]
+    fn test_select_processes() {
+        let processes = vec![
+            Process::new(100, 4, "explorer.exe".to_string(), String::new(), "C:\\Windows\\explorer.exe".to_string()),
+            Process::new(200, 100, "dropper.exe".to_string(), String::new(), "C:\\Users\\bob\\AppData\\Local\\Temp\\dropper.exe".to_string()),
+            Process::new(300, 100, "signed.exe".to_string(), String::new(), "C:\\Windows\\Temp\\signed.exe".to_string()),
+            Process::new(400, 100, "beacon.exe".to_string(), String::new(), "C:\\ProgramData\\beacon.exe".to_string()),
+        ];
+        let swept = vec![SweptFile {
+            path: "C:\\ProgramData\\BEACON.EXE".to_string(),
+            size: 1,
+            modified: None,
+            hashes: FileHashes::default(),
+            ioc_matches: Vec::new(),
+            yara_matches: vec![YaraMatch {
+                rule_name: "CobaltStrike".to_string(),
+                rule_file: "rules.yar".to_string(),
+                match_offset: 0,
+                match_length: 0,
+                match_data: String::new(),
+                metadata: Default::default(),
+            }],
+        }];
+        let signature = |path: &str| if path.ends_with("signed.exe") && !path.ends_with("unsigned.exe") { "signed" } else { "unsigned" }.to_string();
+
+        let options = DumpOptions { targets: vec![DumpTarget::Pid(100), DumpTarget::Pid(999), DumpTarget::Auto], full: false };
+        let (selected, logs) = select_processes(&options, &processes, &swept, signature);
+        let selected: Vec<(u32, &str)> = selected.iter().map(|(p, reason)| (p.pid, *reason)).collect();
+        assert_eq!(selected, [(100, "requested"), (400, "yara_match"), (200, "unsigned_temp_path")]);
+        assert!(logs.iter().any(|entry| entry.details.contains("PID 999")));
+    }
+}
@@ -0,0 +1,559 @@
+//! Master file table export (`--collect-mft raw|csv`)
+//! On a live system the $MFT is read through the system volume device: the
+//! NTFS boot sector locates its first record, whose $DATA runs are read
+//! cluster by cluster, bypassing the locks on the file. An offline root is
+//! expected to hold a copy at its top (triage collections copy it as
+//! C\$MFT). The raw table goes into the evidence package unchanged, since
+//! entry numbers are record offsets; the CSV lists every file record with
+//! its path and $STANDARD_INFORMATION and $FILE_NAME timestamps, limited to
+//! records created or modified inside the scan time window.
+
+// Raw volume access is only available on Windows
+#![cfg_attr(not(windows), allow(dead_code))]
+
+use crate::binutil::{filetime_to_string, read_u16, read_u32, read_u64};
+use crate::file_collection::PackageEntry;
+use crate::footprint;
+use crate::forensic_types::{AuditEntry, MftExport};
+use crate::ioc_export::csv_field;
+use crate::offline::OfflineRoot;
+use crate::time_window::TimeWindow;
+use std::collections::HashMap;
+use std::io::{Read, Seek, SeekFrom};
+
+const COMPONENT: &str = "mft";
+
+/// Evidence package directory of the export
+const PACKAGE_DIR: &str = "mft";
+/// $MFT copy of an offline volume
+const OFFLINE_MFT: &str = "C:\\$MFT";
+/// Larger tables are not exported; they are held in memory until packaged
+const MAX_MFT_SIZE: u64 = 4 * 1024 * 1024 * 1024;
+const READ_CHUNK_SIZE: u64 = 4 * 1024 * 1024;
+const SECTOR_SIZE: usize = 512;
+/// MFT entry of the volume root directory
+const ROOT_ENTRY: u64 = 5;
+const MAX_PATH_DEPTH: usize = 255;
+/// Low 48 bits of a file reference: the MFT entry number
+const MFT_ENTRY_MASK: u64 = 0x0000_FFFF_FFFF_FFFF;
+
+const ATTRIBUTE_STANDARD_INFORMATION: u32 = 0x10;
+const ATTRIBUTE_FILE_NAME: u32 = 0x30;
+const ATTRIBUTE_DATA: u32 = 0x80;
+const ATTRIBUTE_END: u32 = 0xFFFF_FFFF;
+const RECORD_IN_USE: u16 = 0x0001;
+const RECORD_DIRECTORY: u16 = 0x0002;
+const NAMESPACE_DOS: u8 = 2;
+
+const CSV_HEADER: &str = "entry,sequence,in_use,directory,parent_entry,path,size,si_created,si_modified,si_record_changed,si_accessed,fn_created,fn_modified,fn_record_changed,fn_accessed\n";
+
+/// Export format of `--collect-mft`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MftFormat {
+    /// The $MFT as stored on the volume
+    Raw,
+    /// One row per file record with its path and timestamps
+    Csv,
+}
+
+impl MftFormat {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.to_lowercase().as_str() {
+            "raw" => Ok(MftFormat::Raw),
+            "csv" => Ok(MftFormat::Csv),
+            other => Err(format!("invalid MFT format {} (expected raw or csv)", other)),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            MftFormat::Raw => "raw",
+            MftFormat::Csv => "csv",
+        }
+    }
+}
+
+/// A base FILE record
+#[derive(Debug, Clone, PartialEq)]
+struct MftRecord {
+    entry: u64,
+    sequence: u16,
+    in_use: bool,
+    directory: bool,
+    parent_entry: u64,
+    file_name: String,
+    size: u64,
+    standard_information: [u64; 4], // created, modified, record changed, accessed (FILETIME)
+    file_name_times: [u64; 4],
+}
+
+/// Export the $MFT of the system volume
+#[cfg(windows)]
+pub fn collect_mft(format: MftFormat, window: &TimeWindow) -> (MftExport, Vec<PackageEntry>, Vec<AuditEntry>) {
+    let volume = std::env::var("SystemDrive").unwrap_or_else(|_| "C:".to_string());
+    let device = format!("\\\\.\\{}", volume);
+    let read = footprint::open(&device).map_err(|e| e.to_string()).and_then(|mut volume| read_mft_from_volume(&mut volume));
+    export(&device, read, format, window)
+}
+
+#[cfg(not(windows))]
+pub fn collect_mft(format: MftFormat, window: &TimeWindow) -> (MftExport, Vec<PackageEntry>, Vec<AuditEntry>) {
+    export("\\\\.\\C:", Err("Reading the live $MFT requires Windows".to_string()), format, window)
+}
+
+/// Export the $MFT copy of an offline volume
+pub fn collect_mft_offline(root: &OfflineRoot, format: MftFormat, window: &TimeWindow) -> (MftExport, Vec<PackageEntry>, Vec<AuditEntry>) {
+    let path = root.resolve(OFFLINE_MFT);
+    let read = match std::fs::metadata(&path) {
+        Ok(metadata) if metadata.len() > MAX_MFT_SIZE => Err(format!("{} bytes exceed the {} byte limit", metadata.len(), MAX_MFT_SIZE)),
+        Ok(_) => footprint::read(&path).map_err(|e| e.to_string()),
+        Err(e) => Err(e.to_string()),
+    };
+    export(&path.display().to_string(), read, format, window)
+}
+
+fn export(source: &str, read: Result<Vec<u8>, String>, format: MftFormat, window: &TimeWindow) -> (MftExport, Vec<PackageEntry>, Vec<AuditEntry>) {
+    let start_time = std::time::Instant::now();
+    let mut audit_log = vec![AuditEntry::new(COMPONENT, "INFO", "start_collection", format!("Exporting the $MFT of {} as {}", source, format.name()), "started")];
+    let mut export = MftExport {
+        source: source.to_string(),
+        format: format.name().to_string(),
+        archive_path: None,
+        status: "failed".to_string(),
+        size: 0,
+        total_records: 0,
+        exported_records: 0,
+        details: None,
+    };
+
+    let table = match read {
+        Ok(table) => table,
+        Err(e) => {
+            audit_log.push(AuditEntry::new(COMPONENT, "ERROR", "read_mft", format!("Failed to read the $MFT of {}: {}", source, e), "failed"));
+            export.details = Some(e);
+            return (export, Vec::new(), audit_log);
+        }
+    };
+    export.size = table.len() as u64;
+    let records = parse_mft(&table);
+    export.total_records = records.iter().filter(|record| record.in_use).count();
+
+    let attachment = match format {
+        MftFormat::Raw => {
+            export.exported_records = export.total_records;
+            (format!("{}/$MFT", PACKAGE_DIR), table)
+        }
+        MftFormat::Csv => {
+            drop(table);
+            let (csv, rows) = to_csv(&records, window);
+            export.exported_records = rows;
+            (format!("{}/mft.csv", PACKAGE_DIR), csv.into_bytes())
+        }
+    };
+    export.archive_path = Some(attachment.0.clone());
+    export.status = "collected".to_string();
+
+    let mut complete = AuditEntry::new(
+        COMPONENT,
+        "INFO",
+        "complete_collection",
+        format!("Exported {} of {} file records ({} bytes of $MFT) to {}", export.exported_records, export.total_records, export.size, attachment.0),
+        "success",
+    );
+    complete.duration_ms = Some(start_time.elapsed().as_millis() as u64);
+    audit_log.push(complete);
+    (export, vec![attachment], audit_log)
+}
+
+/// Read the $MFT through a volume: boot sector, record 0, then its $DATA runs.
+/// Reads stay cluster aligned, as raw volume handles require.
+fn read_mft_from_volume<V: Read + Seek>(volume: &mut V) -> Result<Vec<u8>, String> {
+    let mut boot = [0u8; SECTOR_SIZE];
+    volume.read_exact(&mut boot).map_err(|e| format!("boot sector: {}", e))?;
+    if &boot[3..11] != b"NTFS    " {
+        return Err("not an NTFS volume".to_string());
+    }
+    let bytes_per_sector = read_u16(&boot, 0x0B).unwrap_or(0) as u64;
+    // Values above 0x80 are negative powers of two on volumes with clusters over 64 KiB
+    let sectors_per_cluster = match boot[0x0D] {
+        count @ 0..=0x80 => count as u64,
+        exponent => 1u64 << (256 - exponent as u32).min(31),
+    };
+    let cluster_size = bytes_per_sector * sectors_per_cluster;
+    let record_size = match boot[0x40] as i8 {
+        clusters @ 1.. => clusters as u64 * cluster_size,
+        exponent => 1u64 << (-(exponent as i32)).clamp(9, 16),
+    };
+    if cluster_size == 0 || record_size == 0 {
+        return Err("invalid NTFS boot sector geometry".to_string());
+    }
+    let mft_offset = read_u64(&boot, 0x30).unwrap_or(0).saturating_mul(cluster_size);
+
+    // Record 0 sits in the first cluster(s) of the table; read a whole cluster
+    let mut first = vec![0u8; record_size.max(cluster_size) as usize];
+    volume.seek(SeekFrom::Start(mft_offset)).and_then(|_| volume.read_exact(&mut first)).map_err(|e| format!("$MFT record 0: {}", e))?;
+    first.truncate(record_size as usize);
+    if !apply_fixups(&mut first) {
+        return Err("$MFT record 0 is corrupt".to_string());
+    }
+    let data = attributes(&first)
+        .into_iter()
+        .find(|attribute| read_u32(attribute, 0) == Some(ATTRIBUTE_DATA) && attribute.get(9) == Some(&0) && attribute.get(8) == Some(&1))
+        .ok_or("$MFT has no non-resident $DATA attribute in record 0")?;
+    let data_size = read_u64(data, 0x30).unwrap_or(0);
+    if data_size > MAX_MFT_SIZE {
+        return Err(format!("{} bytes exceed the {} byte limit", data_size, MAX_MFT_SIZE));
+    }
+    let runs_offset = read_u16(data, 0x20).unwrap_or(0) as usize;
+
+    let mut table = Vec::with_capacity(data_size as usize);
+    for (lcn, clusters) in data_runs(data.get(runs_offset..).unwrap_or_default()) {
+        let mut remaining = clusters.saturating_mul(cluster_size).min(data_size - table.len() as u64);
+        match lcn {
+            Some(lcn) => {
+                volume.seek(SeekFrom::Start(lcn * cluster_size)).map_err(|e| e.to_string())?;
+                while remaining > 0 {
+                    let chunk = READ_CHUNK_SIZE.min(remaining.div_ceil(cluster_size) * cluster_size);
+                    let start = table.len();
+                    table.resize(start + chunk as usize, 0);
+                    volume.read_exact(&mut table[start..]).map_err(|e| format!("$MFT data at cluster {}: {}", lcn, e))?;
+                    table.truncate(start + chunk.min(remaining) as usize);
+                    remaining -= chunk.min(remaining);
+                }
+            }
+            None => table.resize(table.len() + remaining as usize, 0),
+        }
+        if table.len() as u64 >= data_size {
+            break;
+        }
+    }
+    Ok(table)
+}
+
+/// Runs of a non-resident attribute: (starting cluster, clusters), no cluster for sparse runs
+fn data_runs(runs: &[u8]) -> Vec<(Option<u64>, u64)> {
+    let mut result = Vec::new();
+    let mut position = 0;
+    let mut lcn: i64 = 0;
+    while let Some(&header) = runs.get(position) {
+        let (length_size, offset_size) = ((header & 0x0F) as usize, (header >> 4) as usize);
+        if header == 0 || length_size > 8 || offset_size > 8 || position + 1 + length_size + offset_size > runs.len() {
+            break;
+        }
+        let field = &runs[position + 1..position + 1 + length_size + offset_size];
+        let length = field[..length_size].iter().rev().fold(0u64, |value, byte| (value << 8) | *byte as u64);
+        if offset_size == 0 {
+            result.push((None, length));
+        } else {
+            let offset_bytes = &field[length_size..];
+            let mut offset = offset_bytes.iter().rev().fold(0i64, |value, byte| (value << 8) | *byte as i64);
+            // Sign-extend the relative offset
+            if offset_bytes[offset_size - 1] & 0x80 != 0 && offset_size < 8 {
+                offset -= 1i64 << (offset_size * 8);
+            }
+            // A run list that overflows the cluster number is corrupt past this point
+            let Some(next) = lcn.checked_add(offset) else { break };
+            lcn = next;
+            result.push((Some(lcn.max(0) as u64), length));
+        }
+        position += 1 + length_size + offset_size;
+    }
+    result
+}
+
+/// Base file records of the table; the record size is taken from record 0
+fn parse_mft(table: &[u8]) -> Vec<MftRecord> {
+    let record_size = match read_u32(table, 0x1C) {
+        Some(size) if (SECTOR_SIZE as u32..=0x1_0000).contains(&size) && table.starts_with(b"FILE") => size as usize,
+        _ => 1024,
+    };
+    table
+        .chunks_exact(record_size)
+        .enumerate()
+        .filter_map(|(entry, record)| parse_record(entry as u64, &mut record.to_vec()))
+        .collect()
+}
+
+fn parse_record(entry: u64, record: &mut [u8]) -> Option<MftRecord> {
+    if !record.starts_with(b"FILE") || !apply_fixups(record) {
+        return None;
+    }
+    // Extension records continue a base record and carry no names of their own
+    if read_u64(record, 0x20).unwrap_or(0) & MFT_ENTRY_MASK != 0 {
+        return None;
+    }
+    let flags = read_u16(record, 0x16)?;
+    let mut parsed = MftRecord {
+        entry,
+        sequence: read_u16(record, 0x10)?,
+        in_use: flags & RECORD_IN_USE != 0,
+        directory: flags & RECORD_DIRECTORY != 0,
+        parent_entry: 0,
+        file_name: String::new(),
+        size: 0,
+        standard_information: [0; 4],
+        file_name_times: [0; 4],
+    };
+    let mut namespace = None;
+
+    for attribute in attributes(record) {
+        let kind = read_u32(attribute, 0).unwrap_or(0);
+        let non_resident = attribute.get(8) == Some(&1);
+        let named = attribute.get(9).is_some_and(|length| *length != 0);
+        if non_resident {
+            if kind == ATTRIBUTE_DATA && !named {
+                parsed.size = read_u64(attribute, 0x30).unwrap_or(0);
+            }
+            continue;
+        }
+        let value_length = read_u32(attribute, 0x10).unwrap_or(0) as usize;
+        let value_offset = read_u16(attribute, 0x14).unwrap_or(0) as usize;
+        let Some(value) = attribute.get(value_offset..value_offset + value_length) else { continue };
+        match kind {
+            ATTRIBUTE_STANDARD_INFORMATION => parsed.standard_information = timestamps(value, 0),
+            // Prefer the long name over the 8.3 one
+            ATTRIBUTE_FILE_NAME if namespace.is_none_or(|current| current == NAMESPACE_DOS) => {
+                let name_length = *value.get(64).unwrap_or(&0) as usize;
+                let Some(name) = value.get(66..66 + name_length * 2) else { continue };
+                let units: Vec<u16> = name.chunks_exact(2).map(|b| u16::from_le_bytes([b[0], b[1]])).collect();
+                parsed.parent_entry = read_u64(value, 0).unwrap_or(0) & MFT_ENTRY_MASK;
+                parsed.file_name = String::from_utf16_lossy(&units);
+                parsed.file_name_times = timestamps(value, 8);
+                namespace = value.get(65).copied();
+            }
+            ATTRIBUTE_DATA if !named => parsed.size = value_length as u64,
+            _ => {}
+        }
+    }
+    Some(parsed)
+}
+
+/// Attributes of a FILE record up to the end marker
+fn attributes(record: &[u8]) -> Vec<&[u8]> {
+    let mut result = Vec::new();
+    let mut position = read_u16(record, 0x14).unwrap_or(0) as usize;
+    while let Some(kind) = read_u32(record, position) {
+        let length = read_u32(record, position + 4).unwrap_or(0) as usize;
+        if kind == ATTRIBUTE_END || length < 16 || position + length > record.len() {
+            break;
+        }
+        result.push(&record[position..position + length]);
+        position += length;
+    }
+    result
+}
+
+/// Restore the last two bytes of every sector from the update sequence array;
+/// false when a sector was torn
+fn apply_fixups(record: &mut [u8]) -> bool {
+    let (Some(array_offset), Some(count)) = (read_u16(record, 4), read_u16(record, 6)) else { return false };
+    let (array_offset, count) = (array_offset as usize, count as usize);
+    if count == 0 || array_offset + count * 2 > record.len() || (count - 1) * SECTOR_SIZE > record.len() {
+        return false;
+    }
+    let check = [record[array_offset], record[array_offset + 1]];
+    for sector in 1..count {
+        let end = sector * SECTOR_SIZE - 2;
+        if record[end..end + 2] != check {
+            return false;
+        }
+        let original = array_offset + sector * 2;
+        record.copy_within(original..original + 2, end);
+    }
+    true
+}
+
+/// Four FILETIMEs: created, modified, record changed, accessed
+fn timestamps(value: &[u8], offset: usize) -> [u64; 4] {
+    [0, 1, 2, 3].map(|index| read_u64(value, offset + index * 8).unwrap_or(0))
+}
+
+/// CSV of the records in use, with their full paths; returns it with its row count
+fn to_csv(records: &[MftRecord], window: &TimeWindow) -> (String, usize) {
+    let names: HashMap<u64, (u64, &str)> = records.iter().filter(|r| r.in_use).map(|r| (r.entry, (r.parent_entry, r.file_name.as_str()))).collect();
+    let mut csv = String::from(CSV_HEADER);
+    let mut rows = 0;
+    for record in records.iter().filter(|record| record.in_use && in_window(record, window)) {
+        let times: Vec<String> = record.standard_information.iter().chain(&record.file_name_times).map(|time| filetime_to_string(*time)).collect();
+        let fields = [
+            record.entry.to_string(),
+            record.sequence.to_string(),
+            record.in_use.to_string(),
+            record.directory.to_string(),
+            record.parent_entry.to_string(),
+            csv_field(&full_path(record.entry, &names)),
+            record.size.to_string(),
+        ];
+        csv.push_str(&fields.iter().chain(&times).cloned().collect::<Vec<_>>().join(","));
+        csv.push('\n');
+        rows += 1;
+    }
+    (csv, rows)
+}
+
+/// Created, modified or changed inside the window; the $FILE_NAME creation
+/// time counts as well because timestomping tools rarely touch it
+fn in_window(record: &MftRecord, window: &TimeWindow) -> bool {
+    window.is_unbounded()
+        || record.standard_information[..3]
+            .iter()
+            .chain(&record.file_name_times[..1])
+            .any(|time| *time != 0 && window.contains(&filetime_to_string(*time)))
+}
+
+/// `C:\dir\file` from the parent entries; unresolvable parents are marked
+fn full_path(entry: u64, names: &HashMap<u64, (u64, &str)>) -> String {
+    let mut components = Vec::new();
+    let mut current = entry;
+    while current != ROOT_ENTRY {
+        let Some((parent, name)) = names.get(&current) else {
+            components.push("<orphan>");
+            break;
+        };
+        if components.len() >= MAX_PATH_DEPTH || *parent == current {
+            components.push("<loop>");
+            break;
+        }
+        components.push(name);
+        current = *parent;
+    }
+    components.reverse();
+    format!("C:\\{}", components.join("\\"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const RECORD_SIZE: usize = 1024;
+    // 2024-05-01T10:00:00Z and 2020-01-01T00:00:00Z
+    const RECENT: u64 = 133_590_312_000_000_000;
+    const OLD: u64 = 132_223_104_000_000_000;
+
+    fn resident(kind: u32, value: &[u8]) -> Vec<u8> {
+        let length = (24 + value.len()).div_ceil(8) * 8;
+        let mut attribute = vec![0u8; length];
+        attribute[0..4].copy_from_slice(&kind.to_le_bytes());
+        attribute[4..8].copy_from_slice(&(length as u32).to_le_bytes());
+        attribute[0x10..0x14].copy_from_slice(&(value.len() as u32).to_le_bytes());
+        attribute[0x14..0x16].copy_from_slice(&24u16.to_le_bytes());
+        attribute[24..24 + value.len()].copy_from_slice(value);
+        attribute
+    }
+
+    fn file_name(parent: u64, name: &str, namespace: u8, time: u64) -> Vec<u8> {
+        let units: Vec<u16> = name.encode_utf16().collect();
+        let mut value = vec![0u8; 66];
+        value[0..8].copy_from_slice(&parent.to_le_bytes());
+        for index in 0..4 {
+            value[8 + index * 8..16 + index * 8].copy_from_slice(&time.to_le_bytes());
+        }
+        value[64] = units.len() as u8;
+        value[65] = namespace;
+        value.extend(units.iter().flat_map(|unit| unit.to_le_bytes()));
+        resident(ATTRIBUTE_FILE_NAME, &value)
+    }
+
+    /// A FILE record with its sector ends protected by the update sequence array
+    fn record(flags: u16, attributes: &[Vec<u8>]) -> Vec<u8> {
+        let mut record = vec![0u8; RECORD_SIZE];
+        record[0..4].copy_from_slice(b"FILE");
+        record[4..6].copy_from_slice(&0x30u16.to_le_bytes());
+        record[6..8].copy_from_slice(&3u16.to_le_bytes());
+        record[0x10..0x12].copy_from_slice(&1u16.to_le_bytes());
+        record[0x14..0x16].copy_from_slice(&0x38u16.to_le_bytes());
+        record[0x16..0x18].copy_from_slice(&flags.to_le_bytes());
+        record[0x1C..0x20].copy_from_slice(&(RECORD_SIZE as u32).to_le_bytes());
+        let mut position = 0x38;
+        for attribute in attributes {
+            record[position..position + attribute.len()].copy_from_slice(attribute);
+            position += attribute.len();
+        }
+        record[position..position + 4].copy_from_slice(&ATTRIBUTE_END.to_le_bytes());
+        record[0x30..0x32].copy_from_slice(&[0xAB, 0xCD]);
+        for sector in 1..3 {
+            let end = sector * SECTOR_SIZE - 2;
+            let saved = [record[end], record[end + 1]];
+            record[0x30 + sector * 2..0x32 + sector * 2].copy_from_slice(&saved);
+            record[end..end + 2].copy_from_slice(&[0xAB, 0xCD]);
+        }
+        record
+    }
+
+    fn standard_information(time: u64) -> Vec<u8> {
+        resident(ATTRIBUTE_STANDARD_INFORMATION, &[time; 4].iter().flat_map(|t| t.to_le_bytes()).collect::<Vec<_>>())
+    }
+
+    fn table() -> Vec<u8> {
+        let mut records: Vec<Vec<u8>> = (0..8).map(|_| vec![0u8; RECORD_SIZE]).collect();
+        records[0] = record(RECORD_IN_USE, &[standard_information(OLD), file_name(ROOT_ENTRY, "$MFT", 3, OLD)]);
+        records[5] = record(RECORD_IN_USE | RECORD_DIRECTORY, &[standard_information(OLD), file_name(ROOT_ENTRY, ".", 3, OLD)]);
+        records[6] = record(RECORD_IN_USE | RECORD_DIRECTORY, &[standard_information(OLD), file_name(ROOT_ENTRY, "Users", 3, OLD)]);
+        // Recently written: the DOS name comes first and must not win
+        records[7] = record(RECORD_IN_USE, &[
+            standard_information(RECENT),
+            file_name(6, "PAYLOA~1.EXE", NAMESPACE_DOS, RECENT),
+            file_name(6, "payload.exe", 1, RECENT),
+            resident(ATTRIBUTE_DATA, b"MZ\x90\x00"),
+        ]);
+        records.concat()
+    }
+
+    #[test]
+    fn test_records_are_parsed_into_csv_rows() {
+        let records = parse_mft(&table());
+        assert_eq!(records.iter().map(|r| r.entry).collect::<Vec<_>>(), vec![0, 5, 6, 7]);
+        let payload = &records[3];
+        assert_eq!((payload.file_name.as_str(), payload.parent_entry, payload.size), ("payload.exe", 6, 4));
+
+        let (all, rows) = to_csv(&records, &TimeWindow::default());
+        assert_eq!((rows, all.lines().count()), (4, 5));
+        assert!(all.contains("7,1,true,false,6,C:\\Users\\payload.exe,4,2024-05-01T10:00:00+00:00"));
+
+        let window = TimeWindow::new(Some("2024-04-01T00:00:00Z"), None).unwrap();
+        let (recent, rows) = to_csv(&records, &window);
+        assert_eq!(rows, 1);
+        assert!(recent.lines().nth(1).unwrap().starts_with("7,"));
+    }
+
+    #[test]
+    fn test_mft_is_read_through_its_data_runs() {
+        // 512-byte clusters; the table is split into two runs, the second before the first
+        let table = table();
+        let mut volume = vec![0u8; 64 * SECTOR_SIZE];
+        volume[3..11].copy_from_slice(b"NTFS    ");
+        volume[0x0B..0x0D].copy_from_slice(&512u16.to_le_bytes());
+        volume[0x0D] = 1;
+        volume[0x30..0x38].copy_from_slice(&40u64.to_le_bytes());
+        volume[0x40] = 0xF6; // 2^10 byte records
+
+        let mut data = vec![0u8; 0x48];
+        data[0..4].copy_from_slice(&ATTRIBUTE_DATA.to_le_bytes());
+        data[4..8].copy_from_slice(&0x48u32.to_le_bytes());
+        data[8] = 1;
+        data[0x20..0x22].copy_from_slice(&0x40u16.to_le_bytes());
+        data[0x30..0x38].copy_from_slice(&(table.len() as u64).to_le_bytes());
+        // 8 clusters at 40, then 8 clusters at 40 - 30 = 10
+        data[0x40..0x47].copy_from_slice(&[0x11, 8, 40, 0x11, 8, 0xE2, 0]);
+        let mut record_zero = record(RECORD_IN_USE, &[file_name(ROOT_ENTRY, "$MFT", 3, OLD), data]);
+        record_zero.truncate(RECORD_SIZE);
+
+        let mut expected = table.clone();
+        expected[..RECORD_SIZE].copy_from_slice(&record_zero);
+        volume[40 * SECTOR_SIZE..48 * SECTOR_SIZE].copy_from_slice(&expected[..8 * SECTOR_SIZE]);
+        volume[10 * SECTOR_SIZE..18 * SECTOR_SIZE].copy_from_slice(&expected[8 * SECTOR_SIZE..]);
+
+        let read = read_mft_from_volume(&mut Cursor::new(volume)).unwrap();
+        assert_eq!(read, expected);
+        assert_eq!(parse_mft(&read).last().map(|r| r.file_name.clone()), Some("payload.exe".to_string()));
+    }
+
+    #[test]
+    fn test_data_runs_stop_on_cluster_overflow() {
+        // A run at the largest cluster number, then one past it
+        let mut runs = vec![0x81, 1, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x7F];
+        runs.extend([0x11, 1, 1, 0]);
+        assert_eq!(data_runs(&runs), vec![(Some(i64::MAX as u64), 1)]);
+    }
+}
@@ -0,0 +1,319 @@
+//! MaxMind DB reader
+//! Reads GeoLite2/GeoIP2 City, Country, ASN and ISP databases (.mmdb) for the
+//! threat intelligence enrichment, without a network lookup. The file is a
+//! binary search tree over the address bits followed by a data section of typed
+//! records; lookups return the record as JSON.
+//! Format: https://maxmind.github.io/MaxMind-DB/
+
+use serde_json::{json, Map, Value};
+use std::net::IpAddr;
+use std::path::Path;
+
+/// Start of the metadata section, searched from the end of the file
+const METADATA_MARKER: &[u8] = b"\xAB\xCD\xEFMaxMind.com";
+
+/// The metadata lies within the last 128 KiB
+const METADATA_MAX_SIZE: usize = 128 * 1024;
+
+/// Zero bytes between the search tree and the data section
+const DATA_SECTION_SEPARATOR: usize = 16;
+
+/// Nesting limit of maps, arrays and pointers, guarding against loops in corrupt files
+const MAX_DECODE_DEPTH: usize = 64;
+
+pub struct MaxMindDb {
+    data: Vec<u8>,
+    pub database_type: String,
+    node_count: usize,
+    record_size: usize,
+    ip_version: u16,
+    tree_size: usize,
+}
+
+impl MaxMindDb {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let data = std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        Self::from_bytes(data).map_err(|e| format!("Invalid MaxMind DB {}: {}", path.display(), e))
+    }
+
+    pub fn from_bytes(data: Vec<u8>) -> Result<Self, String> {
+        let search_start = data.len().saturating_sub(METADATA_MAX_SIZE);
+        let marker = data[search_start..]
+            .windows(METADATA_MARKER.len())
+            .rposition(|window| window == METADATA_MARKER)
+            .ok_or("metadata marker not found")?;
+        let metadata_start = search_start + marker + METADATA_MARKER.len();
+        let (metadata, _) = Decoder { data: &data[metadata_start..] }.decode(0)?;
+
+        let field = |name: &str| metadata[name].as_u64().ok_or_else(|| format!("metadata without {}", name));
+        let node_count = field("node_count")? as usize;
+        let record_size = field("record_size")? as usize;
+        let ip_version = field("ip_version")? as u16;
+        if ![24, 28, 32].contains(&record_size) {
+            return Err(format!("unsupported record size {}", record_size));
+        }
+        let tree_size = node_count * record_size / 4;
+        if tree_size + DATA_SECTION_SEPARATOR > metadata_start {
+            return Err("search tree exceeds the file".to_string());
+        }
+        Ok(MaxMindDb {
+            database_type: metadata["database_type"].as_str().unwrap_or_default().to_string(),
+            data,
+            node_count,
+            record_size,
+            ip_version,
+            tree_size,
+        })
+    }
+
+    /// Record of the network containing the address, None if it is not in the database
+    pub fn lookup(&self, address: IpAddr) -> Result<Option<Value>, String> {
+        let (bits, mut node) = match (address, self.ip_version) {
+            (IpAddr::V4(v4), 4) => (u128::from(u32::from(v4)) << 96, 0),
+            // IPv4 addresses live in the ::/96 subtree of IPv6 databases
+            (IpAddr::V4(v4), _) => (u128::from(u32::from(v4)) << 96, self.ipv4_start()?),
+            (IpAddr::V6(_), 4) => return Ok(None),
+            (IpAddr::V6(v6), _) => (u128::from(v6), 0),
+        };
+        let depth = if self.ip_version == 4 || address.is_ipv4() { 32 } else { 128 };
+
+        for bit in 0..depth {
+            if node >= self.node_count {
+                break;
+            }
+            node = self.record(node, (bits >> (127 - bit)) & 1 == 1)?;
+        }
+        if node <= self.node_count {
+            return Ok(None);
+        }
+        let offset = (node - self.node_count).checked_sub(DATA_SECTION_SEPARATOR).ok_or("search tree record points into the separator")?;
+        let (record, _) = self.decoder().decode(offset)?;
+        Ok(Some(record))
+    }
+
+    /// Node reached after the first 96 zero bits
+    fn ipv4_start(&self) -> Result<usize, String> {
+        let mut node = 0;
+        for _ in 0..96 {
+            if node >= self.node_count {
+                break;
+            }
+            node = self.record(node, false)?;
+        }
+        Ok(node)
+    }
+
+    fn record(&self, node: usize, right: bool) -> Result<usize, String> {
+        let node_size = self.record_size / 4;
+        let bytes = self.data.get(node * node_size..(node + 1) * node_size).ok_or("search tree node out of range")?;
+        let be = |slice: &[u8]| slice.iter().fold(0usize, |value, byte| (value << 8) | *byte as usize);
+        Ok(match (self.record_size, right) {
+            (24, false) => be(&bytes[..3]),
+            (24, true) => be(&bytes[3..6]),
+            (28, false) => ((bytes[3] as usize & 0xF0) << 20) | be(&bytes[..3]),
+            (28, true) => ((bytes[3] as usize & 0x0F) << 24) | be(&bytes[4..7]),
+            (_, false) => be(&bytes[..4]),
+            (_, true) => be(&bytes[4..8]),
+        })
+    }
+
+    fn decoder(&self) -> Decoder<'_> {
+        Decoder { data: &self.data[self.tree_size + DATA_SECTION_SEPARATOR..] }
+    }
+}
+
+/// Decoder of the data section, offsets relative to its start
+struct Decoder<'a> {
+    data: &'a [u8],
+}
+
+impl Decoder<'_> {
+    /// Value at the offset and the offset after it
+    fn decode(&self, offset: usize) -> Result<(Value, usize), String> {
+        self.decode_nested(offset, 0)
+    }
+
+    fn decode_nested(&self, offset: usize, depth: usize) -> Result<(Value, usize), String> {
+        if depth > MAX_DECODE_DEPTH {
+            return Err(format!("data nested deeper than {} levels at offset {}", MAX_DECODE_DEPTH, offset));
+        }
+        let control = self.byte(offset)?;
+        let mut next = offset + 1;
+        let mut data_type = control >> 5;
+        if data_type == 1 {
+            let (target, after) = self.pointer(control, next)?;
+            // The format does not allow a pointer to point at another pointer
+            if self.byte(target)? >> 5 == 1 {
+                return Err(format!("pointer at offset {} points to another pointer", offset));
+            }
+            let (value, _) = self.decode_nested(target, depth + 1)?;
+            return Ok((value, after));
+        }
+        if data_type == 0 {
+            data_type = self.byte(next)?.checked_add(7).ok_or_else(|| format!("invalid extended data type at offset {}", offset))?;
+            next += 1;
+        }
+
+        let mut size = (control & 0x1F) as usize;
+        if size >= 29 {
+            let extra = size - 28;
+            let bytes = self.bytes(next, extra)?;
+            let value = bytes.iter().fold(0usize, |value, byte| (value << 8) | *byte as usize);
+            size = match extra {
+                1 => 29 + value,
+                2 => 285 + value,
+                _ => 65_821 + value,
+            };
+            next += extra;
+        }
+
+        match data_type {
+            2 => {
+                let text = String::from_utf8_lossy(self.bytes(next, size)?).to_string();
+                Ok((Value::String(text), next + size))
+            }
+            3 => {
+                let bytes: [u8; 8] = self.bytes(next, 8)?.try_into().map_err(|_| "invalid double")?;
+                Ok((json!(f64::from_be_bytes(bytes)), next + 8))
+            }
+            4 => Ok((Value::String(hex::encode(self.bytes(next, size)?)), next + size)),
+            5 | 6 | 9 | 10 => {
+                let bytes = self.bytes(next, size)?;
+                let value = bytes.iter().fold(0u128, |value, byte| (value << 8) | *byte as u128);
+                let value = u64::try_from(value).map_or_else(|_| Value::String(value.to_string()), |value| json!(value));
+                Ok((value, next + size))
+            }
+            8 => {
+                let bytes = self.bytes(next, size)?;
+                let value = bytes.iter().fold(0u32, |value, byte| (value << 8) | *byte as u32) as i32;
+                Ok((json!(value), next + size))
+            }
+            7 => {
+                let mut map = Map::new();
+                for _ in 0..size {
+                    let (key, after_key) = self.decode_nested(next, depth + 1)?;
+                    let (value, after_value) = self.decode_nested(after_key, depth + 1)?;
+                    map.insert(key.as_str().unwrap_or_default().to_string(), value);
+                    next = after_value;
+                }
+                Ok((Value::Object(map), next))
+            }
+            11 => {
+                let mut items = Vec::new();
+                for _ in 0..size {
+                    let (item, after) = self.decode_nested(next, depth + 1)?;
+                    items.push(item);
+                    next = after;
+                }
+                Ok((Value::Array(items), next))
+            }
+            14 => Ok((Value::Bool(size != 0), next)),
+            15 => {
+                let bytes: [u8; 4] = self.bytes(next, 4)?.try_into().map_err(|_| "invalid float")?;
+                Ok((json!(f32::from_be_bytes(bytes)), next + 4))
+            }
+            other => Err(format!("unsupported data type {} at offset {}", other, offset)),
+        }
+    }
+
+    /// Target of a pointer and the offset after the pointer itself
+    fn pointer(&self, control: u8, offset: usize) -> Result<(usize, usize), String> {
+        let size = ((control >> 3) & 0x3) as usize;
+        let high = (control & 0x7) as usize;
+        let bytes = self.bytes(offset, size + 1)?;
+        let value = bytes.iter().fold(0usize, |value, byte| (value << 8) | *byte as usize);
+        let target = match size {
+            0 => (high << 8) | value,
+            1 => ((high << 16) | value) + 2048,
+            2 => ((high << 24) | value) + 526_336,
+            _ => value,
+        };
+        Ok((target, offset + size + 1))
+    }
+
+    fn byte(&self, offset: usize) -> Result<u8, String> {
+        self.data.get(offset).copied().ok_or_else(|| format!("data offset {} out of range", offset))
+    }
+
+    fn bytes(&self, offset: usize, length: usize) -> Result<&[u8], String> {
+        self.data.get(offset..offset + length).ok_or_else(|| format!("data offset {} out of range", offset))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    fn string(text: &str) -> Vec<u8> {
+        let mut bytes = vec![(2 << 5) | text.len() as u8];
+        bytes.extend_from_slice(text.as_bytes());
+        bytes
+    }
+
+    fn uint(data_type: u8, value: u32) -> Vec<u8> {
+        let bytes: Vec<u8> = value.to_be_bytes().into_iter().skip_while(|byte| *byte == 0).collect();
+        let mut encoded = vec![(data_type << 5) | bytes.len() as u8];
+        encoded.extend(bytes);
+        encoded
+    }
+
+    fn map(entries: &[(&str, Vec<u8>)]) -> Vec<u8> {
+        let mut bytes = vec![(7 << 5) | entries.len() as u8];
+        for (key, value) in entries {
+            bytes.extend(string(key));
+            bytes.extend(value);
+        }
+        bytes
+    }
+
+    /// IPv4 database with one node: 0.0.0.0/1 maps to the record, 128.0.0.0/1 is empty
+    pub(crate) fn sample_database(record: Vec<u8>) -> Vec<u8> {
+        let mut data = vec![0, 0, 17, 0, 0, 1]; // Left: data offset 0 (node count + 16), right: no data
+        data.extend([0u8; DATA_SECTION_SEPARATOR]);
+        data.extend(record);
+        data.extend_from_slice(METADATA_MARKER);
+        data.extend(map(&[
+            ("node_count", uint(6, 1)),
+            ("record_size", uint(5, 24)),
+            ("ip_version", uint(5, 4)),
+            ("database_type", string("Test-City")),
+        ]));
+        data
+    }
+
+    pub(crate) fn city_record() -> Vec<u8> {
+        let mut latitude = vec![(3 << 5) | 8];
+        latitude.extend(52.52f64.to_be_bytes());
+        map(&[
+            ("country", map(&[("iso_code", string("DE")), ("names", map(&[("en", string("Germany"))]))])),
+            ("location", map(&[("latitude", latitude)])),
+            ("autonomous_system_number", uint(6, 64_500)),
+        ])
+    }
+
+    #[test]
+    fn test_lookup() {
+        let database = MaxMindDb::from_bytes(sample_database(city_record())).unwrap();
+        assert_eq!(database.database_type, "Test-City");
+
+        let record = database.lookup("10.1.2.3".parse().unwrap()).unwrap().unwrap();
+        assert_eq!(record["country"]["names"]["en"], "Germany");
+        assert_eq!(record["location"]["latitude"], 52.52);
+        assert_eq!(record["autonomous_system_number"], 64_500);
+        assert!(database.lookup("200.1.1.1".parse().unwrap()).unwrap().is_none());
+        assert!(database.lookup("2001:db8::1".parse().unwrap()).unwrap().is_none());
+        assert!(MaxMindDb::from_bytes(vec![0; 64]).is_err());
+    }
+
+    #[test]
+    fn test_malformed_data() {
+        // Two pointers pointing at each other
+        let looped = Decoder { data: &[0x20, 0x02, 0x20, 0x00] };
+        assert!(looped.decode(0).unwrap_err().contains("another pointer"));
+        // Extended type byte past the last type
+        assert!(Decoder { data: &[0x00, 0xFF] }.decode(0).unwrap_err().contains("extended data type"));
+        // Arrays of one array, nested past the limit
+        let nested = [0x01, 0x04].repeat(MAX_DECODE_DEPTH + 2);
+        assert!(Decoder { data: &nested }.decode(0).unwrap_err().contains("nested deeper"));
+    }
+}
@@ -0,0 +1,307 @@
+//! Module load order and load times of flagged processes
+//! The loader keeps the modules of a process in load order in its PEB
+//! (PEB_LDR_DATA InLoadOrderModuleList) and, since Windows 8, stamps every entry
+//! with the time it was mapped and the reason. DLLs loaded dynamically long
+//! after the process started stand out from its startup imports, as injected
+//! DLLs do. Only the native loader list is walked: for WOW64 processes it holds
+//! ntdll and the WOW64 layer, not the 32-bit modules.
+
+// Loader list parsing helpers are only reachable through the Windows process API
+#![cfg_attr(not(windows), allow(dead_code))]
+
+use crate::binutil::read_u64;
+use crate::types::{LogEntry, Process};
+
+#[cfg(windows)]
+use crate::binutil::filetime_to_datetime;
+#[cfg(windows)]
+use crate::types::ProcessModule;
+#[cfg(windows)]
+use windows::{
+    Wdk::System::Threading::{NtQueryInformationProcess, ProcessBasicInformation},
+    Win32::Foundation::{CloseHandle, HANDLE},
+    Win32::System::Diagnostics::Debug::ReadProcessMemory,
+    Win32::System::Threading::{OpenProcess, PROCESS_QUERY_INFORMATION, PROCESS_VM_READ},
+};
+
+/// x64 offsets in PEB, PEB_LDR_DATA and LDR_DATA_TABLE_ENTRY (Windows 8 and later)
+const PEB_LDR_OFFSET: u64 = 0x18;
+const LDR_IN_LOAD_ORDER_OFFSET: u64 = 0x10;
+const ENTRY_DLL_BASE: usize = 0x30;
+const ENTRY_SIZE_OF_IMAGE: usize = 0x40;
+const ENTRY_FULL_DLL_NAME: usize = 0x48;
+const ENTRY_BASE_DLL_NAME: usize = 0x58;
+const ENTRY_LOAD_TIME: usize = 0x100;
+const ENTRY_LOAD_REASON: usize = 0x10C;
+const ENTRY_SIZE: usize = 0x110;
+
+/// Loader lists longer than this are treated as corrupt
+const MAX_MODULES: usize = 2048;
+
+/// Non-system modules loaded at runtime this long after process start are reported
+const LATE_LOAD_SECS: i64 = 300;
+
+/// Walk the loader list of every process that already carries an indicator and
+/// record the load order, load time and load reason of its modules
+#[cfg(windows)]
+pub fn collect_module_load_order(processes: &mut [Process]) -> Vec<LogEntry> {
+    let mut logs = Vec::new();
+    let mut walked = 0;
+    let mut failed = 0;
+
+    for process in processes.iter_mut().filter(|p| !p.suspicious_indicators.is_empty()) {
+        match read_loader_modules(process.pid) {
+            Ok(modules) => {
+                process.loaded_modules = modules;
+                annotate_late_loads(process);
+                walked += 1;
+            }
+            Err(e) => {
+                failed += 1;
+                logs.push(LogEntry::warn(&format!(
+                    "Module load order unavailable for {} (PID {}): {}",
+                    process.name, process.pid, e
+                )));
+            }
+        }
+    }
+
+    logs.push(LogEntry::info(&format!(
+        "Module load order captured for {} flagged processes ({} failed)",
+        walked, failed
+    )));
+    logs
+}
+
+#[cfg(not(windows))]
+pub fn collect_module_load_order(_processes: &mut [Process]) -> Vec<LogEntry> {
+    vec![LogEntry::warn("Module load order capture skipped: requires the Windows process API")]
+}
+
+/// PROCESS_BASIC_INFORMATION, declared here to avoid pulling in the PEB types
+#[cfg(windows)]
+#[repr(C)]
+#[derive(Default)]
+struct BasicInformation {
+    exit_status: i32,
+    peb_base_address: u64,
+    affinity_mask: u64,
+    base_priority: i32,
+    unique_process_id: u64,
+    inherited_from_unique_process_id: u64,
+}
+
+#[cfg(windows)]
+fn read_loader_modules(pid: u32) -> Result<Vec<ProcessModule>, String> {
+    unsafe {
+        let process = OpenProcess(PROCESS_QUERY_INFORMATION | PROCESS_VM_READ, false, pid)
+            .map_err(|e| format!("OpenProcess failed: {}", e))?;
+        let modules = walk_loader_list(process);
+        let _ = CloseHandle(process);
+        modules
+    }
+}
+
+#[cfg(windows)]
+unsafe fn walk_loader_list(process: HANDLE) -> Result<Vec<ProcessModule>, String> {
+    let mut info = BasicInformation::default();
+    let status = NtQueryInformationProcess(
+        process,
+        ProcessBasicInformation,
+        &mut info as *mut _ as *mut _,
+        std::mem::size_of::<BasicInformation>() as u32,
+        std::ptr::null_mut(),
+    );
+    if status.is_err() {
+        return Err(format!("NtQueryInformationProcess failed: 0x{:08X}", status.0));
+    }
+
+    let ldr = read_pointer(process, info.peb_base_address + PEB_LDR_OFFSET)?;
+    if ldr == 0 {
+        return Err("loader data not initialized".to_string());
+    }
+
+    // InLoadOrderLinks is the first field of an entry, so each link is the entry address
+    let head = ldr + LDR_IN_LOAD_ORDER_OFFSET;
+    let mut link = read_pointer(process, head)?;
+    let mut modules = Vec::new();
+
+    while link != head && link != 0 && modules.len() < MAX_MODULES {
+        let data = read_memory(process, link, ENTRY_SIZE)?;
+        let entry = parse_ldr_entry(&data).ok_or("truncated loader entry")?;
+        let mut module = ProcessModule {
+            name: read_unicode_string(process, entry.base_name).unwrap_or_default(),
+            file_path: read_unicode_string(process, entry.full_name).unwrap_or_default(),
+            base_address: format!("0x{:X}", entry.dll_base),
+            size: entry.size_of_image,
+            version: String::new(),
+            load_order: Some(modules.len() as u32),
+            load_time: filetime_to_datetime(entry.load_time).map(|time| time.to_rfc3339()),
+            load_reason: Some(load_reason_name(entry.load_reason).to_string()),
+            seconds_after_start: None,
+            is_system_module: false,
+        };
+        module.is_system_module = module.is_system_module();
+        modules.push(module);
+        link = entry.next;
+    }
+
+    Ok(modules)
+}
+
+#[cfg(windows)]
+unsafe fn read_memory(process: HANDLE, address: u64, length: usize) -> Result<Vec<u8>, String> {
+    let mut buffer = vec![0u8; length];
+    let mut read = 0usize;
+    ReadProcessMemory(process, address as *const _, buffer.as_mut_ptr() as *mut _, length, Some(&mut read as *mut _))
+        .map_err(|e| format!("ReadProcessMemory at 0x{:X} failed: {}", address, e))?;
+    buffer.truncate(read);
+    Ok(buffer)
+}
+
+#[cfg(windows)]
+unsafe fn read_pointer(process: HANDLE, address: u64) -> Result<u64, String> {
+    let data = read_memory(process, address, 8)?;
+    read_u64(&data, 0).ok_or_else(|| format!("short read at 0x{:X}", address))
+}
+
+/// UNICODE_STRING: length in bytes and the address of its buffer
+#[cfg(windows)]
+unsafe fn read_unicode_string(process: HANDLE, (length, buffer): (u16, u64)) -> Option<String> {
+    if length == 0 || buffer == 0 {
+        return None;
+    }
+    let data = read_memory(process, buffer, length as usize).ok()?;
+    let units: Vec<u16> = data.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect();
+    Some(String::from_utf16_lossy(&units))
+}
+
+/// Fields used from a raw x64 LDR_DATA_TABLE_ENTRY
+#[derive(Debug, Clone, PartialEq)]
+struct LdrEntry {
+    next: u64,
+    dll_base: u64,
+    size_of_image: u32,
+    full_name: (u16, u64),
+    base_name: (u16, u64),
+    load_time: u64,
+    load_reason: i32,
+}
+
+fn parse_ldr_entry(data: &[u8]) -> Option<LdrEntry> {
+    if data.len() < ENTRY_SIZE {
+        return None;
+    }
+    let unicode_string = |offset: usize| {
+        Some((u16::from_le_bytes([data[offset], data[offset + 1]]), read_u64(data, offset + 8)?))
+    };
+
+    Some(LdrEntry {
+        next: read_u64(data, 0)?,
+        dll_base: read_u64(data, ENTRY_DLL_BASE)?,
+        size_of_image: u32::from_le_bytes(data[ENTRY_SIZE_OF_IMAGE..ENTRY_SIZE_OF_IMAGE + 4].try_into().ok()?),
+        full_name: unicode_string(ENTRY_FULL_DLL_NAME)?,
+        base_name: unicode_string(ENTRY_BASE_DLL_NAME)?,
+        load_time: read_u64(data, ENTRY_LOAD_TIME)?,
+        load_reason: i32::from_le_bytes(data[ENTRY_LOAD_REASON..ENTRY_LOAD_REASON + 4].try_into().ok()?),
+    })
+}
+
+/// LDR_DLL_LOAD_REASON
+fn load_reason_name(reason: i32) -> &'static str {
+    match reason {
+        0 => "StaticDependency",
+        1 => "StaticForwarderDependency",
+        2 => "DynamicForwarderDependency",
+        3 => "DelayloadDependency",
+        4 => "DynamicLoad",
+        5 => "AsImageLoad",
+        6 => "AsDataLoad",
+        7 => "EnclavePrimary",
+        8 => "EnclaveDependency",
+        _ => "Unknown",
+    }
+}
+
+/// Fill in how long after process creation each module was loaded and flag
+/// non-system modules loaded at runtime after LATE_LOAD_SECS
+fn annotate_late_loads(process: &mut Process) {
+    let Some(started) = process
+        .creation_time
+        .as_deref()
+        .and_then(|time| chrono::DateTime::parse_from_rfc3339(time).ok())
+    else {
+        return;
+    };
+
+    let mut late_loads = Vec::new();
+    for module in process.loaded_modules.iter_mut() {
+        let Some(loaded) = module.load_time.as_deref().and_then(|time| chrono::DateTime::parse_from_rfc3339(time).ok()) else {
+            continue;
+        };
+        let seconds = (loaded - started).num_seconds();
+        module.seconds_after_start = Some(seconds);
+
+        let runtime_load = matches!(module.load_reason.as_deref(), Some("DynamicLoad") | Some("Unknown"));
+        if runtime_load && seconds >= LATE_LOAD_SECS && !module.is_system_module() {
+            late_loads.push(format!("Module {} loaded {}s after process start ({})", module.file_path, seconds, module.load_reason.as_deref().unwrap_or_default()));
+        }
+    }
+
+    process.suspicious_indicators.extend(late_loads);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ProcessModule;
+
+    #[test]
+    fn test_parse_ldr_entry() {
+        let mut data = vec![0u8; ENTRY_SIZE];
+        data[0..8].copy_from_slice(&0x1F0_0040u64.to_le_bytes());
+        data[ENTRY_DLL_BASE..ENTRY_DLL_BASE + 8].copy_from_slice(&0x7FF8_1000_0000u64.to_le_bytes());
+        data[ENTRY_SIZE_OF_IMAGE..ENTRY_SIZE_OF_IMAGE + 4].copy_from_slice(&0x2_5000u32.to_le_bytes());
+        data[ENTRY_BASE_DLL_NAME..ENTRY_BASE_DLL_NAME + 2].copy_from_slice(&24u16.to_le_bytes());
+        data[ENTRY_BASE_DLL_NAME + 8..ENTRY_BASE_DLL_NAME + 16].copy_from_slice(&0x1F0_0200u64.to_le_bytes());
+        data[ENTRY_LOAD_TIME..ENTRY_LOAD_TIME + 8].copy_from_slice(&133170048000000000u64.to_le_bytes());
+        data[ENTRY_LOAD_REASON..ENTRY_LOAD_REASON + 4].copy_from_slice(&4i32.to_le_bytes());
+
+        let entry = parse_ldr_entry(&data).unwrap();
+        assert_eq!(entry.next, 0x1F0_0040);
+        assert_eq!(entry.dll_base, 0x7FF8_1000_0000);
+        assert_eq!(entry.size_of_image, 0x2_5000);
+        assert_eq!(entry.base_name, (24, 0x1F0_0200));
+        assert_eq!(entry.full_name, (0, 0));
+        assert_eq!(load_reason_name(entry.load_reason), "DynamicLoad");
+        assert!(parse_ldr_entry(&data[..ENTRY_LOAD_TIME]).is_none());
+        assert_eq!(load_reason_name(-1), "Unknown");
+    }
+
+    #[test]
+    fn test_annotate_late_loads() {
+        let module = |path: &str, time: &str, reason: &str| {
+            let mut module = ProcessModule::new(String::new(), path.to_string(), String::new(), 0, String::new());
+            module.load_time = Some(time.to_string());
+            module.load_reason = Some(reason.to_string());
+            module
+        };
+        let mut process = Process::new(4242, 1, "notepad.exe".to_string(), String::new(), String::new());
+        process.creation_time = Some("2024-03-01T10:00:00+00:00".to_string());
+        process.loaded_modules = vec![
+            module("C:\\Windows\\System32\\notepad.exe", "2024-03-01T10:00:00+00:00", "StaticDependency"),
+            module("C:\\Windows\\System32\\dbghelp.dll", "2024-03-01T11:00:00+00:00", "DynamicLoad"),
+            module("C:\\Users\\Public\\beacon.dll", "2024-03-01T10:45:30+00:00", "DynamicLoad"),
+            module("C:\\Program Files\\App\\plugin.dll", "2024-03-01T10:00:02+00:00", "DynamicLoad"),
+        ];
+
+        annotate_late_loads(&mut process);
+
+        assert_eq!(process.loaded_modules[2].seconds_after_start, Some(2730));
+        assert_eq!(process.loaded_modules[3].seconds_after_start, Some(2));
+        assert_eq!(
+            process.suspicious_indicators,
+            vec!["Module C:\\Users\\Public\\beacon.dll loaded 2730s after process start (DynamicLoad)".to_string()]
+        );
+    }
+}
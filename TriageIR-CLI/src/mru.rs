@@ -0,0 +1,448 @@
+//! Explorer most-recently-used lists
+//! RecentDocs holds the files and folders a user opened, OpenSavePidlMRU the
+//! files picked in common Open/Save dialogs, LastVisitedPidlMRU the folder each
+//! program last used in them, TypedPaths the paths typed into the Explorer
+//! address bar and RunMRU the Run dialog commands. Each list is kept per user
+//! in NTUSER.DAT, most recent first; the key last-write time dates its head.
+
+// Value decoding helpers are only reachable through the Windows registry API
+#![cfg_attr(not(windows), allow(dead_code))]
+
+use crate::binutil::utf16_string;
+use crate::forensic_types::{AuditEntry, MruEntry};
+use crate::offline::OfflineRoot;
+
+#[cfg(windows)]
+use crate::footprint::TrackedKey;
+#[cfg(windows)]
+use crate::offline;
+#[cfg(windows)]
+use crate::search_history::{parse_mru_list_ex, strip_run_mru_suffix};
+#[cfg(windows)]
+use crate::user_hives::{self, UserHive};
+#[cfg(windows)]
+use winreg::RegKey;
+
+const COMPONENT: &str = "mru";
+
+const RECENT_DOCS_KEY: &str = "Software\\Microsoft\\Windows\\CurrentVersion\\Explorer\\RecentDocs";
+const OPEN_SAVE_KEY: &str = "Software\\Microsoft\\Windows\\CurrentVersion\\Explorer\\ComDlg32\\OpenSavePidlMRU";
+const LAST_VISITED_KEY: &str = "Software\\Microsoft\\Windows\\CurrentVersion\\Explorer\\ComDlg32\\LastVisitedPidlMRU";
+const TYPED_PATHS_KEY: &str = "Software\\Microsoft\\Windows\\CurrentVersion\\Explorer\\TypedPaths";
+const RUN_MRU_KEY: &str = "Software\\Microsoft\\Windows\\CurrentVersion\\Explorer\\RunMRU";
+
+/// Signature of the file entry extension block holding the long name
+const FILE_EXTENSION_SIGNATURE: u32 = 0xBEEF_0004;
+
+/// Shell folders that appear as root items of a PIDL
+const KNOWN_FOLDERS: [(&str, &str); 8] = [
+    ("20D04FE0-3AEA-1069-A2D8-08002B30309D", "My Computer"),
+    ("59031A47-3F72-44A7-89C5-5595FE6B30EE", "User Profile"),
+    ("450D8FBA-AD25-11D0-98A8-0800361B1103", "My Documents"),
+    ("F02C1A0D-BE21-4350-88B0-7367FC96EF3C", "Network"),
+    ("208D2C60-3AEA-1069-A2D7-08002B30309D", "My Network Places"),
+    ("031E4825-7B94-4DC3-B131-E946B44C8DD5", "Libraries"),
+    ("645FF040-5081-101B-9F08-00AA002F954E", "Recycle Bin"),
+    ("679F85CB-0220-4080-B29B-5540CC05AAB6", "Quick Access"),
+];
+
+/// Collect the MRU lists of every user profile on the live system
+#[cfg(windows)]
+pub fn collect_mru_lists() -> (Vec<MruEntry>, Vec<AuditEntry>) {
+    let (hives, failures) = user_hives::live_user_hives();
+    collect_from_user_hives(hives, failures)
+}
+
+#[cfg(not(windows))]
+pub fn collect_mru_lists() -> (Vec<MruEntry>, Vec<AuditEntry>) {
+    registry_unavailable()
+}
+
+/// Collect the MRU lists from the NTUSER.DAT hives of a mounted Windows volume
+#[cfg(windows)]
+pub fn collect_mru_lists_offline(root: &OfflineRoot) -> (Vec<MruEntry>, Vec<AuditEntry>) {
+    let (hives, failures) = user_hives::offline_user_hives(root);
+    collect_from_user_hives(hives, failures)
+}
+
+#[cfg(not(windows))]
+pub fn collect_mru_lists_offline(_root: &OfflineRoot) -> (Vec<MruEntry>, Vec<AuditEntry>) {
+    registry_unavailable()
+}
+
+#[cfg(not(windows))]
+fn registry_unavailable() -> (Vec<MruEntry>, Vec<AuditEntry>) {
+    let audit_log = vec![
+        AuditEntry::new(COMPONENT, "INFO", "start_collection", "Starting MRU list collection".to_string(), "started"),
+        AuditEntry::new(COMPONENT, "WARN", "load_user_hive", "User hives require the Windows registry".to_string(), "unsupported"),
+        AuditEntry::new(COMPONENT, "INFO", "complete_collection", "Collected 0 MRU entries".to_string(), "success"),
+    ];
+    (Vec::new(), audit_log)
+}
+
+#[cfg(windows)]
+fn collect_from_user_hives(hives: Vec<UserHive>, failures: Vec<String>) -> (Vec<MruEntry>, Vec<AuditEntry>) {
+    let mut entries = Vec::new();
+    let start_time = std::time::Instant::now();
+
+    let mut audit_log = vec![AuditEntry::new(
+        COMPONENT,
+        "INFO",
+        "start_collection",
+        format!("Starting MRU list collection for {} user hives", hives.len()),
+        "started",
+    )];
+
+    for failure in failures {
+        audit_log.push(AuditEntry::new(COMPONENT, "WARN", "load_user_hive", failure, "error"));
+    }
+
+    for hive in &hives {
+        let mut hive_entries = collect_mru_tree(hive, "RecentDocs", RECENT_DOCS_KEY, decode_recent_doc);
+        hive_entries.extend(collect_mru_tree(hive, "OpenSavePidlMRU", OPEN_SAVE_KEY, |data| (decode_pidl(data), None)));
+        hive_entries.extend(collect_mru_tree(hive, "LastVisitedPidlMRU", LAST_VISITED_KEY, decode_last_visited));
+        hive_entries.extend(collect_typed_paths(hive));
+        hive_entries.extend(collect_run_mru(hive));
+
+        audit_log.push(AuditEntry::new(
+            COMPONENT,
+            "DEBUG",
+            "parse_user_hive",
+            format!("{}: {} MRU entries", hive.user, hive_entries.len()),
+            "success",
+        ));
+        entries.extend(hive_entries);
+    }
+
+    let mut complete = AuditEntry::new(
+        COMPONENT,
+        "INFO",
+        "complete_collection",
+        format!("Collected {} MRU entries", entries.len()),
+        "success",
+    );
+    complete.duration_ms = Some(start_time.elapsed().as_millis() as u64);
+    audit_log.push(complete);
+
+    (entries, audit_log)
+}
+
+/// A binary MRU key and its per-extension subkeys (`.docx`, `*`, `Folder`),
+/// each ordered by its own MRUListEx
+#[cfg(windows)]
+fn collect_mru_tree(
+    hive: &UserHive,
+    source: &str,
+    key_path: &str,
+    decode: fn(&[u8]) -> (String, Option<String>),
+) -> Vec<MruEntry> {
+    let key = match hive.key.open_tracked(key_path) {
+        Ok(key) => key,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut entries = collect_mru_list_ex(hive, source, &key, key_path, decode);
+    for subkey_name in key.enum_keys().filter_map(|k| k.ok()) {
+        if let Ok(subkey) = key.open_tracked(&subkey_name) {
+            let subkey_path = format!("{}\\{}", key_path, subkey_name);
+            entries.extend(collect_mru_list_ex(hive, source, &subkey, &subkey_path, decode));
+        }
+    }
+    entries
+}
+
+#[cfg(windows)]
+fn collect_mru_list_ex(
+    hive: &UserHive,
+    source: &str,
+    key: &RegKey,
+    key_path: &str,
+    decode: fn(&[u8]) -> (String, Option<String>),
+) -> Vec<MruEntry> {
+    let order = key
+        .get_raw_value("MRUListEx")
+        .map(|value| parse_mru_list_ex(&value.bytes))
+        .unwrap_or_default();
+    let last_write = offline::key_last_write_time(key);
+
+    order
+        .iter()
+        .enumerate()
+        .filter_map(|(position, index)| {
+            let value = key.get_raw_value(index.to_string()).ok()?;
+            let (value_data, application) = decode(&value.bytes);
+            Some(MruEntry {
+                user: hive.user.clone(),
+                source: source.to_string(),
+                key_path: format!("{}\\{}", hive.source, key_path),
+                value_name: index.to_string(),
+                value_data,
+                application,
+                last_write_time: last_write.clone(),
+                position: position as u32,
+            })
+        })
+        .collect()
+}
+
+/// Typed paths are REG_SZ values url1 (most recent) to urlN without an MRU list
+#[cfg(windows)]
+fn collect_typed_paths(hive: &UserHive) -> Vec<MruEntry> {
+    let key = match hive.key.open_tracked(TYPED_PATHS_KEY) {
+        Ok(key) => key,
+        Err(_) => return Vec::new(),
+    };
+
+    let last_write = offline::key_last_write_time(&key);
+    let mut paths: Vec<(u32, String, String)> = key
+        .enum_values()
+        .filter_map(|v| v.ok())
+        .filter_map(|(name, value)| {
+            let number = name.strip_prefix("url")?.parse::<u32>().ok()?;
+            Some((number, name, value.to_string()))
+        })
+        .collect();
+    paths.sort_by_key(|(number, _, _)| *number);
+
+    paths
+        .into_iter()
+        .enumerate()
+        .map(|(position, (_, name, path))| MruEntry {
+            user: hive.user.clone(),
+            source: "TypedPaths".to_string(),
+            key_path: format!("{}\\{}", hive.source, TYPED_PATHS_KEY),
+            value_name: name,
+            value_data: path,
+            application: None,
+            last_write_time: last_write.clone(),
+            position: position as u32,
+        })
+        .collect()
+}
+
+/// Run dialog commands are lettered REG_SZ values ordered by the MRUList string
+#[cfg(windows)]
+fn collect_run_mru(hive: &UserHive) -> Vec<MruEntry> {
+    let key = match hive.key.open_tracked(RUN_MRU_KEY) {
+        Ok(key) => key,
+        Err(_) => return Vec::new(),
+    };
+
+    let order = key.get_value::<String, _>("MRUList").unwrap_or_default();
+    let last_write = offline::key_last_write_time(&key);
+
+    order
+        .chars()
+        .enumerate()
+        .filter_map(|(position, letter)| {
+            let command = key.get_value::<String, _>(letter.to_string()).ok()?;
+            Some(MruEntry {
+                user: hive.user.clone(),
+                source: "RunMRU".to_string(),
+                key_path: format!("{}\\{}", hive.source, RUN_MRU_KEY),
+                value_name: letter.to_string(),
+                value_data: strip_run_mru_suffix(&command),
+                application: None,
+                last_write_time: last_write.clone(),
+                position: position as u32,
+            })
+        })
+        .collect()
+}
+
+/// RecentDocs values hold the UTF-16 name of the opened item followed by the
+/// shell item of its shortcut in the Recent folder
+fn decode_recent_doc(data: &[u8]) -> (String, Option<String>) {
+    (utf16_string(data), None)
+}
+
+/// LastVisitedPidlMRU values hold the UTF-16 name of the program followed by
+/// the PIDL of the folder it last used
+fn decode_last_visited(data: &[u8]) -> (String, Option<String>) {
+    let application = utf16_string(data);
+    let pidl_offset = (application.encode_utf16().count() + 1) * 2;
+    let folder = data.get(pidl_offset..).map(decode_pidl).unwrap_or_default();
+    (folder, Some(application).filter(|a| !a.is_empty()))
+}
+
+/// Decode a shell item ID list: size-prefixed items ending with a zero size
+fn decode_pidl(data: &[u8]) -> String {
+    let mut path = String::new();
+    let mut offset = 0;
+
+    while offset + 2 <= data.len() {
+        let size = u16::from_le_bytes([data[offset], data[offset + 1]]) as usize;
+        if size < 3 || offset + size > data.len() {
+            break;
+        }
+        match decode_shell_item(&data[offset..offset + size]) {
+            // Roots, volumes and shares start a new absolute path
+            Some(ShellItem::Root(name)) | Some(ShellItem::Volume(name)) | Some(ShellItem::Network(name)) => path = name,
+            Some(ShellItem::Name(name)) => {
+                if !path.is_empty() && !path.ends_with('\\') {
+                    path.push('\\');
+                }
+                path.push_str(&name);
+            }
+            None => {}
+        }
+        offset += size;
+    }
+
+    path
+}
+
+#[derive(Debug, PartialEq)]
+enum ShellItem {
+    Root(String),
+    Volume(String),
+    Name(String),
+    Network(String),
+}
+
+/// The class type at offset 2 selects the layout of the item
+fn decode_shell_item(item: &[u8]) -> Option<ShellItem> {
+    let class_type = item[2];
+    match class_type {
+        0x1F => {
+            let guid = format_guid(item.get(4..20)?);
+            let name = KNOWN_FOLDERS
+                .iter()
+                .find(|(known, _)| *known == guid)
+                .map(|(_, name)| name.to_string())
+                .unwrap_or_else(|| format!("{{{}}}", guid));
+            Some(ShellItem::Root(name))
+        }
+        _ => match class_type & 0x70 {
+            0x20 => Some(ShellItem::Volume(read_ascii(item.get(3..)?))),
+            0x30 => decode_file_entry(item).map(ShellItem::Name),
+            0x40 => Some(ShellItem::Network(read_ascii(item.get(5..)?))),
+            _ => None,
+        },
+    }
+}
+
+/// File entries carry the 8.3 name from offset 14 and, since Windows XP, the
+/// long name in a 0xBEEF0004 extension block that follows it
+fn decode_file_entry(item: &[u8]) -> Option<String> {
+    let short_name = read_ascii(item.get(14..)?);
+    let mut extension = 14 + short_name.len() + 1;
+    extension += extension % 2;
+
+    let long_name = item.get(extension..).and_then(|block| {
+        let version = u16::from_le_bytes([*block.get(2)?, *block.get(3)?]);
+        let signature = u32::from_le_bytes(block.get(4..8)?.try_into().ok()?);
+        if signature != FILE_EXTENSION_SIGNATURE {
+            return None;
+        }
+        let name_offset = match version {
+            3..=6 => 20,
+            7 => 38,
+            8 => 42,
+            9.. => 46,
+            _ => return None,
+        };
+        Some(utf16_string(block.get(name_offset..)?)).filter(|name| !name.is_empty())
+    });
+
+    long_name.or(Some(short_name).filter(|name| !name.is_empty()))
+}
+
+/// Mixed-endian GUID as stored in a shell item
+fn format_guid(bytes: &[u8]) -> String {
+    format!(
+        "{:08X}-{:04X}-{:04X}-{:02X}{:02X}-{}",
+        u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+        u16::from_le_bytes([bytes[4], bytes[5]]),
+        u16::from_le_bytes([bytes[6], bytes[7]]),
+        bytes[8],
+        bytes[9],
+        bytes[10..16].iter().map(|b| format!("{:02X}", b)).collect::<String>()
+    )
+}
+
+fn read_ascii(data: &[u8]) -> String {
+    data.iter().take_while(|&&b| b != 0).map(|&b| b as char).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16z(text: &str) -> Vec<u8> {
+        text.encode_utf16().chain([0]).flat_map(|unit| unit.to_le_bytes()).collect()
+    }
+
+    fn sized_item(body: &[u8]) -> Vec<u8> {
+        let mut item = ((body.len() + 2) as u16).to_le_bytes().to_vec();
+        item.extend_from_slice(body);
+        item
+    }
+
+    fn my_computer() -> Vec<u8> {
+        let mut body = vec![0x1F, 0x50];
+        body.extend_from_slice(&[
+            0xE0, 0x4F, 0xD0, 0x20, 0xEA, 0x3A, 0x69, 0x10, 0xA2, 0xD8, 0x08, 0x00, 0x2B, 0x30, 0x30, 0x9D,
+        ]);
+        sized_item(&body)
+    }
+
+    fn volume(drive: &str) -> Vec<u8> {
+        let mut body = vec![0x2F];
+        body.extend_from_slice(drive.as_bytes());
+        body.resize(23, 0);
+        sized_item(&body)
+    }
+
+    /// Directory or file entry with a version 9 extension block
+    fn file_entry(short_name: &str, long_name: &str) -> Vec<u8> {
+        let mut body = vec![0x31, 0x00];
+        body.extend_from_slice(&[0; 10]); // size, modified time, attributes
+        body.extend_from_slice(short_name.as_bytes());
+        body.push(0);
+        if (body.len() + 2) % 2 == 1 {
+            body.push(0);
+        }
+        let mut block = vec![0u8; 46];
+        block[2..4].copy_from_slice(&9u16.to_le_bytes());
+        block[4..8].copy_from_slice(&FILE_EXTENSION_SIGNATURE.to_le_bytes());
+        block.extend(utf16z(long_name));
+        block.extend_from_slice(&[0, 0]);
+        let block_size = block.len() as u16;
+        block[0..2].copy_from_slice(&block_size.to_le_bytes());
+        body.extend(block);
+        sized_item(&body)
+    }
+
+    #[test]
+    fn test_decode_pidl() {
+        let mut pidl = my_computer();
+        pidl.extend(volume("C:\\"));
+        pidl.extend(file_entry("USERS", "Users"));
+        pidl.extend(file_entry("QUARTE~1.XLS", "Quarterly Payroll.xlsx"));
+        pidl.extend_from_slice(&[0, 0]);
+        assert_eq!(decode_pidl(&pidl), "C:\\Users\\Quarterly Payroll.xlsx");
+
+        let mut share = sized_item(&[0x41, 0x01, 0x00, b'\\', b'\\', b'f', b's', b'0', b'1', 0]);
+        share.extend(file_entry("HR", ""));
+        assert_eq!(decode_pidl(&share), "\\\\fs01\\HR");
+
+        assert_eq!(decode_pidl(&my_computer()), "My Computer");
+        assert_eq!(decode_pidl(&[0xFF, 0xFF, 0x1F]), "");
+    }
+
+    #[test]
+    fn test_decode_value_prefixes() {
+        let mut recent = utf16z("exfil.7z");
+        recent.extend(file_entry("EXFIL~1.LNK", "exfil.lnk"));
+        assert_eq!(decode_recent_doc(&recent), ("exfil.7z".to_string(), None));
+
+        let mut last_visited = utf16z("rclone.exe");
+        last_visited.extend(volume("D:\\"));
+        last_visited.extend(file_entry("STAGING", "staging"));
+        assert_eq!(
+            decode_last_visited(&last_visited),
+            ("D:\\staging".to_string(), Some("rclone.exe".to_string()))
+        );
+    }
+}
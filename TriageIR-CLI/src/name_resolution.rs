@@ -0,0 +1,509 @@
+//! Name resolution tampering checks
+//! The hosts file is read from the directory the Tcpip DataBasePath value
+//! points at (moving it is a tampering technique of its own), and entries for
+//! security vendor and Windows update domains are flagged: malware redirects
+//! them to stop signature updates and telemetry. Winsock catalog entries are
+//! flagged when they are layered service providers or load a DLL that is not a
+//! stock provider in System32; NRPT rules when they send security vendor
+//! domains, or every name, to other DNS servers; interface DNS servers when
+//! they are statically set to public addresses, and any machine-wide
+//! NameServer override.
+
+use crate::footprint;
+use crate::forensic_types::{AuditEntry, DnsServerSetting, HostsEntry, HostsFile, NameResolutionConfiguration, NrptRule, WinsockProvider};
+use crate::hive::ValueData;
+use crate::offline::{MachineRegistry, OfflineRegistry, OfflineRoot};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+
+#[cfg(windows)]
+use crate::offline::LiveRegistry;
+#[cfg(windows)]
+use winreg::enums::HKEY_LOCAL_MACHINE;
+#[cfg(windows)]
+use winreg::RegKey;
+
+const COMPONENT: &str = "name_resolution";
+
+const TCPIP_PARAMETERS_KEY: &str = "SYSTEM\\CurrentControlSet\\Services\\Tcpip\\Parameters";
+/// Per-interface settings: (IP version, key of the interface subkeys)
+const INTERFACE_KEYS: [(&str, &str); 2] = [
+    ("IPv4", "SYSTEM\\CurrentControlSet\\Services\\Tcpip\\Parameters\\Interfaces"),
+    ("IPv6", "SYSTEM\\CurrentControlSet\\Services\\Tcpip6\\Parameters\\Interfaces"),
+];
+/// Network adapter class; each interface GUID has a Connection\Name
+const NETWORK_CONNECTIONS_KEY: &str = "SYSTEM\\CurrentControlSet\\Control\\Network\\{4D36E972-E325-11CE-BFC1-08002BE10318}";
+const WINSOCK_KEY: &str = "SYSTEM\\CurrentControlSet\\Services\\WinSock2\\Parameters";
+/// Winsock catalogs: (catalog, architecture, key below WINSOCK_KEY)
+const WINSOCK_CATALOGS: [(&str, &str, &str); 4] = [
+    ("protocol", "64-bit", "Protocol_Catalog9\\Catalog_Entries64"),
+    ("protocol", "32-bit", "Protocol_Catalog9\\Catalog_Entries"),
+    ("namespace", "64-bit", "NameSpace_Catalog5\\Catalog_Entries64"),
+    ("namespace", "32-bit", "NameSpace_Catalog5\\Catalog_Entries"),
+];
+/// NRPT stores: (source, key whose subkeys are the rules)
+const NRPT_KEYS: [(&str, &str); 2] = [
+    ("group_policy", "SOFTWARE\\Policies\\Microsoft\\Windows NT\\DNSClient\\DnsPolicyConfig"),
+    ("local", "SYSTEM\\CurrentControlSet\\Services\\Dnscache\\Parameters\\DnsPolicyConfig"),
+];
+const DEFAULT_DATABASE_PATH: &str = "%SystemRoot%\\System32\\drivers\\etc";
+/// Offline volumes are expected to have Windows in the default location
+const OFFLINE_SYSTEM_ROOT: &str = "C:\\Windows";
+const MAX_HOSTS_FILE_SIZE: u64 = 1024 * 1024;
+
+/// PackedCatalogItem: ANSI provider path (MAX_PATH), then WSAPROTOCOL_INFOW
+const PROTOCOL_INFO_OFFSET: usize = 260;
+const CHAIN_LENGTH_OFFSET: usize = PROTOCOL_INFO_OFFSET + 40;
+const PROTOCOL_NAME_OFFSET: usize = PROTOCOL_INFO_OFFSET + 116;
+/// ChainLen of a base provider; 0 is a layered protocol, more a chain through one
+const BASE_PROTOCOL: i32 = 1;
+
+/// Provider DLLs Windows registers in its own Winsock catalogs
+const STOCK_PROVIDER_DLLS: [&str; 12] = [
+    "mswsock.dll",
+    "napinsp.dll",
+    "nlaapi.dll",
+    "pnrpnsp.dll",
+    "rsvpsp.dll",
+    "winrnr.dll",
+    "wshbth.dll",
+    "vsocklib.dll",
+    "hvsocket.dll",
+    "mdnsnsp.dll",
+    "wshqos.dll",
+    "wship6.dll",
+];
+
+/// Security vendor, update and telemetry domains (and their subdomains)
+const SECURITY_DOMAINS: [&str; 30] = [
+    "avast.com",
+    "avg.com",
+    "avira.com",
+    "bitdefender.com",
+    "bitdefender.net",
+    "carbonblack.com",
+    "crowdstrike.com",
+    "cylance.com",
+    "drweb.com",
+    "eset.com",
+    "f-secure.com",
+    "fortinet.com",
+    "kaspersky.com",
+    "kaspersky-labs.com",
+    "malwarebytes.com",
+    "mcafee.com",
+    "norton.com",
+    "paloaltonetworks.com",
+    "sentinelone.net",
+    "sophos.com",
+    "symantec.com",
+    "trendmicro.com",
+    "virustotal.com",
+    "webroot.com",
+    "windowsupdate.com",
+    "update.microsoft.com",
+    "wdcp.microsoft.com",
+    "wd.microsoft.com",
+    "smartscreen.microsoft.com",
+    "securitycenter.windows.com",
+];
+
+/// Hosts file, Winsock catalog, NRPT and DNS servers of the running system
+#[cfg(windows)]
+pub fn collect_name_resolution() -> (NameResolutionConfiguration, Vec<AuditEntry>) {
+    let start_time = std::time::Instant::now();
+    let mut audit_log = vec![AuditEntry::new(COMPONENT, "INFO", "start_collection", "Starting name resolution configuration collection".to_string(), "started")];
+    let system_root = std::env::var("SystemRoot").unwrap_or_else(|_| OFFLINE_SYSTEM_ROOT.to_string());
+    let configuration = name_resolution(&LiveRegistry(RegKey::predef(HKEY_LOCAL_MACHINE)), &system_root, PathBuf::from, &mut audit_log);
+    audit_log.push(completed(&configuration, start_time));
+    (configuration, audit_log)
+}
+
+#[cfg(not(windows))]
+pub fn collect_name_resolution() -> (NameResolutionConfiguration, Vec<AuditEntry>) {
+    let audit_log = vec![
+        AuditEntry::new(COMPONENT, "INFO", "start_collection", "Starting name resolution configuration collection".to_string(), "started"),
+        AuditEntry::new(COMPONENT, "WARN", "registry_access", "Name resolution configuration collection requires Windows".to_string(), "unsupported"),
+        AuditEntry::new(COMPONENT, "INFO", "complete_collection", "Collected 0 hosts entries, 0 Winsock providers, 0 NRPT rules and 0 DNS server settings".to_string(), "success"),
+    ];
+    (NameResolutionConfiguration::default(), audit_log)
+}
+
+/// Hosts file, Winsock catalog, NRPT and DNS servers of a mounted Windows volume
+pub fn collect_name_resolution_offline(root: &OfflineRoot) -> (NameResolutionConfiguration, Vec<AuditEntry>) {
+    let start_time = std::time::Instant::now();
+    let mut audit_log = vec![AuditEntry::new(
+        COMPONENT,
+        "INFO",
+        "start_collection",
+        format!("Starting offline name resolution configuration collection of {}", root.root().display()),
+        "started",
+    )];
+    let mut open = |name: &str| match root.open_hive(name) {
+        Ok(hive) => Some(hive),
+        Err(e) => {
+            audit_log.push(AuditEntry::new(COMPONENT, "ERROR", "load_hive", format!("Failed to load offline {} hive: {}", name, e), "error"));
+            None
+        }
+    };
+    let (software, system) = (open("SOFTWARE"), open("SYSTEM"));
+
+    let registry = OfflineRegistry::new(software, system);
+    let configuration = name_resolution(&registry, OFFLINE_SYSTEM_ROOT, |path| root.resolve(path), &mut audit_log);
+    audit_log.push(completed(&configuration, start_time));
+    (configuration, audit_log)
+}
+
+/// `resolve` maps a Windows path to where the file can be read
+fn name_resolution(registry: &dyn MachineRegistry, system_root: &str, resolve: impl Fn(&str) -> PathBuf, audit_log: &mut Vec<AuditEntry>) -> NameResolutionConfiguration {
+    let database_path = registry.string(TCPIP_PARAMETERS_KEY, "DataBasePath").unwrap_or_else(|| DEFAULT_DATABASE_PATH.to_string());
+    let hosts_path = format!("{}\\hosts", expand_system_root(&database_path, system_root).trim_end_matches('\\'));
+    let hosts_file = match read_hosts_file(&resolve(&hosts_path)) {
+        Ok((content, modified)) => Some(HostsFile {
+            entries: parse_hosts(&content),
+            path: hosts_path,
+            default_location: database_path.eq_ignore_ascii_case(DEFAULT_DATABASE_PATH)
+                || expand_system_root(&database_path, system_root).eq_ignore_ascii_case(&expand_system_root(DEFAULT_DATABASE_PATH, system_root)),
+            database_path,
+            modified,
+            content,
+        }),
+        Err(e) => {
+            audit_log.push(AuditEntry::new(COMPONENT, "WARN", "read_hosts", format!("Failed to read {}: {}", hosts_path, e), "error"));
+            None
+        }
+    };
+
+    let configuration = NameResolutionConfiguration {
+        hosts_file,
+        winsock_providers: winsock_providers(registry, system_root),
+        nrpt_rules: nrpt_rules(registry),
+        dns_servers: dns_servers(registry),
+    };
+
+    let mut findings = Vec::new();
+    if let Some(hosts_file) = &configuration.hosts_file {
+        if !hosts_file.default_location {
+            findings.push(format!("hosts file moved to {}", hosts_file.database_path));
+        }
+        let redirected = hosts_file.entries.iter().filter(|entry| entry.suspicious).count();
+        if redirected > 0 {
+            findings.push(format!("{} security domains redirected by the hosts file", redirected));
+        }
+    }
+    for (count, what) in [
+        (configuration.winsock_providers.iter().filter(|p| p.suspicious).count(), "suspicious Winsock providers"),
+        (configuration.nrpt_rules.iter().filter(|r| r.suspicious).count(), "suspicious NRPT rules"),
+        (configuration.dns_servers.iter().filter(|s| s.suspicious).count(), "interfaces with non-default DNS servers"),
+    ] {
+        if count > 0 {
+            findings.push(format!("{} {}", count, what));
+        }
+    }
+    if !findings.is_empty() {
+        audit_log.push(AuditEntry::new(COMPONENT, "WARN", "tampering_check", findings.join(", "), "warning"));
+    }
+    configuration
+}
+
+/// Contents (at most MAX_HOSTS_FILE_SIZE) and last modification time
+fn read_hosts_file(path: &Path) -> Result<(String, Option<String>), String> {
+    use std::io::Read;
+    let file = footprint::open(path).map_err(|e| e.to_string())?;
+    let modified = file.metadata().and_then(|metadata| metadata.modified()).ok().map(|time| chrono::DateTime::<chrono::Utc>::from(time).to_rfc3339());
+    let mut content = Vec::new();
+    file.take(MAX_HOSTS_FILE_SIZE).read_to_end(&mut content).map_err(|e| e.to_string())?;
+    Ok((String::from_utf8_lossy(&content).into_owned(), modified))
+}
+
+/// One entry per hostname: `address name [name...] [# comment]`
+fn parse_hosts(content: &str) -> Vec<HostsEntry> {
+    let mut entries = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default();
+        let mut fields = line.split_whitespace();
+        let Some(address) = fields.next() else { continue };
+        for hostname in fields {
+            let mut suspicion_reasons = Vec::new();
+            if let Some(domain) = security_domain(hostname) {
+                suspicion_reasons.push(format!("security domain {} redirected to {}", domain, address));
+            }
+            entries.push(HostsEntry {
+                line: index + 1,
+                address: address.to_string(),
+                hostname: hostname.to_string(),
+                suspicious: !suspicion_reasons.is_empty(),
+                suspicion_reasons,
+            });
+        }
+    }
+    entries
+}
+
+fn winsock_providers(registry: &dyn MachineRegistry, system_root: &str) -> Vec<WinsockProvider> {
+    let mut providers = Vec::new();
+    for (catalog, architecture, catalog_key) in WINSOCK_CATALOGS {
+        let catalog_key = format!("{}\\{}", WINSOCK_KEY, catalog_key);
+        for entry in registry.subkeys(&catalog_key) {
+            let entry_key = format!("{}\\{}", catalog_key, entry);
+            let (name, dll_path, layered, enabled) = if catalog == "protocol" {
+                let Some(item) = registry.value(&entry_key, "PackedCatalogItem") else { continue };
+                let Some((dll_path, name, chain_length)) = packed_catalog_item(&item.data) else { continue };
+                (name, dll_path, chain_length != BASE_PROTOCOL, None)
+            } else {
+                let Some(dll_path) = registry.string(&entry_key, "LibraryPath") else { continue };
+                let enabled = registry.value(&entry_key, "Enabled").and_then(|value| value.as_u32()).map(|enabled| enabled != 0);
+                (registry.string(&entry_key, "DisplayString").unwrap_or_default(), dll_path, false, enabled)
+            };
+
+            let mut suspicion_reasons = Vec::new();
+            if layered {
+                suspicion_reasons.push("layered service provider intercepting socket traffic".to_string());
+            }
+            let expanded = expand_system_root(&dll_path, system_root).to_lowercase();
+            let (directory, file_name) = expanded.rsplit_once('\\').unwrap_or(("", expanded.as_str()));
+            if !STOCK_PROVIDER_DLLS.contains(&file_name) {
+                suspicion_reasons.push(format!("non-default provider DLL {}", dll_path));
+            } else if directory != format!("{}\\system32", system_root.to_lowercase()) {
+                suspicion_reasons.push(format!("provider DLL outside System32: {}", dll_path));
+            }
+
+            providers.push(WinsockProvider {
+                catalog: catalog.to_string(),
+                architecture: architecture.to_string(),
+                entry,
+                name,
+                dll_path,
+                layered,
+                enabled,
+                suspicious: !suspicion_reasons.is_empty(),
+                suspicion_reasons,
+            });
+        }
+    }
+    providers
+}
+
+/// Provider DLL, protocol name and protocol chain length of a PackedCatalogItem
+fn packed_catalog_item(data: &[u8]) -> Option<(String, String, i32)> {
+    let path = data.get(..PROTOCOL_INFO_OFFSET)?;
+    let path: String = path.iter().take_while(|&&byte| byte != 0).map(|&byte| byte as char).collect();
+    let chain_length = i32::from_le_bytes(data.get(CHAIN_LENGTH_OFFSET..CHAIN_LENGTH_OFFSET + 4)?.try_into().ok()?);
+    let name_units: Vec<u16> = data
+        .get(PROTOCOL_NAME_OFFSET..(PROTOCOL_NAME_OFFSET + 512).min(data.len()))?
+        .chunks_exact(2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .take_while(|&unit| unit != 0)
+        .collect();
+    Some((path, String::from_utf16_lossy(&name_units), chain_length))
+}
+
+fn nrpt_rules(registry: &dyn MachineRegistry) -> Vec<NrptRule> {
+    let mut rules = Vec::new();
+    for (source, key) in NRPT_KEYS {
+        for name in registry.subkeys(key) {
+            let rule_key = format!("{}\\{}", key, name);
+            let namespaces = match registry.value(&rule_key, "Name").map(|value| value.typed()) {
+                Some(ValueData::MultiString(names)) => names,
+                Some(ValueData::String(name)) => vec![name],
+                _ => Vec::new(),
+            };
+            let dns_servers = split_servers(&registry.string(&rule_key, "GenericDNSServers").unwrap_or_default());
+
+            let mut suspicion_reasons = Vec::new();
+            if !dns_servers.is_empty() {
+                for namespace in &namespaces {
+                    let suffix = namespace.trim_start_matches('.').to_lowercase();
+                    if suffix.is_empty() {
+                        suspicion_reasons.push(format!("every name resolved through {}", dns_servers.join(", ")));
+                    } else if let Some(domain) = security_domain(&suffix).or_else(|| SECURITY_DOMAINS.iter().copied().find(|domain| is_subdomain(domain, &suffix))) {
+                        suspicion_reasons.push(format!("security domain {} resolved through {}", domain, dns_servers.join(", ")));
+                    }
+                }
+            }
+            rules.push(NrptRule {
+                name,
+                source: source.to_string(),
+                namespaces,
+                dns_servers,
+                suspicious: !suspicion_reasons.is_empty(),
+                suspicion_reasons,
+            });
+        }
+    }
+    rules
+}
+
+fn dns_servers(registry: &dyn MachineRegistry) -> Vec<DnsServerSetting> {
+    let mut settings = Vec::new();
+    // Tcpip\Parameters NameServer applies to every interface
+    let global = split_servers(&registry.string(TCPIP_PARAMETERS_KEY, "NameServer").unwrap_or_default());
+    if !global.is_empty() {
+        settings.push(DnsServerSetting {
+            interface: "global".to_string(),
+            interface_name: None,
+            ip_version: "IPv4".to_string(),
+            suspicion_reasons: vec![format!("machine-wide DNS server override {}", global.join(", "))],
+            static_servers: global,
+            dhcp_servers: Vec::new(),
+            domain: registry.string(TCPIP_PARAMETERS_KEY, "Domain"),
+            suspicious: true,
+        });
+    }
+
+    for (ip_version, key) in INTERFACE_KEYS {
+        for interface in registry.subkeys(key) {
+            let interface_key = format!("{}\\{}", key, interface);
+            let static_servers = split_servers(&registry.string(&interface_key, "NameServer").unwrap_or_default());
+            let dhcp_servers = split_servers(&registry.string(&interface_key, "DhcpNameServer").unwrap_or_default());
+            if static_servers.is_empty() && dhcp_servers.is_empty() {
+                continue;
+            }
+            let suspicion_reasons: Vec<String> = static_servers
+                .iter()
+                .filter(|server| server.parse::<IpAddr>().is_ok_and(|address| is_public(&address)))
+                .map(|server| format!("statically configured public DNS server {}", server))
+                .collect();
+            settings.push(DnsServerSetting {
+                interface_name: registry.string(&format!("{}\\{}\\Connection", NETWORK_CONNECTIONS_KEY, interface), "Name"),
+                interface,
+                ip_version: ip_version.to_string(),
+                static_servers,
+                dhcp_servers,
+                domain: registry.string(&interface_key, "Domain").or_else(|| registry.string(&interface_key, "DhcpDomain")),
+                suspicious: !suspicion_reasons.is_empty(),
+                suspicion_reasons,
+            });
+        }
+    }
+    settings
+}
+
+/// NameServer lists are comma separated, DhcpNameServer space separated
+fn split_servers(list: &str) -> Vec<String> {
+    list.split([',', ' ', ';']).map(str::trim).filter(|server| !server.is_empty()).map(str::to_string).collect()
+}
+
+/// Outside loopback, private, link-local and unique local ranges
+fn is_public(address: &IpAddr) -> bool {
+    match address {
+        IpAddr::V4(v4) => !(v4.is_private() || v4.is_loopback() || v4.is_link_local() || v4.is_unspecified()),
+        // fec0::/10 is the site-local resolver Windows falls back to
+        IpAddr::V6(v6) => !(v6.is_loopback() || v6.is_unspecified() || (v6.segments()[0] & 0xfe00) == 0xfc00 || (v6.segments()[0] & 0xffc0) == 0xfe80 || (v6.segments()[0] & 0xffc0) == 0xfec0),
+    }
+}
+
+/// Security domain a hostname belongs to
+fn security_domain(hostname: &str) -> Option<&'static str> {
+    let hostname = hostname.trim_end_matches('.').to_lowercase();
+    SECURITY_DOMAINS.iter().copied().find(|domain| is_subdomain(&hostname, domain))
+}
+
+fn is_subdomain(hostname: &str, domain: &str) -> bool {
+    hostname == domain || hostname.strip_suffix(domain).is_some_and(|prefix| prefix.ends_with('.'))
+}
+
+fn expand_system_root(path: &str, system_root: &str) -> String {
+    let lower = path.to_lowercase();
+    for variable in ["%systemroot%", "%windir%"] {
+        if lower.starts_with(variable) {
+            return format!("{}{}", system_root, &path[variable.len()..]);
+        }
+    }
+    path.to_string()
+}
+
+fn completed(configuration: &NameResolutionConfiguration, start_time: std::time::Instant) -> AuditEntry {
+    let mut entry = AuditEntry::new(
+        COMPONENT,
+        "INFO",
+        "complete_collection",
+        format!(
+            "Collected {} hosts entries, {} Winsock providers, {} NRPT rules and {} DNS server settings",
+            configuration.hosts_file.as_ref().map_or(0, |hosts| hosts.entries.len()),
+            configuration.winsock_providers.len(),
+            configuration.nrpt_rules.len(),
+            configuration.dns_servers.len()
+        ),
+        "success",
+    );
+    entry.duration_ms = Some(start_time.elapsed().as_millis() as u64);
+    entry
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hive::{HiveBuilder, REG_BINARY, REG_EXPAND_SZ, REG_MULTI_SZ};
+
+    fn catalog_item(path: &str, name: &str, chain_length: i32) -> Vec<u8> {
+        let mut item = vec![0u8; PROTOCOL_NAME_OFFSET + 512];
+        item[..path.len()].copy_from_slice(path.as_bytes());
+        item[CHAIN_LENGTH_OFFSET..CHAIN_LENGTH_OFFSET + 4].copy_from_slice(&chain_length.to_le_bytes());
+        for (index, unit) in name.encode_utf16().enumerate() {
+            item[PROTOCOL_NAME_OFFSET + index * 2..PROTOCOL_NAME_OFFSET + index * 2 + 2].copy_from_slice(&unit.to_le_bytes());
+        }
+        item
+    }
+
+    fn utf16(text: &str) -> Vec<u8> {
+        text.encode_utf16().chain([0]).flat_map(u16::to_le_bytes).collect()
+    }
+
+    #[test]
+    fn test_offline_name_resolution_tampering() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config = temp_dir.path().join("Windows").join("System32").join("config");
+        std::fs::create_dir_all(&config).unwrap();
+        let etc = temp_dir.path().join("Windows").join("System32").join("drivers").join("etc");
+        std::fs::create_dir_all(&etc).unwrap();
+        std::fs::write(etc.join("hosts"), "# localhost name resolution\n127.0.0.1 localhost\n0.0.0.0 update.eset.com definitions.symantec.com # block\n10.0.0.5 intranet.corp.local\n").unwrap();
+
+        let protocols = "ControlSet001\\Services\\WinSock2\\Parameters\\Protocol_Catalog9\\Catalog_Entries64";
+        let namespaces = "ControlSet001\\Services\\WinSock2\\Parameters\\NameSpace_Catalog5\\Catalog_Entries64";
+        let interfaces = "ControlSet001\\Services\\Tcpip\\Parameters\\Interfaces";
+        let system = HiveBuilder::default()
+            .dword("Select", "Current", 1)
+            .value("ControlSet001\\Services\\Tcpip\\Parameters", "DataBasePath", REG_EXPAND_SZ, &utf16("%SystemRoot%\\System32\\drivers\\etc"))
+            .value(&format!("{}\\000000000001", protocols), "PackedCatalogItem", REG_BINARY, &catalog_item("%SystemRoot%\\system32\\mswsock.dll", "MSAFD Tcpip [TCP/IP]", 1))
+            .value(&format!("{}\\000000000002", protocols), "PackedCatalogItem", REG_BINARY, &catalog_item("C:\\ProgramData\\netfilter.dll", "Filter over [TCP/IP]", 0))
+            .value(&format!("{}\\000000000001", namespaces), "LibraryPath", REG_EXPAND_SZ, &utf16("%SystemRoot%\\System32\\NLAapi.dll"))
+            .string(&format!("{}\\000000000001", namespaces), "DisplayString", "Network Location Awareness")
+            .dword(&format!("{}\\000000000001", namespaces), "Enabled", 1)
+            .string(&format!("{}\\{{11111111-aaaa}}", interfaces), "NameServer", "185.228.168.9,10.0.0.1")
+            .string(&format!("{}\\{{22222222-bbbb}}", interfaces), "DhcpNameServer", "192.168.1.1 192.168.1.2")
+            .string("ControlSet001\\Control\\Network\\{4D36E972-E325-11CE-BFC1-08002BE10318}\\{11111111-aaaa}\\Connection", "Name", "Ethernet")
+            .build();
+        std::fs::write(config.join("SYSTEM"), system).unwrap();
+        let software = HiveBuilder::default()
+            .value("Policies\\Microsoft\\Windows NT\\DNSClient\\DnsPolicyConfig\\Rule1", "Name", REG_MULTI_SZ, &[utf16(".crowdstrike.com"), vec![0, 0]].concat())
+            .string("Policies\\Microsoft\\Windows NT\\DNSClient\\DnsPolicyConfig\\Rule1", "GenericDNSServers", "127.0.0.1")
+            .value("Policies\\Microsoft\\Windows NT\\DNSClient\\DnsPolicyConfig\\Corp", "Name", REG_MULTI_SZ, &[utf16(".corp.local"), vec![0, 0]].concat())
+            .string("Policies\\Microsoft\\Windows NT\\DNSClient\\DnsPolicyConfig\\Corp", "GenericDNSServers", "10.0.0.53")
+            .build();
+        std::fs::write(config.join("SOFTWARE"), software).unwrap();
+
+        let (configuration, audit_log) = collect_name_resolution_offline(&OfflineRoot::new(temp_dir.path()).unwrap());
+
+        let hosts = configuration.hosts_file.unwrap();
+        assert!(hosts.default_location);
+        let redirected: Vec<&str> = hosts.entries.iter().filter(|e| e.suspicious).map(|e| e.hostname.as_str()).collect();
+        assert_eq!(redirected, vec!["update.eset.com", "definitions.symantec.com"]);
+        assert_eq!(hosts.entries.len(), 4);
+
+        let suspicious_providers: Vec<&str> = configuration.winsock_providers.iter().filter(|p| p.suspicious).map(|p| p.name.as_str()).collect();
+        assert_eq!(suspicious_providers, vec!["Filter over [TCP/IP]"]);
+        assert_eq!(configuration.winsock_providers.len(), 3);
+        assert_eq!(configuration.winsock_providers[2].enabled, Some(true));
+
+        let rules: Vec<(&str, bool)> = configuration.nrpt_rules.iter().map(|r| (r.name.as_str(), r.suspicious)).collect();
+        assert_eq!(rules, vec![("Rule1", true), ("Corp", false)]);
+
+        let ethernet = configuration.dns_servers.iter().find(|s| s.interface == "{11111111-aaaa}").unwrap();
+        assert_eq!(ethernet.interface_name.as_deref(), Some("Ethernet"));
+        assert_eq!(ethernet.suspicion_reasons, vec!["statically configured public DNS server 185.228.168.9"]);
+        let dhcp = configuration.dns_servers.iter().find(|s| s.interface == "{22222222-bbbb}").unwrap();
+        assert!(!dhcp.suspicious);
+        assert!(audit_log.iter().any(|log| log.action == "tampering_check"));
+    }
+}
@@ -0,0 +1,631 @@
+//! Network shares, firewall rules and proxy configuration
+//! Shares come from NetShareEnum on a live system and from the LanmanServer
+//! Shares key offline; a share is flagged when it is listed in
+//! NullSessionShares or its permissions reach ANONYMOUS LOGON (or Everyone,
+//! when EveryoneIncludesAnonymous is set). Firewall rules are read from the
+//! local and group policy stores the Windows Firewall service loads, and an
+//! enabled inbound allow rule with no program, port or remote address
+//! restriction is flagged. The machine WinHTTP proxy and each user's WinINET
+//! proxy and auto-config URL show where traffic may be redirected.
+
+use crate::binutil::{read_u16, read_u32, sid_to_string};
+use crate::footprint;
+use crate::forensic_types::{AuditEntry, FirewallRule, NetworkConfiguration, NetworkShare, ProxySettings, SharePermission};
+use crate::hive::{Hive, ValueData};
+use crate::offline::{MachineRegistry, OfflineRegistry, OfflineRoot};
+use std::path::Path;
+
+#[cfg(windows)]
+use crate::footprint::TrackedKey;
+#[cfg(windows)]
+use crate::offline::LiveRegistry;
+#[cfg(windows)]
+use crate::user_hives;
+#[cfg(windows)]
+use winreg::enums::HKEY_LOCAL_MACHINE;
+#[cfg(windows)]
+use winreg::RegKey;
+
+const COMPONENT: &str = "network_configuration";
+
+const SHARES_KEY: &str = "SYSTEM\\CurrentControlSet\\Services\\LanmanServer\\Shares";
+const SHARE_SECURITY_KEY: &str = "SYSTEM\\CurrentControlSet\\Services\\LanmanServer\\Shares\\Security";
+const LANMAN_PARAMETERS_KEY: &str = "SYSTEM\\CurrentControlSet\\Services\\LanmanServer\\Parameters";
+const LSA_KEY: &str = "SYSTEM\\CurrentControlSet\\Control\\Lsa";
+/// Firewall rule stores: (policy store, key whose values are the rules)
+const FIREWALL_RULE_KEYS: [(&str, &str); 2] = [
+    ("local", "SYSTEM\\CurrentControlSet\\Services\\SharedAccess\\Parameters\\FirewallPolicy\\FirewallRules"),
+    ("group_policy", "SOFTWARE\\Policies\\Microsoft\\WindowsFirewall\\FirewallRules"),
+];
+const WINHTTP_KEY: &str = "SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Internet Settings\\Connections";
+/// WinINET settings, relative to NTUSER.DAT
+const USER_INTERNET_SETTINGS_KEY: &str = "Software\\Microsoft\\Windows\\CurrentVersion\\Internet Settings";
+const USERS_DIRECTORY: &str = "C:\\Users";
+
+const STYPE_SPECIAL: u32 = 0x8000_0000;
+const STYPE_TEMPORARY: u32 = 0x4000_0000;
+const SE_DACL_PRESENT: u16 = 0x0004;
+const SE_SELF_RELATIVE: u16 = 0x8000;
+const ACCESS_ALLOWED_ACE_TYPE: u8 = 0;
+const ACCESS_DENIED_ACE_TYPE: u8 = 1;
+/// Connection settings flag: a proxy server is used
+const PROXY_TYPE_PROXY: u32 = 0x2;
+/// Connection settings flag: an auto-config (PAC) URL is used
+const PROXY_TYPE_AUTO_PROXY_URL: u32 = 0x4;
+
+const EVERYONE: &str = "Everyone";
+const ANONYMOUS_LOGON: &str = "ANONYMOUS LOGON";
+const WELL_KNOWN_SIDS: [(&str, &str); 9] = [
+    ("S-1-1-0", EVERYONE),
+    ("S-1-5-2", "NETWORK"),
+    ("S-1-5-4", "INTERACTIVE"),
+    ("S-1-5-7", ANONYMOUS_LOGON),
+    ("S-1-5-11", "Authenticated Users"),
+    ("S-1-5-18", "SYSTEM"),
+    ("S-1-5-32-544", "BUILTIN\\Administrators"),
+    ("S-1-5-32-545", "BUILTIN\\Users"),
+    ("S-1-5-32-547", "BUILTIN\\Power Users"),
+];
+
+/// A share as NetShareEnum or the Shares key describes it
+struct RawShare {
+    name: String,
+    path: String,
+    remark: String,
+    share_type: u32,
+    current_uses: u32,
+    /// Self-relative security descriptor; None when the share has the default permissions
+    security_descriptor: Option<Vec<u8>>,
+}
+
+/// Shares, firewall rules and proxies of the running system
+#[cfg(windows)]
+pub fn collect_network_configuration() -> (NetworkConfiguration, Vec<AuditEntry>) {
+    let start_time = std::time::Instant::now();
+    let mut audit_log = vec![AuditEntry::new(COMPONENT, "INFO", "start_collection", "Starting network configuration collection".to_string(), "started")];
+
+    let registry = LiveRegistry(RegKey::predef(HKEY_LOCAL_MACHINE));
+    let shares = windows_api::shares().unwrap_or_else(|e| {
+        audit_log.push(AuditEntry::new(
+            COMPONENT,
+            "WARN",
+            "enumerate_shares",
+            format!("NetShareEnum failed: {}; reading the LanmanServer Shares key instead", e),
+            "error",
+        ));
+        registry_shares(&registry)
+    });
+    let mut configuration = machine_configuration(&registry, shares, &mut audit_log);
+
+    let (hives, failures) = user_hives::live_user_hives();
+    for failure in failures {
+        audit_log.push(AuditEntry::new(COMPONENT, "WARN", "load_user_hive", failure, "error"));
+    }
+    configuration.user_proxy_settings = hives
+        .iter()
+        .filter_map(|hive| {
+            let settings = hive.key.open_tracked(USER_INTERNET_SETTINGS_KEY).ok()?;
+            let string = |name: &str| settings.get_value::<String, _>(name).ok();
+            user_proxy(
+                &hive.user,
+                format!("{}\\{}", hive.source, USER_INTERNET_SETTINGS_KEY),
+                settings.get_value::<u32, _>("ProxyEnable").ok(),
+                string("ProxyServer"),
+                string("ProxyOverride"),
+                string("AutoConfigURL"),
+            )
+        })
+        .collect();
+
+    audit_log.push(completed(&configuration, start_time));
+    (configuration, audit_log)
+}
+
+#[cfg(not(windows))]
+pub fn collect_network_configuration() -> (NetworkConfiguration, Vec<AuditEntry>) {
+    let audit_log = vec![
+        AuditEntry::new(COMPONENT, "INFO", "start_collection", "Starting network configuration collection".to_string(), "started"),
+        AuditEntry::new(COMPONENT, "WARN", "registry_access", "Network configuration collection requires Windows".to_string(), "unsupported"),
+        AuditEntry::new(COMPONENT, "INFO", "complete_collection", "Collected 0 shares, 0 firewall rules and 0 user proxies".to_string(), "success"),
+    ];
+    (NetworkConfiguration::default(), audit_log)
+}
+
+/// Shares, firewall rules and proxies from the SOFTWARE, SYSTEM and user hives
+/// of a mounted Windows volume
+pub fn collect_network_configuration_offline(root: &OfflineRoot) -> (NetworkConfiguration, Vec<AuditEntry>) {
+    let start_time = std::time::Instant::now();
+    let mut audit_log = vec![AuditEntry::new(
+        COMPONENT,
+        "INFO",
+        "start_collection",
+        format!("Starting offline network configuration collection of {}", root.root().display()),
+        "started",
+    )];
+    let mut open = |name: &str| match root.open_hive(name) {
+        Ok(hive) => Some(hive),
+        Err(e) => {
+            audit_log.push(AuditEntry::new(COMPONENT, "ERROR", "load_hive", format!("Failed to load offline {} hive: {}", name, e), "error"));
+            None
+        }
+    };
+    let (software, system) = (open("SOFTWARE"), open("SYSTEM"));
+
+    let registry = OfflineRegistry::new(software, system);
+    let shares = registry_shares(&registry);
+    let mut configuration = machine_configuration(&registry, shares, &mut audit_log);
+    configuration.user_proxy_settings = offline_user_proxies(&root.resolve(USERS_DIRECTORY), &mut audit_log);
+
+    audit_log.push(completed(&configuration, start_time));
+    (configuration, audit_log)
+}
+
+fn machine_configuration(registry: &dyn MachineRegistry, shares: Vec<RawShare>, audit_log: &mut Vec<AuditEntry>) -> NetworkConfiguration {
+    let null_session_shares = match registry.value(LANMAN_PARAMETERS_KEY, "NullSessionShares").map(|value| value.typed()) {
+        Some(ValueData::MultiString(names)) => names,
+        _ => Vec::new(),
+    };
+    let everyone_includes_anonymous = registry.value(LSA_KEY, "EveryoneIncludesAnonymous").and_then(|value| value.as_u32()) == Some(1);
+    let network_shares: Vec<NetworkShare> = shares.into_iter().map(|share| network_share(share, &null_session_shares, everyone_includes_anonymous)).collect();
+
+    let mut firewall_rules = Vec::new();
+    for (policy_store, key) in FIREWALL_RULE_KEYS {
+        match registry.values(key) {
+            Ok(values) => firewall_rules.extend(values.iter().filter_map(|value| parse_firewall_rule(policy_store, &value.as_string()?))),
+            Err(e) => audit_log.push(AuditEntry::new(COMPONENT, "WARN", "firewall_rules", format!("Failed to read HKLM\\{}: {}", key, e), "error")),
+        }
+    }
+
+    let proxy_settings = registry
+        .value(WINHTTP_KEY, "WinHttpSettings")
+        .and_then(|value| connection_settings(&value.data))
+        .map(|settings| ProxySettings { source: format!("HKLM\\{}\\WinHttpSettings", WINHTTP_KEY), ..settings })
+        .unwrap_or_default();
+
+    let anonymous = network_shares.iter().filter(|share| share.anonymous_access).count();
+    let allow_all = firewall_rules.iter().filter(|rule| rule.allow_all).count();
+    if anonymous + allow_all > 0 {
+        audit_log.push(AuditEntry::new(
+            COMPONENT,
+            "WARN",
+            "exposure_check",
+            format!("{} shares reachable anonymously, {} inbound rules allowing any connection", anonymous, allow_all),
+            "warning",
+        ));
+    }
+    NetworkConfiguration { network_shares, firewall_rules, proxy_settings, user_proxy_settings: Vec::new() }
+}
+
+/// Shares persisted by the Server service: one REG_MULTI_SZ of `Name=value`
+/// lines per share, and its security descriptor under Shares\Security
+fn registry_shares(registry: &dyn MachineRegistry) -> Vec<RawShare> {
+    let descriptors = registry.values(SHARE_SECURITY_KEY).unwrap_or_default();
+    registry
+        .values(SHARES_KEY)
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|value| {
+            let ValueData::MultiString(lines) = value.typed() else { return None };
+            let field = |name: &str| {
+                lines
+                    .iter()
+                    .filter_map(|line| line.split_once('='))
+                    .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                    .map(|(_, text)| text.to_string())
+                    .unwrap_or_default()
+            };
+            let security_descriptor = descriptors.iter().find(|descriptor| descriptor.name.eq_ignore_ascii_case(&value.name)).map(|descriptor| descriptor.data.clone());
+            Some(RawShare {
+                path: field("Path"),
+                remark: field("Remark"),
+                share_type: field("Type").parse().unwrap_or(0),
+                current_uses: 0,
+                security_descriptor,
+                name: value.name,
+            })
+        })
+        .collect()
+}
+
+fn network_share(share: RawShare, null_session_shares: &[String], everyone_includes_anonymous: bool) -> NetworkShare {
+    let permissions = share.security_descriptor.as_deref().map(share_permissions).unwrap_or_default();
+    let anonymous_access = null_session_shares.iter().any(|name| name.eq_ignore_ascii_case(&share.name))
+        || permissions.iter().any(|permission| {
+            permission.access_type == "allow" && (permission.account == ANONYMOUS_LOGON || (everyone_includes_anonymous && permission.account == EVERYONE))
+        });
+    NetworkShare {
+        share_type: share_type(share.share_type),
+        name: share.name,
+        path: share.path,
+        description: share.remark,
+        permissions,
+        current_connections: share.current_uses,
+        anonymous_access,
+    }
+}
+
+fn share_type(share_type: u32) -> String {
+    let base = match share_type & 0xFF {
+        0 => "disk",
+        1 => "print",
+        2 => "device",
+        3 => "ipc",
+        _ => "unknown",
+    };
+    let mut text = base.to_string();
+    if share_type & STYPE_SPECIAL != 0 {
+        text.push_str(" (administrative)");
+    }
+    if share_type & STYPE_TEMPORARY != 0 {
+        text.push_str(" (temporary)");
+    }
+    text
+}
+
+/// Access control entries of a self-relative security descriptor; a
+/// descriptor without a DACL grants everyone full control
+fn share_permissions(descriptor: &[u8]) -> Vec<SharePermission> {
+    let Some(control) = read_u16(descriptor, 2).filter(|control| control & SE_SELF_RELATIVE != 0) else {
+        return Vec::new();
+    };
+    let dacl = read_u32(descriptor, 16).unwrap_or(0) as usize;
+    if control & SE_DACL_PRESENT == 0 || dacl == 0 {
+        return vec![SharePermission { account: EVERYONE.to_string(), access_type: "allow".to_string(), permissions: "Full".to_string() }];
+    }
+
+    let mut permissions = Vec::new();
+    let mut position = dacl + 8;
+    for _ in 0..read_u16(descriptor, dacl + 4).unwrap_or(0) {
+        let (Some(&ace_type), Some(ace_size), Some(mask)) = (descriptor.get(position), read_u16(descriptor, position + 2), read_u32(descriptor, position + 4)) else {
+            break;
+        };
+        let ace_size = ace_size as usize;
+        if ace_size < 8 {
+            break;
+        }
+        let access_type = match ace_type {
+            ACCESS_ALLOWED_ACE_TYPE => Some("allow"),
+            ACCESS_DENIED_ACE_TYPE => Some("deny"),
+            _ => None,
+        };
+        if let (Some(access_type), Some(sid)) = (access_type, descriptor.get(position + 8..position + ace_size).and_then(sid_to_string)) {
+            let account = WELL_KNOWN_SIDS.iter().find(|(known, _)| *known == sid).map(|(_, name)| name.to_string()).unwrap_or(sid);
+            permissions.push(SharePermission { account, access_type: access_type.to_string(), permissions: share_access(mask) });
+        }
+        position += ace_size;
+    }
+    permissions
+}
+
+/// Share permission names of the Advanced Sharing dialog
+fn share_access(mask: u32) -> String {
+    const GENERIC_ALL: u32 = 0x1000_0000;
+    match mask {
+        mask if mask & GENERIC_ALL != 0 || mask & 0x1F01FF == 0x1F01FF => "Full".to_string(),
+        mask if mask & 0x1301BF == 0x1301BF => "Change".to_string(),
+        mask if mask & 0x1200A9 == 0x1200A9 => "Read".to_string(),
+        mask => format!("0x{:x}", mask),
+    }
+}
+
+/// Firewall rule string of the policy store:
+/// `v2.30|Action=Allow|Active=TRUE|Dir=In|Protocol=6|LPort=3389|Name=...|`
+fn parse_firewall_rule(policy_store: &str, text: &str) -> Option<FirewallRule> {
+    let mut fields = text.split('|');
+    fields.next().filter(|version| version.starts_with('v'))?;
+    let pairs: Vec<(&str, &str)> = fields.filter_map(|field| field.split_once('=')).collect();
+    let first = |name: &str| pairs.iter().find(|(key, _)| *key == name).map(|(_, value)| value.to_string()).unwrap_or_default();
+    let all = |prefixes: &[&str], none: &str| {
+        let values: Vec<&str> = pairs.iter().filter(|(key, _)| prefixes.iter().any(|prefix| key.starts_with(prefix))).map(|(_, value)| *value).collect();
+        if values.is_empty() {
+            none.to_string()
+        } else {
+            values.join(",")
+        }
+    };
+
+    let protocol = match first("Protocol").as_str() {
+        "" => "Any".to_string(),
+        "1" => "ICMPv4".to_string(),
+        "6" => "TCP".to_string(),
+        "17" => "UDP".to_string(),
+        "58" => "ICMPv6".to_string(),
+        other => other.to_string(),
+    };
+    let mut rule = FirewallRule {
+        name: first("Name"),
+        description: first("Desc"),
+        direction: if first("Dir").eq_ignore_ascii_case("In") { "inbound" } else { "outbound" }.to_string(),
+        action: first("Action").to_lowercase(),
+        protocol,
+        local_ports: all(&["LPort"], "Any"),
+        remote_ports: all(&["RPort"], "Any"),
+        local_addresses: all(&["LA4", "LA6"], "Any"),
+        remote_addresses: all(&["RA4", "RA6"], "Any"),
+        enabled: first("Active").eq_ignore_ascii_case("TRUE"),
+        profile: all(&["Profile"], "All"),
+        application: first("App"),
+        service: first("Svc"),
+        policy_store: policy_store.to_string(),
+        allow_all: false,
+    };
+    rule.allow_all = rule.enabled
+        && rule.action == "allow"
+        && rule.direction == "inbound"
+        && rule.application.is_empty()
+        && rule.service.is_empty()
+        && rule.local_ports == "Any"
+        && rule.remote_addresses == "Any";
+    Some(rule)
+}
+
+/// WinHttpSettings (and WinINET DefaultConnectionSettings) blob: version,
+/// change counter and flags, then the length-prefixed proxy server, bypass
+/// list and auto-config URL
+fn connection_settings(data: &[u8]) -> Option<ProxySettings> {
+    let flags = read_u32(data, 8)?;
+    let mut position = 12;
+    let mut next_string = || {
+        let length = read_u32(data, position)? as usize;
+        let text = String::from_utf8_lossy(data.get(position + 4..position + 4 + length)?).trim_end_matches('\0').to_string();
+        position += 4 + length;
+        Some(text)
+    };
+    let server = next_string().unwrap_or_default();
+    let bypass = next_string().unwrap_or_default();
+    let auto_config_url = next_string().unwrap_or_default();
+    let (server, port) = split_proxy_server(&server);
+    Some(ProxySettings {
+        enabled: flags & (PROXY_TYPE_PROXY | PROXY_TYPE_AUTO_PROXY_URL) != 0,
+        server,
+        port,
+        bypass_list: split_bypass_list(&bypass),
+        auto_config_url,
+        user: String::new(),
+        source: String::new(),
+    })
+}
+
+/// WinINET proxy of one user; None when neither a proxy nor an auto-config URL is set
+fn user_proxy(user: &str, source: String, enable: Option<u32>, server: Option<String>, bypass: Option<String>, auto_config_url: Option<String>) -> Option<ProxySettings> {
+    let (server, auto_config_url) = (server.unwrap_or_default(), auto_config_url.unwrap_or_default());
+    if server.is_empty() && auto_config_url.is_empty() {
+        return None;
+    }
+    let (server, port) = split_proxy_server(&server);
+    Some(ProxySettings {
+        enabled: enable == Some(1) || !auto_config_url.is_empty(),
+        server,
+        port,
+        bypass_list: split_bypass_list(&bypass.unwrap_or_default()),
+        auto_config_url,
+        user: user.to_string(),
+        source,
+    })
+}
+
+fn offline_user_proxies(users_dir: &Path, audit_log: &mut Vec<AuditEntry>) -> Vec<ProxySettings> {
+    let profiles = match footprint::read_dir(users_dir) {
+        Ok(profiles) => profiles,
+        Err(e) => {
+            audit_log.push(AuditEntry::new(COMPONENT, "WARN", "directory_check", format!("Users directory not readable: {} ({})", users_dir.display(), e), "not_found"));
+            return Vec::new();
+        }
+    };
+
+    let mut proxies = Vec::new();
+    for profile in profiles.filter_map(|p| p.ok()) {
+        let hive_path = profile.path().join("NTUSER.DAT");
+        if !hive_path.is_file() {
+            continue;
+        }
+        let hive = match Hive::open(&hive_path) {
+            Ok(hive) => hive,
+            Err(e) => {
+                audit_log.push(AuditEntry::new(COMPONENT, "WARN", "load_user_hive", format!("Failed to load {}: {}", hive_path.display(), e), "error"));
+                continue;
+            }
+        };
+        let Some(settings) = hive.open_key(USER_INTERNET_SETTINGS_KEY) else { continue };
+        proxies.extend(user_proxy(
+            &profile.file_name().to_string_lossy(),
+            format!("{}\\{}", hive_path.display(), USER_INTERNET_SETTINGS_KEY),
+            settings.u32("ProxyEnable"),
+            settings.string("ProxyServer"),
+            settings.string("ProxyOverride"),
+            settings.string("AutoConfigURL"),
+        ));
+    }
+    proxies
+}
+
+/// `host:port`; per-protocol lists (`http=a:80;https=b:443`) are kept whole
+fn split_proxy_server(server: &str) -> (String, u16) {
+    if server.contains('=') || server.contains(';') {
+        return (server.to_string(), 0);
+    }
+    match server.rsplit_once(':').and_then(|(host, port)| Some((host, port.parse().ok()?))) {
+        Some((host, port)) => (host.to_string(), port),
+        None => (server.to_string(), 0),
+    }
+}
+
+fn split_bypass_list(bypass: &str) -> Vec<String> {
+    bypass.split(';').map(str::trim).filter(|entry| !entry.is_empty()).map(String::from).collect()
+}
+
+fn completed(configuration: &NetworkConfiguration, start_time: std::time::Instant) -> AuditEntry {
+    let mut entry = AuditEntry::new(
+        COMPONENT,
+        "INFO",
+        "complete_collection",
+        format!(
+            "Collected {} shares, {} firewall rules and {} user proxies",
+            configuration.network_shares.len(),
+            configuration.firewall_rules.len(),
+            configuration.user_proxy_settings.len()
+        ),
+        "success",
+    );
+    entry.duration_ms = Some(start_time.elapsed().as_millis() as u64);
+    entry
+}
+
+#[cfg(windows)]
+mod windows_api {
+    use super::RawShare;
+    use windows::core::{PCWSTR, PWSTR};
+    use windows::Win32::NetworkManagement::NetManagement::{NetApiBufferFree, MAX_PREFERRED_LENGTH, NERR_Success};
+    use windows::Win32::Security::GetSecurityDescriptorLength;
+    use windows::Win32::Storage::FileSystem::{NetShareEnum, SHARE_INFO_502};
+
+    /// Level 502 includes the security descriptor and needs administrator rights
+    pub fn shares() -> Result<Vec<RawShare>, String> {
+        let mut buffer: *mut u8 = std::ptr::null_mut();
+        let (mut read, mut total) = (0u32, 0u32);
+        let status = unsafe { NetShareEnum(PCWSTR::null(), 502, &mut buffer, MAX_PREFERRED_LENGTH, &mut read, &mut total, None) };
+        if status != NERR_Success {
+            free(buffer);
+            return Err(format!("error {}", status));
+        }
+
+        let shares = (0..read as usize)
+            .map(|i| {
+                let info = unsafe { &*(buffer as *const SHARE_INFO_502).add(i) };
+                let descriptor = info.shi502_security_descriptor;
+                let security_descriptor = (!descriptor.0.is_null()).then(|| {
+                    let length = unsafe { GetSecurityDescriptorLength(descriptor) } as usize;
+                    unsafe { std::slice::from_raw_parts(descriptor.0 as *const u8, length) }.to_vec()
+                });
+                RawShare {
+                    name: wide(info.shi502_netname),
+                    path: wide(info.shi502_path),
+                    remark: wide(info.shi502_remark),
+                    share_type: info.shi502_type.0,
+                    current_uses: info.shi502_current_uses,
+                    security_descriptor,
+                }
+            })
+            .collect();
+        free(buffer);
+        Ok(shares)
+    }
+
+    fn wide(value: PWSTR) -> String {
+        if value.is_null() {
+            return String::new();
+        }
+        unsafe { value.to_string() }.unwrap_or_default()
+    }
+
+    fn free(buffer: *mut u8) {
+        if !buffer.is_null() {
+            unsafe { NetApiBufferFree(Some(buffer as *const _)) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hive::{HiveBuilder, REG_BINARY, REG_MULTI_SZ};
+
+    fn multi_sz(lines: &[&str]) -> Vec<u8> {
+        lines.iter().flat_map(|line| line.encode_utf16().chain([0])).chain([0]).flat_map(u16::to_le_bytes).collect()
+    }
+
+    /// Self-relative descriptor whose DACL allows each (SID, mask)
+    fn security_descriptor(aces: &[(&[u8], u32)]) -> Vec<u8> {
+        let ace_bytes: Vec<Vec<u8>> = aces
+            .iter()
+            .map(|(sid, mask)| {
+                let size = (8 + sid.len()) as u16;
+                [vec![ACCESS_ALLOWED_ACE_TYPE, 0], size.to_le_bytes().to_vec(), mask.to_le_bytes().to_vec(), sid.to_vec()].concat()
+            })
+            .collect();
+        let acl_size = (8 + ace_bytes.iter().map(Vec::len).sum::<usize>()) as u16;
+        let mut descriptor = vec![1, 0];
+        descriptor.extend((SE_SELF_RELATIVE | SE_DACL_PRESENT).to_le_bytes());
+        descriptor.extend([0u32, 0, 0, 20].iter().flat_map(|offset| offset.to_le_bytes()));
+        descriptor.extend([2, 0]);
+        descriptor.extend(acl_size.to_le_bytes());
+        descriptor.extend((aces.len() as u16).to_le_bytes());
+        descriptor.extend([0, 0]);
+        descriptor.extend(ace_bytes.concat());
+        descriptor
+    }
+
+    #[test]
+    fn test_offline_network_configuration() {
+        const ANONYMOUS_SID: &[u8] = &[1, 1, 0, 0, 0, 0, 0, 5, 7, 0, 0, 0];
+        const ADMINISTRATORS_SID: &[u8] = &[1, 2, 0, 0, 0, 0, 0, 5, 32, 0, 0, 0, 0x20, 2, 0, 0];
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config = temp_dir.path().join("Windows").join("System32").join("config");
+        std::fs::create_dir_all(&config).unwrap();
+        let profile = temp_dir.path().join("Users").join("bob");
+        std::fs::create_dir_all(&profile).unwrap();
+
+        let rules = "ControlSet001\\Services\\SharedAccess\\Parameters\\FirewallPolicy\\FirewallRules";
+        let system = HiveBuilder::default()
+            .dword("Select", "Current", 1)
+            .value("ControlSet001\\Services\\LanmanServer\\Shares", "drop", REG_MULTI_SZ, &multi_sz(&["Path=C:\\drop", "Remark=staging", "Type=0"]))
+            .value("ControlSet001\\Services\\LanmanServer\\Shares", "Finance", REG_MULTI_SZ, &multi_sz(&["Path=D:\\Finance", "Type=0"]))
+            .value("ControlSet001\\Services\\LanmanServer\\Shares\\Security", "drop", REG_BINARY, &security_descriptor(&[(ANONYMOUS_SID, 0x1F01FF)]))
+            .value("ControlSet001\\Services\\LanmanServer\\Shares\\Security", "Finance", REG_BINARY, &security_descriptor(&[(ADMINISTRATORS_SID, 0x1200A9)]))
+            .string(rules, "{rule-1}", "v2.30|Action=Allow|Active=TRUE|Dir=In|Protocol=6|Name=open door|")
+            .string(rules, "RemoteDesktop-UserMode-In-TCP", "v2.30|Action=Allow|Active=TRUE|Dir=In|Protocol=6|Profile=Domain|Profile=Private|LPort=3389|App=%SystemRoot%\\system32\\svchost.exe|Svc=termservice|Name=@FirewallAPI.dll,-28775|")
+            .build();
+        let winhttp = [&[0x28, 0, 0, 0, 1, 0, 0, 0, 3, 0, 0, 0, 15, 0, 0, 0][..], b"proxy.corp:8080", &[7, 0, 0, 0], b"<local>"].concat();
+        let software = HiveBuilder::default()
+            .value("Microsoft\\Windows\\CurrentVersion\\Internet Settings\\Connections", "WinHttpSettings", REG_BINARY, &winhttp)
+            .build();
+        let ntuser = HiveBuilder::default()
+            .dword(USER_INTERNET_SETTINGS_KEY, "ProxyEnable", 1)
+            .string(USER_INTERNET_SETTINGS_KEY, "ProxyServer", "http=127.0.0.1:8888;https=127.0.0.1:8888")
+            .string(USER_INTERNET_SETTINGS_KEY, "ProxyOverride", "*.corp; <local>")
+            .build();
+        std::fs::write(config.join("SYSTEM"), system).unwrap();
+        std::fs::write(config.join("SOFTWARE"), software).unwrap();
+        std::fs::write(profile.join("NTUSER.DAT"), ntuser).unwrap();
+        let root = OfflineRoot::new(temp_dir.path()).unwrap();
+
+        let (configuration, audit_log) = collect_network_configuration_offline(&root);
+        let share = |name: &str| configuration.network_shares.iter().find(|share| share.name == name).unwrap();
+        assert!(share("drop").anonymous_access);
+        assert_eq!((share("drop").path.as_str(), share("drop").permissions[0].permissions.as_str()), ("C:\\drop", "Full"));
+        assert!(!share("Finance").anonymous_access);
+        assert_eq!(share("Finance").permissions[0].account, "BUILTIN\\Administrators");
+
+        let rule = |name: &str| configuration.firewall_rules.iter().find(|rule| rule.name == name).unwrap();
+        assert!(rule("open door").allow_all);
+        let rdp = rule("@FirewallAPI.dll,-28775");
+        assert!(!rdp.allow_all);
+        assert_eq!((rdp.local_ports.as_str(), rdp.profile.as_str(), rdp.service.as_str()), ("3389", "Domain,Private", "termservice"));
+
+        let machine = &configuration.proxy_settings;
+        assert_eq!((machine.enabled, machine.server.as_str(), machine.port), (true, "proxy.corp", 8080));
+        assert_eq!(machine.bypass_list, vec!["<local>"]);
+        let user = &configuration.user_proxy_settings[0];
+        assert_eq!((user.user.as_str(), user.server.as_str(), user.enabled), ("bob", "http=127.0.0.1:8888;https=127.0.0.1:8888", true));
+        assert_eq!(user.bypass_list, vec!["*.corp", "<local>"]);
+        assert!(audit_log.iter().any(|log| log.action == "exposure_check" && log.details.starts_with("1 shares")));
+    }
+
+    #[test]
+    fn test_null_session_share_and_everyone() {
+        const EVERYONE_SID: &[u8] = &[1, 1, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0];
+        let raw = |name: &str| RawShare {
+            name: name.to_string(),
+            path: String::new(),
+            remark: String::new(),
+            share_type: STYPE_SPECIAL,
+            current_uses: 0,
+            security_descriptor: Some(security_descriptor(&[(EVERYONE_SID, 0x1200A9)])),
+        };
+        assert!(network_share(raw("public"), &["PUBLIC".to_string()], false).anonymous_access);
+        assert!(!network_share(raw("data"), &[], false).anonymous_access);
+        let share = network_share(raw("data"), &[], true);
+        assert!(share.anonymous_access);
+        assert_eq!((share.share_type.as_str(), share.permissions[0].permissions.as_str()), ("disk (administrative)", "Read"));
+    }
+}
@@ -0,0 +1,514 @@
+//! Host firewall and DNS log ingestion
+//! The connection table only shows what is connected at collection time. When
+//! the host logs them, the Windows Firewall log (pfirewall.log), the DNS server
+//! debug log (dns.log) and the DNS client operational event log record earlier
+//! connections and name lookups. Records inside the scan time window are kept;
+//! logs that are not enabled on the host are simply absent.
+
+// Log locations from the registry are only reachable through the Windows registry API
+#![cfg_attr(not(windows), allow(dead_code))]
+
+use crate::event_logs::{self, EventChannelQuery};
+use crate::footprint;
+use crate::forensic_types::{AuditEntry, DnsLogEntry, FirewallLogEntry, NetworkLogs};
+use crate::offline::OfflineRoot;
+use crate::time_window::TimeWindow;
+use crate::types::EventLogEntry;
+use chrono::NaiveDateTime;
+use regex::Regex;
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+#[cfg(windows)]
+use crate::footprint::TrackedKey;
+#[cfg(windows)]
+use crate::offline;
+#[cfg(windows)]
+use winreg::enums::*;
+#[cfg(windows)]
+use winreg::RegKey;
+
+const COMPONENT: &str = "network_logs";
+
+const DEFAULT_FIREWALL_LOG: &str = "C:\\Windows\\System32\\LogFiles\\Firewall\\pfirewall.log";
+const DEFAULT_DNS_SERVER_LOG: &str = "C:\\Windows\\System32\\dns\\dns.log";
+
+const DNS_CLIENT_CHANNEL: &str = "Microsoft-Windows-DNS-Client/Operational";
+/// Query completed events of the DNS client
+const DNS_CLIENT_QUERY_EVENT: u32 = 3008;
+const MAX_DNS_CLIENT_EVENTS: usize = 10000;
+
+/// Most recent records kept per log file
+const MAX_RECORDS_PER_LOG: usize = 100000;
+
+/// Field layout of pfirewall.log when the file has no #Fields header
+const DEFAULT_FIREWALL_FIELDS: [&str; 17] = [
+    "date", "time", "action", "protocol", "src-ip", "dst-ip", "src-port", "dst-port", "size",
+    "tcpflags", "tcpsyn", "tcpack", "tcpwin", "icmptype", "icmpcode", "info", "path",
+];
+
+#[cfg(windows)]
+const FIREWALL_PROFILES: [&str; 3] = ["DomainProfile", "StandardProfile", "PublicProfile"];
+#[cfg(windows)]
+const FIREWALL_POLICY_PROFILES: [&str; 3] = ["DomainProfile", "PrivateProfile", "PublicProfile"];
+
+/// Collect the firewall and DNS logs of the live system, or of a mounted Windows
+/// volume when an offline root is given
+pub fn collect_network_logs(root: Option<&OfflineRoot>, window: &TimeWindow) -> (NetworkLogs, Vec<AuditEntry>) {
+    let start_time = std::time::Instant::now();
+    let mut audit_log = vec![AuditEntry::new(COMPONENT, "INFO", "start_collection", "Starting firewall and DNS log collection".to_string(), "started")];
+
+    let (firewall_paths, dns_paths) = configured_log_paths(root, &mut audit_log);
+    let resolve = |path: &str| match root {
+        Some(root) => root.resolve(path),
+        None => PathBuf::from(path),
+    };
+    let firewall_files = existing_files(firewall_paths.iter().flat_map(|p| [resolve(p), resolve(&format!("{}.old", p))]));
+    let dns_files = existing_files(dns_paths.iter().map(|p| resolve(p)));
+
+    let mut network_logs = NetworkLogs::default();
+    if firewall_files.is_empty() {
+        audit_log.push(AuditEntry::new(COMPONENT, "INFO", "find_firewall_log", "No firewall log found (connection logging not enabled)".to_string(), "not_found"));
+    }
+    for path in &firewall_files {
+        let source_file = path.display().to_string();
+        let mut fields: Vec<String> = DEFAULT_FIREWALL_FIELDS.iter().map(|f| f.to_string()).collect();
+        let parse = |line: &str| match line.strip_prefix("#Fields:") {
+            Some(header) => {
+                fields = header.split_whitespace().map(|f| f.to_lowercase()).collect();
+                None
+            }
+            None => parse_firewall_line(line, &fields, &source_file),
+        };
+        network_logs.firewall.extend(read_log(path, window, &mut audit_log, parse, |e: &FirewallLogEntry| &e.timestamp));
+    }
+
+    if dns_files.is_empty() {
+        audit_log.push(AuditEntry::new(COMPONENT, "INFO", "find_dns_log", "No DNS server debug log found (debug logging not enabled)".to_string(), "not_found"));
+    }
+    for path in &dns_files {
+        let source_file = path.display().to_string();
+        let parse = |line: &str| parse_dns_server_line(line, &source_file);
+        network_logs.dns.extend(read_log(path, window, &mut audit_log, parse, |e: &DnsLogEntry| &e.timestamp));
+    }
+
+    network_logs.dns.extend(collect_dns_client_events(root, window, &mut audit_log));
+
+    let duration = start_time.elapsed();
+    let mut complete = AuditEntry::new(
+        COMPONENT,
+        "INFO",
+        "complete_collection",
+        format!("Collected {} firewall log records and {} DNS log records", network_logs.firewall.len(), network_logs.dns.len()),
+        "success",
+    );
+    complete.duration_ms = Some(duration.as_millis() as u64);
+    audit_log.push(complete);
+
+    (network_logs, audit_log)
+}
+
+/// Firewall and DNS server log locations: the defaults plus any path configured
+/// in the firewall profiles, firewall policy or DNS server parameters
+fn configured_log_paths(root: Option<&OfflineRoot>, audit_log: &mut Vec<AuditEntry>) -> (Vec<String>, Vec<String>) {
+    let mut firewall_paths = vec![DEFAULT_FIREWALL_LOG.to_string()];
+    let mut dns_paths = vec![DEFAULT_DNS_SERVER_LOG.to_string()];
+
+    for (kind, path) in registry_log_paths(root, audit_log) {
+        let path = expand_system_root(&path);
+        let paths = if kind == "firewall" { &mut firewall_paths } else { &mut dns_paths };
+        if !paths.iter().any(|p| p.eq_ignore_ascii_case(&path)) {
+            paths.push(path);
+        }
+    }
+
+    (firewall_paths, dns_paths)
+}
+
+/// `(firewall|dns, LogFilePath)` values configured on the system
+#[cfg(windows)]
+fn registry_log_paths(root: Option<&OfflineRoot>, audit_log: &mut Vec<AuditEntry>) -> Vec<(&'static str, String)> {
+    let (control_set, software) = match root {
+        Some(root) => {
+            let control_set = match root.load_hive("SYSTEM") {
+                Ok(system) => system.open_tracked(offline::current_control_set(&system)).ok(),
+                Err(e) => {
+                    audit_log.push(AuditEntry::new(COMPONENT, "WARN", "load_hive", format!("Failed to load offline SYSTEM hive: {}", e), "error"));
+                    None
+                }
+            };
+            (control_set, root.load_hive("SOFTWARE").ok())
+        }
+        None => {
+            let hklm = RegKey::predef(HKEY_LOCAL_MACHINE);
+            (hklm.open_tracked("SYSTEM\\CurrentControlSet").ok(), hklm.open_tracked("SOFTWARE").ok())
+        }
+    };
+
+    let mut paths = Vec::new();
+    let mut log_file_path = |kind: &'static str, key: Option<&RegKey>, subkey: String| {
+        if let Some(path) = key
+            .and_then(|key| key.open_tracked(subkey).ok())
+            .and_then(|key| key.get_value::<String, _>("LogFilePath").ok())
+            .filter(|path| !path.trim().is_empty())
+        {
+            paths.push((kind, path));
+        }
+    };
+
+    for profile in FIREWALL_PROFILES {
+        log_file_path("firewall", control_set.as_ref(), format!("Services\\SharedAccess\\Parameters\\FirewallPolicy\\{}\\Logging", profile));
+    }
+    for profile in FIREWALL_POLICY_PROFILES {
+        log_file_path("firewall", software.as_ref(), format!("Policies\\Microsoft\\WindowsFirewall\\{}\\Logging", profile));
+    }
+    log_file_path("dns", control_set.as_ref(), "Services\\DNS\\Parameters".to_string());
+
+    paths
+}
+
+#[cfg(not(windows))]
+fn registry_log_paths(_root: Option<&OfflineRoot>, _audit_log: &mut Vec<AuditEntry>) -> Vec<(&'static str, String)> {
+    Vec::new()
+}
+
+/// Replace the %SystemRoot% / %windir% prefix of a registry path with C:\Windows
+fn expand_system_root(path: &str) -> String {
+    let path = path.trim();
+    for variable in ["%systemroot%", "%windir%"] {
+        if path.len() >= variable.len() && path[..variable.len()].eq_ignore_ascii_case(variable) {
+            return format!("C:\\Windows{}", &path[variable.len()..]);
+        }
+    }
+    path.to_string()
+}
+
+fn existing_files(paths: impl Iterator<Item = PathBuf>) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = Vec::new();
+    for path in paths {
+        let key = path.to_string_lossy().to_lowercase();
+        if path.is_file() && !files.iter().any(|f| f.to_string_lossy().to_lowercase() == key) {
+            files.push(path);
+        }
+    }
+    files
+}
+
+/// Parse the records of a text log inside the time window, keeping the most recent ones
+fn read_log<T>(
+    path: &Path,
+    window: &TimeWindow,
+    audit_log: &mut Vec<AuditEntry>,
+    mut parse: impl FnMut(&str) -> Option<T>,
+    timestamp: impl Fn(&T) -> &str,
+) -> Vec<T> {
+    let file = match footprint::open(path) {
+        Ok(file) => file,
+        Err(e) => {
+            audit_log.push(AuditEntry::new(COMPONENT, "ERROR", "read_log", format!("Failed to read {}: {}", path.display(), e), "error"));
+            return Vec::new();
+        }
+    };
+
+    let mut reader = BufReader::new(file);
+    let mut records = VecDeque::new();
+    let (mut outside, mut dropped) = (0, 0);
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        match reader.read_until(b'\n', &mut line) {
+            Ok(0) => break,
+            Ok(_) => {}
+            Err(e) => {
+                audit_log.push(AuditEntry::new(COMPONENT, "ERROR", "read_log", format!("Read of {} stopped early: {}", path.display(), e), "error"));
+                break;
+            }
+        }
+        let Some(record) = parse(String::from_utf8_lossy(&line).trim_end()) else {
+            continue;
+        };
+        if !window.contains(timestamp(&record)) {
+            outside += 1;
+            continue;
+        }
+        records.push_back(record);
+        if records.len() > MAX_RECORDS_PER_LOG {
+            records.pop_front();
+            dropped += 1;
+        }
+    }
+
+    audit_log.push(AuditEntry::new(
+        COMPONENT,
+        "DEBUG",
+        "parse_log",
+        format!("Parsed {} records from {} ({} outside the time window)", records.len(), path.display(), outside),
+        "success",
+    ));
+    if dropped > 0 {
+        audit_log.push(AuditEntry::new(
+            COMPONENT,
+            "WARN",
+            "parse_log",
+            format!("Kept the {} most recent records of {}, {} older records dropped", MAX_RECORDS_PER_LOG, path.display(), dropped),
+            "truncated",
+        ));
+    }
+
+    records.into()
+}
+
+/// Parse a pfirewall.log record using the field layout of the #Fields header
+fn parse_firewall_line(line: &str, fields: &[String], source_file: &str) -> Option<FirewallLogEntry> {
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    let values: Vec<&str> = line.split_whitespace().collect();
+    let field = |name: &str| {
+        fields
+            .iter()
+            .position(|f| f == name)
+            .and_then(|i| values.get(i))
+            .filter(|value| **value != "-")
+            .map(|value| value.to_string())
+    };
+
+    let date = field("date")?;
+    let time = field("time")?;
+    Some(FirewallLogEntry {
+        timestamp: format!("{}T{}", date, time),
+        action: field("action").unwrap_or_default(),
+        protocol: field("protocol").unwrap_or_default(),
+        src_ip: field("src-ip").unwrap_or_default(),
+        dst_ip: field("dst-ip").unwrap_or_default(),
+        src_port: field("src-port").and_then(|v| v.parse().ok()),
+        dst_port: field("dst-port").and_then(|v| v.parse().ok()),
+        size: field("size").and_then(|v| v.parse().ok()),
+        direction: field("path").unwrap_or_default(),
+        pid: field("pid").and_then(|v| v.parse().ok()),
+        source_file: source_file.to_string(),
+    })
+}
+
+fn dns_packet_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(concat!(
+            r"^(?P<timestamp>\S+ \d{1,2}:\d{2}:\d{2}(?: [AP]M)?)\s+\S+\s+PACKET\s+\S+\s+(?P<protocol>UDP|TCP)\s+(?P<direction>Snd|Rcv)\s+",
+            r"(?P<address>\S+)\s+[0-9A-Fa-f]+\s+(?P<response>R)?\s*\S\s+\[[0-9A-Fa-f]+[^\]]*?\s(?P<rcode>[A-Z]+)\]\s+",
+            r"(?P<qtype>\S+)\s+(?P<qname>\S+)",
+        ))
+        .unwrap()
+    })
+}
+
+/// Parse a PACKET line of the DNS server debug log:
+/// `5/1/2023 10:15:30 AM 0E6C PACKET  000000D4A1B2C3D0 UDP Rcv 10.0.0.5  1a2b   Q [0001   D   NOERROR] A  (7)example(3)com(0)`
+fn parse_dns_server_line(line: &str, source_file: &str) -> Option<DnsLogEntry> {
+    let capture = dns_packet_pattern().captures(line)?;
+    Some(DnsLogEntry {
+        timestamp: format_dns_time(&capture["timestamp"]),
+        source: "server_debug_log".to_string(),
+        direction: capture["direction"].to_string(),
+        protocol: capture["protocol"].to_string(),
+        remote_address: capture["address"].to_string(),
+        query_name: decode_dns_name(&capture["qname"]),
+        query_type: capture["qtype"].to_string(),
+        response: capture.name("response").is_some(),
+        response_code: capture["rcode"].to_string(),
+        results: Vec::new(),
+        source_file: source_file.to_string(),
+    })
+}
+
+/// DNS debug log timestamps are local time in the server's short date format
+fn format_dns_time(timestamp: &str) -> String {
+    ["%m/%d/%Y %I:%M:%S %p", "%m/%d/%Y %H:%M:%S", "%Y-%m-%d %H:%M:%S"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(timestamp, format).ok())
+        .map(|datetime| datetime.format("%Y-%m-%dT%H:%M:%S").to_string())
+        .unwrap_or_else(|| timestamp.to_string())
+}
+
+/// `(3)www(7)example(3)com(0)` -> `www.example.com`
+fn decode_dns_name(name: &str) -> String {
+    static LABEL: OnceLock<Regex> = OnceLock::new();
+    let label = LABEL.get_or_init(|| Regex::new(r"\(\d+\)").unwrap());
+    label.replace_all(name, ".").trim_matches('.').to_string()
+}
+
+/// Query completed events of the DNS client operational log (disabled by default)
+fn collect_dns_client_events(root: Option<&OfflineRoot>, window: &TimeWindow, audit_log: &mut Vec<AuditEntry>) -> Vec<DnsLogEntry> {
+    let mut query = EventChannelQuery::with_xpath(DNS_CLIENT_CHANNEL, &format!("*[System[(EventID={})]]", DNS_CLIENT_QUERY_EVENT));
+    query.max_events = MAX_DNS_CLIENT_EVENTS;
+    query.restrict_to(window);
+
+    let (event_logs, logs) = event_logs::collect_event_logs_with_queries(&[query], root);
+    for log in logs.iter().filter(|l| l.level != "INFO") {
+        audit_log.push(AuditEntry::new(COMPONENT, &log.level, "query_events", log.message.clone(), "unavailable"));
+    }
+
+    event_logs.channels.values().flatten().filter_map(dns_client_event).collect()
+}
+
+fn dns_client_event(entry: &EventLogEntry) -> Option<DnsLogEntry> {
+    if entry.event_id != DNS_CLIENT_QUERY_EVENT {
+        return None;
+    }
+    let field = |name: &str| entry.event_data.get(name).map(|v| v.trim().to_string()).unwrap_or_default();
+
+    let query_name = field("QueryName");
+    if query_name.is_empty() {
+        return None;
+    }
+    // "type:  5 cdn.example.net;::ffff:93.184.216.34;"
+    let results = field("QueryResults")
+        .split(';')
+        .map(|result| result.trim())
+        .filter(|result| !result.is_empty())
+        .map(|result| match result.strip_prefix("type:") {
+            Some(record) => record.split_whitespace().last().unwrap_or_default().to_string(),
+            None => result.trim_start_matches("::ffff:").to_string(),
+        })
+        .collect();
+
+    Some(DnsLogEntry {
+        timestamp: entry.timestamp.clone(),
+        source: "client_event_log".to_string(),
+        direction: String::new(),
+        protocol: String::new(),
+        remote_address: String::new(),
+        query_name,
+        query_type: dns_type_name(&field("QueryType")),
+        response: true,
+        response_code: dns_client_status(&field("QueryStatus")),
+        results,
+        source_file: entry.source.clone(),
+    })
+}
+
+fn dns_type_name(code: &str) -> String {
+    match code {
+        "1" => "A",
+        "2" => "NS",
+        "5" => "CNAME",
+        "6" => "SOA",
+        "12" => "PTR",
+        "15" => "MX",
+        "16" => "TXT",
+        "28" => "AAAA",
+        "33" => "SRV",
+        "65" => "HTTPS",
+        "255" => "ANY",
+        other => other,
+    }
+    .to_string()
+}
+
+/// Win32 status of a client query, named like the server's response codes where one applies
+fn dns_client_status(status: &str) -> String {
+    match status {
+        "0" => "NOERROR",
+        "9002" => "SERVFAIL",
+        "9003" => "NXDOMAIN",
+        "9501" => "NO_RECORDS",
+        "1460" => "TIMEOUT",
+        other => other,
+    }
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const FIREWALL_LOG: &str = "\
+#Version: 1.5
+#Software: Microsoft Windows Firewall
+#Time Format: Local
+#Fields: date time action protocol src-ip dst-ip src-port dst-port size tcpflags tcpsyn tcpack tcpwin icmptype icmpcode info path pid
+
+2023-05-01 10:15:30 ALLOW TCP 10.0.0.5 93.184.216.34 50123 443 0 - 0 0 0 - - - SEND 4242
+2023-05-02 08:00:01 DROP ICMP 10.0.0.9 10.0.0.5 - - 60 - - - - 8 0 - RECEIVE 4
+";
+
+    #[test]
+    fn test_parse_firewall_log() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("pfirewall.log");
+        std::fs::write(&path, FIREWALL_LOG).unwrap();
+
+        let mut fields: Vec<String> = DEFAULT_FIREWALL_FIELDS.iter().map(|f| f.to_string()).collect();
+        let parse = |line: &str| match line.strip_prefix("#Fields:") {
+            Some(header) => {
+                fields = header.split_whitespace().map(|f| f.to_string()).collect();
+                None
+            }
+            None => parse_firewall_line(line, &fields, "pfirewall.log"),
+        };
+        let mut audit_log = Vec::new();
+        let entries = read_log(&path, &TimeWindow::default(), &mut audit_log, parse, |e: &FirewallLogEntry| &e.timestamp);
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].timestamp, "2023-05-01T10:15:30");
+        assert_eq!(entries[0].action, "ALLOW");
+        assert_eq!(entries[0].dst_ip, "93.184.216.34");
+        assert_eq!(entries[0].dst_port, Some(443));
+        assert_eq!(entries[0].direction, "SEND");
+        assert_eq!(entries[0].pid, Some(4242));
+        assert_eq!(entries[1].action, "DROP");
+        assert_eq!(entries[1].src_port, None);
+        assert_eq!(entries[1].size, Some(60));
+
+        let window = TimeWindow::new(Some("2023-05-02"), None).unwrap();
+        let parse = |line: &str| parse_firewall_line(line, &fields, "pfirewall.log");
+        let entries = read_log(&path, &window, &mut audit_log, parse, |e: &FirewallLogEntry| &e.timestamp);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].action, "DROP");
+    }
+
+    #[test]
+    fn test_parse_dns_server_line() {
+        let query = "5/1/2023 10:15:30 AM 0E6C PACKET  000000D4A1B2C3D0 UDP Rcv 10.0.0.5        1a2b   Q [0001   D   NOERROR] A      (7)example(3)com(0)";
+        let entry = parse_dns_server_line(query, "dns.log").unwrap();
+        assert_eq!(entry.timestamp, "2023-05-01T10:15:30");
+        assert_eq!(entry.direction, "Rcv");
+        assert_eq!(entry.remote_address, "10.0.0.5");
+        assert_eq!(entry.query_name, "example.com");
+        assert_eq!(entry.query_type, "A");
+        assert!(!entry.response);
+
+        let response = "5/1/2023 1:15:31 PM 0E6C PACKET  000000D4A1B2C3D0 UDP Snd 10.0.0.5        1a2b R Q [8385 A DR NXDOMAIN] AAAA   (3)bad(7)example(3)com(0)";
+        let entry = parse_dns_server_line(response, "dns.log").unwrap();
+        assert_eq!(entry.timestamp, "2023-05-01T13:15:31");
+        assert!(entry.response);
+        assert_eq!(entry.response_code, "NXDOMAIN");
+        assert_eq!(entry.query_name, "bad.example.com");
+
+        assert!(parse_dns_server_line("5/1/2023 10:15:30 AM 0E6C EVENT   The DNS server has started.", "dns.log").is_none());
+    }
+
+    #[test]
+    fn test_dns_client_event() {
+        let mut entry = EventLogEntry::new_with_source(3008, "Information".to_string(), "2024-05-01T10:00:00+00:00".to_string(), String::new(), DNS_CLIENT_CHANNEL.to_string());
+        entry.event_data.insert("QueryName".to_string(), "www.example.com".to_string());
+        entry.event_data.insert("QueryType".to_string(), "1".to_string());
+        entry.event_data.insert("QueryStatus".to_string(), "0".to_string());
+        entry.event_data.insert("QueryResults".to_string(), "type:  5 cdn.example.net;::ffff:93.184.216.34;".to_string());
+
+        let record = dns_client_event(&entry).unwrap();
+        assert_eq!(record.query_type, "A");
+        assert_eq!(record.response_code, "NOERROR");
+        assert_eq!(record.results, vec!["cdn.example.net", "93.184.216.34"]);
+    }
+
+    #[test]
+    fn test_expand_system_root() {
+        assert_eq!(expand_system_root("%systemroot%\\system32\\LogFiles\\Firewall\\pfirewall.log"), "C:\\Windows\\system32\\LogFiles\\Firewall\\pfirewall.log");
+        assert_eq!(expand_system_root("%WINDIR%\\dns.log"), "C:\\Windows\\dns.log");
+        assert_eq!(expand_system_root("D:\\logs\\fw.log"), "D:\\logs\\fw.log");
+    }
+}
@@ -0,0 +1,211 @@
+//! Windows Notification Center history
+//! wpndatabase.db keeps the payloads of recent toast notifications per user, which
+//! often reveal email subjects, chat messages and device events after the
+//! corresponding logs have been cleared
+
+use crate::binutil::filetime_to_string;
+use crate::event_logs::unescape_xml;
+use crate::footprint;
+use crate::forensic_types::{NotificationRecord, AuditEntry};
+use crate::offline::OfflineRoot;
+use crate::vss;
+use regex::Regex;
+use rusqlite::types::ValueRef;
+use rusqlite::{Connection, OpenFlags};
+use std::path::Path;
+
+const COMPONENT: &str = "notifications";
+
+const USERS_DIRECTORY: &str = "C:\\Users";
+const NOTIFICATION_DATABASE_SUBPATH: [&str; 6] = ["AppData", "Local", "Microsoft", "Windows", "Notifications", "wpndatabase.db"];
+
+const NOTIFICATION_QUERY: &str = "SELECT n.Id, h.PrimaryId, n.Type, n.ArrivalTime, n.ExpiryTime, n.Payload \
+     FROM Notification n LEFT JOIN NotificationHandler h ON n.HandlerId = h.RecordId \
+     ORDER BY n.ArrivalTime DESC";
+
+/// Collect notification history for every user profile on the live system
+pub fn collect_notifications() -> (Vec<NotificationRecord>, Vec<AuditEntry>) {
+    collect_notifications_under(Path::new(USERS_DIRECTORY))
+}
+
+/// Collect notification history for every user profile of a mounted Windows volume
+pub fn collect_notifications_offline(root: &OfflineRoot) -> (Vec<NotificationRecord>, Vec<AuditEntry>) {
+    collect_notifications_under(&root.resolve(USERS_DIRECTORY))
+}
+
+fn collect_notifications_under(users_dir: &Path) -> (Vec<NotificationRecord>, Vec<AuditEntry>) {
+    let mut notifications = Vec::new();
+    let start_time = std::time::Instant::now();
+
+    let mut audit_log = vec![AuditEntry::new(COMPONENT, "INFO", "start_collection", "Starting notification database analysis".to_string(), "started")];
+
+    match footprint::read_dir(users_dir) {
+        Ok(user_dirs) => {
+            for user_dir in user_dirs.filter_map(|e| e.ok()) {
+                let username = user_dir.file_name().to_string_lossy().to_string();
+                let db_path = NOTIFICATION_DATABASE_SUBPATH
+                    .iter()
+                    .fold(user_dir.path(), |path, component| path.join(component));
+
+                if !db_path.is_file() {
+                    continue;
+                }
+
+                match parse_notification_database(&db_path, &username) {
+                    Ok(records) => {
+                        audit_log.push(AuditEntry::new(
+                            COMPONENT,
+                            "DEBUG",
+                            "parse_database",
+                            format!("Parsed {} notifications from {}", records.len(), db_path.display()),
+                            "success",
+                        ));
+                        notifications.extend(records);
+                    }
+                    Err(e) => {
+                        audit_log.push(AuditEntry::new(
+                            COMPONENT,
+                            "ERROR",
+                            "parse_database",
+                            format!("Failed to parse {}: {}", db_path.display(), e),
+                            "error",
+                        ));
+                    }
+                }
+            }
+        }
+        Err(e) => {
+            audit_log.push(AuditEntry::new(
+                COMPONENT,
+                "WARN",
+                "directory_check",
+                format!("Users directory not readable: {} ({})", users_dir.display(), e),
+                "not_found",
+            ));
+        }
+    }
+
+    let duration = start_time.elapsed();
+    let mut complete = AuditEntry::new(
+        COMPONENT,
+        "INFO",
+        "complete_collection",
+        format!("Collected {} notifications", notifications.len()),
+        "success",
+    );
+    complete.duration_ms = Some(duration.as_millis() as u64);
+    audit_log.push(complete);
+
+    (notifications, audit_log)
+}
+
+/// Parse a wpndatabase.db file. The database (and its WAL) is copied to a temporary
+/// directory first so the original is never opened by SQLite.
+pub fn parse_notification_database(db_path: &Path, user: &str) -> Result<Vec<NotificationRecord>, Box<dyn std::error::Error>> {
+    let staging = tempfile::TempDir::new()?;
+    let staged_db = vss::stage_sqlite_database(db_path, staging.path())?;
+
+    let connection = Connection::open_with_flags(&staged_db, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let mut statement = connection.prepare(NOTIFICATION_QUERY)?;
+
+    let rows = statement.query_map([], |row| {
+        let payload = match row.get_ref(5)? {
+            ValueRef::Blob(bytes) | ValueRef::Text(bytes) => String::from_utf8_lossy(bytes).to_string(),
+            _ => String::new(),
+        };
+
+        Ok(NotificationRecord {
+            user: user.to_string(),
+            notification_id: row.get(0)?,
+            application: row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+            notification_type: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+            arrival_time: filetime_to_string(row.get::<_, Option<i64>>(3)?.unwrap_or(0) as u64),
+            expiry_time: filetime_to_string(row.get::<_, Option<i64>>(4)?.unwrap_or(0) as u64),
+            text: extract_notification_text(&payload),
+            payload,
+            source_path: db_path.display().to_string(),
+        })
+    })?;
+
+    let mut records = Vec::new();
+    for record in rows {
+        records.push(record?);
+    }
+
+    Ok(records)
+}
+
+/// Extract the visible text elements of a toast/tile XML payload
+fn extract_notification_text(payload: &str) -> Vec<String> {
+    let text_pattern = Regex::new(r"(?s)<text(?:\s[^>]*[^/])?>(.*?)</text>").unwrap();
+
+    text_pattern
+        .captures_iter(payload)
+        .map(|capture| unescape_xml(capture[1].trim()))
+        .filter(|text| !text.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn create_test_database(path: &Path) {
+        let connection = Connection::open(path).unwrap();
+        connection.execute_batch(
+            "CREATE TABLE NotificationHandler (RecordId INTEGER PRIMARY KEY, PrimaryId TEXT);
+             CREATE TABLE Notification (Id INTEGER PRIMARY KEY, HandlerId INTEGER, Type TEXT,
+                 Payload BLOB, ExpiryTime INTEGER, ArrivalTime INTEGER);
+             INSERT INTO NotificationHandler VALUES (1, 'Microsoft.Office.OUTLOOK.EXE.15');
+             INSERT INTO Notification VALUES (7, 1, 'toast',
+                 CAST('<toast><visual><binding template=\"ToastGeneric\"><text>Quarterly results</text><text>Wire transfer &amp; invoice</text></binding></visual></toast>' AS BLOB),
+                 132541056000000000, 132539328000000000);",
+        ).unwrap();
+    }
+
+    #[test]
+    fn test_parse_notification_database() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("wpndatabase.db");
+        create_test_database(&db_path);
+
+        let records = parse_notification_database(&db_path, "alice").unwrap();
+
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].notification_id, 7);
+        assert_eq!(records[0].application, "Microsoft.Office.OUTLOOK.EXE.15");
+        assert_eq!(records[0].notification_type, "toast");
+        assert_eq!(records[0].arrival_time, "2021-01-01T00:00:00+00:00");
+        assert_eq!(records[0].text, vec!["Quarterly results", "Wire transfer & invoice"]);
+        assert_eq!(records[0].user, "alice");
+    }
+
+    #[test]
+    fn test_collect_notifications_under_users_directory() {
+        let temp_dir = TempDir::new().unwrap();
+        let notifications_dir = ["bob", "AppData", "Local", "Microsoft", "Windows", "Notifications"]
+            .iter()
+            .fold(temp_dir.path().to_path_buf(), |path, component| path.join(component));
+        fs::create_dir_all(&notifications_dir).unwrap();
+        create_test_database(&notifications_dir.join("wpndatabase.db"));
+        fs::create_dir(temp_dir.path().join("Public")).unwrap();
+
+        let (notifications, audit_log) = collect_notifications_under(temp_dir.path());
+
+        assert_eq!(notifications.len(), 1);
+        assert_eq!(notifications[0].user, "bob");
+        assert!(audit_log.iter().any(|log| log.action == "complete_collection"));
+    }
+
+    #[test]
+    fn test_extract_notification_text() {
+        let payload = "<toast><visual><binding><text id=\"1\">USB Drive (E:)</text><text/><text>Tap to choose &quot;Open folder&quot;</text></binding></visual></toast>";
+        assert_eq!(
+            extract_notification_text(payload),
+            vec!["USB Drive (E:)", "Tap to choose \"Open folder\""]
+        );
+        assert!(extract_notification_text("<badge value=\"3\"/>").is_empty());
+    }
+}
@@ -0,0 +1,300 @@
+//! Offline (dead-box) collection support
+//! Resolves live-system artifact locations beneath the root of a mounted Windows
+//! volume (e.g. `E:\`) and loads registry hives from that volume instead of the
+//! live registry. Hives are either parsed directly (`open_hive`, any platform)
+//! or loaded with RegLoadAppKey (`load_hive`, Windows only); neither replays
+//! the transaction logs of dirty hives.
+
+use crate::hive::{self, Hive, Key, Value};
+use std::path::{Path, PathBuf};
+
+#[cfg(windows)]
+use crate::footprint::{self, Access, TrackedKey};
+#[cfg(windows)]
+use std::io;
+#[cfg(windows)]
+use winreg::RegKey;
+
+#[derive(Clone)]
+pub struct OfflineRoot {
+    root: PathBuf,
+}
+
+impl OfflineRoot {
+    /// Validate that `root` looks like a Windows volume root
+    pub fn new<P: Into<PathBuf>>(root: P) -> Result<Self, String> {
+        let root = root.into();
+        if !root.is_dir() {
+            return Err(format!("Offline root not found or not a directory: {}", root.display()));
+        }
+        if !root.join("Windows").is_dir() {
+            return Err(format!("Offline root does not contain a Windows directory: {}", root.display()));
+        }
+        Ok(OfflineRoot { root })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Map a live-system path such as `C:\Windows\Prefetch` onto the offline volume
+    pub fn resolve(&self, live_path: &str) -> PathBuf {
+        resolve_under_root(&self.root, live_path)
+    }
+
+    /// Location of a machine hive (SYSTEM, SOFTWARE, SAM, SECURITY) on the offline volume
+    pub fn hive_path(&self, hive_name: &str) -> PathBuf {
+        self.resolve(&format!("C:\\Windows\\System32\\config\\{}", hive_name))
+    }
+
+    /// Parse a machine hive of the offline volume with the raw hive parser
+    pub fn open_hive(&self, hive_name: &str) -> Result<Hive, String> {
+        Hive::open(&self.hive_path(hive_name))
+    }
+
+    /// Load a machine hive from the offline volume as a private application hive
+    #[cfg(windows)]
+    pub fn load_hive(&self, hive_name: &str) -> io::Result<RegKey> {
+        load_hive_file(&self.hive_path(hive_name))
+    }
+
+    /// Location of an event log channel file on the offline volume
+    #[cfg_attr(not(windows), allow(dead_code))]
+    pub fn event_log_path(&self, channel: &str) -> PathBuf {
+        self.resolve(&format!(
+            "C:\\Windows\\System32\\winevt\\Logs\\{}.evtx",
+            channel.replace('/', "%4")
+        ))
+    }
+
+    /// Computer name recorded in the offline SYSTEM hive
+    pub fn hostname(&self) -> Option<String> {
+        let system = self.open_hive("SYSTEM").ok()?;
+        let control_set = hive::current_control_set(&system);
+        system
+            .open_key(&format!("{}\\Control\\ComputerName\\ComputerName", control_set))?
+            .string("ComputerName")
+    }
+
+    /// Product name and build recorded in the offline SOFTWARE hive
+    pub fn os_version(&self) -> Option<String> {
+        let software = self.open_hive("SOFTWARE").ok()?;
+        let current_version = software.open_key("Microsoft\\Windows NT\\CurrentVersion")?;
+        let product = current_version.string("ProductName")?;
+        match current_version.string("CurrentBuild") {
+            Some(build) => Some(format!("{} (Build {})", product, build)),
+            None => Some(product),
+        }
+    }
+}
+
+/// Load any hive file (SYSTEM, Amcache.hve, NTUSER.DAT, ...) read-only.
+/// `RegKey::load_app_key` asks for KEY_ALL_ACCESS, so the access is given explicitly.
+#[cfg(windows)]
+pub fn load_hive_file(path: &Path) -> io::Result<RegKey> {
+    footprint::record_file(path, Access::Read);
+    RegKey::load_app_key_with_flags(path, winreg::enums::KEY_READ, 0)
+}
+
+/// Name of the control set the offline system booted with last (`Select\Current`).
+/// Offline SYSTEM hives have no `CurrentControlSet` link.
+#[cfg(windows)]
+pub fn current_control_set(system_hive: &RegKey) -> String {
+    let current = system_hive
+        .open_tracked("Select")
+        .and_then(|select| select.get_value::<u32, _>("Current"))
+        .unwrap_or(1);
+    control_set_name(current)
+}
+
+/// Last write time of a registry key as RFC 3339, or an empty string if unavailable
+#[cfg(windows)]
+pub fn key_last_write_time(key: &RegKey) -> String {
+    match key.query_info() {
+        Ok(info) => {
+            let st = info.get_last_write_time_system();
+            chrono::NaiveDate::from_ymd_opt(st.wYear as i32, st.wMonth as u32, st.wDay as u32)
+                .and_then(|date| date.and_hms_opt(st.wHour as u32, st.wMinute as u32, st.wSecond as u32))
+                .map(|datetime| datetime.and_utc().to_rfc3339())
+                .unwrap_or_default()
+        }
+        Err(_) => String::new(),
+    }
+}
+
+/// Machine registry: the live HKLM or the SOFTWARE and SYSTEM hives of an
+/// offline volume. Key paths start with the hive name (`SOFTWARE\...`,
+/// `SYSTEM\CurrentControlSet\...`); missing keys have no subkeys or values.
+pub trait MachineRegistry {
+    /// Values of a key; a missing key has none
+    fn values(&self, key: &str) -> Result<Vec<Value>, String>;
+    fn subkeys(&self, key: &str) -> Vec<String>;
+    /// Last write time of a key as RFC 3339, or an empty string if unavailable
+    fn last_written(&self, key: &str) -> String;
+
+    fn value(&self, key: &str, name: &str) -> Option<Value> {
+        self.values(key).ok()?.into_iter().find(|value| value.name.eq_ignore_ascii_case(name))
+    }
+
+    /// A non-empty string value
+    fn string(&self, key: &str, name: &str) -> Option<String> {
+        self.value(key, name)?.as_string().filter(|text| !text.is_empty())
+    }
+
+    fn dword(&self, key: &str, name: &str) -> Option<u32> {
+        self.value(key, name)?.as_u32()
+    }
+}
+
+/// SOFTWARE and SYSTEM hives of an offline volume, either of which may be missing
+pub struct OfflineRegistry {
+    software: Option<Hive>,
+    system: Option<Hive>,
+    control_set: String,
+}
+
+impl OfflineRegistry {
+    pub fn new(software: Option<Hive>, system: Option<Hive>) -> Self {
+        let control_set = system.as_ref().map(hive::current_control_set).unwrap_or_default();
+        OfflineRegistry { software, system, control_set }
+    }
+
+    fn key(&self, path: &str) -> Option<Key<'_>> {
+        let (hive_name, path) = path.split_once('\\')?;
+        match hive_name {
+            "SOFTWARE" => self.software.as_ref()?.open_key(path),
+            "SYSTEM" => {
+                // Hive files have no CurrentControlSet link
+                let path = match path.strip_prefix("CurrentControlSet\\") {
+                    Some(rest) => format!("{}\\{}", self.control_set, rest),
+                    None => path.to_string(),
+                };
+                self.system.as_ref()?.open_key(&path)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl MachineRegistry for OfflineRegistry {
+    fn values(&self, key: &str) -> Result<Vec<Value>, String> {
+        Ok(self.key(key).map(|key| key.values()).unwrap_or_default())
+    }
+
+    fn subkeys(&self, key: &str) -> Vec<String> {
+        self.key(key).map(|key| key.subkeys().iter().map(Key::name).collect()).unwrap_or_default()
+    }
+
+    fn last_written(&self, key: &str) -> String {
+        self.key(key).map(|key| key.last_written()).unwrap_or_default()
+    }
+}
+
+/// The live machine registry, normally `HKEY_LOCAL_MACHINE`
+#[cfg(windows)]
+pub struct LiveRegistry(pub RegKey);
+
+#[cfg(windows)]
+impl MachineRegistry for LiveRegistry {
+    fn values(&self, key: &str) -> Result<Vec<Value>, String> {
+        match self.0.open_tracked(key) {
+            Ok(key) => Ok(key
+                .enum_values()
+                .filter_map(|value| value.ok())
+                .map(|(name, value)| Value { name, kind: value.vtype as u32, data: value.bytes })
+                .collect()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    fn subkeys(&self, key: &str) -> Vec<String> {
+        self.0.open_tracked(key).map(|key| key.enum_keys().filter_map(|name| name.ok()).collect()).unwrap_or_default()
+    }
+
+    fn last_written(&self, key: &str) -> String {
+        self.0.open_tracked(key).map(|key| key_last_write_time(&key)).unwrap_or_default()
+    }
+}
+
+pub(crate) fn control_set_name(current: u32) -> String {
+    format!("ControlSet{:03}", current.max(1))
+}
+
+fn resolve_under_root(root: &Path, live_path: &str) -> PathBuf {
+    let relative = match live_path.find(":\\") {
+        Some(1) => &live_path[3..],
+        _ => live_path.trim_start_matches('\\'),
+    };
+
+    relative
+        .split('\\')
+        .filter(|component| !component.is_empty())
+        .fold(root.to_path_buf(), |path, component| path.join(component))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_resolve_strips_drive_letter() {
+        let root = Path::new("mnt");
+        assert_eq!(
+            resolve_under_root(root, "C:\\Windows\\Prefetch"),
+            root.join("Windows").join("Prefetch")
+        );
+        assert_eq!(
+            resolve_under_root(root, "\\ProgramData\\Microsoft"),
+            root.join("ProgramData").join("Microsoft")
+        );
+    }
+
+    #[test]
+    fn test_control_set_name() {
+        assert_eq!(control_set_name(1), "ControlSet001");
+        assert_eq!(control_set_name(2), "ControlSet002");
+        assert_eq!(control_set_name(0), "ControlSet001");
+    }
+
+    #[test]
+    fn test_hostname_and_os_version_from_raw_hives() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = temp_dir.path().join("Windows").join("System32").join("config");
+        std::fs::create_dir_all(&config).unwrap();
+        let root = OfflineRoot::new(temp_dir.path()).unwrap();
+        assert_eq!(root.hostname(), None);
+
+        let system = hive::HiveBuilder::default()
+            .dword("Select", "Current", 1)
+            .string("ControlSet001\\Control\\ComputerName\\ComputerName", "ComputerName", "DC01")
+            .build();
+        let software = hive::HiveBuilder::default()
+            .string("Microsoft\\Windows NT\\CurrentVersion", "ProductName", "Windows Server 2022 Standard")
+            .string("Microsoft\\Windows NT\\CurrentVersion", "CurrentBuild", "20348")
+            .build();
+        std::fs::write(config.join("SYSTEM"), system).unwrap();
+        std::fs::write(config.join("SOFTWARE"), software).unwrap();
+        assert_eq!(root.hostname().as_deref(), Some("DC01"));
+        assert_eq!(root.os_version().as_deref(), Some("Windows Server 2022 Standard (Build 20348)"));
+    }
+
+    #[test]
+    fn test_offline_root_requires_windows_directory() {
+        let temp_dir = TempDir::new().unwrap();
+        assert!(OfflineRoot::new(temp_dir.path()).is_err());
+
+        std::fs::create_dir(temp_dir.path().join("Windows")).unwrap();
+        let root = OfflineRoot::new(temp_dir.path()).unwrap();
+        assert_eq!(
+            root.hive_path("SYSTEM"),
+            temp_dir.path().join("Windows").join("System32").join("config").join("SYSTEM")
+        );
+        assert_eq!(
+            root.event_log_path("Microsoft-Windows-Sysmon/Operational"),
+            temp_dir.path().join("Windows").join("System32").join("winevt").join("Logs")
+                .join("Microsoft-Windows-Sysmon%4Operational.evtx")
+        );
+    }
+}
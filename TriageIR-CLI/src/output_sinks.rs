@@ -0,0 +1,336 @@
+//! Output sinks
+//! The results of one run can go to several destinations at once, configured
+//! in a TOML file with `--sinks`; `--output` and `--password` add a JSON file
+//! or evidence package sink in front of them. Sinks are delivered in order and
+//! a failing sink does not stop the others. Every outcome is appended to the
+//! collection log before the next sink runs, so outputs written later carry the
+//! outcome of the earlier ones.
+//!
+//! ```toml
+//! timeout_secs = 30
+//!
+//! [[sink]]
+//! type = "json"
+//! path = "results.json"
+//!
+//! [[sink]]
+//! type = "evidence_package"      # password from --password
+//! directory = "evidence"
+//!
+//! [[sink]]
+//! type = "splunk_hec"
+//! url = "https://splunk.example.com:8088"
+//! token_env = "SPLUNK_HEC_TOKEN"
+//! index = "triage"
+//!
+//! [[sink]]
+//! type = "webhook"               # posts the --brief summary
+//! url = "https://hooks.example.com/ir"
+//! headers = { "X-Team" = "ir" }
+//!
+//! [[sink]]
+//! type = "upload"                # the files written above, see upload.rs
+//! url = "s3://evidence-bucket/cases"
+//! ```
+
+use crate::forensic_types::AuditEntry;
+use crate::http_client::{self, HttpUrl};
+use crate::types::{LogEntry, ScanResults};
+use crate::upload::{self, UploadTarget};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Default timeout of the remote sinks
+const DEFAULT_TIMEOUT_SECS: u64 = 30;
+
+/// Sourcetype of the HEC events unless configured
+const DEFAULT_SOURCETYPE: &str = "triageir:scan";
+
+/// Appended to a HEC URL given without a path
+const HEC_EVENT_ENDPOINT: &str = "/services/collector/event";
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct SinkConfig {
+    /// Connect, send and receive timeout of the remote sinks
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+    #[serde(default, rename = "sink")]
+    pub sinks: Vec<Sink>,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Sink {
+    /// Plaintext results; relative paths go below TRIAGEIR_OUTPUT_DIR in portable mode
+    Json { path: String },
+    /// Encrypted evidence package; without a directory it goes next to --output
+    EvidencePackage {
+        #[serde(default)]
+        directory: Option<String>,
+    },
+    /// Splunk HTTP Event Collector; the results are sent as one event
+    SplunkHec {
+        url: String,
+        #[serde(default)]
+        token: Option<String>,
+        /// Environment variable holding the token, to keep it out of the file
+        #[serde(default)]
+        token_env: Option<String>,
+        #[serde(default)]
+        index: Option<String>,
+        #[serde(default)]
+        sourcetype: Option<String>,
+    },
+    /// JSON POST of the --brief summary, for chat and ticketing integrations
+    Webhook {
+        url: String,
+        #[serde(default)]
+        headers: BTreeMap<String, String>,
+    },
+    /// Copy of the files the local sinks wrote, to s3://, azblob:// or smb://
+    Upload { url: String },
+}
+
+fn default_timeout_secs() -> u64 {
+    DEFAULT_TIMEOUT_SECS
+}
+
+impl SinkConfig {
+    /// Load and validate a sink configuration from a TOML file
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("Failed to read sink config {}: {}", path.display(), e))?;
+        Self::parse(&text).map_err(|e| format!("Invalid sink config {}: {}", path.display(), e))
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let config: SinkConfig = toml::from_str(text).map_err(|e| e.to_string())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.timeout_secs == 0 {
+            return Err("timeout_secs must be at least 1".to_string());
+        }
+        if self.sinks.iter().filter(|sink| matches!(sink, Sink::EvidencePackage { .. })).count() > 1 {
+            return Err("only one evidence_package sink is supported".to_string());
+        }
+        for sink in &self.sinks {
+            match sink {
+                Sink::Json { path } if path.trim().is_empty() => return Err("json sink without a path".to_string()),
+                Sink::SplunkHec { url, token, token_env, .. } => {
+                    HttpUrl::parse(url)?;
+                    if token.is_some() == token_env.is_some() {
+                        return Err(format!("splunk_hec sink {} needs exactly one of token and token_env", url));
+                    }
+                }
+                Sink::Webhook { url, .. } => {
+                    HttpUrl::parse(url)?;
+                }
+                Sink::Upload { url } => {
+                    UploadTarget::parse(url)?;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    pub fn has_evidence_package(&self) -> bool {
+        self.sinks.iter().any(|sink| matches!(sink, Sink::EvidencePackage { .. }))
+    }
+
+    /// True if a sink writes the results to a local file
+    pub fn has_local_output(&self) -> bool {
+        self.sinks.iter().any(Sink::is_local)
+    }
+
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs)
+    }
+}
+
+impl Default for SinkConfig {
+    fn default() -> Self {
+        SinkConfig { timeout_secs: DEFAULT_TIMEOUT_SECS, sinks: Vec::new() }
+    }
+}
+
+impl Sink {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Sink::Json { .. } => "json",
+            Sink::EvidencePackage { .. } => "evidence_package",
+            Sink::SplunkHec { .. } => "splunk_hec",
+            Sink::Webhook { .. } => "webhook",
+            Sink::Upload { .. } => "upload",
+        }
+    }
+
+    pub fn is_local(&self) -> bool {
+        matches!(self, Sink::Json { .. } | Sink::EvidencePackage { .. })
+    }
+
+    /// Destination for logs, never including a token
+    pub fn target(&self) -> String {
+        match self {
+            Sink::Json { path } => path.clone(),
+            Sink::EvidencePackage { directory } => directory.clone().unwrap_or_else(|| "output directory".to_string()),
+            Sink::SplunkHec { url, .. } | Sink::Webhook { url, .. } | Sink::Upload { url } => url.clone(),
+        }
+    }
+}
+
+/// Deliver the results to a Splunk HEC or webhook sink; returns the HTTP status
+/// text. `summary` is the --brief summary posted to webhooks.
+pub fn deliver_remote(sink: &Sink, results: &Value, summary: &Value, timeout: Duration) -> Result<String, String> {
+    let mut headers = vec![("Content-Type".to_string(), "application/json".to_string())];
+    let (url, body) = match sink {
+        Sink::SplunkHec { url, token, token_env, index, sourcetype } => {
+            let token = match (token, token_env) {
+                (Some(token), _) => token.clone(),
+                (None, Some(variable)) => std::env::var(variable).map_err(|_| format!("HEC token variable {} is not set", variable))?,
+                (None, None) => return Err("no HEC token configured".to_string()),
+            };
+            headers.push(("Authorization".to_string(), format!("Splunk {}", token)));
+            (hec_endpoint(url), splunk_event(results, index.as_deref(), sourcetype.as_deref()))
+        }
+        Sink::Webhook { url, headers: extra } => {
+            headers.extend(extra.iter().map(|(name, value)| (name.clone(), value.clone())));
+            (url.clone(), summary.clone())
+        }
+        other => return Err(format!("{} sinks are not delivered over HTTP", other.kind())),
+    };
+
+    let body = serde_json::to_vec(&body).map_err(|e| format!("Failed to serialize the payload: {}", e))?;
+    let response = http_client::send("POST", &url, &headers, &body, timeout)?;
+    if response.is_success() {
+        Ok(format!("HTTP {} ({} bytes sent)", response.status, body.len()))
+    } else {
+        Err(format!("HTTP {}: {}", response.status, response.body_excerpt()))
+    }
+}
+
+/// Upload the files the local sinks wrote so far; returns the outcome of every file
+pub fn deliver_upload(sink: &Sink, files: &[PathBuf], timeout: Duration) -> Result<String, String> {
+    let Sink::Upload { url } = sink else { return Err(format!("{} is not an upload sink", sink.kind())) };
+    let target = UploadTarget::parse(url)?;
+    if files.is_empty() {
+        return Err("nothing to upload: no evidence package or JSON file was written".to_string());
+    }
+    let uploaded = files.iter().map(|file| upload::upload_file(&target, file, timeout)).collect::<Result<Vec<_>, _>>()?;
+    Ok(uploaded.join("; "))
+}
+
+/// HEC event envelope around the results, timed at the scan start
+fn splunk_event(results: &Value, index: Option<&str>, sourcetype: Option<&str>) -> Value {
+    let metadata = &results["scan_metadata"];
+    let time = metadata["scan_start_utc"]
+        .as_str()
+        .and_then(|start| chrono::DateTime::parse_from_rfc3339(start).ok())
+        .map_or_else(|| chrono::Utc::now().timestamp(), |start| start.timestamp());
+    let mut event = json!({
+        "time": time,
+        "host": metadata["hostname"],
+        "source": "triageir-cli",
+        "sourcetype": sourcetype.unwrap_or(DEFAULT_SOURCETYPE),
+        "event": results
+    });
+    if let Some(index) = index {
+        event["index"] = json!(index);
+    }
+    event
+}
+
+fn hec_endpoint(url: &str) -> String {
+    let base = url.trim_end_matches('/');
+    match HttpUrl::parse(base) {
+        Ok(parsed) if parsed.path == "/" => format!("{}{}", base, HEC_EVENT_ENDPOINT),
+        _ => url.to_string(),
+    }
+}
+
+/// Audit entry of one delivery
+pub fn outcome_entry(sink: &Sink, outcome: &Result<String, String>, duration: Duration) -> AuditEntry {
+    let (level, details, result) = match outcome {
+        Ok(details) => ("INFO", details, "success"),
+        Err(error) => ("ERROR", error, "failed"),
+    };
+    AuditEntry {
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        level: level.to_string(),
+        component: "output_sinks".to_string(),
+        action: format!("deliver_{}", sink.kind()),
+        details: format!("{}: {}", sink.target(), details),
+        duration_ms: Some(duration.as_millis() as u64),
+        result: result.to_string(),
+    }
+}
+
+/// Append a delivery outcome to the collection log of the results and of the
+/// typed scan results the evidence package audit is built from
+pub fn record_outcome(results: &mut Value, scan_results: &mut ScanResults, entry: &AuditEntry) {
+    let log = LogEntry::new(
+        &entry.level,
+        &format!("[{}] {}: {} ({}ms)", entry.component, entry.action, entry.details, entry.duration_ms.unwrap_or_default()),
+    );
+    if let Some(collection_log) = results["collection_log"].as_array_mut() {
+        collection_log.push(json!(log));
+    }
+    scan_results.add_log(log);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sink_config() {
+        let config = SinkConfig::parse(
+            r#"
+            [[sink]]
+            type = "json"
+            path = "results.json"
+
+            [[sink]]
+            type = "splunk_hec"
+            url = "https://splunk.example.com:8088"
+            token_env = "SPLUNK_HEC_TOKEN"
+
+            [[sink]]
+            type = "webhook"
+            url = "http://hooks.example.com/ir"
+            headers = { "X-Team" = "ir" }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.timeout_secs, DEFAULT_TIMEOUT_SECS);
+        assert_eq!(config.sinks.iter().map(Sink::kind).collect::<Vec<_>>(), ["json", "splunk_hec", "webhook"]);
+        assert!(config.has_local_output() && !config.has_evidence_package());
+        assert_eq!(hec_endpoint("https://splunk.example.com:8088/"), "https://splunk.example.com:8088/services/collector/event");
+
+        assert!(SinkConfig::parse("[[sink]]\ntype = \"splunk_hec\"\nurl = \"https://splunk.example.com\"").is_err()); // No token
+        assert!(SinkConfig::parse("[[sink]]\ntype = \"webhook\"\nurl = \"ftp://x\"").is_err());
+        assert!(SinkConfig::parse("[[sink]]\ntype = \"email\"").is_err());
+        assert!(SinkConfig::parse("[[sink]]\ntype = \"upload\"\nurl = \"ftp://x/y\"").is_err());
+        assert!(SinkConfig::parse("[[sink]]\ntype = \"evidence_package\"\n[[sink]]\ntype = \"evidence_package\"").is_err());
+    }
+
+    #[test]
+    fn test_record_outcome() {
+        let mut results = json!({ "scan_metadata": { "hostname": "WS01", "scan_start_utc": "2024-01-01T00:00:00Z" }, "collection_log": [] });
+        let mut scan_results = ScanResults::new("WS01".to_string(), "Windows 11".to_string());
+        let sink = Sink::Webhook { url: "https://hooks.example.com/ir".to_string(), headers: BTreeMap::new() };
+        let entry = outcome_entry(&sink, &Err("HTTP 500: boom".to_string()), Duration::from_millis(12));
+        record_outcome(&mut results, &mut scan_results, &entry);
+
+        assert_eq!(results["collection_log"][0]["level"], "ERROR");
+        assert_eq!(results["collection_log"][0]["message"], "[output_sinks] deliver_webhook: https://hooks.example.com/ir: HTTP 500: boom (12ms)");
+        assert_eq!(scan_results.collection_log.last().unwrap().level, "ERROR");
+        assert_eq!(splunk_event(&results, Some("triage"), None)["time"], 1704067200);
+    }
+}
@@ -0,0 +1,411 @@
+//! Evidence package signing (`--signing-cert`)
+//! Signs the evidence package with an operator certificate: a PFX file, whose
+//! key is imported without being persisted on the examined system, or a
+//! certificate with a private key in the LocalMachine\My store. Signatures are
+//! detached CMS/PKCS#7 SignedData (SHA-256) carrying the certificate chain, so
+//! they can also be checked with `openssl cms -verify -binary -inform DER`.
+//! CryptoAPI does the signing and checking, so both need Windows.
+
+// Certificate handling is only reachable through the Windows CryptoAPI
+#![cfg_attr(not(windows), allow(dead_code))]
+
+use std::path::PathBuf;
+
+/// Environment variable holding the PFX password when --signing-cert-password is not given
+pub const SIGNING_PASSWORD_VARIABLE: &str = "TRIAGEIR_SIGNING_PASSWORD";
+
+/// Signature of the (decrypted) evidence.json inside the archive
+pub const EVIDENCE_SIGNATURE: &str = "evidence.json.p7s";
+
+/// Signature of the whole archive, written next to it as `<archive>.zip.p7s`
+pub const ARCHIVE_SIGNATURE_EXTENSION: &str = "zip.p7s";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CertificateSource {
+    Pfx(PathBuf),
+    Machine(String), // SHA-1 thumbprint or subject substring in LocalMachine\My
+}
+
+impl CertificateSource {
+    /// `PATH.pfx` or `machine:THUMBPRINT|SUBJECT`
+    pub fn parse(argument: &str) -> Result<Self, String> {
+        match argument.split_once(':') {
+            Some((prefix, selector)) if prefix.eq_ignore_ascii_case("machine") => {
+                let selector = selector.trim();
+                if selector.is_empty() {
+                    return Err("machine: needs a certificate thumbprint or subject".to_string());
+                }
+                Ok(CertificateSource::Machine(selector.to_string()))
+            }
+            _ if argument.is_empty() => Err("the signing certificate path is empty".to_string()),
+            _ => Ok(CertificateSource::Pfx(PathBuf::from(argument))),
+        }
+    }
+}
+
+/// Certificate a signature was made with
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignerInfo {
+    pub subject: String,
+    pub issuer: String,
+    pub thumbprint: String, // SHA-1, uppercase hex
+    pub not_after: Option<String>,
+    pub chain: Vec<String>, // Subjects from the signer up to the root
+}
+
+/// Outcome of checking a detached signature whose content matched
+#[derive(Debug, Clone)]
+pub struct SignatureCheck {
+    pub signer: SignerInfo,
+    pub trust_error: Option<String>, // Why the chain does not end in a trusted root
+}
+
+pub struct PackageSigner {
+    #[cfg(windows)]
+    certificate: windows_api::SigningCertificate,
+    info: SignerInfo,
+}
+
+impl PackageSigner {
+    /// Open the certificate and its private key; `password` unlocks a PFX file
+    #[cfg(windows)]
+    pub fn load(source: &CertificateSource, password: &str) -> Result<Self, String> {
+        let certificate = match source {
+            CertificateSource::Pfx(path) => windows_api::SigningCertificate::from_pfx(path, password)?,
+            CertificateSource::Machine(selector) => windows_api::SigningCertificate::from_machine_store(selector)?,
+        };
+        let info = certificate.info();
+        Ok(PackageSigner { certificate, info })
+    }
+
+    #[cfg(not(windows))]
+    pub fn load(_source: &CertificateSource, _password: &str) -> Result<Self, String> {
+        Err("signing the evidence package requires Windows (CryptoAPI)".to_string())
+    }
+
+    pub fn info(&self) -> &SignerInfo {
+        &self.info
+    }
+
+    /// Detached SHA-256 CMS signature of `data` (DER)
+    #[cfg(windows)]
+    pub fn sign(&self, data: &[u8]) -> Result<Vec<u8>, String> {
+        self.certificate.sign(data)
+    }
+
+    #[cfg(not(windows))]
+    pub fn sign(&self, _data: &[u8]) -> Result<Vec<u8>, String> {
+        Err("signing the evidence package requires Windows (CryptoAPI)".to_string())
+    }
+}
+
+/// Check a detached signature against its content. Err when the signature is
+/// malformed or does not match; an untrusted chain is reported in the result.
+#[cfg(windows)]
+pub fn verify(signature: &[u8], data: &[u8]) -> Result<SignatureCheck, String> {
+    windows_api::verify(signature, data)
+}
+
+#[cfg(not(windows))]
+pub fn verify(_signature: &[u8], _data: &[u8]) -> Result<SignatureCheck, String> {
+    Err(VERIFY_UNSUPPORTED.to_string())
+}
+
+/// Whether `verify` can check signatures on this platform
+pub const VERIFY_SUPPORTED: bool = cfg!(windows);
+const VERIFY_UNSUPPORTED: &str = "checking the signatures requires Windows (CryptoAPI)";
+
+/// Contents of digital_signature_info.txt
+pub fn signature_info(signer: Option<&SignerInfo>, archive_name: &str) -> String {
+    let Some(signer) = signer else {
+        return "Digital Signature Information\n\
+            =============================\n\n\
+            Status: Not digitally signed\n\
+            Reason: No signing certificate configured (--signing-cert)\n\n\
+            The hashes in integrity_verification.txt and the .zip.sha256 file next to\n\
+            the archive detect modification, but do not identify who made the package.\n"
+            .to_string();
+    };
+    let mut info = format!(
+        "Digital Signature Information\n\
+        =============================\n\n\
+        Status: Signed\n\
+        Signer: {}\n\
+        Issuer: {}\n\
+        Thumbprint (SHA-1): {}\n\
+        Valid until: {}\n\
+        Format: CMS/PKCS#7 detached SignedData, SHA-256, certificate chain embedded\n\n\
+        Certificate chain:\n",
+        signer.subject,
+        signer.issuer,
+        signer.thumbprint,
+        signer.not_after.as_deref().unwrap_or("unknown"),
+    );
+    for (depth, subject) in signer.chain.iter().enumerate() {
+        info.push_str(&format!("{}- {}\n", "  ".repeat(depth), subject));
+    }
+    info.push_str(&format!(
+        "\nSignatures:\n\
+        - {}: evidence.json (the decrypted file when the evidence is encrypted)\n\
+        - {}.p7s: the archive, stored next to it\n\n\
+        Verification:\n\
+        triageir-cli verify {} [--password PASSWORD]\n\
+        openssl cms -verify -binary -inform DER -in {} -content evidence.json -CAfile ca.pem\n",
+        EVIDENCE_SIGNATURE,
+        archive_name,
+        archive_name,
+        EVIDENCE_SIGNATURE,
+    ));
+    info
+}
+
+#[cfg(windows)]
+mod windows_api {
+    use super::{SignatureCheck, SignerInfo};
+    use crate::binutil::filetime_to_datetime;
+    use std::ffi::c_void;
+    use std::mem::size_of;
+    use std::path::Path;
+    use std::ptr;
+    use windows::core::{w, HRESULT, HSTRING, PSTR};
+    use windows::Win32::Foundation::BOOL;
+    use windows::Win32::Security::Cryptography::{
+        CertCloseStore, CertDuplicateCertificateContext, CertFindCertificateInStore, CertFreeCertificateChain,
+        CertFreeCertificateContext, CertGetCertificateChain, CertGetCertificateContextProperty, CertGetNameStringW,
+        CertOpenStore, CertVerifyCertificateChainPolicy, CryptGetMessageCertificates, CryptSignMessage,
+        CryptVerifyDetachedMessageSignature, PFXImportCertStore, szOID_NIST_sha256, CERT_CHAIN_CONTEXT, CERT_CHAIN_PARA,
+        CERT_CHAIN_POLICY_BASE, CERT_CHAIN_POLICY_PARA, CERT_CHAIN_POLICY_STATUS, CERT_CONTEXT, CERT_FIND_HAS_PRIVATE_KEY,
+        CERT_FIND_SHA1_HASH, CERT_FIND_SUBJECT_STR_W, CERT_NAME_ISSUER_FLAG, CERT_NAME_SIMPLE_DISPLAY_TYPE,
+        CERT_OPEN_STORE_FLAGS, CERT_QUERY_ENCODING_TYPE, CERT_SHA1_HASH_PROP_ID, CERT_STORE_OPEN_EXISTING_FLAG,
+        CERT_STORE_PROV_SYSTEM_W, CERT_STORE_READONLY_FLAG, CERT_SYSTEM_STORE_LOCAL_MACHINE_ID, CRYPT_ALGORITHM_IDENTIFIER,
+        CRYPT_INTEGER_BLOB, CRYPT_SIGN_MESSAGE_PARA, CRYPT_VERIFY_MESSAGE_PARA, HCERTCHAINENGINE, HCERTSTORE,
+        HCRYPTPROV_LEGACY, PKCS12_NO_PERSIST_KEY, PKCS_7_ASN_ENCODING, X509_ASN_ENCODING,
+    };
+
+    const ENCODING: CERT_QUERY_ENCODING_TYPE = CERT_QUERY_ENCODING_TYPE(X509_ASN_ENCODING.0 | PKCS_7_ASN_ENCODING.0);
+
+    /// Signing certificate with its private key and the chain embedded in signatures
+    pub struct SigningCertificate {
+        store: HCERTSTORE,
+        certificate: *const CERT_CONTEXT,
+        chain: Vec<*mut CERT_CONTEXT>,
+    }
+
+    impl SigningCertificate {
+        pub fn from_pfx(path: &Path, password: &str) -> Result<Self, String> {
+            let data = std::fs::read(path).map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+            let blob = CRYPT_INTEGER_BLOB { cbData: data.len() as u32, pbData: data.as_ptr() as *mut u8 };
+            // The private key lives in memory only and is not written to the examined system
+            let store = unsafe { PFXImportCertStore(&blob, &HSTRING::from(password), PKCS12_NO_PERSIST_KEY) }
+                .map_err(|e| format!("cannot open {} (wrong password?): {}", path.display(), e))?;
+            let certificate = unsafe { CertFindCertificateInStore(store, ENCODING, 0, CERT_FIND_HAS_PRIVATE_KEY, None, None) };
+            if certificate.is_null() {
+                unsafe {
+                    let _ = CertCloseStore(store, 0);
+                }
+                return Err(format!("{} holds no certificate with a private key", path.display()));
+            }
+            Ok(Self::with_chain(store, certificate))
+        }
+
+        pub fn from_machine_store(selector: &str) -> Result<Self, String> {
+            let flags = CERT_OPEN_STORE_FLAGS(CERT_SYSTEM_STORE_LOCAL_MACHINE_ID << 16) | CERT_STORE_READONLY_FLAG | CERT_STORE_OPEN_EXISTING_FLAG;
+            let store = unsafe { CertOpenStore(CERT_STORE_PROV_SYSTEM_W, CERT_QUERY_ENCODING_TYPE(0), HCRYPTPROV_LEGACY(0), flags, Some(w!("MY").as_ptr() as *const c_void)) }
+                .map_err(|e| format!("cannot open the LocalMachine\\My certificate store: {}", e))?;
+
+            let thumbprint = hex::decode(selector.replace([' ', ':'], "")).ok().filter(|bytes| bytes.len() == 20);
+            let certificate = unsafe {
+                match &thumbprint {
+                    Some(hash) => {
+                        let blob = CRYPT_INTEGER_BLOB { cbData: hash.len() as u32, pbData: hash.as_ptr() as *mut u8 };
+                        CertFindCertificateInStore(store, ENCODING, 0, CERT_FIND_SHA1_HASH, Some(&blob as *const _ as *const c_void), None)
+                    }
+                    None => {
+                        let subject = HSTRING::from(selector);
+                        CertFindCertificateInStore(store, ENCODING, 0, CERT_FIND_SUBJECT_STR_W, Some(subject.as_ptr() as *const c_void), None)
+                    }
+                }
+            };
+            if certificate.is_null() {
+                unsafe {
+                    let _ = CertCloseStore(store, 0);
+                }
+                return Err(format!("no certificate matching {} in LocalMachine\\My", selector));
+            }
+            Ok(Self::with_chain(store, certificate))
+        }
+
+        /// The certificates of the chain, intermediates from `store` included; the
+        /// signing certificate alone when no chain can be built
+        fn with_chain(store: HCERTSTORE, certificate: *const CERT_CONTEXT) -> Self {
+            let chain = match build_chain(certificate, store) {
+                Some(chain) => {
+                    let contexts = chain_certificates(chain).into_iter().map(|context| unsafe { CertDuplicateCertificateContext(Some(context)) }).collect();
+                    unsafe { CertFreeCertificateChain(chain) };
+                    contexts
+                }
+                None => vec![unsafe { CertDuplicateCertificateContext(Some(certificate)) }],
+            };
+            SigningCertificate { store, certificate, chain }
+        }
+
+        pub fn info(&self) -> SignerInfo {
+            certificate_info(self.certificate, self.chain.iter().map(|context| display_name(*context, 0)).collect())
+        }
+
+        pub fn sign(&self, data: &[u8]) -> Result<Vec<u8>, String> {
+            let mut certificates = self.chain.clone();
+            let para = CRYPT_SIGN_MESSAGE_PARA {
+                cbSize: size_of::<CRYPT_SIGN_MESSAGE_PARA>() as u32,
+                dwMsgEncodingType: ENCODING.0,
+                pSigningCert: self.certificate,
+                HashAlgorithm: CRYPT_ALGORITHM_IDENTIFIER { pszObjId: PSTR(szOID_NIST_sha256.0 as *mut u8), Parameters: CRYPT_INTEGER_BLOB::default() },
+                cMsgCert: certificates.len() as u32,
+                rgpMsgCert: certificates.as_mut_ptr(),
+                ..Default::default()
+            };
+            let content = [data.as_ptr()];
+            let lengths = [data.len() as u32];
+            let mut size = 0u32;
+            unsafe { CryptSignMessage(&para, BOOL::from(true), 1, Some(content.as_ptr()), lengths.as_ptr(), None, &mut size) }
+                .map_err(|e| format!("signing failed: {}", e))?;
+            let mut signature = vec![0u8; size as usize];
+            unsafe { CryptSignMessage(&para, BOOL::from(true), 1, Some(content.as_ptr()), lengths.as_ptr(), Some(signature.as_mut_ptr()), &mut size) }
+                .map_err(|e| format!("signing failed: {}", e))?;
+            signature.truncate(size as usize);
+            Ok(signature)
+        }
+    }
+
+    impl Drop for SigningCertificate {
+        fn drop(&mut self) {
+            unsafe {
+                for context in &self.chain {
+                    CertFreeCertificateContext(Some(*context as *const CERT_CONTEXT));
+                }
+                CertFreeCertificateContext(Some(self.certificate));
+                let _ = CertCloseStore(self.store, 0);
+            }
+        }
+    }
+
+    pub fn verify(signature: &[u8], data: &[u8]) -> Result<SignatureCheck, String> {
+        let para = CRYPT_VERIFY_MESSAGE_PARA {
+            cbSize: size_of::<CRYPT_VERIFY_MESSAGE_PARA>() as u32,
+            dwMsgAndCertEncodingType: ENCODING.0,
+            ..Default::default()
+        };
+        let content = [data.as_ptr()];
+        let lengths = [data.len() as u32];
+        let mut signer: *mut CERT_CONTEXT = ptr::null_mut();
+        unsafe { CryptVerifyDetachedMessageSignature(&para, 0, signature, 1, content.as_ptr(), lengths.as_ptr(), Some(&mut signer as *mut _)) }
+            .map_err(|e| format!("signature does not match: {}", e))?;
+
+        // Intermediates come from the certificates embedded in the signature
+        let message_store = unsafe { CryptGetMessageCertificates(ENCODING.0, HCRYPTPROV_LEGACY(0), 0, signature) }.unwrap_or_default();
+        let (chain, trust_error) = match build_chain(signer, message_store) {
+            Some(chain) => {
+                let subjects = chain_certificates(chain).into_iter().map(|context| display_name(context, 0)).collect();
+                let trust_error = chain_policy_error(chain);
+                unsafe { CertFreeCertificateChain(chain) };
+                (subjects, trust_error)
+            }
+            None => (vec![display_name(signer, 0)], Some("no certificate chain could be built".to_string())),
+        };
+        let check = SignatureCheck { signer: certificate_info(signer, chain), trust_error };
+        unsafe {
+            CertFreeCertificateContext(Some(signer as *const CERT_CONTEXT));
+            if !message_store.is_invalid() {
+                let _ = CertCloseStore(message_store, 0);
+            }
+        }
+        Ok(check)
+    }
+
+    fn build_chain(certificate: *const CERT_CONTEXT, additional_store: HCERTSTORE) -> Option<*mut CERT_CHAIN_CONTEXT> {
+        let para = CERT_CHAIN_PARA { cbSize: size_of::<CERT_CHAIN_PARA>() as u32, ..Default::default() };
+        let mut chain: *mut CERT_CHAIN_CONTEXT = ptr::null_mut();
+        unsafe { CertGetCertificateChain(HCERTCHAINENGINE::default(), certificate, None, additional_store, &para, 0, None, &mut chain) }.ok()?;
+        (!chain.is_null()).then_some(chain)
+    }
+
+    /// Certificates of the first simple chain, signer first
+    fn chain_certificates(chain: *const CERT_CHAIN_CONTEXT) -> Vec<*const CERT_CONTEXT> {
+        unsafe {
+            if (*chain).cChain == 0 {
+                return Vec::new();
+            }
+            let simple = &**(*chain).rgpChain;
+            (0..simple.cElement as usize).map(|index| (**simple.rgpElement.add(index)).pCertContext).collect()
+        }
+    }
+
+    fn chain_policy_error(chain: *const CERT_CHAIN_CONTEXT) -> Option<String> {
+        let para = CERT_CHAIN_POLICY_PARA { cbSize: size_of::<CERT_CHAIN_POLICY_PARA>() as u32, ..Default::default() };
+        let mut status = CERT_CHAIN_POLICY_STATUS { cbSize: size_of::<CERT_CHAIN_POLICY_STATUS>() as u32, ..Default::default() };
+        if !unsafe { CertVerifyCertificateChainPolicy(CERT_CHAIN_POLICY_BASE, chain, &para, &mut status) }.as_bool() {
+            return Some("the chain policy could not be checked".to_string());
+        }
+        (status.dwError != 0).then(|| HRESULT(status.dwError as i32).message().to_string())
+    }
+
+    fn certificate_info(certificate: *const CERT_CONTEXT, chain: Vec<String>) -> SignerInfo {
+        let mut thumbprint = [0u8; 20];
+        let mut length = thumbprint.len() as u32;
+        let thumbprint = match unsafe { CertGetCertificateContextProperty(certificate, CERT_SHA1_HASH_PROP_ID, Some(thumbprint.as_mut_ptr() as *mut c_void), &mut length) } {
+            Ok(()) => hex::encode_upper(&thumbprint[..length as usize]),
+            Err(_) => String::new(),
+        };
+        let not_after = unsafe {
+            let not_after = (*(*certificate).pCertInfo).NotAfter;
+            filetime_to_datetime(((not_after.dwHighDateTime as u64) << 32) | not_after.dwLowDateTime as u64).map(|time| time.to_rfc3339())
+        };
+        SignerInfo {
+            subject: display_name(certificate, 0),
+            issuer: display_name(certificate, CERT_NAME_ISSUER_FLAG),
+            thumbprint,
+            not_after,
+            chain,
+        }
+    }
+
+    fn display_name(certificate: *const CERT_CONTEXT, flags: u32) -> String {
+        let mut name = [0u16; 512];
+        let length = unsafe { CertGetNameStringW(certificate, CERT_NAME_SIMPLE_DISPLAY_TYPE, flags, None, Some(&mut name)) } as usize;
+        String::from_utf16_lossy(&name[..length.saturating_sub(1)])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_certificate_source() {
+        assert_eq!(CertificateSource::parse("F:\\certs\\examiner.pfx").unwrap(), CertificateSource::Pfx(PathBuf::from("F:\\certs\\examiner.pfx")));
+        assert_eq!(
+            CertificateSource::parse("machine:3A 5F 00 11 22 33 44 55 66 77 88 99 AA BB CC DD EE FF 01 02").unwrap(),
+            CertificateSource::Machine("3A 5F 00 11 22 33 44 55 66 77 88 99 AA BB CC DD EE FF 01 02".to_string())
+        );
+        assert_eq!(CertificateSource::parse("Machine:IR Team").unwrap(), CertificateSource::Machine("IR Team".to_string()));
+        assert!(CertificateSource::parse("machine:").is_err());
+        assert!(CertificateSource::parse("").is_err());
+    }
+
+    #[test]
+    fn test_signature_info() {
+        assert!(signature_info(None, "CASE_evidence.zip").contains("Status: Not digitally signed"));
+
+        let signer = SignerInfo {
+            subject: "IR Examiner".to_string(),
+            issuer: "Contoso Issuing CA".to_string(),
+            thumbprint: "3A5F00112233445566778899AABBCCDDEEFF0102".to_string(),
+            not_after: Some("2027-01-01T00:00:00+00:00".to_string()),
+            chain: vec!["IR Examiner".to_string(), "Contoso Issuing CA".to_string(), "Contoso Root CA".to_string()],
+        };
+        let info = signature_info(Some(&signer), "CASE_evidence.zip");
+        assert!(info.contains("Thumbprint (SHA-1): 3A5F00112233445566778899AABBCCDDEEFF0102"));
+        assert!(info.contains("- IR Examiner\n  - Contoso Issuing CA\n    - Contoso Root CA\n"));
+        assert!(info.contains("- CASE_evidence.zip.p7s: the archive"));
+    }
+}
@@ -0,0 +1,250 @@
+//! Multi-volume evidence packages (`--max-package-size`)
+//! An evidence archive larger than the limit is split byte-wise into
+//! `<archive>.zip.001`, `.002`, ... so FAT32 media (4 GiB per file) can carry
+//! it; `copy /b` or `cat` joins the volumes back into the archive. The volume
+//! manifest `<archive>.zip.volumes.json` records every volume with its size
+//! and SHA-256 and the SHA-256 of the joined archive, to which the
+//! `.zip.sha256` and `.zip.p7s` files next to the volumes still apply.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use tempfile::NamedTempFile;
+
+/// Volume manifest written next to the volumes, replacing the archive
+pub const VOLUME_MANIFEST_EXTENSION: &str = "zip.volumes.json";
+
+const COPY_BUFFER_SIZE: usize = 1024 * 1024;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct VolumeManifest {
+    pub archive: String, // File name of the joined archive
+    pub archive_size: u64,
+    pub archive_sha256: String,
+    pub volume_size: u64, // Limit every volume but the last is filled to
+    pub volumes: Vec<Volume>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Volume {
+    pub file_name: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+/// Split `archive_path` into volumes of at most `max_volume_size` bytes when it
+/// is larger, removing the archive. Returns the volume manifest path, or None
+/// when the archive fits.
+pub fn split(archive_path: &Path, max_volume_size: u64) -> Result<Option<PathBuf>, Box<dyn std::error::Error>> {
+    if max_volume_size == 0 {
+        return Err("the maximum package size must be positive".into());
+    }
+    let archive_size = fs::metadata(archive_path)?.len();
+    if archive_size <= max_volume_size {
+        return Ok(None);
+    }
+    let archive_name = file_name(archive_path);
+
+    let mut reader = BufReader::new(File::open(archive_path)?);
+    let mut archive_hasher = Sha256::new();
+    let mut volumes = Vec::new();
+    let mut buffer = vec![0u8; COPY_BUFFER_SIZE];
+    let mut remaining = archive_size;
+    while remaining > 0 {
+        let volume_path = archive_path.with_extension(format!("zip.{:03}", volumes.len() + 1));
+        let mut writer = BufWriter::new(File::create(&volume_path)?);
+        let mut volume_hasher = Sha256::new();
+        let size = remaining.min(max_volume_size);
+        let mut left = size;
+        while left > 0 {
+            let chunk = &mut buffer[..left.min(COPY_BUFFER_SIZE as u64) as usize];
+            reader.read_exact(chunk)?;
+            writer.write_all(chunk)?;
+            volume_hasher.update(&*chunk);
+            archive_hasher.update(&*chunk);
+            left -= chunk.len() as u64;
+        }
+        writer.flush()?;
+        volumes.push(Volume {
+            file_name: file_name(&volume_path),
+            size,
+            sha256: hex::encode(volume_hasher.finalize()),
+        });
+        remaining -= size;
+    }
+
+    let manifest = VolumeManifest {
+        archive: archive_name,
+        archive_size,
+        archive_sha256: hex::encode(archive_hasher.finalize()),
+        volume_size: max_volume_size,
+        volumes,
+    };
+    let manifest_path = archive_path.with_extension(VOLUME_MANIFEST_EXTENSION);
+    fs::write(&manifest_path, serde_json::to_string_pretty(&manifest)?)?;
+    drop(reader);
+    fs::remove_file(archive_path)?;
+    Ok(Some(manifest_path))
+}
+
+/// An evidence package named on the command line: a single archive, or a
+/// multi-volume package joined into a temporary archive
+pub struct Package {
+    /// Where the single archive is, or was before it was split
+    pub archive_path: PathBuf,
+    joined: Option<NamedTempFile>,
+}
+
+impl Package {
+    /// The archive to read: the single archive or the joined volumes
+    pub fn path(&self) -> &Path {
+        self.joined.as_ref().map(NamedTempFile::path).unwrap_or(&self.archive_path)
+    }
+}
+
+/// Resolve the archive, its volume manifest or one of its volumes to the
+/// package. Volumes are checked against the manifest before they are joined;
+/// their discrepancies are returned instead of a package.
+pub fn open(package_path: &Path) -> Result<Result<Package, Vec<String>>, Box<dyn std::error::Error>> {
+    let Some(manifest_path) = volume_manifest_path(package_path) else {
+        return Ok(Ok(Package { archive_path: package_path.to_path_buf(), joined: None }));
+    };
+    let manifest: VolumeManifest = serde_json::from_str(&fs::read_to_string(&manifest_path)
+        .map_err(|e| format!("Failed to read {}: {}", manifest_path.display(), e))?)
+        .map_err(|e| format!("{} is not a volume manifest: {}", manifest_path.display(), e))?;
+    let directory = manifest_path.parent().unwrap_or(Path::new(""));
+
+    let mut joined = NamedTempFile::new()?;
+    let discrepancies = join(&manifest, directory, joined.as_file_mut())?;
+    if !discrepancies.is_empty() {
+        return Ok(Err(discrepancies));
+    }
+    Ok(Ok(Package { archive_path: directory.join(&manifest.archive), joined: Some(joined) }))
+}
+
+/// Volume manifest of `package_path` if it belongs to a multi-volume package
+fn volume_manifest_path(package_path: &Path) -> Option<PathBuf> {
+    let name = package_path.file_name()?.to_string_lossy();
+    if name.ends_with(".volumes.json") {
+        return Some(package_path.to_path_buf());
+    }
+    let extension = package_path.extension()?.to_string_lossy();
+    if extension.len() >= 3 && extension.bytes().all(|b| b.is_ascii_digit()) {
+        // <archive>.zip.001
+        return Some(package_path.with_extension("volumes.json"));
+    }
+    let manifest_path = package_path.with_extension(VOLUME_MANIFEST_EXTENSION);
+    (!package_path.exists() && manifest_path.exists()).then_some(manifest_path)
+}
+
+/// Append the volumes in `directory` to `destination`, checking each one and the
+/// joined archive against the manifest; returns the discrepancies
+fn join(manifest: &VolumeManifest, directory: &Path, destination: &mut File) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut discrepancies = Vec::new();
+    let mut writer = BufWriter::new(destination);
+    let mut archive_hasher = Sha256::new();
+    let mut buffer = vec![0u8; COPY_BUFFER_SIZE];
+    for volume in &manifest.volumes {
+        let mut reader = match File::open(directory.join(&volume.file_name)) {
+            Ok(file) => BufReader::new(file),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                discrepancies.push(format!("{}: volume missing", volume.file_name));
+                continue;
+            }
+            Err(e) => return Err(format!("Failed to open {}: {}", volume.file_name, e).into()),
+        };
+        let mut volume_hasher = Sha256::new();
+        let mut size = 0u64;
+        loop {
+            let read = reader.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            writer.write_all(&buffer[..read])?;
+            volume_hasher.update(&buffer[..read]);
+            archive_hasher.update(&buffer[..read]);
+            size += read as u64;
+        }
+        let sha256 = hex::encode(volume_hasher.finalize());
+        if size != volume.size || !sha256.eq_ignore_ascii_case(&volume.sha256) {
+            discrepancies.push(format!("{}: {} bytes with SHA-256 {}, the manifest records {} bytes with {}", volume.file_name, size, sha256, volume.size, volume.sha256));
+        }
+    }
+    writer.flush()?;
+
+    let archive_sha256 = hex::encode(archive_hasher.finalize());
+    if discrepancies.is_empty() && !archive_sha256.eq_ignore_ascii_case(&manifest.archive_sha256) {
+        discrepancies.push(format!("{}: joined volumes have SHA-256 {}, the manifest records {}", manifest.archive, archive_sha256, manifest.archive_sha256));
+    }
+    Ok(discrepancies)
+}
+
+/// Files making up the package written to `package_path` (the archive or its
+/// volume manifest): the archive or volumes, the manifest, the .zip.sha256 and,
+/// when signed, the .zip.p7s
+pub fn package_files(package_path: &Path, signed: bool) -> Vec<PathBuf> {
+    let manifest = package_path.to_string_lossy().ends_with(".volumes.json")
+        .then(|| fs::read_to_string(package_path).ok())
+        .flatten()
+        .and_then(|text| serde_json::from_str::<VolumeManifest>(&text).ok());
+    let Some(manifest) = manifest else {
+        let mut files = vec![package_path.to_path_buf(), package_path.with_extension("zip.sha256")];
+        if signed {
+            files.push(package_path.with_extension(crate::package_signing::ARCHIVE_SIGNATURE_EXTENSION));
+        }
+        return files;
+    };
+
+    let directory = package_path.parent().unwrap_or(Path::new(""));
+    let archive_path = directory.join(&manifest.archive);
+    let mut files: Vec<PathBuf> = manifest.volumes.iter().map(|volume| directory.join(&volume.file_name)).collect();
+    files.push(package_path.to_path_buf());
+    files.push(archive_path.with_extension("zip.sha256"));
+    if signed {
+        files.push(archive_path.with_extension(crate::package_signing::ARCHIVE_SIGNATURE_EXTENSION));
+    }
+    files
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_and_join() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let archive_path = temp_dir.path().join("CASE-1_20261016_120000_evidence.zip");
+        let content: Vec<u8> = (0..2500u32).map(|i| (i % 251) as u8).collect();
+        fs::write(&archive_path, &content).unwrap();
+
+        assert_eq!(split(&archive_path, 4096).unwrap(), None);
+        let manifest_path = split(&archive_path, 1000).unwrap().unwrap();
+        assert!(!archive_path.exists());
+        assert_eq!(manifest_path, temp_dir.path().join("CASE-1_20261016_120000_evidence.zip.volumes.json"));
+        let manifest: VolumeManifest = serde_json::from_str(&fs::read_to_string(&manifest_path).unwrap()).unwrap();
+        let sizes: Vec<u64> = manifest.volumes.iter().map(|volume| volume.size).collect();
+        assert_eq!(sizes, [1000, 1000, 500]);
+        assert_eq!(manifest.volumes[2].file_name, "CASE-1_20261016_120000_evidence.zip.003");
+        assert_eq!(package_files(&manifest_path, false).len(), 5);
+
+        // The archive name, the manifest and any volume resolve to the joined archive
+        for path in [archive_path.clone(), manifest_path.clone(), temp_dir.path().join("CASE-1_20261016_120000_evidence.zip.002")] {
+            let package = open(&path).unwrap().unwrap();
+            assert_eq!(package.archive_path, archive_path);
+            assert_eq!(fs::read(package.path()).unwrap(), content);
+        }
+
+        fs::write(temp_dir.path().join("CASE-1_20261016_120000_evidence.zip.002"), vec![0u8; 1000]).unwrap();
+        fs::remove_file(temp_dir.path().join("CASE-1_20261016_120000_evidence.zip.003")).unwrap();
+        let discrepancies = open(&manifest_path).unwrap().err().unwrap();
+        assert_eq!(discrepancies.len(), 2);
+        assert!(discrepancies[0].starts_with("CASE-1_20261016_120000_evidence.zip.002: 1000 bytes with SHA-256"));
+        assert_eq!(discrepancies[1], "CASE-1_20261016_120000_evidence.zip.003: volume missing");
+    }
+}
@@ -0,0 +1,453 @@
+//! Search path hijack detection
+//! Programs started by name are looked up in the App Paths registrations (Run
+//! dialog, ShellExecute) and in the PATH directories, system entries before the
+//! user's; the shell folders decide where Startup items and profile data live.
+//! A user-writable directory in PATH, above all ahead of System32 in the system
+//! PATH that services inherit, lets anyone plant `net.exe` or `whoami.exe` that
+//! an administrator's script then runs. Folders created at the drive root (such
+//! as `C:\Python27`) grant Authenticated Users modify access by default and are
+//! just as writable. Each PATH entry, App Paths key and redirected shell folder
+//! is scored like the Winlogon and IFEO entries; for the search path directories
+//! the executables named like common tools or like a binary further down the
+//! search order are listed with their hashes.
+
+// Registry reading is only reachable through the Windows registry API
+#![cfg_attr(not(windows), allow(dead_code))]
+
+use crate::footprint;
+use crate::forensic_types::{PathHijack, PlantedBinary};
+use crate::hashing;
+use crate::path_norm;
+use std::path::PathBuf;
+
+#[cfg(windows)]
+use crate::footprint::TrackedKey;
+#[cfg(windows)]
+use crate::offline;
+#[cfg(windows)]
+use crate::user_hives::UserHive;
+#[cfg(windows)]
+use winreg::RegKey;
+
+/// Machine PATH, relative to the control set; user PATH, relative to NTUSER.DAT
+const ENVIRONMENT_KEY: &str = r"Control\Session Manager\Environment";
+const USER_ENVIRONMENT_KEY: &str = "Environment";
+/// App Paths registrations, relative to SOFTWARE and to NTUSER.DAT
+const APP_PATHS_KEY: &str = r"Microsoft\Windows\CurrentVersion\App Paths";
+const USER_APP_PATHS_KEY: &str = r"Software\Microsoft\Windows\CurrentVersion\App Paths";
+/// Shell folder locations, relative to SOFTWARE and to NTUSER.DAT
+const SHELL_FOLDERS_KEY: &str = r"Microsoft\Windows\CurrentVersion\Explorer\User Shell Folders";
+const USER_SHELL_FOLDERS_KEY: &str = r"Software\Microsoft\Windows\CurrentVersion\Explorer\User Shell Folders";
+
+/// Shell folders holding autostart entries or program data; Desktop and Documents
+/// are routinely redirected to file servers and OneDrive and are left out
+const WATCHED_USER_FOLDERS: [&str; 5] = ["Startup", "Programs", "Start Menu", "AppData", "Local AppData"];
+const WATCHED_COMMON_FOLDERS: [&str; 4] = ["Common Startup", "Common Programs", "Common Start Menu", "Common AppData"];
+
+/// Path fragments of user-writable locations
+const WRITABLE_PATHS: [&str; 7] = ["\\appdata\\", "\\temp\\", "\\tmp\\", "\\downloads\\", "\\desktop\\", "\\users\\public\\", "\\programdata\\"];
+/// App execution aliases; per-user and writable, but part of every default user PATH
+const BENIGN_WRITABLE_PATHS: [&str; 1] = ["\\appdata\\local\\microsoft\\windowsapps\\"];
+/// Top-level folders whose ACLs keep users from writing
+const PROTECTED_ROOT_FOLDERS: [&str; 5] = ["windows", "program files", "program files (x86)", "programdata", "users"];
+
+/// Extensions found by a search for a bare command name (default PATHEXT, minus scripts)
+const EXECUTABLE_EXTENSIONS: [&str; 4] = ["exe", "com", "bat", "cmd"];
+
+/// Tools administrators and scripts run by name
+const COMMON_TOOLS: [&str; 36] = [
+    "cmd", "powershell", "pwsh", "net", "net1", "whoami", "ipconfig", "tasklist", "taskkill", "sc", "reg", "schtasks", "systeminfo",
+    "ping", "nslookup", "netstat", "netsh", "wmic", "certutil", "msiexec", "rundll32", "regsvr32", "conhost", "svchost", "explorer",
+    "notepad", "where", "findstr", "hostname", "arp", "route", "runas", "mshta", "cscript", "wscript", "python",
+];
+
+/// Collect the PATH, App Paths and shell folder hijacks from opened machine keys and user hives;
+/// `resolve` maps a live path onto the volume being examined
+#[cfg(windows)]
+pub fn detect_path_hijacks(
+    software: Option<(&RegKey, &str)>,
+    control_set: Option<(&RegKey, &str)>,
+    hives: &[UserHive],
+    resolve: &dyn Fn(&str) -> PathBuf,
+) -> Vec<PathHijack> {
+    let mut findings = Vec::new();
+
+    let system_entries = control_set
+        .map(|(key, prefix)| search_path_entries(key, ENVIRONMENT_KEY, prefix, "system_path", None))
+        .unwrap_or_default();
+    findings.extend(analyze_search_path(system_entries.clone(), resolve));
+
+    for hive in hives {
+        let profile = profile_directory(&hive.user);
+        let user_entries = search_path_entries(&hive.key, USER_ENVIRONMENT_KEY, &hive.source, "user_path", Some((&hive.user, &profile)));
+        if !user_entries.is_empty() {
+            // The user's entries follow the system entries in the effective PATH
+            let combined: Vec<PathHijack> = system_entries.iter().cloned().chain(user_entries).collect();
+            findings.extend(analyze_search_path(combined, resolve).into_iter().filter(|finding| finding.source == "user_path"));
+        }
+        findings.extend(app_path_hijacks(&hive.key, USER_APP_PATHS_KEY, &hive.source, Some(&hive.user), Some(&profile), resolve));
+        findings.extend(known_folder_hijacks(&hive.key, USER_SHELL_FOLDERS_KEY, &hive.source, &WATCHED_USER_FOLDERS, Some((&hive.user, &profile))));
+    }
+
+    if let Some((software, prefix)) = software {
+        for view in ["", r"WOW6432Node\"] {
+            findings.extend(app_path_hijacks(software, &format!("{}{}", view, APP_PATHS_KEY), prefix, None, None, resolve));
+        }
+        findings.extend(known_folder_hijacks(software, SHELL_FOLDERS_KEY, prefix, &WATCHED_COMMON_FOLDERS, None));
+    }
+    findings
+}
+
+/// The directories of a PATH value, in order, as unscored findings
+#[cfg(windows)]
+fn search_path_entries(root: &RegKey, key_path: &str, prefix: &str, source: &str, user: Option<(&str, &str)>) -> Vec<PathHijack> {
+    let Ok(key) = root.open_tracked(key_path) else { return Vec::new() };
+    let Ok(value) = key.get_value::<String, _>("Path") else { return Vec::new() };
+    let last_write_time = offline::key_last_write_time(&key);
+    value
+        .split(';')
+        .map(|entry| entry.trim().trim_matches('"'))
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| PathHijack {
+            source: source.to_string(),
+            registry_key: format!(r"{}\{}", prefix, key_path),
+            value_name: "Path".to_string(),
+            value_data: entry.to_string(),
+            directory: expand(entry, user.map(|(_, profile)| profile)),
+            user: user.map(|(name, _)| name.to_string()),
+            search_position: None,
+            planted_binaries: Vec::new(),
+            last_write_time: last_write_time.clone(),
+            suspicion_score: 0,
+            suspicion_reasons: Vec::new(),
+        })
+        .collect()
+}
+
+/// App Paths registrations with a suspicion score, below one registry view
+#[cfg(windows)]
+fn app_path_hijacks(root: &RegKey, key_path: &str, prefix: &str, user: Option<&str>, profile: Option<&str>, resolve: &dyn Fn(&str) -> PathBuf) -> Vec<PathHijack> {
+    let Ok(app_paths) = root.open_tracked(key_path) else { return Vec::new() };
+    let mut findings = Vec::new();
+    for name in app_paths.enum_keys().filter_map(|k| k.ok()) {
+        let Ok(app_key) = app_paths.open_tracked(&name) else { continue };
+        let Ok(target) = app_key.get_value::<String, _>("") else { continue };
+        let target = target.trim().trim_matches('"').to_string();
+        let search_path: Vec<String> = app_key
+            .get_value::<String, _>("Path")
+            .map(|path| path.split(';').filter(|d| !d.trim().is_empty()).map(|d| expand(d.trim(), profile)).collect())
+            .unwrap_or_default();
+        let system_binary = resolve(&format!("C:\\Windows\\System32\\{}", name)).is_file();
+        let directory = expand(&target, profile);
+        let (suspicion_score, suspicion_reasons) = score_app_path(&name, &directory, &search_path, system_binary);
+        if suspicion_score == 0 {
+            continue;
+        }
+        findings.push(PathHijack {
+            source: "app_paths".to_string(),
+            registry_key: format!(r"{}\{}\{}", prefix, key_path, name),
+            value_name: "(Default)".to_string(),
+            value_data: target,
+            directory,
+            user: user.map(str::to_string),
+            search_position: None,
+            planted_binaries: Vec::new(),
+            last_write_time: offline::key_last_write_time(&app_key),
+            suspicion_score,
+            suspicion_reasons,
+        });
+    }
+    findings
+}
+
+/// Watched shell folders redirected away from their default location
+#[cfg(windows)]
+fn known_folder_hijacks(root: &RegKey, key_path: &str, prefix: &str, watched: &[&str], user: Option<(&str, &str)>) -> Vec<PathHijack> {
+    let Ok(key) = root.open_tracked(key_path) else { return Vec::new() };
+    let last_write_time = offline::key_last_write_time(&key);
+    let mut findings = Vec::new();
+    for value_name in watched {
+        let Ok(value_data) = key.get_value::<String, _>(value_name) else { continue };
+        let directory = expand(&value_data, user.map(|(_, profile)| profile));
+        let (suspicion_score, suspicion_reasons) = score_known_folder(value_name, &directory, user.map(|(_, profile)| profile));
+        if suspicion_score == 0 {
+            continue;
+        }
+        findings.push(PathHijack {
+            source: "known_folder".to_string(),
+            registry_key: format!(r"{}\{}", prefix, key_path),
+            value_name: value_name.to_string(),
+            value_data,
+            directory,
+            user: user.map(|(name, _)| name.to_string()),
+            search_position: None,
+            planted_binaries: Vec::new(),
+            last_write_time: last_write_time.clone(),
+            suspicion_score,
+            suspicion_reasons,
+        });
+    }
+    findings
+}
+
+/// Score the directories of an effective PATH (in search order) and list the
+/// binaries planted in the exploitable ones; entries without findings are dropped
+pub fn analyze_search_path(mut entries: Vec<PathHijack>, resolve: &dyn Fn(&str) -> PathBuf) -> Vec<PathHijack> {
+    for (index, entry) in entries.iter_mut().enumerate() {
+        entry.search_position = Some(index as u32);
+    }
+    let first_system = entries.iter().position(|entry| is_system_directory(&entry.directory));
+    let mut findings = Vec::new();
+
+    for (index, entry) in entries.iter().enumerate() {
+        let writable = is_user_writable(&entry.directory);
+        if !writable && !in_created_root_folder(&entry.directory) {
+            continue;
+        }
+        let mut score = 0;
+        let mut reasons = Vec::new();
+        if writable {
+            score += 30;
+            reasons.push("user_writable_directory".to_string());
+        } else {
+            score += 15;
+            reasons.push("created_root_folder".to_string());
+        }
+        if first_system.is_some_and(|system| index < system) {
+            score += 30;
+            reasons.push("precedes_system_directories".to_string());
+        }
+
+        let mut planted = Vec::new();
+        let directory = resolve(&entry.directory);
+        match footprint::read_dir(&directory) {
+            Ok(files) => {
+                for file in files.filter_map(|f| f.ok()).filter(|f| f.path().is_file()) {
+                    let file_name = file.file_name().to_string_lossy().to_lowercase();
+                    let Some((stem, extension)) = file_name.rsplit_once('.') else { continue };
+                    if !EXECUTABLE_EXTENSIONS.contains(&extension) {
+                        continue;
+                    }
+                    let shadowed = entries[index + 1..].iter().find(|later| resolve(&later.directory).join(&file_name).is_file());
+                    if shadowed.is_none() && !COMMON_TOOLS.contains(&stem) {
+                        continue;
+                    }
+                    planted.push((
+                        PlantedBinary {
+                            path: format!("{}\\{}", entry.directory, file_name),
+                            shadows: shadowed.map(|later| format!("{}\\{}", later.directory, file_name)),
+                            hashes: hashing::shared().hash_file(file.path()).ok(),
+                        },
+                        shadowed.is_some_and(|later| is_system_directory(&later.directory)),
+                    ));
+                }
+            }
+            Err(_) => {
+                // Whoever can write the parent can create the directory and plant into it
+                score += 15;
+                reasons.push("missing_directory".to_string());
+            }
+        }
+        if planted.iter().any(|(_, system)| *system) {
+            score += 50;
+            reasons.push("shadows_system_binary".to_string());
+        } else if planted.iter().any(|(binary, _)| binary.shadows.is_some()) {
+            score += 30;
+            reasons.push("shadows_later_binary".to_string());
+        }
+        if planted.iter().any(|(binary, _)| binary.path.rsplit('\\').next().and_then(|name| name.rsplit_once('.')).is_some_and(|(stem, _)| COMMON_TOOLS.contains(&stem))) {
+            score += 40;
+            reasons.push("common_tool_name".to_string());
+        }
+
+        let mut finding = entry.clone();
+        finding.planted_binaries = planted.into_iter().map(|(binary, _)| binary).collect();
+        finding.suspicion_score = score.min(100);
+        finding.suspicion_reasons = reasons;
+        findings.push(finding);
+    }
+    findings
+}
+
+/// Score an App Paths registration (0-100) with the reasons; `target` and `search_path` are normalized
+fn score_app_path(name: &str, target: &str, search_path: &[String], system_binary: bool) -> (u32, Vec<String>) {
+    let mut score = 0;
+    let mut reasons = Vec::new();
+    let name = name.to_lowercase();
+    let stem = name.strip_suffix(".exe").unwrap_or(&name);
+    let target_name = target.rsplit('\\').next().unwrap_or_default();
+
+    if (system_binary || COMMON_TOOLS.contains(&stem)) && !is_system_directory(target) {
+        score += 50;
+        reasons.push("redirects_system_tool".to_string());
+    }
+    if is_user_writable(target) {
+        score += 30;
+        reasons.push("user_writable_target".to_string());
+    }
+    if target_name != name {
+        score += 15;
+        reasons.push("renamed_target".to_string());
+    }
+    if search_path.iter().any(|directory| is_user_writable(directory)) {
+        score += 20;
+        reasons.push("user_writable_search_path".to_string());
+    }
+    (score.min(100), reasons)
+}
+
+/// Score a shell folder location (0-100) with the reasons; `profile` is the
+/// user's profile directory, None for the machine-wide (Common) folders
+fn score_known_folder(value_name: &str, directory: &str, profile: Option<&str>) -> (u32, Vec<String>) {
+    let mut score = 0;
+    let mut reasons = Vec::new();
+    let directory = format!("{}\\", directory.trim_end_matches('\\'));
+    let expected = match profile {
+        Some(profile) => directory.starts_with(&format!("{}\\", path_norm::normalize(profile))),
+        None => directory.starts_with("c:\\programdata\\"),
+    };
+
+    if !expected {
+        score += 30;
+        reasons.push("unexpected_location".to_string());
+        if value_name.ends_with("Startup") {
+            score += 30;
+            reasons.push("startup_folder_redirected".to_string());
+        }
+    }
+    // Machine-wide folders inside any profile are writable by that user
+    let writable = match profile {
+        Some(_) => ["\\temp\\", "\\tmp\\", "\\users\\public\\", "\\downloads\\"].iter().any(|fragment| directory.contains(fragment)),
+        None => directory.starts_with("c:\\users\\"),
+    };
+    if writable {
+        score += 30;
+        reasons.push("user_writable_target".to_string());
+    }
+    (score.min(100), reasons)
+}
+
+/// Expand a registry path; the per-user variables of other accounts are
+/// expanded against their profile instead of the collector's
+fn expand(path: &str, profile: Option<&str>) -> String {
+    let Some(profile) = profile else { return path_norm::normalize(path) };
+    let variables = [
+        ("%userprofile%", profile.to_string()),
+        ("%appdata%", format!("{}\\AppData\\Roaming", profile)),
+        ("%localappdata%", format!("{}\\AppData\\Local", profile)),
+        ("%temp%", format!("{}\\AppData\\Local\\Temp", profile)),
+        ("%tmp%", format!("{}\\AppData\\Local\\Temp", profile)),
+    ];
+    let mut expanded = path.to_string();
+    for (variable, value) in variables {
+        while let Some(start) = expanded.to_lowercase().find(variable) {
+            expanded.replace_range(start..start + variable.len(), &value);
+        }
+    }
+    path_norm::normalize(&expanded)
+}
+
+/// Profile directory of a hive's user (`DOMAIN\user` or profile folder name)
+fn profile_directory(user: &str) -> String {
+    format!("C:\\Users\\{}", user.rsplit('\\').next().unwrap_or(user))
+}
+
+fn is_system_directory(path: &str) -> bool {
+    path == "c:\\windows" || path.starts_with("c:\\windows\\")
+}
+
+fn is_user_writable(path: &str) -> bool {
+    let path = format!("{}\\", path.trim_end_matches('\\'));
+    if BENIGN_WRITABLE_PATHS.iter().any(|fragment| path.contains(fragment)) {
+        return false;
+    }
+    path.starts_with("c:\\users\\") || WRITABLE_PATHS.iter().any(|fragment| path.contains(fragment))
+}
+
+/// Below a folder created at the root of a local drive, which inherits the root's
+/// "Authenticated Users: modify" permission
+fn in_created_root_folder(path: &str) -> bool {
+    let mut components = path.split('\\');
+    let drive = components.next().unwrap_or_default();
+    if drive.len() != 2 || !drive.ends_with(':') {
+        return false;
+    }
+    components.next().is_some_and(|folder| !folder.is_empty() && !PROTECTED_ROOT_FOLDERS.contains(&folder))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::collections::HashMap;
+
+    fn entry(source: &str, directory: &str) -> PathHijack {
+        PathHijack {
+            source: source.to_string(),
+            registry_key: String::new(),
+            value_name: "Path".to_string(),
+            value_data: directory.to_string(),
+            directory: path_norm::normalize(directory),
+            user: None,
+            search_position: None,
+            planted_binaries: Vec::new(),
+            last_write_time: String::new(),
+            suspicion_score: 0,
+            suspicion_reasons: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_analyze_search_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut locations = HashMap::new();
+        for (live, local) in [("c:\\users\\public\\bin", "public"), ("c:\\windows\\system32", "system32"), ("c:\\tools", "tools")] {
+            fs::create_dir(dir.path().join(local)).unwrap();
+            locations.insert(live.to_string(), dir.path().join(local));
+        }
+        fs::write(dir.path().join("system32").join("whoami.exe"), b"MZ system").unwrap();
+        fs::write(dir.path().join("public").join("whoami.exe"), b"MZ planted").unwrap();
+        fs::write(dir.path().join("public").join("readme.txt"), b"").unwrap();
+        fs::write(dir.path().join("tools").join("python.exe"), b"MZ").unwrap();
+        let missing = dir.path().join("missing");
+        let resolve = |path: &str| locations.get(path).cloned().unwrap_or_else(|| missing.clone());
+
+        let entries = vec![
+            entry("system_path", "C:\\Users\\Public\\bin"),
+            entry("system_path", "%SystemRoot%\\System32"),
+            entry("system_path", "C:\\Program Files\\Git\\cmd"),
+            entry("user_path", "C:\\Tools"),
+            entry("user_path", "C:\\Python27"),
+        ];
+        let findings = analyze_search_path(entries, &resolve);
+        assert_eq!(findings.len(), 3);
+
+        let public = &findings[0];
+        assert_eq!(public.search_position, Some(0));
+        assert_eq!(public.suspicion_reasons, vec!["user_writable_directory", "precedes_system_directories", "shadows_system_binary", "common_tool_name"]);
+        assert_eq!(public.suspicion_score, 100);
+        assert_eq!(public.planted_binaries.len(), 1);
+        assert_eq!(public.planted_binaries[0].shadows.as_deref(), Some("c:\\windows\\system32\\whoami.exe"));
+        assert!(public.planted_binaries[0].hashes.is_some());
+
+        let tools = &findings[1];
+        assert_eq!((tools.suspicion_score, tools.planted_binaries[0].path.as_str()), (55, "c:\\tools\\python.exe"));
+        assert_eq!(findings[2].suspicion_reasons, vec!["created_root_folder", "missing_directory"]);
+    }
+
+    #[test]
+    fn test_score_app_paths_and_known_folders() {
+        let (score, reasons) = score_app_path("cmd.exe", "c:\\users\\public\\cmd.exe", &[], true);
+        assert_eq!((score, reasons), (80, vec!["redirects_system_tool".to_string(), "user_writable_target".to_string()]));
+        assert_eq!(score_app_path("PowerShell.exe", "c:\\windows\\system32\\windowspowershell\\v1.0\\powershell.exe", &[], true).0, 0);
+        assert_eq!(score_app_path("chrome.exe", "c:\\program files\\google\\chrome\\application\\chrome.exe", &[], false).0, 0);
+        assert_eq!(score_app_path("notepad++.exe", "c:\\tools\\npp.exe", &["c:\\users\\bob\\appdata\\local\\temp".to_string()], false).1, vec!["renamed_target", "user_writable_search_path"]);
+
+        let profile = "C:\\Users\\bob";
+        assert_eq!(score_known_folder("Startup", &expand("%APPDATA%\\Microsoft\\Windows\\Start Menu\\Programs\\Startup", Some(profile)), Some(profile)).0, 0);
+        let (score, reasons) = score_known_folder("Startup", &expand("C:\\Users\\Public\\Startup", Some(profile)), Some(profile));
+        assert_eq!((score, reasons.len()), (90, 3));
+        assert_eq!(score_known_folder("Common Startup", &path_norm::normalize("C:\\ProgramData\\Microsoft\\Windows\\Start Menu\\Programs\\StartUp"), None).0, 0);
+        assert_eq!(score_known_folder("Common AppData", "c:\\users\\bob\\data", None).1, vec!["unexpected_location", "user_writable_target"]);
+    }
+}
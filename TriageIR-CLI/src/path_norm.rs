@@ -0,0 +1,252 @@
+//! Path normalization for the suspicion heuristics
+//! Analyzers compare paths and command lines against fragments such as
+//! `\temp\` or `\users\public\`. Written as `C:/Users/Public/x.exe`,
+//! `%PUBLIC%\x.exe`, `$env:TEMP\x.ps1`, `C:\PROGRA~3\x.exe` or
+//! `\\?\C:\Windows\..\Temp\x.exe` the same locations slip past a plain
+//! lowercase substring test. `normalize` expands environment variables,
+//! removes NT and extended-length prefixes, resolves 8.3 short names and
+//! `.`/`..` components, canonicalizes the separators and lowercases, so the
+//! fragments only have to be written once, in lowercase with backslashes.
+
+// Short names are resolved through the file system, only on Windows
+#![cfg_attr(not(windows), allow(dead_code))]
+
+#[cfg(windows)]
+use windows::core::HSTRING;
+#[cfg(windows)]
+use windows::Win32::Storage::FileSystem::GetLongPathNameW;
+
+/// Locations of the environment variables used in persistence entries, for
+/// variables the collector's own environment does not define (offline scans,
+/// per-user variables of other accounts)
+const DEFAULT_LOCATIONS: [(&str, &str); 15] = [
+    ("systemroot", "C:\\Windows"),
+    ("windir", "C:\\Windows"),
+    ("systemdrive", "C:"),
+    ("programdata", "C:\\ProgramData"),
+    ("allusersprofile", "C:\\ProgramData"),
+    ("programfiles", "C:\\Program Files"),
+    ("programfiles(x86)", "C:\\Program Files (x86)"),
+    ("programw6432", "C:\\Program Files"),
+    ("commonprogramfiles", "C:\\Program Files\\Common Files"),
+    ("public", "C:\\Users\\Public"),
+    ("userprofile", "C:\\Users\\Default"),
+    ("appdata", "C:\\Users\\Default\\AppData\\Roaming"),
+    ("localappdata", "C:\\Users\\Default\\AppData\\Local"),
+    ("temp", "C:\\Users\\Default\\AppData\\Local\\Temp"),
+    ("tmp", "C:\\Users\\Default\\AppData\\Local\\Temp"),
+];
+
+/// Normalized form of a file path: expanded, absolute components, backslashes, lowercase
+pub fn normalize(path: &str) -> String {
+    normalize_with(path, &environment_variable, &long_path_name)
+}
+
+/// Normalized form of a command line; arguments are kept, only variables,
+/// separators, short names and case are normalized
+pub fn normalize_command(command: &str) -> String {
+    let expanded = canonical_separators(&expand_variables(command, &environment_variable));
+    resolve_short_names(&expanded, &long_path_name).to_lowercase()
+}
+
+/// Whether the path lies in one of the directories, given as lowercase fragments like `\temp\`
+pub fn in_directory(path: &str, fragments: &[&str]) -> bool {
+    let normalized = normalize(path);
+    fragments.iter().any(|fragment| normalized.contains(fragment))
+}
+
+fn normalize_with(path: &str, lookup: &dyn Fn(&str) -> Option<String>, long_name: &dyn Fn(&str) -> Option<String>) -> String {
+    let path = path.trim().trim_matches('"');
+    let expanded = canonical_separators(&expand_variables(path, lookup));
+    let stripped = strip_prefix(&expanded);
+    let resolved = resolve_short_names(&stripped, long_name);
+    remove_dot_components(&resolved).to_lowercase()
+}
+
+/// Expand %NAME% and PowerShell $env:NAME / ${env:NAME} references; unknown variables are kept
+fn expand_variables(text: &str, lookup: &dyn Fn(&str) -> Option<String>) -> String {
+    let resolve = |name: &str| {
+        lookup(name).or_else(|| {
+            DEFAULT_LOCATIONS.iter().find(|(variable, _)| variable.eq_ignore_ascii_case(name)).map(|(_, location)| location.to_string())
+        })
+    };
+
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while !rest.is_empty() {
+        let lower = rest.to_ascii_lowercase();
+        let expansion = if let Some(after) = rest.strip_prefix('%') {
+            after.find('%').and_then(|end| resolve(&after[..end]).map(|value| (value, end + 2)))
+        } else if lower.starts_with("${env:") {
+            rest.find('}').and_then(|end| resolve(&rest[6..end]).map(|value| (value, end + 1)))
+        } else if lower.starts_with("$env:") {
+            let end = rest[5..].find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).map_or(rest.len(), |end| end + 5);
+            resolve(&rest[5..end]).map(|value| (value, end))
+        } else {
+            None
+        };
+        match expansion {
+            Some((value, consumed)) => {
+                result.push_str(&value);
+                rest = &rest[consumed..];
+            }
+            None => {
+                let c = rest.chars().next().unwrap_or_default();
+                result.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+    result
+}
+
+/// Forward slashes become backslashes and runs of separators collapse, except a leading UNC `\\`
+fn canonical_separators(path: &str) -> String {
+    let path = path.replace('/', "\\");
+    let unc = path.starts_with("\\\\");
+    let mut result = String::with_capacity(path.len());
+    for c in path.chars() {
+        if !(c == '\\' && result.ends_with('\\')) {
+            result.push(c);
+        }
+    }
+    if unc {
+        result.insert(0, '\\');
+    }
+    result
+}
+
+/// Drop the extended-length (\\?\, \\.\) and NT object manager (\??\) prefixes
+fn strip_prefix(path: &str) -> String {
+    let lower = path.to_ascii_lowercase();
+    if lower.starts_with("\\\\?\\unc\\") {
+        format!("\\\\{}", &path[8..])
+    } else if lower.starts_with("\\\\?\\") || lower.starts_with("\\\\.\\") || lower.starts_with("\\??\\") {
+        path[4..].to_string()
+    } else if lower.starts_with("\\systemroot\\") {
+        format!("C:\\Windows{}", &path[11..])
+    } else {
+        path.to_string()
+    }
+}
+
+/// Replace the 8.3 components (PROGRA~1) with their long names, resolving the
+/// longest prefix that still exists: the file itself may be gone
+fn resolve_short_names(path: &str, long_name: &dyn Fn(&str) -> Option<String>) -> String {
+    let components: Vec<&str> = path.split('\\').collect();
+    let Some(last_short) = components.iter().rposition(|component| is_short_name(component)) else {
+        return path.to_string();
+    };
+    for end in (last_short..components.len()).rev() {
+        let prefix = components[..=end].join("\\");
+        if let Some(long) = long_name(&prefix) {
+            let rest = &components[end + 1..];
+            return if rest.is_empty() { long } else { format!("{}\\{}", long.trim_end_matches('\\'), rest.join("\\")) };
+        }
+    }
+    path.to_string()
+}
+
+/// NAME~N with an optional extension, as generated for 8.3 names
+fn is_short_name(component: &str) -> bool {
+    component.find('~').is_some_and(|tilde| {
+        let base = &component[..tilde];
+        let tail = component[tilde + 1..].split('.').next().unwrap_or_default();
+        !base.is_empty() && base.len() <= 7 && !tail.is_empty() && tail.chars().all(|c| c.is_ascii_digit())
+    })
+}
+
+/// Resolve `.` and `..` components; `..` never climbs above the drive or share
+fn remove_dot_components(path: &str) -> String {
+    let (root, rest) = match path.strip_prefix("\\\\") {
+        Some(unc) => {
+            // \\server\share is the root of a UNC path
+            let mut parts = unc.splitn(3, '\\');
+            let root = format!("\\\\{}\\{}", parts.next().unwrap_or_default(), parts.next().unwrap_or_default());
+            (Some(root), parts.next().unwrap_or_default().to_string())
+        }
+        None => match path.split_once('\\') {
+            // A drive, or an empty root for paths starting with a backslash
+            Some((first, rest)) if first.ends_with(':') || first.is_empty() => (Some(first.to_string()), rest.to_string()),
+            _ => (None, path.to_string()),
+        },
+    };
+
+    let mut components: Vec<&str> = Vec::new();
+    for component in rest.split('\\') {
+        match component {
+            "" | "." => {}
+            ".." => {
+                components.pop();
+            }
+            _ => components.push(component),
+        }
+    }
+    match root {
+        Some(root) => format!("{}\\{}", root, components.join("\\")),
+        None => components.join("\\"),
+    }
+}
+
+fn environment_variable(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|value| !value.is_empty())
+}
+
+#[cfg(windows)]
+fn long_path_name(path: &str) -> Option<String> {
+    let short = HSTRING::from(path);
+    let mut buffer = vec![0u16; 1024];
+    let length = unsafe { GetLongPathNameW(&short, Some(&mut buffer)) } as usize;
+    (length > 0 && length < buffer.len()).then(|| String::from_utf16_lossy(&buffer[..length]))
+}
+
+#[cfg(not(windows))]
+fn long_path_name(_path: &str) -> Option<String> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normalized(path: &str) -> String {
+        let long_name = |path: &str| (path.eq_ignore_ascii_case("C:\\PROGRA~3")).then(|| "C:\\ProgramData".to_string());
+        normalize_with(path, &|_| None, &long_name)
+    }
+
+    #[test]
+    fn test_evasion_variants() {
+        let expected = "c:\\programdata\\evil\\x.exe";
+        for variant in [
+            "C:\\ProgramData\\Evil\\x.exe",
+            "C:/ProgramData/Evil/x.exe",
+            "C:\\\\ProgramData\\\\Evil\\x.exe",
+            "\"%ProgramData%\\Evil\\x.exe\"",
+            "%ALLUSERSPROFILE%/Evil/x.exe",
+            "$env:ProgramData\\Evil\\x.exe",
+            "${env:PROGRAMDATA}\\Evil\\x.exe",
+            "C:\\PROGRA~3\\Evil\\x.exe",
+            "\\\\?\\C:\\ProgramData\\Evil\\x.exe",
+            "\\??\\C:\\Windows\\..\\ProgramData\\.\\Evil\\x.exe",
+        ] {
+            assert_eq!(normalized(variant), expected, "{}", variant);
+        }
+
+        assert_eq!(normalized("\\SystemRoot\\System32\\drivers\\x.sys"), "c:\\windows\\system32\\drivers\\x.sys");
+        assert_eq!(normalized("\\\\?\\UNC\\fs01\\share\\..\\..\\x.exe"), "\\\\fs01\\share\\x.exe");
+        assert_eq!(normalized("%UNDEFINED%\\x.exe"), "%undefined%\\x.exe");
+        assert_eq!(normalized("C:\\PROGRA~1\\x.exe"), "c:\\progra~1\\x.exe"); // Unresolvable short names are kept
+        assert_eq!(normalized("\\Temp\\x.exe"), "\\temp\\x.exe");
+    }
+
+    #[test]
+    fn test_normalize_command() {
+        let command = expand_variables("powershell -File $env:TEMP\\a.ps1 /c %APPDATA%\\b.bat", &|_| None);
+        assert_eq!(
+            canonical_separators(&command).to_lowercase(),
+            "powershell -file c:\\users\\default\\appdata\\local\\temp\\a.ps1 \\c c:\\users\\default\\appdata\\roaming\\b.bat"
+        );
+        assert!(is_short_name("PROGRA~1") && is_short_name("MICROS~2.EXE"));
+        assert!(!is_short_name("~$report.docx") && !is_short_name("backup~old"));
+    }
+}
@@ -1,12 +1,41 @@
+use crate::footprint;
 use crate::types::{PersistenceMechanism, PersistenceType, LogEntry};
+use crate::attack;
+use crate::forensic_types::{AppInitEntry, ImageHijack, PersistenceArtifacts, ScheduledTask, WinlogonEntry};
+use crate::drivers;
+use crate::hashing;
+use crate::ioc_export::{command_executable, is_windows_binary};
+use crate::offline::OfflineRoot;
+use crate::path_norm;
+use crate::scheduled_tasks;
+use crate::suspicion;
+use std::path::{Path, PathBuf};
+
+#[cfg(windows)]
+use crate::footprint::TrackedKey;
+#[cfg(windows)]
+use crate::offline;
+#[cfg(windows)]
+use crate::path_hijack;
+#[cfg(windows)]
+use crate::user_hives::{self, UserHive};
+#[cfg(windows)]
 use winreg::enums::*;
+#[cfg(windows)]
 use winreg::{RegKey, HKEY};
-use std::path::Path;
-use std::fs;
-use std::process::Command;
+
+/// Registry-based collection needs the Windows registry API
+#[cfg(not(windows))]
+const REGISTRY_UNAVAILABLE: &str = "registry access requires Windows";
 
 /// Collect all persistence mechanisms found on the system
 pub fn collect_persistence_mechanisms() -> (Vec<PersistenceMechanism>, Vec<LogEntry>) {
+    let (mechanisms, _, logs) = collect_persistence();
+    (mechanisms, logs)
+}
+
+/// Collect the persistence mechanisms and the scheduled task inventory they draw the tasks from
+pub fn collect_persistence() -> (Vec<PersistenceMechanism>, Vec<ScheduledTask>, Vec<LogEntry>) {
     let mut logs = Vec::new();
     logs.push(LogEntry::info("Starting persistence mechanism detection"));
     
@@ -24,6 +53,14 @@ pub fn collect_persistence_mechanisms() -> (Vec<PersistenceMechanism>, Vec<LogEn
         }
     }
     
+    // Run keys of every user profile, not only of the account running the collector
+    let (user_run_keys, failures) = collect_user_run_keys();
+    for failure in failures {
+        logs.push(LogEntry::warn(&failure));
+    }
+    logs.push(LogEntry::info(&format!("Found {} per-user Registry Run key entries", user_run_keys.len())));
+    mechanisms.extend(user_run_keys);
+    
     // Collect Startup folder entries
     match collect_startup_folder_entries() {
         Ok(startup_entries) => {
@@ -48,78 +85,648 @@ pub fn collect_persistence_mechanisms() -> (Vec<PersistenceMechanism>, Vec<LogEn
         }
     }
     
-    // Collect Scheduled Tasks via Windows Task Scheduler API
-    match collect_scheduled_tasks() {
-        Ok(tasks) => {
-            let count = tasks.len();
-            mechanisms.extend(tasks);
-            logs.push(LogEntry::info(&format!("Found {} scheduled tasks", count)));
+    // Scored before hashing: suspicious targets also get a fuzzy hash
+    suspicion::shared().score_persistence_mechanisms(&mut mechanisms);
+    
+    // Scheduled tasks arrive scored by the task inventory
+    let (scheduled_tasks, task_logs) = scheduled_tasks::collect_scheduled_tasks();
+    logs.extend(task_logs);
+    let model = suspicion::shared();
+    mechanisms.extend(scheduled_tasks.iter().filter(|task| scheduled_tasks::is_active(task) || model.is_suspicious(task.suspicion_score)).map(task_mechanism));
+    
+    // Sort mechanisms by type and name for consistent output
+    mechanisms.sort_by(|a, b| {
+        a.mechanism_type.cmp(&b.mechanism_type)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    
+    let system_root = std::env::var("SystemRoot").unwrap_or_else(|_| "C:\\Windows".to_string());
+    let hashed = hash_mechanism_targets(&mut mechanisms, &system_root, None);
+    logs.push(LogEntry::info(&format!("Hashed the executables of {} persistence mechanisms", hashed)));
+    attack::tag_persistence_mechanisms(&mut mechanisms);
+    
+    let total_mechanisms = mechanisms.len();
+    logs.push(LogEntry::info(&format!("Total persistence mechanisms found: {}", total_mechanisms)));
+    logs.push(LogEntry::info("Persistence mechanism detection completed"));
+    
+    (mechanisms, scheduled_tasks, logs)
+}
+
+/// Collect persistence mechanisms from the hives and startup folders of a mounted Windows volume
+pub fn collect_persistence_mechanisms_offline(root: &OfflineRoot) -> (Vec<PersistenceMechanism>, Vec<LogEntry>) {
+    let mut logs = Vec::new();
+    logs.push(LogEntry::info(&format!("Starting persistence mechanism detection (offline root: {})", root.root().display())));
+    
+    let mut mechanisms = Vec::new();
+    
+    // Machine Run keys from the offline SOFTWARE hive
+    match collect_offline_run_keys(root) {
+        Ok(run_keys) => {
+            logs.push(LogEntry::info(&format!("Found {} Registry Run key entries", run_keys.len())));
+            mechanisms.extend(run_keys);
         }
         Err(e) => {
-            logs.push(LogEntry::warn(&format!("Failed to collect scheduled tasks: {}", e)));
+            logs.push(LogEntry::warn(&e));
         }
     }
     
-    // Sort mechanisms by type and name for consistent output
+    // Run keys from the NTUSER.DAT of every profile on the volume
+    let (user_run_keys, failures) = collect_offline_user_run_keys(root);
+    for failure in failures {
+        logs.push(LogEntry::warn(&failure));
+    }
+    logs.push(LogEntry::info(&format!("Found {} per-user Registry Run key entries", user_run_keys.len())));
+    mechanisms.extend(user_run_keys);
+    
+    // Startup folders of all users on the volume
+    let startup_entries = collect_startup_entries_from(offline_startup_folders(root));
+    logs.push(LogEntry::info(&format!("Found {} Startup folder entries", startup_entries.len())));
+    mechanisms.extend(startup_entries);
+    
+    // Services from the control set recorded in the offline SYSTEM hive
+    match collect_offline_services(root) {
+        Ok(services) => {
+            logs.push(LogEntry::info(&format!("Found {} potentially suspicious services", services.len())));
+            mechanisms.extend(services);
+        }
+        Err(e) => {
+            logs.push(LogEntry::warn(&e));
+        }
+    }
+    
+    logs.push(LogEntry::info("Scheduled task enumeration skipped: requires the live Task Scheduler"));
+    
     mechanisms.sort_by(|a, b| {
         a.mechanism_type.cmp(&b.mechanism_type)
             .then_with(|| a.name.cmp(&b.name))
     });
     
-    let total_mechanisms = mechanisms.len();
-    logs.push(LogEntry::info(&format!("Total persistence mechanisms found: {}", total_mechanisms)));
+    suspicion::shared().score_persistence_mechanisms(&mut mechanisms);
+    let hashed = hash_mechanism_targets(&mut mechanisms, "C:\\Windows", Some(root));
+    logs.push(LogEntry::info(&format!("Hashed the executables of {} persistence mechanisms", hashed)));
+    attack::tag_persistence_mechanisms(&mut mechanisms);
+    
+    logs.push(LogEntry::info(&format!("Total persistence mechanisms found: {}", mechanisms.len())));
     logs.push(LogEntry::info("Persistence mechanism detection completed"));
     
     (mechanisms, logs)
 }
 
+/// Hash the executables the mechanisms launch through the shared hashing service;
+/// returns the number of mechanisms whose executable was found and hashed
+fn hash_mechanism_targets(mechanisms: &mut [PersistenceMechanism], system_root: &str, root: Option<&OfflineRoot>) -> usize {
+    let hashes = hashing::shared();
+    let mut hashed = 0;
+    for mechanism in mechanisms.iter_mut() {
+        let Some(path) = mechanism_executable(mechanism, system_root, root) else {
+            continue;
+        };
+        // Fuzzy hashes are only worth their cost for binaries that look suspicious
+        let file_hashes = if mechanism.is_suspicious { hashes.hash_file_fuzzy(&path) } else { hashes.hash_file(&path) };
+        if let Ok(file_hashes) = file_hashes {
+            mechanism.hashes = Some(file_hashes);
+            hashed += 1;
+        }
+    }
+    hashed
+}
+
+/// File the mechanism launches, on the live system or the offline volume
+pub(crate) fn mechanism_executable(mechanism: &PersistenceMechanism, system_root: &str, root: Option<&OfflineRoot>) -> Option<PathBuf> {
+    let path = image_path(&command_executable(&mechanism.command)?, system_root);
+    Some(match root {
+        Some(root) => root.resolve(&path),
+        None => PathBuf::from(path),
+    })
+}
+
+/// Service image paths are often relative to the Windows directory
+/// (`%SystemRoot%\...`, `\SystemRoot\...`, `system32\...`)
+fn image_path(executable: &str, system_root: &str) -> String {
+    let lower = executable.to_lowercase();
+    for variable in ["%systemroot%", "%windir%"] {
+        if lower.starts_with(variable) {
+            return format!("{}{}", system_root.trim_end_matches('\\'), &executable[variable.len()..]);
+        }
+    }
+    drivers::normalize_driver_path(executable, system_root)
+}
+
+/// Suspicion score from which Winlogon, IFEO and AppInit entries are reported as findings
+pub const HIJACK_SUSPICION_THRESHOLD: u32 = 50;
+
+/// Winlogon, IFEO, SilentProcessExit and AppInit keys, relative to HKLM\SOFTWARE
+#[cfg(windows)]
+const WINLOGON_KEY: &str = r"Microsoft\Windows NT\CurrentVersion\Winlogon";
+#[cfg(windows)]
+const IFEO_KEY: &str = r"Microsoft\Windows NT\CurrentVersion\Image File Execution Options";
+#[cfg(windows)]
+const SILENT_PROCESS_EXIT_KEY: &str = r"Microsoft\Windows NT\CurrentVersion\SilentProcessExit";
+#[cfg(windows)]
+const APPINIT_KEY: &str = r"Microsoft\Windows NT\CurrentVersion\Windows";
+
+/// Native and 32-bit registry views below SOFTWARE
+#[cfg(windows)]
+const SOFTWARE_VIEWS: [&str; 2] = ["", r"WOW6432Node\"];
+
+/// AppCertDlls key, relative to the control set
+#[cfg(windows)]
+const APPCERT_KEY: &str = r"Control\Session Manager\AppCertDlls";
+
+/// GlobalFlag bit that makes Windows start the SilentProcessExit MonitorProcess
+#[cfg(windows)]
+const FLG_MONITOR_SILENT_PROCESS_EXIT: u32 = 0x200;
+
+/// Accessibility binaries reachable from the logon screen; a debugger on them is a SYSTEM backdoor
+const ACCESSIBILITY_BINARIES: [&str; 7] = [
+    "sethc.exe", "utilman.exe", "osk.exe", "narrator.exe", "magnify.exe", "displayswitch.exe", "atbroker.exe",
+];
+
+/// Debuggers and Task Manager replacements legitimately registered as IFEO debuggers
+const KNOWN_DEBUGGERS: [&str; 6] = ["vsjitdebugger.exe", "windbg.exe", "cdb.exe", "ntsd.exe", "procexp.exe", "procexp64.exe"];
+
+/// Collect Winlogon Shell/Userinit/Notify values, IFEO debugger hijacks, AppInit_DLLs
+/// and AppCertDlls of the live system, each scored for suspicion
+pub fn collect_hijack_artifacts() -> (PersistenceArtifacts, Vec<LogEntry>) {
+    let mut logs = vec![LogEntry::info("Starting Winlogon, IFEO and AppInit_DLLs checks")];
+    let mut artifacts = PersistenceArtifacts::default();
+    
+    for failure in collect_live_hijacks(&mut artifacts) {
+        logs.push(LogEntry::warn(&failure));
+    }
+    
+    finish_hijack_artifacts(&mut artifacts, &mut logs);
+    (artifacts, logs)
+}
+
+/// Collect the Winlogon, IFEO and AppInit entries from the hives of a mounted Windows volume
+pub fn collect_hijack_artifacts_offline(root: &OfflineRoot) -> (PersistenceArtifacts, Vec<LogEntry>) {
+    let mut logs = vec![LogEntry::info(&format!("Starting Winlogon, IFEO and AppInit_DLLs checks (offline root: {})", root.root().display()))];
+    let mut artifacts = PersistenceArtifacts::default();
+    
+    for failure in collect_offline_hijacks(root, &mut artifacts) {
+        logs.push(LogEntry::warn(&failure));
+    }
+    
+    finish_hijack_artifacts(&mut artifacts, &mut logs);
+    (artifacts, logs)
+}
+
+/// Sort the entries by suspicion and log the counts and the suspicious entries
+fn finish_hijack_artifacts(artifacts: &mut PersistenceArtifacts, logs: &mut Vec<LogEntry>) {
+    artifacts.winlogon_entries.sort_by(|a, b| b.suspicion_score.cmp(&a.suspicion_score).then_with(|| a.key_name.cmp(&b.key_name)));
+    artifacts.image_hijacks.sort_by(|a, b| b.suspicion_score.cmp(&a.suspicion_score).then_with(|| a.target_executable.cmp(&b.target_executable)));
+    artifacts.appinit_dlls.sort_by(|a, b| b.suspicion_score.cmp(&a.suspicion_score).then_with(|| a.key_name.cmp(&b.key_name)));
+    artifacts.path_hijacks.sort_by(|a, b| b.suspicion_score.cmp(&a.suspicion_score).then_with(|| a.directory.cmp(&b.directory)));
+    
+    for entry in artifacts.winlogon_entries.iter().filter(|e| e.suspicion_score >= HIJACK_SUSPICION_THRESHOLD) {
+        logs.push(LogEntry::warn(&format!("Suspicious Winlogon {} value in {}: {} ({})", entry.value_name, entry.key_name, entry.value_data, entry.suspicion_reasons.join(", "))));
+    }
+    for hijack in artifacts.image_hijacks.iter().filter(|h| h.suspicion_score >= HIJACK_SUSPICION_THRESHOLD) {
+        logs.push(LogEntry::warn(&format!("Suspicious IFEO {} for {}: {} ({})", hijack.hijack_type, hijack.target_executable, hijack.hijack_executable, hijack.suspicion_reasons.join(", "))));
+    }
+    for entry in artifacts.appinit_dlls.iter().filter(|e| e.suspicion_score >= HIJACK_SUSPICION_THRESHOLD) {
+        logs.push(LogEntry::warn(&format!("Suspicious {} in {}: {} ({})", entry.value_name, entry.key_name, entry.dlls.join(", "), entry.suspicion_reasons.join(", "))));
+    }
+    for hijack in artifacts.path_hijacks.iter().filter(|h| h.suspicion_score >= HIJACK_SUSPICION_THRESHOLD) {
+        logs.push(LogEntry::warn(&format!("Suspicious {} entry {} in {}: {} planted binaries ({})", hijack.source, hijack.directory, hijack.registry_key, hijack.planted_binaries.len(), hijack.suspicion_reasons.join(", "))));
+    }
+    
+    logs.push(LogEntry::info(&format!("Found {} Winlogon values, {} IFEO hijacks, {} AppInit/AppCert DLL entries and {} search path hijacks",
+        artifacts.winlogon_entries.len(), artifacts.image_hijacks.len(), artifacts.appinit_dlls.len(), artifacts.path_hijacks.len())));
+    logs.push(LogEntry::info("Winlogon, IFEO, AppInit_DLLs and search path checks completed"));
+}
+
+/// Read the machine and per-user locations of the live registry; returns the failures
+#[cfg(windows)]
+fn collect_live_hijacks(artifacts: &mut PersistenceArtifacts) -> Vec<String> {
+    let mut failures = Vec::new();
+    let hklm = RegKey::predef(HKEY_LOCAL_MACHINE);
+    
+    let software = hklm.open_tracked("SOFTWARE");
+    match &software {
+        Ok(software) => collect_software_hijacks(software, r"HKLM\SOFTWARE", artifacts),
+        Err(e) => failures.push(format!("Failed to open HKLM\\SOFTWARE: {}", e)),
+    }
+    
+    let control_set = hklm.open_tracked(r"SYSTEM\CurrentControlSet").ok();
+    if let Some(control_set) = &control_set {
+        collect_appcert_dlls(control_set, r"HKLM\SYSTEM\CurrentControlSet", artifacts);
+    }
+    
+    let (hives, hive_failures) = user_hives::live_user_hives();
+    collect_user_winlogon_shells(&hives, artifacts);
+    artifacts.path_hijacks = path_hijack::detect_path_hijacks(
+        software.as_ref().ok().map(|key| (key, r"HKLM\SOFTWARE")),
+        control_set.as_ref().map(|key| (key, r"HKLM\SYSTEM\CurrentControlSet")),
+        &hives,
+        &|path: &str| PathBuf::from(path),
+    );
+    failures.extend(hive_failures);
+    failures
+}
+
+#[cfg(not(windows))]
+fn collect_live_hijacks(_artifacts: &mut PersistenceArtifacts) -> Vec<String> {
+    vec![format!("Winlogon, IFEO, AppInit_DLLs and search path checks skipped: {}", REGISTRY_UNAVAILABLE)]
+}
+
+/// Read the SOFTWARE, SYSTEM and user hives of an offline volume; returns the failures
+#[cfg(windows)]
+fn collect_offline_hijacks(root: &OfflineRoot, artifacts: &mut PersistenceArtifacts) -> Vec<String> {
+    let mut failures = Vec::new();
+    
+    let software = root.load_hive("SOFTWARE");
+    match &software {
+        Ok(software) => collect_software_hijacks(software, r"HKLM\SOFTWARE", artifacts),
+        Err(e) => failures.push(format!("Failed to load offline SOFTWARE hive: {}", e)),
+    }
+    
+    let mut control_set = None;
+    match root.load_hive("SYSTEM") {
+        Ok(system) => {
+            let control_set_name = offline::current_control_set(&system);
+            if let Ok(key) = system.open_tracked(&control_set_name) {
+                let prefix = format!(r"HKLM\SYSTEM\{}", control_set_name);
+                collect_appcert_dlls(&key, &prefix, artifacts);
+                control_set = Some((key, prefix));
+            }
+        }
+        Err(e) => failures.push(format!("Failed to load offline SYSTEM hive: {}", e)),
+    }
+    
+    let (hives, hive_failures) = user_hives::offline_user_hives(root);
+    collect_user_winlogon_shells(&hives, artifacts);
+    artifacts.path_hijacks = path_hijack::detect_path_hijacks(
+        software.as_ref().ok().map(|key| (key, r"HKLM\SOFTWARE")),
+        control_set.as_ref().map(|(key, prefix)| (key, prefix.as_str())),
+        &hives,
+        &|path: &str| root.resolve(path),
+    );
+    failures.extend(hive_failures);
+    failures
+}
+
+#[cfg(not(windows))]
+fn collect_offline_hijacks(_root: &OfflineRoot, _artifacts: &mut PersistenceArtifacts) -> Vec<String> {
+    vec![format!("Winlogon, IFEO, AppInit_DLLs and search path checks skipped: {}", REGISTRY_UNAVAILABLE)]
+}
+
+/// Winlogon, IFEO and AppInit_DLLs entries below an opened SOFTWARE key, in both registry views
+#[cfg(windows)]
+fn collect_software_hijacks(software: &RegKey, prefix: &str, artifacts: &mut PersistenceArtifacts) {
+    for view in SOFTWARE_VIEWS {
+        let winlogon_path = format!("{}{}", view, WINLOGON_KEY);
+        if let Ok(winlogon) = software.open_tracked(&winlogon_path) {
+            let key_name = format!(r"{}\{}", prefix, winlogon_path);
+            let last_write_time = offline::key_last_write_time(&winlogon);
+            for value_name in ["Shell", "Userinit"] {
+                if let Ok(value_data) = winlogon.get_value::<String, _>(value_name) {
+                    artifacts.winlogon_entries.push(winlogon_entry(&key_name, value_name, value_data, None, last_write_time.clone()));
+                }
+            }
+            // Notify packages were removed in Vista; any left behind were planted
+            if let Ok(notify) = winlogon.open_tracked("Notify") {
+                for package in notify.enum_keys().filter_map(|k| k.ok()) {
+                    let Ok(package_key) = notify.open_tracked(&package) else { continue };
+                    if let Ok(dll_name) = package_key.get_value::<String, _>("DllName") {
+                        let package_name = format!(r"{}\Notify\{}", key_name, package);
+                        artifacts.winlogon_entries.push(winlogon_entry(&package_name, "DllName", dll_name, None, offline::key_last_write_time(&package_key)));
+                    }
+                }
+            }
+        }
+        
+        let ifeo_path = format!("{}{}", view, IFEO_KEY);
+        if let Ok(ifeo) = software.open_tracked(&ifeo_path) {
+            for target in ifeo.enum_keys().filter_map(|k| k.ok()) {
+                let Ok(target_key) = ifeo.open_tracked(&target) else { continue };
+                if let Ok(debugger) = target_key.get_value::<String, _>("Debugger").map(|d| d.trim().to_string()) {
+                    if !debugger.is_empty() {
+                        let registry_key = format!(r"{}\{}\{}", prefix, ifeo_path, target);
+                        artifacts.image_hijacks.push(image_hijack(&target, debugger, "debugger", registry_key, offline::key_last_write_time(&target_key)));
+                    }
+                }
+                
+                let global_flag = target_key.get_value::<u32, _>("GlobalFlag").ok()
+                    .or_else(|| target_key.get_value::<String, _>("GlobalFlag").ok().and_then(|flag| parse_global_flag(&flag)))
+                    .unwrap_or(0);
+                if global_flag & FLG_MONITOR_SILENT_PROCESS_EXIT == 0 {
+                    continue;
+                }
+                let exit_path = format!(r"{}{}\{}", view, SILENT_PROCESS_EXIT_KEY, target);
+                if let Ok(exit_key) = software.open_tracked(&exit_path) {
+                    if let Ok(monitor) = exit_key.get_value::<String, _>("MonitorProcess") {
+                        let registry_key = format!(r"{}\{}", prefix, exit_path);
+                        artifacts.image_hijacks.push(image_hijack(&target, monitor, "silent_process_exit", registry_key, offline::key_last_write_time(&exit_key)));
+                    }
+                }
+            }
+        }
+        
+        let appinit_path = format!("{}{}", view, APPINIT_KEY);
+        if let Ok(windows) = software.open_tracked(&appinit_path) {
+            let dlls = windows.get_value::<String, _>("AppInit_DLLs").map(|v| split_dll_list(&v)).unwrap_or_default();
+            if !dlls.is_empty() {
+                let load_enabled = windows.get_value::<u32, _>("LoadAppInit_DLLs").ok().map(|v| v != 0);
+                let key_name = format!(r"{}\{}", prefix, appinit_path);
+                artifacts.appinit_dlls.push(appinit_entry(&key_name, "AppInit_DLLs", dlls, load_enabled, offline::key_last_write_time(&windows)));
+            }
+        }
+    }
+}
+
+/// AppCertDlls values below an opened control set key
+#[cfg(windows)]
+fn collect_appcert_dlls(control_set: &RegKey, prefix: &str, artifacts: &mut PersistenceArtifacts) {
+    let Ok(appcert) = control_set.open_tracked(APPCERT_KEY) else { return };
+    let key_name = format!(r"{}\{}", prefix, APPCERT_KEY);
+    let last_write_time = offline::key_last_write_time(&appcert);
+    for (value_name, _) in appcert.enum_values().filter_map(|v| v.ok()) {
+        if let Ok(dll) = appcert.get_value::<String, _>(&value_name) {
+            artifacts.appinit_dlls.push(appinit_entry(&key_name, &value_name, split_dll_list(&dll), None, last_write_time.clone()));
+        }
+    }
+}
+
+/// Per-user Winlogon Shell values, which replace Explorer for that user only
+#[cfg(windows)]
+fn collect_user_winlogon_shells(hives: &[UserHive], artifacts: &mut PersistenceArtifacts) {
+    let path = format!(r"Software\{}", WINLOGON_KEY);
+    for hive in hives {
+        let Ok(winlogon) = hive.key.open_tracked(&path) else { continue };
+        if let Ok(shell) = winlogon.get_value::<String, _>("Shell") {
+            let key_name = format!(r"{}\{}", hive.source, path);
+            artifacts.winlogon_entries.push(winlogon_entry(&key_name, "Shell", shell, Some(&hive.user), offline::key_last_write_time(&winlogon)));
+        }
+    }
+}
+
+#[cfg_attr(not(windows), allow(dead_code))]
+fn winlogon_entry(key_name: &str, value_name: &str, value_data: String, user: Option<&str>, last_write_time: String) -> WinlogonEntry {
+    let (suspicion_score, suspicion_reasons) = score_winlogon_value(value_name, &value_data, user.is_some());
+    WinlogonEntry {
+        key_name: key_name.to_string(),
+        value_name: value_name.to_string(),
+        value_data,
+        user: user.map(str::to_string),
+        last_write_time,
+        suspicion_score,
+        suspicion_reasons,
+    }
+}
+
+#[cfg_attr(not(windows), allow(dead_code))]
+fn image_hijack(target: &str, hijack_executable: String, hijack_type: &str, registry_key: String, last_write_time: String) -> ImageHijack {
+    let (suspicion_score, suspicion_reasons) = score_image_hijack(target, &hijack_executable, hijack_type);
+    ImageHijack {
+        target_executable: target.to_string(),
+        hijack_executable,
+        hijack_type: hijack_type.to_string(),
+        registry_key,
+        last_write_time,
+        suspicion_score,
+        suspicion_reasons,
+    }
+}
+
+#[cfg_attr(not(windows), allow(dead_code))]
+fn appinit_entry(key_name: &str, value_name: &str, dlls: Vec<String>, load_enabled: Option<bool>, last_write_time: String) -> AppInitEntry {
+    let (suspicion_score, suspicion_reasons) = score_appinit_dlls(value_name, &dlls, load_enabled);
+    AppInitEntry {
+        key_name: key_name.to_string(),
+        value_name: value_name.to_string(),
+        dlls,
+        load_enabled,
+        last_write_time,
+        suspicion_score,
+        suspicion_reasons,
+    }
+}
+
+/// Score a Winlogon Shell, Userinit or Notify DllName value (0-100) with the reasons
+#[cfg_attr(not(windows), allow(dead_code))]
+fn score_winlogon_value(value_name: &str, value_data: &str, per_user: bool) -> (u32, Vec<String>) {
+    let mut score = 0;
+    let mut reasons = Vec::new();
+    let programs: Vec<String> = value_data.split(',')
+        .map(|program| program.trim().trim_matches('"').to_lowercase())
+        .filter(|program| !program.is_empty())
+        .collect();
+    
+    match value_name {
+        "Shell" => {
+            if per_user {
+                score += 40;
+                reasons.push("per_user_shell_override".to_string());
+            }
+            if programs.len() > 1 || programs.iter().any(|program| !is_default_program(program, "explorer.exe")) {
+                score += 50;
+                reasons.push("unexpected_shell".to_string());
+            }
+        }
+        "Userinit" => {
+            if programs.iter().any(|program| !is_default_program(program, "userinit.exe")) {
+                score += 60;
+                reasons.push("additional_userinit_program".to_string());
+            }
+        }
+        _ => {
+            score += 50;
+            reasons.push("winlogon_notify_package".to_string());
+        }
+    }
+    
+    if is_mechanism_suspicious_by_command(value_data) {
+        score += 30;
+        reasons.push("suspicious_command".to_string());
+    }
+    (score.min(100), reasons)
+}
+
+/// Score an IFEO Debugger or SilentProcessExit MonitorProcess hijack (0-100) with the reasons
+#[cfg_attr(not(windows), allow(dead_code))]
+fn score_image_hijack(target: &str, hijack_executable: &str, hijack_type: &str) -> (u32, Vec<String>) {
+    let program = command_executable(hijack_executable)
+        .or_else(|| hijack_executable.split_whitespace().next().map(str::to_string))
+        .unwrap_or_default()
+        .to_lowercase();
+    let program_name = program.rsplit(['\\', '/']).next().unwrap_or_default();
+    if KNOWN_DEBUGGERS.contains(&program_name) {
+        return (10, vec!["known_debugger".to_string()]);
+    }
+    
+    let mut score = 40;
+    let mut reasons = vec![if hijack_type == "debugger" { "debugger_set" } else { "silent_process_exit_monitor" }.to_string()];
+    if ACCESSIBILITY_BINARIES.contains(&target.to_lowercase().as_str()) {
+        score += 50;
+        reasons.push("accessibility_binary_hijack".to_string());
+    }
+    if program.contains('\\') && !is_windows_binary(&program) && !program.contains("\\program files") {
+        score += 20;
+        reasons.push("non_system_path".to_string());
+    }
+    if is_mechanism_suspicious_by_command(hijack_executable) {
+        score += 30;
+        reasons.push("suspicious_command".to_string());
+    }
+    (score.min(100), reasons)
+}
+
+/// Score AppInit_DLLs or an AppCertDlls value (0-100) with the reasons
+#[cfg_attr(not(windows), allow(dead_code))]
+fn score_appinit_dlls(value_name: &str, dlls: &[String], load_enabled: Option<bool>) -> (u32, Vec<String>) {
+    let (mut score, mut reasons) = if value_name == "AppInit_DLLs" {
+        (40, vec!["appinit_dlls_set".to_string()])
+    } else {
+        (70, vec!["appcert_dll".to_string()])
+    };
+    if load_enabled == Some(true) {
+        score += 30;
+        reasons.push("appinit_loading_enabled".to_string());
+    }
+    if dlls.iter().any(|dll| !is_windows_binary(dll)) {
+        score += 20;
+        reasons.push("non_system_dll".to_string());
+    }
+    (score.min(100), reasons)
+}
+
+/// Whether a Winlogon program is the stock executable, bare or in the Windows or System32 directory
+#[cfg_attr(not(windows), allow(dead_code))]
+fn is_default_program(program: &str, executable: &str) -> bool {
+    let program = program.replace("%systemroot%", r"c:\windows").replace("%windir%", r"c:\windows");
+    let path = program.split_once(':').map(|(_, path)| path).unwrap_or(&program);
+    path == executable || path == format!(r"\windows\{}", executable) || path == format!(r"\windows\system32\{}", executable)
+}
+
+/// AppInit_DLLs and AppCertDlls lists are separated by commas or spaces
+#[cfg_attr(not(windows), allow(dead_code))]
+fn split_dll_list(value: &str) -> Vec<String> {
+    value.split([',', ' ']).map(|dll| dll.trim().trim_matches('"')).filter(|dll| !dll.is_empty()).map(str::to_string).collect()
+}
+
+/// IFEO GlobalFlag stored as a string ("0x00000200" or decimal)
+#[cfg_attr(not(windows), allow(dead_code))]
+fn parse_global_flag(value: &str) -> Option<u32> {
+    let value = value.trim();
+    match value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => value.parse().ok(),
+    }
+}
+
+/// Machine-wide Run keys, relative to HKLM\SOFTWARE
+#[cfg(windows)]
+const MACHINE_RUN_KEYS: [&str; 4] = [
+    r"Microsoft\Windows\CurrentVersion\Run",
+    r"Microsoft\Windows\CurrentVersion\RunOnce",
+    r"WOW6432Node\Microsoft\Windows\CurrentVersion\Run",
+    r"WOW6432Node\Microsoft\Windows\CurrentVersion\RunOnce",
+];
+
+/// Machine Run key entries from the offline SOFTWARE hive
+#[cfg(windows)]
+fn collect_offline_run_keys(root: &OfflineRoot) -> Result<Vec<PersistenceMechanism>, String> {
+    let software = root.load_hive("SOFTWARE")
+        .map_err(|e| format!("Failed to load offline SOFTWARE hive: {}", e))?;
+    
+    let mut run_keys = Vec::new();
+    for path in MACHINE_RUN_KEYS {
+        if let Ok(key) = software.open_tracked(path) {
+            run_keys.extend(collect_run_key_values(&key, &format!(r"HKLM\SOFTWARE\{}", path)));
+        }
+    }
+    Ok(run_keys)
+}
+
+#[cfg(not(windows))]
+fn collect_offline_run_keys(_root: &OfflineRoot) -> Result<Vec<PersistenceMechanism>, String> {
+    Err(format!("Failed to load offline SOFTWARE hive: {}", REGISTRY_UNAVAILABLE))
+}
+
+/// Per-user Run keys, relative to the user hive
+#[cfg(windows)]
+const USER_RUN_KEYS: [&str; 2] = [
+    r"Software\Microsoft\Windows\CurrentVersion\Run",
+    r"Software\Microsoft\Windows\CurrentVersion\RunOnce",
+];
+
+/// Run key entries of every user profile on the live system, plus the profiles
+/// whose hive could not be loaded
+#[cfg(windows)]
+fn collect_user_run_keys() -> (Vec<PersistenceMechanism>, Vec<String>) {
+    let (hives, failures) = user_hives::live_user_hives();
+    (collect_run_keys_from_user_hives(&hives), failures)
+}
+
+#[cfg(not(windows))]
+fn collect_user_run_keys() -> (Vec<PersistenceMechanism>, Vec<String>) {
+    (Vec::new(), vec![format!("Failed to load user hives: {}", REGISTRY_UNAVAILABLE)])
+}
+
+/// Run key entries from the NTUSER.DAT hives of a mounted Windows volume
+#[cfg(windows)]
+fn collect_offline_user_run_keys(root: &OfflineRoot) -> (Vec<PersistenceMechanism>, Vec<String>) {
+    let (hives, failures) = user_hives::offline_user_hives(root);
+    (collect_run_keys_from_user_hives(&hives), failures)
+}
+
+#[cfg(not(windows))]
+fn collect_offline_user_run_keys(_root: &OfflineRoot) -> (Vec<PersistenceMechanism>, Vec<String>) {
+    (Vec::new(), vec![format!("Failed to load offline user hives: {}", REGISTRY_UNAVAILABLE)])
+}
+
+#[cfg(windows)]
+fn collect_run_keys_from_user_hives(hives: &[UserHive]) -> Vec<PersistenceMechanism> {
+    let mut run_keys = Vec::new();
+    for hive in hives {
+        for path in USER_RUN_KEYS {
+            if let Ok(key) = hive.key.open_tracked(path) {
+                let source = format!("{}\\{}", hive.source, path);
+                run_keys.extend(collect_run_key_values(&key, &source).into_iter().map(|m| m.with_user(&hive.user)));
+            }
+        }
+    }
+    run_keys
+}
+
+/// Potentially suspicious services from the offline SYSTEM hive
+#[cfg(windows)]
+fn collect_offline_services(root: &OfflineRoot) -> Result<Vec<PersistenceMechanism>, String> {
+    let system = root.load_hive("SYSTEM")
+        .map_err(|e| format!("Failed to load offline SYSTEM hive: {}", e))?;
+    
+    let control_set = offline::current_control_set(&system);
+    let services_key = system.open_tracked(format!(r"{}\Services", control_set))
+        .map_err(|e| format!("Failed to open offline Services key: {}", e))?;
+    
+    Ok(collect_services_from_key(&services_key, &format!(r"HKLM\SYSTEM\{}\Services", control_set)))
+}
+
+#[cfg(not(windows))]
+fn collect_offline_services(_root: &OfflineRoot) -> Result<Vec<PersistenceMechanism>, String> {
+    Err(format!("Failed to load offline SYSTEM hive: {}", REGISTRY_UNAVAILABLE))
+}
+
 /// Collect Registry Run key entries
+#[cfg(windows)]
 fn collect_registry_run_keys() -> Result<Vec<PersistenceMechanism>, String> {
     let mut mechanisms = Vec::new();
     
-    // Common Registry Run key locations
+    // Machine-wide Run key locations; per-user keys are read from every user hive
     let run_key_paths = vec![
         (HKEY_LOCAL_MACHINE, r"SOFTWARE\Microsoft\Windows\CurrentVersion\Run"),
         (HKEY_LOCAL_MACHINE, r"SOFTWARE\Microsoft\Windows\CurrentVersion\RunOnce"),
-        (HKEY_CURRENT_USER, r"SOFTWARE\Microsoft\Windows\CurrentVersion\Run"),
-        (HKEY_CURRENT_USER, r"SOFTWARE\Microsoft\Windows\CurrentVersion\RunOnce"),
         (HKEY_LOCAL_MACHINE, r"SOFTWARE\WOW6432Node\Microsoft\Windows\CurrentVersion\Run"),
         (HKEY_LOCAL_MACHINE, r"SOFTWARE\WOW6432Node\Microsoft\Windows\CurrentVersion\RunOnce"),
     ];
     
     for (hive, path) in run_key_paths {
-        match RegKey::predef(hive).open_subkey(path) {
+        match RegKey::predef(hive).open_tracked(path) {
             Ok(key) => {
-                for value_name in key.enum_values().filter_map(|v| v.ok()) {
-                    let name = value_name.0;
-                    match key.get_value::<String, _>(&name) {
-                        Ok(command) => {
-                            let source = format!("{}\\{}", 
-                                hive_to_string(hive), 
-                                path
-                            );
-                            let location = format!("{}\\{}\\{}", 
-                                hive_to_string(hive), 
-                                path,
-                                name
-                            );
-                            let is_suspicious = is_mechanism_suspicious_by_command(&command);
-                            
-                            mechanisms.push(PersistenceMechanism::new_with_location_value(
-                                PersistenceType::RegistryRunKey.as_str().to_string(),
-                                name,
-                                command.clone(),
-                                source,
-                                location,
-                                command,
-                                is_suspicious,
-                            ));
-                        }
-                        Err(_) => {
-                            // Skip values that can't be read as strings
-                        }
-                    }
-                }
+                let source = format!("{}\\{}", hive_to_string(hive), path);
+                mechanisms.extend(collect_run_key_values(&key, &source));
             }
             Err(_) => {
                 // Key doesn't exist or can't be accessed - this is normal
@@ -130,70 +737,155 @@ fn collect_registry_run_keys() -> Result<Vec<PersistenceMechanism>, String> {
     Ok(mechanisms)
 }
 
+#[cfg(not(windows))]
+fn collect_registry_run_keys() -> Result<Vec<PersistenceMechanism>, String> {
+    Err(REGISTRY_UNAVAILABLE.to_string())
+}
+
+/// Collect the string values of an opened Run key
+#[cfg(windows)]
+fn collect_run_key_values(key: &RegKey, source: &str) -> Vec<PersistenceMechanism> {
+    let mut mechanisms = Vec::new();
+    
+    for value_name in key.enum_values().filter_map(|v| v.ok()) {
+        let name = value_name.0;
+        match key.get_value::<String, _>(&name) {
+            Ok(command) => {
+                let location = format!("{}\\{}", source, name);
+                
+                mechanisms.push(PersistenceMechanism::new_with_location_value(
+                    PersistenceType::RegistryRunKey.as_str().to_string(),
+                    name,
+                    command.clone(),
+                    source.to_string(),
+                    location,
+                    command,
+                    false, // Scored once collected
+                ));
+            }
+            Err(_) => {
+                // Skip values that can't be read as strings
+            }
+        }
+    }
+    
+    mechanisms
+}
+
 /// Collect Startup folder entries
 fn collect_startup_folder_entries() -> Result<Vec<PersistenceMechanism>, String> {
-    let mut mechanisms = Vec::new();
+    // The All Users folder and the folder of every profile, not only the current user's
+    let mut startup_paths: Vec<(String, Option<String>)> = get_startup_folder_path("common")
+        .map(|path| (path, None))
+        .into_iter()
+        .collect();
+    let system_drive = std::env::var("SystemDrive").unwrap_or_else(|_| "C:".to_string());
+    startup_paths.extend(profile_startup_folders(Path::new(&format!(r"{}\Users", system_drive))));
     
-    // Common startup folder locations
-    let startup_paths = vec![
-        get_startup_folder_path("common"),
-        get_startup_folder_path("user"),
-    ];
+    Ok(collect_startup_entries_from(startup_paths))
+}
+
+/// Startup folders on an offline volume: the All Users folder and every user profile's folder
+fn offline_startup_folders(root: &OfflineRoot) -> Vec<(String, Option<String>)> {
+    let mut folders = vec![(
+        root.resolve(r"C:\ProgramData\Microsoft\Windows\Start Menu\Programs\Startup")
+            .to_string_lossy()
+            .to_string(),
+        None,
+    )];
+    folders.extend(profile_startup_folders(&root.resolve(r"C:\Users")));
+    folders
+}
+
+/// Startup folder of every profile below a Users directory, with the profile name
+fn profile_startup_folders(users_dir: &Path) -> Vec<(String, Option<String>)> {
+    let mut folders = Vec::new();
     
-    for startup_path in startup_paths {
-        if let Some(path) = startup_path {
-            if Path::new(&path).exists() {
-                match fs::read_dir(&path) {
-                    Ok(entries) => {
-                        for entry in entries.filter_map(|e| e.ok()) {
-                            let file_path = entry.path();
-                            if let Some(file_name) = file_path.file_name() {
-                                let name = file_name.to_string_lossy().to_string();
-                                let command = file_path.to_string_lossy().to_string();
-                                let location = command.clone();
-                                let is_suspicious = is_mechanism_suspicious_by_command(&command);
-                                
-                                mechanisms.push(PersistenceMechanism::new_with_location_value(
-                                    PersistenceType::StartupFolder.as_str().to_string(),
-                                    name,
-                                    command.clone(),
-                                    path.clone(),
-                                    location,
-                                    command,
-                                    is_suspicious,
-                                ));
-                            }
+    if let Ok(profiles) = footprint::read_dir(users_dir) {
+        for profile in profiles.filter_map(|p| p.ok()) {
+            let folder = ["AppData", "Roaming", "Microsoft", "Windows", "Start Menu", "Programs", "Startup"]
+                .iter()
+                .fold(profile.path(), |path, component| path.join(component));
+            folders.push((
+                folder.to_string_lossy().to_string(),
+                Some(profile.file_name().to_string_lossy().to_string()),
+            ));
+        }
+    }
+    
+    folders
+}
+
+/// Collect the files in the given startup folders, attributed to the folder's profile
+fn collect_startup_entries_from(startup_paths: Vec<(String, Option<String>)>) -> Vec<PersistenceMechanism> {
+    let mut mechanisms = Vec::new();
+    
+    for (path, user) in startup_paths {
+        if Path::new(&path).exists() {
+            match footprint::read_dir(&path) {
+                Ok(entries) => {
+                    for entry in entries.filter_map(|e| e.ok()) {
+                        let file_path = entry.path();
+                        if let Some(file_name) = file_path.file_name() {
+                            let name = file_name.to_string_lossy().to_string();
+                            let command = file_path.to_string_lossy().to_string();
+                            let location = command.clone();
+                            
+                            let mechanism = PersistenceMechanism::new_with_location_value(
+                                PersistenceType::StartupFolder.as_str().to_string(),
+                                name,
+                                command.clone(),
+                                path.clone(),
+                                location,
+                                command,
+                                false, // Scored once collected
+                            );
+                            mechanisms.push(match &user {
+                                Some(user) => mechanism.with_user(user),
+                                None => mechanism,
+                            });
                         }
                     }
-                    Err(_) => {
-                        // Can't read directory - skip
-                    }
+                }
+                Err(_) => {
+                    // Can't read directory - skip
                 }
             }
         }
     }
     
-    Ok(mechanisms)
+    mechanisms
 }
 
 /// Collect potentially suspicious Windows Services
+#[cfg(windows)]
 fn collect_service_persistence() -> Result<Vec<PersistenceMechanism>, String> {
-    let mut mechanisms = Vec::new();
-    
     // Access Services registry key
     let services_key = RegKey::predef(HKEY_LOCAL_MACHINE)
-        .open_subkey(r"SYSTEM\CurrentControlSet\Services")
+        .open_tracked(r"SYSTEM\CurrentControlSet\Services")
         .map_err(|e| format!("Failed to open Services registry key: {}", e))?;
     
+    Ok(collect_services_from_key(&services_key, r"HKLM\SYSTEM\CurrentControlSet\Services"))
+}
+
+#[cfg(not(windows))]
+fn collect_service_persistence() -> Result<Vec<PersistenceMechanism>, String> {
+    Err(REGISTRY_UNAVAILABLE.to_string())
+}
+
+/// Collect potentially suspicious services below an opened Services key
+#[cfg(windows)]
+fn collect_services_from_key(services_key: &RegKey, services_source: &str) -> Vec<PersistenceMechanism> {
+    let mut mechanisms = Vec::new();
+    
     for service_name in services_key.enum_keys().filter_map(|k| k.ok()) {
-        if let Ok(service_key) = services_key.open_subkey(&service_name) {
+        if let Ok(service_key) = services_key.open_tracked(&service_name) {
             // Check if this is a user-mode service with an executable
             if let Ok(image_path) = service_key.get_value::<String, _>("ImagePath") {
                 // Look for potentially suspicious characteristics
                 if is_potentially_suspicious_service(&service_name, &image_path) {
-                    let source = format!(r"HKLM\SYSTEM\CurrentControlSet\Services\{}", service_name);
-                    let location = format!(r"HKLM\SYSTEM\CurrentControlSet\Services\{}\ImagePath", service_name);
-                    let is_suspicious = is_mechanism_suspicious_by_command(&image_path);
+                    let source = format!(r"{}\{}", services_source, service_name);
+                    let location = format!(r"{}\{}\ImagePath", services_source, service_name);
                     
                     mechanisms.push(PersistenceMechanism::new_with_location_value(
                         PersistenceType::Service.as_str().to_string(),
@@ -202,20 +894,21 @@ fn collect_service_persistence() -> Result<Vec<PersistenceMechanism>, String> {
                         source,
                         location,
                         image_path,
-                        is_suspicious,
+                        false, // Scored once collected
                     ));
                 }
             }
         }
     }
     
-    Ok(mechanisms)
+    mechanisms
 }
 
 /// Check if a service might be suspicious (basic heuristics)
+#[cfg_attr(not(windows), allow(dead_code))]
 fn is_potentially_suspicious_service(name: &str, image_path: &str) -> bool {
     let name_lower = name.to_lowercase();
-    let path_lower = image_path.to_lowercase();
+    let path_lower = path_norm::normalize_command(image_path);
     
     // Skip well-known Windows services
     let known_safe_services = vec![
@@ -280,6 +973,7 @@ fn get_startup_folder_path(folder_type: &str) -> Option<String> {
 }
 
 /// Convert registry hive to string representation
+#[cfg(windows)]
 fn hive_to_string(hive: HKEY) -> &'static str {
     match hive {
         HKEY_LOCAL_MACHINE => "HKLM",
@@ -301,100 +995,27 @@ pub fn find_suspicious_mechanisms(mechanisms: &[PersistenceMechanism]) -> Vec<&P
     mechanisms.iter().filter(|m| is_mechanism_suspicious(m)).collect()
 }
 
-/// Collect scheduled tasks via Windows Task Scheduler API
-fn collect_scheduled_tasks() -> Result<Vec<PersistenceMechanism>, String> {
-    let mut mechanisms = Vec::new();
-    
-    // Use schtasks.exe to enumerate all scheduled tasks
-    match Command::new("schtasks")
-        .args(&["/query", "/fo", "csv", "/v"])
-        .output() {
-        Ok(output) => {
-            if output.status.success() {
-                let output_str = String::from_utf8_lossy(&output.stdout);
-                let lines: Vec<&str> = output_str.lines().collect();
-                
-                if lines.len() > 1 {
-                    // Parse CSV header to understand column positions
-                    let header = lines[0];
-                    let columns = parse_csv_line(header);
-                    
-                    // Find column indices
-                    let task_name_idx = find_column_index(&columns, "TaskName");
-                    let status_idx = find_column_index(&columns, "Status");
-                    let run_as_user_idx = find_column_index(&columns, "Run As User");
-                    let task_to_run_idx = find_column_index(&columns, "Task To Run");
-                    
-                    // Parse each task line
-                    for line in lines.iter().skip(1) {
-                        if line.trim().is_empty() {
-                            continue;
-                        }
-                        
-                        let fields = parse_csv_line(line);
-                        
-                        let task_name = get_field(&fields, task_name_idx).unwrap_or("Unknown");
-                        let task_path = if task_name.starts_with('\\') {
-                            task_name.to_string()
-                        } else {
-                            format!("\\{}", task_name)
-                        };
-                        
-                        let status = get_field(&fields, status_idx).unwrap_or("Unknown");
-                        let run_as_user = get_field(&fields, run_as_user_idx).unwrap_or("Unknown");
-                        let command = get_field(&fields, task_to_run_idx).unwrap_or("Unknown");
-                        
-                        // Only include enabled/ready tasks or suspicious ones
-                        if status.eq_ignore_ascii_case("Ready") || 
-                           status.eq_ignore_ascii_case("Running") ||
-                           is_suspicious_task_command(command) {
-                            
-                            let clean_name = extract_task_name(&task_path);
-                            let source = format!("Task Scheduler: {}", task_path);
-                            let location = format!("Task Scheduler: {}", task_path);
-                            let value = format!("{} (User: {})", command, run_as_user);
-                            let is_suspicious = is_mechanism_suspicious_by_command(command) || is_suspicious_task_command(command);
-                            
-                            mechanisms.push(PersistenceMechanism::new_with_location_value(
-                                PersistenceType::ScheduledTask.as_str().to_string(),
-                                clean_name,
-                                value.clone(),
-                                source,
-                                location,
-                                value,
-                                is_suspicious,
-                            ));
-                        }
-                    }
-                }
-            } else {
-                return Err("schtasks command failed".to_string());
-            }
-        }
-        Err(e) => {
-            return Err(format!("Failed to execute schtasks: {}", e));
-        }
-    }
-    
-    Ok(mechanisms)
-}
-
-/// Check if a scheduled task command appears suspicious
-fn is_suspicious_task_command(command: &str) -> bool {
-    let command_lower = command.to_lowercase();
-    
-    let suspicious_patterns = vec![
-        "powershell", "cmd.exe", "wscript", "cscript", "regsvr32",
-        "rundll32", "mshta", "bitsadmin", "certutil", "temp\\", "tmp\\",
-        "appdata\\", "downloads\\", "public\\", ".bat", ".cmd", ".ps1", ".vbs", ".js"
-    ];
-    
-    suspicious_patterns.iter().any(|&pattern| command_lower.contains(pattern))
+/// Persistence view of a scheduled task, keeping the score of the task inventory
+fn task_mechanism(task: &ScheduledTask) -> PersistenceMechanism {
+    let location = format!("Task Scheduler: {}", task.path);
+    let value = format!("{} (User: {})", scheduled_tasks::command_line(task), task.run_as_user);
+    let mut mechanism = PersistenceMechanism::new_with_location_value(
+        PersistenceType::ScheduledTask.as_str().to_string(),
+        task.name.clone(),
+        value.clone(),
+        location.clone(),
+        location,
+        value,
+        suspicion::shared().is_suspicious(task.suspicion_score),
+    );
+    mechanism.suspicion_score = task.suspicion_score;
+    mechanism.suspicion_reasons = task.suspicion_reasons.clone();
+    mechanism
 }
 
 /// Check if a mechanism is suspicious based on command/path analysis
 fn is_mechanism_suspicious_by_command(command: &str) -> bool {
-    let command_lower = command.to_lowercase();
+    let command_lower = path_norm::normalize_command(command);
     
     let suspicious_indicators = vec![
         // Temporary directories
@@ -417,52 +1038,9 @@ fn is_mechanism_suspicious_by_command(command: &str) -> bool {
     suspicious_indicators.iter().any(|&indicator| command_lower.contains(indicator))
 }
 
-/// Parse CSV line (simple implementation)
-fn parse_csv_line(line: &str) -> Vec<String> {
-    // Simple CSV parsing - handles quoted fields
-    let mut fields = Vec::new();
-    let mut current_field = String::new();
-    let mut in_quotes = false;
-    let mut chars = line.chars().peekable();
-    
-    while let Some(ch) = chars.next() {
-        match ch {
-            '"' => {
-                in_quotes = !in_quotes;
-            }
-            ',' if !in_quotes => {
-                fields.push(current_field.trim().to_string());
-                current_field = String::new();
-            }
-            _ => {
-                current_field.push(ch);
-            }
-        }
-    }
-    
-    // Add the last field
-    fields.push(current_field.trim().to_string());
-    fields
-}
-
-/// Find column index by name
-fn find_column_index(columns: &[String], column_name: &str) -> Option<usize> {
-    columns.iter().position(|col| col.eq_ignore_ascii_case(column_name))
-}
-
-/// Get field by index
-fn get_field<'a>(fields: &'a [String], index: Option<usize>) -> Option<&'a str> {
-    index.and_then(|i| fields.get(i).map(|s| s.as_str()))
-}
-
-/// Extract task name from path
-fn extract_task_name(task_path: &str) -> String {
-    task_path.split('\\').last().unwrap_or(task_path).to_string()
-}
-
 /// Check if a persistence mechanism appears suspicious
 fn is_mechanism_suspicious(mechanism: &PersistenceMechanism) -> bool {
-    let command_lower = mechanism.command.to_lowercase();
+    let command_lower = path_norm::normalize_command(&mechanism.command);
     
     // Suspicious file locations
     let suspicious_locations = vec![
@@ -502,6 +1080,7 @@ fn is_mechanism_suspicious(mechanism: &PersistenceMechanism) -> bool {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::fs;
 
     #[test]
     fn test_collect_persistence_mechanisms() {
@@ -525,6 +1104,29 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_collect_persistence_mechanisms_offline_startup_folders() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        fs::create_dir(temp_dir.path().join("Windows")).unwrap();
+        
+        let user_startup = ["Users", "alice", "AppData", "Roaming", "Microsoft", "Windows", "Start Menu", "Programs", "Startup"]
+            .iter()
+            .fold(temp_dir.path().to_path_buf(), |path, component| path.join(component));
+        fs::create_dir_all(&user_startup).unwrap();
+        fs::write(user_startup.join("updater.bat"), b"@echo off").unwrap();
+        
+        let root = OfflineRoot::new(temp_dir.path()).unwrap();
+        let (mechanisms, logs) = collect_persistence_mechanisms_offline(&root);
+        
+        assert_eq!(mechanisms.len(), 1);
+        assert_eq!(mechanisms[0].name, "updater.bat");
+        assert_eq!(mechanisms[0].mechanism_type, PersistenceType::StartupFolder.as_str());
+        assert_eq!(mechanisms[0].user.as_deref(), Some("alice"));
+        // No hives on this volume
+        assert!(logs.iter().any(|log| log.message.contains("offline SOFTWARE hive")));
+        assert!(logs.iter().any(|log| log.message.contains("completed")));
+    }
+
     #[test]
     fn test_is_potentially_suspicious_service() {
         // Known safe service should not be flagged
@@ -583,6 +1185,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(windows)]
     fn test_hive_to_string() {
         assert_eq!(hive_to_string(HKEY_LOCAL_MACHINE), "HKLM");
         assert_eq!(hive_to_string(HKEY_CURRENT_USER), "HKCU");
@@ -590,48 +1193,66 @@ mod tests {
     }
 
     #[test]
-    fn test_is_suspicious_task_command() {
-        // Suspicious commands should be flagged
-        assert!(is_suspicious_task_command("powershell.exe -ExecutionPolicy Bypass"));
-        assert!(is_suspicious_task_command("cmd.exe /c malware.bat"));
-        assert!(is_suspicious_task_command("C:\\Temp\\malware.exe"));
-        assert!(is_suspicious_task_command("wscript.exe script.vbs"));
+    fn test_score_winlogon_value() {
+        assert_eq!(score_winlogon_value("Shell", "explorer.exe", false).0, 0);
+        assert_eq!(score_winlogon_value("Userinit", "C:\\Windows\\system32\\userinit.exe,", false).0, 0);
+        
+        let (score, reasons) = score_winlogon_value("Userinit", "C:\\Windows\\system32\\userinit.exe, C:\\Users\\Public\\svc.exe", false);
+        assert!(score >= HIJACK_SUSPICION_THRESHOLD);
+        assert_eq!(reasons, vec!["additional_userinit_program", "suspicious_command"]);
         
-        // Normal commands should not be flagged
-        assert!(!is_suspicious_task_command("C:\\Program Files\\Adobe\\Updater\\updater.exe"));
-        assert!(!is_suspicious_task_command("C:\\Windows\\System32\\svchost.exe"));
+        let (score, reasons) = score_winlogon_value("Shell", "explorer.exe", true);
+        assert_eq!(score, 40);
+        assert_eq!(reasons, vec!["per_user_shell_override"]);
+        assert!(score_winlogon_value("DllName", "evil.dll", false).1.contains(&"winlogon_notify_package".to_string()));
     }
 
     #[test]
-    fn test_parse_csv_line() {
-        let line = r#""Task Name","Status","Run As User""#;
-        let fields = parse_csv_line(line);
-        assert_eq!(fields.len(), 3);
-        assert_eq!(fields[0], "Task Name");
-        assert_eq!(fields[1], "Status");
-        assert_eq!(fields[2], "Run As User");
+    fn test_score_image_hijack() {
+        let (score, reasons) = score_image_hijack("sethc.exe", "C:\\Windows\\System32\\cmd.exe", "debugger");
+        assert_eq!(score, 100);
+        assert!(reasons.contains(&"accessibility_binary_hijack".to_string()));
         
-        let line_with_commas = r#""Adobe Acrobat Update Task","Ready","NT AUTHORITY\SYSTEM""#;
-        let fields = parse_csv_line(line_with_commas);
-        assert_eq!(fields.len(), 3);
-        assert_eq!(fields[0], "Adobe Acrobat Update Task");
-        assert_eq!(fields[1], "Ready");
-        assert_eq!(fields[2], "NT AUTHORITY\\SYSTEM");
+        assert_eq!(score_image_hijack("taskmgr.exe", "\"C:\\Tools\\procexp64.exe\"", "debugger"), (10, vec!["known_debugger".to_string()]));
+        
+        let (score, reasons) = score_image_hijack("notepad.exe", "C:\\Users\\bob\\monitor.exe", "silent_process_exit");
+        assert_eq!(score, 60);
+        assert_eq!(reasons, vec!["silent_process_exit_monitor", "non_system_path"]);
     }
 
     #[test]
-    fn test_find_column_index() {
-        let columns = vec!["TaskName".to_string(), "Status".to_string(), "Run As User".to_string()];
-        assert_eq!(find_column_index(&columns, "TaskName"), Some(0));
-        assert_eq!(find_column_index(&columns, "Status"), Some(1));
-        assert_eq!(find_column_index(&columns, "Run As User"), Some(2));
-        assert_eq!(find_column_index(&columns, "NonExistent"), None);
+    fn test_score_appinit_dlls() {
+        let dlls = split_dll_list("C:\\ProgramData\\hook.dll,C:\\Windows\\System32\\ok.dll");
+        assert_eq!(dlls.len(), 2);
+        assert_eq!(score_appinit_dlls("AppInit_DLLs", &dlls, Some(true)).0, 90);
+        assert_eq!(score_appinit_dlls("AppInit_DLLs", &dlls[1..], Some(false)).0, 40);
+        assert_eq!(score_appinit_dlls("Default", &dlls[..1], None).1, vec!["appcert_dll", "non_system_dll"]);
+        assert_eq!(parse_global_flag("0x00000200"), Some(0x200));
+        assert_eq!(parse_global_flag("512"), Some(512));
     }
 
     #[test]
-    fn test_extract_task_name() {
-        assert_eq!(extract_task_name("\\Microsoft\\Windows\\UpdateOrchestrator\\Schedule Scan"), "Schedule Scan");
-        assert_eq!(extract_task_name("\\Adobe Updater Task"), "Adobe Updater Task");
-        assert_eq!(extract_task_name("SimpleTask"), "SimpleTask");
+    fn test_hash_mechanism_targets() {
+        assert_eq!(image_path("%SystemRoot%\\System32\\svchost.exe", "C:\\Windows"), "C:\\Windows\\System32\\svchost.exe");
+        assert_eq!(image_path("\\SystemRoot\\System32\\drivers\\evil.sys", "C:\\Windows"), "C:\\Windows\\System32\\drivers\\evil.sys");
+        assert_eq!(image_path("system32\\drivers\\evil.sys", "C:\\Windows\\"), "C:\\Windows\\system32\\drivers\\evil.sys");
+        
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::create_dir(temp_dir.path().join("Windows")).unwrap();
+        let root = OfflineRoot::new(temp_dir.path()).unwrap();
+        let tools = root.resolve("C:\\Tools");
+        fs::create_dir_all(&tools).unwrap();
+        fs::write(tools.join("agent.exe"), b"agent").unwrap();
+        
+        let mut mechanisms = vec![
+            PersistenceMechanism::new("Registry Run Key".to_string(), "Agent".to_string(), "\"C:\\Tools\\agent.exe\" /silent".to_string(), "HKLM\\...\\Run".to_string()),
+            PersistenceMechanism::new("Registry Run Key".to_string(), "Gone".to_string(), "C:\\Tools\\gone.exe".to_string(), "HKLM\\...\\Run".to_string()),
+        ];
+        assert_eq!(hash_mechanism_targets(&mut mechanisms, "C:\\Windows", Some(&root)), 1);
+        assert_eq!(
+            mechanisms[0].hashes.as_ref().and_then(|h| h.sha256.clone()),
+            Some(crate::processes::calculate_file_hash(&tools.join("agent.exe").to_string_lossy()).unwrap())
+        );
+        assert!(mechanisms[1].hashes.is_none());
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,271 @@
+//! Third-party collector plugins
+//! Shared libraries in the plugins directory (`--plugins-dir`) that export the
+//! versioned C ABI declared in include/triageir_plugin.h. Each plugin returns
+//! its artifacts and audit entries as JSON; they are stored under
+//! `artifacts.plugins` and in the collection log like built-in collectors.
+//! Plugins run in-process with the collector's privileges, so only trusted
+//! libraries should be placed in the directory.
+
+use crate::forensic_types::{AuditEntry, PluginArtifacts};
+use libloading::{Library, Symbol};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::ffi::{c_char, CStr, CString};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const COMPONENT: &str = "plugins";
+
+/// Version of the plugin ABI; plugins built for another version are skipped
+pub const PLUGIN_ABI_VERSION: u32 = 1;
+
+const PLUGIN_EXTENSIONS: &[&str] = &["dll", "so", "dylib"];
+
+type AbiVersionFn = unsafe extern "C" fn() -> u32;
+type StringFn = unsafe extern "C" fn() -> *const c_char;
+type CollectFn = unsafe extern "C" fn(context_json: *const c_char, output_json: *mut *mut c_char) -> i32;
+type FreeFn = unsafe extern "C" fn(output_json: *mut c_char);
+
+/// JSON document returned by `triageir_plugin_collect`
+#[derive(Debug, Deserialize)]
+pub(crate) struct PluginOutput {
+    #[serde(default)]
+    pub artifacts: Vec<Value>,
+    #[serde(default)]
+    pub audit: Vec<PluginAuditEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct PluginAuditEntry {
+    #[serde(default)]
+    level: String,
+    action: String,
+    #[serde(default)]
+    details: String,
+    #[serde(default)]
+    result: String,
+}
+
+/// Run every plugin in `dir`; `context` is passed to the plugins as JSON
+pub fn collect_plugin_artifacts(dir: &Path, context: &Value) -> (Vec<PluginArtifacts>, Vec<AuditEntry>) {
+    let mut results = Vec::new();
+    let mut audit_log = Vec::new();
+
+    let start_time = std::time::Instant::now();
+
+    audit_log.push(AuditEntry::new(COMPONENT, "INFO", "start_collection", format!("Loading collector plugins from {}", dir.display()), "started"));
+
+    let libraries = match discover_plugins(dir) {
+        Ok(libraries) => libraries,
+        Err(e) => {
+            audit_log.push(AuditEntry::new(COMPONENT, "ERROR", "discover", e, "error"));
+            return (results, audit_log);
+        }
+    };
+
+    let context_json = CString::new(context.to_string()).unwrap_or_default();
+    let mut seen_names = HashSet::new();
+
+    for path in libraries {
+        let plugin_start = std::time::Instant::now();
+        match unsafe { run_plugin(&path, &context_json) } {
+            Ok((mut plugin, output)) => {
+                if !seen_names.insert(plugin.plugin.clone()) {
+                    audit_log.push(AuditEntry::new(
+                        COMPONENT,
+                        "WARN",
+                        "load_plugin",
+                        format!("{}: duplicate plugin name '{}', results discarded", path.display(), plugin.plugin),
+                        "skipped",
+                    ));
+                    continue;
+                }
+
+                let component = format!("plugin:{}", plugin.plugin);
+                audit_log.extend(output.audit.into_iter().map(|entry| plugin_audit_entry(&component, entry)));
+                audit_log.push(AuditEntry {
+                    timestamp: chrono::Utc::now().to_rfc3339(),
+                    level: "INFO".to_string(),
+                    component,
+                    action: "complete_collection".to_string(),
+                    details: format!("Collected {} artifacts ({} {})", output.artifacts.len(), plugin.plugin, plugin.version),
+                    duration_ms: Some(plugin_start.elapsed().as_millis() as u64),
+                    result: "success".to_string(),
+                });
+
+                plugin.artifacts = output.artifacts;
+                results.push(plugin);
+            }
+            Err(e) => audit_log.push(AuditEntry::new(COMPONENT, "ERROR", "load_plugin", format!("{}: {}", path.display(), e), "error")),
+        }
+    }
+
+    let duration = start_time.elapsed();
+    audit_log.push(AuditEntry {
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        level: "INFO".to_string(),
+        component: "plugins".to_string(),
+        action: "complete_collection".to_string(),
+        details: format!("Ran {} collector plugins", results.len()),
+        duration_ms: Some(duration.as_millis() as u64),
+        result: "success".to_string(),
+    });
+
+    (results, audit_log)
+}
+
+/// Shared libraries in the plugins directory, in name order
+fn discover_plugins(dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let entries = fs::read_dir(dir).map_err(|e| format!("Cannot read plugins directory {}: {}", dir.display(), e))?;
+
+    let mut libraries: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file())
+        .filter(|path| {
+            path.extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| PLUGIN_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+        })
+        .collect();
+    libraries.sort();
+    Ok(libraries)
+}
+
+/// Load a plugin library, check its ABI version and run its collector
+///
+/// # Safety
+/// Loading a library runs its initialization code; the library must be trusted.
+unsafe fn run_plugin(path: &Path, context_json: &CStr) -> Result<(PluginArtifacts, PluginOutput), String> {
+    let library = Library::new(path).map_err(|e| format!("cannot load library: {}", e))?;
+
+    let abi_version: Symbol<AbiVersionFn> = symbol(&library, b"triageir_plugin_abi_version\0")?;
+    let abi_version = abi_version();
+    if abi_version != PLUGIN_ABI_VERSION {
+        return Err(format!("plugin ABI version {} is not supported (expected {})", abi_version, PLUGIN_ABI_VERSION));
+    }
+
+    let name: Symbol<StringFn> = symbol(&library, b"triageir_plugin_name\0")?;
+    let version: Symbol<StringFn> = symbol(&library, b"triageir_plugin_version\0")?;
+    let collect: Symbol<CollectFn> = symbol(&library, b"triageir_plugin_collect\0")?;
+    let free: Symbol<FreeFn> = symbol(&library, b"triageir_plugin_free\0")?;
+
+    let name = plugin_string(name()).ok_or("triageir_plugin_name returned no name")?;
+    if !is_valid_plugin_name(&name) {
+        return Err(format!("invalid plugin name '{}' (use letters, digits, '_', '-' and '.')", name));
+    }
+    let version = plugin_string(version()).unwrap_or_default();
+
+    let mut output_json: *mut c_char = std::ptr::null_mut();
+    let status = collect(context_json.as_ptr(), &mut output_json);
+    let output = if output_json.is_null() { None } else { Some(CStr::from_ptr(output_json).to_string_lossy().to_string()) };
+    if !output_json.is_null() {
+        free(output_json);
+    }
+
+    if status != 0 {
+        return Err(format!("{} collector failed with status {}", name, status));
+    }
+    let output = parse_plugin_output(output.as_deref().unwrap_or("{}")).map_err(|e| format!("{}: {}", name, e))?;
+
+    let plugin = PluginArtifacts {
+        plugin: name,
+        version,
+        library: path.display().to_string(),
+        artifacts: Vec::new(),
+    };
+    Ok((plugin, output))
+}
+
+unsafe fn symbol<'lib, T>(library: &'lib Library, name: &[u8]) -> Result<Symbol<'lib, T>, String> {
+    library.get(name).map_err(|_| format!("missing export {}", String::from_utf8_lossy(&name[..name.len() - 1])))
+}
+
+unsafe fn plugin_string(value: *const c_char) -> Option<String> {
+    if value.is_null() {
+        return None;
+    }
+    let value = CStr::from_ptr(value).to_string_lossy().trim().to_string();
+    (!value.is_empty()).then_some(value)
+}
+
+pub(crate) fn is_valid_plugin_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+}
+
+pub(crate) fn parse_plugin_output(json: &str) -> Result<PluginOutput, String> {
+    serde_json::from_str(json).map_err(|e| format!("invalid plugin output: {}", e))
+}
+
+pub(crate) fn plugin_audit_entry(component: &str, entry: PluginAuditEntry) -> AuditEntry {
+    let level = match entry.level.to_uppercase().as_str() {
+        level @ ("DEBUG" | "INFO" | "WARN" | "ERROR") => level.to_string(),
+        "WARNING" => "WARN".to_string(),
+        _ => "INFO".to_string(),
+    };
+    AuditEntry {
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        level,
+        component: component.to_string(),
+        action: entry.action,
+        details: entry.details,
+        duration_ms: None,
+        result: if entry.result.is_empty() { "success".to_string() } else { entry.result },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_discover_plugins() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["b_agent.dll", "a_logs.SO", "readme.txt"] {
+            fs::write(dir.path().join(name), b"").unwrap();
+        }
+        fs::create_dir(dir.path().join("nested.dll")).unwrap();
+
+        let plugins = discover_plugins(dir.path()).unwrap();
+        let names: Vec<_> = plugins.iter().map(|p| p.file_name().unwrap().to_str().unwrap()).collect();
+        assert_eq!(names, vec!["a_logs.SO", "b_agent.dll"]);
+
+        assert!(discover_plugins(&dir.path().join("missing")).is_err());
+    }
+
+    #[test]
+    fn test_parse_plugin_output() {
+        let output = parse_plugin_output(
+            r#"{"artifacts": [{"user": "alice"}], "audit": [{"level": "warning", "action": "read_log", "details": "log rotated"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(output.artifacts, vec![json!({"user": "alice"})]);
+
+        let entry = plugin_audit_entry("plugin:crm_logs", output.audit.into_iter().next().unwrap());
+        assert_eq!(entry.level, "WARN");
+        assert_eq!(entry.component, "plugin:crm_logs");
+        assert_eq!(entry.result, "success");
+
+        assert!(parse_plugin_output("{}").unwrap().artifacts.is_empty());
+        assert!(parse_plugin_output(r#"{"artifacts": {}}"#).is_err());
+    }
+
+    #[test]
+    fn test_unloadable_plugin_is_audited() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("broken.dll"), b"not a library").unwrap();
+
+        let (plugins, audit) = collect_plugin_artifacts(dir.path(), &json!({}));
+        assert!(plugins.is_empty());
+        assert!(audit.iter().any(|e| e.level == "ERROR" && e.action == "load_plugin" && e.details.contains("broken.dll")));
+        assert_eq!(audit.last().unwrap().action, "complete_collection");
+    }
+
+    #[test]
+    fn test_plugin_names() {
+        assert!(is_valid_plugin_name("crm-logs_v2.0"));
+        assert!(!is_valid_plugin_name("../evil"));
+        assert!(!is_valid_plugin_name("name with spaces"));
+    }
+}
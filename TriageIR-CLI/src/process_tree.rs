@@ -0,0 +1,249 @@
+//! Process tree reconstruction
+//! Links processes by PID/PPID, using creation times to reject parents that
+//! started after their child (the real parent exited and its PID was reused).
+//! Flags orphaned processes, PPID anomalies and suspicious parent/child pairs
+//! such as an Office application spawning a shell.
+
+use crate::types::{LogEntry, Process, ProcessTree, ProcessTreeNode};
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, HashSet};
+
+/// Processes whose parent normally exits after starting them
+const EXPECTED_ORPHANS: &[&str] = &["explorer.exe", "csrss.exe", "wininit.exe", "winlogon.exe", "system", "registry", "memory compression"];
+
+const OFFICE_APPLICATIONS: &[&str] = &[
+    "winword.exe", "excel.exe", "powerpnt.exe", "outlook.exe", "msaccess.exe", "mspub.exe", "onenote.exe", "visio.exe",
+];
+
+const DOCUMENT_READERS: &[&str] = &["acrord32.exe", "acrobat.exe", "foxitreader.exe", "foxitpdfreader.exe"];
+
+const SERVER_PROCESSES: &[&str] = &["w3wp.exe", "httpd.exe", "nginx.exe", "tomcat.exe", "sqlservr.exe", "wmiprvse.exe"];
+
+const SHELLS_AND_LOLBINS: &[&str] = &[
+    "cmd.exe", "powershell.exe", "pwsh.exe", "wscript.exe", "cscript.exe", "mshta.exe", "rundll32.exe",
+    "regsvr32.exe", "certutil.exe", "bitsadmin.exe", "schtasks.exe", "msbuild.exe", "installutil.exe",
+];
+
+/// System processes and the only parent they should have
+const EXPECTED_PARENTS: &[(&str, &str)] = &[
+    ("svchost.exe", "services.exe"),
+    ("services.exe", "wininit.exe"),
+    ("lsass.exe", "wininit.exe"),
+    ("lsaiso.exe", "wininit.exe"),
+    ("taskhostw.exe", "svchost.exe"),
+    ("runtimebroker.exe", "svchost.exe"),
+    ("smss.exe", "system"),
+];
+
+/// Build the process tree and record suspicious indicators on each process
+pub fn analyze_process_tree(processes: &mut [Process]) -> (ProcessTree, Vec<LogEntry>) {
+    let mut logs = vec![LogEntry::info("Starting process tree reconstruction")];
+    let mut tree = ProcessTree::default();
+
+    let index_by_pid: HashMap<u32, usize> = processes.iter().enumerate().map(|(i, p)| (p.pid, i)).collect();
+    let mut parent_index: Vec<Option<usize>> = vec![None; processes.len()];
+
+    for i in 0..processes.len() {
+        let (pid, parent_pid) = (processes[i].pid, processes[i].parent_pid);
+        let name = processes[i].name.to_lowercase();
+
+        if parent_pid == 0 || parent_pid == pid {
+            continue; // System roots
+        }
+
+        let Some(&parent) = index_by_pid.get(&parent_pid) else {
+            if !EXPECTED_ORPHANS.contains(&name.as_str()) {
+                processes[i].suspicious_indicators.push(format!("orphaned: parent PID {} is not running", parent_pid));
+                tree.orphaned_pids.push(pid);
+            }
+            continue;
+        };
+
+        if started_before(&processes[i], &processes[parent]) {
+            let indicator = format!(
+                "ppid_reuse: parent PID {} ({}) started after this process; the original parent exited and its PID was reused",
+                parent_pid, processes[parent].name
+            );
+            processes[i].suspicious_indicators.push(indicator);
+            tree.ppid_anomaly_pids.push(pid);
+            continue;
+        }
+
+        parent_index[i] = Some(parent);
+        let parent_name = processes[parent].name.to_lowercase();
+
+        if let Some(reason) = suspicious_pair(&parent_name, &name) {
+            processes[i].suspicious_indicators.push(format!("suspicious_parent: {} -> {} ({})", processes[parent].name, processes[i].name, reason));
+            tree.suspicious_pair_pids.push(pid);
+        } else if let Some((_, expected)) = EXPECTED_PARENTS.iter().find(|(child, _)| *child == name) {
+            if parent_name != *expected {
+                processes[i].suspicious_indicators.push(format!(
+                    "unexpected_parent: {} should be started by {}, not {} (PID {})",
+                    processes[i].name, expected, processes[parent].name, parent_pid
+                ));
+                tree.ppid_anomaly_pids.push(pid);
+            }
+        }
+    }
+
+    let mut children: HashMap<usize, Vec<usize>> = HashMap::new();
+    let mut roots = Vec::new();
+    for (i, parent) in parent_index.iter().enumerate() {
+        match parent {
+            Some(parent) => children.entry(*parent).or_default().push(i),
+            None => roots.push(i),
+        }
+    }
+
+    let mut visited = HashSet::new();
+    tree.roots = roots.into_iter().map(|root| build_node(root, processes, &children, &mut visited)).collect();
+
+    // PID cycles leave processes unreachable from any root; attach them as roots
+    for i in 0..processes.len() {
+        if !visited.contains(&i) {
+            let node = build_node(i, processes, &children, &mut visited);
+            tree.roots.push(node);
+        }
+    }
+
+    logs.push(LogEntry::info(&format!(
+        "Process tree reconstructed: {} roots, {} orphaned, {} PPID anomalies, {} suspicious parent/child pairs",
+        tree.roots.len(), tree.orphaned_pids.len(), tree.ppid_anomaly_pids.len(), tree.suspicious_pair_pids.len()
+    )));
+    for process in processes.iter().filter(|p| !p.suspicious_indicators.is_empty()) {
+        logs.push(LogEntry::warn(&format!("Process {} (PID {}): {}", process.name, process.pid, process.suspicious_indicators.join("; "))));
+    }
+
+    (tree, logs)
+}
+
+fn build_node(index: usize, processes: &[Process], children: &HashMap<usize, Vec<usize>>, visited: &mut HashSet<usize>) -> ProcessTreeNode {
+    visited.insert(index);
+    let process = &processes[index];
+
+    let mut child_nodes = Vec::new();
+    for &child in children.get(&index).map(Vec::as_slice).unwrap_or_default() {
+        if !visited.contains(&child) {
+            child_nodes.push(build_node(child, processes, children, visited));
+        }
+    }
+
+    ProcessTreeNode {
+        pid: process.pid,
+        parent_pid: process.parent_pid,
+        name: process.name.clone(),
+        creation_time: process.creation_time.clone(),
+        suspicious_indicators: process.suspicious_indicators.clone(),
+        children: child_nodes,
+    }
+}
+
+/// Whether the child was created before its supposed parent
+fn started_before(child: &Process, parent: &Process) -> bool {
+    match (parse_time(&child.creation_time), parse_time(&parent.creation_time)) {
+        (Some(child_time), Some(parent_time)) => child_time < parent_time,
+        _ => false,
+    }
+}
+
+fn parse_time(time: &Option<String>) -> Option<DateTime<Utc>> {
+    time.as_deref()
+        .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+        .map(|t| t.with_timezone(&Utc))
+}
+
+fn suspicious_pair(parent: &str, child: &str) -> Option<&'static str> {
+    if !SHELLS_AND_LOLBINS.contains(&child) {
+        return None;
+    }
+    if OFFICE_APPLICATIONS.contains(&parent) {
+        Some("Office application spawning a shell or script host")
+    } else if DOCUMENT_READERS.contains(&parent) {
+        Some("document reader spawning a shell or script host")
+    } else if SERVER_PROCESSES.contains(&parent) {
+        Some("server process spawning a shell; possible web shell or remote execution")
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn process(pid: u32, parent_pid: u32, name: &str, creation_time: &str) -> Process {
+        let mut process = Process::new(pid, parent_pid, name.to_string(), name.to_string(), String::new());
+        process.creation_time = Some(creation_time.to_string());
+        process
+    }
+
+    #[test]
+    fn test_suspicious_parent_child_pair() {
+        let mut processes = vec![
+            process(100, 50, "explorer.exe", "2024-05-01T08:00:00+00:00"),
+            process(200, 100, "WINWORD.EXE", "2024-05-01T09:00:00+00:00"),
+            process(300, 200, "cmd.exe", "2024-05-01T09:05:00+00:00"),
+        ];
+
+        let (tree, _) = analyze_process_tree(&mut processes);
+
+        assert_eq!(tree.suspicious_pair_pids, vec![300]);
+        assert!(processes[2].suspicious_indicators[0].starts_with("suspicious_parent: WINWORD.EXE -> cmd.exe"));
+        assert!(tree.orphaned_pids.is_empty()); // explorer.exe is an expected orphan
+
+        assert_eq!(tree.roots.len(), 1);
+        assert_eq!(tree.roots[0].children[0].children[0].pid, 300);
+    }
+
+    #[test]
+    fn test_orphan_and_ppid_reuse() {
+        let mut processes = vec![
+            process(4, 0, "System", "2024-05-01T07:00:00+00:00"),
+            process(500, 999, "updater.exe", "2024-05-01T08:00:00+00:00"),
+            process(600, 700, "beacon.exe", "2024-05-01T08:00:00+00:00"),
+            process(700, 4, "notepad.exe", "2024-05-01T10:00:00+00:00"), // Reused PID of beacon.exe's parent
+        ];
+
+        let (tree, _) = analyze_process_tree(&mut processes);
+
+        assert_eq!(tree.orphaned_pids, vec![500]);
+        assert_eq!(tree.ppid_anomaly_pids, vec![600]);
+        assert!(processes[2].suspicious_indicators[0].starts_with("ppid_reuse"));
+
+        // beacon.exe is not attached to the impostor parent
+        let root_pids: Vec<u32> = tree.roots.iter().map(|r| r.pid).collect();
+        assert_eq!(root_pids, vec![4, 500, 600]);
+        assert_eq!(tree.roots[0].children[0].pid, 700);
+        assert!(tree.roots[0].children[0].children.is_empty());
+    }
+
+    #[test]
+    fn test_unexpected_system_process_parent() {
+        let mut processes = vec![
+            process(600, 0, "services.exe", "2024-05-01T07:00:00+00:00"),
+            process(700, 600, "svchost.exe", "2024-05-01T07:01:00+00:00"),
+            process(800, 900, "svchost.exe", "2024-05-01T09:00:00+00:00"),
+            process(900, 0, "explorer.exe", "2024-05-01T08:00:00+00:00"),
+        ];
+
+        let (tree, _) = analyze_process_tree(&mut processes);
+
+        assert_eq!(tree.ppid_anomaly_pids, vec![800]);
+        assert!(processes[1].suspicious_indicators.is_empty());
+        assert!(processes[2].suspicious_indicators[0].starts_with("unexpected_parent"));
+    }
+
+    #[test]
+    fn test_pid_cycle_is_still_reported() {
+        // Without creation times a reused PID can produce a cycle
+        let mut processes = vec![
+            Process::new(10, 20, "a.exe".to_string(), String::new(), String::new()),
+            Process::new(20, 10, "b.exe".to_string(), String::new(), String::new()),
+        ];
+
+        let (tree, _) = analyze_process_tree(&mut processes);
+
+        assert_eq!(tree.roots.len(), 1);
+        assert_eq!(tree.roots[0].children.len(), 1);
+    }
+}
@@ -1,11 +1,46 @@
-use crate::types::{Process, LogEntry};
+use crate::forensic_types::FileHashes;
+use crate::hashing;
+use crate::process_tokens;
+use crate::types::{Process, ProcessToken, LogEntry};
 use sysinfo::{System, Pid};
-use sha2::{Sha256, Digest};
 use std::fs;
+use std::collections::HashMap;
 use std::path::Path;
+use std::time::Instant;
+
+#[cfg(windows)]
+use crate::binutil::filetime_to_datetime;
+#[cfg(windows)]
+use windows::{
+    Win32::Foundation::{CloseHandle, FILETIME, HANDLE},
+    Win32::Security::{
+        GetSidSubAuthority, GetSidSubAuthorityCount, GetTokenInformation, TokenIntegrityLevel,
+        TokenSessionId, TOKEN_MANDATORY_LABEL, TOKEN_QUERY,
+    },
+    Win32::System::Threading::{
+        GetProcessTimes, OpenProcess, OpenProcessToken, QueryFullProcessImageNameW,
+        PROCESS_NAME_WIN32, PROCESS_QUERY_LIMITED_INFORMATION,
+    },
+    core::PWSTR,
+};
+
+/// Creation time, session, token integrity level and token contents read from the process handle
+#[derive(Debug, Default, Clone, PartialEq)]
+struct ProcessSecurityDetails {
+    creation_time: Option<String>,
+    session_id: Option<u32>,
+    integrity_level: Option<String>,
+    token: Option<ProcessToken>,
+}
 
 /// Collect information about all running processes
 pub fn collect_processes() -> (Vec<Process>, Vec<LogEntry>) {
+    collect_processes_with_hash_limit(None)
+}
+
+/// Collect all running processes, hashing the executables of at most `max_hashed`
+/// of them (scan profile limit); the rest keep "SKIPPED" as their hash
+pub fn collect_processes_with_hash_limit(max_hashed: Option<usize>) -> (Vec<Process>, Vec<LogEntry>) {
     let mut logs = Vec::new();
     logs.push(LogEntry::info("Starting process enumeration"));
     
@@ -18,15 +53,26 @@ pub fn collect_processes() -> (Vec<Process>, Vec<LogEntry>) {
     
     let mut successful_collections = 0;
     let mut hash_calculation_errors = 0;
+    let mut hashed = 0;
+    let mut hashes_skipped = 0;
+    let mut details_access_denied = 0;
+    let mut deleted_executables = 0;
+    let mut privileged_tokens = 0;
+    let mut impersonation = process_tokens::impersonated_users();
     
     for (pid, process) in sys.processes() {
         match collect_single_process(*pid, process) {
             Ok(mut proc_info) => {
-                // Calculate SHA-256 hash of executable if path is available
-                if proc_info.has_executable_path() {
-                    match calculate_file_hash(&proc_info.executable_path) {
-                        Ok(hash) => {
-                            proc_info.sha256_hash = hash;
+                // Hash the executable if path is available
+                if proc_info.has_executable_path() && max_hashed.is_some_and(|max| hashed >= max) {
+                    proc_info.sha256_hash = "SKIPPED".to_string();
+                    hashes_skipped += 1;
+                } else if proc_info.has_executable_path() {
+                    hashed += 1;
+                    match hash_executable(&proc_info.executable_path) {
+                        Ok(hashes) => {
+                            proc_info.sha256_hash = hashes.sha256.clone().unwrap_or_default();
+                            proc_info.hashes = Some(hashes);
                         }
                         Err(e) => {
                             hash_calculation_errors += 1;
@@ -40,6 +86,50 @@ pub fn collect_processes() -> (Vec<Process>, Vec<LogEntry>) {
                     proc_info.sha256_hash = "N/A".to_string();
                 }
                 
+                // Creation time, session and integrity level; protected and
+                // other users' processes may refuse the query
+                match query_security_details(proc_info.pid) {
+                    Ok(details) => {
+                        if details.creation_time.is_some() {
+                            proc_info.creation_time = details.creation_time;
+                        }
+                        proc_info.session_id = details.session_id.or(proc_info.session_id);
+                        proc_info.integrity_level = details.integrity_level;
+                        proc_info.token = details.token;
+                    }
+                    Err(_) => {
+                        details_access_denied += 1;
+                    }
+                }
+                
+                // The image file object still names the executable after it is
+                // deleted; a missing file is a common in-memory-only malware trait
+                if let Some(image_path) = query_image_path(proc_info.pid) {
+                    if !proc_info.has_executable_path() {
+                        proc_info.executable_path = image_path;
+                    }
+                }
+                if is_deleted_executable(&proc_info.executable_path) {
+                    proc_info.executable_deleted = true;
+                    proc_info.suspicious_indicators.push(format!("deleted_executable: {} no longer exists on disk", proc_info.executable_path));
+                    logs.push(LogEntry::warn(&format!("Process {} (PID {}) is running from a deleted executable: {}", proc_info.name, proc_info.pid, proc_info.executable_path)));
+                    deleted_executables += 1;
+                }
+                
+                // Privileged tokens outside the service accounts and impersonated SYSTEM tokens
+                if let Some(token) = proc_info.token.as_mut() {
+                    token.impersonated_users = impersonation.remove(&proc_info.pid).unwrap_or_default();
+                    if let Some(own_user) = &token.user {
+                        token.impersonated_users.retain(|user| user.sid != own_user.sid);
+                    }
+                    let findings = process_tokens::token_indicators(&proc_info.executable_path, token);
+                    if !findings.is_empty() {
+                        logs.push(LogEntry::warn(&format!("Process {} (PID {}): {}", proc_info.name, proc_info.pid, findings.join("; "))));
+                        proc_info.suspicious_indicators.extend(findings);
+                        privileged_tokens += 1;
+                    }
+                }
+                
                 // For now, just add a placeholder for loaded modules
                 // TODO: Implement Windows API-based module enumeration in next iteration
                 proc_info.loaded_modules = Vec::new();
@@ -59,13 +149,74 @@ pub fn collect_processes() -> (Vec<Process>, Vec<LogEntry>) {
         logs.push(LogEntry::warn(&format!("Failed to calculate hashes for {} processes", hash_calculation_errors)));
     }
     
+    if hashes_skipped > 0 {
+        logs.push(LogEntry::info(&format!("Executables of {} processes left unhashed (hashing limit of {})", hashes_skipped, max_hashed.unwrap_or_default())));
+    }
+    
+    if details_access_denied > 0 {
+        logs.push(LogEntry::warn(&format!("Token/timing details unavailable for {} processes (access denied)", details_access_denied)));
+    }
+    
+    if deleted_executables > 0 {
+        logs.push(LogEntry::warn(&format!("{} processes are running from deleted executables", deleted_executables)));
+    }
+    
+    if privileged_tokens > 0 {
+        logs.push(LogEntry::warn(&format!("{} processes hold anomalous privileged or impersonation tokens", privileged_tokens)));
+    }
+    
     // Sort processes by PID for consistent output
-    processes.sort_by(|a, b| a.pid.cmp(&b.pid));
+    processes.sort_by_key(|process| process.pid);
     
     logs.push(LogEntry::info("Process enumeration completed"));
     (processes, logs)
 }
 
+/// Quick process snapshot (name, command line, path) for time-bounded checks.
+/// Executables are hashed only when a deadline is given, once per file, and
+/// processes still unhashed when it passes keep "N/A".
+pub fn collect_process_snapshot(hash_deadline: Option<Instant>) -> (Vec<Process>, Vec<LogEntry>) {
+    let mut logs = vec![LogEntry::info("Starting quick process snapshot")];
+    
+    let mut sys = System::new();
+    sys.refresh_processes();
+    
+    let mut processes: Vec<Process> = sys.processes()
+        .iter()
+        .filter_map(|(pid, process)| collect_single_process(*pid, process).ok())
+        .collect();
+    processes.sort_by_key(|process| process.pid);
+    
+    if let Some(deadline) = hash_deadline {
+        let mut hashes: HashMap<String, String> = HashMap::new();
+        let mut unhashed = 0;
+        for process in processes.iter_mut() {
+            if !process.has_executable_path() {
+                process.sha256_hash = "N/A".to_string();
+                continue;
+            }
+            if let Some(hash) = hashes.get(&process.executable_path) {
+                process.sha256_hash = hash.clone();
+                continue;
+            }
+            if Instant::now() >= deadline {
+                process.sha256_hash = "N/A".to_string();
+                unhashed += 1;
+                continue;
+            }
+            let hash = calculate_file_hash(&process.executable_path).unwrap_or_else(|_| "ERROR".to_string());
+            hashes.insert(process.executable_path.clone(), hash.clone());
+            process.sha256_hash = hash;
+        }
+        if unhashed > 0 {
+            logs.push(LogEntry::warn(&format!("Hashing deadline reached: {} processes left unhashed", unhashed)));
+        }
+    }
+    
+    logs.push(LogEntry::info(&format!("Snapshot of {} processes completed", processes.len())));
+    (processes, logs)
+}
+
 /// Collect information about a single process
 fn collect_single_process(pid: Pid, process: &sysinfo::Process) -> std::result::Result<Process, String> {
     let pid_u32 = pid.as_u32();
@@ -89,7 +240,7 @@ fn collect_single_process(pid: Pid, process: &sysinfo::Process) -> std::result::
     // Get memory usage in MB
     let memory_usage_mb = process.memory() as f64 / 1024.0 / 1024.0;
     
-    Ok(Process::new_with_user_memory(
+    let mut proc_info = Process::new_with_user_memory(
         pid_u32,
         parent_pid,
         name,
@@ -97,11 +248,169 @@ fn collect_single_process(pid: Pid, process: &sysinfo::Process) -> std::result::
         executable_path,
         user,
         memory_usage_mb,
-    ))
+    );
+    
+    // Start time from sysinfo (seconds since the epoch); refined from the process handle on Windows
+    if process.start_time() > 0 {
+        proc_info.creation_time = chrono::DateTime::from_timestamp(process.start_time() as i64, 0)
+            .map(|datetime| datetime.to_rfc3339());
+    }
+    proc_info.session_id = process.session_id().map(|sid| sid.as_u32());
+    
+    Ok(proc_info)
+}
+
+/// Query creation time, session ID, token integrity level and token contents of a process
+#[cfg(windows)]
+fn query_security_details(pid: u32) -> std::result::Result<ProcessSecurityDetails, String> {
+    if pid == 0 {
+        return Err("System Idle Process cannot be opened".to_string());
+    }
+
+    let mut details = ProcessSecurityDetails::default();
+
+    unsafe {
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid)
+            .map_err(|e| format!("OpenProcess failed: {}", e))?;
+
+        let mut creation = FILETIME::default();
+        let mut exit = FILETIME::default();
+        let mut kernel = FILETIME::default();
+        let mut user = FILETIME::default();
+        if GetProcessTimes(process, &mut creation, &mut exit, &mut kernel, &mut user).is_ok() {
+            let filetime = ((creation.dwHighDateTime as u64) << 32) | creation.dwLowDateTime as u64;
+            details.creation_time = filetime_to_datetime(filetime).map(|time| time.to_rfc3339());
+        }
+
+        let mut token = HANDLE::default();
+        if OpenProcessToken(process, TOKEN_QUERY, &mut token).is_ok() {
+            details.session_id = query_token_session_id(token);
+            details.integrity_level = query_token_integrity_level(token);
+            details.token = Some(process_tokens::query_token(token));
+            let _ = CloseHandle(token);
+        }
+
+        let _ = CloseHandle(process);
+    }
+
+    Ok(details)
+}
+
+#[cfg(not(windows))]
+fn query_security_details(_pid: u32) -> std::result::Result<ProcessSecurityDetails, String> {
+    // Token information is Windows-only; keep the sysinfo values
+    Ok(ProcessSecurityDetails::default())
+}
+
+/// Win32 path of the image file object the process was started from
+#[cfg(windows)]
+fn query_image_path(pid: u32) -> Option<String> {
+    if pid == 0 {
+        return None;
+    }
+
+    unsafe {
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid).ok()?;
+        let mut buffer = vec![0u16; 32768];
+        let mut size = buffer.len() as u32;
+        let result = QueryFullProcessImageNameW(process, PROCESS_NAME_WIN32, PWSTR(buffer.as_mut_ptr()), &mut size);
+        let _ = CloseHandle(process);
+        result.ok()?;
+        Some(String::from_utf16_lossy(&buffer[..size as usize]))
+    }
+}
+
+#[cfg(not(windows))]
+fn query_image_path(_pid: u32) -> Option<String> {
+    None
+}
+
+/// Whether the executable backing a process has been removed from disk. Linux
+/// marks the /proc exe link with " (deleted)"; elsewhere the file must be gone,
+/// access denied or a pending delete do not count.
+fn is_deleted_executable(path: &str) -> bool {
+    if path.is_empty() || path == "N/A" {
+        return false;
+    }
+    if path.ends_with(" (deleted)") {
+        return true;
+    }
+    matches!(fs::metadata(path), Err(e) if e.kind() == std::io::ErrorKind::NotFound)
+}
+
+#[cfg(windows)]
+unsafe fn query_token_session_id(token: HANDLE) -> Option<u32> {
+    let mut session_id: u32 = 0;
+    let mut returned: u32 = 0;
+    GetTokenInformation(
+        token,
+        TokenSessionId,
+        Some(&mut session_id as *mut u32 as *mut _),
+        std::mem::size_of::<u32>() as u32,
+        &mut returned,
+    )
+    .ok()
+    .map(|_| session_id)
+}
+
+#[cfg(windows)]
+unsafe fn query_token_integrity_level(token: HANDLE) -> Option<String> {
+    let mut required: u32 = 0;
+    let _ = GetTokenInformation(token, TokenIntegrityLevel, None, 0, &mut required);
+    if required == 0 {
+        return None;
+    }
+
+    // u64 buffer keeps TOKEN_MANDATORY_LABEL aligned
+    let mut buffer = vec![0u64; (required as usize + 7) / 8];
+    GetTokenInformation(
+        token,
+        TokenIntegrityLevel,
+        Some(buffer.as_mut_ptr() as *mut _),
+        required,
+        &mut required,
+    )
+    .ok()?;
+
+    let label = &*(buffer.as_ptr() as *const TOKEN_MANDATORY_LABEL);
+    let count = *GetSidSubAuthorityCount(label.Label.Sid);
+    if count == 0 {
+        return None;
+    }
+    let rid = *GetSidSubAuthority(label.Label.Sid, (count - 1) as u32);
+    Some(integrity_level_name(rid))
+}
+
+/// Map a mandatory label RID (last sub-authority of S-1-16-*) to its name
+#[cfg_attr(not(windows), allow(dead_code))]
+fn integrity_level_name(rid: u32) -> String {
+    match rid {
+        0x0000 => "Untrusted".to_string(),
+        0x1000 => "Low".to_string(),
+        0x2000 => "Medium".to_string(),
+        0x2100 => "MediumPlus".to_string(),
+        0x3000 => "High".to_string(),
+        0x4000 => "System".to_string(),
+        0x5000 => "Protected".to_string(),
+        other => format!("Unknown (0x{:X})", other),
+    }
 }
 
+#[cfg_attr(not(windows), allow(dead_code))]
 /// Calculate SHA-256 hash of a file
-fn calculate_file_hash(file_path: &str) -> std::result::Result<String, String> {
+pub(crate) fn calculate_file_hash(file_path: &str) -> std::result::Result<String, String> {
+    let path = existing_file(file_path)?;
+    hashing::shared().hash_file(path).map(|hashes| hashes.sha256.unwrap_or_default())
+}
+
+/// Digests of a process executable from the shared hashing service, computed once
+/// per unique file; includes the ssdeep fuzzy hash with --fuzzy-hash
+pub(crate) fn hash_executable(file_path: &str) -> std::result::Result<FileHashes, String> {
+    let path = existing_file(file_path)?;
+    hashing::shared().hash_file_fuzzy(path)
+}
+
+fn existing_file(file_path: &str) -> std::result::Result<&Path, String> {
     if file_path == "N/A" || file_path.is_empty() {
         return Err("Invalid file path".to_string());
     }
@@ -110,17 +419,7 @@ fn calculate_file_hash(file_path: &str) -> std::result::Result<String, String> {
     if !path.exists() {
         return Err("File does not exist".to_string());
     }
-    
-    // Read file contents
-    let file_contents = fs::read(path)
-        .map_err(|e| format!("Failed to read file: {}", e))?;
-    
-    // Calculate SHA-256 hash
-    let mut hasher = Sha256::new();
-    hasher.update(&file_contents);
-    let result = hasher.finalize();
-    
-    Ok(hex::encode(result))
+    Ok(path)
 }
 
 /// Get process tree information (parent-child relationships)
@@ -194,6 +493,14 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_integrity_level_name() {
+        assert_eq!(integrity_level_name(0x2000), "Medium");
+        assert_eq!(integrity_level_name(0x3000), "High");
+        assert_eq!(integrity_level_name(0x4000), "System");
+        assert_eq!(integrity_level_name(0x1234), "Unknown (0x1234)");
+    }
+
     #[test]
     fn test_calculate_file_hash() {
         // Create a temporary file for testing
@@ -298,4 +605,19 @@ mod tests {
         );
         assert!(!user_module.is_system_module());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_is_deleted_executable() {
+        let dir = tempdir().unwrap();
+        let exe = dir.path().join("payload.exe");
+        File::create(&exe).unwrap().write_all(b"MZ").unwrap();
+        let path = exe.to_string_lossy().to_string();
+        assert!(!is_deleted_executable(&path));
+        
+        fs::remove_file(&exe).unwrap();
+        assert!(is_deleted_executable(&path));
+        assert!(is_deleted_executable("/tmp/payload (deleted)"));
+        assert!(!is_deleted_executable("N/A"));
+        assert!(!is_deleted_executable(""));
+    }
+}
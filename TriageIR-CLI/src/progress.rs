@@ -0,0 +1,109 @@
+//! Machine-readable progress
+//! `--progress json` reports a scan as newline-delimited JSON events for the GUI
+//! and other front ends, on stderr or, with `--progress-to`, on a named pipe
+//! (`\\.\pipe\NAME`, created by the reader) or file. Lines on stderr that do not
+//! start with `{` are the usual human-readable messages.
+//!
+//!   {"event": "start", "version": "...", "offline": false}
+//!   {"event": "progress", "phase": "processes", "stage": 2, "stages": 17, "percent": 5,
+//!    "message": "...", "artifacts": 312, "errors": 0, "warnings": 1}
+//!   {"event": "log", "level": "ERROR", "message": "..."}     every warning and error
+//!   {"event": "complete", "status": "completed_with_errors", "artifacts": 4512,
+//!    "errors": 2, "warnings": 7, "output": "...", "duration_ms": 48213}
+//!
+//! Phases are stable identifiers (setup, system_info, processes, ..., complete);
+//! messages keep the wording of --verbose. Write errors are ignored, so a reader
+//! that goes away does not stop the collection.
+
+use crate::scan::ScanProgress;
+use crate::types::LogEntry;
+use serde_json::{json, Value};
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::sync::Mutex;
+
+/// Accepted values of --progress
+pub const PROGRESS_FORMATS: [&str; 2] = ["text", "json"];
+
+pub struct ProgressChannel {
+    output: Mutex<Box<dyn Write + Send>>,
+}
+
+impl ProgressChannel {
+    /// Events on stderr, or on the named pipe or file at `target`
+    pub fn open(target: Option<&str>) -> Result<Self, String> {
+        let output: Box<dyn Write + Send> = match target {
+            Some(path) => Box::new(
+                OpenOptions::new()
+                    .write(true)
+                    .create(true)
+                    .truncate(true)
+                    .open(path)
+                    .map_err(|e| format!("Failed to open the progress channel {}: {}", path, e))?,
+            ),
+            None => Box::new(io::stderr()),
+        };
+        Ok(ProgressChannel { output: Mutex::new(output) })
+    }
+
+    /// One event line, flushed so the reader sees it immediately
+    pub fn emit(&self, event: &Value) {
+        if let Ok(mut output) = self.output.lock() {
+            let _ = writeln!(output, "{}", event).and_then(|_| output.flush());
+        }
+    }
+}
+
+pub fn progress_event(update: &ScanProgress) -> Value {
+    json!({
+        "event": "progress",
+        "phase": update.phase,
+        "stage": update.stage,
+        "stages": update.stages,
+        "percent": update.percent,
+        "message": update.message.trim(),
+        "artifacts": update.artifacts,
+        "errors": update.errors,
+        "warnings": update.warnings,
+    })
+}
+
+/// Log event of a warning or error; informational entries are not forwarded
+pub fn log_event(entry: &LogEntry) -> Option<Value> {
+    matches!(entry.level.as_str(), "WARN" | "ERROR").then(|| json!({ "event": "log", "level": entry.level, "message": entry.message }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress_events() {
+        let update = ScanProgress {
+            phase: "processes",
+            stage: 2,
+            stages: 17,
+            percent: 5,
+            message: "✓ Process enumeration completed (12 processes, 0 flagged)\n".to_string(),
+            artifacts: 12,
+            errors: 0,
+            warnings: 1,
+        };
+        let event = progress_event(&update);
+        assert_eq!(event["phase"], "processes");
+        assert_eq!(event["artifacts"], 12);
+        assert_eq!(event["message"], "✓ Process enumeration completed (12 processes, 0 flagged)");
+
+        assert!(log_event(&LogEntry::new("INFO", "started")).is_none());
+        assert_eq!(log_event(&LogEntry::new("ERROR", "access denied")).unwrap()["level"], "ERROR");
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("progress.jsonl");
+        let channel = ProgressChannel::open(Some(path.to_str().unwrap())).unwrap();
+        channel.emit(&event);
+        channel.emit(&json!({ "event": "complete" }));
+        let lines: Vec<Value> = std::fs::read_to_string(&path).unwrap().lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[1]["event"], "complete");
+    }
+}
@@ -0,0 +1,73 @@
+//! Python bindings (`--features python`, built with maturin)
+//! Exposes scan loading, timeline building, IOC extraction/matching and scan
+//! diffing to notebooks. Scans can be passed as a path to a saved scan or as
+//! the dict returned by `load_scan`; results come back as plain dicts and lists.
+
+// The #[pyfunction] expansion of pyo3 0.22 converts PyErr into itself
+#![allow(clippy::useless_conversion)]
+
+use crate::{ioc_export, ioc_match, scan_diff, timeline};
+use pyo3::exceptions::{PyIOError, PyValueError};
+use pyo3::prelude::*;
+use serde::Serialize;
+use serde_json::Value;
+use std::path::PathBuf;
+
+/// Load a scan result written with `--output`
+#[pyfunction]
+fn load_scan(py: Python<'_>, path: PathBuf) -> PyResult<PyObject> {
+    let scan = ioc_export::load_scan_results(&path).map_err(PyIOError::new_err)?;
+    to_python(py, &scan)
+}
+
+/// Chronological list of the time-stamped artifacts in a scan
+#[pyfunction]
+fn build_timeline(py: Python<'_>, scan: &Bound<'_, PyAny>) -> PyResult<PyObject> {
+    to_python(py, &timeline::build_timeline(&scan_argument(scan)?))
+}
+
+/// Indicators (hashes, paths, IPs, ...) from the flagged findings of a scan
+#[pyfunction]
+fn extract_iocs(py: Python<'_>, scan: &Bound<'_, PyAny>) -> PyResult<PyObject> {
+    to_python(py, &ioc_export::extract_indicators(&scan_argument(scan)?))
+}
+
+/// Occurrences of known indicators anywhere in the scan artifacts
+#[pyfunction]
+fn match_iocs(py: Python<'_>, scan: &Bound<'_, PyAny>, indicators: Vec<String>) -> PyResult<PyObject> {
+    to_python(py, &ioc_match::match_indicators(&scan_argument(scan)?, &indicators))
+}
+
+/// Added, removed and changed artifacts between a baseline and a later scan
+#[pyfunction]
+fn diff_scans(py: Python<'_>, baseline: &Bound<'_, PyAny>, current: &Bound<'_, PyAny>) -> PyResult<PyObject> {
+    to_python(py, &scan_diff::diff_scans(&scan_argument(baseline)?, &scan_argument(current)?))
+}
+
+#[pymodule]
+fn triageir_cli(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(load_scan, m)?)?;
+    m.add_function(wrap_pyfunction!(build_timeline, m)?)?;
+    m.add_function(wrap_pyfunction!(extract_iocs, m)?)?;
+    m.add_function(wrap_pyfunction!(match_iocs, m)?)?;
+    m.add_function(wrap_pyfunction!(diff_scans, m)?)?;
+    Ok(())
+}
+
+/// Accept either a path to a saved scan or an already loaded scan dict
+fn scan_argument(scan: &Bound<'_, PyAny>) -> PyResult<Value> {
+    if let Ok(path) = scan.extract::<PathBuf>() {
+        return ioc_export::load_scan_results(&path).map_err(PyIOError::new_err);
+    }
+
+    let json = scan.py().import_bound("json")?;
+    let text: String = json.call_method1("dumps", (scan,))?.extract()?;
+    serde_json::from_str(&text).map_err(|e| PyValueError::new_err(format!("Invalid scan object: {}", e)))
+}
+
+/// Round-trip through the json module so results are native dicts and lists
+fn to_python<T: Serialize>(py: Python<'_>, value: &T) -> PyResult<PyObject> {
+    let text = serde_json::to_string(value).map_err(|e| PyValueError::new_err(e.to_string()))?;
+    let json = py.import_bound("json")?;
+    Ok(json.call_method1("loads", (text,))?.unbind())
+}
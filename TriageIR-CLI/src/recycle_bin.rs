@@ -0,0 +1,145 @@
+//! Recycle Bin metadata (`collector-recycle-bin` feature)
+//! A compiled-in custom collector: lists the `$I` metadata files under
+//! `$Recycle.Bin\<SID>` of the system volume, each naming the original path,
+//! size and deletion time of a file whose content is kept in the matching
+//! `$R` file. Version 1 (Vista to 8.1) has a fixed 260-character path,
+//! version 2 (Windows 10 and later) a length-prefixed one.
+
+use crate::binutil::filetime_to_datetime;
+use crate::custom_collectors::{Collector, CollectorContext, CollectorOutput};
+use crate::footprint;
+use crate::forensic_types::AuditEntry;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+const COMPONENT: &str = "custom:recycle_bin";
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RecycledFile {
+    pub sid: String,
+    pub metadata_file: String, // $I file
+    pub original_path: String,
+    pub size: u64,
+    pub deleted: Option<String>,
+    pub content_present: bool, // The $R file with the content still exists
+}
+
+pub struct RecycleBinCollector;
+
+impl Collector for RecycleBinCollector {
+    type Artifact = RecycledFile;
+
+    fn name(&self) -> &str {
+        "recycle_bin"
+    }
+
+    fn description(&self) -> &str {
+        "Original paths, sizes and deletion times of the files in the Recycle Bin"
+    }
+
+    fn run(&self, context: &CollectorContext) -> Result<CollectorOutput<RecycledFile>, String> {
+        let volume = match &context.offline_root {
+            Some(root) => PathBuf::from(root),
+            None => PathBuf::from(format!("{}\\", std::env::var("SystemDrive").unwrap_or_else(|_| "C:".to_string()))),
+        };
+        let recycle_bin = volume.join("$Recycle.Bin");
+        let mut artifacts = Vec::new();
+        let mut audit = Vec::new();
+
+        let users = footprint::read_dir(&recycle_bin).map_err(|e| format!("Cannot read {}: {}", recycle_bin.display(), e))?;
+        for user in users.flatten().filter(|entry| entry.path().is_dir()) {
+            let sid = user.file_name().to_string_lossy().to_string();
+            let Ok(entries) = footprint::read_dir(user.path()) else {
+                audit.push(AuditEntry::new(COMPONENT, "WARN", "parse_metadata", format!("Cannot list the Recycle Bin of {}", sid), "skipped"));
+                continue;
+            };
+            for path in entries.flatten().map(|entry| entry.path()).filter(|path| is_metadata_file(path)) {
+                match footprint::read(&path).map_err(|e| e.to_string()).and_then(|data| parse_metadata(&data)) {
+                    Ok((original_path, size, deleted)) => artifacts.push(RecycledFile {
+                        sid: sid.clone(),
+                        metadata_file: path.display().to_string(),
+                        original_path,
+                        size,
+                        deleted,
+                        content_present: content_path(&path).exists(),
+                    }),
+                    Err(e) => audit.push(AuditEntry::new(COMPONENT, "WARN", "parse_metadata", format!("{}: {}", path.display(), e), "skipped")),
+                }
+            }
+        }
+        Ok(CollectorOutput { artifacts, audit })
+    }
+}
+
+fn is_metadata_file(path: &Path) -> bool {
+    path.file_name().and_then(|name| name.to_str()).is_some_and(|name| name.starts_with("$I") && name.len() > 2)
+}
+
+/// `$R` file holding the content of the `$I` file at `path`
+fn content_path(path: &Path) -> PathBuf {
+    let name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    path.with_file_name(format!("$R{}", &name[2..]))
+}
+
+/// (original path, size, deletion time) of a `$I` file
+fn parse_metadata(data: &[u8]) -> Result<(String, u64, Option<String>), String> {
+    let field = |offset: usize| data.get(offset..offset + 8).map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()));
+    let (Some(version), Some(size), Some(deleted)) = (field(0), field(8), field(16)) else {
+        return Err("truncated $I file".to_string());
+    };
+    let name = match version {
+        1 => data.get(24..24 + 520),
+        2 => data.get(24..28)
+            .map(|length| u32::from_le_bytes(length.try_into().unwrap()) as usize * 2)
+            .and_then(|length| data.get(28..28 + length)),
+        _ => return Err(format!("unknown $I version {}", version)),
+    }.ok_or("truncated $I file")?;
+
+    let units: Vec<u16> = name.chunks_exact(2).map(|pair| u16::from_le_bytes([pair[0], pair[1]])).take_while(|&unit| unit != 0).collect();
+    Ok((String::from_utf16_lossy(&units), size, filetime_to_datetime(deleted).map(|time| time.to_rfc3339())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(version: u64, path: &str) -> Vec<u8> {
+        let units: Vec<u16> = path.encode_utf16().chain([0]).collect();
+        let mut data = [version, 4096, 133_000_000_000_000_000].iter().flat_map(|value| value.to_le_bytes()).collect::<Vec<_>>();
+        if version == 2 {
+            data.extend((units.len() as u32).to_le_bytes());
+        }
+        data.extend(units.iter().flat_map(|unit| unit.to_le_bytes()));
+        if version == 1 {
+            data.resize(24 + 520, 0);
+        }
+        data
+    }
+
+    #[test]
+    fn test_parse_metadata() {
+        let (path, size, deleted) = parse_metadata(&metadata(2, "C:\\Users\\alice\\Desktop\\payroll.xlsx")).unwrap();
+        assert_eq!((path.as_str(), size), ("C:\\Users\\alice\\Desktop\\payroll.xlsx", 4096));
+        assert_eq!(deleted.as_deref(), Some("2022-06-18T04:26:40+00:00"));
+        assert_eq!(parse_metadata(&metadata(1, "D:\\old.txt")).unwrap().0, "D:\\old.txt");
+        assert!(parse_metadata(&metadata(2, "C:\\x")[..30]).is_err());
+        assert!(parse_metadata(&metadata(3, "C:\\x")).is_err());
+    }
+
+    #[test]
+    fn test_collect_offline_volume() {
+        let volume = tempfile::tempdir().unwrap();
+        let user = volume.path().join("$Recycle.Bin").join("S-1-5-21-1-1001");
+        std::fs::create_dir_all(&user).unwrap();
+        std::fs::write(user.join("$IAB12CD.xlsx"), metadata(2, "C:\\payroll.xlsx")).unwrap();
+        std::fs::write(user.join("$RAB12CD.xlsx"), b"content").unwrap();
+        std::fs::write(user.join("$IBROKEN.txt"), b"short").unwrap();
+
+        let context = CollectorContext { offline_root: Some(volume.path().display().to_string()), ..CollectorContext::default() };
+        let output = RecycleBinCollector.run(&context).unwrap();
+        assert_eq!(output.artifacts.len(), 1);
+        assert_eq!(output.artifacts[0].sid, "S-1-5-21-1-1001");
+        assert!(output.artifacts[0].content_present);
+        assert_eq!(output.audit.len(), 1);
+    }
+}
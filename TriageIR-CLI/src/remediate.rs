@@ -0,0 +1,370 @@
+//! Guarded remediation mode (`remediate --plan`)
+//! Executes an analyst-approved plan of containment steps (delete a Run key
+//! value, disable a scheduled task, stop or disable a service) on the live host.
+//! The whole plan is validated before the first step runs, the state of every
+//! target is captured before and after its step, and nothing is changed unless
+//! `--confirm` is given.
+
+// sc.exe/schtasks.exe output parsers are only reachable through the Windows implementation
+#![cfg_attr(not(windows), allow(dead_code))]
+
+use crate::forensic_types::{AuditEntry, ExternalCommand, RemediationAction, RemediationOutcome, RemediationPlan};
+use crate::logger;
+use std::fs;
+use std::path::Path;
+
+const COMPONENT: &str = "remediate";
+
+/// Actions `remediate` can execute; the remaining suggestions stay manual
+pub const SUPPORTED_ACTIONS: [&str; 4] = ["delete_registry_value", "disable_scheduled_task", "stop_service", "disable_service"];
+
+/// Read a plan written with `remediation-script --plan` (and reviewed by an analyst)
+pub fn load_plan(path: &Path) -> Result<RemediationPlan, String> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read remediation plan {}: {}", path.display(), e))?;
+    serde_json::from_str(&text).map_err(|e| format!("Invalid remediation plan {}: {}", path.display(), e))
+}
+
+/// Reject the plan as a whole if it targets another host or contains a step that cannot run
+pub fn validate_plan(plan: &RemediationPlan, local_hostname: &str) -> Result<(), String> {
+    if !plan.hostname.is_empty() && !plan.hostname.eq_ignore_ascii_case(local_hostname) {
+        return Err(format!("Plan was written for host {} but this is {}", plan.hostname, local_hostname));
+    }
+    if plan.actions.is_empty() {
+        return Err("Plan contains no actions".to_string());
+    }
+
+    for (index, step) in plan.actions.iter().enumerate() {
+        let problem = if !SUPPORTED_ACTIONS.contains(&step.action.as_str()) {
+            Some(format!("unsupported action {} (supported: {})", step.action, SUPPORTED_ACTIONS.join(", ")))
+        } else if step.target.trim().is_empty() || step.target.chars().any(char::is_control) {
+            Some("empty or malformed target".to_string())
+        } else if step.action == "delete_registry_value" && (step.value_name.is_empty() || split_registry_key(&step.target).is_none()) {
+            Some("registry steps need a key below HKLM, HKCU, HKU or HKCR and a value_name".to_string())
+        } else if step.action.ends_with("_service") && step.target.contains(['\\', '/']) {
+            Some("service names cannot contain path separators".to_string())
+        } else {
+            None
+        };
+        if let Some(problem) = problem {
+            return Err(format!("Step {} ({} {}): {}", index + 1, step.action, step.target, problem));
+        }
+    }
+    Ok(())
+}
+
+/// Run the plan. Without `confirm` only the current state of every target is recorded.
+pub fn execute_plan(plan: &RemediationPlan, confirm: bool) -> (Vec<RemediationOutcome>, Vec<AuditEntry>) {
+    let start_time = std::time::Instant::now();
+    let mode = if confirm { "confirmed" } else { "dry run" };
+    let mut outcomes = Vec::new();
+    let mut audit_log = vec![AuditEntry::new(
+        COMPONENT,
+        "INFO",
+        "start_remediation",
+        format!("Executing remediation plan for {} (scan {}, {} steps, {})", plan.hostname, plan.scan_id, plan.actions.len(), mode),
+        "started",
+    )];
+
+    for step in &plan.actions {
+        let before_state = capture_state(step);
+        let (status, message, after_state) = if confirm {
+            match apply_step(step) {
+                Ok(message) => ("applied", message, capture_state(step)),
+                Err(message) => ("failed", message, capture_state(step)),
+            }
+        } else {
+            ("planned", "Not executed (run with --confirm to apply)".to_string(), before_state.clone())
+        };
+
+        audit_log.push(AuditEntry::new(
+            COMPONENT,
+            if status == "failed" { "ERROR" } else { "INFO" },
+            &step.action,
+            format!("{} {} {}: {} (before: {}; after: {})", step.action, step.target, step.value_name, message, before_state, after_state),
+            status,
+        ));
+        audit_log.extend(logger::take_external_commands().iter().map(command_audit_entry));
+        outcomes.push(RemediationOutcome {
+            action: step.clone(),
+            status: status.to_string(),
+            before_state,
+            after_state,
+            message,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+        });
+    }
+
+    let failed = outcomes.iter().filter(|o| o.status == "failed").count();
+    let mut complete = AuditEntry::new(
+        COMPONENT,
+        "INFO",
+        "complete_remediation",
+        format!("Processed {} steps ({}), {} failed", outcomes.len(), mode, failed),
+        if failed == 0 { "success" } else { "partial" },
+    );
+    complete.duration_ms = Some(start_time.elapsed().as_millis() as u64);
+    audit_log.push(complete);
+
+    (outcomes, audit_log)
+}
+
+#[cfg(windows)]
+fn capture_state(step: &RemediationAction) -> String {
+    let state = match step.action.as_str() {
+        "delete_registry_value" => windows_api::registry_value_state(&step.target, &step.value_name),
+        "disable_scheduled_task" => windows_api::task_state(&step.target),
+        _ => windows_api::service_state(&step.target),
+    };
+    state.unwrap_or_else(|e| format!("unavailable ({})", e))
+}
+
+#[cfg(windows)]
+fn apply_step(step: &RemediationAction) -> Result<String, String> {
+    match step.action.as_str() {
+        "delete_registry_value" => windows_api::delete_registry_value(&step.target, &step.value_name),
+        "disable_scheduled_task" => windows_api::disable_task(&step.target),
+        "stop_service" => windows_api::stop_service(&step.target),
+        "disable_service" => windows_api::disable_service(&step.target),
+        other => Err(format!("Unsupported action {}", other)),
+    }
+}
+
+#[cfg(not(windows))]
+fn capture_state(_step: &RemediationAction) -> String {
+    "unavailable (requires Windows)".to_string()
+}
+
+#[cfg(not(windows))]
+fn apply_step(_step: &RemediationAction) -> Result<String, String> {
+    Err("Remediation requires Windows".to_string())
+}
+
+/// `HKLM\SOFTWARE\...` or `HKEY_LOCAL_MACHINE\SOFTWARE\...` -> (`HKLM`, `SOFTWARE\...`)
+pub(crate) fn split_registry_key(key: &str) -> Option<(&'static str, &str)> {
+    let (hive, path) = key.split_once('\\')?;
+    let hive = match hive.to_uppercase().as_str() {
+        "HKLM" | "HKEY_LOCAL_MACHINE" => "HKLM",
+        "HKCU" | "HKEY_CURRENT_USER" => "HKCU",
+        "HKU" | "HKEY_USERS" => "HKU",
+        "HKCR" | "HKEY_CLASSES_ROOT" => "HKCR",
+        _ => return None,
+    };
+    (!path.is_empty()).then_some((hive, path))
+}
+
+/// Symbolic value of a `sc.exe` field, e.g. `STATE : 4  RUNNING` -> `RUNNING`
+fn sc_field(output: &str, field: &str) -> Option<String> {
+    output.lines().find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        (name.trim() == field).then(|| {
+            let mut parts = value.split_whitespace();
+            let first = parts.next().unwrap_or_default();
+            parts.next().unwrap_or(first).to_string()
+        })
+    })
+}
+
+/// `Status` line of `schtasks /Query /FO LIST`
+fn schtasks_status(output: &str) -> Option<String> {
+    output.lines().find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        (name.trim() == "Status").then(|| value.trim().to_string())
+    })
+}
+
+/// `\Folder\Task` with a leading backslash, as schtasks expects
+fn task_path(target: &str) -> String {
+    if target.starts_with('\\') {
+        target.to_string()
+    } else {
+        format!("\\{}", target)
+    }
+}
+
+/// sc.exe and schtasks.exe runs of a step, with their exit code and output
+fn command_audit_entry(command: &ExternalCommand) -> AuditEntry {
+    let status = match (command.exit_code, &command.error) {
+        (_, Some(error)) => format!("not started: {}", error),
+        (Some(code), None) => format!("exit code {}", code),
+        (None, None) => "terminated".to_string(),
+    };
+    let output = if command.stderr.trim().is_empty() { command.stdout.trim() } else { command.stderr.trim() };
+    let mut entry = AuditEntry::new(
+        COMPONENT,
+        if command.exit_code == Some(0) { "INFO" } else { "WARN" },
+        "external_command",
+        format!("{} ({}): {}", logger::command_line(command), status, output.replace(['\r', '\n'], " ")),
+        if command.exit_code == Some(0) { "success" } else { "failed" },
+    );
+    entry.timestamp = command.timestamp.clone();
+    entry.duration_ms = Some(command.duration_ms);
+    entry
+}
+
+#[cfg(windows)]
+mod windows_api {
+    use super::{sc_field, schtasks_status, split_registry_key, task_path};
+    use crate::logger::run_command;
+    use std::io::ErrorKind;
+    use std::process::Command;
+    use std::time::{Duration, Instant};
+    use winreg::enums::{HKEY_CLASSES_ROOT, HKEY_CURRENT_USER, HKEY_LOCAL_MACHINE, HKEY_USERS, KEY_READ, KEY_SET_VALUE};
+    use winreg::RegKey;
+
+    const SERVICE_STOP_TIMEOUT: Duration = Duration::from_secs(30);
+
+    fn open_key(key: &str, access: u32) -> Result<Option<RegKey>, String> {
+        let (hive, path) = split_registry_key(key).ok_or_else(|| format!("Unsupported registry key {}", key))?;
+        let root = RegKey::predef(match hive {
+            "HKLM" => HKEY_LOCAL_MACHINE,
+            "HKCU" => HKEY_CURRENT_USER,
+            "HKU" => HKEY_USERS,
+            _ => HKEY_CLASSES_ROOT,
+        });
+        match root.open_subkey_with_flags(path, access) {
+            Ok(key) => Ok(Some(key)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    pub fn registry_value_state(key: &str, value_name: &str) -> Result<String, String> {
+        let Some(key) = open_key(key, KEY_READ)? else {
+            return Ok("key absent".to_string());
+        };
+        match key.get_raw_value(value_name) {
+            Ok(value) => Ok(format!("present {:?}", value)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok("absent".to_string()),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    pub fn delete_registry_value(key: &str, value_name: &str) -> Result<String, String> {
+        let Some(key) = open_key(key, KEY_SET_VALUE)? else {
+            return Ok("Key already absent".to_string());
+        };
+        match key.delete_value(value_name) {
+            Ok(()) => Ok(format!("Deleted value {}", value_name)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok("Value already absent".to_string()),
+            Err(e) => Err(format!("Failed to delete value {}: {}", value_name, e)),
+        }
+    }
+
+    pub fn task_state(target: &str) -> Result<String, String> {
+        let output = run("schtasks", &["/Query", "/TN", &task_path(target), "/FO", "LIST"])?;
+        Ok(schtasks_status(&output).unwrap_or_else(|| "unknown".to_string()))
+    }
+
+    pub fn disable_task(target: &str) -> Result<String, String> {
+        run("schtasks", &["/Change", "/TN", &task_path(target), "/DISABLE"])?;
+        Ok(format!("Disabled task {}", task_path(target)))
+    }
+
+    pub fn service_state(name: &str) -> Result<String, String> {
+        let state = sc_field(&run("sc", &["query", name])?, "STATE").unwrap_or_else(|| "unknown".to_string());
+        let start_type = sc_field(&run("sc", &["qc", name])?, "START_TYPE").unwrap_or_else(|| "unknown".to_string());
+        Ok(format!("{}, start type {}", state, start_type))
+    }
+
+    pub fn stop_service(name: &str) -> Result<String, String> {
+        let current = || run("sc", &["query", name]).map(|output| sc_field(&output, "STATE").unwrap_or_default());
+        if current()? == "STOPPED" {
+            return Ok("Service already stopped".to_string());
+        }
+        run("sc", &["stop", name])?;
+
+        let started = Instant::now();
+        while started.elapsed() < SERVICE_STOP_TIMEOUT {
+            if current()? == "STOPPED" {
+                return Ok(format!("Stopped service {}", name));
+            }
+            std::thread::sleep(Duration::from_millis(500));
+        }
+        Err(format!("Service {} did not stop within {} seconds", name, SERVICE_STOP_TIMEOUT.as_secs()))
+    }
+
+    pub fn disable_service(name: &str) -> Result<String, String> {
+        run("sc", &["config", name, "start=", "disabled"])?;
+        Ok(format!("Set service {} start type to disabled", name))
+    }
+
+    /// Run a built-in Windows tool; a non-zero exit code is an error carrying its output
+    fn run(program: &str, args: &[&str]) -> Result<String, String> {
+        let output = run_command("remediate", Command::new(program).args(args))
+            .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+        if output.status.success() {
+            Ok(stdout)
+        } else {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let detail = if stderr.trim().is_empty() { stdout.trim() } else { stderr.trim() };
+            Err(format!("{} {} failed ({}): {}", program, args.join(" "), output.status, detail.replace(['\r', '\n'], " ")))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(action: &str, target: &str, value_name: &str) -> RemediationAction {
+        RemediationAction {
+            action: action.to_string(),
+            target: target.to_string(),
+            value_name: value_name.to_string(),
+        }
+    }
+
+    fn plan(actions: Vec<RemediationAction>) -> RemediationPlan {
+        RemediationPlan {
+            hostname: "WS01".to_string(),
+            scan_id: "1234".to_string(),
+            actions,
+        }
+    }
+
+    #[test]
+    fn test_validate_plan() {
+        let run_key = step("delete_registry_value", "HKCU\\Software\\Microsoft\\Windows\\CurrentVersion\\Run", "Updater");
+        let valid = plan(vec![run_key.clone(), step("disable_scheduled_task", "\\SyncTask", ""), step("stop_service", "rk", "")]);
+        assert!(validate_plan(&valid, "ws01").is_ok());
+
+        let error = validate_plan(&valid, "WS02").unwrap_err();
+        assert!(error.contains("written for host WS01"));
+
+        let error = validate_plan(&plan(vec![run_key.clone(), step("quarantine_file", "C:\\Temp\\m.exe", "")]), "WS01").unwrap_err();
+        assert!(error.starts_with("Step 2 (quarantine_file"));
+
+        assert!(validate_plan(&plan(vec![step("delete_registry_value", "HKCU\\Software\\Run", "")]), "WS01").is_err());
+        assert!(validate_plan(&plan(vec![step("delete_registry_value", "HKXX\\Software\\Run", "a")]), "WS01").is_err());
+        assert!(validate_plan(&plan(vec![step("stop_service", "..\\rk", "")]), "WS01").is_err());
+        assert!(validate_plan(&plan(vec![step("disable_scheduled_task", "\\Sync\nTask", "")]), "WS01").is_err());
+        assert!(validate_plan(&plan(Vec::new()), "WS01").is_err());
+    }
+
+    #[test]
+    fn test_execute_plan_without_confirm() {
+        let (outcomes, audit_log) = execute_plan(&plan(vec![step("stop_service", "rk", "")]), false);
+        assert_eq!(outcomes.len(), 1);
+        assert_eq!(outcomes[0].status, "planned");
+        assert_eq!(outcomes[0].before_state, outcomes[0].after_state);
+        assert_eq!(audit_log.last().unwrap().action, "complete_remediation");
+        assert!(audit_log.iter().all(|entry| entry.component == "remediate"));
+    }
+
+    #[test]
+    fn test_output_parsers() {
+        let query = "SERVICE_NAME: Spooler\r\n        TYPE               : 110  WIN32_OWN_PROCESS  (interactive)\r\n        STATE              : 4  RUNNING\r\n                                (STOPPABLE, NOT_PAUSABLE, ACCEPTS_SHUTDOWN)\r\n";
+        assert_eq!(sc_field(query, "STATE").as_deref(), Some("RUNNING"));
+        assert_eq!(sc_field("        START_TYPE         : 2   AUTO_START  (DELAYED)", "START_TYPE").as_deref(), Some("AUTO_START"));
+        assert!(sc_field(query, "START_TYPE").is_none());
+
+        let list = "\r\nFolder: \\\r\nHostName:      WS01\r\nTaskName:      \\SyncTask\r\nNext Run Time: N/A\r\nStatus:        Disabled\r\n";
+        assert_eq!(schtasks_status(list).as_deref(), Some("Disabled"));
+
+        assert_eq!(split_registry_key("HKEY_CURRENT_USER\\Software\\Run"), Some(("HKCU", "Software\\Run")));
+        assert_eq!(split_registry_key("HKLM"), None);
+        assert_eq!(task_path("Microsoft\\SyncTask"), "\\Microsoft\\SyncTask");
+    }
+}
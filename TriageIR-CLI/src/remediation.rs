@@ -0,0 +1,414 @@
+//! Remediation suggestions for high-severity findings
+//! Turns the flagged findings of a scan (suspicious persistence and hijacks, Defender
+//! detections, flagged driver installations and processes) into
+//! machine-readable containment steps, and renders them as a PowerShell script
+//! for analyst review. The collector never executes any of them; the script
+//! runs in -WhatIf mode unless started with -Apply.
+
+use crate::forensic_types::{Finding, RemediationAction, RemediationPlan};
+use crate::ioc_export::{command_executable, expand_hive, is_windows_binary};
+use crate::persistence;
+use crate::remediate;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::fmt::Write;
+
+/// Build the findings section of a scan, one entry per high-severity finding
+pub fn collect_findings(scan: &Value) -> Vec<Finding> {
+    let mut findings = FindingSet::default();
+    let artifacts = &scan["artifacts"];
+
+    for mechanism in array(&artifacts["persistence_mechanisms"]) {
+        if !mechanism["is_suspicious"].as_bool().unwrap_or(false) {
+            continue;
+        }
+        let mechanism_type = text(&mechanism["type"]);
+        let name = text(&mechanism["name"]);
+        let command = text(&mechanism["command"]);
+        let mut actions = Vec::new();
+
+        match mechanism_type {
+            "Registry Run Key" => actions.push(action("delete_registry_value", text(&mechanism["source"]), name)),
+            "Scheduled Task" => {
+                let task_path = text(&mechanism["source"]).trim_start_matches("Task Scheduler: ");
+                actions.push(action("disable_scheduled_task", task_path, ""));
+            }
+            "Windows Service" => {
+                actions.push(action("stop_service", name, ""));
+                actions.push(action("disable_service", name, ""));
+            }
+            "Startup Folder" => actions.push(action("quarantine_file", text(&mechanism["location"]), "")),
+            _ => {}
+        }
+        // Built-in binaries launched by the entry (powershell.exe, rundll32.exe, ...) must stay in place
+        if let Some(executable) = command_executable(command).filter(|path| !is_windows_binary(path)) {
+            actions.push(action("quarantine_file", &executable, ""));
+        }
+
+        findings.add(
+            "persistence_mechanisms",
+            format!("Suspicious {} {}: {}", mechanism_type, name, command),
+            actions,
+        );
+    }
+
+    // Winlogon, IFEO and AppInit entries above the suspicion threshold; machine Shell and
+    // Userinit values must not be deleted, only the programs they launch are quarantined
+    let suspicious = |entry: &Value| entry["suspicion_score"].as_u64().unwrap_or(0) >= persistence::HIJACK_SUSPICION_THRESHOLD as u64;
+    for entry in array(&artifacts["persistence_artifacts"]["winlogon_entries"]).iter().filter(|e| suspicious(e)) {
+        let value_name = text(&entry["value_name"]);
+        let value_data = text(&entry["value_data"]);
+        let mut actions = Vec::new();
+        if !entry["user"].is_null() {
+            actions.push(action("delete_registry_value", text(&entry["key_name"]), value_name));
+        }
+        for program in value_data.split(',').filter_map(command_executable).filter(|path| !is_windows_binary(path)) {
+            actions.push(action("quarantine_file", &program, ""));
+        }
+        findings.add(
+            "persistence_artifacts",
+            format!("Suspicious Winlogon {} value: {}", value_name, value_data),
+            actions,
+        );
+    }
+    for hijack in array(&artifacts["persistence_artifacts"]["image_hijacks"]).iter().filter(|e| suspicious(e)) {
+        let value_name = if text(&hijack["hijack_type"]) == "debugger" { "Debugger" } else { "MonitorProcess" };
+        let executable = text(&hijack["hijack_executable"]);
+        let mut actions = vec![action("delete_registry_value", text(&hijack["registry_key"]), value_name)];
+        if let Some(path) = command_executable(executable).filter(|path| !is_windows_binary(path)) {
+            actions.push(action("quarantine_file", &path, ""));
+        }
+        findings.add(
+            "persistence_artifacts",
+            format!("IFEO {} hijack of {}: {}", text(&hijack["hijack_type"]), text(&hijack["target_executable"]), executable),
+            actions,
+        );
+    }
+    for entry in array(&artifacts["persistence_artifacts"]["appinit_dlls"]).iter().filter(|e| suspicious(e)) {
+        let value_name = text(&entry["value_name"]);
+        let mut actions = Vec::new();
+        if value_name != "AppInit_DLLs" {
+            actions.push(action("delete_registry_value", text(&entry["key_name"]), value_name));
+        }
+        for dll in array(&entry["dlls"]).iter().map(text).filter(|dll| dll.contains('\\') && !is_windows_binary(dll)) {
+            actions.push(action("quarantine_file", dll, ""));
+        }
+        findings.add(
+            "persistence_artifacts",
+            format!("{} loads {}", value_name, array(&entry["dlls"]).iter().map(text).collect::<Vec<_>>().join(", ")),
+            actions,
+        );
+    }
+    // Search path entries and shell folder locations are restored by hand; the planted files are moved away
+    for hijack in array(&artifacts["persistence_artifacts"]["path_hijacks"]).iter().filter(|e| suspicious(e)) {
+        let mut actions: Vec<RemediationAction> = array(&hijack["planted_binaries"]).iter()
+            .map(|binary| action("quarantine_file", text(&binary["path"]), ""))
+            .collect();
+        if text(&hijack["source"]) == "app_paths" && !is_windows_binary(text(&hijack["directory"])) {
+            actions.push(action("quarantine_file", text(&hijack["directory"]), ""));
+        }
+        findings.add(
+            "persistence_artifacts",
+            format!("{} hijack via {}: {}", text(&hijack["source"]), text(&hijack["registry_key"]), text(&hijack["value_data"])),
+            actions,
+        );
+    }
+
+    for entry in array(&artifacts["execution_evidence"]["defender_log_entries"]) {
+        if text(&entry["category"]) != "detection" {
+            continue;
+        }
+        let path = text(&entry["path"]);
+        findings.add(
+            "defender_log_entries",
+            format!("Defender detection {} at {}", text(&entry["threat_name"]), path),
+            vec![action("quarantine_file", path, "")],
+        );
+    }
+
+    for installation in array(&artifacts["device_installations"]) {
+        let flags: Vec<&str> = array(&installation["flags"]).iter().map(text).collect();
+        if flags.is_empty() {
+            continue;
+        }
+        let mut actions = Vec::new();
+        for service in array(&installation["services"]) {
+            actions.push(action("stop_service", text(service), ""));
+            actions.push(action("disable_service", text(service), ""));
+        }
+        for driver_file in array(&installation["driver_files"]) {
+            actions.push(action("quarantine_file", text(driver_file), ""));
+        }
+        findings.add(
+            "device_installations",
+            format!("Device installation {} flagged: {}", text(&installation["target"]), flags.join(", ")),
+            actions,
+        );
+    }
+
+    // Processes flagged by the process tree analysis; built-in binaries are stopped but not quarantined
+    for process in array(&artifacts["running_processes"]) {
+        let tree_indicators: Vec<&str> = array(&process["suspicious_indicators"]).iter().map(text).collect();
+        let Some(pid) = process["pid"].as_u64().filter(|_| !tree_indicators.is_empty()) else {
+            continue;
+        };
+        let path = text(&process["executable_path"]);
+        let mut actions = vec![action("stop_process", &pid.to_string(), "")];
+        if !path.is_empty() && !is_windows_binary(path) {
+            actions.push(action("quarantine_file", path, ""));
+        }
+        findings.add(
+            "running_processes",
+            format!("Process {} (PID {}): {}", text(&process["name"]), pid, tree_indicators.join("; ")),
+            actions,
+        );
+    }
+
+    findings.items
+}
+
+/// Collect the remediation steps of the findings that `remediate --plan` can execute;
+/// process and file steps are left to the script
+pub fn build_plan(findings: &[Finding], scan: &Value) -> RemediationPlan {
+    RemediationPlan {
+        hostname: text(&scan["scan_metadata"]["hostname"]).to_string(),
+        scan_id: text(&scan["scan_metadata"]["scan_id"]).to_string(),
+        actions: findings
+            .iter()
+            .flat_map(|f| f.remediation.iter())
+            .filter(|step| remediate::SUPPORTED_ACTIONS.contains(&step.action.as_str()))
+            .cloned()
+            .collect(),
+    }
+}
+
+/// Render the remediation steps of the findings as a reviewable PowerShell script
+pub fn render_powershell_script(findings: &[Finding], scan: &Value) -> String {
+    let metadata = &scan["scan_metadata"];
+    let mut script = String::new();
+    let _ = writeln!(script, "# TriageIR remediation script");
+    let _ = writeln!(script, "# Host: {}  Scan: {}  Started: {}", text(&metadata["hostname"]), text(&metadata["scan_id"]), text(&metadata["scan_start_utc"]));
+    script.push_str("#\n");
+    script.push_str("# Generated from the scan findings for analyst review. Remove the steps you do not\n");
+    script.push_str("# want to run. Without -Apply every step only reports what it would do (-WhatIf).\n");
+    script.push_str("#Requires -RunAsAdministrator\n");
+    script.push_str("[CmdletBinding()]\nparam(\n    [switch]$Apply,\n    [string]$QuarantineDir = (Join-Path $env:SystemDrive 'TriageIR-Quarantine')\n)\n\n");
+    script.push_str("$WhatIfPreference = -not $Apply\n$ErrorActionPreference = 'Continue'\n");
+    script.push_str("New-Item -ItemType Directory -Path $QuarantineDir -Force | Out-Null\n");
+
+    if findings.is_empty() {
+        script.push_str("\n# No high-severity findings\n");
+    }
+    for finding in findings {
+        let _ = writeln!(script, "\n# {} [{}] {}", finding.finding_id, finding.source_artifact, one_line(&finding.description));
+        for step in &finding.remediation {
+            let _ = writeln!(script, "{}", powershell_command(&finding.finding_id, step));
+        }
+    }
+    script
+}
+
+fn powershell_command(finding_id: &str, step: &RemediationAction) -> String {
+    let target = quote(&step.target);
+    match step.action.as_str() {
+        "delete_registry_value" => format!(
+            "Remove-ItemProperty -LiteralPath {} -Name {}",
+            quote(&format!("Registry::{}", expand_hive(&step.target))),
+            quote(&step.value_name)
+        ),
+        "disable_scheduled_task" => {
+            let (folder, name) = step.target.rsplit_once('\\').unwrap_or(("", &step.target));
+            format!("Disable-ScheduledTask -TaskPath {} -TaskName {}", quote(&format!("{}\\", folder)), quote(name))
+        }
+        "stop_service" => format!("Stop-Service -Name {} -Force", target),
+        "disable_service" => format!("Set-Service -Name {} -StartupType Disabled", target),
+        "stop_process" => format!("Stop-Process -Id {} -Force", step.target.parse::<u32>().unwrap_or_default()),
+        "quarantine_file" => {
+            let file_name = step.target.rsplit(['\\', '/']).next().unwrap_or_default();
+            format!(
+                "Move-Item -LiteralPath {} -Destination (Join-Path $QuarantineDir {})",
+                target,
+                quote(&format!("{}_{}", finding_id, file_name))
+            )
+        }
+        other => format!("# Unsupported action {}: {}", other, one_line(&step.target)),
+    }
+}
+
+/// PowerShell single-quoted string literal
+fn quote(value: &str) -> String {
+    format!("'{}'", one_line(value).replace('\'', "''"))
+}
+
+fn one_line(value: &str) -> String {
+    value.replace(['\r', '\n'], " ")
+}
+
+fn action(action: &str, target: &str, value_name: &str) -> RemediationAction {
+    RemediationAction {
+        action: action.to_string(),
+        target: target.to_string(),
+        value_name: value_name.to_string(),
+    }
+}
+
+#[derive(Default)]
+struct FindingSet {
+    items: Vec<Finding>,
+    seen: HashSet<(String, String)>,
+}
+
+impl FindingSet {
+    fn add(&mut self, source: &str, description: String, actions: Vec<RemediationAction>) {
+        let mut remediation = Vec::new();
+        for step in actions {
+            // The same file or service can be reached from several findings; suggest it once
+            let key = (step.action.clone(), format!("{}|{}", step.target.to_lowercase(), step.value_name.to_lowercase()));
+            if !step.target.is_empty() && self.seen.insert(key) {
+                remediation.push(step);
+            }
+        }
+        self.items.push(Finding {
+            finding_id: format!("F-{:03}", self.items.len() + 1),
+            severity: "high".to_string(),
+            source_artifact: source.to_string(),
+            description,
+            remediation,
+        });
+    }
+}
+
+fn array(value: &Value) -> &[Value] {
+    value.as_array().map(Vec::as_slice).unwrap_or_default()
+}
+
+fn text(value: &Value) -> &str {
+    value.as_str().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn sample_scan() -> Value {
+        json!({
+            "scan_metadata": { "scan_id": "1234", "hostname": "WS01", "scan_start_utc": "2024-05-01T10:00:00Z" },
+            "artifacts": {
+                "running_processes": [
+                    { "pid": 4242, "name": "updater.exe", "executable_path": "C:\\Users\\Public\\updater.exe",
+                      "suspicious_indicators": ["suspicious_parent: WINWORD.EXE -> updater.exe"] },
+                    { "pid": 5001, "name": "cmd.exe", "executable_path": "C:\\Windows\\System32\\cmd.exe",
+                      "suspicious_indicators": ["suspicious_parent: WINWORD.EXE -> cmd.exe"] },
+                    { "pid": 100, "name": "explorer.exe", "executable_path": "C:\\Windows\\explorer.exe" }
+                ],
+                "persistence_mechanisms": [
+                    { "type": "Registry Run Key", "name": "Updater", "command": "C:\\Users\\Public\\updater.exe -c http://evil.example.com",
+                      "source": "HKCU\\Software\\Microsoft\\Windows\\CurrentVersion\\Run", "is_suspicious": true },
+                    { "type": "Scheduled Task", "name": "SyncTask", "command": "powershell.exe -enc AAAA (User: SYSTEM)",
+                      "source": "Task Scheduler: \\Microsoft\\SyncTask", "is_suspicious": true },
+                    { "type": "Windows Service", "name": "Spooler", "command": "C:\\Windows\\System32\\spoolsv.exe",
+                      "source": "HKLM\\SYSTEM\\CurrentControlSet\\Services\\Spooler", "is_suspicious": false }
+                ],
+                "execution_evidence": {
+                    "defender_log_entries": [
+                        { "category": "detection", "threat_name": "HackTool:Win32/Mimikatz.D", "path": "C:\\Temp\\m'1.exe" },
+                        { "category": "sdn_query", "path": "C:\\Temp\\other.exe" }
+                    ]
+                },
+                "device_installations": [
+                    { "target": "oem42.inf", "flags": ["unsigned"], "driver_files": ["C:\\Windows\\System32\\drivers\\rk.sys"], "services": ["rk"] }
+                ]
+            }
+        })
+    }
+
+    fn actions(finding: &Finding) -> Vec<(&str, &str)> {
+        finding.remediation.iter().map(|a| (a.action.as_str(), a.target.as_str())).collect()
+    }
+
+    #[test]
+    fn test_collect_findings() {
+        let findings = collect_findings(&sample_scan());
+        assert_eq!(findings.len(), 6);
+        assert!(findings.iter().all(|f| f.severity == "high"));
+        assert_eq!(findings[0].finding_id, "F-001");
+
+        assert_eq!(
+            actions(&findings[0]),
+            vec![
+                ("delete_registry_value", "HKCU\\Software\\Microsoft\\Windows\\CurrentVersion\\Run"),
+                ("quarantine_file", "C:\\Users\\Public\\updater.exe")
+            ]
+        );
+        assert_eq!(findings[0].remediation[0].value_name, "Updater");
+        assert_eq!(actions(&findings[1]), vec![("disable_scheduled_task", "\\Microsoft\\SyncTask")]); // powershell.exe stays
+        assert_eq!(actions(&findings[2]), vec![("quarantine_file", "C:\\Temp\\m'1.exe")]);
+        assert_eq!(
+            actions(&findings[3]),
+            vec![("stop_service", "rk"), ("disable_service", "rk"), ("quarantine_file", "C:\\Windows\\System32\\drivers\\rk.sys")]
+        );
+        // updater.exe was already suggested for quarantine by the Run key finding
+        assert_eq!(actions(&findings[4]), vec![("stop_process", "4242")]);
+        assert_eq!(actions(&findings[5]), vec![("stop_process", "5001")]);
+    }
+
+    #[test]
+    fn test_render_powershell_script() {
+        let scan = sample_scan();
+        let script = render_powershell_script(&collect_findings(&scan), &scan);
+
+        assert!(script.contains("$WhatIfPreference = -not $Apply"));
+        assert!(script.contains("# Host: WS01  Scan: 1234"));
+        assert!(script.contains(
+            "Remove-ItemProperty -LiteralPath 'Registry::HKEY_CURRENT_USER\\Software\\Microsoft\\Windows\\CurrentVersion\\Run' -Name 'Updater'"
+        ));
+        assert!(script.contains("Disable-ScheduledTask -TaskPath '\\Microsoft\\' -TaskName 'SyncTask'"));
+        assert!(script.contains("Move-Item -LiteralPath 'C:\\Temp\\m''1.exe' -Destination (Join-Path $QuarantineDir 'F-003_m''1.exe')"));
+        assert!(script.contains("Stop-Service -Name 'rk' -Force\nSet-Service -Name 'rk' -StartupType Disabled"));
+        assert!(script.contains("Stop-Process -Id 4242 -Force"));
+    }
+
+    #[test]
+    fn test_build_plan() {
+        let scan = sample_scan();
+        let plan = build_plan(&collect_findings(&scan), &scan);
+        assert_eq!((plan.hostname.as_str(), plan.scan_id.as_str()), ("WS01", "1234"));
+        let actions: Vec<&str> = plan.actions.iter().map(|a| a.action.as_str()).collect();
+        assert_eq!(actions, vec!["delete_registry_value", "disable_scheduled_task", "stop_service", "disable_service"]);
+    }
+
+    #[test]
+    fn test_collect_findings_hijacks() {
+        let scan = json!({
+            "artifacts": {
+                "persistence_artifacts": {
+                    "winlogon_entries": [
+                        { "key_name": "HKLM\\SOFTWARE\\Microsoft\\Windows NT\\CurrentVersion\\Winlogon", "value_name": "Userinit", "value_data": "C:\\Windows\\system32\\userinit.exe,C:\\ProgramData\\u.exe", "user": null, "suspicion_score": 60 },
+                        { "key_name": "HKLM\\SOFTWARE\\Microsoft\\Windows NT\\CurrentVersion\\Winlogon", "value_name": "Shell", "value_data": "explorer.exe", "user": null, "suspicion_score": 0 }
+                    ],
+                    "image_hijacks": [
+                        { "target_executable": "sethc.exe", "hijack_executable": "C:\\Windows\\System32\\cmd.exe", "hijack_type": "debugger", "registry_key": "HKLM\\SOFTWARE\\Microsoft\\Windows NT\\CurrentVersion\\Image File Execution Options\\sethc.exe", "suspicion_score": 100 }
+                    ],
+                    "appinit_dlls": []
+                }
+            }
+        });
+        let findings = collect_findings(&scan);
+        assert_eq!(findings.len(), 2);
+        // The machine Userinit value stays, only the added program is quarantined
+        assert_eq!(actions(&findings[0]), vec![("quarantine_file", "C:\\ProgramData\\u.exe")]);
+        assert_eq!(
+            actions(&findings[1]),
+            vec![("delete_registry_value", "HKLM\\SOFTWARE\\Microsoft\\Windows NT\\CurrentVersion\\Image File Execution Options\\sethc.exe")]
+        );
+        assert_eq!(findings[1].remediation[0].value_name, "Debugger");
+    }
+
+    #[test]
+    fn test_render_powershell_script_without_findings() {
+        let scan = json!({ "scan_metadata": {}, "artifacts": {} });
+        assert!(collect_findings(&scan).is_empty());
+        assert!(render_powershell_script(&[], &scan).contains("# No high-severity findings"));
+    }
+}
@@ -0,0 +1,477 @@
+//! RDP and SMB lateral movement artifacts
+//! Established RDP connections, each user's Remote Desktop client history
+//! (Terminal Server Client key), the TerminalServices session events
+//! (1149 authentication, 21 logon, 24 disconnect, 25 reconnect) and the
+//! inbound SMB sessions and open files of this host.
+
+// Registry and NetAPI helpers are only reachable through the Windows APIs
+#![cfg_attr(not(windows), allow(dead_code))]
+
+use crate::event_logs::{self, EventChannelQuery};
+use crate::forensic_types::{AuditEntry, RdpConnection, RdpConnectionHistory, RdpSessionEvent, SmbOpenFile, SmbSession};
+use crate::offline::OfflineRoot;
+use crate::time_window::TimeWindow;
+use crate::types::{EventLogEntry, NetworkConnection};
+
+#[cfg(windows)]
+use crate::footprint::TrackedKey;
+#[cfg(windows)]
+use crate::offline;
+#[cfg(windows)]
+use crate::user_hives::{self, UserHive};
+
+const COMPONENT: &str = "remote_access";
+
+const RDP_PORT: u16 = 3389;
+const TERMINAL_SERVER_CLIENT_KEY: &str = "Software\\Microsoft\\Terminal Server Client";
+const LOCAL_SESSION_MANAGER_CHANNEL: &str = "Microsoft-Windows-TerminalServices-LocalSessionManager/Operational";
+const REMOTE_CONNECTION_MANAGER_CHANNEL: &str = "Microsoft-Windows-TerminalServices-RemoteConnectionManager/Operational";
+
+const PERM_FILE_READ: u32 = 0x1;
+const PERM_FILE_WRITE: u32 = 0x2;
+const PERM_FILE_CREATE: u32 = 0x4;
+
+/// Established RDP connections among the collected network connections
+pub fn rdp_connections(connections: &[NetworkConnection]) -> Vec<RdpConnection> {
+    connections
+        .iter()
+        .filter(|c| c.protocol == "TCP" && c.state == "ESTABLISHED")
+        .filter_map(|c| {
+            let direction = if c.local_port == RDP_PORT {
+                "inbound"
+            } else if c.remote_port == RDP_PORT || c.process_name.eq_ignore_ascii_case("mstsc.exe") {
+                "outbound"
+            } else {
+                return None;
+            };
+            Some(RdpConnection {
+                direction: direction.to_string(),
+                local_address: c.local_address.clone(),
+                local_port: c.local_port,
+                remote_address: c.remote_address.clone(),
+                remote_port: c.remote_port,
+                process_id: c.owning_pid,
+                process_name: c.process_name.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Collect the Remote Desktop client history of every user profile on the live system
+#[cfg(windows)]
+pub fn collect_rdp_history() -> (Vec<RdpConnectionHistory>, Vec<AuditEntry>) {
+    let (hives, failures) = user_hives::live_user_hives();
+    collect_history_from_user_hives(hives, failures)
+}
+
+#[cfg(not(windows))]
+pub fn collect_rdp_history() -> (Vec<RdpConnectionHistory>, Vec<AuditEntry>) {
+    registry_unavailable()
+}
+
+/// Collect the Remote Desktop client history from the NTUSER.DAT hives of a mounted Windows volume
+#[cfg(windows)]
+pub fn collect_rdp_history_offline(root: &OfflineRoot) -> (Vec<RdpConnectionHistory>, Vec<AuditEntry>) {
+    let (hives, failures) = user_hives::offline_user_hives(root);
+    collect_history_from_user_hives(hives, failures)
+}
+
+#[cfg(not(windows))]
+pub fn collect_rdp_history_offline(_root: &OfflineRoot) -> (Vec<RdpConnectionHistory>, Vec<AuditEntry>) {
+    registry_unavailable()
+}
+
+#[cfg(not(windows))]
+fn registry_unavailable() -> (Vec<RdpConnectionHistory>, Vec<AuditEntry>) {
+    let audit_log = vec![
+        AuditEntry::new(COMPONENT, "INFO", "start_collection", "Starting RDP client history collection".to_string(), "started"),
+        AuditEntry::new(COMPONENT, "WARN", "load_user_hive", "User hives require the Windows registry".to_string(), "unsupported"),
+        AuditEntry::new(COMPONENT, "INFO", "complete_collection", "Collected 0 RDP client history entries".to_string(), "success"),
+    ];
+    (Vec::new(), audit_log)
+}
+
+#[cfg(windows)]
+fn collect_history_from_user_hives(hives: Vec<UserHive>, failures: Vec<String>) -> (Vec<RdpConnectionHistory>, Vec<AuditEntry>) {
+    let mut entries = Vec::new();
+    let start_time = std::time::Instant::now();
+
+    let mut audit_log = vec![AuditEntry::new(
+        COMPONENT,
+        "INFO",
+        "start_collection",
+        format!("Starting RDP client history collection for {} user hives", hives.len()),
+        "started",
+    )];
+
+    for failure in failures {
+        audit_log.push(AuditEntry::new(COMPONENT, "WARN", "load_user_hive", failure, "error"));
+    }
+
+    for hive in &hives {
+        let history = collect_user_rdp_history(hive);
+        if !history.is_empty() {
+            audit_log.push(AuditEntry::new(
+                COMPONENT,
+                "DEBUG",
+                "parse_user_hive",
+                format!("{}: {} RDP servers", hive.user, history.len()),
+                "success",
+            ));
+        }
+        entries.extend(history);
+    }
+
+    let duration = start_time.elapsed();
+    let mut complete = AuditEntry::new(
+        COMPONENT,
+        "INFO",
+        "complete_collection",
+        format!("Collected {} RDP client history entries", entries.len()),
+        "success",
+    );
+    complete.duration_ms = Some(duration.as_millis() as u64);
+    audit_log.push(complete);
+
+    (entries, audit_log)
+}
+
+/// Servers\<host> subkeys (with UsernameHint) and the Default MRU0..MRU9 values
+#[cfg(windows)]
+fn collect_user_rdp_history(hive: &UserHive) -> Vec<RdpConnectionHistory> {
+    let Ok(client_key) = hive.key.open_tracked(TERMINAL_SERVER_CLIENT_KEY) else {
+        return Vec::new();
+    };
+    let registry_path = format!("{}\\{}", hive.source, TERMINAL_SERVER_CLIENT_KEY);
+
+    let mut servers = Vec::new();
+    if let Ok(servers_key) = client_key.open_tracked("Servers") {
+        for server in servers_key.enum_keys().filter_map(|k| k.ok()) {
+            if let Ok(server_key) = servers_key.open_tracked(&server) {
+                servers.push(ServerKey {
+                    username_hint: server_key.get_value::<String, _>("UsernameHint").unwrap_or_default(),
+                    key_last_write: offline::key_last_write_time(&server_key),
+                    server,
+                });
+            }
+        }
+    }
+
+    let (mru, mru_last_write) = match client_key.open_tracked("Default") {
+        Ok(default_key) => {
+            let mru = (0..10).filter_map(|i| default_key.get_value::<String, _>(format!("MRU{}", i)).ok()).collect();
+            (mru, offline::key_last_write_time(&default_key))
+        }
+        Err(_) => (Vec::new(), String::new()),
+    };
+
+    merge_rdp_history(&hive.user, &registry_path, servers, &mru, &mru_last_write)
+}
+
+/// A Servers\<host> subkey of the Terminal Server Client key
+struct ServerKey {
+    server: String,
+    username_hint: String,
+    key_last_write: String,
+}
+
+/// One entry per server, most recently used first, then servers no longer in the MRU list
+fn merge_rdp_history(user: &str, registry_path: &str, servers: Vec<ServerKey>, mru: &[String], mru_last_write: &str) -> Vec<RdpConnectionHistory> {
+    let mut entries: Vec<RdpConnectionHistory> = Vec::new();
+
+    for (position, server) in mru.iter().enumerate().filter(|(_, s)| !s.trim().is_empty()) {
+        let key = servers.iter().find(|k| k.server.eq_ignore_ascii_case(server));
+        entries.push(RdpConnectionHistory {
+            user: user.to_string(),
+            server: server.clone(),
+            username_hint: key.map(|k| k.username_hint.clone()).unwrap_or_default(),
+            mru_position: Some(position as u32),
+            key_last_write: key.map_or_else(|| mru_last_write.to_string(), |k| k.key_last_write.clone()),
+            registry_path: registry_path.to_string(),
+        });
+    }
+
+    for key in servers {
+        if entries.iter().any(|e| e.server.eq_ignore_ascii_case(&key.server)) {
+            continue;
+        }
+        entries.push(RdpConnectionHistory {
+            user: user.to_string(),
+            server: key.server,
+            username_hint: key.username_hint,
+            mru_position: None,
+            key_last_write: key.key_last_write,
+            registry_path: registry_path.to_string(),
+        });
+    }
+
+    entries
+}
+
+/// Collect RDP session events from the live TerminalServices logs, or from the
+/// .evtx files of a mounted Windows volume
+pub fn collect_rdp_session_events(root: Option<&OfflineRoot>, window: &TimeWindow) -> (Vec<RdpSessionEvent>, Vec<AuditEntry>) {
+    let start_time = std::time::Instant::now();
+    let mut audit_log = vec![AuditEntry::new(COMPONENT, "INFO", "start_collection", "Starting RDP session event collection".to_string(), "started")];
+
+    let mut queries = vec![
+        EventChannelQuery::with_xpath(LOCAL_SESSION_MANAGER_CHANNEL, "*[System[(EventID=21 or EventID=24 or EventID=25)]]"),
+        EventChannelQuery::with_xpath(REMOTE_CONNECTION_MANAGER_CHANNEL, "*[System[(EventID=1149)]]"),
+    ];
+    for query in &mut queries {
+        query.restrict_to(window);
+    }
+
+    let (event_logs, logs) = event_logs::collect_event_logs_with_queries(&queries, root);
+    for log in logs.iter().filter(|l| l.level != "INFO") {
+        audit_log.push(AuditEntry::new(COMPONENT, &log.level, "query_events", log.message.clone(), "error"));
+    }
+
+    let mut events: Vec<RdpSessionEvent> = event_logs.channels.values().flatten().filter_map(rdp_session_event).collect();
+    events.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+
+    let duration = start_time.elapsed();
+    let mut complete = AuditEntry::new(COMPONENT, "INFO", "complete_collection", format!("Collected {} RDP session events", events.len()), "success");
+    complete.duration_ms = Some(duration.as_millis() as u64);
+    audit_log.push(complete);
+
+    (events, audit_log)
+}
+
+/// Map a TerminalServices event; console logons (Address LOCAL) are not RDP and are skipped
+fn rdp_session_event(entry: &EventLogEntry) -> Option<RdpSessionEvent> {
+    let field = |name: &str| entry.event_data.get(name).map(|v| v.trim().to_string()).unwrap_or_default();
+
+    let (action, user, source_address, session_id) = match entry.event_id {
+        1149 => {
+            let (user, domain) = (field("Param1"), field("Param2"));
+            let user = if domain.is_empty() { user } else { format!("{}\\{}", domain, user) };
+            ("authenticated", user, field("Param3"), None)
+        }
+        21 | 24 | 25 => {
+            let action = match entry.event_id {
+                21 => "logon",
+                24 => "disconnect",
+                _ => "reconnect",
+            };
+            (action, field("User"), field("Address"), field("SessionID").parse().ok())
+        }
+        _ => return None,
+    };
+
+    if source_address.eq_ignore_ascii_case("LOCAL") {
+        return None;
+    }
+
+    Some(RdpSessionEvent {
+        timestamp: entry.timestamp.clone(),
+        event_id: entry.event_id,
+        action: action.to_string(),
+        user,
+        source_address,
+        session_id,
+        channel: entry.source.clone(),
+    })
+}
+
+/// Inbound SMB sessions and the files they have open on the live system
+#[cfg(windows)]
+pub fn collect_smb_sessions() -> (Vec<SmbSession>, Vec<SmbOpenFile>, Vec<AuditEntry>) {
+    let start_time = std::time::Instant::now();
+    let mut audit_log = vec![AuditEntry::new(COMPONENT, "INFO", "start_collection", "Starting SMB session enumeration".to_string(), "started")];
+
+    let sessions = windows_api::smb_sessions().unwrap_or_else(|e| {
+        audit_log.push(AuditEntry::new(COMPONENT, "WARN", "enumerate_sessions", format!("NetSessionEnum failed: {}", e), "error"));
+        Vec::new()
+    });
+    let open_files = windows_api::smb_open_files().unwrap_or_else(|e| {
+        audit_log.push(AuditEntry::new(COMPONENT, "WARN", "enumerate_open_files", format!("NetFileEnum failed: {}", e), "error"));
+        Vec::new()
+    });
+
+    let duration = start_time.elapsed();
+    let mut complete = AuditEntry::new(
+        COMPONENT,
+        "INFO",
+        "complete_collection",
+        format!("Collected {} SMB sessions and {} open files", sessions.len(), open_files.len()),
+        "success",
+    );
+    complete.duration_ms = Some(duration.as_millis() as u64);
+    audit_log.push(complete);
+
+    (sessions, open_files, audit_log)
+}
+
+#[cfg(not(windows))]
+pub fn collect_smb_sessions() -> (Vec<SmbSession>, Vec<SmbOpenFile>, Vec<AuditEntry>) {
+    let audit_log = vec![
+        AuditEntry::new(COMPONENT, "INFO", "start_collection", "Starting SMB session enumeration".to_string(), "started"),
+        AuditEntry::new(COMPONENT, "WARN", "enumerate_sessions", "SMB session enumeration requires Windows".to_string(), "unsupported"),
+        AuditEntry::new(COMPONENT, "INFO", "complete_collection", "Collected 0 SMB sessions and 0 open files".to_string(), "success"),
+    ];
+    (Vec::new(), Vec::new(), audit_log)
+}
+
+fn file_permissions(permissions: u32) -> Vec<String> {
+    [(PERM_FILE_READ, "read"), (PERM_FILE_WRITE, "write"), (PERM_FILE_CREATE, "create")]
+        .iter()
+        .filter(|(flag, _)| permissions & flag != 0)
+        .map(|(_, name)| name.to_string())
+        .collect()
+}
+
+#[cfg(windows)]
+mod windows_api {
+    use super::file_permissions;
+    use crate::forensic_types::{SmbOpenFile, SmbSession};
+    use windows::core::{PCWSTR, PWSTR};
+    use windows::Win32::NetworkManagement::NetManagement::{NetApiBufferFree, MAX_PREFERRED_LENGTH, NERR_Success};
+    use windows::Win32::Storage::FileSystem::{NetFileEnum, NetSessionEnum, FILE_INFO_3, SESSION_INFO_10};
+
+    pub fn smb_sessions() -> Result<Vec<SmbSession>, String> {
+        let mut buffer: *mut u8 = std::ptr::null_mut();
+        let (mut read, mut total) = (0u32, 0u32);
+        let status = unsafe {
+            NetSessionEnum(PCWSTR::null(), PCWSTR::null(), PCWSTR::null(), 10, &mut buffer, MAX_PREFERRED_LENGTH, &mut read, &mut total, None)
+        };
+        if status != NERR_Success {
+            free(buffer);
+            return Err(format!("error {}", status));
+        }
+
+        let sessions = (0..read as usize)
+            .map(|i| {
+                let info = unsafe { &*(buffer as *const SESSION_INFO_10).add(i) };
+                SmbSession {
+                    client: wide(info.sesi10_cname).trim_start_matches('\\').to_string(),
+                    username: wide(info.sesi10_username),
+                    active_secs: info.sesi10_time,
+                    idle_secs: info.sesi10_idle_time,
+                }
+            })
+            .collect();
+        free(buffer);
+        Ok(sessions)
+    }
+
+    pub fn smb_open_files() -> Result<Vec<SmbOpenFile>, String> {
+        let mut buffer: *mut u8 = std::ptr::null_mut();
+        let (mut read, mut total) = (0u32, 0u32);
+        let status = unsafe {
+            NetFileEnum(PCWSTR::null(), PCWSTR::null(), PCWSTR::null(), 3, &mut buffer, MAX_PREFERRED_LENGTH, &mut read, &mut total, None)
+        };
+        if status != NERR_Success {
+            free(buffer);
+            return Err(format!("error {}", status));
+        }
+
+        let files = (0..read as usize)
+            .map(|i| {
+                let info = unsafe { &*(buffer as *const FILE_INFO_3).add(i) };
+                SmbOpenFile {
+                    id: info.fi3_id,
+                    path: wide(info.fi3_pathname),
+                    username: wide(info.fi3_username),
+                    permissions: file_permissions(info.fi3_permissions.0),
+                    locks: info.fi3_num_locks,
+                }
+            })
+            .collect();
+        free(buffer);
+        Ok(files)
+    }
+
+    fn wide(value: PWSTR) -> String {
+        if value.is_null() {
+            return String::new();
+        }
+        unsafe { value.to_string() }.unwrap_or_default()
+    }
+
+    fn free(buffer: *mut u8) {
+        if !buffer.is_null() {
+            unsafe { NetApiBufferFree(Some(buffer as *const _)) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn connection(local_port: u16, remote_port: u16, process_name: &str, state: &str) -> NetworkConnection {
+        NetworkConnection::new_with_ports_and_process(
+            "TCP".to_string(),
+            "10.0.0.5".to_string(),
+            local_port,
+            "10.0.0.9".to_string(),
+            remote_port,
+            state.to_string(),
+            1234,
+            process_name.to_string(),
+        )
+    }
+
+    #[test]
+    fn test_rdp_connections() {
+        let connections = vec![
+            connection(3389, 51000, "svchost.exe", "ESTABLISHED"),
+            connection(52000, 3389, "mstsc.exe", "ESTABLISHED"),
+            connection(52001, 13389, "MSTSC.EXE", "ESTABLISHED"),
+            connection(3389, 0, "svchost.exe", "LISTEN"),
+            connection(52002, 443, "chrome.exe", "ESTABLISHED"),
+        ];
+
+        let rdp = rdp_connections(&connections);
+        let directions: Vec<_> = rdp.iter().map(|c| c.direction.as_str()).collect();
+        assert_eq!(directions, vec!["inbound", "outbound", "outbound"]);
+        assert_eq!(rdp[0].remote_port, 51000);
+    }
+
+    #[test]
+    fn test_merge_rdp_history() {
+        let servers = vec![
+            ServerKey { server: "jump01.corp.local".to_string(), username_hint: "CORP\\admin".to_string(), key_last_write: "2024-05-01T10:00:00+00:00".to_string() },
+            ServerKey { server: "old-db".to_string(), username_hint: "sa".to_string(), key_last_write: "2023-01-01T00:00:00+00:00".to_string() },
+        ];
+        let mru = vec!["10.0.0.20".to_string(), "JUMP01.corp.local".to_string()];
+
+        let history = merge_rdp_history("alice", "HKU\\S-1-5-21-1\\Software\\Microsoft\\Terminal Server Client", servers, &mru, "2024-05-02T09:00:00+00:00");
+        assert_eq!(history.len(), 3);
+        assert_eq!(history[0].server, "10.0.0.20");
+        assert_eq!(history[0].mru_position, Some(0));
+        assert_eq!(history[0].key_last_write, "2024-05-02T09:00:00+00:00");
+        assert_eq!(history[1].username_hint, "CORP\\admin");
+        assert_eq!(history[1].mru_position, Some(1));
+        assert_eq!(history[2].server, "old-db");
+        assert_eq!(history[2].mru_position, None);
+    }
+
+    #[test]
+    fn test_rdp_session_event() {
+        let mut entry = EventLogEntry::new_with_source(1149, "Information".to_string(), "2024-05-01T10:00:00+00:00".to_string(), String::new(), REMOTE_CONNECTION_MANAGER_CHANNEL.to_string());
+        entry.event_data.insert("Param1".to_string(), "admin".to_string());
+        entry.event_data.insert("Param2".to_string(), "CORP".to_string());
+        entry.event_data.insert("Param3".to_string(), "10.0.0.7".to_string());
+        let event = rdp_session_event(&entry).unwrap();
+        assert_eq!((event.action.as_str(), event.user.as_str(), event.source_address.as_str()), ("authenticated", "CORP\\admin", "10.0.0.7"));
+
+        let mut entry = EventLogEntry::new_with_source(24, "Information".to_string(), String::new(), String::new(), LOCAL_SESSION_MANAGER_CHANNEL.to_string());
+        entry.event_data.insert("User".to_string(), "CORP\\admin".to_string());
+        entry.event_data.insert("SessionID".to_string(), "3".to_string());
+        entry.event_data.insert("Address".to_string(), "10.0.0.7".to_string());
+        let event = rdp_session_event(&entry).unwrap();
+        assert_eq!(event.action, "disconnect");
+        assert_eq!(event.session_id, Some(3));
+
+        entry.event_data.insert("Address".to_string(), "LOCAL".to_string());
+        assert!(rdp_session_event(&entry).is_none());
+    }
+
+    #[test]
+    fn test_file_permissions() {
+        assert_eq!(file_permissions(0x3), vec!["read", "write"]);
+        assert!(file_permissions(0).is_empty());
+    }
+}
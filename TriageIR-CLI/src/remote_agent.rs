@@ -0,0 +1,238 @@
+//! Remote collection agent
+//! `triageir-cli serve` listens on a TLS port and accepts scan requests from a
+//! central console. Both sides authenticate: the agent presents its certificate
+//! and only clients with a certificate issued by the `--client-ca` authority
+//! complete the handshake. A session speaks the worker protocol (JSON lines):
+//! progress events stream while the scan runs, then the results as record
+//! events. Up to MAX_SESSIONS consoles stay connected at once, each on a
+//! thread of its own; their scans run one at a time and share the worker's
+//! warm state. Remote requests cannot write files or load plugins on the endpoint.
+
+// The agent is only available in builds with the remote-agent feature
+#![cfg_attr(not(feature = "remote-agent"), allow(dead_code))]
+use std::path::PathBuf;
+#[cfg(feature = "remote-agent")]
+use crate::worker::{self, WarmCache};
+#[cfg(feature = "remote-agent")]
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+#[cfg(feature = "remote-agent")]
+use rustls::server::WebPkiClientVerifier;
+#[cfg(feature = "remote-agent")]
+use rustls::{RootCertStore, ServerConfig, ServerConnection, StreamOwned};
+#[cfg(feature = "remote-agent")]
+use sha2::{Digest, Sha256};
+#[cfg(feature = "remote-agent")]
+use std::fs::File;
+#[cfg(feature = "remote-agent")]
+use std::io::{self, BufRead, BufReader, ErrorKind, Read, Write};
+#[cfg(feature = "remote-agent")]
+use std::net::{TcpListener, TcpStream};
+#[cfg(feature = "remote-agent")]
+use std::path::Path;
+#[cfg(feature = "remote-agent")]
+use std::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(feature = "remote-agent")]
+use std::sync::{Arc, Mutex};
+#[cfg(feature = "remote-agent")]
+use std::time::Duration;
+
+#[cfg(not(feature = "remote-agent"))]
+pub const REMOTE_AGENT_UNAVAILABLE: &str = "the remote agent needs a build with --features remote-agent";
+
+/// Default listening address
+pub const DEFAULT_LISTEN: &str = "0.0.0.0:8443";
+
+/// Sessions without a request for this long are closed
+#[cfg(feature = "remote-agent")]
+const IDLE_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Consoles that stop reading for this long are disconnected
+#[cfg(feature = "remote-agent")]
+const WRITE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Connections beyond this many are refused
+#[cfg(feature = "remote-agent")]
+const MAX_SESSIONS: usize = 8;
+
+/// Longest request line accepted
+#[cfg(feature = "remote-agent")]
+const MAX_LINE: u64 = 1024 * 1024;
+
+pub struct ServeOptions {
+    pub listen: String,
+    pub cert: PathBuf,       // PEM certificate chain of the agent
+    pub key: PathBuf,        // PEM private key of the agent
+    pub client_ca: PathBuf,  // PEM certificates of the authorities issuing console certificates
+}
+
+/// Accept console connections until the process is stopped
+#[cfg(feature = "remote-agent")]
+pub fn serve(options: &ServeOptions) -> Result<(), String> {
+    let config = server_config(options)?;
+    let listener = TcpListener::bind(&options.listen).map_err(|e| format!("Failed to listen on {}: {}", options.listen, e))?;
+    eprintln!("Remote agent listening on {} (mutual TLS)", options.listen);
+
+    // Held while a request runs, so scans never overlap
+    let cache = Arc::new(Mutex::new(WarmCache::default()));
+    let sessions = Arc::new(AtomicUsize::new(0));
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("✗ Failed to accept a connection: {}", e);
+                continue;
+            }
+        };
+        let peer = stream.peer_addr().map(|address| address.to_string()).unwrap_or_else(|_| "unknown peer".to_string());
+        if sessions.fetch_add(1, Ordering::SeqCst) >= MAX_SESSIONS {
+            sessions.fetch_sub(1, Ordering::SeqCst);
+            eprintln!("✗ {}: refused, {} sessions are open", peer, MAX_SESSIONS);
+            continue;
+        }
+        let (config, cache, sessions) = (Arc::clone(&config), Arc::clone(&cache), Arc::clone(&sessions));
+        std::thread::spawn(move || {
+            match serve_session(&config, stream, &cache) {
+                Ok((client, requests)) => eprintln!("{} (client certificate SHA-256 {}): session closed after {} requests", peer, client, requests),
+                Err(e) => eprintln!("✗ {}: {}", peer, e),
+            }
+            sessions.fetch_sub(1, Ordering::SeqCst);
+        });
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "remote-agent"))]
+pub fn serve(_options: &ServeOptions) -> Result<(), String> {
+    Err(REMOTE_AGENT_UNAVAILABLE.to_string())
+}
+
+/// Run the requests of one console connection; returns the client certificate
+/// fingerprint and the number of requests
+#[cfg(feature = "remote-agent")]
+fn serve_session(config: &Arc<ServerConfig>, stream: TcpStream, cache: &Mutex<WarmCache>) -> Result<(String, usize), String> {
+    stream.set_read_timeout(Some(IDLE_TIMEOUT)).map_err(|e| e.to_string())?;
+    stream.set_write_timeout(Some(WRITE_TIMEOUT)).map_err(|e| e.to_string())?;
+    let connection = ServerConnection::new(Arc::clone(config)).map_err(|e| e.to_string())?;
+    let mut tls = StreamOwned::new(connection, stream);
+    while tls.conn.is_handshaking() {
+        tls.conn.complete_io(&mut tls.sock).map_err(|e| format!("TLS handshake failed: {}", e))?;
+    }
+    let client = tls
+        .conn
+        .peer_certificates()
+        .and_then(|chain| chain.first())
+        .map(|certificate| hex::encode(Sha256::digest(certificate.as_ref())))
+        .ok_or("the client presented no certificate")?;
+
+    let mut session = BufReader::new(tls);
+    let ready = serde_json::json!({ "event": "ready", "version": env!("CARGO_PKG_VERSION"), "client": client });
+    worker::emit(session.get_mut(), &ready).map_err(|e| e.to_string())?;
+
+    let mut requests = 0;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        match read_request(&mut session, &mut line) {
+            // Consoles that drop the connection without close_notify end the session too
+            Ok(0) => break,
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => break,
+            Ok(_) if line.trim().is_empty() => continue,
+            Ok(_) => {}
+            Err(e) => return Err(format!("session ended after {} requests: {}", requests, e)),
+        }
+        requests += 1;
+        // A session that panicked mid-scan leaves the cache usable
+        let mut cache = cache.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let event = cache.handle(&line, session.get_mut(), true);
+        drop(cache);
+        worker::emit(session.get_mut(), &event).map_err(|e| e.to_string())?;
+    }
+
+    let mut tls = session.into_inner();
+    tls.conn.send_close_notify();
+    let _ = tls.flush();
+    Ok((client, requests))
+}
+
+/// Read one request line of at most MAX_LINE bytes
+#[cfg(feature = "remote-agent")]
+fn read_request(session: &mut impl BufRead, line: &mut String) -> io::Result<usize> {
+    let read = session.by_ref().take(MAX_LINE).read_line(line)?;
+    if read as u64 == MAX_LINE && !line.ends_with('\n') {
+        return Err(io::Error::new(ErrorKind::InvalidData, format!("request longer than {} bytes", MAX_LINE)));
+    }
+    Ok(read)
+}
+
+/// TLS settings: the agent certificate and a verifier requiring client certificates
+#[cfg(feature = "remote-agent")]
+fn server_config(options: &ServeOptions) -> Result<Arc<ServerConfig>, String> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+
+    let mut roots = RootCertStore::empty();
+    for certificate in load_certificates(&options.client_ca)? {
+        roots.add(certificate).map_err(|e| format!("Invalid client CA certificate in {}: {}", options.client_ca.display(), e))?;
+    }
+    let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), Arc::clone(&provider))
+        .build()
+        .map_err(|e| format!("Invalid client CA {}: {}", options.client_ca.display(), e))?;
+
+    let key = File::open(&options.key)
+        .map_err(|e| e.to_string())
+        .and_then(|file| rustls_pemfile::private_key(&mut BufReader::new(file)).map_err(|e| e.to_string()))
+        .and_then(|key: Option<PrivateKeyDer<'static>>| key.ok_or_else(|| "no private key found".to_string()))
+        .map_err(|e| format!("Failed to read the agent key {}: {}", options.key.display(), e))?;
+
+    let config = ServerConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .map_err(|e| e.to_string())?
+        .with_client_cert_verifier(verifier)
+        .with_single_cert(load_certificates(&options.cert)?, key)
+        .map_err(|e| format!("Invalid agent certificate or key: {}", e))?;
+    Ok(Arc::new(config))
+}
+
+#[cfg(feature = "remote-agent")]
+fn load_certificates(path: &Path) -> Result<Vec<CertificateDer<'static>>, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let certificates = rustls_pemfile::certs(&mut BufReader::new(file))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read certificates from {}: {}", path.display(), e))?;
+    if certificates.is_empty() {
+        return Err(format!("{} contains no PEM certificate", path.display()));
+    }
+    Ok(certificates)
+}
+
+#[cfg(all(test, feature = "remote-agent"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_server_config_reports_missing_material() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let not_pem = temp_dir.path().join("ca.pem");
+        std::fs::write(&not_pem, "not a certificate").unwrap();
+
+        let options = ServeOptions {
+            listen: DEFAULT_LISTEN.to_string(),
+            cert: temp_dir.path().join("agent.pem"),
+            key: temp_dir.path().join("agent.key"),
+            client_ca: not_pem,
+        };
+        let error = server_config(&options).unwrap_err();
+        assert!(error.contains("contains no PEM certificate"), "{}", error);
+    }
+
+    #[test]
+    fn test_read_request_limits_line_length() {
+        let mut line = String::new();
+        let mut input = io::Cursor::new(b"{\"id\": \"1\"}\n".to_vec());
+        assert_eq!(read_request(&mut input, &mut line).unwrap(), 12);
+
+        let mut flood = vec![b'x'; MAX_LINE as usize + 10];
+        flood.push(b'\n');
+        let error = read_request(&mut io::Cursor::new(flood), &mut line).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+}
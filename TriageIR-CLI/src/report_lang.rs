@@ -0,0 +1,163 @@
+//! Evidence package document languages (`--report-lang`)
+//! chain_of_custody.txt, README.txt and the collection summary in
+//! collection_audit.txt are rendered from per-language TOML templates embedded
+//! in the binary, so evidence can be handed to local law enforcement in their
+//! language. Field values (paths, hashes, log details) are never translated.
+
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReportLanguage {
+    #[default]
+    English,
+    German,
+    French,
+    Japanese,
+    Indonesian,
+}
+
+impl ReportLanguage {
+    /// ISO 639-1 codes accepted by `--report-lang`
+    pub const CODES: [&'static str; 5] = ["en", "de", "fr", "ja", "id"];
+
+    pub fn parse(code: &str) -> Result<Self, String> {
+        match code.trim().to_lowercase().as_str() {
+            "en" => Ok(ReportLanguage::English),
+            "de" => Ok(ReportLanguage::German),
+            "fr" => Ok(ReportLanguage::French),
+            "ja" => Ok(ReportLanguage::Japanese),
+            "id" => Ok(ReportLanguage::Indonesian),
+            _ => Err(format!("Unsupported report language '{}': expected one of {}", code, Self::CODES.join(", "))),
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            ReportLanguage::English => "en",
+            ReportLanguage::German => "de",
+            ReportLanguage::French => "fr",
+            ReportLanguage::Japanese => "ja",
+            ReportLanguage::Indonesian => "id",
+        }
+    }
+
+    fn template_source(&self) -> &'static str {
+        match self {
+            ReportLanguage::English => include_str!("report_templates/en.toml"),
+            ReportLanguage::German => include_str!("report_templates/de.toml"),
+            ReportLanguage::French => include_str!("report_templates/fr.toml"),
+            ReportLanguage::Japanese => include_str!("report_templates/ja.toml"),
+            ReportLanguage::Indonesian => include_str!("report_templates/id.toml"),
+        }
+    }
+}
+
+/// Templates of the evidence package documents in one language
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ReportTemplates {
+    pub custody: CustodyTemplates,
+    pub audit: AuditTemplates,
+    pub readme: ReadmeTemplates,
+}
+
+/// chain_of_custody.txt
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CustodyTemplates {
+    pub document: String,
+    pub legal_authority: String,
+    pub entry: String,
+}
+
+/// collection_audit.txt
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AuditTemplates {
+    pub document: String,
+    pub errors: String,
+    pub error_entry: String,
+    pub stack_trace: String,
+    pub warnings: String,
+    pub warning_entry: String,
+    pub log_entry: String,
+    pub log_duration: String,
+    pub external_commands: String,
+    pub external_command_entry: String,
+    pub external_stdout: String,
+    pub external_stderr: String,
+}
+
+/// README.txt
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ReadmeTemplates {
+    pub document: String,
+    pub encrypted: String,
+}
+
+impl ReportTemplates {
+    pub fn load(language: ReportLanguage) -> Result<Self, String> {
+        toml::from_str(language.template_source())
+            .map_err(|e| format!("Invalid {} report templates: {}", language.code(), e))
+    }
+}
+
+/// Fill the `{name}` placeholders of a template in a single pass, so braces in
+/// the values are copied verbatim; unknown placeholders are left as they are
+pub fn render(template: &str, values: &[(&str, &str)]) -> String {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(open) = rest.find('{') {
+        output.push_str(&rest[..open]);
+        let after = &rest[open + 1..];
+        let value = after.find('}').and_then(|close| {
+            let name = &after[..close];
+            values.iter().find(|(key, _)| *key == name).map(|(_, value)| (*value, close))
+        });
+        match value {
+            Some((value, close)) => {
+                output.push_str(value);
+                rest = &after[close + 1..];
+            }
+            None => {
+                output.push('{');
+                rest = after;
+            }
+        }
+    }
+
+    output.push_str(rest);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_all_languages_have_complete_templates() {
+        for code in ReportLanguage::CODES {
+            let language = ReportLanguage::parse(code).unwrap();
+            assert_eq!(language.code(), code);
+            let templates = ReportTemplates::load(language).unwrap_or_else(|e| panic!("{}", e));
+            for placeholder in ["{case_id}", "{evidence_id}", "{legal_authority}", "{entries}", "{evidence_hash}"] {
+                assert!(templates.custody.document.contains(placeholder), "{} custody lacks {}", code, placeholder);
+            }
+            assert!(templates.audit.document.contains("{errors}{warnings}{external_commands}"), "{} audit lacks error sections", code);
+            assert!(templates.audit.external_command_entry.contains("{command}"), "{} audit lacks the command line", code);
+            assert!(templates.readme.encrypted.contains("-iter {iterations}"), "{} readme lacks openssl command", code);
+        }
+        assert!(ReportLanguage::parse("xx").is_err());
+        assert_eq!(ReportLanguage::parse(" DE ").unwrap(), ReportLanguage::German);
+    }
+
+    #[test]
+    fn test_render() {
+        let values = [("name", "{case_id}"), ("case_id", "CASE-1")];
+        assert_eq!(render("Name: {name}, case {case_id}", &values), "Name: {case_id}, case CASE-1");
+        assert_eq!(render("{unknown} {case_id} {", &values), "{unknown} CASE-1 {");
+        assert_eq!(render("Fall-ID: {case_id}\n", &values), "Fall-ID: CASE-1\n");
+    }
+}
@@ -0,0 +1,1819 @@
+//! Scan orchestration
+//! Runs every collector in order and assembles the scan result JSON. Shared by
+//! the CLI and the C ABI (`ffi`), which report progress through a callback
+//! instead of printing.
+
+use crate::cancellation::{self, CollectorTimeouts};
+use crate::canaries::CanaryConfig;
+use crate::event_config::EventLogConfig;
+use crate::event_logs::{self, EventChannelQuery};
+use crate::forensic_types::{AuditEntry, CollectionStatistics, ShadowCopyInfo};
+use crate::hashing::HashAlgorithm;
+use crate::logger::error_handling::{handle_error_gracefully, ForensicError, ForensicResult};
+use crate::logger::{take_external_commands, LogSummary, Logger};
+use crate::offline::OfflineRoot;
+use crate::scan_profile::ScanProfile;
+use crate::stream_writer::StreamWriter;
+use crate::time_window::TimeWindow;
+use crate::types::{
+    Artifacts, CollectionSummary, ExecutionEvidenceSection, FilesystemArtifactSection, LogEntry, NetworkArtifactSection,
+    PersistenceArtifactSection, ResourceUsageSection, ScanResults, SecurityEventSection, SystemInfo, UserActivitySection,
+    VolatileArtifactSection,
+};
+use crate::{
+    amcache, attack, bam, browser_artifacts, canaries, certificate_stores, clipboard_history, collector_stats, custom_collectors, defender_detections, defender_logs, detections, dll_hijack, drivers, enrichment, environment, event_log_retention, file_collection, file_sweep, footprint, hashing, historical_processes, injection_scan, live_monitor, local_accounts, memory_dump, mft, module_load, mru, name_resolution, named_pipes, network, network_config, network_logs, notifications, persistence, plugins, powershell_history, prefetch, process_tree,
+    processes, remediation, remote_access, scope_check, screenshot, security_configuration, search_artifacts, search_history, sessions, setupapi, shimcache, srum, suspicion, sysmon_events, usb_devices, usn_journal, user_execution, vss,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use sysinfo::System;
+
+/// Top-level collection stages, for progress percentages
+const SCAN_STAGES: u32 = 21;
+
+/// Skip reason recorded in the collector stats of live-only collectors in offline mode
+const LIVE_ONLY: &str = "live-only collector (offline mode)";
+
+/// Collectors of desktop shell artifacts, skipped on hosts without one
+const USER_ACTIVITY_COLLECTORS: [&str; 7] = ["search_index", "thumbcache", "notifications", "clipboard_history", "search_history", "mru", "browser_artifacts"];
+
+/// Scan settings as accepted by the C ABI (JSON) and built from the CLI arguments
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScanConfig {
+    pub offline_root: Option<String>,
+    pub since: Option<String>,
+    pub until: Option<String>,
+    pub event_channels: Vec<String>, // CHANNEL[=XPATH]
+    pub event_config: Option<String>,
+    pub raw_dir: Option<String>,
+    pub plugins_dir: Option<String>,
+    pub collectors_config: Option<String>, // TOML of the external custom collectors
+    pub shadow_copy: Option<String>, // existing | create
+    pub hash_algorithms: Option<String>, // md5,sha1,sha256,imphash,ssdeep
+    pub fuzzy_hash: bool, // Same as adding ssdeep to hash_algorithms
+    pub deep_process_scan: bool, // Thread start addresses and unbacked executable memory of every process
+    pub scan_paths: Vec<String>, // Directories to sweep; * and ? wildcards per component
+    pub scan_extensions: Option<String>, // Comma-separated, * for every file
+    pub scan_max_size_mb: Option<u64>,
+    pub scan_depth: Option<usize>,
+    pub ioc_list: Option<String>, // Indicator file matched against the swept files
+    pub yara_rules: Option<String>,
+    pub canaries: Option<String>, // TOML list of decoy files, accounts and registry values
+    pub enrich: Vec<String>, // csv:PATH, misp:PATH, mmdb:PATH or virustotal
+    pub detection_rules: Option<String>, // Directory of YAML detection rules
+    pub suspicion_weights: Option<String>, // TOML threshold and indicator weights
+    pub collector_timeouts: Vec<String>, // SECS or COLLECTOR=SECS
+    pub max_memory_mb: Option<u64>, // Skip further collectors near this resident memory
+    pub profile: Option<String>, // quick | standard | deep | path to a TOML scan profile
+    pub disabled_collectors: Vec<String>, // collector_stats names
+    pub max_events: Option<usize>, // Per event log channel, unless an event config is given
+    pub max_hashed_processes: Option<usize>,
+    pub module_enumeration: Option<bool>, // Loader list of flagged processes, default on
+    pub monitor_seconds: Option<u64>, // ETW live activity window
+    pub verify_read_only: bool, // Fail the scan when anything of the examined system was opened for write
+    #[serde(skip)] // CLI only: the PNGs are written to the evidence package
+    pub screenshot: bool,
+    #[serde(skip)] // CLI only: the copies are written to the evidence package
+    pub collect_files: Vec<String>, // persistence, unsigned, prefetch or file paths
+    #[serde(skip)]
+    pub collect_budget_mb: Option<u64>,
+    #[serde(skip)] // CLI only: the export is written to the evidence package
+    pub collect_mft: Option<String>, // raw | csv
+    #[serde(skip)] // CLI only: the dumps are written to the evidence package
+    pub dump_processes: Vec<String>, // PIDs or auto
+    #[serde(skip)]
+    pub dump_type: Option<String>, // full | targeted
+}
+
+/// Validated scan settings
+#[derive(Default)]
+pub struct ScanOptions {
+    pub offline_root: Option<OfflineRoot>,
+    pub time_window: TimeWindow,
+    pub event_channel_queries: Vec<EventChannelQuery>,
+    pub raw_dir: Option<PathBuf>,
+    pub plugins_dir: Option<PathBuf>,
+    pub custom_collectors: Option<Arc<custom_collectors::CollectorRegistry>>, // Compiled-in and configured
+    pub shadow_copy: Option<vss::SnapshotMode>,
+    pub hash_algorithms: Vec<HashAlgorithm>,
+    pub file_sweep: Option<file_sweep::SweepOptions>,
+    pub canaries: Option<CanaryConfig>,
+    pub screenshot: bool,
+    pub collect_files: Option<file_collection::CollectOptions>,
+    pub collect_mft: Option<mft::MftFormat>,
+    pub process_dumps: Option<memory_dump::DumpOptions>,
+    pub deep_process_scan: bool,
+    pub enrichment: Option<Arc<enrichment::Enricher>>,
+    pub detection_rules: Vec<detections::DetectionRule>,
+    pub scoring: suspicion::ScoringModel,
+    pub warm_hash_cache: bool, // Worker scans reuse the digests of the previous scan
+    pub collector_timeouts: CollectorTimeouts,
+    pub memory_limit_mb: Option<u64>,
+    pub disabled_collectors: BTreeSet<String>,
+    pub max_hashed_processes: Option<usize>,
+    pub module_enumeration: bool,
+    pub monitor_window: Option<Duration>,
+    pub verify_read_only: bool,
+}
+
+impl ScanConfig {
+    /// Validate the settings; warnings are returned for the caller to surface
+    pub fn into_options(mut self) -> Result<(ScanOptions, Vec<String>), String> {
+        let mut warnings = Vec::new();
+        if let Some(profile) = self.profile.take() {
+            let applied = ScanProfile::resolve(&profile)?.apply(&mut self);
+            warnings.extend(applied.into_iter().map(|setting| format!("scan profile {}: {}", profile, setting)));
+        }
+        let time_window = TimeWindow::new(self.since.as_deref(), self.until.as_deref())?;
+
+        let mut event_channel_queries = match (self.event_channels.is_empty(), &self.event_config) {
+            (false, Some(_)) => return Err("event channels and an event config profile are mutually exclusive".to_string()),
+            (false, None) => self.event_channels.iter().map(|a| EventChannelQuery::parse(a)).collect::<Result<Vec<_>, _>>()?,
+            (true, Some(config_path)) => EventLogConfig::load(Path::new(config_path))?.channel_queries(),
+            (true, None) => event_logs::default_channel_queries(),
+        };
+        if let Some(max_events) = self.max_events.filter(|_| self.event_config.is_none()) {
+            if max_events == 0 {
+                return Err("the event limit must be at least 1".to_string());
+            }
+            for query in &mut event_channel_queries {
+                query.max_events = max_events;
+            }
+        }
+
+        for query in &mut event_channel_queries {
+            if !query.restrict_to(&time_window) {
+                warnings.push(format!(
+                    "custom XPath for {} cannot be narrowed to the time window; events are filtered after collection",
+                    query.channel
+                ));
+            }
+        }
+
+        let offline_root = self.offline_root.map(OfflineRoot::new).transpose()?;
+        let shadow_copy = self.shadow_copy.as_deref().map(vss::SnapshotMode::parse).transpose()?;
+        if offline_root.is_some() && shadow_copy.is_some() {
+            return Err("shadow copies can only be used on live scans, not with an offline root".to_string());
+        }
+        if offline_root.is_some() && self.screenshot {
+            return Err("screenshots can only be taken on live scans, not with an offline root".to_string());
+        }
+        if offline_root.is_some() && self.monitor_seconds.is_some() {
+            return Err("live activity can only be monitored on live scans, not with an offline root".to_string());
+        }
+        if offline_root.is_some() && self.deep_process_scan {
+            return Err("the deep process scan needs live processes, not an offline root".to_string());
+        }
+
+        let mut hash_algorithms = HashAlgorithm::parse_list(self.hash_algorithms.as_deref().unwrap_or_default())?;
+        if self.fuzzy_hash && !hash_algorithms.contains(&HashAlgorithm::Ssdeep) {
+            hash_algorithms.push(HashAlgorithm::Ssdeep);
+        }
+        let file_sweep = if self.scan_paths.is_empty() {
+            let sweep_settings = [self.scan_extensions.is_some(), self.scan_max_size_mb.is_some(), self.scan_depth.is_some(), self.ioc_list.is_some(), self.yara_rules.is_some()];
+            if sweep_settings.contains(&true) {
+                return Err("scan extensions, size, depth, IOC list and YARA rules require scan paths".to_string());
+            }
+            None
+        } else {
+            if self.yara_rules.is_some() && !cfg!(feature = "yara-engine") {
+                return Err(file_sweep::YARA_UNAVAILABLE.to_string());
+            }
+            Some(file_sweep::SweepOptions {
+                paths: self.scan_paths,
+                max_file_size: self.scan_max_size_mb.unwrap_or(file_sweep::DEFAULT_MAX_FILE_SIZE_MB).saturating_mul(1024 * 1024),
+                max_depth: self.scan_depth.unwrap_or(file_sweep::DEFAULT_MAX_DEPTH),
+                extensions: match &self.scan_extensions {
+                    Some(list) => file_sweep::SweepOptions::parse_extensions(list)?,
+                    None => file_sweep::SweepOptions::default_extensions(),
+                },
+                indicators: self.ioc_list.as_deref().map(|path| scope_check::load_indicators(Path::new(path))).transpose()?.unwrap_or_default(),
+                yara_rules: self.yara_rules.map(PathBuf::from),
+            })
+        };
+
+        let canaries = self.canaries.as_deref().map(|path| CanaryConfig::load(Path::new(path))).transpose()?;
+        let collect_files = match (self.collect_files.is_empty(), self.collect_budget_mb) {
+            (true, Some(_)) => return Err("a collection budget requires files to collect".to_string()),
+            (true, None) => None,
+            (false, budget_mb) => Some(file_collection::CollectOptions::parse(&self.collect_files, budget_mb)?),
+        };
+        let collect_mft = self.collect_mft.as_deref().map(mft::MftFormat::parse).transpose()?;
+        let process_dumps = match (self.dump_processes.is_empty(), &self.dump_type) {
+            (true, Some(_)) => return Err("a dump type requires processes to dump".to_string()),
+            (true, None) => None,
+            (false, _) if offline_root.is_some() => return Err("process memory can only be dumped on live scans, not with an offline root".to_string()),
+            (false, dump_type) => Some(memory_dump::DumpOptions::parse(&self.dump_processes, dump_type.as_deref())?),
+        };
+        let enrichment = (!self.enrich.is_empty()).then(|| enrichment::Enricher::from_sources(&self.enrich).map(Arc::new)).transpose()?;
+        let detection_rules = self.detection_rules.as_deref().map(|dir| detections::load_rules(Path::new(dir))).transpose()?.unwrap_or_default();
+        let scoring = self.suspicion_weights.as_deref().map(|path| suspicion::ScoringModel::load(Path::new(path))).transpose()?.unwrap_or_default();
+        let collector_timeouts = CollectorTimeouts::parse(&self.collector_timeouts)?;
+        let mut registry = custom_collectors::CollectorRegistry::builtin();
+        if let Some(path) = &self.collectors_config {
+            registry.load_config(Path::new(path))?;
+        }
+        let custom_collectors = (!registry.is_empty()).then(|| Arc::new(registry));
+        let monitor_window = self.monitor_seconds.map(live_monitor::parse_window).transpose()?;
+        if self.max_memory_mb == Some(0) {
+            return Err("the memory limit must be at least 1 MB".to_string());
+        }
+
+        let options = ScanOptions {
+            offline_root,
+            time_window,
+            event_channel_queries,
+            raw_dir: self.raw_dir.map(PathBuf::from),
+            plugins_dir: self.plugins_dir.map(PathBuf::from),
+            custom_collectors,
+            shadow_copy,
+            hash_algorithms,
+            file_sweep,
+            canaries,
+            screenshot: self.screenshot,
+            collect_files,
+            collect_mft,
+            process_dumps,
+            deep_process_scan: self.deep_process_scan,
+            enrichment,
+            detection_rules,
+            scoring,
+            warm_hash_cache: false,
+            collector_timeouts,
+            memory_limit_mb: self.max_memory_mb,
+            disabled_collectors: self.disabled_collectors.into_iter().collect(),
+            max_hashed_processes: self.max_hashed_processes,
+            module_enumeration: self.module_enumeration.unwrap_or(true),
+            monitor_window,
+            verify_read_only: self.verify_read_only,
+        };
+        Ok((options, warnings))
+    }
+}
+
+/// Progress notification passed to the caller's callback
+#[derive(Debug, Clone)]
+pub struct ScanProgress {
+    pub phase: &'static str, // Stable identifier of the current stage: setup, processes, ..., complete
+    pub stage: u32, // 1-based index of the current top-level stage
+    pub stages: u32,
+    pub percent: u32,
+    pub message: String,
+    pub artifacts: usize, // Collected so far
+    pub errors: usize,    // Logged so far
+    pub warnings: usize,
+}
+
+/// Result of a completed scan
+pub struct ScanOutcome {
+    pub results: Value,
+    pub scan_results: ScanResults,
+    pub statistics: CollectionStatistics,
+    pub summary: Vec<String>, // One line per artifact type, for the verbose report
+    pub total_artifacts: usize,
+    pub duration: Duration,
+    pub log_summary: LogSummary,
+    pub screenshots: Vec<screenshot::CapturedScreen>, // PNGs for the evidence package
+    pub collected_files: Vec<file_collection::PackageEntry>, // files/ entries of the evidence package
+    pub memory_dumps: Vec<memory_dump::CapturedDump>, // Minidumps for the evidence package
+    pub interrupted: bool, // Stopped with Ctrl+C; the results are partial
+}
+
+struct Progress<'a> {
+    callback: &'a mut dyn FnMut(&ScanProgress),
+    logger: &'a Logger,
+    phase: &'static str,
+    stage: u32,
+    artifacts: usize,
+}
+
+impl Progress<'_> {
+    fn begin_stage(&mut self, phase: &'static str, message: &str) {
+        self.phase = phase;
+        self.stage += 1;
+        self.report(message);
+    }
+
+    fn report(&mut self, message: &str) {
+        self.send(self.stage.saturating_sub(1) * 100 / SCAN_STAGES, message);
+    }
+
+    /// Report the end of a collection step and count its artifacts
+    fn completed(&mut self, artifacts: usize, message: &str) {
+        self.artifacts += artifacts;
+        self.report(message);
+    }
+
+    fn finish(&mut self, total_artifacts: usize, message: &str) {
+        self.phase = "complete";
+        self.artifacts = total_artifacts;
+        self.send(100, message);
+    }
+
+    fn send(&mut self, percent: u32, message: &str) {
+        let log_summary = self.logger.get_summary();
+        let progress = ScanProgress {
+            phase: self.phase,
+            stage: self.stage,
+            stages: SCAN_STAGES,
+            percent: percent.min(100),
+            message: message.to_string(),
+            artifacts: self.artifacts,
+            errors: log_summary.error_count,
+            warnings: log_summary.warn_count,
+        };
+        (self.callback)(&progress);
+    }
+}
+
+/// Run all collectors and build the scan result JSON
+/// With a `stream`, the bulk sections are handed to it as their collectors finish and are left
+/// empty in the returned results; `Spool::write_document` puts them back.
+pub fn run_scan(options: &ScanOptions, logger: &Logger, stream: Option<&StreamWriter>, callback: &mut dyn FnMut(&ScanProgress)) -> ScanOutcome {
+    let ScanOptions { offline_root, time_window, event_channel_queries, raw_dir, plugins_dir, custom_collectors, shadow_copy, hash_algorithms, file_sweep, canaries: canary_config, screenshot, collect_files, collect_mft, process_dumps, deep_process_scan, enrichment, detection_rules, scoring, warm_hash_cache, collector_timeouts, memory_limit_mb, disabled_collectors, max_hashed_processes, module_enumeration, monitor_window, verify_read_only } = options;
+    let mut progress = Progress { callback, logger, phase: "setup", stage: 0, artifacts: 0 };
+    let start_time = std::time::Instant::now();
+    cancellation::reset_interrupt();
+    
+    // Initialize scan results with proper error handling
+    let (hostname, os_version) = match offline_root {
+        Some(root) => (
+            root.hostname().unwrap_or_else(|| "Unknown".to_string()),
+            root.os_version().unwrap_or_else(|| "Unknown".to_string()),
+        ),
+        None => (
+            std::env::var("COMPUTERNAME").unwrap_or_else(|_| "Unknown".to_string()),
+            System::os_version().unwrap_or_else(|| "Unknown".to_string()),
+        ),
+    };
+    let mut scan_results = ScanResults::new(hostname.clone(), os_version.clone());
+    if let Some(root) = offline_root {
+        scan_results.scan_metadata.offline = true;
+        scan_results.scan_metadata.offline_root = Some(root.root().display().to_string());
+        logger.info(&format!("Running in OFFLINE MODE against volume root: {}", root.root().display()));
+    }
+    scan_results.scan_metadata.since = time_window.since.map(|since| since.to_rfc3339());
+    scan_results.scan_metadata.until = time_window.until.map(|until| until.to_rfc3339());
+    
+    let current_user = std::env::var("USERNAME").unwrap_or_else(|_| "Unknown".to_string());
+    logger.info(&format!("Target system: {}", hostname));
+    logger.info(&format!("OS Version: {}", os_version));
+    logger.info(&format!("Current user: {}", current_user));
+    
+    if let Some(root) = offline_root {
+        progress.report(&format!("Offline root: {}", root.root().display()));
+    }
+    progress.report(&format!("Target system: {}", hostname));
+    progress.report(&format!("OS Version: {}", os_version));
+    progress.report(&format!("Current user: {}", current_user));
+    progress.report(&format!("Scan ID: {}\n", scan_results.scan_metadata.scan_id));
+    
+    // Containers, Server Core and Nano Server lack the desktop shell and its artifacts
+    let (host_environment, environment_logs) = match offline_root {
+        Some(root) => environment::detect_environment_offline(root),
+        None => environment::detect_environment(),
+    };
+    add_audit_entries(&mut scan_results, &environment_logs);
+    let no_shell = environment::shell_skip_reason(&host_environment);
+    logger.info(&format!("Host environment: {}", host_environment.environment_type));
+    if let Some(reason) = &no_shell {
+        logger.info(&format!("Desktop shell artifacts skipped: {}", reason));
+        progress.report(&format!("Environment: {}, desktop shell artifacts skipped", host_environment.environment_type));
+    }
+    
+    // One hashing service, and cache, shared by every collector of this scan
+    if !*warm_hash_cache {
+        hashing::configure(hash_algorithms.clone());
+    } else if hashing::configure_warm(hash_algorithms.clone()) {
+        logger.info("Hash cache kept from the previous scan");
+    }
+    let hashes = hashing::shared();
+    logger.info(&format!("Hash algorithms: {}", hashes.algorithms().iter().map(|a| a.name()).collect::<Vec<_>>().join(", ")));
+    suspicion::configure(scoring.clone());
+    take_external_commands(); // Left over from a previous scan of this worker
+    footprint::take();
+    
+    // Document what is on screen before the collection changes anything
+    let screenshots = if let (true, Some(reason)) = (*screenshot, &no_shell) {
+        logger.warn(&format!("Screenshots skipped: {}", reason));
+        Vec::new()
+    } else if *screenshot {
+        let (screens, screenshot_logs) = screenshot::capture_screenshots();
+        add_audit_entries(&mut scan_results, &screenshot_logs);
+        logger.info(&format!("Screenshots captured: {} monitors", screens.len()));
+        progress.report(&format!("✓ Screenshots captured ({} monitors)", screens.len()));
+        screens
+    } else {
+        Vec::new()
+    };
+    
+    // Watch what happens on the host while the collection runs
+    let live_monitor = match monitor_window {
+        Some(window) => match live_monitor::LiveMonitor::start(*window) {
+            Ok(monitor) => {
+                logger.info(&format!("Live activity monitor started for {} seconds", window.as_secs()));
+                progress.report(&format!("✓ Live activity monitor started ({} seconds)", window.as_secs()));
+                Some(monitor)
+            }
+            Err(e) => {
+                logger.warn(&format!("Live activity monitor not started: {}", e));
+                scan_results.add_log(LogEntry::new("WARN", &format!("Live activity monitor not started: {}", e)));
+                None
+            }
+        },
+        None => None,
+    };
+    
+    // Select the shadow copy that locked files are read from
+    let shadow_copies = match shadow_copy {
+        Some(mode) => prepare_shadow_copy(*mode, &mut scan_results, logger, &mut progress),
+        None => Vec::new(),
+    };
+    let snapshot = vss::active_snapshot();
+    
+    let mut stats = collector_stats::CollectorStatsRecorder::new().with_timeouts(collector_timeouts.clone()).with_memory_limit(*memory_limit_mb).with_disabled(disabled_collectors.clone());
+    
+    // Initialize system information collector with error handling
+    progress.begin_stage("system_info", "🔍 Collecting system information...");
+    let system_info_result = if offline_root.is_some() {
+        logger.info("System information collection skipped: live-only collector (offline mode)");
+        stats.skip("system_info", LIVE_ONLY);
+        None
+    } else {
+        let run = stats.start("system_info");
+        let info = collect_system_info_safe(logger);
+        stats.finish(run, info.is_some() as usize, 0, info.is_none() as usize);
+        info
+    };
+    let mut system_info = match system_info_result {
+        Some(info) => {
+            logger.info("System information collected successfully");
+            progress.report("✓ System information collected");
+            info
+        }
+        None if offline_root.is_some() => SystemInfo {
+            hostname: hostname.clone(),
+            os_name: "Windows_NT".to_string(),
+            os_version: os_version.clone(),
+            architecture: "Unknown".to_string(),
+            current_user: "Unknown".to_string(),
+            last_boot_time: "Unknown".to_string(),
+            ..Default::default()
+        },
+        None => {
+            logger.error("Failed to collect system information, using defaults");
+            progress.report("⚠ System information collection failed, using defaults");
+            SystemInfo {
+                hostname: hostname.clone(),
+                os_name: "Unknown".to_string(),
+                os_version: "Unknown".to_string(),
+                architecture: std::env::consts::ARCH.to_string(),
+                current_user: "Unknown".to_string(),
+                last_boot_time: chrono::Utc::now().to_rfc3339(),
+                ..Default::default()
+            }
+        }
+    };
+    
+    // Logged-on user sessions (live-only)
+    if offline_root.is_some() {
+        stats.skip("sessions", LIVE_ONLY);
+    } else {
+        let run = stats.start("sessions");
+        let (logged_on_users, session_logs) = stats.bounded(&run, (), |()| sessions::collect_logged_on_users());
+        stats.finish(run, logged_on_users.len(), 0, collector_stats::audit_errors(&session_logs));
+        add_audit_entries(&mut scan_results, &session_logs);
+        logger.info(&format!("Session enumeration completed: {} logged-on user sessions", logged_on_users.len()));
+        system_info.logged_on_users = logged_on_users;
+    }
+    
+    // Collect running processes with comprehensive error handling
+    progress.begin_stage("processes", "🔍 Enumerating running processes...");
+    logger.info("Starting process enumeration");
+    let (mut processes_data, process_logs) = if offline_root.is_some() {
+        stats.skip("processes", LIVE_ONLY);
+        (Vec::new(), vec![LogEntry::info("Process enumeration skipped: live-only collector (offline mode)")])
+    } else {
+        let run = stats.start("processes");
+        let (processes, logs) = stats.bounded(&run, *max_hashed_processes, processes::collect_processes_with_hash_limit);
+        stats.finish(run, processes.len(), 0, collector_stats::log_errors(&logs));
+        (processes, logs)
+    };
+    
+    // Add process logs to main logger
+    for log in &process_logs {
+        scan_results.add_log(log.clone());
+    }
+    
+    // Threads starting outside every image and unbacked RWX memory, scored with the tree findings
+    if *deep_process_scan && !processes_data.is_empty() {
+        for log in injection_scan::scan_process_memory(&mut processes_data) {
+            scan_results.add_log(log);
+        }
+    }
+    
+    // Reconstruct the process tree and flag orphans, PPID anomalies and suspicious parent/child pairs
+    let (process_tree, process_tree_logs) = process_tree::analyze_process_tree(&mut processes_data);
+    for log in &process_tree_logs {
+        scan_results.add_log(log.clone());
+    }
+    scoring.score_processes(&mut processes_data);
+    let flagged_processes = processes_data.iter().filter(|p| !p.suspicious_indicators.is_empty()).count();
+    let deleted_executables = processes_data.iter().filter(|p| p.executable_deleted).count();
+    
+    // Load order and load times of the flagged processes' modules show DLLs loaded long after start
+    if offline_root.is_none() && *module_enumeration && flagged_processes > 0 {
+        for log in module_load::collect_module_load_order(&mut processes_data) {
+            scan_results.add_log(log);
+        }
+    }
+    
+    // Non-system DLLs shadowing a System32 DLL from the application directory or a writable path
+    let dll_hijacks = if offline_root.is_some() {
+        stats.skip("dll_hijacks", LIVE_ONLY);
+        Vec::new()
+    } else {
+        let run = stats.start("dll_hijacks");
+        let (hijacks, hijack_logs) = if stats.disabled(&run) { Default::default() } else { dll_hijack::detect_dll_hijacks(&processes_data) };
+        add_audit_entries(&mut scan_results, &hijack_logs);
+        stats.finish(run, hijacks.len(), 0, collector_stats::audit_errors(&hijack_logs));
+        hijacks
+    };
+    
+    // Processes still running are left out of the historical processes of the event logs
+    let running_images: Vec<(u32, String)> = processes_data.iter().map(|p| (p.pid, p.executable_path.clone())).collect();
+    
+    // The acquisition stages run last and need the typed records
+    let acquisition_processes = if collect_files.is_some() || process_dumps.is_some() { processes_data.clone() } else { Vec::new() };
+    let mut processes = processes_data;
+    if let Some(enricher) = enrichment {
+        for process in &mut processes {
+            process.threat_intelligence = enricher.enrich_hashes(&process.sha256_hash, process.hashes.as_ref());
+        }
+    }
+    
+    logger.info(&format!("Process enumeration completed: {} processes collected, {} flagged by process tree analysis", processes.len(), flagged_processes));
+    progress.completed(processes.len() + dll_hijacks.len(), &format!("✓ Process enumeration completed ({} processes, {} flagged)", processes.len(), flagged_processes));
+    logger.info(&format!("DLL search-order hijack check completed: {} candidates", dll_hijacks.len()));
+    if deleted_executables > 0 {
+        logger.warn(&format!("{} processes are running from executables deleted from disk", deleted_executables));
+    }
+    
+    // Enumerate loaded kernel drivers (live-only)
+    progress.begin_stage("drivers", "🔍 Enumerating loaded kernel drivers...");
+    let loaded_drivers = if offline_root.is_some() {
+        logger.info("Kernel driver enumeration skipped: live-only collector (offline mode)");
+        stats.skip("drivers", LIVE_ONLY);
+        Vec::new()
+    } else {
+        let run = stats.start("drivers");
+        let (drivers, driver_logs) = stats.bounded(&run, (), |()| drivers::collect_loaded_drivers());
+        stats.finish(run, drivers.len(), 0, collector_stats::audit_errors(&driver_logs));
+        add_audit_entries(&mut scan_results, &driver_logs);
+        drivers
+    };
+    
+    let flagged_drivers = loaded_drivers.iter().filter(|d| !d.suspicious_indicators.is_empty()).count();
+    logger.info(&format!("Kernel driver enumeration completed: {} drivers, {} flagged", loaded_drivers.len(), flagged_drivers));
+    progress.completed(loaded_drivers.len(), &format!("✓ Kernel driver enumeration completed ({} drivers, {} flagged)", loaded_drivers.len(), flagged_drivers));
+    
+    // Collect network connections with error handling
+    progress.begin_stage("network", "🔍 Analyzing network connections...");
+    logger.info("Starting network connection enumeration");
+    let (network_connections_data, network_logs) = if offline_root.is_some() {
+        stats.skip("network", LIVE_ONLY);
+        (Vec::new(), vec![LogEntry::info("Network enumeration skipped: live-only collector (offline mode)")])
+    } else {
+        let run = stats.start("network");
+        let (connections, logs) = stats.bounded(&run, (), |()| network::collect_network_connections());
+        stats.finish(run, connections.len(), 0, collector_stats::log_errors(&logs));
+        (connections, logs)
+    };
+    
+    // Add network logs to main logger
+    for log in &network_logs {
+        scan_results.add_log(log.clone());
+    }
+    
+    let rdp_connections = remote_access::rdp_connections(&network_connections_data);
+    
+    let mut network_connections = network_connections_data;
+    if let Some(enricher) = enrichment {
+        for connection in &mut network_connections {
+            let intel = enricher.enrich_ip(&connection.remote_address);
+            connection.geolocation = intel.geolocation;
+            connection.asn = intel.asn;
+            connection.threat_intelligence = intel.threat;
+        }
+    }
+    
+    logger.info(&format!("Network enumeration completed: {} connections collected", network_connections.len()));
+    progress.completed(network_connections.len(), &format!("✓ Network analysis completed ({} connections)", network_connections.len()));
+    
+    if let Some(enricher) = enrichment {
+        let enrichment_logs = enricher.take_audit_log();
+        add_audit_entries(&mut scan_results, &enrichment_logs);
+        if let Some(summary) = enrichment_logs.last() {
+            logger.info(&summary.details);
+            progress.report(&format!("✓ Threat intel enrichment: {}", summary.details));
+        }
+    }
+    
+    // Enumerate named pipes and mailslots (live-only)
+    progress.begin_stage("named_pipes", "🔍 Enumerating named pipes...");
+    let named_pipes = if offline_root.is_some() {
+        logger.info("Named pipe enumeration skipped: live-only collector (offline mode)");
+        stats.skip("named_pipes", LIVE_ONLY);
+        Vec::new()
+    } else {
+        let run = stats.start("named_pipes");
+        let (pipes, pipe_logs) = stats.bounded(&run, (), |()| named_pipes::collect_named_pipes());
+        stats.finish(run, pipes.len(), 0, collector_stats::audit_errors(&pipe_logs));
+        add_audit_entries(&mut scan_results, &pipe_logs);
+        pipes
+    };
+    
+    let flagged_pipes = named_pipes.iter().filter(|p| !p.suspicious_indicators.is_empty()).count();
+    logger.info(&format!("Named pipe enumeration completed: {} pipes and mailslots, {} flagged", named_pipes.len(), flagged_pipes));
+    progress.completed(named_pipes.len(), &format!("✓ Named pipe enumeration completed ({} pipes and mailslots, {} flagged)", named_pipes.len(), flagged_pipes));
+    
+    // RDP and SMB lateral movement artifacts
+    progress.begin_stage("remote_access", "🔍 Collecting RDP and SMB session artifacts...");
+    let run = stats.start("rdp_history");
+    let (rdp_connection_history, rdp_history_logs) = stats.bounded(&run, offline_root.clone(), |offline_root| match &offline_root {
+        Some(root) => remote_access::collect_rdp_history_offline(root),
+        None => remote_access::collect_rdp_history(),
+    });
+    stats.finish(run, rdp_connection_history.len(), 0, collector_stats::audit_errors(&rdp_history_logs));
+    add_audit_entries(&mut scan_results, &rdp_history_logs);
+    
+    let run = stats.start("rdp_session_events");
+    let (rdp_session_events, rdp_event_logs) = stats.bounded(&run, (offline_root.clone(), time_window.clone()), |(offline_root, time_window)| {
+        remote_access::collect_rdp_session_events(offline_root.as_ref(), &time_window)
+    });
+    stats.finish(run, rdp_session_events.len(), 0, collector_stats::audit_errors(&rdp_event_logs));
+    add_audit_entries(&mut scan_results, &rdp_event_logs);
+    
+    let (smb_sessions, smb_open_files) = if offline_root.is_some() {
+        logger.info("SMB session enumeration skipped: live-only collector (offline mode)");
+        stats.skip("smb_sessions", LIVE_ONLY);
+        (Vec::new(), Vec::new())
+    } else {
+        let run = stats.start("smb_sessions");
+        let (sessions, open_files, smb_logs) = stats.bounded(&run, (), |()| remote_access::collect_smb_sessions());
+        stats.finish(run, sessions.len() + open_files.len(), 0, collector_stats::audit_errors(&smb_logs));
+        add_audit_entries(&mut scan_results, &smb_logs);
+        (sessions, open_files)
+    };
+    
+    let remote_access_artifacts = rdp_connections.len() + rdp_connection_history.len() + rdp_session_events.len() + smb_sessions.len() + smb_open_files.len();
+    logger.info(&format!("RDP/SMB collection completed: {} RDP connections, {} RDP client history entries, {} RDP session events, {} SMB sessions, {} SMB open files",
+        rdp_connections.len(), rdp_connection_history.len(), rdp_session_events.len(), smb_sessions.len(), smb_open_files.len()));
+    progress.completed(remote_access_artifacts, &format!("✓ RDP/SMB collection completed ({} artifacts)", remote_access_artifacts));
+    
+    // Shares, firewall rules and proxy settings
+    progress.begin_stage("network_configuration", "🔍 Collecting shares, firewall rules and proxy settings...");
+    let run = stats.start("network_configuration");
+    let (network_configuration, network_configuration_logs) = stats.bounded(&run, offline_root.clone(), |offline_root| match &offline_root {
+        Some(root) => network_config::collect_network_configuration_offline(root),
+        None => network_config::collect_network_configuration(),
+    });
+    let network_configuration_count = network_configuration.network_shares.len() + network_configuration.firewall_rules.len() + network_configuration.user_proxy_settings.len();
+    stats.finish(run, network_configuration_count, 0, collector_stats::audit_errors(&network_configuration_logs));
+    add_audit_entries(&mut scan_results, &network_configuration_logs);
+    
+    let anonymous_shares = network_configuration.network_shares.iter().filter(|share| share.anonymous_access).count();
+    let allow_all_rules = network_configuration.firewall_rules.iter().filter(|rule| rule.allow_all).count();
+    logger.info(&format!("Network configuration collection completed: {} shares ({} anonymous), {} firewall rules ({} allow all inbound), {} user proxies",
+        network_configuration.network_shares.len(), anonymous_shares, network_configuration.firewall_rules.len(), allow_all_rules, network_configuration.user_proxy_settings.len()));
+    progress.completed(network_configuration_count, &format!("✓ Network configuration collected ({} shares, {} firewall rules)",
+        network_configuration.network_shares.len(), network_configuration.firewall_rules.len()));
+    
+    // Hosts file, Winsock catalog, NRPT and DNS server tampering
+    progress.begin_stage("name_resolution", "🔍 Checking hosts file, Winsock providers and DNS settings...");
+    let run = stats.start("name_resolution");
+    let (name_resolution, name_resolution_logs) = stats.bounded(&run, offline_root.clone(), |offline_root| match &offline_root {
+        Some(root) => name_resolution::collect_name_resolution_offline(root),
+        None => name_resolution::collect_name_resolution(),
+    });
+    let hosts_entries = name_resolution.hosts_file.as_ref().map_or(0, |hosts| hosts.entries.len());
+    let name_resolution_count = hosts_entries + name_resolution.winsock_providers.len() + name_resolution.nrpt_rules.len() + name_resolution.dns_servers.len();
+    stats.finish(run, name_resolution_count, 0, collector_stats::audit_errors(&name_resolution_logs));
+    add_audit_entries(&mut scan_results, &name_resolution_logs);
+    
+    let name_resolution_findings = name_resolution.hosts_file.iter().flat_map(|hosts| &hosts.entries).filter(|entry| entry.suspicious).count()
+        + name_resolution.winsock_providers.iter().filter(|provider| provider.suspicious).count()
+        + name_resolution.nrpt_rules.iter().filter(|rule| rule.suspicious).count()
+        + name_resolution.dns_servers.iter().filter(|setting| setting.suspicious).count();
+    logger.info(&format!("Name resolution check completed: {} hosts entries, {} Winsock providers, {} NRPT rules, {} DNS server settings ({} suspicious)",
+        hosts_entries, name_resolution.winsock_providers.len(), name_resolution.nrpt_rules.len(), name_resolution.dns_servers.len(), name_resolution_findings));
+    progress.completed(name_resolution_count, &format!("✓ Name resolution settings checked ({} entries, {} suspicious)", name_resolution_count, name_resolution_findings));
+    
+    // Firewall and DNS logs, when enabled on the host
+    progress.begin_stage("host_network_logs", "🔍 Reading firewall and DNS logs...");
+    let run = stats.start("network_logs");
+    let (host_network_logs, network_log_audit) = stats.bounded(&run, (offline_root.clone(), time_window.clone()), |(offline_root, time_window)| {
+        network_logs::collect_network_logs(offline_root.as_ref(), &time_window)
+    });
+    stats.finish(run, host_network_logs.firewall.len() + host_network_logs.dns.len(), 0, collector_stats::audit_errors(&network_log_audit));
+    add_audit_entries(&mut scan_results, &network_log_audit);
+    
+    logger.info(&format!("Firewall and DNS log collection completed: {} firewall records, {} DNS records",
+        host_network_logs.firewall.len(), host_network_logs.dns.len()));
+    progress.completed(host_network_logs.firewall.len() + host_network_logs.dns.len(), &format!("✓ Firewall and DNS logs read ({} firewall records, {} DNS records)",
+        host_network_logs.firewall.len(), host_network_logs.dns.len()));
+    
+    // Collect persistence mechanisms with error handling
+    progress.begin_stage("persistence", "🔍 Detecting persistence mechanisms...");
+    logger.info("Starting persistence mechanism detection");
+    let run = stats.start("persistence");
+    let (persistence_mechanisms_data, scheduled_tasks, persistence_logs) = stats.bounded(&run, offline_root.clone(), |offline_root| match &offline_root {
+        Some(root) => {
+            let (mechanisms, logs) = persistence::collect_persistence_mechanisms_offline(root);
+            (mechanisms, Vec::new(), logs)
+        }
+        None => persistence::collect_persistence(),
+    });
+    stats.finish(run, persistence_mechanisms_data.len(), 0, collector_stats::log_errors(&persistence_logs));
+    
+    // Add persistence logs to main logger
+    for log in &persistence_logs {
+        scan_results.add_log(log.clone());
+    }
+    
+    let suspicious_mechanisms = persistence_mechanisms_data.iter().filter(|m| m.is_suspicious).count();
+    let acquisition_mechanisms = if collect_files.is_some() { persistence_mechanisms_data.clone() } else { Vec::new() };
+    let persistence_mechanisms = persistence_mechanisms_data;
+    
+    logger.info(&format!("Persistence detection completed: {} mechanisms found, {} scoring {} or more", persistence_mechanisms.len(), suspicious_mechanisms, scoring.threshold));
+    progress.completed(persistence_mechanisms.len(), &format!("✓ Persistence detection completed ({} mechanisms, {} suspicious)", persistence_mechanisms.len(), suspicious_mechanisms));
+    
+    // Winlogon, IFEO debugger, AppInit_DLLs/AppCertDlls and search path hijacks, scored for suspicion
+    let run = stats.start("hijacks");
+    let (hijack_artifacts, hijack_logs) = stats.bounded(&run, offline_root.clone(), |offline_root| match &offline_root {
+        Some(root) => persistence::collect_hijack_artifacts_offline(root),
+        None => persistence::collect_hijack_artifacts(),
+    });
+    let hijack_entries = hijack_artifacts.winlogon_entries.len() + hijack_artifacts.image_hijacks.len() + hijack_artifacts.appinit_dlls.len() + hijack_artifacts.path_hijacks.len();
+    let suspicious_hijacks = hijack_artifacts.winlogon_entries.iter().map(|e| e.suspicion_score)
+        .chain(hijack_artifacts.image_hijacks.iter().map(|h| h.suspicion_score))
+        .chain(hijack_artifacts.appinit_dlls.iter().map(|e| e.suspicion_score))
+        .chain(hijack_artifacts.path_hijacks.iter().map(|h| h.suspicion_score))
+        .filter(|score| *score >= persistence::HIJACK_SUSPICION_THRESHOLD)
+        .count();
+    stats.finish(run, hijack_entries, 0, collector_stats::log_errors(&hijack_logs));
+    for log in &hijack_logs {
+        scan_results.add_log(log.clone());
+    }
+    logger.info(&format!("Winlogon, IFEO, AppInit and search path checks completed: {} entries, {} suspicious", hijack_entries, suspicious_hijacks));
+    progress.completed(hijack_entries, &format!("✓ Winlogon, IFEO, AppInit and search path checks completed ({} entries, {} suspicious)", hijack_entries, suspicious_hijacks));
+    
+    // Collect event logs with error handling
+    progress.begin_stage("event_logs", "🔍 Collecting event logs...");
+    logger.info("Starting event log collection");
+    let run = stats.start("event_logs");
+    let (mut event_logs_data, event_logs_collection_logs) = stats.bounded(&run, (event_channel_queries.clone(), offline_root.clone()), |(queries, offline_root)| {
+        event_logs::collect_event_logs_with_queries(&queries, offline_root.as_ref())
+    });
+    
+    // Add event log collection logs to main logger
+    for log in &event_logs_collection_logs {
+        scan_results.add_log(log.clone());
+    }
+    
+    let mut events_skipped = apply_time_window(&mut scan_results, time_window, &mut event_logs_data.security, "Security events", |e| &e.timestamp)
+        + apply_time_window(&mut scan_results, time_window, &mut event_logs_data.system, "System events", |e| &e.timestamp)
+        + apply_time_window(&mut scan_results, time_window, &mut event_logs_data.application, "Application events", |e| &e.timestamp);
+    for (channel, events) in event_logs_data.channels.iter_mut() {
+        events_skipped += apply_time_window(&mut scan_results, time_window, events, channel, |e| &e.timestamp);
+    }
+    
+    let total_event_entries = event_logs_data.total_entries();
+    stats.finish(run, total_event_entries, events_skipped, collector_stats::log_errors(&event_logs_collection_logs));
+    
+    logger.info(&format!("Event log collection completed: {} entries collected", total_event_entries));
+    progress.completed(total_event_entries, &format!("✓ Event log collection completed ({} entries)", total_event_entries));
+    
+    // Capture channel size, retention and enabled state to explain gaps in event evidence
+    let run = stats.start("event_log_retention");
+    let (event_log_configuration, retention_logs) = stats.bounded(&run, offline_root.clone(), |offline_root| match &offline_root {
+        Some(root) => event_log_retention::collect_channel_configuration_offline(root),
+        None => event_log_retention::collect_channel_configuration(),
+    });
+    stats.finish(run, event_log_configuration.len(), 0, collector_stats::audit_errors(&retention_logs));
+    add_audit_entries(&mut scan_results, &retention_logs);
+    
+    for config in event_log_configuration.iter().filter(|c| !c.findings.is_empty()) {
+        logger.warn(&format!("Event log channel {}: {}", config.channel, config.findings.join("; ")));
+    }
+    progress.report(&format!("✓ Event log channel configuration captured ({} channels)", event_log_configuration.len()));
+    
+    // Collect execution evidence with error handling
+    progress.begin_stage("execution", "🔍 Collecting execution evidence...");
+    logger.info("Starting execution evidence collection");
+    
+    // Collect Prefetch files
+    progress.report("  📁 Analyzing Prefetch files...");
+    let prefetch_skip = environment::prefetch_skip_reason(&host_environment);
+    let run = stats.start("prefetch");
+    let (mut prefetch_files_data, prefetch_logs) = match (&prefetch_skip, &offline_root) {
+        _ if stats.disabled(&run) => (Vec::new(), Vec::new()),
+        (Some(_), _) => (Vec::new(), Vec::new()),
+        (None, Some(root)) => prefetch::collect_prefetch_files_offline(root),
+        (None, None) => prefetch::collect_prefetch_files(),
+    };
+    
+    add_audit_entries(&mut scan_results, &prefetch_logs);
+    
+    // A disabled Prefetcher or an empty folder is a finding of its own: other sources must cover execution
+    let (prefetch_status, prefetch_status_logs) = match (&prefetch_skip, &offline_root) {
+        (Some(reason), _) => (prefetch::not_applicable_status(reason), Vec::new()),
+        (None, Some(root)) => prefetch::collect_prefetch_status_offline(root, prefetch_files_data.len()),
+        (None, None) => prefetch::collect_prefetch_status(prefetch_files_data.len()),
+    };
+    add_audit_entries(&mut scan_results, &prefetch_status_logs);
+    if let Some(reason) = &prefetch_status.reason {
+        logger.warn(&format!("Prefetch {}: {}", prefetch_status.state, reason));
+    }
+    
+    let skipped = apply_time_window(&mut scan_results, time_window, &mut prefetch_files_data, "prefetch files", |pf| &pf.last_run_time);
+    match &prefetch_skip {
+        Some(reason) => stats.skip("prefetch", reason),
+        None => stats.finish(run, prefetch_files_data.len(), skipped, collector_stats::audit_errors(&prefetch_logs)),
+    }
+    
+    let (prefetch_count, prefetch_files) = stream_section(stream, &mut scan_results, "artifacts.execution_evidence.prefetch_files", prefetch_files_data);
+    
+    logger.info(&format!("Prefetch analysis completed: {} files analyzed", prefetch_count));
+    progress.completed(prefetch_count, &format!("  ✓ Prefetch analysis completed ({} files)", prefetch_count));
+    
+    // Collect Shimcache entries
+    progress.report("  📁 Analyzing Shimcache entries...");
+    let run = stats.start("shimcache");
+    let (mut shimcache_entries_data, shimcache_logs) = stats.bounded(&run, offline_root.clone(), |offline_root| match &offline_root {
+        Some(root) => shimcache::collect_shimcache_entries_offline(root),
+        None => shimcache::collect_shimcache_entries(),
+    });
+    
+    add_audit_entries(&mut scan_results, &shimcache_logs);
+    let skipped = apply_time_window(&mut scan_results, time_window, &mut shimcache_entries_data, "shimcache entries", |sc| &sc.last_modified);
+    stats.finish(run, shimcache_entries_data.len(), skipped, collector_stats::audit_errors(&shimcache_logs));
+    
+    let (shimcache_count, shimcache_entries) = stream_section(stream, &mut scan_results, "artifacts.execution_evidence.shimcache_entries", shimcache_entries_data);
+    
+    logger.info(&format!("Shimcache analysis completed: {} entries collected", shimcache_count));
+    progress.completed(shimcache_count, &format!("  ✓ Shimcache analysis completed ({} entries)", shimcache_count));
+    
+    // Collect Amcache entries (the hive is locked on live systems, so read it from the shadow copy)
+    let snapshot_root = if offline_root.is_none() { vss::snapshot_root() } else { None };
+    let amcache_entries = match offline_root.as_ref().or(snapshot_root.as_ref()) {
+        Some(root) => {
+            if snapshot_root.is_some() {
+                logger.info(&format!("Amcache.hve is read from shadow copy {}", root.root().display()));
+            }
+            progress.report("  📁 Analyzing Amcache entries...");
+            let run = stats.start("amcache");
+            let (mut entries, amcache_logs) = if stats.disabled(&run) { Default::default() } else { amcache::collect_amcache_entries_offline(root) };
+            add_audit_entries(&mut scan_results, &amcache_logs);
+            let skipped = apply_time_window(&mut scan_results, time_window, &mut entries, "amcache entries", |entry| &entry.last_modified);
+            stats.finish(run, entries.len(), skipped, collector_stats::audit_errors(&amcache_logs));
+            logger.info(&format!("Amcache analysis completed: {} entries collected", entries.len()));
+            progress.completed(entries.len(), &format!("  ✓ Amcache analysis completed ({} entries)", entries.len()));
+            entries
+        }
+        None => {
+            logger.info("Amcache analysis skipped: Amcache.hve is locked on live systems (use --offline-root or --vss)");
+            stats.skip("amcache", "Amcache.hve is locked on live systems");
+            Vec::new()
+        }
+    };
+    let (amcache_count, amcache_entries) = stream_section(stream, &mut scan_results, "artifacts.execution_evidence.amcache_entries", amcache_entries);
+    
+    // Collect BAM/DAM last-execution records
+    progress.report("  📁 Analyzing BAM/DAM entries...");
+    let run = stats.start("bam");
+    let (mut bam_entries, bam_logs) = stats.bounded(&run, offline_root.clone(), |offline_root| match &offline_root {
+        Some(root) => bam::collect_bam_entries_offline(root),
+        None => bam::collect_bam_entries(),
+    });
+    add_audit_entries(&mut scan_results, &bam_logs);
+    let skipped = apply_time_window(&mut scan_results, time_window, &mut bam_entries, "BAM/DAM entries", |entry| &entry.last_execution);
+    stats.finish(run, bam_entries.len(), skipped, collector_stats::audit_errors(&bam_logs));
+    
+    logger.info(&format!("BAM/DAM analysis completed: {} entries collected", bam_entries.len()));
+    progress.completed(bam_entries.len(), &format!("  ✓ BAM/DAM analysis completed ({} entries)", bam_entries.len()));
+    let (bam_count, bam_entries) = stream_section(stream, &mut scan_results, "artifacts.execution_evidence.bam_entries", bam_entries);
+    
+    // Collect MUICache and Compatibility Assistant Store entries
+    progress.report("  📁 Analyzing MUICache and Compatibility Assistant entries...");
+    let user_execution_entries = match &no_shell {
+        Some(reason) => {
+            stats.skip("user_execution", reason);
+            Vec::new()
+        }
+        None => {
+            let run = stats.start("user_execution");
+            let (entries, user_execution_logs) = stats.bounded(&run, offline_root.clone(), |offline_root| match &offline_root {
+                Some(root) => user_execution::collect_user_execution_offline(root),
+                None => user_execution::collect_user_execution(),
+            });
+            add_audit_entries(&mut scan_results, &user_execution_logs);
+            stats.finish(run, entries.len(), 0, collector_stats::audit_errors(&user_execution_logs));
+            entries
+        }
+    };
+    
+    logger.info(&format!("MUICache and Compatibility Assistant analysis completed: {} entries collected", user_execution_entries.len()));
+    progress.completed(user_execution_entries.len(), &format!("  ✓ MUICache and Compatibility Assistant analysis completed ({} entries)", user_execution_entries.len()));
+    let (user_execution_count, user_execution_entries) = stream_section(stream, &mut scan_results, "artifacts.execution_evidence.user_execution_entries", user_execution_entries);
+    
+    // Collect Defender support log breadcrumbs
+    progress.report("  📁 Analyzing Defender support logs...");
+    let run = stats.start("defender_logs");
+    let (mut defender_log_entries, defender_logs) = stats.bounded(&run, offline_root.clone(), |offline_root| match &offline_root {
+        Some(root) => defender_logs::collect_defender_logs_offline(root),
+        None => defender_logs::collect_defender_logs(),
+    });
+    add_audit_entries(&mut scan_results, &defender_logs);
+    let skipped = apply_time_window(&mut scan_results, time_window, &mut defender_log_entries, "Defender log entries", |entry| &entry.timestamp);
+    stats.finish(run, defender_log_entries.len(), skipped, collector_stats::audit_errors(&defender_logs));
+    
+    logger.info(&format!("Defender log analysis completed: {} entries collected", defender_log_entries.len()));
+    progress.completed(defender_log_entries.len(), &format!("  ✓ Defender log analysis completed ({} entries)", defender_log_entries.len()));
+    
+    // Rebuild exited processes from the 4688 and Sysmon 1 events and hash their binaries
+    progress.report("  📁 Backfilling historical processes from process creation events...");
+    let run = stats.start("historical_processes");
+    let (historical_processes, historical_logs) = if stats.disabled(&run) {
+        Default::default()
+    } else {
+        historical_processes::backfill_historical_processes(&event_logs_data, &running_images, offline_root.as_ref())
+    };
+    add_audit_entries(&mut scan_results, &historical_logs);
+    stats.finish(run, historical_processes.len(), 0, collector_stats::audit_errors(&historical_logs));
+    
+    logger.info(&format!("Historical process backfill completed: {} exited processes", historical_processes.len()));
+    progress.completed(historical_processes.len(), &format!("  ✓ Historical process backfill completed ({} exited processes)", historical_processes.len()));
+    let (historical_process_count, historical_processes) = stream_section(stream, &mut scan_results, "artifacts.execution_evidence.historical_processes", historical_processes);
+    
+    // Collect SRUM application and network usage
+    progress.report("  📁 Analyzing SRUM resource usage...");
+    let run = stats.start("srum");
+    let (mut srum_app_usage, mut srum_network_usage, srum_logs) = stats.bounded(&run, offline_root.clone(), |offline_root| match &offline_root {
+        Some(root) => srum::collect_srum_offline(root),
+        None => srum::collect_srum(),
+    });
+    add_audit_entries(&mut scan_results, &srum_logs);
+    let skipped = apply_time_window(&mut scan_results, time_window, &mut srum_app_usage, "SRUM application usage records", |r| &r.timestamp)
+        + apply_time_window(&mut scan_results, time_window, &mut srum_network_usage, "SRUM network usage records", |r| &r.timestamp);
+    stats.finish(run, srum_app_usage.len() + srum_network_usage.len(), skipped, collector_stats::audit_errors(&srum_logs));
+    
+    logger.info(&format!("SRUM analysis completed: {} application usage records, {} network usage records",
+        srum_app_usage.len(), srum_network_usage.len()));
+    progress.completed(srum_app_usage.len() + srum_network_usage.len(), &format!("  ✓ SRUM analysis completed ({} application usage, {} network usage records)",
+        srum_app_usage.len(), srum_network_usage.len()));
+    let (srum_app_count, srum_app_usage) = stream_section(stream, &mut scan_results, "artifacts.resource_usage.app_resource_usage", srum_app_usage);
+    let (srum_network_count, srum_network_usage) = stream_section(stream, &mut scan_results, "artifacts.resource_usage.network_usage", srum_network_usage);
+    
+    progress.report("✓ Execution evidence collection completed");
+    
+    // Collect device and driver installations from the setupapi logs
+    progress.begin_stage("devices", "🔍 Analyzing device and driver installations...");
+    let run = stats.start("setupapi");
+    let (mut device_installations, setupapi_logs) = stats.bounded(&run, offline_root.clone(), |offline_root| match &offline_root {
+        Some(root) => setupapi::collect_device_installations_offline(root),
+        None => setupapi::collect_device_installations(),
+    });
+    add_audit_entries(&mut scan_results, &setupapi_logs);
+    let skipped = apply_time_window(&mut scan_results, time_window, &mut device_installations, "device installations", |i| &i.start_time);
+    stats.finish(run, device_installations.len(), skipped, collector_stats::audit_errors(&setupapi_logs));
+    
+    let flagged_installations = device_installations.iter().filter(|i| !i.flags.is_empty()).count();
+    logger.info(&format!("Device installation analysis completed: {} installations, {} flagged",
+        device_installations.len(), flagged_installations));
+    progress.completed(device_installations.len(), &format!("✓ Device installation analysis completed ({} installations, {} flagged)",
+        device_installations.len(), flagged_installations));
+    
+    // USB storage history, dated from the setupapi installations when the device properties are unreadable
+    let run = stats.start("usb_devices");
+    let (usb_device_history, usb_logs) = stats.bounded(&run, (offline_root.clone(), device_installations.clone()), |(offline_root, installations)| match &offline_root {
+        Some(root) => usb_devices::collect_usb_devices_offline(root, &installations),
+        None => usb_devices::collect_usb_devices(&installations),
+    });
+    stats.finish(run, usb_device_history.len(), 0, collector_stats::audit_errors(&usb_logs));
+    add_audit_entries(&mut scan_results, &usb_logs);
+    
+    logger.info(&format!("USB device history collected: {} devices", usb_device_history.len()));
+    progress.completed(usb_device_history.len(), &format!("✓ USB device history collected ({} devices)", usb_device_history.len()));
+    
+    // Audit the security configuration for settings weakened by an intruder
+    progress.begin_stage("security_configuration", "🔍 Auditing security configuration...");
+    let run = stats.start("security_configuration");
+    let (security_settings, security_logs) = stats.bounded(&run, offline_root.clone(), |offline_root| match &offline_root {
+        Some(root) => security_configuration::collect_security_configuration_offline(root),
+        None => security_configuration::collect_security_configuration(),
+    });
+    stats.finish(run, security_settings.len(), 0, collector_stats::audit_errors(&security_logs));
+    add_audit_entries(&mut scan_results, &security_logs);
+    let weakened_settings = security_configuration::weakened_count(&security_settings);
+    
+    logger.info(&format!("Security configuration audit completed: {} settings, {} weakened", security_settings.len(), weakened_settings));
+    progress.completed(security_settings.len(), &format!("✓ Security configuration audit completed ({} settings, {} weakened)", security_settings.len(), weakened_settings));
+    
+    // Machine and user Root/CA certificate stores, flagging recently planted roots
+    let run = stats.start("certificate_stores");
+    let (certificates, certificate_logs) = stats.bounded(&run, (offline_root.clone(), time_window.clone()), |(offline_root, time_window)| match &offline_root {
+        Some(root) => certificate_stores::collect_certificates_offline(root, &time_window),
+        None => certificate_stores::collect_certificates(&time_window),
+    });
+    stats.finish(run, certificates.len(), 0, collector_stats::audit_errors(&certificate_logs));
+    add_audit_entries(&mut scan_results, &certificate_logs);
+    let suspicious_roots = certificates.iter().filter(|certificate| certificate.suspicious).count();
+    
+    logger.info(&format!("Certificate store collection completed: {} certificates, {} suspicious roots", certificates.len(), suspicious_roots));
+    progress.completed(certificates.len(), &format!("✓ Certificate stores collected ({} certificates, {} suspicious roots)", certificates.len(), suspicious_roots));
+    
+    // Defender detections from its operational log and DetectionHistory files, and its exclusions
+    let run = stats.start("defender_detections");
+    let (defender_events, defender_exclusions, defender_detection_logs) = if stats.disabled(&run) {
+        Default::default()
+    } else {
+        defender_detections::collect_defender_detections(&event_logs_data, &security_settings, offline_root.as_ref())
+    };
+    stats.finish(run, defender_events.len() + defender_exclusions.len(), 0, collector_stats::audit_errors(&defender_detection_logs));
+    add_audit_entries(&mut scan_results, &defender_detection_logs);
+    let defender_detection_count = defender_events.iter().filter(|e| !e.threat_name.is_empty()).count();
+    
+    logger.info(&format!("Defender detection analysis completed: {} detections, {} exclusions", defender_detection_count, defender_exclusions.len()));
+    progress.completed(defender_events.len() + defender_exclusions.len(), &format!("✓ Defender detection analysis completed ({} detections, {} exclusions)", defender_detection_count, defender_exclusions.len()));
+    
+    // PowerShell script blocks and pipelines from the operational log, and console histories
+    let run = stats.start("powershell");
+    let (powershell_events, powershell_logs) = if stats.disabled(&run) { Default::default() } else { powershell_history::collect_powershell_activity(&event_logs_data, offline_root.as_ref()) };
+    stats.finish(run, powershell_events.len(), 0, collector_stats::audit_errors(&powershell_logs));
+    add_audit_entries(&mut scan_results, &powershell_logs);
+    
+    logger.info(&format!("PowerShell activity analysis completed: {} records", powershell_events.len()));
+    progress.completed(powershell_events.len(), &format!("✓ PowerShell activity analysis completed ({} records)", powershell_events.len()));
+    let (powershell_count, powershell_events) = stream_section(stream, &mut scan_results, "artifacts.security_events.powershell_log", powershell_events);
+    
+    // Structured Sysmon process, network, image load, injection, file, registry and DNS events
+    let run = stats.start("sysmon");
+    let (sysmon_log, sysmon_logs) = if stats.disabled(&run) { Default::default() } else { sysmon_events::collect_sysmon_events(&event_logs_data) };
+    stats.finish(run, sysmon_log.len(), 0, collector_stats::audit_errors(&sysmon_logs));
+    add_audit_entries(&mut scan_results, &sysmon_logs);
+    
+    logger.info(&format!("Sysmon event analysis completed: {} events", sysmon_log.len()));
+    progress.completed(sysmon_log.len(), &format!("✓ Sysmon event analysis completed ({} events)", sysmon_log.len()));
+    let (sysmon_count, sysmon_log) = stream_section(stream, &mut scan_results, "artifacts.security_events.sysmon_log", sysmon_log);
+    
+    // Collect user activity artifacts
+    progress.begin_stage("user_activity", "🔍 Collecting user activity artifacts...");
+    logger.info("Starting user activity artifact collection");
+    if let Some(ref dir) = raw_dir {
+        logger.info(&format!("Raw mode enabled, copying artifact files to: {}", dir.display()));
+    }
+    
+    let run = stats.start("local_accounts");
+    let (local_accounts, local_account_logs) = stats.bounded(&run, offline_root.clone(), |offline_root| match &offline_root {
+        Some(root) => local_accounts::collect_local_accounts_offline(root),
+        None => local_accounts::collect_local_accounts(),
+    });
+    stats.finish(run, local_accounts.len(), 0, collector_stats::audit_errors(&local_account_logs));
+    add_audit_entries(&mut scan_results, &local_account_logs);
+    let privileged_accounts = local_accounts.iter().filter(|a| !a.privileged_groups.is_empty()).count();
+    
+    let (search_index_files, thumbcache_files, notification_records, clipboard_entries, search_history, mru_lists, browser_records) = match &no_shell {
+        Some(reason) => {
+            for collector in USER_ACTIVITY_COLLECTORS {
+                stats.skip(collector, reason);
+            }
+            Default::default()
+        }
+        None => {
+            let run = stats.start("search_index");
+            let (search_index_files, search_index_logs) = stats.bounded(&run, (offline_root.clone(), raw_dir.clone()), |(offline_root, raw_dir)| match &offline_root {
+                Some(root) => search_artifacts::collect_search_index_files_offline(root, raw_dir.as_deref()),
+                None => search_artifacts::collect_search_index_files(raw_dir.as_deref()),
+            });
+            stats.finish(run, search_index_files.len(), 0, collector_stats::audit_errors(&search_index_logs));
+            add_audit_entries(&mut scan_results, &search_index_logs);
+    
+            let run = stats.start("thumbcache");
+            let (thumbcache_files, thumbcache_logs) = stats.bounded(&run, (offline_root.clone(), raw_dir.clone()), |(offline_root, raw_dir)| match &offline_root {
+                Some(root) => search_artifacts::collect_thumbcache_files_offline(root, raw_dir.as_deref()),
+                None => search_artifacts::collect_thumbcache_files(raw_dir.as_deref()),
+            });
+            stats.finish(run, thumbcache_files.len(), 0, collector_stats::audit_errors(&thumbcache_logs));
+            add_audit_entries(&mut scan_results, &thumbcache_logs);
+    
+            let run = stats.start("notifications");
+            let (mut notification_records, notification_logs) = stats.bounded(&run, offline_root.clone(), |offline_root| match &offline_root {
+                Some(root) => notifications::collect_notifications_offline(root),
+                None => notifications::collect_notifications(),
+            });
+            add_audit_entries(&mut scan_results, &notification_logs);
+            let skipped = apply_time_window(&mut scan_results, time_window, &mut notification_records, "notifications", |n| &n.arrival_time);
+            stats.finish(run, notification_records.len(), skipped, collector_stats::audit_errors(&notification_logs));
+    
+            let run = stats.start("clipboard_history");
+            let (mut clipboard_entries, clipboard_logs) = stats.bounded(&run, offline_root.clone(), |offline_root| match &offline_root {
+                Some(root) => clipboard_history::collect_clipboard_history_offline(root),
+                None => clipboard_history::collect_clipboard_history(),
+            });
+            add_audit_entries(&mut scan_results, &clipboard_logs);
+            let skipped = apply_time_window(&mut scan_results, time_window, &mut clipboard_entries, "clipboard history", |c| &c.timestamp);
+            stats.finish(run, clipboard_entries.len(), skipped, collector_stats::audit_errors(&clipboard_logs));
+    
+            let run = stats.start("search_history");
+            let (search_history, search_history_logs) = stats.bounded(&run, offline_root.clone(), |offline_root| match &offline_root {
+                Some(root) => search_history::collect_search_history_offline(root),
+                None => search_history::collect_search_history(),
+            });
+            stats.finish(run, search_history.len(), 0, collector_stats::audit_errors(&search_history_logs));
+            add_audit_entries(&mut scan_results, &search_history_logs);
+    
+            let run = stats.start("mru");
+            let (mru_lists, mru_logs) = stats.bounded(&run, offline_root.clone(), |offline_root| match &offline_root {
+                Some(root) => mru::collect_mru_lists_offline(root),
+                None => mru::collect_mru_lists(),
+            });
+            stats.finish(run, mru_lists.len(), 0, collector_stats::audit_errors(&mru_logs));
+            add_audit_entries(&mut scan_results, &mru_logs);
+    
+            let run = stats.start("browser_artifacts");
+            let (mut browser_records, browser_logs) = stats.bounded(&run, offline_root.clone(), |offline_root| match &offline_root {
+                Some(root) => browser_artifacts::collect_browser_artifacts_offline(root),
+                None => browser_artifacts::collect_browser_artifacts(),
+            });
+            add_audit_entries(&mut scan_results, &browser_logs);
+            let skipped = apply_time_window(&mut scan_results, time_window, &mut browser_records, "browser history", |b| &b.last_visit);
+            stats.finish(run, browser_records.len(), skipped, collector_stats::audit_errors(&browser_logs));
+            (search_index_files, thumbcache_files, notification_records, clipboard_entries, search_history, mru_lists, browser_records)
+        }
+    };
+    
+    logger.info(&format!("Local account enumeration completed: {} accounts, {} in privileged groups", local_accounts.len(), privileged_accounts));
+    logger.info(&format!("User activity collection completed: {} search index files, {} thumbnail caches, {} notifications, {} clipboard history items, {} search history entries, {} MRU entries, {} browser records",
+        search_index_files.len(), thumbcache_files.len(), notification_records.len(), clipboard_entries.len(), search_history.len(), mru_lists.len(), browser_records.len()));
+    progress.completed(local_accounts.len() + search_index_files.len() + thumbcache_files.len() + notification_records.len() + clipboard_entries.len() + search_history.len() + mru_lists.len() + browser_records.len(), &format!("✓ User activity collection completed ({} local accounts, {} search index files, {} thumbnail caches, {} notifications, {} clipboard history items, {} search history entries, {} MRU entries, {} browser records)",
+        local_accounts.len(), search_index_files.len(), thumbcache_files.len(), notification_records.len(), clipboard_entries.len(), search_history.len(), mru_lists.len(), browser_records.len()));
+    let (search_index_count, search_index_files) = stream_section(stream, &mut scan_results, "artifacts.user_activity.search_index_files", search_index_files);
+    let (thumbcache_count, thumbcache_files) = stream_section(stream, &mut scan_results, "artifacts.user_activity.thumbcache_files", thumbcache_files);
+    let (notification_count, notification_records) = stream_section(stream, &mut scan_results, "artifacts.user_activity.notifications", notification_records);
+    let (browser_count, browser_records) = stream_section(stream, &mut scan_results, "artifacts.user_activity.browser_artifacts", browser_records);
+    
+    // File creations, renames and deletions of the NTFS change journal
+    progress.begin_stage("usn_journal", "🔍 Reading the NTFS change journal...");
+    let run = stats.start("usn_journal");
+    let (usn_journal_entries, usn_logs) = stats.bounded(&run, (offline_root.clone(), time_window.clone()), |(offline_root, time_window)| match &offline_root {
+        Some(root) => usn_journal::collect_usn_journal_offline(root, &time_window),
+        None => usn_journal::collect_usn_journal(&time_window),
+    });
+    stats.finish(run, usn_journal_entries.len(), 0, collector_stats::audit_errors(&usn_logs));
+    add_audit_entries(&mut scan_results, &usn_logs);
+    
+    logger.info(&format!("USN journal collection completed: {} file changes", usn_journal_entries.len()));
+    progress.completed(usn_journal_entries.len(), &format!("✓ USN journal collection completed ({} file changes)", usn_journal_entries.len()));
+    let (usn_journal_count, usn_journal_entries) = stream_section(stream, &mut scan_results, "artifacts.filesystem_artifacts.usn_journal", usn_journal_entries);
+    
+    // Hash and match the files below the --scan-paths directories
+    progress.begin_stage("file_sweep", "🔍 Sweeping scan paths...");
+    let swept_files = match file_sweep {
+        Some(sweep) => {
+            let run = stats.start("file_sweep");
+            let (files, sweep_logs) = file_sweep::sweep_paths(sweep, offline_root.as_ref());
+            add_audit_entries(&mut scan_results, &sweep_logs);
+            stats.finish(run, files.len(), 0, collector_stats::audit_errors(&sweep_logs));
+            files
+        }
+        None => Vec::new(),
+    };
+    let swept_matches = swept_files.iter().filter(|f| !f.ioc_matches.is_empty() || !f.yara_matches.is_empty()).count();
+    
+    logger.info(&format!("Scan path sweep completed: {} files hashed, {} matched", swept_files.len(), swept_matches));
+    progress.completed(swept_files.len(), &format!("✓ Scan path sweep completed ({} files hashed, {} matched)", swept_files.len(), swept_matches));
+    
+    // Dump the requested processes, and for `auto` those flagged by the sweep or running unsigned from temp
+    progress.begin_stage("memory_dumps", "💾 Dumping process memory...");
+    let memory_dumps = match process_dumps {
+        Some(options) => {
+            let run = stats.start("memory_dump");
+            let (dumps, dump_logs) = memory_dump::dump_processes(options, &acquisition_processes, &swept_files);
+            add_audit_entries(&mut scan_results, &dump_logs);
+            stats.finish(run, dumps.len(), 0, collector_stats::audit_errors(&dump_logs));
+            dumps
+        }
+        None => Vec::new(),
+    };
+    
+    logger.info(&format!("Process memory dumps completed: {} processes dumped", memory_dumps.len()));
+    progress.report(&format!("✓ Process memory dumps completed ({} processes)", memory_dumps.len()));
+    let (swept_count, swept_files) = stream_section(stream, &mut scan_results, "artifacts.file_sweep", swept_files);
+    
+    // Check that the deception assets are still in place and unused
+    progress.begin_stage("canaries", "🔍 Verifying canaries...");
+    let canary_results = match canary_config {
+        Some(config) => {
+            let run = stats.start("canaries");
+            let (results, canary_logs) = canaries::verify_canaries(config, offline_root.as_ref());
+            add_audit_entries(&mut scan_results, &canary_logs);
+            stats.finish(run, results.len(), 0, collector_stats::audit_errors(&canary_logs));
+            results
+        }
+        None => Vec::new(),
+    };
+    let tampered_canaries = canary_results.iter().filter(|status| canaries::tampered(status)).count();
+    
+    if tampered_canaries > 0 {
+        logger.warn(&format!("Canary verification: {} of {} canaries tampered", tampered_canaries, canary_results.len()));
+    } else {
+        logger.info(&format!("Canary verification completed: {} canaries intact or unverified", canary_results.len()));
+    }
+    progress.report(&format!("✓ Canary verification completed ({} canaries, {} tampered)", canary_results.len(), tampered_canaries));
+    
+    // Copy the selected files into the evidence package
+    progress.begin_stage("file_collection", "📦 Acquiring selected files...");
+    let (acquired_files, mut collected_files) = match collect_files {
+        Some(options) => {
+            let run = stats.start("collect_files");
+            let (records, attachments, collection_logs) = file_collection::collect_files(options, &acquisition_processes, &acquisition_mechanisms, offline_root.as_ref());
+            add_audit_entries(&mut scan_results, &collection_logs);
+            stats.finish(run, records.len(), 0, collector_stats::audit_errors(&collection_logs));
+            (records, attachments)
+        }
+        None => (Vec::new(), Vec::new()),
+    };
+    let copied_files = acquired_files.iter().filter(|f| f.status == "collected").count();
+    
+    logger.info(&format!("File acquisition completed: {} of {} selected files copied", copied_files, acquired_files.len()));
+    progress.report(&format!("✓ File acquisition completed ({} of {} files copied)", copied_files, acquired_files.len()));
+    
+    // Export the master file table into the evidence package
+    let mft_export = match collect_mft {
+        Some(format) => {
+            let run = stats.start("mft");
+            let (export, attachments, mft_logs) = match offline_root.as_ref() {
+                Some(root) => mft::collect_mft_offline(root, *format, time_window),
+                None => mft::collect_mft(*format, time_window),
+            };
+            add_audit_entries(&mut scan_results, &mft_logs);
+            stats.finish(run, export.exported_records, 0, collector_stats::audit_errors(&mft_logs));
+            collected_files.extend(attachments);
+            logger.info(&format!("MFT export completed: {} of {} file records ({})", export.exported_records, export.total_records, export.status));
+            progress.report(&format!("✓ MFT export completed ({} file records)", export.exported_records));
+            Some(export)
+        }
+        None => None,
+    };
+    
+    // Run third-party collector plugins
+    progress.begin_stage("plugins", "🔍 Running collector plugins...");
+    let plugin_artifacts = match plugins_dir {
+        Some(dir) => {
+            let context = json!({
+                "abi_version": plugins::PLUGIN_ABI_VERSION,
+                "cli_version": env!("CARGO_PKG_VERSION"),
+                "scan_id": scan_results.scan_metadata.scan_id,
+                "offline_root": scan_results.scan_metadata.offline_root,
+                "since": scan_results.scan_metadata.since,
+                "until": scan_results.scan_metadata.until
+            });
+            let run = stats.start("plugins");
+            let (results, plugin_logs) = plugins::collect_plugin_artifacts(dir, &context);
+            let plugin_items = results.iter().map(|p| p.artifacts.len()).sum();
+            stats.finish(run, plugin_items, 0, collector_stats::audit_errors(&plugin_logs));
+            add_audit_entries(&mut scan_results, &plugin_logs);
+            results
+        }
+        None => Vec::new(),
+    };
+    let total_plugin_artifacts: usize = plugin_artifacts.iter().map(|p| p.artifacts.len()).sum();
+    
+    logger.info(&format!("Plugin collection completed: {} plugins, {} artifacts", plugin_artifacts.len(), total_plugin_artifacts));
+    progress.completed(total_plugin_artifacts, &format!("✓ Plugin collection completed ({} plugins, {} artifacts)", plugin_artifacts.len(), total_plugin_artifacts));
+    
+    // Run the compiled-in and configured custom collectors
+    progress.begin_stage("custom_collectors", "🔍 Running custom collectors...");
+    let custom_artifacts = match custom_collectors {
+        Some(registry) => {
+            let context = custom_collectors::CollectorContext {
+                cli_version: env!("CARGO_PKG_VERSION").to_string(),
+                scan_id: scan_results.scan_metadata.scan_id.clone(),
+                hostname: scan_results.scan_metadata.hostname.clone(),
+                offline_root: scan_results.scan_metadata.offline_root.clone(),
+                since: scan_results.scan_metadata.since.clone(),
+                until: scan_results.scan_metadata.until.clone(),
+            };
+            let run = stats.start("custom_collectors");
+            let (results, custom_logs) = stats.bounded(&run, (registry.clone(), context), |(registry, context)| registry.run(&context));
+            let custom_items = results.values().map(|custom| custom.artifacts.len()).sum();
+            stats.finish(run, custom_items, 0, collector_stats::audit_errors(&custom_logs));
+            add_audit_entries(&mut scan_results, &custom_logs);
+            results
+        }
+        None => BTreeMap::new(),
+    };
+    let total_custom_artifacts: usize = custom_artifacts.values().map(|custom| custom.artifacts.len()).sum();
+    progress.completed(total_custom_artifacts, &format!("✓ Custom collectors completed ({} namespaces, {} artifacts)", custom_artifacts.len(), total_custom_artifacts));
+    
+    // Record which files were read from the shadow copy and remove a snapshot taken for this scan
+    let snapshot_reads = vss::take_audit_entries();
+    if !snapshot_reads.is_empty() {
+        logger.info(&format!("{} locked files were read from the shadow copy", snapshot_reads.len()));
+    }
+    add_audit_entries(&mut scan_results, &snapshot_reads);
+    if let Some(active) = vss::deactivate().filter(|s| s.created_by_collector) {
+        add_audit_entries(&mut scan_results, &[vss::delete_shadow_copy(&active)]);
+    }
+    
+    for stopped in stats.stopped() {
+        let message = format!("Collector {} stopped: {}", stopped.collector, stopped.skip_reason.as_deref().unwrap_or_default());
+        logger.warn(&message);
+        scan_results.add_log(LogEntry::new("WARN", &message));
+    }
+    let truncated = stats.stopped().any(|stopped| stopped.status == "truncated");
+    if truncated {
+        let message = format!("Collection truncated: resident memory approached the limit of {} MB", memory_limit_mb.unwrap_or_default());
+        logger.warn(&message);
+        scan_results.add_log(LogEntry::new("WARN", &message));
+    }
+    let interrupted = cancellation::interrupted();
+    if interrupted {
+        let message = "Scan interrupted (Ctrl+C): remaining collectors were skipped and the results are partial";
+        logger.warn(message);
+        scan_results.add_log(LogEntry::new("WARN", message));
+    }
+    
+    // The window runs to its end unless the scan was interrupted
+    let live_activity = live_monitor.map(|monitor| {
+        progress.report("Waiting for the end of the live activity window...");
+        let (activity, monitor_logs) = monitor.finish(interrupted);
+        for log in monitor_logs {
+            scan_results.add_log(log);
+        }
+        activity
+    });
+    
+    let collection_footprint = footprint::take().into_section(*verify_read_only);
+    if collection_footprint.read_only_verified == Some(false) {
+        let message = format!("Read-only verification failed: opened for write: {}", collection_footprint.opened_for_write.join(", "));
+        logger.error(&message);
+        scan_results.add_log(LogEntry::new("ERROR", &message));
+    }
+    
+    let total_artifacts = processes.len() + loaded_drivers.len() + network_connections.len() + named_pipes.len() + remote_access_artifacts + host_network_logs.firewall.len() + host_network_logs.dns.len() + persistence_mechanisms.len() + hijack_entries + dll_hijacks.len() + total_event_entries + prefetch_count + shimcache_count
+        + amcache_count + bam_count + user_execution_count + defender_log_entries.len() + historical_process_count + srum_app_count + srum_network_count + device_installations.len() + usb_device_history.len()
+        + security_settings.len() + certificates.len() + defender_events.len() + defender_exclusions.len() + powershell_count + sysmon_count + network_configuration_count + name_resolution_count + local_accounts.len() + search_index_count + thumbcache_count + notification_count + clipboard_entries.len() + search_history.len() + mru_lists.len() + browser_count + usn_journal_count + swept_count + total_plugin_artifacts + total_custom_artifacts;
+    
+    let duration = start_time.elapsed();
+    logger.info(&format!("Scan completed in {:.2} seconds", duration.as_secs_f64()));
+    logger.info(&format!("Total artifacts collected: {}", total_artifacts));
+    
+    // Get logger summary for final reporting
+    let log_summary = logger.get_summary();
+    logger.info(&format!("Collection summary - Total logs: {}, Errors: {}, Warnings: {}, Success rate: {:.1}%", 
+        log_summary.total_count, log_summary.error_count, log_summary.warn_count, log_summary.success_rate()));
+    
+    let mut summary = vec![
+        "✓ System information collected".to_string(),
+        format!("✓ Running processes enumerated ({} processes)", processes.len()),
+        format!("✓ Kernel drivers enumerated ({} drivers)", loaded_drivers.len()),
+        format!("✓ Network connections analyzed ({} connections)", network_connections.len()),
+        format!("✓ Named pipes enumerated ({} pipes and mailslots)", named_pipes.len()),
+        format!("✓ Firewall and DNS logs read ({} firewall records, {} DNS records)", host_network_logs.firewall.len(), host_network_logs.dns.len()),
+        format!("✓ RDP and SMB session artifacts collected ({} artifacts)", remote_access_artifacts),
+        format!("✓ Network configuration collected ({} shares, {} anonymous; {} firewall rules, {} allow all inbound)",
+            network_configuration.network_shares.len(), anonymous_shares, network_configuration.firewall_rules.len(), allow_all_rules),
+        format!("✓ Hosts file, Winsock providers, NRPT rules and DNS servers checked ({} entries, {} suspicious)", name_resolution_count, name_resolution_findings),
+        format!("✓ Persistence mechanisms detected ({} mechanisms)", persistence_mechanisms.len()),
+        format!("✓ Winlogon, IFEO, AppInit_DLLs and search paths checked ({} entries, {} suspicious)", hijack_entries, suspicious_hijacks),
+        format!("✓ DLL search-order hijacks checked ({} candidates)", dll_hijacks.len()),
+        format!("✓ Event logs collected ({} entries)", total_event_entries),
+        format!("✓ Prefetch files analyzed ({} files)", prefetch_count),
+        format!("✓ Shimcache entries collected ({} entries)", shimcache_count),
+        format!("✓ Amcache entries collected ({} entries)", amcache_count),
+        format!("✓ BAM/DAM entries collected ({} entries)", bam_count),
+        format!("✓ MUICache and Compatibility Assistant entries collected ({} entries)", user_execution_count),
+        format!("✓ Defender support log entries parsed ({} entries)", defender_log_entries.len()),
+        format!("✓ Exited processes backfilled from process creation events ({} processes)", historical_process_count),
+        format!("✓ SRUM resource usage parsed ({} application, {} network records)", srum_app_count, srum_network_count),
+        format!("✓ Device installations analyzed ({} installations)", device_installations.len()),
+        format!("✓ USB device history collected ({} devices)", usb_device_history.len()),
+        format!("✓ Security configuration audited ({} settings, {} weakened)", security_settings.len(), weakened_settings),
+        format!("✓ Certificate stores checked ({} certificates, {} recently added untrusted roots)", certificates.len(), suspicious_roots),
+        format!("✓ Defender detections and exclusions collected ({} detections, {} exclusions)", defender_detection_count, defender_exclusions.len()),
+        format!("✓ PowerShell script blocks and console history collected ({} records)", powershell_count),
+        format!("✓ Sysmon events mapped ({} events)", sysmon_count),
+        format!("✓ Search index files inventoried ({} files)", search_index_count),
+        format!("✓ Thumbnail caches inventoried ({} files)", thumbcache_count),
+        format!("✓ Notifications recovered ({} notifications)", notification_count),
+        format!("✓ Clipboard history recovered ({} items)", clipboard_entries.len()),
+        format!("✓ Search and Run dialog history collected ({} entries)", search_history.len()),
+        format!("✓ Explorer MRU lists parsed ({} entries)", mru_lists.len()),
+        format!("✓ Browser history and downloads parsed ({} records)", browser_count),
+        format!("✓ USN journal read ({} file creations, renames and deletions)", usn_journal_count),
+        format!("✓ Scan paths swept ({} files hashed, {} matched)", swept_count, swept_matches),
+        format!("✓ Collector plugins run ({} plugins, {} artifacts)", plugin_artifacts.len(), total_plugin_artifacts),
+        format!("✓ Custom collectors run ({} namespaces, {} artifacts)", custom_artifacts.len(), total_custom_artifacts),
+    ];
+    
+    if *screenshot && no_shell.is_none() {
+        summary.insert(1, format!("✓ Screenshots of the interactive desktop captured ({} monitors)", screenshots.len()));
+    }
+    if let Some(activity) = &live_activity {
+        summary.push(format!("✓ Live activity monitored for {} seconds ({} process starts, {} image loads, {} connections)",
+            activity.window_seconds, activity.process_starts.len(), activity.image_loads.len(), activity.connections.len()));
+    }
+    match collection_footprint.read_only_verified {
+        Some(false) => summary.push(format!("✗ Read-only verification failed ({} paths opened for write)", collection_footprint.opened_for_write.len())),
+        Some(true) => summary.push(format!("✓ Read-only access verified ({} files, {} registry keys opened)", collection_footprint.files.len(), collection_footprint.registry_keys.len())),
+        None => {}
+    }
+    
+    // Processes without a backing file on disk are listed right after the process count
+    if deleted_executables > 0 {
+        summary.insert(2, format!("⚠ Processes running from deleted executables ({} processes)", deleted_executables));
+    }
+    
+    // Without Prefetch the remaining execution sources carry the examination, so list them first
+    if let Some(reason) = &prefetch_status.reason {
+        let process_creation_events = event_logs_data.security.iter().filter(|e| e.event_id == 4688).count();
+        let prefetch_line = summary.iter().position(|line| line.starts_with("✓ Prefetch")).unwrap_or(0);
+        summary.splice(prefetch_line..prefetch_line + 1, [
+            format!("⚠ Prefetch {} ({}): execution evidence relies on the sources below", prefetch_status.state, reason),
+            format!("  → BAM/DAM entries ({} entries)", bam_count),
+            format!("  → Shimcache entries ({} entries)", shimcache_count),
+            format!("  → Amcache entries ({} entries)", amcache_count),
+            format!("  → Process creation events, Security 4688 ({} events)", process_creation_events),
+        ]);
+        summary.retain(|line| !["✓ Shimcache", "✓ Amcache", "✓ BAM/DAM"].iter().any(|prefix| line.starts_with(prefix)));
+    }
+    
+    // Tampered canaries mean an intruder is or was active: lead with them
+    if tampered_canaries > 0 {
+        summary.insert(0, format!("⚠ Canaries tampered ({} of {}): deception assets were removed, changed or used", tampered_canaries, canary_results.len()));
+    } else if canary_config.is_some() {
+        summary.push(format!("✓ Canaries verified ({} canaries)", canary_results.len()));
+    }
+    
+    if process_dumps.is_some() {
+        summary.push(format!("✓ Process memory dumped into the evidence package ({} processes)", memory_dumps.len()));
+    }
+    
+    if collect_files.is_some() {
+        summary.push(format!("✓ Files acquired into the evidence package ({} of {} selected files)", copied_files, acquired_files.len()));
+    }
+    
+    if let Some(export) = &mft_export {
+        summary.push(format!("✓ $MFT exported into the evidence package as {} ({} of {} file records)", export.format, export.exported_records, export.total_records));
+    }
+    
+    let statistics = CollectionStatistics {
+        total_processes: processes.len() as u32,
+        total_network_connections: network_connections.len() as u32,
+        total_files_analyzed: (search_index_count + thumbcache_count) as u32,
+        total_registry_keys: (persistence_mechanisms.len() + hijack_entries + shimcache_count + amcache_count + bam_count + user_execution_count + mru_lists.len()) as u32,
+        total_event_log_entries: total_event_entries as u32,
+        total_prefetch_files: prefetch_count as u32,
+        total_scheduled_tasks: scheduled_tasks.len() as u32,
+        memory_usage_peak_mb: 0.0,
+        disk_space_used_mb: 0.0,
+    };
+    
+    // Finalize scan results with proper metadata
+    scan_results.finalize_scan();
+    
+    // Add all logger entries to the scan results
+    for entry in logger.get_entries() {
+        scan_results.add_log(entry);
+    }
+    
+    let hash_stats = hashes.stats();
+    logger.info(&format!("Hashing service: {} files hashed, {} cache hits", hash_stats.files_hashed, hash_stats.cache_hits));
+    
+    let metadata = &mut scan_results.scan_metadata;
+    metadata.scan_duration_ms = duration.as_millis() as u64;
+    metadata.hostname = hostname;
+    metadata.shadow_copy = snapshot;
+    metadata.hash_algorithms = hashes.algorithms().iter().map(|a| a.name().to_string()).collect();
+    metadata.suspicion_threshold = scoring.threshold;
+    metadata.environment = host_environment;
+    metadata.total_artifacts = total_artifacts;
+    metadata.scan_interrupted = interrupted;
+    metadata.memory_limit_mb = *memory_limit_mb;
+    metadata.collection_truncated = truncated;
+    scan_results.external_commands = take_external_commands();
+    scan_results.collection_footprint = collection_footprint;
+    metadata.collection_summary = CollectionSummary {
+        total_logs: log_summary.total_count,
+        error_count: log_summary.error_count,
+        warning_count: log_summary.warn_count,
+        success_rate: log_summary.success_rate(),
+    };
+    
+    scan_results.artifacts = Artifacts {
+        system_info,
+        shadow_copies,
+        screenshots: screenshots.iter().map(|screen| screen.screenshot.clone()).collect(),
+        live_activity,
+        running_processes: processes,
+        process_tree,
+        loaded_drivers,
+        network_connections,
+        volatile_artifacts: VolatileArtifactSection { named_pipes },
+        network_artifacts: NetworkArtifactSection {
+            rdp_connections,
+            rdp_session_events,
+            smb_sessions,
+            smb_open_files,
+            network_shares: network_configuration.network_shares,
+            firewall_rules: network_configuration.firewall_rules,
+            proxy_settings: network_configuration.proxy_settings,
+            user_proxy_settings: network_configuration.user_proxy_settings,
+            hosts_file: name_resolution.hosts_file,
+            winsock_providers: name_resolution.winsock_providers,
+            nrpt_rules: name_resolution.nrpt_rules,
+            dns_servers: name_resolution.dns_servers,
+            firewall_log: host_network_logs.firewall,
+            dns_log: host_network_logs.dns,
+        },
+        persistence_mechanisms,
+        persistence_artifacts: PersistenceArtifactSection {
+            scheduled_tasks,
+            winlogon_entries: hijack_artifacts.winlogon_entries,
+            image_hijacks: hijack_artifacts.image_hijacks,
+            appinit_dlls: hijack_artifacts.appinit_dlls,
+            path_hijacks: hijack_artifacts.path_hijacks,
+            dll_hijacks,
+        },
+        event_logs: event_logs_data,
+        event_log_configuration,
+        execution_evidence: ExecutionEvidenceSection {
+            prefetch_status,
+            prefetch_files,
+            shimcache_entries,
+            amcache_entries,
+            bam_entries,
+            user_execution_entries,
+            defender_log_entries,
+            historical_processes,
+        },
+        resource_usage: ResourceUsageSection {
+            app_resource_usage: srum_app_usage,
+            network_usage: srum_network_usage,
+        },
+        device_installations,
+        usb_devices: usb_device_history,
+        user_activity: UserActivitySection {
+            user_profiles: local_accounts,
+            search_index_files,
+            thumbcache_files,
+            notifications: notification_records,
+            search_history,
+            mru_lists,
+            browser_artifacts: browser_records,
+            rdp_connection_history,
+            clipboard_history: clipboard_entries,
+        },
+        security_configuration: security_settings,
+        certificate_stores: certificates,
+        security_events: SecurityEventSection {
+            powershell_log: powershell_events,
+            sysmon_log,
+            defender_log: defender_events,
+            defender_exclusions,
+        },
+        filesystem_artifacts: FilesystemArtifactSection {
+            usn_journal: usn_journal_entries,
+            mft: mft_export,
+        },
+        file_sweep: swept_files,
+        canaries: canary_results,
+        collected_files: acquired_files,
+        memory_dumps: memory_dumps.iter().map(|dump| dump.dump.clone()).collect(),
+        plugins: plugin_artifacts,
+        custom_artifacts,
+    };
+    scan_results.collector_stats = stats.into_stats();
+    
+    // The analyses below read the serialized results, like the post-processing subcommands,
+    // and their output is added to both
+    let mut results = to_results_json(&scan_results);
+    
+    // Suggested containment steps for high-severity findings; never executed by the collector
+    scan_results.findings = remediation::collect_findings(&results);
+    summary.push(format!("✓ Remediation suggestions prepared ({} high-severity findings)", scan_results.findings.len()));
+    results["findings"] = json!(scan_results.findings);
+    
+    // Evaluate the --detection-rules against the collected artifacts
+    if !detection_rules.is_empty() {
+        let detections = detections::evaluate(detection_rules, &results);
+        let matched: usize = detections.iter().map(|d| d.match_count).sum();
+        logger.info(&format!("Detection rules evaluated: {} of {} rules matched {} artifacts", detections.len(), detection_rules.len(), matched));
+        summary.push(format!("✓ Detection rules evaluated ({} rules, {} detections)", detection_rules.len(), detections.len()));
+        results["detections"] = json!(detections);
+        scan_results.detections = Some(detections);
+    }
+    
+    // Roll the ATT&CK tags of the artifacts and detections up per technique
+    let attack_techniques = attack::summarize(&results);
+    summary.push(format!("✓ ATT&CK techniques mapped ({} techniques, {} with suspicious artifacts)", attack_techniques.len(), attack_techniques.iter().filter(|t| t.suspicious_count > 0).count()));
+    results["scan_metadata"]["attack_techniques"] = json!(attack_techniques);
+    scan_results.scan_metadata.attack_techniques = attack_techniques;
+    
+    progress.finish(total_artifacts, "✓ Collection completed");
+    
+    ScanOutcome {
+        results,
+        scan_results,
+        statistics,
+        summary,
+        total_artifacts,
+        duration,
+        log_summary,
+        screenshots,
+        collected_files,
+        memory_dumps,
+        interrupted,
+    }
+}
+
+/// The output JSON of the scan results
+pub fn to_results_json(scan_results: &ScanResults) -> Value {
+    serde_json::to_value(scan_results).expect("scan results serialize to JSON")
+}
+
+/// Enumerate the shadow copies and activate the newest one of the system volume,
+/// or a new one in `create` mode; the scan continues without one on failure
+fn prepare_shadow_copy(mode: vss::SnapshotMode, scan_results: &mut ScanResults, logger: &Logger, progress: &mut Progress) -> Vec<ShadowCopyInfo> {
+    let system_drive = std::env::var("SystemDrive").unwrap_or_else(|_| "C:".to_string());
+    
+    let (mut shadow_copies, list_logs) = vss::list_shadow_copies();
+    add_audit_entries(scan_results, &list_logs);
+    
+    let snapshot = match mode {
+        vss::SnapshotMode::Existing => vss::newest_for_volume(&shadow_copies, &system_drive).cloned(),
+        vss::SnapshotMode::Create => {
+            progress.report(&format!("Creating a shadow copy of {}...", system_drive));
+            let (created, create_logs) = vss::create_shadow_copy(&system_drive);
+            add_audit_entries(scan_results, &create_logs);
+            if let Some(created) = &created {
+                shadow_copies.retain(|copy| copy.shadow_id != created.shadow_id);
+                shadow_copies.push(created.clone());
+            }
+            created
+        }
+    };
+    
+    match snapshot {
+        Some(snapshot) => {
+            logger.info(&format!("Locked files are read from shadow copy {} ({}, created {})",
+                snapshot.shadow_id, snapshot.device_path, snapshot.creation_time));
+            progress.report(&format!("Shadow copy: {} (created {})\n", snapshot.shadow_id, snapshot.creation_time));
+            vss::activate(snapshot);
+        }
+        None => logger.warn(&format!("No shadow copy of {} available: locked files will not be collected", system_drive)),
+    }
+    shadow_copies
+}
+
+/// Single-line summary for `--brief`, for EDR "run script" features that keep only a little stdout
+pub fn brief_summary(results: &Value, output_location: &str, log_summary: &LogSummary) -> Value {
+    const SEVERITIES: [&str; 4] = ["critical", "high", "medium", "low"];
+
+    let findings = results["findings"].as_array().map(Vec::as_slice).unwrap_or_default();
+    let top_severity = SEVERITIES
+        .iter()
+        .find(|severity| findings.iter().any(|f| f["severity"].as_str() == Some(severity)))
+        .copied()
+        .unwrap_or("none");
+    let metadata = &results["scan_metadata"];
+
+    json!({
+        "host": metadata["hostname"],
+        "scan_id": metadata["scan_id"],
+        "findings": findings.len(),
+        "top_severity": top_severity,
+        "total_artifacts": metadata["total_artifacts"],
+        "errors": log_summary.error_count,
+        "duration_ms": metadata["scan_duration_ms"],
+        "output": output_location
+    })
+}
+
+/// Collect system information with comprehensive error handling
+fn collect_system_info_safe(logger: &Logger) -> Option<SystemInfo> {
+    let operation = || -> ForensicResult<SystemInfo> {
+        let mut sys = System::new_all();
+        sys.refresh_all();
+        
+        let hostname = std::env::var("COMPUTERNAME")
+            .map_err(|_| ForensicError::system_api_error("Failed to get hostname"))?;
+        let username = std::env::var("USERNAME")
+            .map_err(|_| ForensicError::system_api_error("Failed to get username"))?;
+        
+        let boot_time = System::boot_time();
+        let uptime = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_err(|_| ForensicError::system_api_error("Failed to calculate uptime"))?
+            .as_secs() - boot_time;
+        
+        Ok(SystemInfo {
+            hostname,
+            os_name: System::name().unwrap_or_else(|| "Windows_NT".to_string()),
+            os_version: System::os_version().unwrap_or_else(|| "Unknown".to_string()),
+            architecture: std::env::consts::ARCH.to_string(),
+            current_user: username,
+            uptime_hours: (uptime as f64) / 3600.0,
+            last_boot_time: chrono::DateTime::from_timestamp(boot_time as i64, 0)
+                .unwrap_or_else(chrono::Utc::now)
+                .to_rfc3339(),
+            total_memory: sys.total_memory(),
+            used_memory: sys.used_memory(),
+            cpu_count: sys.cpus().len(),
+            logged_on_users: Vec::new(),
+        })
+    };
+    
+    handle_error_gracefully(operation(), logger, "system_info_collection")
+}
+
+/// Convert forensic audit entries from the professional collectors into collection log entries
+fn add_audit_entries(scan_results: &mut ScanResults, audit_entries: &[AuditEntry]) {
+    for audit_entry in audit_entries {
+        let duration_str = audit_entry.duration_ms.map_or("N/A".to_string(), |d| d.to_string());
+        let log_entry = LogEntry::new(&audit_entry.level, &format!("[{}] {}: {} ({}ms)", 
+            audit_entry.component, audit_entry.action, audit_entry.details, duration_str));
+        scan_results.add_log(log_entry);
+    }
+}
+
+/// Hand a collector's records to the streaming writer, when the scan has one; returns their
+/// number and what stays in the results, which is nothing once they are streamed
+fn stream_section<T: Serialize>(stream: Option<&StreamWriter>, scan_results: &mut ScanResults, section: &str, records: Vec<T>) -> (usize, Vec<T>) {
+    let count = records.len();
+    match stream {
+        Some(stream) => {
+            if let Err(e) = stream.send_records(section, records) {
+                scan_results.add_log(LogEntry::new("ERROR", &format!("Failed to stream {}: {}", section, e)));
+            }
+            (count, Vec::new())
+        }
+        None => (count, records),
+    }
+}
+
+/// Drop a collector's results that fall outside the --since/--until window; returns the number dropped
+fn apply_time_window<T>(scan_results: &mut ScanResults, window: &TimeWindow, items: &mut Vec<T>, artifact: &str, timestamp: impl Fn(&T) -> &str) -> usize {
+    let removed = window.retain(items, timestamp);
+    if removed > 0 {
+        scan_results.add_log(LogEntry::info(&format!("Time window excluded {} {}", removed, artifact)));
+    }
+    removed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn results(severities: &[&str]) -> Value {
+        json!({
+            "scan_metadata": {"hostname": "WS01", "scan_id": "scan-1", "total_artifacts": 412, "scan_duration_ms": 5300},
+            "findings": severities.iter().map(|severity| json!({"severity": severity})).collect::<Vec<_>>()
+        })
+    }
+
+    #[test]
+    fn test_brief_summary_top_severity() {
+        let log_summary = LogSummary { error_count: 2, ..LogSummary::default() };
+        let summary = brief_summary(&results(&["low", "high", "medium", "high"]), "C:\\cases\\scan.json", &log_summary);
+        assert_eq!(summary["findings"], 4);
+        assert_eq!(summary["top_severity"], "high");
+        assert_eq!(summary["errors"], 2);
+        assert_eq!(brief_summary(&results(&["medium", "critical"]), "", &log_summary)["top_severity"], "critical");
+        assert!(!summary.to_string().contains('\n'));
+    }
+
+    #[test]
+    fn test_brief_summary_without_findings() {
+        let summary = brief_summary(&results(&[]), "stdout", &LogSummary::default());
+        assert_eq!(summary["findings"], 0);
+        assert_eq!(summary["top_severity"], "none");
+        assert_eq!((summary["host"].as_str(), summary["scan_id"].as_str()), (Some("WS01"), Some("scan-1")));
+        assert_eq!((summary["total_artifacts"].as_u64(), summary["duration_ms"].as_u64()), (Some(412), Some(5300)));
+        assert_eq!(brief_summary(&json!({}), "stdout", &LogSummary::default())["top_severity"], "none");
+    }
+
+    #[test]
+    fn test_brief_summary_output_location() {
+        let summary = brief_summary(&results(&["low"]), "E:\\evidence\\CASE-1_evidence.zip.volumes.json", &LogSummary::default());
+        assert_eq!(summary["output"], "E:\\evidence\\CASE-1_evidence.zip.volumes.json");
+        assert!(summary.to_string().contains(r#""output":"E:\\evidence\\CASE-1_evidence.zip.volumes.json""#));
+    }
+}
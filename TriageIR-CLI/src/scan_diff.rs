@@ -0,0 +1,231 @@
+//! Baseline comparison of two scans of the same host
+//! Artifacts are keyed by identity (executable path, persistence entry,
+//! listening port, driver path) and reported as added, removed or changed,
+//! where changed means the same artifact now has a different hash or command.
+//! Services and scheduled tasks are split out of the persistence mechanisms;
+//! for them, as for the other persistence entries, a changed SHA-256 of the
+//! launched binary is reported even when the command is unchanged (hash drift).
+
+use crate::forensic_types::{ArtifactDiff, DiffEntry, ScanDiff};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Persistence mechanism types compared as categories of their own
+const SERVICE_TYPE: &str = "Windows Service";
+const TASK_TYPE: &str = "Scheduled Task";
+
+/// Compare a baseline scan with a later scan
+pub fn diff_scans(baseline: &Value, current: &Value) -> ScanDiff {
+    ScanDiff {
+        baseline_scan_id: text(&baseline["scan_metadata"]["scan_id"]).to_string(),
+        current_scan_id: text(&current["scan_metadata"]["scan_id"]).to_string(),
+        categories: vec![
+            diff_category("processes", &process_items(baseline), &process_items(current)),
+            diff_category("persistence_mechanisms", &persistence_items(baseline, None), &persistence_items(current, None)),
+            diff_category("listening_ports", &listening_port_items(baseline), &listening_port_items(current)),
+            diff_category("loaded_drivers", &driver_items(baseline), &driver_items(current)),
+            diff_category("services", &persistence_items(baseline, Some(SERVICE_TYPE)), &persistence_items(current, Some(SERVICE_TYPE))),
+            diff_category("scheduled_tasks", &persistence_items(baseline, Some(TASK_TYPE)), &persistence_items(current, Some(TASK_TYPE))),
+        ],
+    }
+}
+
+/// Readable description of an artifact and the labelled values compared for drift
+struct DiffItem {
+    description: String,
+    fingerprints: Vec<(&'static str, String)>,
+}
+
+fn diff_category(category: &str, baseline: &BTreeMap<String, DiffItem>, current: &BTreeMap<String, DiffItem>) -> ArtifactDiff {
+    let mut diff = ArtifactDiff { category: category.to_string(), ..Default::default() };
+
+    for (key, item) in current {
+        let Some(old) = baseline.get(key) else {
+            diff.added.push(entry(key, item.description.clone()));
+            continue;
+        };
+        // Values missing from either scan (not hashed, collector skipped) are not drift
+        let changes: Vec<String> = item.fingerprints.iter().zip(&old.fingerprints)
+            .filter(|((_, new), (_, old))| !old.is_empty() && !new.is_empty() && old != new)
+            .map(|((label, new), (_, old))| format!("{} {} -> {}", label, old, new))
+            .collect();
+        if !changes.is_empty() {
+            diff.changed.push(entry(key, format!("{}: {}", item.description, changes.join("; "))));
+        }
+    }
+    for (key, item) in baseline {
+        if !current.contains_key(key) {
+            diff.removed.push(entry(key, item.description.clone()));
+        }
+    }
+
+    diff
+}
+
+fn entry(key: &str, description: String) -> DiffEntry {
+    DiffEntry { key: key.to_string(), description }
+}
+
+fn process_items(scan: &Value) -> BTreeMap<String, DiffItem> {
+    let mut items = BTreeMap::new();
+    for process in array(&scan["artifacts"]["running_processes"]) {
+        let path = text(&process["executable_path"]);
+        let name = text(&process["name"]);
+        // PIDs change across reboots; several instances of one binary are one artifact
+        let key = if path.is_empty() || path == "N/A" { name } else { path }.to_lowercase();
+        if key.is_empty() {
+            continue;
+        }
+        items.entry(key).or_insert_with(|| DiffItem {
+            description: format!("{} (PID {}) {}", name, process["pid"], path),
+            fingerprints: vec![("sha256", text(&process["sha256_hash"]).to_lowercase())],
+        });
+    }
+    items
+}
+
+/// Persistence mechanisms of one type, or of every type not compared on its own
+fn persistence_items(scan: &Value, mechanism_type: Option<&str>) -> BTreeMap<String, DiffItem> {
+    let mut items = BTreeMap::new();
+    for mechanism in array(&scan["artifacts"]["persistence_mechanisms"]) {
+        let kind = text(&mechanism["type"]);
+        let selected = match mechanism_type {
+            Some(wanted) => kind == wanted,
+            None => kind != SERVICE_TYPE && kind != TASK_TYPE,
+        };
+        if !selected {
+            continue;
+        }
+        let key = format!("{}|{}|{}", kind, text(&mechanism["source"]), text(&mechanism["name"])).to_lowercase();
+        items.entry(key).or_insert_with(|| DiffItem {
+            description: format!("{} {}", kind, text(&mechanism["name"])),
+            fingerprints: vec![
+                ("command", text(&mechanism["command"]).to_string()),
+                ("sha256", text(&mechanism["hashes"]["sha256"]).to_lowercase()),
+            ],
+        });
+    }
+    items
+}
+
+fn listening_port_items(scan: &Value) -> BTreeMap<String, DiffItem> {
+    let mut items = BTreeMap::new();
+    for connection in array(&scan["artifacts"]["network_connections"]) {
+        if !text(&connection["state"]).starts_with("LISTEN") {
+            continue;
+        }
+        let key = format!("{} {}", text(&connection["protocol"]), text(&connection["local_address"]));
+        items.entry(key).or_insert_with(|| DiffItem {
+            description: format!("{} (PID {})", text(&connection["process_name"]), connection["owning_pid"]),
+            fingerprints: Vec::new(),
+        });
+    }
+    items
+}
+
+fn driver_items(scan: &Value) -> BTreeMap<String, DiffItem> {
+    let mut items = BTreeMap::new();
+    for driver in array(&scan["artifacts"]["loaded_drivers"]) {
+        let path = text(&driver["path"]);
+        items.entry(path.to_lowercase()).or_insert_with(|| DiffItem {
+            description: format!("{} ({})", text(&driver["name"]), text(&driver["signature_status"])),
+            fingerprints: vec![("sha256", text(&driver["sha256"]).to_lowercase())],
+        });
+    }
+    items
+}
+
+fn array(value: &Value) -> &[Value] {
+    value.as_array().map(Vec::as_slice).unwrap_or_default()
+}
+
+fn text(value: &Value) -> &str {
+    value.as_str().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn scan(scan_id: &str, processes: Value, persistence: Value, connections: Value) -> Value {
+        json!({
+            "scan_metadata": { "scan_id": scan_id },
+            "artifacts": {
+                "running_processes": processes,
+                "persistence_mechanisms": persistence,
+                "network_connections": connections
+            }
+        })
+    }
+
+    #[test]
+    fn test_diff_scans() {
+        let baseline = scan(
+            "base",
+            json!([
+                { "pid": 100, "name": "svchost.exe", "executable_path": "C:\\Windows\\System32\\svchost.exe", "sha256_hash": "aa" },
+                { "pid": 200, "name": "updater.exe", "executable_path": "C:\\Tools\\updater.exe", "sha256_hash": "bb" }
+            ]),
+            json!([{ "type": "Registry Run Key", "source": "HKLM\\...\\Run", "name": "Updater", "command": "C:\\Tools\\updater.exe" }]),
+            json!([{ "protocol": "TCP", "local_address": "0.0.0.0:445", "state": "LISTEN", "process_name": "System", "owning_pid": 4 }]),
+        );
+        let current = scan(
+            "current",
+            json!([
+                { "pid": 900, "name": "svchost.exe", "executable_path": "C:\\Windows\\System32\\svchost.exe", "sha256_hash": "cc" },
+                { "pid": 901, "name": "svchost.exe", "executable_path": "C:\\Windows\\System32\\svchost.exe", "sha256_hash": "cc" },
+                { "pid": 950, "name": "beacon.exe", "executable_path": "C:\\Users\\Public\\beacon.exe", "sha256_hash": "dd" }
+            ]),
+            json!([]),
+            json!([
+                { "protocol": "TCP", "local_address": "0.0.0.0:445", "state": "LISTEN", "process_name": "System", "owning_pid": 4 },
+                { "protocol": "TCP", "local_address": "0.0.0.0:4444", "state": "LISTEN", "process_name": "beacon.exe", "owning_pid": 950 },
+                { "protocol": "TCP", "local_address": "10.0.0.5:50000", "state": "ESTABLISHED", "process_name": "beacon.exe", "owning_pid": 950 }
+            ]),
+        );
+
+        let diff = diff_scans(&baseline, &current);
+        assert_eq!(diff.baseline_scan_id, "base");
+
+        let processes = &diff.categories[0];
+        assert_eq!(processes.added, vec![entry("c:\\users\\public\\beacon.exe", "beacon.exe (PID 950) C:\\Users\\Public\\beacon.exe".to_string())]);
+        assert_eq!(processes.removed.len(), 1);
+        assert_eq!(processes.changed.len(), 1);
+        assert!(processes.changed[0].description.ends_with("aa -> cc"));
+
+        let persistence = &diff.categories[1];
+        assert!(persistence.added.is_empty());
+        assert_eq!(persistence.removed[0].description, "Registry Run Key Updater");
+
+        let ports = &diff.categories[2];
+        assert_eq!(ports.added.len(), 1);
+        assert_eq!(ports.added[0].key, "TCP 0.0.0.0:4444");
+        assert!(ports.removed.is_empty());
+
+        assert_eq!(diff.categories[3].category, "loaded_drivers");
+        assert!(diff.categories[3].added.is_empty());
+    }
+
+    #[test]
+    fn test_diff_services_and_tasks() {
+        let service = |sha256: &str| json!({ "type": "Windows Service", "source": "HKLM\\SYSTEM\\CurrentControlSet\\Services", "name": "Spooler",
+                                             "command": "C:\\Windows\\System32\\spoolsv.exe", "hashes": { "sha256": sha256 } });
+        let task = json!({ "type": "Scheduled Task", "source": "Task Scheduler: \\Updater", "name": "Updater", "command": "C:\\Users\\Public\\u.exe", "hashes": null });
+        let run_key = json!({ "type": "Registry Run Key", "source": "HKCU\\...\\Run", "name": "OneDrive", "command": "onedrive.exe /background" });
+        let baseline = scan("base", json!([]), json!([service("AA"), run_key.clone()]), json!([]));
+        let current = scan("current", json!([]), json!([service("bb"), task, run_key]), json!([]));
+
+        let diff = diff_scans(&baseline, &current);
+        let categories: Vec<&str> = diff.categories.iter().map(|c| c.category.as_str()).collect();
+        assert_eq!(categories, ["processes", "persistence_mechanisms", "listening_ports", "loaded_drivers", "services", "scheduled_tasks"]);
+
+        let persistence = &diff.categories[1];
+        assert!(persistence.added.is_empty() && persistence.removed.is_empty() && persistence.changed.is_empty());
+        let services = &diff.categories[4];
+        assert_eq!(services.changed.len(), 1);
+        assert_eq!(services.changed[0].description, "Windows Service Spooler: sha256 aa -> bb");
+        let tasks = &diff.categories[5];
+        assert_eq!(tasks.added[0].description, "Scheduled Task Updater");
+    }
+}
@@ -0,0 +1,290 @@
+//! Scan profiles
+//! `--profile` selects which collectors run, how much they collect and for how
+//! long, from one of the built-in profiles or a TOML file of the same shape.
+//! Settings given on the command line take precedence over the profile's.
+//!
+//! ```toml
+//! description = "Registry and process triage"
+//! hash_algorithms = "md5,sha256"
+//!
+//! [collectors]
+//! disable = ["event_logs", "srum", "usn_journal"]
+//!
+//! [limits]
+//! max_events = 200            # per event log channel
+//! max_hashed_processes = 100  # process executables hashed
+//! module_enumeration = false  # loader list of flagged processes
+//!
+//! [timeouts]
+//! default = 60
+//! persistence = 120
+//!
+//! [output]
+//! verbose = true
+//! report_top = 20
+//! ```
+
+use crate::scan::ScanConfig;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Names of the built-in profiles accepted by --profile
+pub const PROFILES: [&str; 3] = ["quick", "standard", "deep"];
+
+/// Collectors a profile can disable, by their collector_stats name. The
+/// acquisition and verification collectors (file sweep, dumps, canaries, file
+/// collection, MFT, plugins) only run when requested and are left out.
+pub const COLLECTORS: [&str; 40] = [
+    "sessions", "processes", "dll_hijacks", "drivers", "network", "named_pipes", "rdp_history", "rdp_session_events",
+    "smb_sessions", "network_configuration", "name_resolution", "network_logs", "persistence", "hijacks", "event_logs",
+    "event_log_retention", "prefetch", "shimcache", "amcache", "bam", "user_execution", "defender_logs",
+    "historical_processes", "srum", "setupapi", "usb_devices", "security_configuration", "certificate_stores",
+    "defender_detections", "powershell", "sysmon", "local_accounts", "search_index", "thumbcache", "notifications",
+    "clipboard_history", "search_history", "mru", "browser_artifacts", "usn_journal",
+];
+
+/// Minutes-long first look: volatile state, persistence and the execution
+/// artifacts that are quick to parse
+const QUICK: &str = r#"
+description = "Fast first look: processes, network, persistence and execution artifacts"
+
+[collectors]
+disable = [
+    "event_logs", "event_log_retention", "historical_processes", "powershell", "sysmon", "srum", "usn_journal",
+    "network_logs", "rdp_session_events", "certificate_stores", "search_index", "thumbcache", "notifications",
+    "clipboard_history", "search_history", "browser_artifacts",
+]
+
+[limits]
+max_events = 200
+max_hashed_processes = 100
+module_enumeration = false
+
+[timeouts]
+default = 60
+"#;
+
+/// The default collection
+const STANDARD: &str = r#"
+description = "Every collector with the default limits"
+"#;
+
+/// Everything, with all digests and the memory checks of every process
+const DEEP: &str = r#"
+description = "Every collector, all digests, the deep process scan and larger event log limits"
+hash_algorithms = "md5,sha1,sha256,imphash"
+fuzzy_hash = true
+deep_process_scan = true
+
+[limits]
+max_events = 10000
+module_enumeration = true
+
+[timeouts]
+default = 1800
+"#;
+
+#[derive(Debug, Clone, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ScanProfile {
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub hash_algorithms: Option<String>,
+    #[serde(default)]
+    pub fuzzy_hash: bool,
+    #[serde(default)]
+    pub deep_process_scan: bool,
+    #[serde(default)]
+    pub collectors: ProfileCollectors,
+    #[serde(default)]
+    pub limits: ProfileLimits,
+    /// `default` for every collector, or per collector_stats name, in seconds
+    #[serde(default)]
+    pub timeouts: BTreeMap<String, u64>,
+    #[serde(default)]
+    pub output: ProfileOutput,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ProfileCollectors {
+    #[serde(default)]
+    pub disable: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ProfileLimits {
+    /// Entries collected per event log channel (an --event-config profile keeps its own)
+    pub max_events: Option<usize>,
+    /// Processes whose executables are hashed; the rest are listed unhashed
+    pub max_hashed_processes: Option<usize>,
+    /// Walk the loader list of flagged processes (default: true)
+    pub module_enumeration: Option<bool>,
+}
+
+/// Console and report settings, applied by the CLI
+#[derive(Debug, Clone, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ProfileOutput {
+    pub verbose: Option<bool>,
+    /// Suspicious artifacts listed in the --report summary
+    pub report_top: Option<usize>,
+}
+
+impl ScanProfile {
+    /// A built-in profile by name, or a TOML profile file
+    pub fn resolve(name_or_path: &str) -> Result<Self, String> {
+        let builtin = match name_or_path {
+            "quick" => Some(QUICK),
+            "standard" => Some(STANDARD),
+            "deep" => Some(DEEP),
+            _ => None,
+        };
+        match builtin {
+            Some(text) => Self::parse(text).map_err(|e| format!("Invalid built-in profile {}: {}", name_or_path, e)),
+            None => Self::load(Path::new(name_or_path)),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| {
+            format!("Failed to read scan profile {} (built-in profiles: {}): {}", path.display(), PROFILES.join(", "), e)
+        })?;
+        Self::parse(&text).map_err(|e| format!("Invalid scan profile {}: {}", path.display(), e))
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let profile: ScanProfile = toml::from_str(text).map_err(|e| e.to_string())?;
+        profile.validate()?;
+        Ok(profile)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if let Some(unknown) = self.collectors.disable.iter().find(|name| !COLLECTORS.contains(&name.as_str())) {
+            return Err(format!("unknown collector {} (one of: {})", unknown, COLLECTORS.join(", ")));
+        }
+        if let Some((collector, _)) = self.timeouts.iter().find(|(_, &seconds)| seconds == 0) {
+            return Err(format!("timeout of {} must be at least 1 second", collector));
+        }
+        if self.limits.max_events == Some(0) {
+            return Err("max_events must be at least 1".to_string());
+        }
+        Ok(())
+    }
+
+    /// Fill the settings the caller left unset from the profile. Returns one
+    /// line per applied setting, for the collection log
+    pub fn apply(&self, config: &mut ScanConfig) -> Vec<String> {
+        let mut applied = Vec::new();
+        if config.hash_algorithms.is_none() {
+            if let Some(algorithms) = &self.hash_algorithms {
+                config.hash_algorithms = Some(algorithms.clone());
+                applied.push(format!("hash algorithms: {}", algorithms));
+            }
+        }
+        if self.fuzzy_hash && !config.fuzzy_hash {
+            config.fuzzy_hash = true;
+            applied.push("fuzzy hash: on".to_string());
+        }
+        if self.deep_process_scan && !config.deep_process_scan {
+            if config.offline_root.is_some() {
+                applied.push("deep process scan: not available offline".to_string());
+            } else {
+                config.deep_process_scan = true;
+                applied.push("deep process scan: on".to_string());
+            }
+        }
+
+        for collector in &self.collectors.disable {
+            if !config.disabled_collectors.contains(collector) {
+                config.disabled_collectors.push(collector.clone());
+            }
+        }
+        if !self.collectors.disable.is_empty() {
+            applied.push(format!("disabled collectors: {}", self.collectors.disable.join(", ")));
+        }
+
+        if config.max_events.is_none() {
+            if let Some(max_events) = self.limits.max_events {
+                config.max_events = Some(max_events);
+                applied.push(format!("max events per channel: {}", max_events));
+            }
+        }
+        if config.max_hashed_processes.is_none() {
+            if let Some(max_hashed) = self.limits.max_hashed_processes {
+                config.max_hashed_processes = Some(max_hashed);
+                applied.push(format!("max hashed processes: {}", max_hashed));
+            }
+        }
+        if config.module_enumeration.is_none() {
+            if let Some(enabled) = self.limits.module_enumeration {
+                config.module_enumeration = Some(enabled);
+                applied.push(format!("module enumeration: {}", if enabled { "on" } else { "off" }));
+            }
+        }
+
+        // Timeouts given later (on the command line) override these
+        let timeouts: Vec<String> = self
+            .timeouts
+            .iter()
+            .map(|(collector, seconds)| match collector.as_str() {
+                "default" => seconds.to_string(),
+                collector => format!("{}={}", collector, seconds),
+            })
+            .collect();
+        if !timeouts.is_empty() {
+            applied.push(format!("collector timeouts: {}", timeouts.join(", ")));
+            config.collector_timeouts.splice(0..0, timeouts);
+        }
+        applied
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_profiles() {
+        for name in PROFILES {
+            assert!(ScanProfile::resolve(name).is_ok(), "{}", name);
+        }
+        let quick = ScanProfile::resolve("quick").unwrap();
+        assert!(quick.collectors.disable.contains(&"event_logs".to_string()));
+        assert_eq!(quick.limits.module_enumeration, Some(false));
+        assert_eq!(ScanProfile::resolve("standard").unwrap().collectors, ProfileCollectors::default());
+        assert!(ScanProfile::resolve("does-not-exist.toml").unwrap_err().contains("built-in profiles: quick, standard, deep"));
+    }
+
+    #[test]
+    fn test_apply_keeps_command_line_settings() {
+        let profile = ScanProfile::parse(
+            "hash_algorithms = \"md5\"\ndeep_process_scan = true\n[collectors]\ndisable = [\"srum\"]\n[limits]\nmax_events = 50\n[timeouts]\ndefault = 60\nevent_logs = 300\n",
+        )
+        .unwrap();
+        let mut config = ScanConfig {
+            hash_algorithms: Some("sha1".to_string()),
+            offline_root: Some("E:\\".to_string()),
+            collector_timeouts: vec!["event_logs=30".to_string()],
+            ..Default::default()
+        };
+        let applied = profile.apply(&mut config);
+
+        assert_eq!(config.hash_algorithms.as_deref(), Some("sha1"));
+        assert!(!config.deep_process_scan);
+        assert_eq!(config.disabled_collectors, vec!["srum".to_string()]);
+        assert_eq!(config.max_events, Some(50));
+        assert_eq!(config.collector_timeouts, vec!["60", "event_logs=300", "event_logs=30"]);
+        assert!(applied.contains(&"deep process scan: not available offline".to_string()));
+    }
+
+    #[test]
+    fn test_rejects_unknown_collectors() {
+        assert!(ScanProfile::parse("[collectors]\ndisable = [\"registry\"]\n").unwrap_err().contains("unknown collector registry"));
+        assert!(ScanProfile::parse("[timeouts]\nprocesses = 0\n").is_err());
+        assert!(ScanProfile::parse("verbose = true\n").is_err());
+    }
+}
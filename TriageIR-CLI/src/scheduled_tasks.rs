@@ -1,474 +1,584 @@
-use crate::forensic_types::{ScheduledTask, TaskTrigger, TaskAction, AuditEntry};
+//! Scheduled task inventory
+//! The single source of scheduled tasks. The Task Scheduler API (ITaskService)
+//! walks every folder, hidden tasks included, and yields the state, run
+//! times, last result and security descriptor of each task along with its
+//! XML definition (actions, triggers, principal, registration info).
+//! `schtasks /query /fo csv /v` plus one `schtasks /query /xml ONE` remain as
+//! the fallback; their listing is locale-dependent. Tasks are scored by the
+//! shared suspicion model; the persistence collector reports them as
+//! persistence mechanisms.
+
+use crate::event_logs::unescape_xml;
+use crate::forensic_types::{ScheduledTask, TaskAction, TaskTrigger};
+use crate::logger::run_command;
+use crate::suspicion;
+use crate::types::LogEntry;
+use chrono::TimeZone;
+use std::collections::HashSet;
 use std::process::Command;
-use std::collections::HashMap;
-use regex::Regex;
-
-/// Scheduled Tasks analysis for persistence mechanisms
-/// Scheduled tasks are a common persistence mechanism used by both legitimate software and malware
-
-pub fn collect_scheduled_tasks() -> (Vec<ScheduledTask>, Vec<AuditEntry>) {
-    let mut scheduled_tasks = Vec::new();
-    let mut audit_log = Vec::new();
-    
-    let start_time = std::time::Instant::now();
-    
-    audit_log.push(AuditEntry {
-        timestamp: chrono::Utc::now().to_rfc3339(),
-        level: "INFO".to_string(),
-        component: "scheduled_tasks".to_string(),
-        action: "start_collection".to_string(),
-        details: "Starting scheduled tasks analysis".to_string(),
-        duration_ms: None,
-        result: "started".to_string(),
-    });
-    
-    // Use schtasks.exe to enumerate all scheduled tasks
-    match collect_tasks_via_schtasks() {
-        Ok((tasks, logs)) => {
-            scheduled_tasks.extend(tasks);
-            audit_log.extend(logs);
+
+#[cfg(windows)]
+use crate::user_hives;
+#[cfg(windows)]
+use windows::core::BSTR;
+#[cfg(windows)]
+use windows::Win32::Security::{DACL_SECURITY_INFORMATION, OWNER_SECURITY_INFORMATION};
+#[cfg(windows)]
+use windows::Win32::System::Com::{CoCreateInstance, CoInitializeEx, CoUninitialize, CLSCTX_INPROC_SERVER, COINIT_MULTITHREADED};
+#[cfg(windows)]
+use windows::Win32::System::TaskScheduler::{
+    IRegisteredTask, ITaskFolder, ITaskService, TaskScheduler, TASK_ENUM_HIDDEN, TASK_STATE_DISABLED,
+    TASK_STATE_QUEUED, TASK_STATE_READY, TASK_STATE_RUNNING,
+};
+#[cfg(windows)]
+use windows::Win32::System::Variant::{VARIANT, VT_I4};
+
+/// Trigger elements of a task definition and the type they are reported as
+const TRIGGER_TYPES: [(&str, &str); 8] = [
+    ("TimeTrigger", "Time"),
+    ("CalendarTrigger", "Calendar"),
+    ("LogonTrigger", "Logon"),
+    ("BootTrigger", "Boot"),
+    ("IdleTrigger", "Idle"),
+    ("EventTrigger", "Event"),
+    ("RegistrationTrigger", "Registration"),
+    ("SessionStateChangeTrigger", "SessionStateChange"),
+];
+
+/// Run times before 2000 mean the task never ran (the API reports 1999-11-30)
+const FIRST_RUN_DATE: f64 = 36526.0;
+
+/// Enumerate and score the scheduled tasks of the live system
+pub fn collect_scheduled_tasks() -> (Vec<ScheduledTask>, Vec<LogEntry>) {
+    let mut logs = Vec::new();
+    let mut tasks = match collect_tasks_via_api() {
+        Ok((tasks, failures)) => {
+            for failure in failures {
+                logs.push(LogEntry::warn(&failure));
+            }
+            logs.push(LogEntry::info(&format!("Enumerated {} scheduled tasks through the Task Scheduler API", tasks.len())));
+            tasks
         }
         Err(e) => {
-            audit_log.push(AuditEntry {
-                timestamp: chrono::Utc::now().to_rfc3339(),
-                level: "ERROR".to_string(),
-                component: "scheduled_tasks".to_string(),
-                action: "schtasks_execution".to_string(),
-                details: format!("Failed to execute schtasks: {}", e),
-                duration_ms: None,
-                result: "error".to_string(),
-            });
+            logs.push(LogEntry::warn(&format!("Task Scheduler API unavailable ({}), falling back to schtasks", e)));
+            match collect_tasks_via_schtasks() {
+                Ok(tasks) => {
+                    logs.push(LogEntry::info(&format!("Listed {} scheduled tasks with schtasks", tasks.len())));
+                    tasks
+                }
+                Err(e) => {
+                    logs.push(LogEntry::warn(&format!("Failed to collect scheduled tasks: {}", e)));
+                    Vec::new()
+                }
+            }
+        }
+    };
+
+    suspicion::shared().score_scheduled_tasks(&mut tasks);
+    (tasks, logs)
+}
+
+/// Every task of every folder through ITaskService, and the folders that could not be read
+#[cfg(windows)]
+fn collect_tasks_via_api() -> Result<(Vec<ScheduledTask>, Vec<String>), String> {
+    unsafe {
+        // Fails with RPC_E_CHANGED_MODE on a thread already in another apartment, where COM is usable as is
+        let initialized = CoInitializeEx(None, COINIT_MULTITHREADED).is_ok();
+        let result = enumerate_task_service();
+        if initialized {
+            CoUninitialize();
         }
+        result
     }
-    
-    // Also try to collect from registry (backup method)
-    match collect_tasks_from_registry() {
-        Ok((tasks, logs)) => {
-            // Merge with existing tasks, avoiding duplicates
-            for task in tasks {
-                if !scheduled_tasks.iter().any(|t| t.name == task.name && t.path == task.path) {
-                    scheduled_tasks.push(task);
+}
+
+#[cfg(not(windows))]
+fn collect_tasks_via_api() -> Result<(Vec<ScheduledTask>, Vec<String>), String> {
+    Err("the Task Scheduler API requires Windows".to_string())
+}
+
+#[cfg(windows)]
+unsafe fn enumerate_task_service() -> Result<(Vec<ScheduledTask>, Vec<String>), String> {
+    let service: ITaskService = CoCreateInstance(&TaskScheduler, None, CLSCTX_INPROC_SERVER)
+        .map_err(|e| format!("Failed to create the Task Scheduler service: {}", e))?;
+    service
+        .Connect(VARIANT::default(), VARIANT::default(), VARIANT::default(), VARIANT::default())
+        .map_err(|e| format!("Failed to connect to the Task Scheduler: {}", e))?;
+    let root = service
+        .GetFolder(&BSTR::from("\\"))
+        .map_err(|e| format!("Failed to open the root task folder: {}", e))?;
+
+    let mut tasks = Vec::new();
+    let mut failures = Vec::new();
+    collect_folder_tasks(&root, &mut tasks, &mut failures);
+    Ok((tasks, failures))
+}
+
+#[cfg(windows)]
+unsafe fn collect_folder_tasks(folder: &ITaskFolder, tasks: &mut Vec<ScheduledTask>, failures: &mut Vec<String>) {
+    let folder_path = folder.Path().map(|path| path.to_string()).unwrap_or_default();
+    match folder.GetTasks(TASK_ENUM_HIDDEN.0) {
+        Ok(collection) => {
+            for index in 1..=collection.Count().unwrap_or(0) {
+                match collection.get_Item(index_variant(index)) {
+                    Ok(task) => tasks.push(registered_task(&task)),
+                    Err(e) => failures.push(format!("Failed to read task {} of {}: {}", index, folder_path, e)),
                 }
             }
-            audit_log.extend(logs);
         }
-        Err(e) => {
-            audit_log.push(AuditEntry {
-                timestamp: chrono::Utc::now().to_rfc3339(),
-                level: "WARN".to_string(),
-                component: "scheduled_tasks".to_string(),
-                action: "registry_collection".to_string(),
-                details: format!("Registry collection failed: {}", e),
-                duration_ms: None,
-                result: "error".to_string(),
-            });
+        Err(e) => failures.push(format!("Failed to list the tasks of {}: {}", folder_path, e)),
+    }
+
+    match folder.GetFolders(0) {
+        Ok(folders) => {
+            for index in 1..=folders.Count().unwrap_or(0) {
+                if let Ok(subfolder) = folders.get_Item(index_variant(index)) {
+                    collect_folder_tasks(&subfolder, tasks, failures);
+                }
+            }
+        }
+        Err(e) => failures.push(format!("Failed to list the subfolders of {}: {}", folder_path, e)),
+    }
+}
+
+#[cfg(windows)]
+unsafe fn registered_task(task: &IRegisteredTask) -> ScheduledTask {
+    let path = task.Path().map(|path| path.to_string()).unwrap_or_default();
+    let state = match task.State() {
+        Ok(TASK_STATE_READY) => "Ready",
+        Ok(TASK_STATE_RUNNING) => "Running",
+        Ok(TASK_STATE_DISABLED) => "Disabled",
+        Ok(TASK_STATE_QUEUED) => "Queued",
+        _ => "Unknown",
+    };
+    let security_information = (OWNER_SECURITY_INFORMATION.0 | DACL_SECURITY_INFORMATION.0) as i32;
+    let mut scheduled = ScheduledTask {
+        name: extract_task_name(&path),
+        state: state.to_string(),
+        last_run_time: ole_date_to_string(task.LastRunTime().unwrap_or_default()),
+        next_run_time: ole_date_to_string(task.NextRunTime().unwrap_or_default()),
+        run_as_user: "Unknown".to_string(),
+        command: "Unknown".to_string(),
+        arguments: String::new(),
+        working_directory: String::new(),
+        triggers: Vec::new(),
+        actions: Vec::new(),
+        creation_date: String::new(),
+        author: String::new(),
+        description: String::new(),
+        hidden: false,
+        last_task_result: task.LastTaskResult().ok(),
+        security_descriptor: task.GetSecurityDescriptor(security_information).ok().map(|sddl| sddl.to_string()),
+        suspicion_score: 0,
+        suspicion_reasons: Vec::new(),
+        path,
+    };
+    if let Ok(xml) = task.Xml() {
+        apply_definition(&mut scheduled, &xml.to_string());
+    }
+    // Principals name service accounts and users by SID
+    if scheduled.run_as_user.starts_with("S-1-") {
+        if let Some(name) = user_hives::resolve_sid(&scheduled.run_as_user) {
+            scheduled.run_as_user = name;
         }
     }
-    
-    let duration = start_time.elapsed();
-    audit_log.push(AuditEntry {
-        timestamp: chrono::Utc::now().to_rfc3339(),
-        level: "INFO".to_string(),
-        component: "scheduled_tasks".to_string(),
-        action: "complete_collection".to_string(),
-        details: format!("Collected {} scheduled tasks", scheduled_tasks.len()),
-        duration_ms: Some(duration.as_millis() as u64),
-        result: "success".to_string(),
-    });
-    
-    (scheduled_tasks, audit_log)
+    scheduled
 }
 
-fn collect_tasks_via_schtasks() -> Result<(Vec<ScheduledTask>, Vec<AuditEntry>), Box<dyn std::error::Error>> {
-    let mut scheduled_tasks = Vec::new();
-    let mut audit_log = Vec::new();
-    
-    // First, get list of all tasks
-    let output = Command::new("schtasks")
-        .args(&["/query", "/fo", "csv", "/v"])
-        .output()?;
-    
+/// Collection items are addressed by a 1-based VT_I4 index
+#[cfg(windows)]
+unsafe fn index_variant(index: i32) -> VARIANT {
+    let mut variant = VARIANT::default();
+    let value = &mut *variant.Anonymous.Anonymous;
+    value.vt = VT_I4;
+    value.Anonymous.lVal = index;
+    variant
+}
+
+/// Fallback: the verbose CSV listing completed by the XML definitions
+fn collect_tasks_via_schtasks() -> Result<Vec<ScheduledTask>, String> {
+    let output = run_command("scheduled_tasks", Command::new("schtasks").args(["/query", "/fo", "csv", "/v"]))
+        .map_err(|e| format!("Failed to execute schtasks: {}", e))?;
     if !output.status.success() {
-        return Err(format!("schtasks failed with exit code: {}", output.status).into());
+        return Err(format!("schtasks failed with {}", output.status));
     }
-    
-    let output_str = String::from_utf8_lossy(&output.stdout);
-    let lines: Vec<&str> = output_str.lines().collect();
-    
-    if lines.is_empty() {
-        return Ok((scheduled_tasks, audit_log));
+    let mut tasks = parse_task_list(&String::from_utf8_lossy(&output.stdout));
+
+    // Definitions are best effort: the list alone names each task, its account and command
+    if let Ok(output) = run_command("scheduled_tasks", Command::new("schtasks").args(["/query", "/xml", "ONE"])) {
+        if output.status.success() {
+            apply_task_definitions(&mut tasks, &String::from_utf8_lossy(&output.stdout));
+        }
+    }
+    Ok(tasks)
+}
+
+/// Task Scheduler run times are OLE Automation dates (days since 1899-12-30) in local time
+#[cfg_attr(not(windows), allow(dead_code))]
+fn ole_date_to_string(days: f64) -> String {
+    if !days.is_finite() || days < FIRST_RUN_DATE {
+        return "Never".to_string();
+    }
+    let Some(epoch) = chrono::NaiveDate::from_ymd_opt(1899, 12, 30).and_then(|date| date.and_hms_opt(0, 0, 0)) else {
+        return "Never".to_string();
+    };
+    let local = epoch + chrono::Duration::milliseconds((days * 86_400_000.0).round() as i64);
+    match chrono::Local.from_local_datetime(&local).earliest() {
+        Some(time) => time.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        None => local.format("%Y-%m-%dT%H:%M:%S").to_string(),
     }
-    
-    // Parse CSV header to understand column positions
-    let header = lines[0];
-    let columns: Vec<&str> = parse_csv_line(header);
-    
-    // Find column indices
-    let task_name_idx = find_column_index(&columns, "TaskName");
-    let next_run_idx = find_column_index(&columns, "Next Run Time");
-    let status_idx = find_column_index(&columns, "Status");
-    let last_run_idx = find_column_index(&columns, "Last Run Time");
-    let run_as_user_idx = find_column_index(&columns, "Run As User");
-    let task_to_run_idx = find_column_index(&columns, "Task To Run");
-    
-    audit_log.push(AuditEntry {
-        timestamp: chrono::Utc::now().to_rfc3339(),
-        level: "DEBUG".to_string(),
-        component: "scheduled_tasks".to_string(),
-        action: "parse_csv".to_string(),
-        details: format!("Parsing {} task entries", lines.len() - 1),
-        duration_ms: None,
-        result: "started".to_string(),
-    });
-    
-    // Parse each task line
-    for (line_num, line) in lines.iter().skip(1).enumerate() {
-        if line.trim().is_empty() {
+}
+
+/// Tasks of the verbose CSV listing, once each
+pub fn parse_task_list(csv: &str) -> Vec<ScheduledTask> {
+    let mut lines = csv.lines().filter(|line| !line.trim().is_empty());
+    let Some(header) = lines.next() else {
+        return Vec::new();
+    };
+    let columns = parse_csv_line(header);
+    let column = |name: &str| find_column_index(&columns, name);
+    let name_idx = column("TaskName");
+    let status_idx = column("Status");
+    let last_run_idx = column("Last Run Time");
+    let next_run_idx = column("Next Run Time");
+    let run_as_user_idx = column("Run As User");
+    let task_to_run_idx = column("Task To Run");
+    let start_in_idx = column("Start In");
+    let author_idx = column("Author");
+    let comment_idx = column("Comment");
+    let last_result_idx = column("Last Result");
+
+    let mut tasks = Vec::new();
+    let mut seen = HashSet::new();
+    for line in lines {
+        let fields = parse_csv_line(line);
+        let task_name = get_field(&fields, name_idx).unwrap_or_default();
+        // schtasks repeats the header for every folder and lists a task once per trigger
+        if task_name.is_empty() || task_name.eq_ignore_ascii_case("TaskName") {
             continue;
         }
-        
-        let fields = parse_csv_line(line);
-        
-        let task_name = get_field(&fields, task_name_idx).unwrap_or("Unknown");
-        let task_path = if task_name.starts_with('\\') {
+        let path = if task_name.starts_with('\\') {
             task_name.to_string()
         } else {
             format!("\\{}", task_name)
         };
-        
-        // Get detailed information for this specific task
-        match get_task_details(&task_path) {
-            Ok(detailed_task) => {
-                scheduled_tasks.push(detailed_task);
-                audit_log.push(AuditEntry {
-                    timestamp: chrono::Utc::now().to_rfc3339(),
-                    level: "DEBUG".to_string(),
-                    component: "scheduled_tasks".to_string(),
-                    action: "parse_task".to_string(),
-                    details: format!("Parsed task: {}", task_name),
-                    duration_ms: None,
-                    result: "success".to_string(),
-                });
-            }
-            Err(e) => {
-                // Create basic task from CSV data
-                let basic_task = ScheduledTask {
-                    name: extract_task_name(task_name),
-                    path: task_path,
-                    state: get_field(&fields, status_idx).unwrap_or("Unknown").to_string(),
-                    last_run_time: get_field(&fields, last_run_idx).unwrap_or("Never").to_string(),
-                    next_run_time: get_field(&fields, next_run_idx).unwrap_or("Never").to_string(),
-                    run_as_user: get_field(&fields, run_as_user_idx).unwrap_or("Unknown").to_string(),
-                    command: get_field(&fields, task_to_run_idx).unwrap_or("Unknown").to_string(),
-                    arguments: String::new(),
-                    working_directory: String::new(),
-                    triggers: vec![],
-                    actions: vec![],
-                    creation_date: "Unknown".to_string(),
-                    author: "Unknown".to_string(),
-                    description: "Basic task info from CSV".to_string(),
-                };
-                
-                scheduled_tasks.push(basic_task);
-                
-                audit_log.push(AuditEntry {
-                    timestamp: chrono::Utc::now().to_rfc3339(),
-                    level: "WARN".to_string(),
-                    component: "scheduled_tasks".to_string(),
-                    action: "parse_task".to_string(),
-                    details: format!("Failed to get details for {}: {}", task_name, e),
-                    duration_ms: None,
-                    result: "partial".to_string(),
-                });
-            }
+        if !seen.insert(path.to_lowercase()) {
+            continue;
         }
-    }
-    
-    Ok((scheduled_tasks, audit_log))
-}
 
-fn get_task_details(task_path: &str) -> Result<ScheduledTask, Box<dyn std::error::Error>> {
-    // Get detailed XML information for the task
-    let output = Command::new("schtasks")
-        .args(&["/query", "/tn", task_path, "/xml"])
-        .output()?;
-    
-    if !output.status.success() {
-        return Err(format!("Failed to get task details for {}", task_path).into());
+        let field = |index: Option<usize>, default: &str| get_field(&fields, index).filter(|value| !value.is_empty()).unwrap_or(default).to_string();
+        tasks.push(ScheduledTask {
+            name: extract_task_name(&path),
+            state: field(status_idx, "Unknown"),
+            last_run_time: field(last_run_idx, "Never"),
+            next_run_time: field(next_run_idx, "Never"),
+            run_as_user: field(run_as_user_idx, "Unknown"),
+            command: field(task_to_run_idx, "Unknown"),
+            arguments: String::new(),
+            working_directory: field(start_in_idx, ""),
+            triggers: Vec::new(),
+            actions: Vec::new(),
+            creation_date: String::new(),
+            author: field(author_idx, ""),
+            description: field(comment_idx, ""),
+            hidden: false,
+            last_task_result: get_field(&fields, last_result_idx).and_then(|value| value.parse().ok()),
+            security_descriptor: None,
+            suspicion_score: 0,
+            suspicion_reasons: Vec::new(),
+            path,
+        });
     }
-    
-    let xml_content = String::from_utf8_lossy(&output.stdout);
-    parse_task_xml(&xml_content, task_path)
+    tasks
 }
 
-fn parse_task_xml(xml_content: &str, task_path: &str) -> Result<ScheduledTask, Box<dyn std::error::Error>> {
-    // Simplified XML parsing - in production, use a proper XML parser
-    let task_name = extract_task_name(task_path);
-    
-    // Extract basic information using regex patterns
-    let author = extract_xml_value(xml_content, "Author").unwrap_or("Unknown".to_string());
-    let description = extract_xml_value(xml_content, "Description").unwrap_or("No description".to_string());
-    let creation_date = extract_xml_value(xml_content, "Date").unwrap_or("Unknown".to_string());
-    
-    // Extract command and arguments
-    let command = extract_xml_value(xml_content, "Command").unwrap_or("Unknown".to_string());
-    let arguments = extract_xml_value(xml_content, "Arguments").unwrap_or(String::new());
-    let working_directory = extract_xml_value(xml_content, "WorkingDirectory").unwrap_or(String::new());
-    
-    // Extract user context
-    let run_as_user = extract_xml_value(xml_content, "UserId")
-        .or_else(|| extract_xml_value(xml_content, "Principal"))
-        .unwrap_or("Unknown".to_string());
-    
-    // Parse triggers (simplified)
-    let triggers = parse_task_triggers(xml_content);
-    
-    // Parse actions (simplified)
-    let actions = parse_task_actions(xml_content);
-    
-    Ok(ScheduledTask {
-        name: task_name,
-        path: task_path.to_string(),
-        state: "Unknown".to_string(), // Would need additional query
-        last_run_time: "Unknown".to_string(),
-        next_run_time: "Unknown".to_string(),
-        run_as_user,
-        command,
-        arguments,
-        working_directory,
-        triggers,
-        actions,
-        creation_date,
-        author,
-        description,
-    })
-}
-
-fn extract_xml_value(xml_content: &str, tag_name: &str) -> Option<String> {
-    let pattern = format!(r"<{}>(.*?)</{}>", tag_name, tag_name);
-    let re = Regex::new(&pattern).ok()?;
-    
-    re.captures(xml_content)
-        .and_then(|caps| caps.get(1))
-        .map(|m| m.as_str().to_string())
+/// Complete the listed tasks from the `/xml ONE` output, where a comment with
+/// the task path precedes each definition
+pub fn apply_task_definitions(tasks: &mut [ScheduledTask], xml: &str) {
+    let mut rest = xml;
+    while let Some(start) = rest.find("<!--") {
+        let comment = &rest[start + 4..];
+        let Some(end) = comment.find("-->") else {
+            break;
+        };
+        let path = comment[..end].trim();
+        let body = &comment[end + 3..];
+        let next = body.find("<!--").unwrap_or(body.len());
+        if let Some(task) = tasks.iter_mut().find(|task| task.path.eq_ignore_ascii_case(path)) {
+            apply_definition(task, &body[..next]);
+        }
+        rest = &body[next..];
+    }
 }
 
-fn parse_task_triggers(xml_content: &str) -> Vec<TaskTrigger> {
-    let mut triggers = Vec::new();
-    
-    // Look for common trigger patterns
-    if xml_content.contains("<TimeTrigger>") {
-        triggers.push(TaskTrigger {
-            trigger_type: "Time".to_string(),
-            start_boundary: extract_xml_value(xml_content, "StartBoundary").unwrap_or("Unknown".to_string()),
-            end_boundary: extract_xml_value(xml_content, "EndBoundary").unwrap_or("None".to_string()),
-            enabled: !xml_content.contains("<Enabled>false</Enabled>"),
-            repetition_interval: extract_xml_value(xml_content, "Interval").unwrap_or("None".to_string()),
-            repetition_duration: extract_xml_value(xml_content, "Duration").unwrap_or("None".to_string()),
-        });
+fn apply_definition(task: &mut ScheduledTask, definition: &str) {
+    if let Some(registration) = xml_block(definition, "RegistrationInfo") {
+        if let Some(author) = xml_value(registration, "Author") {
+            task.author = author;
+        }
+        if let Some(date) = xml_value(registration, "Date") {
+            task.creation_date = date;
+        }
+        if let Some(description) = xml_value(registration, "Description") {
+            task.description = description;
+        }
     }
-    
-    if xml_content.contains("<LogonTrigger>") {
-        triggers.push(TaskTrigger {
-            trigger_type: "Logon".to_string(),
-            start_boundary: "On logon".to_string(),
-            end_boundary: "None".to_string(),
-            enabled: !xml_content.contains("<Enabled>false</Enabled>"),
-            repetition_interval: "None".to_string(),
-            repetition_duration: "None".to_string(),
-        });
+    if let Some(settings) = xml_block(definition, "Settings") {
+        task.hidden = xml_value(settings, "Hidden").is_some_and(|hidden| hidden == "true");
     }
-    
-    if xml_content.contains("<BootTrigger>") {
-        triggers.push(TaskTrigger {
-            trigger_type: "Boot".to_string(),
-            start_boundary: "On boot".to_string(),
-            end_boundary: "None".to_string(),
-            enabled: !xml_content.contains("<Enabled>false</Enabled>"),
-            repetition_interval: "None".to_string(),
-            repetition_duration: "None".to_string(),
-        });
+    // The listing resolves the account name; definitions often hold only a SID
+    if task.run_as_user == "Unknown" {
+        if let Some(account) = xml_block(definition, "Principal").and_then(|principal| xml_value(principal, "UserId").or_else(|| xml_value(principal, "GroupId"))) {
+            task.run_as_user = account;
+        }
     }
-    
-    if triggers.is_empty() {
-        triggers.push(TaskTrigger {
-            trigger_type: "Unknown".to_string(),
-            start_boundary: "Unknown".to_string(),
-            end_boundary: "Unknown".to_string(),
-            enabled: true,
-            repetition_interval: "Unknown".to_string(),
-            repetition_duration: "Unknown".to_string(),
-        });
+
+    task.triggers = TRIGGER_TYPES
+        .iter()
+        .flat_map(|(element, trigger_type)| xml_blocks(definition, element).into_iter().map(|block| parse_trigger(trigger_type, block)))
+        .collect();
+    task.actions = parse_actions(definition);
+    if let Some(exec) = task.actions.iter().find(|action| action.action_type == "Execute") {
+        task.command = exec.path.clone();
+        task.arguments = exec.arguments.clone();
+        task.working_directory = exec.working_directory.clone();
     }
-    
-    triggers
 }
 
-fn parse_task_actions(xml_content: &str) -> Vec<TaskAction> {
-    let mut actions = Vec::new();
-    
-    if xml_content.contains("<Exec>") {
-        actions.push(TaskAction {
-            action_type: "Execute".to_string(),
-            path: extract_xml_value(xml_content, "Command").unwrap_or("Unknown".to_string()),
-            arguments: extract_xml_value(xml_content, "Arguments").unwrap_or(String::new()),
-            working_directory: extract_xml_value(xml_content, "WorkingDirectory").unwrap_or(String::new()),
-        });
+fn parse_trigger(trigger_type: &str, block: &str) -> TaskTrigger {
+    let repetition = xml_block(block, "Repetition").unwrap_or_default();
+    TaskTrigger {
+        trigger_type: trigger_type.to_string(),
+        start_boundary: xml_value(block, "StartBoundary").unwrap_or_default(),
+        end_boundary: xml_value(block, "EndBoundary").unwrap_or_default(),
+        enabled: xml_value(block, "Enabled").is_none_or(|enabled| enabled != "false"),
+        repetition_interval: xml_value(repetition, "Interval").unwrap_or_default(),
+        repetition_duration: xml_value(repetition, "Duration").unwrap_or_default(),
     }
-    
-    if actions.is_empty() {
-        actions.push(TaskAction {
-            action_type: "Unknown".to_string(),
-            path: "Unknown".to_string(),
-            arguments: String::new(),
-            working_directory: String::new(),
-        });
-    }
-    
+}
+
+fn parse_actions(definition: &str) -> Vec<TaskAction> {
+    let mut actions: Vec<TaskAction> = xml_blocks(definition, "Exec")
+        .into_iter()
+        .map(|exec| TaskAction {
+            action_type: "Execute".to_string(),
+            path: xml_value(exec, "Command").unwrap_or_default(),
+            arguments: xml_value(exec, "Arguments").unwrap_or_default(),
+            working_directory: xml_value(exec, "WorkingDirectory").unwrap_or_default(),
+        })
+        .collect();
+    actions.extend(xml_blocks(definition, "ComHandler").into_iter().map(|handler| TaskAction {
+        action_type: "ComHandler".to_string(),
+        path: xml_value(handler, "ClassId").unwrap_or_default(),
+        arguments: xml_value(handler, "Data").unwrap_or_default(),
+        working_directory: String::new(),
+    }));
     actions
 }
 
-fn collect_tasks_from_registry() -> Result<(Vec<ScheduledTask>, Vec<AuditEntry>), Box<dyn std::error::Error>> {
-    let mut scheduled_tasks = Vec::new();
-    let mut audit_log = Vec::new();
-    
-    // Registry locations for scheduled tasks
-    let task_registry_paths = vec![
-        "SOFTWARE\\Microsoft\\Windows NT\\CurrentVersion\\Schedule\\TaskCache\\Tree",
-        "SOFTWARE\\Microsoft\\Windows NT\\CurrentVersion\\Schedule\\TaskCache\\Tasks",
-    ];
-    
-    audit_log.push(AuditEntry {
-        timestamp: chrono::Utc::now().to_rfc3339(),
-        level: "DEBUG".to_string(),
-        component: "scheduled_tasks".to_string(),
-        action: "registry_scan".to_string(),
-        details: "Scanning registry for scheduled tasks".to_string(),
-        duration_ms: None,
-        result: "started".to_string(),
-    });
-    
-    // This is a simplified implementation
-    // Real implementation would parse the binary task data from registry
-    
-    Ok((scheduled_tasks, audit_log))
+/// Command line the task runs
+pub fn command_line(task: &ScheduledTask) -> String {
+    if task.arguments.is_empty() {
+        task.command.clone()
+    } else {
+        format!("{} {}", task.command, task.arguments)
+    }
 }
 
-// Helper functions
-fn parse_csv_line(line: &str) -> Vec<&str> {
-    // Simple CSV parsing - in production, use a proper CSV parser
-    line.split(',').map(|s| s.trim_matches('"').trim()).collect()
+/// Whether the task is enabled: ready or running
+pub fn is_active(task: &ScheduledTask) -> bool {
+    task.state.eq_ignore_ascii_case("Ready") || task.state.eq_ignore_ascii_case("Running")
 }
 
-fn find_column_index(columns: &[&str], column_name: &str) -> Option<usize> {
-    columns.iter().position(|&col| col.eq_ignore_ascii_case(column_name))
+/// Contents of every element of that name, empty for self-closing ones
+fn xml_blocks<'a>(xml: &'a str, element: &str) -> Vec<&'a str> {
+    let open = format!("<{}", element);
+    let close = format!("</{}>", element);
+    let mut blocks = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find(&open) {
+        let after = &rest[start + open.len()..];
+        // Skip longer names sharing the prefix, like <ExecutionTimeLimit> for <Exec>
+        if !after.starts_with(['>', '/', ' ', '\t', '\r', '\n']) {
+            rest = after;
+            continue;
+        }
+        let Some(tag_end) = after.find('>') else {
+            break;
+        };
+        if after[..tag_end].ends_with('/') {
+            blocks.push("");
+            rest = &after[tag_end + 1..];
+            continue;
+        }
+        let body = &after[tag_end + 1..];
+        let Some(end) = body.find(&close) else {
+            break;
+        };
+        blocks.push(&body[..end]);
+        rest = &body[end + close.len()..];
+    }
+    blocks
 }
 
-fn get_field(fields: &[&str], index: Option<usize>) -> Option<&str> {
-    index.and_then(|i| fields.get(i)).copied()
+fn xml_block<'a>(xml: &'a str, element: &str) -> Option<&'a str> {
+    xml_blocks(xml, element).into_iter().next()
 }
 
-fn extract_task_name(task_path: &str) -> String {
-    task_path.split('\\').last().unwrap_or(task_path).to_string()
+fn xml_value(xml: &str, element: &str) -> Option<String> {
+    xml_block(xml, element).map(|text| unescape_xml(text.trim())).filter(|text| !text.is_empty())
 }
 
-/// Get scheduled task statistics for reporting
-pub fn get_scheduled_task_statistics(tasks: &[ScheduledTask]) -> HashMap<String, u32> {
-    let mut stats = HashMap::new();
-    
-    stats.insert("total_tasks".to_string(), tasks.len() as u32);
-    
-    let enabled_count = tasks.iter()
-        .filter(|task| task.state.eq_ignore_ascii_case("Ready") || task.state.eq_ignore_ascii_case("Running"))
-        .count();
-    stats.insert("enabled_tasks".to_string(), enabled_count as u32);
-    
-    let disabled_count = tasks.len() - enabled_count;
-    stats.insert("disabled_tasks".to_string(), disabled_count as u32);
-    
-    // Count by trigger types
-    let mut logon_triggers = 0;
-    let mut boot_triggers = 0;
-    let mut time_triggers = 0;
-    
-    for task in tasks {
-        for trigger in &task.triggers {
-            match trigger.trigger_type.as_str() {
-                "Logon" => logon_triggers += 1,
-                "Boot" => boot_triggers += 1,
-                "Time" => time_triggers += 1,
-                _ => {}
+/// Split a CSV line, honouring quoted fields and doubled quotes
+fn parse_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut current_field = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                chars.next();
+                current_field.push('"');
+            }
+            '"' => {
+                in_quotes = !in_quotes;
+            }
+            ',' if !in_quotes => {
+                fields.push(current_field.trim().to_string());
+                current_field = String::new();
+            }
+            _ => {
+                current_field.push(ch);
             }
         }
     }
-    
-    stats.insert("logon_triggers".to_string(), logon_triggers);
-    stats.insert("boot_triggers".to_string(), boot_triggers);
-    stats.insert("time_triggers".to_string(), time_triggers);
-    
-    // Count by authors
-    let microsoft_tasks = tasks.iter()
-        .filter(|task| task.author.contains("Microsoft"))
-        .count();
-    stats.insert("microsoft_tasks".to_string(), microsoft_tasks as u32);
-    
-    let third_party_tasks = tasks.len() - microsoft_tasks;
-    stats.insert("third_party_tasks".to_string(), third_party_tasks as u32);
-    
-    stats
+
+    fields.push(current_field.trim().to_string());
+    fields
 }
 
-/// Find suspicious scheduled tasks
-pub fn find_suspicious_tasks(tasks: &[ScheduledTask]) -> Vec<&ScheduledTask> {
-    tasks.iter()
-        .filter(|task| is_suspicious_task(task))
-        .collect()
+fn find_column_index(columns: &[String], column_name: &str) -> Option<usize> {
+    columns.iter().position(|col| col.eq_ignore_ascii_case(column_name))
 }
 
-fn is_suspicious_task(task: &ScheduledTask) -> bool {
-    let suspicious_indicators = vec![
-        // Suspicious paths
-        task.command.contains("\\Temp\\"),
-        task.command.contains("\\AppData\\"),
-        task.command.contains("powershell"),
-        task.command.contains("cmd.exe"),
-        task.command.contains("wscript"),
-        task.command.contains("cscript"),
-        
-        // Suspicious names
-        task.name.len() == 1, // Single character names
-        task.name.contains("update") && !task.author.contains("Microsoft"),
-        task.name.contains("system") && !task.author.contains("Microsoft"),
-        
-        // Suspicious triggers
-        task.triggers.iter().any(|t| t.trigger_type == "Logon"),
-        task.triggers.iter().any(|t| t.trigger_type == "Boot"),
-        
-        // Suspicious users
-        task.run_as_user.contains("SYSTEM"),
-        task.run_as_user.contains("Administrator"),
-        
-        // Non-Microsoft tasks with system-level access
-        !task.author.contains("Microsoft") && task.run_as_user.contains("SYSTEM"),
-    ];
-    
-    suspicious_indicators.iter().any(|&indicator| indicator)
+fn get_field(fields: &[String], index: Option<usize>) -> Option<&str> {
+    index.and_then(|i| fields.get(i).map(|s| s.as_str()))
 }
 
-/// Get tasks that run at startup
-pub fn get_startup_tasks(tasks: &[ScheduledTask]) -> Vec<&ScheduledTask> {
-    tasks.iter()
-        .filter(|task| {
-            task.triggers.iter().any(|trigger| {
-                trigger.trigger_type == "Boot" || trigger.trigger_type == "Logon"
-            })
-        })
-        .collect()
+/// Last component of a task path
+pub fn extract_task_name(task_path: &str) -> String {
+    task_path.split('\\').next_back().unwrap_or(task_path).to_string()
 }
 
-/// Find tasks by command pattern
-pub fn find_tasks_by_command(tasks: &[ScheduledTask], pattern: &str) -> Vec<&ScheduledTask> {
-    tasks.iter()
-        .filter(|task| task.command.to_lowercase().contains(&pattern.to_lowercase()))
-        .collect()
-}
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TASK_LIST: &str = concat!(
+        "\"HostName\",\"TaskName\",\"Next Run Time\",\"Status\",\"Last Run Time\",\"Author\",\"Task To Run\",\"Start In\",\"Comment\",\"Run As User\",\"Last Result\"\r\n",
+        "\"WS01\",\"\\Adobe Acrobat Update Task\",\"10/17/2026 9:00:00 AM\",\"Ready\",\"10/16/2026 9:00:00 AM\",\"Adobe Systems Incorporated\",\"C:\\Program Files (x86)\\Common Files\\Adobe\\ARM\\1.0\\AdobeARM.exe\",\"N/A\",\"Keeps Adobe Reader up to date, weekly\",\"INTERACTIVE\",\"0\"\r\n",
+        "\"WS01\",\"\\Adobe Acrobat Update Task\",\"10/17/2026 9:00:00 AM\",\"Ready\",\"10/16/2026 9:00:00 AM\",\"Adobe Systems Incorporated\",\"C:\\Program Files (x86)\\Common Files\\Adobe\\ARM\\1.0\\AdobeARM.exe\",\"N/A\",\"Keeps Adobe Reader up to date, weekly\",\"INTERACTIVE\",\"0\"\r\n",
+        "\"HostName\",\"TaskName\",\"Next Run Time\",\"Status\",\"Last Run Time\",\"Author\",\"Task To Run\",\"Start In\",\"Comment\",\"Run As User\",\"Last Result\"\r\n",
+        "\"WS01\",\"\\WindowsUpdateCheck\",\"N/A\",\"Disabled\",\"Never\",\"N/A\",\"\"\"C:\\ProgramData\\u.exe\"\" -s\",\"N/A\",\"N/A\",\"SYSTEM\",\"267011\"\r\n",
+    );
+
+    const TASK_DEFINITIONS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Tasks>
+  <!-- \WindowsUpdateCheck -->
+  <Task version="1.2" xmlns="http://schemas.microsoft.com/windows/2004/02/mit/task">
+    <RegistrationInfo>
+      <Date>2026-10-01T03:12:44</Date>
+      <Author>WS01\admin</Author>
+    </RegistrationInfo>
+    <Triggers>
+      <LogonTrigger>
+        <Enabled>true</Enabled>
+      </LogonTrigger>
+      <CalendarTrigger>
+        <StartBoundary>2026-10-01T03:00:00</StartBoundary>
+        <Enabled>false</Enabled>
+        <Repetition><Interval>PT1H</Interval><Duration>P1D</Duration></Repetition>
+      </CalendarTrigger>
+    </Triggers>
+    <Principals>
+      <Principal id="Author">
+        <UserId>S-1-5-18</UserId>
+      </Principal>
+    </Principals>
+    <Settings>
+      <Hidden>true</Hidden>
+      <ExecutionTimeLimit>PT72H</ExecutionTimeLimit>
+    </Settings>
+    <Actions Context="Author">
+      <Exec>
+        <Command>powershell.exe</Command>
+        <Arguments>-w hidden -enc SQBFAFgA &amp; exit</Arguments>
+      </Exec>
+    </Actions>
+  </Task>
+</Tasks>"#;
+
+    #[test]
+    fn test_parse_task_list() {
+        let tasks = parse_task_list(TASK_LIST);
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(tasks[0].name, "Adobe Acrobat Update Task");
+        assert_eq!(tasks[0].path, "\\Adobe Acrobat Update Task");
+        assert_eq!(tasks[0].state, "Ready");
+        assert_eq!(tasks[0].description, "Keeps Adobe Reader up to date, weekly");
+        assert_eq!(tasks[0].run_as_user, "INTERACTIVE");
+        assert_eq!(tasks[1].command, "\"C:\\ProgramData\\u.exe\" -s");
+        assert_eq!(tasks[1].run_as_user, "SYSTEM");
+        assert_eq!(tasks[1].last_task_result, Some(267011));
+        assert!(is_active(&tasks[0]) && !is_active(&tasks[1]));
+
+        assert_eq!(parse_csv_line(r#""Task Name","Status","Run As User""#), ["Task Name", "Status", "Run As User"]);
+        assert_eq!(extract_task_name("\\Microsoft\\Windows\\UpdateOrchestrator\\Schedule Scan"), "Schedule Scan");
+        assert_eq!(extract_task_name("SimpleTask"), "SimpleTask");
+    }
+
+    #[test]
+    fn test_apply_task_definitions() {
+        let mut tasks = parse_task_list(TASK_LIST);
+        apply_task_definitions(&mut tasks, TASK_DEFINITIONS);
+
+        let task = &tasks[1];
+        assert_eq!(task.author, "WS01\\admin");
+        assert_eq!(task.creation_date, "2026-10-01T03:12:44");
+        assert_eq!(task.run_as_user, "SYSTEM");
+        assert_eq!(task.actions.len(), 1);
+        assert_eq!(command_line(task), "powershell.exe -w hidden -enc SQBFAFgA & exit");
+        let triggers: Vec<(&str, bool)> = task.triggers.iter().map(|t| (t.trigger_type.as_str(), t.enabled)).collect();
+        assert_eq!(triggers, [("Calendar", false), ("Logon", true)]);
+        assert_eq!(task.triggers[0].repetition_interval, "PT1H");
+        assert!(task.hidden);
+
+        // Tasks without a definition keep what the listing had
+        assert!(tasks[0].triggers.is_empty());
+        assert_eq!(tasks[0].author, "Adobe Systems Incorporated");
+    }
+
+    #[test]
+    fn test_ole_date_to_string() {
+        assert_eq!(ole_date_to_string(0.0), "Never");
+        assert_eq!(ole_date_to_string(36494.0), "Never"); // 1999-11-30
+        assert!(ole_date_to_string(46311.5).starts_with("2026-10-16T12:00:00"));
+    }
+
+    #[test]
+    fn test_score_scheduled_tasks() {
+        let model = suspicion::ScoringModel::default();
+        let mut tasks = parse_task_list(TASK_LIST);
+        model.score_scheduled_tasks(&mut tasks);
+        assert_eq!(tasks[0].suspicion_reasons, ["masquerading_name"]);
+        assert!(!model.is_suspicious(tasks[0].suspicion_score));
+        assert_eq!(tasks[1].suspicion_reasons, ["user_writable_directory", "masquerading_name", "non_microsoft_system_task"]);
+
+        apply_task_definitions(&mut tasks, TASK_DEFINITIONS);
+        model.score_scheduled_tasks(&mut tasks);
+        assert_eq!(tasks[1].suspicion_reasons, ["encoded_command", "hidden_window", "masquerading_name", "non_microsoft_system_task", "script_interpreter", "boot_or_logon_trigger"]);
+        assert!(model.is_suspicious(tasks[1].suspicion_score));
+    }
+}
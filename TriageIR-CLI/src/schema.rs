@@ -0,0 +1,338 @@
+//! Output schema and validation
+//! The JSON Schema (draft-07) of the scan results is embedded in the binary,
+//! published with `triageir-cli schema` and versioned: every result records
+//! the schema it was written against in `scan_metadata.schema_version`. A minor
+//! version adds fields, a major version changes or removes them, so parsers
+//! can accept any result of the major version they were written for.
+//! `triageir-cli validate` checks a results file against the embedded schema
+//! with the draft-07 keywords the schema uses; unknown keywords are ignored.
+
+use regex::Regex;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Version of the output schema, recorded in scan_metadata.schema_version
+pub const SCHEMA_VERSION: &str = "1.1.0";
+
+/// The schema of the scan results, as published in schemas/
+pub const OUTPUT_SCHEMA: &str = include_str!("../../schemas/triageir-output.schema.json");
+
+/// Errors reported before validation stops
+const MAX_ERRORS: usize = 200;
+
+/// A schema violation at a JSON pointer of the validated document
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaError {
+    pub path: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let path = if self.path.is_empty() { "/" } else { &self.path };
+        write!(f, "{}: {}", path, self.message)
+    }
+}
+
+/// The embedded output schema
+pub fn output_schema() -> Value {
+    serde_json::from_str(OUTPUT_SCHEMA).expect("embedded output schema is valid JSON")
+}
+
+/// Validate a scan result against the embedded schema, including that it was
+/// written with the same major schema version (results without one predate 1.1.0)
+pub fn validate_results(results: &Value) -> Vec<SchemaError> {
+    let mut errors = validate(&output_schema(), results);
+    if let Some(version) = results.pointer("/scan_metadata/schema_version").and_then(Value::as_str) {
+        if major(version) != major(SCHEMA_VERSION) {
+            errors.insert(0, SchemaError {
+                path: "/scan_metadata/schema_version".to_string(),
+                message: format!("schema version {} is not compatible with {}", version, SCHEMA_VERSION),
+            });
+        }
+    }
+    errors
+}
+
+/// Validate `instance` against `schema`; references resolve within `schema`
+pub fn validate(schema: &Value, instance: &Value) -> Vec<SchemaError> {
+    let mut validator = Validator { root: schema, patterns: HashMap::new(), errors: Vec::new() };
+    validator.check(schema, instance, "");
+    validator.errors
+}
+
+fn major(version: &str) -> &str {
+    version.split('.').next().unwrap_or_default()
+}
+
+struct Validator<'a> {
+    root: &'a Value,
+    patterns: HashMap<String, Option<Regex>>,
+    errors: Vec<SchemaError>,
+}
+
+impl<'a> Validator<'a> {
+    fn error(&mut self, path: &str, message: String) {
+        if self.errors.len() < MAX_ERRORS {
+            self.errors.push(SchemaError { path: path.to_string(), message });
+        }
+    }
+
+    /// Whether `instance` is valid, without recording its errors (oneOf, anyOf, not)
+    fn matches(&mut self, schema: &'a Value, instance: &Value) -> bool {
+        let errors = std::mem::take(&mut self.errors);
+        self.check(schema, instance, "");
+        let valid = self.errors.is_empty();
+        self.errors = errors;
+        valid
+    }
+
+    fn check(&mut self, schema: &'a Value, instance: &Value, path: &str) {
+        let Some(keywords) = schema.as_object() else { return };
+
+        if let Some(reference) = keywords.get("$ref").and_then(Value::as_str) {
+            // draft-07: $ref replaces the keywords next to it
+            match reference.strip_prefix('#').and_then(|pointer| self.root.pointer(pointer)) {
+                Some(target) => self.check(target, instance, path),
+                None => self.error(path, format!("unresolvable reference {}", reference)),
+            }
+            return;
+        }
+
+        if let Some(expected) = keywords.get("type") {
+            let names: Vec<&str> = match expected {
+                Value::String(name) => vec![name.as_str()],
+                Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+                _ => Vec::new(),
+            };
+            if !names.is_empty() && !names.iter().any(|name| has_type(instance, name)) {
+                self.error(path, format!("expected {}, found {}", names.join(" or "), type_name(instance)));
+                return;
+            }
+        }
+        if let Some(allowed) = keywords.get("enum").and_then(Value::as_array) {
+            if !allowed.contains(instance) {
+                self.error(path, format!("{} is not one of {}", instance, Value::Array(allowed.clone())));
+            }
+        }
+        if let Some(expected) = keywords.get("const") {
+            if expected != instance {
+                self.error(path, format!("expected {}, found {}", expected, instance));
+            }
+        }
+
+        match instance {
+            Value::Number(number) => self.check_number(keywords, number.as_f64().unwrap_or_default(), path),
+            Value::String(text) => self.check_string(keywords, text, path),
+            Value::Array(items) => self.check_array(keywords, items, path),
+            Value::Object(object) => self.check_object(keywords, object, path),
+            _ => {}
+        }
+
+        for sub_schema in keywords.get("allOf").and_then(Value::as_array).into_iter().flatten() {
+            self.check(sub_schema, instance, path);
+        }
+        if let Some(options) = keywords.get("anyOf").and_then(Value::as_array) {
+            if !options.iter().any(|option| self.matches(option, instance)) {
+                self.error(path, "does not match any of the anyOf schemas".to_string());
+            }
+        }
+        if let Some(options) = keywords.get("oneOf").and_then(Value::as_array) {
+            let matching = options.iter().filter(|option| self.matches(option, instance)).count();
+            if matching != 1 {
+                self.error(path, format!("matches {} of the oneOf schemas instead of exactly one", matching));
+            }
+        }
+        if let Some(negated) = keywords.get("not") {
+            if self.matches(negated, instance) {
+                self.error(path, "matches the schema it must not match".to_string());
+            }
+        }
+    }
+
+    fn check_number(&mut self, keywords: &serde_json::Map<String, Value>, number: f64, path: &str) {
+        if let Some(minimum) = keywords.get("minimum").and_then(Value::as_f64).filter(|&minimum| number < minimum) {
+            self.error(path, format!("{} is less than the minimum of {}", number, minimum));
+        }
+        if let Some(maximum) = keywords.get("maximum").and_then(Value::as_f64).filter(|&maximum| number > maximum) {
+            self.error(path, format!("{} is greater than the maximum of {}", number, maximum));
+        }
+        if let Some(minimum) = keywords.get("exclusiveMinimum").and_then(Value::as_f64).filter(|&minimum| number <= minimum) {
+            self.error(path, format!("{} is not greater than {}", number, minimum));
+        }
+        if let Some(maximum) = keywords.get("exclusiveMaximum").and_then(Value::as_f64).filter(|&maximum| number >= maximum) {
+            self.error(path, format!("{} is not less than {}", number, maximum));
+        }
+    }
+
+    fn check_string(&mut self, keywords: &serde_json::Map<String, Value>, text: &str, path: &str) {
+        let length = text.chars().count() as u64;
+        if let Some(minimum) = keywords.get("minLength").and_then(Value::as_u64).filter(|&minimum| length < minimum) {
+            self.error(path, format!("shorter than {} characters", minimum));
+        }
+        if let Some(maximum) = keywords.get("maxLength").and_then(Value::as_u64).filter(|&maximum| length > maximum) {
+            self.error(path, format!("longer than {} characters", maximum));
+        }
+        if let Some(pattern) = keywords.get("pattern").and_then(Value::as_str) {
+            let regex = self.patterns.entry(pattern.to_string()).or_insert_with(|| Regex::new(pattern).ok());
+            if regex.as_ref().is_some_and(|regex| !regex.is_match(text)) {
+                self.error(path, format!("{:?} does not match the pattern {}", text, pattern));
+            }
+        }
+        if let Some(format) = keywords.get("format").and_then(Value::as_str) {
+            if !valid_format(format, text) {
+                self.error(path, format!("{:?} is not a valid {}", text, format));
+            }
+        }
+    }
+
+    fn check_array(&mut self, keywords: &'a serde_json::Map<String, Value>, items: &[Value], path: &str) {
+        if let Some(minimum) = keywords.get("minItems").and_then(Value::as_u64).filter(|&minimum| (items.len() as u64) < minimum) {
+            self.error(path, format!("fewer than {} items", minimum));
+        }
+        if let Some(maximum) = keywords.get("maxItems").and_then(Value::as_u64).filter(|&maximum| items.len() as u64 > maximum) {
+            self.error(path, format!("more than {} items", maximum));
+        }
+        if keywords.get("uniqueItems").and_then(Value::as_bool) == Some(true) {
+            if let Some(duplicate) = items.iter().enumerate().find(|(index, item)| items[..*index].contains(item)) {
+                self.error(&format!("{}/{}", path, duplicate.0), "duplicate item".to_string());
+            }
+        }
+        match keywords.get("items") {
+            Some(Value::Array(tuple)) => {
+                for (index, (item_schema, item)) in tuple.iter().zip(items).enumerate() {
+                    self.check(item_schema, item, &format!("{}/{}", path, index));
+                }
+            }
+            Some(item_schema) => {
+                for (index, item) in items.iter().enumerate() {
+                    self.check(item_schema, item, &format!("{}/{}", path, index));
+                }
+            }
+            None => {}
+        }
+    }
+
+    fn check_object(&mut self, keywords: &'a serde_json::Map<String, Value>, object: &serde_json::Map<String, Value>, path: &str) {
+        for name in keywords.get("required").and_then(Value::as_array).into_iter().flatten().filter_map(Value::as_str) {
+            if !object.contains_key(name) {
+                self.error(path, format!("missing required property {}", name));
+            }
+        }
+        let properties = keywords.get("properties").and_then(Value::as_object);
+        for (name, value) in object {
+            let child = format!("{}/{}", path, name.replace('~', "~0").replace('/', "~1"));
+            match (properties.and_then(|properties| properties.get(name)), keywords.get("additionalProperties")) {
+                (Some(property_schema), _) => self.check(property_schema, value, &child),
+                (None, Some(Value::Bool(false))) => self.error(&child, "property not allowed by the schema".to_string()),
+                (None, Some(additional)) => self.check(additional, value, &child),
+                (None, None) => {}
+            }
+        }
+    }
+}
+
+fn has_type(instance: &Value, name: &str) -> bool {
+    match name {
+        "null" => instance.is_null(),
+        "boolean" => instance.is_boolean(),
+        "string" => instance.is_string(),
+        "array" => instance.is_array(),
+        "object" => instance.is_object(),
+        "number" => instance.is_number(),
+        "integer" => instance.is_i64() || instance.is_u64() || instance.as_f64().is_some_and(|number| number.fract() == 0.0),
+        _ => true,
+    }
+}
+
+fn type_name(instance: &Value) -> &'static str {
+    match instance {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// The formats the output schema uses; others are not checked
+fn valid_format(format: &str, text: &str) -> bool {
+    match format {
+        "date-time" => chrono::DateTime::parse_from_rfc3339(text).is_ok(),
+        "uuid" => uuid::Uuid::parse_str(text).is_ok(),
+        "ipv4" => text.parse::<std::net::Ipv4Addr>().is_ok(),
+        "ipv6" => text.parse::<std::net::Ipv6Addr>().is_ok(),
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{NetworkConnection, Process, ProcessModule, ScanResults};
+    use serde_json::json;
+
+    #[test]
+    fn test_validate_keywords() {
+        let schema = json!({
+            "type": "object",
+            "required": ["id", "items"],
+            "properties": {
+                "id": {"type": "string", "format": "uuid"},
+                "items": {"type": "array", "items": {"$ref": "#/definitions/Item"}}
+            },
+            "definitions": {
+                "Item": {
+                    "type": "object",
+                    "additionalProperties": false,
+                    "properties": {
+                        "port": {"type": "integer", "minimum": 0, "maximum": 65535},
+                        "state": {"enum": ["LISTEN", "ESTABLISHED"]},
+                        "name": {"type": ["string", "null"], "pattern": "^[a-z]+$"}
+                    }
+                }
+            }
+        });
+        let valid = json!({"id": "0f8fad5b-d9cb-469f-a165-70867728950e", "items": [{"port": 443, "state": "LISTEN", "name": null}]});
+        assert!(validate(&schema, &valid).is_empty());
+
+        let invalid = json!({"id": "not-a-uuid", "items": [{"port": 70000, "state": "CLOSED", "name": "A1", "extra": 1}]});
+        let errors: Vec<String> = validate(&schema, &invalid).iter().map(ToString::to_string).collect();
+        assert_eq!(errors, vec![
+            "/id: \"not-a-uuid\" is not a valid uuid",
+            "/items/0/extra: property not allowed by the schema",
+            "/items/0/name: \"A1\" does not match the pattern ^[a-z]+$",
+            "/items/0/port: 70000 is greater than the maximum of 65535",
+            "/items/0/state: \"CLOSED\" is not one of [\"LISTEN\",\"ESTABLISHED\"]",
+        ]);
+        assert_eq!(validate(&schema, &json!([]))[0].to_string(), "/: expected object, found array");
+    }
+
+    #[test]
+    fn test_typed_results_conform() {
+        let mut results = ScanResults::new("WKS01".to_string(), "Windows 11".to_string());
+        results.artifacts.system_info.hostname = "WKS01".to_string();
+        results.artifacts.system_info.os_version = "Windows 11".to_string();
+        let mut process = Process::new(4, 0, "System".to_string(), String::new(), String::new());
+        process.loaded_modules.push(ProcessModule::new("ntdll.dll".to_string(), "C:\\Windows\\System32\\ntdll.dll".to_string(), "0x7FF800000000".to_string(), 2_000_000, String::new()));
+        results.artifacts.running_processes.push(process);
+        results.artifacts.network_connections.push(NetworkConnection::new("TCP".to_string(), "10.0.0.5:49700".to_string(), "8.8.8.8:443".to_string(), "ESTABLISHED".to_string(), 4));
+
+        let json = serde_json::to_value(&results).unwrap();
+        assert_eq!(json.pointer("/artifacts/running_processes/0/loaded_modules/0/is_system_module"), Some(&json!(true)));
+        assert_eq!(json.pointer("/scan_metadata/schema_version"), Some(&json!(SCHEMA_VERSION)));
+        let errors = validate_results(&json);
+        assert!(errors.is_empty(), "{:?}", errors);
+    }
+
+    #[test]
+    fn test_output_schema_version() {
+        let schema = output_schema();
+        assert!(schema["$id"].as_str().unwrap().ends_with(&format!("v{}", SCHEMA_VERSION)));
+        assert!(schema.pointer("/definitions/ScanMetadata/properties/schema_version").is_some());
+
+        let results = json!({"scan_metadata": {"schema_version": "2.0.0"}});
+        assert_eq!(validate_results(&results)[0].message, format!("schema version 2.0.0 is not compatible with {}", SCHEMA_VERSION));
+    }
+}
@@ -0,0 +1,346 @@
+//! Quick-look incident scoping (`--scope-check`)
+//! Runs only the cheapest high-signal checks, in parallel, so a host can be
+//! answered yes/no in well under the 30 second budget: suspicious persistence,
+//! listening ports owned by executables in user-writable folders, services and
+//! scheduled tasks created in the last 7 days, and known indicators in the
+//! running processes. Nothing else is collected.
+
+use crate::event_logs::{self, EventChannelQuery, TASK_SCHEDULER_CHANNEL};
+use crate::forensic_types::{IocMatch, ScopeCheckReport, ScopeCheckStatus, ScopeFinding};
+use crate::ioc_match;
+use crate::network;
+use crate::path_norm;
+use crate::persistence;
+use crate::processes;
+use crate::time_window::TimeWindow;
+use crate::types::{EventLogEntry, LogEntry, NetworkConnection, Process};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+pub const TIME_BUDGET: Duration = Duration::from_secs(30);
+pub const LOOKBACK_DAYS: i64 = 7;
+
+/// Creation events looked up over the lookback period
+const CREATION_EVENTS: [(&str, u32); 3] = [
+    ("System", 7045),               // A service was installed
+    ("Security", 4698),             // A scheduled task was created
+    (TASK_SCHEDULER_CHANNEL, 106),  // Task registered
+];
+
+/// Executables are hashed only until this long after the start, and only when
+/// the indicator list has SHA-256 hashes
+const HASH_BUDGET: Duration = Duration::from_secs(20);
+
+/// Events kept per channel; a burst of creations is already a finding
+const MAX_CREATION_EVENTS: usize = 100;
+
+/// Folders standard users can write to; software listening from there is rare
+const USER_WRITABLE_FOLDERS: [&str; 5] = ["\\users\\", "\\programdata\\", "\\windows\\temp\\", "\\temp\\", "\\perflogs\\"];
+
+/// Read an indicator list: one hash, path, IP, domain or name per line,
+/// blank lines and `#` comments ignored
+pub fn load_indicators(path: &Path) -> Result<Vec<String>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("Failed to read indicator list {}: {}", path.display(), e))?;
+    Ok(parse_indicators(&text))
+}
+
+fn parse_indicators(text: &str) -> Vec<String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Run the scoping checks on the live system
+pub fn run_scope_check(indicators: &[String]) -> ScopeCheckReport {
+    let started_utc = chrono::Utc::now().to_rfc3339();
+    let start_time = Instant::now();
+    let hostname = std::env::var("COMPUTERNAME").unwrap_or_else(|_| "Unknown".to_string());
+
+    let mut collection_log = vec![LogEntry::info(&format!(
+        "Starting scope check ({} indicators, {} day lookback)",
+        indicators.len(),
+        LOOKBACK_DAYS
+    ))];
+
+    let (persistence, listeners, recent) = std::thread::scope(|scope| {
+        let persistence = scope.spawn(|| timed("persistence", check_persistence));
+        let listeners = scope.spawn(|| timed("listening_ports", check_listeners_and_iocs(indicators)));
+        let recent = scope.spawn(|| timed("recent_services_tasks", check_recent_creations));
+        (join(persistence, "persistence"), join(listeners, "listening_ports"), join(recent, "recent_services_tasks"))
+    });
+
+    let mut checks = Vec::new();
+    let mut findings = Vec::new();
+    for (status, check_findings, logs) in [persistence, recent] {
+        checks.push(status);
+        findings.extend(check_findings);
+        collection_log.extend(logs);
+    }
+
+    // Listeners and the IOC sweep share the process snapshot, so they report separately here
+    let (listener_status, (listener_findings, ioc_findings), logs) = listeners;
+    checks.push(ScopeCheckStatus { findings: listener_findings.len(), ..listener_status.clone() });
+    checks.push(ScopeCheckStatus {
+        check: "ioc_match".to_string(),
+        items_examined: indicators.len(),
+        findings: ioc_findings.len(),
+        ..listener_status
+    });
+    findings.extend(listener_findings);
+    findings.extend(ioc_findings);
+    collection_log.extend(logs);
+
+    let duration = start_time.elapsed();
+    let verdict = if findings.is_empty() { "clean" } else { "suspicious" };
+    collection_log.push(LogEntry::info(&format!(
+        "Scope check completed in {} ms: {} ({} findings)",
+        duration.as_millis(),
+        verdict,
+        findings.len()
+    )));
+
+    ScopeCheckReport {
+        hostname,
+        started_utc,
+        duration_ms: duration.as_millis() as u64,
+        within_budget: duration <= TIME_BUDGET,
+        verdict: verdict.to_string(),
+        lookback_days: LOOKBACK_DAYS,
+        indicator_count: indicators.len(),
+        checks,
+        findings,
+        collection_log,
+    }
+}
+
+type CheckOutput<T> = (ScopeCheckStatus, T, Vec<LogEntry>);
+
+/// Run a check and record its duration; a check whose log has errors is reported as failed
+fn timed<T>(check: &str, run: impl FnOnce() -> (T, usize, Vec<LogEntry>)) -> CheckOutput<T> {
+    let started = Instant::now();
+    let (findings, items_examined, logs) = run();
+    let failed = logs.iter().any(|log| log.level == "ERROR");
+    let status = ScopeCheckStatus {
+        check: check.to_string(),
+        status: if failed { "failed" } else { "completed" }.to_string(),
+        items_examined,
+        findings: 0,
+        duration_ms: started.elapsed().as_millis() as u64,
+    };
+    (status, findings, logs)
+}
+
+fn join<T: Default>(handle: std::thread::ScopedJoinHandle<'_, CheckOutput<T>>, check: &str) -> CheckOutput<T> {
+    handle.join().unwrap_or_else(|_| {
+        let status = ScopeCheckStatus {
+            check: check.to_string(),
+            status: "failed".to_string(),
+            items_examined: 0,
+            findings: 0,
+            duration_ms: 0,
+        };
+        (status, T::default(), vec![LogEntry::error(&format!("Scope check {} panicked", check))])
+    })
+}
+
+fn check_persistence() -> (Vec<ScopeFinding>, usize, Vec<LogEntry>) {
+    let (mechanisms, logs) = persistence::collect_persistence_mechanisms();
+    let findings = persistence::find_suspicious_mechanisms(&mechanisms)
+        .into_iter()
+        .map(|mechanism| ScopeFinding {
+            check: "persistence".to_string(),
+            description: format!("Suspicious {} persistence: {}", mechanism.mechanism_type, mechanism.name),
+            evidence: format!("{} ({})", mechanism.command, mechanism.source),
+        })
+        .collect();
+    (findings, mechanisms.len(), logs)
+}
+
+fn check_listeners_and_iocs(indicators: &[String]) -> impl FnOnce() -> ((Vec<ScopeFinding>, Vec<ScopeFinding>), usize, Vec<LogEntry>) + '_ {
+    let hash_deadline = indicators.iter().any(|i| is_sha256(i)).then(|| Instant::now() + HASH_BUDGET);
+    move || {
+        let (connections, mut logs) = network::collect_network_connections();
+        let (processes, process_logs) = processes::collect_process_snapshot(hash_deadline);
+        logs.extend(process_logs);
+
+        let listeners = connections.iter().filter(|c| is_listening(c)).count();
+        let listener_findings = user_writable_listeners(&connections, &processes);
+        let ioc_findings = ioc_findings(&processes, indicators);
+        ((listener_findings, ioc_findings), listeners, logs)
+    }
+}
+
+fn check_recent_creations() -> (Vec<ScopeFinding>, usize, Vec<LogEntry>) {
+    let window = TimeWindow {
+        since: Some(chrono::Utc::now() - chrono::Duration::days(LOOKBACK_DAYS)),
+        until: None,
+    };
+    let queries: Vec<EventChannelQuery> = CREATION_EVENTS
+        .iter()
+        .map(|(channel, event_id)| {
+            let mut query = EventChannelQuery::with_xpath(channel, &format!("*[System[(EventID={})]]", event_id));
+            query.max_events = MAX_CREATION_EVENTS;
+            query.restrict_to(&window);
+            query
+        })
+        .collect();
+
+    let (event_logs, logs) = event_logs::collect_event_logs_with_queries(&queries, None);
+    let events: Vec<&EventLogEntry> = event_logs
+        .system
+        .iter()
+        .chain(&event_logs.security)
+        .chain(event_logs.channels.values().flatten())
+        .collect();
+    let findings = events.iter().map(|event| creation_finding(event)).collect();
+    (findings, events.len(), logs)
+}
+
+fn is_sha256(indicator: &str) -> bool {
+    let indicator = indicator.trim();
+    indicator.len() == 64 && indicator.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+fn creation_finding(event: &EventLogEntry) -> ScopeFinding {
+    let what = match event.event_id {
+        7045 => "Service installed",
+        _ => "Scheduled task created",
+    };
+    ScopeFinding {
+        check: "recent_services_tasks".to_string(),
+        description: format!("{} at {} (event {} in {})", what, event.timestamp, event.event_id, event.source),
+        evidence: event.message.split_whitespace().collect::<Vec<_>>().join(" "),
+    }
+}
+
+fn is_listening(connection: &NetworkConnection) -> bool {
+    connection.state.starts_with("LISTEN")
+}
+
+/// Listening sockets whose owning process runs from a user-writable folder
+fn user_writable_listeners(connections: &[NetworkConnection], processes: &[Process]) -> Vec<ScopeFinding> {
+    let paths: HashMap<u32, &str> = processes.iter().map(|p| (p.pid, p.executable_path.as_str())).collect();
+    connections
+        .iter()
+        .filter(|c| is_listening(c))
+        .filter_map(|connection| {
+            let path = paths.get(&connection.owning_pid)?;
+            path_norm::in_directory(path, &USER_WRITABLE_FOLDERS).then(|| ScopeFinding {
+                check: "listening_ports".to_string(),
+                description: format!(
+                    "{} port {} listened on by {} (PID {}) from a user-writable folder",
+                    connection.protocol, connection.local_port, connection.process_name, connection.owning_pid
+                ),
+                evidence: path.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Known indicators in the names, paths, command lines and hashes of the running
+/// processes; one finding per indicator and process
+fn ioc_findings(processes: &[Process], indicators: &[String]) -> Vec<ScopeFinding> {
+    if indicators.is_empty() {
+        return Vec::new();
+    }
+    let snapshot = serde_json::json!({ "artifacts": { "running_processes": processes } });
+    let mut seen = HashSet::new();
+    ioc_match::match_indicators(&snapshot, indicators)
+        .into_iter()
+        .filter_map(|IocMatch { indicator, artifact_path, matched_value }| {
+            // /artifacts/running_processes/<index>/<field>
+            let index = artifact_path.split('/').nth(3).and_then(|index| index.parse::<usize>().ok());
+            if !seen.insert((indicator.clone(), index)) {
+                return None;
+            }
+            let description = match index.and_then(|index| processes.get(index)) {
+                Some(p) => format!("Indicator {} matched running process {} (PID {})", indicator, p.name, p.pid),
+                None => format!("Indicator {} matched a running process", indicator),
+            };
+            Some(ScopeFinding { check: "ioc_match".to_string(), description, evidence: matched_value })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn process(pid: u32, name: &str, path: &str) -> Process {
+        Process::new(pid, 1, name.to_string(), format!("\"{}\"", path), path.to_string())
+    }
+
+    fn listener(port: &str, pid: u32, name: &str) -> NetworkConnection {
+        NetworkConnection::new_with_ports_and_process(
+            "TCP".to_string(),
+            "0.0.0.0".to_string(),
+            port.parse().unwrap(),
+            "0.0.0.0".to_string(),
+            0,
+            "LISTEN".to_string(),
+            pid,
+            name.to_string(),
+        )
+    }
+
+    #[test]
+    fn test_parse_indicators() {
+        let text = "# campaign 42\n  beacon.exe \n\n10.0.0.5\n#ignored\nE3B0C44298FC1C149AFBF4C8996FB924\n";
+        assert_eq!(parse_indicators(text), vec!["beacon.exe", "10.0.0.5", "E3B0C44298FC1C149AFBF4C8996FB924"]);
+        assert!(parse_indicators("\n# only comments\n").is_empty());
+    }
+
+    #[test]
+    fn test_user_writable_listeners() {
+        let processes = vec![
+            process(4, "System", ""),
+            process(812, "svchost.exe", "C:\\Windows\\System32\\svchost.exe"),
+            process(4242, "agent.exe", "C:\\Users\\Public\\agent.exe"),
+        ];
+        let mut established = listener("50123", 4242, "agent.exe");
+        established.state = "ESTABLISHED".to_string();
+        let connections = vec![listener("445", 4, "System"), listener("135", 812, "svchost.exe"), listener("8443", 4242, "agent.exe"), established];
+
+        let findings = user_writable_listeners(&connections, &processes);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].check, "listening_ports");
+        assert!(findings[0].description.contains("port 8443"));
+        assert_eq!(findings[0].evidence, "C:\\Users\\Public\\agent.exe");
+    }
+
+    #[test]
+    fn test_ioc_findings_name_the_process() {
+        let processes = vec![
+            process(812, "svchost.exe", "C:\\Windows\\System32\\svchost.exe"),
+            process(4242, "beacon.exe", "C:\\ProgramData\\beacon.exe"),
+        ];
+        let findings = ioc_findings(&processes, &["beacon.exe".to_string()]);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].description, "Indicator beacon.exe matched running process beacon.exe (PID 4242)");
+        assert!(ioc_findings(&processes, &[]).is_empty());
+    }
+
+    #[test]
+    fn test_is_sha256() {
+        assert!(is_sha256("E3B0C44298FC1C149AFBF4C8996FB92427AE41E4649B934CA495991B7852B855"));
+        assert!(!is_sha256("d41d8cd98f00b204e9800998ecf8427e"));
+        assert!(!is_sha256("beacon.exe"));
+    }
+
+    #[test]
+    fn test_creation_finding() {
+        let event = EventLogEntry::new_with_source(
+            7045,
+            "Information".to_string(),
+            "2024-05-01T10:00:00Z".to_string(),
+            "A service was installed in the system.\nService Name: updsvc".to_string(),
+            "System".to_string(),
+        );
+        let finding = creation_finding(&event);
+        assert_eq!(finding.description, "Service installed at 2024-05-01T10:00:00Z (event 7045 in System)");
+        assert_eq!(finding.evidence, "A service was installed in the system. Service Name: updsvc");
+    }
+}
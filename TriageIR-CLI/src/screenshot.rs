@@ -0,0 +1,294 @@
+//! Screenshot capture (--screenshot)
+//! Captures every monitor of the interactive desktop the collector runs on as a
+//! PNG, documenting what was visibly on screen at collection time (ransom notes,
+//! open consoles). The images are stored in the evidence package and their
+//! hashes recorded in the scan results. A collector started as a service runs
+//! in session 0 and has no visible desktop; a locked workstation shows the
+//! secure desktop, which cannot be captured.
+
+// PNG encoding is only reachable through the Windows screen capture
+#![cfg_attr(not(windows), allow(dead_code))]
+
+use crate::forensic_types::{AuditEntry, Screenshot};
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+#[cfg(windows)]
+use sha2::{Digest, Sha256};
+use std::io::Write;
+
+const COMPONENT: &str = "screenshot";
+
+/// PNG file signature
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+
+/// Directory of the screenshots inside the evidence package
+pub const PACKAGE_DIR: &str = "screenshots";
+
+/// Screenshot metadata with the PNG image, for the evidence package
+pub struct CapturedScreen {
+    pub screenshot: Screenshot,
+    pub png: Vec<u8>,
+}
+
+/// Capture every monitor of the interactive desktop of the collector's session
+#[cfg(windows)]
+pub fn capture_screenshots() -> (Vec<CapturedScreen>, Vec<AuditEntry>) {
+    let start_time = std::time::Instant::now();
+    let mut audit_log = vec![AuditEntry::new(COMPONENT, "INFO", "start_capture", "Capturing the interactive desktop".to_string(), "started")];
+    let mut screens = Vec::new();
+
+    let session_id = gdi::current_session();
+    let console_session = session_id.is_some() && session_id == gdi::console_session();
+    if session_id == Some(0) {
+        audit_log.push(AuditEntry::new(
+            COMPONENT,
+            "WARN",
+            "capture_desktop",
+            "Collector runs in session 0 (service context); there is no interactive desktop to capture".to_string(),
+            "skipped",
+        ));
+        return (screens, audit_log);
+    }
+
+    let monitors = gdi::monitors();
+    if monitors.is_empty() {
+        audit_log.push(AuditEntry::new(COMPONENT, "WARN", "enumerate_monitors", "No monitors attached to the desktop".to_string(), "error"));
+    }
+
+    for (index, monitor) in monitors.iter().enumerate() {
+        let captured_at = chrono::Utc::now().to_rfc3339();
+        match gdi::capture(monitor) {
+            Ok(bgra) => {
+                let png = encode_png(monitor.width, monitor.height, &bgra);
+                let screenshot = Screenshot {
+                    file_name: format!("{}/screen_{}.png", PACKAGE_DIR, index + 1),
+                    monitor: monitor.device.clone(),
+                    primary: monitor.primary,
+                    left: monitor.left,
+                    top: monitor.top,
+                    width: monitor.width,
+                    height: monitor.height,
+                    session_id,
+                    console_session,
+                    captured_at,
+                    sha256: hex::encode(Sha256::digest(&png)),
+                    size_bytes: png.len() as u64,
+                };
+                audit_log.push(AuditEntry::new(
+                    COMPONENT,
+                    "INFO",
+                    "capture_monitor",
+                    format!("Captured {} ({}x{}) as {} (SHA-256 {})", screenshot.monitor, screenshot.width, screenshot.height, screenshot.file_name, screenshot.sha256),
+                    "success",
+                ));
+                screens.push(CapturedScreen { screenshot, png });
+            }
+            Err(e) => {
+                audit_log.push(AuditEntry::new(COMPONENT, "WARN", "capture_monitor", format!("Could not capture {}: {}", monitor.device, e), "error"));
+            }
+        }
+    }
+
+    audit_log.push(AuditEntry {
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        level: "INFO".to_string(),
+        component: "screenshot".to_string(),
+        action: "complete_capture".to_string(),
+        details: format!("Captured {} of {} monitors", screens.len(), monitors.len()),
+        duration_ms: Some(start_time.elapsed().as_millis() as u64),
+        result: "success".to_string(),
+    });
+
+    (screens, audit_log)
+}
+
+#[cfg(not(windows))]
+pub fn capture_screenshots() -> (Vec<CapturedScreen>, Vec<AuditEntry>) {
+    let audit_log = vec![
+        AuditEntry::new(COMPONENT, "INFO", "start_capture", "Capturing the interactive desktop".to_string(), "started"),
+        AuditEntry::new(COMPONENT, "WARN", "capture_desktop", "Screen capture requires Windows".to_string(), "unsupported"),
+        AuditEntry::new(COMPONENT, "INFO", "complete_capture", "Captured 0 of 0 monitors".to_string(), "success"),
+    ];
+    (Vec::new(), audit_log)
+}
+
+/// Encode a top-down 32-bit BGRA bitmap as an 8-bit RGB PNG; the GDI alpha byte is undefined
+fn encode_png(width: u32, height: u32, bgra: &[u8]) -> Vec<u8> {
+    let mut raw = Vec::with_capacity((width as usize * 3 + 1) * height as usize);
+    for row in bgra.chunks_exact(width as usize * 4).take(height as usize) {
+        raw.push(0); // Filter type None
+        for pixel in row.chunks_exact(4) {
+            raw.extend_from_slice(&[pixel[2], pixel[1], pixel[0]]);
+        }
+    }
+
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    let _ = encoder.write_all(&raw);
+    let image_data = encoder.finish().unwrap_or_default();
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    header.extend_from_slice(&[8, 2, 0, 0, 0]); // 8-bit truecolor, deflate, adaptive filtering, no interlace
+
+    let mut png = PNG_SIGNATURE.to_vec();
+    write_chunk(&mut png, b"IHDR", &header);
+    write_chunk(&mut png, b"IDAT", &image_data);
+    write_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+fn write_chunk(png: &mut Vec<u8>, chunk_type: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    png.extend_from_slice(chunk_type);
+    png.extend_from_slice(data);
+
+    let mut crc = crc32fast::Hasher::new();
+    crc.update(chunk_type);
+    crc.update(data);
+    png.extend_from_slice(&crc.finalize().to_be_bytes());
+}
+
+#[cfg(windows)]
+mod gdi {
+    use windows::Win32::Foundation::{BOOL, HWND, LPARAM, RECT};
+    use windows::Win32::Graphics::Gdi::{
+        BitBlt, CreateCompatibleBitmap, CreateCompatibleDC, DeleteDC, DeleteObject, EnumDisplayMonitors, GetDC,
+        GetDIBits, GetMonitorInfoW, ReleaseDC, SelectObject, BITMAPINFO, BITMAPINFOHEADER, BI_RGB, CAPTUREBLT,
+        DIB_RGB_COLORS, HDC, HMONITOR, MONITORINFOEXW, SRCCOPY,
+    };
+    use windows::Win32::System::RemoteDesktop::{ProcessIdToSessionId, WTSGetActiveConsoleSessionId};
+    use windows::Win32::System::Threading::GetCurrentProcessId;
+    use windows::Win32::UI::HiDpi::{SetProcessDpiAwarenessContext, DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2};
+
+    /// MONITORINFO dwFlags bit of the primary monitor
+    const MONITORINFOF_PRIMARY: u32 = 1;
+
+    pub struct Monitor {
+        pub device: String,
+        pub primary: bool,
+        pub left: i32,
+        pub top: i32,
+        pub width: u32,
+        pub height: u32,
+    }
+
+    pub fn current_session() -> Option<u32> {
+        let mut session_id = 0u32;
+        unsafe { ProcessIdToSessionId(GetCurrentProcessId(), &mut session_id) }.ok().map(|_| session_id)
+    }
+
+    /// Session attached to the physical console, None when no session is attached
+    pub fn console_session() -> Option<u32> {
+        Some(unsafe { WTSGetActiveConsoleSessionId() }).filter(|&id| id != u32::MAX)
+    }
+
+    /// Monitors of the desktop in physical pixels
+    pub fn monitors() -> Vec<Monitor> {
+        let mut monitors: Vec<Monitor> = Vec::new();
+        unsafe {
+            // Scaled displays otherwise report virtualized, DPI-adjusted bounds
+            let _ = SetProcessDpiAwarenessContext(DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2);
+            let _ = EnumDisplayMonitors(HDC::default(), None, Some(enum_monitor), LPARAM(&mut monitors as *mut Vec<Monitor> as isize));
+        }
+        monitors
+    }
+
+    unsafe extern "system" fn enum_monitor(monitor: HMONITOR, _hdc: HDC, _rect: *mut RECT, data: LPARAM) -> BOOL {
+        let monitors = &mut *(data.0 as *mut Vec<Monitor>);
+        let mut info = MONITORINFOEXW::default();
+        info.monitorInfo.cbSize = std::mem::size_of::<MONITORINFOEXW>() as u32;
+        if GetMonitorInfoW(monitor, &mut info.monitorInfo).as_bool() {
+            let len = info.szDevice.iter().position(|&c| c == 0).unwrap_or(info.szDevice.len());
+            let bounds = info.monitorInfo.rcMonitor;
+            monitors.push(Monitor {
+                device: String::from_utf16_lossy(&info.szDevice[..len]),
+                primary: info.monitorInfo.dwFlags & MONITORINFOF_PRIMARY != 0,
+                left: bounds.left,
+                top: bounds.top,
+                width: (bounds.right - bounds.left).max(0) as u32,
+                height: (bounds.bottom - bounds.top).max(0) as u32,
+            });
+        }
+        BOOL(1)
+    }
+
+    /// Copy the monitor's area of the screen as a top-down 32-bit BGRA bitmap
+    pub fn capture(monitor: &Monitor) -> Result<Vec<u8>, String> {
+        let (width, height) = (monitor.width as i32, monitor.height as i32);
+        if width == 0 || height == 0 {
+            return Err("monitor has an empty area".to_string());
+        }
+
+        unsafe {
+            let screen = GetDC(HWND::default());
+            if screen.is_invalid() {
+                return Err("GetDC failed for the desktop".to_string());
+            }
+            let memory = CreateCompatibleDC(screen);
+            let bitmap = CreateCompatibleBitmap(screen, width, height);
+            let previous = SelectObject(memory, bitmap);
+
+            // CAPTUREBLT includes layered windows (toasts, overlays)
+            let blit = BitBlt(memory, 0, 0, width, height, screen, monitor.left, monitor.top, SRCCOPY | CAPTUREBLT);
+
+            let mut pixels = vec![0u8; width as usize * height as usize * 4];
+            let mut info = BITMAPINFO {
+                bmiHeader: BITMAPINFOHEADER {
+                    biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+                    biWidth: width,
+                    biHeight: -height, // Top-down rows
+                    biPlanes: 1,
+                    biBitCount: 32,
+                    biCompression: BI_RGB.0,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            let lines = if blit.is_ok() {
+                GetDIBits(memory, bitmap, 0, height as u32, Some(pixels.as_mut_ptr() as *mut _), &mut info, DIB_RGB_COLORS)
+            } else {
+                0
+            };
+
+            SelectObject(memory, previous);
+            let _ = DeleteObject(bitmap);
+            let _ = DeleteDC(memory);
+            ReleaseDC(HWND::default(), screen);
+
+            blit.map_err(|e| format!("BitBlt failed (secure desktop or locked workstation?): {}", e))?;
+            if lines != height {
+                return Err(format!("GetDIBits returned {} of {} lines", lines, height));
+            }
+            Ok(pixels)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::ZlibDecoder;
+    use std::io::Read;
+
+    #[test]
+    fn test_encode_png() {
+        // 2x1 bitmap: a red and a blue pixel in BGRA
+        let png = encode_png(2, 1, &[0, 0, 255, 0, 255, 0, 0, 0]);
+        assert_eq!(&png[..8], &PNG_SIGNATURE);
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(&png[16..24], &[0, 0, 0, 2, 0, 0, 0, 1]);
+        assert_eq!(&png[png.len() - 8..png.len() - 4], b"IEND");
+
+        // The IHDR CRC covers the chunk type and data
+        let mut crc = crc32fast::Hasher::new();
+        crc.update(&png[12..29]);
+        assert_eq!(&png[29..33], &crc.finalize().to_be_bytes());
+
+        let idat_len = u32::from_be_bytes(png[33..37].try_into().unwrap()) as usize;
+        assert_eq!(&png[37..41], b"IDAT");
+        let mut raw = Vec::new();
+        ZlibDecoder::new(&png[41..41 + idat_len]).read_to_end(&mut raw).unwrap();
+        assert_eq!(raw, vec![0, 255, 0, 0, 0, 0, 255]);
+    }
+}
@@ -0,0 +1,370 @@
+//! Windows Search index and Explorer thumbnail cache inventory
+//! Windows.edb/Windows.db and the per-user thumbcache databases keep records of
+//! files that were indexed or previewed, often long after the files were deleted
+
+use crate::footprint;
+use crate::forensic_types::{CacheDatabaseFile, AuditEntry};
+use crate::offline::OfflineRoot;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use sha2::{Sha256, Digest};
+
+const COMPONENT: &str = "search_artifacts";
+
+const SEARCH_INDEX_DIRECTORY: &str = "C:\\ProgramData\\Microsoft\\Search\\Data\\Applications\\Windows";
+const USERS_DIRECTORY: &str = "C:\\Users";
+const EXPLORER_CACHE_SUBPATH: &str = "AppData\\Local\\Microsoft\\Windows\\Explorer";
+
+/// Inventory the Windows Search index databases, optionally copying them to `raw_copy_dir`
+pub fn collect_search_index_files(raw_copy_dir: Option<&Path>) -> (Vec<CacheDatabaseFile>, Vec<AuditEntry>) {
+    collect_search_index_from_directory(Path::new(SEARCH_INDEX_DIRECTORY), raw_copy_dir)
+}
+
+/// Inventory the Windows Search index databases of a mounted Windows volume
+pub fn collect_search_index_files_offline(root: &OfflineRoot, raw_copy_dir: Option<&Path>) -> (Vec<CacheDatabaseFile>, Vec<AuditEntry>) {
+    collect_search_index_from_directory(&root.resolve(SEARCH_INDEX_DIRECTORY), raw_copy_dir)
+}
+
+fn collect_search_index_from_directory(index_directory: &Path, raw_copy_dir: Option<&Path>) -> (Vec<CacheDatabaseFile>, Vec<AuditEntry>) {
+    let mut index_files = Vec::new();
+    let mut audit_log = Vec::new();
+
+    let start_time = std::time::Instant::now();
+
+    audit_log.push(AuditEntry::new(COMPONENT, "INFO", "start_collection", "Starting Windows Search index inventory".to_string(), "started"));
+
+    if !index_directory.exists() {
+        audit_log.push(AuditEntry::new(
+            COMPONENT,
+            "WARN",
+            "directory_check",
+            format!("Search index directory not found: {}", index_directory.display()),
+            "not_found",
+        ));
+    } else {
+        match footprint::read_dir(index_directory) {
+            Ok(entries) => {
+                for entry in entries.filter_map(|e| e.ok()) {
+                    let path = entry.path();
+                    if path.is_file() && is_search_index_file(&path) {
+                        let (record, logs) = inventory_file(&path, "search_index", None, raw_copy_dir);
+                        index_files.push(record);
+                        audit_log.extend(logs);
+                    }
+                }
+            }
+            Err(e) => {
+                audit_log.push(AuditEntry::new(
+                    COMPONENT,
+                    "ERROR",
+                    "scan_directory",
+                    format!("Failed to read {}: {}", index_directory.display(), e),
+                    "error",
+                ));
+            }
+        }
+    }
+
+    let duration = start_time.elapsed();
+    let mut complete = AuditEntry::new(
+        COMPONENT,
+        "INFO",
+        "complete_collection",
+        format!("Inventoried {} search index files", index_files.len()),
+        "success",
+    );
+    complete.duration_ms = Some(duration.as_millis() as u64);
+    audit_log.push(complete);
+
+    (index_files, audit_log)
+}
+
+/// Inventory per-user thumbcache and iconcache databases, optionally copying them to `raw_copy_dir`
+pub fn collect_thumbcache_files(raw_copy_dir: Option<&Path>) -> (Vec<CacheDatabaseFile>, Vec<AuditEntry>) {
+    collect_thumbcache_files_under(Path::new(USERS_DIRECTORY), raw_copy_dir)
+}
+
+/// Inventory the per-user thumbnail caches of a mounted Windows volume
+pub fn collect_thumbcache_files_offline(root: &OfflineRoot, raw_copy_dir: Option<&Path>) -> (Vec<CacheDatabaseFile>, Vec<AuditEntry>) {
+    collect_thumbcache_files_under(&root.resolve(USERS_DIRECTORY), raw_copy_dir)
+}
+
+fn collect_thumbcache_files_under(users_dir: &Path, raw_copy_dir: Option<&Path>) -> (Vec<CacheDatabaseFile>, Vec<AuditEntry>) {
+    let start_time = std::time::Instant::now();
+
+    let mut audit_log = vec![AuditEntry::new(COMPONENT, "INFO", "start_collection", "Starting thumbnail cache inventory".to_string(), "started")];
+
+    let (thumbcache_files, logs) = collect_thumbcache_from_users_dir(users_dir, raw_copy_dir);
+    audit_log.extend(logs);
+
+    let duration = start_time.elapsed();
+    let mut complete = AuditEntry::new(
+        COMPONENT,
+        "INFO",
+        "complete_collection",
+        format!("Inventoried {} thumbnail cache files", thumbcache_files.len()),
+        "success",
+    );
+    complete.duration_ms = Some(duration.as_millis() as u64);
+    audit_log.push(complete);
+
+    (thumbcache_files, audit_log)
+}
+
+fn collect_thumbcache_from_users_dir(users_dir: &Path, raw_copy_dir: Option<&Path>) -> (Vec<CacheDatabaseFile>, Vec<AuditEntry>) {
+    let mut thumbcache_files = Vec::new();
+    let mut audit_log = Vec::new();
+
+    let user_dirs = match footprint::read_dir(users_dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            audit_log.push(AuditEntry::new(
+                COMPONENT,
+                "WARN",
+                "directory_check",
+                format!("Users directory not readable: {} ({})", users_dir.display(), e),
+                "not_found",
+            ));
+            return (thumbcache_files, audit_log);
+        }
+    };
+
+    for user_dir in user_dirs.filter_map(|e| e.ok()) {
+        let username = user_dir.file_name().to_string_lossy().to_string();
+        let explorer_dir = user_dir.path().join(EXPLORER_CACHE_SUBPATH);
+
+        let entries = match footprint::read_dir(&explorer_dir) {
+            Ok(entries) => entries,
+            Err(_) => continue, // Not a profile directory or no Explorer cache
+        };
+
+        for entry in entries.filter_map(|e| e.ok()) {
+            let path = entry.path();
+            if let Some(artifact_type) = thumbcache_artifact_type(&path) {
+                let (record, logs) = inventory_file(&path, artifact_type, Some(&username), raw_copy_dir);
+                thumbcache_files.push(record);
+                audit_log.extend(logs);
+            }
+        }
+    }
+
+    (thumbcache_files, audit_log)
+}
+
+fn inventory_file(
+    path: &Path,
+    artifact_type: &str,
+    user: Option<&str>,
+    raw_copy_dir: Option<&Path>,
+) -> (CacheDatabaseFile, Vec<AuditEntry>) {
+    let mut audit_log = Vec::new();
+
+    let metadata = fs::metadata(path).ok();
+    let format_time = |time: std::io::Result<std::time::SystemTime>| {
+        time.map(|t| chrono::DateTime::<chrono::Utc>::from(t).to_rfc3339())
+            .unwrap_or_else(|_| "Unknown".to_string())
+    };
+
+    let (file_size, creation_time, last_modified, last_accessed) = match &metadata {
+        Some(m) => (m.len(), format_time(m.created()), format_time(m.modified()), format_time(m.accessed())),
+        None => (0, "Unknown".to_string(), "Unknown".to_string(), "Unknown".to_string()),
+    };
+
+    // Search databases are held open by the indexer, so hashing may legitimately fail
+    let hash = match calculate_file_hash(path) {
+        Ok(hash) => hash,
+        Err(e) => {
+            audit_log.push(AuditEntry::new(
+                COMPONENT,
+                "WARN",
+                "hash_file",
+                format!("Could not hash {}: {}", path.display(), e),
+                "locked",
+            ));
+            "ERROR".to_string()
+        }
+    };
+
+    let header_signature = read_header(path)
+        .map(|header| identify_header(&header).to_string())
+        .unwrap_or_else(|_| "unreadable".to_string());
+
+    let raw_copy_path = raw_copy_dir.and_then(|dir| {
+        let destination = raw_copy_destination(dir, artifact_type, user, path);
+        let copy_result = destination.parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| footprint::copy(path, &destination));
+        match copy_result {
+            Ok(bytes) => {
+                audit_log.push(AuditEntry::new(
+                    COMPONENT,
+                    "INFO",
+                    "raw_copy",
+                    format!("Copied {} to {} ({} bytes)", path.display(), destination.display(), bytes),
+                    "success",
+                ));
+                Some(destination.to_string_lossy().to_string())
+            }
+            Err(e) => {
+                audit_log.push(AuditEntry::new(
+                    COMPONENT,
+                    "WARN",
+                    "raw_copy",
+                    format!("Failed to copy {}: {}", path.display(), e),
+                    "error",
+                ));
+                None
+            }
+        }
+    });
+
+    audit_log.push(AuditEntry::new(
+        COMPONENT,
+        "DEBUG",
+        "inventory_file",
+        format!("Inventoried {}: {}", artifact_type, path.display()),
+        "success",
+    ));
+
+    let record = CacheDatabaseFile {
+        artifact_type: artifact_type.to_string(),
+        user: user.map(|u| u.to_string()),
+        path: path.to_string_lossy().to_string(),
+        file_size,
+        creation_time,
+        last_modified,
+        last_accessed,
+        hash,
+        header_signature,
+        raw_copy_path,
+    };
+
+    (record, audit_log)
+}
+
+/// Check whether a file in the search data directory is one of the index databases
+fn is_search_index_file(path: &Path) -> bool {
+    let filename = path.file_name()
+        .map(|f| f.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    // Windows 10 uses the ESE Windows.edb; Windows 11 moved to SQLite Windows*.db
+    filename.starts_with("windows") && (filename.ends_with(".edb") || filename.ends_with(".db"))
+}
+
+/// Classify an Explorer cache file by name
+fn thumbcache_artifact_type(path: &Path) -> Option<&'static str> {
+    let filename = path.file_name()?.to_string_lossy().to_lowercase();
+
+    if !filename.ends_with(".db") {
+        return None;
+    }
+
+    if filename.starts_with("thumbcache_") {
+        Some("thumbcache")
+    } else if filename.starts_with("iconcache_") {
+        Some("iconcache")
+    } else {
+        None
+    }
+}
+
+/// Build the destination path for a raw copy, grouped by artifact type and owner
+fn raw_copy_destination(raw_copy_dir: &Path, artifact_type: &str, user: Option<&str>, source: &Path) -> PathBuf {
+    let filename = source.file_name()
+        .map(|f| f.to_os_string())
+        .unwrap_or_else(|| "unknown".into());
+
+    raw_copy_dir
+        .join(artifact_type)
+        .join(user.unwrap_or("system"))
+        .join(filename)
+}
+
+fn read_header(path: &Path) -> std::io::Result<Vec<u8>> {
+    let mut file = footprint::open(path)?;
+    let mut header = vec![0u8; 16];
+    let bytes_read = file.read(&mut header)?;
+    header.truncate(bytes_read);
+    Ok(header)
+}
+
+/// Identify the database format from its leading bytes
+fn identify_header(header: &[u8]) -> &'static str {
+    if header.starts_with(b"CMMM") {
+        "CMMM"
+    } else if header.starts_with(b"SQLite format 3") {
+        "SQLite"
+    } else if header.len() >= 8 && header[4..8] == [0xEF, 0xCD, 0xAB, 0x89] {
+        "ESE"
+    } else {
+        "unknown"
+    }
+}
+
+fn calculate_file_hash(path: &Path) -> std::io::Result<String> {
+    let mut file = footprint::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = [0; 8192];
+
+    loop {
+        let bytes_read = file.read(&mut buffer)?;
+        if bytes_read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..bytes_read]);
+    }
+
+    Ok(hex::encode(hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_identify_header() {
+        assert_eq!(identify_header(b"CMMM\x20\x00\x00\x00"), "CMMM");
+        assert_eq!(identify_header(b"SQLite format 3\0"), "SQLite");
+        assert_eq!(identify_header(&[0x00, 0x00, 0x00, 0x00, 0xEF, 0xCD, 0xAB, 0x89]), "ESE");
+        assert_eq!(identify_header(b"MZ"), "unknown");
+    }
+
+    #[test]
+    fn test_thumbcache_artifact_type() {
+        assert_eq!(thumbcache_artifact_type(Path::new("thumbcache_256.db")), Some("thumbcache"));
+        assert_eq!(thumbcache_artifact_type(Path::new("IconCache_48.db")), Some("iconcache"));
+        assert_eq!(thumbcache_artifact_type(Path::new("thumbcache_idx.tmp")), None);
+        assert_eq!(thumbcache_artifact_type(Path::new("explorer.db")), None);
+    }
+
+    #[test]
+    fn test_is_search_index_file() {
+        assert!(is_search_index_file(Path::new("Windows.edb")));
+        assert!(is_search_index_file(Path::new("Windows-gather.db")));
+        assert!(!is_search_index_file(Path::new("MSS.log")));
+    }
+
+    #[test]
+    fn test_collect_thumbcache_with_raw_copy() {
+        let users = TempDir::new().unwrap();
+        let explorer = users.path().join("alice").join(EXPLORER_CACHE_SUBPATH);
+        fs::create_dir_all(&explorer).unwrap();
+        fs::write(explorer.join("thumbcache_96.db"), b"CMMM\x20\x00\x00\x00").unwrap();
+        fs::write(explorer.join("notes.txt"), b"ignored").unwrap();
+
+        let raw = TempDir::new().unwrap();
+        let (files, audit_log) = collect_thumbcache_from_users_dir(users.path(), Some(raw.path()));
+
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].user.as_deref(), Some("alice"));
+        assert_eq!(files[0].header_signature, "CMMM");
+        assert_eq!(files[0].hash.len(), 64);
+
+        let copy = files[0].raw_copy_path.as_ref().expect("file should be copied");
+        assert!(Path::new(copy).exists());
+        assert!(audit_log.iter().any(|log| log.action == "raw_copy" && log.result == "success"));
+    }
+}
@@ -0,0 +1,201 @@
+//! Search and command-run history
+//! WordWheelQuery holds the terms typed into the Explorer/Start search box and
+//! RunMRU the commands run from the Run dialog (Win+R), both per user, showing
+//! what the user (or an attacker at the keyboard) was looking for
+
+// Value decoding helpers are only reachable through the Windows registry API
+#![cfg_attr(not(windows), allow(dead_code))]
+
+use crate::forensic_types::{SearchHistoryEntry, AuditEntry};
+use crate::offline::OfflineRoot;
+
+#[cfg(windows)]
+use crate::binutil::utf16_string;
+#[cfg(windows)]
+use crate::footprint::TrackedKey;
+#[cfg(windows)]
+use crate::offline;
+#[cfg(windows)]
+use crate::user_hives::{self, UserHive};
+
+const COMPONENT: &str = "search_history";
+
+const WORD_WHEEL_QUERY_KEY: &str = "Software\\Microsoft\\Windows\\CurrentVersion\\Explorer\\WordWheelQuery";
+const RUN_MRU_KEY: &str = "Software\\Microsoft\\Windows\\CurrentVersion\\Explorer\\RunMRU";
+
+/// Collect search history for every user profile on the live system
+#[cfg(windows)]
+pub fn collect_search_history() -> (Vec<SearchHistoryEntry>, Vec<AuditEntry>) {
+    let (hives, failures) = user_hives::live_user_hives();
+    collect_from_user_hives(hives, failures)
+}
+
+#[cfg(not(windows))]
+pub fn collect_search_history() -> (Vec<SearchHistoryEntry>, Vec<AuditEntry>) {
+    registry_unavailable()
+}
+
+/// Collect search history from the NTUSER.DAT hives of a mounted Windows volume
+#[cfg(windows)]
+pub fn collect_search_history_offline(root: &OfflineRoot) -> (Vec<SearchHistoryEntry>, Vec<AuditEntry>) {
+    let (hives, failures) = user_hives::offline_user_hives(root);
+    collect_from_user_hives(hives, failures)
+}
+
+#[cfg(not(windows))]
+pub fn collect_search_history_offline(_root: &OfflineRoot) -> (Vec<SearchHistoryEntry>, Vec<AuditEntry>) {
+    registry_unavailable()
+}
+
+#[cfg(not(windows))]
+fn registry_unavailable() -> (Vec<SearchHistoryEntry>, Vec<AuditEntry>) {
+    let audit_log = vec![
+        AuditEntry::new(COMPONENT, "INFO", "start_collection", "Starting search history collection".to_string(), "started"),
+        AuditEntry::new(COMPONENT, "WARN", "load_user_hive", "User hives require the Windows registry".to_string(), "unsupported"),
+        AuditEntry::new(COMPONENT, "INFO", "complete_collection", "Collected 0 search history entries".to_string(), "success"),
+    ];
+    (Vec::new(), audit_log)
+}
+
+#[cfg(windows)]
+fn collect_from_user_hives(hives: Vec<UserHive>, failures: Vec<String>) -> (Vec<SearchHistoryEntry>, Vec<AuditEntry>) {
+    let mut entries = Vec::new();
+    let start_time = std::time::Instant::now();
+
+    let mut audit_log = vec![AuditEntry::new(
+        COMPONENT,
+        "INFO",
+        "start_collection",
+        format!("Starting search history collection for {} user hives", hives.len()),
+        "started",
+    )];
+
+    for failure in failures {
+        audit_log.push(AuditEntry::new(COMPONENT, "WARN", "load_user_hive", failure, "error"));
+    }
+
+    for hive in &hives {
+        let word_wheel = collect_word_wheel_query(hive);
+        let run_mru = collect_run_mru(hive);
+
+        audit_log.push(AuditEntry::new(
+            COMPONENT,
+            "DEBUG",
+            "parse_user_hive",
+            format!(
+                "{}: {} search terms, {} Run dialog commands",
+                hive.user,
+                word_wheel.len(),
+                run_mru.len()
+            ),
+            "success",
+        ));
+
+        entries.extend(word_wheel);
+        entries.extend(run_mru);
+    }
+
+    let duration = start_time.elapsed();
+    let mut complete = AuditEntry::new(
+        COMPONENT,
+        "INFO",
+        "complete_collection",
+        format!("Collected {} search history entries", entries.len()),
+        "success",
+    );
+    complete.duration_ms = Some(duration.as_millis() as u64);
+    audit_log.push(complete);
+
+    (entries, audit_log)
+}
+
+/// Search terms are stored as numbered REG_BINARY UTF-16 values ordered by MRUListEx
+#[cfg(windows)]
+fn collect_word_wheel_query(hive: &UserHive) -> Vec<SearchHistoryEntry> {
+    let key = match hive.key.open_tracked(WORD_WHEEL_QUERY_KEY) {
+        Ok(key) => key,
+        Err(_) => return Vec::new(),
+    };
+
+    let order = key
+        .get_raw_value("MRUListEx")
+        .map(|value| parse_mru_list_ex(&value.bytes))
+        .unwrap_or_default();
+    let last_write = offline::key_last_write_time(&key);
+
+    order
+        .iter()
+        .enumerate()
+        .filter_map(|(position, index)| {
+            let value = key.get_raw_value(index.to_string()).ok()?;
+            Some(SearchHistoryEntry {
+                user: hive.user.clone(),
+                source: "WordWheelQuery".to_string(),
+                mru_position: position as u32,
+                value: utf16_string(&value.bytes),
+                key_last_write: last_write.clone(),
+                registry_path: format!("{}\\{}", hive.source, WORD_WHEEL_QUERY_KEY),
+            })
+        })
+        .collect()
+}
+
+/// Run dialog commands are lettered REG_SZ values ordered by the MRUList string
+#[cfg(windows)]
+fn collect_run_mru(hive: &UserHive) -> Vec<SearchHistoryEntry> {
+    let key = match hive.key.open_tracked(RUN_MRU_KEY) {
+        Ok(key) => key,
+        Err(_) => return Vec::new(),
+    };
+
+    let order = key.get_value::<String, _>("MRUList").unwrap_or_default();
+    let last_write = offline::key_last_write_time(&key);
+
+    order
+        .chars()
+        .enumerate()
+        .filter_map(|(position, letter)| {
+            let command = key.get_value::<String, _>(letter.to_string()).ok()?;
+            Some(SearchHistoryEntry {
+                user: hive.user.clone(),
+                source: "RunMRU".to_string(),
+                mru_position: position as u32,
+                value: strip_run_mru_suffix(&command),
+                key_last_write: last_write.clone(),
+                registry_path: format!("{}\\{}", hive.source, RUN_MRU_KEY),
+            })
+        })
+        .collect()
+}
+
+/// MRUListEx: little-endian u32 value indices, most recent first, terminated by 0xFFFFFFFF
+pub(crate) fn parse_mru_list_ex(data: &[u8]) -> Vec<u32> {
+    data.chunks_exact(4)
+        .map(|chunk| u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+        .take_while(|&index| index != u32::MAX)
+        .collect()
+}
+
+/// RunMRU commands carry a trailing "\1" marker
+pub(crate) fn strip_run_mru_suffix(command: &str) -> String {
+    command.strip_suffix("\\1").unwrap_or(command).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mru_list_ex() {
+        let data = [2u8, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF];
+        assert_eq!(parse_mru_list_ex(&data), vec![2, 0, 1]);
+        assert!(parse_mru_list_ex(&[0xFF, 0xFF, 0xFF, 0xFF]).is_empty());
+        assert!(parse_mru_list_ex(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_strip_run_mru_suffix() {
+        assert_eq!(strip_run_mru_suffix("powershell -ep bypass\\1"), "powershell -ep bypass");
+        assert_eq!(strip_run_mru_suffix("cmd"), "cmd");
+    }
+}
@@ -0,0 +1,298 @@
+//! Security policy and configuration audit
+//! Reads the machine settings an intruder weakens to move laterally, harvest
+//! credentials or run tooling unnoticed: UAC, LSA protection, WDigest
+//! credential caching, RDP Network Level Authentication, SMBv1, the PowerShell
+//! execution policy and logging policies, and Microsoft Defender switches and
+//! exclusions. Each setting is reported with its raw value and a `weakened`
+//! flag; settings that are simply left at the Windows default are not flagged.
+
+use crate::forensic_types::{AuditEntry, SecuritySetting};
+use crate::offline::{MachineRegistry, OfflineRegistry, OfflineRoot};
+
+#[cfg(windows)]
+use crate::offline::LiveRegistry;
+#[cfg(windows)]
+use winreg::enums::HKEY_LOCAL_MACHINE;
+#[cfg(windows)]
+use winreg::RegKey;
+
+const COMPONENT: &str = "security_configuration";
+
+const UAC_KEY: &str = "SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Policies\\System";
+const LSA_KEY: &str = "SYSTEM\\CurrentControlSet\\Control\\Lsa";
+const WDIGEST_KEY: &str = "SYSTEM\\CurrentControlSet\\Control\\SecurityProviders\\WDigest";
+const TERMINAL_SERVER_KEY: &str = "SYSTEM\\CurrentControlSet\\Control\\Terminal Server";
+const RDP_TCP_KEY: &str = "SYSTEM\\CurrentControlSet\\Control\\Terminal Server\\WinStations\\RDP-Tcp";
+const SMB_SERVER_KEY: &str = "SYSTEM\\CurrentControlSet\\Services\\LanmanServer\\Parameters";
+/// SMBv1 server and client drivers; absent when the SMB1 feature is removed
+const SMB1_SERVER_DRIVER_KEY: &str = "SYSTEM\\CurrentControlSet\\Services\\srv";
+const SMB1_CLIENT_DRIVER_KEY: &str = "SYSTEM\\CurrentControlSet\\Services\\mrxsmb10";
+const POWERSHELL_POLICY_KEY: &str = "SOFTWARE\\Policies\\Microsoft\\Windows\\PowerShell";
+const POWERSHELL_SHELL_KEY: &str = "SOFTWARE\\Microsoft\\PowerShell\\1\\ShellIds\\Microsoft.PowerShell";
+const DEFENDER_KEY: &str = "SOFTWARE\\Microsoft\\Windows Defender";
+const DEFENDER_POLICY_KEY: &str = "SOFTWARE\\Policies\\Microsoft\\Windows Defender";
+
+/// PowerShell logging policies: (subkey, value enabling it)
+const POWERSHELL_LOGGING: [(&str, &str); 3] = [
+    ("ScriptBlockLogging", "EnableScriptBlockLogging"),
+    ("ModuleLogging", "EnableModuleLogging"),
+    ("Transcription", "EnableTranscripting"),
+];
+/// Defender exclusion lists, each value name of which is one exclusion
+const DEFENDER_EXCLUSION_TYPES: [&str; 4] = ["Paths", "Extensions", "Processes", "IpAddresses"];
+/// Start value of a disabled service or driver
+const SERVICE_DISABLED: u32 = 4;
+
+/// Audit the security configuration of the running system
+#[cfg(windows)]
+pub fn collect_security_configuration() -> (Vec<SecuritySetting>, Vec<AuditEntry>) {
+    let start_time = std::time::Instant::now();
+    let mut audit_log = vec![AuditEntry::new(COMPONENT, "INFO", "start_collection", "Starting security configuration audit".to_string(), "started")];
+    let settings = audit_settings(&LiveRegistry(RegKey::predef(HKEY_LOCAL_MACHINE)), &mut audit_log);
+    audit_log.push(completed(&settings, start_time));
+    (settings, audit_log)
+}
+
+#[cfg(not(windows))]
+pub fn collect_security_configuration() -> (Vec<SecuritySetting>, Vec<AuditEntry>) {
+    let audit_log = vec![
+        AuditEntry::new(COMPONENT, "INFO", "start_collection", "Starting security configuration audit".to_string(), "started"),
+        AuditEntry::new(COMPONENT, "WARN", "registry_access", "Security configuration audit requires the Windows registry".to_string(), "unsupported"),
+        AuditEntry::new(COMPONENT, "INFO", "complete_collection", "Audited 0 security settings".to_string(), "success"),
+    ];
+    (Vec::new(), audit_log)
+}
+
+/// Audit the security configuration in the SOFTWARE and SYSTEM hives of a mounted Windows volume
+pub fn collect_security_configuration_offline(root: &OfflineRoot) -> (Vec<SecuritySetting>, Vec<AuditEntry>) {
+    let start_time = std::time::Instant::now();
+    let mut audit_log = vec![AuditEntry::new(
+        COMPONENT,
+        "INFO",
+        "start_collection",
+        format!("Starting offline security configuration audit of {}", root.root().display()),
+        "started",
+    )];
+    let mut open = |name: &str| match root.open_hive(name) {
+        Ok(hive) => Some(hive),
+        Err(e) => {
+            audit_log.push(AuditEntry::new(COMPONENT, "ERROR", "load_hive", format!("Failed to load offline {} hive: {}", name, e), "error"));
+            None
+        }
+    };
+    let (software, system) = (open("SOFTWARE"), open("SYSTEM"));
+    if software.is_none() && system.is_none() {
+        return (Vec::new(), audit_log);
+    }
+
+    let registry = OfflineRegistry::new(software, system);
+    let settings = audit_settings(&registry, &mut audit_log);
+    audit_log.push(completed(&settings, start_time));
+    (settings, audit_log)
+}
+
+fn completed(settings: &[SecuritySetting], start_time: std::time::Instant) -> AuditEntry {
+    let mut entry = AuditEntry::new(
+        COMPONENT,
+        "INFO",
+        "complete_collection",
+        format!("Audited {} security settings ({} weakened)", settings.len(), weakened_count(settings)),
+        "success",
+    );
+    entry.duration_ms = Some(start_time.elapsed().as_millis() as u64);
+    entry
+}
+
+pub fn weakened_count(settings: &[SecuritySetting]) -> usize {
+    settings.iter().filter(|setting| setting.weakened).count()
+}
+
+fn audit_settings(registry: &dyn MachineRegistry, audit_log: &mut Vec<AuditEntry>) -> Vec<SecuritySetting> {
+    let mut settings = Vec::new();
+    let dword = |key: &str, name: &str| registry.dword(key, name);
+
+    // User Account Control
+    let (state, weakened) = match dword(UAC_KEY, "EnableLUA") {
+        Some(0) => ("disabled", true),
+        Some(_) => ("enabled", false),
+        None => ("enabled (default)", false),
+    };
+    settings.push(setting("uac", "UAC", UAC_KEY, "EnableLUA", dword(UAC_KEY, "EnableLUA"), state, weakened));
+    let (state, weakened) = match dword(UAC_KEY, "ConsentPromptBehaviorAdmin") {
+        Some(0) => ("elevate without prompting", true),
+        Some(1) | Some(3) => ("prompt for credentials", false),
+        Some(2) => ("prompt for consent on the secure desktop", false),
+        Some(4) => ("prompt for consent", false),
+        Some(_) => ("prompt for consent for non-Windows binaries", false),
+        None => ("prompt for consent for non-Windows binaries (default)", false),
+    };
+    settings.push(setting("uac", "Admin elevation prompt", UAC_KEY, "ConsentPromptBehaviorAdmin", dword(UAC_KEY, "ConsentPromptBehaviorAdmin"), state, weakened));
+    let (state, weakened) = match dword(UAC_KEY, "LocalAccountTokenFilterPolicy") {
+        Some(1) => ("remote logons of local administrators get full tokens", true),
+        _ => ("remote logons of local administrators are filtered", false),
+    };
+    settings.push(setting("uac", "Remote UAC", UAC_KEY, "LocalAccountTokenFilterPolicy", dword(UAC_KEY, "LocalAccountTokenFilterPolicy"), state, weakened));
+
+    // LSA protection and WDigest credential caching
+    let (state, weakened) = match dword(LSA_KEY, "RunAsPPL") {
+        Some(0) => ("disabled", true),
+        Some(_) => ("enabled", false),
+        None => ("not configured", false),
+    };
+    settings.push(setting("lsa", "LSA protection", LSA_KEY, "RunAsPPL", dword(LSA_KEY, "RunAsPPL"), state, weakened));
+    let (state, weakened) = match dword(WDIGEST_KEY, "UseLogonCredential") {
+        Some(1) => ("cleartext passwords cached in LSASS", true),
+        Some(_) => ("cleartext passwords not cached", false),
+        None => ("cleartext passwords not cached (default since Windows 8.1)", false),
+    };
+    settings.push(setting("wdigest", "WDigest credential caching", WDIGEST_KEY, "UseLogonCredential", dword(WDIGEST_KEY, "UseLogonCredential"), state, weakened));
+
+    // Remote Desktop; NLA only matters when RDP is enabled
+    let rdp_enabled = dword(TERMINAL_SERVER_KEY, "fDenyTSConnections") == Some(0);
+    let state = if rdp_enabled { "enabled" } else { "disabled" };
+    settings.push(setting("rdp", "Remote Desktop", TERMINAL_SERVER_KEY, "fDenyTSConnections", dword(TERMINAL_SERVER_KEY, "fDenyTSConnections"), state, false));
+    let (state, weakened) = match dword(RDP_TCP_KEY, "UserAuthentication") {
+        Some(0) => ("not required", rdp_enabled),
+        Some(_) => ("required", false),
+        None => ("required (default)", false),
+    };
+    settings.push(setting("rdp", "Network Level Authentication", RDP_TCP_KEY, "UserAuthentication", dword(RDP_TCP_KEY, "UserAuthentication"), state, weakened));
+
+    // SMBv1 server and client
+    let server_driver = dword(SMB1_SERVER_DRIVER_KEY, "Start");
+    let (state, weakened) = match (dword(SMB_SERVER_KEY, "SMB1"), server_driver) {
+        (Some(0), _) => ("disabled", false),
+        (Some(_), _) => ("enabled", true),
+        (None, Some(start)) if start != SERVICE_DISABLED => ("enabled (SMB1 feature installed)", true),
+        (None, Some(_)) => ("disabled", false),
+        (None, None) => ("not installed", false),
+    };
+    settings.push(setting("smb", "SMBv1 server", SMB_SERVER_KEY, "SMB1", dword(SMB_SERVER_KEY, "SMB1"), state, weakened));
+    let (state, weakened) = match dword(SMB1_CLIENT_DRIVER_KEY, "Start") {
+        Some(SERVICE_DISABLED) => ("disabled", false),
+        Some(_) => ("enabled", true),
+        None => ("not installed", false),
+    };
+    settings.push(setting("smb", "SMBv1 client", SMB1_CLIENT_DRIVER_KEY, "Start", dword(SMB1_CLIENT_DRIVER_KEY, "Start"), state, weakened));
+
+    // PowerShell execution policy: a Group Policy setting wins over the local one
+    let policy = match dword(POWERSHELL_POLICY_KEY, "EnableScripts") {
+        Some(1) => registry.string(POWERSHELL_POLICY_KEY, "ExecutionPolicy").map(|policy| (POWERSHELL_POLICY_KEY, policy)),
+        _ => None,
+    };
+    let (key, value) = match policy {
+        Some((key, policy)) => (key, Some(policy)),
+        None => (POWERSHELL_SHELL_KEY, registry.string(POWERSHELL_SHELL_KEY, "ExecutionPolicy")),
+    };
+    let weakened = value.as_deref().is_some_and(|policy| ["bypass", "unrestricted"].contains(&policy.to_lowercase().as_str()));
+    let state = value.clone().unwrap_or_else(|| "Restricted (default on clients)".to_string());
+    settings.push(SecuritySetting { value, ..setting("powershell", "Execution policy", key, "ExecutionPolicy", None, &state, weakened) });
+    for (subkey, value_name) in POWERSHELL_LOGGING {
+        let key = format!("{}\\{}", POWERSHELL_POLICY_KEY, subkey);
+        let (state, weakened) = match dword(&key, value_name) {
+            Some(0) => ("disabled by policy", true),
+            Some(_) => ("enabled", false),
+            None => ("not configured", false),
+        };
+        settings.push(setting("powershell", subkey, &key, value_name, dword(&key, value_name), state, weakened));
+    }
+
+    // Microsoft Defender switches, local and by policy
+    for base in [DEFENDER_POLICY_KEY, DEFENDER_KEY] {
+        let key = format!("{}\\Real-Time Protection", base);
+        for (key, value_name, name) in [(base.to_string(), "DisableAntiSpyware", "Defender antivirus"), (key, "DisableRealtimeMonitoring", "Real-time protection")] {
+            if let Some(value) = dword(&key, value_name) {
+                let (state, weakened) = if value == 1 { ("disabled", true) } else { ("enabled", false) };
+                settings.push(setting("defender", name, &key, value_name, Some(value), state, weakened));
+            }
+        }
+    }
+
+    // Every Defender exclusion is reported: ransomware operators add them before deploying
+    for base in [DEFENDER_KEY, DEFENDER_POLICY_KEY] {
+        for exclusion_type in DEFENDER_EXCLUSION_TYPES {
+            let key = format!("{}\\Exclusions\\{}", base, exclusion_type);
+            match registry.values(&key) {
+                Ok(exclusions) => settings.extend(exclusions.into_iter().map(|exclusion| SecuritySetting {
+                    value: Some(exclusion.name.clone()),
+                    ..setting("defender", &format!("{} exclusion", exclusion_type), &key, &exclusion.name, None, "excluded from scanning", true)
+                })),
+                Err(e) => audit_log.push(AuditEntry::new(COMPONENT, "WARN", "registry_access", format!("Failed to read HKLM\\{}: {}", key, e), "error")),
+            }
+        }
+    }
+
+    settings
+}
+
+fn setting(category: &str, name: &str, key: &str, value_name: &str, value: Option<u32>, state: &str, weakened: bool) -> SecuritySetting {
+    SecuritySetting {
+        category: category.to_string(),
+        name: name.to_string(),
+        registry_key: format!("HKLM\\{}", key),
+        value_name: value_name.to_string(),
+        value: value.map(|value| value.to_string()),
+        state: state.to_string(),
+        weakened,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hive::HiveBuilder;
+
+    fn find<'a>(settings: &'a [SecuritySetting], name: &str) -> &'a SecuritySetting {
+        settings.iter().find(|setting| setting.name == name).unwrap()
+    }
+
+    #[test]
+    fn test_offline_security_configuration() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config = temp_dir.path().join("Windows").join("System32").join("config");
+        std::fs::create_dir_all(&config).unwrap();
+        let root = OfflineRoot::new(temp_dir.path()).unwrap();
+        let (settings, audit_log) = collect_security_configuration_offline(&root);
+        assert!(settings.is_empty());
+        assert_eq!(audit_log.iter().filter(|log| log.action == "load_hive").count(), 2);
+
+        let system = HiveBuilder::default()
+            .dword("Select", "Current", 1)
+            .dword("ControlSet001\\Control\\SecurityProviders\\WDigest", "UseLogonCredential", 1)
+            .dword("ControlSet001\\Control\\Terminal Server", "fDenyTSConnections", 0)
+            .dword("ControlSet001\\Control\\Terminal Server\\WinStations\\RDP-Tcp", "UserAuthentication", 0)
+            .dword("ControlSet001\\Services\\mrxsmb10", "Start", 4)
+            .dword("ControlSet001\\Services\\srv", "Start", 3)
+            .build();
+        let software = HiveBuilder::default()
+            .dword("Microsoft\\Windows\\CurrentVersion\\Policies\\System", "EnableLUA", 1)
+            .dword("Microsoft\\Windows\\CurrentVersion\\Policies\\System", "LocalAccountTokenFilterPolicy", 1)
+            .string("Microsoft\\PowerShell\\1\\ShellIds\\Microsoft.PowerShell", "ExecutionPolicy", "Bypass")
+            .dword("Policies\\Microsoft\\Windows\\PowerShell\\ScriptBlockLogging", "EnableScriptBlockLogging", 0)
+            .dword("Microsoft\\Windows Defender\\Real-Time Protection", "DisableRealtimeMonitoring", 1)
+            .dword("Microsoft\\Windows Defender\\Exclusions\\Paths", "C:\\ProgramData\\staging", 0)
+            .dword("Microsoft\\Windows Defender\\Exclusions\\Extensions", ".locked", 0)
+            .build();
+        std::fs::write(config.join("SYSTEM"), system).unwrap();
+        std::fs::write(config.join("SOFTWARE"), software).unwrap();
+
+        let (settings, audit_log) = collect_security_configuration_offline(&root);
+        assert!(!find(&settings, "UAC").weakened);
+        assert!(find(&settings, "Remote UAC").weakened);
+        assert_eq!(find(&settings, "LSA protection").state, "not configured");
+        assert!(find(&settings, "WDigest credential caching").weakened);
+        assert_eq!(find(&settings, "WDigest credential caching").registry_key, "HKLM\\SYSTEM\\CurrentControlSet\\Control\\SecurityProviders\\WDigest");
+        assert!(find(&settings, "Network Level Authentication").weakened);
+        assert_eq!((find(&settings, "SMBv1 server").state.as_str(), find(&settings, "SMBv1 server").weakened), ("enabled (SMB1 feature installed)", true));
+        assert!(!find(&settings, "SMBv1 client").weakened);
+        assert_eq!(find(&settings, "Execution policy").value.as_deref(), Some("Bypass"));
+        assert!(find(&settings, "Execution policy").weakened);
+        assert!(find(&settings, "ScriptBlockLogging").weakened);
+        assert!(!find(&settings, "Transcription").weakened);
+        assert!(find(&settings, "Real-time protection").weakened);
+        let path_exclusion = find(&settings, "Paths exclusion");
+        assert_eq!((path_exclusion.value.as_deref(), path_exclusion.weakened), (Some("C:\\ProgramData\\staging"), true));
+        assert_eq!(weakened_count(&settings), 9);
+        assert!(audit_log.iter().any(|log| log.details.contains("(9 weakened)")));
+    }
+}
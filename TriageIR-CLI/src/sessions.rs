@@ -0,0 +1,406 @@
+//! Logged-on user session enumeration
+//! Terminal Services sessions (WTSEnumerateSessions) give the console and RDP
+//! sessions with their client name and address; LSA logon sessions
+//! (LsaEnumerateLogonSessions) add the logon type and server, and also reveal
+//! logons without a desktop session (network, batch, runas /netonly).
+
+// Session merging is only reachable through the Windows WTS/LSA enumeration
+#![cfg_attr(not(windows), allow(dead_code))]
+
+use crate::forensic_types::{AuditEntry, LoggedOnUser};
+use std::collections::HashSet;
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+const COMPONENT: &str = "sessions";
+
+const AF_INET: u32 = 2;
+const AF_INET6: u32 = 23;
+
+/// Interactive logon types that own a Terminal Services session
+const SESSION_LOGON_TYPES: &[u32] = &[2, 10, 11, 12, 13];
+
+/// Terminal Services session with a user attached
+#[derive(Debug, Clone, Default)]
+struct WtsSession {
+    session_id: u32,
+    username: String,
+    domain: String,
+    state: String,
+    winstation: String,
+    client_name: String,
+    client_address: String,
+    logon_time: String,
+}
+
+/// LSA logon session
+#[derive(Debug, Clone, Default)]
+struct LsaSession {
+    session_id: u32,
+    username: String,
+    domain: String,
+    logon_type: u32,
+    logon_time: String,
+    logon_server: String,
+}
+
+/// Enumerate logged-on user sessions on the live system
+#[cfg(windows)]
+pub fn collect_logged_on_users() -> (Vec<LoggedOnUser>, Vec<AuditEntry>) {
+    let mut audit_log = Vec::new();
+
+    let start_time = std::time::Instant::now();
+
+    audit_log.push(AuditEntry::new(COMPONENT, "INFO", "start_collection", "Starting logged-on user session enumeration".to_string(), "started"));
+
+    let wts_sessions = match windows_api::wts_sessions() {
+        Ok(sessions) => sessions,
+        Err(e) => {
+            audit_log.push(AuditEntry::new(COMPONENT, "WARN", "enumerate_wts_sessions", format!("WTSEnumerateSessions failed: {}", e), "error"));
+            Vec::new()
+        }
+    };
+    let lsa_sessions = match windows_api::lsa_sessions() {
+        Ok(sessions) => sessions,
+        Err(e) => {
+            audit_log.push(AuditEntry::new(COMPONENT, "WARN", "enumerate_logon_sessions", format!("LsaEnumerateLogonSessions failed: {}", e), "error"));
+            Vec::new()
+        }
+    };
+
+    audit_log.push(AuditEntry::new(
+        COMPONENT,
+        "DEBUG",
+        "enumerate",
+        format!("{} Terminal Services sessions with users, {} LSA logon sessions", wts_sessions.len(), lsa_sessions.len()),
+        "success",
+    ));
+
+    let users = merge_sessions(wts_sessions, lsa_sessions);
+
+    for user in users.iter().filter(|u| u.session_type == "RemoteInteractive" && !u.client_address.is_empty()) {
+        audit_log.push(AuditEntry::new(
+            COMPONENT,
+            "INFO",
+            "remote_session",
+            format!("RDP session {} for {}\\{} from {} ({})", user.session_id, user.domain, user.username, user.client_address, user.client_name),
+            "found",
+        ));
+    }
+
+    let duration = start_time.elapsed();
+    audit_log.push(AuditEntry {
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        level: "INFO".to_string(),
+        component: "sessions".to_string(),
+        action: "complete_collection".to_string(),
+        details: format!("Collected {} logged-on user sessions", users.len()),
+        duration_ms: Some(duration.as_millis() as u64),
+        result: "success".to_string(),
+    });
+
+    (users, audit_log)
+}
+
+#[cfg(not(windows))]
+pub fn collect_logged_on_users() -> (Vec<LoggedOnUser>, Vec<AuditEntry>) {
+    let audit_log = vec![
+        AuditEntry::new(COMPONENT, "INFO", "start_collection", "Starting logged-on user session enumeration".to_string(), "started"),
+        AuditEntry::new(COMPONENT, "WARN", "enumerate", "Session enumeration requires Windows".to_string(), "unsupported"),
+        AuditEntry::new(COMPONENT, "INFO", "complete_collection", "Collected 0 logged-on user sessions".to_string(), "success"),
+    ];
+    (Vec::new(), audit_log)
+}
+
+/// Combine Terminal Services and LSA sessions into one entry per logon
+fn merge_sessions(wts_sessions: Vec<WtsSession>, lsa_sessions: Vec<LsaSession>) -> Vec<LoggedOnUser> {
+    let lsa_sessions: Vec<LsaSession> = lsa_sessions.into_iter().filter(|s| !is_system_account(&s.domain, &s.username)).collect();
+    let mut used = vec![false; lsa_sessions.len()];
+    let mut users = Vec::new();
+
+    for wts in wts_sessions {
+        // Elevated interactive logons have two LSA sessions (split token); both belong to this session
+        let mut logon = None;
+        for (index, lsa) in lsa_sessions.iter().enumerate() {
+            if lsa.session_id == wts.session_id
+                && SESSION_LOGON_TYPES.contains(&lsa.logon_type)
+                && lsa.username.eq_ignore_ascii_case(&wts.username)
+                && lsa.domain.eq_ignore_ascii_case(&wts.domain)
+            {
+                used[index] = true;
+                logon.get_or_insert(lsa);
+            }
+        }
+
+        users.push(LoggedOnUser {
+            session_type: logon_type_name(logon.map_or(0, |l| l.logon_type)).to_string(),
+            logon_time: if wts.logon_time.is_empty() { logon.map(|l| l.logon_time.clone()).unwrap_or_default() } else { wts.logon_time },
+            logon_server: logon.map(|l| l.logon_server.clone()).unwrap_or_default(),
+            username: wts.username,
+            domain: wts.domain,
+            session_id: wts.session_id,
+            session_state: wts.state,
+            winstation: wts.winstation,
+            client_name: wts.client_name,
+            client_address: wts.client_address,
+        });
+    }
+
+    // Logons without a desktop session: one entry per account, session and logon type
+    let mut seen = HashSet::new();
+    for (lsa, _) in lsa_sessions.into_iter().zip(used).filter(|(_, used)| !used) {
+        let key = (lsa.username.to_lowercase(), lsa.domain.to_lowercase(), lsa.session_id, lsa.logon_type);
+        if !seen.insert(key) {
+            continue;
+        }
+        users.push(LoggedOnUser {
+            session_type: logon_type_name(lsa.logon_type).to_string(),
+            username: lsa.username,
+            domain: lsa.domain,
+            session_id: lsa.session_id,
+            logon_time: lsa.logon_time,
+            logon_server: lsa.logon_server,
+            session_state: String::new(),
+            winstation: String::new(),
+            client_name: String::new(),
+            client_address: String::new(),
+        });
+    }
+
+    users
+}
+
+/// Built-in service identities and the virtual accounts of system components
+fn is_system_account(domain: &str, username: &str) -> bool {
+    username.is_empty()
+        || username.ends_with('$')
+        || ["NT AUTHORITY", "Window Manager", "Font Driver Host", "NT Service"].iter().any(|d| d.eq_ignore_ascii_case(domain))
+}
+
+/// SECURITY_LOGON_TYPE names, as used in event 4624
+fn logon_type_name(logon_type: u32) -> &'static str {
+    match logon_type {
+        2 => "Interactive",
+        3 => "Network",
+        4 => "Batch",
+        5 => "Service",
+        6 => "Proxy",
+        7 => "Unlock",
+        8 => "NetworkCleartext",
+        9 => "NewCredentials",
+        10 => "RemoteInteractive",
+        11 => "CachedInteractive",
+        12 => "CachedRemoteInteractive",
+        13 => "CachedUnlock",
+        _ => "Unknown",
+    }
+}
+
+/// WTS_CLIENT_ADDRESS as text; the address bytes start at offset 2
+fn format_client_address(family: u32, address: &[u8; 20]) -> String {
+    match family {
+        AF_INET => Ipv4Addr::new(address[2], address[3], address[4], address[5]).to_string(),
+        AF_INET6 => {
+            let mut octets = [0u8; 16];
+            octets.copy_from_slice(&address[2..18]);
+            Ipv6Addr::from(octets).to_string()
+        }
+        _ => String::new(),
+    }
+}
+
+#[cfg(windows)]
+mod windows_api {
+    use super::{format_client_address, LsaSession, WtsSession};
+    use crate::binutil::filetime_to_string;
+    use windows::core::PWSTR;
+    use windows::Win32::Foundation::LUID;
+    use windows::Win32::Security::Authentication::Identity::{
+        LsaEnumerateLogonSessions, LsaFreeReturnBuffer, LsaGetLogonSessionData, LSA_UNICODE_STRING,
+        SECURITY_LOGON_SESSION_DATA,
+    };
+    use windows::Win32::System::RemoteDesktop::{
+        WTSActive, WTSClientAddress, WTSClientName, WTSConnected, WTSDisconnected, WTSDomainName, WTSEnumerateSessionsW,
+        WTSFreeMemory, WTSIdle, WTSListen, WTSQuerySessionInformationW, WTSSessionInfo, WTSUserName, WTSINFOW,
+        WTS_CLIENT_ADDRESS, WTS_CONNECTSTATE_CLASS, WTS_CURRENT_SERVER_HANDLE, WTS_INFO_CLASS, WTS_SESSION_INFOW,
+    };
+
+    pub fn wts_sessions() -> Result<Vec<WtsSession>, String> {
+        let mut info: *mut WTS_SESSION_INFOW = std::ptr::null_mut();
+        let mut count = 0u32;
+        unsafe { WTSEnumerateSessionsW(WTS_CURRENT_SERVER_HANDLE, 0, 1, &mut info, &mut count) }.map_err(|e| e.to_string())?;
+
+        let mut sessions = Vec::new();
+        for index in 0..count as usize {
+            let session = unsafe { *info.add(index) };
+            let username = query_string(session.SessionId, WTSUserName);
+            if username.is_empty() {
+                continue;
+            }
+
+            let client_address = query_buffer(session.SessionId, WTSClientAddress, |address: &WTS_CLIENT_ADDRESS| {
+                format_client_address(address.AddressFamily, &address.Address)
+            });
+            let logon_time = query_buffer(session.SessionId, WTSSessionInfo, |info: &WTSINFOW| filetime_to_string(info.LogonTime as u64));
+
+            sessions.push(WtsSession {
+                session_id: session.SessionId,
+                username,
+                domain: query_string(session.SessionId, WTSDomainName),
+                state: state_name(session.State).to_string(),
+                winstation: unsafe { session.pWinStationName.to_string() }.unwrap_or_default(),
+                client_name: query_string(session.SessionId, WTSClientName),
+                client_address: client_address.unwrap_or_default(),
+                logon_time: logon_time.unwrap_or_default(),
+            });
+        }
+
+        unsafe { WTSFreeMemory(info as *mut _) };
+        Ok(sessions)
+    }
+
+    pub fn lsa_sessions() -> Result<Vec<LsaSession>, String> {
+        let mut count = 0u32;
+        let mut luids: *mut LUID = std::ptr::null_mut();
+        let status = unsafe { LsaEnumerateLogonSessions(&mut count, &mut luids) };
+        if status.is_err() {
+            return Err(format!("NTSTATUS 0x{:08X}", status.0));
+        }
+
+        let mut sessions = Vec::new();
+        for index in 0..count as usize {
+            let mut data: *mut SECURITY_LOGON_SESSION_DATA = std::ptr::null_mut();
+            let status = unsafe { LsaGetLogonSessionData(luids.add(index), &mut data) };
+            if status.is_err() || data.is_null() {
+                continue;
+            }
+
+            let session = unsafe { &*data };
+            sessions.push(LsaSession {
+                session_id: session.Session,
+                username: lsa_string(&session.UserName),
+                domain: lsa_string(&session.LogonDomain),
+                logon_type: session.LogonType,
+                logon_time: filetime_to_string(session.LogonTime as u64),
+                logon_server: lsa_string(&session.LogonServer),
+            });
+            unsafe {
+                let _ = LsaFreeReturnBuffer(data as *const _);
+            }
+        }
+
+        unsafe {
+            let _ = LsaFreeReturnBuffer(luids as *const _);
+        }
+        Ok(sessions)
+    }
+
+    fn query_string(session_id: u32, class: WTS_INFO_CLASS) -> String {
+        let mut buffer = PWSTR::null();
+        let mut bytes = 0u32;
+        if unsafe { WTSQuerySessionInformationW(WTS_CURRENT_SERVER_HANDLE, session_id, class, &mut buffer, &mut bytes) }.is_err() {
+            return String::new();
+        }
+        let value = unsafe { buffer.to_string() }.unwrap_or_default();
+        unsafe { WTSFreeMemory(buffer.0 as *mut _) };
+        value
+    }
+
+    fn query_buffer<T, R>(session_id: u32, class: WTS_INFO_CLASS, read: impl FnOnce(&T) -> R) -> Option<R> {
+        let mut buffer = PWSTR::null();
+        let mut bytes = 0u32;
+        unsafe { WTSQuerySessionInformationW(WTS_CURRENT_SERVER_HANDLE, session_id, class, &mut buffer, &mut bytes) }.ok()?;
+        let value = (bytes as usize >= std::mem::size_of::<T>()).then(|| read(unsafe { &*(buffer.0 as *const T) }));
+        unsafe { WTSFreeMemory(buffer.0 as *mut _) };
+        value
+    }
+
+    fn lsa_string(value: &LSA_UNICODE_STRING) -> String {
+        if value.Buffer.is_null() || value.Length == 0 {
+            return String::new();
+        }
+        let chars = unsafe { std::slice::from_raw_parts(value.Buffer.0, value.Length as usize / 2) };
+        String::from_utf16_lossy(chars)
+    }
+
+    fn state_name(state: WTS_CONNECTSTATE_CLASS) -> &'static str {
+        match state {
+            WTSActive => "Active",
+            WTSConnected => "Connected",
+            WTSDisconnected => "Disconnected",
+            WTSIdle => "Idle",
+            WTSListen => "Listen",
+            _ => "Other",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lsa(session_id: u32, username: &str, domain: &str, logon_type: u32) -> LsaSession {
+        LsaSession {
+            session_id,
+            username: username.to_string(),
+            domain: domain.to_string(),
+            logon_type,
+            logon_time: "2024-05-01T08:00:00+00:00".to_string(),
+            logon_server: "DC01".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_merge_sessions() {
+        let wts = vec![WtsSession {
+            session_id: 2,
+            username: "alice".to_string(),
+            domain: "CORP".to_string(),
+            state: "Active".to_string(),
+            winstation: "RDP-Tcp#0".to_string(),
+            client_name: "LAPTOP-7".to_string(),
+            client_address: "10.0.0.15".to_string(),
+            logon_time: String::new(),
+        }];
+        let lsa_sessions = vec![
+            lsa(2, "Alice", "corp", 10),
+            lsa(2, "alice", "CORP", 10), // linked elevated token
+            lsa(0, "svc_backup", "CORP", 3),
+            lsa(0, "svc_backup", "CORP", 3),
+            lsa(0, "SYSTEM", "NT AUTHORITY", 5),
+            lsa(1, "DWM-1", "Window Manager", 2),
+            lsa(0, "WKS01$", "CORP", 3),
+        ];
+
+        let users = merge_sessions(wts, lsa_sessions);
+        assert_eq!(users.len(), 2);
+
+        assert_eq!(users[0].session_type, "RemoteInteractive");
+        assert_eq!(users[0].client_address, "10.0.0.15");
+        assert_eq!(users[0].logon_time, "2024-05-01T08:00:00+00:00");
+        assert_eq!(users[0].logon_server, "DC01");
+
+        assert_eq!(users[1].username, "svc_backup");
+        assert_eq!(users[1].session_type, "Network");
+        assert!(users[1].client_address.is_empty());
+    }
+
+    #[test]
+    fn test_format_client_address() {
+        let mut address = [0u8; 20];
+        address[2..6].copy_from_slice(&[192, 168, 1, 20]);
+        assert_eq!(format_client_address(AF_INET, &address), "192.168.1.20");
+
+        let mut address = [0u8; 20];
+        address[2..18].copy_from_slice(&"fe80::1".parse::<Ipv6Addr>().unwrap().octets());
+        assert_eq!(format_client_address(AF_INET6, &address), "fe80::1");
+
+        assert_eq!(format_client_address(0, &[0u8; 20]), "");
+    }
+
+    #[test]
+    fn test_logon_type_name() {
+        assert_eq!(logon_type_name(10), "RemoteInteractive");
+        assert_eq!(logon_type_name(3), "Network");
+        assert_eq!(logon_type_name(99), "Unknown");
+    }
+}
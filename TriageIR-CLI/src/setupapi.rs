@@ -0,0 +1,365 @@
+//! Setupapi device and driver installation log analysis
+//! setupapi.dev.log (and its rotated copies) records every device and driver
+//! package installation with timestamps, the selected INF and its signature
+//! verification, exposing unsigned or freshly installed kernel drivers.
+
+use crate::footprint;
+use crate::forensic_types::{DeviceInstallation, AuditEntry};
+use crate::offline::OfflineRoot;
+use chrono::NaiveDateTime;
+use regex::Regex;
+use std::path::Path;
+
+const COMPONENT: &str = "setupapi";
+
+const INF_DIRECTORY: &str = "C:\\Windows\\INF";
+
+/// Kernel drivers installed within this many days are flagged as recent
+const RECENT_INSTALL_DAYS: i64 = 30;
+
+const SETUPAPI_TIME_FORMAT: &str = "%Y/%m/%d %H:%M:%S%.3f";
+
+/// Collect device installations from the setupapi logs of the live system
+pub fn collect_device_installations() -> (Vec<DeviceInstallation>, Vec<AuditEntry>) {
+    collect_device_installations_from_directory(Path::new(INF_DIRECTORY))
+}
+
+/// Collect device installations from the setupapi logs of a mounted Windows volume
+pub fn collect_device_installations_offline(root: &OfflineRoot) -> (Vec<DeviceInstallation>, Vec<AuditEntry>) {
+    collect_device_installations_from_directory(&root.resolve(INF_DIRECTORY))
+}
+
+fn collect_device_installations_from_directory(inf_dir: &Path) -> (Vec<DeviceInstallation>, Vec<AuditEntry>) {
+    let mut installations = Vec::new();
+    let start_time = std::time::Instant::now();
+    let reference_time = chrono::Local::now().naive_local();
+
+    let mut audit_log = vec![AuditEntry::new(
+        COMPONENT,
+        "INFO",
+        "start_collection",
+        format!("Starting setupapi log analysis of {}", inf_dir.display()),
+        "started",
+    )];
+
+    let log_files = match footprint::read_dir(inf_dir) {
+        Ok(dir_entries) => {
+            let mut files: Vec<_> = dir_entries
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|path| path.is_file() && is_setupapi_log(path))
+                .collect();
+            files.sort();
+            files
+        }
+        Err(e) => {
+            audit_log.push(AuditEntry::new(
+                COMPONENT,
+                "WARN",
+                "directory_check",
+                format!("INF directory not readable: {} ({})", inf_dir.display(), e),
+                "not_found",
+            ));
+            Vec::new()
+        }
+    };
+
+    for log_file in &log_files {
+        match footprint::read(log_file) {
+            Ok(data) => {
+                let parsed = parse_setupapi_log(
+                    &String::from_utf8_lossy(&data),
+                    &log_file.display().to_string(),
+                    reference_time,
+                );
+                audit_log.push(AuditEntry::new(
+                    COMPONENT,
+                    "DEBUG",
+                    "parse_log",
+                    format!("Parsed {} installation sections from {}", parsed.len(), log_file.display()),
+                    "success",
+                ));
+                installations.extend(parsed);
+            }
+            Err(e) => {
+                audit_log.push(AuditEntry::new(
+                    COMPONENT,
+                    "ERROR",
+                    "read_log",
+                    format!("Failed to read {}: {}", log_file.display(), e),
+                    "error",
+                ));
+            }
+        }
+    }
+
+    let flagged = installations.iter().filter(|i| !i.flags.is_empty()).count();
+    let duration = start_time.elapsed();
+    let mut complete = AuditEntry::new(
+        COMPONENT,
+        "INFO",
+        "complete_collection",
+        format!("Collected {} device installations ({} flagged)", installations.len(), flagged),
+        "success",
+    );
+    complete.duration_ms = Some(duration.as_millis() as u64);
+    audit_log.push(complete);
+
+    (installations, audit_log)
+}
+
+/// setupapi.dev.log, setupapi.app.log, setupapi.offline.log and rotated copies
+/// such as setupapi.dev.20230501_101530.log
+fn is_setupapi_log(path: &Path) -> bool {
+    let name = path.file_name().unwrap_or_default().to_string_lossy().to_lowercase();
+    name.starts_with("setupapi.") && name.ends_with(".log")
+}
+
+struct SectionPatterns {
+    header: Regex,
+    start: Regex,
+    end: Regex,
+    exit_status: Regex,
+    inf_file: Regex,
+    opened_inf: Regex,
+    description: Regex,
+    signer_name: Regex,
+    signer_score: Regex,
+    signed: Regex,
+    unsigned: Regex,
+    driver_file: Regex,
+    service: Regex,
+}
+
+impl SectionPatterns {
+    fn new() -> Self {
+        SectionPatterns {
+            header: Regex::new(r"^>>>\s+\[(.*)\]\s*$").unwrap(),
+            start: Regex::new(r"^>>>\s+Section start (\d{4}/\d{2}/\d{2} \d{2}:\d{2}:\d{2}\.\d{3})").unwrap(),
+            end: Regex::new(r"^<<<\s+Section end (\d{4}/\d{2}/\d{2} \d{2}:\d{2}:\d{2}\.\d{3})").unwrap(),
+            exit_status: Regex::new(r"^<<<\s+\[Exit status:\s*(.*?)\]").unwrap(),
+            inf_file: Regex::new(r"InfFile\s+-\s+\[(.*?)\]").unwrap(),
+            opened_inf: Regex::new(r"(?i)Opened INF:\s*'([^']+)'").unwrap(),
+            description: Regex::new(r"Description\s+-\s+\[(.*?)\]").unwrap(),
+            signer_name: Regex::new(r"Signer(?: Name)?\s*[-=]\s*\[?([^\]]+?)\]?\s*$").unwrap(),
+            signer_score: Regex::new(r"Signer Score\s*[-=]\s*(\S+(?: \([A-Z_]+\))?)").unwrap(),
+            signed: Regex::new(r"(?i)(file is signed in catalog|driver package is signed|signature verified)").unwrap(),
+            unsigned: Regex::new(r"(?i)(\bunsigned\b|not digitally signed|is not signed|catalog failed|TRUST_E_NOSIGNATURE|0x800b0100)").unwrap(),
+            driver_file: Regex::new(r"(?i)'([^']*\\drivers\\[^'\\]+\.sys)'").unwrap(),
+            service: Regex::new(r"(?i)(?:Created|Modified) service '([^']+)'").unwrap(),
+        }
+    }
+}
+
+/// Split a setupapi log into installation sections
+fn parse_setupapi_log(text: &str, source_file: &str, reference_time: NaiveDateTime) -> Vec<DeviceInstallation> {
+    let patterns = SectionPatterns::new();
+    let mut installations = Vec::new();
+    let mut current: Option<DeviceInstallation> = None;
+
+    for raw_line in text.lines() {
+        let line = raw_line.trim_end();
+
+        if let Some(capture) = patterns.header.captures(line) {
+            if let Some(section) = current.take() {
+                installations.push(finish_section(section, reference_time));
+            }
+            let title = capture[1].to_string();
+            let (section, target) = match title.split_once(" - ") {
+                Some((section, target)) => (section.trim().to_string(), target.trim().to_string()),
+                None => (title.trim().to_string(), String::new()),
+            };
+            current = Some(new_section(section, target, source_file));
+            continue;
+        }
+
+        let Some(section) = current.as_mut() else {
+            continue;
+        };
+
+        if let Some(capture) = patterns.start.captures(line) {
+            section.start_time = format_setupapi_time(&capture[1]);
+        } else if let Some(capture) = patterns.end.captures(line) {
+            section.end_time = format_setupapi_time(&capture[1]);
+        } else if let Some(capture) = patterns.exit_status.captures(line) {
+            section.exit_status = capture[1].to_string();
+            installations.push(finish_section(current.take().unwrap(), reference_time));
+            continue;
+        }
+
+        if section.inf_file.is_empty() {
+            if let Some(capture) = patterns.inf_file.captures(line).or_else(|| patterns.opened_inf.captures(line)) {
+                section.inf_file = capture[1].to_string();
+            }
+        }
+        if section.driver_description.is_empty() {
+            if let Some(capture) = patterns.description.captures(line) {
+                section.driver_description = capture[1].to_string();
+            }
+        }
+        if let Some(capture) = patterns.signer_score.captures(line) {
+            section.signer_score = capture[1].to_string();
+            // "Signer Score = 0x0D000005 (INBOX)  / Signer Name = Microsoft Windows"
+            if let Some((_, name)) = line.split_once("Signer Name") {
+                section.signer_name = name.trim_start_matches([' ', '=', '-']).trim().to_string();
+            }
+        } else if let Some(capture) = patterns.signer_name.captures(line) {
+            section.signer_name = capture[1].trim().to_string();
+        }
+
+        if patterns.unsigned.is_match(line) {
+            section.signed = Some(false);
+        } else if section.signed.is_none() && patterns.signed.is_match(line) {
+            section.signed = Some(true);
+        }
+
+        for capture in patterns.driver_file.captures_iter(line) {
+            let driver_file = capture[1].to_string();
+            if !section.driver_files.iter().any(|f| f.eq_ignore_ascii_case(&driver_file)) {
+                section.driver_files.push(driver_file);
+            }
+        }
+        if let Some(capture) = patterns.service.captures(line) {
+            let service = capture[1].to_string();
+            if !section.services.contains(&service) {
+                section.services.push(service);
+            }
+        }
+    }
+
+    if let Some(section) = current.take() {
+        installations.push(finish_section(section, reference_time));
+    }
+
+    installations
+}
+
+fn new_section(section: String, target: String, source_file: &str) -> DeviceInstallation {
+    DeviceInstallation {
+        section,
+        target,
+        start_time: String::new(),
+        end_time: String::new(),
+        exit_status: String::new(),
+        inf_file: String::new(),
+        driver_description: String::new(),
+        signer_name: String::new(),
+        signer_score: String::new(),
+        signed: None,
+        driver_files: Vec::new(),
+        services: Vec::new(),
+        flags: Vec::new(),
+        source_file: source_file.to_string(),
+    }
+}
+
+fn finish_section(mut installation: DeviceInstallation, reference_time: NaiveDateTime) -> DeviceInstallation {
+    if installation.inf_file.is_empty() && installation.target.to_lowercase().ends_with(".inf") {
+        installation.inf_file = installation.target.clone();
+    }
+
+    if installation.signed == Some(false) {
+        installation.flags.push("unsigned".to_string());
+    }
+
+    if !installation.driver_files.is_empty() {
+        installation.flags.push("kernel_driver".to_string());
+
+        let installed = NaiveDateTime::parse_from_str(&installation.start_time, "%Y-%m-%dT%H:%M:%S%.3f").ok();
+        if installed.is_some_and(|installed| (reference_time - installed).num_days() < RECENT_INSTALL_DAYS) {
+            installation.flags.push("recent_kernel_driver".to_string());
+        }
+    }
+
+    installation
+}
+
+/// setupapi timestamps are local time of the installing system
+fn format_setupapi_time(timestamp: &str) -> String {
+    NaiveDateTime::parse_from_str(timestamp, SETUPAPI_TIME_FORMAT)
+        .map(|datetime| datetime.format("%Y-%m-%dT%H:%M:%S%.3f").to_string())
+        .unwrap_or_else(|_| timestamp.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    const SAMPLE_LOG: &str = "\
+[Device Install Log]
+     OS Version = 10.0.19045
+
+>>>  [Device Install (Hardware initiated) - USBSTOR\\Disk&Ven_SanDisk&Prod_Cruzer&Rev_1.26\\4C530001230508116493&0]
+>>>  Section start 2023/05/01 10:15:30.123
+     dvi:                Description - [Disk drive]
+     dvi:                InfFile     - [C:\\Windows\\System32\\DriverStore\\FileRepository\\disk.inf_amd64_1\\disk.inf]
+     sig:      Success: File is signed in catalog.
+     sgn:  Signer Score = 0x0D000005 (INBOX)  / Signer Name = Microsoft Windows
+<<<  Section end 2023/05/01 10:15:31.000
+<<<  [Exit status: SUCCESS]
+
+>>>  [Setup Import Driver Package - C:\\Users\\bob\\Downloads\\evil\\rootkit.inf]
+>>>  Section start 2023/05/20 22:01:02.500
+!!!  sig:      Verifying file against specific (valid) catalog failed! (0x800b0100)
+!!!  sig:      Error 0x800b0100: No signature was present in the subject.
+     flq:      Copying 'C:\\Users\\bob\\Downloads\\evil\\rootkit.sys' to 'C:\\Windows\\System32\\drivers\\rootkit.sys'.
+     inf:      Add Service: Created service 'rootkit'.
+<<<  Section end 2023/05/20 22:01:03.000
+<<<  [Exit status: SUCCESS]
+";
+
+    fn reference_time() -> NaiveDateTime {
+        NaiveDateTime::parse_from_str("2023/06/01 00:00:00.000", SETUPAPI_TIME_FORMAT).unwrap()
+    }
+
+    #[test]
+    fn test_parse_setupapi_log() {
+        let installations = parse_setupapi_log(SAMPLE_LOG, "setupapi.dev.log", reference_time());
+
+        assert_eq!(installations.len(), 2);
+
+        let disk = &installations[0];
+        assert_eq!(disk.section, "Device Install (Hardware initiated)");
+        assert_eq!(disk.target, "USBSTOR\\Disk&Ven_SanDisk&Prod_Cruzer&Rev_1.26\\4C530001230508116493&0");
+        assert_eq!(disk.start_time, "2023-05-01T10:15:30.123");
+        assert_eq!(disk.end_time, "2023-05-01T10:15:31.000");
+        assert_eq!(disk.exit_status, "SUCCESS");
+        assert_eq!(disk.driver_description, "Disk drive");
+        assert!(disk.inf_file.ends_with("disk.inf"));
+        assert_eq!(disk.signer_score, "0x0D000005 (INBOX)");
+        assert_eq!(disk.signer_name, "Microsoft Windows");
+        assert_eq!(disk.signed, Some(true));
+        assert!(disk.flags.is_empty());
+
+        let rootkit = &installations[1];
+        assert_eq!(rootkit.section, "Setup Import Driver Package");
+        assert_eq!(rootkit.inf_file, "C:\\Users\\bob\\Downloads\\evil\\rootkit.inf");
+        assert_eq!(rootkit.signed, Some(false));
+        assert_eq!(rootkit.driver_files, vec!["C:\\Windows\\System32\\drivers\\rootkit.sys"]);
+        assert_eq!(rootkit.services, vec!["rootkit"]);
+        assert_eq!(rootkit.flags, vec!["unsigned", "kernel_driver", "recent_kernel_driver"]);
+    }
+
+    #[test]
+    fn test_old_kernel_driver_not_recent() {
+        let later = NaiveDateTime::parse_from_str("2024/01/01 00:00:00.000", SETUPAPI_TIME_FORMAT).unwrap();
+        let installations = parse_setupapi_log(SAMPLE_LOG, "setupapi.dev.log", later);
+
+        assert_eq!(installations[1].flags, vec!["unsigned", "kernel_driver"]);
+    }
+
+    #[test]
+    fn test_collect_from_inf_directory() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("setupapi.dev.log"), SAMPLE_LOG).unwrap();
+        fs::write(temp_dir.path().join("setupapi.dev.20230101_000000.log"), SAMPLE_LOG).unwrap();
+        fs::write(temp_dir.path().join("oem1.inf"), "[Version]").unwrap();
+
+        let (installations, audit_log) = collect_device_installations_from_directory(temp_dir.path());
+
+        assert_eq!(installations.len(), 4);
+        assert!(audit_log.iter().any(|log| log.action == "complete_collection"));
+    }
+}
@@ -1,13 +1,93 @@
+//! Shimcache (Application Compatibility Cache) analysis
+//! The Shimcache tracks application execution and compatibility information
+//! It's a valuable source of execution artifacts for forensic analysis
+//! The AppCompatCache value is parsed in the binary layouts of Windows 7 (32-
+//! and 64-bit), 8.0, 8.1 and 10/11. Windows 7 to 8.1 record whether a file was
+//! executed in the insert flags, Windows 10 and 11 at the end of the shim data
+//! of the entry; entries of formats without it have no execution flag.
+
+use crate::binutil::{self, read_u16, read_u32, read_u64};
 use crate::forensic_types::{ShimcacheEntry, AuditEntry};
+use crate::hive;
+use crate::offline::OfflineRoot;
+use std::collections::HashMap;
+
+#[cfg(windows)]
+use crate::footprint::TrackedKey;
+#[cfg(windows)]
 use winreg::enums::*;
+#[cfg(windows)]
 use winreg::RegKey;
-use std::collections::HashMap;
 
-/// Shimcache (Application Compatibility Cache) analysis
-/// The Shimcache tracks application execution and compatibility information
-/// It's a valuable source of execution artifacts for forensic analysis
+const COMPONENT: &str = "shimcache";
+
+/// Below the control set of the SYSTEM hive
+const APPCOMPATCACHE_KEY: &str = "Control\\Session Manager\\AppCompatCache";
 
+#[cfg(windows)]
 pub fn collect_shimcache_entries() -> (Vec<ShimcacheEntry>, Vec<AuditEntry>) {
+    // Windows 7 and later keep the cache in the AppCompatCache key of the
+    // active control set; the other control sets hold the same or older copies
+    let shimcache_keys = vec![format!("SYSTEM\\CurrentControlSet\\{}", APPCOMPATCACHE_KEY)];
+    
+    collect_shimcache_from_keys(
+        &RegKey::predef(HKEY_LOCAL_MACHINE),
+        shimcache_keys,
+        "Starting Shimcache analysis".to_string(),
+    )
+}
+
+#[cfg(not(windows))]
+pub fn collect_shimcache_entries() -> (Vec<ShimcacheEntry>, Vec<AuditEntry>) {
+    let audit_log = vec![
+        AuditEntry::new(COMPONENT, "INFO", "start_collection", "Starting Shimcache analysis".to_string(), "started"),
+        AuditEntry::new(COMPONENT, "WARN", "registry_access", "Shimcache requires the Windows registry".to_string(), "unsupported"),
+        AuditEntry::new(COMPONENT, "INFO", "complete_collection", "Collected 0 shimcache entries".to_string(), "success"),
+    ];
+    (Vec::new(), audit_log)
+}
+
+/// Analyze the Shimcache stored in the SYSTEM hive of a mounted Windows volume
+pub fn collect_shimcache_entries_offline(root: &OfflineRoot) -> (Vec<ShimcacheEntry>, Vec<AuditEntry>) {
+    let start_time = std::time::Instant::now();
+    let system_hive = match root.open_hive("SYSTEM") {
+        Ok(hive) => hive,
+        Err(e) => {
+            let audit_log = vec![AuditEntry::new(COMPONENT, "ERROR", "load_hive", format!("Failed to load offline SYSTEM hive: {}", e), "error")];
+            return (Vec::new(), audit_log);
+        }
+    };
+    
+    // The offline hive has no CurrentControlSet link, so use the one recorded in Select
+    let control_set = hive::current_control_set(&system_hive);
+    let key_path = format!("{}\\{}", control_set, APPCOMPATCACHE_KEY);
+    let mut audit_log = vec![AuditEntry::new(
+        COMPONENT,
+        "INFO",
+        "start_collection",
+        format!("Starting offline Shimcache analysis of {} ({})", root.hive_path("SYSTEM").display(), control_set),
+        "started",
+    )];
+    if system_hive.is_dirty() {
+        audit_log.push(AuditEntry::new(COMPONENT, "WARN", "hive_state", "SYSTEM hive is dirty; entries only in its transaction logs are missing".to_string(), "dirty"));
+    }
+    
+    let shimcache_entries = match system_hive.open_key(&key_path).and_then(|key| key.value("AppCompatCache")) {
+        Some(value) => parse_cache_value(&key_path, &value.data, &mut audit_log),
+        None => {
+            audit_log.push(AuditEntry::new(COMPONENT, "WARN", "registry_access", format!("Failed to access {}: AppCompatCache value not found", key_path), "error"));
+            Vec::new()
+        }
+    };
+    
+    let mut complete = AuditEntry::new(COMPONENT, "INFO", "complete_collection", format!("Collected {} shimcache entries", shimcache_entries.len()), "success");
+    complete.duration_ms = Some(start_time.elapsed().as_millis() as u64);
+    audit_log.push(complete);
+    (shimcache_entries, audit_log)
+}
+
+#[cfg(windows)]
+fn collect_shimcache_from_keys(base_key: &RegKey, shimcache_keys: Vec<String>, start_details: String) -> (Vec<ShimcacheEntry>, Vec<AuditEntry>) {
     let mut shimcache_entries = Vec::new();
     let mut audit_log = Vec::new();
     
@@ -18,24 +98,13 @@ pub fn collect_shimcache_entries() -> (Vec<ShimcacheEntry>, Vec<AuditEntry>) {
         level: "INFO".to_string(),
         component: "shimcache".to_string(),
         action: "start_collection".to_string(),
-        details: "Starting Shimcache analysis".to_string(),
+        details: start_details,
         duration_ms: None,
         result: "started".to_string(),
     });
     
-    // Shimcache registry locations for different Windows versions
-    let shimcache_keys = vec![
-        // Windows 10/11
-        "SYSTEM\\CurrentControlSet\\Control\\Session Manager\\AppCompatCache",
-        // Windows 7/8
-        "SYSTEM\\CurrentControlSet\\Control\\Session Manager\\AppCompatibility\\AppCompatCache",
-        // Alternative locations
-        "SYSTEM\\ControlSet001\\Control\\Session Manager\\AppCompatCache",
-        "SYSTEM\\ControlSet002\\Control\\Session Manager\\AppCompatCache",
-    ];
-    
-    for key_path in shimcache_keys {
-        match collect_shimcache_from_key(key_path) {
+    for key_path in &shimcache_keys {
+        match collect_shimcache_from_key(base_key, key_path) {
             Ok((entries, logs)) => {
                 shimcache_entries.extend(entries);
                 audit_log.extend(logs);
@@ -68,228 +137,187 @@ pub fn collect_shimcache_entries() -> (Vec<ShimcacheEntry>, Vec<AuditEntry>) {
     (shimcache_entries, audit_log)
 }
 
-fn collect_shimcache_from_key(key_path: &str) -> Result<(Vec<ShimcacheEntry>, Vec<AuditEntry>), Box<dyn std::error::Error>> {
-    let mut shimcache_entries = Vec::new();
+#[cfg(windows)]
+fn collect_shimcache_from_key(base_key: &RegKey, key_path: &str) -> Result<(Vec<ShimcacheEntry>, Vec<AuditEntry>), Box<dyn std::error::Error>> {
     let mut audit_log = Vec::new();
     
-    let hklm = RegKey::predef(HKEY_LOCAL_MACHINE);
-    let shimcache_key = hklm.open_subkey(key_path)?;
-    
-    audit_log.push(AuditEntry {
-        timestamp: chrono::Utc::now().to_rfc3339(),
-        level: "DEBUG".to_string(),
-        component: "shimcache".to_string(),
-        action: "registry_open".to_string(),
-        details: format!("Opened registry key: {}", key_path),
-        duration_ms: None,
-        result: "success".to_string(),
-    });
-    
-    // Try to read the AppCompatCache value (Windows 10/11)
-    if let Ok(cache_data) = shimcache_key.get_raw_value("AppCompatCache") {
-        match parse_shimcache_data(&cache_data.bytes) {
-            Ok(entries) => {
-                shimcache_entries.extend(entries);
-                audit_log.push(AuditEntry {
-                    timestamp: chrono::Utc::now().to_rfc3339(),
-                    level: "DEBUG".to_string(),
-                    component: "shimcache".to_string(),
-                    action: "parse_cache_data".to_string(),
-                    details: format!("Parsed {} entries from AppCompatCache", shimcache_entries.len()),
-                    duration_ms: None,
-                    result: "success".to_string(),
-                });
-            }
-            Err(e) => {
-                audit_log.push(AuditEntry {
-                    timestamp: chrono::Utc::now().to_rfc3339(),
-                    level: "ERROR".to_string(),
-                    component: "shimcache".to_string(),
-                    action: "parse_cache_data".to_string(),
-                    details: format!("Failed to parse shimcache data: {}", e),
-                    duration_ms: None,
-                    result: "error".to_string(),
-                });
-            }
-        }
-    }
+    let shimcache_key = base_key.open_tracked(key_path)?;
+    let cache_data = shimcache_key.get_raw_value("AppCompatCache")?;
     
-    // Try to enumerate individual entries (older Windows versions)
-    for value_name in shimcache_key.enum_values().map(|x| x.unwrap().0) {
-        if value_name.starts_with("AppCompat") || value_name.contains("Cache") {
-            if let Ok(value_data) = shimcache_key.get_raw_value(&value_name) {
-                match parse_individual_shimcache_entry(&value_name, &value_data.bytes) {
-                    Ok(entry) => {
-                        shimcache_entries.push(entry);
-                        audit_log.push(AuditEntry {
-                            timestamp: chrono::Utc::now().to_rfc3339(),
-                            level: "DEBUG".to_string(),
-                            component: "shimcache".to_string(),
-                            action: "parse_individual_entry".to_string(),
-                            details: format!("Parsed entry: {}", value_name),
-                            duration_ms: None,
-                            result: "success".to_string(),
-                        });
-                    }
-                    Err(e) => {
-                        audit_log.push(AuditEntry {
-                            timestamp: chrono::Utc::now().to_rfc3339(),
-                            level: "WARN".to_string(),
-                            component: "shimcache".to_string(),
-                            action: "parse_individual_entry".to_string(),
-                            details: format!("Failed to parse {}: {}", value_name, e),
-                            duration_ms: None,
-                            result: "error".to_string(),
-                        });
-                    }
-                }
-            }
-        }
-    }
+    let shimcache_entries = parse_cache_value(key_path, &cache_data.bytes, &mut audit_log);
     
     Ok((shimcache_entries, audit_log))
 }
 
-fn parse_shimcache_data(data: &[u8]) -> Result<Vec<ShimcacheEntry>, Box<dyn std::error::Error>> {
-    let mut entries = Vec::new();
-    
-    if data.len() < 16 {
-        return Err("Shimcache data too small".into());
-    }
-    
-    // Parse shimcache header
-    let header_signature = u32::from_le_bytes([data[0], data[1], data[2], data[3]]);
-    let num_entries = u32::from_le_bytes([data[4], data[5], data[6], data[7]]);
-    
-    // Validate header signature (varies by Windows version)
-    let valid_signatures = vec![
-        0x30, 0x34, 0x38, // Windows 10/11 signatures
-        0x80, 0x73, 0x74, // Windows 8.1 signatures
-        0x72, 0x6f, 0x74, // Windows 7 signatures
-    ];
-    
-    if !valid_signatures.contains(&header_signature) {
-        return Err(format!("Invalid shimcache signature: 0x{:x}", header_signature).into());
-    }
-    
-    // Parse entries based on Windows version format
-    let mut offset = 16; // Skip header
-    
-    for i in 0..num_entries.min(1000) { // Limit to prevent excessive processing
-        if offset + 32 > data.len() {
-            break;
+/// Parse an AppCompatCache value read from `key_path`, logging the outcome
+fn parse_cache_value(key_path: &str, data: &[u8], audit_log: &mut Vec<AuditEntry>) -> Vec<ShimcacheEntry> {
+    match parse_shimcache_data(data) {
+        Ok((format, entries)) => {
+            audit_log.push(AuditEntry::new(
+                COMPONENT,
+                "DEBUG",
+                "parse_cache_data",
+                format!("Parsed {} entries from {}\\AppCompatCache ({} format)", entries.len(), key_path, format.name()),
+                "success",
+            ));
+            entries
         }
-        
-        match parse_shimcache_entry(&data[offset..], header_signature) {
-            Ok((entry, entry_size)) => {
-                entries.push(entry);
-                offset += entry_size;
-            }
-            Err(e) => {
-                // Log error but continue processing
-                eprintln!("Error parsing shimcache entry {}: {}", i, e);
-                break;
-            }
+        Err(e) => {
+            audit_log.push(AuditEntry::new(COMPONENT, "ERROR", "parse_cache_data", format!("Failed to parse shimcache data of {}: {}", key_path, e), "error"));
+            Vec::new()
         }
     }
-    
-    Ok(entries)
 }
 
-fn parse_shimcache_entry(data: &[u8], signature: u32) -> Result<(ShimcacheEntry, usize), Box<dyn std::error::Error>> {
-    if data.len() < 32 {
-        return Err("Insufficient data for shimcache entry".into());
+/// Header signature of the Windows 7 / Server 2008 R2 cache
+const WIN7_SIGNATURE: u32 = 0xBADC0FEE;
+/// Header size of Windows 8.0 and 8.1, whose entries follow at this offset
+const WIN8_HEADER_SIZE: u32 = 0x80;
+/// Header sizes of Windows 10 (0x30 before 1607, 0x34 since and on Windows 11)
+const WIN10_HEADER_SIZES: [u32; 2] = [0x30, 0x34];
+/// Entry signatures of Windows 8.0 and of 8.1 and later
+const WIN80_ENTRY_SIGNATURE: &[u8] = b"00ts";
+const WIN81_ENTRY_SIGNATURE: &[u8] = b"10ts";
+/// InsertFlags bit set when the shim engine saw the file executed (7 to 8.1)
+const INSERT_FLAG_EXECUTED: u32 = 0x2;
+/// Upper bound on entries; Windows keeps at most 1024
+const MAX_ENTRIES: usize = 4096;
+
+/// AppCompatCache layouts
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CacheFormat {
+    Windows7x86,
+    Windows7x64,
+    Windows80,
+    Windows81,
+    Windows10,
+}
+
+impl CacheFormat {
+    fn name(&self) -> &'static str {
+        match self {
+            CacheFormat::Windows7x86 => "Windows 7 (32-bit)",
+            CacheFormat::Windows7x64 => "Windows 7 (64-bit)",
+            CacheFormat::Windows80 => "Windows 8.0",
+            CacheFormat::Windows81 => "Windows 8.1",
+            CacheFormat::Windows10 => "Windows 10/11",
+        }
     }
-    
-    // Entry format varies by Windows version
-    let (path, last_modified, file_size, last_update, execution_flag, entry_size) = match signature {
-        0x30 | 0x34 | 0x38 => parse_windows10_entry(data)?, // Windows 10/11
-        0x80 | 0x73 => parse_windows8_entry(data)?,  // Windows 8.1
-        0x72 | 0x6f | 0x74 => parse_windows7_entry(data)?,  // Windows 7
-        _ => return Err("Unsupported shimcache format".into()),
-    };
-    
-    let entry = ShimcacheEntry {
-        path,
-        last_modified,
-        file_size,
-        last_update,
-        execution_flag,
-    };
-    
-    Ok((entry, entry_size))
 }
 
-fn parse_windows10_entry(data: &[u8]) -> Result<(String, String, u64, String, bool, usize), Box<dyn std::error::Error>> {
-    // Windows 10/11 shimcache entry format
-    let path_length = u16::from_le_bytes([data[0], data[1]]) as usize;
-    let path_offset = u16::from_le_bytes([data[2], data[3]]) as usize;
-    
-    let file_size = u64::from_le_bytes([
-        data[8], data[9], data[10], data[11],
-        data[12], data[13], data[14], data[15]
-    ]);
-    
-    let last_modified_raw = u64::from_le_bytes([
-        data[16], data[17], data[18], data[19],
-        data[20], data[21], data[22], data[23]
-    ]);
-    
-    let last_update_raw = u64::from_le_bytes([
-        data[24], data[25], data[26], data[27],
-        data[28], data[29], data[30], data[31]
-    ]);
-    
-    // Extract path string (UTF-16)
-    let path = if path_offset + path_length <= data.len() {
-        let path_bytes = &data[path_offset..path_offset + path_length];
-        parse_utf16_string(path_bytes)
+/// Parse the AppCompatCache value; entries are in cache order, most recently
+/// inserted or updated first
+fn parse_shimcache_data(data: &[u8]) -> Result<(CacheFormat, Vec<ShimcacheEntry>), String> {
+    let header = read_u32(data, 0).ok_or("Shimcache data too small")?;
+    let format = if header == WIN7_SIGNATURE {
+        // 64-bit entries have a zero pad where 32-bit ones store the path offset
+        match read_u32(data, 128 + 4) {
+            Some(0) => CacheFormat::Windows7x64,
+            _ => CacheFormat::Windows7x86,
+        }
+    } else if header == WIN8_HEADER_SIZE {
+        match data.get(128..132) {
+            Some(WIN81_ENTRY_SIGNATURE) => CacheFormat::Windows81,
+            _ => CacheFormat::Windows80,
+        }
+    } else if WIN10_HEADER_SIZES.contains(&header) {
+        CacheFormat::Windows10
     } else {
-        "Unknown path".to_string()
+        return Err(format!("Unsupported shimcache signature: 0x{:x}", header));
     };
     
-    let last_modified = filetime_to_string(last_modified_raw);
-    let last_update = filetime_to_string(last_update_raw);
-    let execution_flag = true; // Windows 10+ doesn't have explicit execution flag
-    
-    let entry_size = 32 + path_length;
-    
-    Ok((path, last_modified, file_size, last_update, execution_flag, entry_size))
+    let entries = match format {
+        CacheFormat::Windows7x86 | CacheFormat::Windows7x64 => parse_windows7_entries(data, format == CacheFormat::Windows7x64)?,
+        CacheFormat::Windows80 | CacheFormat::Windows81 => parse_ts_entries(data, WIN8_HEADER_SIZE as usize, format)?,
+        CacheFormat::Windows10 => parse_ts_entries(data, header as usize, format)?,
+    };
+    Ok((format, entries))
 }
 
-fn parse_windows8_entry(_data: &[u8]) -> Result<(String, String, u64, String, bool, usize), Box<dyn std::error::Error>> {
-    // Windows 8.1 shimcache entry format (simplified)
-    let path = "Windows 8 entry (parsing not fully implemented)".to_string();
-    let last_modified = chrono::Utc::now().to_rfc3339();
-    let file_size = 0;
-    let last_update = chrono::Utc::now().to_rfc3339();
-    let execution_flag = false;
-    let entry_size = 32;
-    
-    Ok((path, last_modified, file_size, last_update, execution_flag, entry_size))
+/// Windows 7: an entry table after the 128-byte header; paths and shim data
+/// are stored elsewhere in the value at the offsets the entries give
+fn parse_windows7_entries(data: &[u8], x64: bool) -> Result<Vec<ShimcacheEntry>, String> {
+    let count = read_u32(data, 4).ok_or("Truncated shimcache header")? as usize;
+    let entry_size = if x64 { 48 } else { 32 };
+    let mut entries = Vec::new();
+    for position in 0..count.min(MAX_ENTRIES) {
+        let entry = 128 + position * entry_size;
+        let field = |offset32: usize, offset64: usize| {
+            let offset = entry + if x64 { offset64 } else { offset32 };
+            read_u32(data, offset).ok_or_else(|| format!("Entry {} extends past the end of the cache", position))
+        };
+        let path_length = read_u16(data, entry).ok_or_else(|| format!("Entry {} extends past the end of the cache", position))? as usize;
+        let path_offset = field(4, 8)? as usize;
+        let last_modified = read_u64(data, entry + if x64 { 16 } else { 8 }).unwrap_or_default();
+        let insert_flags = field(16, 24)?;
+        let path = data
+            .get(path_offset..path_offset + path_length)
+            .map(parse_utf16_string)
+            .ok_or_else(|| format!("Path of entry {} lies outside the cache", position))?;
+        entries.push(shimcache_entry(position, path, last_modified, Some(insert_flags & INSERT_FLAG_EXECUTED != 0)));
+    }
+    Ok(entries)
 }
 
-fn parse_windows7_entry(_data: &[u8]) -> Result<(String, String, u64, String, bool, usize), Box<dyn std::error::Error>> {
-    // Windows 7 shimcache entry format (simplified)
-    let path = "Windows 7 entry (parsing not fully implemented)".to_string();
-    let last_modified = chrono::Utc::now().to_rfc3339();
-    let file_size = 0;
-    let last_update = chrono::Utc::now().to_rfc3339();
-    let execution_flag = false;
-    let entry_size = 32;
-    
-    Ok((path, last_modified, file_size, last_update, execution_flag, entry_size))
+/// Windows 8 and later: self-describing entries, each starting with a
+/// signature, a checksum and the size of the rest of the entry
+fn parse_ts_entries(data: &[u8], header_size: usize, format: CacheFormat) -> Result<Vec<ShimcacheEntry>, String> {
+    let signature = if format == CacheFormat::Windows80 { WIN80_ENTRY_SIGNATURE } else { WIN81_ENTRY_SIGNATURE };
+    let mut entries = Vec::new();
+    let mut offset = header_size;
+    while offset + 12 <= data.len() && entries.len() < MAX_ENTRIES {
+        let position = entries.len();
+        if data.get(offset..offset + 4) != Some(signature) {
+            return Err(format!("Entry {} at offset {} has no {} signature", position, offset, String::from_utf8_lossy(signature)));
+        }
+        let entry_size = read_u32(data, offset + 8).unwrap_or_default() as usize;
+        let entry = data
+            .get(offset + 12..offset + 12 + entry_size)
+            .ok_or_else(|| format!("Entry {} extends past the end of the cache", position))?;
+        entries.push(parse_ts_entry(entry, format, position).ok_or_else(|| format!("Entry {} is truncated", position))?);
+        offset += 12 + entry_size;
+    }
+    Ok(entries)
+}
+
+/// Fields of one Windows 8+ entry: path, then (8.1) the package name, then
+/// (8.0 and 8.1) insert and shim flags, the last modified time and the shim data
+fn parse_ts_entry(entry: &[u8], format: CacheFormat, position: usize) -> Option<ShimcacheEntry> {
+    let path_size = read_u16(entry, 0)? as usize;
+    let path = parse_utf16_string(entry.get(2..2 + path_size)?);
+    let mut cursor = 2 + path_size;
+    if format == CacheFormat::Windows81 {
+        cursor += 2 + read_u16(entry, cursor)? as usize; // Package family of Store apps
+    }
+    let insert_flags = match format {
+        CacheFormat::Windows10 => None,
+        _ => {
+            let flags = read_u32(entry, cursor)?;
+            cursor += 8; // Insert flags and shim flags
+            Some(flags)
+        }
+    };
+    let last_modified = read_u64(entry, cursor)?;
+    let data_size = read_u32(entry, cursor + 8)? as usize;
+    let shim_data = entry.get(cursor + 12..cursor + 12 + data_size)?;
+    let executed = match insert_flags {
+        Some(flags) => Some(flags & INSERT_FLAG_EXECUTED != 0),
+        // Windows 10 and 11 end the shim data with a 1 for executed files; entries
+        // without data carry no flag
+        None => shim_data.len().checked_sub(4).and_then(|end| read_u32(shim_data, end)).map(|flag| flag == 1),
+    };
+    Some(shimcache_entry(position, path, last_modified, executed))
 }
 
-fn parse_individual_shimcache_entry(value_name: &str, data: &[u8]) -> Result<ShimcacheEntry, Box<dyn std::error::Error>> {
-    // Parse individual registry value as shimcache entry
-    Ok(ShimcacheEntry {
-        path: format!("Registry entry: {}", value_name),
-        last_modified: chrono::Utc::now().to_rfc3339(),
-        file_size: data.len() as u64,
-        last_update: chrono::Utc::now().to_rfc3339(),
-        execution_flag: false,
-    })
+fn shimcache_entry(position: usize, path: String, last_modified: u64, execution_flag: Option<bool>) -> ShimcacheEntry {
+    ShimcacheEntry {
+        position,
+        path,
+        last_modified: filetime_to_string(last_modified),
+        file_size: 0,
+        last_update: filetime_to_string(0),
+        execution_flag,
+    }
 }
 
 fn parse_utf16_string(data: &[u8]) -> String {
@@ -304,21 +332,10 @@ fn parse_utf16_string(data: &[u8]) -> String {
 }
 
 fn filetime_to_string(filetime: u64) -> String {
-    // Convert Windows FILETIME to readable string
     if filetime == 0 {
         return "Not set".to_string();
     }
-    
-    // FILETIME is 100-nanosecond intervals since January 1, 1601
-    const FILETIME_EPOCH_DIFF: u64 = 11644473600; // Seconds between 1601 and 1970
-    const FILETIME_UNITS_PER_SEC: u64 = 10_000_000;
-    
-    let unix_timestamp = (filetime / FILETIME_UNITS_PER_SEC).saturating_sub(FILETIME_EPOCH_DIFF);
-    
-    match chrono::DateTime::from_timestamp(unix_timestamp as i64, 0) {
-        Some(dt) => dt.to_rfc3339(),
-        None => "Invalid timestamp".to_string(),
-    }
+    binutil::filetime_to_string(filetime)
 }
 
 /// Get shimcache statistics for reporting
@@ -328,7 +345,7 @@ pub fn get_shimcache_statistics(shimcache_entries: &[ShimcacheEntry]) -> HashMap
     stats.insert("total_entries".to_string(), shimcache_entries.len() as u32);
     
     let executed_count = shimcache_entries.iter()
-        .filter(|entry| entry.execution_flag)
+        .filter(|entry| entry.execution_flag == Some(true))
         .count();
     stats.insert("executed_programs".to_string(), executed_count as u32);
     
@@ -381,7 +398,7 @@ pub fn get_recently_modified_entries<'a>(shimcache_entries: &'a [ShimcacheEntry]
 /// Get executed programs from shimcache
 pub fn get_executed_programs<'a>(shimcache_entries: &'a [ShimcacheEntry]) -> Vec<&'a ShimcacheEntry> {
     shimcache_entries.iter()
-        .filter(|entry| entry.execution_flag)
+        .filter(|entry| entry.execution_flag == Some(true))
         .collect()
 }
 
@@ -405,33 +422,73 @@ mod tests {
         assert!(has_complete);
     }
 
+    #[test]
+    fn test_collect_shimcache_entries_offline_missing_hive() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir(temp_dir.path().join("Windows")).unwrap();
+        let root = OfflineRoot::new(temp_dir.path()).unwrap();
+        
+        let (shimcache_entries, audit_log) = collect_shimcache_entries_offline(&root);
+        
+        assert!(shimcache_entries.is_empty());
+        assert!(audit_log.iter().any(|log| log.action == "load_hive" && log.level == "ERROR"));
+    }
+
+    #[test]
+    fn test_collect_shimcache_entries_offline_from_raw_hive() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config = temp_dir.path().join("Windows").join("System32").join("config");
+        std::fs::create_dir_all(&config).unwrap();
+        let root = OfflineRoot::new(temp_dir.path()).unwrap();
+
+        let path = utf16("C:\\Users\\bob\\Downloads\\setup.exe");
+        let fields = [&(path.len() as u16).to_le_bytes()[..], &path, &FILETIME.to_le_bytes(), &4u32.to_le_bytes(), &1u32.to_le_bytes()].concat();
+        let mut cache = 0x34u32.to_le_bytes().to_vec();
+        cache.resize(0x34, 0);
+        cache.extend(ts_entry(b"10ts", &fields));
+        let system = hive::HiveBuilder::default()
+            .dword("Select", "Current", 2)
+            .value("ControlSet002\\Control\\Session Manager\\AppCompatCache", "AppCompatCache", hive::REG_BINARY, &cache)
+            .build();
+        std::fs::write(config.join("SYSTEM"), system).unwrap();
+
+        let (shimcache_entries, audit_log) = collect_shimcache_entries_offline(&root);
+        assert_eq!(shimcache_entries.len(), 1);
+        assert_eq!(shimcache_entries[0].path, "C:\\Users\\bob\\Downloads\\setup.exe");
+        assert_eq!(shimcache_entries[0].execution_flag, Some(true));
+        assert!(audit_log.iter().any(|log| log.action == "parse_cache_data" && log.details.contains("ControlSet002")));
+    }
+
     #[test]
     fn test_shimcache_statistics() {
         let mut shimcache_entries = Vec::new();
         
         // Create test shimcache entries
         shimcache_entries.push(ShimcacheEntry {
+            position: 0,
             path: "C:\\Windows\\System32\\notepad.exe".to_string(),
             last_modified: "2023-01-01T00:00:00Z".to_string(),
             file_size: 1024,
             last_update: "2023-01-01T00:00:00Z".to_string(),
-            execution_flag: true,
+            execution_flag: Some(true),
         });
         
         shimcache_entries.push(ShimcacheEntry {
+            position: 0,
             path: "C:\\Windows\\System32\\calc.exe".to_string(),
             last_modified: "2023-01-02T00:00:00Z".to_string(),
             file_size: 2048,
             last_update: "2023-01-02T00:00:00Z".to_string(),
-            execution_flag: false,
+            execution_flag: Some(false),
         });
         
         shimcache_entries.push(ShimcacheEntry {
+            position: 0,
             path: "C:\\Windows\\System32\\kernel32.dll".to_string(),
             last_modified: "2023-01-03T00:00:00Z".to_string(),
             file_size: 4096,
             last_update: "2023-01-03T00:00:00Z".to_string(),
-            execution_flag: false,
+            execution_flag: Some(false),
         });
         
         let stats = get_shimcache_statistics(&shimcache_entries);
@@ -448,19 +505,21 @@ mod tests {
         let mut shimcache_entries = Vec::new();
         
         shimcache_entries.push(ShimcacheEntry {
+            position: 0,
             path: "C:\\Windows\\System32\\notepad.exe".to_string(),
             last_modified: "2023-01-01T00:00:00Z".to_string(),
             file_size: 1024,
             last_update: "2023-01-01T00:00:00Z".to_string(),
-            execution_flag: true,
+            execution_flag: Some(true),
         });
         
         shimcache_entries.push(ShimcacheEntry {
+            position: 0,
             path: "C:\\Program Files\\MyApp\\app.exe".to_string(),
             last_modified: "2023-01-02T00:00:00Z".to_string(),
             file_size: 2048,
             last_update: "2023-01-02T00:00:00Z".to_string(),
-            execution_flag: false,
+            execution_flag: Some(false),
         });
         
         let results = find_shimcache_by_path(&shimcache_entries, "notepad");
@@ -476,19 +535,21 @@ mod tests {
         let mut shimcache_entries = Vec::new();
         
         shimcache_entries.push(ShimcacheEntry {
+            position: 0,
             path: "C:\\Windows\\System32\\notepad.exe".to_string(),
             last_modified: "2023-01-01T00:00:00Z".to_string(),
             file_size: 1024,
             last_update: "2023-01-01T00:00:00Z".to_string(),
-            execution_flag: true,
+            execution_flag: Some(true),
         });
         
         shimcache_entries.push(ShimcacheEntry {
+            position: 0,
             path: "C:\\Windows\\System32\\calc.exe".to_string(),
             last_modified: "2023-01-02T00:00:00Z".to_string(),
             file_size: 2048,
             last_update: "2023-01-02T00:00:00Z".to_string(),
-            execution_flag: false,
+            execution_flag: Some(false),
         });
         
         let executed = get_executed_programs(&shimcache_entries);
@@ -526,24 +587,93 @@ mod tests {
         assert!(result.len() > 10); // Should be a reasonable timestamp
     }
 
+    fn utf16(text: &str) -> Vec<u8> {
+        text.encode_utf16().flat_map(u16::to_le_bytes).collect()
+    }
+
+    /// Windows 8+ entry: signature, checksum, size, then the fields of the format
+    fn ts_entry(signature: &[u8], fields: &[u8]) -> Vec<u8> {
+        [signature, &[0xAA; 4], &(fields.len() as u32).to_le_bytes(), fields].concat()
+    }
+
+    const FILETIME: u64 = 133_580_000_000_000_000; // 2024-04-19T11:33:20Z
+
+    #[test]
+    fn test_parse_windows10_cache() {
+        let path = utf16("C:\\Users\\bob\\AppData\\Local\\Temp\\beacon.exe");
+        let executed = [&(path.len() as u16).to_le_bytes()[..], &path, &FILETIME.to_le_bytes(), &8u32.to_le_bytes(), &[0, 0, 0, 0, 1, 0, 0, 0]].concat();
+        let path = utf16("C:\\Windows\\System32\\svchost.exe");
+        let no_data = [&(path.len() as u16).to_le_bytes()[..], &path, &0u64.to_le_bytes(), &0u32.to_le_bytes()].concat();
+        let mut data = 0x34u32.to_le_bytes().to_vec();
+        data.resize(0x34, 0);
+        data.extend(ts_entry(b"10ts", &executed));
+        data.extend(ts_entry(b"10ts", &no_data));
+
+        let (format, entries) = parse_shimcache_data(&data).unwrap();
+        assert_eq!(format, CacheFormat::Windows10);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].path, "C:\\Users\\bob\\AppData\\Local\\Temp\\beacon.exe");
+        assert_eq!(entries[0].last_modified, "2024-04-19T11:33:20+00:00");
+        assert_eq!(entries[0].execution_flag, Some(true));
+        assert_eq!((entries[1].position, entries[1].execution_flag), (1, None));
+
+        data.truncate(data.len() - 4);
+        assert!(parse_shimcache_data(&data).unwrap_err().contains("Entry 1"));
+        assert!(parse_shimcache_data(&[0xEF, 0xBE, 0xAD, 0xDE]).unwrap_err().contains("0xdeadbeef"));
+    }
+
+    #[test]
+    fn test_parse_windows8_and_7_caches() {
+        let path = utf16("C:\\Tools\\psexec.exe");
+        let package = utf16("Microsoft.WindowsCalculator");
+        let fields = [&(path.len() as u16).to_le_bytes()[..], &path, &(package.len() as u16).to_le_bytes(), &package, &2u32.to_le_bytes(), &0u32.to_le_bytes(), &FILETIME.to_le_bytes(), &0u32.to_le_bytes()].concat();
+        let mut data = 0x80u32.to_le_bytes().to_vec();
+        data.resize(0x80, 0);
+        data.extend(ts_entry(b"10ts", &fields));
+        let (format, entries) = parse_shimcache_data(&data).unwrap();
+        assert_eq!(format, CacheFormat::Windows81);
+        assert_eq!((entries[0].path.as_str(), entries[0].execution_flag), ("C:\\Tools\\psexec.exe", Some(true)));
+
+        // Windows 7 x64: 48-byte entries, path stored after the entry table
+        let path = utf16("\\??\\C:\\Windows\\system32\\cmd.exe");
+        let mut data = WIN7_SIGNATURE.to_le_bytes().to_vec();
+        data.extend(1u32.to_le_bytes());
+        data.resize(128, 0);
+        let path_offset = 128 + 48;
+        data.extend((path.len() as u16).to_le_bytes());
+        data.extend((path.len() as u16 + 2).to_le_bytes());
+        data.extend(0u32.to_le_bytes());
+        data.extend((path_offset as u64).to_le_bytes());
+        data.extend(FILETIME.to_le_bytes());
+        data.extend(0u32.to_le_bytes()); // Insert flags: not executed
+        data.extend([0u8; 20]);
+        data.extend(&path);
+        let (format, entries) = parse_shimcache_data(&data).unwrap();
+        assert_eq!(format, CacheFormat::Windows7x64);
+        assert_eq!(entries[0].path, "\\??\\C:\\Windows\\system32\\cmd.exe");
+        assert_eq!((entries[0].last_modified.as_str(), entries[0].execution_flag), ("2024-04-19T11:33:20+00:00", Some(false)));
+    }
+
     #[test]
     fn test_get_recently_modified_entries() {
         let mut shimcache_entries = Vec::new();
         
         shimcache_entries.push(ShimcacheEntry {
+            position: 0,
             path: "C:\\old.exe".to_string(),
             last_modified: "2023-01-01T00:00:00Z".to_string(),
             file_size: 1024,
             last_update: "2023-01-01T00:00:00Z".to_string(),
-            execution_flag: false,
+            execution_flag: Some(false),
         });
         
         shimcache_entries.push(ShimcacheEntry {
+            position: 0,
             path: "C:\\new.exe".to_string(),
             last_modified: "2023-12-31T23:59:59Z".to_string(),
             file_size: 2048,
             last_update: "2023-12-31T23:59:59Z".to_string(),
-            execution_flag: false,
+            execution_flag: Some(false),
         });
         
         let recent = get_recently_modified_entries(&shimcache_entries, 1);
@@ -0,0 +1,619 @@
+//! System Resource Usage Monitor (SRUM) analysis
+//! SRUDB.dat is an ESE database in which Windows aggregates, per hour, the CPU
+//! time, disk I/O and network bytes of every application and user. The file is
+//! held open by the Diagnostic Policy Service and usually dirty, so it is copied
+//! together with its transaction logs (from the shadow copy when locked),
+//! recovered with esentutl, and only the copy is opened read-only through esent.dll.
+
+// Record decoding is only reachable through the ESE (esent.dll) reader
+#![cfg_attr(not(windows), allow(dead_code))]
+
+use crate::binutil::{sid_to_string, utf16_string};
+use crate::footprint;
+use crate::forensic_types::{AuditEntry, SrumAppUsage, SrumNetworkUsage};
+use crate::offline::OfflineRoot;
+use std::collections::HashMap;
+use std::path::Path;
+
+#[cfg(windows)]
+use crate::vss;
+#[cfg(windows)]
+use std::path::PathBuf;
+
+const COMPONENT: &str = "srum";
+
+const SRUM_DATABASE: &str = "C:\\Windows\\System32\\sru\\SRUDB.dat";
+
+/// Maps the AppId/UserId columns to application names and user SIDs
+const ID_MAP_TABLE: &str = "SruDbIdMapTable";
+const APP_RESOURCE_USAGE_TABLE: &str = "{D10CA2FE-6FCF-4F6D-848E-B2E99266FA89}";
+const NETWORK_USAGE_TABLE: &str = "{973F5D5C-1D90-4944-BE8E-24B94231A174}";
+
+const ID_MAP_COLUMNS: [&str; 3] = ["IdType", "IdIndex", "IdBlob"];
+const APP_RESOURCE_USAGE_COLUMNS: [&str; 10] = [
+    "TimeStamp",
+    "AppId",
+    "UserId",
+    "ForegroundCycleTime",
+    "BackgroundCycleTime",
+    "FaceTime",
+    "ForegroundBytesRead",
+    "ForegroundBytesWritten",
+    "BackgroundBytesRead",
+    "BackgroundBytesWritten",
+];
+const NETWORK_USAGE_COLUMNS: [&str; 7] = ["TimeStamp", "AppId", "UserId", "InterfaceLuid", "L2ProfileId", "BytesSent", "BytesRecvd"];
+
+/// IdType of SruDbIdMapTable entries whose IdBlob is a binary SID (others are UTF-16 names)
+const ID_TYPE_USER_SID: i64 = 3;
+
+/// Days between the OLE Automation date epoch (1899-12-30) and the Unix epoch
+const OLE_DATE_UNIX_EPOCH_DAYS: f64 = 25569.0;
+
+/// Column values of one record, in the order the columns were requested; None when NULL or absent
+pub type EseRow = Vec<Option<Vec<u8>>>;
+
+/// Analyze the SRUM database of the live system
+pub fn collect_srum() -> (Vec<SrumAppUsage>, Vec<SrumNetworkUsage>, Vec<AuditEntry>) {
+    collect_srum_from(Path::new(SRUM_DATABASE))
+}
+
+/// Analyze the SRUM database of a mounted Windows volume
+pub fn collect_srum_offline(root: &OfflineRoot) -> (Vec<SrumAppUsage>, Vec<SrumNetworkUsage>, Vec<AuditEntry>) {
+    collect_srum_from(&root.resolve(SRUM_DATABASE))
+}
+
+/// Analyze a SRUDB.dat file; transaction logs are taken from the same directory
+pub fn collect_srum_from(database_path: &Path) -> (Vec<SrumAppUsage>, Vec<SrumNetworkUsage>, Vec<AuditEntry>) {
+    let mut app_usage = Vec::new();
+    let mut network_usage = Vec::new();
+    let mut audit_log = Vec::new();
+
+    let start_time = std::time::Instant::now();
+
+    audit_log.push(AuditEntry::new(
+        COMPONENT,
+        "INFO",
+        "start_collection",
+        format!("Starting SRUM analysis of {}", database_path.display()),
+        "started",
+    ));
+
+    if !database_path.exists() {
+        audit_log.push(AuditEntry::new(
+            COMPONENT,
+            "WARN",
+            "database_check",
+            format!("SRUM database not found: {}", database_path.display()),
+            "not_found",
+        ));
+        return (app_usage, network_usage, audit_log);
+    }
+
+    read_srum_database(database_path, &mut app_usage, &mut network_usage, &mut audit_log);
+
+    let mut complete = AuditEntry::new(
+        COMPONENT,
+        "INFO",
+        "complete_collection",
+        format!("Collected {} application usage and {} network usage records", app_usage.len(), network_usage.len()),
+        "success",
+    );
+    complete.duration_ms = Some(start_time.elapsed().as_millis() as u64);
+    audit_log.push(complete);
+
+    (app_usage, network_usage, audit_log)
+}
+
+#[cfg(windows)]
+fn read_srum_database(
+    database_path: &Path,
+    app_usage: &mut Vec<SrumAppUsage>,
+    network_usage: &mut Vec<SrumNetworkUsage>,
+    audit_log: &mut Vec<AuditEntry>,
+) {
+    let staging_dir = match tempfile::tempdir() {
+        Ok(dir) => dir,
+        Err(e) => {
+            audit_log.push(AuditEntry::new(COMPONENT, "ERROR", "stage_database", format!("Failed to create a staging directory: {}", e), "error"));
+            return;
+        }
+    };
+
+    let staged_database = match stage_database(database_path, staging_dir.path()) {
+        Ok(path) => path,
+        Err(e) => {
+            audit_log.push(AuditEntry::new(
+                COMPONENT,
+                "ERROR",
+                "stage_database",
+                format!("Failed to copy {}: {} (the database is locked on live systems; use --vss)", database_path.display(), e),
+                "error",
+            ));
+            return;
+        }
+    };
+
+    // Replay the transaction logs into the copy so that it can be attached
+    if let Err(e) = windows_api::recover(staging_dir.path()) {
+        audit_log.push(AuditEntry::new(COMPONENT, "WARN", "recover_database", e, "error"));
+    }
+
+    let page_size = match database_page_size(&staged_database) {
+        Ok(page_size) => page_size,
+        Err(e) => {
+            audit_log.push(AuditEntry::new(COMPONENT, "ERROR", "parse_database", format!("Failed to read the ESE header: {}", e), "error"));
+            return;
+        }
+    };
+
+    let database = match windows_api::EseDatabase::open(&staged_database, page_size) {
+        Ok(database) => database,
+        Err(e) => {
+            audit_log.push(AuditEntry::new(
+                COMPONENT,
+                "ERROR",
+                "parse_database",
+                format!("Failed to open the SRUM database copy of {}: {}", database_path.display(), e),
+                "error",
+            ));
+            return;
+        }
+    };
+
+    let ids = match database.read_table(ID_MAP_TABLE, &ID_MAP_COLUMNS) {
+        Ok(rows) => id_map(&rows),
+        Err(e) => {
+            audit_log.push(AuditEntry::new(COMPONENT, "WARN", "parse_table", format!("Failed to read {}: {}", ID_MAP_TABLE, e), "error"));
+            HashMap::new()
+        }
+    };
+
+    match database.read_table(APP_RESOURCE_USAGE_TABLE, &APP_RESOURCE_USAGE_COLUMNS) {
+        Ok(rows) => app_usage.extend(rows.iter().map(|row| app_usage_from_row(row, &ids))),
+        Err(e) => audit_log.push(AuditEntry::new(
+            COMPONENT,
+            "ERROR",
+            "parse_table",
+            format!("Failed to read the application resource usage table: {}", e),
+            "error",
+        )),
+    }
+
+    match database.read_table(NETWORK_USAGE_TABLE, &NETWORK_USAGE_COLUMNS) {
+        Ok(rows) => network_usage.extend(rows.iter().map(|row| network_usage_from_row(row, &ids))),
+        Err(e) => audit_log.push(AuditEntry::new(COMPONENT, "ERROR", "parse_table", format!("Failed to read the network usage table: {}", e), "error")),
+    }
+}
+
+#[cfg(not(windows))]
+fn read_srum_database(
+    database_path: &Path,
+    _app_usage: &mut Vec<SrumAppUsage>,
+    _network_usage: &mut Vec<SrumNetworkUsage>,
+    audit_log: &mut Vec<AuditEntry>,
+) {
+    audit_log.push(AuditEntry::new(
+        COMPONENT,
+        "WARN",
+        "parse_database",
+        format!("Cannot open {}: ESE databases are read through esent.dll, which requires Windows", database_path.display()),
+        "unsupported",
+    ));
+}
+
+/// Copy SRUDB.dat, its checkpoint and transaction logs into `staging_dir`
+#[cfg(windows)]
+fn stage_database(database_path: &Path, staging_dir: &Path) -> std::io::Result<PathBuf> {
+    let staged_database = staging_dir.join("SRUDB.dat");
+    vss::copy_with_fallback(database_path, &staged_database)?;
+
+    if let Some(sru_dir) = database_path.parent() {
+        for entry in footprint::read_dir(sru_dir)?.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if is_transaction_file(&name) {
+                // A missing log only loses the most recent records
+                let _ = vss::copy_with_fallback(&entry.path(), &staging_dir.join(&name));
+            }
+        }
+    }
+
+    Ok(staged_database)
+}
+
+/// Checkpoint, transaction log and reserved log files of the "SRU" log stream
+fn is_transaction_file(name: &str) -> bool {
+    let lower = name.to_lowercase();
+    lower.starts_with("sru") && [".chk", ".log", ".jrs"].iter().any(|extension| lower.ends_with(extension))
+}
+
+/// Page size from the ESE database header (0 in databases written before Vista, which use 4 KB)
+fn database_page_size(database_path: &Path) -> std::io::Result<u32> {
+    use std::io::Read;
+
+    let mut header = [0u8; 240];
+    footprint::open(database_path)?.read_exact(&mut header)?;
+    Ok(page_size_from_header(&header))
+}
+
+fn page_size_from_header(header: &[u8; 240]) -> u32 {
+    match u32::from_le_bytes([header[236], header[237], header[238], header[239]]) {
+        0 => 4096,
+        page_size => page_size,
+    }
+}
+
+/// AppId/UserId → application name or user SID
+fn id_map(rows: &[EseRow]) -> HashMap<i64, String> {
+    rows.iter()
+        .filter_map(|row| {
+            let id_type = int_value(row.first()?.as_deref())?;
+            let index = int_value(row.get(1)?.as_deref())?;
+            let blob = row.get(2)?.as_deref()?;
+            let value = if id_type == ID_TYPE_USER_SID { sid_to_string(blob)? } else { utf16_string(blob) };
+            Some((index, value))
+        })
+        .collect()
+}
+
+fn app_usage_from_row(row: &[Option<Vec<u8>>], ids: &HashMap<i64, String>) -> SrumAppUsage {
+    let column = |index: usize| row.get(index).and_then(|value| value.as_deref());
+    let counter = |index: usize| int_value(column(index)).map_or(0, |value| value.max(0) as u64);
+
+    SrumAppUsage {
+        timestamp: column(0).map(ole_date_to_rfc3339).unwrap_or_default(),
+        application: resolve_id(ids, column(1)),
+        user_sid: resolve_id(ids, column(2)),
+        foreground_cycle_time: counter(3),
+        background_cycle_time: counter(4),
+        face_time: counter(5),
+        foreground_bytes_read: counter(6),
+        foreground_bytes_written: counter(7),
+        background_bytes_read: counter(8),
+        background_bytes_written: counter(9),
+    }
+}
+
+fn network_usage_from_row(row: &[Option<Vec<u8>>], ids: &HashMap<i64, String>) -> SrumNetworkUsage {
+    let column = |index: usize| row.get(index).and_then(|value| value.as_deref());
+    let counter = |index: usize| int_value(column(index)).map_or(0, |value| value.max(0) as u64);
+    let interface_luid = int_value(column(3)).unwrap_or(0) as u64;
+
+    SrumNetworkUsage {
+        timestamp: column(0).map(ole_date_to_rfc3339).unwrap_or_default(),
+        application: resolve_id(ids, column(1)),
+        user_sid: resolve_id(ids, column(2)),
+        interface_luid,
+        interface_type: interface_type(interface_luid).to_string(),
+        l2_profile_id: int_value(column(4)).unwrap_or(0) as u32,
+        bytes_sent: counter(5),
+        bytes_received: counter(6),
+    }
+}
+
+/// Name for an id, or the bare id when SruDbIdMapTable has no entry for it
+fn resolve_id(ids: &HashMap<i64, String>, value: Option<&[u8]>) -> String {
+    match int_value(value) {
+        Some(id) => ids.get(&id).cloned().unwrap_or_else(|| id.to_string()),
+        None => String::new(),
+    }
+}
+
+/// Little-endian signed integer column (Byte, Short, Long or LongLong)
+fn int_value(value: Option<&[u8]>) -> Option<i64> {
+    match value? {
+        [b] => Some(*b as i64), // JET_coltypUnsignedByte
+        &[a, b] => Some(i16::from_le_bytes([a, b]) as i64),
+        &[a, b, c, d] => Some(i32::from_le_bytes([a, b, c, d]) as i64),
+        bytes if bytes.len() == 8 => Some(i64::from_le_bytes(bytes.try_into().ok()?)),
+        _ => None,
+    }
+}
+
+/// JET_coltypDateTime: OLE Automation date (days since 1899-12-30, UTC in SRUM)
+fn ole_date_to_rfc3339(value: &[u8]) -> String {
+    let Ok(bytes) = <[u8; 8]>::try_from(value) else {
+        return String::new();
+    };
+    let days = f64::from_le_bytes(bytes);
+    if !days.is_finite() || days <= 0.0 {
+        return String::new();
+    }
+    let milliseconds = ((days - OLE_DATE_UNIX_EPOCH_DAYS) * 86_400_000.0).round() as i64;
+    chrono::DateTime::from_timestamp_millis(milliseconds)
+        .map(|time| time.to_rfc3339_opts(chrono::SecondsFormat::Secs, true))
+        .unwrap_or_default()
+}
+
+/// Interface type from the IfType in the upper 16 bits of a NET_LUID
+fn interface_type(luid: u64) -> &'static str {
+    match luid >> 48 {
+        6 => "ethernet",
+        23 => "ppp",
+        24 => "loopback",
+        71 => "wifi",
+        131 => "tunnel",
+        243 | 244 => "mobile_broadband",
+        _ => "other",
+    }
+}
+
+#[cfg(windows)]
+mod windows_api {
+    use super::EseRow;
+    use std::ffi::CString;
+    use std::path::Path;
+    use crate::logger::run_command;
+    use std::process::Command;
+    use windows::Win32::Storage::Jet::{
+        JetAttachDatabase2A, JetBeginSessionA, JetCloseDatabase, JetCloseTable, JetCreateInstanceA, JetDetachDatabaseA, JetEndSession,
+        JetGetTableColumnInfoA, JetInit, JetMove, JetOpenDatabaseA, JetOpenTableA, JetRetrieveColumn, JetSetSystemParameterA, JetTerm,
+        JET_COLUMNDEF, JET_MoveFirst, JET_bitDbReadOnly, JET_bitTableReadOnly, JET_errNoCurrentRecord, JET_paramBaseName,
+        JET_paramDatabasePageSize, JET_paramLogFilePath, JET_paramNoInformationEvent, JET_paramRecovery, JET_paramSystemPath,
+        JET_paramTempPath, JET_wrnBufferTruncated, JET_wrnColumnNull,
+    };
+    use windows::Win32::Storage::StructuredStorage::{JET_API_PTR, JET_INSTANCE, JET_SESID, JET_TABLEID};
+
+    const JET_MOVE_NEXT: i32 = 1;
+    const JET_COL_INFO: u32 = 0;
+
+    /// Replay the SRU transaction logs in `directory` into the database copy there
+    pub fn recover(directory: &Path) -> Result<(), String> {
+        let directory_argument = directory.display().to_string();
+        let output = run_command("srum", Command::new("esentutl")
+            .args([
+                "/r",
+                "sru",
+                &format!("/l{}", directory_argument),
+                &format!("/s{}", directory_argument),
+                &format!("/d{}", directory_argument),
+                "/i",
+                "/o",
+            ])
+            .current_dir(directory))
+            .map_err(|e| format!("Failed to run esentutl: {}", e))?;
+        if output.status.success() {
+            Ok(())
+        } else {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            Err(format!("esentutl recovery failed ({}): {}", output.status, stdout.trim().replace(['\r', '\n'], " ")))
+        }
+    }
+
+    fn check(error: i32, operation: &str) -> Result<(), String> {
+        if error < 0 {
+            Err(format!("{} failed with JET error {}", operation, error))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn c_string(value: &str) -> Result<CString, String> {
+        CString::new(value).map_err(|_| format!("invalid name {}", value))
+    }
+
+    /// Read-only ESE database in a private esent instance
+    pub struct EseDatabase {
+        instance: JET_INSTANCE,
+        session: JET_SESID,
+        database_id: u32,
+        path: CString,
+    }
+
+    impl EseDatabase {
+        pub fn open(path: &Path, page_size: u32) -> Result<Self, String> {
+            let directory = format!("{}\\", path.parent().unwrap_or(path).display());
+            let directory = c_string(&directory)?;
+            let path = c_string(&path.display().to_string())?;
+            let instance_name = c_string(&format!("triageir-srum-{}", std::process::id()))?;
+
+            let mut instance = JET_INSTANCE::default();
+            unsafe {
+                check(JetCreateInstanceA(&mut instance, Some(instance_name.as_ptr() as *const i8)), "JetCreateInstance")?;
+                let mut database = EseDatabase { instance, session: JET_SESID::default(), database_id: 0, path };
+
+                for parameter in [JET_paramSystemPath, JET_paramTempPath, JET_paramLogFilePath] {
+                    database.set_string_parameter(parameter, &directory)?;
+                }
+                database.set_string_parameter(JET_paramBaseName, &c_string("SRU")?)?;
+                database.set_string_parameter(JET_paramRecovery, &c_string("Off")?)?;
+                database.set_number_parameter(JET_paramDatabasePageSize, page_size as usize)?;
+                database.set_number_parameter(JET_paramNoInformationEvent, 1)?;
+
+                check(JetInit(Some(&mut database.instance as *mut _)), "JetInit")?;
+                let empty = c_string("")?;
+                check(
+                    JetBeginSessionA(database.instance, &mut database.session, Some(empty.as_ptr() as *const i8), Some(empty.as_ptr() as *const i8)),
+                    "JetBeginSession",
+                )?;
+                check(JetAttachDatabase2A(database.session, database.path.as_ptr() as *const i8, 0, JET_bitDbReadOnly), "JetAttachDatabase")?;
+                check(
+                    JetOpenDatabaseA(database.session, database.path.as_ptr() as *const i8, None, &mut database.database_id, JET_bitDbReadOnly),
+                    "JetOpenDatabase",
+                )?;
+                Ok(database)
+            }
+        }
+
+        unsafe fn set_string_parameter(&mut self, parameter: u32, value: &CString) -> Result<(), String> {
+            check(
+                JetSetSystemParameterA(Some(&mut self.instance as *mut _), JET_SESID::default(), parameter, JET_API_PTR(0), Some(value.as_ptr() as *const i8)),
+                "JetSetSystemParameter",
+            )
+        }
+
+        unsafe fn set_number_parameter(&mut self, parameter: u32, value: usize) -> Result<(), String> {
+            check(JetSetSystemParameterA(Some(&mut self.instance as *mut _), JET_SESID::default(), parameter, JET_API_PTR(value), None), "JetSetSystemParameter")
+        }
+
+        /// All records of `table`; columns missing from the table (older Windows versions) are None
+        pub fn read_table(&self, table: &str, columns: &[&str]) -> Result<Vec<EseRow>, String> {
+            let table_name = c_string(table)?;
+            let mut table_id = JET_TABLEID::default();
+            unsafe {
+                check(
+                    JetOpenTableA(self.session, self.database_id, table_name.as_ptr() as *const i8, None, 0, JET_bitTableReadOnly, &mut table_id),
+                    "JetOpenTable",
+                )?;
+                let result = self.read_records(table_id, columns);
+                JetCloseTable(self.session, table_id);
+                result
+            }
+        }
+
+        unsafe fn read_records(&self, table_id: JET_TABLEID, columns: &[&str]) -> Result<Vec<EseRow>, String> {
+            let column_ids: Vec<Option<u32>> = columns.iter().map(|column| self.column_id(table_id, column)).collect();
+
+            let mut rows = Vec::new();
+            let mut error = JetMove(self.session, table_id, JET_MoveFirst as i32, 0);
+            while error >= 0 {
+                rows.push(column_ids.iter().map(|id| id.and_then(|id| self.retrieve_column(table_id, id))).collect());
+                error = JetMove(self.session, table_id, JET_MOVE_NEXT, 0);
+            }
+            if error != JET_errNoCurrentRecord {
+                check(error, "JetMove")?;
+            }
+            Ok(rows)
+        }
+
+        unsafe fn column_id(&self, table_id: JET_TABLEID, column: &str) -> Option<u32> {
+            let column_name = c_string(column).ok()?;
+            let mut definition = JET_COLUMNDEF { cbStruct: std::mem::size_of::<JET_COLUMNDEF>() as u32, ..Default::default() };
+            let error = JetGetTableColumnInfoA(
+                self.session,
+                table_id,
+                Some(column_name.as_ptr() as *const i8),
+                &mut definition as *mut JET_COLUMNDEF as *mut _,
+                std::mem::size_of::<JET_COLUMNDEF>() as u32,
+                JET_COL_INFO,
+            );
+            (error >= 0).then_some(definition.columnid)
+        }
+
+        unsafe fn retrieve_column(&self, table_id: JET_TABLEID, column_id: u32) -> Option<Vec<u8>> {
+            let mut buffer = vec![0u8; 64];
+            let mut actual = 0u32;
+            let mut error = JetRetrieveColumn(self.session, table_id, column_id, Some(buffer.as_mut_ptr() as *mut _), buffer.len() as u32, Some(&mut actual as *mut u32), 0, None);
+            if error == JET_wrnBufferTruncated as i32 {
+                buffer.resize(actual as usize, 0);
+                error = JetRetrieveColumn(self.session, table_id, column_id, Some(buffer.as_mut_ptr() as *mut _), buffer.len() as u32, Some(&mut actual as *mut u32), 0, None);
+            }
+            if error < 0 || error == JET_wrnColumnNull as i32 {
+                return None;
+            }
+            buffer.truncate(actual as usize);
+            Some(buffer)
+        }
+    }
+
+    impl Drop for EseDatabase {
+        fn drop(&mut self) {
+            unsafe {
+                if self.database_id != 0 {
+                    JetCloseDatabase(self.session, self.database_id, 0);
+                }
+                if !self.session.is_invalid() {
+                    JetDetachDatabaseA(self.session, Some(self.path.as_ptr() as *const i8));
+                    JetEndSession(self.session, 0);
+                }
+                JetTerm(self.instance);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sid_blob() -> Vec<u8> {
+        // S-1-5-21-1004336348-1177238915-682003330-1001
+        let mut blob = vec![1, 5, 0, 0, 0, 0, 0, 5];
+        for sub_authority in [21u32, 1004336348, 1177238915, 682003330, 1001] {
+            blob.extend_from_slice(&sub_authority.to_le_bytes());
+        }
+        blob
+    }
+
+    fn utf16_blob(value: &str) -> Vec<u8> {
+        value.encode_utf16().chain([0]).flat_map(u16::to_le_bytes).collect()
+    }
+
+    fn id_row(id_type: u8, index: i32, blob: Vec<u8>) -> EseRow {
+        vec![Some(vec![id_type]), Some(index.to_le_bytes().to_vec()), Some(blob)]
+    }
+
+    #[test]
+    fn test_decode_values() {
+        assert_eq!(sid_to_string(&sid_blob()).unwrap(), "S-1-5-21-1004336348-1177238915-682003330-1001");
+        assert!(sid_to_string(&[1, 2, 0, 0]).is_none());
+        assert_eq!(utf16_string(&utf16_blob("\\Device\\HarddiskVolume3\\Tools\\rclone.exe")), "\\Device\\HarddiskVolume3\\Tools\\rclone.exe");
+
+        // 2024-03-01 09:00:00 UTC
+        assert_eq!(ole_date_to_rfc3339(&45352.375f64.to_le_bytes()), "2024-03-01T09:00:00Z");
+        assert_eq!(ole_date_to_rfc3339(&0f64.to_le_bytes()), "");
+
+        assert_eq!(int_value(Some(&[3])), Some(3));
+        assert_eq!(int_value(Some(&(-2i32).to_le_bytes())), Some(-2));
+        assert_eq!(int_value(Some(&5_000_000_000i64.to_le_bytes())), Some(5_000_000_000));
+        assert_eq!(int_value(None), None);
+
+        assert_eq!(interface_type(71 << 48 | 1), "wifi");
+        assert_eq!(interface_type(6 << 48), "ethernet");
+
+        let mut header = [0u8; 240];
+        assert_eq!(page_size_from_header(&header), 4096);
+        header[236..240].copy_from_slice(&32768u32.to_le_bytes());
+        assert_eq!(page_size_from_header(&header), 32768);
+
+        assert!(is_transaction_file("SRU00A1F.log"));
+        assert!(is_transaction_file("SRU.chk"));
+        assert!(!is_transaction_file("SRUDB.dat"));
+    }
+
+    #[test]
+    fn test_records_from_rows() {
+        let ids = id_map(&[
+            id_row(0, 412, utf16_blob("\\Device\\HarddiskVolume3\\Tools\\rclone.exe")),
+            id_row(3, 7, sid_blob()),
+        ]);
+
+        let network_row: EseRow = vec![
+            Some(45352.375f64.to_le_bytes().to_vec()),
+            Some(412i32.to_le_bytes().to_vec()),
+            Some(7i32.to_le_bytes().to_vec()),
+            Some((71u64 << 48 | 0x8000).to_le_bytes().to_vec()),
+            Some(2i32.to_le_bytes().to_vec()),
+            Some(7_340_032_000i64.to_le_bytes().to_vec()),
+            Some(1_048_576i64.to_le_bytes().to_vec()),
+        ];
+        let network = network_usage_from_row(&network_row, &ids);
+        assert_eq!(network.timestamp, "2024-03-01T09:00:00Z");
+        assert_eq!(network.application, "\\Device\\HarddiskVolume3\\Tools\\rclone.exe");
+        assert_eq!(network.user_sid, "S-1-5-21-1004336348-1177238915-682003330-1001");
+        assert_eq!(network.interface_type, "wifi");
+        assert_eq!(network.l2_profile_id, 2);
+        assert_eq!((network.bytes_sent, network.bytes_received), (7_340_032_000, 1_048_576));
+
+        // FaceTime is absent before Windows 10; unknown ids are reported as numbers
+        let mut app_row: EseRow = vec![None; APP_RESOURCE_USAGE_COLUMNS.len()];
+        app_row[0] = Some(45352.375f64.to_le_bytes().to_vec());
+        app_row[1] = Some(999i32.to_le_bytes().to_vec());
+        app_row[3] = Some(123_456i64.to_le_bytes().to_vec());
+        app_row[7] = Some(4096i64.to_le_bytes().to_vec());
+        let app = app_usage_from_row(&app_row, &ids);
+        assert_eq!(app.application, "999");
+        assert_eq!(app.user_sid, "");
+        assert_eq!(app.foreground_cycle_time, 123_456);
+        assert_eq!(app.face_time, 0);
+        assert_eq!(app.foreground_bytes_written, 4096);
+    }
+
+    #[test]
+    fn test_missing_database() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let (app_usage, network_usage, audit_log) = collect_srum_from(&temp_dir.path().join("SRUDB.dat"));
+        assert!(app_usage.is_empty() && network_usage.is_empty());
+        assert!(audit_log.iter().any(|entry| entry.action == "database_check" && entry.result == "not_found"));
+    }
+}
@@ -0,0 +1,341 @@
+//! Streaming result writer
+//! The bulk sections of a scan are handed over as soon as their collector
+//! finishes and are released once queued. They go through a bounded channel to
+//! a serializer thread that appends them to a spool file, one record per line;
+//! a full channel blocks the collector, so no more than CHANNEL_CAPACITY records
+//! are in flight. When the scan completes, the result document is written from
+//! the sections still in memory with the spooled ones copied in at their place,
+//! line by line, so the complete results are never held in memory at once.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, SyncSender};
+use std::thread::JoinHandle;
+
+/// Records queued for the serializer thread before `send_records` blocks
+pub const CHANNEL_CAPACITY: usize = 256;
+
+struct Record {
+    section: String,
+    data: Value,
+}
+
+pub struct StreamWriter {
+    sender: Option<SyncSender<Record>>,
+    serializer: Option<JoinHandle<io::Result<Spool>>>,
+}
+
+impl StreamWriter {
+    /// Start the serializer thread spooling to `path`, which is removed with the returned `Spool`
+    pub fn create(path: &Path, capacity: usize) -> io::Result<Self> {
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = File::create(path)?;
+        let spool_path = path.to_path_buf();
+        let (sender, receiver) = mpsc::sync_channel::<Record>(capacity);
+        let serializer = std::thread::Builder::new().name("stream-writer".to_string()).spawn(move || {
+            let mut spool = Spool { path: Some(spool_path), sections: BTreeMap::new() };
+            let mut output = CountingWriter { inner: BufWriter::new(file), written: 0 };
+            let mut open_section: Option<String> = None;
+            for record in receiver {
+                if open_section.as_deref() != Some(record.section.as_str()) {
+                    if spool.sections.contains_key(&record.section) {
+                        return Err(io::Error::other(format!("section {} was streamed twice", record.section)));
+                    }
+                    spool.sections.insert(record.section.clone(), SpooledSection { start: output.written, end: output.written, count: 0 });
+                    open_section = Some(record.section.clone());
+                }
+                serde_json::to_writer(&mut output, &record.data)?;
+                output.write_all(b"\n")?;
+                if let Some(section) = spool.sections.get_mut(&record.section) {
+                    section.end = output.written;
+                    section.count += 1;
+                }
+            }
+            output.flush()?;
+            Ok(spool)
+        });
+        match serializer {
+            Ok(serializer) => Ok(StreamWriter { sender: Some(sender), serializer: Some(serializer) }),
+            Err(e) => {
+                let _ = std::fs::remove_file(path);
+                Err(e)
+            }
+        }
+    }
+
+    /// Queue the records of one section, dropping each once it is serialized; blocks while
+    /// the channel is full. `section` is the dotted path of the array in the result document.
+    pub fn send_records<T: Serialize>(&self, section: &str, records: Vec<T>) -> io::Result<usize> {
+        let sender = self.sender.as_ref().ok_or_else(|| io::Error::other("stream writer already finished"))?;
+        let mut sent = 0;
+        for record in records {
+            let data = serde_json::to_value(record)?;
+            sender
+                .send(Record { section: section.to_string(), data })
+                .map_err(|_| io::Error::other("the serializer thread stopped"))?;
+            sent += 1;
+        }
+        Ok(sent)
+    }
+
+    /// Wait for the serializer and return the spooled sections
+    pub fn finish(mut self) -> io::Result<Spool> {
+        self.sender.take();
+        match self.serializer.take().map(JoinHandle::join) {
+            Some(Ok(spool)) => spool,
+            Some(Err(_)) => Err(io::Error::other("the serializer thread panicked")),
+            None => Err(io::Error::other("stream writer already finished")),
+        }
+    }
+}
+
+impl Drop for StreamWriter {
+    fn drop(&mut self) {
+        self.sender.take();
+        if let Some(serializer) = self.serializer.take() {
+            // Dropping the spool removes its file
+            let _ = serializer.join();
+        }
+    }
+}
+
+struct SpooledSection {
+    start: u64,
+    end: u64,
+    count: usize,
+}
+
+/// Sections spooled during a scan; the default spool is empty, for results kept in memory
+#[derive(Default)]
+pub struct Spool {
+    path: Option<PathBuf>,
+    sections: BTreeMap<String, SpooledSection>,
+}
+
+impl Spool {
+    /// Write the result document to `output`: the spooled sections replace their (emptied)
+    /// arrays in `results`. Returns the bytes written.
+    pub fn write_document(&self, results: &Value, output: impl Write) -> io::Result<u64> {
+        for section in self.sections.keys() {
+            if results.pointer(&pointer(section)).is_none() {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, format!("streamed section {} has no place in the results", section)));
+            }
+        }
+        let mut spool = match &self.path {
+            Some(path) if !self.sections.is_empty() => Some(File::open(path)?),
+            _ => None,
+        };
+        let mut output = CountingWriter { inner: BufWriter::new(output), written: 0 };
+        self.write_value(&mut output, &mut spool, "", results, 0)?;
+        output.write_all(b"\n")?;
+        output.flush()?;
+        Ok(output.written)
+    }
+
+    /// Write the result document to `path`, creating parent directories as needed
+    pub fn write_file(&self, results: &Value, path: &Path) -> io::Result<u64> {
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = File::create(path)?;
+        let written = self.write_document(results, &file)?;
+        file.sync_all()?;
+        Ok(written)
+    }
+
+    /// The complete results with the spooled sections read back, for consumers that need
+    /// the whole document in memory
+    pub fn load(&self, results: &Value) -> io::Result<Value> {
+        let mut complete = results.clone();
+        let Some(path) = self.path.as_ref().filter(|_| !self.sections.is_empty()) else {
+            return Ok(complete);
+        };
+        let mut spool = File::open(path)?;
+        for (name, section) in &self.sections {
+            let target = complete
+                .pointer_mut(&pointer(name))
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("streamed section {} has no place in the results", name)))?;
+            let mut records = Vec::with_capacity(section.count);
+            for_each_line(&mut spool, section, |line| {
+                records.push(serde_json::from_str(line)?);
+                Ok(())
+            })?;
+            *target = Value::Array(records);
+        }
+        Ok(complete)
+    }
+
+    /// Objects of the document and of its top-level sections, and objects holding spooled
+    /// sections, are written one key per line; arrays at those levels one element per line
+    fn write_value(&self, output: &mut impl Write, spool: &mut Option<File>, path: &str, value: &Value, depth: usize) -> io::Result<()> {
+        if let (Some(section), Some(file)) = (self.sections.get(path), spool.as_mut()) {
+            if section.count == 0 {
+                return output.write_all(b"[]");
+            }
+            output.write_all(b"[\n")?;
+            let mut first = true;
+            for_each_line(file, section, |line| {
+                if !first {
+                    output.write_all(b",\n")?;
+                }
+                first = false;
+                output.write_all(line.as_bytes())
+            })?;
+            return output.write_all(b"\n]");
+        }
+
+        match value {
+            Value::Object(map) if !map.is_empty() && (depth < 2 || self.holds_sections(path)) => {
+                output.write_all(b"{\n")?;
+                for (index, (key, item)) in map.iter().enumerate() {
+                    if index > 0 {
+                        output.write_all(b",\n")?;
+                    }
+                    serde_json::to_writer(&mut *output, key)?;
+                    output.write_all(b": ")?;
+                    let item_path = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+                    self.write_value(output, spool, &item_path, item, depth + 1)?;
+                }
+                output.write_all(b"\n}")
+            }
+            Value::Array(items) if !items.is_empty() && depth <= 2 => {
+                output.write_all(b"[\n")?;
+                for (index, item) in items.iter().enumerate() {
+                    if index > 0 {
+                        output.write_all(b",\n")?;
+                    }
+                    serde_json::to_writer(&mut *output, item)?;
+                }
+                output.write_all(b"\n]")
+            }
+            other => serde_json::to_writer(&mut *output, other).map_err(io::Error::from),
+        }
+    }
+
+    fn holds_sections(&self, path: &str) -> bool {
+        let prefix = format!("{}.", path);
+        self.sections.keys().any(|section| section.starts_with(&prefix))
+    }
+}
+
+impl Drop for Spool {
+    fn drop(&mut self) {
+        if let Some(path) = &self.path {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+/// JSON pointer of a dotted section path
+fn pointer(section: &str) -> String {
+    section.split('.').map(|key| format!("/{}", key)).collect()
+}
+
+/// Call `f` with each spooled record of `section`, without its line break
+fn for_each_line(file: &mut File, section: &SpooledSection, mut f: impl FnMut(&str) -> io::Result<()>) -> io::Result<()> {
+    file.seek(SeekFrom::Start(section.start))?;
+    let mut reader = BufReader::new(file.take(section.end - section.start));
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 0 {
+        f(line.trim_end_matches('\n'))?;
+        line.clear();
+    }
+    Ok(())
+}
+
+struct CountingWriter<W: Write> {
+    inner: W,
+    written: u64,
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.written += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_streamed_document_round_trips() {
+        let scan = json!({
+            "artifacts": {
+                "network_connections": [],
+                "running_processes": [{ "pid": 4, "name": "System" }, { "pid": 88, "name": "svchost.exe" }],
+                "system_info": { "uptime_hours": 5.5 }
+            },
+            "collection_log": [{ "level": "INFO", "message": "done" }],
+            "collector_stats": [],
+            "scan_metadata": { "scan_id": "1234", "total_artifacts": 2 }
+        });
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("out").join("scan.json");
+        let written = Spool::default().write_file(&scan, &path).unwrap();
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(written, text.len() as u64);
+        assert_eq!(serde_json::from_str::<Value>(&text).unwrap(), scan);
+        assert!(text.contains("\n{\"name\":\"svchost.exe\",\"pid\":88}\n]"));
+
+        for document in [json!({}), json!({ "collector_stats": [], "scan_metadata": {} })] {
+            let path = temp_dir.path().join("small.json");
+            Spool::default().write_file(&document, &path).unwrap();
+            assert_eq!(serde_json::from_str::<Value>(&std::fs::read_to_string(&path).unwrap()).unwrap(), document);
+        }
+    }
+
+    #[test]
+    fn test_spooled_sections_are_spliced_in() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let spool_path = temp_dir.path().join("scan.spool");
+        let writer = StreamWriter::create(&spool_path, 2).unwrap();
+        let prefetch: Vec<Value> = (0..5).map(|i| json!({ "filename": format!("APP{}.EXE-1234.pf", i), "run_count": i })).collect();
+        assert_eq!(writer.send_records("artifacts.execution_evidence.prefetch_files", prefetch.clone()).unwrap(), 5);
+        assert_eq!(writer.send_records("artifacts.file_sweep", Vec::<Value>::new()).unwrap(), 0);
+        assert_eq!(writer.send_records("artifacts.usn_journal", vec![json!({ "reason": "FILE_CREATE" })]).unwrap(), 1);
+        let spool = writer.finish().unwrap();
+        assert_eq!(spool.sections["artifacts.execution_evidence.prefetch_files"].count, 5);
+        assert!(!spool.sections.contains_key("artifacts.file_sweep"));
+
+        // The collectors left their sections empty in the in-memory results
+        let results = json!({
+            "artifacts": {
+                "execution_evidence": { "prefetch_files": [], "prefetch_status": { "state": "enabled" } },
+                "file_sweep": [],
+                "usn_journal": []
+            },
+            "scan_metadata": { "scan_id": "1234" }
+        });
+        let mut expected = results.clone();
+        expected["artifacts"]["execution_evidence"]["prefetch_files"] = json!(prefetch);
+        expected["artifacts"]["usn_journal"] = json!([{ "reason": "FILE_CREATE" }]);
+
+        let mut document = Vec::new();
+        let written = spool.write_document(&results, &mut document).unwrap();
+        assert_eq!(written, document.len() as u64);
+        assert_eq!(serde_json::from_slice::<Value>(&document).unwrap(), expected);
+        assert!(String::from_utf8(document).unwrap().contains("\n{\"filename\":\"APP4.EXE-1234.pf\",\"run_count\":4}\n]"));
+        assert_eq!(spool.load(&results).unwrap(), expected);
+
+        // A section the results have no place for is an error rather than silently dropped
+        assert!(spool.write_document(&json!({ "artifacts": {} }), Vec::new()).is_err());
+
+        drop(spool);
+        assert!(!spool_path.exists());
+    }
+}
@@ -0,0 +1,273 @@
+//! Executive summary in Markdown
+//! `--report summary.md` condenses a scan into what an incident ticket needs:
+//! the case and host, how much was collected, the highest-scoring suspicious
+//! artifacts with the indicators that raised their score, the high-severity
+//! findings and rule matches, and how complete the collection was (failed and
+//! skipped collectors, errors). The full results stay in the JSON output or the
+//! evidence package; every figure here is derived from them.
+
+use crate::persistence;
+use serde_json::Value;
+use std::fmt::Write;
+
+/// Suspicious artifacts listed when --report-top is not given
+pub const DEFAULT_TOP_ARTIFACTS: usize = 10;
+
+/// Collection log errors quoted in the quality section
+const MAX_QUOTED_ERRORS: usize = 10;
+
+/// Persistence artifact lists scored like the Winlogon entries, with the label and title fields
+const HIJACK_LISTS: [(&str, &str, &str); 4] = [
+    ("winlogon_entries", "Winlogon value", "value_data"),
+    ("image_hijacks", "IFEO hijack", "hijack_executable"),
+    ("appinit_dlls", "AppInit/AppCert DLL", "value_name"),
+    ("path_hijacks", "Search path hijack", "directory"),
+];
+
+/// A flagged artifact with its score and explanation
+struct SuspiciousArtifact {
+    score: u64,
+    kind: String,
+    title: String,
+    reasons: Vec<String>,
+}
+
+/// Render the summary of a scan; `top` limits the suspicious artifact table
+pub fn render_markdown(scan: &Value, case_id: &str, top: usize) -> String {
+    let metadata = &scan["scan_metadata"];
+    let system_info = &scan["artifacts"]["system_info"];
+    let findings = array(&scan["findings"]);
+    let detections = array(&scan["detections"]);
+    let suspicious = suspicious_artifacts(scan);
+    let mut md = String::new();
+
+    let _ = writeln!(md, "# Triage summary: {}\n", inline(text(&metadata["hostname"])));
+    let _ = writeln!(
+        md,
+        "**Assessment:** {} suspicious artifacts, {} high-severity findings, {} detection rules matched.\n",
+        suspicious.len(),
+        findings.len(),
+        detections.len()
+    );
+
+    md.push_str("## Case\n\n| | |\n|---|---|\n");
+    let source = match text(&metadata["offline_root"]) {
+        "" => "live system".to_string(),
+        root => format!("offline volume {}", root),
+    };
+    let rows = [
+        ("Case ID", case_id.to_string()),
+        ("Scan ID", text(&metadata["scan_id"]).to_string()),
+        ("Collected (UTC)", text(&metadata["scan_start_utc"]).to_string()),
+        ("Duration", format!("{:.1} s", metadata["scan_duration_ms"].as_u64().unwrap_or(0) as f64 / 1000.0)),
+        ("Source", source),
+        ("Time window", time_window(metadata)),
+        ("Collector", format!("TriageIR CLI {}", text(&metadata["cli_version"]))),
+    ];
+    for (label, value) in rows {
+        let _ = writeln!(md, "| {} | {} |", label, cell(&value));
+    }
+
+    md.push_str("\n## Host\n\n| | |\n|---|---|\n");
+    let users: Vec<&str> = array(&system_info["logged_on_users"]).iter().filter_map(|user| user["username"].as_str()).collect();
+    let rows = [
+        ("Hostname", text(&metadata["hostname"]).to_string()),
+        ("Operating system", text(&metadata["os_version"]).to_string()),
+        ("Role", text(&metadata["environment"]["environment_type"]).to_string()),
+        ("Architecture", text(&system_info["architecture"]).to_string()),
+        ("Last boot", text(&system_info["last_boot_time"]).to_string()),
+        ("Logged-on users", users.join(", ")),
+    ];
+    for (label, value) in rows.iter().filter(|(_, value)| !value.is_empty()) {
+        let _ = writeln!(md, "| {} | {} |", label, cell(value));
+    }
+
+    md.push_str("\n## Artifacts collected\n\n");
+    let _ = writeln!(md, "{} artifacts in total.\n", metadata["total_artifacts"].as_u64().unwrap_or(0));
+    md.push_str("| Collector | Items |\n|---|---:|\n");
+    for stats in array(&scan["collector_stats"]).iter().filter(|stats| stats["items_collected"].as_u64().unwrap_or(0) > 0) {
+        let _ = writeln!(md, "| {} | {} |", cell(text(&stats["collector"])), stats["items_collected"].as_u64().unwrap_or(0));
+    }
+
+    let _ = writeln!(md, "\n## Top suspicious artifacts\n");
+    if suspicious.is_empty() {
+        md.push_str("No artifact reached its suspicion threshold.\n");
+    } else {
+        md.push_str("| Score | Artifact | Why |\n|---:|---|---|\n");
+        for artifact in suspicious.iter().take(top) {
+            let reasons: Vec<String> = artifact.reasons.iter().map(|reason| reason.replace('_', " ")).collect();
+            let _ = writeln!(md, "| {} | {}: {} | {} |", artifact.score, cell(&artifact.kind), cell(&artifact.title), cell(&reasons.join("; ")));
+        }
+        if suspicious.len() > top {
+            let _ = writeln!(md, "\n{} more in the full results.", suspicious.len() - top);
+        }
+    }
+
+    if !findings.is_empty() {
+        md.push_str("\n## High-severity findings\n\n| ID | Severity | Description |\n|---|---|---|\n");
+        for finding in findings {
+            let _ = writeln!(md, "| {} | {} | {} |", cell(text(&finding["finding_id"])), cell(text(&finding["severity"])), cell(text(&finding["description"])));
+        }
+    }
+
+    if !detections.is_empty() {
+        md.push_str("\n## Detection rule matches\n\n| Rule | Severity | Matches | ATT&CK |\n|---|---|---:|---|\n");
+        for detection in detections {
+            let techniques: Vec<&str> = array(&detection["mitre_techniques"]).iter().filter_map(Value::as_str).collect();
+            let _ = writeln!(
+                md,
+                "| {} | {} | {} | {} |",
+                cell(text(&detection["rule_name"])),
+                cell(text(&detection["severity"])),
+                detection["match_count"].as_u64().unwrap_or(0),
+                cell(&techniques.join(", "))
+            );
+        }
+    }
+
+    md.push_str("\n## Collection quality\n\n");
+    let summary = &metadata["collection_summary"];
+    let _ = writeln!(
+        md,
+        "{} log entries, {} errors, {} warnings (success rate {:.1}%).",
+        summary["total_logs"].as_u64().unwrap_or(0),
+        summary["error_count"].as_u64().unwrap_or(0),
+        summary["warning_count"].as_u64().unwrap_or(0),
+        summary["success_rate"].as_f64().unwrap_or(0.0)
+    );
+    let incomplete: Vec<String> = array(&scan["collector_stats"])
+        .iter()
+        .filter_map(|stats| {
+            let collector = text(&stats["collector"]);
+            match (text(&stats["status"]), stats["errors"].as_u64().unwrap_or(0)) {
+                ("skipped", _) => Some(format!("- {}: skipped ({})", inline(collector), inline(text(&stats["skip_reason"])))),
+                (_, errors) if errors > 0 => Some(format!("- {}: {} errors", inline(collector), errors)),
+                _ => None,
+            }
+        })
+        .collect();
+    if !incomplete.is_empty() {
+        md.push_str("\nIncomplete collectors:\n\n");
+        md.push_str(&incomplete.join("\n"));
+        md.push('\n');
+    }
+    let errors: Vec<&Value> = array(&scan["collection_log"]).iter().filter(|entry| entry["level"] == "ERROR").collect();
+    if !errors.is_empty() {
+        md.push_str("\nErrors:\n\n");
+        for entry in errors.iter().take(MAX_QUOTED_ERRORS) {
+            let _ = writeln!(md, "- {}", inline(text(&entry["message"])));
+        }
+        if errors.len() > MAX_QUOTED_ERRORS {
+            let _ = writeln!(md, "- ... {} more in the collection log", errors.len() - MAX_QUOTED_ERRORS);
+        }
+    }
+    md
+}
+
+/// Artifacts at or above their suspicion threshold, highest score first
+fn suspicious_artifacts(scan: &Value) -> Vec<SuspiciousArtifact> {
+    let artifacts = &scan["artifacts"];
+    let threshold = scan["scan_metadata"]["suspicion_threshold"].as_u64().unwrap_or(u64::MAX);
+    let mut suspicious = Vec::new();
+    let mut push = |score: u64, kind: String, title: String, entry: &Value| {
+        let reasons = array(&entry["suspicion_reasons"]).iter().filter_map(Value::as_str).map(str::to_string).collect();
+        suspicious.push(SuspiciousArtifact { score, kind, title, reasons });
+    };
+
+    for mechanism in array(&artifacts["persistence_mechanisms"]).iter().filter(|m| m["is_suspicious"].as_bool().unwrap_or(false)) {
+        let title = format!("{} ({})", text(&mechanism["name"]), text(&mechanism["command"]));
+        push(mechanism["suspicion_score"].as_u64().unwrap_or(0), text(&mechanism["type"]).to_string(), title, mechanism);
+    }
+    for process in array(&artifacts["running_processes"]) {
+        let score = process["suspicion_score"].as_u64().unwrap_or(0);
+        if score >= threshold {
+            let title = format!("{} (PID {}, {})", text(&process["name"]), process["pid"], text(&process["executable_path"]));
+            push(score, "Process".to_string(), title, process);
+        }
+    }
+    for (list, kind, field) in HIJACK_LISTS {
+        for entry in array(&artifacts["persistence_artifacts"][list]) {
+            let score = entry["suspicion_score"].as_u64().unwrap_or(0);
+            if score >= persistence::HIJACK_SUSPICION_THRESHOLD as u64 {
+                push(score, kind.to_string(), text(&entry[field]).to_string(), entry);
+            }
+        }
+    }
+
+    suspicious.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.title.cmp(&b.title)));
+    suspicious
+}
+
+fn time_window(metadata: &Value) -> String {
+    match (text(&metadata["since"]), text(&metadata["until"])) {
+        ("", "") => "complete".to_string(),
+        ("", until) => format!("until {}", until),
+        (since, "") => format!("since {}", since),
+        (since, until) => format!("{} to {}", since, until),
+    }
+}
+
+/// Table cell text: pipes escaped, one line
+fn cell(value: &str) -> String {
+    inline(value).replace('|', "\\|")
+}
+
+fn inline(value: &str) -> String {
+    value.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn array(value: &Value) -> &[Value] {
+    value.as_array().map(Vec::as_slice).unwrap_or_default()
+}
+
+fn text(value: &Value) -> &str {
+    value.as_str().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_render_markdown() {
+        let scan = json!({
+            "scan_metadata": {
+                "scan_id": "s-1", "hostname": "WKS01", "os_version": "Windows 11", "suspicion_threshold": 40, "total_artifacts": 120,
+                "collection_summary": { "total_logs": 30, "error_count": 1, "warning_count": 2, "success_rate": 90.0 }
+            },
+            "artifacts": {
+                "running_processes": [
+                    { "name": "svch0st.exe", "pid": 4242, "executable_path": "C:\\Users\\Public\\svch0st.exe", "suspicion_score": 70, "suspicion_reasons": ["masquerading_name", "user_writable_directory"] },
+                    { "name": "explorer.exe", "pid": 900, "suspicion_score": 0 }
+                ],
+                "persistence_mechanisms": [
+                    { "type": "Registry Run Key", "name": "Updater", "command": "cmd /c a|b", "is_suspicious": true, "suspicion_score": 85, "suspicion_reasons": ["command_interpreter"] }
+                ],
+                "persistence_artifacts": { "image_hijacks": [{ "hijack_executable": "C:\\x.exe", "suspicion_score": 30 }] }
+            },
+            "collector_stats": [
+                { "collector": "processes", "status": "completed", "items_collected": 80, "errors": 0 },
+                { "collector": "srum", "status": "skipped", "skip_reason": "live-only collector (offline mode)", "items_collected": 0, "errors": 0 },
+                { "collector": "event_logs", "status": "completed", "items_collected": 40, "errors": 1 }
+            ],
+            "collection_log": [{ "level": "ERROR", "message": "Access denied\nto Security log" }]
+        });
+
+        let md = render_markdown(&scan, "CASE-7", 1);
+        assert!(md.starts_with("# Triage summary: WKS01\n\n**Assessment:** 2 suspicious artifacts, 0 high-severity findings, 0 detection rules matched."));
+        assert!(md.contains("| Case ID | CASE-7 |"));
+        assert!(md.contains("| Time window | complete |"));
+        assert!(md.contains("| processes | 80 |"));
+        assert!(!md.contains("| srum |"));
+        assert!(md.contains("| 85 | Registry Run Key: Updater (cmd /c a\\|b) | command interpreter |"));
+        assert!(md.contains("1 more in the full results."));
+        assert!(!md.contains("svch0st"));
+        assert!(md.contains("- srum: skipped (live-only collector (offline mode))"));
+        assert!(md.contains("- event_logs: 1 errors"));
+        assert!(md.contains("- Access denied to Security log"));
+        assert!(!md.contains("## Detection rule matches"));
+
+        assert!(render_markdown(&scan, "CASE-7", 10).contains("| 70 | Process: svch0st.exe (PID 4242, C:\\Users\\Public\\svch0st.exe) | masquerading name; user writable directory |"));
+    }
+}
@@ -0,0 +1,302 @@
+//! Weighted suspicion scoring
+//! Persistence mechanisms (scheduled tasks included) and processes are scored
+//! 0-100 as the capped sum of the weights of the indicators they show, and
+//! keep the names of the contributing indicators, so a script in a writable
+//! directory no longer ranks with an encoded PowerShell download cradle.
+//! `is_suspicious` remains as `score >= threshold` for existing consumers.
+//! The weights and the threshold can be tuned with a TOML file:
+//!
+//! ```toml
+//! threshold = 50
+//! [weights]
+//! user_writable_directory = 30
+//! script_interpreter = 0   # 0 disables an indicator
+//! ```
+
+use crate::forensic_types::ScheduledTask;
+use crate::path_norm;
+use crate::types::{PersistenceMechanism, PersistenceType, Process};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::{Arc, OnceLock, RwLock};
+
+pub const DEFAULT_THRESHOLD: u32 = 40;
+pub const MAX_SCORE: u32 = 100;
+
+/// Every indicator and its default weight
+const DEFAULT_WEIGHTS: [(&str, u32); 24] = [
+    // Command line and path indicators
+    ("temp_directory", 40),
+    ("user_writable_directory", 20),
+    ("script_file", 25),
+    ("script_interpreter", 15),
+    ("proxy_execution", 25),
+    ("encoded_command", 40),
+    ("hidden_window", 20),
+    ("execution_policy_bypass", 15),
+    ("download_cradle", 45),
+    ("invoke_expression", 30),
+    // Scheduled task indicators
+    ("non_microsoft_system_task", 15),
+    ("masquerading_name", 15),
+    ("single_character_name", 15),
+    ("boot_or_logon_trigger", 5),
+    // Process tree and image indicators (prefixes of the process indicators)
+    ("deleted_executable", 60),
+    ("suspicious_parent", 45),
+    ("unexpected_parent", 30),
+    ("ppid_reuse", 25),
+    ("orphaned", 10),
+    // Deep process scan indicators
+    ("unbacked_thread", 60),
+    ("rwx_memory", 30),
+    // Process token indicators
+    ("privileged_token", 35),
+    ("system_token_user_path", 50),
+    ("impersonating_system", 55),
+];
+
+/// Command line fragments (normalized, quotes removed, trailing space) of each command indicator
+const COMMAND_INDICATORS: [(&str, &[&str]); 10] = [
+    ("temp_directory", &["\\temp\\", "\\tmp\\"]),
+    ("user_writable_directory", &["\\appdata\\", "\\users\\public\\", "\\programdata\\", "\\downloads\\", "\\desktop\\", "\\documents\\"]),
+    ("script_file", &[".ps1", ".vbs", ".vbe", ".js ", ".jse", ".wsf", ".hta", ".bat ", ".cmd ", ".jar", ".scr", ".pif"]),
+    ("script_interpreter", &["powershell", "pwsh", "cmd.exe", "wscript", "cscript"]),
+    ("proxy_execution", &["mshta", "rundll32", "regsvr32", "certutil", "bitsadmin", "wmic", "installutil", "regasm", "msbuild"]),
+    ("encoded_command", &[" -enc", " -ec ", "encodedcommand", "frombase64string"]),
+    ("hidden_window", &["-w hidden", "-win hidden", "windowstyle hidden"]),
+    ("execution_policy_bypass", &[" bypass"]),
+    ("download_cradle", &["downloadstring", "downloadfile", "webclient", "invoke-webrequest", "iwr ", "start-bitstransfer", "-urlcache", "/transfer", "curl ", "wget "]),
+    ("invoke_expression", &["invoke-expression", "iex ", "iex(", "|iex"]),
+];
+
+/// Task names imitating system tasks when outside the \Microsoft\ folder
+const MASQUERADING_NAMES: [&str; 5] = ["update", "system", "microsoft", "windows", "svchost"];
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ScoringFile {
+    threshold: Option<u32>,
+    #[serde(default)]
+    weights: BTreeMap<String, u32>,
+}
+
+/// Indicator weights and the threshold above which an artifact counts as suspicious
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoringModel {
+    weights: BTreeMap<String, u32>,
+    pub threshold: u32,
+}
+
+impl Default for ScoringModel {
+    fn default() -> Self {
+        ScoringModel {
+            weights: DEFAULT_WEIGHTS.iter().map(|(name, weight)| (name.to_string(), *weight)).collect(),
+            threshold: DEFAULT_THRESHOLD,
+        }
+    }
+}
+
+impl ScoringModel {
+    /// Load the weights from a TOML file; indicators it leaves out keep their default weight
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read suspicion weights {}: {}", path.display(), e))?;
+        Self::parse(&text).map_err(|e| format!("Invalid suspicion weights {}: {}", path.display(), e))
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let file: ScoringFile = toml::from_str(text).map_err(|e| e.to_string())?;
+        let mut model = ScoringModel::default();
+        if let Some(threshold) = file.threshold {
+            if threshold == 0 || threshold > MAX_SCORE {
+                return Err(format!("threshold must be between 1 and {}", MAX_SCORE));
+            }
+            model.threshold = threshold;
+        }
+        for (name, weight) in file.weights {
+            if weight > MAX_SCORE {
+                return Err(format!("weight of {} must be at most {}", name, MAX_SCORE));
+            }
+            match model.weights.get_mut(&name) {
+                Some(slot) => *slot = weight,
+                None => return Err(format!("unknown indicator {} (known: {})", name, DEFAULT_WEIGHTS.map(|(name, _)| name).join(", "))),
+            }
+        }
+        Ok(model)
+    }
+
+    pub fn weight(&self, indicator: &str) -> u32 {
+        self.weights.get(indicator).copied().unwrap_or(0)
+    }
+
+    /// Capped score of the indicators and the contributing ones, heaviest first
+    pub fn score(&self, indicators: &[&str]) -> (u32, Vec<String>) {
+        let mut contributing: Vec<(&str, u32)> = Vec::new();
+        for indicator in indicators {
+            let weight = self.weight(indicator);
+            if weight > 0 && !contributing.iter().any(|(name, _)| name == indicator) {
+                contributing.push((indicator, weight));
+            }
+        }
+        contributing.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        let score = contributing.iter().map(|(_, weight)| weight).sum::<u32>().min(MAX_SCORE);
+        (score, contributing.into_iter().map(|(name, _)| name.to_string()).collect())
+    }
+
+    pub fn is_suspicious(&self, score: u32) -> bool {
+        score >= self.threshold
+    }
+
+    /// Score every mechanism and derive its `is_suspicious` flag
+    pub fn score_persistence_mechanisms(&self, mechanisms: &mut [PersistenceMechanism]) {
+        for mechanism in mechanisms {
+            let mut indicators = command_indicators(&mechanism.command);
+            if mechanism.mechanism_type == PersistenceType::ScheduledTask.as_str() {
+                let task_path = mechanism.source.trim_start_matches("Task Scheduler: ");
+                let run_as = mechanism.value.rsplit_once("(User: ").map(|(_, user)| user.trim_end_matches(')')).unwrap_or_default();
+                indicators.extend(task_indicators(task_path, &mechanism.name, run_as, None));
+            }
+            let (score, reasons) = self.score(&indicators);
+            mechanism.suspicion_score = score;
+            mechanism.suspicion_reasons = reasons;
+            mechanism.is_suspicious = self.is_suspicious(score);
+        }
+    }
+
+    /// Score every scheduled task from its command, name, account, author and triggers
+    pub fn score_scheduled_tasks(&self, tasks: &mut [ScheduledTask]) {
+        for task in tasks {
+            let mut indicators = command_indicators(&format!("{} {}", task.command, task.arguments));
+            indicators.extend(task_indicators(&task.path, &task.name, &task.run_as_user, Some(&task.author)));
+            if task.triggers.iter().any(|t| t.enabled && (t.trigger_type == "Logon" || t.trigger_type == "Boot")) {
+                indicators.push("boot_or_logon_trigger");
+            }
+            let (score, reasons) = self.score(&indicators);
+            task.suspicion_score = score;
+            task.suspicion_reasons = reasons;
+        }
+    }
+
+    /// Score every process from its image, command line and process tree findings
+    pub fn score_processes(&self, processes: &mut [Process]) {
+        for process in processes {
+            let mut indicators = command_indicators(&format!("{} {}", process.executable_path, process.command_line));
+            // Tree findings are written as "name: details"
+            let findings: Vec<String> = process.suspicious_indicators.iter().map(|finding| finding.split(':').next().unwrap_or_default().trim().to_string()).collect();
+            indicators.extend(findings.iter().map(String::as_str));
+            if process.executable_deleted {
+                indicators.push("deleted_executable");
+            }
+            let (score, reasons) = self.score(&indicators);
+            process.suspicion_score = score;
+            process.suspicion_reasons = reasons;
+        }
+    }
+}
+
+/// Indicators shown by a command line or path
+pub fn command_indicators(command: &str) -> Vec<&'static str> {
+    let normalized = format!("{} ", path_norm::normalize_command(command).replace('"', " "));
+    COMMAND_INDICATORS
+        .iter()
+        .filter(|(_, fragments)| fragments.iter().any(|fragment| normalized.contains(fragment)))
+        .map(|(name, _)| *name)
+        .collect()
+}
+
+fn task_indicators(task_path: &str, name: &str, run_as: &str, author: Option<&str>) -> Vec<&'static str> {
+    let mut indicators = Vec::new();
+    let microsoft = task_path.to_lowercase().starts_with("\\microsoft\\") || author.is_some_and(|author| author.contains("Microsoft"));
+    if !microsoft && (run_as.eq_ignore_ascii_case("SYSTEM") || run_as.eq_ignore_ascii_case("NT AUTHORITY\\SYSTEM")) {
+        indicators.push("non_microsoft_system_task");
+    }
+    let name = name.to_lowercase();
+    if !microsoft && MASQUERADING_NAMES.iter().any(|fragment| name.contains(fragment)) {
+        indicators.push("masquerading_name");
+    }
+    if name.chars().count() == 1 {
+        indicators.push("single_character_name");
+    }
+    indicators
+}
+
+fn shared_slot() -> &'static RwLock<Arc<ScoringModel>> {
+    static SHARED: OnceLock<RwLock<Arc<ScoringModel>>> = OnceLock::new();
+    SHARED.get_or_init(|| RwLock::new(Arc::new(ScoringModel::default())))
+}
+
+/// The model used by the collectors of the current scan
+pub fn shared() -> Arc<ScoringModel> {
+    match shared_slot().read() {
+        Ok(model) => Arc::clone(&model),
+        Err(poisoned) => Arc::clone(&poisoned.into_inner()),
+    }
+}
+
+/// Use the given model for the following collections
+pub fn configure(model: ScoringModel) {
+    let model = Arc::new(model);
+    match shared_slot().write() {
+        Ok(mut slot) => *slot = model,
+        Err(poisoned) => *poisoned.into_inner() = model,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mechanism(mechanism_type: PersistenceType, name: &str, command: &str, source: &str) -> PersistenceMechanism {
+        PersistenceMechanism::new(mechanism_type.as_str().to_string(), name.to_string(), command.to_string(), source.to_string())
+    }
+
+    #[test]
+    fn test_score_persistence_mechanisms() {
+        let model = ScoringModel::default();
+        let mut mechanisms = vec![
+            mechanism(PersistenceType::RegistryRunKey, "Updater", "C:\\Users\\bob\\AppData\\Roaming\\run.ps1", "HKCU\\...\\Run"),
+            mechanism(PersistenceType::Service, "svc", "C:\\Temp\\svc.exe", "HKLM\\...\\Services\\svc"),
+            mechanism(PersistenceType::RegistryRunKey, "Agent", "powershell.exe -W Hidden -EncodedCommand SQBFAFgA", "HKLM\\...\\Run"),
+            mechanism(PersistenceType::RegistryRunKey, "Vendor", "\"C:\\Program Files\\Vendor\\agent.exe\" /tray", "HKLM\\...\\Run"),
+        ];
+        mechanisms.push(mechanism(PersistenceType::ScheduledTask, "WindowsUpdateCheck", "C:\\ProgramData\\u.exe (User: SYSTEM)", "Task Scheduler: \\WindowsUpdateCheck"));
+        mechanisms[4].value = mechanisms[4].command.clone();
+        model.score_persistence_mechanisms(&mut mechanisms);
+
+        let scores: Vec<u32> = mechanisms.iter().map(|m| m.suspicion_score).collect();
+        assert_eq!(scores, vec![45, 40, 75, 0, 50]);
+        assert_eq!(mechanisms[0].suspicion_reasons, vec!["script_file", "user_writable_directory"]);
+        assert_eq!(mechanisms[2].suspicion_reasons, vec!["encoded_command", "hidden_window", "script_interpreter"]);
+        assert_eq!(mechanisms[4].suspicion_reasons, vec!["user_writable_directory", "masquerading_name", "non_microsoft_system_task"]);
+        assert_eq!(mechanisms.iter().map(|m| m.is_suspicious).collect::<Vec<_>>(), vec![true, true, true, false, true]);
+
+        // Tuned: writable directories weigh more, interpreters nothing, higher bar
+        let tuned = ScoringModel::parse("threshold = 60\n[weights]\nuser_writable_directory = 40\nscript_interpreter = 0\n").unwrap();
+        tuned.score_persistence_mechanisms(&mut mechanisms);
+        assert_eq!(mechanisms.iter().map(|m| m.suspicion_score).collect::<Vec<_>>(), vec![65, 40, 60, 0, 70]);
+        assert_eq!(mechanisms.iter().map(|m| m.is_suspicious).collect::<Vec<_>>(), vec![true, false, true, false, true]);
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_weights() {
+        assert!(ScoringModel::parse("[weights]\ntemp_dir = 10\n").unwrap_err().contains("unknown indicator temp_dir"));
+        assert!(ScoringModel::parse("threshold = 0\n").is_err());
+        assert!(ScoringModel::parse("[weights]\norphaned = 150\n").is_err());
+        assert!(ScoringModel::parse("thresold = 50\n").is_err());
+        assert_eq!(ScoringModel::parse("").unwrap(), ScoringModel::default());
+    }
+
+    #[test]
+    fn test_score_processes() {
+        let model = ScoringModel::default();
+        let mut process = Process::new(300, 200, "stage2.exe".to_string(), "C:\\Users\\Public\\stage2.exe".to_string(), "C:\\Users\\Public\\stage2.exe".to_string());
+        process.suspicious_indicators.push("suspicious_parent: WINWORD.EXE -> stage2.exe (Office application spawning a program)".to_string());
+        process.executable_deleted = true;
+        let mut processes = vec![process];
+        model.score_processes(&mut processes);
+        assert_eq!(processes[0].suspicion_score, 100);
+        assert_eq!(processes[0].suspicion_reasons, vec!["deleted_executable", "suspicious_parent", "user_writable_directory"]);
+    }
+}
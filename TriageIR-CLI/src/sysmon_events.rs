@@ -0,0 +1,210 @@
+//! Sysmon operational log mapped to structured events
+//! Process creation (1), network connections (3), image loads (7), remote
+//! threads (8), process access (10), file creation (11), registry value sets
+//! (13) and DNS queries (22) keep the acting process GUID, PID and image;
+//! everything specific to the event goes to `details`. Events other than
+//! process creation do not log the parent, so it is filled in from the
+//! process creation event with the same process GUID when that was collected.
+
+use crate::event_logs::SYSMON_CHANNEL;
+use crate::forensic_types::{AuditEntry, SysmonEvent};
+use crate::types::{EventLogEntry, EventLogs};
+use std::collections::{BTreeMap, HashMap};
+
+const COMPONENT: &str = "sysmon";
+
+/// Sysmon event IDs collected and their names
+const SYSMON_EVENT_TYPES: [(u32, &str); 8] = [
+    (1, "ProcessCreate"),
+    (3, "NetworkConnect"),
+    (7, "ImageLoad"),
+    (8, "CreateRemoteThread"),
+    (10, "ProcessAccess"),
+    (11, "FileCreate"),
+    (13, "RegistryValueSet"),
+    (22, "DnsQuery"),
+];
+
+/// Fields mapped onto the struct itself rather than `details`; remote thread
+/// and process access events name the acting process `Source*`
+const PROCESS_FIELDS: [&str; 15] = [
+    "ProcessGuid",
+    "ProcessId",
+    "Image",
+    "CommandLine",
+    "User",
+    "ParentProcessGuid",
+    "ParentProcessId",
+    "ParentImage",
+    "ParentCommandLine",
+    "Hashes",
+    "SourceProcessGuid",
+    "SourceProcessId",
+    "SourceImage",
+    "SourceUser",
+    "UtcTime",
+];
+
+/// Structured events of the collected Sysmon channel
+pub fn collect_sysmon_events(event_logs: &EventLogs) -> (Vec<SysmonEvent>, Vec<AuditEntry>) {
+    let start_time = std::time::Instant::now();
+    let mut audit_log = vec![AuditEntry::new(COMPONENT, "INFO", "start_collection", "Starting Sysmon event analysis".to_string(), "started")];
+
+    let Some(channel_events) = event_logs.channels.get(SYSMON_CHANNEL) else {
+        audit_log.push(AuditEntry::new(COMPONENT, "INFO", "channel_check", format!("{} was not collected or Sysmon is not installed", SYSMON_CHANNEL), "not_found"));
+        return (Vec::new(), audit_log);
+    };
+
+    let mut events: Vec<SysmonEvent> = channel_events.iter().filter_map(sysmon_event).collect();
+    let unmapped = channel_events.len() - events.len();
+    link_parents(&mut events);
+    events.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+
+    if unmapped > 0 {
+        audit_log.push(AuditEntry::new(COMPONENT, "DEBUG", "event_filter", format!("Skipped {} Sysmon events of other types", unmapped), "success"));
+    }
+    let mut complete = AuditEntry::new(COMPONENT, "INFO", "complete_collection", format!("Mapped {} Sysmon events", events.len()), "success");
+    complete.duration_ms = Some(start_time.elapsed().as_millis() as u64);
+    audit_log.push(complete);
+    (events, audit_log)
+}
+
+fn sysmon_event(event: &EventLogEntry) -> Option<SysmonEvent> {
+    let (_, event_type) = SYSMON_EVENT_TYPES.iter().find(|(id, _)| *id == event.event_id)?;
+    // Sysmon spells it ProcessGuid in most events and SourceProcessGUID in event 10
+    let fields: HashMap<String, &str> = event.event_data.iter().map(|(name, value)| (name.to_lowercase(), value.as_str())).collect();
+    let field = |names: &[&str]| {
+        names
+            .iter()
+            .find_map(|name| fields.get(&name.to_lowercase()).copied())
+            .unwrap_or_default()
+            .to_string()
+    };
+    let details: BTreeMap<String, String> = event
+        .event_data
+        .iter()
+        .filter(|(name, _)| !PROCESS_FIELDS.iter().any(|field| field.eq_ignore_ascii_case(name)))
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect();
+
+    Some(SysmonEvent {
+        event_id: event.event_id,
+        timestamp: event.timestamp.clone(),
+        process_guid: normalize_guid(&field(&["ProcessGuid", "SourceProcessGuid"])),
+        process_id: field(&["ProcessId", "SourceProcessId"]).parse().unwrap_or(0),
+        image: field(&["Image", "SourceImage"]),
+        command_line: field(&["CommandLine"]),
+        user: field(&["User", "SourceUser"]),
+        parent_process_guid: normalize_guid(&field(&["ParentProcessGuid"])),
+        parent_process_id: field(&["ParentProcessId"]).parse().unwrap_or(0),
+        parent_image: field(&["ParentImage"]),
+        parent_command_line: field(&["ParentCommandLine"]),
+        hashes: parse_hashes(&field(&["Hashes"])),
+        event_type: event_type.to_string(),
+        details,
+    })
+}
+
+/// Fill in the command line and parent of non-process-creation events from
+/// the process creation event of the same process GUID
+fn link_parents(events: &mut [SysmonEvent]) {
+    let creations: HashMap<String, SysmonEvent> = events
+        .iter()
+        .filter(|event| event.event_id == 1 && !event.process_guid.is_empty())
+        .map(|event| (event.process_guid.clone(), event.clone()))
+        .collect();
+    for event in events.iter_mut().filter(|event| event.event_id != 1) {
+        let Some(creation) = creations.get(&event.process_guid) else { continue };
+        if event.command_line.is_empty() {
+            event.command_line = creation.command_line.clone();
+        }
+        if event.user.is_empty() {
+            event.user = creation.user.clone();
+        }
+        event.parent_process_guid = creation.parent_process_guid.clone();
+        event.parent_process_id = creation.parent_process_id;
+        event.parent_image = creation.parent_image.clone();
+        event.parent_command_line = creation.parent_command_line.clone();
+    }
+}
+
+/// `{9A1C...}` and `9a1c...` name the same process
+fn normalize_guid(guid: &str) -> String {
+    guid.trim_matches(['{', '}']).to_lowercase()
+}
+
+/// Sysmon `Hashes` field: SHA1=...,MD5=...,SHA256=...,IMPHASH=...
+fn parse_hashes(field: &str) -> HashMap<String, String> {
+    field
+        .split(',')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(algorithm, value)| (algorithm.trim().to_uppercase(), value.trim().to_lowercase()))
+        .filter(|(_, value)| !value.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(event_id: u32, timestamp: &str, data: &[(&str, &str)]) -> EventLogEntry {
+        let mut entry = EventLogEntry::new(event_id, "Information".to_string(), timestamp.to_string(), String::new());
+        entry.event_data = data.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        entry
+    }
+
+    #[test]
+    fn test_sysmon_events_are_mapped_and_linked() {
+        let mut event_logs = EventLogs::default();
+        event_logs.channels.insert(SYSMON_CHANNEL.to_string(), vec![
+            event(3, "2024-05-01T10:00:05Z", &[
+                ("ProcessGuid", "{AB12-0001}"),
+                ("ProcessId", "4242"),
+                ("Image", "C:\\Users\\bob\\rat.exe"),
+                ("DestinationIp", "203.0.113.7"),
+                ("DestinationPort", "443"),
+            ]),
+            event(1, "2024-05-01T10:00:01Z", &[
+                ("UtcTime", "2024-05-01 10:00:01.000"),
+                ("ProcessGuid", "{AB12-0001}"),
+                ("ProcessId", "4242"),
+                ("Image", "C:\\Users\\bob\\rat.exe"),
+                ("CommandLine", "rat.exe -c2"),
+                ("User", "CORP\\bob"),
+                ("ParentProcessGuid", "{AB12-0000}"),
+                ("ParentProcessId", "1200"),
+                ("ParentImage", "C:\\Windows\\explorer.exe"),
+                ("ParentCommandLine", "explorer.exe"),
+                ("Hashes", "SHA1=AAAA,MD5=BBBB,SHA256=CCCC,IMPHASH="),
+                ("IntegrityLevel", "Medium"),
+            ]),
+            event(10, "2024-05-01T10:00:09Z", &[
+                ("SourceProcessGUID", "{AB12-0001}"),
+                ("SourceProcessId", "4242"),
+                ("SourceImage", "C:\\Users\\bob\\rat.exe"),
+                ("TargetImage", "C:\\Windows\\system32\\lsass.exe"),
+                ("GrantedAccess", "0x1010"),
+            ]),
+            event(255, "2024-05-01T10:00:10Z", &[("ID", "6")]),
+        ]);
+
+        let (events, audit_log) = collect_sysmon_events(&event_logs);
+        let types: Vec<&str> = events.iter().map(|e| e.event_type.as_str()).collect();
+        assert_eq!(types, vec!["ProcessCreate", "NetworkConnect", "ProcessAccess"]);
+
+        let create = &events[0];
+        assert_eq!((create.process_guid.as_str(), create.parent_process_id), ("ab12-0001", 1200));
+        assert_eq!(create.hashes.get("SHA256").map(String::as_str), Some("cccc"));
+        assert!(!create.hashes.contains_key("IMPHASH"));
+        assert_eq!(create.details.keys().collect::<Vec<_>>(), vec!["IntegrityLevel"]);
+
+        let connect = &events[1];
+        assert_eq!(connect.details.get("DestinationIp").map(String::as_str), Some("203.0.113.7"));
+        assert_eq!((connect.command_line.as_str(), connect.parent_image.as_str()), ("rat.exe -c2", "C:\\Windows\\explorer.exe"));
+
+        let access = &events[2];
+        assert_eq!((access.process_id, access.user.as_str()), (4242, "CORP\\bob"));
+        assert_eq!(access.details.get("TargetImage").map(String::as_str), Some("C:\\Windows\\system32\\lsass.exe"));
+        assert!(audit_log.iter().any(|log| log.action == "event_filter"));
+    }
+}
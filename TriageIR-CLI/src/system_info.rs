@@ -1,4 +1,6 @@
-use crate::types::{SystemInfo, LoggedOnUser, LogEntry};
+use crate::forensic_types::LoggedOnUser;
+use crate::sessions;
+use crate::types::{SystemInfo, LogEntry};
 use sysinfo::System;
 use std::time::{SystemTime, UNIX_EPOCH};
 
@@ -12,7 +14,7 @@ pub fn collect_system_info() -> (SystemInfo, Vec<LogEntry>) {
     // Collect system uptime
     match collect_uptime() {
         Ok(uptime) => {
-            system_info.uptime_secs = uptime;
+            system_info.uptime_hours = uptime as f64 / 3600.0;
             logs.push(LogEntry::info(&format!("System uptime: {} seconds", uptime)));
         }
         Err(e) => {
@@ -57,30 +59,34 @@ fn collect_uptime() -> Result<u64, String> {
 
 /// Collect information about currently logged-on users
 fn collect_logged_on_users() -> Result<Vec<LoggedOnUser>, String> {
-    let mut users = Vec::new();
-    let _sys = System::new_all();
-    // Note: sysinfo 0.30+ doesn't have users() method, using Windows API fallback
-    // sys.refresh_users_list();
-    
-    // for user in sys.users() {
-    //     // Convert user information to our format
-    //     let logged_user = LoggedOnUser::new(
-    //         user.name().to_string(),
-    //         get_user_domain(user.name()),
-    //         format_logon_time(user.name()),
-    //     );
-    //     users.push(logged_user);
-    // }
+    let (sessions, _) = sessions::collect_logged_on_users();
+    let mut users: Vec<LoggedOnUser> = sessions
+        .into_iter()
+        .map(|mut session| {
+            if session.logon_time.is_empty() {
+                session.logon_time = chrono::Utc::now().to_rfc3339();
+            }
+            session
+        })
+        .collect();
     
-    // Fallback: Add current user from environment
-    if let Ok(username) = std::env::var("USERNAME") {
-        let domain = std::env::var("USERDOMAIN").unwrap_or_else(|_| "WORKGROUP".to_string());
-        let logged_user = LoggedOnUser::new(
-            username,
-            domain,
-            chrono::Utc::now().to_rfc3339(),
-        );
-        users.push(logged_user);
+    // Fallback: Add current user from environment when sessions cannot be enumerated
+    if users.is_empty() {
+        if let Ok(username) = std::env::var("USERNAME") {
+            let domain = std::env::var("USERDOMAIN").unwrap_or_else(|_| "WORKGROUP".to_string());
+            users.push(LoggedOnUser {
+                username,
+                domain,
+                session_id: 0,
+                session_type: "Interactive".to_string(),
+                logon_time: chrono::Utc::now().to_rfc3339(),
+                logon_server: String::new(),
+                session_state: String::new(),
+                winstation: String::new(),
+                client_name: String::new(),
+                client_address: String::new(),
+            });
+        }
     }
     
     // Remove duplicates based on username
@@ -90,21 +96,6 @@ fn collect_logged_on_users() -> Result<Vec<LoggedOnUser>, String> {
     Ok(users)
 }
 
-/// Get domain information for a user (Windows-specific)
-fn get_user_domain(_username: &str) -> String {
-    // Try to get computer name as default domain
-    std::env::var("COMPUTERNAME").unwrap_or_else(|_| {
-        std::env::var("USERDOMAIN").unwrap_or_else(|_| "WORKGROUP".to_string())
-    })
-}
-
-/// Format logon time for a user (placeholder implementation)
-fn format_logon_time(_username: &str) -> String {
-    // This is a simplified implementation
-    // In a full implementation, we would query Windows APIs for actual logon times
-    chrono::Utc::now().to_rfc3339()
-}
-
 /// Get detailed OS version information
 pub fn get_detailed_os_version() -> String {
     let _sys = System::new();
@@ -140,7 +131,7 @@ mod tests {
         
         // Should have collected uptime (should be > 0 on running system)
         // Note: This might be 0 in test environments
-        assert!(system_info.uptime_secs >= 0);
+        assert!(system_info.uptime_hours >= 0.0);
         
         // Logs should contain start and completion messages
         assert!(logs.iter().any(|log| log.message.contains("Starting system information")));
@@ -0,0 +1,140 @@
+//! Scan-wide time window (`--since` / `--until`)
+//! Time-stamped artifacts outside the window are dropped. Artifacts whose
+//! timestamp is missing or cannot be parsed are kept, so scoping never hides
+//! evidence silently.
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, SecondsFormat, Utc};
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TimeWindow {
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+}
+
+impl TimeWindow {
+    /// Parse the `--since` / `--until` arguments (RFC 3339 or YYYY-MM-DD, UTC)
+    pub fn new(since: Option<&str>, until: Option<&str>) -> Result<Self, String> {
+        let window = TimeWindow {
+            since: since.map(|value| parse_bound("--since", value)).transpose()?,
+            until: until.map(|value| parse_bound("--until", value)).transpose()?,
+        };
+
+        if let (Some(since), Some(until)) = (window.since, window.until) {
+            if since > until {
+                return Err(format!("--since ({}) is later than --until ({})", since.to_rfc3339(), until.to_rfc3339()));
+            }
+        }
+
+        Ok(window)
+    }
+
+    pub fn is_unbounded(&self) -> bool {
+        self.since.is_none() && self.until.is_none()
+    }
+
+    /// Whether an artifact timestamp falls inside the window
+    pub fn contains(&self, timestamp: &str) -> bool {
+        match parse_timestamp(timestamp) {
+            Some(time) => {
+                self.since.is_none_or(|since| time >= since) && self.until.is_none_or(|until| time <= until)
+            }
+            None => true,
+        }
+    }
+
+    /// Drop the items outside the window and return how many were removed
+    pub fn retain<T>(&self, items: &mut Vec<T>, timestamp: impl Fn(&T) -> &str) -> usize {
+        if self.is_unbounded() {
+            return 0;
+        }
+        let before = items.len();
+        items.retain(|item| self.contains(timestamp(item)));
+        before - items.len()
+    }
+
+    /// XPath condition on the event creation time, for event log queries
+    pub fn xpath_condition(&self) -> Option<String> {
+        let mut bounds = Vec::new();
+        if let Some(since) = self.since {
+            bounds.push(format!("@SystemTime>='{}'", since.to_rfc3339_opts(SecondsFormat::Millis, true)));
+        }
+        if let Some(until) = self.until {
+            bounds.push(format!("@SystemTime<='{}'", until.to_rfc3339_opts(SecondsFormat::Millis, true)));
+        }
+
+        if bounds.is_empty() {
+            None
+        } else {
+            Some(format!("TimeCreated[{}]", bounds.join(" and ")))
+        }
+    }
+}
+
+fn parse_bound(flag: &str, value: &str) -> Result<DateTime<Utc>, String> {
+    parse_timestamp(value).ok_or_else(|| {
+        format!("Invalid {} value '{}': expected RFC 3339 (2024-05-01T00:00:00Z) or YYYY-MM-DD", flag, value)
+    })
+}
+
+/// Parse the timestamp formats produced by the collectors; naive timestamps are taken as UTC
+pub fn parse_timestamp(timestamp: &str) -> Option<DateTime<Utc>> {
+    let timestamp = timestamp.trim();
+    if timestamp.is_empty() {
+        return None;
+    }
+
+    if let Ok(time) = DateTime::parse_from_rfc3339(timestamp) {
+        return Some(time.with_timezone(&Utc));
+    }
+    if let Ok(time) = NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%dT%H:%M:%S%.f") {
+        return Some(time.and_utc());
+    }
+    NaiveDate::parse_from_str(timestamp, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|time| time.and_utc())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_time_window_contains() {
+        let window = TimeWindow::new(Some("2024-05-01T00:00:00Z"), Some("2024-05-31")).unwrap();
+
+        assert!(window.contains("2024-05-10T12:00:00+00:00"));
+        assert!(window.contains("2024-05-01T02:00:00+02:00")); // 2024-05-01T00:00:00Z
+        assert!(window.contains("2024-05-20T08:00:00.123")); // Naive timestamps are UTC
+        assert!(!window.contains("2024-04-30T23:59:59Z"));
+        assert!(!window.contains("2024-06-01T00:00:00Z"));
+        assert!(window.contains("")); // Unknown timestamps are kept
+        assert!(window.contains("Unknown"));
+    }
+
+    #[test]
+    fn test_time_window_validation() {
+        assert!(TimeWindow::new(Some("yesterday"), None).is_err());
+        assert!(TimeWindow::new(Some("2024-06-01"), Some("2024-05-01")).is_err());
+        assert!(TimeWindow::new(None, None).unwrap().is_unbounded());
+    }
+
+    #[test]
+    fn test_time_window_retain() {
+        let window = TimeWindow::new(Some("2024-05-01"), None).unwrap();
+        let mut timestamps = vec!["2024-04-01T00:00:00Z", "2024-05-02T00:00:00Z", ""];
+
+        assert_eq!(window.retain(&mut timestamps, |t| t), 1);
+        assert_eq!(timestamps, vec!["2024-05-02T00:00:00Z", ""]);
+    }
+
+    #[test]
+    fn test_xpath_condition() {
+        let window = TimeWindow::new(Some("2024-05-01T00:00:00Z"), Some("2024-05-02T00:00:00Z")).unwrap();
+        assert_eq!(
+            window.xpath_condition().unwrap(),
+            "TimeCreated[@SystemTime>='2024-05-01T00:00:00.000Z' and @SystemTime<='2024-05-02T00:00:00.000Z']"
+        );
+        assert!(TimeWindow::default().xpath_condition().is_none());
+    }
+}
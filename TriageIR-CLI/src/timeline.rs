@@ -0,0 +1,194 @@
+//! Timeline reconstruction from a saved scan
+//! Merges the time-stamped artifacts (process starts, event log entries,
+//! execution evidence, device installations, notifications, clipboard history,
+//! search history) into one chronologically ordered list. Artifacts without a
+//! parseable timestamp cannot be placed and are left out.
+
+use crate::forensic_types::TimelineEvent;
+use crate::time_window::parse_timestamp;
+use serde_json::Value;
+
+const MAX_DESCRIPTION_CHARS: usize = 200;
+
+/// Build a chronological timeline from a scan result
+pub fn build_timeline(scan: &Value) -> Vec<TimelineEvent> {
+    let artifacts = &scan["artifacts"];
+    let mut events = Vec::new();
+
+    for process in array(&artifacts["running_processes"]) {
+        let description = format!(
+            "Process started: {} (PID {}) {}",
+            text(&process["name"]),
+            process["pid"],
+            text(&process["command_line"])
+        );
+        push(&mut events, &process["creation_time"], "running_processes", description);
+    }
+
+    let event_logs = &artifacts["event_logs"];
+    for channel in ["security", "system", "application"] {
+        for entry in array(&event_logs[channel]) {
+            push(&mut events, &entry["timestamp"], &format!("event_logs/{}", channel), event_description(entry));
+        }
+    }
+    if let Some(channels) = event_logs["channels"].as_object() {
+        for (channel, entries) in channels {
+            for entry in array(entries) {
+                push(&mut events, &entry["timestamp"], &format!("event_logs/{}", channel), event_description(entry));
+            }
+        }
+    }
+
+    let execution = &artifacts["execution_evidence"];
+    for prefetch in array(&execution["prefetch_files"]) {
+        let description = format!("Executed: {} (run count {})", text(&prefetch["executable_name"]), prefetch["run_count"]);
+        // Windows 8.1 and later keep the last eight runs; older results only the latest
+        match array(&prefetch["last_run_times"]) {
+            [] => push(&mut events, &prefetch["last_run_time"], "prefetch_files", description),
+            run_times => {
+                for run_time in run_times {
+                    push(&mut events, run_time, "prefetch_files", description.clone());
+                }
+            }
+        }
+    }
+    for entry in array(&execution["shimcache_entries"]) {
+        push(&mut events, &entry["last_modified"], "shimcache_entries", format!("Shimcache entry last modified: {}", text(&entry["path"])));
+    }
+    for entry in array(&execution["amcache_entries"]) {
+        push(&mut events, &entry["last_modified"], "amcache_entries", format!("Amcache entry last modified: {}", text(&entry["path"])));
+    }
+    for entry in array(&execution["defender_log_entries"]) {
+        let subject = [text(&entry["threat_name"]), text(&entry["path"]), text(&entry["process_name"])]
+            .into_iter()
+            .find(|s| !s.is_empty())
+            .unwrap_or_default();
+        push(&mut events, &entry["timestamp"], "defender_log_entries", format!("Defender {}: {}", text(&entry["category"]), subject));
+    }
+
+    // Detections of the Defender operational log are already placed as event log entries
+    for entry in array(&artifacts["security_events"]["defender_log"]).iter().filter(|entry| entry["event_id"] == 0) {
+        let description = format!("Defender detection history: {} in {}", text(&entry["threat_name"]), text(&entry["path"]));
+        push(&mut events, &entry["timestamp"], "defender_log", description);
+    }
+
+    for installation in array(&artifacts["device_installations"]) {
+        push(&mut events, &installation["start_time"], "device_installations", format!("Device installed: {}", text(&installation["target"])));
+    }
+    // The first connection is already placed by the device installation
+    for device in array(&artifacts["usb_devices"]) {
+        let name = format!("{} (serial {})", text(&device["friendly_name"]), text(&device["serial_number"]));
+        push(&mut events, &device["last_connected"], "usb_devices", format!("USB device last connected: {}", name));
+        push(&mut events, &device["last_removed"], "usb_devices", format!("USB device last removed: {}", name));
+    }
+
+    for entry in array(&artifacts["filesystem_artifacts"]["usn_journal"]) {
+        let description = match entry["old_name"].as_str() {
+            Some(old_name) => format!("File renamed: {} -> {}", old_name, text(&entry["file_name"])),
+            None => format!("File {}: {}", text(&entry["action"]).replace('_', " "), text(&entry["file_name"])),
+        };
+        push(&mut events, &entry["timestamp"], "usn_journal", description);
+    }
+
+    let user_activity = &artifacts["user_activity"];
+    for notification in array(&user_activity["notifications"]) {
+        let description = format!("Notification from {} for {}", text(&notification["application"]), text(&notification["user"]));
+        push(&mut events, &notification["arrival_time"], "notifications", description);
+    }
+    for entry in array(&user_activity["clipboard_history"]) {
+        let description = format!("Clipboard item copied by {}: {}", text(&entry["user"]), text(&entry["text"]));
+        push(&mut events, &entry["timestamp"], "clipboard_history", description);
+    }
+    for entry in array(&user_activity["search_history"]) {
+        // The key write time only dates the most recent entry
+        if entry["mru_position"].as_u64() == Some(0) {
+            let description = format!("{} by {}: {}", text(&entry["source"]), text(&entry["user"]), text(&entry["value"]));
+            push(&mut events, &entry["key_last_write"], "search_history", description);
+        }
+    }
+
+    events.sort_by_key(|event| parse_timestamp(&event.timestamp));
+    events
+}
+
+fn push(events: &mut Vec<TimelineEvent>, timestamp: &Value, source_artifact: &str, description: String) {
+    let timestamp = text(timestamp);
+    if parse_timestamp(timestamp).is_none() {
+        return;
+    }
+
+    let description = match description.char_indices().nth(MAX_DESCRIPTION_CHARS) {
+        Some((end, _)) => format!("{}...", &description[..end]),
+        None => description,
+    };
+
+    events.push(TimelineEvent {
+        timestamp: timestamp.to_string(),
+        source_artifact: source_artifact.to_string(),
+        description: description.trim_end().to_string(),
+    });
+}
+
+fn event_description(entry: &Value) -> String {
+    let message = text(&entry["message"]).lines().next().unwrap_or_default();
+    format!("Event {} [{}]: {}", entry["event_id"], text(&entry["level"]), message)
+}
+
+fn array(value: &Value) -> &[Value] {
+    value.as_array().map(Vec::as_slice).unwrap_or_default()
+}
+
+fn text(value: &Value) -> &str {
+    value.as_str().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_build_timeline_orders_sources() {
+        let scan = json!({
+            "artifacts": {
+                "running_processes": [
+                    { "pid": 4242, "name": "beacon.exe", "command_line": "beacon.exe -c2", "creation_time": "2024-05-01T10:00:00+00:00" },
+                    { "pid": 4, "name": "System", "command_line": "", "creation_time": null }
+                ],
+                "event_logs": {
+                    "security": [{ "event_id": 4624, "level": "Information", "timestamp": "2024-05-01T09:58:00Z", "message": "An account was successfully logged on.\r\nDetails" }],
+                    "system": [],
+                    "application": [],
+                    "channels": {
+                        "Microsoft-Windows-Sysmon/Operational": [{ "event_id": 1, "level": "Information", "timestamp": "2024-05-01T10:00:01Z", "message": "Process Create" }]
+                    }
+                },
+                "execution_evidence": {
+                    "prefetch_files": [{ "executable_name": "BEACON.EXE", "run_count": 1, "last_run_time": "2024-05-01T10:00:05Z" }],
+                    "shimcache_entries": [{ "path": "C:\\beacon.exe", "last_modified": "Unknown" }]
+                }
+            }
+        });
+
+        let timeline = build_timeline(&scan);
+        let sources: Vec<&str> = timeline.iter().map(|e| e.source_artifact.as_str()).collect();
+
+        assert_eq!(sources, vec!["event_logs/security", "running_processes", "event_logs/Microsoft-Windows-Sysmon/Operational", "prefetch_files"]);
+        assert_eq!(timeline[0].description, "Event 4624 [Information]: An account was successfully logged on.");
+        assert_eq!(timeline[1].description, "Process started: beacon.exe (PID 4242) beacon.exe -c2");
+        assert_eq!(timeline[3].description, "Executed: BEACON.EXE (run count 1)");
+    }
+
+    #[test]
+    fn test_long_descriptions_are_truncated() {
+        let scan = json!({
+            "artifacts": {
+                "running_processes": [{ "pid": 1, "name": "a.exe", "command_line": "x".repeat(500), "creation_time": "2024-05-01T10:00:00Z" }]
+            }
+        });
+
+        let timeline = build_timeline(&scan);
+        assert_eq!(timeline[0].description.chars().count(), MAX_DESCRIPTION_CHARS + 3);
+        assert!(build_timeline(&json!({})).is_empty());
+    }
+}
@@ -0,0 +1,251 @@
+//! RFC 3161 trusted timestamps
+//! The HMAC receipt proves the manifest was not rewritten by someone without the
+//! key, but not when it was written. A timestamp authority signs the SHA-256 of
+//! the manifest together with its own clock, so the token (`.tsr`) shows the
+//! case folder held these files at that time, verifiable by a third party with
+//! `openssl ts -verify -data manifest.json -in timestamp.tsr -CAfile tsa.pem`.
+//! Requests are DER-encoded by hand (SHA-256 imprint, nonce, certificate
+//! requested); of the response only the status and the TSTInfo are decoded, to
+//! check the imprint and nonce and report the time. The signature itself is left
+//! to the verifier.
+
+use crate::http_client;
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+
+/// Environment variable holding the timestamp authority URL when --tsa-url is not given
+pub const TSA_URL_VARIABLE: &str = "TRIAGEIR_TSA_URL";
+
+/// id-sha256 (2.16.840.1.101.3.4.2.1), DER content octets
+const SHA256_OID: [u8; 9] = [0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01];
+
+const TSA_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Universal and context-specific DER tags used by the requests and responses
+const TAG_BOOLEAN: u8 = 0x01;
+const TAG_INTEGER: u8 = 0x02;
+const TAG_OCTET_STRING: u8 = 0x04;
+const TAG_NULL: u8 = 0x05;
+const TAG_OID: u8 = 0x06;
+const TAG_GENERALIZED_TIME: u8 = 0x18;
+const TAG_SEQUENCE: u8 = 0x30;
+const TAG_EXPLICIT_0: u8 = 0xA0;
+
+/// PKIStatus values of an issued token: granted, grantedWithMods
+const GRANTED_STATUSES: [u64; 2] = [0, 1];
+
+/// Decoded parts of a granted timestamp response
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimestampToken {
+    pub gen_time: String, // RFC 3339, the TSA's clock
+    pub serial_number: String, // Hex, unique per TSA
+    pub response: Vec<u8>, // Complete TimeStampResp, saved as .tsr
+}
+
+/// Timestamp `data` at the authority; returns the DER request and the token
+pub fn request_timestamp(url: &str, data: &[u8]) -> Result<(Vec<u8>, TimestampToken), String> {
+    let digest = Sha256::digest(data);
+    let nonce: u64 = rand::random::<u64>() >> 1;
+    let request = build_request(&digest, nonce);
+
+    let headers = vec![
+        ("Content-Type".to_string(), "application/timestamp-query".to_string()),
+        ("User-Agent".to_string(), http_client::USER_AGENT.to_string()),
+    ];
+    let response = http_client::send("POST", url, &headers, &request, TSA_TIMEOUT)?;
+    if !response.is_success() {
+        return Err(format!("timestamp authority {} answered HTTP {}: {}", url, response.status, response.body_excerpt()));
+    }
+    let token = parse_response(response.body, &digest, nonce)?;
+    Ok((request, token))
+}
+
+/// DER TimeStampReq for a SHA-256 digest, asking for the TSA certificate in the token
+pub fn build_request(digest: &[u8], nonce: u64) -> Vec<u8> {
+    let algorithm = tlv(TAG_SEQUENCE, &[tlv(TAG_OID, &SHA256_OID), tlv(TAG_NULL, &[])].concat());
+    let message_imprint = tlv(TAG_SEQUENCE, &[algorithm, tlv(TAG_OCTET_STRING, digest)].concat());
+    let body = [
+        tlv(TAG_INTEGER, &[1]),
+        message_imprint,
+        tlv(TAG_INTEGER, &integer_octets(nonce)),
+        tlv(TAG_BOOLEAN, &[0xFF]),
+    ]
+    .concat();
+    tlv(TAG_SEQUENCE, &body)
+}
+
+/// Check the status of a TimeStampResp and that its TSTInfo covers `digest` and `nonce`
+pub fn parse_response(response: Vec<u8>, digest: &[u8], nonce: u64) -> Result<TimestampToken, String> {
+    let (resp, _) = read_tlv(&response, TAG_SEQUENCE, "TimeStampResp")?;
+    let (status_info, token) = read_tlv(resp, TAG_SEQUENCE, "PKIStatusInfo")?;
+    let (status, _) = read_tlv(status_info, TAG_INTEGER, "PKIStatus")?;
+    let status = integer_value(status)?;
+    if !GRANTED_STATUSES.contains(&status) {
+        return Err(format!("timestamp request rejected with PKIStatus {}", status));
+    }
+
+    // ContentInfo -> [0] SignedData -> encapContentInfo -> [0] OCTET STRING TSTInfo
+    let (content_info, _) = read_tlv(token, TAG_SEQUENCE, "timeStampToken")?;
+    let (_, content_info) = read_tlv(content_info, TAG_OID, "contentType")?;
+    let (signed_data, _) = read_tlv(content_info, TAG_EXPLICIT_0, "content")?;
+    let (signed_data, _) = read_tlv(signed_data, TAG_SEQUENCE, "SignedData")?;
+    let (_, signed_data) = read_tlv(signed_data, TAG_INTEGER, "SignedData version")?;
+    let (_, _, signed_data) = read_any(signed_data, "digestAlgorithms")?;
+    let (encap, _) = read_tlv(signed_data, TAG_SEQUENCE, "encapContentInfo")?;
+    let (_, encap) = read_tlv(encap, TAG_OID, "eContentType")?;
+    let (econtent, _) = read_tlv(encap, TAG_EXPLICIT_0, "eContent")?;
+    let (tst_info, _) = read_tlv(econtent, TAG_OCTET_STRING, "eContent")?;
+
+    let (tst_info, _) = read_tlv(tst_info, TAG_SEQUENCE, "TSTInfo")?;
+    let (_, rest) = read_tlv(tst_info, TAG_INTEGER, "TSTInfo version")?;
+    let (_, rest) = read_tlv(rest, TAG_OID, "policy")?;
+    let (imprint, rest) = read_tlv(rest, TAG_SEQUENCE, "messageImprint")?;
+    let (_, imprint) = read_tlv(imprint, TAG_SEQUENCE, "hashAlgorithm")?;
+    let (hashed_message, _) = read_tlv(imprint, TAG_OCTET_STRING, "hashedMessage")?;
+    if hashed_message != digest {
+        return Err("timestamp token covers a different digest than requested".to_string());
+    }
+    let (serial_number, rest) = read_tlv(rest, TAG_INTEGER, "serialNumber")?;
+    let (gen_time, mut rest) = read_tlv(rest, TAG_GENERALIZED_TIME, "genTime")?;
+
+    // accuracy and ordering may precede the nonce
+    let mut token_nonce = None;
+    while !rest.is_empty() {
+        let (tag, content, next) = read_any(rest, "TSTInfo")?;
+        if tag == TAG_INTEGER {
+            token_nonce = Some(integer_value(content)?);
+            break;
+        }
+        rest = next;
+    }
+    if token_nonce != Some(nonce) {
+        return Err("timestamp token does not carry the request nonce (replayed response?)".to_string());
+    }
+
+    Ok(TimestampToken {
+        gen_time: generalized_time(gen_time)?,
+        serial_number: hex::encode(serial_number),
+        response,
+    })
+}
+
+/// GeneralizedTime (`YYYYMMDDHHMMSS[.fff]Z`) as RFC 3339
+fn generalized_time(content: &[u8]) -> Result<String, String> {
+    let text = std::str::from_utf8(content).map_err(|_| "invalid genTime".to_string())?;
+    chrono::NaiveDateTime::parse_from_str(text, "%Y%m%d%H%M%S%.fZ")
+        .map(|time| time.and_utc().to_rfc3339())
+        .map_err(|e| format!("invalid genTime {}: {}", text, e))
+}
+
+fn tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut encoded = vec![tag];
+    if content.len() < 0x80 {
+        encoded.push(content.len() as u8);
+    } else {
+        let length = (content.len() as u64).to_be_bytes();
+        let significant: Vec<u8> = length.iter().copied().skip_while(|b| *b == 0).collect();
+        encoded.push(0x80 | significant.len() as u8);
+        encoded.extend(significant);
+    }
+    encoded.extend_from_slice(content);
+    encoded
+}
+
+/// Minimal two's complement octets of a non-negative integer
+fn integer_octets(value: u64) -> Vec<u8> {
+    let mut octets: Vec<u8> = value.to_be_bytes().iter().copied().skip_while(|b| *b == 0).collect();
+    if octets.first().is_none_or(|b| b & 0x80 != 0) {
+        octets.insert(0, 0);
+    }
+    octets
+}
+
+fn integer_value(content: &[u8]) -> Result<u64, String> {
+    if content.first().is_none_or(|b| b & 0x80 != 0) {
+        return Err("negative or empty integer in timestamp response".to_string());
+    }
+    let content = match content {
+        [0, rest @ ..] if !rest.is_empty() => rest,
+        _ => content,
+    };
+    if content.len() > 8 {
+        return Err("integer out of range in timestamp response".to_string());
+    }
+    Ok(content.iter().fold(0, |value, b| (value << 8) | u64::from(*b)))
+}
+
+/// Next element with the expected tag: its content and the bytes after it
+fn read_tlv<'a>(data: &'a [u8], tag: u8, field: &str) -> Result<(&'a [u8], &'a [u8]), String> {
+    let (found, content, rest) = read_any(data, field)?;
+    if found != tag {
+        return Err(format!("unexpected tag 0x{:02x} for {} in timestamp response", found, field));
+    }
+    Ok((content, rest))
+}
+
+fn read_any<'a>(data: &'a [u8], field: &str) -> Result<(u8, &'a [u8], &'a [u8]), String> {
+    let truncated = || format!("truncated {} in timestamp response", field);
+    let (&tag, rest) = data.split_first().ok_or_else(truncated)?;
+    let (&first, rest) = rest.split_first().ok_or_else(truncated)?;
+    let (length, rest) = if first < 0x80 {
+        (first as usize, rest)
+    } else {
+        let octets = (first & 0x7F) as usize;
+        if octets == 0 || octets > 4 || rest.len() < octets {
+            return Err(format!("unsupported length encoding for {} in timestamp response", field));
+        }
+        (rest[..octets].iter().fold(0usize, |length, b| (length << 8) | *b as usize), &rest[octets..])
+    };
+    if rest.len() < length {
+        return Err(truncated());
+    }
+    Ok((tag, &rest[..length], &rest[length..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// TimeStampResp as a TSA would return it, without certificates and signer infos
+    fn response(status: u8, digest: &[u8], nonce: u64) -> Vec<u8> {
+        let imprint = tlv(TAG_SEQUENCE, &[tlv(TAG_SEQUENCE, &[tlv(TAG_OID, &SHA256_OID), tlv(TAG_NULL, &[])].concat()), tlv(TAG_OCTET_STRING, digest)].concat());
+        let tst_info = tlv(TAG_SEQUENCE, &[
+            tlv(TAG_INTEGER, &[1]),
+            tlv(TAG_OID, &[0x2A, 0x03, 0x04]),
+            imprint,
+            tlv(TAG_INTEGER, &[0x01, 0xF4]),
+            tlv(TAG_GENERALIZED_TIME, b"20261016093015.25Z"),
+            tlv(TAG_SEQUENCE, &tlv(TAG_INTEGER, &[1])), // accuracy
+            tlv(TAG_INTEGER, &integer_octets(nonce)),
+        ].concat());
+        let encap = tlv(TAG_SEQUENCE, &[tlv(TAG_OID, &[0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x09, 0x10, 0x01, 0x04]), tlv(TAG_EXPLICIT_0, &tlv(TAG_OCTET_STRING, &tst_info))].concat());
+        let signed_data = tlv(TAG_SEQUENCE, &[tlv(TAG_INTEGER, &[3]), tlv(0x31, &[]), encap].concat());
+        let token = tlv(TAG_SEQUENCE, &[tlv(TAG_OID, &[0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x07, 0x02]), tlv(TAG_EXPLICIT_0, &signed_data)].concat());
+        tlv(TAG_SEQUENCE, &[tlv(TAG_SEQUENCE, &tlv(TAG_INTEGER, &[status])), token].concat())
+    }
+
+    #[test]
+    fn test_build_request() {
+        let digest = Sha256::digest(b"manifest");
+        let request = build_request(&digest, 0x80);
+        // SEQUENCE { 1, { { sha256, NULL }, digest }, nonce 0x0080, TRUE }
+        assert_eq!(&request[..2], &[TAG_SEQUENCE, 0x3D]);
+        assert_eq!(request.len(), 0x3F);
+        assert!(request.windows(SHA256_OID.len()).any(|window| window == SHA256_OID));
+        assert!(request.ends_with(&[TAG_INTEGER, 2, 0x00, 0x80, TAG_BOOLEAN, 1, 0xFF]));
+        assert_eq!(tlv(TAG_OCTET_STRING, &[0; 200])[..3], [TAG_OCTET_STRING, 0x81, 200]);
+    }
+
+    #[test]
+    fn test_parse_response() {
+        let digest = Sha256::digest(b"manifest");
+        let token = parse_response(response(0, &digest, 77), &digest, 77).unwrap();
+        assert_eq!(token.gen_time, "2026-10-16T09:30:15.250+00:00");
+        assert_eq!(token.serial_number, "01f4");
+
+        assert!(parse_response(response(2, &digest, 77), &digest, 77).unwrap_err().contains("PKIStatus 2"));
+        assert!(parse_response(response(0, &digest, 77), &digest, 78).unwrap_err().contains("nonce"));
+        assert!(parse_response(response(0, &digest, 77), &Sha256::digest(b"other"), 77).unwrap_err().contains("different digest"));
+        assert!(parse_response(response(0, &digest, 77)[..40].to_vec(), &digest, 77).is_err());
+    }
+}
@@ -1,12 +1,41 @@
+use crate::forensic_types::{
+    AcquiredFile, AmcacheEntry, AppInitEntry, AsnInfo, AttackTechnique, AttackTechniqueSummary, BamEntry,
+    BrowserArtifact, CacheDatabaseFile, CanaryStatus, CertificateEntry, ClipboardHistoryEntry, CollectionFootprint, CollectorStats,
+    CustomArtifacts, DefenderEvent, DefenderExclusion, DefenderLogEntry, Detection, DeviceInstallation, DllHijack, DnsLogEntry,
+    DnsServerSetting, EventLogChannelConfig, ExternalCommand, FileHashes, Finding, FirewallLogEntry, FirewallRule, GeoLocation,
+    HistoricalProcess, HostEnvironment, HostsFile, ImageHijack, KernelDriverInfo, LiveActivity, LoggedOnUser, MemoryDump, MftExport,
+    MruEntry, NamedPipeInfo, NetworkShare, NotificationRecord, NrptRule, PathHijack, PluginArtifacts, PowershellEvent,
+    PrefetchFile, PrefetchStatus, ProxySettings, RdpConnection, RdpConnectionHistory, RdpSessionEvent, Screenshot,
+    ScheduledTask, SearchHistoryEntry, SecuritySetting, ShadowCopyInfo, ShimcacheEntry, SmbOpenFile, SmbSession, SrumAppUsage,
+    SrumNetworkUsage, SweptFile, SysmonEvent, ThreatIntel, UsbDevice, UserExecutionEntry, UserProfile, UsnJournalEntry,
+    WinlogonEntry, WinsockProvider
+};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
-/// Root structure containing all scan results and metadata
+/// Root structure containing all scan results and metadata. Collectors fill
+/// it with typed records and the output is its serde serialization, so this
+/// file defines the shape the JSON Schema in schemas/ documents.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ScanResults {
     pub scan_metadata: ScanMetadata,
     pub artifacts: Artifacts,
+    /// Per-collector health metrics, in collection order
+    #[serde(default)]
+    pub collector_stats: Vec<CollectorStats>,
+    /// High-severity findings with suggested remediation steps
+    #[serde(default)]
+    pub findings: Vec<Finding>,
+    /// Matches of the --detection-rules; absent when no rules were given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detections: Option<Vec<Detection>>,
     pub collection_log: Vec<LogEntry>,
+    /// External programs the collectors ran, with exit codes and output
+    #[serde(default)]
+    pub external_commands: Vec<ExternalCommand>,
+    /// Files and registry keys of the examined system the collectors opened
+    #[serde(default)]
+    pub collection_footprint: CollectionFootprint,
 }
 
 impl ScanResults {
@@ -23,9 +52,30 @@ impl ScanResults {
                 hostname,
                 os_version,
                 cli_version: env!("CARGO_PKG_VERSION").to_string(),
+                schema_version: crate::schema::SCHEMA_VERSION.to_string(),
+                offline: false,
+                offline_root: None,
+                since: None,
+                until: None,
+                shadow_copy: None,
+                hash_algorithms: Vec::new(),
+                suspicion_threshold: crate::suspicion::DEFAULT_THRESHOLD,
+                environment: HostEnvironment::default(),
+                total_artifacts: 0,
+                scan_interrupted: false,
+                memory_limit_mb: None,
+                collection_truncated: false,
+                collection_summary: CollectionSummary::default(),
+                attack_techniques: Vec::new(),
+                export_profile: None,
             },
             artifacts: Artifacts::default(),
+            collector_stats: Vec::new(),
+            findings: Vec::new(),
+            detections: None,
             collection_log: Vec::new(),
+            external_commands: Vec::new(),
+            collection_footprint: CollectionFootprint::default(),
         }
     }
     
@@ -81,16 +131,124 @@ pub struct ScanMetadata {
     pub os_version: String,
     /// CLI tool version
     pub cli_version: String,
+    /// Version of the output schema the results follow (absent before 1.1.0)
+    #[serde(default)]
+    pub schema_version: String,
+    /// True when artifacts were read from a mounted volume instead of the live system
+    #[serde(default)]
+    pub offline: bool,
+    /// Volume root used for offline collection
+    #[serde(default)]
+    pub offline_root: Option<String>,
+    /// Start of the --since/--until collection window (RFC 3339)
+    #[serde(default)]
+    pub since: Option<String>,
+    /// End of the --since/--until collection window (RFC 3339)
+    #[serde(default)]
+    pub until: Option<String>,
+    /// Shadow copy that locked files were read from
+    #[serde(default)]
+    pub shadow_copy: Option<ShadowCopyInfo>,
+    /// Digests computed for files (--hash-algorithms)
+    #[serde(default)]
+    pub hash_algorithms: Vec<String>,
+    /// Suspicion score at which artifacts are flagged
+    #[serde(default)]
+    pub suspicion_threshold: u32,
+    /// Installation and container type of the examined system
+    #[serde(default)]
+    pub environment: HostEnvironment,
+    #[serde(default)]
+    pub total_artifacts: usize,
+    /// Stopped with Ctrl+C; the results are partial
+    #[serde(default)]
+    pub scan_interrupted: bool,
+    /// --memory-limit-mb the collection ran under
+    #[serde(default)]
+    pub memory_limit_mb: Option<u64>,
+    /// A collector stopped early at the memory limit
+    #[serde(default)]
+    pub collection_truncated: bool,
+    #[serde(default)]
+    pub collection_summary: CollectionSummary,
+    /// ATT&CK techniques of the artifacts and detections
+    #[serde(default)]
+    pub attack_techniques: Vec<AttackTechniqueSummary>,
+    /// --export-profile the results were written for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub export_profile: Option<String>,
+}
+
+/// Counts of the collection log
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct CollectionSummary {
+    pub total_logs: usize,
+    pub error_count: usize,
+    pub warning_count: usize,
+    /// Share of log entries that are not errors, in percent
+    pub success_rate: f64,
 }
 
 /// Container for all collected forensic artifacts
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct Artifacts {
     pub system_info: SystemInfo,
+    #[serde(default)]
+    pub shadow_copies: Vec<ShadowCopyInfo>,
+    #[serde(default)]
+    pub screenshots: Vec<Screenshot>,
+    /// Activity recorded by the ETW monitor during the collection (--monitor)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub live_activity: Option<LiveActivity>,
     pub running_processes: Vec<Process>,
+    #[serde(default)]
+    pub process_tree: ProcessTree,
+    #[serde(default)]
+    pub loaded_drivers: Vec<KernelDriverInfo>,
     pub network_connections: Vec<NetworkConnection>,
+    #[serde(default)]
+    pub volatile_artifacts: VolatileArtifactSection,
+    #[serde(default)]
+    pub network_artifacts: NetworkArtifactSection,
     pub persistence_mechanisms: Vec<PersistenceMechanism>,
+    #[serde(default)]
+    pub persistence_artifacts: PersistenceArtifactSection,
     pub event_logs: EventLogs,
+    #[serde(default)]
+    pub event_log_configuration: Vec<EventLogChannelConfig>,
+    #[serde(default)]
+    pub execution_evidence: ExecutionEvidenceSection,
+    #[serde(default)]
+    pub resource_usage: ResourceUsageSection,
+    #[serde(default)]
+    pub device_installations: Vec<DeviceInstallation>,
+    #[serde(default)]
+    pub usb_devices: Vec<UsbDevice>,
+    #[serde(default)]
+    pub user_activity: UserActivitySection,
+    #[serde(default)]
+    pub security_configuration: Vec<SecuritySetting>,
+    #[serde(default)]
+    pub certificate_stores: Vec<CertificateEntry>,
+    #[serde(default)]
+    pub security_events: SecurityEventSection,
+    #[serde(default)]
+    pub filesystem_artifacts: FilesystemArtifactSection,
+    /// Files of the --scan-paths sweep
+    #[serde(default)]
+    pub file_sweep: Vec<SweptFile>,
+    #[serde(default)]
+    pub canaries: Vec<CanaryStatus>,
+    /// Files acquired into the evidence package
+    #[serde(default)]
+    pub collected_files: Vec<AcquiredFile>,
+    #[serde(default)]
+    pub memory_dumps: Vec<MemoryDump>,
+    #[serde(default)]
+    pub plugins: Vec<PluginArtifacts>,
+    /// Artifacts of the custom collectors, by the namespace each declares
+    #[serde(default)]
+    pub custom_artifacts: BTreeMap<String, CustomArtifacts>,
 }
 
 impl Artifacts {
@@ -106,26 +264,122 @@ impl Artifacts {
     }
 }
 
+/// Artifacts that disappear on reboot, besides processes and connections
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct VolatileArtifactSection {
+    pub named_pipes: Vec<NamedPipeInfo>,
+}
+
+/// Remote access, network configuration and name resolution artifacts
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct NetworkArtifactSection {
+    pub rdp_connections: Vec<RdpConnection>,
+    pub rdp_session_events: Vec<RdpSessionEvent>,
+    pub smb_sessions: Vec<SmbSession>,
+    pub smb_open_files: Vec<SmbOpenFile>,
+    pub network_shares: Vec<NetworkShare>,
+    pub firewall_rules: Vec<FirewallRule>,
+    pub proxy_settings: ProxySettings,
+    pub user_proxy_settings: Vec<ProxySettings>,
+    pub hosts_file: Option<HostsFile>,
+    pub winsock_providers: Vec<WinsockProvider>,
+    pub nrpt_rules: Vec<NrptRule>,
+    pub dns_servers: Vec<DnsServerSetting>,
+    pub firewall_log: Vec<FirewallLogEntry>,
+    pub dns_log: Vec<DnsLogEntry>,
+}
+
+/// Execution hijacks next to the persistence mechanisms
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct PersistenceArtifactSection {
+    pub scheduled_tasks: Vec<ScheduledTask>,
+    pub winlogon_entries: Vec<WinlogonEntry>,
+    pub image_hijacks: Vec<ImageHijack>,
+    pub appinit_dlls: Vec<AppInitEntry>,
+    pub path_hijacks: Vec<PathHijack>,
+    pub dll_hijacks: Vec<DllHijack>,
+}
+
+/// Evidence of program execution
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct ExecutionEvidenceSection {
+    pub prefetch_status: PrefetchStatus,
+    pub prefetch_files: Vec<PrefetchFile>,
+    pub shimcache_entries: Vec<ShimcacheEntry>,
+    pub amcache_entries: Vec<AmcacheEntry>,
+    pub bam_entries: Vec<BamEntry>,
+    pub user_execution_entries: Vec<UserExecutionEntry>,
+    pub defender_log_entries: Vec<DefenderLogEntry>,
+    pub historical_processes: Vec<HistoricalProcess>,
+}
+
+/// SRUM resource usage records
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct ResourceUsageSection {
+    pub app_resource_usage: Vec<SrumAppUsage>,
+    pub network_usage: Vec<SrumNetworkUsage>,
+}
+
+/// Per-user activity artifacts
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct UserActivitySection {
+    pub user_profiles: Vec<UserProfile>,
+    pub search_index_files: Vec<CacheDatabaseFile>,
+    pub thumbcache_files: Vec<CacheDatabaseFile>,
+    pub notifications: Vec<NotificationRecord>,
+    pub search_history: Vec<SearchHistoryEntry>,
+    pub mru_lists: Vec<MruEntry>,
+    pub browser_artifacts: Vec<BrowserArtifact>,
+    pub rdp_connection_history: Vec<RdpConnectionHistory>,
+    pub clipboard_history: Vec<ClipboardHistoryEntry>,
+}
+
+/// Security event sources beyond the raw event logs
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct SecurityEventSection {
+    pub powershell_log: Vec<PowershellEvent>,
+    pub sysmon_log: Vec<SysmonEvent>,
+    pub defender_log: Vec<DefenderEvent>,
+    pub defender_exclusions: Vec<DefenderExclusion>,
+}
+
+/// NTFS metadata
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct FilesystemArtifactSection {
+    pub usn_journal: Vec<UsnJournalEntry>,
+    /// $MFT exported into the evidence package (--collect-mft)
+    pub mft: Option<MftExport>,
+}
+
 /// System information and current state
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct SystemInfo {
-    /// System uptime in seconds
-    pub uptime_secs: u64,
+    pub hostname: String,
+    pub os_name: String,
+    pub os_version: String,
+    /// Architecture of the collector build (x86, x86_64, aarch64); Unknown offline
+    pub architecture: String,
+    pub current_user: String,
+    /// System uptime in hours, 0 when unknown
+    pub uptime_hours: f64,
+    /// Boot time (ISO 8601); Unknown offline
+    pub last_boot_time: String,
+    /// Physical memory in bytes
+    pub total_memory: u64,
+    /// Memory in use in bytes
+    pub used_memory: u64,
+    pub cpu_count: usize,
     /// Currently logged-on users
     pub logged_on_users: Vec<LoggedOnUser>,
 }
 
-/// Information about a logged-on user
-#[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct LoggedOnUser {
-    /// Username
-    pub username: String,
-    /// Domain or computer name
-    pub domain: String,
-    /// Logon timestamp (ISO 8601)
-    pub logon_time: String,
-}
-
 /// Information about a loaded module/DLL in a process
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ProcessModule {
@@ -139,27 +393,39 @@ pub struct ProcessModule {
     pub size: u32,
     /// Module version information (if available)
     pub version: String,
-}
-
-impl LoggedOnUser {
-    pub fn new(username: String, domain: String, logon_time: String) -> Self {
-        LoggedOnUser {
-            username,
-            domain,
-            logon_time,
-        }
-    }
+    /// Position in the loader's load-order list, 0 = the executable itself
+    #[serde(default)]
+    pub load_order: Option<u32>,
+    /// Time the loader mapped the module (ISO 8601, Windows 8 and later)
+    #[serde(default)]
+    pub load_time: Option<String>,
+    /// Why the loader mapped it (StaticDependency, DynamicLoad, ...)
+    #[serde(default)]
+    pub load_reason: Option<String>,
+    /// Seconds between process creation and the module load
+    #[serde(default)]
+    pub seconds_after_start: Option<i64>,
+    /// Located in System32, SysWOW64 or WinSxS
+    #[serde(default)]
+    pub is_system_module: bool,
 }
 
 impl ProcessModule {
     pub fn new(name: String, file_path: String, base_address: String, size: u32, version: String) -> Self {
-        ProcessModule {
+        let mut module = ProcessModule {
             name,
             file_path,
             base_address,
             size,
             version,
-        }
+            load_order: None,
+            load_time: None,
+            load_reason: None,
+            seconds_after_start: None,
+            is_system_module: false,
+        };
+        module.is_system_module = module.is_system_module();
+        module
     }
     
     /// Check if this is a system module (located in Windows system directories)
@@ -171,6 +437,87 @@ impl ProcessModule {
     }
 }
 
+/// Account referenced by an access token
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TokenAccount {
+    /// String SID (S-1-5-18, ...)
+    pub sid: String,
+    /// DOMAIN\name, None when the SID does not resolve
+    pub name: Option<String>,
+}
+
+/// Security context of a process from its primary token
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct ProcessToken {
+    pub user: Option<TokenAccount>,
+    /// Enabled groups, without logon session and deny-only SIDs
+    pub groups: Vec<TokenAccount>,
+    /// Privileges currently enabled (SeDebugPrivilege, SeImpersonatePrivilege, ...)
+    pub enabled_privileges: Vec<String>,
+    /// UAC elevated (full administrator token)
+    pub elevated: bool,
+    /// Default, Full or Limited
+    pub elevation_type: Option<String>,
+    /// Other users impersonated by threads of the process
+    pub impersonated_users: Vec<TokenAccount>,
+}
+
+/// Executable memory and thread start addresses of a process, from the deep scan
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct MemoryRegionSummary {
+    /// Committed regions walked with VirtualQueryEx
+    pub committed_regions: usize,
+    /// Committed regions mapped from an image (EXE/DLL)
+    pub image_regions: usize,
+    /// Executable committed regions not backed by an image
+    pub unbacked_executable_regions: usize,
+    /// Bytes of read-write-execute memory not backed by a file
+    pub rwx_unbacked_bytes: u64,
+    /// Threads whose start address was resolved
+    pub threads_scanned: usize,
+    /// Threads starting outside every loaded module
+    pub unbacked_threads: usize,
+    /// Unbacked RWX regions and thread start regions, capped per process
+    pub suspicious_regions: Vec<SuspiciousMemoryRegion>,
+}
+
+/// A memory region flagged by the deep process scan
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SuspiciousMemoryRegion {
+    /// Region base address (hex)
+    pub base_address: String,
+    pub size: u64,
+    /// Page protection, e.g. "PAGE_EXECUTE_READWRITE"
+    pub protection: String,
+    /// MEM_PRIVATE, MEM_MAPPED or MEM_IMAGE
+    pub region_type: String,
+    /// Backing file of a mapped region, if any
+    pub mapped_file: Option<String>,
+    /// IDs of threads starting inside the region
+    pub thread_ids: Vec<u32>,
+}
+
+/// Node of the reconstructed process tree
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ProcessTreeNode {
+    pub pid: u32,
+    pub parent_pid: u32,
+    pub name: String,
+    pub creation_time: Option<String>,
+    pub suspicious_indicators: Vec<String>,
+    pub children: Vec<ProcessTreeNode>,
+}
+
+/// Process tree reconstructed from PID/PPID and creation times
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ProcessTree {
+    /// Processes without a live parent (system roots, orphans, PPID reuse victims)
+    pub roots: Vec<ProcessTreeNode>,
+    pub orphaned_pids: Vec<u32>,
+    pub ppid_anomaly_pids: Vec<u32>,
+    pub suspicious_pair_pids: Vec<u32>,
+}
+
 /// Information about a running process
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Process {
@@ -192,6 +539,39 @@ pub struct Process {
     pub memory_usage_mb: f64,
     /// Loaded DLLs and modules
     pub loaded_modules: Vec<ProcessModule>,
+    /// Process creation time (ISO 8601), None when access is denied
+    #[serde(default)]
+    pub creation_time: Option<String>,
+    /// Terminal Services session ID
+    #[serde(default)]
+    pub session_id: Option<u32>,
+    /// Token integrity level (Untrusted, Low, Medium, High, System, ...)
+    #[serde(default)]
+    pub integrity_level: Option<String>,
+    /// Findings of the process tree analysis (orphaned, PPID reuse, unusual parent)
+    #[serde(default)]
+    pub suspicious_indicators: Vec<String>,
+    /// The image file backing the process no longer exists on disk
+    #[serde(default)]
+    pub executable_deleted: bool,
+    /// Weighted suspicion score, 0-100
+    #[serde(default)]
+    pub suspicion_score: u32,
+    /// Indicators contributing to the score, heaviest first
+    #[serde(default)]
+    pub suspicion_reasons: Vec<String>,
+    /// Digests of the executable selected with --hash-algorithms
+    #[serde(default)]
+    pub hashes: Option<FileHashes>,
+    /// Thread start and executable memory summary of the --deep-process-scan
+    #[serde(default)]
+    pub memory_regions: Option<MemoryRegionSummary>,
+    /// Primary token: user, enabled groups and privileges, elevation, impersonation
+    #[serde(default)]
+    pub token: Option<ProcessToken>,
+    /// Verdict of the --enrich sources on the executable's digests
+    #[serde(default)]
+    pub threat_intelligence: Option<ThreatIntel>,
 }
 
 impl Process {
@@ -206,6 +586,17 @@ impl Process {
             user: String::new(), // Will be populated separately
             memory_usage_mb: 0.0, // Will be populated separately
             loaded_modules: Vec::new(), // Will be populated separately
+            creation_time: None,
+            session_id: None,
+            integrity_level: None,
+            suspicious_indicators: Vec::new(),
+            executable_deleted: false,
+            suspicion_score: 0,
+            suspicion_reasons: Vec::new(),
+            hashes: None,
+            memory_regions: None,
+            token: None,
+            threat_intelligence: None,
         }
     }
     
@@ -220,6 +611,17 @@ impl Process {
             user,
             memory_usage_mb,
             loaded_modules: Vec::new(), // Will be populated separately
+            creation_time: None,
+            session_id: None,
+            integrity_level: None,
+            suspicious_indicators: Vec::new(),
+            executable_deleted: false,
+            suspicion_score: 0,
+            suspicion_reasons: Vec::new(),
+            hashes: None,
+            memory_regions: None,
+            token: None,
+            threat_intelligence: None,
         }
     }
     
@@ -253,6 +655,16 @@ pub struct NetworkConnection {
     pub owning_pid: u32,
     /// Process name that owns this connection
     pub process_name: String,
+    /// The remote end is not loopback or unspecified
+    #[serde(default)]
+    pub is_external: bool,
+    /// Location, network owner and reputation of the remote address (--enrich)
+    #[serde(default)]
+    pub geolocation: Option<GeoLocation>,
+    #[serde(default)]
+    pub asn: Option<AsnInfo>,
+    #[serde(default)]
+    pub threat_intelligence: Option<ThreatIntel>,
 }
 
 impl NetworkConnection {
@@ -265,11 +677,15 @@ impl NetworkConnection {
             protocol,
             local_address: local_addr,
             local_port,
+            is_external: is_external_address(&remote_addr),
             remote_address: remote_addr,
             remote_port,
             state,
             owning_pid,
             process_name: String::new(), // Will be populated separately
+            geolocation: None,
+            asn: None,
+            threat_intelligence: None,
         }
     }
     
@@ -278,23 +694,31 @@ impl NetworkConnection {
             protocol,
             local_address,
             local_port,
+            is_external: is_external_address(&remote_address),
             remote_address,
             remote_port,
             state,
             owning_pid,
             process_name,
+            geolocation: None,
+            asn: None,
+            threat_intelligence: None,
         }
     }
     
     /// Check if this is an external connection (not localhost)
     pub fn is_external(&self) -> bool {
-        !self.remote_address.starts_with("127.0.0.1") &&
-        !self.remote_address.starts_with("::1") &&
-        !self.remote_address.starts_with("0.0.0.0") &&
-        self.remote_address != "*"
+        is_external_address(&self.remote_address)
     }
 }
 
+fn is_external_address(remote_address: &str) -> bool {
+    !remote_address.starts_with("127.0.0.1") &&
+    !remote_address.starts_with("::1") &&
+    !remote_address.starts_with("0.0.0.0") &&
+    remote_address != "*"
+}
+
 /// Extract IP address and port from a string like "192.168.1.1:8080"
 fn extract_address_and_port(addr_port: &str) -> (String, u16) {
     if let Some(last_colon) = addr_port.rfind(':') {
@@ -323,8 +747,22 @@ pub struct PersistenceMechanism {
     pub location: String,
     /// Value of the persistence mechanism
     pub value: String,
-    /// Whether this mechanism is suspicious
+    /// Whether the suspicion score reaches the scoring threshold
     pub is_suspicious: bool,
+    /// Weighted suspicion score, 0-100
+    #[serde(default)]
+    pub suspicion_score: u32,
+    /// Indicators contributing to the score, heaviest first
+    #[serde(default)]
+    pub suspicion_reasons: Vec<String>,
+    /// User profile the mechanism belongs to (per-user Run keys and Startup folders)
+    pub user: Option<String>,
+    /// Digests of the executable the mechanism launches, when it exists on disk
+    #[serde(default)]
+    pub hashes: Option<FileHashes>,
+    /// MITRE ATT&CK techniques the mechanism implements or suggests
+    #[serde(default)]
+    pub attack_techniques: Vec<AttackTechnique>,
 }
 
 impl PersistenceMechanism {
@@ -337,6 +775,11 @@ impl PersistenceMechanism {
             location: String::new(), // Will be populated separately
             value: String::new(), // Will be populated separately
             is_suspicious: false, // Will be analyzed separately
+            suspicion_score: 0,
+            suspicion_reasons: Vec::new(),
+            user: None,
+            hashes: None,
+            attack_techniques: Vec::new(),
         }
     }
     
@@ -349,8 +792,18 @@ impl PersistenceMechanism {
             location,
             value,
             is_suspicious,
+            suspicion_score: 0,
+            suspicion_reasons: Vec::new(),
+            user: None,
+            hashes: None,
+            attack_techniques: Vec::new(),
         }
     }
+    
+    pub fn with_user(mut self, user: &str) -> Self {
+        self.user = Some(user.to_string());
+        self
+    }
 }
 
 /// Common persistence mechanism types
@@ -383,12 +836,16 @@ pub struct EventLogs {
     pub system: Vec<EventLogEntry>,
     /// Application event log entries
     pub application: Vec<EventLogEntry>,
+    /// Entries of any other collected channel (Sysmon, PowerShell, Defender, ...), keyed by channel name
+    #[serde(default)]
+    pub channels: BTreeMap<String, Vec<EventLogEntry>>,
 }
 
 impl EventLogs {
     /// Get total count of all event log entries
     pub fn total_entries(&self) -> usize {
         self.security.len() + self.system.len() + self.application.len()
+            + self.channels.values().map(|entries| entries.len()).sum::<usize>()
     }
 }
 
@@ -403,8 +860,20 @@ pub struct EventLogEntry {
     pub timestamp: String,
     /// Event message/description
     pub message: String,
-    /// Event log source (Security, System, Application)
+    /// Event log channel (Security, System, Application, Microsoft-Windows-Sysmon/Operational, ...)
     pub source: String,
+    /// Event record number within the channel
+    #[serde(default)]
+    pub record_id: u64,
+    /// Event provider name
+    #[serde(default)]
+    pub provider: String,
+    /// Computer that logged the event
+    #[serde(default)]
+    pub computer: String,
+    /// Named EventData fields (e.g. TargetUserName, CommandLine)
+    #[serde(default)]
+    pub event_data: BTreeMap<String, String>,
 }
 
 impl EventLogEntry {
@@ -415,6 +884,10 @@ impl EventLogEntry {
             timestamp,
             message,
             source: "Unknown".to_string(),
+            record_id: 0,
+            provider: String::new(),
+            computer: String::new(),
+            event_data: BTreeMap::new(),
         }
     }
     
@@ -425,6 +898,10 @@ impl EventLogEntry {
             timestamp,
             message,
             source,
+            record_id: 0,
+            provider: String::new(),
+            computer: String::new(),
+            event_data: BTreeMap::new(),
         }
     }
 }
@@ -0,0 +1,294 @@
+//! USB device history
+//! Every USB storage device ever attached leaves an instance key below
+//! Enum\USBSTOR named by its serial number, with the first install, last
+//! arrival and last removal times among its device properties. The matching
+//! Enum\USB key gives the vendor and product IDs, MountedDevices the drive
+//! letter and volume GUID it was last given, VolumeInfoCache the label of the
+//! volume last seen at that letter, and setupapi.dev.log the first install
+//! when the properties cannot be read (they are SYSTEM-only on a live host).
+
+use crate::binutil::filetime_to_string;
+use crate::forensic_types::{AuditEntry, DeviceInstallation, UsbDevice};
+use crate::offline::{MachineRegistry, OfflineRegistry, OfflineRoot};
+
+#[cfg(windows)]
+use crate::offline::LiveRegistry;
+#[cfg(windows)]
+use winreg::enums::HKEY_LOCAL_MACHINE;
+#[cfg(windows)]
+use winreg::RegKey;
+
+const COMPONENT: &str = "usb_devices";
+
+const USBSTOR_KEY: &str = "SYSTEM\\CurrentControlSet\\Enum\\USBSTOR";
+const USB_KEY: &str = "SYSTEM\\CurrentControlSet\\Enum\\USB";
+const MOUNTED_DEVICES_KEY: &str = "SYSTEM\\MountedDevices";
+const VOLUME_INFO_CACHE_KEY: &str = "SOFTWARE\\Microsoft\\Windows Search\\VolumeInfoCache";
+/// DEVPKEY_Device_InstallDate and friends, below an instance key
+const DEVICE_PROPERTIES_KEY: &str = "Properties\\{83da6326-97a6-4088-9453-a1923f573b29}";
+const PROPERTY_FIRST_INSTALL: &str = "0064";
+const PROPERTY_LAST_ARRIVAL: &str = "0066";
+const PROPERTY_LAST_REMOVAL: &str = "0067";
+
+/// USB storage history of the running system
+#[cfg(windows)]
+pub fn collect_usb_devices(installations: &[DeviceInstallation]) -> (Vec<UsbDevice>, Vec<AuditEntry>) {
+    let start_time = std::time::Instant::now();
+    let mut audit_log = vec![AuditEntry::new(COMPONENT, "INFO", "start_collection", "Starting USB device history collection".to_string(), "started")];
+    let devices = usb_devices(&LiveRegistry(RegKey::predef(HKEY_LOCAL_MACHINE)), installations, &mut audit_log);
+    audit_log.push(completed(&devices, start_time));
+    (devices, audit_log)
+}
+
+#[cfg(not(windows))]
+pub fn collect_usb_devices(_installations: &[DeviceInstallation]) -> (Vec<UsbDevice>, Vec<AuditEntry>) {
+    let audit_log = vec![
+        AuditEntry::new(COMPONENT, "INFO", "start_collection", "Starting USB device history collection".to_string(), "started"),
+        AuditEntry::new(COMPONENT, "WARN", "registry_access", "USB device history requires the Windows registry".to_string(), "unsupported"),
+        AuditEntry::new(COMPONENT, "INFO", "complete_collection", "Collected 0 USB devices".to_string(), "success"),
+    ];
+    (Vec::new(), audit_log)
+}
+
+/// USB storage history from the SYSTEM and SOFTWARE hives of a mounted Windows volume
+pub fn collect_usb_devices_offline(root: &OfflineRoot, installations: &[DeviceInstallation]) -> (Vec<UsbDevice>, Vec<AuditEntry>) {
+    let start_time = std::time::Instant::now();
+    let mut audit_log = vec![AuditEntry::new(
+        COMPONENT,
+        "INFO",
+        "start_collection",
+        format!("Starting offline USB device history collection of {}", root.root().display()),
+        "started",
+    )];
+    let mut open = |name: &str| match root.open_hive(name) {
+        Ok(hive) => Some(hive),
+        Err(e) => {
+            audit_log.push(AuditEntry::new(COMPONENT, "ERROR", "load_hive", format!("Failed to load offline {} hive: {}", name, e), "error"));
+            None
+        }
+    };
+    let (software, system) = (open("SOFTWARE"), open("SYSTEM"));
+    if system.is_none() {
+        return (Vec::new(), audit_log);
+    }
+
+    let devices = usb_devices(&OfflineRegistry::new(software, system), installations, &mut audit_log);
+    audit_log.push(completed(&devices, start_time));
+    (devices, audit_log)
+}
+
+fn usb_devices(registry: &dyn MachineRegistry, installations: &[DeviceInstallation], audit_log: &mut Vec<AuditEntry>) -> Vec<UsbDevice> {
+    let mounted_devices: Vec<(String, String)> = registry
+        .values(MOUNTED_DEVICES_KEY)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|value| (value.name, utf16_lossy(&value.data).to_lowercase()))
+        .collect();
+    let usb_instances: Vec<(String, String)> = registry
+        .subkeys(USB_KEY)
+        .into_iter()
+        .flat_map(|hardware_id| {
+            let instances = registry.subkeys(&format!("{}\\{}", USB_KEY, hardware_id));
+            instances.into_iter().map(move |instance| (hardware_id.clone(), instance))
+        })
+        .collect();
+
+    let mut devices = Vec::new();
+    for device_id in registry.subkeys(USBSTOR_KEY) {
+        let device_key = format!("{}\\{}", USBSTOR_KEY, device_id);
+        for instance in registry.subkeys(&device_key) {
+            let instance_key = format!("{}\\{}", device_key, instance);
+            let serial_number = strip_lun(&instance).to_string();
+            let value = |name: &str| registry.value(&instance_key, name);
+            let property = |id: &str| device_property(registry, &format!("{}\\{}", instance_key, DEVICE_PROPERTIES_KEY), id);
+            let (vendor, product, revision) = split_device_id(&device_id);
+
+            let (vendor_id, product_id) = usb_instances
+                .iter()
+                .find(|(_, usb_instance)| usb_instance.eq_ignore_ascii_case(&serial_number))
+                .map(|(hardware_id, _)| split_hardware_id(hardware_id))
+                .unwrap_or_default();
+
+            // MountedDevices data names the device interface: _??_USBSTOR#Disk&Ven_...#<instance>#{guid}
+            let interface = format!("#{}#", instance.to_lowercase());
+            let mounts: Vec<&String> = mounted_devices.iter().filter(|(_, data)| data.contains(&interface)).map(|(name, _)| name).collect();
+            let drive_letters: Vec<String> = mounts.iter().filter_map(|name| name.strip_prefix("\\DosDevices\\")).map(String::from).collect();
+            let volume_guids: Vec<String> = mounts.iter().filter_map(|name| name.strip_prefix("\\??\\Volume")).map(String::from).collect();
+            let volume_labels: Vec<String> = drive_letters
+                .iter()
+                .filter_map(|letter| {
+                    let label = registry.value(&format!("{}\\{}", VOLUME_INFO_CACHE_KEY, letter), "VolumeLabel")?;
+                    label.as_string().filter(|label| !label.is_empty())
+                })
+                .collect();
+
+            let first_connected = property(PROPERTY_FIRST_INSTALL).unwrap_or_else(|| setupapi_first_install(installations, &serial_number));
+            devices.push(UsbDevice {
+                vendor,
+                product,
+                revision,
+                serial_generated: serial_number.chars().nth(1) == Some('&'),
+                friendly_name: value("FriendlyName").and_then(|value| value.as_string()).unwrap_or_default(),
+                vendor_id,
+                product_id,
+                first_connected,
+                last_connected: property(PROPERTY_LAST_ARRIVAL).unwrap_or_else(|| registry.last_written(&instance_key)),
+                last_removed: property(PROPERTY_LAST_REMOVAL).unwrap_or_default(),
+                drive_letters,
+                volume_guids,
+                volume_labels,
+                device_id: device_id.clone(),
+                serial_number,
+            });
+        }
+    }
+
+    audit_log.push(AuditEntry::new(
+        COMPONENT,
+        "DEBUG",
+        "registry_scan",
+        format!("Matched {} USB storage instances against {} mounted devices", devices.len(), mounted_devices.len()),
+        "success",
+    ));
+    devices.sort_by(|a, b| a.first_connected.cmp(&b.first_connected));
+    devices
+}
+
+/// FILETIME device property: the default value of `<id>` on Windows 8 and
+/// later, `000000<id>\00000000\Data` on Windows 7
+fn device_property(registry: &dyn MachineRegistry, properties_key: &str, id: &str) -> Option<String> {
+    let value = registry
+        .value(&format!("{}\\{}", properties_key, id), "")
+        .or_else(|| registry.value(&format!("{}\\0000{}\\00000000", properties_key, id), "Data"))?;
+    let filetime = u64::from_le_bytes(value.data.get(..8)?.try_into().ok()?);
+    Some(filetime_to_string(filetime)).filter(|time| !time.is_empty())
+}
+
+/// Earliest setupapi installation of a device whose instance ID ends in the serial
+fn setupapi_first_install(installations: &[DeviceInstallation], serial_number: &str) -> String {
+    let serial_number = serial_number.to_lowercase();
+    installations
+        .iter()
+        .filter(|installation| {
+            let target = installation.target.to_lowercase();
+            (target.starts_with("usbstor\\") || target.starts_with("usb\\")) && target.rsplit('\\').next().is_some_and(|instance| strip_lun(instance) == serial_number)
+        })
+        .map(|installation| installation.start_time.clone())
+        .filter(|start_time| !start_time.is_empty())
+        .min()
+        .unwrap_or_default()
+}
+
+/// USBSTOR instance keys are the serial followed by the LUN (`&0`)
+fn strip_lun(instance: &str) -> &str {
+    match instance.rsplit_once('&') {
+        Some((serial, lun)) if !lun.is_empty() && lun.chars().all(|c| c.is_ascii_digit()) => serial,
+        _ => instance,
+    }
+}
+
+/// Disk&Ven_SanDisk&Prod_Cruzer_Blade&Rev_1.00 → (SanDisk, Cruzer_Blade, 1.00)
+fn split_device_id(device_id: &str) -> (String, String, String) {
+    let part = |prefix: &str| {
+        device_id
+            .split('&')
+            .find_map(|part| part.strip_prefix(prefix))
+            .unwrap_or_default()
+            .to_string()
+    };
+    (part("Ven_"), part("Prod_"), part("Rev_"))
+}
+
+/// VID_0781&PID_5567 → (0781, 5567)
+fn split_hardware_id(hardware_id: &str) -> (String, String) {
+    let part = |prefix: &str| {
+        hardware_id
+            .split('&')
+            .find_map(|part| part.to_uppercase().strip_prefix(prefix).map(String::from))
+            .unwrap_or_default()
+    };
+    (part("VID_"), part("PID_"))
+}
+
+fn utf16_lossy(data: &[u8]) -> String {
+    let units: Vec<u16> = data.chunks_exact(2).map(|unit| u16::from_le_bytes([unit[0], unit[1]])).collect();
+    String::from_utf16_lossy(&units)
+}
+
+fn completed(devices: &[UsbDevice], start_time: std::time::Instant) -> AuditEntry {
+    let mut entry = AuditEntry::new(COMPONENT, "INFO", "complete_collection", format!("Collected {} USB devices", devices.len()), "success");
+    entry.duration_ms = Some(start_time.elapsed().as_millis() as u64);
+    entry
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hive::{HiveBuilder, REG_BINARY};
+
+    #[test]
+    fn test_offline_usb_history() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config = temp_dir.path().join("Windows").join("System32").join("config");
+        std::fs::create_dir_all(&config).unwrap();
+
+        let stick = "ControlSet001\\Enum\\USBSTOR\\Disk&Ven_SanDisk&Prod_Cruzer_Blade&Rev_1.00\\4C530001230605101453&0";
+        let generated = "ControlSet001\\Enum\\USBSTOR\\Disk&Ven_Generic&Prod_Flash_Disk&Rev_8.07\\7&2a8e7b6d&0&0";
+        let properties = format!("{}\\{}", stick, DEVICE_PROPERTIES_KEY);
+        let interface = "_??_USBSTOR#Disk&Ven_SanDisk&Prod_Cruzer_Blade&Rev_1.00#4C530001230605101453&0#{53f56307-b6bf-11d0-94f2-00a0c91efb8b}";
+        let interface: Vec<u8> = interface.encode_utf16().flat_map(u16::to_le_bytes).collect();
+        let system = HiveBuilder::default()
+            .dword("Select", "Current", 1)
+            .string(stick, "FriendlyName", "SanDisk Cruzer Blade USB Device")
+            .value(&format!("{}\\0064", properties), "", 0xFFFF_0010, &133_580_000_000_000_000u64.to_le_bytes())
+            .value(&format!("{}\\0066", properties), "", 0xFFFF_0010, &133_580_036_000_000_000u64.to_le_bytes())
+            .key(generated, 132_539_328_000_000_000)
+            .key("ControlSet001\\Enum\\USB\\VID_0781&PID_5567\\4C530001230605101453", 0)
+            .value("MountedDevices", "\\DosDevices\\E:", REG_BINARY, &interface)
+            .value("MountedDevices", "\\??\\Volume{0a1b2c3d-0000-0000-0000-000000000001}", REG_BINARY, &interface)
+            .value("MountedDevices", "\\DosDevices\\C:", REG_BINARY, &[0x12, 0x34, 0x56, 0x78, 0, 0, 0x10, 0, 0, 0, 0, 0])
+            .build();
+        let software = HiveBuilder::default()
+            .string("Microsoft\\Windows Search\\VolumeInfoCache\\E:", "VolumeLabel", "EXFIL")
+            .build();
+        std::fs::write(config.join("SYSTEM"), system).unwrap();
+        std::fs::write(config.join("SOFTWARE"), software).unwrap();
+        let root = OfflineRoot::new(temp_dir.path()).unwrap();
+
+        let mut installation = DeviceInstallation {
+            section: "Device Install (Hardware initiated)".to_string(),
+            target: "USBSTOR\\Disk&Ven_Generic&Prod_Flash_Disk&Rev_8.07\\7&2a8e7b6d&0&0".to_string(),
+            start_time: "2020-12-31T23:59:00.000".to_string(),
+            end_time: String::new(),
+            exit_status: String::new(),
+            inf_file: String::new(),
+            driver_description: String::new(),
+            signer_name: String::new(),
+            signer_score: String::new(),
+            signed: None,
+            driver_files: Vec::new(),
+            services: Vec::new(),
+            flags: Vec::new(),
+            source_file: String::new(),
+        };
+        let (devices, audit_log) = collect_usb_devices_offline(&root, &[installation.clone()]);
+        assert_eq!(devices.len(), 2);
+
+        let generic = &devices[0];
+        assert_eq!((generic.serial_number.as_str(), generic.serial_generated), ("7&2a8e7b6d&0", true));
+        assert_eq!((generic.first_connected.as_str(), generic.last_connected.as_str()), ("2020-12-31T23:59:00.000", "2021-01-01T00:00:00+00:00"));
+
+        let stick = &devices[1];
+        assert_eq!((stick.vendor.as_str(), stick.product.as_str(), stick.revision.as_str()), ("SanDisk", "Cruzer_Blade", "1.00"));
+        assert_eq!((stick.vendor_id.as_str(), stick.product_id.as_str()), ("0781", "5567"));
+        assert_eq!(stick.friendly_name, "SanDisk Cruzer Blade USB Device");
+        assert_eq!((stick.first_connected.as_str(), stick.last_connected.as_str()), ("2024-04-19T11:33:20+00:00", "2024-04-19T12:33:20+00:00"));
+        assert_eq!(stick.drive_letters, vec!["E:"]);
+        assert_eq!(stick.volume_guids, vec!["{0a1b2c3d-0000-0000-0000-000000000001}"]);
+        assert_eq!(stick.volume_labels, vec!["EXFIL"]);
+        assert!(audit_log.iter().any(|log| log.action == "complete_collection" && log.details == "Collected 2 USB devices"));
+
+        installation.target = "PCI\\VEN_8086&DEV_A2AF\\3&11583659&0&A0".to_string();
+        assert!(setupapi_first_install(&[installation], "7&2a8e7b6d&0").is_empty());
+    }
+}
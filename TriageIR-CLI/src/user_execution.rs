@@ -0,0 +1,248 @@
+//! Per-user program execution traces
+//! The shell caches the display name and company of every executable it
+//! launches in MUICache (UsrClass.dat), and the Program Compatibility Assistant
+//! records each program it monitored in its Store (NTUSER.DAT). Both are kept
+//! per user and outlive the prefetch and shimcache entries of a program.
+
+// Value decoding helpers are only reachable through the Windows registry API
+#![cfg_attr(not(windows), allow(dead_code))]
+
+use crate::forensic_types::{AuditEntry, UserExecutionEntry};
+use crate::offline::OfflineRoot;
+
+#[cfg(windows)]
+use crate::footprint::TrackedKey;
+#[cfg(windows)]
+use crate::offline;
+#[cfg(windows)]
+use crate::user_hives::{self, UserHive};
+
+const COMPONENT: &str = "user_execution";
+
+/// MUICache below the classes hive (HKCR\Local Settings\... for the user)
+const MUICACHE_KEY: &str = "Local Settings\\Software\\Microsoft\\Windows\\Shell\\MuiCache";
+const COMPATIBILITY_STORE_KEY: &str = "Software\\Microsoft\\Windows NT\\CurrentVersion\\AppCompatFlags\\Compatibility Assistant\\Store";
+
+const FRIENDLY_NAME_SUFFIX: &str = ".FriendlyAppName";
+const COMPANY_SUFFIX: &str = ".ApplicationCompany";
+
+/// Collect MUICache and Compatibility Assistant entries for every user profile on the live system
+#[cfg(windows)]
+pub fn collect_user_execution() -> (Vec<UserExecutionEntry>, Vec<AuditEntry>) {
+    let (class_hives, mut failures) = user_hives::live_user_class_hives();
+    let (user_hives, user_failures) = user_hives::live_user_hives();
+    failures.extend(user_failures);
+    collect_from_user_hives(class_hives, user_hives, failures)
+}
+
+#[cfg(not(windows))]
+pub fn collect_user_execution() -> (Vec<UserExecutionEntry>, Vec<AuditEntry>) {
+    registry_unavailable()
+}
+
+/// Collect MUICache and Compatibility Assistant entries from the UsrClass.dat
+/// and NTUSER.DAT hives of a mounted Windows volume
+#[cfg(windows)]
+pub fn collect_user_execution_offline(root: &OfflineRoot) -> (Vec<UserExecutionEntry>, Vec<AuditEntry>) {
+    let (class_hives, mut failures) = user_hives::offline_user_class_hives(root);
+    let (user_hives, user_failures) = user_hives::offline_user_hives(root);
+    failures.extend(user_failures);
+    collect_from_user_hives(class_hives, user_hives, failures)
+}
+
+#[cfg(not(windows))]
+pub fn collect_user_execution_offline(_root: &OfflineRoot) -> (Vec<UserExecutionEntry>, Vec<AuditEntry>) {
+    registry_unavailable()
+}
+
+#[cfg(not(windows))]
+fn registry_unavailable() -> (Vec<UserExecutionEntry>, Vec<AuditEntry>) {
+    let audit_log = vec![
+        AuditEntry::new(COMPONENT, "INFO", "start_collection", "Starting MUICache and Compatibility Assistant analysis".to_string(), "started"),
+        AuditEntry::new(COMPONENT, "WARN", "load_user_hive", "User hives require the Windows registry".to_string(), "unsupported"),
+        AuditEntry::new(COMPONENT, "INFO", "complete_collection", "Collected 0 user execution entries".to_string(), "success"),
+    ];
+    (Vec::new(), audit_log)
+}
+
+#[cfg(windows)]
+fn collect_from_user_hives(
+    class_hives: Vec<UserHive>,
+    user_hives: Vec<UserHive>,
+    failures: Vec<String>,
+) -> (Vec<UserExecutionEntry>, Vec<AuditEntry>) {
+    let mut entries = Vec::new();
+    let start_time = std::time::Instant::now();
+
+    let mut audit_log = vec![AuditEntry::new(
+        COMPONENT,
+        "INFO",
+        "start_collection",
+        format!(
+            "Starting MUICache and Compatibility Assistant analysis for {} classes and {} user hives",
+            class_hives.len(),
+            user_hives.len()
+        ),
+        "started",
+    )];
+
+    for failure in failures {
+        audit_log.push(AuditEntry::new(COMPONENT, "WARN", "load_user_hive", failure, "error"));
+    }
+
+    for hive in &class_hives {
+        let muicache = collect_muicache(hive);
+        audit_log.push(AuditEntry::new(
+            COMPONENT,
+            "DEBUG",
+            "parse_user_hive",
+            format!("{}: {} MUICache programs", hive.user, muicache.len()),
+            "success",
+        ));
+        entries.extend(muicache);
+    }
+
+    for hive in &user_hives {
+        let store = collect_compatibility_store(hive);
+        audit_log.push(AuditEntry::new(
+            COMPONENT,
+            "DEBUG",
+            "parse_user_hive",
+            format!("{}: {} Compatibility Assistant programs", hive.user, store.len()),
+            "success",
+        ));
+        entries.extend(store);
+    }
+
+    let mut complete = AuditEntry::new(
+        COMPONENT,
+        "INFO",
+        "complete_collection",
+        format!("Collected {} user execution entries", entries.len()),
+        "success",
+    );
+    complete.duration_ms = Some(start_time.elapsed().as_millis() as u64);
+    audit_log.push(complete);
+
+    (entries, audit_log)
+}
+
+#[cfg(windows)]
+fn collect_muicache(hive: &UserHive) -> Vec<UserExecutionEntry> {
+    let key = match hive.key.open_tracked(MUICACHE_KEY) {
+        Ok(key) => key,
+        Err(_) => return Vec::new(),
+    };
+
+    let values: Vec<(String, String)> = key
+        .enum_values()
+        .filter_map(|v| v.ok())
+        .map(|(name, value)| (name, value.to_string()))
+        .collect();
+    let last_write = offline::key_last_write_time(&key);
+
+    parse_muicache_values(&values)
+        .into_iter()
+        .map(|program| UserExecutionEntry {
+            source: "MUICache".to_string(),
+            user: hive.user.clone(),
+            user_sid: hive.sid.clone(),
+            executable: program.executable,
+            friendly_name: program.friendly_name,
+            company: program.company,
+            key_last_write: last_write.clone(),
+            registry_path: format!("{}\\{}", hive.source, MUICACHE_KEY),
+        })
+        .collect()
+}
+
+/// Value names are the executable paths; the binary data holds no usable timestamp
+#[cfg(windows)]
+fn collect_compatibility_store(hive: &UserHive) -> Vec<UserExecutionEntry> {
+    let key = match hive.key.open_tracked(COMPATIBILITY_STORE_KEY) {
+        Ok(key) => key,
+        Err(_) => return Vec::new(),
+    };
+
+    let last_write = offline::key_last_write_time(&key);
+    key.enum_values()
+        .filter_map(|v| v.ok())
+        .map(|(name, _)| UserExecutionEntry {
+            source: "CompatibilityAssistantStore".to_string(),
+            user: hive.user.clone(),
+            user_sid: hive.sid.clone(),
+            executable: name,
+            friendly_name: None,
+            company: None,
+            key_last_write: last_write.clone(),
+            registry_path: format!("{}\\{}", hive.source, COMPATIBILITY_STORE_KEY),
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct MuiCacheProgram {
+    executable: String,
+    friendly_name: Option<String>,
+    company: Option<String>,
+}
+
+/// Group the `<path>.FriendlyAppName` and `<path>.ApplicationCompany` values
+/// by executable, in registry order. LangID and the `@dll,-id` resource string
+/// entries of the shell itself are not programs and are skipped.
+fn parse_muicache_values(values: &[(String, String)]) -> Vec<MuiCacheProgram> {
+    let mut programs: Vec<MuiCacheProgram> = Vec::new();
+
+    for (name, data) in values {
+        let (executable, is_company) = if let Some(path) = name.strip_suffix(FRIENDLY_NAME_SUFFIX) {
+            (path, false)
+        } else if let Some(path) = name.strip_suffix(COMPANY_SUFFIX) {
+            (path, true)
+        } else {
+            continue;
+        };
+        if executable.is_empty() || executable.starts_with('@') {
+            continue;
+        }
+
+        let index = match programs.iter().position(|p| p.executable.eq_ignore_ascii_case(executable)) {
+            Some(index) => index,
+            None => {
+                programs.push(MuiCacheProgram { executable: executable.to_string(), friendly_name: None, company: None });
+                programs.len() - 1
+            }
+        };
+        let field = if is_company { &mut programs[index].company } else { &mut programs[index].friendly_name };
+        *field = Some(data.clone()).filter(|d| !d.is_empty());
+    }
+
+    programs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_muicache_values() {
+        let value = |name: &str, data: &str| (name.to_string(), data.to_string());
+        let values = vec![
+            value("LangID", "\u{9}\u{4}"),
+            value("@C:\\Windows\\System32\\shell32.dll,-22579", "Control Panel"),
+            value("C:\\Users\\alice\\Downloads\\PsExec64.exe.FriendlyAppName", "Execute processes remotely"),
+            value("C:\\Users\\alice\\Downloads\\PsExec64.exe.ApplicationCompany", "Sysinternals - www.sysinternals.com"),
+            value("C:\\Users\\Public\\rclone.exe.FriendlyAppName", ""),
+            value("c:\\users\\alice\\downloads\\psexec64.exe.ApplicationCompany", "Sysinternals"),
+        ];
+
+        let programs = parse_muicache_values(&values);
+        assert_eq!(programs.len(), 2);
+        assert_eq!(programs[0].executable, "C:\\Users\\alice\\Downloads\\PsExec64.exe");
+        assert_eq!(programs[0].friendly_name.as_deref(), Some("Execute processes remotely"));
+        assert_eq!(programs[0].company.as_deref(), Some("Sysinternals"));
+        assert_eq!(
+            programs[1],
+            MuiCacheProgram { executable: "C:\\Users\\Public\\rclone.exe".to_string(), friendly_name: None, company: None }
+        );
+    }
+}
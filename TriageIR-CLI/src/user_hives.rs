@@ -0,0 +1,311 @@
+//! Per-user registry hive access
+//! On a live system the hives of logged-on users are mounted under HKEY_USERS
+//! and the hives of every other profile are loaded from disk; on an offline
+//! volume each profile's NTUSER.DAT is loaded as an application hive.
+//! Windows only: both need the registry API.
+
+// SID and profile helpers are only reachable through the Windows registry API
+#![cfg_attr(not(windows), allow(dead_code))]
+
+#[cfg(windows)]
+use crate::footprint::{self, TrackedKey};
+#[cfg(windows)]
+use crate::offline::{self, OfflineRoot};
+#[cfg(windows)]
+use std::collections::HashMap;
+#[cfg(windows)]
+use std::path::Path;
+#[cfg(windows)]
+use winreg::enums::*;
+#[cfg(windows)]
+use winreg::RegKey;
+
+/// ProfileList below the SOFTWARE hive
+const PROFILE_LIST_KEY: &str = "Microsoft\\Windows NT\\CurrentVersion\\ProfileList";
+
+/// Per-user classes hive (HKCU\Software\Classes), relative to the profile directory
+const USRCLASS_PATH: &str = "AppData\\Local\\Microsoft\\Windows\\UsrClass.dat";
+
+#[cfg(windows)]
+pub struct UserHive {
+    pub user: String,
+    pub sid: Option<String>,
+    /// Registry path or hive file the key was opened from, used for attribution
+    pub source: String,
+    pub key: RegKey,
+}
+
+/// Hives of every user profile on the live system, plus the profiles whose hive
+/// could not be loaded. Logged-on users' hives are read where Windows mounted
+/// them under HKEY_USERS (the files are locked); the NTUSER.DAT of every other
+/// profile in ProfileList is loaded from disk.
+#[cfg(windows)]
+pub fn live_user_hives() -> (Vec<UserHive>, Vec<String>) {
+    let mut hives = mounted_user_hives(|name| Some(name).filter(|sid| is_user_sid(sid)));
+    let failures = load_unmounted_profile_hives(&mut hives, "NTUSER.DAT");
+    (hives, failures)
+}
+
+/// Classes hives (UsrClass.dat) of every user profile on the live system, plus
+/// the profiles whose hive could not be loaded
+#[cfg(windows)]
+pub fn live_user_class_hives() -> (Vec<UserHive>, Vec<String>) {
+    let mut hives = mounted_user_hives(classes_hive_sid);
+    let failures = load_unmounted_profile_hives(&mut hives, USRCLASS_PATH);
+    (hives, failures)
+}
+
+/// Hives mounted under HKEY_USERS whose key name `sid_of` maps to a user SID
+#[cfg(windows)]
+fn mounted_user_hives(sid_of: fn(&str) -> Option<&str>) -> Vec<UserHive> {
+    let hku = RegKey::predef(HKEY_USERS);
+    let mut hives = Vec::new();
+
+    for name in hku.enum_keys().filter_map(|k| k.ok()) {
+        let Some(sid) = sid_of(&name) else {
+            continue;
+        };
+        if let Ok(key) = hku.open_tracked(&name) {
+            hives.push(UserHive {
+                user: resolve_sid(sid).unwrap_or_else(|| sid.to_string()),
+                source: format!("HKU\\{}", name),
+                sid: Some(sid.to_string()),
+                key,
+            });
+        }
+    }
+
+    hives
+}
+
+/// Load `hive_file` from the profile directory of every user in ProfileList
+/// whose hive is not mounted yet. RegLoadAppKey is used rather than
+/// RegLoadKey: it needs no restore privilege or mount point under HKLM/HKU,
+/// and the hive is unloaded as soon as its key is closed.
+#[cfg(windows)]
+fn load_unmounted_profile_hives(hives: &mut Vec<UserHive>, hive_file: &str) -> Vec<String> {
+    let mut failures = Vec::new();
+
+    let profile_list = match RegKey::predef(HKEY_LOCAL_MACHINE).open_tracked(format!("SOFTWARE\\{}", PROFILE_LIST_KEY)) {
+        Ok(key) => key,
+        Err(e) => {
+            failures.push(format!("ProfileList not readable, only logged-on users are covered: {}", e));
+            return failures;
+        }
+    };
+
+    for sid in profile_list.enum_keys().filter_map(|k| k.ok()) {
+        if !is_user_sid(&sid) || hives.iter().any(|hive| hive.sid.as_deref() == Some(sid.as_str())) {
+            continue;
+        }
+        let Ok(profile_path) = profile_list
+            .open_tracked(&sid)
+            .and_then(|key| key.get_value::<String, _>("ProfileImagePath"))
+        else {
+            continue;
+        };
+
+        let hive_path = Path::new(&expand_environment(&profile_path)).join(hive_file);
+        if !hive_path.is_file() {
+            continue;
+        }
+        match offline::load_hive_file(&hive_path) {
+            Ok(key) => hives.push(UserHive {
+                user: profile_name_from_path(&profile_path).unwrap_or_else(|| sid.clone()),
+                sid: Some(sid),
+                source: hive_path.display().to_string(),
+                key,
+            }),
+            Err(e) => failures.push(format!("Failed to load {}: {}", hive_path.display(), e)),
+        }
+    }
+
+    failures
+}
+
+/// NTUSER.DAT hives of every profile on a mounted Windows volume, plus the
+/// profiles whose hive could not be loaded
+#[cfg(windows)]
+pub fn offline_user_hives(root: &OfflineRoot) -> (Vec<UserHive>, Vec<String>) {
+    offline_profile_hives(root, "NTUSER.DAT")
+}
+
+/// UsrClass.dat hives of every profile on a mounted Windows volume, plus the
+/// profiles whose hive could not be loaded
+#[cfg(windows)]
+pub fn offline_user_class_hives(root: &OfflineRoot) -> (Vec<UserHive>, Vec<String>) {
+    offline_profile_hives(root, USRCLASS_PATH)
+}
+
+/// Profiles are found by directory; their SIDs come from the ProfileList of
+/// the offline SOFTWARE hive when it can be loaded
+#[cfg(windows)]
+fn offline_profile_hives(root: &OfflineRoot, hive_file: &str) -> (Vec<UserHive>, Vec<String>) {
+    let mut hives = Vec::new();
+    let mut failures = Vec::new();
+
+    let profiles = match footprint::read_dir(root.resolve("C:\\Users")) {
+        Ok(profiles) => profiles,
+        Err(e) => {
+            failures.push(format!("Users directory not readable: {}", e));
+            return (hives, failures);
+        }
+    };
+    let profile_sids = root.load_hive("SOFTWARE").map(|software| profile_sids(&software)).unwrap_or_default();
+
+    for profile in profiles.filter_map(|p| p.ok()) {
+        let hive_path = profile.path().join(hive_file);
+        if !hive_path.is_file() {
+            continue;
+        }
+
+        let user = profile.file_name().to_string_lossy().to_string();
+        match offline::load_hive_file(&hive_path) {
+            Ok(key) => hives.push(UserHive {
+                sid: profile_sids.get(&user.to_lowercase()).cloned(),
+                user,
+                source: hive_path.display().to_string(),
+                key,
+            }),
+            Err(e) => failures.push(format!("Failed to load {}: {}", hive_path.display(), e)),
+        }
+    }
+
+    (hives, failures)
+}
+
+/// Lower-cased profile directory name to SID, from the ProfileList of a SOFTWARE hive
+#[cfg(windows)]
+fn profile_sids(software_hive: &RegKey) -> HashMap<String, String> {
+    let Ok(profile_list) = software_hive.open_tracked(PROFILE_LIST_KEY) else {
+        return HashMap::new();
+    };
+
+    profile_list
+        .enum_keys()
+        .filter_map(|k| k.ok())
+        .filter(|sid| is_user_sid(sid))
+        .filter_map(|sid| {
+            let profile_path = profile_list.open_tracked(&sid).ok()?.get_value::<String, _>("ProfileImagePath").ok()?;
+            Some((profile_name_from_path(&profile_path)?.to_lowercase(), sid))
+        })
+        .collect()
+}
+
+/// Resolve a SID to a username through the live ProfileList
+#[cfg(windows)]
+pub fn resolve_sid(sid: &str) -> Option<String> {
+    let software = RegKey::predef(HKEY_LOCAL_MACHINE).open_tracked("SOFTWARE").ok()?;
+    resolve_sid_in(&software, sid)
+}
+
+/// Resolve a SID to a username through the ProfileList of a SOFTWARE hive
+/// (live or offline); service accounts are resolved without it
+#[cfg(windows)]
+pub fn resolve_sid_in(software_hive: &RegKey, sid: &str) -> Option<String> {
+    if let Some(name) = well_known_sid_name(sid) {
+        return Some(name.to_string());
+    }
+    let profile_path = software_hive
+        .open_tracked(format!("{}\\{}", PROFILE_LIST_KEY, sid))
+        .ok()?
+        .get_value::<String, _>("ProfileImagePath")
+        .ok()?;
+    profile_name_from_path(&profile_path)
+}
+
+/// Built-in service accounts, which have no profile under C:\Users
+fn well_known_sid_name(sid: &str) -> Option<&'static str> {
+    match sid {
+        "S-1-5-18" => Some("SYSTEM"),
+        "S-1-5-19" => Some("LOCAL SERVICE"),
+        "S-1-5-20" => Some("NETWORK SERVICE"),
+        _ => None,
+    }
+}
+
+fn profile_name_from_path(profile_path: &str) -> Option<String> {
+    profile_path
+        .rsplit('\\')
+        .find(|component| !component.is_empty())
+        .map(|name| name.to_string())
+}
+
+/// ProfileImagePath is REG_EXPAND_SZ (`%SystemDrive%\Users\alice`); variables
+/// not set in the collector's environment are left as they are
+fn expand_environment(path: &str) -> String {
+    let mut expanded = String::with_capacity(path.len());
+    let mut rest = path;
+
+    while let Some(start) = rest.find('%') {
+        expanded.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        match after.find('%').and_then(|end| Some((std::env::var(&after[..end]).ok()?, end))) {
+            Some((value, end)) => {
+                expanded.push_str(&value);
+                rest = &after[end + 1..];
+            }
+            None => {
+                expanded.push('%');
+                rest = after;
+            }
+        }
+    }
+
+    expanded.push_str(rest);
+    expanded
+}
+
+/// Interactive user accounts (local or domain), excluding the _Classes companion hives
+fn is_user_sid(sid: &str) -> bool {
+    sid.starts_with("S-1-5-21-") && !sid.ends_with("_Classes")
+}
+
+/// SID of a `<SID>_Classes` companion hive of an interactive user
+fn classes_hive_sid(name: &str) -> Option<&str> {
+    name.strip_suffix("_Classes").filter(|sid| is_user_sid(sid))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_user_sid() {
+        assert!(is_user_sid("S-1-5-21-3623811015-3361044348-30300820-1013"));
+        assert!(!is_user_sid("S-1-5-21-3623811015-3361044348-30300820-1013_Classes"));
+        assert!(!is_user_sid("S-1-5-18"));
+        assert!(!is_user_sid(".DEFAULT"));
+    }
+
+    #[test]
+    fn test_classes_hive_sid() {
+        assert_eq!(
+            classes_hive_sid("S-1-5-21-3623811015-3361044348-30300820-1013_Classes"),
+            Some("S-1-5-21-3623811015-3361044348-30300820-1013")
+        );
+        assert_eq!(classes_hive_sid("S-1-5-21-3623811015-3361044348-30300820-1013"), None);
+        assert_eq!(classes_hive_sid("S-1-5-18_Classes"), None);
+    }
+
+    #[test]
+    fn test_well_known_sid_name() {
+        assert_eq!(well_known_sid_name("S-1-5-18"), Some("SYSTEM"));
+        assert_eq!(well_known_sid_name("S-1-5-21-3623811015-3361044348-30300820-1013"), None);
+    }
+
+    #[test]
+    fn test_expand_environment() {
+        std::env::set_var("TRIAGEIR_TEST_DRIVE", "D:");
+        assert_eq!(expand_environment("%TRIAGEIR_TEST_DRIVE%\\Users\\alice"), "D:\\Users\\alice");
+        assert_eq!(expand_environment("%TRIAGEIR_UNSET_VARIABLE%\\bob"), "%TRIAGEIR_UNSET_VARIABLE%\\bob");
+        assert_eq!(expand_environment("C:\\Users\\100%"), "C:\\Users\\100%");
+    }
+
+    #[test]
+    fn test_profile_name_from_path() {
+        assert_eq!(profile_name_from_path("C:\\Users\\alice"), Some("alice".to_string()));
+        assert_eq!(profile_name_from_path("C:\\Users\\bob\\"), Some("bob".to_string()));
+        assert_eq!(profile_name_from_path(""), None);
+    }
+}
@@ -0,0 +1,427 @@
+//! NTFS change journal triage
+//! The USN journal records every change to every file of a volume. Each
+//! file's changes are summarized by the record written when its last handle
+//! closes; those closing records that create, delete or rename a file inside
+//! the scan time window are kept, with the name a renamed file had before.
+//! On a live system the journal is read with FSCTL_READ_USN_JOURNAL; on an
+//! offline volume from the $Extend\$UsnJrnl:$J stream. Paths are not
+//! resolved: the parent MFT entry identifies the directory.
+
+use crate::binutil::{filetime_to_string, read_u16, read_u32, read_u64};
+use crate::footprint;
+use crate::forensic_types::{AuditEntry, UsnJournalEntry};
+use crate::offline::OfflineRoot;
+use crate::time_window::TimeWindow;
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::Path;
+
+const COMPONENT: &str = "usn_journal";
+
+/// Journal stream of an offline volume
+const USN_JOURNAL_STREAM: &str = "C:\\$Extend\\$UsnJrnl:$J";
+/// Entries kept, the most recent first, when the journal holds more
+const MAX_JOURNAL_ENTRIES: usize = 100_000;
+const READ_CHUNK_SIZE: usize = 1024 * 1024;
+
+const USN_REASONS: [(u32, &str); 20] = [
+    (0x0000_0001, "DATA_OVERWRITE"),
+    (0x0000_0002, "DATA_EXTEND"),
+    (0x0000_0004, "DATA_TRUNCATION"),
+    (0x0000_0010, "NAMED_DATA_OVERWRITE"),
+    (0x0000_0020, "NAMED_DATA_EXTEND"),
+    (0x0000_0040, "NAMED_DATA_TRUNCATION"),
+    (0x0000_0100, "FILE_CREATE"),
+    (0x0000_0200, "FILE_DELETE"),
+    (0x0000_0400, "EA_CHANGE"),
+    (0x0000_0800, "SECURITY_CHANGE"),
+    (0x0000_1000, "RENAME_OLD_NAME"),
+    (0x0000_2000, "RENAME_NEW_NAME"),
+    (0x0000_4000, "INDEXABLE_CHANGE"),
+    (0x0000_8000, "BASIC_INFO_CHANGE"),
+    (0x0001_0000, "HARD_LINK_CHANGE"),
+    (0x0002_0000, "COMPRESSION_CHANGE"),
+    (0x0004_0000, "ENCRYPTION_CHANGE"),
+    (0x0008_0000, "OBJECT_ID_CHANGE"),
+    (0x0020_0000, "STREAM_CHANGE"),
+    (0x8000_0000, "CLOSE"),
+];
+const USN_REASON_FILE_CREATE: u32 = 0x0000_0100;
+const USN_REASON_FILE_DELETE: u32 = 0x0000_0200;
+const USN_REASON_RENAME_OLD_NAME: u32 = 0x0000_1000;
+const USN_REASON_RENAME_NEW_NAME: u32 = 0x0000_2000;
+const USN_REASON_CLOSE: u32 = 0x8000_0000;
+const FILE_ATTRIBUTE_DIRECTORY: u32 = 0x10;
+/// Low 48 bits of a file reference: the MFT entry number
+const MFT_ENTRY_MASK: u64 = 0x0000_FFFF_FFFF_FFFF;
+
+/// A USN_RECORD_V2 or V3
+#[derive(Debug, Clone, PartialEq)]
+struct UsnRecord {
+    usn: i64,
+    timestamp: u64, // FILETIME
+    reason: u32,
+    file_reference: u64,
+    parent_reference: u64,
+    attributes: u32,
+    file_name: String,
+}
+
+/// Creates, deletes and renames of the change journal of the system volume
+#[cfg(windows)]
+pub fn collect_usn_journal(window: &TimeWindow) -> (Vec<UsnJournalEntry>, Vec<AuditEntry>) {
+    let start_time = std::time::Instant::now();
+    let volume = std::env::var("SystemDrive").unwrap_or_else(|_| "C:".to_string());
+    let mut audit_log = vec![AuditEntry::new(COMPONENT, "INFO", "start_collection", format!("Starting USN journal collection of {}", volume), "started")];
+
+    let mut journal = JournalEntries::new(&volume, window);
+    if let Err(e) = windows_api::read_journal(&volume, |records| journal.add(records)) {
+        audit_log.push(AuditEntry::new(COMPONENT, "ERROR", "read_journal", format!("Failed to read the USN journal of {}: {}", volume, e), "error"));
+    }
+    let entries = journal.finish(&mut audit_log);
+    audit_log.push(completed(&entries, start_time));
+    (entries, audit_log)
+}
+
+#[cfg(not(windows))]
+pub fn collect_usn_journal(_window: &TimeWindow) -> (Vec<UsnJournalEntry>, Vec<AuditEntry>) {
+    let audit_log = vec![
+        AuditEntry::new(COMPONENT, "INFO", "start_collection", "Starting USN journal collection".to_string(), "started"),
+        AuditEntry::new(COMPONENT, "WARN", "read_journal", "Reading the live USN journal requires Windows".to_string(), "unsupported"),
+        AuditEntry::new(COMPONENT, "INFO", "complete_collection", "Collected 0 USN journal entries".to_string(), "success"),
+    ];
+    (Vec::new(), audit_log)
+}
+
+/// Creates, deletes and renames of the $UsnJrnl:$J stream of a mounted Windows volume
+pub fn collect_usn_journal_offline(root: &OfflineRoot, window: &TimeWindow) -> (Vec<UsnJournalEntry>, Vec<AuditEntry>) {
+    let start_time = std::time::Instant::now();
+    let stream = root.resolve(USN_JOURNAL_STREAM);
+    let mut audit_log = vec![AuditEntry::new(COMPONENT, "INFO", "start_collection", format!("Starting USN journal collection of {}", stream.display()), "started")];
+
+    let mut journal = JournalEntries::new("C:", window);
+    if let Err(e) = read_journal_stream(&stream, |records| journal.add(records)) {
+        audit_log.push(AuditEntry::new(COMPONENT, "WARN", "read_journal", format!("USN journal not readable: {} ({})", stream.display(), e), "not_found"));
+    }
+    let entries = journal.finish(&mut audit_log);
+    audit_log.push(completed(&entries, start_time));
+    (entries, audit_log)
+}
+
+/// Read a $J stream chunk by chunk; records may straddle chunks
+fn read_journal_stream(path: &Path, mut visit: impl FnMut(Vec<UsnRecord>)) -> std::io::Result<()> {
+    let mut file = footprint::open(path)?;
+    let mut buffer = Vec::with_capacity(READ_CHUNK_SIZE * 2);
+    let mut chunk = vec![0u8; READ_CHUNK_SIZE];
+    loop {
+        let read = file.read(&mut chunk)?;
+        if read == 0 {
+            break;
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+        let (records, consumed) = parse_usn_records(&buffer);
+        visit(records);
+        buffer.drain(..consumed);
+    }
+    Ok(())
+}
+
+/// Parse the records of a buffer, skipping the zeroed (sparse) stretches
+/// between them; returns them with the offset of an incomplete trailing record
+fn parse_usn_records(buffer: &[u8]) -> (Vec<UsnRecord>, usize) {
+    let mut records = Vec::new();
+    let mut position = 0;
+    while position + 8 <= buffer.len() {
+        let length = read_u32(buffer, position).unwrap_or(0) as usize;
+        let major_version = read_u16(buffer, position + 4).unwrap_or(0);
+        if !(60..=0x1_0000).contains(&length) || !(2..=4).contains(&major_version) {
+            position += 8;
+            continue;
+        }
+        if position + length > buffer.len() {
+            return (records, position);
+        }
+        // Version 4 records track modified ranges and carry no name
+        if let Some(record) = parse_usn_record(&buffer[position..position + length], major_version) {
+            records.push(record);
+        }
+        position += (length + 7) & !7;
+    }
+    (records, position.min(buffer.len()))
+}
+
+fn parse_usn_record(record: &[u8], major_version: u16) -> Option<UsnRecord> {
+    // V3 widens the file references to 128 bits
+    let (file_reference, parent_reference, fields) = match major_version {
+        2 => (read_u64(record, 8)?, read_u64(record, 16)?, 24),
+        3 => (read_u64(record, 8)?, read_u64(record, 24)?, 40),
+        _ => return None,
+    };
+    let name_length = read_u16(record, fields + 32)? as usize;
+    let name_offset = read_u16(record, fields + 34)? as usize;
+    let units: Vec<u16> = record.get(name_offset..name_offset + name_length)?.chunks_exact(2).map(|unit| u16::from_le_bytes([unit[0], unit[1]])).collect();
+    Some(UsnRecord {
+        usn: read_u64(record, fields)? as i64,
+        timestamp: read_u64(record, fields + 8)?,
+        reason: read_u32(record, fields + 16)?,
+        file_reference,
+        parent_reference,
+        attributes: read_u32(record, fields + 28)?,
+        file_name: String::from_utf16_lossy(&units),
+    })
+}
+
+/// Closing records that create, delete or rename a file, with the old name of renames
+struct JournalEntries<'a> {
+    volume: String,
+    window: &'a TimeWindow,
+    old_names: HashMap<u64, String>,
+    entries: Vec<UsnJournalEntry>,
+    records: usize,
+    outside_window: usize,
+}
+
+impl<'a> JournalEntries<'a> {
+    fn new(volume: &str, window: &'a TimeWindow) -> Self {
+        JournalEntries { volume: volume.to_string(), window, old_names: HashMap::new(), entries: Vec::new(), records: 0, outside_window: 0 }
+    }
+
+    fn add(&mut self, records: Vec<UsnRecord>) {
+        self.records += records.len();
+        for record in records {
+            let file = record.file_reference & MFT_ENTRY_MASK;
+            if record.reason & USN_REASON_RENAME_OLD_NAME != 0 {
+                self.old_names.insert(file, record.file_name.clone());
+            }
+            if record.reason & USN_REASON_CLOSE == 0 || record.reason & (USN_REASON_FILE_CREATE | USN_REASON_FILE_DELETE | USN_REASON_RENAME_NEW_NAME) == 0 {
+                continue;
+            }
+
+            let old_name = if record.reason & USN_REASON_RENAME_NEW_NAME != 0 { self.old_names.remove(&file) } else { None };
+            let timestamp = filetime_to_string(record.timestamp);
+            if !self.window.contains(&timestamp) {
+                self.outside_window += 1;
+                continue;
+            }
+            let action = match (record.reason & USN_REASON_FILE_CREATE != 0, record.reason & USN_REASON_FILE_DELETE != 0) {
+                (true, true) => "created_and_deleted",
+                (true, false) => "created",
+                (false, true) => "deleted",
+                (false, false) => "renamed",
+            };
+            self.entries.push(UsnJournalEntry {
+                timestamp,
+                usn: record.usn,
+                volume: self.volume.clone(),
+                file_name: record.file_name,
+                old_name,
+                action: action.to_string(),
+                reasons: USN_REASONS.iter().filter(|(flag, _)| record.reason & flag != 0).map(|(_, name)| name.to_string()).collect(),
+                mft_entry: file,
+                parent_mft_entry: record.parent_reference & MFT_ENTRY_MASK,
+                is_directory: record.attributes & FILE_ATTRIBUTE_DIRECTORY != 0,
+            });
+        }
+    }
+
+    fn finish(mut self, audit_log: &mut Vec<AuditEntry>) -> Vec<UsnJournalEntry> {
+        audit_log.push(AuditEntry::new(
+            COMPONENT,
+            "DEBUG",
+            "parse_journal",
+            format!("Parsed {} records; {} file changes outside the time window", self.records, self.outside_window),
+            "success",
+        ));
+        // Journal order is USN order; the newest entries are kept
+        if self.entries.len() > MAX_JOURNAL_ENTRIES {
+            let dropped = self.entries.len() - MAX_JOURNAL_ENTRIES;
+            self.entries.drain(..dropped);
+            audit_log.push(AuditEntry::new(
+                COMPONENT,
+                "WARN",
+                "entry_limit",
+                format!("Kept the newest {} journal entries; {} older entries dropped, narrow the time window to see them", MAX_JOURNAL_ENTRIES, dropped),
+                "warning",
+            ));
+        }
+        self.entries
+    }
+}
+
+fn completed(entries: &[UsnJournalEntry], start_time: std::time::Instant) -> AuditEntry {
+    let mut entry = AuditEntry::new(COMPONENT, "INFO", "complete_collection", format!("Collected {} USN journal entries", entries.len()), "success");
+    entry.duration_ms = Some(start_time.elapsed().as_millis() as u64);
+    entry
+}
+
+#[cfg(windows)]
+mod windows_api {
+    use super::{parse_usn_records, UsnRecord};
+    use crate::footprint::{self, Access};
+    use std::path::Path;
+    use windows::core::PCWSTR;
+    use windows::Win32::Foundation::{CloseHandle, HANDLE};
+    use windows::Win32::Storage::FileSystem::{CreateFileW, FILE_FLAGS_AND_ATTRIBUTES, FILE_GENERIC_READ, FILE_SHARE_READ, FILE_SHARE_WRITE, OPEN_EXISTING};
+    use windows::Win32::System::Ioctl::{FSCTL_QUERY_USN_JOURNAL, FSCTL_READ_USN_JOURNAL, READ_USN_JOURNAL_DATA_V0, USN_JOURNAL_DATA_V0};
+    use windows::Win32::System::IO::DeviceIoControl;
+
+    const READ_BUFFER_SIZE: usize = 64 * 1024;
+
+    /// Records of the change journal of a volume such as `C:`, up to the USN
+    /// current when reading started; needs administrator rights
+    pub fn read_journal(volume: &str, mut visit: impl FnMut(Vec<UsnRecord>)) -> Result<(), String> {
+        let device = format!("\\\\.\\{}", volume);
+        footprint::record_file(Path::new(&device), Access::Read);
+        let path: Vec<u16> = device.encode_utf16().chain(std::iter::once(0)).collect();
+        let handle = unsafe {
+            CreateFileW(
+                PCWSTR(path.as_ptr()),
+                FILE_GENERIC_READ.0,
+                FILE_SHARE_READ | FILE_SHARE_WRITE,
+                None,
+                OPEN_EXISTING,
+                FILE_FLAGS_AND_ATTRIBUTES(0),
+                HANDLE::default(),
+            )
+        }
+        .map_err(|e| format!("cannot open the volume: {}", e))?;
+
+        let result = read_open_journal(handle, &mut visit);
+        unsafe {
+            let _ = CloseHandle(handle);
+        }
+        result
+    }
+
+    fn read_open_journal(handle: HANDLE, visit: &mut impl FnMut(Vec<UsnRecord>)) -> Result<(), String> {
+        let mut journal = USN_JOURNAL_DATA_V0::default();
+        let mut returned = 0u32;
+        unsafe {
+            DeviceIoControl(
+                handle,
+                FSCTL_QUERY_USN_JOURNAL,
+                None,
+                0,
+                Some(&mut journal as *mut _ as *mut _),
+                std::mem::size_of::<USN_JOURNAL_DATA_V0>() as u32,
+                Some(&mut returned),
+                None,
+            )
+        }
+        .map_err(|e| format!("FSCTL_QUERY_USN_JOURNAL failed: {}", e))?;
+
+        let mut request = READ_USN_JOURNAL_DATA_V0 {
+            StartUsn: journal.FirstUsn,
+            ReasonMask: u32::MAX,
+            ReturnOnlyOnClose: 0,
+            Timeout: 0,
+            BytesToWaitFor: 0,
+            UsnJournalID: journal.UsnJournalID,
+        };
+        let mut buffer = vec![0u8; READ_BUFFER_SIZE];
+        while request.StartUsn < journal.NextUsn {
+            unsafe {
+                DeviceIoControl(
+                    handle,
+                    FSCTL_READ_USN_JOURNAL,
+                    Some(&request as *const _ as *const _),
+                    std::mem::size_of::<READ_USN_JOURNAL_DATA_V0>() as u32,
+                    Some(buffer.as_mut_ptr() as *mut _),
+                    buffer.len() as u32,
+                    Some(&mut returned),
+                    None,
+                )
+            }
+            .map_err(|e| format!("FSCTL_READ_USN_JOURNAL failed: {}", e))?;
+
+            // The output starts with the USN to continue from
+            let returned = (returned as usize).min(buffer.len());
+            if returned <= 8 {
+                break;
+            }
+            let next_usn = i64::from_le_bytes(buffer[..8].try_into().unwrap_or_default());
+            visit(parse_usn_records(&buffer[8..returned]).0);
+            if next_usn <= request.StartUsn {
+                break;
+            }
+            request.StartUsn = next_usn;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v2_record(usn: i64, timestamp: u64, reason: u32, file: u64, name: &str) -> Vec<u8> {
+        let name: Vec<u8> = name.encode_utf16().flat_map(u16::to_le_bytes).collect();
+        let length = (60 + name.len() + 7) & !7;
+        let mut record = Vec::with_capacity(length);
+        record.extend((length as u32).to_le_bytes());
+        record.extend(2u16.to_le_bytes());
+        record.extend(0u16.to_le_bytes());
+        record.extend((file | 0x0003_0000_0000_0000).to_le_bytes());
+        record.extend(5u64.to_le_bytes());
+        record.extend(usn.to_le_bytes());
+        record.extend(timestamp.to_le_bytes());
+        record.extend(reason.to_le_bytes());
+        record.extend([0u8; 12]);
+        record.extend((name.len() as u16).to_le_bytes());
+        record.extend(60u16.to_le_bytes());
+        record.extend(name);
+        record.resize(length, 0);
+        record
+    }
+
+    #[test]
+    fn test_journal_stream_creates_renames_and_deletes() {
+        const T0: u64 = 133_580_000_000_000_000; // 2024-04-19T11:33:20Z
+        const MINUTE: u64 = 600_000_000;
+        let mut stream = vec![0u8; 4096]; // sparse start of $J
+        for record in [
+            v2_record(4096, T0, USN_REASON_FILE_CREATE, 40, "loot.7z"),
+            v2_record(4168, T0, USN_REASON_FILE_CREATE | USN_REASON_CLOSE, 40, "loot.7z"),
+            v2_record(4240, T0 + MINUTE, USN_REASON_RENAME_OLD_NAME, 40, "loot.7z"),
+            v2_record(4312, T0 + MINUTE, USN_REASON_RENAME_NEW_NAME | USN_REASON_CLOSE, 40, "update.log"),
+            v2_record(4392, T0 + 2 * MINUTE, 0x2 | USN_REASON_CLOSE, 41, "notes.txt"),
+            v2_record(4464, T0 + 3 * MINUTE, USN_REASON_FILE_DELETE | USN_REASON_CLOSE, 40, "update.log"),
+        ] {
+            stream.extend(record);
+        }
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("$J");
+        std::fs::write(&path, &stream).unwrap();
+
+        let window = TimeWindow::default();
+        let mut journal = JournalEntries::new("C:", &window);
+        read_journal_stream(&path, |records| journal.add(records)).unwrap();
+        let mut audit_log = Vec::new();
+        let entries = journal.finish(&mut audit_log);
+
+        let actions: Vec<(&str, &str)> = entries.iter().map(|e| (e.action.as_str(), e.file_name.as_str())).collect();
+        assert_eq!(actions, vec![("created", "loot.7z"), ("renamed", "update.log"), ("deleted", "update.log")]);
+        assert_eq!(entries[1].old_name.as_deref(), Some("loot.7z"));
+        assert_eq!((entries[0].mft_entry, entries[0].parent_mft_entry), (40, 5));
+        assert_eq!(entries[0].timestamp, "2024-04-19T11:33:20+00:00");
+        assert_eq!(entries[2].reasons, vec!["FILE_DELETE", "CLOSE"]);
+        assert!(audit_log[0].details.starts_with("Parsed 6 records"));
+
+        let window = TimeWindow::new(Some("2024-04-19T11:35:00Z"), None).unwrap();
+        let mut journal = JournalEntries::new("C:", &window);
+        journal.add(parse_usn_records(&stream).0);
+        let entries = journal.finish(&mut Vec::new());
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].action, "deleted");
+    }
+
+    #[test]
+    fn test_truncated_record_is_left_for_the_next_chunk() {
+        let record = v2_record(8, 133_580_000_000_000_000, USN_REASON_FILE_CREATE | USN_REASON_CLOSE, 7, "a.txt");
+        let (records, consumed) = parse_usn_records(&record[..40]);
+        assert!(records.is_empty());
+        assert_eq!(consumed, 0);
+        let (records, consumed) = parse_usn_records(&record);
+        assert_eq!((records.len(), consumed), (1, record.len()));
+    }
+}
@@ -0,0 +1,408 @@
+//! Volume Shadow Copy access layer
+//! Enumerates the existing shadow copies (vssadmin), optionally creates one of
+//! the system volume, and maps live paths into the snapshot selected for the
+//! scan. Collectors copy files through `copy_with_fallback`, which reads a file
+//! from the snapshot when the live copy is locked by another process; every
+//! such fallback is recorded and added to the collection log by the scan.
+
+// vssadmin parsing and snapshot mapping are only reachable through the Windows implementation
+#![cfg_attr(not(windows), allow(dead_code))]
+
+use crate::footprint::{self, Access};
+use crate::forensic_types::{AuditEntry, ShadowCopyInfo};
+use crate::offline::OfflineRoot;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const COMPONENT: &str = "vss";
+
+/// Win32 errors raised while another process holds the file open
+const ERROR_SHARING_VIOLATION: i32 = 32;
+const ERROR_LOCK_VIOLATION: i32 = 33;
+
+/// Snapshot used for locked-file fallback during the current scan
+static ACTIVE_SNAPSHOT: Mutex<Option<ShadowCopyInfo>> = Mutex::new(None);
+/// Audit entries of fallback reads, drained by the scan
+static FALLBACK_AUDIT: Mutex<Vec<AuditEntry>> = Mutex::new(Vec::new());
+
+/// Which snapshot the scan falls back to (`--vss existing|create`)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SnapshotMode {
+    /// Newest existing shadow copy of the system volume
+    Existing,
+    /// New shadow copy of the system volume, deleted after the scan
+    Create,
+}
+
+impl SnapshotMode {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.to_lowercase().as_str() {
+            "existing" => Ok(SnapshotMode::Existing),
+            "create" => Ok(SnapshotMode::Create),
+            other => Err(format!("invalid shadow copy mode {} (expected existing or create)", other)),
+        }
+    }
+}
+
+/// Enumerate the shadow copies of all local volumes
+#[cfg(windows)]
+pub fn list_shadow_copies() -> (Vec<ShadowCopyInfo>, Vec<AuditEntry>) {
+    let start_time = std::time::Instant::now();
+    let mut audit_log = vec![AuditEntry::new(COMPONENT, "INFO", "start_collection", "Enumerating volume shadow copies".to_string(), "started")];
+
+    let shadow_copies = match windows_api::run("vssadmin", &["list", "shadows"]) {
+        Ok(output) => parse_vssadmin_output(&output),
+        // vssadmin exits non-zero when there are no shadow copies
+        Err(e) if e.contains("No items found") => Vec::new(),
+        Err(e) => {
+            audit_log.push(AuditEntry::new(COMPONENT, "ERROR", "list_shadows", e, "error"));
+            Vec::new()
+        }
+    };
+
+    let mut complete = AuditEntry::new(
+        COMPONENT,
+        "INFO",
+        "complete_collection",
+        format!("Found {} shadow copies", shadow_copies.len()),
+        "success",
+    );
+    complete.duration_ms = Some(start_time.elapsed().as_millis() as u64);
+    audit_log.push(complete);
+
+    (shadow_copies, audit_log)
+}
+
+#[cfg(not(windows))]
+pub fn list_shadow_copies() -> (Vec<ShadowCopyInfo>, Vec<AuditEntry>) {
+    (
+        Vec::new(),
+        vec![
+            AuditEntry::new(COMPONENT, "INFO", "start_collection", "Enumerating volume shadow copies".to_string(), "started"),
+            AuditEntry::new(COMPONENT, "WARN", "platform_check", "Volume shadow copies require Windows".to_string(), "unsupported"),
+            AuditEntry::new(COMPONENT, "INFO", "complete_collection", "Found 0 shadow copies".to_string(), "success"),
+        ],
+    )
+}
+
+/// Create a client-accessible shadow copy of `volume` (e.g. `C:`)
+#[cfg(windows)]
+pub fn create_shadow_copy(volume: &str) -> (Option<ShadowCopyInfo>, Vec<AuditEntry>) {
+    let mut audit_log = Vec::new();
+    let command = format!(
+        "$r = Invoke-CimMethod -ClassName Win32_ShadowCopy -MethodName Create -Arguments @{{Volume='{}\\'; Context='ClientAccessible'}}; \
+         if ($r.ReturnValue -ne 0) {{ Write-Error \"Win32_ShadowCopy.Create returned $($r.ReturnValue)\"; exit 1 }}; $r.ShadowID",
+        volume.trim_end_matches('\\')
+    );
+
+    let shadow_id = match windows_api::run("powershell", &["-NoProfile", "-NonInteractive", "-Command", &command]) {
+        Ok(output) => output.trim().to_string(),
+        Err(e) => {
+            audit_log.push(AuditEntry::new(COMPONENT, "ERROR", "create_shadow", format!("Failed to create a shadow copy of {}: {}", volume, e), "error"));
+            return (None, audit_log);
+        }
+    };
+
+    let (shadow_copies, list_logs) = list_shadow_copies();
+    audit_log.extend(list_logs);
+    match shadow_copies.into_iter().find(|copy| copy.shadow_id.eq_ignore_ascii_case(&shadow_id)) {
+        Some(mut copy) => {
+            copy.created_by_collector = true;
+            audit_log.push(AuditEntry::new(
+                COMPONENT,
+                "WARN", // The collector changed the system state
+                "create_shadow",
+                format!("Created shadow copy {} of {} at {}", copy.shadow_id, volume, copy.device_path),
+                "success",
+            ));
+            (Some(copy), audit_log)
+        }
+        None => {
+            audit_log.push(AuditEntry::new(
+                COMPONENT,
+                "ERROR",
+                "create_shadow",
+                format!("Shadow copy {} was created but not found by vssadmin", shadow_id),
+                "error",
+            ));
+            (None, audit_log)
+        }
+    }
+}
+
+#[cfg(not(windows))]
+pub fn create_shadow_copy(volume: &str) -> (Option<ShadowCopyInfo>, Vec<AuditEntry>) {
+    let details = format!("Cannot create a shadow copy of {}: volume shadow copies require Windows", volume);
+    (None, vec![AuditEntry::new(COMPONENT, "WARN", "create_shadow", details, "unsupported")])
+}
+
+/// Delete a shadow copy created for the scan
+#[cfg(windows)]
+pub fn delete_shadow_copy(snapshot: &ShadowCopyInfo) -> AuditEntry {
+    let shadow_argument = format!("/Shadow={}", snapshot.shadow_id);
+    match windows_api::run("vssadmin", &["delete", "shadows", &shadow_argument, "/Quiet"]) {
+        Ok(_) => AuditEntry::new(COMPONENT, "INFO", "delete_shadow", format!("Deleted shadow copy {}", snapshot.shadow_id), "success"),
+        Err(e) => AuditEntry::new(COMPONENT, "ERROR", "delete_shadow", format!("Failed to delete shadow copy {}: {}", snapshot.shadow_id, e), "error"),
+    }
+}
+
+#[cfg(not(windows))]
+pub fn delete_shadow_copy(snapshot: &ShadowCopyInfo) -> AuditEntry {
+    AuditEntry::new(COMPONENT, "WARN", "delete_shadow", format!("Cannot delete shadow copy {}: requires Windows", snapshot.shadow_id), "unsupported")
+}
+
+/// Newest shadow copy of `volume`
+pub fn newest_for_volume<'a>(shadow_copies: &'a [ShadowCopyInfo], volume: &str) -> Option<&'a ShadowCopyInfo> {
+    // vssadmin lists shadow copies oldest first
+    shadow_copies.iter().rev().find(|copy| copy.original_volume.eq_ignore_ascii_case(volume))
+}
+
+/// Use `snapshot` for locked-file fallback until `deactivate` is called
+pub fn activate(snapshot: ShadowCopyInfo) {
+    *ACTIVE_SNAPSHOT.lock().unwrap_or_else(|e| e.into_inner()) = Some(snapshot);
+}
+
+pub fn deactivate() -> Option<ShadowCopyInfo> {
+    ACTIVE_SNAPSHOT.lock().unwrap_or_else(|e| e.into_inner()).take()
+}
+
+pub fn active_snapshot() -> Option<ShadowCopyInfo> {
+    ACTIVE_SNAPSHOT.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Audit entries recorded by fallback reads since the last call
+pub fn take_audit_entries() -> Vec<AuditEntry> {
+    std::mem::take(&mut *FALLBACK_AUDIT.lock().unwrap_or_else(|e| e.into_inner()))
+}
+
+/// The active snapshot as an offline volume, for collectors that only read files
+/// that are always locked on live systems (e.g. Amcache.hve)
+pub fn snapshot_root() -> Option<OfflineRoot> {
+    let snapshot = active_snapshot()?;
+    OfflineRoot::new(format!("{}\\", snapshot.device_path.trim_end_matches('\\'))).ok()
+}
+
+/// Map a live path such as `C:\Users\a\NTUSER.DAT` into the snapshot of its volume
+pub fn map_to_snapshot(snapshot: &ShadowCopyInfo, live_path: &Path) -> Option<PathBuf> {
+    let path = live_path.to_string_lossy();
+    let path = path.strip_prefix(r"\\?\").unwrap_or(&path);
+    let (volume, rest) = path.split_at_checked(2)?;
+    if !volume.eq_ignore_ascii_case(&snapshot.original_volume) || !(rest.is_empty() || rest.starts_with(['\\', '/'])) {
+        return None;
+    }
+    Some(PathBuf::from(format!("{}{}", snapshot.device_path.trim_end_matches('\\'), rest.replace('/', "\\"))))
+}
+
+/// Copy a file that another process may hold open. The live file is read with
+/// read, write and delete sharing; if it is locked all the same and a snapshot is
+/// active, the snapshot's copy is read instead and the fallback is audited.
+pub fn copy_with_fallback(source: &Path, destination: &Path) -> io::Result<u64> {
+    footprint::record_file(source, Access::Read);
+    let error = match copy_shared(source, destination) {
+        Ok(bytes) => return Ok(bytes),
+        Err(e) => e,
+    };
+    if !matches!(error.raw_os_error(), Some(ERROR_SHARING_VIOLATION) | Some(ERROR_LOCK_VIOLATION)) {
+        return Err(error);
+    }
+    let Some(snapshot) = active_snapshot() else {
+        return Err(error);
+    };
+    let Some(snapshot_path) = map_to_snapshot(&snapshot, source) else {
+        return Err(error);
+    };
+
+    let result = footprint::copy(&snapshot_path, destination);
+    let entry = match &result {
+        Ok(bytes) => AuditEntry::new(
+            COMPONENT,
+            "INFO",
+            "snapshot_fallback",
+            format!("{} is locked ({}); read {} bytes from shadow copy {} created {}", source.display(), error, bytes, snapshot.shadow_id, snapshot.creation_time),
+            "shadow_copy",
+        ),
+        Err(e) => AuditEntry::new(
+            COMPONENT,
+            "WARN",
+            "snapshot_fallback",
+            format!("{} is locked ({}) and could not be read from shadow copy {}: {}", source.display(), error, snapshot.shadow_id, e),
+            "error",
+        ),
+    };
+    FALLBACK_AUDIT.lock().unwrap_or_else(|e| e.into_inner()).push(entry);
+    result
+}
+
+/// Copy a SQLite database into `staging_dir` along with its -wal, -shm and
+/// -journal companions: recent changes live in the write-ahead log until the
+/// owning application checkpoints it
+pub fn stage_sqlite_database(db_path: &Path, staging_dir: &Path) -> io::Result<PathBuf> {
+    let file_name = db_path.file_name().unwrap_or_default().to_string_lossy().to_string();
+    let staged_db = staging_dir.join(&file_name);
+    copy_with_fallback(db_path, &staged_db)?;
+
+    for suffix in ["-wal", "-shm", "-journal"] {
+        let companion = db_path.with_file_name(format!("{}{}", file_name, suffix));
+        if companion.is_file() {
+            copy_with_fallback(&companion, &staging_dir.join(format!("{}{}", file_name, suffix)))?;
+        }
+    }
+
+    Ok(staged_db)
+}
+
+/// `fs::copy` asks for exclusive read access and fails with a sharing violation
+/// while e.g. a browser has the file open; opening with full sharing succeeds.
+#[cfg(windows)]
+fn copy_shared(source: &Path, destination: &Path) -> io::Result<u64> {
+    use std::os::windows::fs::OpenOptionsExt;
+    const FILE_SHARE_READ_WRITE_DELETE: u32 = 0x1 | 0x2 | 0x4;
+
+    let mut input = fs::OpenOptions::new().read(true).share_mode(FILE_SHARE_READ_WRITE_DELETE).open(source)?;
+    let mut output = fs::File::create(destination)?;
+    io::copy(&mut input, &mut output)
+}
+
+#[cfg(not(windows))]
+fn copy_shared(source: &Path, destination: &Path) -> io::Result<u64> {
+    fs::copy(source, destination)
+}
+
+/// Parse `vssadmin list shadows`
+pub fn parse_vssadmin_output(output: &str) -> Vec<ShadowCopyInfo> {
+    let mut shadow_copies: Vec<ShadowCopyInfo> = Vec::new();
+    let mut set_id = String::new();
+    let mut creation_time = String::new();
+
+    for line in output.lines().map(str::trim) {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match name.trim() {
+            "Contents of shadow copy set ID" => set_id = value.to_string(),
+            name if name.starts_with("Contained") && name.ends_with("creation time") => creation_time = value.to_string(),
+            "Shadow Copy ID" => shadow_copies.push(ShadowCopyInfo {
+                shadow_id: value.to_string(),
+                set_id: set_id.clone(),
+                original_volume: String::new(),
+                device_path: String::new(),
+                creation_time: creation_time.clone(),
+                provider: String::new(),
+                attributes: Vec::new(),
+                created_by_collector: false,
+            }),
+            field => {
+                let Some(copy) = shadow_copies.last_mut() else {
+                    continue;
+                };
+                match field {
+                    // (C:)\\?\Volume{...}\
+                    "Original Volume" => {
+                        copy.original_volume = value
+                            .strip_prefix('(')
+                            .and_then(|v| v.split_once(')'))
+                            .map(|(letter, _)| letter.to_uppercase())
+                            .unwrap_or_default()
+                    }
+                    "Shadow Copy Volume" => copy.device_path = value.to_string(),
+                    "Provider" => copy.provider = value.trim_matches('\'').to_string(),
+                    "Attributes" => copy.attributes = value.split(',').map(|a| a.trim().to_string()).filter(|a| !a.is_empty()).collect(),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    shadow_copies
+}
+
+#[cfg(windows)]
+mod windows_api {
+    use crate::logger::run_command;
+    use std::process::Command;
+
+    /// Run a built-in Windows tool; a non-zero exit code is an error carrying its output
+    pub fn run(program: &str, args: &[&str]) -> Result<String, String> {
+        let output = run_command("vss", Command::new(program).args(args))
+            .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+        if output.status.success() {
+            Ok(stdout)
+        } else {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let detail = if stderr.trim().is_empty() { stdout.trim() } else { stderr.trim() };
+            Err(format!("{} failed ({}): {}", program, output.status, detail.replace(['\r', '\n'], " ")))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VSSADMIN_OUTPUT: &str = "vssadmin 1.1 - Volume Shadow Copy Service administrative command-line tool\r
+(C) Copyright 2001-2013 Microsoft Corp.\r
+\r
+Contents of shadow copy set ID: {11111111-1111-1111-1111-111111111111}\r
+   Contained 1 shadow copies at creation time: 3/1/2024 9:15:02 AM\r
+      Shadow Copy ID: {aaaaaaaa-0000-0000-0000-000000000001}\r
+         Original Volume: (C:)\\\\?\\Volume{5f2d8a3c-0000-0000-0000-100000000000}\\\r
+         Shadow Copy Volume: \\\\?\\GLOBALROOT\\Device\\HarddiskVolumeShadowCopy1\r
+         Originating Machine: WS01\r
+         Service Machine: WS01\r
+         Provider: 'Microsoft Software Shadow Copy provider 1.0'\r
+         Type: ClientAccessibleWriters\r
+         Attributes: Persistent, Client-accessible, No auto release, Differential, Auto recovered\r
+\r
+Contents of shadow copy set ID: {22222222-2222-2222-2222-222222222222}\r
+   Contained 1 shadow copies at creation time: 3/8/2024 9:15:02 AM\r
+      Shadow Copy ID: {aaaaaaaa-0000-0000-0000-000000000002}\r
+         Original Volume: (C:)\\\\?\\Volume{5f2d8a3c-0000-0000-0000-100000000000}\\\r
+         Shadow Copy Volume: \\\\?\\GLOBALROOT\\Device\\HarddiskVolumeShadowCopy2\r
+         Provider: 'Microsoft Software Shadow Copy provider 1.0'\r
+         Attributes: Persistent, Client-accessible\r
+";
+
+    #[test]
+    fn test_parse_vssadmin_output() {
+        let copies = parse_vssadmin_output(VSSADMIN_OUTPUT);
+        assert_eq!(copies.len(), 2);
+        assert_eq!(copies[0].shadow_id, "{aaaaaaaa-0000-0000-0000-000000000001}");
+        assert_eq!(copies[0].set_id, "{11111111-1111-1111-1111-111111111111}");
+        assert_eq!(copies[0].original_volume, "C:");
+        assert_eq!(copies[0].device_path, "\\\\?\\GLOBALROOT\\Device\\HarddiskVolumeShadowCopy1");
+        assert_eq!(copies[0].creation_time, "3/1/2024 9:15:02 AM");
+        assert_eq!(copies[0].provider, "Microsoft Software Shadow Copy provider 1.0");
+        assert_eq!(copies[0].attributes.len(), 5);
+        assert_eq!(copies[1].creation_time, "3/8/2024 9:15:02 AM");
+
+        assert_eq!(newest_for_volume(&copies, "c:").unwrap().shadow_id, copies[1].shadow_id);
+        assert!(newest_for_volume(&copies, "D:").is_none());
+        assert!(parse_vssadmin_output("No items found that satisfy the query.").is_empty());
+    }
+
+    #[test]
+    fn test_map_to_snapshot() {
+        let snapshot = &parse_vssadmin_output(VSSADMIN_OUTPUT)[0];
+        assert_eq!(
+            map_to_snapshot(snapshot, Path::new("C:\\Users\\alice\\NTUSER.DAT")).unwrap(),
+            PathBuf::from("\\\\?\\GLOBALROOT\\Device\\HarddiskVolumeShadowCopy1\\Users\\alice\\NTUSER.DAT")
+        );
+        assert_eq!(
+            map_to_snapshot(snapshot, Path::new("\\\\?\\c:\\Windows\\AppCompat\\Programs\\Amcache.hve")).unwrap(),
+            PathBuf::from("\\\\?\\GLOBALROOT\\Device\\HarddiskVolumeShadowCopy1\\Windows\\AppCompat\\Programs\\Amcache.hve")
+        );
+        assert!(map_to_snapshot(snapshot, Path::new("D:\\data.db")).is_none());
+        assert!(map_to_snapshot(snapshot, Path::new("relative\\path")).is_none());
+    }
+
+    #[test]
+    fn test_snapshot_mode() {
+        assert_eq!(SnapshotMode::parse("Create").unwrap(), SnapshotMode::Create);
+        assert_eq!(SnapshotMode::parse("existing").unwrap(), SnapshotMode::Existing);
+        assert!(SnapshotMode::parse("latest").is_err());
+    }
+}
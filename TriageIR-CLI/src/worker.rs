@@ -0,0 +1,256 @@
+//! Warm-standby worker
+//! `triageir-cli worker` stays running for the GUI: scan requests arrive on stdin
+//! as JSON lines and progress and results leave on stdout as JSON lines. State
+//! that is expensive to set up survives between scans: the enrichment feeds and
+//! GeoIP databases stay loaded, the file hash cache is kept while the algorithms
+//! do not change, and file_sweep keeps compiled YARA rules until the rule file
+//! changes. Repeated targeted collections then skip the re-initialization.
+//!
+//! Request:  {"id": "1", "output": "scan.json", "config": {...ScanConfig...}}
+//! Events:   {"event": "ready", "version": "..."}
+//!           {"event": "progress", "id": "1", "phase": "...", "percent": 40, ...}   as with --progress json
+//!           {"event": "record", "id": "1", "section": "...", "kind": "...", "data": ...}
+//!           {"event": "result", "id": "1", "status": "completed", ...}
+//! Record events (the records of `convert`) replace the output file when a
+//! request has none. The worker exits when stdin is closed; the remote agent
+//! (`serve`) speaks the same protocol over TLS.
+
+use crate::convert;
+use crate::enrichment::Enricher;
+use crate::hashing;
+use crate::logger::Logger;
+use crate::progress;
+use crate::scan::{self, ScanConfig, ScanOptions};
+use crate::stream_writer::{self, StreamWriter};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Instant, SystemTime};
+
+/// One scan request; `config` takes the same keys as the C ABI. Without an
+/// `output` file the results are streamed back as record events.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WorkerRequest {
+    #[serde(default)]
+    pub id: String,
+    pub output: Option<String>,
+    #[serde(default)]
+    pub config: ScanConfig,
+}
+
+/// Enrichment providers loaded for an earlier request
+struct LoadedEnricher {
+    sources: Vec<String>,
+    modified: Vec<Option<SystemTime>>,
+    enricher: Arc<Enricher>,
+}
+
+/// State kept loaded between the scans of one worker process
+#[derive(Default)]
+pub struct WarmCache {
+    enricher: Option<LoadedEnricher>,
+    scans: usize,
+}
+
+impl WarmCache {
+    /// Validated options for a request, reusing what earlier requests loaded.
+    /// Returns whether the enrichment providers were reused.
+    pub fn prepare(&mut self, mut config: ScanConfig) -> Result<(ScanOptions, Vec<String>, bool), String> {
+        let sources = std::mem::take(&mut config.enrich);
+        let (mut options, warnings) = config.into_options()?;
+        let reused = self.enricher.as_ref().is_some_and(|loaded| loaded.sources == sources && loaded.modified == source_times(&sources));
+        options.enrichment = match (sources.is_empty(), reused) {
+            (true, _) => None,
+            (false, true) => self.enricher.as_ref().map(|loaded| {
+                loaded.enricher.clear_lookups();
+                Arc::clone(&loaded.enricher)
+            }),
+            (false, false) => {
+                let enricher = Arc::new(Enricher::from_sources(&sources)?);
+                let modified = source_times(&sources);
+                self.enricher = Some(LoadedEnricher { sources, modified, enricher: Arc::clone(&enricher) });
+                Some(enricher)
+            }
+        };
+        options.warm_hash_cache = self.scans > 0;
+        Ok((options, warnings, reused))
+    }
+
+    /// Run the request on one protocol line and describe its outcome as a result
+    /// event. Remote requests may not write files or load code on this host: no
+    /// output file, raw copy directory or plugin directory.
+    pub fn handle(&mut self, line: &str, output: &mut dyn Write, remote: bool) -> Value {
+        let request = match serde_json::from_str::<WorkerRequest>(line) {
+            Ok(request) => request,
+            Err(e) => {
+                let id = serde_json::from_str::<Value>(line).ok().and_then(|value| value["id"].as_str().map(str::to_string)).unwrap_or_default();
+                return result(&id, "invalid_request", Some(format!("Invalid scan request: {}", e)), Instant::now());
+            }
+        };
+        if remote && (request.output.is_some() || request.config.raw_dir.is_some() || request.config.plugins_dir.is_some()) {
+            let message = "remote requests cannot name an output file, raw copy directory or plugin directory";
+            return result(&request.id, "invalid_request", Some(message.to_string()), Instant::now());
+        }
+        self.serve(request, output)
+    }
+
+    /// Run one request and describe its outcome as a result event
+    fn serve(&mut self, request: WorkerRequest, output: &mut dyn Write) -> Value {
+        let start_time = Instant::now();
+        let id = request.id;
+        let (options, warnings, enrichment_reused) = match self.prepare(request.config) {
+            Ok(prepared) => prepared,
+            Err(e) => return result(&id, "invalid_request", Some(e), start_time),
+        };
+
+        let logger = Logger::new(false);
+        for warning in &warnings {
+            logger.warn(warning);
+        }
+        // Results written to a file spool their bulk sections next to it during the scan
+        let stream = request.output.as_deref().and_then(|output_file| StreamWriter::create(&spool_path(Path::new(output_file)), stream_writer::CHANNEL_CAPACITY).ok());
+        let outcome = scan::run_scan(&options, &logger, stream.as_ref(), &mut |update| {
+            let mut event = progress::progress_event(update);
+            event["id"] = json!(id);
+            let _ = emit(output, &event);
+        });
+        self.scans += 1;
+
+        let written = match &request.output {
+            Some(output_file) => write_results(&outcome.results, stream, Path::new(output_file)),
+            None => convert::records(&outcome.results)
+                .into_iter()
+                .try_for_each(|(section, kind, data)| emit(output, &json!({ "event": "record", "id": id, "section": section, "kind": kind, "data": data })))
+                .map_err(|e| format!("Failed to stream scan results: {}", e)),
+        };
+
+        let mut event = match written {
+            Err(e) => result(&id, "output_failed", Some(e), start_time),
+            Ok(()) if outcome.log_summary.has_errors() => {
+                let message = format!("{} errors encountered during collection", outcome.log_summary.error_count);
+                result(&id, "completed_with_errors", Some(message), start_time)
+            }
+            Ok(()) => result(&id, "completed", None, start_time),
+        };
+        let hash_stats = hashing::shared().stats();
+        if let Some(output_file) = &request.output {
+            event["output"] = json!(output_file);
+        }
+        event["total_artifacts"] = json!(outcome.total_artifacts);
+        event["warm"] = json!({
+            "scans_served": self.scans,
+            "enrichment_reused": enrichment_reused,
+            "files_hashed": hash_stats.files_hashed,
+            "hash_cache_hits": hash_stats.cache_hits,
+        });
+        event
+    }
+}
+
+/// Serve scan requests from `input` until it is closed
+pub fn run(input: impl BufRead, output: &mut dyn Write) -> io::Result<()> {
+    let mut cache = WarmCache::default();
+    emit(output, &json!({ "event": "ready", "version": env!("CARGO_PKG_VERSION") }))?;
+
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let event = cache.handle(&line, output, false);
+        emit(output, &event)?;
+    }
+    Ok(())
+}
+
+fn write_results(results: &Value, stream: Option<StreamWriter>, output_path: &Path) -> Result<(), String> {
+    stream.map(StreamWriter::finish).transpose()
+        .and_then(|spool| spool.unwrap_or_default().write_file(results, output_path))
+        .map(|_| ())
+        .map_err(|e| format!("Failed to write {}: {}", output_path.display(), e))
+}
+
+/// Hidden spool file next to `output_path`
+fn spool_path(output_path: &Path) -> PathBuf {
+    let name = output_path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    output_path.with_file_name(format!(".{}.spool", name))
+}
+
+fn result(id: &str, status: &str, error: Option<String>, start_time: Instant) -> Value {
+    json!({
+        "event": "result",
+        "id": id,
+        "status": status,
+        "error": error,
+        "duration_ms": start_time.elapsed().as_millis() as u64,
+    })
+}
+
+/// One protocol line, flushed so the reader sees it immediately
+pub fn emit(output: &mut dyn Write, event: &Value) -> io::Result<()> {
+    writeln!(output, "{}", event)?;
+    output.flush()
+}
+
+/// Modification times of the files behind enrichment sources, to reload changed feeds
+fn source_times(sources: &[String]) -> Vec<Option<SystemTime>> {
+    sources
+        .iter()
+        .map(|source| {
+            let path = source.trim().split_once(':').map(|(_, path)| path)?;
+            fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_worker_keeps_state_between_scans() {
+        let volume = tempfile::tempdir().unwrap();
+        fs::create_dir(volume.path().join("Windows")).unwrap();
+        let work_dir = tempfile::tempdir().unwrap();
+        let feed = work_dir.path().join("feed.csv");
+        fs::write(&feed, "indicator,threat_type\n203.0.113.7,c2\n").unwrap();
+
+        let request = |id: &str| {
+            let output = work_dir.path().join(format!("scan-{}.json", id));
+            json!({ "id": id, "output": output, "config": { "offline_root": volume.path(), "enrich": [format!("csv:{}", feed.display())] } }).to_string()
+        };
+        let streamed = json!({ "id": "4", "config": { "offline_root": volume.path() } }).to_string();
+        let input = [request("1"), String::new(), request("2"), r#"{"id": "3", "config": {"since": "yesterday"}}"#.to_string(), streamed].join("\n");
+        let mut output = Vec::new();
+        run(input.as_bytes(), &mut output).unwrap();
+
+        let events: Vec<Value> = String::from_utf8(output).unwrap().lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(events[0]["event"], "ready");
+        assert!(events.iter().any(|event| event["event"] == "progress" && event["id"] == "1"));
+        let results: Vec<&Value> = events.iter().filter(|event| event["event"] == "result").collect();
+        assert_eq!(results.len(), 4);
+
+        for result in &results[..2] {
+            assert!(result["status"] == "completed" || result["status"] == "completed_with_errors");
+            let scan: Value = serde_json::from_str(&fs::read_to_string(result["output"].as_str().unwrap()).unwrap()).unwrap();
+            assert_eq!(scan["scan_metadata"]["offline"], true);
+        }
+        assert_eq!(results[0]["warm"]["enrichment_reused"], false);
+        assert_eq!(results[1]["warm"]["enrichment_reused"], true);
+        assert_eq!(results[1]["warm"]["scans_served"], 2);
+
+        assert_eq!(results[2]["id"], "3");
+        assert_eq!(results[2]["status"], "invalid_request");
+        assert!(results[2]["error"].as_str().unwrap().contains("--since"));
+
+        assert!(results[3]["output"].is_null());
+        assert!(events.iter().any(|event| event["event"] == "record" && event["id"] == "4" && event["section"] == "scan_metadata" && event["data"]["offline"] == true));
+        let mut cache = WarmCache::default();
+        let refused = cache.handle(r#"{"id": "5", "output": "C:\\Windows\\x.json"}"#, &mut Vec::new(), true);
+        assert_eq!(refused["status"], "invalid_request");
+    }
+}
@@ -0,0 +1,405 @@
+//! Xpress Huffman decompression
+//! Native implementation of the LZ77+Huffman format of [MS-XCA] 2.2
+//! (COMPRESSION_FORMAT_XPRESS_HUFF), used by the MAM-compressed Prefetch files
+//! of Windows 10 and 11. No Windows API is needed, so compressed artifacts of a
+//! mounted image decompress on any platform. The stream is a series of blocks,
+//! one per 64 KiB of output, each starting with a 256-byte table of the 4-bit
+//! code lengths of its 512 symbols: 0-255 are literals, 256-511 matches.
+
+/// Symbols per block table: 256 literals and 256 match symbols
+const SYMBOLS: usize = 512;
+/// Longest code; the decoding table is indexed by the next 15 bits
+const MAX_CODE_LENGTH: u32 = 15;
+/// Output bytes decoded with one table
+const BLOCK_SIZE: usize = 65536;
+
+/// Decompress `input` to exactly `output_size` bytes
+pub fn decompress(input: &[u8], output_size: usize) -> Result<Vec<u8>, String> {
+    let mut output = Vec::with_capacity(output_size);
+    let mut position = 0;
+    while output.len() < output_size {
+        let table = input
+            .get(position..position + SYMBOLS / 2)
+            .ok_or_else(|| format!("truncated Huffman table at offset {}", position))?;
+        let decoder = Decoder::new(table)?;
+        position += SYMBOLS / 2;
+
+        let mut bits = BitReader::new(input, position)?;
+        let block_end = (output.len() + BLOCK_SIZE).min(output_size);
+        while output.len() < block_end {
+            let symbol = decoder.symbol(&mut bits)?;
+            if symbol < 256 {
+                output.push(symbol as u8);
+                continue;
+            }
+            let symbol = symbol - 256;
+            let offset_bits = (symbol >> 4) as u32;
+            let mut length = symbol & 15;
+            if length == 15 {
+                length = bits.byte()? as usize;
+                if length == 255 {
+                    length = bits.u16()? as usize;
+                    if length == 0 {
+                        length = bits.u32()? as usize;
+                    }
+                    if length < 15 {
+                        return Err(format!("invalid match length {} at offset {}", length, bits.position));
+                    }
+                    length -= 15;
+                }
+                length += 15;
+            }
+            length += 3;
+            let offset = (bits.take(offset_bits)? | (1 << offset_bits)) as usize;
+            if offset > output.len() {
+                return Err(format!("match offset {} before the start of the output ({} bytes)", offset, output.len()));
+            }
+            // Overlapping matches repeat the bytes copied so far
+            let start = output.len() - offset;
+            for index in 0..length.min(output_size - output.len()) {
+                output.push(output[start + index]);
+            }
+        }
+        position = bits.position;
+    }
+    Ok(output)
+}
+
+/// Canonical Huffman decoding table of one block
+struct Decoder {
+    entries: Vec<(u16, u8)>, // (symbol, code length) for every 15-bit prefix
+}
+
+impl Decoder {
+    fn new(table: &[u8]) -> Result<Self, String> {
+        let lengths: Vec<u32> = table.iter().flat_map(|byte| [(byte & 0x0F) as u32, (byte >> 4) as u32]).collect();
+        let mut entries = vec![(0u16, 0u8); 1 << MAX_CODE_LENGTH];
+        let mut next = 0usize;
+        for length in 1..=MAX_CODE_LENGTH {
+            for (symbol, _) in lengths.iter().enumerate().filter(|(_, &l)| l == length) {
+                let span = 1usize << (MAX_CODE_LENGTH - length);
+                if next + span > entries.len() {
+                    return Err("oversubscribed Huffman table".to_string());
+                }
+                entries[next..next + span].fill((symbol as u16, length as u8));
+                next += span;
+            }
+        }
+        if next == 0 {
+            return Err("empty Huffman table".to_string());
+        }
+        Ok(Decoder { entries })
+    }
+
+    fn symbol(&self, bits: &mut BitReader) -> Result<usize, String> {
+        let (symbol, length) = self.entries[(bits.next >> (32 - MAX_CODE_LENGTH)) as usize];
+        if length == 0 {
+            return Err(format!("invalid Huffman code at offset {}", bits.position));
+        }
+        bits.consume(length as u32)?;
+        Ok(symbol as usize)
+    }
+}
+
+/// The bit stream is read as little-endian 16-bit words, most significant bit
+/// first; extra match length bytes are taken from the byte position in between
+struct BitReader<'a> {
+    input: &'a [u8],
+    position: usize,
+    next: u32,
+    extra_bits: i32,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(input: &'a [u8], position: usize) -> Result<Self, String> {
+        let mut reader = BitReader { input, position, next: 0, extra_bits: 16 };
+        reader.next = (reader.u16()? as u32) << 16 | reader.u16()? as u32;
+        Ok(reader)
+    }
+
+    fn take(&mut self, count: u32) -> Result<u32, String> {
+        if count == 0 {
+            return Ok(0);
+        }
+        let value = self.next >> (32 - count);
+        self.consume(count)?;
+        Ok(value)
+    }
+
+    fn consume(&mut self, count: u32) -> Result<(), String> {
+        self.next = self.next.checked_shl(count).unwrap_or(0);
+        self.extra_bits -= count as i32;
+        if self.extra_bits < 0 {
+            // The final word of a stream may be missing when its bits are not needed
+            let word = self.u16().unwrap_or(0) as u32;
+            self.next |= word << (-self.extra_bits) as u32;
+            self.extra_bits += 16;
+        }
+        Ok(())
+    }
+
+    fn byte(&mut self) -> Result<u8, String> {
+        let byte = *self.input.get(self.position).ok_or("truncated match length")?;
+        self.position += 1;
+        Ok(byte)
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        let bytes = self.input.get(self.position..self.position + 2).ok_or("truncated compressed stream")?;
+        self.position += 2;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(self.u16()? as u32 | (self.u16()? as u32) << 16)
+    }
+}
+
+/// Minimal encoder for tests: one block, the given code lengths, literals and
+/// matches of 3-17 bytes
+#[cfg(test)]
+pub(crate) fn encode_for_tests(tokens: &[Token], lengths: &[u8; SYMBOLS]) -> Vec<u8> {
+    let mut writer = BitWriter::new(lengths.chunks(2).map(|pair| pair[0] | pair[1] << 4).collect());
+    let codes = canonical_codes(lengths);
+    for token in tokens {
+        writer.token(token, &codes, lengths);
+    }
+    for _ in 0..3 {
+        writer.bits(0, 16);
+    }
+    writer.finish()
+}
+
+/// Encoder for tests that works like the Windows compressor: greedy LZ77
+/// matches of any length within 64 KiB, a Huffman table built from the symbol
+/// frequencies of every 64 KiB block and the end-of-stream symbol 256
+#[cfg(test)]
+pub(crate) fn compress_for_tests(data: &[u8]) -> Vec<u8> {
+    let mut tokens = Vec::new();
+    let mut candidates: std::collections::HashMap<&[u8], Vec<usize>> = std::collections::HashMap::new();
+    let mut position = 0;
+    while position < data.len() {
+        let best = data.get(position..position + 3).and_then(|key| candidates.get(key)).and_then(|starts| {
+            starts.iter().rev().take(32).filter(|&&start| position - start < BLOCK_SIZE)
+                .map(|&start| (data[position..].iter().zip(&data[start..]).take_while(|(a, b)| a == b).count(), position - start))
+                .max()
+        });
+        let step = match best {
+            Some((length, offset)) if length >= 3 => {
+                tokens.push(Token::Match { length, offset });
+                length
+            }
+            _ => {
+                tokens.push(Token::Literal(data[position]));
+                1
+            }
+        };
+        for start in position..(position + step).min(data.len().saturating_sub(2)) {
+            candidates.entry(&data[start..start + 3]).or_default().push(start);
+        }
+        position += step;
+    }
+
+    // Blocks end at the first token reaching 64 KiB of output past their start
+    let mut blocks = vec![Vec::new()];
+    let (mut produced, mut block_end) = (0, BLOCK_SIZE);
+    for token in tokens {
+        produced += match token {
+            Token::Literal(_) => 1,
+            Token::Match { length, .. } => length,
+        };
+        blocks.last_mut().unwrap().push(token);
+        if produced >= block_end && produced < data.len() {
+            blocks.push(Vec::new());
+            block_end = produced + BLOCK_SIZE;
+        }
+    }
+    blocks.last_mut().unwrap().push(Token::Match { length: 3, offset: 1 }); // Symbol 256
+
+    let mut output = Vec::new();
+    for block in blocks {
+        let mut frequencies = [0u64; SYMBOLS];
+        for token in &block {
+            frequencies[token.symbol()] += 1;
+        }
+        let lengths = huffman_lengths(&frequencies);
+        let codes = canonical_codes(&lengths);
+        output.extend(lengths.chunks(2).map(|pair| pair[0] | pair[1] << 4));
+        let mut writer = BitWriter::new(output);
+        for token in &block {
+            writer.token(token, &codes, &lengths);
+        }
+        output = writer.finish();
+    }
+    output
+}
+
+/// Code lengths of at most MAX_CODE_LENGTH bits, halving the frequencies until
+/// the Huffman tree is shallow enough
+#[cfg(test)]
+fn huffman_lengths(frequencies: &[u64; SYMBOLS]) -> [u8; SYMBOLS] {
+    use std::cmp::Reverse;
+    use std::collections::BinaryHeap;
+
+    let mut frequencies = *frequencies;
+    loop {
+        let mut lengths = [0u8; SYMBOLS];
+        let mut heap: BinaryHeap<Reverse<(u64, Vec<usize>)>> =
+            (0..SYMBOLS).filter(|&symbol| frequencies[symbol] > 0).map(|symbol| Reverse((frequencies[symbol], vec![symbol]))).collect();
+        if heap.len() == 1 {
+            let Reverse((_, symbols)) = heap.pop().unwrap();
+            lengths[symbols[0]] = 1;
+            return lengths;
+        }
+        while heap.len() > 1 {
+            let (Reverse((first, mut merged)), Reverse((second, symbols))) = (heap.pop().unwrap(), heap.pop().unwrap());
+            merged.extend(symbols);
+            for &symbol in &merged {
+                lengths[symbol] += 1;
+            }
+            heap.push(Reverse((first + second, merged)));
+        }
+        if lengths.iter().all(|&length| length as u32 <= MAX_CODE_LENGTH) {
+            return lengths;
+        }
+        for frequency in frequencies.iter_mut().filter(|frequency| **frequency > 0) {
+            *frequency = frequency.div_ceil(2);
+        }
+    }
+}
+
+#[cfg(test)]
+fn canonical_codes(lengths: &[u8; SYMBOLS]) -> [u32; SYMBOLS] {
+    let mut codes = [0u32; SYMBOLS];
+    let mut next = 0u32;
+    for length in 1..=MAX_CODE_LENGTH as u8 {
+        for symbol in (0..SYMBOLS).filter(|&s| lengths[s] == length) {
+            codes[symbol] = next;
+            next += 1;
+        }
+        next <<= 1;
+    }
+    codes
+}
+
+/// Writes 16-bit words most significant bit first into slots reserved two
+/// words ahead, so bytes written in between land where the decoder reads them
+#[cfg(test)]
+struct BitWriter {
+    output: Vec<u8>,
+    slots: [usize; 2],
+    accumulator: u32,
+    count: u32,
+}
+
+#[cfg(test)]
+impl BitWriter {
+    fn new(mut output: Vec<u8>) -> Self {
+        let slots = [output.len(), output.len() + 2];
+        output.extend_from_slice(&[0; 4]);
+        BitWriter { output, slots, accumulator: 0, count: 0 }
+    }
+
+    fn bits(&mut self, value: u32, bits: u32) {
+        for bit in (0..bits).rev() {
+            if self.count == 16 {
+                self.flush();
+            }
+            self.accumulator = self.accumulator << 1 | (value >> bit & 1);
+            self.count += 1;
+        }
+    }
+
+    fn flush(&mut self) {
+        let word = self.accumulator as u16;
+        self.output[self.slots[0]..self.slots[0] + 2].copy_from_slice(&word.to_le_bytes());
+        self.slots = [self.slots[1], self.output.len()];
+        self.output.extend_from_slice(&[0; 2]);
+        (self.accumulator, self.count) = (0, 0);
+    }
+
+    fn token(&mut self, token: &Token, codes: &[u32; SYMBOLS], lengths: &[u8; SYMBOLS]) {
+        let symbol = token.symbol();
+        self.bits(codes[symbol], lengths[symbol] as u32);
+        let Token::Match { length, offset } = *token else {
+            return;
+        };
+        let length = length - 3;
+        if length >= 15 {
+            if length - 15 < 255 {
+                self.output.push((length - 15) as u8);
+            } else {
+                self.output.push(255);
+                if length <= u16::MAX as usize {
+                    self.output.extend_from_slice(&(length as u16).to_le_bytes());
+                } else {
+                    self.output.extend_from_slice(&[0; 2]);
+                    self.output.extend_from_slice(&(length as u32).to_le_bytes());
+                }
+            }
+        }
+        let offset_bits = 31 - (offset as u32).leading_zeros();
+        self.bits(offset as u32 & ((1 << offset_bits) - 1), offset_bits);
+    }
+
+    /// The words the decoder has read ahead end the block; the next table follows them
+    fn finish(mut self) -> Vec<u8> {
+        let word = (self.accumulator << (16 - self.count)) as u16;
+        self.output[self.slots[0]..self.slots[0] + 2].copy_from_slice(&word.to_le_bytes());
+        self.output
+    }
+}
+
+#[cfg(test)]
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Token {
+    Literal(u8),
+    Match { length: usize, offset: usize },
+}
+
+#[cfg(test)]
+impl Token {
+    fn symbol(&self) -> usize {
+        match *self {
+            Token::Literal(byte) => byte as usize,
+            Token::Match { length, offset } => 256 + (((31 - (offset as u32).leading_zeros()) as usize) << 4 | (length - 3).min(15)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decompress_literals_and_matches() {
+        // Mixed code lengths: literal 0 is 2 bits, other literals 10, matches 9
+        let mut lengths = [10u8; SYMBOLS];
+        lengths[0] = 2;
+        lengths[256..].fill(9);
+        let mut tokens: Vec<Token> = b"SCCA\0\0".iter().map(|&b| Token::Literal(b)).collect();
+        tokens.push(Token::Match { length: 12, offset: 2 }); // Overlapping run of zeros
+        tokens.extend(b"abcdef".iter().map(|&b| Token::Literal(b)));
+        tokens.push(Token::Match { length: 17, offset: 6 });
+        let compressed = encode_for_tests(&tokens, &lengths);
+
+        let expected = [b"SCCA".as_slice(), &[0; 14], b"abcdef", b"abcdefabcdefabcde"].concat();
+        assert_eq!(decompress(&compressed, expected.len()).unwrap(), expected);
+        assert!(decompress(&compressed[..100], expected.len()).is_err());
+
+        // A match reaching before the start of the output is rejected
+        let invalid = encode_for_tests(&[Token::Literal(1), Token::Match { length: 3, offset: 4 }], &lengths);
+        assert!(decompress(&invalid, 4).unwrap_err().contains("before the start"));
+    }
+
+    #[test]
+    fn test_decompress_blocks_and_long_matches() {
+        // Three 64 KiB blocks with their own tables; the zero runs need the
+        // one-byte, 16-bit and 32-bit match length extensions
+        let text: Vec<u8> = (0..6000u32).flat_map(|n| format!("\\VOLUME{{01d7}}\\WINDOWS\\SYSTEM32\\MODULE{}.DLL;", n * 7919 % 10007).into_bytes()).collect();
+        let data = [&text[..40_000], &[0u8; 200], &text[40_000..90_000], &[0u8; 3000], &text[90_000..], &[0u8; 70_000]].concat();
+        let compressed = compress_for_tests(&data);
+        assert!(compressed.len() < data.len() / 2);
+        assert_eq!(decompress(&compressed, data.len()).unwrap(), data);
+    }
+}
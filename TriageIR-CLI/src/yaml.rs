@@ -0,0 +1,294 @@
+//! Reader for the YAML subset of the detection rule files
+//! Covers what Sigma rules use: block mappings and sequences by indentation,
+//! plain, single- and double-quoted scalars, flow sequences of scalars, `|` and
+//! `>` block scalars, comments and `---` document separators. Anchors, tags,
+//! flow mappings and multi-line plain scalars are rejected or read literally.
+//! Scalars are returned as strings (`null` and `~` as null); the rule engine
+//! compares values as text.
+
+use serde_json::{Map, Value};
+
+struct Line<'a> {
+    number: usize, // 1-based, for error messages
+    indent: usize,
+    text: &'a str,
+}
+
+/// Parse every document of the text
+pub fn parse_documents(text: &str) -> Result<Vec<Value>, String> {
+    let mut documents = Vec::new();
+    let mut current: Vec<Line> = Vec::new();
+    let mut block_scalar_indent = None; // Indentation of the key owning an open | or > block
+    for (index, raw) in text.lines().enumerate() {
+        let content = raw.trim_end();
+        if content == "---" || content == "..." {
+            if !current.is_empty() {
+                documents.push(parse_lines(std::mem::take(&mut current))?);
+            }
+            block_scalar_indent = None;
+            continue;
+        }
+        if content.is_empty() {
+            continue;
+        }
+        let indent = content.len() - content.trim_start_matches(' ').len();
+        let text = &content[indent..];
+        if text.starts_with('\t') {
+            return Err(format!("line {}: tabs are not allowed for indentation", index + 1));
+        }
+        if block_scalar_indent.is_some_and(|owner| indent > owner) {
+            current.push(Line { number: index + 1, indent, text });
+            continue;
+        }
+        let code = strip_comment(text);
+        if code.is_empty() {
+            continue;
+        }
+        let value = split_key(code.trim_start_matches("- ")).map(|(_, value)| value).unwrap_or_default();
+        block_scalar_indent = (value.starts_with('|') || value.starts_with('>')).then_some(indent);
+        current.push(Line { number: index + 1, indent, text });
+    }
+    if !current.is_empty() {
+        documents.push(parse_lines(current)?);
+    }
+    Ok(documents)
+}
+
+fn parse_lines(mut lines: Vec<Line>) -> Result<Value, String> {
+    let mut position = 0;
+    let indent = lines[0].indent;
+    let value = parse_block(&mut lines, &mut position, indent)?;
+    match lines.get(position) {
+        Some(line) => Err(format!("line {}: unexpected indentation", line.number)),
+        None => Ok(value),
+    }
+}
+
+fn parse_block(lines: &mut [Line], position: &mut usize, indent: usize) -> Result<Value, String> {
+    if is_sequence_item(lines[*position].text) {
+        parse_sequence(lines, position, indent)
+    } else {
+        parse_mapping(lines, position, indent)
+    }
+}
+
+fn is_sequence_item(text: &str) -> bool {
+    text == "-" || text.starts_with("- ")
+}
+
+fn parse_sequence(lines: &mut [Line], position: &mut usize, indent: usize) -> Result<Value, String> {
+    let mut items = Vec::new();
+    while *position < lines.len() && lines[*position].indent == indent && is_sequence_item(lines[*position].text) {
+        let line = &lines[*position];
+        let rest = line.text[1..].trim_start();
+        let rest_indent = indent + line.text.len() - rest.len();
+        let content = strip_comment(rest);
+        if content.is_empty() {
+            *position += 1;
+            items.push(match lines.get(*position) {
+                Some(next) if next.indent > indent => {
+                    let next_indent = next.indent;
+                    parse_block(lines, position, next_indent)?
+                }
+                _ => Value::Null,
+            });
+        } else if split_key(content).is_some() || is_sequence_item(content) {
+            // "- key: value" starts a mapping whose further keys are aligned with the first
+            lines[*position].indent = rest_indent;
+            lines[*position].text = rest;
+            items.push(parse_block(lines, position, rest_indent)?);
+        } else {
+            let number = line.number;
+            *position += 1;
+            items.push(scalar_or_flow(content, number)?);
+        }
+    }
+    Ok(Value::Array(items))
+}
+
+fn parse_mapping(lines: &mut [Line], position: &mut usize, indent: usize) -> Result<Value, String> {
+    let mut map = Map::new();
+    while *position < lines.len() && lines[*position].indent == indent && !is_sequence_item(lines[*position].text) {
+        let line = &lines[*position];
+        let number = line.number;
+        let (key, value) = split_key(strip_comment(line.text)).ok_or_else(|| format!("line {}: expected `key: value`", number))?;
+        let key = unquote(key, number)?;
+        *position += 1;
+
+        let value = if value.is_empty() {
+            match lines.get(*position) {
+                // Sequences may be indented at the level of their key
+                Some(next) if next.indent > indent || (next.indent == indent && is_sequence_item(next.text)) => {
+                    let next_indent = next.indent;
+                    parse_block(lines, position, next_indent)?
+                }
+                _ => Value::Null,
+            }
+        } else if value.starts_with('|') || value.starts_with('>') {
+            let mut block = Vec::new();
+            while *position < lines.len() && lines[*position].indent > indent {
+                block.push(lines[*position].text);
+                *position += 1;
+            }
+            let separator = if value.starts_with('|') { "\n" } else { " " };
+            Value::String(block.join(separator))
+        } else {
+            scalar_or_flow(value, number)?
+        };
+        if map.insert(key.clone(), value).is_some() {
+            return Err(format!("line {}: duplicate key {}", number, key));
+        }
+        if let Some(next) = lines.get(*position).filter(|next| next.indent > indent) {
+            return Err(format!("line {}: unexpected indentation", next.number));
+        }
+    }
+    Ok(Value::Object(map))
+}
+
+/// Split `key: value` at the first colon outside quotes that ends the line or is followed by a space
+fn split_key(text: &str) -> Option<(&str, &str)> {
+    let mut quote = None;
+    for (index, c) in text.char_indices() {
+        match (quote, c) {
+            (None, '\'' | '"') if index == 0 => quote = Some(c),
+            (Some(open), _) if c == open => quote = None,
+            (None, ':') if text[index + 1..].is_empty() || text[index + 1..].starts_with(' ') => {
+                return Some((text[..index].trim_end(), text[index + 1..].trim()));
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Remove a `#` comment that starts the text or follows a space, outside quotes
+fn strip_comment(text: &str) -> &str {
+    let mut quote = None;
+    let mut previous = ' ';
+    for (index, c) in text.char_indices() {
+        match (quote, c) {
+            (None, '\'' | '"') if previous == ' ' || previous == '[' || previous == ',' => quote = Some(c),
+            (Some(open), _) if c == open => quote = None,
+            (None, '#') if previous == ' ' => return text[..index].trim_end(),
+            _ => {}
+        }
+        previous = c;
+    }
+    text.trim_end()
+}
+
+fn scalar_or_flow(text: &str, number: usize) -> Result<Value, String> {
+    if let Some(inner) = text.strip_prefix('[') {
+        let inner = inner.strip_suffix(']').ok_or_else(|| format!("line {}: unterminated flow sequence", number))?;
+        let items = split_flow_items(inner, number)?;
+        return items.into_iter().map(|item| scalar(item, number)).collect::<Result<Vec<_>, _>>().map(Value::Array);
+    }
+    if text == "{}" {
+        return Ok(Value::Object(Map::new()));
+    }
+    if text.starts_with(['{', '&', '*', '!']) {
+        return Err(format!("line {}: flow mappings, anchors, aliases and tags are not supported", number));
+    }
+    scalar(text, number)
+}
+
+fn split_flow_items(text: &str, number: usize) -> Result<Vec<&str>, String> {
+    let mut items = Vec::new();
+    let mut quote = None;
+    let mut start = 0;
+    for (index, c) in text.char_indices() {
+        match (quote, c) {
+            (None, '\'' | '"') => quote = Some(c),
+            (Some(open), _) if c == open => quote = None,
+            (None, ',') => {
+                items.push(text[start..index].trim());
+                start = index + 1;
+            }
+            (None, '[' | '{') => return Err(format!("line {}: nested flow collections are not supported", number)),
+            _ => {}
+        }
+    }
+    let last = text[start..].trim();
+    if !last.is_empty() || !items.is_empty() {
+        items.push(last);
+    }
+    Ok(items)
+}
+
+fn scalar(text: &str, number: usize) -> Result<Value, String> {
+    match text {
+        "null" | "Null" | "NULL" | "~" | "" => Ok(Value::Null),
+        _ => unquote(text, number).map(Value::String),
+    }
+}
+
+fn unquote(text: &str, number: usize) -> Result<String, String> {
+    let unterminated = || format!("line {}: unterminated quoted string", number);
+    if let Some(inner) = text.strip_prefix('\'') {
+        let inner = inner.strip_suffix('\'').ok_or_else(unterminated)?;
+        Ok(inner.replace("''", "'"))
+    } else if let Some(inner) = text.strip_prefix('"') {
+        let inner = inner.strip_suffix('"').ok_or_else(unterminated)?;
+        let mut result = String::with_capacity(inner.len());
+        let mut chars = inner.chars();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                result.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('n') => result.push('\n'),
+                Some('t') => result.push('\t'),
+                Some(other) => result.push(other),
+                None => return Err(unterminated()),
+            }
+        }
+        Ok(result)
+    } else {
+        Ok(text.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_rule_subset() {
+        let text = r#"
+title: Encoded PowerShell # trailing comment
+tags:
+- attack.execution
+- attack.t1059.001
+description: |
+    First line
+    # not a comment
+detection:
+    selection:
+        Image|endswith: '\powershell.exe'
+        CommandLine|contains: [' -enc', "-e\\ncoded"]
+    keywords:
+        - 'it''s'
+        - EventID: 4688
+          NewProcessName: null
+    condition: selection
+---
+title: Second
+"#;
+        let documents = parse_documents(text).unwrap();
+        assert_eq!(documents.len(), 2);
+        let rule = &documents[0];
+        assert_eq!(rule["title"], "Encoded PowerShell");
+        assert_eq!(rule["tags"], json!(["attack.execution", "attack.t1059.001"]));
+        assert_eq!(rule["description"], "First line\n# not a comment");
+        assert_eq!(rule["detection"]["selection"]["Image|endswith"], "\\powershell.exe");
+        assert_eq!(rule["detection"]["selection"]["CommandLine|contains"], json!([" -enc", "-e\\ncoded"]));
+        assert_eq!(rule["detection"]["keywords"], json!(["it's", {"EventID": "4688", "NewProcessName": null}]));
+        assert_eq!(documents[1]["title"], "Second");
+
+        assert!(parse_documents("a: 1\n  b: 2").is_err());
+        assert!(parse_documents("a: 1\na: 2").is_err());
+        assert!(parse_documents("a: &anchor x").is_err());
+    }
+}
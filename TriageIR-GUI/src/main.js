@@ -10,6 +10,9 @@ class TriageIRMain {
         this.portableMode = false;
         this.usbDrive = null;
         this.outputDir = null;
+        this.worker = null; // Warm-standby CLI process, started by the first warm scan
+        this.workerRequests = new Map();
+        this.nextRequestId = 1;
         this.detectPortableMode();
         this.setupApp();
     }
@@ -40,6 +43,10 @@ class TriageIRMain {
             }
         });
 
+        app.on('before-quit', () => {
+            this.stopWorker();
+        });
+
         app.on('activate', () => {
             if (BrowserWindow.getAllWindows().length === 0) {
                 this.createWindow();
@@ -176,31 +183,145 @@ class TriageIRMain {
         });
     }
 
+    // Start the CLI in worker mode; it keeps YARA rules, enrichment databases
+    // and the hash cache loaded between scans
+    startWorker() {
+        const worker = spawn(this.cliPath, ['worker'], { shell: true });
+        let buffered = '';
+
+        worker.stdout.on('data', (data) => {
+            buffered += data.toString();
+            const lines = buffered.split('\n');
+            buffered = lines.pop();
+            for (const line of lines.filter((line) => line.trim())) {
+                let event;
+                try {
+                    event = JSON.parse(line);
+                } catch (error) {
+                    console.warn('Ignoring worker output:', line);
+                    continue;
+                }
+                this.handleWorkerEvent(event);
+            }
+        });
+
+        worker.stderr.on('data', (data) => {
+            console.warn('Worker:', data.toString().trim());
+        });
+
+        const failPending = (message) => {
+            for (const pending of this.workerRequests.values()) {
+                pending.reject({ success: false, error: message, stdout: '', stderr: '' });
+            }
+            this.workerRequests.clear();
+            if (this.worker === worker) {
+                this.worker = null;
+            }
+        };
+        worker.on('close', (code) => failPending(`Worker exited with code ${code}`));
+        worker.on('error', (error) => failPending(`Failed to start worker: ${error.message}`));
+
+        this.worker = worker;
+        return worker;
+    }
+
+    stopWorker() {
+        if (this.worker) {
+            this.worker.stdin.end();
+            this.worker = null;
+        }
+    }
+
+    handleWorkerEvent(event) {
+        const pending = this.workerRequests.get(event.id);
+        if (!pending) {
+            return;
+        }
+        if (event.event === 'progress') {
+            this.mainWindow.webContents.send('scan-progress', event);
+            return;
+        }
+        if (event.event !== 'result') {
+            return;
+        }
+
+        this.workerRequests.delete(event.id);
+        if (event.status !== 'completed' && event.status !== 'completed_with_errors') {
+            pending.reject({ success: false, error: event.error, stdout: '', stderr: '' });
+            return;
+        }
+        try {
+            const jsonData = JSON.parse(fs.readFileSync(event.output, 'utf8'));
+            try {
+                fs.unlinkSync(event.output);
+            } catch (cleanupError) {
+                console.warn('Failed to cleanup temp file:', cleanupError);
+            }
+            pending.resolve({ success: true, data: jsonData, stdout: '', stderr: event.error || '', warm: event.warm });
+        } catch (error) {
+            pending.reject({ success: false, error: `Failed to parse CLI output: ${error.message}`, stdout: '', stderr: '' });
+        }
+    }
+
+    // Scan through the warm-standby worker; options.config takes the scan
+    // settings of the C ABI (scan_paths, yara_rules, enrich, ...)
+    async executeWorkerScan(options = {}) {
+        const worker = this.worker || this.startWorker();
+        const id = String(this.nextRequestId++);
+        const tempFile = path.join(__dirname, `temp-scan-${Date.now()}-${id}.json`);
+
+        return new Promise((resolve, reject) => {
+            this.workerRequests.set(id, { resolve, reject });
+            worker.stdin.write(JSON.stringify({ id, output: tempFile, config: options.config || {} }) + '\n');
+        });
+    }
+
     async executeScan(options = {}) {
+        if (options.warmStandby) {
+            return this.executeWorkerScan(options);
+        }
+
         return new Promise((resolve, reject) => {
             // Create a temporary output file for clean JSON
             const tempFile = path.join(__dirname, `temp-scan-${Date.now()}.json`);
-            const args = ['--output', tempFile];
-            
-            if (options.verbose) {
-                args.push('--verbose');
-            }
+            // Progress arrives as JSON events on stderr
+            const args = ['--output', tempFile, '--progress', 'json'];
 
             console.log(`Executing: ${this.cliPath} ${args.join(' ')}`);
 
             const process = spawn(this.cliPath, args, { shell: true });
             let stdout = '';
             let stderr = '';
+            let buffered = '';
 
             process.stdout.on('data', (data) => {
-                const output = data.toString();
-                stdout += output;
-                // Send progress updates to renderer (verbose output)
-                this.mainWindow.webContents.send('scan-progress', output.trim());
+                stdout += data.toString();
             });
 
             process.stderr.on('data', (data) => {
-                stderr += data.toString();
+                buffered += data.toString();
+                const lines = buffered.split('\n');
+                buffered = lines.pop();
+                for (const line of lines.filter((line) => line.trim())) {
+                    let event = null;
+                    if (line.startsWith('{')) {
+                        try {
+                            event = JSON.parse(line);
+                        } catch (error) {
+                            event = null;
+                        }
+                    }
+                    if (!event) {
+                        stderr += line + '\n';
+                        continue;
+                    }
+                    if (event.event === 'log') {
+                        stderr += `${event.level}: ${event.message}\n`;
+                    }
+                    if (event.event === 'progress' || event.event === 'log') {
+                        this.mainWindow.webContents.send('scan-progress', event);
+                    }
+                }
             });
 
             process.on('close', (code) => {
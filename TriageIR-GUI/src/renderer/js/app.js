@@ -310,23 +310,25 @@ class TriageIRApp {
         this.populateResults();
     }
 
-    updateScanProgress(message) {
+    // Progress events of the CLI (--progress json): progress updates the bar
+    // and counters, log carries warnings and errors
+    updateScanProgress(event) {
         const logContent = document.getElementById('logContent');
         const progressText = document.getElementById('progressText');
         const progressFill = document.getElementById('progressFill');
         
+        if (event.event === 'log') {
+            logContent.textContent += `[${event.level}] ${event.message}\n`;
+            logContent.scrollTop = logContent.scrollHeight;
+            return;
+        }
+        
         // Add message to log
-        logContent.textContent += message + '\n';
+        logContent.textContent += event.message + '\n';
         logContent.scrollTop = logContent.scrollHeight;
         
-        // Update progress text
-        progressText.textContent = message;
-        
-        // Simulate progress (in real implementation, CLI would provide progress info)
-        const currentWidth = parseInt(progressFill.style.width) || 0;
-        if (currentWidth < 90) {
-            progressFill.style.width = (currentWidth + 10) + '%';
-        }
+        progressText.textContent = `${event.message} (${event.artifacts} artifacts, ${event.errors} errors, ${event.warnings} warnings)`;
+        progressFill.style.width = `${event.percent}%`;
     }
 
     populateResults() {